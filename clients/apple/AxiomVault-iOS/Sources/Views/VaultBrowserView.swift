@@ -410,6 +410,7 @@ struct VaultInfoView: View {
             List {
                 if let info = vaultManager.vaultInfo {
                     Section(header: Text("Vault Details")) {
+                        LabeledContent("Name", value: info.displayName)
                         LabeledContent("Vault ID", value: info.vaultId)
                         LabeledContent("Path", value: info.rootPath)
                         LabeledContent("Version", value: "\(info.version)")
@@ -361,6 +361,10 @@ struct VaultInfoSheet: View {
 
             if let info = vaultManager.vaultInfo {
                 Grid(alignment: .leading, horizontalSpacing: 16, verticalSpacing: 8) {
+                    GridRow {
+                        Text("Name").foregroundStyle(.secondary)
+                        Text(info.displayName)
+                    }
                     GridRow {
                         Text("Vault ID").foregroundStyle(.secondary)
                         Text(info.vaultId).textSelection(.enabled)
@@ -24,12 +24,33 @@ typedef struct {
     int file_count;
     long long total_size;
     int version;
+    unsigned long long key_generation;
+    const char *display_name;
+    const char *description;
+    const char *icon;
+    const char *color;
 } FFIVaultInfo;
 
+// Wrapped master key — free with axiom_wrapped_key_free.
+typedef struct {
+    uint8_t *data;
+    size_t len;
+} FFIWrappedKey;
+
 // Event callback — receives a JSON string on a background thread.
 // The pointer is only valid for the duration of the call.
 typedef void (*FFIEventCallback)(const char *json);
 
+// Opaque cancellation token for the async open/create calls.
+typedef struct FFICancelToken FFICancelToken;
+
+// Async open/create completion — invoked exactly once on a background
+// thread. On failure or cancellation result_handle is NULL and error_json
+// is {"code": .., "message": ..}; cancellation is code 11.
+typedef void (*FFIVaultCallback)(FFIVaultHandle *result_handle,
+                                 const char *error_json,
+                                 void *user_data);
+
 // ---------------------------------------------------------------------------
 // Initialization
 // ---------------------------------------------------------------------------
@@ -43,7 +64,38 @@ const char *axiom_version(void);
 
 FFIVaultHandle *axiom_vault_create(const char *path, const char *password);
 FFIVaultHandle *axiom_vault_open(const char *path, const char *password);
+// keyfile_path may be NULL for password-only vaults.
+FFIVaultHandle *axiom_vault_create_with_keyfile(const char *path,
+                                                const char *password,
+                                                const char *keyfile_path);
+FFIVaultHandle *axiom_vault_open_with_keyfile(const char *path,
+                                              const char *password,
+                                              const char *keyfile_path);
+// Unlock with a blob from axiom_vault_rewrap_key. Fails with error code 14
+// (wrapped key stale) after a password change.
+FFIVaultHandle *axiom_vault_open_with_wrapped_key(const char *path,
+                                                  const uint8_t *wrapping_key,
+                                                  size_t wrapping_key_len,
+                                                  const uint8_t *wrapped_key,
+                                                  size_t wrapped_key_len);
+// Unlock a vault created with an externally supplied master key (32 bytes,
+// not all zeros). Such vaults have no password.
+FFIVaultHandle *axiom_vault_open_with_key(const char *path,
+                                          const uint8_t *key,
+                                          size_t key_len);
 int axiom_vault_close(FFIVaultHandle *handle);
+// Non-blocking variants; free the returned token with
+// axiom_cancel_token_free. NULL means invalid arguments and no callback.
+FFICancelToken *axiom_vault_open_async(const char *path,
+                                       const char *password,
+                                       FFIVaultCallback callback,
+                                       void *user_data);
+FFICancelToken *axiom_vault_create_async(const char *path,
+                                         const char *password,
+                                         FFIVaultCallback callback,
+                                         void *user_data);
+int axiom_cancel(const FFICancelToken *token);
+void axiom_cancel_token_free(FFICancelToken *token);
 
 // ---------------------------------------------------------------------------
 // Vault info
@@ -58,6 +110,11 @@ void axiom_vault_info_free(FFIVaultInfo *info);
 
 char *axiom_vault_list(const FFIVaultHandle *handle, const char *path);
 
+char *axiom_vault_list_page(const FFIVaultHandle *handle,
+                            const char *path,
+                            size_t offset,
+                            size_t limit);
+
 int axiom_vault_add_file(const FFIVaultHandle *handle,
                           const char *local_path,
                           const char *vault_path);
@@ -77,6 +134,18 @@ int axiom_vault_remove(const FFIVaultHandle *handle, const char *vault_path);
 int axiom_vault_change_password(const FFIVaultHandle *handle,
                                  const char *old_password,
                                  const char *new_password);
+int axiom_vault_change_password_with_keyfile(const FFIVaultHandle *handle,
+                                             const char *old_password,
+                                             const char *old_keyfile_path,
+                                             const char *new_password,
+                                             const char *new_keyfile_path);
+
+// Wrap the master key for the current key generation. Call again after
+// every password change and replace the keystore entry.
+FFIWrappedKey *axiom_vault_rewrap_key(const FFIVaultHandle *handle,
+                                      const uint8_t *wrapping_key,
+                                      size_t len);
+void axiom_wrapped_key_free(FFIWrappedKey *key);
 
 // Returns recovery words from vault creation (one-time, cleared after call).
 // Returns NULL if vault was opened or words already consumed.
@@ -105,6 +174,9 @@ int axiom_vault_migrate(const char *path, const char *password);
 // Returns JSON health report. password may be NULL for structure-only check.
 char *axiom_vault_health_check(const char *path, const char *password);
 
+// Returns JSON summary read from vault.config only; no password needed.
+char *axiom_vault_inspect(const char *path);
+
 // ---------------------------------------------------------------------------
 // Event subscription
 // ---------------------------------------------------------------------------
@@ -114,11 +186,23 @@ char *axiom_vault_health_check(const char *path, const char *password);
 int axiom_vault_subscribe_events(const FFIVaultHandle *handle,
                                   FFIEventCallback callback);
 
+// ---------------------------------------------------------------------------
+// Sync
+// ---------------------------------------------------------------------------
+
+// Push connectivity/battery state, e.g. {"metered": true, "on_power": false}.
+// Missing fields count as unmetered and on power. Returns 0 on success.
+int axiom_sync_set_environment(const FFIVaultHandle *handle, const char *json);
+
 // ---------------------------------------------------------------------------
 // Error and string management
 // ---------------------------------------------------------------------------
 
 char *axiom_last_error(void);
+// Code of the last error without clearing it (0 = none); call before
+// axiom_last_error. See axiom_last_error_code in core/ffi for the table;
+// 16 means a Rust panic was caught at the boundary.
+int axiom_last_error_code(void);
 void axiom_string_free(char *s);
 
 // Zeroize-then-free for strings containing secrets (recovery mnemonics).
@@ -33,6 +33,8 @@ enum VaultError: Error, LocalizedError {
 /// Information about a vault
 struct VaultInfo {
     let vaultId: String
+    let displayName: String
+    let description: String?
     let rootPath: String
     let fileCount: Int
     let totalSize: Int64
@@ -275,6 +277,8 @@ class VaultCore {
         let info = infoPtr.pointee
         return VaultInfo(
             vaultId: info.vault_id != nil ? String(cString: info.vault_id) : "",
+            displayName: info.display_name != nil ? String(cString: info.display_name) : "",
+            description: info.description != nil ? String(cString: info.description) : nil,
             rootPath: info.root_path != nil ? String(cString: info.root_path) : "",
             fileCount: Int(info.file_count),
             totalSize: info.total_size,
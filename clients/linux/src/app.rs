@@ -7,12 +7,13 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
 use adw::prelude::*;
 use gtk::{gio, glib};
 use tokio::runtime::Runtime;
 
-use axiomvault_app::AppService;
+use axiomvault_app::{tray, AppService};
 
 use crate::ui;
 
@@ -32,6 +33,9 @@ impl AppState {
     }
 }
 
+/// How long quitting waits for pending vault saves before locking anyway.
+const EXIT_DEADLINE: Duration = Duration::from_secs(10);
+
 /// Run the GTK application. Returns the exit code.
 pub fn run() -> i32 {
     let app = adw::Application::builder()
@@ -41,11 +45,26 @@ pub fn run() -> i32 {
 
     let state = Rc::new(RefCell::new(AppState::new()));
 
+    let activate_state = Rc::clone(&state);
     app.connect_activate(move |app| {
-        let state = Rc::clone(&state);
+        let state = Rc::clone(&activate_state);
         ui::build_window(app, state);
     });
 
+    // Lock on quit so keys are zeroized and pending saves are flushed.
+    app.connect_shutdown(move |_| {
+        let state = state.borrow();
+        let report = state.runtime.block_on(tray::shutdown(
+            &state.service,
+            async { Ok(()) },
+            async {},
+            EXIT_DEADLINE,
+        ));
+        if !report.is_locked() {
+            tracing::warn!("Vault was not locked on exit: {:?}", report.lock_error);
+        }
+    });
+
     app.run().into()
 }
 
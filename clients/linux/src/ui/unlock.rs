@@ -98,6 +98,7 @@ impl UnlockView {
                         service
                             .open_vault(OpenVaultParams {
                                 password: zeroize::Zeroizing::new(password),
+                                keyfile: None,
                                 provider_type: "local".to_string(),
                                 provider_config: serde_json::json!({ "root": path }),
                             })
@@ -145,6 +146,7 @@ impl UnlockView {
                             .create_vault(axiomvault_app::CreateVaultParams {
                                 vault_id: vault_name,
                                 password: zeroize::Zeroizing::new(password),
+                                keyfile: None,
                                 provider_type: "local".to_string(),
                                 provider_config: serde_json::json!({ "root": path }),
                             })
@@ -4,7 +4,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use adw::prelude::*;
-use gtk::glib;
+use gtk::{gio, glib};
 
 use crate::app::AppState;
 use crate::ui::browser::BrowserView;
@@ -76,9 +76,40 @@ fn handle_event(
             tracing::info!("Vault closed — returning to unlock view");
             nav.pop_to_tag("unlock");
         }
+        axiomvault_app::AppEvent::SyncQuotaLow { needed, remaining } => {
+            notify(
+                "Cloud storage almost full",
+                &format!(
+                    "Pending uploads need {} bytes but only {} bytes are free.",
+                    needed, remaining
+                ),
+            );
+        }
+        axiomvault_app::AppEvent::SyncQuotaExhausted {
+            paused,
+            paused_bytes,
+        } => {
+            notify(
+                "Cloud storage full",
+                &format!(
+                    "Sync paused {} uploads ({} bytes). Free up space to resume.",
+                    paused, paused_bytes
+                ),
+            );
+        }
         axiomvault_app::AppEvent::Error { message } => {
             tracing::error!("Core error: {}", message);
         }
         _ => {}
     }
 }
+
+/// Raise a desktop notification; later quota warnings replace earlier ones.
+fn notify(title: &str, body: &str) {
+    let Some(app) = gio::Application::default() else {
+        return;
+    };
+    let notification = gio::Notification::new(title);
+    notification.set_body(Some(body));
+    app.send_notification(Some("sync-quota"), &notification);
+}
@@ -0,0 +1,530 @@
+//! Breached password checks against Have I Been Pwned.
+//!
+//! Uses the k-anonymity range API: the password is hashed with SHA-1
+//! locally and only the first five hex characters of the hash are sent.
+//! The response lists every known hash suffix under that prefix and the
+//! match happens here, so neither the password nor its full hash leaves the
+//! machine. Range responses can be cached on disk so repeated audits do not
+//! download them again.
+//!
+//! [`BreachChecker::audit`] checks a batch of labelled passwords and also
+//! flags passwords shared between entries and passwords too short to be
+//! safe. With [`BreachChecker::offline`] no request is made at all.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use blake2::digest::consts::U16;
+use blake2::digest::{Digest, Mac};
+use futures::{stream, StreamExt};
+use serde::Serialize;
+use sha1::Sha1;
+use tracing::{debug, warn};
+use zeroize::Zeroizing;
+
+use crate::error::{AppError, AppResult};
+
+/// Have I Been Pwned range API endpoint.
+pub const HIBP_RANGE_API: &str = "https://api.pwnedpasswords.com/range";
+
+/// How long a cached range response is used before it is fetched again.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Range requests an audit keeps in flight at once.
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// Passwords shorter than this are reported as weak, matching the minimum
+/// the clients enforce for vault passwords.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Number of hex characters of the SHA-1 hash sent to the API.
+const PREFIX_LEN: usize = 5;
+
+/// Timeout for a single range request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Outcome of checking one password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BreachResult {
+    /// The password appears in known breaches this many times.
+    Breached {
+        /// Number of times the password was seen in breach corpora.
+        count: u64,
+    },
+    /// The password does not appear in any known breach.
+    NotFound,
+    /// The check was skipped because the checker is offline.
+    Skipped,
+}
+
+/// A password to audit, identified by a label shown in the report.
+pub struct AuditEntry {
+    /// Name of the entry, e.g. the site or account.
+    pub label: String,
+    /// The password itself; zeroized on drop.
+    pub password: Zeroizing<Vec<u8>>,
+}
+
+/// Findings for one audited entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AuditFinding {
+    /// Label of the entry.
+    pub label: String,
+    /// Breach check result.
+    pub breach: BreachResult,
+    /// Labels of other entries with the same password.
+    pub reused_with: Vec<String>,
+    /// Whether the password is shorter than [`MIN_PASSWORD_LENGTH`].
+    pub weak: bool,
+}
+
+impl AuditFinding {
+    /// Whether anything about this entry needs attention.
+    pub fn needs_attention(&self) -> bool {
+        matches!(self.breach, BreachResult::Breached { .. })
+            || !self.reused_with.is_empty()
+            || self.weak
+    }
+}
+
+/// Result of [`BreachChecker::audit`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AuditReport {
+    /// One finding per entry, in input order.
+    pub findings: Vec<AuditFinding>,
+    /// Breach checks were skipped because the checker is offline.
+    pub offline: bool,
+}
+
+impl AuditReport {
+    /// Number of entries found in breaches.
+    pub fn breached_count(&self) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| matches!(f.breach, BreachResult::Breached { .. }))
+            .count()
+    }
+
+    /// Number of entries sharing their password with another entry.
+    pub fn reused_count(&self) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| !f.reused_with.is_empty())
+            .count()
+    }
+
+    /// Number of entries with a weak password.
+    pub fn weak_count(&self) -> usize {
+        self.findings.iter().filter(|f| f.weak).count()
+    }
+}
+
+/// Client for the Have I Been Pwned range API.
+pub struct BreachChecker {
+    http: reqwest::Client,
+    api_base: String,
+    /// Directory for cached range responses, if caching is enabled.
+    cache_dir: Option<PathBuf>,
+    cache_ttl: Duration,
+    offline: bool,
+    max_concurrent_requests: usize,
+}
+
+impl BreachChecker {
+    /// Create an online checker without a cache.
+    pub fn new() -> AppResult<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("axiomvault/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+        Ok(Self {
+            http,
+            api_base: HIBP_RANGE_API.to_string(),
+            cache_dir: None,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            offline: false,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+        })
+    }
+
+    /// Cache range responses in `dir` and reuse them for `ttl`.
+    pub fn with_cache(mut self, dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        self.cache_dir = Some(dir.into());
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Skip every network check; results are [`BreachResult::Skipped`].
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Limit how many range requests an audit keeps in flight.
+    pub fn with_max_concurrent_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_requests = max.max(1);
+        self
+    }
+
+    /// Point the checker at a local stand-in for the API.
+    #[cfg(test)]
+    pub(crate) fn with_api_base(mut self, api_base: &str) -> Self {
+        self.api_base = api_base.to_string();
+        self
+    }
+
+    /// Check whether `password` appears in a known breach.
+    ///
+    /// # Errors
+    /// - `Storage` if the API cannot be reached or answers with an error
+    /// - `RateLimited` if the API is throttling requests
+    pub async fn check_password(&self, password: &[u8]) -> AppResult<BreachResult> {
+        if self.offline {
+            return Ok(BreachResult::Skipped);
+        }
+
+        let hash = Zeroizing::new(hex_upper(&Sha1::digest(password)));
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+        let range = self.range(prefix).await?;
+
+        Ok(find_suffix(&range, suffix)
+            .map(|count| BreachResult::Breached { count })
+            .unwrap_or(BreachResult::NotFound))
+    }
+
+    /// Audit `entries` for breached, reused and weak passwords.
+    ///
+    /// Breach checks run concurrently, bounded by
+    /// [`with_max_concurrent_requests`](Self::with_max_concurrent_requests).
+    /// Reuse is detected by comparing hashes keyed with a salt drawn for
+    /// this audit, so no plaintext is kept around for the comparison.
+    ///
+    /// # Errors
+    /// Same as [`check_password`](Self::check_password); the first failed
+    /// check aborts the audit.
+    pub async fn audit(&self, entries: &[AuditEntry]) -> AppResult<AuditReport> {
+        let breaches: Vec<BreachResult> = stream::iter(entries)
+            .map(|entry| self.check_password(&entry.password))
+            .buffered(self.max_concurrent_requests)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<AppResult<_>>()?;
+
+        let salt = axiomvault_crypto::Salt::generate(axiomvault_crypto::SALT_LENGTH);
+        let mut by_hash: HashMap<[u8; 16], Vec<usize>> = HashMap::new();
+        for (i, entry) in entries.iter().enumerate() {
+            by_hash
+                .entry(salted_hash(salt.as_bytes(), &entry.password)?)
+                .or_default()
+                .push(i);
+        }
+        let mut reused_with = vec![Vec::new(); entries.len()];
+        for group in by_hash.values().filter(|g| g.len() > 1) {
+            for &i in group {
+                reused_with[i] = group
+                    .iter()
+                    .filter(|&&j| j != i)
+                    .map(|&j| entries[j].label.clone())
+                    .collect();
+            }
+        }
+
+        let findings = entries
+            .iter()
+            .zip(breaches)
+            .zip(reused_with)
+            .map(|((entry, breach), reused_with)| AuditFinding {
+                label: entry.label.clone(),
+                breach,
+                reused_with,
+                weak: entry.password.len() < MIN_PASSWORD_LENGTH,
+            })
+            .collect();
+
+        Ok(AuditReport {
+            findings,
+            offline: self.offline,
+        })
+    }
+
+    /// Range response for `prefix`, from the cache if it is fresh.
+    async fn range(&self, prefix: &str) -> AppResult<String> {
+        let cache_path = self
+            .cache_dir
+            .as_ref()
+            .map(|dir| dir.join(cache_file_name(prefix)));
+
+        if let Some(path) = &cache_path {
+            if let Some(cached) = self.read_cache(path).await {
+                debug!("Using cached breach range");
+                return Ok(cached);
+            }
+        }
+
+        let url = format!("{}/{}", self.api_base, prefix);
+        let response = self
+            .http
+            .get(&url)
+            .header("Add-Padding", "true")
+            .send()
+            .await
+            .map_err(|e| AppError::Storage(format!("Breach check request failed: {}", e)))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(AppError::RateLimited(
+                "Breach check API is throttling requests".to_string(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(AppError::Storage(format!(
+                "Breach check API returned {}",
+                status
+            )));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| AppError::Storage(format!("Breach check response failed: {}", e)))?;
+
+        if let Some(path) = &cache_path {
+            self.write_cache(path, &body).await;
+        }
+        Ok(body)
+    }
+
+    async fn read_cache(&self, path: &PathBuf) -> Option<String> {
+        let modified = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+        if modified.elapsed().map_or(true, |age| age > self.cache_ttl) {
+            return None;
+        }
+        tokio::fs::read_to_string(path).await.ok()
+    }
+
+    /// Store a range response; a failure only costs a later download.
+    async fn write_cache(&self, path: &PathBuf, body: &str) {
+        if let Some(dir) = path.parent() {
+            if let Err(e) = tokio::fs::create_dir_all(dir).await {
+                warn!("Failed to create breach cache directory: {}", e);
+                return;
+            }
+        }
+        if let Err(e) = tokio::fs::write(path, body).await {
+            warn!("Failed to cache breach range: {}", e);
+        }
+    }
+}
+
+/// Count for `suffix` in a range response, if it is listed.
+///
+/// Padding entries, which the API adds with a count of zero, never match.
+fn find_suffix(range: &str, suffix: &str) -> Option<u64> {
+    range
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+        .and_then(|(_, count)| count.trim().parse().ok())
+        .filter(|&count| count > 0)
+}
+
+/// Cache file name for a prefix, so the directory listing does not show
+/// which prefixes were looked up.
+fn cache_file_name(prefix: &str) -> String {
+    let digest = blake2::Blake2b::<U16>::digest(prefix.as_bytes());
+    format!("{}.range", hex_upper(&digest).to_lowercase())
+}
+
+/// Keyed hash of a password for reuse detection within one audit.
+fn salted_hash(salt: &[u8], password: &[u8]) -> AppResult<[u8; 16]> {
+    let mut mac = <blake2::Blake2bMac<U16> as Mac>::new_from_slice(salt)
+        .map_err(|e| AppError::Crypto(format!("Invalid audit salt: {}", e)))?;
+    mac.update(password);
+    Ok(mac.finalize().into_bytes().into())
+}
+
+fn hex_upper(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// SHA-1 of "password".
+    const PASSWORD_SHA1: &str = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8";
+
+    /// Serve range responses listing the suffix of "password" with a count
+    /// of 42, plus a padding entry, and record every request head and body.
+    async fn spawn_range_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/range", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::<String>::new()));
+        let seen = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                seen.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf).to_string());
+
+                let body = format!(
+                    "{}:42\r\n0000000000000000000000000000000000A:0\r\n",
+                    &PASSWORD_SHA1[PREFIX_LEN..]
+                );
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (base, requests)
+    }
+
+    fn entry(label: &str, password: &[u8]) -> AuditEntry {
+        AuditEntry {
+            label: label.to_string(),
+            password: Zeroizing::new(password.to_vec()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_password_sends_only_the_prefix() {
+        let (base, requests) = spawn_range_server().await;
+        let checker = BreachChecker::new().unwrap().with_api_base(&base);
+
+        assert_eq!(
+            checker.check_password(b"password").await.unwrap(),
+            BreachResult::Breached { count: 42 }
+        );
+        assert_eq!(
+            checker
+                .check_password(b"correct horse battery staple")
+                .await
+                .unwrap(),
+            BreachResult::NotFound
+        );
+
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        let first_line = requests[0].lines().next().unwrap();
+        assert_eq!(
+            first_line,
+            format!("GET /range/{} HTTP/1.1", &PASSWORD_SHA1[..PREFIX_LEN])
+        );
+        for request in &requests {
+            let request = request.to_uppercase();
+            assert!(!request.contains(&PASSWORD_SHA1[PREFIX_LEN..]));
+            assert!(!request.contains("PASSWORD"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_range_cache_avoids_repeated_downloads() {
+        let (base, requests) = spawn_range_server().await;
+        let cache = TempDir::new().unwrap();
+        let checker = BreachChecker::new()
+            .unwrap()
+            .with_api_base(&base)
+            .with_cache(cache.path(), DEFAULT_CACHE_TTL);
+
+        for _ in 0..3 {
+            assert_eq!(
+                checker.check_password(b"password").await.unwrap(),
+                BreachResult::Breached { count: 42 }
+            );
+        }
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // File names do not reveal the prefix.
+        let names: Vec<_> = std::fs::read_dir(cache.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert_eq!(names.len(), 1);
+        assert!(!names[0]
+            .to_uppercase()
+            .contains(&PASSWORD_SHA1[..PREFIX_LEN]));
+
+        // An expired entry is fetched again.
+        let stale = BreachChecker::new()
+            .unwrap()
+            .with_api_base(&base)
+            .with_cache(cache.path(), Duration::ZERO);
+        stale.check_password(b"password").await.unwrap();
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_audit_flags_breached_reused_and_weak() {
+        let (base, _requests) = spawn_range_server().await;
+        let checker = BreachChecker::new()
+            .unwrap()
+            .with_api_base(&base)
+            .with_max_concurrent_requests(2);
+
+        let report = checker
+            .audit(&[
+                entry("mail", b"password"),
+                entry("bank", b"Tr0ub4dor&3-unique"),
+                entry("forum", b"password"),
+                entry("router", b"admin"),
+            ])
+            .await
+            .unwrap();
+
+        assert!(!report.offline);
+        let mail = &report.findings[0];
+        assert_eq!(mail.breach, BreachResult::Breached { count: 42 });
+        assert_eq!(mail.reused_with, ["forum"]);
+        assert!(!mail.weak);
+        assert!(!report.findings[1].needs_attention());
+        assert_eq!(report.findings[2].reused_with, ["mail"]);
+        assert!(report.findings[3].weak);
+        assert_eq!(report.breached_count(), 2);
+        assert_eq!(report.reused_count(), 2);
+        assert_eq!(report.weak_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_offline_audit_makes_no_requests() {
+        let (base, requests) = spawn_range_server().await;
+        let checker = BreachChecker::new()
+            .unwrap()
+            .with_api_base(&base)
+            .offline(true);
+
+        let report = checker
+            .audit(&[entry("a", b"password"), entry("b", b"password")])
+            .await
+            .unwrap();
+
+        assert!(report.offline);
+        assert!(report
+            .findings
+            .iter()
+            .all(|f| f.breach == BreachResult::Skipped));
+        assert_eq!(report.reused_count(), 2);
+        assert!(requests.lock().unwrap().is_empty());
+    }
+}
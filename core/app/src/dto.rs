@@ -40,12 +40,45 @@ fn zeroize_json_value(value: &mut serde_json::Value) {
 /// Information about an open vault.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VaultInfoDto {
-    /// Vault identifier.
+    /// Vault identifier. Fixed for the life of the vault; show
+    /// `display_name` first.
     pub id: String,
+    /// Name shown to users; the ID for vaults that never set one.
+    #[serde(default)]
+    pub display_name: String,
+    /// Free-form description.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Icon hint.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Color hint, as `#rrggbb`.
+    #[serde(default)]
+    pub color: Option<String>,
     /// Storage provider type (e.g. "local", "gdrive").
     pub provider_type: String,
     /// Whether the vault is currently unlocked.
     pub is_unlocked: bool,
+    /// Key generation; wrapped keys minted for an older one are stale.
+    #[serde(default)]
+    pub key_generation: u64,
+}
+
+/// New display details for the open vault; see
+/// [`AppService::update_vault_details`](crate::AppService::update_vault_details).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VaultDetailsParams {
+    /// Name shown to users.
+    pub display_name: String,
+    /// Free-form description; `None` clears it.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Icon hint; `None` clears it.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Color hint as `#rrggbb`; `None` clears it.
+    #[serde(default)]
+    pub color: Option<String>,
 }
 
 /// Result of vault creation, including the recovery words.
@@ -89,6 +122,72 @@ pub struct DirectoryEntryDto {
     pub modified_at: Option<DateTime<Utc>>,
 }
 
+/// One page of a directory listing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryPageDto {
+    /// Entries on this page, sorted by name.
+    pub entries: Vec<DirectoryEntryDto>,
+    /// Whether more entries follow this page.
+    pub has_more: bool,
+}
+
+/// A file whose contents match a search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHitDto {
+    /// Full vault path.
+    pub path: String,
+    /// Where the query occurs in the file's text.
+    pub matches: Vec<SearchMatchDto>,
+}
+
+/// One occurrence of a search query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatchDto {
+    /// Byte offset into the file's text.
+    pub offset: usize,
+    /// Length in bytes.
+    pub len: usize,
+    /// The match with surrounding text.
+    pub snippet: String,
+}
+
+/// Options for comparing the open vault with another vault or a directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompareOptionsDto {
+    /// Compare sizes and etags only, without reading file contents.
+    #[serde(default)]
+    pub fast: bool,
+    /// Do not report files that differ only in modification time.
+    #[serde(default)]
+    pub ignore_timestamps: bool,
+    /// Vault paths of subtrees left out on both sides.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+/// One path reported by a comparison.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffEntryDto {
+    /// Full vault path.
+    pub path: String,
+    /// `only_in_a`, `only_in_b`, `content_differs` or `metadata_differs`,
+    /// where A is the open vault.
+    pub kind: String,
+    /// Whether the entry is a directory.
+    pub is_directory: bool,
+}
+
+/// Result of comparing the open vault with another vault or a directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffReportDto {
+    /// Whether no differences were found.
+    pub identical: bool,
+    /// Paths present on both sides.
+    pub compared: usize,
+    /// Differences, sorted by path.
+    pub differences: Vec<DiffEntryDto>,
+}
+
 /// File metadata.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMetadataDto {
@@ -119,6 +218,8 @@ pub struct CreateVaultParams {
     pub vault_id: String,
     /// Password for the vault.
     pub password: Zeroizing<String>,
+    /// Keyfile content required together with the password, if any.
+    pub keyfile: Option<Zeroizing<Vec<u8>>>,
     /// Storage provider type.
     pub provider_type: String,
     /// Provider-specific configuration.
@@ -136,6 +237,7 @@ impl std::fmt::Debug for CreateVaultParams {
         f.debug_struct("CreateVaultParams")
             .field("vault_id", &self.vault_id)
             .field("password", &"[REDACTED]")
+            .field("keyfile", &self.keyfile.as_ref().map(|_| "[REDACTED]"))
             .field("provider_type", &self.provider_type)
             .field("provider_config", &"[REDACTED]")
             .finish()
@@ -153,6 +255,8 @@ impl std::fmt::Debug for CreateVaultParams {
 pub struct OpenVaultParams {
     /// Password for the vault.
     pub password: Zeroizing<String>,
+    /// Keyfile content required together with the password, if any.
+    pub keyfile: Option<Zeroizing<Vec<u8>>>,
     /// Storage provider type.
     pub provider_type: String,
     /// Provider-specific configuration.
@@ -169,6 +273,70 @@ impl std::fmt::Debug for OpenVaultParams {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("OpenVaultParams")
             .field("password", &"[REDACTED]")
+            .field("keyfile", &self.keyfile.as_ref().map(|_| "[REDACTED]"))
+            .field("provider_type", &self.provider_type)
+            .field("provider_config", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// Parameters for opening a vault with a device-wrapped key instead of
+/// the password.
+///
+/// `wrapping_key` is held in [`Zeroizing`]; `Debug` redacts it along with
+/// `wrapped_key` and `provider_config`.
+pub struct OpenWrappedKeyParams {
+    /// Device-held key the master key was wrapped with.
+    pub wrapping_key: Zeroizing<Vec<u8>>,
+    /// Blob returned by `AppService::rewrap_key`.
+    pub wrapped_key: Vec<u8>,
+    /// Storage provider type.
+    pub provider_type: String,
+    /// Provider-specific configuration.
+    pub provider_config: serde_json::Value,
+}
+
+impl Drop for OpenWrappedKeyParams {
+    fn drop(&mut self) {
+        zeroize_json_value(&mut self.provider_config);
+    }
+}
+
+impl std::fmt::Debug for OpenWrappedKeyParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenWrappedKeyParams")
+            .field("wrapping_key", &"[REDACTED]")
+            .field("wrapped_key", &"[REDACTED]")
+            .field("provider_type", &self.provider_type)
+            .field("provider_config", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// Parameters for opening a vault whose master key comes from an external
+/// key management system.
+///
+/// `master_key` is held in [`Zeroizing`]; `Debug` redacts it along with
+/// `provider_config`.
+pub struct OpenExternalKeyParams {
+    /// Raw master key bytes.
+    pub master_key: Zeroizing<Vec<u8>>,
+    /// Storage provider type.
+    pub provider_type: String,
+    /// Provider-specific configuration.
+    pub provider_config: serde_json::Value,
+}
+
+impl Drop for OpenExternalKeyParams {
+    fn drop(&mut self) {
+        zeroize_json_value(&mut self.provider_config);
+    }
+}
+
+impl std::fmt::Debug for OpenExternalKeyParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenExternalKeyParams")
+            .field("master_key", &"[REDACTED]")
             .field("provider_type", &self.provider_type)
             .field("provider_config", &"[REDACTED]")
             .finish()
@@ -211,6 +379,90 @@ impl std::fmt::Debug for RecoverVaultParams {
     }
 }
 
+/// How expensive the vault's key derivation is.
+///
+/// Stronger settings take longer to unlock and to brute-force; use
+/// `AppService::estimate_kdf_time` to show the cost on this machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KdfStrength {
+    /// Lighter settings for phones and older machines.
+    Moderate,
+    /// Default settings for desktops.
+    #[default]
+    Interactive,
+    /// Several seconds per unlock, for sensitive data.
+    Sensitive,
+}
+
+/// A folder a vault can be created in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriveFolderDto {
+    /// Google Drive folder ID.
+    pub id: String,
+    /// Folder name.
+    pub name: String,
+}
+
+/// Choices made in the vault creation wizard before the password.
+///
+/// `Debug` is implemented by hand and redacts `keyfile` and
+/// `provider_config` — see the `CreateVaultParams` doc for the rationale.
+pub struct VaultDraftParams {
+    /// Vault identifier.
+    pub vault_id: String,
+    /// Keyfile content required together with the password, if any.
+    pub keyfile: Option<Zeroizing<Vec<u8>>>,
+    /// Storage provider type.
+    pub provider_type: String,
+    /// Provider-specific configuration, including the chosen folder.
+    pub provider_config: serde_json::Value,
+    /// Key derivation strength.
+    pub strength: KdfStrength,
+}
+
+impl Drop for VaultDraftParams {
+    fn drop(&mut self) {
+        zeroize_json_value(&mut self.provider_config);
+    }
+}
+
+impl std::fmt::Debug for VaultDraftParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultDraftParams")
+            .field("vault_id", &self.vault_id)
+            .field("keyfile", &self.keyfile.as_ref().map(|_| "[REDACTED]"))
+            .field("provider_type", &self.provider_type)
+            .field("provider_config", &"[REDACTED]")
+            .field("strength", &self.strength)
+            .finish()
+    }
+}
+
+/// A vault draft awaiting confirmation of its recovery words.
+///
+/// Like [`VaultCreatedDto`], `recovery_words` is zeroized on drop, the
+/// struct is neither `Clone` nor serializable, and `Debug` redacts the
+/// words.
+pub struct VaultDraftDto {
+    /// Handle for the remaining wizard steps.
+    pub draft_id: String,
+    /// BIP39 recovery words (24 words) the vault will be created with.
+    pub recovery_words: Zeroizing<String>,
+    /// 1-based positions of the words the user must re-enter, ascending.
+    pub confirm_positions: Vec<usize>,
+}
+
+impl std::fmt::Debug for VaultDraftDto {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultDraftDto")
+            .field("draft_id", &self.draft_id)
+            .field("recovery_words", &"[REDACTED]")
+            .field("confirm_positions", &self.confirm_positions)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,6 +487,7 @@ mod tests {
         let params = CreateVaultParams {
             vault_id: "vault-1".to_string(),
             password: Zeroizing::new("super-secret-password-do-not-print".to_string()),
+            keyfile: None,
             provider_type: "local".to_string(),
             provider_config: serde_json::json!({}),
         };
@@ -258,6 +511,7 @@ mod tests {
     fn open_vault_params_debug_redacts_password() {
         let params = OpenVaultParams {
             password: Zeroizing::new("another-secret-passphrase".to_string()),
+            keyfile: None,
             provider_type: "gdrive".to_string(),
             provider_config: serde_json::json!({"folder": "vault-secret-folder-id"}),
         };
@@ -314,8 +568,13 @@ mod tests {
         let dto = VaultCreatedDto {
             info: VaultInfoDto {
                 id: "vault-1".to_string(),
+                display_name: "vault-1".to_string(),
+                description: None,
+                icon: None,
+                color: None,
                 provider_type: "local".to_string(),
                 is_unlocked: true,
+                key_generation: 0,
             },
             recovery_words: Zeroizing::new(
                 "abandon ability able about above absent absorb abstract absurd abuse access \
@@ -333,4 +592,17 @@ mod tests {
         // Non-secret info should still be visible.
         assert!(s.contains("vault-1"));
     }
+
+    #[test]
+    fn vault_draft_dto_debug_redacts_recovery_words() {
+        let dto = VaultDraftDto {
+            draft_id: "draft-1".to_string(),
+            recovery_words: Zeroizing::new("abandon ability able about".to_string()),
+            confirm_positions: vec![2, 9, 17],
+        };
+        let s = format!("{:?}", dto);
+        assert!(!s.contains("abandon ability"));
+        assert!(s.contains("[REDACTED]"));
+        assert!(s.contains("draft-1"));
+    }
 }
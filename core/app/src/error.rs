@@ -27,6 +27,14 @@ pub enum AppError {
     #[error("Invalid recovery key")]
     InvalidRecoveryKey,
 
+    /// Vault requires a keyfile that was not supplied.
+    #[error("Keyfile required")]
+    KeyfileRequired,
+
+    /// Supplied keyfile does not match the vault.
+    #[error("Invalid keyfile")]
+    InvalidKeyfile,
+
     /// No vault is currently open.
     #[error("No vault is open")]
     NoOpenVault,
@@ -59,6 +67,58 @@ pub enum AppError {
     #[error("Encryption error: {0}")]
     Crypto(String),
 
+    /// Operation not supported for this vault or provider.
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+
+    /// Operation was cancelled by the user or the app.
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    /// Provider is throttling requests; try again later.
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    /// Provider storage is full; free space before syncing again.
+    #[error("Storage quota exhausted: {0}")]
+    QuotaExhausted(String),
+
+    /// Vault data failed an integrity check and may have been tampered with.
+    #[error("Integrity check failed: {0}")]
+    Integrity(String),
+
+    /// Vault configuration was modified without the vault's keys.
+    #[error("Vault configuration has been tampered with")]
+    ConfigTampered,
+
+    /// Stored wrapped key predates the last password change.
+    #[error("Wrapped key is stale: {0}")]
+    WrappedKeyStale(String),
+
+    /// Another process is syncing the same vault.
+    #[error("Sync already in progress{}", .0.map(|pid| format!(" by pid {}", pid)).unwrap_or_default())]
+    SyncInProgress(Option<u32>),
+
+    /// Vault must be migrated before it can be opened.
+    #[error("Vault needs migration: {}", .0.join(", "))]
+    MigrationRequired(Vec<String>),
+
+    /// The mount point is busy; carries the processes holding it open.
+    #[error("Mount point is busy{}", if .0.is_empty() {
+        String::new()
+    } else {
+        format!(", held open by pid {}", .0.iter().map(u32::to_string).collect::<Vec<_>>().join(", "))
+    })]
+    MountBusy(Vec<u32>),
+
+    /// Vault creation draft is unknown, finished or abandoned.
+    #[error("Vault draft not found: {0}")]
+    DraftNotFound(String),
+
+    /// Recovery words must be confirmed before the vault is created.
+    #[error("Recovery words not confirmed")]
+    RecoveryNotConfirmed,
+
     /// Internal error that should not happen.
     #[error("Internal error: {0}")]
     Internal(String),
@@ -78,6 +138,8 @@ impl From<CommonError> for AppError {
             }
             CommonError::NotPermitted(msg) if msg.contains("locked") => AppError::VaultLocked,
             CommonError::NotPermitted(msg) => AppError::InvalidInput(msg),
+            CommonError::KeyfileRequired => AppError::KeyfileRequired,
+            CommonError::KeyfileMismatch => AppError::InvalidKeyfile,
             CommonError::InvalidInput(msg) => AppError::InvalidInput(msg),
             CommonError::Crypto(msg) => AppError::Crypto(msg),
             CommonError::Storage(msg) => AppError::Storage(msg),
@@ -88,6 +150,17 @@ impl From<CommonError> for AppError {
             CommonError::Serialization(msg) => AppError::Internal(msg),
             CommonError::Vault(msg) => AppError::Internal(msg),
             CommonError::Conflict(msg) => AppError::SyncConflict(msg),
+            CommonError::Unsupported(msg) => AppError::Unsupported(msg),
+            CommonError::Cancelled(msg) => AppError::Cancelled(msg),
+            CommonError::RateLimited(msg) => AppError::RateLimited(msg),
+            CommonError::QuotaExhausted(msg) => AppError::QuotaExhausted(msg),
+            CommonError::Integrity(msg) => AppError::Integrity(msg),
+            CommonError::ConfigTampered => AppError::ConfigTampered,
+            CommonError::WrappedKeyStale(msg) => AppError::WrappedKeyStale(msg),
+            CommonError::SyncInProgress(pid) => AppError::SyncInProgress(pid),
+            CommonError::MigrationRequired(ids) => AppError::MigrationRequired(ids),
+            CommonError::MountBusy(pids) => AppError::MountBusy(pids),
+            CommonError::Provider { message, .. } => AppError::Storage(message),
         }
     }
 }
@@ -6,6 +6,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use axiomvault_sync::SyncEvent;
+
 use crate::dto::{DirectoryEntryDto, VaultInfoDto};
 
 /// Broadcast channel sender.
@@ -42,6 +44,10 @@ pub enum AppEvent {
     /// Vault password was changed.
     PasswordChanged,
 
+    /// The display name, description, icon or color of the open vault
+    /// changed.
+    VaultDetailsChanged(VaultInfoDto),
+
     // -- File operations --
     /// A file was created at the given path.
     FileCreated { path: String },
@@ -75,7 +81,33 @@ pub enum AppEvent {
     /// Sync failed.
     SyncFailed { error: String },
 
+    /// The planned uploads need more space than the storage provider has
+    /// left. The sync still runs.
+    SyncQuotaLow { needed: u64, remaining: u64 },
+
+    /// The storage provider ran out of space; the remaining uploads were
+    /// paused until the next sync.
+    SyncQuotaExhausted { paused: usize, paused_bytes: u64 },
+
     // -- Errors --
     /// A non-fatal error occurred.
     Error { message: String },
 }
+
+impl From<SyncEvent> for AppEvent {
+    fn from(event: SyncEvent) -> Self {
+        match event {
+            SyncEvent::QuotaShortfallPredicted(shortfall) => AppEvent::SyncQuotaLow {
+                needed: shortfall.needed,
+                remaining: shortfall.remaining,
+            },
+            SyncEvent::QuotaExhausted {
+                paused,
+                paused_bytes,
+            } => AppEvent::SyncQuotaExhausted {
+                paused,
+                paused_bytes,
+            },
+        }
+    }
+}
@@ -13,14 +13,27 @@
 //! - **Thread-safe**: `AppService` is `Send + Sync` and safe to share across
 //!   threads via `Arc`.
 
+#[cfg(feature = "breach-check")]
+pub mod breach_check;
 pub mod dto;
 pub mod error;
 pub mod events;
 pub mod local_index;
+pub mod onboarding;
 pub mod service;
+pub mod tray;
 
 pub use dto::*;
 pub use error::{AppError, AppResult};
 pub use events::{AppEvent, EventReceiver, EventSender};
 pub use local_index::{IndexEntry, LocalIndex};
 pub use service::AppService;
+
+/// Device state pushed into `AppService::set_sync_environment`.
+pub use axiomvault_sync::DeviceEnvironment;
+
+/// Hook settings for `AppService::set_hooks` and its status query.
+pub use axiomvault_hooks::{EventFilter, Hook, HookAction, HookStatus, HooksConfig};
+
+/// How `AppService::import_file_and_shred` removed the source.
+pub use axiomvault_common::ShredOutcome;
@@ -15,6 +15,9 @@ use std::path::Path;
 use std::sync::Mutex;
 use tracing::{debug, info};
 
+use axiomvault_common::VaultPath;
+use axiomvault_vault::{TreeNode, VaultTree};
+
 use crate::error::{AppError, AppResult};
 
 fn sqlite_err(e: rusqlite::Error) -> AppError {
@@ -26,7 +29,7 @@ fn lock_err() -> AppError {
 }
 
 /// Represents a cached vault entry.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct IndexEntry {
     pub path: String,
     pub encrypted_name: String,
@@ -36,6 +39,54 @@ pub struct IndexEntry {
     pub etag: Option<String>,
 }
 
+impl IndexEntry {
+    /// Build the index row for the tree node stored at `path`.
+    pub fn from_node(path: &VaultPath, node: &TreeNode) -> Self {
+        let metadata = &node.metadata;
+        Self {
+            path: path.to_string(),
+            encrypted_name: metadata.encrypted_name.clone(),
+            is_directory: node.is_directory(),
+            size: metadata
+                .size
+                .map(|size| i64::try_from(size).unwrap_or(i64::MAX)),
+            modified_at: metadata.modified_at.timestamp(),
+            etag: metadata.etag.clone(),
+        }
+    }
+}
+
+const INSERT_ENTRY: &str = r#"
+    INSERT OR REPLACE INTO vault_entries
+    (path, encrypted_name, is_directory, size, modified_at, etag)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+"#;
+
+fn insert_entry(conn: &Connection, entry: &IndexEntry) -> rusqlite::Result<usize> {
+    conn.execute(
+        INSERT_ENTRY,
+        params![
+            entry.path,
+            entry.encrypted_name,
+            entry.is_directory as i32,
+            entry.size,
+            entry.modified_at,
+            entry.etag,
+        ],
+    )
+}
+
+fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<IndexEntry> {
+    Ok(IndexEntry {
+        path: row.get(0)?,
+        encrypted_name: row.get(1)?,
+        is_directory: row.get::<_, i32>(2)? != 0,
+        size: row.get::<_, Option<i64>>(3)?,
+        modified_at: row.get(4)?,
+        etag: row.get(5)?,
+    })
+}
+
 /// Local index manager using SQLite.
 pub struct LocalIndex {
     conn: Mutex<Connection>,
@@ -97,22 +148,42 @@ impl LocalIndex {
     pub fn upsert_entry(&self, entry: &IndexEntry) -> AppResult<()> {
         debug!("Upserting index entry");
         let conn = self.conn.lock().map_err(|_| lock_err())?;
-        conn.execute(
-            r#"
-            INSERT OR REPLACE INTO vault_entries
-            (path, encrypted_name, is_directory, size, modified_at, etag)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
-            "#,
-            params![
-                entry.path,
-                entry.encrypted_name,
-                entry.is_directory as i32,
-                entry.size,
-                entry.modified_at,
-                entry.etag,
-            ],
-        )
-        .map_err(sqlite_err)?;
+        insert_entry(&conn, entry).map_err(sqlite_err)?;
+        Ok(())
+    }
+
+    /// Replace every entry with the current contents of `tree`.
+    ///
+    /// Runs in a single transaction, so readers never observe a
+    /// half-rebuilt index.
+    pub fn rebuild_from_tree(&self, tree: &VaultTree) -> AppResult<u64> {
+        info!("Rebuilding local index from vault tree");
+        let mut conn = self.conn.lock().map_err(|_| lock_err())?;
+        let tx = conn.transaction().map_err(sqlite_err)?;
+        tx.execute("DELETE FROM vault_entries", [])
+            .map_err(sqlite_err)?;
+        let mut count = 0;
+        Self::insert_children(&tx, tree.root(), &VaultPath::root(), &mut count)?;
+        tx.commit().map_err(sqlite_err)?;
+        Ok(count)
+    }
+
+    fn insert_children(
+        conn: &Connection,
+        node: &TreeNode,
+        path: &VaultPath,
+        count: &mut u64,
+    ) -> AppResult<()> {
+        for child in node.children.values() {
+            let child_path = path
+                .join(&child.metadata.name)
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            insert_entry(conn, &IndexEntry::from_node(&child_path, child)).map_err(sqlite_err)?;
+            *count += 1;
+            if child.is_directory() {
+                Self::insert_children(conn, child, &child_path, count)?;
+            }
+        }
         Ok(())
     }
 
@@ -128,16 +199,7 @@ impl LocalIndex {
             )
             .map_err(sqlite_err)?;
 
-        let entry = stmt.query_row([path], |row| {
-            Ok(IndexEntry {
-                path: row.get(0)?,
-                encrypted_name: row.get(1)?,
-                is_directory: row.get::<_, i32>(2)? != 0,
-                size: row.get::<_, Option<i64>>(3)?,
-                modified_at: row.get(4)?,
-                etag: row.get(5)?,
-            })
-        });
+        let entry = stmt.query_row([path], row_to_entry);
 
         match entry {
             Ok(e) => Ok(Some(e)),
@@ -169,14 +231,7 @@ impl LocalIndex {
             let path: String = row.get(0)?;
             let relative = &path[prefix.len()..];
             if !relative.contains('/') {
-                Ok(Some(IndexEntry {
-                    path,
-                    encrypted_name: row.get(1)?,
-                    is_directory: row.get::<_, i32>(2)? != 0,
-                    size: row.get::<_, Option<i64>>(3)?,
-                    modified_at: row.get(4)?,
-                    etag: row.get(5)?,
-                }))
+                row_to_entry(row).map(Some)
             } else {
                 Ok(None)
             }
@@ -191,6 +246,25 @@ impl LocalIndex {
         Ok(result)
     }
 
+    /// List every entry, ordered by path.
+    pub fn all_entries(&self) -> AppResult<Vec<IndexEntry>> {
+        let conn = self.conn.lock().map_err(|_| lock_err())?;
+        let mut stmt = conn
+            .prepare(
+                r#"
+            SELECT path, encrypted_name, is_directory, size, modified_at, etag
+            FROM vault_entries ORDER BY path
+            "#,
+            )
+            .map_err(sqlite_err)?;
+        let entries = stmt
+            .query_map([], row_to_entry)
+            .map_err(sqlite_err)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(sqlite_err)?;
+        Ok(entries)
+    }
+
     /// Delete an entry by path.
     pub fn delete_entry(&self, path: &str) -> AppResult<()> {
         debug!("Deleting index entry");
@@ -385,4 +459,47 @@ mod tests {
         index.delete_tree("/dir").unwrap();
         assert_eq!(index.count().unwrap(), 0);
     }
+
+    #[test]
+    fn test_rebuild_from_tree() {
+        let index = LocalIndex::in_memory().unwrap();
+        index
+            .upsert_entry(&IndexEntry {
+                path: "/stale.txt".to_string(),
+                encrypted_name: "enc".to_string(),
+                is_directory: false,
+                size: Some(1),
+                modified_at: 0,
+                etag: None,
+            })
+            .unwrap();
+
+        let mut tree = VaultTree::new();
+        let docs = VaultPath::parse("/docs").unwrap();
+        let report = VaultPath::parse("/docs/report.pdf").unwrap();
+        tree.create_directory(&docs, "enc_docs".to_string())
+            .unwrap();
+        tree.create_file(&report, "enc_report".to_string(), 42)
+            .unwrap();
+        tree.get_node_mut(&report).unwrap().metadata.etag = Some("v1".to_string());
+
+        assert_eq!(index.rebuild_from_tree(&tree).unwrap(), 2);
+
+        let entries = index.all_entries().unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/docs", "/docs/report.pdf"]);
+        assert!(entries[0].is_directory);
+        assert_eq!(entries[0].encrypted_name, "enc_docs");
+        assert_eq!(entries[1].encrypted_name, "enc_report");
+        assert_eq!(entries[1].size, Some(42));
+        assert_eq!(entries[1].etag.as_deref(), Some("v1"));
+        assert_eq!(
+            entries[1].modified_at,
+            tree.get_node(&report)
+                .unwrap()
+                .metadata
+                .modified_at
+                .timestamp()
+        );
+    }
 }
@@ -0,0 +1,248 @@
+//! Guided vault creation.
+//!
+//! The wizard creates a vault in two phases. `begin` records the choices
+//! made so far as a draft and generates the recovery key; once the user has
+//! re-entered some of its words, `finalize` creates the vault with that key.
+//! Drafts live only in memory, so nothing reaches storage before
+//! `finalize`, and abandoned drafts expire after [`DRAFT_TTL`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::seq::index;
+use zeroize::Zeroizing;
+
+use axiomvault_crypto::recovery::RecoveryKey;
+use axiomvault_crypto::KdfParams;
+use axiomvault_storage::gdrive::{AuthConfig, AuthManager, DriveClient, TokenManager, Tokens};
+
+use crate::dto::{DriveFolderDto, KdfStrength, VaultDraftParams};
+use crate::error::{AppError, AppResult};
+
+/// How long a draft survives without being finalized.
+pub const DRAFT_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Number of recovery words the user re-enters to confirm them.
+pub const CONFIRM_WORD_COUNT: usize = 3;
+
+/// Key derivation parameters for a wizard strength choice.
+pub fn kdf_params(strength: KdfStrength) -> KdfParams {
+    match strength {
+        KdfStrength::Moderate => KdfParams::moderate(),
+        KdfStrength::Interactive => KdfParams::interactive(),
+        KdfStrength::Sensitive => KdfParams::sensitive(),
+    }
+}
+
+/// A vault that has been planned but not created.
+pub(crate) struct VaultDraft {
+    pub(crate) params: VaultDraftParams,
+    pub(crate) recovery_key: RecoveryKey,
+    pub(crate) confirm_positions: Vec<usize>,
+    pub(crate) confirmed: bool,
+    created_at: Instant,
+}
+
+impl VaultDraft {
+    /// Plan a vault with a fresh recovery key.
+    pub(crate) fn new(params: VaultDraftParams) -> AppResult<(Self, Zeroizing<String>)> {
+        let recovery_key = RecoveryKey::generate();
+        let words = recovery_key.to_mnemonic().map_err(AppError::from)?;
+        let word_count = words.split_whitespace().count();
+
+        let mut confirm_positions: Vec<usize> = index::sample(
+            &mut rand::rng(),
+            word_count,
+            CONFIRM_WORD_COUNT.min(word_count),
+        )
+        .into_iter()
+        .map(|i| i + 1)
+        .collect();
+        confirm_positions.sort_unstable();
+
+        let draft = Self {
+            params,
+            recovery_key,
+            confirm_positions,
+            confirmed: false,
+            created_at: Instant::now(),
+        };
+        Ok((draft, words))
+    }
+
+    /// Check the words re-entered for `confirm_positions`, in order.
+    ///
+    /// Comparison ignores case and surrounding whitespace.
+    pub(crate) fn confirm(&mut self, words: &[String]) -> AppResult<()> {
+        let mnemonic = self.recovery_key.to_mnemonic().map_err(AppError::from)?;
+        let expected: Vec<&str> = mnemonic.split_whitespace().collect();
+
+        let matches = words.len() == self.confirm_positions.len()
+            && self
+                .confirm_positions
+                .iter()
+                .zip(words)
+                .all(|(&position, word)| {
+                    expected
+                        .get(position - 1)
+                        .is_some_and(|e| e.eq_ignore_ascii_case(word.trim()))
+                });
+        if !matches {
+            return Err(AppError::InvalidRecoveryKey);
+        }
+        self.confirmed = true;
+        Ok(())
+    }
+}
+
+/// In-memory drafts keyed by draft ID.
+pub(crate) struct DraftStore {
+    drafts: HashMap<String, VaultDraft>,
+    ttl: Duration,
+}
+
+impl DraftStore {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            drafts: HashMap::new(),
+            ttl,
+        }
+    }
+
+    /// Store a draft and return its ID.
+    pub(crate) fn insert(&mut self, draft: VaultDraft) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.drafts.insert(id.clone(), draft);
+        id
+    }
+
+    /// Look up a live draft.
+    pub(crate) fn get_mut(&mut self, id: &str) -> AppResult<&mut VaultDraft> {
+        self.drafts
+            .get_mut(id)
+            .ok_or_else(|| AppError::DraftNotFound(id.to_string()))
+    }
+
+    /// Drop a draft, returning it.
+    pub(crate) fn remove(&mut self, id: &str) -> AppResult<VaultDraft> {
+        self.drafts
+            .remove(id)
+            .ok_or_else(|| AppError::DraftNotFound(id.to_string()))
+    }
+
+    /// Drop drafts older than the TTL, returning how many were dropped.
+    pub(crate) fn prune_expired(&mut self) -> usize {
+        let before = self.drafts.len();
+        let ttl = self.ttl;
+        self.drafts.retain(|_, d| d.created_at.elapsed() < ttl);
+        before - self.drafts.len()
+    }
+}
+
+impl Default for DraftStore {
+    fn default() -> Self {
+        Self::new(DRAFT_TTL)
+    }
+}
+
+/// List the folders directly inside `parent_id` of a Google Drive account.
+///
+/// `tokens_json` holds the OAuth2 tokens obtained in the wizard's sign-in
+/// step; OAuth2 client credentials come from the environment.
+pub(crate) async fn list_gdrive_folders(
+    tokens_json: &str,
+    parent_id: &str,
+) -> AppResult<Vec<DriveFolderDto>> {
+    let tokens: Tokens = serde_json::from_str(tokens_json)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid Google Drive tokens: {}", e)))?;
+    let auth = AuthManager::new(AuthConfig::default()).map_err(AppError::from)?;
+    let client = DriveClient::new(Arc::new(TokenManager::new(auth, tokens)))?;
+
+    let mut folders: Vec<DriveFolderDto> = client
+        .list_folder(parent_id)
+        .await?
+        .into_iter()
+        .filter(|f| f.is_folder() && !f.trashed)
+        .map(|f| DriveFolderDto {
+            id: f.id,
+            name: f.name,
+        })
+        .collect();
+    folders.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(folders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> VaultDraftParams {
+        VaultDraftParams {
+            vault_id: "draft".to_string(),
+            keyfile: None,
+            provider_type: "memory".to_string(),
+            provider_config: serde_json::Value::Null,
+            strength: KdfStrength::Moderate,
+        }
+    }
+
+    fn expected_words(draft: &VaultDraft, words: &str) -> Vec<String> {
+        let words: Vec<&str> = words.split_whitespace().collect();
+        draft
+            .confirm_positions
+            .iter()
+            .map(|&p| words[p - 1].to_uppercase())
+            .collect()
+    }
+
+    #[test]
+    fn test_confirmation_requires_the_asked_words() {
+        let (mut draft, words) = VaultDraft::new(params()).unwrap();
+        assert_eq!(draft.confirm_positions.len(), CONFIRM_WORD_COUNT);
+        assert!(draft.confirm_positions.windows(2).all(|w| w[0] < w[1]));
+        assert!(draft
+            .confirm_positions
+            .iter()
+            .all(|&p| (1..=24).contains(&p)));
+
+        let answers = expected_words(&draft, &words);
+        let mut wrong = answers.clone();
+        wrong[1] = "notaword".to_string();
+        assert!(matches!(
+            draft.confirm(&wrong),
+            Err(AppError::InvalidRecoveryKey)
+        ));
+        assert!(draft.confirm(&answers[..1]).is_err());
+        assert!(!draft.confirmed);
+
+        let padded: Vec<String> = answers.iter().map(|w| format!(" {} ", w)).collect();
+        draft.confirm(&padded).unwrap();
+        assert!(draft.confirmed);
+    }
+
+    #[test]
+    fn test_expired_drafts_are_pruned() {
+        let mut store = DraftStore::new(Duration::ZERO);
+        let id = store.insert(VaultDraft::new(params()).unwrap().0);
+        assert_eq!(store.prune_expired(), 1);
+        assert!(matches!(
+            store.get_mut(&id),
+            Err(AppError::DraftNotFound(_))
+        ));
+
+        let mut store = DraftStore::default();
+        let id = store.insert(VaultDraft::new(params()).unwrap().0);
+        assert_eq!(store.prune_expired(), 0);
+        assert!(store.remove(&id).is_ok());
+        assert!(matches!(store.remove(&id), Err(AppError::DraftNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_gdrive_folders_rejects_bad_tokens() {
+        assert!(matches!(
+            list_gdrive_folders("not json", "root").await,
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+}
@@ -1,26 +1,33 @@
 //! Application facade — the single entry point for all vault operations.
 
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::sync::{RwLock, RwLockReadGuard};
+use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
 use tracing::info;
 use zeroize::Zeroizing;
 
-use axiomvault_common::{VaultId, VaultPath};
-use axiomvault_crypto::KdfParams;
-use axiomvault_vault::{VaultManager, VaultOperations, VaultSession};
+use axiomvault_common::shred::{self, ShredOutcome};
+use axiomvault_common::{Secret, VaultId, VaultPath};
+use axiomvault_crypto::{measure_derivation, KdfParams, MasterKey};
+use axiomvault_hooks::{HookDispatcher, HookEvent, HookStatus, HooksConfig};
+use axiomvault_storage::{create_default_registry, ProviderRegistry};
+use axiomvault_sync::{DeviceEnvironment, EnvironmentProbe, PushedEnvironment};
+use axiomvault_vault::{
+    BlobCache, DiffKind, DiffOptions, DiffReport, DiffTarget, DirEntry, IndexerHandle,
+    SearchConfig, VaultManager, VaultMetadata, VaultOperations, VaultSession,
+};
 
 use crate::dto::*;
 use crate::error::{AppError, AppResult};
 use crate::events::{event_channel, AppEvent, EventReceiver, EventSender};
 use crate::local_index::{IndexEntry, LocalIndex};
+use crate::onboarding::{self, DraftStore, VaultDraft};
 
-fn now_timestamp() -> i64 {
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
-}
+/// How long [`AppService::lock_vault`] lets pending saves run before
+/// clearing keys anyway.
+const LOCK_FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Application service wrapping all vault subsystems.
 ///
@@ -30,6 +37,24 @@ pub struct AppService {
     manager: VaultManager,
     session: RwLock<Option<ActiveVault>>,
     event_tx: EventSender,
+    /// Vault creation drafts awaiting recovery word confirmation.
+    drafts: Mutex<DraftStore>,
+    /// Device conditions pushed in by the host shell, for sync constraints.
+    sync_environment: PushedEnvironment,
+    /// Automation hooks fed from the event channel, once configured.
+    hooks: std::sync::Mutex<Option<ActiveHooks>>,
+}
+
+/// Configured hooks and the task feeding them events.
+struct ActiveHooks {
+    dispatcher: HookDispatcher,
+    listener: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for ActiveHooks {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
 }
 
 /// Internal state for an open vault.
@@ -38,16 +63,41 @@ struct ActiveVault {
     provider_type: String,
     /// Optional local metadata cache, updated on file operations.
     index: Option<LocalIndex>,
+    /// Background content indexer, while content search is enabled.
+    indexer: Option<IndexerHandle>,
+}
+
+impl ActiveVault {
+    /// Stop the content indexer, so it no longer shares the session.
+    async fn stop_indexer(&mut self) {
+        if let Some(indexer) = self.indexer.take() {
+            indexer.stop();
+            if let Err(e) = indexer.join().await {
+                tracing::warn!("Content indexer failed: {}", e);
+            }
+        }
+    }
 }
 
 impl AppService {
     /// Create a new application service.
     pub fn new() -> Self {
+        Self::with_registry(create_default_registry())
+    }
+
+    /// Create an application service backed by a custom provider registry.
+    ///
+    /// The registry is shared, so providers registered on another clone
+    /// after construction are visible to this service as well.
+    pub fn with_registry(registry: ProviderRegistry) -> Self {
         let (event_tx, _) = event_channel(64);
         Self {
-            manager: VaultManager::new(),
+            manager: VaultManager::with_registry(registry),
             session: RwLock::new(None),
             event_tx,
+            drafts: Mutex::new(DraftStore::default()),
+            sync_environment: PushedEnvironment::new(),
+            hooks: std::sync::Mutex::new(None),
         }
     }
 
@@ -61,6 +111,58 @@ impl AppService {
         &self.event_tx
     }
 
+    /// Report the device's connectivity and power state.
+    ///
+    /// Mobile shells call this whenever it changes; periodic syncs gated
+    /// on an unmetered network or external power follow it.
+    pub fn set_sync_environment(&self, environment: DeviceEnvironment) {
+        self.sync_environment.set(environment);
+    }
+
+    /// Probe reporting the state set with `set_sync_environment`, for the
+    /// sync scheduler.
+    pub fn sync_environment(&self) -> Arc<dyn EnvironmentProbe> {
+        Arc::new(self.sync_environment.clone())
+    }
+
+    /// Replace the automation hooks run for this service's events.
+    ///
+    /// Hooks come from the shell's settings, not from the vault. Command
+    /// hooks are refused unless `allow_exec_hooks` is set. Hooks run on
+    /// tasks of their own; a slow or failing hook never delays or fails
+    /// the operation that raised the event. An empty config removes all
+    /// hooks.
+    pub async fn set_hooks(&self, config: HooksConfig) -> AppResult<()> {
+        let active = if config.hooks.is_empty() {
+            None
+        } else {
+            let dispatcher = HookDispatcher::new(config)?;
+            let listener = dispatcher.listen(self.event_tx.subscribe(), |event: &AppEvent| {
+                match event {
+                    // A listing is a read, not something to automate on.
+                    AppEvent::DirectoryListed { .. } => None,
+                    event => HookEvent::from_enum(event),
+                }
+            });
+            Some(ActiveHooks {
+                dispatcher,
+                listener,
+            })
+        };
+        *self.hooks.lock().unwrap_or_else(|e| e.into_inner()) = active;
+        Ok(())
+    }
+
+    /// Runs, failures and rate-limited events of each configured hook.
+    pub fn hook_status(&self) -> Vec<HookStatus> {
+        self.hooks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|active| active.dispatcher.status())
+            .unwrap_or_default()
+    }
+
     fn emit(&self, event: AppEvent) {
         // Ignore send errors — no receivers is fine.
         let _ = self.event_tx.send(event);
@@ -73,6 +175,17 @@ impl AppService {
         &self.manager
     }
 
+    /// Validate a provider configuration without touching storage.
+    pub fn validate_provider_config(
+        &self,
+        provider_type: &str,
+        provider_config: &serde_json::Value,
+    ) -> AppResult<()> {
+        self.manager
+            .validate_provider_config(provider_type, provider_config)
+            .map_err(AppError::from)
+    }
+
     /// Parse a vault path string, mapping errors to `AppError::InvalidInput`.
     fn parse_path(path: &str) -> AppResult<VaultPath> {
         VaultPath::parse(path).map_err(|e| AppError::InvalidInput(e.to_string()))
@@ -101,6 +214,7 @@ impl AppService {
     pub async fn create_vault(&self, mut params: CreateVaultParams) -> AppResult<VaultCreatedDto> {
         let vault_id =
             VaultId::new(&params.vault_id).map_err(|e| AppError::InvalidInput(e.to_string()))?;
+        self.validate_provider_config(&params.provider_type, &params.provider_config)?;
 
         // Check if vault already exists.
         let exists = self
@@ -118,9 +232,10 @@ impl AppService {
         let provider_config = std::mem::take(&mut params.provider_config);
         let creation = self
             .manager
-            .create_vault(
+            .create_vault_with_keyfile(
                 vault_id,
-                params.password.as_bytes(),
+                &Secret::from(std::mem::take(&mut params.password)),
+                params.keyfile.as_deref().map(Vec::as_slice),
                 &params.provider_type,
                 provider_config,
                 KdfParams::default(),
@@ -129,55 +244,186 @@ impl AppService {
             .map_err(AppError::from)?;
 
         let provider_type = std::mem::take(&mut params.provider_type);
-        let info = VaultInfoDto {
-            id: creation.session.vault_id().to_string(),
-            provider_type: provider_type.clone(),
-            is_unlocked: true,
-        };
+        let info = self.activate_created(creation.session, provider_type).await;
 
+        info!(vault_id = %params.vault_id, "Vault created");
         // Move the mnemonic out of the manager response and into the DTO so the
         // bytes are never copied into a non-zeroizing buffer.
-        let dto = VaultCreatedDto {
-            info: info.clone(),
+        Ok(VaultCreatedDto {
+            info,
             recovery_words: creation.recovery_words,
-        };
+        })
+    }
+
+    /// Make a freshly created vault the open one and announce it.
+    async fn activate_created(&self, session: VaultSession, provider_type: String) -> VaultInfoDto {
+        let info = vault_info_dto(&session, &provider_type);
 
         *self.session.write().await = Some(ActiveVault {
-            session: Arc::new(creation.session),
+            session: Arc::new(session),
             provider_type,
             index: None,
+            indexer: None,
         });
 
-        self.emit(AppEvent::VaultCreated(info));
+        self.emit(AppEvent::VaultCreated(info.clone()));
+        info
+    }
+
+    // -- Guided vault creation --
 
-        info!(vault_id = %params.vault_id, "Vault created");
-        Ok(dto)
+    /// Measure how long unlocking takes with `strength` on this machine.
+    ///
+    /// Runs one key derivation on a blocking thread and returns its
+    /// duration in milliseconds.
+    pub async fn estimate_kdf_time(&self, strength: KdfStrength) -> AppResult<u64> {
+        let params = onboarding::kdf_params(strength);
+        let elapsed = tokio::task::spawn_blocking(move || measure_derivation(&params))
+            .await
+            .map_err(|e| AppError::Internal(format!("KDF calibration failed: {}", e)))??;
+        Ok(elapsed.as_millis() as u64)
+    }
+
+    /// List the Google Drive folders a vault can be created in.
+    ///
+    /// `parent_id` defaults to the root of the user's Drive.
+    pub async fn list_gdrive_folders(
+        &self,
+        tokens_json: &str,
+        parent_id: Option<&str>,
+    ) -> AppResult<Vec<DriveFolderDto>> {
+        onboarding::list_gdrive_folders(tokens_json, parent_id.unwrap_or("root")).await
+    }
+
+    /// Start creating a vault: check the choices and generate its recovery
+    /// words.
+    ///
+    /// Nothing is written to storage. The user must re-enter the words at
+    /// `confirm_positions` via [`confirm_recovery_words`](Self::confirm_recovery_words)
+    /// before [`finalize_vault_creation`](Self::finalize_vault_creation).
+    pub async fn begin_vault_creation(&self, draft: VaultDraftParams) -> AppResult<VaultDraftDto> {
+        VaultId::new(&draft.vault_id).map_err(|e| AppError::InvalidInput(e.to_string()))?;
+        self.validate_provider_config(&draft.provider_type, &draft.provider_config)?;
+        if self
+            .vault_exists(&draft.provider_type, draft.provider_config.clone())
+            .await?
+        {
+            return Err(AppError::VaultAlreadyExists(draft.vault_id.clone()));
+        }
+
+        let (draft, recovery_words) = VaultDraft::new(draft)?;
+        let confirm_positions = draft.confirm_positions.clone();
+
+        let mut drafts = self.drafts.lock().await;
+        drafts.prune_expired();
+        let draft_id = drafts.insert(draft);
+        Ok(VaultDraftDto {
+            draft_id,
+            recovery_words,
+            confirm_positions,
+        })
+    }
+
+    /// Check the recovery words re-entered for a draft, in the order of
+    /// its `confirm_positions`.
+    ///
+    /// # Errors
+    /// - `DraftNotFound` if the draft expired, was abandoned or finalized
+    /// - `InvalidRecoveryKey` if any word does not match
+    pub async fn confirm_recovery_words(&self, draft_id: &str, words: &[String]) -> AppResult<()> {
+        let mut drafts = self.drafts.lock().await;
+        drafts.prune_expired();
+        drafts.get_mut(draft_id)?.confirm(words)
+    }
+
+    /// Create and open the vault planned by a confirmed draft.
+    ///
+    /// The draft is kept if creation fails, so the user can retry.
+    ///
+    /// # Errors
+    /// - `DraftNotFound` if the draft expired, was abandoned or finalized
+    /// - `RecoveryNotConfirmed` if the recovery words were not confirmed
+    /// - Any error of vault creation
+    pub async fn finalize_vault_creation(
+        &self,
+        draft_id: &str,
+        password: Zeroizing<String>,
+    ) -> AppResult<VaultInfoDto> {
+        // Hold the lock throughout so the draft cannot be finalized twice.
+        let mut drafts = self.drafts.lock().await;
+        drafts.prune_expired();
+        let draft = drafts.get_mut(draft_id)?;
+        if !draft.confirmed {
+            return Err(AppError::RecoveryNotConfirmed);
+        }
+
+        let params = &draft.params;
+        let vault_id =
+            VaultId::new(&params.vault_id).map_err(|e| AppError::InvalidInput(e.to_string()))?;
+        if self
+            .vault_exists(&params.provider_type, params.provider_config.clone())
+            .await?
+        {
+            return Err(AppError::VaultAlreadyExists(params.vault_id.clone()));
+        }
+
+        let creation = self
+            .manager
+            .create_vault_with_recovery_key(
+                vault_id,
+                &Secret::from(password),
+                params.keyfile.as_deref().map(Vec::as_slice),
+                &draft.recovery_key,
+                &params.provider_type,
+                params.provider_config.clone(),
+                onboarding::kdf_params(params.strength),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let draft = drafts.remove(draft_id)?;
+        drop(drafts);
+        let info = self
+            .activate_created(creation.session, draft.params.provider_type.clone())
+            .await;
+
+        info!(vault_id = %draft.params.vault_id, "Vault created");
+        Ok(info)
+    }
+
+    /// Discard a draft the user backed out of.
+    ///
+    /// Drafts that are never finalized or abandoned expire on their own
+    /// after [`onboarding::DRAFT_TTL`].
+    pub async fn abandon_vault_creation(&self, draft_id: &str) -> AppResult<()> {
+        let mut drafts = self.drafts.lock().await;
+        drafts.prune_expired();
+        drafts.remove(draft_id).map(drop)
     }
 
     /// Open an existing vault.
     pub async fn open_vault(&self, mut params: OpenVaultParams) -> AppResult<VaultInfoDto> {
+        self.validate_provider_config(&params.provider_type, &params.provider_config)?;
         let provider_config = std::mem::take(&mut params.provider_config);
         let session = self
             .manager
-            .open_vault(
+            .open_vault_with_keyfile(
                 &params.provider_type,
                 provider_config,
-                params.password.as_bytes(),
+                &Secret::from(std::mem::take(&mut params.password)),
+                params.keyfile.as_deref().map(Vec::as_slice),
             )
             .await
             .map_err(AppError::from)?;
 
         let provider_type = std::mem::take(&mut params.provider_type);
-        let info = VaultInfoDto {
-            id: session.vault_id().to_string(),
-            provider_type: provider_type.clone(),
-            is_unlocked: true,
-        };
+        let info = vault_info_dto(&session, &provider_type);
 
         *self.session.write().await = Some(ActiveVault {
             session: Arc::new(session),
             provider_type,
             index: None,
+            indexer: None,
         });
 
         self.emit(AppEvent::VaultOpened(info.clone()));
@@ -195,22 +441,19 @@ impl AppService {
                 &params.provider_type,
                 provider_config,
                 &params.recovery_words,
-                params.new_password.as_bytes(),
+                &Secret::from(std::mem::take(&mut params.new_password)),
             )
             .await
             .map_err(AppError::from)?;
 
         let provider_type = std::mem::take(&mut params.provider_type);
-        let info = VaultInfoDto {
-            id: session.vault_id().to_string(),
-            provider_type: provider_type.clone(),
-            is_unlocked: true,
-        };
+        let info = vault_info_dto(&session, &provider_type);
 
         *self.session.write().await = Some(ActiveVault {
             session: Arc::new(session),
             provider_type,
             index: None,
+            indexer: None,
         });
 
         self.emit(AppEvent::VaultOpened(info.clone()));
@@ -219,10 +462,94 @@ impl AppService {
         Ok(info)
     }
 
+    /// Open a vault with a key previously exported by
+    /// [`rewrap_key`](Self::rewrap_key), e.g. after a biometric prompt.
+    ///
+    /// Fails with [`AppError::WrappedKeyStale`] once the password has
+    /// changed since the key was wrapped; unlock with the password and call
+    /// `rewrap_key` again.
+    pub async fn open_vault_with_wrapped_key(
+        &self,
+        mut params: OpenWrappedKeyParams,
+    ) -> AppResult<VaultInfoDto> {
+        self.validate_provider_config(&params.provider_type, &params.provider_config)?;
+        let provider_config = std::mem::take(&mut params.provider_config);
+        let session = self
+            .manager
+            .open_vault_with_wrapped_key(
+                &params.provider_type,
+                provider_config,
+                &params.wrapping_key,
+                &params.wrapped_key,
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let provider_type = std::mem::take(&mut params.provider_type);
+        let info = vault_info_dto(&session, &provider_type);
+
+        *self.session.write().await = Some(ActiveVault {
+            session: Arc::new(session),
+            provider_type,
+            index: None,
+            indexer: None,
+        });
+
+        self.emit(AppEvent::VaultOpened(info.clone()));
+
+        info!(vault_id = %info.id, "Vault opened with wrapped key");
+        Ok(info)
+    }
+
+    /// Open an existing vault with a master key supplied by an external key
+    /// management system.
+    ///
+    /// The key must be 32 bytes and not all zeros; it is wiped from memory
+    /// with `params` regardless of the outcome.
+    pub async fn open_vault_with_key(
+        &self,
+        mut params: OpenExternalKeyParams,
+    ) -> AppResult<VaultInfoDto> {
+        self.validate_provider_config(&params.provider_type, &params.provider_config)?;
+        let master_key = MasterKey::from_external(&params.master_key).map_err(AppError::from)?;
+        let provider_config = std::mem::take(&mut params.provider_config);
+        let session = self
+            .manager
+            .open_vault_with_key(&params.provider_type, provider_config, master_key)
+            .await
+            .map_err(AppError::from)?;
+
+        let provider_type = std::mem::take(&mut params.provider_type);
+        let info = vault_info_dto(&session, &provider_type);
+
+        *self.session.write().await = Some(ActiveVault {
+            session: Arc::new(session),
+            provider_type,
+            index: None,
+            indexer: None,
+        });
+
+        self.emit(AppEvent::VaultOpened(info.clone()));
+
+        info!(vault_id = %info.id, "Vault opened with external key");
+        Ok(info)
+    }
+
     /// Lock the active vault (clears keys from memory, wipes index).
     ///
     /// Requires exclusive access to the session — FUSE must be unmounted first.
     pub async fn lock_vault(&self) -> AppResult<()> {
+        self.lock_vault_within(LOCK_FLUSH_TIMEOUT).await
+    }
+
+    /// Lock the active vault, giving pending saves at most `budget`.
+    ///
+    /// The session is closed so a tree save or search index still pending
+    /// reaches storage first; if that fails or takes longer than `budget`,
+    /// the keys are cleared anyway and the vault journal lets the next open
+    /// recover. Requires exclusive access to the session, like
+    /// [`lock_vault`](Self::lock_vault).
+    pub async fn lock_vault_within(&self, budget: Duration) -> AppResult<()> {
         let mut guard = self.session.write().await;
         let active = guard.as_mut().ok_or(AppError::NoOpenVault)?;
 
@@ -233,11 +560,17 @@ impl AppService {
             }
         }
 
+        active.stop_indexer().await;
         let session = Arc::get_mut(&mut active.session).ok_or_else(|| {
             AppError::InvalidInput(
                 "Cannot lock vault while FUSE is mounted. Unmount first.".to_string(),
             )
         })?;
+        match tokio::time::timeout(budget, session.close()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::warn!("Failed to flush vault on lock: {}", e),
+            Err(_) => tracing::warn!("Flushing vault on lock timed out after {:?}", budget),
+        }
         session.lock();
         drop(guard);
 
@@ -249,7 +582,8 @@ impl AppService {
     /// Close the active vault entirely.
     pub async fn close_vault(&self) -> AppResult<()> {
         let mut guard = self.session.write().await;
-        let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
+        let active = guard.as_mut().ok_or(AppError::NoOpenVault)?;
+        active.stop_indexer().await;
 
         // Wipe cached plaintext metadata before closing.
         if let Some(ref index) = active.index {
@@ -275,23 +609,50 @@ impl AppService {
         &self,
         old_password: Zeroizing<String>,
         new_password: Zeroizing<String>,
+    ) -> AppResult<()> {
+        self.change_password_with_keyfile(old_password, None, new_password, None)
+            .await
+    }
+
+    /// Change the vault password and keyfile together.
+    ///
+    /// `old_keyfile` must match the vault's current keyfile, if it has one.
+    /// `new_keyfile` is required from now on; `None` drops the requirement.
+    /// All secrets are wiped from memory on return.
+    pub async fn change_password_with_keyfile(
+        &self,
+        old_password: Zeroizing<String>,
+        old_keyfile: Option<Zeroizing<Vec<u8>>>,
+        new_password: Zeroizing<String>,
+        new_keyfile: Option<Zeroizing<Vec<u8>>>,
     ) -> AppResult<()> {
         let mut guard = self.session.write().await;
         let active = guard.as_mut().ok_or(AppError::NoOpenVault)?;
 
+        let searching = active.indexer.is_some();
+        active.stop_indexer().await;
         let session = Arc::get_mut(&mut active.session).ok_or_else(|| {
             AppError::InvalidInput(
                 "Cannot change password while FUSE is mounted. Unmount first.".to_string(),
             )
         })?;
-        session
-            .change_password(old_password.as_bytes(), new_password.as_bytes())
-            .map_err(AppError::from)?;
+        let changed = session.change_password_with_keyfile(
+            &Secret::from_slice(old_password.as_bytes()),
+            old_keyfile.as_deref().map(Vec::as_slice),
+            &Secret::from_slice(new_password.as_bytes()),
+            new_keyfile.as_deref().map(Vec::as_slice),
+        );
+        if searching {
+            active.indexer = Some(active.session.start_indexing().map_err(AppError::from)?);
+        }
+        changed.map_err(AppError::from)?;
 
-        // Drop both passwords as soon as the underlying call returns. The
+        // Drop all secrets as soon as the underlying call returns. The
         // `Zeroizing` wrapper wipes the heap allocation on drop.
         drop(old_password);
+        drop(old_keyfile);
         drop(new_password);
+        drop(new_keyfile);
 
         // Persist the updated config.
         self.manager
@@ -304,6 +665,22 @@ impl AppService {
         Ok(())
     }
 
+    /// Wrap the master key of the open vault with a device-held key.
+    ///
+    /// The blob is bound to the current key generation, so clients should
+    /// call this again after every password change to replace the copy in
+    /// their keystore.
+    pub async fn rewrap_key(&self, wrapping_key: Zeroizing<Vec<u8>>) -> AppResult<Vec<u8>> {
+        let guard = self.active_vault().await?;
+        let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
+        let master_key = active.session.master_key().map_err(AppError::from)?;
+        active
+            .session
+            .config()
+            .wrap_key_for_device(&master_key, &wrapping_key)
+            .map_err(AppError::from)
+    }
+
     /// Check if a vault is currently open.
     pub async fn is_vault_open(&self) -> bool {
         self.session.read().await.is_some()
@@ -313,24 +690,239 @@ impl AppService {
     pub async fn vault_info(&self) -> AppResult<VaultInfoDto> {
         let guard = self.active_vault().await?;
         let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
-        Ok(VaultInfoDto {
-            id: active.session.vault_id().to_string(),
-            provider_type: active.provider_type.clone(),
-            is_unlocked: active.session.is_active(),
-        })
+        Ok(vault_info_dto(&active.session, &active.provider_type))
+    }
+
+    /// Change the display name, description, icon and color of the open
+    /// vault.
+    ///
+    /// The vault ID, storage paths and keys are untouched, so the vault
+    /// keeps opening everywhere under its old ID.
+    ///
+    /// # Errors
+    /// - `InvalidInput` for invalid details, or while FUSE is mounted
+    /// - `SyncConflict` if another client changed the details first; the
+    ///   open vault then shows theirs, and the update can be retried
+    pub async fn update_vault_details(
+        &self,
+        params: VaultDetailsParams,
+    ) -> AppResult<VaultInfoDto> {
+        let mut guard = self.session.write().await;
+        let active = guard.as_mut().ok_or(AppError::NoOpenVault)?;
+
+        let searching = active.indexer.is_some();
+        active.stop_indexer().await;
+        let session = Arc::get_mut(&mut active.session).ok_or_else(|| {
+            AppError::InvalidInput(
+                "Cannot change vault details while FUSE is mounted. Unmount first.".to_string(),
+            )
+        })?;
+        let updated = session
+            .update_metadata(VaultMetadata {
+                display_name: params.display_name,
+                description: params.description,
+                icon: params.icon,
+                color: params.color,
+            })
+            .await;
+        if searching {
+            active.indexer = Some(active.session.start_indexing().map_err(AppError::from)?);
+        }
+        updated.map_err(AppError::from)?;
+
+        let info = vault_info_dto(&active.session, &active.provider_type);
+        self.emit(AppEvent::VaultDetailsChanged(info.clone()));
+        info!(vault_id = %info.id, "Vault details changed");
+        Ok(info)
     }
 
     /// Attach a local index to the active vault for metadata caching.
     ///
-    /// Must be called after `create_vault` or `open_vault`. File operations
-    /// will automatically maintain the index when one is attached.
+    /// Must be called after `create_vault` or `open_vault`. The index is
+    /// rebuilt from the vault tree on attach, and file operations
+    /// automatically maintain it afterwards.
     pub async fn set_local_index(&self, index: LocalIndex) -> AppResult<()> {
         let mut guard = self.session.write().await;
         let active = guard.as_mut().ok_or(AppError::NoOpenVault)?;
+        index.rebuild_from_tree(&*active.session.tree().read().await)?;
         active.index = Some(index);
         Ok(())
     }
 
+    /// Keep encrypted copies of read files in `cache_dir`, so they can
+    /// still be read while the provider is unreachable.
+    pub async fn set_blob_cache(&self, cache_dir: &str) -> AppResult<()> {
+        let cache = BlobCache::open(cache_dir)?;
+        let guard = self.active_vault().await?;
+        let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
+        active.session.set_blob_cache(Some(cache));
+        Ok(())
+    }
+
+    // -- Content search --
+
+    /// Enable full-text search of file contents in the open vault.
+    ///
+    /// The encrypted index is loaded from the vault, or built, and kept up
+    /// to date in the background as files change.
+    pub async fn enable_content_search(&self) -> AppResult<()> {
+        let mut guard = self.session.write().await;
+        let active = guard.as_mut().ok_or(AppError::NoOpenVault)?;
+        active.stop_indexer().await;
+        active
+            .session
+            .enable_search(SearchConfig::default())
+            .await
+            .map_err(AppError::from)?;
+        active.indexer = Some(active.session.start_indexing().map_err(AppError::from)?);
+        Ok(())
+    }
+
+    /// Disable content search and delete the stored index.
+    pub async fn disable_content_search(&self) -> AppResult<()> {
+        let mut guard = self.session.write().await;
+        let active = guard.as_mut().ok_or(AppError::NoOpenVault)?;
+        active.stop_indexer().await;
+        active
+            .session
+            .disable_search()
+            .await
+            .map_err(AppError::from)
+    }
+
+    /// Rebuild the content index from scratch in the background.
+    ///
+    /// Returns the number of files queued.
+    pub async fn reindex_content(&self) -> AppResult<usize> {
+        let guard = self.active_vault().await?;
+        let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
+        active.session.reindex().await.map_err(AppError::from)
+    }
+
+    /// Find files whose contents contain `query` as a phrase.
+    pub async fn search_content(&self, query: &str, limit: usize) -> AppResult<Vec<SearchHitDto>> {
+        let guard = self.active_vault().await?;
+        let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
+        let hits = Self::ops(active)?
+            .search_content(query, limit)
+            .await
+            .map_err(AppError::from)?;
+        Ok(hits
+            .into_iter()
+            .map(|hit| SearchHitDto {
+                path: hit.path.to_string(),
+                matches: hit
+                    .matches
+                    .into_iter()
+                    .map(|m| SearchMatchDto {
+                        offset: m.offset,
+                        len: m.len,
+                        snippet: m.snippet,
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// Compare the open vault with a directory of plaintext files, such as
+    /// an export or a restore.
+    pub async fn compare_with_directory(
+        &self,
+        dir: &str,
+        options: CompareOptionsDto,
+    ) -> AppResult<DiffReportDto> {
+        let options = Self::diff_options(options)?;
+        let guard = self.active_vault().await?;
+        let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
+        let report = Self::ops(active)?
+            .diff_against(DiffTarget::LocalDir(std::path::Path::new(dir)), &options)
+            .await
+            .map_err(AppError::from)?;
+        Ok(Self::diff_report_dto(report))
+    }
+
+    /// Compare the open vault with another vault, which is opened for the
+    /// comparison only and not kept.
+    pub async fn compare_with_vault(
+        &self,
+        mut other: OpenVaultParams,
+        options: CompareOptionsDto,
+    ) -> AppResult<DiffReportDto> {
+        let options = Self::diff_options(options)?;
+        self.validate_provider_config(&other.provider_type, &other.provider_config)?;
+        let provider_config = std::mem::take(&mut other.provider_config);
+        let other = self
+            .manager
+            .open_vault_with_keyfile(
+                &other.provider_type,
+                provider_config,
+                &Secret::from(std::mem::take(&mut other.password)),
+                other.keyfile.as_deref().map(Vec::as_slice),
+            )
+            .await
+            .map_err(AppError::from)?;
+
+        let guard = self.active_vault().await?;
+        let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
+        let report = Self::ops(active)?
+            .diff_against(DiffTarget::Vault(&other), &options)
+            .await
+            .map_err(AppError::from)?;
+        Ok(Self::diff_report_dto(report))
+    }
+
+    fn diff_options(options: CompareOptionsDto) -> AppResult<DiffOptions> {
+        Ok(DiffOptions {
+            fast: options.fast,
+            ignore_timestamps: options.ignore_timestamps,
+            ignore: options
+                .ignore
+                .iter()
+                .map(|p| VaultPath::parse(p).map_err(AppError::from))
+                .collect::<AppResult<_>>()?,
+        })
+    }
+
+    fn diff_report_dto(report: DiffReport) -> DiffReportDto {
+        DiffReportDto {
+            identical: report.is_identical(),
+            compared: report.compared,
+            differences: report
+                .entries
+                .into_iter()
+                .map(|entry| DiffEntryDto {
+                    path: entry.path.to_string(),
+                    kind: match entry.kind {
+                        DiffKind::OnlyInA => "only_in_a",
+                        DiffKind::OnlyInB => "only_in_b",
+                        DiffKind::ContentDiffers => "content_differs",
+                        DiffKind::MetadataDiffers => "metadata_differs",
+                    }
+                    .to_string(),
+                    is_directory: entry.is_directory,
+                })
+                .collect(),
+        }
+    }
+
+    /// Bring the index row for `path` in line with the vault tree.
+    ///
+    /// Index failures are logged rather than returned: the vault operation
+    /// has already succeeded, and the next rebuild repairs any drift.
+    async fn refresh_index(active: &ActiveVault, path: &VaultPath) {
+        let Some(ref index) = active.index else {
+            return;
+        };
+        let tree = active.session.tree().read().await;
+        let result = match tree.get_node(path) {
+            Ok(node) => index.upsert_entry(&IndexEntry::from_node(path, node)),
+            Err(_) => index.delete_tree(&path.to_string()),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to update local index: {}", e);
+        }
+    }
+
     /// Get a shared reference to the vault session for FUSE mounting.
     ///
     /// The caller must drop the returned Arc before calling `lock_vault`,
@@ -355,16 +947,7 @@ impl AppService {
             .await
             .map_err(AppError::from)?;
 
-        if let Some(ref index) = active.index {
-            let _ = index.upsert_entry(&IndexEntry {
-                path: path.to_string(),
-                encrypted_name: String::new(),
-                is_directory: false,
-                size: Some(i64::try_from(content.len()).unwrap_or(i64::MAX)),
-                modified_at: now_timestamp(),
-                etag: None,
-            });
-        }
+        Self::refresh_index(active, &vault_path).await;
 
         drop(guard);
         self.emit(AppEvent::FileCreated {
@@ -394,16 +977,7 @@ impl AppService {
             .await
             .map_err(AppError::from)?;
 
-        if let Some(ref index) = active.index {
-            let _ = index.upsert_entry(&IndexEntry {
-                path: path.to_string(),
-                encrypted_name: String::new(),
-                is_directory: false,
-                size: Some(i64::try_from(content.len()).unwrap_or(i64::MAX)),
-                modified_at: now_timestamp(),
-                etag: None,
-            });
-        }
+        Self::refresh_index(active, &vault_path).await;
 
         drop(guard);
         self.emit(AppEvent::FileUpdated {
@@ -421,9 +995,7 @@ impl AppService {
 
         ops.delete_file(&vault_path).await.map_err(AppError::from)?;
 
-        if let Some(ref index) = active.index {
-            let _ = index.delete_entry(path);
-        }
+        Self::refresh_index(active, &vault_path).await;
 
         drop(guard);
         self.emit(AppEvent::FileDeleted {
@@ -445,16 +1017,7 @@ impl AppService {
             .await
             .map_err(AppError::from)?;
 
-        if let Some(ref index) = active.index {
-            let _ = index.upsert_entry(&IndexEntry {
-                path: path.to_string(),
-                encrypted_name: String::new(),
-                is_directory: true,
-                size: None,
-                modified_at: now_timestamp(),
-                etag: None,
-            });
-        }
+        Self::refresh_index(active, &vault_path).await;
 
         drop(guard);
         self.emit(AppEvent::DirectoryCreated {
@@ -475,7 +1038,45 @@ impl AppService {
             .await
             .map_err(AppError::from)?;
 
-        let dtos: Vec<DirectoryEntryDto> = entries
+        let dtos = Self::entry_dtos(path, entries);
+
+        drop(guard);
+        self.emit(AppEvent::DirectoryListed {
+            path: path.to_string(),
+            entries: dtos.clone(),
+        });
+        Ok(dtos)
+    }
+
+    /// List one page of a directory, sorted by name.
+    ///
+    /// Unlike [`list_directory`](Self::list_directory) this emits no
+    /// `DirectoryListed` event, since a page is not the whole directory.
+    pub async fn list_directory_page(
+        &self,
+        path: &str,
+        offset: usize,
+        limit: usize,
+    ) -> AppResult<DirectoryPageDto> {
+        let vault_path = Self::parse_path(path)?;
+        let guard = self.active_vault().await?;
+        let active = guard.as_ref().ok_or(AppError::NoOpenVault)?;
+        let ops = Self::ops(active)?;
+
+        let (entries, has_more) = ops
+            .list_directory_page(&vault_path, offset, limit)
+            .await
+            .map_err(AppError::from)?;
+
+        Ok(DirectoryPageDto {
+            entries: Self::entry_dtos(path, entries),
+            has_more,
+        })
+    }
+
+    /// Convert (name, is_directory, size) tuples listed under `path`.
+    fn entry_dtos(path: &str, entries: Vec<DirEntry>) -> Vec<DirectoryEntryDto> {
+        entries
             .into_iter()
             .map(|(name, is_directory, size)| {
                 let entry_path = if path == "/" {
@@ -491,14 +1092,7 @@ impl AppService {
                     modified_at: None,
                 }
             })
-            .collect();
-
-        drop(guard);
-        self.emit(AppEvent::DirectoryListed {
-            path: path.to_string(),
-            entries: dtos.clone(),
-        });
-        Ok(dtos)
+            .collect()
     }
 
     /// Delete an empty directory.
@@ -512,9 +1106,7 @@ impl AppService {
             .await
             .map_err(AppError::from)?;
 
-        if let Some(ref index) = active.index {
-            let _ = index.delete_entry(path);
-        }
+        Self::refresh_index(active, &vault_path).await;
 
         drop(guard);
         self.emit(AppEvent::DirectoryDeleted {
@@ -561,6 +1153,39 @@ impl AppService {
         self.create_file(vault_path, &content).await
     }
 
+    /// Import a local file and shred the original.
+    ///
+    /// The source is checked up front (no symlinks, no other hard links),
+    /// and is only overwritten and deleted once the copy read back from the
+    /// vault matches it; if anything fails before that it is left intact.
+    /// Shredding cannot reach old data on SSDs, and on copy-on-write
+    /// filesystems the file is only deleted, so shells should warn users.
+    pub async fn import_file_and_shred(
+        &self,
+        local_path: &str,
+        vault_path: &str,
+    ) -> AppResult<ShredOutcome> {
+        let source = PathBuf::from(local_path);
+        let sources = vec![source.clone()];
+        shred::check_shreddable(&source, &sources)?;
+
+        self.import_file(local_path, vault_path).await?;
+        let vault_copy = self.read_file(vault_path).await?;
+
+        let outcome =
+            tokio::task::spawn_blocking(move || shred::shred_file(&source, &sources, &vault_copy))
+                .await
+                .map_err(|e| AppError::Internal(format!("Shredding failed: {}", e)))??;
+        if let ShredOutcome::Deleted { filesystem } = &outcome {
+            tracing::warn!(
+                "{} is on {}; deleted without overwriting",
+                local_path,
+                filesystem
+            );
+        }
+        Ok(outcome)
+    }
+
     /// Export a vault file to the local filesystem.
     pub async fn export_file(&self, vault_path: &str, local_path: &str) -> AppResult<()> {
         let content = self.read_file(vault_path).await?;
@@ -596,6 +1221,21 @@ impl Default for AppService {
     }
 }
 
+/// Describe an open vault.
+fn vault_info_dto(session: &VaultSession, provider_type: &str) -> VaultInfoDto {
+    let config = session.config();
+    VaultInfoDto {
+        id: session.vault_id().to_string(),
+        display_name: config.display_name().to_string(),
+        description: config.description.clone(),
+        icon: config.icon.clone(),
+        color: config.color.clone(),
+        provider_type: provider_type.to_string(),
+        is_unlocked: session.is_active(),
+        key_generation: config.key_generation,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -609,6 +1249,7 @@ mod tests {
             .create_vault(CreateVaultParams {
                 vault_id: "test-vault".to_string(),
                 password: Zeroizing::new("secure-password".to_string()),
+                keyfile: None,
                 provider_type: "memory".to_string(),
                 provider_config: serde_json::Value::Null,
             })
@@ -632,6 +1273,7 @@ mod tests {
             .create_vault(CreateVaultParams {
                 vault_id: "test-vault".to_string(),
                 password: Zeroizing::new("password".to_string()),
+                keyfile: None,
                 provider_type: "memory".to_string(),
                 provider_config: serde_json::Value::Null,
             })
@@ -661,6 +1303,162 @@ mod tests {
         assert!(!service.exists("/hello.txt").await.unwrap());
     }
 
+    #[tokio::test]
+    async fn test_failing_hooks_do_not_fail_file_operations() {
+        use axiomvault_hooks::{EventFilter, Hook, HookAction};
+
+        let service = AppService::new();
+        service
+            .create_vault(CreateVaultParams {
+                vault_id: "test-vault".to_string(),
+                password: Zeroizing::new("password".to_string()),
+                keyfile: None,
+                provider_type: "memory".to_string(),
+                provider_config: serde_json::Value::Null,
+            })
+            .await
+            .unwrap();
+
+        let command = Hook::new(
+            EventFilter::default(),
+            HookAction::Command {
+                argv: vec!["true".to_string()],
+                working_dir: None,
+                timeout_secs: 5,
+            },
+        );
+        let refused = service
+            .set_hooks(HooksConfig {
+                allow_exec_hooks: false,
+                hooks: vec![command],
+            })
+            .await;
+        assert!(matches!(refused, Err(AppError::InvalidInput(_))));
+
+        // Nothing listens on this port once the listener is dropped.
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", closed.local_addr().unwrap());
+        drop(closed);
+        service
+            .set_hooks(HooksConfig {
+                allow_exec_hooks: false,
+                hooks: vec![Hook::new(
+                    EventFilter::events(["FileCreated"]),
+                    HookAction::Http {
+                        url,
+                        bearer_token: None,
+                        timeout_secs: 5,
+                    },
+                )],
+            })
+            .await
+            .unwrap();
+
+        service.create_file("/a.txt", b"a").await.unwrap();
+        service.create_directory("/docs").await.unwrap();
+        assert_eq!(service.read_file("/a.txt").await.unwrap(), b"a");
+
+        let status = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let status = service.hook_status();
+                if status[0].failures > 0 {
+                    return status;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!((status[0].runs, status[0].failures), (1, 1));
+
+        service.set_hooks(HooksConfig::default()).await.unwrap();
+        assert!(service.hook_status().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_content_search_follows_edits_and_lock() {
+        let service = AppService::new();
+
+        service
+            .create_vault(CreateVaultParams {
+                vault_id: "test-vault".to_string(),
+                password: Zeroizing::new("password".to_string()),
+                keyfile: None,
+                provider_type: "memory".to_string(),
+                provider_config: serde_json::Value::Null,
+            })
+            .await
+            .unwrap();
+
+        assert!(service.search_content("cobalt", 10).await.is_err());
+
+        service.enable_content_search().await.unwrap();
+        service
+            .create_file("/notes.txt", b"the cobalt lantern")
+            .await
+            .unwrap();
+
+        let hits = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            loop {
+                let hits = service.search_content("cobalt", 10).await.unwrap();
+                if !hits.is_empty() {
+                    break hits;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(hits[0].path, "/notes.txt");
+        assert!(hits[0].matches[0].snippet.contains("cobalt lantern"));
+
+        // Locking must stop the indexer so the session can be locked.
+        service.lock_vault().await.unwrap();
+        service.close_vault().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compare_with_directory() {
+        let service = AppService::new();
+        service
+            .create_vault(CreateVaultParams {
+                vault_id: "test-vault".to_string(),
+                password: Zeroizing::new("password".to_string()),
+                keyfile: None,
+                provider_type: "memory".to_string(),
+                provider_config: serde_json::Value::Null,
+            })
+            .await
+            .unwrap();
+        service.create_file("/a.txt", b"alpha").await.unwrap();
+        service.create_file("/b.txt", b"bravo").await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"alpha").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"BRAVO").unwrap();
+        std::fs::write(dir.path().join("c.txt"), b"charlie").unwrap();
+
+        let options = CompareOptionsDto {
+            ignore_timestamps: true,
+            ..Default::default()
+        };
+        let report = service
+            .compare_with_directory(dir.path().to_str().unwrap(), options)
+            .await
+            .unwrap();
+        assert!(!report.identical);
+        assert_eq!(report.compared, 2);
+        let found: Vec<(&str, &str)> = report
+            .differences
+            .iter()
+            .map(|d| (d.path.as_str(), d.kind.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [("/b.txt", "content_differs"), ("/c.txt", "only_in_b")]
+        );
+    }
+
     #[tokio::test]
     async fn test_directory_operations() {
         let service = AppService::new();
@@ -669,6 +1467,7 @@ mod tests {
             .create_vault(CreateVaultParams {
                 vault_id: "test-vault".to_string(),
                 password: Zeroizing::new("password".to_string()),
+                keyfile: None,
                 provider_type: "memory".to_string(),
                 provider_config: serde_json::Value::Null,
             })
@@ -685,6 +1484,160 @@ mod tests {
         assert_eq!(entries.len(), 1);
         assert_eq!(entries[0].name, "readme.txt");
         assert!(!entries[0].is_directory);
+
+        service
+            .create_file("/docs/notes.txt", b"Notes")
+            .await
+            .unwrap();
+        let page = service.list_directory_page("/docs", 0, 1).await.unwrap();
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].path, "/docs/notes.txt");
+        assert!(page.has_more);
+        let page = service.list_directory_page("/docs", 1, 1).await.unwrap();
+        assert_eq!(page.entries[0].path, "/docs/readme.txt");
+        assert!(!page.has_more);
+        assert!(matches!(
+            service.list_directory_page("/docs", 0, 0).await,
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+
+    fn draft_params(provider_type: &str, config: serde_json::Value) -> VaultDraftParams {
+        VaultDraftParams {
+            vault_id: "wizard".to_string(),
+            keyfile: None,
+            provider_type: provider_type.to_string(),
+            provider_config: config,
+            strength: KdfStrength::Moderate,
+        }
+    }
+
+    /// The words a draft asks for, in order.
+    fn asked_words(draft: &VaultDraftDto) -> Vec<String> {
+        let words: Vec<&str> = draft.recovery_words.split_whitespace().collect();
+        draft
+            .confirm_positions
+            .iter()
+            .map(|&p| words[p - 1].to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_vault_creation_draft_lifecycle() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let config = serde_json::json!({ "root": temp.path() });
+        let service = AppService::new();
+        let mut rx = service.subscribe();
+
+        let draft = service
+            .begin_vault_creation(draft_params("local", config.clone()))
+            .await
+            .unwrap();
+        assert_eq!(draft.recovery_words.split_whitespace().count(), 24);
+        assert_eq!(draft.confirm_positions.len(), 3);
+
+        // Nothing is written until the words are confirmed and finalized.
+        let password = || Zeroizing::new("wizard-password".to_string());
+        assert!(matches!(
+            service
+                .finalize_vault_creation(&draft.draft_id, password())
+                .await,
+            Err(AppError::RecoveryNotConfirmed)
+        ));
+        assert!(matches!(
+            service
+                .confirm_recovery_words(&draft.draft_id, &vec!["wrong".to_string(); 3])
+                .await,
+            Err(AppError::InvalidRecoveryKey)
+        ));
+        service
+            .confirm_recovery_words(&draft.draft_id, &asked_words(&draft))
+            .await
+            .unwrap();
+        assert!(std::fs::read_dir(temp.path()).unwrap().next().is_none());
+        assert!(!service.vault_exists("local", config.clone()).await.unwrap());
+        assert!(rx.try_recv().is_err());
+
+        let info = service
+            .finalize_vault_creation(&draft.draft_id, password())
+            .await
+            .unwrap();
+        assert_eq!(info.id, "wizard");
+        assert!(service.is_vault_open().await);
+        assert!(matches!(rx.try_recv().unwrap(), AppEvent::VaultCreated(_)));
+
+        // The draft is used up, and the shown words recover the vault.
+        assert!(matches!(
+            service
+                .finalize_vault_creation(&draft.draft_id, password())
+                .await,
+            Err(AppError::DraftNotFound(_))
+        ));
+        service.close_vault().await.unwrap();
+        service
+            .recover_vault(RecoverVaultParams {
+                recovery_words: Zeroizing::new(draft.recovery_words.to_string()),
+                new_password: Zeroizing::new("new-password".to_string()),
+                provider_type: "local".to_string(),
+                provider_config: config,
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_vault_draft_is_discarded() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let config = serde_json::json!({ "root": temp.path() });
+        let service = AppService::new();
+
+        let draft = service
+            .begin_vault_creation(draft_params("local", config))
+            .await
+            .unwrap();
+        service
+            .abandon_vault_creation(&draft.draft_id)
+            .await
+            .unwrap();
+
+        for result in [
+            service
+                .confirm_recovery_words(&draft.draft_id, &asked_words(&draft))
+                .await,
+            service.abandon_vault_creation(&draft.draft_id).await,
+        ] {
+            assert!(matches!(result, Err(AppError::DraftNotFound(_))));
+        }
+        assert!(std::fs::read_dir(temp.path()).unwrap().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_begin_vault_creation_validates_choices() {
+        let service = AppService::new();
+        let mut params = draft_params("memory", serde_json::Value::Null);
+        params.vault_id = String::new();
+        assert!(matches!(
+            service.begin_vault_creation(params).await,
+            Err(AppError::InvalidInput(_))
+        ));
+        assert!(service
+            .begin_vault_creation(draft_params("nope", serde_json::Value::Null))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_sync_environment_reaches_probe() {
+        let service = AppService::new();
+        let probe = service.sync_environment();
+        assert_eq!(probe.environment(), DeviceEnvironment::default());
+
+        let pushed = DeviceEnvironment {
+            metered: true,
+            on_power: false,
+        };
+        service.set_sync_environment(pushed);
+        assert_eq!(probe.environment(), pushed);
     }
 
     #[tokio::test]
@@ -695,6 +1648,7 @@ mod tests {
             .create_vault(CreateVaultParams {
                 vault_id: "test-vault".to_string(),
                 password: Zeroizing::new("password".to_string()),
+                keyfile: None,
                 provider_type: "memory".to_string(),
                 provider_config: serde_json::Value::Null,
             })
@@ -730,6 +1684,29 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_create_vault_rejects_invalid_provider_config() {
+        let service = AppService::new();
+
+        let result = service
+            .create_vault(CreateVaultParams {
+                vault_id: "test-vault".to_string(),
+                password: Zeroizing::new("secure-password".to_string()),
+                keyfile: None,
+                provider_type: "local".to_string(),
+                provider_config: serde_json::json!({ "path": "/tmp/vault" }),
+            })
+            .await;
+
+        match result {
+            Err(AppError::InvalidInput(msg)) => {
+                assert_eq!(msg, "Invalid local config: missing field 'root'")
+            }
+            other => panic!("expected InvalidInput, got {:?}", other.map(|_| ())),
+        }
+        assert!(!service.is_vault_open().await);
+    }
+
     #[tokio::test]
     async fn test_open_nonexistent_vault_returns_vault_not_found() {
         let service = AppService::new();
@@ -737,6 +1714,7 @@ mod tests {
         let result = service
             .open_vault(OpenVaultParams {
                 password: Zeroizing::new("password".to_string()),
+                keyfile: None,
                 provider_type: "memory".to_string(),
                 provider_config: serde_json::Value::Null,
             })
@@ -767,6 +1745,7 @@ mod tests {
             .create_vault(CreateVaultParams {
                 vault_id: "test-vault".to_string(),
                 password: Zeroizing::new("old-password".to_string()),
+                keyfile: None,
                 provider_type: "memory".to_string(),
                 provider_config: serde_json::Value::Null,
             })
@@ -0,0 +1,366 @@
+//! Shell-agnostic state behind a system tray or menu bar icon.
+//!
+//! [`TrayState`] folds [`AppEvent`]s into the set of unlocked vaults and an
+//! aggregate [`TrayStatus`] that a UI shell renders as its icon. Shells
+//! without tray support simply never construct one. [`shutdown`] runs the
+//! quit sequence a tray "Quit" item needs: unmount, drain syncs, then lock
+//! so keys are zeroized before the process exits.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::error::{AppError, AppResult};
+use crate::events::AppEvent;
+use crate::service::AppService;
+
+/// Aggregate status shown by the tray icon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayStatus {
+    /// No vault is unlocked.
+    AllLocked,
+    /// At least one vault is unlocked and none is syncing or failed.
+    UnlockedIdle,
+    /// A sync is in progress.
+    Syncing,
+    /// The last sync of some unlocked vault failed.
+    SyncError,
+}
+
+/// Per-vault state tracked for the tray menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VaultTrayState {
+    /// Unlocked, not syncing.
+    Idle,
+    /// Unlocked, sync in progress.
+    Syncing,
+    /// Unlocked, last sync failed with this message.
+    SyncFailed(String),
+}
+
+/// Unlocked vaults and their sync state, fed from the event stream.
+#[derive(Debug, Clone, Default)]
+pub struct TrayState {
+    vaults: BTreeMap<String, VaultTrayState>,
+    /// Display names of unlocked vaults, by ID.
+    names: BTreeMap<String, String>,
+}
+
+impl TrayState {
+    /// Empty state with every vault locked.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply an event emitted by the service owning `vault_id`.
+    ///
+    /// Open and create events carry their own vault id; the others are
+    /// attributed to `vault_id`. Sync events for a locked vault are ignored.
+    pub fn apply(&mut self, vault_id: &str, event: &AppEvent) {
+        match event {
+            AppEvent::VaultCreated(info) | AppEvent::VaultOpened(info) => {
+                self.vaults.insert(info.id.clone(), VaultTrayState::Idle);
+                self.names
+                    .insert(info.id.clone(), info.display_name.clone());
+            }
+            AppEvent::VaultDetailsChanged(info) => {
+                if self.vaults.contains_key(&info.id) {
+                    self.names
+                        .insert(info.id.clone(), info.display_name.clone());
+                }
+            }
+            AppEvent::VaultLocked | AppEvent::VaultClosed => {
+                self.vaults.remove(vault_id);
+                self.names.remove(vault_id);
+            }
+            AppEvent::SyncStarted => self.set(vault_id, VaultTrayState::Syncing),
+            AppEvent::SyncCompleted => self.set(vault_id, VaultTrayState::Idle),
+            AppEvent::SyncFailed { error } => {
+                self.set(vault_id, VaultTrayState::SyncFailed(error.clone()))
+            }
+            AppEvent::SyncQuotaExhausted { paused, .. } => self.set(
+                vault_id,
+                VaultTrayState::SyncFailed(format!("Storage is full; {} uploads paused", paused)),
+            ),
+            _ => {}
+        }
+    }
+
+    fn set(&mut self, vault_id: &str, state: VaultTrayState) {
+        if let Some(current) = self.vaults.get_mut(vault_id) {
+            *current = state;
+        }
+    }
+
+    /// Aggregate status; a failure outranks a running sync, which outranks idle.
+    pub fn status(&self) -> TrayStatus {
+        let mut status = TrayStatus::AllLocked;
+        for state in self.vaults.values() {
+            status = match (status, state) {
+                (_, VaultTrayState::SyncFailed(_)) => return TrayStatus::SyncError,
+                (_, VaultTrayState::Syncing) => TrayStatus::Syncing,
+                (TrayStatus::AllLocked, VaultTrayState::Idle) => TrayStatus::UnlockedIdle,
+                (current, VaultTrayState::Idle) => current,
+            };
+        }
+        status
+    }
+
+    /// Unlocked vaults in menu order, with their state.
+    pub fn unlocked_vaults(&self) -> impl Iterator<Item = (&str, &VaultTrayState)> {
+        self.vaults.iter().map(|(id, state)| (id.as_str(), state))
+    }
+
+    /// Menu label for an unlocked vault: its display name, followed by
+    /// the ID when the two differ.
+    pub fn label(&self, vault_id: &str) -> String {
+        match self.names.get(vault_id) {
+            Some(name) if !name.is_empty() && name != vault_id => {
+                format!("{} ({})", name, vault_id)
+            }
+            _ => vault_id.to_string(),
+        }
+    }
+}
+
+/// Step of the quit sequence, in the order [`shutdown`] runs them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownStep {
+    /// Mounts were released.
+    Unmounted,
+    /// In-flight syncs finished or the drain timed out.
+    Drained,
+    /// The session was locked, or no vault was open.
+    Locked,
+}
+
+/// Outcome of [`shutdown`].
+#[derive(Debug)]
+pub struct ShutdownReport {
+    /// Steps that ran, in order.
+    pub steps: Vec<ShutdownStep>,
+    /// Error from unmounting, if any. Locking is still attempted.
+    pub unmount_error: Option<AppError>,
+    /// Whether in-flight syncs were abandoned at the deadline.
+    pub drain_timed_out: bool,
+    /// Error from locking. When set, keys may still be in memory.
+    pub lock_error: Option<AppError>,
+}
+
+impl ShutdownReport {
+    /// Whether keys were zeroized.
+    pub fn is_locked(&self) -> bool {
+        self.lock_error.is_none()
+    }
+}
+
+/// Unmount, drain in-flight syncs, then lock `service`, all within
+/// `deadline`.
+///
+/// Unmounting comes first because locking needs exclusive access to the
+/// session, which a mount holds. Each step gets the time the earlier ones
+/// left over: an unmount or drain still running when it is spent is
+/// abandoned, and locking then clears the keys without waiting for pending
+/// saves. Having no open vault counts as locked.
+pub async fn shutdown<U, D>(
+    service: &AppService,
+    unmount: U,
+    drain: D,
+    deadline: Duration,
+) -> ShutdownReport
+where
+    U: Future<Output = AppResult<()>>,
+    D: Future<Output = ()>,
+{
+    let started = Instant::now();
+    let remaining = || deadline.saturating_sub(started.elapsed());
+    let mut steps = Vec::with_capacity(3);
+
+    let unmount_error = match tokio::time::timeout(remaining(), unmount).await {
+        Ok(result) => result.err(),
+        Err(_) => Some(AppError::Cancelled(format!(
+            "unmount did not finish within {:?}",
+            deadline
+        ))),
+    };
+    if let Some(ref e) = unmount_error {
+        tracing::warn!("Unmount failed during shutdown: {}", e);
+    }
+    steps.push(ShutdownStep::Unmounted);
+
+    let drain_timed_out = tokio::time::timeout(remaining(), drain).await.is_err();
+    if drain_timed_out {
+        tracing::warn!("In-flight syncs did not finish within {:?}", deadline);
+    }
+    steps.push(ShutdownStep::Drained);
+
+    let lock_error = match service.lock_vault_within(remaining()).await {
+        Ok(()) | Err(AppError::NoOpenVault) => None,
+        Err(e) => Some(e),
+    };
+    steps.push(ShutdownStep::Locked);
+
+    ShutdownReport {
+        steps,
+        unmount_error,
+        drain_timed_out,
+        lock_error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::{CreateVaultParams, VaultInfoDto};
+    use zeroize::Zeroizing;
+
+    fn opened(id: &str) -> AppEvent {
+        AppEvent::VaultOpened(VaultInfoDto {
+            id: id.to_string(),
+            display_name: id.to_string(),
+            description: None,
+            icon: None,
+            color: None,
+            provider_type: "memory".to_string(),
+            is_unlocked: true,
+            key_generation: 0,
+        })
+    }
+
+    #[test]
+    fn test_status_aggregation() {
+        let mut tray = TrayState::new();
+        assert_eq!(tray.status(), TrayStatus::AllLocked);
+
+        tray.apply("a", &opened("a"));
+        tray.apply("b", &opened("b"));
+        assert_eq!(tray.status(), TrayStatus::UnlockedIdle);
+
+        tray.apply("a", &AppEvent::SyncStarted);
+        assert_eq!(tray.status(), TrayStatus::Syncing);
+
+        tray.apply(
+            "b",
+            &AppEvent::SyncFailed {
+                error: "offline".to_string(),
+            },
+        );
+        assert_eq!(tray.status(), TrayStatus::SyncError);
+
+        tray.apply("b", &AppEvent::VaultLocked);
+        assert_eq!(tray.status(), TrayStatus::Syncing);
+
+        tray.apply("a", &AppEvent::SyncCompleted);
+        assert_eq!(tray.status(), TrayStatus::UnlockedIdle);
+        let ids: Vec<_> = tray.unlocked_vaults().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec!["a"]);
+
+        tray.apply("a", &AppEvent::VaultClosed);
+        assert_eq!(tray.status(), TrayStatus::AllLocked);
+    }
+
+    #[test]
+    fn test_quota_exhaustion_shows_as_sync_error() {
+        let mut tray = TrayState::new();
+        tray.apply("a", &opened("a"));
+        tray.apply("a", &AppEvent::SyncStarted);
+        tray.apply(
+            "a",
+            &AppEvent::SyncQuotaExhausted {
+                paused: 3,
+                paused_bytes: 300,
+            },
+        );
+        assert_eq!(tray.status(), TrayStatus::SyncError);
+        let (_, state) = tray.unlocked_vaults().next().unwrap();
+        assert_eq!(
+            state,
+            &VaultTrayState::SyncFailed("Storage is full; 3 uploads paused".to_string())
+        );
+    }
+
+    #[test]
+    fn test_label_prefers_display_name() {
+        let mut tray = TrayState::new();
+        tray.apply("a", &opened("a"));
+        assert_eq!(tray.label("a"), "a");
+
+        let AppEvent::VaultOpened(mut info) = opened("a") else {
+            unreachable!()
+        };
+        info.display_name = "Work".to_string();
+        tray.apply("a", &AppEvent::VaultDetailsChanged(info.clone()));
+        assert_eq!(tray.label("a"), "Work (a)");
+
+        // Details of a vault that is not unlocked are not tracked.
+        info.id = "b".to_string();
+        tray.apply("b", &AppEvent::VaultDetailsChanged(info));
+        assert_eq!(tray.label("b"), "b");
+
+        tray.apply("a", &AppEvent::VaultLocked);
+        assert_eq!(tray.label("a"), "a");
+    }
+
+    #[test]
+    fn test_sync_events_for_locked_vault_are_ignored() {
+        let mut tray = TrayState::new();
+        tray.apply("a", &AppEvent::SyncStarted);
+        assert_eq!(tray.status(), TrayStatus::AllLocked);
+        assert_eq!(tray.unlocked_vaults().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_runs_steps_in_order_without_vault() {
+        let service = AppService::new();
+        let report = shutdown(
+            &service,
+            async { Err(AppError::InvalidInput("busy".to_string())) },
+            std::future::pending(),
+            Duration::from_millis(10),
+        )
+        .await;
+
+        assert_eq!(
+            report.steps,
+            vec![
+                ShutdownStep::Unmounted,
+                ShutdownStep::Drained,
+                ShutdownStep::Locked
+            ]
+        );
+        assert!(report.unmount_error.is_some());
+        assert!(report.drain_timed_out);
+        assert!(report.is_locked());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_locks_when_steps_overrun_deadline() {
+        let service = AppService::new();
+        service
+            .create_vault(CreateVaultParams {
+                vault_id: "test-vault".to_string(),
+                password: Zeroizing::new("password".to_string()),
+                keyfile: None,
+                provider_type: "memory".to_string(),
+                provider_config: serde_json::Value::Null,
+            })
+            .await
+            .unwrap();
+        service.create_file("/a.txt", b"kept").await.unwrap();
+
+        let started = std::time::Instant::now();
+        let report = shutdown(
+            &service,
+            std::future::pending(),
+            std::future::pending(),
+            Duration::from_millis(50),
+        )
+        .await;
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(report.unmount_error, Some(AppError::Cancelled(_))));
+        assert!(report.drain_timed_out);
+        assert!(report.is_locked());
+        assert!(!service.vault_info().await.unwrap().is_unlocked);
+    }
+}
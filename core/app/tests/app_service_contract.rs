@@ -17,6 +17,7 @@ fn memory_params(vault_id: &str, password: &str) -> CreateVaultParams {
     CreateVaultParams {
         vault_id: vault_id.to_string(),
         password: Zeroizing::new(password.to_string()),
+        keyfile: None,
         provider_type: "memory".to_string(),
         provider_config: serde_json::Value::Null,
     }
@@ -25,6 +26,7 @@ fn memory_params(vault_id: &str, password: &str) -> CreateVaultParams {
 fn memory_open_params(password: &str) -> OpenVaultParams {
     OpenVaultParams {
         password: Zeroizing::new(password.to_string()),
+        keyfile: None,
         provider_type: "memory".to_string(),
         provider_config: serde_json::Value::Null,
     }
@@ -74,6 +76,7 @@ async fn create_duplicate_vault_fails() {
     svc.create_vault(CreateVaultParams {
         vault_id: "dup".to_string(),
         password: Zeroizing::new("pass".to_string()),
+        keyfile: None,
         provider_type: "local".to_string(),
         provider_config: config.clone(),
     })
@@ -86,6 +89,7 @@ async fn create_duplicate_vault_fails() {
         .create_vault(CreateVaultParams {
             vault_id: "dup".to_string(),
             password: Zeroizing::new("pass".to_string()),
+            keyfile: None,
             provider_type: "local".to_string(),
             provider_config: config,
         })
@@ -181,6 +185,7 @@ async fn recover_vault_with_valid_words() {
         .create_vault(CreateVaultParams {
             vault_id: "rec".to_string(),
             password: Zeroizing::new("old-pass".to_string()),
+            keyfile: None,
             provider_type: "local".to_string(),
             provider_config: config.clone(),
         })
@@ -219,6 +224,7 @@ async fn recover_vault_with_wrong_words_fails() {
     svc.create_vault(CreateVaultParams {
         vault_id: "rec2".to_string(),
         password: Zeroizing::new("pass".to_string()),
+        keyfile: None,
         provider_type: "local".to_string(),
         provider_config: config.clone(),
     })
@@ -284,6 +290,48 @@ async fn change_password_with_wrong_old_password_fails() {
     );
 }
 
+#[tokio::test]
+async fn change_password_with_keyfile_requires_keyfile_afterwards() {
+    let svc = service_with_vault().await;
+
+    svc.change_password_with_keyfile(
+        Zeroizing::new("password".to_string()),
+        None,
+        Zeroizing::new("new-password".to_string()),
+        Some(Zeroizing::new(b"keyfile".to_vec())),
+    )
+    .await
+    .unwrap();
+
+    let err = svc
+        .change_password(
+            Zeroizing::new("new-password".to_string()),
+            Zeroizing::new("other-password".to_string()),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, AppError::KeyfileRequired),
+        "expected KeyfileRequired, got {:?}",
+        err
+    );
+
+    let err = svc
+        .change_password_with_keyfile(
+            Zeroizing::new("new-password".to_string()),
+            Some(Zeroizing::new(b"wrong".to_vec())),
+            Zeroizing::new("other-password".to_string()),
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, AppError::InvalidKeyfile),
+        "expected InvalidKeyfile, got {:?}",
+        err
+    );
+}
+
 // ===========================================================================
 // File CRUD
 // ===========================================================================
@@ -664,38 +712,98 @@ async fn operations_fail_without_vault() {
 // LocalIndex integration
 // ===========================================================================
 
-#[tokio::test]
-async fn index_tracks_file_operations() {
-    let svc = AppService::new();
-    let result = svc
-        .create_vault(memory_params("idx", "pass"))
+/// Attach a file-backed index and return a second connection to it.
+async fn attach_file_index(svc: &AppService, dir: &tempfile::TempDir) -> LocalIndex {
+    let db = dir.path().join("index.db");
+    svc.set_local_index(LocalIndex::open(&db).unwrap())
         .await
         .unwrap();
-    let index = LocalIndex::in_memory().unwrap();
-    svc.set_local_index(index).await.unwrap();
+    LocalIndex::open(&db).unwrap()
+}
 
-    // Create file → index should have an entry.
-    svc.create_file("/tracked.txt", b"data").await.unwrap();
-    // We can't query the index directly from here, but the contract is:
-    // the service doesn't error, and operations remain consistent.
+/// Assert the index rows are exactly what a rebuild from the tree produces.
+async fn assert_index_matches_tree(svc: &AppService, index: &LocalIndex) {
+    let expected = LocalIndex::in_memory().unwrap();
+    let session = svc.vault_session().await.unwrap();
+    expected
+        .rebuild_from_tree(&*session.tree().read().await)
+        .unwrap();
+    assert_eq!(
+        index.all_entries().unwrap(),
+        expected.all_entries().unwrap()
+    );
+}
 
-    // Update file.
-    svc.update_file("/tracked.txt", b"updated").await.unwrap();
+#[tokio::test]
+async fn index_tracks_file_operations() {
+    let dir = tempfile::tempdir().unwrap();
+    let svc = service_with_vault().await;
+    let index = attach_file_index(&svc, &dir).await;
 
-    // Delete file.
-    svc.delete_file("/tracked.txt").await.unwrap();
-    assert!(!svc.exists("/tracked.txt").await.unwrap());
+    svc.create_directory("/docs").await.unwrap();
+    svc.create_file("/docs/tracked.txt", b"data").await.unwrap();
+    assert_index_matches_tree(&svc, &index).await;
+    let entry = index.get_entry("/docs/tracked.txt").unwrap().unwrap();
+    assert!(!entry.encrypted_name.is_empty());
+    assert_eq!(entry.size, Some(4));
+    assert!(!index
+        .get_entry("/docs")
+        .unwrap()
+        .unwrap()
+        .encrypted_name
+        .is_empty());
+
+    svc.update_file("/docs/tracked.txt", b"updated")
+        .await
+        .unwrap();
+    assert_index_matches_tree(&svc, &index).await;
+    let entry = index.get_entry("/docs/tracked.txt").unwrap().unwrap();
+    assert_eq!(entry.size, Some(7));
 
-    drop(result);
+    svc.delete_file("/docs/tracked.txt").await.unwrap();
+    assert_index_matches_tree(&svc, &index).await;
+    assert!(index.get_entry("/docs/tracked.txt").unwrap().is_none());
 }
 
 #[tokio::test]
 async fn index_tracks_directory_operations() {
-    let svc = service_with_index().await;
+    let dir = tempfile::tempdir().unwrap();
+    let svc = service_with_vault().await;
+    let index = attach_file_index(&svc, &dir).await;
 
     svc.create_directory("/indexed-dir").await.unwrap();
+    assert_index_matches_tree(&svc, &index).await;
+
     svc.delete_directory("/indexed-dir").await.unwrap();
     assert!(!svc.exists("/indexed-dir").await.unwrap());
+    assert_index_matches_tree(&svc, &index).await;
+    assert_eq!(index.count().unwrap(), 0);
+}
+
+#[tokio::test]
+async fn attaching_index_rebuilds_from_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let svc = service_with_vault().await;
+    svc.create_directory("/before").await.unwrap();
+    svc.create_file("/before/a.txt", b"a").await.unwrap();
+
+    let index = attach_file_index(&svc, &dir).await;
+    assert_eq!(index.count().unwrap(), 2);
+    assert_index_matches_tree(&svc, &index).await;
+}
+
+#[tokio::test]
+async fn blob_cache_keeps_read_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let svc = service_with_vault().await;
+    svc.set_blob_cache(dir.path().to_str().unwrap())
+        .await
+        .unwrap();
+
+    svc.create_file("/cached.txt", b"data").await.unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    assert_eq!(svc.read_file("/cached.txt").await.unwrap(), b"data");
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
 }
 
 #[tokio::test]
@@ -768,6 +876,75 @@ async fn import_nonexistent_local_file_fails() {
     );
 }
 
+#[tokio::test]
+async fn import_and_shred_removes_source_after_vault_copy_matches() {
+    let svc = service_with_vault().await;
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("secret.txt");
+    std::fs::write(&source, b"plaintext to shred").unwrap();
+
+    svc.import_file_and_shred(source.to_str().unwrap(), "/secret.txt")
+        .await
+        .unwrap();
+
+    assert!(!source.exists());
+    let content = svc.read_file("/secret.txt").await.unwrap();
+    assert_eq!(content, b"plaintext to shred");
+}
+
+#[tokio::test]
+async fn import_and_shred_keeps_source_when_vault_write_fails() {
+    let svc = service_with_vault().await;
+    svc.create_file("/taken.txt", b"already here")
+        .await
+        .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let source = dir.path().join("secret.txt");
+    std::fs::write(&source, b"plaintext to keep").unwrap();
+
+    let err = svc
+        .import_file_and_shred(source.to_str().unwrap(), "/taken.txt")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, AppError::PathAlreadyExists(_)),
+        "expected PathAlreadyExists, got {:?}",
+        err
+    );
+
+    assert_eq!(std::fs::read(&source).unwrap(), b"plaintext to keep");
+    let content = svc.read_file("/taken.txt").await.unwrap();
+    assert_eq!(content, b"already here");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn import_and_shred_refuses_symlinked_source() {
+    let svc = service_with_vault().await;
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target.txt");
+    let link = dir.path().join("link.txt");
+    std::fs::write(&target, b"behind a link").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let err = svc
+        .import_file_and_shred(link.to_str().unwrap(), "/link.txt")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, AppError::InvalidInput(_)),
+        "expected InvalidInput, got {:?}",
+        err
+    );
+
+    assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+    assert_eq!(std::fs::read(&target).unwrap(), b"behind a link");
+    assert!(matches!(
+        svc.read_file("/link.txt").await,
+        Err(AppError::PathNotFound(_))
+    ));
+}
+
 // ===========================================================================
 // vault_exists
 // ===========================================================================
@@ -786,6 +963,7 @@ async fn vault_exists_before_and_after_create() {
     svc.create_vault(CreateVaultParams {
         vault_id: "ex".to_string(),
         password: Zeroizing::new("pass".to_string()),
+        keyfile: None,
         provider_type: "local".to_string(),
         provider_config: config.clone(),
     })
@@ -853,6 +1031,14 @@ async fn event_round_trip_serialization() {
         AppEvent::SyncFailed {
             error: "err".to_string(),
         },
+        AppEvent::SyncQuotaLow {
+            needed: 200,
+            remaining: 100,
+        },
+        AppEvent::SyncQuotaExhausted {
+            paused: 2,
+            paused_bytes: 200,
+        },
         AppEvent::Error {
             message: "msg".to_string(),
         },
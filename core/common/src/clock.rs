@@ -0,0 +1,105 @@
+//! Source of the current time for time-dependent logic.
+//!
+//! Token expiry, conflict-copy names and trash retention read the time
+//! through a [`Clock`] rather than calling `Utc::now()` directly, so tests
+//! can drive them with a [`MockClock`] and skew can be injected.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Source of the current time.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// The current time.
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// A shared clock handle.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system's wall clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// The system clock as a shared handle.
+    pub fn shared() -> SharedClock {
+        Arc::new(Self)
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    /// A clock stopped at `start`.
+    pub fn new(start: DateTime<Utc>) -> Arc<Self> {
+        Arc::new(Self {
+            now: Mutex::new(start),
+        })
+    }
+
+    /// A clock stopped at the current system time.
+    pub fn starting_now() -> Arc<Self> {
+        Self::new(Utc::now())
+    }
+
+    /// Move the clock forward by `by`; a negative duration moves it back.
+    pub fn advance(&self, by: Duration) {
+        *self.lock() += by;
+    }
+
+    /// Set the clock to `now`.
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.lock() = now;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DateTime<Utc>> {
+        // A panic while holding the lock cannot leave the time half-written.
+        self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.lock()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_moves_only_when_told() {
+        let start = Utc::now() - Duration::days(365);
+        let clock = MockClock::new(start);
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::hours(2));
+        assert_eq!(clock.now(), start + Duration::hours(2));
+        clock.advance(Duration::minutes(-30));
+        assert_eq!(clock.now(), start + Duration::minutes(90));
+
+        clock.set(start);
+        let shared: SharedClock = clock.clone();
+        assert_eq!(shared.now(), start);
+    }
+
+    #[test]
+    fn test_system_clock_follows_utc_now() {
+        let before = Utc::now();
+        let now = SystemClock::shared().now();
+        assert!(before <= now && now <= Utc::now());
+    }
+}
@@ -1,7 +1,11 @@
 //! Common error types for AxiomVault.
 
+use std::time::Duration;
+
 use thiserror::Error;
 
+use crate::messages::MessageArg;
+
 /// Top-level error type for AxiomVault operations.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -41,6 +45,14 @@ pub enum Error {
     #[error("Already exists: {0}")]
     AlreadyExists(String),
 
+    /// The vault requires a keyfile but none was supplied.
+    #[error("This vault requires a keyfile")]
+    KeyfileRequired,
+
+    /// The supplied keyfile does not belong to this vault.
+    #[error("Keyfile does not match this vault")]
+    KeyfileMismatch,
+
     /// Conflict detected.
     #[error("Conflict: {0}")]
     Conflict(String),
@@ -66,7 +78,229 @@ pub enum Error {
     /// Network operation failed.
     #[error("Network error: {0}")]
     Network(String),
+
+    /// The operation is not supported by this provider, vault, or session.
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+
+    /// The operation was cancelled before it finished.
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    /// A provider or local limit rejected the request; retry after backing
+    /// off.
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
+    /// The provider account has no storage space left. Retrying does not
+    /// help until space is freed or the quota is raised.
+    #[error("Storage quota exhausted: {0}")]
+    QuotaExhausted(String),
+
+    /// Stored data failed an integrity check (bad MAC, checksum mismatch,
+    /// tampered or truncated metadata).
+    #[error("Integrity check failed: {0}")]
+    Integrity(String),
+
+    /// The vault configuration does not match its MAC: a protected field
+    /// was changed by someone who could not unlock the vault.
+    #[error("Vault configuration has been tampered with")]
+    ConfigTampered,
+
+    /// A wrapped key was minted for an older key generation and must be
+    /// re-wrapped before it can unlock the vault.
+    #[error("Wrapped key is stale: {0}")]
+    WrappedKeyStale(String),
+
+    /// Another process is syncing the same vault. Carries its process ID
+    /// when it could be read.
+    #[error("Sync already in progress by {}", sync_holder(.0))]
+    SyncInProgress(Option<u32>),
+
+    /// The vault must be migrated before it can be opened. Carries the IDs
+    /// of the migrations that need the user's go-ahead.
+    #[error("Vault needs migration: {}", .0.join(", "))]
+    MigrationRequired(Vec<String>),
+
+    /// A mount point could not be released because files on it are open.
+    /// Carries the IDs of the processes holding them, where they could be
+    /// found.
+    #[error("Mount point is busy{}", mount_holders(.0))]
+    MountBusy(Vec<u32>),
+
+    /// A storage provider's API refused a request for a reason with no
+    /// more specific variant.
+    #[error("Provider error: {message}")]
+    Provider {
+        /// HTTP status of the response, if there was one.
+        status: Option<u16>,
+        /// Whether repeating the request may succeed.
+        retryable: bool,
+        /// How long the provider asked to wait before retrying.
+        retry_after: Option<Duration>,
+        /// What went wrong.
+        message: String,
+    },
+}
+
+impl Error {
+    /// Stable key of the error's message, for rendering it with a
+    /// [`Catalog`](crate::messages::Catalog) in another language.
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            Error::Crypto(_) => "error-crypto",
+            Error::Vault(_) => "error-vault",
+            Error::Storage(_) => "error-storage",
+            Error::Io(_) => "error-io",
+            Error::Serialization(_) => "error-serialization",
+            Error::InvalidInput(_) => "error-invalid-input",
+            Error::NotPermitted(_) => "error-not-permitted",
+            Error::NotFound(_) => "error-not-found",
+            Error::AlreadyExists(_) => "error-already-exists",
+            Error::KeyfileRequired => "error-keyfile-required",
+            Error::KeyfileMismatch => "error-keyfile-mismatch",
+            Error::Conflict(_) => "error-conflict",
+            Error::Authentication(_) => "error-authentication",
+            Error::AuthenticationExpired(_) => "error-authentication-expired",
+            Error::Network(_) => "error-network",
+            Error::Unsupported(_) => "error-unsupported",
+            Error::Cancelled(_) => "error-cancelled",
+            Error::RateLimited(_) => "error-rate-limited",
+            Error::QuotaExhausted(_) => "error-quota-exhausted",
+            Error::Integrity(_) => "error-integrity",
+            Error::ConfigTampered => "error-config-tampered",
+            Error::WrappedKeyStale(_) => "error-wrapped-key-stale",
+            Error::SyncInProgress(Some(_)) => "error-sync-in-progress-pid",
+            Error::SyncInProgress(None) => "error-sync-in-progress",
+            Error::MigrationRequired(_) => "error-migration-required",
+            Error::MountBusy(pids) if pids.is_empty() => "error-mount-busy",
+            Error::MountBusy(_) => "error-mount-busy-pids",
+            Error::Provider { .. } => "error-provider",
+        }
+    }
+
+    /// Named values filled into the error's message. Errors carrying a
+    /// description have it as `detail`.
+    pub fn args(&self) -> Vec<(&'static str, MessageArg)> {
+        match self {
+            Error::Crypto(detail)
+            | Error::Vault(detail)
+            | Error::Storage(detail)
+            | Error::Serialization(detail)
+            | Error::InvalidInput(detail)
+            | Error::NotPermitted(detail)
+            | Error::NotFound(detail)
+            | Error::AlreadyExists(detail)
+            | Error::Conflict(detail)
+            | Error::Authentication(detail)
+            | Error::AuthenticationExpired(detail)
+            | Error::Network(detail)
+            | Error::Unsupported(detail)
+            | Error::Cancelled(detail)
+            | Error::RateLimited(detail)
+            | Error::QuotaExhausted(detail)
+            | Error::Integrity(detail)
+            | Error::WrappedKeyStale(detail) => {
+                vec![("detail", MessageArg::Text(detail.clone()))]
+            }
+            Error::Io(e) => vec![("detail", MessageArg::Text(e.to_string()))],
+            Error::SyncInProgress(Some(pid)) => vec![("pid", MessageArg::Number(u64::from(*pid)))],
+            Error::MigrationRequired(ids) => vec![("detail", MessageArg::Text(ids.join(", ")))],
+            Error::MountBusy(pids) if !pids.is_empty() => {
+                vec![("detail", MessageArg::Text(pid_list(pids)))]
+            }
+            Error::Provider { message, .. } => vec![("detail", MessageArg::Text(message.clone()))],
+            Error::KeyfileRequired
+            | Error::KeyfileMismatch
+            | Error::ConfigTampered
+            | Error::SyncInProgress(None)
+            | Error::MountBusy(_) => Vec::new(),
+        }
+    }
+}
+
+/// Describe the process holding a sync, for [`Error::SyncInProgress`].
+fn sync_holder(pid: &Option<u32>) -> String {
+    match pid {
+        Some(pid) => format!("pid {}", pid),
+        None => "another process".to_string(),
+    }
+}
+
+/// Describe the processes holding a mount, for [`Error::MountBusy`].
+fn mount_holders(pids: &[u32]) -> String {
+    if pids.is_empty() {
+        String::new()
+    } else {
+        format!(", held open by pid {}", pid_list(pids))
+    }
+}
+
+fn pid_list(pids: &[u32]) -> String {
+    pids.iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 /// Result type alias using the common Error.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_messages() {
+        let cases = [
+            (
+                Error::Conflict("etag changed".into()),
+                "Conflict: etag changed",
+            ),
+            (
+                Error::Unsupported("streaming".into()),
+                "Unsupported: streaming",
+            ),
+            (
+                Error::Cancelled("unlock aborted".into()),
+                "Cancelled: unlock aborted",
+            ),
+            (
+                Error::RateLimited("429 from Drive".into()),
+                "Rate limited: 429 from Drive",
+            ),
+            (
+                Error::QuotaExhausted("Drive storage is full".into()),
+                "Storage quota exhausted: Drive storage is full",
+            ),
+            (
+                Error::Integrity("config MAC mismatch".into()),
+                "Integrity check failed: config MAC mismatch",
+            ),
+            (
+                Error::ConfigTampered,
+                "Vault configuration has been tampered with",
+            ),
+            (
+                Error::SyncInProgress(Some(4242)),
+                "Sync already in progress by pid 4242",
+            ),
+            (
+                Error::SyncInProgress(None),
+                "Sync already in progress by another process",
+            ),
+            (
+                Error::MigrationRequired(vec!["legacy-key-wrap".into(), "tree-stream".into()]),
+                "Vault needs migration: legacy-key-wrap, tree-stream",
+            ),
+            (Error::MountBusy(Vec::new()), "Mount point is busy"),
+            (
+                Error::MountBusy(vec![812, 4242]),
+                "Mount point is busy, held open by pid 812, 4242",
+            ),
+        ];
+        for (error, expected) in cases {
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+}
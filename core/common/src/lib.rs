@@ -3,10 +3,19 @@
 //! This module provides foundational types that are used throughout the codebase,
 //! ensuring consistency and type safety.
 
+pub mod clock;
 pub mod error;
 pub mod health;
+pub mod messages;
+pub mod secret;
+pub mod shred;
+pub mod telemetry;
 pub mod types;
 
+pub use clock::{Clock, MockClock, SharedClock, SystemClock};
 pub use error::{Error, Result};
 pub use health::{DiagnosticResult, HealthReport, HealthStatus, Severity};
-pub use types::{VaultId, VaultPath};
+pub use messages::{Catalog, MessageArg};
+pub use secret::Secret;
+pub use shred::{shred_file, ShredOutcome};
+pub use types::{escape_lossy, RelativeVaultPath, VaultId, VaultPath};
@@ -0,0 +1,394 @@
+//! Localized rendering of [`Error`]s.
+//!
+//! Every error has a stable [`message_key`](Error::message_key) and named
+//! [`args`](Error::args). A [`Catalog`] maps those keys to templates for one
+//! locale, in which `{name}` stands for the argument of that name. English
+//! and German catalogs ship with the crate; clients load others at runtime
+//! with [`Catalog::parse`]. The English catalog renders errors exactly like
+//! their `Display`, which the CLI uses.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+
+/// Key of the catalog entry holding the locale's decimal separator.
+const DECIMAL_SEPARATOR_KEY: &str = "decimal-separator";
+
+/// Every message key an [`Error`] can have.
+pub const MESSAGE_KEYS: &[&str] = &[
+    "error-crypto",
+    "error-vault",
+    "error-storage",
+    "error-io",
+    "error-serialization",
+    "error-invalid-input",
+    "error-not-permitted",
+    "error-not-found",
+    "error-already-exists",
+    "error-keyfile-required",
+    "error-keyfile-mismatch",
+    "error-conflict",
+    "error-authentication",
+    "error-authentication-expired",
+    "error-network",
+    "error-unsupported",
+    "error-cancelled",
+    "error-rate-limited",
+    "error-quota-exhausted",
+    "error-integrity",
+    "error-config-tampered",
+    "error-wrapped-key-stale",
+    "error-sync-in-progress",
+    "error-sync-in-progress-pid",
+    "error-migration-required",
+    "error-mount-busy",
+    "error-mount-busy-pids",
+    "error-provider",
+];
+
+/// A value filled into a message template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageArg {
+    /// Text inserted as is.
+    Text(String),
+    /// A plain integer.
+    Number(u64),
+    /// A size, rendered with [`Catalog::format_bytes`].
+    Bytes(u64),
+    /// A duration, rendered with [`Catalog::format_duration`].
+    Duration(Duration),
+}
+
+/// Message templates of one locale.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    locale: String,
+    decimal_separator: char,
+    templates: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// The English catalog shipped with the crate.
+    pub fn english() -> Self {
+        Self::parse("en", include_str!("../locales/en.txt")).expect("shipped catalog parses")
+    }
+
+    /// The German catalog shipped with the crate.
+    pub fn german() -> Self {
+        Self::parse("de", include_str!("../locales/de.txt")).expect("shipped catalog parses")
+    }
+
+    /// The shipped catalog for a language tag such as `de` or `de-AT`, or
+    /// `None` if the language is not shipped.
+    pub fn shipped(locale: &str) -> Option<Self> {
+        match locale.split(['-', '_']).next()? {
+            "en" => Some(Self::english()),
+            "de" => Some(Self::german()),
+            _ => None,
+        }
+    }
+
+    /// Parse a catalog: one `key = template` per line. Blank lines and
+    /// lines starting with `#` are skipped. The `decimal-separator` entry
+    /// sets the separator numbers are rendered with, `.` by default.
+    ///
+    /// # Errors
+    /// - `InvalidInput` for a line without `=`, an empty key, a key given
+    ///   twice, or a decimal separator that is not a single character
+    pub fn parse(locale: impl Into<String>, text: &str) -> Result<Self> {
+        let mut templates = HashMap::new();
+        let mut decimal_separator = '.';
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| {
+                Error::InvalidInput(format!("Catalog line {}: {}", index + 1, reason))
+            };
+            let (key, template) = line.split_once('=').ok_or_else(|| invalid("missing '='"))?;
+            let (key, template) = (key.trim(), template.trim());
+            if key.is_empty() {
+                return Err(invalid("empty key"));
+            }
+            if key == DECIMAL_SEPARATOR_KEY {
+                let mut chars = template.chars();
+                decimal_separator = match (chars.next(), chars.next()) {
+                    (Some(separator), None) => separator,
+                    _ => return Err(invalid("decimal separator must be one character")),
+                };
+                continue;
+            }
+            if templates
+                .insert(key.to_string(), template.to_string())
+                .is_some()
+            {
+                return Err(invalid(&format!("duplicate key {}", key)));
+            }
+        }
+        Ok(Self {
+            locale: locale.into(),
+            decimal_separator,
+            templates,
+        })
+    }
+
+    /// Language tag of the catalog.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Whether the catalog has a template for `key`.
+    pub fn contains(&self, key: &str) -> bool {
+        self.templates.contains_key(key)
+    }
+
+    /// Render `error` in the catalog's locale.
+    ///
+    /// Falls back to the English `Display` of the error if the catalog has
+    /// no template for its key.
+    pub fn render(&self, error: &Error) -> String {
+        match self.templates.get(error.message_key()) {
+            Some(template) => self.fill(template, &error.args()),
+            None => error.to_string(),
+        }
+    }
+
+    /// Replace each `{name}` in `template` with the argument of that name.
+    /// Placeholders without an argument are left as they are.
+    pub fn fill(&self, template: &str, args: &[(&str, MessageArg)]) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let Some(end) = after.find('}') else {
+                rest = &rest[start..];
+                break;
+            };
+            let name = &after[..end];
+            match args.iter().find(|(arg, _)| *arg == name) {
+                Some((_, value)) => out.push_str(&self.format_arg(value)),
+                None => out.push_str(&rest[start..start + end + 2]),
+            }
+            rest = &after[end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn format_arg(&self, arg: &MessageArg) -> String {
+        match arg {
+            MessageArg::Text(text) => text.clone(),
+            MessageArg::Number(number) => number.to_string(),
+            MessageArg::Bytes(bytes) => self.format_bytes(*bytes),
+            MessageArg::Duration(duration) => self.format_duration(*duration),
+        }
+    }
+
+    /// A size in binary units with one decimal, e.g. `1.5 MiB`, or `1,5 MiB`
+    /// in a locale with a decimal comma. Sizes under 1 KiB are exact.
+    pub fn format_bytes(&self, bytes: u64) -> String {
+        const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+        if bytes < 1024 {
+            return format!("{} B", bytes);
+        }
+        let mut value = bytes as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        format!("{} {}", self.decimal(value), UNITS[unit])
+    }
+
+    /// A duration in its two largest units, e.g. `2 min 5 s` or `1 h 30
+    /// min`. Durations under a minute get one decimal, e.g. `1.5 s`, and
+    /// those under a second are given in milliseconds.
+    pub fn format_duration(&self, duration: Duration) -> String {
+        let secs = duration.as_secs();
+        if secs == 0 {
+            return format!("{} ms", duration.as_millis());
+        }
+        if secs < 60 {
+            return format!("{} s", self.decimal(duration.as_secs_f64()));
+        }
+        let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+        if hours > 0 {
+            format!("{} h {} min", hours, minutes)
+        } else {
+            format!("{} min {} s", minutes, seconds)
+        }
+    }
+
+    /// `value` with one decimal and the locale's separator.
+    fn decimal(&self, value: f64) -> String {
+        let text = format!("{:.1}", value);
+        if self.decimal_separator == '.' {
+            text
+        } else {
+            text.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One error of every variant.
+    fn every_variant() -> Vec<Error> {
+        vec![
+            Error::Crypto("bad tag".into()),
+            Error::Vault("locked".into()),
+            Error::Storage("disk gone".into()),
+            Error::Io(std::io::Error::other("broken pipe")),
+            Error::Serialization("unexpected end".into()),
+            Error::InvalidInput("empty name".into()),
+            Error::NotPermitted("read-only".into()),
+            Error::NotFound("/a.txt".into()),
+            Error::AlreadyExists("/a.txt".into()),
+            Error::KeyfileRequired,
+            Error::KeyfileMismatch,
+            Error::Conflict("etag changed".into()),
+            Error::Authentication("revoked".into()),
+            Error::AuthenticationExpired("401".into()),
+            Error::Network("timeout".into()),
+            Error::Unsupported("streaming".into()),
+            Error::Cancelled("unlock aborted".into()),
+            Error::RateLimited("429 from Drive".into()),
+            Error::QuotaExhausted("Drive storage is full".into()),
+            Error::Integrity("config MAC mismatch".into()),
+            Error::ConfigTampered,
+            Error::WrappedKeyStale("generation 2".into()),
+            Error::SyncInProgress(Some(4242)),
+            Error::SyncInProgress(None),
+            Error::MigrationRequired(vec!["legacy-key-wrap".into()]),
+            Error::MountBusy(Vec::new()),
+            Error::MountBusy(vec![4242]),
+            Error::Provider {
+                status: Some(503),
+                retryable: true,
+                retry_after: None,
+                message: "API error: 503 Service Unavailable".into(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_english_catalog_matches_display() {
+        let english = Catalog::english();
+        for error in every_variant() {
+            assert!(english.contains(error.message_key()), "{:?}", error);
+            assert_eq!(english.render(&error), error.to_string());
+        }
+    }
+
+    #[test]
+    fn test_shipped_catalogs_cover_every_key() {
+        let keys: Vec<&str> = every_variant().iter().map(Error::message_key).collect();
+        for key in MESSAGE_KEYS {
+            assert!(keys.contains(key), "no variant has key {}", key);
+        }
+        for catalog in [Catalog::english(), Catalog::german()] {
+            for key in &keys {
+                assert!(
+                    catalog.contains(key),
+                    "{} catalog lacks {}",
+                    catalog.locale(),
+                    key
+                );
+            }
+            assert_eq!(catalog.templates.len(), MESSAGE_KEYS.len());
+        }
+    }
+
+    #[test]
+    fn test_german_rendering() {
+        let german = Catalog::shipped("de-AT").unwrap();
+        assert_eq!(german.locale(), "de");
+        assert_eq!(
+            german.render(&Error::NotFound("/a.txt".into())),
+            "Nicht gefunden: /a.txt"
+        );
+        assert_eq!(
+            german.render(&Error::SyncInProgress(Some(4242))),
+            "Synchronisierung läuft bereits in Prozess 4242"
+        );
+        assert_eq!(german.format_bytes(1536), "1,5 KiB");
+        assert!(Catalog::shipped("fr").is_none());
+    }
+
+    #[test]
+    fn test_runtime_catalog() {
+        let catalog = Catalog::parse(
+            "x-test",
+            "# comment\n\nerror-not-found = Missing {detail} ({detail})\n",
+        )
+        .unwrap();
+        assert_eq!(
+            catalog.render(&Error::NotFound("/a".into())),
+            "Missing /a (/a)"
+        );
+        // Keys the catalog lacks fall back to English.
+        assert_eq!(
+            catalog.render(&Error::KeyfileRequired),
+            "This vault requires a keyfile"
+        );
+        assert_eq!(
+            catalog.fill(
+                "{size} of {limit} {unknown",
+                &[("size", MessageArg::Bytes(512))]
+            ),
+            "512 B of {limit} {unknown"
+        );
+
+        for text in [
+            "no separator",
+            " = empty key",
+            "a = 1\na = 2",
+            "decimal-separator = ,,",
+        ] {
+            assert!(matches!(
+                Catalog::parse("x", text),
+                Err(Error::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_format_bytes_and_durations() {
+        let english = Catalog::english();
+        assert_eq!(english.format_bytes(0), "0 B");
+        assert_eq!(english.format_bytes(1023), "1023 B");
+        assert_eq!(english.format_bytes(1024), "1.0 KiB");
+        assert_eq!(
+            english.format_bytes(5 * 1024 * 1024 + 1024 * 1024 / 2),
+            "5.5 MiB"
+        );
+        assert_eq!(english.format_bytes(3 << 40), "3.0 TiB");
+        assert_eq!(english.format_bytes(u64::MAX), "16384.0 PiB");
+
+        assert_eq!(
+            english.format_duration(Duration::from_millis(250)),
+            "250 ms"
+        );
+        assert_eq!(
+            english.format_duration(Duration::from_millis(1500)),
+            "1.5 s"
+        );
+        assert_eq!(
+            english.format_duration(Duration::from_secs(125)),
+            "2 min 5 s"
+        );
+        assert_eq!(
+            english.format_duration(Duration::from_secs(5400)),
+            "1 h 30 min"
+        );
+        assert_eq!(
+            Catalog::german().format_duration(Duration::from_millis(2500)),
+            "2,5 s"
+        );
+    }
+}
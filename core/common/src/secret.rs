@@ -0,0 +1,132 @@
+//! Secret input that is wiped from memory when dropped.
+
+use std::fmt;
+
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// A password or other secret supplied by the user.
+///
+/// The bytes are zeroized when the value is dropped and never appear in
+/// `Debug` output. Conversions from owned buffers take the buffer over
+/// instead of copying it, so no unwiped copy is left behind.
+#[derive(Clone, Default)]
+pub struct Secret(Zeroizing<Vec<u8>>);
+
+impl Secret {
+    /// Copy `bytes` into a new secret.
+    ///
+    /// The caller still owns `bytes` and is responsible for wiping it.
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        Self(Zeroizing::new(bytes.to_vec()))
+    }
+
+    /// The secret bytes.
+    ///
+    /// # Security
+    /// Use the slice right away; do not copy it into unwiped storage.
+    pub fn expose(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the secret is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for Secret {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+}
+
+impl From<String> for Secret {
+    fn from(text: String) -> Self {
+        Self::from(text.into_bytes())
+    }
+}
+
+impl From<Zeroizing<Vec<u8>>> for Secret {
+    fn from(bytes: Zeroizing<Vec<u8>>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Zeroizing<String>> for Secret {
+    /// Takes the string's buffer, leaving `text` empty.
+    fn from(mut text: Zeroizing<String>) -> Self {
+        Self::from(std::mem::take(&mut *text))
+    }
+}
+
+impl Zeroize for Secret {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for Secret {}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_never_prints_secret() {
+        let secret = Secret::from("hunter2-correct-horse".to_string());
+        let debug = format!("{:?}", secret);
+        assert_eq!(debug, "Secret([REDACTED])");
+        assert!(!debug.contains("hunter2"));
+        assert!(!format!("{:#?}", Some(&secret)).contains("hunter2"));
+    }
+
+    #[test]
+    fn test_conversions_take_the_buffer() {
+        let text = Zeroizing::new("pass phrase".to_string());
+        let ptr = text.as_ptr();
+        let secret = Secret::from(text);
+        assert_eq!(secret.expose(), b"pass phrase");
+        assert_eq!(
+            secret.expose().as_ptr(),
+            ptr,
+            "the buffer was moved, not copied"
+        );
+
+        let bytes = b"bytes".to_vec();
+        let ptr = bytes.as_ptr();
+        let secret = Secret::from(bytes);
+        assert_eq!(secret.expose().as_ptr(), ptr);
+        assert_eq!(Secret::from_slice(b"bytes").expose(), secret.expose());
+        assert_eq!(secret.len(), 5);
+        assert!(Secret::default().is_empty());
+    }
+
+    #[test]
+    fn test_buffer_is_zeroized_on_drop() {
+        fn zeroized_on_drop<T: ZeroizeOnDrop>() {}
+        zeroized_on_drop::<Secret>();
+
+        // Drop runs the same wipe; after it the allocation is gone, so
+        // check the wipe while the buffer is still owned.
+        let mut secret = Secret::from(b"top secret".to_vec());
+        let ptr = secret.expose().as_ptr();
+        let len = secret.len();
+        secret.zeroize();
+        assert!(secret.is_empty());
+        // SAFETY: zeroizing a Vec clears it but keeps its allocation, and
+        // every byte of it was just overwritten with zeros.
+        let wiped = unsafe { std::slice::from_raw_parts(ptr, len) };
+        assert!(wiped.iter().all(|&b| b == 0));
+    }
+}
@@ -0,0 +1,407 @@
+//! Removing plaintext originals once they are safely in a vault.
+//!
+//! [`shred_file`] overwrites a file with random data, truncates it and
+//! unlinks it, but only after checking the copy read back from the vault
+//! against it, and only inside the source paths the user named. Symlinks are
+//! never followed and files with other hard links are left alone.
+//!
+//! Overwriting in place is not reliable everywhere. SSDs remap writes to
+//! fresh flash cells, and copy-on-write filesystems (btrfs, ZFS, APFS)
+//! write the random data to new blocks and leave the old ones intact. On
+//! filesystems recognised as copy-on-write the overwrite is skipped and the
+//! file is only deleted; on SSDs nothing can be detected, so callers should
+//! warn whenever shredding is requested.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use rand::RngExt;
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+
+/// Size of the buffer used to compare and overwrite file contents.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// How a source file was removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum ShredOutcome {
+    /// Overwritten with random data, truncated and unlinked.
+    Overwritten { bytes: u64 },
+    /// Only unlinked: the file lives on a copy-on-write filesystem, where
+    /// overwriting would not reach the original blocks.
+    Deleted { filesystem: String },
+}
+
+/// Check that `path` may be shredded: it lies within one of `sources`, no
+/// component below that source is a symlink, and it is a regular file
+/// without other hard links.
+///
+/// # Errors
+/// - `NotPermitted` when any of these does not hold
+/// - `Io` when the file or a directory on the way does not exist
+pub fn check_shreddable(path: &Path, sources: &[PathBuf]) -> Result<fs::Metadata> {
+    let absolute = lexical_absolute(path)?;
+    let mut source = None;
+    for candidate in sources {
+        let candidate = lexical_absolute(candidate)?;
+        if absolute.starts_with(&candidate) {
+            source = Some(candidate);
+            break;
+        }
+    }
+    let Some(source) = source else {
+        return Err(Error::NotPermitted(format!(
+            "{} is outside the source paths; not shredding it",
+            path.display()
+        )));
+    };
+
+    // The source itself was named by the user; everything below it must be
+    // a real directory or file.
+    let mut current = source.clone();
+    let below = absolute
+        .strip_prefix(&source)
+        .map_err(|_| Error::InvalidInput("Path is not below its source".to_string()))?;
+    for component in below.components() {
+        current.push(component);
+        if fs::symlink_metadata(&current)?.file_type().is_symlink() {
+            return Err(Error::NotPermitted(format!(
+                "{} is a symlink; not shredding through it",
+                current.display()
+            )));
+        }
+    }
+
+    let metadata = fs::symlink_metadata(&absolute)?;
+    if metadata.file_type().is_symlink() {
+        return Err(Error::NotPermitted(format!(
+            "{} is a symlink; not shredding it",
+            path.display()
+        )));
+    }
+    if !metadata.is_file() {
+        return Err(Error::NotPermitted(format!(
+            "{} is not a regular file; not shredding it",
+            path.display()
+        )));
+    }
+    if link_count(&metadata) > 1 {
+        return Err(Error::NotPermitted(format!(
+            "{} has other hard links; not shredding it",
+            path.display()
+        )));
+    }
+    Ok(metadata)
+}
+
+/// Shred the source file at `path` after an import.
+///
+/// `vault_copy` is the content read back from the vault after writing it;
+/// the file is only touched when it matches byte for byte. On a
+/// copy-on-write filesystem the file is deleted without overwriting.
+///
+/// # Errors
+/// - Everything [`check_shreddable`] refuses
+/// - `Integrity` when the file differs from `vault_copy`; it is left intact
+/// - I/O failures while overwriting or removing the file
+pub fn shred_file(path: &Path, sources: &[PathBuf], vault_copy: &[u8]) -> Result<ShredOutcome> {
+    let checked = check_shreddable(path, sources)?;
+    let mut file = open_no_follow(path)?;
+    // The path must still name the file that was checked.
+    if !same_file(&checked, &file.metadata()?) {
+        return Err(Error::NotPermitted(format!(
+            "{} changed while being checked; not shredding it",
+            path.display()
+        )));
+    }
+
+    if !matches_content(&mut file, vault_copy)? {
+        return Err(Error::Integrity(format!(
+            "{} differs from the copy in the vault; not shredding it",
+            path.display()
+        )));
+    }
+
+    if let Some(filesystem) = copy_on_write_filesystem(path) {
+        drop(file);
+        fs::remove_file(path)?;
+        return Ok(ShredOutcome::Deleted { filesystem });
+    }
+
+    let bytes = vault_copy.len() as u64;
+    overwrite(&mut file, bytes)?;
+    file.set_len(0)?;
+    file.sync_all()?;
+    drop(file);
+    fs::remove_file(path)?;
+    Ok(ShredOutcome::Overwritten { bytes })
+}
+
+/// Name of the filesystem holding `path` when it is copy-on-write, where
+/// overwriting a file in place does not reach its old blocks.
+///
+/// Best effort: detected with `statfs` on Linux and macOS, `None` elsewhere.
+pub fn copy_on_write_filesystem(path: &Path) -> Option<String> {
+    filesystem_type(path)
+        .filter(|name| ["btrfs", "zfs", "apfs", "bcachefs"].contains(&name.as_str()))
+}
+
+/// `path` made absolute without resolving symlinks, with `.` and `..`
+/// applied lexically.
+fn lexical_absolute(path: &Path) -> Result<PathBuf> {
+    let mut absolute = PathBuf::new();
+    for component in std::path::absolute(path)?.components() {
+        match component {
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            Component::CurDir => {}
+            other => absolute.push(other),
+        }
+    }
+    Ok(absolute)
+}
+
+/// Compare the file's content with `expected`, reading it in chunks.
+fn matches_content(file: &mut File, expected: &[u8]) -> Result<bool> {
+    if file.metadata()?.len() != expected.len() as u64 {
+        return Ok(false);
+    }
+    file.seek(SeekFrom::Start(0))?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut offset = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            return Ok(offset == expected.len());
+        }
+        if expected.get(offset..offset + n) != Some(&buf[..n]) {
+            return Ok(false);
+        }
+        offset += n;
+    }
+}
+
+/// Overwrite the first `len` bytes of the file with random data.
+fn overwrite(file: &mut File, len: u64) -> Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut rng = rand::rng();
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(CHUNK_SIZE as u64) as usize;
+        rng.fill(&mut buf[..n]);
+        file.write_all(&buf[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn open_no_follow(path: &Path) -> Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(path)
+        .map_err(|e| match e.raw_os_error() {
+            Some(libc::ELOOP) => {
+                Error::NotPermitted(format!("{} is a symlink; not shredding it", path.display()))
+            }
+            _ => e.into(),
+        })
+}
+
+#[cfg(not(unix))]
+fn open_no_follow(path: &Path) -> Result<File> {
+    // Checked with `symlink_metadata` just before; `same_file` catches a
+    // swap in between.
+    Ok(OpenOptions::new().read(true).write(true).open(path)?)
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    a.len() == b.len() && a.modified().ok() == b.modified().ok()
+}
+
+#[cfg(unix)]
+fn link_count(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink()
+}
+
+#[cfg(not(unix))]
+fn link_count(_metadata: &fs::Metadata) -> u64 {
+    1
+}
+
+/// Name of the filesystem holding `path`. On Linux only the
+/// copy-on-write filesystems are named.
+#[cfg(target_os = "linux")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    const BTRFS_SUPER_MAGIC: u64 = 0x9123_683e;
+    const ZFS_SUPER_MAGIC: u64 = 0x2fc1_2fc1;
+    const BCACHEFS_SUPER_MAGIC: u64 = 0xca45_1a4e;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `path` is NUL-terminated and `stat` is a valid out pointer.
+    let stat = unsafe {
+        let mut stat: libc::statfs = std::mem::zeroed();
+        if libc::statfs(path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        stat
+    };
+    // `f_type` is signed on some targets; the magic numbers are 32-bit.
+    let name = match (stat.f_type as u64) & 0xffff_ffff {
+        BTRFS_SUPER_MAGIC => "btrfs",
+        ZFS_SUPER_MAGIC => "zfs",
+        BCACHEFS_SUPER_MAGIC => "bcachefs",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn filesystem_type(path: &Path) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `path` is NUL-terminated, `stat` is a valid out pointer and
+    // `f_fstypename` is NUL-terminated by the kernel.
+    unsafe {
+        let mut stat: libc::statfs = std::mem::zeroed();
+        if libc::statfs(path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+        let name = std::ffi::CStr::from_ptr(stat.f_fstypename.as_ptr());
+        Some(name.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn filesystem_type(_path: &Path) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A temporary directory to shred files in.
+    struct Scratch(tempfile::TempDir);
+
+    impl Scratch {
+        fn new() -> Self {
+            Self(tempfile::tempdir().unwrap())
+        }
+
+        fn path(&self) -> PathBuf {
+            self.0.path().to_path_buf()
+        }
+
+        fn file(&self, name: &str, content: &[u8]) -> PathBuf {
+            let path = self.0.path().join(name);
+            fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    #[test]
+    fn test_shred_removes_a_verified_file() {
+        let scratch = Scratch::new();
+        let content = vec![0x5a; 3 * CHUNK_SIZE + 17];
+        let path = scratch.file("secret.txt", &content);
+
+        let outcome = shred_file(&path, std::slice::from_ref(&path), &content).unwrap();
+        assert!(!path.exists());
+        match outcome {
+            ShredOutcome::Overwritten { bytes } => assert_eq!(bytes, content.len() as u64),
+            ShredOutcome::Deleted { filesystem } => {
+                assert_eq!(copy_on_write_filesystem(&scratch.path()), Some(filesystem))
+            }
+        }
+    }
+
+    #[test]
+    fn test_shred_refuses_a_mismatched_vault_copy() {
+        let scratch = Scratch::new();
+        let path = scratch.file("secret.txt", b"original");
+        let sources = [scratch.path()];
+
+        for copy in [&b"0riginal"[..], b"origina", b"original!"] {
+            let result = shred_file(&path, &sources, copy);
+            assert!(matches!(result, Err(Error::Integrity(_))));
+        }
+        assert_eq!(fs::read(&path).unwrap(), b"original");
+    }
+
+    #[test]
+    fn test_shred_stays_within_the_source_paths() {
+        let scratch = Scratch::new();
+        let inside = scratch.file("inside.txt", b"a");
+        let outside = scratch.file("outside.txt", b"b");
+
+        let sources = [inside.clone()];
+        let result = shred_file(&outside, &sources, b"b");
+        assert!(matches!(result, Err(Error::NotPermitted(_))));
+        let sneaky = inside.join("..").join("outside.txt");
+        let result = shred_file(&sneaky, &sources, b"b");
+        assert!(matches!(result, Err(Error::NotPermitted(_))));
+        assert!(outside.exists());
+
+        // A directory source covers the files below it.
+        fs::create_dir(scratch.path().join("dir")).unwrap();
+        let nested = scratch.file("dir/nested.txt", b"c");
+        shred_file(&nested, &[scratch.path().join("dir")], b"c").unwrap();
+        assert!(!nested.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shred_never_follows_symlinks() {
+        let scratch = Scratch::new();
+        let target = scratch.file("target.txt", b"keep me");
+        let link = scratch.path().join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        // Neither the link itself nor a symlinked directory below a source.
+        let result = shred_file(&link, &[scratch.path()], b"keep me");
+        assert!(matches!(result, Err(Error::NotPermitted(_))));
+        fs::create_dir(scratch.path().join("real")).unwrap();
+        let inner = scratch.file("real/inner.txt", b"inner");
+        std::os::unix::fs::symlink(scratch.path().join("real"), scratch.path().join("alias"))
+            .unwrap();
+        let through = scratch.path().join("alias").join("inner.txt");
+        let result = shred_file(&through, &[scratch.path()], b"inner");
+        assert!(matches!(result, Err(Error::NotPermitted(_))));
+
+        assert_eq!(fs::read(&target).unwrap(), b"keep me");
+        assert!(link.exists() && inner.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shred_refuses_hard_linked_files() {
+        let scratch = Scratch::new();
+        let path = scratch.file("a.txt", b"shared");
+        fs::hard_link(&path, scratch.path().join("b.txt")).unwrap();
+
+        let result = shred_file(&path, std::slice::from_ref(&path), b"shared");
+        assert!(matches!(result, Err(Error::NotPermitted(_))));
+        assert_eq!(fs::read(&path).unwrap(), b"shared");
+    }
+}
@@ -0,0 +1,311 @@
+//! Tracing and metrics helpers shared by the core crates.
+//!
+//! Spans across the workspace follow the same rules:
+//! - Logical paths only reach span fields through [`path_field`], which
+//!   replaces them with an opaque tag when path redaction is enabled.
+//! - Key material and passwords are never recorded. Storage-side paths
+//!   contain only encrypted names and are redacted the same way.
+//! - Timing comes from span close events, so subscribers that want durations
+//!   enable close events (e.g. `FmtSpan::CLOSE`) instead of callers logging
+//!   elapsed times by hand.
+//!
+//! The functions in [`metrics`] are no-ops unless the `metrics` feature is
+//! enabled, so call sites never need their own `cfg` guards.
+
+use std::fmt::Display;
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+static REDACT_PATHS: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable path redaction in span fields for this process.
+pub fn set_redact_paths(redact: bool) {
+    REDACT_PATHS.store(redact, Ordering::Relaxed);
+}
+
+/// Whether logical paths are currently redacted in span fields.
+pub fn redact_paths() -> bool {
+    REDACT_PATHS.load(Ordering::Relaxed)
+}
+
+/// Render a logical path for use as a span or event field.
+///
+/// With redaction enabled the path is replaced by a tag that is stable for
+/// the lifetime of the process, so spans touching the same file can still be
+/// correlated, but that cannot be linked back to the name across runs.
+/// Without redaction control characters are escaped, so a name holding a
+/// newline cannot forge log lines.
+pub fn path_field(path: &impl Display) -> String {
+    format_path(path, redact_paths())
+}
+
+fn format_path(path: &impl Display, redact: bool) -> String {
+    let path = path.to_string();
+    if !redact || path == "/" {
+        return crate::escape_lossy(&path).into_owned();
+    }
+    static KEY: OnceLock<RandomState> = OnceLock::new();
+    let tag = KEY.get_or_init(RandomState::new).hash_one(&path);
+    format!("<redacted:{:016x}>", tag)
+}
+
+/// Operation counters and latency histograms.
+///
+/// Names follow the Prometheus conventions used by [`metrics::render`]:
+/// - `axiomvault_operations_total{op}`
+/// - `axiomvault_bytes_total{direction}` (`uploaded` / `downloaded`)
+/// - `axiomvault_kdf_seconds`
+/// - `axiomvault_cache_lookups_total{cache, result}` (`hit` / `miss`)
+/// - `axiomvault_provider_call_seconds{provider, method}`
+pub mod metrics {
+    use std::time::Duration;
+
+    /// Direction of a byte transfer between the vault and its storage.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Direction {
+        /// Written to the storage provider.
+        Uploaded,
+        /// Read from the storage provider.
+        Downloaded,
+    }
+
+    impl Direction {
+        #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+        fn as_str(self) -> &'static str {
+            match self {
+                Direction::Uploaded => "uploaded",
+                Direction::Downloaded => "downloaded",
+            }
+        }
+    }
+
+    /// Count one vault, sync or filesystem operation.
+    pub fn operation(op: &'static str) {
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!("axiomvault_operations_total", "op" => op).increment(1);
+        #[cfg(not(feature = "metrics"))]
+        let _ = op;
+    }
+
+    /// Count bytes moved to or from storage.
+    pub fn bytes(direction: Direction, count: u64) {
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!("axiomvault_bytes_total", "direction" => direction.as_str())
+            .increment(count);
+        #[cfg(not(feature = "metrics"))]
+        let _ = (direction, count);
+    }
+
+    /// Record how long one password-based key derivation took.
+    pub fn kdf_duration(elapsed: Duration) {
+        #[cfg(feature = "metrics")]
+        ::metrics::histogram!("axiomvault_kdf_seconds").record(elapsed.as_secs_f64());
+        #[cfg(not(feature = "metrics"))]
+        let _ = elapsed;
+    }
+
+    /// Record a hit or miss in a named cache.
+    pub fn cache_lookup(cache: &'static str, hit: bool) {
+        #[cfg(feature = "metrics")]
+        ::metrics::counter!(
+            "axiomvault_cache_lookups_total",
+            "cache" => cache,
+            "result" => if hit { "hit" } else { "miss" }
+        )
+        .increment(1);
+        #[cfg(not(feature = "metrics"))]
+        let _ = (cache, hit);
+    }
+
+    /// Record the latency of one storage provider call.
+    pub fn provider_call(provider: &str, method: &'static str, elapsed: Duration) {
+        #[cfg(feature = "metrics")]
+        ::metrics::histogram!(
+            "axiomvault_provider_call_seconds",
+            "provider" => provider.to_string(),
+            "method" => method
+        )
+        .record(elapsed.as_secs_f64());
+        #[cfg(not(feature = "metrics"))]
+        let _ = (provider, method, elapsed);
+    }
+
+    #[cfg(feature = "metrics")]
+    pub use recorder::{install, render};
+
+    #[cfg(feature = "metrics")]
+    mod recorder {
+        use std::collections::BTreeMap;
+        use std::fmt::Write as _;
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::{Arc, Mutex, OnceLock};
+
+        use ::metrics::{
+            Counter, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString,
+            Unit,
+        };
+
+        type Series = (String, Vec<(String, String)>);
+
+        #[derive(Default)]
+        struct Summary {
+            count: u64,
+            sum: f64,
+        }
+
+        #[derive(Default)]
+        struct SummaryCell(Mutex<Summary>);
+
+        impl HistogramFn for SummaryCell {
+            fn record(&self, value: f64) {
+                let mut summary = self.0.lock().unwrap_or_else(|e| e.into_inner());
+                summary.count += 1;
+                summary.sum += value;
+            }
+        }
+
+        #[derive(Default)]
+        struct Registry {
+            counters: Mutex<BTreeMap<Series, Arc<AtomicU64>>>,
+            gauges: Mutex<BTreeMap<Series, Arc<AtomicU64>>>,
+            histograms: Mutex<BTreeMap<Series, Arc<SummaryCell>>>,
+        }
+
+        fn series(key: &Key) -> Series {
+            let labels = key
+                .labels()
+                .map(|l| (l.key().to_string(), l.value().to_string()))
+                .collect();
+            (key.name().to_string(), labels)
+        }
+
+        fn entry<T: Default>(map: &Mutex<BTreeMap<Series, Arc<T>>>, key: &Key) -> Arc<T> {
+            let mut map = map.lock().unwrap_or_else(|e| e.into_inner());
+            map.entry(series(key)).or_default().clone()
+        }
+
+        #[derive(Clone)]
+        struct SnapshotRecorder(Arc<Registry>);
+
+        impl Recorder for SnapshotRecorder {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+                Counter::from_arc(entry(&self.0.counters, key))
+            }
+
+            fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::from_arc(entry(&self.0.gauges, key))
+            }
+
+            fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::from_arc(entry(&self.0.histograms, key))
+            }
+        }
+
+        static REGISTRY: OnceLock<Arc<Registry>> = OnceLock::new();
+        static INSTALLED: OnceLock<bool> = OnceLock::new();
+
+        /// Install the in-process recorder as the global `metrics` recorder.
+        ///
+        /// Returns `false` if a different recorder was installed first.
+        /// Calling this more than once is harmless.
+        pub fn install() -> bool {
+            *INSTALLED.get_or_init(|| {
+                let registry = Arc::new(Registry::default());
+                let installed =
+                    ::metrics::set_global_recorder(SnapshotRecorder(registry.clone())).is_ok();
+                if installed {
+                    let _ = REGISTRY.set(registry);
+                }
+                installed
+            })
+        }
+
+        fn labels(labels: &[(String, String)]) -> String {
+            if labels.is_empty() {
+                return String::new();
+            }
+            let pairs: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"")))
+                .collect();
+            format!("{{{}}}", pairs.join(","))
+        }
+
+        /// Render everything recorded so far in the Prometheus text format.
+        ///
+        /// Histograms are exported as `_count` / `_sum` pairs. Returns an
+        /// empty string if [`install`] was never called.
+        pub fn render() -> String {
+            let Some(registry) = REGISTRY.get() else {
+                return String::new();
+            };
+            let mut out = String::new();
+            let counters = registry.counters.lock().unwrap_or_else(|e| e.into_inner());
+            for ((name, l), value) in counters.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}{} {}",
+                    name,
+                    labels(l),
+                    value.load(Ordering::Relaxed)
+                );
+            }
+            let gauges = registry.gauges.lock().unwrap_or_else(|e| e.into_inner());
+            for ((name, l), value) in gauges.iter() {
+                let value = f64::from_bits(value.load(Ordering::Relaxed));
+                let _ = writeln!(out, "{}{} {}", name, labels(l), value);
+            }
+            let histograms = registry
+                .histograms
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            for ((name, l), summary) in histograms.iter() {
+                let summary = summary.0.lock().unwrap_or_else(|e| e.into_inner());
+                let _ = writeln!(out, "{}_count{} {}", name, labels(l), summary.count);
+                let _ = writeln!(out, "{}_sum{} {}", name, labels(l), summary.sum);
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_path_redaction() {
+        assert_eq!(format_path(&"/docs/tax.pdf", false), "/docs/tax.pdf");
+        assert_eq!(format_path(&"/a\nb", false), "/a\\nb");
+
+        let redacted = format_path(&"/docs/tax.pdf", true);
+        assert!(redacted.starts_with("<redacted:"));
+        assert!(!redacted.contains("tax"));
+        assert_eq!(redacted, format_path(&"/docs/tax.pdf", true));
+        assert_ne!(redacted, format_path(&"/docs/other.pdf", true));
+        assert_eq!(format_path(&"/", true), "/");
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_metrics_render() {
+        assert!(metrics::install());
+        metrics::operation("test_render");
+        metrics::cache_lookup("test_cache", true);
+        metrics::provider_call("memory", "download", std::time::Duration::from_millis(5));
+
+        let text = metrics::render();
+        assert!(text.contains("axiomvault_operations_total{op=\"test_render\"} 1"));
+        assert!(
+            text.contains("axiomvault_cache_lookups_total{cache=\"test_cache\",result=\"hit\"}")
+        );
+        assert!(text.contains(
+            "axiomvault_provider_call_seconds_count{provider=\"memory\",method=\"download\"} 1"
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 //! Common types used throughout AxiomVault.
 
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fmt;
 use zeroize::Zeroize;
 
@@ -70,26 +71,13 @@ impl VaultPath {
     /// # Preconditions
     /// - Components must not contain path separators
     /// - Components must not be empty strings
+    /// - Components must not contain NUL
     ///
     /// # Errors
     /// - Returns error if any component is invalid
     pub fn from_components(components: Vec<String>) -> crate::Result<Self> {
         for comp in &components {
-            if comp.is_empty() {
-                return Err(crate::Error::InvalidInput(
-                    "Path component cannot be empty".to_string(),
-                ));
-            }
-            if comp.contains('/') || comp.contains('\\') {
-                return Err(crate::Error::InvalidInput(
-                    "Path component cannot contain separators".to_string(),
-                ));
-            }
-            if comp == "." || comp == ".." {
-                return Err(crate::Error::InvalidInput(
-                    "Path component cannot be '.' or '..'".to_string(),
-                ));
-            }
+            validate_component(comp)?;
         }
         Ok(Self { components })
     }
@@ -127,31 +115,69 @@ impl VaultPath {
         }
     }
 
+    /// Every proper ancestor of this path, starting at the root.
+    ///
+    /// For `/a/b/c` yields `/`, `/a` and `/a/b`; the root has none.
+    pub fn ancestors(&self) -> impl Iterator<Item = VaultPath> + '_ {
+        (0..self.components.len()).map(|len| Self {
+            components: self.components[..len].to_vec(),
+        })
+    }
+
     /// Get the file/directory name (last component).
     pub fn name(&self) -> Option<&str> {
         self.components.last().map(|s| s.as_str())
     }
 
     /// Join this path with a child component.
+    ///
+    /// # Errors
+    /// - Returns error if `child` is empty, `.` or `..`, or contains a
+    ///   separator or NUL
     pub fn join(&self, child: &str) -> crate::Result<Self> {
-        if child.is_empty() {
-            return Err(crate::Error::InvalidInput(
-                "Child component cannot be empty".to_string(),
-            ));
-        }
-        if child.contains('/') || child.contains('\\') {
-            return Err(crate::Error::InvalidInput(
-                "Child component cannot contain separators".to_string(),
-            ));
-        }
-        if child == "." || child == ".." {
-            return Err(crate::Error::InvalidInput(
-                "Path component cannot be '.' or '..'".to_string(),
-            ));
-        }
+        validate_component(child)?;
+        Ok(self.join_unchecked(child))
+    }
+
+    /// Join this path with a child component the caller has already
+    /// validated or produced itself, such as an encrypted name.
+    ///
+    /// Use [`join`](Self::join) for anything that came from a user or a
+    /// remote listing.
+    pub fn join_unchecked(&self, child: &str) -> Self {
+        debug_assert!(
+            validate_component(child).is_ok(),
+            "invalid path component {:?}",
+            child
+        );
         let mut components = self.components.clone();
         components.push(child.to_string());
-        Ok(Self { components })
+        Self { components }
+    }
+
+    /// Append every component of a relative path.
+    pub fn join_relative(&self, relative: &RelativeVaultPath) -> Self {
+        let mut components = self.components.clone();
+        components.extend(relative.components.iter().cloned());
+        Self { components }
+    }
+
+    /// Whether `base` is this path or one of its ancestors.
+    ///
+    /// Compares whole components, so `/docs` is not a prefix of
+    /// `/docs-old`.
+    pub fn starts_with(&self, base: &VaultPath) -> bool {
+        self.components.starts_with(&base.components)
+    }
+
+    /// The part of this path below `base`, or `None` if `base` is not a
+    /// prefix. A path stripped of itself yields an empty relative path.
+    pub fn strip_prefix(&self, base: &VaultPath) -> Option<RelativeVaultPath> {
+        self.components
+            .strip_prefix(base.components.as_slice())
+            .map(|rest| RelativeVaultPath {
+                components: rest.to_vec(),
+            })
     }
 
     /// Get the path components.
@@ -160,18 +186,133 @@ impl VaultPath {
     }
 
     /// Convert to a string representation.
+    #[deprecated(note = "use `to_string()` for the exact path or `display_lossy()` for output")]
     pub fn to_string_path(&self) -> String {
-        if self.is_root() {
-            "/".to_string()
+        self.to_string()
+    }
+
+    /// The path with control characters escaped, safe to print to a
+    /// terminal or write to a log line.
+    ///
+    /// Not reversible; use `to_string()` wherever the path is parsed again.
+    pub fn display_lossy(&self) -> String {
+        escape_lossy(&self.to_string()).into_owned()
+    }
+}
+
+/// A path relative to some [`VaultPath`], such as a template entry or the
+/// part of a path below a sync root. May be empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RelativeVaultPath {
+    components: Vec<String>,
+}
+
+impl RelativeVaultPath {
+    /// Create a relative path from string components.
+    ///
+    /// # Errors
+    /// - Returns error if any component is invalid, as for
+    ///   [`VaultPath::from_components`]
+    pub fn from_components(components: Vec<String>) -> crate::Result<Self> {
+        for comp in &components {
+            validate_component(comp)?;
+        }
+        Ok(Self { components })
+    }
+
+    /// Parse a `/`-separated relative path. A leading `/` is rejected so
+    /// absolute paths are not silently re-rooted.
+    pub fn parse(path: &str) -> crate::Result<Self> {
+        if path.starts_with('/') {
+            return Err(crate::Error::InvalidInput(format!(
+                "Relative path cannot start with '/': {}",
+                escape_lossy(path)
+            )));
+        }
+        let path = path.trim_end_matches('/');
+        if path.is_empty() {
+            return Ok(Self::default());
+        }
+        Self::from_components(path.split('/').map(String::from).collect())
+    }
+
+    /// Whether the path has no components.
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+
+    /// Get the path components.
+    pub fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    /// The path with control characters escaped, as for
+    /// [`VaultPath::display_lossy`].
+    pub fn display_lossy(&self) -> String {
+        escape_lossy(&self.to_string()).into_owned()
+    }
+}
+
+impl fmt::Display for RelativeVaultPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.components.join("/"))
+    }
+}
+
+/// Escape control characters in a name or path for terminal and log
+/// output. Borrows when there is nothing to escape.
+pub fn escape_lossy(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(char::is_control) {
+        return Cow::Borrowed(s);
+    }
+    let mut escaped = String::with_capacity(s.len() + 4);
+    for c in s.chars() {
+        if c.is_control() {
+            escaped.extend(c.escape_default());
         } else {
-            format!("/{}", self.components.join("/"))
+            escaped.push(c);
         }
     }
+    Cow::Owned(escaped)
+}
+
+/// Check that a single path component is a legal name.
+///
+/// Leading and trailing spaces are kept verbatim; they are valid in POSIX
+/// filenames and trimming them would make distinct names collide. Other
+/// control characters such as newlines are legal too, for the same reason;
+/// print names through [`escape_lossy`].
+fn validate_component(comp: &str) -> crate::Result<()> {
+    if comp.is_empty() {
+        return Err(crate::Error::InvalidInput(
+            "Path component cannot be empty".to_string(),
+        ));
+    }
+    if comp.contains('/') || comp.contains('\\') {
+        return Err(crate::Error::InvalidInput(
+            "Path component cannot contain separators".to_string(),
+        ));
+    }
+    if comp == "." || comp == ".." {
+        return Err(crate::Error::InvalidInput(
+            "Path component cannot be '.' or '..'".to_string(),
+        ));
+    }
+    if comp.contains('\0') {
+        return Err(crate::Error::InvalidInput(
+            "Path component cannot contain NUL".to_string(),
+        ));
+    }
+    Ok(())
 }
 
 impl fmt::Display for VaultPath {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.to_string_path())
+        if self.is_root() {
+            write!(f, "/")
+        } else {
+            write!(f, "/{}", self.components.join("/"))
+        }
     }
 }
 
@@ -269,7 +410,7 @@ mod tests {
             components in prop::collection::vec(valid_component_strategy(), 0..8),
         ) {
             let path = VaultPath::from_components(components).unwrap();
-            let string_repr = path.to_string_path();
+            let string_repr = path.to_string();
             let parsed = VaultPath::parse(&string_repr).unwrap();
             prop_assert_eq!(path, parsed);
         }
@@ -316,27 +457,54 @@ mod tests {
     fn test_vault_path_root() {
         let path = VaultPath::root();
         assert!(path.is_root());
-        assert_eq!(path.to_string_path(), "/");
+        assert_eq!(path.to_string(), "/");
     }
 
     #[test]
     fn test_vault_path_parse() {
         let path = VaultPath::parse("/foo/bar/baz").unwrap();
         assert_eq!(path.components(), &["foo", "bar", "baz"]);
-        assert_eq!(path.to_string_path(), "/foo/bar/baz");
+        assert_eq!(path.to_string(), "/foo/bar/baz");
     }
 
     #[test]
     fn test_vault_path_join() {
         let path = VaultPath::root().join("foo").unwrap().join("bar").unwrap();
-        assert_eq!(path.to_string_path(), "/foo/bar");
+        assert_eq!(path.to_string(), "/foo/bar");
     }
 
     #[test]
     fn test_vault_path_parent() {
         let path = VaultPath::parse("/foo/bar").unwrap();
         let parent = path.parent().unwrap();
-        assert_eq!(parent.to_string_path(), "/foo");
+        assert_eq!(parent.to_string(), "/foo");
+    }
+
+    #[test]
+    fn test_vault_path_ancestors_of_root() {
+        assert_eq!(VaultPath::root().ancestors().count(), 0);
+    }
+
+    #[test]
+    fn test_vault_path_ancestors_single_level() {
+        let path = VaultPath::parse("/foo").unwrap();
+        let ancestors: Vec<VaultPath> = path.ancestors().collect();
+        assert_eq!(ancestors, vec![VaultPath::root()]);
+    }
+
+    #[test]
+    fn test_vault_path_ancestors_multi_level() {
+        let path = VaultPath::parse("/a/b/c").unwrap();
+        let ancestors: Vec<String> = path.ancestors().map(|p| p.to_string()).collect();
+        assert_eq!(ancestors, vec!["/", "/a", "/a/b"]);
+        assert!(path.ancestors().all(|a| path.starts_with(&a) && a != path));
+    }
+
+    #[test]
+    fn test_vault_path_ancestors_ignore_trailing_separator() {
+        let path = VaultPath::parse("/a/b/").unwrap();
+        let ancestors: Vec<String> = path.ancestors().map(|p| p.to_string()).collect();
+        assert_eq!(ancestors, vec!["/", "/a"]);
     }
 
     #[test]
@@ -359,4 +527,95 @@ mod tests {
             Err(crate::Error::InvalidInput(_))
         ));
     }
+
+    #[test]
+    fn test_vault_path_join_rejects_slash() {
+        let path = VaultPath::root();
+        assert!(matches!(
+            path.join("foo/bar"),
+            Err(crate::Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_vault_path_rejects_nul() {
+        let path = VaultPath::root();
+        assert!(matches!(
+            path.join("foo\0bar"),
+            Err(crate::Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            VaultPath::parse("/dir/foo\0bar"),
+            Err(crate::Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            RelativeVaultPath::parse("dir/foo\0bar"),
+            Err(crate::Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_vault_path_accepts_other_control_chars() {
+        let path = VaultPath::root().join("a\nb").unwrap();
+        assert_eq!(path.name(), Some("a\nb"));
+        assert_eq!(path.to_string(), "/a\nb");
+        assert_eq!(VaultPath::parse("/a\nb").unwrap(), path);
+        assert!(VaultPath::from_components(vec!["a\x7f".to_string()]).is_ok());
+    }
+
+    #[test]
+    fn test_display_lossy_escapes_control_chars() {
+        let path = VaultPath::parse("/dir/a\nb\t\x1b[31m").unwrap();
+        assert_eq!(path.display_lossy(), "/dir/a\\nb\\t\\u{1b}[31m");
+        assert!(!path.display_lossy().chars().any(char::is_control));
+
+        let plain = "caf\u{e9} notes.txt";
+        assert!(matches!(escape_lossy(plain), Cow::Borrowed(_)));
+        assert_eq!(escape_lossy(plain), plain);
+    }
+
+    #[test]
+    fn test_relative_vault_path() {
+        let rel = RelativeVaultPath::parse("docs/readme.md").unwrap();
+        assert_eq!(rel.components(), &["docs", "readme.md"]);
+        assert_eq!(rel.to_string(), "docs/readme.md");
+        assert!(RelativeVaultPath::parse("").unwrap().is_empty());
+        assert!(RelativeVaultPath::parse("/docs").is_err());
+        assert!(RelativeVaultPath::parse("docs/../etc").is_err());
+        assert!(RelativeVaultPath::parse("docs//x").is_err());
+
+        let base = VaultPath::parse("/team").unwrap();
+        let joined = base.join_relative(&rel);
+        assert_eq!(joined.to_string(), "/team/docs/readme.md");
+        assert_eq!(base.join_relative(&RelativeVaultPath::default()), base);
+    }
+
+    #[test]
+    fn test_starts_with_and_strip_prefix() {
+        let base = VaultPath::parse("/docs").unwrap();
+        let child = VaultPath::parse("/docs/a/b.txt").unwrap();
+        let sibling = VaultPath::parse("/docs-old/b.txt").unwrap();
+
+        assert!(child.starts_with(&base));
+        assert!(base.starts_with(&base));
+        assert!(child.starts_with(&VaultPath::root()));
+        assert!(!sibling.starts_with(&base));
+        assert!(!base.starts_with(&child));
+
+        let rel = child.strip_prefix(&base).unwrap();
+        assert_eq!(rel.to_string(), "a/b.txt");
+        assert_eq!(base.join_relative(&rel), child);
+        assert!(base.strip_prefix(&base).unwrap().is_empty());
+        assert_eq!(sibling.strip_prefix(&base), None);
+    }
+
+    #[test]
+    fn test_vault_path_preserves_surrounding_spaces() {
+        let path = VaultPath::root().join(" notes.txt ").unwrap();
+        assert_eq!(path.name(), Some(" notes.txt "));
+
+        let parsed = VaultPath::parse("/ dir / notes.txt ").unwrap();
+        assert_eq!(parsed.components(), &[" dir ", " notes.txt "]);
+        assert_ne!(parsed, VaultPath::parse("/dir/notes.txt").unwrap());
+    }
 }
@@ -0,0 +1,63 @@
+//! Compare cipher suites on representative blob sizes.
+//!
+//! Run with `cargo bench -p axiomvault-crypto --features aes-gcm-siv` to
+//! include AES-256-GCM-SIV; without the feature only XChaCha20-Poly1305 is
+//! measured.
+
+use std::hint::black_box;
+
+use axiomvault_crypto::{decrypt_with_suite, encrypt_with_suite, CipherSuite};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Small metadata, a typical document and a large photo.
+const SIZES: [usize; 3] = [1024, 64 * 1024, 4 * 1024 * 1024];
+
+const AAD: &[u8] = b"axiomvault-bench";
+
+fn suites() -> impl Iterator<Item = CipherSuite> {
+    CipherSuite::ALL
+        .into_iter()
+        .filter(|suite| suite.is_available())
+}
+
+fn bench_encrypt(c: &mut Criterion) {
+    let key = [7u8; 32];
+    let mut group = c.benchmark_group("encrypt");
+    for size in SIZES {
+        let plaintext = vec![0x5au8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        for suite in suites() {
+            group.bench_with_input(
+                BenchmarkId::new(suite.to_string(), size),
+                &plaintext,
+                |b, plaintext| {
+                    b.iter(|| encrypt_with_suite(suite, &key, black_box(plaintext), AAD).unwrap())
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bench_decrypt(c: &mut Criterion) {
+    let key = [7u8; 32];
+    let mut group = c.benchmark_group("decrypt");
+    for size in SIZES {
+        let plaintext = vec![0x5au8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        for suite in suites() {
+            let ciphertext = encrypt_with_suite(suite, &key, &plaintext, AAD).unwrap();
+            group.bench_with_input(
+                BenchmarkId::new(suite.to_string(), size),
+                &ciphertext,
+                |b, ciphertext| {
+                    b.iter(|| decrypt_with_suite(&key, black_box(ciphertext), AAD).unwrap())
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encrypt, bench_decrypt);
+criterion_main!(benches);
@@ -2,12 +2,19 @@
 //!
 //! XChaCha20-Poly1305 provides both confidentiality and authenticity,
 //! with a 24-byte nonce that is safe for random generation.
+//!
+//! [`encrypt_with_suite`] output names its [`CipherSuite`] in a leading
+//! byte, so data sealed under different suites can be mixed and read back
+//! by [`decrypt_with_suite`]. AES-256-GCM-SIV needs the `aes-gcm-siv`
+//! feature.
 
 use chacha20poly1305::{
-    aead::{Aead, Generate, KeyInit},
+    aead::{Aead, Generate, KeyInit, Payload},
     XChaCha20Poly1305, XNonce,
 };
+use serde::{Deserialize, Serialize};
 
+use crate::ciphertext::Ciphertext;
 use crate::keys::KEY_LENGTH;
 use axiomvault_common::{Error, Result};
 
@@ -17,6 +24,104 @@ pub const NONCE_SIZE: usize = 24;
 /// Authentication tag size (16 bytes).
 pub const TAG_SIZE: usize = 16;
 
+/// Nonce size for AES-256-GCM-SIV (12 bytes).
+const AES_GCM_SIV_NONCE_SIZE: usize = 12;
+
+/// Length of the suite ID opening [`encrypt_with_suite`] output.
+pub const SUITE_ID_LEN: usize = 1;
+
+/// AEAD algorithm identified by a one-byte ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum CipherSuite {
+    /// XChaCha20-Poly1305 with a random 24-byte nonce.
+    #[default]
+    #[serde(rename = "xchacha20-poly1305")]
+    XChaCha20Poly1305,
+    /// AES-256-GCM-SIV with a random 12-byte nonce. Nonce-misuse
+    /// resistant, and fast where the CPU has AES instructions.
+    #[serde(rename = "aes-256-gcm-siv")]
+    Aes256GcmSiv,
+}
+
+impl CipherSuite {
+    /// Every suite this version knows, whether or not it is built in.
+    pub const ALL: [Self; 2] = [Self::XChaCha20Poly1305, Self::Aes256GcmSiv];
+
+    /// ID written in front of the ciphertext.
+    pub fn id(self) -> u8 {
+        match self {
+            Self::XChaCha20Poly1305 => 1,
+            Self::Aes256GcmSiv => 2,
+        }
+    }
+
+    /// Look up a suite by ID.
+    ///
+    /// # Errors
+    /// - `Unsupported` for an ID no suite has
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            1 => Ok(Self::XChaCha20Poly1305),
+            2 => Ok(Self::Aes256GcmSiv),
+            other => Err(Error::Unsupported(format!("cipher suite {}", other))),
+        }
+    }
+
+    /// Whether this build can encrypt and decrypt with the suite.
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::XChaCha20Poly1305 => true,
+            Self::Aes256GcmSiv => cfg!(feature = "aes-gcm-siv"),
+        }
+    }
+
+    /// Nonce length the suite stores after its ID.
+    pub fn nonce_size(self) -> usize {
+        match self {
+            Self::XChaCha20Poly1305 => NONCE_SIZE,
+            Self::Aes256GcmSiv => AES_GCM_SIV_NONCE_SIZE,
+        }
+    }
+
+    /// Bytes [`encrypt_with_suite`] adds to the plaintext.
+    pub fn overhead(self) -> usize {
+        SUITE_ID_LEN + self.nonce_size() + TAG_SIZE
+    }
+
+    /// Whether this is the default suite.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    #[cfg(not(feature = "aes-gcm-siv"))]
+    fn unavailable(self) -> Error {
+        Error::Unsupported(format!(
+            "{} (this build lacks the aes-gcm-siv feature)",
+            self
+        ))
+    }
+}
+
+impl std::fmt::Display for CipherSuite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::XChaCha20Poly1305 => "xchacha20-poly1305",
+            Self::Aes256GcmSiv => "aes-256-gcm-siv",
+        })
+    }
+}
+
+impl std::str::FromStr for CipherSuite {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|suite| suite.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown cipher suite: {}", s)))
+    }
+}
+
 /// Encrypt plaintext using XChaCha20-Poly1305.
 ///
 /// # Preconditions
@@ -36,6 +141,24 @@ pub const TAG_SIZE: usize = 16;
 /// - Uses random nonce generation
 /// - Authenticates the ciphertext with Poly1305
 pub fn encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+    encrypt_with_aad(key, plaintext, &[])
+}
+
+/// Like [`encrypt`], but marks the output as [`Ciphertext`].
+pub fn seal(key: &[u8], plaintext: &[u8]) -> Result<Ciphertext> {
+    encrypt(key, plaintext).map(Ciphertext::new)
+}
+
+/// Like [`encrypt_with_aad`], but marks the output as [`Ciphertext`].
+pub fn seal_with_aad(key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Ciphertext> {
+    encrypt_with_aad(key, plaintext, aad).map(Ciphertext::new)
+}
+
+/// Encrypt plaintext, authenticating `aad` alongside it.
+///
+/// The associated data is not stored in the output; the same `aad` must be
+/// supplied to [`decrypt_with_aad`]. Output format matches [`encrypt`].
+pub fn encrypt_with_aad(key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
     if key.len() != KEY_LENGTH {
         return Err(Error::Crypto(format!(
             "Invalid key length: expected {}, got {}",
@@ -49,7 +172,13 @@ pub fn encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
     let nonce = XNonce::generate();
 
     let ciphertext = cipher
-        .encrypt(&nonce, plaintext)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
         .map_err(|e| Error::Crypto(format!("Encryption failed: {}", e)))?;
 
     // Prepend nonce to ciphertext
@@ -80,6 +209,14 @@ pub fn encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
 /// - Authenticates before decrypting
 /// - Returns error on any authentication failure
 pub fn decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+    decrypt_with_aad(key, ciphertext, &[])
+}
+
+/// Decrypt ciphertext produced by [`encrypt_with_aad`].
+///
+/// # Errors
+/// - Same as [`decrypt`]; a different `aad` fails authentication
+pub fn decrypt_with_aad(key: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
     if key.len() != KEY_LENGTH {
         return Err(Error::Crypto(format!(
             "Invalid key length: expected {}, got {}",
@@ -102,10 +239,156 @@ pub fn decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
         .map_err(|e| Error::Crypto(format!("Invalid key length: {:?}", e)))?;
 
     cipher
-        .decrypt(&nonce, encrypted)
+        .decrypt(
+            &nonce,
+            Payload {
+                msg: encrypted,
+                aad,
+            },
+        )
         .map_err(|e| Error::Crypto(format!("Decryption failed: {}", e)))
 }
 
+/// Encrypt plaintext under `suite`, authenticating `aad` alongside it.
+///
+/// Output is suite ID || nonce || ciphertext || tag. The suite ID is
+/// authenticated with `aad`, so relabelling the ciphertext as another
+/// suite fails decryption.
+///
+/// # Errors
+/// - `Unsupported` if `suite` is not built in
+/// - Same as [`encrypt`] otherwise
+pub fn encrypt_with_suite(
+    suite: CipherSuite,
+    key: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    let id = suite.id();
+    let aad = suite_aad(id, aad);
+    let sealed = match suite {
+        CipherSuite::XChaCha20Poly1305 => encrypt_with_aad(key, plaintext, &aad)?,
+        #[cfg(feature = "aes-gcm-siv")]
+        CipherSuite::Aes256GcmSiv => aes_gcm_siv::encrypt(key, plaintext, &aad)?,
+        #[cfg(not(feature = "aes-gcm-siv"))]
+        CipherSuite::Aes256GcmSiv => return Err(suite.unavailable()),
+    };
+    let mut result = Vec::with_capacity(SUITE_ID_LEN + sealed.len());
+    result.push(id);
+    result.extend_from_slice(&sealed);
+    Ok(result)
+}
+
+/// Like [`encrypt_with_suite`], but marks the output as [`Ciphertext`].
+pub fn seal_with_suite(
+    suite: CipherSuite,
+    key: &[u8],
+    plaintext: &[u8],
+    aad: &[u8],
+) -> Result<Ciphertext> {
+    encrypt_with_suite(suite, key, plaintext, aad).map(Ciphertext::new)
+}
+
+/// Decrypt [`encrypt_with_suite`] output under whichever suite it names.
+///
+/// # Errors
+/// - `Unsupported` for an unknown suite or one that is not built in
+/// - Same as [`decrypt_with_aad`] otherwise
+pub fn decrypt_with_suite(key: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let (&id, sealed) = ciphertext
+        .split_first()
+        .ok_or_else(|| Error::Crypto("Ciphertext too short".to_string()))?;
+    let suite = CipherSuite::from_id(id)?;
+    let aad = suite_aad(id, aad);
+    match suite {
+        CipherSuite::XChaCha20Poly1305 => decrypt_with_aad(key, sealed, &aad),
+        #[cfg(feature = "aes-gcm-siv")]
+        CipherSuite::Aes256GcmSiv => aes_gcm_siv::decrypt(key, sealed, &aad),
+        #[cfg(not(feature = "aes-gcm-siv"))]
+        CipherSuite::Aes256GcmSiv => Err(suite.unavailable()),
+    }
+}
+
+/// Suite ID the output of [`encrypt_with_suite`] opens with.
+///
+/// # Errors
+/// - `Crypto` for empty input
+/// - `Unsupported` for an unknown ID
+pub fn suite_of(ciphertext: &[u8]) -> Result<CipherSuite> {
+    let id = ciphertext
+        .first()
+        .ok_or_else(|| Error::Crypto("Ciphertext too short".to_string()))?;
+    CipherSuite::from_id(*id)
+}
+
+fn suite_aad(id: u8, aad: &[u8]) -> Vec<u8> {
+    let mut suite_aad = Vec::with_capacity(SUITE_ID_LEN + aad.len());
+    suite_aad.push(id);
+    suite_aad.extend_from_slice(aad);
+    suite_aad
+}
+
+/// Backend for [`CipherSuite::Aes256GcmSiv`]; output is nonce || ciphertext || tag.
+#[cfg(feature = "aes-gcm-siv")]
+mod aes_gcm_siv {
+    use aes_gcm_siv::aead::{Aead, KeyInit, Payload};
+    use aes_gcm_siv::{Aes256GcmSiv, Nonce};
+
+    use super::{AES_GCM_SIV_NONCE_SIZE as NONCE_SIZE, TAG_SIZE};
+    use crate::keys::KEY_LENGTH;
+    use axiomvault_common::{Error, Result};
+
+    pub(super) fn encrypt(key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        use rand::RngExt;
+
+        let cipher = cipher(key)?;
+        let mut nonce = [0u8; NONCE_SIZE];
+        rand::rng().fill(&mut nonce[..]);
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|e| Error::Crypto(format!("Encryption failed: {}", e)))?;
+
+        let mut result = Vec::with_capacity(NONCE_SIZE + ciphertext.len());
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&ciphertext);
+        Ok(result)
+    }
+
+    pub(super) fn decrypt(key: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < NONCE_SIZE + TAG_SIZE {
+            return Err(Error::Crypto("Ciphertext too short".to_string()));
+        }
+        let (nonce, encrypted) = ciphertext.split_at(NONCE_SIZE);
+        cipher(key)?
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: encrypted,
+                    aad,
+                },
+            )
+            .map_err(|e| Error::Crypto(format!("Decryption failed: {}", e)))
+    }
+
+    fn cipher(key: &[u8]) -> Result<Aes256GcmSiv> {
+        if key.len() != KEY_LENGTH {
+            return Err(Error::Crypto(format!(
+                "Invalid key length: expected {}, got {}",
+                KEY_LENGTH,
+                key.len()
+            )));
+        }
+        Aes256GcmSiv::new_from_slice(key)
+            .map_err(|e| Error::Crypto(format!("Invalid key length: {:?}", e)))
+    }
+}
+
 /// Encrypt plaintext with a specific nonce.
 ///
 /// # Warning
@@ -171,6 +454,86 @@ pub fn decrypt_with_nonce(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_suite_roundtrip() {
+        let key = [42u8; KEY_LENGTH];
+        for suite in CipherSuite::ALL.into_iter().filter(|s| s.is_available()) {
+            let ciphertext = encrypt_with_suite(suite, &key, b"payload", b"aad").unwrap();
+            assert_eq!(ciphertext[0], suite.id());
+            assert_eq!(ciphertext.len(), b"payload".len() + suite.overhead());
+            assert_eq!(suite_of(&ciphertext).unwrap(), suite);
+            assert_eq!(
+                decrypt_with_suite(&key, &ciphertext, b"aad").unwrap(),
+                b"payload"
+            );
+            assert!(decrypt_with_suite(&key, &ciphertext, b"other").is_err());
+        }
+    }
+
+    #[test]
+    fn test_suite_id_is_authenticated() {
+        let key = [42u8; KEY_LENGTH];
+        let ciphertext =
+            encrypt_with_suite(CipherSuite::XChaCha20Poly1305, &key, b"payload", b"aad").unwrap();
+
+        // The body only opens with the suite ID bound in.
+        assert!(decrypt_with_aad(&key, &ciphertext[1..], b"aad").is_err());
+        assert_eq!(
+            decrypt_with_aad(&key, &ciphertext[1..], b"\x01aad").unwrap(),
+            b"payload"
+        );
+
+        let mut relabelled = ciphertext.clone();
+        relabelled[0] = CipherSuite::Aes256GcmSiv.id();
+        assert!(decrypt_with_suite(&key, &relabelled, b"aad").is_err());
+
+        let mut unknown = ciphertext;
+        unknown[0] = 0x7f;
+        assert!(matches!(
+            decrypt_with_suite(&key, &unknown, b"aad"),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(decrypt_with_suite(&key, &[], b"aad").is_err());
+    }
+
+    #[cfg(feature = "aes-gcm-siv")]
+    #[test]
+    fn test_aes_gcm_siv_id_is_authenticated() {
+        let key = [42u8; KEY_LENGTH];
+        let mut ciphertext =
+            encrypt_with_suite(CipherSuite::Aes256GcmSiv, &key, b"payload", b"aad").unwrap();
+        assert_eq!(
+            aes_gcm_siv::decrypt(&key, &ciphertext[1..], b"\x02aad").unwrap(),
+            b"payload"
+        );
+        assert!(aes_gcm_siv::decrypt(&key, &ciphertext[1..], b"aad").is_err());
+
+        ciphertext[0] = CipherSuite::XChaCha20Poly1305.id();
+        assert!(decrypt_with_suite(&key, &ciphertext, b"aad").is_err());
+    }
+
+    #[cfg(not(feature = "aes-gcm-siv"))]
+    #[test]
+    fn test_unavailable_suite_is_refused() {
+        let key = [42u8; KEY_LENGTH];
+        assert!(!CipherSuite::Aes256GcmSiv.is_available());
+        assert!(matches!(
+            encrypt_with_suite(CipherSuite::Aes256GcmSiv, &key, b"payload", b""),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_suite_names() {
+        for suite in CipherSuite::ALL {
+            assert_eq!(CipherSuite::from_id(suite.id()).unwrap(), suite);
+            assert_eq!(suite.to_string().parse::<CipherSuite>().unwrap(), suite);
+            let json = serde_json::to_string(&suite).unwrap();
+            assert_eq!(json, format!("\"{}\"", suite));
+        }
+        assert!("rot13".parse::<CipherSuite>().is_err());
+    }
+
     #[test]
     fn test_encrypt_decrypt_roundtrip() {
         let key = [42u8; KEY_LENGTH];
@@ -182,6 +545,19 @@ mod tests {
         assert_eq!(decrypted, plaintext);
     }
 
+    #[test]
+    fn test_seal_matches_encrypt_format() {
+        let key = [42u8; KEY_LENGTH];
+        let sealed = seal_with_aad(&key, b"secret", b"aad").unwrap();
+
+        assert_eq!(sealed.len(), NONCE_SIZE + b"secret".len() + TAG_SIZE);
+        assert_eq!(
+            decrypt_with_aad(&key, sealed.as_bytes(), b"aad").unwrap(),
+            b"secret"
+        );
+        assert_eq!(format!("{:?}", sealed), "Ciphertext(46 bytes)");
+    }
+
     #[test]
     fn test_ciphertext_size() {
         let key = [42u8; KEY_LENGTH];
@@ -263,11 +639,25 @@ mod tests {
         let plaintext = b"";
 
         let ciphertext = encrypt(&key, plaintext).unwrap();
+        assert_eq!(ciphertext.len(), NONCE_SIZE + TAG_SIZE);
         let decrypted = decrypt(&key, &ciphertext).unwrap();
 
         assert_eq!(decrypted, plaintext);
     }
 
+    #[test]
+    fn test_empty_plaintext_is_still_authenticated() {
+        let key = [42u8; KEY_LENGTH];
+        let ciphertext = encrypt_with_aad(&key, b"", b"context").unwrap();
+
+        assert!(decrypt_with_aad(&key, &ciphertext, b"context")
+            .unwrap()
+            .is_empty());
+        assert!(decrypt_with_aad(&key, &ciphertext, b"other").is_err());
+        // Dropping the tag leaves only the nonce, which must not decrypt.
+        assert!(decrypt(&key, &ciphertext[..NONCE_SIZE]).is_err());
+    }
+
     #[test]
     fn test_large_plaintext() {
         let key = [42u8; KEY_LENGTH];
@@ -278,4 +668,17 @@ mod tests {
 
         assert_eq!(decrypted, plaintext);
     }
+
+    #[test]
+    fn test_aad_roundtrip_and_binding() {
+        let key = [7u8; KEY_LENGTH];
+        let ciphertext = encrypt_with_aad(&key, b"payload", b"context-a").unwrap();
+
+        assert_eq!(
+            decrypt_with_aad(&key, &ciphertext, b"context-a").unwrap(),
+            b"payload"
+        );
+        assert!(decrypt_with_aad(&key, &ciphertext, b"context-b").is_err());
+        assert!(decrypt(&key, &ciphertext).is_err());
+    }
 }
@@ -0,0 +1,492 @@
+//! Streaming encryption over tokio's [`AsyncRead`] and [`AsyncWrite`].
+//!
+//! The async counterparts of [`EncryptingStream`](crate::stream::EncryptingStream)
+//! and [`DecryptingStream`](crate::stream::DecryptingStream). Both produce
+//! and accept the same format as the sync versions; header and chunk
+//! framing come from the same codec in [`crate::stream`].
+//!
+//! Plaintext is held one chunk at a time in buffers that are wiped on drop,
+//! so cancelling a future at any await point leaves no plaintext behind and
+//! the writer with a truncated prefix of the output.
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use zeroize::Zeroizing;
+
+use crate::keys::KEY_LENGTH;
+use crate::stream::{
+    chunk_count, encode_header, encrypted_chunk_len, open_chunk, parse_header, seal_chunk,
+    trailing_data, DEFAULT_CHUNK_SIZE, HEADER_SIZE,
+};
+use axiomvault_common::{Error, Result};
+
+/// Default bound on the plaintext [`AsyncEncryptingStream::encrypt_stream`]
+/// accepts: 64 MiB.
+pub const DEFAULT_MAX_UNSIZED: u64 = 64 * 1024 * 1024;
+
+/// Encrypting stream that reads from an [`AsyncRead`].
+pub struct AsyncEncryptingStream<'a> {
+    key: &'a [u8],
+    chunk_size: usize,
+    max_unsized: u64,
+}
+
+impl<'a> AsyncEncryptingStream<'a> {
+    /// Create a new encrypting stream.
+    ///
+    /// # Errors
+    /// - Returns error if key length is invalid
+    pub fn new(key: &'a [u8]) -> Result<Self> {
+        if key.len() != KEY_LENGTH {
+            return Err(Error::Crypto("Invalid key length".to_string()));
+        }
+        Ok(Self {
+            key,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_unsized: DEFAULT_MAX_UNSIZED,
+        })
+    }
+
+    /// Set custom chunk size.
+    pub fn with_chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Set the most plaintext [`encrypt_stream`](Self::encrypt_stream)
+    /// accepts. Defaults to [`DEFAULT_MAX_UNSIZED`].
+    pub fn with_max_unsized(mut self, bytes: u64) -> Self {
+        self.max_unsized = bytes;
+        self
+    }
+
+    /// Encrypt everything `reader` yields into `writer`.
+    ///
+    /// The header records the chunk count, so like
+    /// [`EncryptingStream::encrypt_stream`](crate::stream::EncryptingStream::encrypt_stream)
+    /// this keeps every sealed chunk until `reader` ends. The format is
+    /// shared with the sync streams and existing blobs, so rather than
+    /// changing the header this refuses inputs over
+    /// [`with_max_unsized`](Self::with_max_unsized) bytes; larger inputs
+    /// must go through [`encrypt_sized`](Self::encrypt_sized), which holds
+    /// one chunk at a time.
+    ///
+    /// Returns the number of plaintext bytes read.
+    ///
+    /// # Errors
+    /// - `reader` yields more than the bound; nothing is written
+    /// - I/O errors from reader/writer
+    /// - Encryption errors
+    pub async fn encrypt_stream<R, W>(&self, mut reader: R, mut writer: W) -> Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut buffer = Zeroizing::new(vec![0u8; self.chunk_size]);
+        let mut encrypted_chunks: Vec<Vec<u8>> = Vec::new();
+        let mut total_bytes = 0u64;
+
+        loop {
+            let bytes_read = read_chunk(&mut reader, &mut buffer).await?;
+            if bytes_read == 0 {
+                break;
+            }
+            let chunk_index = encrypted_chunks.len() as u64;
+            total_bytes += bytes_read as u64;
+            if total_bytes > self.max_unsized {
+                return Err(Error::InvalidInput(format!(
+                    "unsized stream exceeds {} bytes; use encrypt_sized",
+                    self.max_unsized
+                )));
+            }
+            encrypted_chunks.push(seal_chunk(
+                self.key,
+                chunk_index,
+                &buffer[..bytes_read],
+                &[],
+            )?);
+        }
+
+        writer
+            .write_all(&encode_header(
+                self.chunk_size,
+                encrypted_chunks.len() as u64,
+            ))
+            .await?;
+        for chunk in encrypted_chunks {
+            writer.write_all(&chunk).await?;
+        }
+        writer.flush().await?;
+
+        Ok(total_bytes)
+    }
+
+    /// Encrypt exactly `len` bytes from `reader` into `writer`.
+    ///
+    /// Knowing the length up front lets the header go out first, so each
+    /// chunk is written as soon as it is sealed and at most one chunk is
+    /// held at a time. A slow writer therefore slows the reads.
+    ///
+    /// Returns `len`.
+    ///
+    /// # Errors
+    /// - `reader` ends before `len` bytes or has more than `len`; what was
+    ///   written so far does not decrypt
+    /// - I/O errors from reader/writer
+    /// - Encryption errors
+    pub async fn encrypt_sized<R, W>(&self, mut reader: R, len: u64, mut writer: W) -> Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let total_chunks = chunk_count(len, self.chunk_size);
+        writer
+            .write_all(&encode_header(self.chunk_size, total_chunks))
+            .await?;
+
+        let mut buffer = Zeroizing::new(vec![0u8; self.chunk_size]);
+        let mut remaining = len;
+        for chunk_index in 0..total_chunks {
+            let want = remaining.min(self.chunk_size as u64) as usize;
+            let bytes_read = read_chunk(&mut reader, &mut buffer[..want]).await?;
+            if bytes_read < want {
+                return Err(Error::InvalidInput(format!(
+                    "Input ended after {} of {} bytes",
+                    len - remaining + bytes_read as u64,
+                    len
+                )));
+            }
+            let sealed = seal_chunk(self.key, chunk_index, &buffer[..want], &[])?;
+            writer.write_all(&sealed).await?;
+            remaining -= want as u64;
+        }
+
+        let mut byte = [0u8; 1];
+        if reader.read(&mut byte).await? != 0 {
+            return Err(Error::InvalidInput(format!(
+                "Input is longer than {} bytes",
+                len
+            )));
+        }
+        writer.flush().await?;
+
+        Ok(len)
+    }
+}
+
+/// Decrypting stream that writes to an [`AsyncWrite`].
+pub struct AsyncDecryptingStream<'a> {
+    key: &'a [u8],
+}
+
+impl<'a> AsyncDecryptingStream<'a> {
+    /// Create a new decrypting stream.
+    ///
+    /// # Errors
+    /// - Returns error if key length is invalid
+    pub fn new(key: &'a [u8]) -> Result<Self> {
+        if key.len() != KEY_LENGTH {
+            return Err(Error::Crypto("Invalid key length".to_string()));
+        }
+        Ok(Self { key })
+    }
+
+    /// Decrypt data from `reader` and write the plaintext to `writer`.
+    ///
+    /// Each chunk is written as soon as it is authenticated, so at most one
+    /// encrypted and one decrypted chunk are held at a time.
+    ///
+    /// Returns the number of plaintext bytes written.
+    ///
+    /// # Errors
+    /// - I/O errors
+    /// - Invalid format
+    /// - Authentication failure (tampered data)
+    /// - Data after the last chunk recorded in the header
+    pub async fn decrypt_stream<R, W>(&self, mut reader: R, mut writer: W) -> Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut header = [0u8; HEADER_SIZE];
+        reader.read_exact(&mut header).await?;
+        let (chunk_size, total_chunks) = parse_header(&header)?;
+
+        let mut encrypted_buffer = vec![0u8; encrypted_chunk_len(chunk_size)];
+        let mut total_bytes = 0u64;
+
+        for i in 0..total_chunks {
+            let bytes_read = read_chunk(&mut reader, &mut encrypted_buffer).await?;
+            let plaintext = Zeroizing::new(open_chunk(
+                self.key,
+                &encrypted_buffer[..bytes_read],
+                i,
+                &[],
+            )?);
+            writer.write_all(&plaintext).await?;
+            total_bytes += plaintext.len() as u64;
+        }
+
+        let mut byte = [0u8; 1];
+        trailing_data(reader.read(&mut byte).await?)?;
+        writer.flush().await?;
+
+        Ok(total_bytes)
+    }
+}
+
+/// Fill `buffer` from `reader`, stopping early only at EOF.
+///
+/// The async counterpart of the sync `read_chunk`: short reads are retried
+/// so every chunk but the last is full.
+async fn read_chunk<R: AsyncRead + Unpin>(reader: &mut R, buffer: &mut [u8]) -> Result<usize> {
+    let mut total_read = 0;
+    while total_read < buffer.len() {
+        match reader.read(&mut buffer[total_read..]).await? {
+            0 => break,
+            n => total_read += n,
+        }
+    }
+    Ok(total_read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::{decrypt_bytes, EncryptingStream};
+    use std::time::Duration;
+
+    const KEY: [u8; KEY_LENGTH] = [42u8; KEY_LENGTH];
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// Split a stream into its header and the opened plaintext of each chunk.
+    ///
+    /// Nonces are random, so two encryptions never match byte for byte;
+    /// everything else — header, chunk boundaries, chunk lengths and the
+    /// authenticated contents — must.
+    fn frames(data: &[u8]) -> (Vec<u8>, Vec<(usize, Vec<u8>)>) {
+        let header: [u8; HEADER_SIZE] = data[..HEADER_SIZE].try_into().unwrap();
+        let (chunk_size, total_chunks) = parse_header(&header).unwrap();
+        let full = encrypted_chunk_len(chunk_size);
+        let mut chunks = Vec::new();
+        let mut rest = &data[HEADER_SIZE..];
+        for i in 0..total_chunks {
+            let take = rest.len().min(full);
+            chunks.push((take, open_chunk(&KEY, &rest[..take], i, &[]).unwrap()));
+            rest = &rest[take..];
+        }
+        assert!(rest.is_empty());
+        (header.to_vec(), chunks)
+    }
+
+    #[tokio::test]
+    async fn test_async_output_matches_sync_framing() {
+        for (len, chunk_size) in [
+            (0, 16),
+            (1, 16),
+            (16, 16),
+            (17, 16),
+            (1000, 64),
+            (5000, 4096),
+        ] {
+            let data = sample(len);
+
+            let mut sync_out = Vec::new();
+            EncryptingStream::new(&KEY)
+                .unwrap()
+                .with_chunk_size(chunk_size)
+                .encrypt_stream(&data[..], &mut sync_out)
+                .unwrap();
+
+            let encryptor = AsyncEncryptingStream::new(&KEY)
+                .unwrap()
+                .with_chunk_size(chunk_size);
+            let mut unsized_out = Vec::new();
+            let n = encryptor
+                .encrypt_stream(&data[..], &mut unsized_out)
+                .await
+                .unwrap();
+            assert_eq!(n, len as u64);
+            let mut sized_out = Vec::new();
+            encryptor
+                .encrypt_sized(&data[..], len as u64, &mut sized_out)
+                .await
+                .unwrap();
+
+            assert_eq!(frames(&unsized_out), frames(&sync_out));
+            assert_eq!(frames(&sized_out), frames(&sync_out));
+            assert_eq!(decrypt_bytes(&KEY, &sized_out).unwrap(), data);
+
+            let mut plaintext = Vec::new();
+            let written = AsyncDecryptingStream::new(&KEY)
+                .unwrap()
+                .decrypt_stream(&sync_out[..], &mut plaintext)
+                .await
+                .unwrap();
+            assert_eq!(written, len as u64);
+            assert_eq!(plaintext, data);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_short_reads_still_fill_chunks() {
+        let data = sample(300);
+        // A duplex pipe smaller than a chunk hands out short reads.
+        let (mut tx, rx) = tokio::io::duplex(7);
+        let feed = {
+            let data = data.clone();
+            tokio::spawn(async move { tx.write_all(&data).await })
+        };
+
+        let mut out = Vec::new();
+        AsyncEncryptingStream::new(&KEY)
+            .unwrap()
+            .with_chunk_size(64)
+            .encrypt_sized(rx, data.len() as u64, &mut out)
+            .await
+            .unwrap();
+        feed.await.unwrap().unwrap();
+
+        assert_eq!(decrypt_bytes(&KEY, &out).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_sized_rejects_wrong_length() {
+        let data = sample(100);
+        let encryptor = AsyncEncryptingStream::new(&KEY).unwrap();
+
+        let result = encryptor.encrypt_sized(&data[..], 101, Vec::new()).await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        let result = encryptor.encrypt_sized(&data[..], 99, Vec::new()).await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_encryption_leaves_truncated_prefix() {
+        let data = sample(10_000);
+        let chunk_size = 1000;
+        // Nobody reads the pipe, so the encryptor stalls once it is full.
+        let (tx, mut rx) = tokio::io::duplex(4096);
+
+        let encryptor = AsyncEncryptingStream::new(&KEY)
+            .unwrap()
+            .with_chunk_size(chunk_size);
+        let result = tokio::time::timeout(
+            Duration::from_millis(50),
+            encryptor.encrypt_sized(&data[..], data.len() as u64, tx),
+        )
+        .await;
+        assert!(result.is_err(), "encryption should stall on backpressure");
+
+        let mut written = Vec::new();
+        rx.read_to_end(&mut written).await.unwrap();
+        assert!(written.len() <= 4096);
+        assert_eq!(written[..HEADER_SIZE], encode_header(chunk_size, 10));
+
+        // The complete chunks in the prefix decrypt to the start of the
+        // input; the stream as a whole is rejected.
+        let mut plaintext = Vec::new();
+        let result = AsyncDecryptingStream::new(&KEY)
+            .unwrap()
+            .decrypt_stream(&written[..], &mut plaintext)
+            .await;
+        assert!(result.is_err());
+        let complete = (written.len() - HEADER_SIZE) / encrypted_chunk_len(chunk_size);
+        assert_eq!(plaintext, data[..complete * chunk_size]);
+    }
+
+    #[tokio::test]
+    async fn test_unsized_stream_is_bounded() {
+        let data = sample(1000);
+        let encryptor = AsyncEncryptingStream::new(&KEY)
+            .unwrap()
+            .with_chunk_size(64)
+            .with_max_unsized(1000);
+
+        let mut encrypted = Vec::new();
+        let n = encryptor
+            .encrypt_stream(&data[..], &mut encrypted)
+            .await
+            .unwrap();
+        assert_eq!(n, 1000);
+        assert_eq!(decrypt_bytes(&KEY, &encrypted).unwrap(), data);
+
+        let mut refused = Vec::new();
+        let result = encryptor
+            .encrypt_stream(&sample(1001)[..], &mut refused)
+            .await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert!(refused.is_empty());
+
+        // The sized path has no bound.
+        let mut sized = Vec::new();
+        encryptor
+            .encrypt_sized(&sample(1001)[..], 1001, &mut sized)
+            .await
+            .unwrap();
+        assert_eq!(decrypt_bytes(&KEY, &sized).unwrap(), sample(1001));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_decryption_writes_whole_chunks_only() {
+        let data = sample(10_000);
+        let mut encrypted = Vec::new();
+        EncryptingStream::new(&KEY)
+            .unwrap()
+            .with_chunk_size(1000)
+            .encrypt_stream(&data[..], &mut encrypted)
+            .unwrap();
+
+        // Feed only part of the ciphertext and never close the pipe.
+        let (mut tx, rx) = tokio::io::duplex(64 * 1024);
+        tx.write_all(&encrypted[..HEADER_SIZE + 2500])
+            .await
+            .unwrap();
+
+        let mut plaintext = Vec::new();
+        let result = tokio::time::timeout(
+            Duration::from_millis(50),
+            AsyncDecryptingStream::new(&KEY)
+                .unwrap()
+                .decrypt_stream(rx, &mut plaintext),
+        )
+        .await;
+        assert!(result.is_err(), "decryption should wait for more input");
+        assert_eq!(plaintext, data[..2 * 1000]);
+    }
+
+    #[tokio::test]
+    async fn test_async_decrypt_rejects_tampering_and_trailing_data() {
+        let data = sample(500);
+        let mut encrypted = Vec::new();
+        AsyncEncryptingStream::new(&KEY)
+            .unwrap()
+            .encrypt_sized(&data[..], data.len() as u64, &mut encrypted)
+            .await
+            .unwrap();
+        let decryptor = AsyncDecryptingStream::new(&KEY).unwrap();
+
+        let mut tampered = encrypted.clone();
+        tampered[HEADER_SIZE + 30] ^= 0xFF;
+        assert!(decryptor
+            .decrypt_stream(&tampered[..], Vec::new())
+            .await
+            .is_err());
+
+        let mut extended = encrypted.clone();
+        extended.push(0);
+        assert!(decryptor
+            .decrypt_stream(&extended[..], Vec::new())
+            .await
+            .is_err());
+
+        let wrong_key = [7u8; KEY_LENGTH];
+        assert!(AsyncDecryptingStream::new(&wrong_key)
+            .unwrap()
+            .decrypt_stream(&encrypted[..], Vec::new())
+            .await
+            .is_err());
+    }
+}
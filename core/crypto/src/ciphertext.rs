@@ -0,0 +1,57 @@
+//! Type-level marker for encrypted bytes.
+//!
+//! A [`Ciphertext`] can only be produced by this crate's encryption
+//! functions, so an API that takes one cannot be handed plaintext by
+//! mistake. Layers that persist data outside the encrypted storage, such as
+//! the sync staging area, accept `Ciphertext` instead of raw bytes.
+
+use std::fmt;
+
+/// Bytes produced by authenticated encryption.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Ciphertext(Vec<u8>);
+
+impl Ciphertext {
+    /// Wrap bytes this crate just encrypted.
+    pub(crate) fn new(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    /// The encrypted bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Number of encrypted bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there are no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Unwrap the encrypted bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Prepend public framing, such as a format header, to the encrypted
+    /// bytes.
+    ///
+    /// The prefix is not encrypted; bind it in as associated data when it
+    /// must not be altered.
+    pub fn with_prefix(self, prefix: &[u8]) -> Self {
+        let mut bytes = Vec::with_capacity(prefix.len() + self.0.len());
+        bytes.extend_from_slice(prefix);
+        bytes.extend_from_slice(&self.0);
+        Self(bytes)
+    }
+}
+
+impl fmt::Debug for Ciphertext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Ciphertext({} bytes)", self.0.len())
+    }
+}
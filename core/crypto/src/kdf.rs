@@ -5,14 +5,22 @@
 
 use argon2::{Algorithm, Argon2, Params, Version};
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
 use subtle::ConstantTimeEq;
 use zeroize::Zeroizing;
 
-use crate::keys::{MasterKey, Salt, KEY_LENGTH};
-use axiomvault_common::{Error, Result};
+use crate::keyfile::hash_keyfile;
+use crate::keys::{MasterKey, Salt, KEY_LENGTH, SALT_LENGTH};
+use axiomvault_common::{telemetry, Error, Result};
+
+/// Domain separation tag for combining the password key with a keyfile.
+const KEYFILE_MIX_CONTEXT: &[u8] = b"axiomvault-keyfile-mix-v1";
+
+/// Domain separation tag for password verification tags.
+const KEY_VERIFICATION_CONTEXT: &[u8] = b"axiomvault-key-verification-v1";
 
 /// Parameters for Argon2id key derivation.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KdfParams {
     /// Memory cost in KiB (e.g., 65536 = 64 MiB).
     pub memory_cost: u32,
@@ -94,14 +102,100 @@ pub fn derive_key(password: &[u8], salt: &Salt, params: &KdfParams) -> Result<Ma
 
     let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params);
 
+    let started = Instant::now();
     let mut key_bytes = Zeroizing::new([0u8; KEY_LENGTH]);
     argon2
         .hash_password_into(password, salt.as_bytes(), &mut key_bytes[..])
         .map_err(|e| Error::Crypto(format!("Key derivation failed: {}", e)))?;
+    telemetry::metrics::kdf_duration(started.elapsed());
+
+    Ok(MasterKey::from_bytes(*key_bytes))
+}
+
+/// Time one key derivation with `params` on this machine.
+///
+/// Derives a throwaway key from a fixed password and a fresh salt, so the
+/// result tells a user how long unlocking will take with these parameters.
+///
+/// # Errors
+/// - Returns error if Argon2id parameters are invalid
+pub fn measure_derivation(params: &KdfParams) -> Result<std::time::Duration> {
+    let started = Instant::now();
+    derive_key(
+        b"axiomvault-kdf-calibration",
+        &Salt::generate(SALT_LENGTH),
+        params,
+    )?;
+    Ok(started.elapsed())
+}
+
+/// Derive a key from a password, optionally combined with a keyfile.
+///
+/// Without a keyfile this is identical to [`derive_key`]. With a keyfile,
+/// the Argon2id output and the keyfile hash are mixed with Blake2b, so both
+/// factors are needed to reproduce the key.
+///
+/// # Errors
+/// - Returns error if password or keyfile is empty
+/// - Returns error if Argon2id parameters are invalid
+pub fn derive_key_with_keyfile(
+    password: &[u8],
+    keyfile: Option<&[u8]>,
+    salt: &Salt,
+    params: &KdfParams,
+) -> Result<MasterKey> {
+    use blake2::digest::consts::U32;
+    use blake2::{Blake2b, Digest};
+
+    let Some(keyfile) = keyfile else {
+        return derive_key(password, salt, params);
+    };
 
+    let keyfile_hash = hash_keyfile(keyfile)?;
+    let password_key = derive_key(password, salt, params)?;
+
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(KEYFILE_MIX_CONTEXT);
+    hasher.update(password_key.as_bytes());
+    hasher.update(&keyfile_hash[..]);
+    let result = hasher.finalize();
+
+    let mut key_bytes = Zeroizing::new([0u8; KEY_LENGTH]);
+    key_bytes.copy_from_slice(&result);
     Ok(MasterKey::from_bytes(*key_bytes))
 }
 
+/// Compute a password verification tag for a derived key.
+///
+/// The tag is a Blake2b hash over a fixed label, the key, the salt, the KDF
+/// parameters and a caller-supplied `context` (e.g. the vault id). Because
+/// every input is bound in, a tag copied from another vault or paired with
+/// different parameters will not match.
+pub fn verification_tag(
+    key: &MasterKey,
+    salt: &Salt,
+    params: &KdfParams,
+    context: &[u8],
+) -> [u8; KEY_LENGTH] {
+    use blake2::digest::consts::U32;
+    use blake2::{Blake2b, Digest};
+
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(KEY_VERIFICATION_CONTEXT);
+    hasher.update(key.as_bytes());
+    hasher.update(salt.as_bytes());
+    hasher.update(params.memory_cost.to_le_bytes());
+    hasher.update(params.time_cost.to_le_bytes());
+    hasher.update(params.parallelism.to_le_bytes());
+    hasher.update((context.len() as u64).to_le_bytes());
+    hasher.update(context);
+    let result = hasher.finalize();
+
+    let mut tag = [0u8; KEY_LENGTH];
+    tag.copy_from_slice(&result);
+    tag
+}
+
 /// Verify that a password produces the expected key.
 ///
 /// This performs constant-time comparison to prevent timing attacks.
@@ -157,14 +251,58 @@ mod tests {
         assert_ne!(key1.as_bytes(), key2.as_bytes());
     }
 
+    #[test]
+    fn test_derive_key_salt_lengths() {
+        let params = KdfParams::moderate();
+
+        let short = derive_key(b"pw", &Salt::generate(crate::MIN_SALT_LENGTH), &params).unwrap();
+        let long = derive_key(b"pw", &Salt::generate(64), &params).unwrap();
+        assert_ne!(short.as_bytes(), long.as_bytes());
+
+        let too_short = Salt::generate(crate::MIN_SALT_LENGTH - 1);
+        assert!(derive_key(b"pw", &too_short, &params).is_err());
+    }
+
     #[test]
     fn test_derive_key_empty_password_fails() {
-        let salt = Salt::generate();
+        let salt = Salt::generate(SALT_LENGTH);
         let params = KdfParams::moderate();
 
         assert!(derive_key(b"", &salt, &params).is_err());
     }
 
+    #[test]
+    fn test_measure_derivation() {
+        let params = KdfParams {
+            memory_cost: 1024,
+            time_cost: 1,
+            parallelism: 1,
+        };
+        assert!(measure_derivation(&params).is_ok());
+
+        let invalid = KdfParams {
+            memory_cost: 1,
+            ..params
+        };
+        assert!(measure_derivation(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_derive_key_with_keyfile() {
+        let password = b"test-password-123";
+        let salt = Salt::from_bytes([7u8; 32]);
+        let params = KdfParams::moderate();
+
+        let plain = derive_key(password, &salt, &params).unwrap();
+        let without = derive_key_with_keyfile(password, None, &salt, &params).unwrap();
+        let with_a = derive_key_with_keyfile(password, Some(b"keyfile-a"), &salt, &params).unwrap();
+        let with_b = derive_key_with_keyfile(password, Some(b"keyfile-b"), &salt, &params).unwrap();
+
+        assert_eq!(plain.as_bytes(), without.as_bytes());
+        assert_ne!(plain.as_bytes(), with_a.as_bytes());
+        assert_ne!(with_a.as_bytes(), with_b.as_bytes());
+    }
+
     #[test]
     fn test_verify_password() {
         let password = b"secure-password";
@@ -175,4 +313,32 @@ mod tests {
         assert!(verify_password(password, &salt, &params, &key).unwrap());
         assert!(!verify_password(b"wrong-password", &salt, &params, &key).unwrap());
     }
+
+    #[test]
+    fn test_verification_tag_binds_inputs() {
+        let key = MasterKey::from_bytes([5u8; 32]);
+        let salt = Salt::from_bytes([6u8; 32]);
+        let params = KdfParams::moderate();
+
+        let tag = verification_tag(&key, &salt, &params, b"vault-a");
+        assert_eq!(tag, verification_tag(&key, &salt, &params, b"vault-a"));
+        assert_ne!(tag, verification_tag(&key, &salt, &params, b"vault-b"));
+        assert_ne!(
+            tag,
+            verification_tag(&key, &Salt::from_bytes([7u8; 32]), &params, b"vault-a")
+        );
+        assert_ne!(
+            tag,
+            verification_tag(&key, &salt, &KdfParams::interactive(), b"vault-a")
+        );
+        assert_ne!(
+            tag,
+            verification_tag(
+                &MasterKey::from_bytes([8u8; 32]),
+                &salt,
+                &params,
+                b"vault-a"
+            )
+        );
+    }
 }
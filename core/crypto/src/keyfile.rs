@@ -0,0 +1,93 @@
+//! Keyfile support.
+//!
+//! A keyfile is an arbitrary file that must be presented together with the
+//! password to unlock a vault. Its content is hashed to 32 bytes and mixed
+//! into the password-derived key, so the vault config never stores anything
+//! that reveals the keyfile itself.
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use zeroize::Zeroizing;
+
+use crate::keys::{Salt, KEY_LENGTH};
+use axiomvault_common::{Error, Result};
+
+/// Size of generated keyfiles in bytes.
+pub const KEYFILE_LENGTH: usize = 64;
+
+/// Domain separation tag for hashing keyfile content.
+const KEYFILE_HASH_CONTEXT: &[u8] = b"axiomvault-keyfile-hash-v1";
+
+/// Domain separation tag for the keyfile identifier stored in the config.
+const KEYFILE_ID_CONTEXT: &[u8] = b"axiomvault-keyfile-id-v1";
+
+/// Generate random content for a new keyfile.
+pub fn generate_keyfile() -> Zeroizing<Vec<u8>> {
+    use rand::RngExt;
+    let mut content = Zeroizing::new(vec![0u8; KEYFILE_LENGTH]);
+    rand::rng().fill(&mut content[..]);
+    content
+}
+
+/// Hash keyfile content down to a fixed-size secret.
+///
+/// # Errors
+/// - Returns error if the keyfile is empty
+pub fn hash_keyfile(content: &[u8]) -> Result<Zeroizing<[u8; KEY_LENGTH]>> {
+    if content.is_empty() {
+        return Err(Error::InvalidInput("Keyfile cannot be empty".to_string()));
+    }
+
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(KEYFILE_HASH_CONTEXT);
+    hasher.update(content);
+    let result = hasher.finalize();
+
+    let mut hash = Zeroizing::new([0u8; KEY_LENGTH]);
+    hash.copy_from_slice(&result);
+    Ok(hash)
+}
+
+/// Compute the salted identifier of a keyfile.
+///
+/// The identifier lets a vault tell a wrong keyfile apart from a wrong
+/// password without running the KDF. It is derived from the keyfile hash,
+/// never the raw content, and is bound to the vault salt.
+pub fn keyfile_id(keyfile_hash: &[u8; KEY_LENGTH], salt: &Salt) -> [u8; KEY_LENGTH] {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(KEYFILE_ID_CONTEXT);
+    hasher.update(salt.as_bytes());
+    hasher.update(keyfile_hash);
+    let result = hasher.finalize();
+
+    let mut id = [0u8; KEY_LENGTH];
+    id.copy_from_slice(&result);
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_keyfile_is_random() {
+        let a = generate_keyfile();
+        let b = generate_keyfile();
+        assert_eq!(a.len(), KEYFILE_LENGTH);
+        assert_ne!(*a, *b);
+    }
+
+    #[test]
+    fn test_hash_keyfile_rejects_empty() {
+        assert!(hash_keyfile(b"").is_err());
+    }
+
+    #[test]
+    fn test_keyfile_id_depends_on_salt() {
+        let hash = hash_keyfile(b"keyfile").unwrap();
+        let id1 = keyfile_id(&hash, &Salt::from_bytes([1u8; 32]));
+        let id2 = keyfile_id(&hash, &Salt::from_bytes([2u8; 32]));
+        assert_ne!(id1, id2);
+        assert_ne!(&id1, &*hash);
+    }
+}
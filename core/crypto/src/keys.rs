@@ -3,13 +3,180 @@
 //! All key types automatically zeroize their memory on drop to prevent
 //! sensitive data from persisting in memory.
 
+use hkdf::Hkdf;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use std::fmt;
 use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
+use axiomvault_common::{Error, Result};
+
 /// Length of encryption keys in bytes (256-bit).
 pub const KEY_LENGTH: usize = 32;
 
+/// Length of [`MasterKey::fingerprint`] in hex characters.
+pub const FINGERPRINT_LENGTH: usize = 16;
+
+/// Version of the [`KeyContext::encode`] layout.
+pub const KEY_CONTEXT_VERSION: u8 = 1;
+
+/// [`MasterKey::derive_subkey`] context of file content keys, the label of
+/// [`KeyContext::FileContent`].
+pub const FILE_KEY_CONTEXT: &str = "axiomvault:file";
+
+/// [`MasterKey::derive_subkey`] context of directory keys, the label of
+/// [`KeyContext::Directory`].
+pub const DIRECTORY_KEY_CONTEXT: &str = "axiomvault:dir";
+
+/// [`MasterKey::derive_subkey`] context of the name encryption key.
+///
+/// Vaults have always encrypted names with the directory key for the
+/// identifier `names`, which this context still yields.
+pub const NAMES_KEY_CONTEXT: &str = "axiomvault:dir:names";
+
+/// What a key derived from the [`MasterKey`] is for.
+///
+/// Every derivation names its purpose here, so two features can never
+/// share a key by picking the same ad-hoc label. Adding a feature that
+/// needs its own key means adding a variant; the matches below have no
+/// catch-all, so the compiler asks for its label and derivation.
+///
+/// Keys derive with HKDF-SHA256 over [`encode`](Self::encode). Names,
+/// directories, file contents and the tree were encrypted before this
+/// registry existed, under a Blake2b derivation that existing vaults still
+/// need; the compat paths for those ask for it by name through
+/// [`MasterKey::derive_legacy_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyContext<'a> {
+    /// File and directory names.
+    Names,
+    /// Directory key for a directory identifier.
+    Directory { id: &'a [u8] },
+    /// Content of one file, identified by its encrypted name.
+    FileContent { name_or_id: &'a [u8] },
+    /// The encrypted directory tree.
+    Tree,
+    /// The write-ahead journal of file mutations.
+    Journal,
+    /// The encrypted full-text search index.
+    SearchIndex,
+    /// The sync staging registry and staged blobs.
+    SyncStaging,
+    /// The MAC over the vault config.
+    ConfigMac,
+    /// Audit log entries.
+    Audit,
+    /// Keyed content hashes for deduplication.
+    DedupHash,
+    /// Keys handed to a viewer limited to the subtree at `path`.
+    ViewerScope { path: &'a str },
+}
+
+impl KeyContext<'_> {
+    /// Stable label naming the purpose, distinct for every variant.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Names => "axiomvault:names",
+            Self::Directory { .. } => "axiomvault:dir",
+            Self::FileContent { .. } => "axiomvault:file",
+            Self::Tree => "axiomvault:tree",
+            Self::Journal => "axiomvault:journal",
+            Self::SearchIndex => "axiomvault:search-index",
+            Self::SyncStaging => "axiomvault:sync-staging",
+            Self::ConfigMac => "axiomvault:config-mac",
+            Self::Audit => "axiomvault:audit",
+            Self::DedupHash => "axiomvault:dedup-hash",
+            Self::ViewerScope { .. } => "axiomvault:viewer-scope",
+        }
+    }
+
+    /// The context with `label`, taking `parameter` as its per-key
+    /// parameter.
+    ///
+    /// `None` if no variant has `label`, if a single-key context gets a
+    /// non-empty parameter, or if a viewer scope path is not UTF-8.
+    pub fn from_label<'a>(label: &str, parameter: &'a [u8]) -> Option<KeyContext<'a>> {
+        let single = |context: KeyContext<'a>| parameter.is_empty().then_some(context);
+        match label {
+            "axiomvault:names" => single(KeyContext::Names),
+            "axiomvault:dir" => Some(KeyContext::Directory { id: parameter }),
+            "axiomvault:file" => Some(KeyContext::FileContent {
+                name_or_id: parameter,
+            }),
+            "axiomvault:tree" => single(KeyContext::Tree),
+            "axiomvault:journal" => single(KeyContext::Journal),
+            "axiomvault:search-index" => single(KeyContext::SearchIndex),
+            "axiomvault:sync-staging" => single(KeyContext::SyncStaging),
+            "axiomvault:config-mac" => single(KeyContext::ConfigMac),
+            "axiomvault:audit" => single(KeyContext::Audit),
+            "axiomvault:dedup-hash" => single(KeyContext::DedupHash),
+            "axiomvault:viewer-scope" => std::str::from_utf8(parameter)
+                .ok()
+                .map(|path| KeyContext::ViewerScope { path }),
+            _ => None,
+        }
+    }
+
+    /// The per-key parameter, empty for contexts with a single key.
+    fn parameter(&self) -> &[u8] {
+        match self {
+            Self::Directory { id } => id,
+            Self::FileContent { name_or_id } => name_or_id,
+            Self::ViewerScope { path } => path.as_bytes(),
+            Self::Names
+            | Self::Tree
+            | Self::Journal
+            | Self::SearchIndex
+            | Self::SyncStaging
+            | Self::ConfigMac
+            | Self::Audit
+            | Self::DedupHash => b"",
+        }
+    }
+
+    /// Canonical encoding: version, then the label and the parameter, each
+    /// prefixed with its length as a little-endian `u64`.
+    ///
+    /// No two contexts encode the same, whatever their parameters.
+    pub fn encode(&self) -> Zeroizing<Vec<u8>> {
+        encode_context(self.label(), self.parameter())
+    }
+
+    /// Blake2b input of the keys derived before this registry, or `None`
+    /// for contexts that never had such a key.
+    ///
+    /// These inputs are not length-prefixed: a file whose identifier were
+    /// `vault_tree_index_v1` would share the tree key. Encrypted names
+    /// never take that form, and no new context may be added here.
+    fn legacy_input(&self) -> Option<[&[u8]; 2]> {
+        match self {
+            Self::Names => Some([b"names", b"dirkey"]),
+            Self::Directory { id } => Some([id, b"dirkey"]),
+            Self::FileContent { name_or_id } => Some([name_or_id, b"filekey"]),
+            Self::Tree => Some([b"vault_tree_index_v1", b"filekey"]),
+            Self::Journal
+            | Self::SearchIndex
+            | Self::SyncStaging
+            | Self::ConfigMac
+            | Self::Audit
+            | Self::DedupHash
+            | Self::ViewerScope { .. } => None,
+        }
+    }
+}
+
+/// The [`KeyContext::encode`] layout for any label and parameter.
+fn encode_context(label: &str, parameter: &[u8]) -> Zeroizing<Vec<u8>> {
+    let label = label.as_bytes();
+    let mut encoded = Zeroizing::new(Vec::with_capacity(17 + label.len() + parameter.len()));
+    encoded.push(KEY_CONTEXT_VERSION);
+    encoded.extend_from_slice(&(label.len() as u64).to_le_bytes());
+    encoded.extend_from_slice(label);
+    encoded.extend_from_slice(&(parameter.len() as u64).to_le_bytes());
+    encoded.extend_from_slice(parameter);
+    encoded
+}
+
 /// Master key derived from user password.
 ///
 /// This key is the root of the key hierarchy and is used to derive
@@ -34,6 +201,34 @@ impl MasterKey {
         Self { key }
     }
 
+    /// Create a master key from bytes supplied by an external key system.
+    ///
+    /// No copy of `key` outlives this call; the caller should wipe its own.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `key` is not [`KEY_LENGTH`] bytes or is all zeros
+    pub fn from_external(key: &[u8]) -> Result<Self> {
+        use subtle::ConstantTimeEq;
+
+        if key.len() != KEY_LENGTH {
+            return Err(Error::InvalidInput(format!(
+                "Master key must be {} bytes, got {}",
+                KEY_LENGTH,
+                key.len()
+            )));
+        }
+        if bool::from(key.ct_eq(&[0u8; KEY_LENGTH])) {
+            return Err(Error::InvalidInput(
+                "Master key must not be all zeros".to_string(),
+            ));
+        }
+        let mut master_key = Self {
+            key: [0u8; KEY_LENGTH],
+        };
+        master_key.key.copy_from_slice(key);
+        Ok(master_key)
+    }
+
     /// Get the key bytes.
     ///
     /// # Security
@@ -42,38 +237,154 @@ impl MasterKey {
         &self.key
     }
 
-    /// Derive a file key from this master key and a file-specific identifier.
+    /// Derive a file key for `context`.
+    pub fn derive_file_key(&self, context: &KeyContext<'_>) -> FileKey {
+        let derived = Zeroizing::new(self.derive_key(context));
+        FileKey::from_bytes(*derived)
+    }
+
+    /// Derive a directory key for `context`.
+    pub fn derive_directory_key(&self, context: &KeyContext<'_>) -> DirectoryKey {
+        let derived = Zeroizing::new(self.derive_key(context));
+        DirectoryKey::from_bytes(*derived)
+    }
+
+    /// Derive the key that encrypts file and directory names.
+    ///
+    /// Names of every vault are encrypted under the legacy key of
+    /// [`KeyContext::Names`].
+    pub fn derive_names_key(&self) -> DirectoryKey {
+        self.derive_legacy_directory_key(&KeyContext::Names)
+            .expect("names have a legacy derivation")
+    }
+
+    /// Derive the key for `context`.
+    ///
+    /// Uses HKDF-SHA256 with this key as input keying material and
+    /// [`KeyContext::encode`] as the HKDF info.
+    ///
+    /// # Security
+    /// The returned bytes are key material; wrap them in a key type or
+    /// [`Zeroizing`] right away.
+    pub fn derive_key(&self, context: &KeyContext<'_>) -> [u8; KEY_LENGTH] {
+        self.expand(&context.encode())
+    }
+
+    /// HKDF-SHA256 with this key as input keying material and `info`.
+    fn expand(&self, info: &[u8]) -> [u8; KEY_LENGTH] {
+        let mut derived = [0u8; KEY_LENGTH];
+        Hkdf::<Sha256>::new(None, &self.key)
+            .expand(info, &mut derived)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        derived
+    }
+
+    /// Derive the key `context` had under the Blake2b derivation used
+    /// before [`KeyContext`].
     ///
-    /// Uses blake2b for secure key derivation.
-    pub fn derive_file_key(&self, file_id: &[u8]) -> FileKey {
+    /// Only for the compat paths of data encrypted that way: names,
+    /// directories, file contents and the tree. Everything else uses
+    /// [`derive_key`](Self::derive_key).
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `context` never had a legacy derivation
+    ///
+    /// # Security
+    /// The returned bytes are key material; wrap them in a key type or
+    /// [`Zeroizing`] right away.
+    pub fn derive_legacy_key(&self, context: &KeyContext<'_>) -> Result<[u8; KEY_LENGTH]> {
         use blake2::digest::consts::U32;
         use blake2::{Blake2b, Digest};
 
+        let parts = context.legacy_input().ok_or_else(|| {
+            Error::InvalidInput(format!("{} has no legacy key derivation", context.label()))
+        })?;
         let mut hasher = Blake2b::<U32>::new();
         hasher.update(self.key);
-        hasher.update(file_id);
-        hasher.update(b"filekey");
+        for part in parts {
+            hasher.update(part);
+        }
+        Ok(hasher.finalize().into())
+    }
 
-        let result = hasher.finalize();
-        let mut derived = Zeroizing::new([0u8; KEY_LENGTH]);
-        derived[..].copy_from_slice(&result);
-        FileKey::from_bytes(*derived)
+    /// Derive the legacy file key of `context`; see
+    /// [`derive_legacy_key`](Self::derive_legacy_key).
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `context` never had a legacy derivation
+    pub fn derive_legacy_file_key(&self, context: &KeyContext<'_>) -> Result<FileKey> {
+        let derived = Zeroizing::new(self.derive_legacy_key(context)?);
+        Ok(FileKey::from_bytes(*derived))
+    }
+
+    /// Derive the legacy directory key of `context`; see
+    /// [`derive_legacy_key`](Self::derive_legacy_key).
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `context` never had a legacy derivation
+    pub fn derive_legacy_directory_key(&self, context: &KeyContext<'_>) -> Result<DirectoryKey> {
+        let derived = Zeroizing::new(self.derive_legacy_key(context)?);
+        Ok(DirectoryKey::from_bytes(*derived))
+    }
+
+    /// Derive a subkey for the context labelled `context`, bound to `info`.
+    ///
+    /// `context` is an `axiomvault:<purpose>` label. Keys derive with
+    /// HKDF-SHA256 over the [`KeyContext::encode`] layout of `context` and
+    /// `info`, so the label of a [`KeyContext`] yields the same key as
+    /// [`derive_key`](Self::derive_key) on it, and any other label a key of
+    /// its own. Only [`FILE_KEY_CONTEXT`], [`DIRECTORY_KEY_CONTEXT`] and
+    /// [`NAMES_KEY_CONTEXT`] yield the keys existing vaults were encrypted
+    /// with, through [`derive_legacy_key`](Self::derive_legacy_key).
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `context` is not of the form `axiomvault:<purpose>`
+    ///
+    /// # Security
+    /// The returned bytes are key material; wrap them in a key type or
+    /// [`Zeroizing`] right away.
+    pub fn derive_subkey(&self, context: &str, info: &[u8]) -> Result<[u8; KEY_LENGTH]> {
+        let legacy = match context {
+            NAMES_KEY_CONTEXT if info.is_empty() => Some(KeyContext::Names),
+            FILE_KEY_CONTEXT => Some(KeyContext::FileContent { name_or_id: info }),
+            DIRECTORY_KEY_CONTEXT => Some(KeyContext::Directory { id: info }),
+            _ => None,
+        };
+        if let Some(legacy) = legacy {
+            return self.derive_legacy_key(&legacy);
+        }
+        match context.strip_prefix("axiomvault:") {
+            Some(purpose) if !purpose.is_empty() => Ok(self.expand(&encode_context(context, info))),
+            _ => Err(Error::InvalidInput(format!(
+                "Key context {:?} is not of the form axiomvault:<purpose>",
+                context
+            ))),
+        }
     }
 
-    /// Derive a directory key from this master key.
-    pub fn derive_directory_key(&self, dir_id: &[u8]) -> DirectoryKey {
+    /// Short, non-reversible identifier of this key for logs and UIs.
+    ///
+    /// Hex-encodes the first bytes of a labelled Blake2b hash of the key,
+    /// so the same key always yields the same [`FINGERPRINT_LENGTH`]
+    /// characters. This is never the raw key and must not be used to
+    /// derive anything.
+    pub fn fingerprint(&self) -> String {
         use blake2::digest::consts::U32;
         use blake2::{Blake2b, Digest};
+        use std::fmt::Write;
 
         let mut hasher = Blake2b::<U32>::new();
+        hasher.update(b"axiomvault-key-fingerprint-v1");
         hasher.update(self.key);
-        hasher.update(dir_id);
-        hasher.update(b"dirkey");
 
-        let result = hasher.finalize();
-        let mut derived = Zeroizing::new([0u8; KEY_LENGTH]);
-        derived[..].copy_from_slice(&result);
-        DirectoryKey::from_bytes(*derived)
+        let digest = hasher.finalize();
+        digest[..FINGERPRINT_LENGTH / 2].iter().fold(
+            String::with_capacity(FINGERPRINT_LENGTH),
+            |mut out, b| {
+                let _ = write!(out, "{:02x}", b);
+                out
+            },
+        )
     }
 }
 
@@ -147,28 +458,46 @@ impl fmt::Debug for DirectoryKey {
     }
 }
 
+/// Length of salts generated for new vaults, in bytes.
+pub const SALT_LENGTH: usize = 32;
+
+/// Shortest salt Argon2id accepts, in bytes.
+pub const MIN_SALT_LENGTH: usize = 8;
+
 /// Salt for key derivation.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Salt(pub [u8; 32]);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Salt(pub Vec<u8>);
 
 impl Salt {
-    /// Generate a random salt.
-    pub fn generate() -> Self {
+    /// Generate a random salt of `len` bytes.
+    ///
+    /// Salts shorter than [`MIN_SALT_LENGTH`] are refused by the KDF.
+    pub fn generate(len: usize) -> Self {
         use rand::RngExt;
-        let mut salt = Zeroizing::new([0u8; 32]);
+        let mut salt = vec![0u8; len];
         rand::rng().fill(&mut salt[..]);
-        Self(*salt)
+        Self(salt)
     }
 
     /// Create from bytes.
-    pub fn from_bytes(bytes: [u8; 32]) -> Self {
-        Self(bytes)
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        Self(bytes.into())
     }
 
     /// Get the salt bytes.
-    pub fn as_bytes(&self) -> &[u8; 32] {
+    pub fn as_bytes(&self) -> &[u8] {
         &self.0
     }
+
+    /// Salt length in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the salt is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 #[cfg(test)]
@@ -180,17 +509,313 @@ mod tests {
         let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
         let file_id = b"test-file";
 
-        let key1 = master.derive_file_key(file_id);
-        let key2 = master.derive_file_key(file_id);
+        let key1 = master.derive_file_key(&KeyContext::FileContent {
+            name_or_id: file_id,
+        });
+        let key2 = master.derive_file_key(&KeyContext::FileContent {
+            name_or_id: file_id,
+        });
 
         // Same input should produce same key
         assert_eq!(key1.as_bytes(), key2.as_bytes());
 
         // Different input should produce different key
-        let key3 = master.derive_file_key(b"other-file");
+        let key3 = master.derive_file_key(&KeyContext::FileContent {
+            name_or_id: b"other-file",
+        });
         assert_ne!(key1.as_bytes(), key3.as_bytes());
     }
 
+    #[test]
+    fn test_master_key_from_external_rejects_weak_keys() {
+        let key = MasterKey::from_external(&[7u8; KEY_LENGTH]).unwrap();
+        assert_eq!(key.as_bytes(), &[7u8; KEY_LENGTH]);
+
+        for bad in [&[7u8; 16][..], &[7u8; 33][..], &[0u8; KEY_LENGTH][..], &[]] {
+            assert!(matches!(
+                MasterKey::from_external(bad),
+                Err(Error::InvalidInput(_))
+            ));
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_existing_derivations_are_byte_stable() {
+        let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
+
+        assert_eq!(
+            hex(master
+                .derive_legacy_file_key(&KeyContext::FileContent {
+                    name_or_id: b"test-file"
+                })
+                .unwrap()
+                .as_bytes()),
+            "d866ee815fe234a85238e6dbf5e7956207155ff205100ca866915e824017b3c5"
+        );
+        assert_eq!(
+            hex(master
+                .derive_legacy_directory_key(&KeyContext::Directory { id: b"test-dir" })
+                .unwrap()
+                .as_bytes()),
+            "bd4b2839a746c9807dc8b3b508475da8667ebfd7e69c74ee2b375a68b7e65cec"
+        );
+        assert_eq!(
+            hex(master.derive_names_key().as_bytes()),
+            "6dada8eff07aa4c30750a987aee99276ea5d3d2b24ac23d9eae6add24e786379"
+        );
+        assert_eq!(
+            master.derive_names_key().as_bytes(),
+            master
+                .derive_legacy_directory_key(&KeyContext::Directory { id: b"names" })
+                .unwrap()
+                .as_bytes()
+        );
+    }
+
+    /// The keys of data written before [`KeyContext`], as derived then:
+    /// Blake2b over the master key, the raw identifier and a key-type tag.
+    #[test]
+    fn test_legacy_contexts_reproduce_raw_derivation() {
+        use blake2::digest::consts::U32;
+        use blake2::{Blake2b, Digest};
+
+        let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
+        let raw = |id: &[u8], tag: &[u8]| -> [u8; KEY_LENGTH] {
+            let mut hasher = Blake2b::<U32>::new();
+            hasher.update(master.as_bytes());
+            hasher.update(id);
+            hasher.update(tag);
+            hasher.finalize().into()
+        };
+
+        let cases: [(KeyContext, &[u8], &[u8]); 4] = [
+            (KeyContext::Names, b"names", b"dirkey"),
+            (KeyContext::Directory { id: b"d1" }, b"d1", b"dirkey"),
+            (
+                KeyContext::FileContent {
+                    name_or_id: b"enc-name",
+                },
+                b"enc-name",
+                b"filekey",
+            ),
+            (KeyContext::Tree, b"vault_tree_index_v1", b"filekey"),
+        ];
+        for (context, id, tag) in cases {
+            assert_eq!(
+                master.derive_legacy_key(&context).unwrap(),
+                raw(id, tag),
+                "{:?}",
+                context
+            );
+            assert_ne!(master.derive_key(&context), raw(id, tag), "{:?}", context);
+        }
+
+        // Contexts added with the registry never had a legacy key.
+        for context in sample_contexts() {
+            if !cases
+                .iter()
+                .any(|(legacy, _, _)| legacy.label() == context.label())
+            {
+                assert!(
+                    matches!(
+                        master.derive_legacy_key(&context),
+                        Err(Error::InvalidInput(_))
+                    ),
+                    "{:?}",
+                    context
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_contexts_use_hkdf_over_encoding() {
+        let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
+        let encoded = KeyContext::ViewerScope { path: "/a" }.encode();
+        let mut expected = vec![KEY_CONTEXT_VERSION];
+        expected.extend_from_slice(&23u64.to_le_bytes());
+        expected.extend_from_slice(b"axiomvault:viewer-scope");
+        expected.extend_from_slice(&2u64.to_le_bytes());
+        expected.extend_from_slice(b"/a");
+        assert_eq!(*encoded, expected);
+
+        let mut derived = [0u8; KEY_LENGTH];
+        Hkdf::<Sha256>::new(None, master.as_bytes())
+            .expand(&expected, &mut derived)
+            .unwrap();
+        assert_eq!(
+            master.derive_key(&KeyContext::ViewerScope { path: "/a" }),
+            derived
+        );
+    }
+
+    /// One sample of every variant. The match makes a new variant fail to
+    /// compile until it is added here too.
+    fn sample_contexts() -> Vec<KeyContext<'static>> {
+        let samples = vec![
+            KeyContext::Names,
+            KeyContext::Directory { id: b"id" },
+            KeyContext::Directory { id: b"other" },
+            KeyContext::FileContent { name_or_id: b"id" },
+            KeyContext::FileContent {
+                name_or_id: b"other",
+            },
+            KeyContext::Tree,
+            KeyContext::Journal,
+            KeyContext::SearchIndex,
+            KeyContext::SyncStaging,
+            KeyContext::ConfigMac,
+            KeyContext::Audit,
+            KeyContext::DedupHash,
+            KeyContext::ViewerScope { path: "/id" },
+            KeyContext::ViewerScope { path: "/other" },
+        ];
+        for context in &samples {
+            match context {
+                KeyContext::Names
+                | KeyContext::Directory { .. }
+                | KeyContext::FileContent { .. }
+                | KeyContext::Tree
+                | KeyContext::Journal
+                | KeyContext::SearchIndex
+                | KeyContext::SyncStaging
+                | KeyContext::ConfigMac
+                | KeyContext::Audit
+                | KeyContext::DedupHash
+                | KeyContext::ViewerScope { .. } => {}
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn test_distinct_contexts_yield_distinct_keys() {
+        let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
+        let contexts = sample_contexts();
+
+        let labels: std::collections::HashSet<_> = contexts.iter().map(|c| c.label()).collect();
+        assert_eq!(labels.len(), 11, "every variant has its own label");
+
+        let keys: Vec<_> = contexts.iter().map(|c| master.derive_key(c)).collect();
+        for (i, a) in keys.iter().enumerate() {
+            for (j, b) in keys.iter().enumerate().skip(i + 1) {
+                assert_ne!(a, b, "{:?} and {:?}", contexts[i], contexts[j]);
+            }
+        }
+
+        // Moving bytes between label and parameter does not collide.
+        assert_ne!(
+            KeyContext::ViewerScope { path: "/ab" }.encode(),
+            KeyContext::ViewerScope { path: "/a" }.encode()
+        );
+    }
+
+    #[test]
+    fn test_derive_subkey_wraps_key_contexts() {
+        let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
+
+        // The original constants keep their original keys.
+        assert_eq!(
+            hex(&master
+                .derive_subkey(FILE_KEY_CONTEXT, b"test-file")
+                .unwrap()),
+            "d866ee815fe234a85238e6dbf5e7956207155ff205100ca866915e824017b3c5"
+        );
+        assert_eq!(
+            hex(&master
+                .derive_subkey(DIRECTORY_KEY_CONTEXT, b"test-dir")
+                .unwrap()),
+            "bd4b2839a746c9807dc8b3b508475da8667ebfd7e69c74ee2b375a68b7e65cec"
+        );
+        assert_eq!(
+            hex(&master.derive_subkey(NAMES_KEY_CONTEXT, b"").unwrap()),
+            "6dada8eff07aa4c30750a987aee99276ea5d3d2b24ac23d9eae6add24e786379"
+        );
+
+        // Every other label is the HKDF key of its context.
+        for context in sample_contexts() {
+            let parameter = context.parameter();
+            assert_eq!(
+                KeyContext::from_label(context.label(), parameter),
+                Some(context)
+            );
+            if context.legacy_input().is_none() {
+                assert_eq!(
+                    master.derive_subkey(context.label(), parameter).unwrap(),
+                    master.derive_key(&context),
+                    "{:?}",
+                    context
+                );
+            }
+        }
+        assert_eq!(
+            master.derive_subkey("axiomvault:tree", b"").unwrap(),
+            master.derive_key(&KeyContext::Tree)
+        );
+    }
+
+    #[test]
+    fn test_from_label_rejects_unknown_contexts() {
+        assert_eq!(KeyContext::from_label("axiomvault:unknown", b""), None);
+        assert_eq!(KeyContext::from_label("axiomvault:journal", b"x"), None);
+        assert_eq!(
+            KeyContext::from_label("axiomvault:viewer-scope", &[0xff]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_derive_subkey_accepts_unregistered_purposes() {
+        let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
+
+        let export = master.derive_subkey("axiomvault:export", b"").unwrap();
+        assert_eq!(
+            master.derive_subkey("axiomvault:export", b"").unwrap(),
+            export
+        );
+        let others = [
+            master.derive_subkey("axiomvault:export", b"x").unwrap(),
+            master.derive_subkey("axiomvault:exports", b"").unwrap(),
+            master.derive_subkey(NAMES_KEY_CONTEXT, b"x").unwrap(),
+            master.derive_key(&KeyContext::Tree),
+        ];
+        for other in others {
+            assert_ne!(export, other);
+        }
+    }
+
+    #[test]
+    fn test_derive_subkey_rejects_malformed_contexts() {
+        let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
+        for context in ["", "export", "axiomvault:", "other:export"] {
+            assert!(
+                matches!(
+                    master.derive_subkey(context, b""),
+                    Err(Error::InvalidInput(_))
+                ),
+                "{:?}",
+                context
+            );
+        }
+    }
+
+    #[test]
+    fn test_master_key_fingerprint() {
+        let master = MasterKey::from_bytes([1u8; KEY_LENGTH]);
+        let other = MasterKey::from_bytes([2u8; KEY_LENGTH]);
+
+        let fingerprint = master.fingerprint();
+        assert_eq!(fingerprint, master.fingerprint());
+        assert_ne!(fingerprint, other.fingerprint());
+        assert_eq!(fingerprint.len(), FINGERPRINT_LENGTH);
+        assert_eq!(other.fingerprint().len(), FINGERPRINT_LENGTH);
+        assert!(fingerprint.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
     #[test]
     fn test_file_key_generate() {
         let key1 = FileKey::generate();
@@ -202,10 +827,25 @@ mod tests {
 
     #[test]
     fn test_salt_generate() {
-        let salt1 = Salt::generate();
-        let salt2 = Salt::generate();
+        let salt1 = Salt::generate(SALT_LENGTH);
+        let salt2 = Salt::generate(SALT_LENGTH);
 
         // Random salts should be different
         assert_ne!(salt1.as_bytes(), salt2.as_bytes());
     }
+
+    #[test]
+    fn test_salt_generate_lengths() {
+        for len in [MIN_SALT_LENGTH, 16, SALT_LENGTH, 64] {
+            let salt = Salt::generate(len);
+            assert_eq!(salt.len(), len);
+            assert_eq!(salt.as_bytes().len(), len);
+        }
+        assert!(Salt::generate(0).is_empty());
+
+        // Shorter salts serialize the same way as 32-byte ones.
+        let salt = Salt::generate(16);
+        let json = serde_json::to_string(&salt).unwrap();
+        assert_eq!(serde_json::from_str::<Salt>(&json).unwrap(), salt);
+    }
 }
@@ -1,10 +1,11 @@
 //! Cryptographic primitives for AxiomVault.
 //!
 //! This module provides:
-//! - Key derivation using Argon2id
-//! - Authenticated encryption using XChaCha20-Poly1305
+//! - Key derivation using Argon2id, optionally combined with a keyfile
+//! - Authenticated encryption using XChaCha20-Poly1305, or AES-256-GCM-SIV
+//!   through a [`CipherSuite`] with the `aes-gcm-siv` feature
 //! - Secure key management with automatic zeroization
-//! - Streaming encryption for large files
+//! - Streaming encryption for large files, sync and over tokio I/O
 //!
 //! # Security notes
 //! - Key types implement zeroization on drop. Intermediate buffers are wiped on a best-effort basis.
@@ -12,13 +13,26 @@
 //! - Logging policy is enforced at higher layers. Avoid logging plaintext paths or secrets.
 
 pub mod aead;
+pub mod async_stream;
+pub mod ciphertext;
 pub mod kdf;
+pub mod keyfile;
 pub mod keys;
 pub mod recovery;
 pub mod stream;
 
-pub use aead::{decrypt, encrypt};
-pub use kdf::{derive_key, KdfParams};
-pub use keys::{DirectoryKey, FileKey, MasterKey, Salt};
+pub use aead::{
+    decrypt, decrypt_with_aad, decrypt_with_suite, encrypt, encrypt_with_aad, encrypt_with_suite,
+    seal, seal_with_aad, seal_with_suite, CipherSuite,
+};
+pub use async_stream::{AsyncDecryptingStream, AsyncEncryptingStream};
+pub use ciphertext::Ciphertext;
+pub use kdf::{
+    derive_key, derive_key_with_keyfile, measure_derivation, verification_tag, KdfParams,
+};
+pub use keys::{
+    DirectoryKey, FileKey, KeyContext, MasterKey, Salt, DIRECTORY_KEY_CONTEXT, FILE_KEY_CONTEXT,
+    KEY_CONTEXT_VERSION, MIN_SALT_LENGTH, NAMES_KEY_CONTEXT, SALT_LENGTH,
+};
 pub use recovery::RecoveryKey;
-pub use stream::{DecryptingStream, EncryptingStream};
+pub use stream::{DecryptingReader, DecryptingStream, EncryptingStream, EncryptingWriter};
@@ -7,7 +7,7 @@ use std::io::{Read, Write};
 
 use zeroize::Zeroize;
 
-use crate::aead::{decrypt, encrypt, NONCE_SIZE, TAG_SIZE};
+use crate::aead::{decrypt_with_aad, encrypt_with_aad, NONCE_SIZE, TAG_SIZE};
 use crate::keys::KEY_LENGTH;
 use axiomvault_common::{Error, Result};
 
@@ -20,10 +20,14 @@ pub const HEADER_SIZE: usize = 13;
 /// Stream encryption version.
 pub const STREAM_VERSION: u8 = 1;
 
+/// Largest chunk size accepted from a stream header (64 MiB).
+const MAX_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
 /// Encrypting stream that processes data in chunks.
 pub struct EncryptingStream<'a> {
     key: &'a [u8],
     chunk_size: usize,
+    aad: &'a [u8],
 }
 
 impl<'a> EncryptingStream<'a> {
@@ -41,12 +45,25 @@ impl<'a> EncryptingStream<'a> {
         Ok(Self {
             key,
             chunk_size: DEFAULT_CHUNK_SIZE,
+            aad: &[],
         })
     }
 
     /// Set custom chunk size.
+    ///
+    /// A size of zero is raised to one byte; a zero-length read buffer
+    /// would end the stream before any input is consumed.
     pub fn with_chunk_size(mut self, size: usize) -> Self {
-        self.chunk_size = size;
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Authenticate `aad` with every chunk.
+    ///
+    /// The associated data is not stored; the stream only opens with the
+    /// same `aad`. Without it chunks are sealed with empty associated data.
+    pub fn with_aad(mut self, aad: &'a [u8]) -> Self {
+        self.aad = aad;
         self
     }
 
@@ -56,6 +73,9 @@ impl<'a> EncryptingStream<'a> {
     /// - Header: version (1 byte) + chunk_size (4 bytes) + total_chunks (8 bytes)
     /// - Chunks: nonce (24 B) || encrypt(index_le64 || plaintext) || tag (16 B)
     ///
+    /// Empty input produces a header with a chunk count of zero and no
+    /// chunks, so every non-empty stream ends with a non-empty chunk.
+    ///
     /// The chunk index is prepended to the plaintext (and therefore authenticated
     /// by Poly1305) to detect chunk reordering or injection attacks.
     ///
@@ -82,29 +102,27 @@ impl<'a> EncryptingStream<'a> {
         // Encrypt each chunk as it arrives; store encrypted output until we know
         // the total count (needed for the header).
         loop {
-            let bytes_read = reader.read(&mut buffer)?;
+            let bytes_read = read_chunk(&mut reader, &mut buffer)?;
             if bytes_read == 0 {
                 break;
             }
             let chunk_index = encrypted_chunks.len() as u64;
             total_bytes += bytes_read as u64;
-
-            // Prepend chunk index to the plaintext so it is authenticated.
-            let mut plaintext = Vec::with_capacity(8 + bytes_read);
-            plaintext.extend_from_slice(&chunk_index.to_le_bytes());
-            plaintext.extend_from_slice(&buffer[..bytes_read]);
-
-            let encrypted = encrypt(self.key, &plaintext)?;
-            plaintext.zeroize();
-            encrypted_chunks.push(encrypted);
+            encrypted_chunks.push(seal_chunk(
+                self.key,
+                chunk_index,
+                &buffer[..bytes_read],
+                self.aad,
+            )?);
         }
 
         buffer.zeroize();
 
         // Write header
-        writer.write_all(&[STREAM_VERSION])?;
-        writer.write_all(&(self.chunk_size as u32).to_le_bytes())?;
-        writer.write_all(&(encrypted_chunks.len() as u64).to_le_bytes())?;
+        writer.write_all(&encode_header(
+            self.chunk_size,
+            encrypted_chunks.len() as u64,
+        ))?;
 
         // Write encrypted chunks
         for chunk in encrypted_chunks {
@@ -118,6 +136,7 @@ impl<'a> EncryptingStream<'a> {
 /// Decrypting stream that processes encrypted chunks.
 pub struct DecryptingStream<'a> {
     key: &'a [u8],
+    aad: &'a [u8],
 }
 
 impl<'a> DecryptingStream<'a> {
@@ -129,7 +148,14 @@ impl<'a> DecryptingStream<'a> {
         if key.len() != KEY_LENGTH {
             return Err(Error::Crypto("Invalid key length".to_string()));
         }
-        Ok(Self { key })
+        Ok(Self { key, aad: &[] })
+    }
+
+    /// Expect `aad` to be authenticated with every chunk; see
+    /// [`EncryptingStream::with_aad`].
+    pub fn with_aad(mut self, aad: &'a [u8]) -> Self {
+        self.aad = aad;
+        self
     }
 
     /// Decrypt data from reader and write to writer.
@@ -146,77 +172,372 @@ impl<'a> DecryptingStream<'a> {
     /// - I/O errors
     /// - Invalid format
     /// - Authentication failure (tampered data)
+    /// - Data after the last chunk recorded in the header
     pub fn decrypt_stream<R: Read, W: Write>(&self, mut reader: R, mut writer: W) -> Result<u64> {
-        // Read header
-        let mut version = [0u8; 1];
-        reader.read_exact(&mut version)?;
-        if version[0] != STREAM_VERSION {
-            return Err(Error::Crypto(format!(
-                "Unsupported stream version: {}",
-                version[0]
-            )));
-        }
-
-        let mut chunk_size_bytes = [0u8; 4];
-        reader.read_exact(&mut chunk_size_bytes)?;
-        let chunk_size = u32::from_le_bytes(chunk_size_bytes) as usize;
-
-        // Validate chunk size to prevent malicious headers causing huge allocations (e.g. 4GB)
-        const MAX_CHUNK_SIZE: usize = 64 * 1024 * 1024; // 64 MiB
-        if chunk_size > MAX_CHUNK_SIZE {
-            return Err(Error::Crypto(format!(
-                "Chunk size {} exceeds maximum allowed ({} bytes)",
-                chunk_size, MAX_CHUNK_SIZE
-            )));
-        }
+        let (chunk_size, total_chunks) = read_header(&mut reader)?;
 
-        let mut total_chunks_bytes = [0u8; 8];
-        reader.read_exact(&mut total_chunks_bytes)?;
-        let total_chunks = u64::from_le_bytes(total_chunks_bytes);
-
-        let encrypted_chunk_size = NONCE_SIZE + chunk_size + 8 + TAG_SIZE;
-        let mut encrypted_buffer = vec![0u8; encrypted_chunk_size];
+        let mut encrypted_buffer = vec![0u8; encrypted_chunk_len(chunk_size)];
         let mut total_bytes = 0u64;
 
         // Decrypt each chunk
         for i in 0..total_chunks {
             // Read encrypted chunk (size may vary for last chunk)
             let bytes_read = read_chunk(&mut reader, &mut encrypted_buffer)?;
-            if bytes_read == 0 {
-                return Err(Error::Crypto("Unexpected end of stream".to_string()));
-            }
+            let plaintext = open_chunk(self.key, &encrypted_buffer[..bytes_read], i, self.aad)?;
+            writer.write_all(&plaintext)?;
+            total_bytes += plaintext.len() as u64;
+        }
+        expect_end(&mut reader)?;
 
-            let mut decrypted = decrypt(self.key, &encrypted_buffer[..bytes_read])?;
+        Ok(total_bytes)
+    }
+}
 
-            // Verify chunk index
-            if decrypted.len() < 8 {
-                decrypted.zeroize();
-                return Err(Error::Crypto("Invalid chunk format".to_string()));
-            }
-            let chunk_index = u64::from_le_bytes(decrypted[..8].try_into().unwrap());
-            if chunk_index != i {
-                decrypted.zeroize();
-                return Err(Error::Crypto("Chunk order mismatch".to_string()));
+/// Chunked encryptor that accepts plaintext through [`Write`].
+///
+/// Produces the same format as [`EncryptingStream`], but lets serializers
+/// write straight into the cipher instead of first building the whole
+/// plaintext. Plaintext is buffered one chunk at a time; sealed chunks are
+/// kept until [`finish`](Self::finish) because the header records the chunk
+/// count.
+pub struct EncryptingWriter<'a> {
+    key: &'a [u8],
+    aad: &'a [u8],
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    chunks: Vec<Vec<u8>>,
+}
+
+impl<'a> EncryptingWriter<'a> {
+    /// Create a new encrypting writer.
+    ///
+    /// # Errors
+    /// - Returns error if key length is invalid
+    pub fn new(key: &'a [u8]) -> Result<Self> {
+        if key.len() != KEY_LENGTH {
+            return Err(Error::Crypto("Invalid key length".to_string()));
+        }
+        Ok(Self {
+            key,
+            aad: &[],
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            buffer: Vec::with_capacity(DEFAULT_CHUNK_SIZE),
+            chunks: Vec::new(),
+        })
+    }
+
+    /// Set custom chunk size.
+    pub fn with_chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.max(1);
+        self
+    }
+
+    /// Authenticate `aad` with every chunk; see
+    /// [`EncryptingStream::with_aad`].
+    pub fn with_aad(mut self, aad: &'a [u8]) -> Self {
+        self.aad = aad;
+        self
+    }
+
+    /// Seal the buffered plaintext as the next chunk.
+    fn seal_chunk(&mut self) -> Result<()> {
+        let chunk_index = self.chunks.len() as u64;
+        let encrypted = seal_chunk(self.key, chunk_index, &self.buffer, self.aad);
+        self.buffer.zeroize();
+        self.buffer.clear();
+        self.chunks.push(encrypted?);
+        Ok(())
+    }
+
+    /// Seal the final chunk and return the encrypted stream.
+    ///
+    /// The first element is the header, followed by one element per chunk;
+    /// concatenated they form a stream readable by [`DecryptingStream`] and
+    /// [`DecryptingReader`].
+    ///
+    /// # Errors
+    /// - Encryption errors
+    pub fn finish(mut self) -> Result<Vec<Vec<u8>>> {
+        if !self.buffer.is_empty() {
+            self.seal_chunk()?;
+        }
+
+        let header = encode_header(self.chunk_size, self.chunks.len() as u64).to_vec();
+
+        let mut output = Vec::with_capacity(self.chunks.len() + 1);
+        output.push(header);
+        output.append(&mut self.chunks);
+        Ok(output)
+    }
+}
+
+impl Write for EncryptingWriter<'_> {
+    fn write(&mut self, mut buf: &[u8]) -> std::io::Result<usize> {
+        let written = buf.len();
+        while !buf.is_empty() {
+            let take = (self.chunk_size - self.buffer.len()).min(buf.len());
+            self.buffer.extend_from_slice(&buf[..take]);
+            buf = &buf[take..];
+            if self.buffer.len() == self.chunk_size {
+                self.seal_chunk()
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
             }
+        }
+        Ok(written)
+    }
 
-            let plaintext = &decrypted[8..];
-            writer.write_all(plaintext)?;
-            total_bytes += plaintext.len() as u64;
-            decrypted.zeroize();
+    fn flush(&mut self) -> std::io::Result<()> {
+        // Chunks must be full except the last, so partial data stays
+        // buffered until `finish`.
+        Ok(())
+    }
+}
+
+impl Drop for EncryptingWriter<'_> {
+    fn drop(&mut self) {
+        self.buffer.zeroize();
+    }
+}
+
+/// Chunked decryptor that exposes plaintext through [`Read`].
+///
+/// The counterpart of [`EncryptingWriter`]: deserializers can read straight
+/// from the cipher, so at most one decrypted chunk is held at a time.
+pub struct DecryptingReader<'a, R> {
+    key: &'a [u8],
+    aad: &'a [u8],
+    reader: R,
+    encrypted: Vec<u8>,
+    plaintext: Vec<u8>,
+    position: usize,
+    next_chunk: u64,
+    total_chunks: u64,
+    finished: bool,
+}
+
+impl<'a, R: Read> DecryptingReader<'a, R> {
+    /// Create a decrypting reader, consuming the stream header.
+    ///
+    /// # Errors
+    /// - Returns error if key length is invalid
+    /// - Invalid or truncated header
+    pub fn new(key: &'a [u8], mut reader: R) -> Result<Self> {
+        if key.len() != KEY_LENGTH {
+            return Err(Error::Crypto("Invalid key length".to_string()));
+        }
+        let (chunk_size, total_chunks) = read_header(&mut reader)?;
+        Ok(Self {
+            key,
+            aad: &[],
+            reader,
+            encrypted: vec![0u8; encrypted_chunk_len(chunk_size)],
+            plaintext: Vec::new(),
+            position: 0,
+            next_chunk: 0,
+            total_chunks,
+            finished: false,
+        })
+    }
+
+    /// Expect `aad` to be authenticated with every chunk; see
+    /// [`EncryptingStream::with_aad`].
+    pub fn with_aad(mut self, aad: &'a [u8]) -> Self {
+        self.aad = aad;
+        self
+    }
+
+    fn fill(&mut self) -> Result<()> {
+        let bytes_read = read_chunk(&mut self.reader, &mut self.encrypted)?;
+        let plaintext = open_chunk(
+            self.key,
+            &self.encrypted[..bytes_read],
+            self.next_chunk,
+            self.aad,
+        )?;
+        self.plaintext.zeroize();
+        self.plaintext = plaintext;
+        self.position = 0;
+        self.next_chunk += 1;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let invalid =
+            |e: Error| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string());
+        while self.position == self.plaintext.len() {
+            if self.next_chunk == self.total_chunks {
+                if !self.finished {
+                    expect_end(&mut self.reader).map_err(invalid)?;
+                    self.finished = true;
+                }
+                return Ok(0);
+            }
+            self.fill().map_err(invalid)?;
         }
+        let available = &self.plaintext[self.position..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.position += n;
+        Ok(n)
+    }
+}
 
-        Ok(total_bytes)
+impl<R> Drop for DecryptingReader<'_, R> {
+    fn drop(&mut self) {
+        self.plaintext.zeroize();
     }
 }
 
-/// Read a complete encrypted chunk from the reader.
+/// Size of one full encrypted chunk for the given plaintext chunk size.
+pub(crate) fn encrypted_chunk_len(chunk_size: usize) -> usize {
+    NONCE_SIZE + chunk_size + 8 + TAG_SIZE
+}
+
+/// Number of chunks `len` plaintext bytes are split into.
+pub(crate) fn chunk_count(len: u64, chunk_size: usize) -> u64 {
+    len.div_ceil(chunk_size as u64)
+}
+
+/// Encode the stream header for the given chunk size and count.
+pub(crate) fn encode_header(chunk_size: usize, total_chunks: u64) -> [u8; HEADER_SIZE] {
+    let mut header = [0u8; HEADER_SIZE];
+    header[0] = STREAM_VERSION;
+    header[1..5].copy_from_slice(&(chunk_size as u32).to_le_bytes());
+    header[5..].copy_from_slice(&total_chunks.to_le_bytes());
+    header
+}
+
+/// Validate a stream header, returning `(chunk_size, total_chunks)`.
+pub(crate) fn parse_header(header: &[u8; HEADER_SIZE]) -> Result<(usize, u64)> {
+    if header[0] != STREAM_VERSION {
+        return Err(Error::Crypto(format!(
+            "Unsupported stream version: {}",
+            header[0]
+        )));
+    }
+
+    let mut chunk_size_bytes = [0u8; 4];
+    chunk_size_bytes.copy_from_slice(&header[1..5]);
+    let chunk_size = u32::from_le_bytes(chunk_size_bytes) as usize;
+
+    // Validate chunk size to prevent malicious headers causing huge allocations (e.g. 4GB)
+    if chunk_size > MAX_CHUNK_SIZE {
+        return Err(Error::Crypto(format!(
+            "Chunk size {} exceeds maximum allowed ({} bytes)",
+            chunk_size, MAX_CHUNK_SIZE
+        )));
+    }
+
+    let mut total_chunks_bytes = [0u8; 8];
+    total_chunks_bytes.copy_from_slice(&header[5..]);
+    Ok((chunk_size, u64::from_le_bytes(total_chunks_bytes)))
+}
+
+/// Read and validate the stream header, returning `(chunk_size, total_chunks)`.
+fn read_header<R: Read>(reader: &mut R) -> Result<(usize, u64)> {
+    let mut header = [0u8; HEADER_SIZE];
+    reader.read_exact(&mut header)?;
+    parse_header(&header)
+}
+
+/// Encrypt one chunk, authenticating its index with the plaintext and
+/// `aad` alongside it.
+///
+/// The index is prepended to the plaintext so Poly1305 covers it and a
+/// reordered or injected chunk fails to open.
+pub(crate) fn seal_chunk(key: &[u8], index: u64, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+    let mut framed = Vec::with_capacity(8 + plaintext.len());
+    framed.extend_from_slice(&index.to_le_bytes());
+    framed.extend_from_slice(plaintext);
+    let encrypted = encrypt_with_aad(key, &framed, aad);
+    framed.zeroize();
+    encrypted
+}
+
+/// Check that nothing follows the last chunk recorded in the header.
+///
+/// Without this a header claiming zero chunks would decrypt any body to an
+/// empty file, and appended data would go unnoticed after the last chunk.
+fn expect_end<R: Read>(reader: &mut R) -> Result<()> {
+    let mut byte = [0u8; 1];
+    trailing_data(reader.read(&mut byte)?)
+}
+
+/// The error for `read` bytes found after the final chunk, if any.
+pub(crate) fn trailing_data(read: usize) -> Result<()> {
+    if read != 0 {
+        return Err(Error::Crypto(
+            "Unexpected data after final chunk".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Decrypt one chunk sealed with `aad` and check its index, returning the
+/// plaintext.
+pub(crate) fn open_chunk(
+    key: &[u8],
+    encrypted: &[u8],
+    expected_index: u64,
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    if encrypted.is_empty() {
+        return Err(Error::Crypto("Unexpected end of stream".to_string()));
+    }
+
+    let mut decrypted = decrypt_with_aad(key, encrypted, aad)?;
+
+    // Verify chunk index
+    if decrypted.len() < 8 {
+        decrypted.zeroize();
+        return Err(Error::Crypto("Invalid chunk format".to_string()));
+    }
+    let mut index_bytes = [0u8; 8];
+    index_bytes.copy_from_slice(&decrypted[..8]);
+    if u64::from_le_bytes(index_bytes) != expected_index {
+        decrypted.zeroize();
+        return Err(Error::Crypto("Chunk order mismatch".to_string()));
+    }
+
+    let plaintext = decrypted[8..].to_vec();
+    decrypted.zeroize();
+    Ok(plaintext)
+}
+
+/// Check whether `data` is laid out as a complete encrypted stream.
+///
+/// Validates the header and that the length matches the recorded chunk
+/// count exactly. This distinguishes stream output from a single AEAD blob
+/// without attempting decryption.
+pub fn is_stream_format(data: &[u8]) -> bool {
+    let Ok((chunk_size, total_chunks)) = read_header(&mut &data[..]) else {
+        return false;
+    };
+    let body = (data.len() - HEADER_SIZE) as u64;
+    if total_chunks == 0 {
+        return body == 0;
+    }
+    let full = encrypted_chunk_len(chunk_size) as u64;
+    let Some(before_last) = (total_chunks - 1).checked_mul(full) else {
+        return false;
+    };
+    let Some(last) = body.checked_sub(before_last) else {
+        return false;
+    };
+    let overhead = encrypted_chunk_len(0) as u64;
+    last > overhead && last <= full
+}
+
+/// Read a complete chunk, plaintext or encrypted, from the reader.
 ///
 /// Reads as many bytes as possible into `buffer`, returning the count.
 /// Returns 0 only if the reader is immediately at EOF (no data for this chunk).
 ///
 /// For all but the last chunk the buffer will be filled completely.
 /// For the last (partial) chunk fewer bytes are returned — the caller must
-/// pass only `buffer[..bytes_read]` to the decryption function.
+/// pass only `buffer[..bytes_read]` on.
 ///
 /// Note: `Read::read` may return partial data on a single call (this is
 /// legal for file/network I/O). We loop until the buffer is full or we hit
@@ -256,6 +577,7 @@ pub fn decrypt_bytes(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aead::encrypt;
     use proptest::prelude::*;
 
     proptest! {
@@ -433,6 +755,68 @@ mod tests {
         assert_eq!(decrypted, plaintext);
     }
 
+    #[test]
+    fn test_stream_empty_data_has_zero_chunks() {
+        let key = [42u8; KEY_LENGTH];
+        let mut encrypted = Vec::new();
+        let written = EncryptingStream::new(&key)
+            .unwrap()
+            .encrypt_stream(&b""[..], &mut encrypted)
+            .unwrap();
+        assert_eq!(written, 0);
+        assert_eq!(encrypted.len(), HEADER_SIZE);
+        assert_eq!(u64::from_le_bytes(encrypted[5..13].try_into().unwrap()), 0);
+
+        let mut decrypted = Vec::new();
+        let read = DecryptingStream::new(&key)
+            .unwrap()
+            .decrypt_stream(&encrypted[..], &mut decrypted)
+            .unwrap();
+        assert_eq!(read, 0);
+        assert!(decrypted.is_empty());
+
+        let mut reader = DecryptingReader::new(&key, &encrypted[..]).unwrap();
+        let mut buf = [0u8; 16];
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_writer_finishes_empty_input_as_header_only() {
+        let key = [42u8; KEY_LENGTH];
+        let encrypted = EncryptingWriter::new(&key).unwrap().finish().unwrap();
+        assert_eq!(encrypted.len(), 1);
+        assert_eq!(encrypted[0], encrypt_bytes(&key, b"").unwrap());
+    }
+
+    #[test]
+    fn test_zero_chunk_size_still_encrypts_data() {
+        let key = [42u8; KEY_LENGTH];
+        let stream = EncryptingStream::new(&key).unwrap().with_chunk_size(0);
+        let mut encrypted = Vec::new();
+        assert_eq!(
+            stream.encrypt_stream(&b"abc"[..], &mut encrypted).unwrap(),
+            3
+        );
+        assert_eq!(decrypt_bytes(&key, &encrypted).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_data_after_final_chunk_is_rejected() {
+        let key = [42u8; KEY_LENGTH];
+        for plaintext in [&b""[..], &b"data"[..]] {
+            let mut encrypted = encrypt_bytes(&key, plaintext).unwrap();
+            encrypted.push(0);
+            assert!(decrypt_bytes(&key, &encrypted).is_err());
+
+            let mut decrypted = Vec::new();
+            let result = DecryptingReader::new(&key, &encrypted[..])
+                .unwrap()
+                .read_to_end(&mut decrypted);
+            assert!(result.is_err());
+        }
+    }
+
     #[test]
     fn test_stream_custom_chunk_size() {
         let key = [42u8; KEY_LENGTH];
@@ -474,4 +858,127 @@ mod tests {
         let total_chunks = u64::from_le_bytes(encrypted[5..13].try_into().unwrap());
         assert_eq!(total_chunks, 1); // Single chunk for small data
     }
+
+    #[test]
+    fn test_writer_and_reader_roundtrip() {
+        let key = [9u8; KEY_LENGTH];
+        let plaintext: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut writer = EncryptingWriter::new(&key).unwrap().with_chunk_size(64);
+        // Uneven writes must still produce full chunks.
+        for piece in plaintext.chunks(7) {
+            writer.write_all(piece).unwrap();
+        }
+        let encrypted = writer.finish().unwrap().concat();
+
+        assert!(is_stream_format(&encrypted));
+        assert_eq!(decrypt_bytes(&key, &encrypted).unwrap(), plaintext);
+
+        let mut reader = DecryptingReader::new(&key, &encrypted[..]).unwrap();
+        let mut decrypted = Vec::new();
+        reader.read_to_end(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_reader_accepts_encrypting_stream_output() {
+        let key = [9u8; KEY_LENGTH];
+        let plaintext = vec![0x5A; DEFAULT_CHUNK_SIZE + 17];
+        let encrypted = encrypt_bytes(&key, &plaintext).unwrap();
+
+        let mut decrypted = Vec::new();
+        DecryptingReader::new(&key, &encrypted[..])
+            .unwrap()
+            .read_to_end(&mut decrypted)
+            .unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_reader_rejects_tampered_chunk() {
+        let key = [9u8; KEY_LENGTH];
+        let mut encrypted = encrypt_bytes(&key, b"tamper with me").unwrap();
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 0x01;
+
+        let mut decrypted = Vec::new();
+        let result = DecryptingReader::new(&key, &encrypted[..])
+            .unwrap()
+            .read_to_end(&mut decrypted);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_stream_binds_associated_data() {
+        let key = [9u8; KEY_LENGTH];
+        let plaintext = vec![0x3C; 100];
+        let mut encrypted = Vec::new();
+        EncryptingStream::new(&key)
+            .unwrap()
+            .with_chunk_size(32)
+            .with_aad(b"context")
+            .encrypt_stream(&plaintext[..], &mut encrypted)
+            .unwrap();
+        assert!(is_stream_format(&encrypted));
+
+        let mut decrypted = Vec::new();
+        DecryptingStream::new(&key)
+            .unwrap()
+            .with_aad(b"context")
+            .decrypt_stream(&encrypted[..], &mut decrypted)
+            .unwrap();
+        assert_eq!(decrypted, plaintext);
+
+        let mut read = Vec::new();
+        DecryptingReader::new(&key, &encrypted[..])
+            .unwrap()
+            .with_aad(b"context")
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, plaintext);
+
+        // Missing or different associated data fails every chunk.
+        assert!(decrypt_bytes(&key, &encrypted).is_err());
+        let result = DecryptingStream::new(&key)
+            .unwrap()
+            .with_aad(b"other")
+            .decrypt_stream(&encrypted[..], &mut Vec::new());
+        assert!(result.is_err());
+        let result = DecryptingReader::new(&key, &encrypted[..])
+            .unwrap()
+            .with_aad(b"other")
+            .read_to_end(&mut Vec::new());
+        assert!(result.is_err());
+
+        // The writer seals the same layout.
+        let mut writer = EncryptingWriter::new(&key)
+            .unwrap()
+            .with_chunk_size(32)
+            .with_aad(b"context");
+        writer.write_all(&plaintext).unwrap();
+        let written = writer.finish().unwrap().concat();
+        let mut decrypted = Vec::new();
+        DecryptingStream::new(&key)
+            .unwrap()
+            .with_aad(b"context")
+            .decrypt_stream(&written[..], &mut decrypted)
+            .unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_is_stream_format() {
+        let key = [9u8; KEY_LENGTH];
+        assert!(is_stream_format(&encrypt_bytes(&key, b"").unwrap()));
+        assert!(is_stream_format(&encrypt_bytes(&key, b"data").unwrap()));
+
+        let encrypted = encrypt_bytes(&key, b"data").unwrap();
+        assert!(!is_stream_format(&encrypted[..HEADER_SIZE]));
+
+        // A single AEAD blob is not a stream, even with a matching first byte.
+        let mut blob = encrypt(&key, b"data").unwrap();
+        blob[0] = STREAM_VERSION;
+        assert!(!is_stream_format(&blob));
+        assert!(!is_stream_format(&[]));
+    }
 }
@@ -26,6 +26,54 @@ pub enum FFIError {
     StringConversionError,
     /// IO error.
     IOError(String),
+    /// Conflicting concurrent change.
+    Conflict(String),
+    /// Operation not supported.
+    Unsupported(String),
+    /// Operation cancelled.
+    Cancelled(String),
+    /// Provider is throttling requests.
+    RateLimited(String),
+    /// Stored data failed an integrity check.
+    IntegrityError(String),
+    /// Wrapped key predates the last password change; re-wrap it.
+    WrappedKeyStale(String),
+    /// Provider storage is full.
+    QuotaExhausted(String),
+    /// The core panicked; the call was aborted and nothing is known about
+    /// how far it got.
+    Panic(String),
+}
+
+impl FFIError {
+    /// Stable numeric code for this error, as returned by
+    /// `axiom_last_error_code`. `0` means no error.
+    pub fn code(&self) -> i32 {
+        match self {
+            FFIError::NullPointer(_) => 1,
+            FFIError::InvalidUtf8(_) => 2,
+            FFIError::RuntimeError(_) => 3,
+            FFIError::VaultError(_) => 4,
+            FFIError::StorageError(_) => 5,
+            FFIError::CryptoError(_) => 6,
+            FFIError::StringConversionError => 7,
+            FFIError::IOError(_) => 8,
+            FFIError::Conflict(_) => 9,
+            FFIError::Unsupported(_) => 10,
+            FFIError::Cancelled(_) => 11,
+            FFIError::RateLimited(_) => 12,
+            FFIError::IntegrityError(_) => 13,
+            FFIError::WrappedKeyStale(_) => 14,
+            FFIError::QuotaExhausted(_) => 15,
+            FFIError::Panic(_) => 16,
+        }
+    }
+
+    /// JSON object with the code and message of this error, as passed to
+    /// asynchronous completion callbacks.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({ "code": self.code(), "message": self.to_string() }).to_string()
+    }
 }
 
 impl fmt::Display for FFIError {
@@ -39,6 +87,14 @@ impl fmt::Display for FFIError {
             FFIError::CryptoError(msg) => write!(f, "Crypto error: {}", msg),
             FFIError::StringConversionError => write!(f, "String conversion error"),
             FFIError::IOError(msg) => write!(f, "IO error: {}", msg),
+            FFIError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            FFIError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            FFIError::Cancelled(msg) => write!(f, "Cancelled: {}", msg),
+            FFIError::RateLimited(msg) => write!(f, "Rate limited: {}", msg),
+            FFIError::IntegrityError(msg) => write!(f, "Integrity error: {}", msg),
+            FFIError::WrappedKeyStale(msg) => write!(f, "Wrapped key stale: {}", msg),
+            FFIError::QuotaExhausted(msg) => write!(f, "Storage quota exhausted: {}", msg),
+            FFIError::Panic(msg) => write!(f, "Internal panic: {}", msg),
         }
     }
 }
@@ -58,6 +114,8 @@ impl From<AppError> for FFIError {
             AppError::InvalidRecoveryKey => {
                 FFIError::CryptoError("Invalid recovery key".to_string())
             }
+            AppError::KeyfileRequired => FFIError::CryptoError("Keyfile required".to_string()),
+            AppError::InvalidKeyfile => FFIError::CryptoError("Invalid keyfile".to_string()),
             AppError::NoOpenVault => FFIError::VaultError("No vault is open".to_string()),
             AppError::VaultLocked => FFIError::VaultError("Vault is locked".to_string()),
             AppError::PathNotFound(msg) => FFIError::VaultError(format!("Path not found: {}", msg)),
@@ -66,8 +124,26 @@ impl From<AppError> for FFIError {
             }
             AppError::InvalidInput(msg) => FFIError::VaultError(format!("Invalid input: {}", msg)),
             AppError::Storage(msg) => FFIError::StorageError(msg),
-            AppError::SyncConflict(msg) => FFIError::VaultError(format!("Sync conflict: {}", msg)),
+            AppError::SyncConflict(msg) => FFIError::Conflict(msg),
             AppError::Crypto(msg) => FFIError::CryptoError(msg),
+            AppError::Unsupported(msg) => FFIError::Unsupported(msg),
+            AppError::Cancelled(msg) => FFIError::Cancelled(msg),
+            AppError::RateLimited(msg) => FFIError::RateLimited(msg),
+            AppError::Integrity(msg) => FFIError::IntegrityError(msg),
+            AppError::ConfigTampered => {
+                FFIError::IntegrityError("Vault configuration has been tampered with".to_string())
+            }
+            AppError::WrappedKeyStale(msg) => FFIError::WrappedKeyStale(msg),
+            AppError::QuotaExhausted(msg) => FFIError::QuotaExhausted(msg),
+            err @ AppError::SyncInProgress(_) => FFIError::Conflict(err.to_string()),
+            err @ AppError::MigrationRequired(_) => FFIError::VaultError(err.to_string()),
+            err @ AppError::MountBusy(_) => FFIError::Conflict(err.to_string()),
+            AppError::DraftNotFound(msg) => {
+                FFIError::VaultError(format!("Vault draft not found: {}", msg))
+            }
+            AppError::RecoveryNotConfirmed => {
+                FFIError::VaultError("Recovery words not confirmed".to_string())
+            }
             AppError::Internal(msg) => FFIError::VaultError(format!("Internal error: {}", msg)),
         }
     }
@@ -92,3 +168,54 @@ pub fn set_last_error(error: FFIError) {
 pub fn take_last_error() -> Option<FFIError> {
     LAST_ERROR.with(|e| e.borrow_mut().take())
 }
+
+/// Code of the last error on the current thread, leaving it in place.
+pub fn last_error_code() -> i32 {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(0, FFIError::code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axiomvault_common::Error as CommonError;
+
+    #[test]
+    fn test_new_common_errors_map_to_codes() {
+        let cases = [
+            (CommonError::Conflict("x".into()), 9, "Conflict: x"),
+            (CommonError::Unsupported("x".into()), 10, "Unsupported: x"),
+            (CommonError::Cancelled("x".into()), 11, "Cancelled: x"),
+            (CommonError::RateLimited("x".into()), 12, "Rate limited: x"),
+            (CommonError::Integrity("x".into()), 13, "Integrity error: x"),
+            (
+                CommonError::ConfigTampered,
+                13,
+                "Integrity error: Vault configuration has been tampered with",
+            ),
+            (
+                CommonError::WrappedKeyStale("x".into()),
+                14,
+                "Wrapped key stale: x",
+            ),
+            (
+                CommonError::QuotaExhausted("x".into()),
+                15,
+                "Storage quota exhausted: x",
+            ),
+        ];
+        for (error, code, message) in cases {
+            let ffi = FFIError::from(AppError::from(error));
+            assert_eq!(ffi.code(), code);
+            assert_eq!(ffi.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_last_error_code_does_not_consume() {
+        assert_eq!(last_error_code(), 0);
+        set_last_error(FFIError::RateLimited("slow down".into()));
+        assert_eq!(last_error_code(), 12);
+        assert_eq!(take_last_error().unwrap().code(), 12);
+        assert_eq!(last_error_code(), 0);
+    }
+}
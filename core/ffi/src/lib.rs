@@ -26,14 +26,17 @@ pub mod runtime;
 pub mod types;
 pub mod vault_ops;
 
-use std::ffi::{c_char, c_int, CStr, CString};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
 use std::ptr;
 
+use tokio::sync::watch;
 use zeroize::Zeroizing;
 
-use crate::error::FFIError;
+use crate::error::{FFIError, FFIResult};
 use crate::runtime::get_runtime;
-use crate::types::{FFIEventCallback, FFIVaultHandle, FFIVaultInfo};
+use crate::types::{
+    FFICancelToken, FFIEventCallback, FFIVaultCallback, FFIVaultHandle, FFIVaultInfo, FFIWrappedKey,
+};
 
 // ---------------------------------------------------------------------------
 // Helpers
@@ -74,6 +77,43 @@ unsafe fn zeroizing_string_from_ptr(ptr: *const c_char, name: &str) -> Option<Ze
     Some(Zeroizing::new(s.to_owned()))
 }
 
+/// Read the keyfile named by a nullable C string path.
+///
+/// Returns `Some(None)` for a null pointer (no keyfile), `Some(Some(_))` with
+/// the keyfile content, or `None` after setting the FFI error if the path is
+/// not valid UTF-8 or the file cannot be read.
+///
+/// # Safety
+/// Same contract as [`str_from_ptr`] for non-null pointers.
+// SAFETY: contract delegated to `str_from_ptr`.
+unsafe fn keyfile_from_ptr(ptr: *const c_char, name: &str) -> Option<Option<Zeroizing<Vec<u8>>>> {
+    if ptr.is_null() {
+        return Some(None);
+    }
+    let path = str_from_ptr(ptr, name)?;
+    match vault_ops::read_keyfile(path) {
+        Ok(content) => Some(Some(content)),
+        Err(e) => {
+            error::set_last_error(e);
+            None
+        }
+    }
+}
+
+/// Borrow `len` bytes at `ptr`, setting the FFI error for a null pointer.
+///
+/// # Safety
+/// `ptr` must be null or point to `len` readable bytes that outlive the
+/// returned slice.
+// SAFETY: contract documented above.
+unsafe fn bytes_from_ptr<'a>(ptr: *const u8, len: usize, name: &str) -> Option<&'a [u8]> {
+    if ptr.is_null() {
+        error::set_last_error(FFIError::NullPointer(format!("{} is null", name)));
+        return None;
+    }
+    Some(std::slice::from_raw_parts(ptr, len))
+}
+
 /// Copy a `Zeroizing<String>` (containing a recovery mnemonic or other secret)
 /// into a freshly allocated C-owned buffer, then zeroize the source.
 ///
@@ -87,6 +127,26 @@ fn into_secret_cstr(secret: Zeroizing<String>) -> Result<*mut c_char, FFIError>
     Ok(cstring.into_raw())
 }
 
+/// Run the body of an FFI entry point, turning a panic into an error.
+///
+/// Unwinding out of an `extern "C"` function aborts the host app, so every
+/// `#[no_mangle]` function runs its body through this guard. A panic is
+/// recorded as [`FFIError::Panic`] and `on_panic` is returned instead.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)) {
+        Ok(value) => value,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            error::set_last_error(FFIError::Panic(message));
+            on_panic
+        }
+    }
+}
+
 /// Run an async operation on the global runtime, mapping errors to FFI.
 fn block_on<F, T>(f: F) -> Result<T, ()>
 where
@@ -118,20 +178,22 @@ where
 /// This function is safe to call from foreign code.
 #[no_mangle]
 pub extern "C" fn axiom_init() -> c_int {
-    let _ = tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .try_init();
-
-    match get_runtime() {
-        Ok(_) => {
-            tracing::info!("AxiomVault FFI initialized");
-            0
-        }
-        Err(e) => {
-            tracing::error!("Failed to initialize runtime: {}", e);
-            -1
+    guard(-1, || {
+        let _ = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::INFO)
+            .try_init();
+
+        match get_runtime() {
+            Ok(_) => {
+                tracing::info!("AxiomVault FFI initialized");
+                0
+            }
+            Err(e) => {
+                tracing::error!("Failed to initialize runtime: {}", e);
+                -1
+            }
         }
-    }
+    })
 }
 
 /// Get the version of the AxiomVault library.
@@ -140,8 +202,10 @@ pub extern "C" fn axiom_init() -> c_int {
 /// Returns a pointer to a static string. Do not free.
 #[no_mangle]
 pub extern "C" fn axiom_version() -> *const c_char {
-    static VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
-    VERSION.as_ptr() as *const c_char
+    guard(ptr::null(), || {
+        static VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+        VERSION.as_ptr() as *const c_char
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -161,19 +225,60 @@ pub unsafe extern "C" fn axiom_vault_create(
     path: *const c_char,
     password: *const c_char,
 ) -> *mut FFIVaultHandle {
-    let path_str = match str_from_ptr(path, "path") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
-    let password_zeroizing = match zeroizing_string_from_ptr(password, "password") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let password_zeroizing = match zeroizing_string_from_ptr(password, "password") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::create_vault(path_str, password_zeroizing, None)) {
+            Ok(handle) => Box::into_raw(Box::new(handle)),
+            Err(()) => ptr::null_mut(),
+        }
+    })
+}
 
-    match block_on(vault_ops::create_vault(path_str, password_zeroizing)) {
-        Ok(handle) => Box::into_raw(Box::new(handle)),
-        Err(()) => ptr::null_mut(),
-    }
+/// Create a new vault that requires a keyfile in addition to the password.
+///
+/// # Safety
+/// - `path` and `password` must be valid null-terminated UTF-8 strings
+/// - `keyfile_path` may be null (no keyfile) or a valid null-terminated
+///   UTF-8 path to the keyfile
+/// - Returns a handle that must be freed with `axiom_vault_close`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_create_with_keyfile(
+    path: *const c_char,
+    password: *const c_char,
+    keyfile_path: *const c_char,
+) -> *mut FFIVaultHandle {
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let password_zeroizing = match zeroizing_string_from_ptr(password, "password") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let keyfile = match keyfile_from_ptr(keyfile_path, "keyfile_path") {
+            Some(k) => k,
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::create_vault(
+            path_str,
+            password_zeroizing,
+            keyfile,
+        )) {
+            Ok(handle) => Box::into_raw(Box::new(handle)),
+            Err(()) => ptr::null_mut(),
+        }
+    })
 }
 
 /// Open an existing vault at the specified path with the given password.
@@ -188,19 +293,135 @@ pub unsafe extern "C" fn axiom_vault_open(
     path: *const c_char,
     password: *const c_char,
 ) -> *mut FFIVaultHandle {
-    let path_str = match str_from_ptr(path, "path") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
-    let password_zeroizing = match zeroizing_string_from_ptr(password, "password") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let password_zeroizing = match zeroizing_string_from_ptr(password, "password") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::open_vault(path_str, password_zeroizing, None)) {
+            Ok(handle) => Box::into_raw(Box::new(handle)),
+            Err(()) => ptr::null_mut(),
+        }
+    })
+}
 
-    match block_on(vault_ops::open_vault(path_str, password_zeroizing)) {
-        Ok(handle) => Box::into_raw(Box::new(handle)),
-        Err(()) => ptr::null_mut(),
-    }
+/// Open an existing vault with its password and keyfile.
+///
+/// # Safety
+/// - `path` and `password` must be valid null-terminated UTF-8 strings
+/// - `keyfile_path` may be null (no keyfile) or a valid null-terminated
+///   UTF-8 path to the keyfile
+/// - Returns a handle that must be freed with `axiom_vault_close`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_open_with_keyfile(
+    path: *const c_char,
+    password: *const c_char,
+    keyfile_path: *const c_char,
+) -> *mut FFIVaultHandle {
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let password_zeroizing = match zeroizing_string_from_ptr(password, "password") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let keyfile = match keyfile_from_ptr(keyfile_path, "keyfile_path") {
+            Some(k) => k,
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::open_vault(path_str, password_zeroizing, keyfile)) {
+            Ok(handle) => Box::into_raw(Box::new(handle)),
+            Err(()) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Open an existing vault with a wrapped key from `axiom_vault_rewrap_key`.
+///
+/// Fails with error code 14 (`WrappedKeyStale`) if the password changed
+/// since the key was wrapped; unlock with the password and re-wrap.
+///
+/// # Safety
+/// - `path` must be a valid null-terminated UTF-8 string
+/// - `wrapping_key` must point to `wrapping_key_len` readable bytes
+/// - `wrapped_key` must point to `wrapped_key_len` readable bytes
+/// - Returns a handle that must be freed with `axiom_vault_close`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_open_with_wrapped_key(
+    path: *const c_char,
+    wrapping_key: *const u8,
+    wrapping_key_len: usize,
+    wrapped_key: *const u8,
+    wrapped_key_len: usize,
+) -> *mut FFIVaultHandle {
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let wrapping_key = match bytes_from_ptr(wrapping_key, wrapping_key_len, "wrapping_key") {
+            Some(b) => Zeroizing::new(b.to_vec()),
+            None => return ptr::null_mut(),
+        };
+        let wrapped_key = match bytes_from_ptr(wrapped_key, wrapped_key_len, "wrapped_key") {
+            Some(b) => b.to_vec(),
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::open_vault_with_wrapped_key(
+            path_str,
+            wrapping_key,
+            wrapped_key,
+        )) {
+            Ok(handle) => Box::into_raw(Box::new(handle)),
+            Err(()) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Open an existing vault with a master key from an external key
+/// management system.
+///
+/// For integrators whose vaults were created with an external key; such
+/// vaults have no password. The key must be 32 bytes and not all zeros.
+///
+/// # Safety
+/// - `path` must be a valid null-terminated UTF-8 string
+/// - `key` must point to `key_len` readable bytes; the caller should wipe
+///   them after the call
+/// - Returns a handle that must be freed with `axiom_vault_close`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_open_with_key(
+    path: *const c_char,
+    key: *const u8,
+    key_len: usize,
+) -> *mut FFIVaultHandle {
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let master_key = match bytes_from_ptr(key, key_len, "key") {
+            Some(b) => Zeroizing::new(b.to_vec()),
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::open_vault_with_key(path_str, master_key)) {
+            Ok(handle) => Box::into_raw(Box::new(handle)),
+            Err(()) => ptr::null_mut(),
+        }
+    })
 }
 
 /// Close a vault and free its resources.
@@ -211,34 +432,252 @@ pub unsafe extern "C" fn axiom_vault_open(
 // SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
 #[no_mangle]
 pub unsafe extern "C" fn axiom_vault_close(handle: *mut FFIVaultHandle) -> c_int {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return -1;
-    }
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
 
-    let handle = Box::from_raw(handle);
+        let handle = Box::from_raw(handle);
 
-    // Abort any active event subscription task.
-    if let Ok(mut guard) = handle.event_task.lock() {
-        if let Some(task) = guard.take() {
-            task.abort();
+        // Abort any active event subscription task.
+        if let Ok(mut guard) = handle.event_task.lock() {
+            if let Some(task) = guard.take() {
+                task.abort();
+            }
         }
+
+        let runtime = match get_runtime() {
+            Ok(rt) => rt,
+            Err(e) => {
+                error::set_last_error(FFIError::RuntimeError(e.to_string()));
+                return -1;
+            }
+        };
+
+        // Close the vault through AppService so the index is wiped.
+        if let Err(e) = runtime.block_on(handle.service.close_vault()) {
+            // Log but don't fail — the handle is being freed regardless.
+            tracing::warn!("Error closing vault: {}", e);
+        }
+        0
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Asynchronous unlock
+// ---------------------------------------------------------------------------
+
+/// Caller context handed back to an [`FFIVaultCallback`].
+struct UserData(*mut c_void);
+
+// SAFETY: the pointer is opaque to us and only passed back to the caller's
+// callback, which is documented to run on a background thread.
+unsafe impl Send for UserData {}
+
+impl UserData {
+    fn get(&self) -> *mut c_void {
+        self.0
     }
+}
 
-    let runtime = match get_runtime() {
-        Ok(rt) => rt,
+/// Run `work` on the runtime's blocking pool and report its outcome through
+/// `callback` exactly once.
+///
+/// The outcome is decided by whichever comes first: `work` finishing or
+/// `cancelled` turning true. Work that has not started when cancellation
+/// arrives is skipped. Work that is already running, such as an Argon2id
+/// derivation, cannot be interrupted; it is abandoned, finishes in the
+/// background, and its result is dropped. The callback itself runs on the
+/// blocking pool so it may call back into the synchronous FFI functions.
+fn spawn_vault_task<W>(
+    mut cancelled: watch::Receiver<bool>,
+    work: W,
+    callback: FFIVaultCallback,
+    user_data: *mut c_void,
+) -> FFIResult<()>
+where
+    W: FnOnce() -> FFIResult<FFIVaultHandle> + Send + 'static,
+{
+    let runtime = get_runtime().map_err(FFIError::RuntimeError)?;
+    let user_data = UserData(user_data);
+    let skip = cancelled.clone();
+
+    runtime.spawn(async move {
+        let mut task = tokio::task::spawn_blocking(move || {
+            if *skip.borrow() {
+                return None;
+            }
+            Some(work())
+        });
+        let cancelled_error = || FFIError::Cancelled("operation cancelled".to_string());
+        let outcome = tokio::select! {
+            biased;
+            Ok(_) = cancelled.wait_for(|c| *c) => Err(cancelled_error()),
+            joined = &mut task => match joined {
+                Ok(Some(result)) => result,
+                Ok(None) => Err(cancelled_error()),
+                Err(e) => Err(FFIError::RuntimeError(e.to_string())),
+            },
+        };
+
+        let deliver = tokio::task::spawn_blocking(move || match outcome {
+            Ok(handle) => callback(
+                Box::into_raw(Box::new(handle)),
+                ptr::null(),
+                user_data.get(),
+            ),
+            Err(e) => {
+                let json = CString::new(e.to_json()).unwrap_or_default();
+                callback(ptr::null_mut(), json.as_ptr(), user_data.get());
+            }
+        });
+        if let Err(e) = deliver.await {
+            tracing::error!("Vault callback failed: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Start `work` and return its cancellation token, or null after setting
+/// the FFI error if the runtime is unavailable.
+fn start_vault_task<W>(
+    work: W,
+    callback: FFIVaultCallback,
+    user_data: *mut c_void,
+) -> *mut FFICancelToken
+where
+    W: FnOnce() -> FFIResult<FFIVaultHandle> + Send + 'static,
+{
+    let (cancel, cancelled) = watch::channel(false);
+    match spawn_vault_task(cancelled, work, callback, user_data) {
+        Ok(()) => Box::into_raw(Box::new(FFICancelToken { cancel })),
         Err(e) => {
-            error::set_last_error(FFIError::RuntimeError(e.to_string()));
+            error::set_last_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Open an existing vault without blocking the calling thread.
+///
+/// Key derivation and the open run on a background thread. `callback` is
+/// invoked exactly once with the new handle, or with an error JSON object
+/// on failure or cancellation (see [`FFIVaultCallback`]). Cancelling with
+/// [`axiom_cancel`] reports code 11 promptly, even mid-derivation.
+///
+/// # Safety
+/// - `path` and `password` must be valid null-terminated UTF-8 strings;
+///   they are copied before this function returns
+/// - `user_data` is passed to `callback` unchanged and must be usable
+///   from any thread
+/// - Returns a token that must be freed with `axiom_cancel_token_free`
+/// - On invalid arguments, returns null, sets the error retrievable via
+///   `axiom_last_error`, and never invokes `callback`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_open_async(
+    path: *const c_char,
+    password: *const c_char,
+    callback: FFIVaultCallback,
+    user_data: *mut c_void,
+) -> *mut FFICancelToken {
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s.to_owned(),
+            None => return ptr::null_mut(),
+        };
+        let password_zeroizing = match zeroizing_string_from_ptr(password, "password") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+
+        start_vault_task(
+            move || {
+                tokio::runtime::Handle::current().block_on(vault_ops::open_vault(
+                    &path_str,
+                    password_zeroizing,
+                    None,
+                ))
+            },
+            callback,
+            user_data,
+        )
+    })
+}
+
+/// Create a new vault without blocking the calling thread.
+///
+/// Same contract as [`axiom_vault_open_async`]. A create cancelled after
+/// key derivation finished may still complete in the background and leave
+/// the new vault on disk.
+///
+/// # Safety
+/// Same as [`axiom_vault_open_async`].
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_create_async(
+    path: *const c_char,
+    password: *const c_char,
+    callback: FFIVaultCallback,
+    user_data: *mut c_void,
+) -> *mut FFICancelToken {
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s.to_owned(),
+            None => return ptr::null_mut(),
+        };
+        let password_zeroizing = match zeroizing_string_from_ptr(password, "password") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+
+        start_vault_task(
+            move || {
+                tokio::runtime::Handle::current().block_on(vault_ops::create_vault(
+                    &path_str,
+                    password_zeroizing,
+                    None,
+                ))
+            },
+            callback,
+            user_data,
+        )
+    })
+}
+
+/// Cancel the operation behind `token`. Has no effect once its callback
+/// has been invoked.
+///
+/// # Safety
+/// - `token` must be a valid token that has not been freed
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_cancel(token: *const FFICancelToken) -> c_int {
+    guard(-1, || {
+        if token.is_null() {
+            error::set_last_error(FFIError::NullPointer("token is null".into()));
             return -1;
         }
-    };
+        (*token).cancel.send_replace(true);
+        0
+    })
+}
 
-    // Close the vault through AppService so the index is wiped.
-    if let Err(e) = runtime.block_on(handle.service.close_vault()) {
-        // Log but don't fail — the handle is being freed regardless.
-        tracing::warn!("Error closing vault: {}", e);
-    }
-    0
+/// Free a cancellation token. Freeing does not cancel: the operation runs
+/// on and its callback is still invoked.
+///
+/// # Safety
+/// - `token` must be null or a token returned by an async FFI function
+/// - After this call, the token is invalid
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_cancel_token_free(token: *mut FFICancelToken) {
+    guard((), || {
+        if !token.is_null() {
+            drop(Box::from_raw(token));
+        }
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -253,18 +692,20 @@ pub unsafe extern "C" fn axiom_vault_close(handle: *mut FFIVaultHandle) -> c_int
 // SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
 #[no_mangle]
 pub unsafe extern "C" fn axiom_vault_info(handle: *const FFIVaultHandle) -> *mut FFIVaultInfo {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return ptr::null_mut();
-    }
+    guard(ptr::null_mut(), || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return ptr::null_mut();
+        }
 
-    match vault_ops::get_vault_info(&*handle) {
-        Ok(info) => Box::into_raw(Box::new(info)),
-        Err(e) => {
-            error::set_last_error(e);
-            ptr::null_mut()
+        match vault_ops::get_vault_info(&*handle) {
+            Ok(info) => Box::into_raw(Box::new(info)),
+            Err(e) => {
+                error::set_last_error(e);
+                ptr::null_mut()
+            }
         }
-    }
+    })
 }
 
 /// Free vault info structure.
@@ -274,15 +715,23 @@ pub unsafe extern "C" fn axiom_vault_info(handle: *const FFIVaultHandle) -> *mut
 // SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
 #[no_mangle]
 pub unsafe extern "C" fn axiom_vault_info_free(info: *mut FFIVaultInfo) {
-    if !info.is_null() {
-        let info = Box::from_raw(info);
-        if !info.vault_id.is_null() {
-            let _ = CString::from_raw(info.vault_id as *mut c_char);
-        }
-        if !info.root_path.is_null() {
-            let _ = CString::from_raw(info.root_path as *mut c_char);
+    guard((), || {
+        if !info.is_null() {
+            let info = Box::from_raw(info);
+            for field in [
+                info.vault_id,
+                info.root_path,
+                info.display_name,
+                info.description,
+                info.icon,
+                info.color,
+            ] {
+                if !field.is_null() {
+                    let _ = CString::from_raw(field as *mut c_char);
+                }
+            }
         }
-    }
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -302,24 +751,68 @@ pub unsafe extern "C" fn axiom_vault_list(
     handle: *const FFIVaultHandle,
     path: *const c_char,
 ) -> *mut c_char {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return ptr::null_mut();
-    }
-    let path_str = match str_from_ptr(path, "path") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
+    guard(ptr::null_mut(), || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return ptr::null_mut();
+        }
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
 
-    match block_on(vault_ops::list_vault(&*handle, path_str)) {
-        Ok(json) => CString::new(json)
-            .map(|s| s.into_raw())
-            .unwrap_or_else(|_| {
-                error::set_last_error(FFIError::StringConversionError);
-                ptr::null_mut()
-            }),
-        Err(()) => ptr::null_mut(),
-    }
+        match block_on(vault_ops::list_vault(&*handle, path_str)) {
+            Ok(json) => CString::new(json)
+                .map(|s| s.into_raw())
+                .unwrap_or_else(|_| {
+                    error::set_last_error(FFIError::StringConversionError);
+                    ptr::null_mut()
+                }),
+            Err(()) => ptr::null_mut(),
+        }
+    })
+}
+
+/// List one page of files in the vault at the specified path.
+///
+/// Entries are sorted by name. The JSON object has `entries` (at most
+/// `limit`, starting at `offset`) and `has_more`.
+///
+/// # Safety
+/// - `handle` must be a valid vault handle
+/// - `path` must be a valid null-terminated UTF-8 string (use "/" for root)
+/// - `limit` must be at least 1
+/// - Returned string must be freed with `axiom_string_free`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_list_page(
+    handle: *const FFIVaultHandle,
+    path: *const c_char,
+    offset: usize,
+    limit: usize,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return ptr::null_mut();
+        }
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::list_vault_page(
+            &*handle, path_str, offset, limit,
+        )) {
+            Ok(json) => CString::new(json)
+                .map(|s| s.into_raw())
+                .unwrap_or_else(|_| {
+                    error::set_last_error(FFIError::StringConversionError);
+                    ptr::null_mut()
+                }),
+            Err(()) => ptr::null_mut(),
+        }
+    })
 }
 
 /// Add a file to the vault.
@@ -335,23 +828,25 @@ pub unsafe extern "C" fn axiom_vault_add_file(
     local_path: *const c_char,
     vault_path: *const c_char,
 ) -> c_int {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return -1;
-    }
-    let local_str = match str_from_ptr(local_path, "local_path") {
-        Some(s) => s,
-        None => return -1,
-    };
-    let vault_str = match str_from_ptr(vault_path, "vault_path") {
-        Some(s) => s,
-        None => return -1,
-    };
-
-    match block_on(vault_ops::add_file(&*handle, local_str, vault_str)) {
-        Ok(()) => 0,
-        Err(()) => -1,
-    }
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
+        let local_str = match str_from_ptr(local_path, "local_path") {
+            Some(s) => s,
+            None => return -1,
+        };
+        let vault_str = match str_from_ptr(vault_path, "vault_path") {
+            Some(s) => s,
+            None => return -1,
+        };
+
+        match block_on(vault_ops::add_file(&*handle, local_str, vault_str)) {
+            Ok(()) => 0,
+            Err(()) => -1,
+        }
+    })
 }
 
 /// Extract a file from the vault.
@@ -367,23 +862,25 @@ pub unsafe extern "C" fn axiom_vault_extract_file(
     vault_path: *const c_char,
     local_path: *const c_char,
 ) -> c_int {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return -1;
-    }
-    let vault_str = match str_from_ptr(vault_path, "vault_path") {
-        Some(s) => s,
-        None => return -1,
-    };
-    let local_str = match str_from_ptr(local_path, "local_path") {
-        Some(s) => s,
-        None => return -1,
-    };
-
-    match block_on(vault_ops::extract_file(&*handle, vault_str, local_str)) {
-        Ok(()) => 0,
-        Err(()) => -1,
-    }
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
+        let vault_str = match str_from_ptr(vault_path, "vault_path") {
+            Some(s) => s,
+            None => return -1,
+        };
+        let local_str = match str_from_ptr(local_path, "local_path") {
+            Some(s) => s,
+            None => return -1,
+        };
+
+        match block_on(vault_ops::extract_file(&*handle, vault_str, local_str)) {
+            Ok(()) => 0,
+            Err(()) => -1,
+        }
+    })
 }
 
 /// Create a directory in the vault.
@@ -397,19 +894,21 @@ pub unsafe extern "C" fn axiom_vault_mkdir(
     handle: *const FFIVaultHandle,
     vault_path: *const c_char,
 ) -> c_int {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return -1;
-    }
-    let vault_str = match str_from_ptr(vault_path, "vault_path") {
-        Some(s) => s,
-        None => return -1,
-    };
-
-    match block_on(vault_ops::create_directory(&*handle, vault_str)) {
-        Ok(()) => 0,
-        Err(()) => -1,
-    }
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
+        let vault_str = match str_from_ptr(vault_path, "vault_path") {
+            Some(s) => s,
+            None => return -1,
+        };
+
+        match block_on(vault_ops::create_directory(&*handle, vault_str)) {
+            Ok(()) => 0,
+            Err(()) => -1,
+        }
+    })
 }
 
 /// Remove a file or directory from the vault.
@@ -423,19 +922,21 @@ pub unsafe extern "C" fn axiom_vault_remove(
     handle: *const FFIVaultHandle,
     vault_path: *const c_char,
 ) -> c_int {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return -1;
-    }
-    let vault_str = match str_from_ptr(vault_path, "vault_path") {
-        Some(s) => s,
-        None => return -1,
-    };
-
-    match block_on(vault_ops::remove_entry(&*handle, vault_str)) {
-        Ok(()) => 0,
-        Err(()) => -1,
-    }
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
+        let vault_str = match str_from_ptr(vault_path, "vault_path") {
+            Some(s) => s,
+            None => return -1,
+        };
+
+        match block_on(vault_ops::remove_entry(&*handle, vault_str)) {
+            Ok(()) => 0,
+            Err(()) => -1,
+        }
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -454,23 +955,133 @@ pub unsafe extern "C" fn axiom_vault_change_password(
     old_password: *const c_char,
     new_password: *const c_char,
 ) -> c_int {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return -1;
-    }
-    let old_pw = match zeroizing_string_from_ptr(old_password, "old_password") {
-        Some(s) => s,
-        None => return -1,
-    };
-    let new_pw = match zeroizing_string_from_ptr(new_password, "new_password") {
-        Some(s) => s,
-        None => return -1,
-    };
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
+        let old_pw = match zeroizing_string_from_ptr(old_password, "old_password") {
+            Some(s) => s,
+            None => return -1,
+        };
+        let new_pw = match zeroizing_string_from_ptr(new_password, "new_password") {
+            Some(s) => s,
+            None => return -1,
+        };
+
+        match block_on(vault_ops::change_password(
+            &*handle, old_pw, None, new_pw, None,
+        )) {
+            Ok(()) => 0,
+            Err(()) => -1,
+        }
+    })
+}
 
-    match block_on(vault_ops::change_password(&*handle, old_pw, new_pw)) {
-        Ok(()) => 0,
-        Err(()) => -1,
-    }
+/// Change the vault password and keyfile.
+///
+/// # Safety
+/// - `handle` must be a valid vault handle
+/// - `old_password` and `new_password` must be valid null-terminated UTF-8 strings
+/// - `old_keyfile_path` / `new_keyfile_path` may be null (no keyfile) or
+///   valid null-terminated UTF-8 paths
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_change_password_with_keyfile(
+    handle: *const FFIVaultHandle,
+    old_password: *const c_char,
+    old_keyfile_path: *const c_char,
+    new_password: *const c_char,
+    new_keyfile_path: *const c_char,
+) -> c_int {
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
+        let old_pw = match zeroizing_string_from_ptr(old_password, "old_password") {
+            Some(s) => s,
+            None => return -1,
+        };
+        let old_kf = match keyfile_from_ptr(old_keyfile_path, "old_keyfile_path") {
+            Some(k) => k,
+            None => return -1,
+        };
+        let new_pw = match zeroizing_string_from_ptr(new_password, "new_password") {
+            Some(s) => s,
+            None => return -1,
+        };
+        let new_kf = match keyfile_from_ptr(new_keyfile_path, "new_keyfile_path") {
+            Some(k) => k,
+            None => return -1,
+        };
+
+        match block_on(vault_ops::change_password(
+            &*handle, old_pw, old_kf, new_pw, new_kf,
+        )) {
+            Ok(()) => 0,
+            Err(()) => -1,
+        }
+    })
+}
+
+/// Wrap the master key with a device-held key for the current key
+/// generation.
+///
+/// Call after unlocking with the password — in particular right after a
+/// successful password change — and replace the blob in the keystore.
+///
+/// # Safety
+/// - `handle` must be a valid vault handle
+/// - `wrapping_key` must point to `len` readable bytes
+/// - Returns a pointer that must be freed with `axiom_wrapped_key_free`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_rewrap_key(
+    handle: *const FFIVaultHandle,
+    wrapping_key: *const u8,
+    len: usize,
+) -> *mut FFIWrappedKey {
+    guard(ptr::null_mut(), || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return ptr::null_mut();
+        }
+        let wrapping_key = match bytes_from_ptr(wrapping_key, len, "wrapping_key") {
+            Some(b) => Zeroizing::new(b.to_vec()),
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::rewrap_key(&*handle, wrapping_key)) {
+            Ok(blob) => {
+                let blob = blob.into_boxed_slice();
+                let len = blob.len();
+                let data = Box::into_raw(blob) as *mut u8;
+                Box::into_raw(Box::new(FFIWrappedKey { data, len }))
+            }
+            Err(()) => ptr::null_mut(),
+        }
+    })
+}
+
+/// Free a wrapped key returned by `axiom_vault_rewrap_key`, wiping it first.
+///
+/// # Safety
+/// - `key` must be null or a pointer returned by `axiom_vault_rewrap_key`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_wrapped_key_free(key: *mut FFIWrappedKey) {
+    guard((), || {
+        if key.is_null() {
+            return;
+        }
+        let key = Box::from_raw(key);
+        if !key.data.is_null() {
+            use zeroize::Zeroize;
+            let mut blob = Box::from_raw(std::ptr::slice_from_raw_parts_mut(key.data, key.len));
+            blob.zeroize();
+        }
+    })
 }
 
 /// Get the recovery words from a newly created vault.
@@ -492,27 +1103,29 @@ pub unsafe extern "C" fn axiom_vault_change_password(
 pub unsafe extern "C" fn axiom_vault_get_recovery_words(
     handle: *const FFIVaultHandle,
 ) -> *mut c_char {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return ptr::null_mut();
-    }
-
-    let words = (*handle)
-        .recovery_words
-        .lock()
-        .ok()
-        .and_then(|mut guard| guard.take());
+    guard(ptr::null_mut(), || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return ptr::null_mut();
+        }
 
-    match words {
-        Some(w) => match into_secret_cstr(w) {
-            Ok(ptr) => ptr,
-            Err(e) => {
-                error::set_last_error(e);
-                ptr::null_mut()
-            }
-        },
-        None => ptr::null_mut(),
-    }
+        let words = (*handle)
+            .recovery_words
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take());
+
+        match words {
+            Some(w) => match into_secret_cstr(w) {
+                Ok(ptr) => ptr,
+                Err(e) => {
+                    error::set_last_error(e);
+                    ptr::null_mut()
+                }
+            },
+            None => ptr::null_mut(),
+        }
+    })
 }
 
 /// Show recovery key for an open vault (requires active session).
@@ -529,21 +1142,23 @@ pub unsafe extern "C" fn axiom_vault_get_recovery_words(
 pub unsafe extern "C" fn axiom_vault_show_recovery_key(
     handle: *const FFIVaultHandle,
 ) -> *mut c_char {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return ptr::null_mut();
-    }
+    guard(ptr::null_mut(), || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return ptr::null_mut();
+        }
 
-    match block_on(vault_ops::show_recovery_key(&*handle)) {
-        Ok(words) => match into_secret_cstr(words) {
-            Ok(ptr) => ptr,
-            Err(e) => {
-                error::set_last_error(e);
-                ptr::null_mut()
-            }
-        },
-        Err(()) => ptr::null_mut(),
-    }
+        match block_on(vault_ops::show_recovery_key(&*handle)) {
+            Ok(words) => match into_secret_cstr(words) {
+                Ok(ptr) => ptr,
+                Err(e) => {
+                    error::set_last_error(e);
+                    ptr::null_mut()
+                }
+            },
+            Err(()) => ptr::null_mut(),
+        }
+    })
 }
 
 /// Reset the vault password using recovery key words.
@@ -560,27 +1175,29 @@ pub unsafe extern "C" fn axiom_vault_reset_password(
     recovery_words: *const c_char,
     new_password: *const c_char,
 ) -> *mut FFIVaultHandle {
-    let path_str = match str_from_ptr(path, "path") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
-    let words_zeroizing = match zeroizing_string_from_ptr(recovery_words, "recovery_words") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
-    let password_zeroizing = match zeroizing_string_from_ptr(new_password, "new_password") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
-
-    match block_on(vault_ops::reset_password(
-        path_str,
-        words_zeroizing,
-        password_zeroizing,
-    )) {
-        Ok(handle) => Box::into_raw(Box::new(handle)),
-        Err(()) => ptr::null_mut(),
-    }
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let words_zeroizing = match zeroizing_string_from_ptr(recovery_words, "recovery_words") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+        let password_zeroizing = match zeroizing_string_from_ptr(new_password, "new_password") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::reset_password(
+            path_str,
+            words_zeroizing,
+            password_zeroizing,
+        )) {
+            Ok(handle) => Box::into_raw(Box::new(handle)),
+            Err(()) => ptr::null_mut(),
+        }
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -599,18 +1216,20 @@ pub unsafe extern "C" fn axiom_vault_reset_password(
 // SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
 #[no_mangle]
 pub unsafe extern "C" fn axiom_vault_check_migration(path: *const c_char) -> c_int {
-    let path_str = match str_from_ptr(path, "path") {
-        Some(s) => s,
-        None => return -1,
-    };
-
-    match vault_ops::check_migration(path_str) {
-        Ok(status) => status,
-        Err(e) => {
-            error::set_last_error(e);
-            -1
+    guard(-1, || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return -1,
+        };
+
+        match vault_ops::check_migration(path_str) {
+            Ok(status) => status,
+            Err(e) => {
+                error::set_last_error(e);
+                -1
+            }
         }
-    }
+    })
 }
 
 /// Run migrations on a vault at the given path.
@@ -628,22 +1247,24 @@ pub unsafe extern "C" fn axiom_vault_migrate(
     path: *const c_char,
     password: *const c_char,
 ) -> c_int {
-    let path_str = match str_from_ptr(path, "path") {
-        Some(s) => s,
-        None => return -1,
-    };
-    let password_str = match str_from_ptr(password, "password") {
-        Some(s) => s,
-        None => return -1,
-    };
-
-    match vault_ops::run_migration(path_str, password_str) {
-        Ok(()) => 0,
-        Err(e) => {
-            error::set_last_error(e);
-            -1
+    guard(-1, || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return -1,
+        };
+        let password_str = match str_from_ptr(password, "password") {
+            Some(s) => s,
+            None => return -1,
+        };
+
+        match vault_ops::run_migration(path_str, password_str) {
+            Ok(()) => 0,
+            Err(e) => {
+                error::set_last_error(e);
+                -1
+            }
         }
-    }
+    })
 }
 
 /// Run a vault health check and return results as JSON.
@@ -658,32 +1279,60 @@ pub unsafe extern "C" fn axiom_vault_health_check(
     path: *const c_char,
     password: *const c_char,
 ) -> *mut c_char {
-    let path_str = match str_from_ptr(path, "path") {
-        Some(s) => s,
-        None => return ptr::null_mut(),
-    };
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
 
-    let password_opt = if password.is_null() {
-        None
-    } else {
-        match CStr::from_ptr(password).to_str() {
-            Ok(s) => Some(s),
-            Err(_) => {
-                error::set_last_error(FFIError::InvalidUtf8("password".into()));
-                return ptr::null_mut();
+        let password_opt = if password.is_null() {
+            None
+        } else {
+            match CStr::from_ptr(password).to_str() {
+                Ok(s) => Some(s),
+                Err(_) => {
+                    error::set_last_error(FFIError::InvalidUtf8("password".into()));
+                    return ptr::null_mut();
+                }
             }
+        };
+
+        match block_on(vault_ops::health_check(path_str, password_opt)) {
+            Ok(json) => CString::new(json)
+                .map(|s| s.into_raw())
+                .unwrap_or_else(|_| {
+                    error::set_last_error(FFIError::StringConversionError);
+                    ptr::null_mut()
+                }),
+            Err(()) => ptr::null_mut(),
         }
-    };
+    })
+}
 
-    match block_on(vault_ops::health_check(path_str, password_opt)) {
-        Ok(json) => CString::new(json)
-            .map(|s| s.into_raw())
-            .unwrap_or_else(|_| {
-                error::set_last_error(FFIError::StringConversionError);
-                ptr::null_mut()
-            }),
-        Err(()) => ptr::null_mut(),
-    }
+/// Summarize a vault from its config without unlocking it. Returns JSON.
+///
+/// # Safety
+/// - `path` must be a valid null-terminated UTF-8 string
+/// - Returned string must be freed with `axiom_string_free`
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_vault_inspect(path: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let path_str = match str_from_ptr(path, "path") {
+            Some(s) => s,
+            None => return ptr::null_mut(),
+        };
+
+        match block_on(vault_ops::inspect_vault(path_str)) {
+            Ok(json) => CString::new(json)
+                .map(|s| s.into_raw())
+                .unwrap_or_else(|_| {
+                    error::set_last_error(FFIError::StringConversionError);
+                    ptr::null_mut()
+                }),
+            Err(()) => ptr::null_mut(),
+        }
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -707,58 +1356,99 @@ pub unsafe extern "C" fn axiom_vault_subscribe_events(
     handle: *const FFIVaultHandle,
     callback: Option<FFIEventCallback>,
 ) -> c_int {
-    if handle.is_null() {
-        error::set_last_error(FFIError::NullPointer("handle is null".into()));
-        return -1;
-    }
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
 
-    let handle = &*handle;
+        let handle = &*handle;
 
-    // Abort any existing subscription task.
-    if let Ok(mut guard) = handle.event_task.lock() {
-        if let Some(task) = guard.take() {
-            task.abort();
+        // Abort any existing subscription task.
+        if let Ok(mut guard) = handle.event_task.lock() {
+            if let Some(task) = guard.take() {
+                task.abort();
+            }
         }
-    }
 
-    // If no callback, we just unsubscribed — done.
-    let callback = match callback {
-        Some(cb) => cb,
-        None => return 0,
-    };
+        // If no callback, we just unsubscribed — done.
+        let callback = match callback {
+            Some(cb) => cb,
+            None => return 0,
+        };
 
-    let mut rx = handle.service.subscribe();
+        let mut rx = handle.service.subscribe();
 
-    let runtime = match get_runtime() {
-        Ok(rt) => rt,
-        Err(e) => {
-            error::set_last_error(FFIError::RuntimeError(e.to_string()));
-            return -1;
-        }
-    };
-
-    let task = runtime.spawn(async move {
-        loop {
-            match rx.recv().await {
-                Ok(event) => {
-                    if let Ok(json) = serde_json::to_string(&event) {
-                        if let Ok(cstr) = CString::new(json) {
-                            callback(cstr.as_ptr());
+        let runtime = match get_runtime() {
+            Ok(rt) => rt,
+            Err(e) => {
+                error::set_last_error(FFIError::RuntimeError(e.to_string()));
+                return -1;
+            }
+        };
+
+        let task = runtime.spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        if let Ok(json) = serde_json::to_string(&event) {
+                            if let Ok(cstr) = CString::new(json) {
+                                callback(cstr.as_ptr());
+                            }
                         }
                     }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
                 }
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
             }
+        });
+
+        // Store the task handle so it can be aborted on re-subscribe or close.
+        if let Ok(mut guard) = handle.event_task.lock() {
+            *guard = Some(task);
         }
-    });
 
-    // Store the task handle so it can be aborted on re-subscribe or close.
-    if let Ok(mut guard) = handle.event_task.lock() {
-        *guard = Some(task);
-    }
+        0
+    })
+}
 
-    0
+// ---------------------------------------------------------------------------
+// Sync
+// ---------------------------------------------------------------------------
+
+/// Report the device's connectivity and power state to the sync scheduler.
+///
+/// `json` is an object like `{"metered": true, "on_power": false}`; missing
+/// fields count as unmetered and on power. Periodic syncs configured to
+/// require an unmetered network or external power follow the latest state.
+///
+/// # Safety
+/// - `handle` must be a valid vault handle
+/// - `json` must be a valid null-terminated UTF-8 string
+// SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
+#[no_mangle]
+pub unsafe extern "C" fn axiom_sync_set_environment(
+    handle: *const FFIVaultHandle,
+    json: *const c_char,
+) -> c_int {
+    guard(-1, || {
+        if handle.is_null() {
+            error::set_last_error(FFIError::NullPointer("handle is null".into()));
+            return -1;
+        }
+        let json = match str_from_ptr(json, "json") {
+            Some(s) => s,
+            None => return -1,
+        };
+
+        match vault_ops::set_sync_environment(&*handle, json) {
+            Ok(()) => 0,
+            Err(e) => {
+                error::set_last_error(e);
+                -1
+            }
+        }
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -773,13 +1463,28 @@ pub unsafe extern "C" fn axiom_vault_subscribe_events(
 /// - Returns null if no error occurred
 #[no_mangle]
 pub extern "C" fn axiom_last_error() -> *mut c_char {
-    error::take_last_error()
-        .map(|e| {
-            CString::new(e.to_string())
-                .map(|s| s.into_raw())
-                .unwrap_or(ptr::null_mut())
-        })
-        .unwrap_or(ptr::null_mut())
+    guard(ptr::null_mut(), || {
+        error::take_last_error()
+            .map(|e| {
+                CString::new(e.to_string())
+                    .map(|s| s.into_raw())
+                    .unwrap_or(ptr::null_mut())
+            })
+            .unwrap_or(ptr::null_mut())
+    })
+}
+
+/// Get the code of the last error without clearing it.
+///
+/// Call before [`axiom_last_error`], which consumes the error. Returns `0`
+/// if no error occurred. Codes are stable across releases: 1 null pointer,
+/// 2 invalid UTF-8, 3 runtime, 4 vault, 5 storage, 6 crypto, 7 string
+/// conversion, 8 I/O, 9 conflict, 10 unsupported, 11 cancelled,
+/// 12 rate limited, 13 integrity, 14 stale wrapped key, 15 quota
+/// exhausted, 16 internal panic.
+#[no_mangle]
+pub extern "C" fn axiom_last_error_code() -> i32 {
+    guard(-1, error::last_error_code)
 }
 
 /// Free a string returned by an FFI function.
@@ -794,9 +1499,11 @@ pub extern "C" fn axiom_last_error() -> *mut c_char {
 // SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
 #[no_mangle]
 pub unsafe extern "C" fn axiom_string_free(s: *mut c_char) {
-    if !s.is_null() {
-        let _ = CString::from_raw(s);
-    }
+    guard((), || {
+        if !s.is_null() {
+            let _ = CString::from_raw(s);
+        }
+    })
 }
 
 /// Free a recovery-words string returned by [`axiom_vault_get_recovery_words`]
@@ -817,32 +1524,34 @@ pub unsafe extern "C" fn axiom_string_free(s: *mut c_char) {
 // SAFETY: see `# Safety` rustdoc above; caller upholds raw-pointer invariants.
 #[no_mangle]
 pub unsafe extern "C" fn axiom_recovery_words_free(s: *mut c_char) {
-    if s.is_null() {
-        return;
-    }
-    // Reclaim ownership of the allocation FIRST so its internal length is
-    // recovered from the (still-intact) NUL terminator. Zeroizing before
-    // `from_raw` would cause `strlen` to see length 0 and the allocator
-    // would only free 1 byte.
-    //
-    // SAFETY: The caller must ensure `s` came from a recovery-words FFI
-    // function, which allocated it via `CString::into_raw`. `from_raw` thus
-    // takes back ownership of the same allocation and won't be called twice.
-    let cstring = CString::from_raw(s);
-
-    // Convert into an owned `Vec<u8>` so the wipe operates on memory we own.
-    // Writing through a `*mut` derived from `&[u8]` (as an earlier version
-    // did via `cstring.as_bytes_with_nul().as_ptr() as *mut u8`) violates
-    // Rust's aliasing model. We could fall back to `ptr::write_bytes` plus a
-    // `compiler_fence`, but the compiler is still permitted to elide a write
-    // it considers a dead store (the bytes are dropped immediately after).
-    // `zeroize::Zeroize` is the standard primitive for exactly this case:
-    // its implementation uses volatile writes that the optimizer must not
-    // remove.
-    use zeroize::Zeroize;
-    let mut bytes = cstring.into_bytes_with_nul();
-    bytes.zeroize();
-    drop(bytes);
+    guard((), || {
+        if s.is_null() {
+            return;
+        }
+        // Reclaim ownership of the allocation FIRST so its internal length is
+        // recovered from the (still-intact) NUL terminator. Zeroizing before
+        // `from_raw` would cause `strlen` to see length 0 and the allocator
+        // would only free 1 byte.
+        //
+        // SAFETY: The caller must ensure `s` came from a recovery-words FFI
+        // function, which allocated it via `CString::into_raw`. `from_raw` thus
+        // takes back ownership of the same allocation and won't be called twice.
+        let cstring = CString::from_raw(s);
+
+        // Convert into an owned `Vec<u8>` so the wipe operates on memory we own.
+        // Writing through a `*mut` derived from `&[u8]` (as an earlier version
+        // did via `cstring.as_bytes_with_nul().as_ptr() as *mut u8`) violates
+        // Rust's aliasing model. We could fall back to `ptr::write_bytes` plus a
+        // `compiler_fence`, but the compiler is still permitted to elide a write
+        // it considers a dead store (the bytes are dropped immediately after).
+        // `zeroize::Zeroize` is the standard primitive for exactly this case:
+        // its implementation uses volatile writes that the optimizer must not
+        // remove.
+        use zeroize::Zeroize;
+        let mut bytes = cstring.into_bytes_with_nul();
+        bytes.zeroize();
+        drop(bytes);
+    })
 }
 
 #[cfg(test)]
@@ -870,6 +1579,363 @@ mod tests {
         unsafe { axiom_recovery_words_free(raw) };
     }
 
+    /// After a password change, a wrapped key from before is refused with
+    /// `WrappedKeyStale` until the client re-wraps it.
+    #[test]
+    fn wrapped_key_goes_stale_after_password_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().join("vault").to_str().unwrap()).unwrap();
+        let old_password = CString::new("old-password").unwrap();
+        let new_password = CString::new("new-password").unwrap();
+        let wrapping_key = [0x42u8; 32];
+
+        // SAFETY: all pointers below are live CStrings / arrays owned by this
+        // test, and every returned handle or buffer is freed exactly once.
+        unsafe {
+            let handle = axiom_vault_create(path.as_ptr(), old_password.as_ptr());
+            assert!(!handle.is_null());
+            let stale = axiom_vault_rewrap_key(handle, wrapping_key.as_ptr(), wrapping_key.len());
+            assert!(!stale.is_null());
+
+            assert_eq!(
+                axiom_vault_change_password(handle, old_password.as_ptr(), new_password.as_ptr()),
+                0
+            );
+            let info = axiom_vault_info(handle);
+            assert_eq!((*info).key_generation, 1);
+            // No display name was set, so the ID stands in for it.
+            assert_eq!(
+                std::ffi::CStr::from_ptr((*info).display_name),
+                std::ffi::CStr::from_ptr((*info).vault_id)
+            );
+            assert!((*info).description.is_null());
+            axiom_vault_info_free(info);
+            assert_eq!(axiom_vault_close(handle), 0);
+
+            let refused = axiom_vault_open_with_wrapped_key(
+                path.as_ptr(),
+                wrapping_key.as_ptr(),
+                wrapping_key.len(),
+                (*stale).data,
+                (*stale).len,
+            );
+            assert!(refused.is_null());
+            assert_eq!(error::last_error_code(), 14);
+            let _ = error::take_last_error();
+            axiom_wrapped_key_free(stale);
+
+            let handle = axiom_vault_open(path.as_ptr(), new_password.as_ptr());
+            assert!(!handle.is_null());
+            let fresh = axiom_vault_rewrap_key(handle, wrapping_key.as_ptr(), wrapping_key.len());
+            assert!(!fresh.is_null());
+            assert_eq!(axiom_vault_close(handle), 0);
+
+            let handle = axiom_vault_open_with_wrapped_key(
+                path.as_ptr(),
+                wrapping_key.as_ptr(),
+                wrapping_key.len(),
+                (*fresh).data,
+                (*fresh).len,
+            );
+            assert!(!handle.is_null());
+            let info = axiom_vault_info(handle);
+            assert_eq!((*info).key_generation, 1);
+            axiom_vault_info_free(info);
+            assert_eq!(axiom_vault_close(handle), 0);
+            axiom_wrapped_key_free(fresh);
+        }
+    }
+
+    #[test]
+    fn open_with_external_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("vault");
+        std::fs::create_dir_all(&root).unwrap();
+        let path = CString::new(root.to_str().unwrap()).unwrap();
+        let key = [0x5au8; 32];
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            axiomvault_vault::VaultManager::new()
+                .create_vault_with_key(
+                    axiomvault_common::VaultId::new("byok").unwrap(),
+                    axiomvault_crypto::MasterKey::from_external(&key).unwrap(),
+                    "local",
+                    serde_json::json!({ "root": root.to_str().unwrap() }),
+                )
+                .await
+                .unwrap();
+        });
+
+        // SAFETY: all pointers below are live CStrings / arrays owned by this
+        // test, and every returned handle is freed exactly once.
+        unsafe {
+            let wrong = [0x11u8; 32];
+            assert!(
+                axiom_vault_open_with_key(path.as_ptr(), wrong.as_ptr(), wrong.len()).is_null()
+            );
+            let _ = error::take_last_error();
+            let zeros = [0u8; 32];
+            assert!(
+                axiom_vault_open_with_key(path.as_ptr(), zeros.as_ptr(), zeros.len()).is_null()
+            );
+            let _ = error::take_last_error();
+            assert!(axiom_vault_open_with_key(path.as_ptr(), key.as_ptr(), 16).is_null());
+            let _ = error::take_last_error();
+
+            let handle = axiom_vault_open_with_key(path.as_ptr(), key.as_ptr(), key.len());
+            assert!(!handle.is_null());
+            assert_eq!(axiom_vault_close(handle), 0);
+        }
+    }
+
+    /// Outcome of an async vault call: the handle address, or the error code.
+    type Outcome = Result<usize, i64>;
+
+    extern "C" fn record_outcome(
+        handle: *mut FFIVaultHandle,
+        error_json: *const c_char,
+        user_data: *mut c_void,
+    ) {
+        // SAFETY: every test passes a `Sender<Outcome>` that outlives the
+        // callback, and `error_json` is a valid C string when non-null.
+        let tx = unsafe { &*(user_data as *const std::sync::mpsc::Sender<Outcome>) };
+        let outcome = if handle.is_null() {
+            let json = unsafe { CStr::from_ptr(error_json) }.to_str().unwrap();
+            let value: serde_json::Value = serde_json::from_str(json).unwrap();
+            Err(value["code"].as_i64().unwrap())
+        } else {
+            Ok(handle as usize)
+        };
+        tx.send(outcome).unwrap();
+    }
+
+    fn outcome_channel() -> (
+        Box<std::sync::mpsc::Sender<Outcome>>,
+        std::sync::mpsc::Receiver<Outcome>,
+    ) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        (Box::new(tx), rx)
+    }
+
+    fn user_data(tx: &std::sync::mpsc::Sender<Outcome>) -> *mut c_void {
+        tx as *const _ as *mut c_void
+    }
+
+    fn bare_handle() -> FFIVaultHandle {
+        FFIVaultHandle {
+            service: axiomvault_app::AppService::new(),
+            path: String::new(),
+            recovery_words: std::sync::Mutex::new(None),
+            event_task: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Pushed sync environment JSON reaches the service's probe.
+    #[test]
+    fn sync_environment_is_pushed_into_the_core() {
+        let handle = bare_handle();
+        let probe = handle.service.sync_environment();
+        let update = CString::new(r#"{"metered": true}"#).unwrap();
+        let invalid = CString::new("metered").unwrap();
+
+        // SAFETY: `handle` and the CStrings are live for the whole block.
+        unsafe {
+            assert_eq!(axiom_sync_set_environment(&handle, update.as_ptr()), 0);
+            assert_eq!(axiom_sync_set_environment(&handle, invalid.as_ptr()), -1);
+            assert!(error::take_last_error().is_some());
+            assert_eq!(axiom_sync_set_environment(ptr::null(), update.as_ptr()), -1);
+            let _ = error::take_last_error();
+        }
+        let environment = probe.environment();
+        assert!(environment.metered);
+        assert!(environment.on_power);
+    }
+
+    /// A panic inside an entry point body becomes error code 16.
+    #[test]
+    fn guard_turns_panics_into_errors() {
+        assert_eq!(guard(-1, || panic!("boom")), -1);
+        assert_eq!(axiom_last_error_code(), 16);
+        let message = error::take_last_error().unwrap().to_string();
+        assert!(message.contains("boom"), "{message}");
+
+        assert_eq!(guard(-1, || 7), 7);
+        assert_eq!(axiom_last_error_code(), 0);
+    }
+
+    /// Null pointers, invalid UTF-8 and garbage JSON come back as errors
+    /// rather than crashing the caller.
+    #[test]
+    fn entry_points_reject_adversarial_input() {
+        let handle = bare_handle();
+        let invalid_utf8 = CString::new(vec![0xff, 0xfe, b'a']).unwrap();
+        let garbage = CString::new("{not json").unwrap();
+        let path = CString::new("/does/not/exist").unwrap();
+        let null = ptr::null::<c_char>();
+
+        // SAFETY: every non-null pointer is a live CString or the local
+        // handle; null pointers are what the functions must tolerate.
+        unsafe {
+            assert!(axiom_vault_open(null, null).is_null());
+            assert!(axiom_vault_open(invalid_utf8.as_ptr(), invalid_utf8.as_ptr()).is_null());
+            assert!(axiom_vault_create(null, path.as_ptr()).is_null());
+            assert!(axiom_vault_open_with_keyfile(path.as_ptr(), null, null).is_null());
+            assert!(axiom_vault_open_with_wrapped_key(
+                path.as_ptr(),
+                ptr::null(),
+                32,
+                ptr::null(),
+                0
+            )
+            .is_null());
+            assert!(axiom_vault_open_with_key(path.as_ptr(), ptr::null(), 32).is_null());
+            assert!(axiom_vault_info(ptr::null()).is_null());
+            assert!(axiom_vault_list(ptr::null(), path.as_ptr()).is_null());
+            assert!(axiom_vault_list(&handle, null).is_null());
+            assert!(axiom_vault_list(&handle, invalid_utf8.as_ptr()).is_null());
+            assert!(
+                axiom_vault_list_page(&handle, path.as_ptr(), usize::MAX, usize::MAX).is_null()
+            );
+            assert_eq!(axiom_vault_add_file(&handle, null, path.as_ptr()), -1);
+            assert_eq!(axiom_vault_extract_file(&handle, path.as_ptr(), null), -1);
+            assert_eq!(axiom_vault_mkdir(&handle, invalid_utf8.as_ptr()), -1);
+            assert_eq!(axiom_vault_remove(ptr::null(), path.as_ptr()), -1);
+            assert_eq!(axiom_vault_change_password(&handle, null, null), -1);
+            assert!(axiom_vault_rewrap_key(&handle, ptr::null(), 32).is_null());
+            assert!(axiom_vault_show_recovery_key(ptr::null()).is_null());
+            assert!(axiom_vault_reset_password(null, null, null).is_null());
+            assert_eq!(axiom_vault_check_migration(null), -1);
+            assert_eq!(axiom_vault_migrate(invalid_utf8.as_ptr(), null), -1);
+            assert!(axiom_vault_health_check(null, null).is_null());
+            assert!(axiom_vault_inspect(invalid_utf8.as_ptr()).is_null());
+            assert_eq!(axiom_vault_subscribe_events(ptr::null(), None), -1);
+            assert_eq!(axiom_sync_set_environment(&handle, garbage.as_ptr()), -1);
+            assert_eq!(
+                axiom_sync_set_environment(&handle, invalid_utf8.as_ptr()),
+                -1
+            );
+            assert_eq!(axiom_cancel(ptr::null()), -1);
+            assert_eq!(axiom_vault_close(ptr::null_mut()), -1);
+        }
+        assert_ne!(axiom_last_error_code(), 16);
+        let _ = error::take_last_error();
+    }
+
+    const NO_SECOND_CALL: std::time::Duration = std::time::Duration::from_millis(300);
+
+    /// Async create and open each deliver one handle, and cancelling after
+    /// completion changes nothing.
+    #[test]
+    fn async_create_and_open_complete_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().join("vault").to_str().unwrap()).unwrap();
+        let password = CString::new("password").unwrap();
+        let (tx, rx) = outcome_channel();
+
+        // SAFETY: pointers are live CStrings and a Sender that outlives every
+        // callback; handles and tokens are freed exactly once.
+        unsafe {
+            let token = axiom_vault_create_async(
+                path.as_ptr(),
+                password.as_ptr(),
+                record_outcome,
+                user_data(&tx),
+            );
+            assert!(!token.is_null());
+            let handle = rx.recv().unwrap().expect("create succeeds") as *mut FFIVaultHandle;
+            assert_eq!(axiom_cancel(token), 0);
+            assert!(rx.recv_timeout(NO_SECOND_CALL).is_err());
+            axiom_cancel_token_free(token);
+            assert_eq!(axiom_vault_close(handle), 0);
+
+            let token = axiom_vault_open_async(
+                path.as_ptr(),
+                password.as_ptr(),
+                record_outcome,
+                user_data(&tx),
+            );
+            let handle = rx.recv().unwrap().expect("open succeeds") as *mut FFIVaultHandle;
+            assert!(rx.recv_timeout(NO_SECOND_CALL).is_err());
+            axiom_cancel_token_free(token);
+            assert_eq!(axiom_vault_close(handle), 0);
+
+            let wrong = CString::new("wrong").unwrap();
+            let token = axiom_vault_open_async(
+                path.as_ptr(),
+                wrong.as_ptr(),
+                record_outcome,
+                user_data(&tx),
+            );
+            assert_eq!(rx.recv().unwrap(), Err(6));
+            assert!(rx.recv_timeout(NO_SECOND_CALL).is_err());
+            axiom_cancel_token_free(token);
+        }
+    }
+
+    /// Work cancelled before it starts never runs and reports cancelled.
+    #[test]
+    fn async_cancel_before_start_skips_work() {
+        let (tx, rx) = outcome_channel();
+        let ran = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let (cancel, cancelled) = watch::channel(false);
+        cancel.send_replace(true);
+
+        let flag = ran.clone();
+        spawn_vault_task(
+            cancelled,
+            move || {
+                flag.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(bare_handle())
+            },
+            record_outcome,
+            user_data(&tx),
+        )
+        .unwrap();
+
+        assert_eq!(rx.recv().unwrap(), Err(11));
+        assert!(rx.recv_timeout(NO_SECOND_CALL).is_err());
+        assert!(!ran.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    /// Cancelling mid-derivation reports cancelled without waiting for the
+    /// derivation, and its late result is dropped rather than delivered.
+    #[test]
+    fn async_cancel_mid_work_reports_promptly() {
+        let (tx, rx) = outcome_channel();
+        let (done_tx, done_rx) = std::sync::mpsc::channel();
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let (cancel, cancelled) = watch::channel(false);
+
+        spawn_vault_task(
+            cancelled,
+            move || {
+                started_tx.send(()).unwrap();
+                std::thread::sleep(std::time::Duration::from_secs(1));
+                done_tx.send(()).unwrap();
+                Ok(bare_handle())
+            },
+            record_outcome,
+            user_data(&tx),
+        )
+        .unwrap();
+
+        started_rx.recv().unwrap();
+        let token = Box::into_raw(Box::new(FFICancelToken { cancel }));
+        // SAFETY: `token` was just boxed above and is freed once below.
+        unsafe {
+            assert_eq!(axiom_cancel(token), 0);
+            axiom_cancel_token_free(token);
+        }
+        assert_eq!(
+            rx.recv_timeout(std::time::Duration::from_millis(500))
+                .unwrap(),
+            Err(11)
+        );
+
+        done_rx.recv().unwrap();
+        assert!(rx.recv_timeout(NO_SECOND_CALL).is_err());
+    }
+
     /// Calling the free function on a null pointer must be a no-op (matches
     /// the contract of `axiom_string_free`).
     #[test]
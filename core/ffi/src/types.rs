@@ -2,10 +2,11 @@
 //!
 //! Types that can cross the FFI boundary safely.
 
-use std::ffi::{c_char, c_int, c_longlong};
+use std::ffi::{c_char, c_int, c_longlong, c_ulonglong, c_void};
 use std::sync::Mutex;
 
 use axiomvault_app::AppService;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use zeroize::Zeroizing;
 
@@ -40,6 +41,28 @@ pub struct FFIVaultInfo {
     pub total_size: c_longlong,
     /// Vault version.
     pub version: c_int,
+    /// Key generation. A wrapped key minted for an older generation is
+    /// stale and must be refreshed with `axiom_vault_rewrap_key`.
+    pub key_generation: c_ulonglong,
+    /// Name to show for the vault; the vault ID if none was set.
+    pub display_name: *const c_char,
+    /// Description, or null.
+    pub description: *const c_char,
+    /// Icon hint, or null.
+    pub icon: *const c_char,
+    /// Color hint as `#rrggbb`, or null.
+    pub color: *const c_char,
+}
+
+/// Wrapped master key returned by `axiom_vault_rewrap_key` (C-safe).
+///
+/// Must be freed with `axiom_wrapped_key_free`.
+#[repr(C)]
+pub struct FFIWrappedKey {
+    /// Blob bytes.
+    pub data: *mut u8,
+    /// Number of bytes at `data`.
+    pub len: usize,
 }
 
 /// Callback for receiving events as JSON strings.
@@ -48,3 +71,26 @@ pub struct FFIVaultInfo {
 /// only valid for the duration of the call — copy the string if you need
 /// to retain it.
 pub type FFIEventCallback = extern "C" fn(json: *const c_char);
+
+/// Callback reporting the outcome of `axiom_vault_open_async` or
+/// `axiom_vault_create_async`.
+///
+/// Invoked exactly once, on a background thread. On success
+/// `result_handle` is a new handle (free with `axiom_vault_close`) and
+/// `error_json` is null. On failure or cancellation `result_handle` is null
+/// and `error_json` is a `{"code": .., "message": ..}` object using the
+/// codes of `axiom_last_error_code`; cancellation reports code 11. The
+/// `error_json` pointer is only valid for the duration of the call.
+pub type FFIVaultCallback = extern "C" fn(
+    result_handle: *mut FFIVaultHandle,
+    error_json: *const c_char,
+    user_data: *mut c_void,
+);
+
+/// Cancellation token for an asynchronous vault open or create.
+///
+/// Cancel with `axiom_cancel` and free with `axiom_cancel_token_free`.
+/// C code should treat this as an opaque pointer.
+pub struct FFICancelToken {
+    pub(crate) cancel: watch::Sender<bool>,
+}
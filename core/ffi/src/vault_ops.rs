@@ -5,7 +5,11 @@
 use std::ffi::CString;
 use std::path::Path;
 
-use axiomvault_app::{AppService, CreateVaultParams, OpenVaultParams, RecoverVaultParams};
+use axiomvault_app::{
+    AppError, AppService, CreateVaultParams, DeviceEnvironment, OpenExternalKeyParams,
+    OpenVaultParams, OpenWrappedKeyParams, RecoverVaultParams,
+};
+use axiomvault_common::Secret;
 use axiomvault_vault::{
     check_migration_needed, check_vault_health, check_vault_structure, MigrationRegistry,
     MigrationStatus, VaultConfig, VaultManager as CoreVaultManager, VaultVersion,
@@ -28,11 +32,22 @@ fn resolve_path(path: &str) -> FFIResult<String> {
     Ok(abs_path.to_string_lossy().to_string())
 }
 
+/// Read keyfile content from disk into a zeroizing buffer.
+pub fn read_keyfile(path: &str) -> FFIResult<Zeroizing<Vec<u8>>> {
+    std::fs::read(path)
+        .map(Zeroizing::new)
+        .map_err(|e| FFIError::IOError(format!("Failed to read keyfile: {}", e)))
+}
+
 /// Create a new vault at the specified path.
 ///
-/// `password` is taken by value as [`Zeroizing<String>`] so the secret is
-/// wiped from memory regardless of success or failure.
-pub async fn create_vault(path: &str, password: Zeroizing<String>) -> FFIResult<FFIVaultHandle> {
+/// `password` and `keyfile` are taken by value as [`Zeroizing`] buffers so
+/// the secrets are wiped from memory regardless of success or failure.
+pub async fn create_vault(
+    path: &str,
+    password: Zeroizing<String>,
+    keyfile: Option<Zeroizing<Vec<u8>>>,
+) -> FFIResult<FFIVaultHandle> {
     let abs_path = resolve_path(path)?;
 
     // Derive vault ID from directory name.
@@ -48,6 +63,7 @@ pub async fn create_vault(path: &str, password: Zeroizing<String>) -> FFIResult<
         .create_vault(CreateVaultParams {
             vault_id: vault_name.to_string(),
             password,
+            keyfile,
             provider_type: "local".to_string(),
             provider_config,
         })
@@ -65,9 +81,13 @@ pub async fn create_vault(path: &str, password: Zeroizing<String>) -> FFIResult<
 
 /// Open an existing vault at the specified path.
 ///
-/// `password` is taken by value as [`Zeroizing<String>`] so the secret is
-/// wiped from memory regardless of success or failure.
-pub async fn open_vault(path: &str, password: Zeroizing<String>) -> FFIResult<FFIVaultHandle> {
+/// `password` and `keyfile` are taken by value as [`Zeroizing`] buffers so
+/// the secrets are wiped from memory regardless of success or failure.
+pub async fn open_vault(
+    path: &str,
+    password: Zeroizing<String>,
+    keyfile: Option<Zeroizing<Vec<u8>>>,
+) -> FFIResult<FFIVaultHandle> {
     let abs_path = resolve_path(path)?;
     let provider_config = serde_json::json!({ "root": abs_path });
 
@@ -75,6 +95,7 @@ pub async fn open_vault(path: &str, password: Zeroizing<String>) -> FFIResult<FF
     service
         .open_vault(OpenVaultParams {
             password,
+            keyfile,
             provider_type: "local".to_string(),
             provider_config,
         })
@@ -89,6 +110,79 @@ pub async fn open_vault(path: &str, password: Zeroizing<String>) -> FFIResult<FF
     })
 }
 
+/// Open an existing vault with a wrapped key from `rewrap_key`.
+///
+/// `wrapping_key` is taken by value as a [`Zeroizing`] buffer so it is
+/// wiped from memory regardless of success or failure.
+pub async fn open_vault_with_wrapped_key(
+    path: &str,
+    wrapping_key: Zeroizing<Vec<u8>>,
+    wrapped_key: Vec<u8>,
+) -> FFIResult<FFIVaultHandle> {
+    let abs_path = resolve_path(path)?;
+    let provider_config = serde_json::json!({ "root": abs_path });
+
+    let service = AppService::new();
+    service
+        .open_vault_with_wrapped_key(OpenWrappedKeyParams {
+            wrapping_key,
+            wrapped_key,
+            provider_type: "local".to_string(),
+            provider_config,
+        })
+        .await
+        .map_err(FFIError::from)?;
+
+    Ok(FFIVaultHandle {
+        service,
+        path: abs_path,
+        recovery_words: std::sync::Mutex::new(None),
+        event_task: std::sync::Mutex::new(None),
+    })
+}
+
+/// Open an existing vault with a master key from an external key
+/// management system.
+///
+/// `master_key` is taken by value as a [`Zeroizing`] buffer so it is wiped
+/// from memory regardless of success or failure.
+pub async fn open_vault_with_key(
+    path: &str,
+    master_key: Zeroizing<Vec<u8>>,
+) -> FFIResult<FFIVaultHandle> {
+    let abs_path = resolve_path(path)?;
+    let provider_config = serde_json::json!({ "root": abs_path });
+
+    let service = AppService::new();
+    service
+        .open_vault_with_key(OpenExternalKeyParams {
+            master_key,
+            provider_type: "local".to_string(),
+            provider_config,
+        })
+        .await
+        .map_err(FFIError::from)?;
+
+    Ok(FFIVaultHandle {
+        service,
+        path: abs_path,
+        recovery_words: std::sync::Mutex::new(None),
+        event_task: std::sync::Mutex::new(None),
+    })
+}
+
+/// Wrap the master key of an open vault for the current key generation.
+pub async fn rewrap_key(
+    handle: &FFIVaultHandle,
+    wrapping_key: Zeroizing<Vec<u8>>,
+) -> FFIResult<Vec<u8>> {
+    handle
+        .service
+        .rewrap_key(wrapping_key)
+        .await
+        .map_err(FFIError::from)
+}
+
 /// Get information about an open vault.
 pub fn get_vault_info(handle: &FFIVaultHandle) -> FFIResult<FFIVaultInfo> {
     let runtime =
@@ -100,6 +194,18 @@ pub fn get_vault_info(handle: &FFIVaultHandle) -> FFIResult<FFIVaultInfo> {
         let vault_id_cstr = CString::new(info.id).map_err(|_| FFIError::StringConversionError)?;
         let root_path_cstr =
             CString::new(handle.path.clone()).map_err(|_| FFIError::StringConversionError)?;
+        let display_name_cstr =
+            CString::new(info.display_name).map_err(|_| FFIError::StringConversionError)?;
+        let optional = |value: Option<String>| -> FFIResult<Option<CString>> {
+            value
+                .map(|v| CString::new(v).map_err(|_| FFIError::StringConversionError))
+                .transpose()
+        };
+        let description_cstr = optional(info.description)?;
+        let icon_cstr = optional(info.icon)?;
+        let color_cstr = optional(info.color)?;
+        let into_raw =
+            |value: Option<CString>| value.map_or(std::ptr::null(), |v| v.into_raw() as *const _);
 
         Ok(FFIVaultInfo {
             vault_id: vault_id_cstr.into_raw() as *const _,
@@ -107,6 +213,11 @@ pub fn get_vault_info(handle: &FFIVaultHandle) -> FFIResult<FFIVaultInfo> {
             file_count: 0, // Not cheaply available via AppService; list_directory if needed.
             total_size: 0,
             version: 1,
+            key_generation: info.key_generation,
+            display_name: display_name_cstr.into_raw() as *const _,
+            description: into_raw(description_cstr),
+            icon: into_raw(icon_cstr),
+            color: into_raw(color_cstr),
         })
     })
 }
@@ -122,6 +233,22 @@ pub async fn list_vault(handle: &FFIVaultHandle, path: &str) -> FFIResult<String
     serde_json::to_string(&entries).map_err(|e| FFIError::VaultError(e.to_string()))
 }
 
+/// List one page of vault contents at the specified path (returns JSON).
+pub async fn list_vault_page(
+    handle: &FFIVaultHandle,
+    path: &str,
+    offset: usize,
+    limit: usize,
+) -> FFIResult<String> {
+    let page = handle
+        .service
+        .list_directory_page(path, offset, limit)
+        .await
+        .map_err(FFIError::from)?;
+
+    serde_json::to_string(&page).map_err(|e| FFIError::VaultError(e.to_string()))
+}
+
 /// Add a file to the vault (import from local filesystem).
 pub async fn add_file(
     handle: &FFIVaultHandle,
@@ -157,6 +284,20 @@ pub async fn create_directory(handle: &FFIVaultHandle, vault_path: &str) -> FFIR
         .map_err(FFIError::from)
 }
 
+/// Push the device's connectivity and power state, given as JSON such as
+/// `{"metered": true, "on_power": false}`. Missing fields count as
+/// unmetered and on power.
+pub fn set_sync_environment(handle: &FFIVaultHandle, json: &str) -> FFIResult<()> {
+    let environment: DeviceEnvironment = serde_json::from_str(json).map_err(|e| {
+        FFIError::from(AppError::InvalidInput(format!(
+            "Invalid sync environment: {}",
+            e
+        )))
+    })?;
+    handle.service.set_sync_environment(environment);
+    Ok(())
+}
+
 /// Remove a file or directory from the vault.
 pub async fn remove_entry(handle: &FFIVaultHandle, vault_path: &str) -> FFIResult<()> {
     let meta = handle
@@ -182,16 +323,18 @@ pub async fn remove_entry(handle: &FFIVaultHandle, vault_path: &str) -> FFIResul
 
 /// Change the vault password.
 ///
-/// Both passwords are taken by value as [`Zeroizing<String>`] so they are
-/// wiped from memory regardless of success or failure.
+/// Passwords and keyfiles are taken by value as [`Zeroizing`] buffers so
+/// they are wiped from memory regardless of success or failure.
 pub async fn change_password(
     handle: &FFIVaultHandle,
     old_password: Zeroizing<String>,
+    old_keyfile: Option<Zeroizing<Vec<u8>>>,
     new_password: Zeroizing<String>,
+    new_keyfile: Option<Zeroizing<Vec<u8>>>,
 ) -> FFIResult<()> {
     handle
         .service
-        .change_password(old_password, new_password)
+        .change_password_with_keyfile(old_password, old_keyfile, new_password, new_keyfile)
         .await
         .map_err(FFIError::from)
 }
@@ -214,7 +357,7 @@ pub async fn show_recovery_key(handle: &FFIVaultHandle) -> FFIResult<Zeroizing<S
 
     let recovery_key = session
         .config()
-        .decrypt_recovery_key(master_key)
+        .decrypt_recovery_key(&master_key)
         .map_err(|e| FFIError::VaultError(e.to_string()))?;
 
     recovery_key
@@ -311,7 +454,7 @@ pub async fn health_check(path: &str, password: Option<&str>) -> FFIResult<Strin
         }
         Some(pw) => {
             let session = manager
-                .open_vault("local", provider_config, pw.as_bytes())
+                .open_vault("local", provider_config, &Secret::from_slice(pw.as_bytes()))
                 .await
                 .map_err(|e| FFIError::VaultError(e.to_string()))?;
 
@@ -320,7 +463,7 @@ pub async fn health_check(path: &str, password: Option<&str>) -> FFIResult<Strin
                 .map_err(|e| FFIError::VaultError(e.to_string()))?;
 
             let report =
-                check_vault_health(provider.as_ref(), session.config(), master_key, &abs_path)
+                check_vault_health(provider.as_ref(), session.config(), &master_key, &abs_path)
                     .await
                     .map_err(|e| FFIError::VaultError(e.to_string()))?;
             Ok(report
@@ -329,3 +472,13 @@ pub async fn health_check(path: &str, password: Option<&str>) -> FFIResult<Strin
         }
     }
 }
+
+/// Summarize a vault from its config alone. Returns JSON; no password needed.
+pub async fn inspect_vault(path: &str) -> FFIResult<String> {
+    let abs_path = resolve_path(path)?;
+    let summary = CoreVaultManager::new()
+        .inspect("local", serde_json::json!({ "root": abs_path }))
+        .await
+        .map_err(|e| FFIError::VaultError(e.to_string()))?;
+    serde_json::to_string(&summary).map_err(|e| FFIError::VaultError(e.to_string()))
+}
@@ -0,0 +1,324 @@
+//! Access control for mounts shared with other local users.
+//!
+//! Without `allow_other` the kernel only lets the mounting user in, so
+//! nothing is checked here. With it, every local user reaches the
+//! filesystem: unless `default_permissions` delegates mode checks to the
+//! kernel, [`AccessPolicy`] compares each request's uid/gid against the
+//! node's owner and mode. The `allowed_uids` whitelist is always enforced
+//! here, since the kernel knows nothing about it.
+
+use fuser::{AccessFlags, Errno, Request};
+
+use crate::mount::MountOptions;
+use axiomvault_vault::NodePermissions;
+
+/// User and primary group a request runs as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Caller {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Caller {
+    /// The calling process's own user and group.
+    pub fn current() -> Self {
+        // SAFETY: `getuid`/`getgid` have no preconditions and always succeed.
+        unsafe {
+            Self {
+                uid: libc::getuid(),
+                gid: libc::getgid(),
+            }
+        }
+    }
+
+    fn is_root(&self) -> bool {
+        self.uid == 0
+    }
+}
+
+impl From<&Request> for Caller {
+    fn from(req: &Request) -> Self {
+        Self {
+            uid: req.uid(),
+            gid: req.gid(),
+        }
+    }
+}
+
+/// Ownership and mode of a node with defaults filled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attributes {
+    /// Permission bits.
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+/// Who may do what on a mount.
+#[derive(Debug, Clone)]
+pub struct AccessPolicy {
+    owner: Caller,
+    umask: u32,
+    allow_other: bool,
+    check_modes: bool,
+    allowed_uids: Vec<u32>,
+}
+
+impl AccessPolicy {
+    /// Policy for a mount made by `owner` with `options`.
+    pub fn new(owner: Caller, options: &MountOptions) -> Self {
+        Self {
+            owner,
+            umask: options.umask & 0o777,
+            allow_other: options.allow_other,
+            check_modes: options.allow_other && !options.default_permissions,
+            allowed_uids: options.allowed_uids.clone(),
+        }
+    }
+
+    /// The mounting user, who owns nodes without a stored owner.
+    pub fn owner(&self) -> Caller {
+        self.owner
+    }
+
+    /// Whether requests need checking at all.
+    pub fn is_enforced(&self) -> bool {
+        self.allow_other && (self.check_modes || !self.allowed_uids.is_empty())
+    }
+
+    /// Attributes of a node, defaulting to the mounting user and a mode
+    /// derived from the umask.
+    pub fn attributes(&self, stored: NodePermissions, is_dir: bool) -> Attributes {
+        let default_mode = if is_dir { 0o777 } else { 0o666 } & !self.umask;
+        Attributes {
+            mode: stored.mode.unwrap_or(default_mode) & 0o7777,
+            uid: stored.uid.unwrap_or(self.owner.uid),
+            gid: stored.gid.unwrap_or(self.owner.gid),
+        }
+    }
+
+    /// Mode of a node created with `mode` by a process with `umask`.
+    pub fn creation_mode(&self, mode: u32, umask: u32) -> u32 {
+        mode & !(umask | self.umask) & 0o7777
+    }
+
+    /// What to store as the owner of a node `caller` creates.
+    ///
+    /// Nodes of the mounting user keep no stored owner, so they follow
+    /// whoever mounts the vault on another machine.
+    pub fn creation_owner(&self, caller: Caller) -> (Option<u32>, Option<u32>) {
+        (
+            (caller.uid != self.owner.uid).then_some(caller.uid),
+            (caller.gid != self.owner.gid).then_some(caller.gid),
+        )
+    }
+
+    /// Whether `caller` may use the mount at all.
+    fn admits(&self, caller: Caller) -> bool {
+        !self.allow_other
+            || self.allowed_uids.is_empty()
+            || caller.is_root()
+            || caller.uid == self.owner.uid
+            || self.allowed_uids.contains(&caller.uid)
+    }
+
+    /// Check that `caller` may access a node with `attributes` for `mask`.
+    ///
+    /// # Errors
+    /// - `EACCES` if the caller is not whitelisted or the mode denies it
+    pub fn check(
+        &self,
+        caller: Caller,
+        attributes: &Attributes,
+        mask: AccessFlags,
+    ) -> Result<(), Errno> {
+        if !self.admits(caller) {
+            return Err(Errno::EACCES);
+        }
+        if !self.check_modes || caller.is_root() {
+            return Ok(());
+        }
+
+        let granted = if caller.uid == attributes.uid {
+            attributes.mode >> 6
+        } else if caller.gid == attributes.gid {
+            attributes.mode >> 3
+        } else {
+            attributes.mode
+        } & 0o7;
+        // R_OK, W_OK and X_OK line up with the rwx bits of a mode triplet.
+        let wanted = mask.bits() as u32 & 0o7;
+        if granted & wanted == wanted {
+            Ok(())
+        } else {
+            Err(Errno::EACCES)
+        }
+    }
+
+    /// Check that `caller` may change the mode or ownership of a node.
+    ///
+    /// Follows POSIX: only the owner may chmod, only root may give a node
+    /// away, and the owner may only move it to their own (primary) group.
+    ///
+    /// # Errors
+    /// - `EACCES` if the caller is not whitelisted
+    /// - `EPERM` if the change is not the caller's to make
+    pub fn check_change(
+        &self,
+        caller: Caller,
+        attributes: &Attributes,
+        change: NodePermissions,
+    ) -> Result<(), Errno> {
+        if !self.admits(caller) {
+            return Err(Errno::EACCES);
+        }
+        if caller.is_root() {
+            return Ok(());
+        }
+
+        let owns = caller.uid == attributes.uid;
+        if change.mode.is_some() && !owns {
+            return Err(Errno::EPERM);
+        }
+        if change.uid.is_some_and(|uid| uid != attributes.uid) {
+            return Err(Errno::EPERM);
+        }
+        if let Some(gid) = change.gid {
+            if !owns || (gid != attributes.gid && gid != caller.gid) {
+                return Err(Errno::EPERM);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OWNER: Caller = Caller {
+        uid: 1000,
+        gid: 1000,
+    };
+    const OTHER: Caller = Caller {
+        uid: 2000,
+        gid: 2000,
+    };
+
+    fn errno(result: Result<(), Errno>) -> Option<i32> {
+        result.err().map(|e| e.code())
+    }
+
+    fn shared(default_permissions: bool, allowed_uids: Vec<u32>) -> AccessPolicy {
+        let options = MountOptions {
+            allow_other: true,
+            default_permissions,
+            allowed_uids,
+            ..Default::default()
+        };
+        AccessPolicy::new(OWNER, &options)
+    }
+
+    #[test]
+    fn test_owner_only_mount_checks_nothing() {
+        let policy = AccessPolicy::new(OWNER, &MountOptions::default());
+        let attrs = policy.attributes(NodePermissions::default(), false);
+        assert!(!policy.is_enforced());
+        assert_eq!(attrs.mode, 0o600);
+        assert!(policy.check(OTHER, &attrs, AccessFlags::W_OK).is_ok());
+    }
+
+    #[test]
+    fn test_mode_bits_are_checked_per_class() {
+        let policy = shared(false, Vec::new());
+        let attrs = Attributes {
+            mode: 0o640,
+            uid: OWNER.uid,
+            gid: 3000,
+        };
+        let member = Caller {
+            uid: 2500,
+            gid: 3000,
+        };
+
+        assert!(policy
+            .check(OWNER, &attrs, AccessFlags::R_OK | AccessFlags::W_OK)
+            .is_ok());
+        assert!(policy.check(member, &attrs, AccessFlags::R_OK).is_ok());
+        assert_eq!(
+            errno(policy.check(member, &attrs, AccessFlags::W_OK)),
+            Some(libc::EACCES)
+        );
+        assert_eq!(
+            errno(policy.check(OTHER, &attrs, AccessFlags::R_OK)),
+            Some(libc::EACCES)
+        );
+        assert!(policy.check(OTHER, &attrs, AccessFlags::F_OK).is_ok());
+    }
+
+    #[test]
+    fn test_whitelist_applies_even_with_kernel_mode_checks() {
+        let policy = shared(true, vec![OTHER.uid]);
+        let attrs = policy.attributes(NodePermissions::default(), true);
+        let stranger = Caller { uid: 3000, gid: 0 };
+
+        assert!(policy.check(OTHER, &attrs, AccessFlags::W_OK).is_ok());
+        assert_eq!(
+            errno(policy.check(stranger, &attrs, AccessFlags::R_OK)),
+            Some(libc::EACCES)
+        );
+        assert!(policy
+            .check(Caller { uid: 0, gid: 0 }, &attrs, AccessFlags::R_OK)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_changes_follow_posix_ownership_rules() {
+        let policy = shared(false, Vec::new());
+        let attrs = policy.attributes(NodePermissions::default(), false);
+        let chmod = NodePermissions {
+            mode: Some(0o644),
+            ..Default::default()
+        };
+
+        assert!(policy.check_change(OWNER, &attrs, chmod).is_ok());
+        assert_eq!(
+            errno(policy.check_change(OTHER, &attrs, chmod)),
+            Some(libc::EPERM)
+        );
+
+        let give_away = NodePermissions {
+            uid: Some(OTHER.uid),
+            ..Default::default()
+        };
+        assert_eq!(
+            errno(policy.check_change(OWNER, &attrs, give_away)),
+            Some(libc::EPERM)
+        );
+        assert!(policy
+            .check_change(Caller { uid: 0, gid: 0 }, &attrs, give_away)
+            .is_ok());
+
+        let chgrp = |gid| NodePermissions {
+            gid: Some(gid),
+            ..Default::default()
+        };
+        assert!(policy.check_change(OWNER, &attrs, chgrp(OWNER.gid)).is_ok());
+        assert_eq!(
+            errno(policy.check_change(OWNER, &attrs, chgrp(4000))),
+            Some(libc::EPERM)
+        );
+    }
+
+    #[test]
+    fn test_creation_applies_both_umasks_and_skips_owner() {
+        let options = MountOptions {
+            umask: 0o027,
+            ..Default::default()
+        };
+        let policy = AccessPolicy::new(OWNER, &options);
+        assert_eq!(policy.creation_mode(0o100666, 0o002), 0o640);
+        assert_eq!(policy.creation_owner(OWNER), (None, None));
+        assert_eq!(policy.creation_owner(OTHER), (Some(2000), Some(2000)));
+    }
+}
@@ -9,20 +9,139 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 use fuser::{
-    BsdFileFlags, Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags, Generation,
-    INodeNo, LockOwner, OpenFlags, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty,
-    ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow, WriteFlags,
+    AccessFlags, BsdFileFlags, Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags,
+    Generation, INodeNo, KernelConfig, LockOwner, OpenFlags, ReplyAttr, ReplyCreate, ReplyData,
+    ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow, WriteFlags,
 };
 use tokio::runtime::Handle;
 use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use tracing::span::EnteredSpan;
+use tracing::{debug, debug_span, error, info};
 use zeroize::Zeroize;
 
-use axiomvault_common::VaultPath;
-use axiomvault_vault::{VaultOperations, VaultSession};
+use crate::access::{AccessPolicy, Attributes, Caller};
+use crate::mount::{MountOptions, DEFAULT_MAX_READAHEAD};
+use crate::watchdog::MountHealth;
+use axiomvault_common::telemetry::metrics;
+use axiomvault_common::{Error, VaultPath};
+use axiomvault_vault::{NodePermissions, VaultOperations, VaultSession};
+
+/// Build the vault path for a new entry named `name` under `parent_path`.
+///
+/// The name is joined as a single component so that embedded separators
+/// or NUL are rejected instead of being split.
+fn child_vault_path(parent_path: &str, name: &str) -> axiomvault_common::Result<VaultPath> {
+    VaultPath::parse(parent_path)?.join(name)
+}
+
+/// Errno for a failed vault operation.
+///
+/// A locked session, including one that locked itself after reaching its
+/// byte limit, reports `EACCES` until the password is entered again.
+fn operation_errno(e: &Error) -> Errno {
+    match e {
+        Error::NotPermitted(_) => Errno::EACCES,
+        Error::NotFound(_) => Errno::ENOENT,
+        _ => Errno::EIO,
+    }
+}
+
+/// The bytes of `buffer` covered by a read of `size` bytes at `offset`.
+///
+/// Reads at or past the end, including any read of an empty file, yield
+/// an empty slice, which FUSE reports as end of file.
+fn read_range(buffer: &[u8], offset: u64, size: u32) -> &[u8] {
+    let start = usize::try_from(offset)
+        .unwrap_or(usize::MAX)
+        .min(buffer.len());
+    let end = start.saturating_add(size as usize).min(buffer.len());
+    &buffer[start..end]
+}
+
+/// Copy `data` into `buffer` at `offset`, zero-filling any gap.
+///
+/// # Errors
+/// - `EINVAL` if the written range overflows the address space
+/// - `ENOMEM` if the buffer cannot grow to hold it
+fn write_at(buffer: &mut Vec<u8>, offset: u64, data: &[u8]) -> Result<(), Errno> {
+    let start = usize::try_from(offset).map_err(|_| Errno::EINVAL)?;
+    let end = start
+        .checked_add(data.len())
+        .filter(|&end| end <= isize::MAX as usize)
+        .ok_or(Errno::EINVAL)?;
+
+    if end > buffer.len() {
+        buffer
+            .try_reserve(end - buffer.len())
+            .map_err(|_| Errno::ENOMEM)?;
+        buffer.resize(end, 0);
+    }
+    buffer[start..end].copy_from_slice(data);
+    Ok(())
+}
+
+/// Write an open file's buffer back to the vault if it changed.
+async fn save_if_dirty(session: &VaultSession, file: &mut OpenFile) -> Result<(), Errno> {
+    if !file.dirty {
+        return Ok(());
+    }
+
+    let ops = VaultOperations::new(session).map_err(|e| {
+        error!("Failed to get operations: {}", e);
+        operation_errno(&e)
+    })?;
+
+    let path = VaultPath::parse(&file.path).map_err(|e| {
+        error!("Invalid path: {}", e);
+        Errno::EIO
+    })?;
+
+    ops.update_file(&path, &file.buffer).await.map_err(|e| {
+        error!("Failed to write file: {}", e);
+        operation_errno(&e)
+    })?;
+
+    file.dirty = false;
+    info!("File saved");
+    Ok(())
+}
+
+/// Access to the open-file buffers of a filesystem handed to fuser.
+///
+/// Lets the mount handle save pending writes before detaching.
+pub(crate) struct DirtyBuffers {
+    session: Arc<VaultSession>,
+    open_files: Arc<RwLock<HashMap<FileHandle, OpenFile>>>,
+}
+
+impl DirtyBuffers {
+    /// Save every dirty open file; returns how many could not be saved.
+    ///
+    /// Files stay open, so a later `release` still finds them.
+    pub(crate) async fn flush(&self) -> usize {
+        let mut files = self.open_files.write().await;
+        let mut failed = 0;
+        for file in files.values_mut() {
+            if save_if_dirty(&self.session, file).await.is_err() {
+                failed += 1;
+            }
+        }
+        failed
+    }
+}
+
+/// Enter the span covering one FUSE callback and count the call.
+///
+/// Callbacks run synchronously on a FUSE worker thread and block on the
+/// runtime, so the entered guard stays on one thread for the whole call.
+/// Names are never recorded; the inode and file handle identify the target.
+fn fuse_span(op: &'static str, ino: u64, fh: Option<u64>) -> EnteredSpan {
+    metrics::operation(op);
+    debug_span!("fuse_op", op, ino, fh).entered()
+}
 
 /// Helper function to create FileAttr with common defaults.
-fn create_file_attr(ino: INodeNo, is_dir: bool, size: u64) -> FileAttr {
+fn create_file_attr(ino: INodeNo, is_dir: bool, size: u64, attributes: &Attributes) -> FileAttr {
     let now = SystemTime::now();
     FileAttr {
         ino,
@@ -37,14 +156,10 @@ fn create_file_attr(ino: INodeNo, is_dir: bool, size: u64) -> FileAttr {
         } else {
             FileType::RegularFile
         },
-        perm: if is_dir { 0o700 } else { 0o600 },
+        perm: attributes.mode as u16,
         nlink: if is_dir { 2 } else { 1 },
-        // SAFETY: `getuid`/`getgid` are async-signal-safe POSIX syscalls with no
-        // preconditions and no side effects; they always return the current
-        // process's UID/GID.
-        uid: unsafe { libc::getuid() },
-        // SAFETY: see `getuid` above.
-        gid: unsafe { libc::getgid() },
+        uid: attributes.uid,
+        gid: attributes.gid,
         rdev: 0,
         blksize: 4096,
         flags: 0,
@@ -119,6 +234,11 @@ pub struct VaultFilesystem {
     open_files: Arc<RwLock<HashMap<FileHandle, OpenFile>>>,
     next_fh: Arc<RwLock<u64>>,
     ttl: Duration,
+    access: AccessPolicy,
+    /// Readahead requested from the kernel at init, in bytes.
+    max_readahead: u32,
+    /// Set degraded by the mount's watchdog; requests then fail fast.
+    health: MountHealth,
 }
 
 // SAFETY: All components are Arc/RwLock (thread-safe) or owned Tokio Handle.
@@ -139,6 +259,30 @@ impl VaultFilesystem {
     /// - `session`: Active vault session
     /// - `runtime`: Tokio runtime handle for async operations
     pub fn new(session: Arc<VaultSession>, runtime: Handle) -> Self {
+        Self::with_options(session, runtime, &MountOptions::default())
+    }
+
+    /// Create a filesystem enforcing the access settings of `options`.
+    ///
+    /// Nodes without a stored owner belong to the calling process's user.
+    pub fn with_options(
+        session: Arc<VaultSession>,
+        runtime: Handle,
+        options: &MountOptions,
+    ) -> Self {
+        let access = AccessPolicy::new(Caller::current(), options);
+        Self {
+            max_readahead: options.max_readahead,
+            ..Self::with_access_policy(session, runtime, access)
+        }
+    }
+
+    /// Create a filesystem enforcing `access`.
+    pub fn with_access_policy(
+        session: Arc<VaultSession>,
+        runtime: Handle,
+        access: AccessPolicy,
+    ) -> Self {
         Self {
             session,
             runtime,
@@ -146,12 +290,169 @@ impl VaultFilesystem {
             open_files: Arc::new(RwLock::new(HashMap::new())),
             next_fh: Arc::new(RwLock::new(1)),
             ttl: Duration::from_secs(1),
+            access,
+            max_readahead: DEFAULT_MAX_READAHEAD,
+            health: MountHealth::new(),
+        }
+    }
+
+    /// Handle for saving this filesystem's dirty buffers from outside.
+    pub(crate) fn dirty_buffers(&self) -> DirtyBuffers {
+        DirtyBuffers {
+            session: self.session.clone(),
+            open_files: self.open_files.clone(),
+        }
+    }
+
+    /// Health shared with the mount's watchdog and handle.
+    pub(crate) fn health(&self) -> MountHealth {
+        self.health.clone()
+    }
+
+    /// Refuse requests while the mount is degraded.
+    ///
+    /// `ENOTCONN` tells applications the filesystem went away, where the
+    /// `EIO` of a failing vault call would look like a broken file.
+    fn connected(&self) -> Result<(), Errno> {
+        if self.health.is_degraded() {
+            return Err(Errno::ENOTCONN);
+        }
+        Ok(())
+    }
+
+    /// Ownership and mode of the node at `path`, with defaults filled in.
+    async fn attributes(
+        &self,
+        ops: &VaultOperations<'_>,
+        path: &VaultPath,
+        is_dir: bool,
+    ) -> Result<Attributes, Errno> {
+        let stored = ops
+            .permissions(path)
+            .await
+            .map_err(|e| operation_errno(&e))?;
+        Ok(self.access.attributes(stored, is_dir))
+    }
+
+    /// Check that `caller` may access the node at `path` for `mask`.
+    ///
+    /// # Errors
+    /// - `ENOTCONN` if the mount is degraded
+    /// - `EACCES` if the mount's access policy denies it
+    /// - `ENOENT` if the node does not exist
+    pub async fn check_access(
+        &self,
+        caller: Caller,
+        path: &str,
+        mask: AccessFlags,
+    ) -> Result<(), Errno> {
+        self.connected()?;
+        if !self.access.is_enforced() {
+            return Ok(());
         }
+        let ops = VaultOperations::new(&self.session).map_err(|e| operation_errno(&e))?;
+        let path = VaultPath::parse(path).map_err(|_| Errno::ENOENT)?;
+        let (_, is_dir, _) = ops.metadata(&path).await.map_err(|e| operation_errno(&e))?;
+        let attributes = self.attributes(&ops, &path, is_dir).await?;
+        self.access.check(caller, &attributes, mask)
+    }
+
+    /// Apply a chmod/chown by `caller` to the node at `path`.
+    ///
+    /// # Errors
+    /// - `ENOTCONN` if the mount is degraded
+    /// - `EPERM` if the change is not the caller's to make
+    /// - `EACCES` if the caller may not use the mount
+    /// - `ENOENT` if the node does not exist
+    pub async fn change_permissions(
+        &self,
+        caller: Caller,
+        path: &str,
+        change: NodePermissions,
+    ) -> Result<(), Errno> {
+        self.connected()?;
+        let ops = VaultOperations::new(&self.session).map_err(|e| operation_errno(&e))?;
+        let path = VaultPath::parse(path).map_err(|_| Errno::ENOENT)?;
+        let (_, is_dir, _) = ops.metadata(&path).await.map_err(|e| operation_errno(&e))?;
+        let attributes = self.attributes(&ops, &path, is_dir).await?;
+        self.access.check_change(caller, &attributes, change)?;
+        ops.set_permissions(&path, change)
+            .await
+            .map_err(|e| operation_errno(&e))
+    }
+
+    /// Record the mode and owner of a node `caller` just created, and
+    /// return its attributes.
+    async fn record_creation(
+        &self,
+        ops: &VaultOperations<'_>,
+        path: &VaultPath,
+        is_dir: bool,
+        caller: Caller,
+        mode: u32,
+        umask: u32,
+    ) -> Result<Attributes, Errno> {
+        let (uid, gid) = self.access.creation_owner(caller);
+        let permissions = NodePermissions {
+            mode: Some(self.access.creation_mode(mode, umask)),
+            uid,
+            gid,
+        };
+        ops.set_permissions(path, permissions)
+            .await
+            .map_err(|e| operation_errno(&e))?;
+        Ok(self.access.attributes(permissions, is_dir))
     }
 }
 
+/// Request `wanted` bytes of readahead through `set`, which behaves like
+/// `KernelConfig::set_max_readahead`, falling back to the nearest value the
+/// kernel accepts. Returns the readahead granted.
+fn request_readahead(wanted: u32, mut set: impl FnMut(u32) -> Result<u32, u32>) -> u32 {
+    match set(wanted) {
+        Ok(_) => wanted,
+        Err(nearest) => {
+            let _ = set(nearest);
+            nearest
+        }
+    }
+}
+
+/// Permissions `open` needs for `flags`.
+fn open_access(flags: OpenFlags) -> AccessFlags {
+    let mut mask = match flags.0 & libc::O_ACCMODE {
+        libc::O_WRONLY => AccessFlags::W_OK,
+        libc::O_RDWR => AccessFlags::R_OK | AccessFlags::W_OK,
+        _ => AccessFlags::R_OK,
+    };
+    if flags.0 & libc::O_TRUNC != 0 {
+        mask |= AccessFlags::W_OK;
+    }
+    mask
+}
+
 impl Filesystem for VaultFilesystem {
-    fn lookup(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+    /// Ask for the configured readahead, or the most the kernel allows.
+    ///
+    /// The kernel's readahead is all this mount does for sequential
+    /// readers. `open` decrypts the whole file into the handle's buffer, so
+    /// later reads make no provider calls to prefetch ahead of.
+    // TODO: Detect sequential access per handle and prefetch the next
+    // chunks into a bounded cache once reads use ranged downloads.
+    fn init(&mut self, _req: &Request, config: &mut KernelConfig) -> std::io::Result<()> {
+        let granted =
+            request_readahead(self.max_readahead, |bytes| config.set_max_readahead(bytes));
+        debug!("init: max_readahead={}", granted);
+        Ok(())
+    }
+
+    fn lookup(&self, req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        let _span = fuse_span("lookup", u64::from(parent), None);
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
+        let caller = Caller::from(req);
         let name_str = match name.to_str() {
             Some(s) => s,
             None => {
@@ -178,6 +479,14 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
+            if let Err(errno) = self
+                .check_access(caller, &parent_path, AccessFlags::X_OK)
+                .await
+            {
+                reply.error(errno);
+                return;
+            }
+
             let child_path = if parent_path == "/" {
                 format!("/{}", name_str)
             } else {
@@ -186,8 +495,8 @@ impl Filesystem for VaultFilesystem {
 
             let ops = match VaultOperations::new(&session) {
                 Ok(o) => o,
-                Err(_) => {
-                    reply.error(Errno::EIO);
+                Err(e) => {
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
@@ -200,27 +509,31 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
-            if !ops.exists(&path).await {
-                reply.error(Errno::ENOENT);
-                return;
-            }
-
-            match ops.metadata(&path).await {
-                Ok((_, is_dir, size)) => {
+            match ops.exists_detailed(&path).await {
+                Some((_, is_dir, size)) => {
+                    let attributes = match self.attributes(&ops, &path, is_dir).await {
+                        Ok(a) => a,
+                        Err(errno) => {
+                            reply.error(errno);
+                            return;
+                        }
+                    };
                     let mut map = inodes.write().await;
                     let ino = map.get_or_create_inode(&child_path);
-                    let attr = create_file_attr(ino, is_dir, size.unwrap_or(0));
+                    let attr = create_file_attr(ino, is_dir, size.unwrap_or(0), &attributes);
                     reply.entry(&ttl, &attr, Generation(0));
                 }
-                Err(e) => {
-                    error!("Failed to get metadata: {}", e);
-                    reply.error(Errno::EIO);
-                }
+                None => reply.error(Errno::ENOENT),
             }
         });
     }
 
-    fn getattr(&self, _req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+    fn getattr(&self, _req: &Request, ino: INodeNo, fh: Option<FileHandle>, reply: ReplyAttr) {
+        let _span = fuse_span("getattr", u64::from(ino), fh.map(u64::from));
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
         debug!("getattr: ino={}", ino);
 
         let session = self.session.clone();
@@ -239,17 +552,10 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
-            if path_str == "/" {
-                // Root directory
-                let attr = create_file_attr(INodeNo::ROOT, true, 0);
-                reply.attr(&ttl, &attr);
-                return;
-            }
-
             let ops = match VaultOperations::new(&session) {
                 Ok(o) => o,
-                Err(_) => {
-                    reply.error(Errno::EIO);
+                Err(e) => {
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
@@ -262,28 +568,34 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
-            match ops.metadata(&path).await {
-                Ok((_, is_dir, size)) => {
-                    let attr = create_file_attr(ino, is_dir, size.unwrap_or(0));
-                    reply.attr(&ttl, &attr);
-                }
-                Err(e) => {
-                    error!("Failed to get metadata: {}", e);
-                    reply.error(Errno::EIO);
-                }
+            match ops.exists_detailed(&path).await {
+                Some((_, is_dir, size)) => match self.attributes(&ops, &path, is_dir).await {
+                    Ok(attributes) => {
+                        let attr = create_file_attr(ino, is_dir, size.unwrap_or(0), &attributes);
+                        reply.attr(&ttl, &attr);
+                    }
+                    Err(errno) => reply.error(errno),
+                },
+                None => reply.error(Errno::ENOENT),
             }
         });
     }
 
     fn readdir(
         &self,
-        _req: &Request,
+        req: &Request,
         ino: INodeNo,
-        _fh: FileHandle,
+        fh: FileHandle,
         offset: u64,
         mut reply: ReplyDirectory,
     ) {
+        let _span = fuse_span("readdir", u64::from(ino), Some(u64::from(fh)));
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
         debug!("readdir: ino={}, offset={}", u64::from(ino), offset);
+        let caller = Caller::from(req);
 
         let session = self.session.clone();
         let inodes = self.inodes.clone();
@@ -300,10 +612,18 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
+            if let Err(errno) = self
+                .check_access(caller, &path_str, AccessFlags::R_OK)
+                .await
+            {
+                reply.error(errno);
+                return;
+            }
+
             let ops = match VaultOperations::new(&session) {
                 Ok(o) => o,
-                Err(_) => {
-                    reply.error(Errno::EIO);
+                Err(e) => {
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
@@ -325,7 +645,7 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
-            let mut i = offset as usize;
+            let mut i = usize::try_from(offset).unwrap_or(usize::MAX);
 
             // . and .. entries
             if i == 0 {
@@ -393,8 +713,14 @@ impl Filesystem for VaultFilesystem {
         });
     }
 
-    fn open(&self, _req: &Request, ino: INodeNo, flags: OpenFlags, reply: ReplyOpen) {
+    fn open(&self, req: &Request, ino: INodeNo, flags: OpenFlags, reply: ReplyOpen) {
+        let _span = fuse_span("open", u64::from(ino), None);
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
         debug!("open: ino={}, flags={:?}", ino, flags);
+        let caller = Caller::from(req);
 
         let session = self.session.clone();
         let inodes = self.inodes.clone();
@@ -413,10 +739,18 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
+            if let Err(errno) = self
+                .check_access(caller, &path_str, open_access(flags))
+                .await
+            {
+                reply.error(errno);
+                return;
+            }
+
             let ops = match VaultOperations::new(&session) {
                 Ok(o) => o,
-                Err(_) => {
-                    reply.error(Errno::EIO);
+                Err(e) => {
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
@@ -434,7 +768,7 @@ impl Filesystem for VaultFilesystem {
                 Ok(data) => data,
                 Err(e) => {
                     error!("Failed to read file: {}", e);
-                    reply.error(Errno::EIO);
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
@@ -465,7 +799,7 @@ impl Filesystem for VaultFilesystem {
     fn read(
         &self,
         _req: &Request,
-        _ino: INodeNo,
+        ino: INodeNo,
         fh: FileHandle,
         offset: u64,
         size: u32,
@@ -473,6 +807,11 @@ impl Filesystem for VaultFilesystem {
         _lock_owner: Option<LockOwner>,
         reply: ReplyData,
     ) {
+        let _span = fuse_span("read", u64::from(ino), Some(u64::from(fh)));
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
         debug!(
             "read: fh={}, offset={}, size={}",
             u64::from(fh),
@@ -486,13 +825,7 @@ impl Filesystem for VaultFilesystem {
             let files = open_files.read().await;
             match files.get(&fh) {
                 Some(file) => {
-                    let offset = offset as usize;
-                    let end = (offset + size as usize).min(file.buffer.len());
-                    if offset >= file.buffer.len() {
-                        reply.data(&[]);
-                    } else {
-                        reply.data(&file.buffer[offset..end]);
-                    }
+                    reply.data(read_range(&file.buffer, offset, size));
                 }
                 None => {
                     reply.error(Errno::EBADF);
@@ -504,7 +837,7 @@ impl Filesystem for VaultFilesystem {
     fn write(
         &self,
         _req: &Request,
-        _ino: INodeNo,
+        ino: INodeNo,
         fh: FileHandle,
         offset: u64,
         data: &[u8],
@@ -513,6 +846,11 @@ impl Filesystem for VaultFilesystem {
         _lock_owner: Option<LockOwner>,
         reply: ReplyWrite,
     ) {
+        let _span = fuse_span("write", u64::from(ino), Some(u64::from(fh)));
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
         debug!(
             "write: fh={}, offset={}, size={}",
             u64::from(fh),
@@ -525,20 +863,13 @@ impl Filesystem for VaultFilesystem {
         self.runtime.block_on(async move {
             let mut files = open_files.write().await;
             match files.get_mut(&fh) {
-                Some(file) => {
-                    let offset = offset as usize;
-                    let end = offset + data.len();
-
-                    // Extend buffer if necessary
-                    if end > file.buffer.len() {
-                        file.buffer.resize(end, 0);
+                Some(file) => match write_at(&mut file.buffer, offset, data) {
+                    Ok(()) => {
+                        file.dirty = true;
+                        reply.written(data.len() as u32);
                     }
-
-                    file.buffer[offset..end].copy_from_slice(data);
-                    file.dirty = true;
-
-                    reply.written(data.len() as u32);
-                }
+                    Err(errno) => reply.error(errno),
+                },
                 None => {
                     reply.error(Errno::EBADF);
                 }
@@ -546,16 +877,50 @@ impl Filesystem for VaultFilesystem {
         });
     }
 
+    /// Save on every `close()`. `release` arrives asynchronously after the
+    /// last close, so without this a write could still be unsaved when
+    /// `close()` returns or the filesystem is unmounted.
+    fn flush(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        fh: FileHandle,
+        _lock_owner: LockOwner,
+        reply: ReplyEmpty,
+    ) {
+        let _span = fuse_span("flush", u64::from(ino), Some(u64::from(fh)));
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
+        debug!("flush: fh={}", u64::from(fh));
+
+        let session = self.session.clone();
+        let open_files = self.open_files.clone();
+
+        self.runtime.block_on(async move {
+            let mut files = open_files.write().await;
+            match files.get_mut(&fh) {
+                Some(file) => match save_if_dirty(&session, file).await {
+                    Ok(()) => reply.ok(),
+                    Err(errno) => reply.error(errno),
+                },
+                None => reply.error(Errno::EBADF),
+            }
+        });
+    }
+
     fn release(
         &self,
         _req: &Request,
-        _ino: INodeNo,
+        ino: INodeNo,
         fh: FileHandle,
         _flags: OpenFlags,
         _lock_owner: Option<LockOwner>,
         _flush: bool,
         reply: ReplyEmpty,
     ) {
+        let _span = fuse_span("release", u64::from(ino), Some(u64::from(fh)));
         debug!("release: fh={}", u64::from(fh));
 
         let session = self.session.clone();
@@ -568,30 +933,7 @@ impl Filesystem for VaultFilesystem {
             };
 
             if let Some(mut file) = file {
-                let flush_result: std::result::Result<(), Errno> = async {
-                    if !file.dirty {
-                        return Ok(());
-                    }
-
-                    let ops = VaultOperations::new(&session).map_err(|e| {
-                        error!("Failed to get operations: {}", e);
-                        Errno::EIO
-                    })?;
-
-                    let path = VaultPath::parse(&file.path).map_err(|e| {
-                        error!("Invalid path: {}", e);
-                        Errno::EIO
-                    })?;
-
-                    ops.update_file(&path, &file.buffer).await.map_err(|e| {
-                        error!("Failed to write file: {}", e);
-                        Errno::EIO
-                    })?;
-
-                    info!("File saved");
-                    Ok(())
-                }
-                .await;
+                let flush_result = save_if_dirty(&session, &mut file).await;
 
                 // Always zeroize decrypted file content, regardless of flush outcome.
                 file.buffer.zeroize();
@@ -610,14 +952,20 @@ impl Filesystem for VaultFilesystem {
 
     fn create(
         &self,
-        _req: &Request,
+        req: &Request,
         parent: INodeNo,
         name: &OsStr,
-        _mode: u32,
-        _umask: u32,
+        mode: u32,
+        umask: u32,
         _flags: i32,
         reply: ReplyCreate,
     ) {
+        let _span = fuse_span("create", u64::from(parent), None);
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
+        let caller = Caller::from(req);
         let name_str = match name.to_str() {
             Some(s) => s,
             None => {
@@ -646,6 +994,14 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
+            if let Err(errno) = self
+                .check_access(caller, &parent_path, AccessFlags::W_OK | AccessFlags::X_OK)
+                .await
+            {
+                reply.error(errno);
+                return;
+            }
+
             let child_path = if parent_path == "/" {
                 format!("/{}", name_str)
             } else {
@@ -654,13 +1010,13 @@ impl Filesystem for VaultFilesystem {
 
             let ops = match VaultOperations::new(&session) {
                 Ok(o) => o,
-                Err(_) => {
-                    reply.error(Errno::EIO);
+                Err(e) => {
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
 
-            let path = match VaultPath::parse(&child_path) {
+            let path = match child_vault_path(&parent_path, name_str) {
                 Ok(p) => p,
                 Err(_) => {
                     reply.error(Errno::EINVAL);
@@ -671,10 +1027,24 @@ impl Filesystem for VaultFilesystem {
             // Create empty file
             if let Err(e) = ops.create_file(&path, &[]).await {
                 error!("Failed to create file: {}", e);
-                reply.error(Errno::EIO);
+                reply.error(match e {
+                    Error::InvalidInput(_) => Errno::EINVAL,
+                    _ => Errno::EIO,
+                });
                 return;
             }
 
+            let attributes = match self
+                .record_creation(&ops, &path, false, caller, mode, umask)
+                .await
+            {
+                Ok(a) => a,
+                Err(errno) => {
+                    reply.error(errno);
+                    return;
+                }
+            };
+
             let ino = {
                 let mut map = inodes.write().await;
                 map.get_or_create_inode(&child_path)
@@ -699,7 +1069,7 @@ impl Filesystem for VaultFilesystem {
                 );
             }
 
-            let attr = create_file_attr(ino, false, 0);
+            let attr = create_file_attr(ino, false, 0, &attributes);
 
             reply.created(&ttl, &attr, Generation(0), fh, FopenFlags::empty());
         });
@@ -707,13 +1077,19 @@ impl Filesystem for VaultFilesystem {
 
     fn mkdir(
         &self,
-        _req: &Request,
+        req: &Request,
         parent: INodeNo,
         name: &OsStr,
-        _mode: u32,
-        _umask: u32,
+        mode: u32,
+        umask: u32,
         reply: ReplyEntry,
     ) {
+        let _span = fuse_span("mkdir", u64::from(parent), None);
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
+        let caller = Caller::from(req);
         let name_str = match name.to_str() {
             Some(s) => s,
             None => {
@@ -740,6 +1116,14 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
+            if let Err(errno) = self
+                .check_access(caller, &parent_path, AccessFlags::W_OK | AccessFlags::X_OK)
+                .await
+            {
+                reply.error(errno);
+                return;
+            }
+
             let child_path = if parent_path == "/" {
                 format!("/{}", name_str)
             } else {
@@ -748,13 +1132,13 @@ impl Filesystem for VaultFilesystem {
 
             let ops = match VaultOperations::new(&session) {
                 Ok(o) => o,
-                Err(_) => {
-                    reply.error(Errno::EIO);
+                Err(e) => {
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
 
-            let path = match VaultPath::parse(&child_path) {
+            let path = match child_vault_path(&parent_path, name_str) {
                 Ok(p) => p,
                 Err(_) => {
                     reply.error(Errno::EINVAL);
@@ -764,22 +1148,42 @@ impl Filesystem for VaultFilesystem {
 
             if let Err(e) = ops.create_directory(&path).await {
                 error!("Failed to create directory: {}", e);
-                reply.error(Errno::EIO);
+                reply.error(match e {
+                    Error::InvalidInput(_) => Errno::EINVAL,
+                    _ => Errno::EIO,
+                });
                 return;
             }
 
+            let attributes = match self
+                .record_creation(&ops, &path, true, caller, mode, umask)
+                .await
+            {
+                Ok(a) => a,
+                Err(errno) => {
+                    reply.error(errno);
+                    return;
+                }
+            };
+
             let ino = {
                 let mut map = inodes.write().await;
                 map.get_or_create_inode(&child_path)
             };
 
-            let attr = create_file_attr(ino, true, 0);
+            let attr = create_file_attr(ino, true, 0, &attributes);
 
             reply.entry(&ttl, &attr, Generation(0));
         });
     }
 
-    fn unlink(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+    fn unlink(&self, req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+        let _span = fuse_span("unlink", u64::from(parent), None);
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
+        let caller = Caller::from(req);
         let name_str = match name.to_str() {
             Some(s) => s,
             None => {
@@ -805,6 +1209,14 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
+            if let Err(errno) = self
+                .check_access(caller, &parent_path, AccessFlags::W_OK | AccessFlags::X_OK)
+                .await
+            {
+                reply.error(errno);
+                return;
+            }
+
             let child_path = if parent_path == "/" {
                 format!("/{}", name_str)
             } else {
@@ -813,8 +1225,8 @@ impl Filesystem for VaultFilesystem {
 
             let ops = match VaultOperations::new(&session) {
                 Ok(o) => o,
-                Err(_) => {
-                    reply.error(Errno::EIO);
+                Err(e) => {
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
@@ -842,7 +1254,13 @@ impl Filesystem for VaultFilesystem {
         });
     }
 
-    fn rmdir(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+    fn rmdir(&self, req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+        let _span = fuse_span("rmdir", u64::from(parent), None);
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
+        let caller = Caller::from(req);
         let name_str = match name.to_str() {
             Some(s) => s,
             None => {
@@ -868,6 +1286,14 @@ impl Filesystem for VaultFilesystem {
                 }
             };
 
+            if let Err(errno) = self
+                .check_access(caller, &parent_path, AccessFlags::W_OK | AccessFlags::X_OK)
+                .await
+            {
+                reply.error(errno);
+                return;
+            }
+
             let child_path = if parent_path == "/" {
                 format!("/{}", name_str)
             } else {
@@ -876,8 +1302,8 @@ impl Filesystem for VaultFilesystem {
 
             let ops = match VaultOperations::new(&session) {
                 Ok(o) => o,
-                Err(_) => {
-                    reply.error(Errno::EIO);
+                Err(e) => {
+                    reply.error(operation_errno(&e));
                     return;
                 }
             };
@@ -907,11 +1333,11 @@ impl Filesystem for VaultFilesystem {
 
     fn setattr(
         &self,
-        _req: &Request,
+        req: &Request,
         ino: INodeNo,
-        _mode: Option<u32>,
-        _uid: Option<u32>,
-        _gid: Option<u32>,
+        mode: Option<u32>,
+        uid: Option<u32>,
+        gid: Option<u32>,
         size: Option<u64>,
         _atime: Option<TimeOrNow>,
         _mtime: Option<TimeOrNow>,
@@ -923,10 +1349,468 @@ impl Filesystem for VaultFilesystem {
         _flags: Option<BsdFileFlags>,
         reply: ReplyAttr,
     ) {
+        let _span = fuse_span("setattr", u64::from(ino), fh.map(u64::from));
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
         debug!("setattr: ino={}, size={:?}", u64::from(ino), size);
 
-        // For now, just return current attributes
+        let change = NodePermissions { mode, uid, gid };
+        if change != NodePermissions::default() {
+            let caller = Caller::from(req);
+            let inodes = self.inodes.clone();
+            let result = self.runtime.block_on(async move {
+                let path_str = inodes
+                    .read()
+                    .await
+                    .get_path(ino)
+                    .map(str::to_string)
+                    .ok_or(Errno::ENOENT)?;
+                self.change_permissions(caller, &path_str, change).await
+            });
+            if let Err(errno) = result {
+                reply.error(errno);
+                return;
+            }
+        }
+
         // TODO: Implement truncation if size is set
-        self.getattr(_req, ino, fh, reply);
+        self.getattr(req, ino, fh, reply);
+    }
+
+    fn access(&self, req: &Request, ino: INodeNo, mask: AccessFlags, reply: ReplyEmpty) {
+        let _span = fuse_span("access", u64::from(ino), None);
+        if let Err(errno) = self.connected() {
+            reply.error(errno);
+            return;
+        }
+        debug!("access: ino={}, mask={}", u64::from(ino), mask);
+
+        let caller = Caller::from(req);
+        let inodes = self.inodes.clone();
+
+        self.runtime.block_on(async move {
+            let path_str = {
+                let map = inodes.read().await;
+                match map.get_path(ino) {
+                    Some(p) => p.to_string(),
+                    None => {
+                        reply.error(Errno::ENOENT);
+                        return;
+                    }
+                }
+            };
+
+            match self.check_access(caller, &path_str, mask).await {
+                Ok(()) => reply.ok(),
+                Err(errno) => reply.error(errno),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_child_vault_path_rejects_illegal_names() {
+        assert!(matches!(
+            child_vault_path("/docs", "a/b"),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            child_vault_path("/docs", "a\0b"),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_child_vault_path_accepts_control_characters() {
+        let path = child_vault_path("/", "line\nbreak").unwrap();
+        assert_eq!(path.name(), Some("line\nbreak"));
+    }
+
+    #[test]
+    fn test_child_vault_path_joins_under_parent() {
+        let path = child_vault_path("/docs", "notes.txt").unwrap();
+        assert_eq!(path.to_string(), "/docs/notes.txt");
+        let path = child_vault_path("/", "notes.txt").unwrap();
+        assert_eq!(path.to_string(), "/notes.txt");
+    }
+
+    /// A kernel that accepts readahead up to `limit`, recording the value
+    /// it was left with.
+    fn kernel(limit: u32, granted: &mut u32) -> impl FnMut(u32) -> Result<u32, u32> + '_ {
+        move |bytes| {
+            if bytes > limit {
+                return Err(limit);
+            }
+            *granted = bytes;
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_readahead_requests_configured_size() {
+        let mut granted = 0;
+        let reported = request_readahead(DEFAULT_MAX_READAHEAD, kernel(u32::MAX, &mut granted));
+        assert_eq!(reported, DEFAULT_MAX_READAHEAD);
+        assert_eq!(granted, DEFAULT_MAX_READAHEAD);
+    }
+
+    #[test]
+    fn test_readahead_falls_back_to_kernel_limit() {
+        let mut granted = 0;
+        let reported = request_readahead(DEFAULT_MAX_READAHEAD, kernel(128 * 1024, &mut granted));
+        assert_eq!(reported, 128 * 1024);
+        assert_eq!(granted, 128 * 1024);
+    }
+
+    #[tokio::test]
+    async fn test_mount_options_set_readahead() {
+        let fs = shared_filesystem(MountOptions::default()).await;
+        assert_eq!(fs.max_readahead, DEFAULT_MAX_READAHEAD);
+
+        let options = MountOptions {
+            max_readahead: 1024 * 1024,
+            ..Default::default()
+        };
+        let fs = VaultFilesystem::with_options(fs.session.clone(), Handle::current(), &options);
+        assert_eq!(fs.max_readahead, 1024 * 1024);
+    }
+
+    #[test]
+    fn test_locked_session_maps_to_eacces() {
+        assert_eq!(
+            operation_errno(&Error::NotPermitted("Session is locked".to_string())).code(),
+            Errno::EACCES.code()
+        );
+        assert_eq!(
+            operation_errno(&Error::Storage("disk".to_string())).code(),
+            Errno::EIO.code()
+        );
+    }
+
+    #[test]
+    fn test_read_range_clamps_to_buffer() {
+        assert_eq!(read_range(b"hello", 1, 3), b"ell");
+        assert_eq!(read_range(b"hello", 3, 4096), b"lo");
+        assert!(read_range(b"hello", 5, 10).is_empty());
+        assert!(read_range(b"hello", u64::MAX, u32::MAX).is_empty());
+    }
+
+    #[test]
+    fn test_write_at_rejects_overflowing_offsets() {
+        let mut buffer = b"hello".to_vec();
+        write_at(&mut buffer, 1, b"EL").unwrap();
+        write_at(&mut buffer, 7, b"!").unwrap();
+        assert_eq!(buffer, b"hELlo\0\0!");
+
+        for offset in [u64::MAX, u64::MAX - 1, isize::MAX as u64] {
+            assert_eq!(
+                write_at(&mut buffer, offset, b"xy").unwrap_err().code(),
+                Errno::EINVAL.code()
+            );
+        }
+        assert_eq!(buffer, b"hELlo\0\0!");
+    }
+
+    #[tokio::test]
+    async fn test_empty_file_reads_zero_bytes() {
+        let fs = shared_filesystem(MountOptions::default()).await;
+        let ops = VaultOperations::new(&fs.session).unwrap();
+        let path = VaultPath::parse("/.gitkeep").unwrap();
+        ops.create_file(&path, b"").await.unwrap();
+
+        // Mirrors `open` followed by `read`.
+        let buffer = ops.read_file(&path).await.unwrap();
+        assert!(buffer.is_empty());
+        assert!(read_range(&buffer, 0, 4096).is_empty());
+    }
+
+    const OWNER: Caller = Caller {
+        uid: 1000,
+        gid: 1000,
+    };
+    const OTHER: Caller = Caller {
+        uid: 2000,
+        gid: 2000,
+    };
+
+    fn errno(result: Result<(), Errno>) -> Option<i32> {
+        result.err().map(|e| e.code())
+    }
+
+    async fn shared_filesystem(options: MountOptions) -> VaultFilesystem {
+        let creation = axiomvault_vault::VaultManager::new()
+            .create_vault(
+                axiomvault_common::VaultId::new("fuse-access").unwrap(),
+                &axiomvault_common::Secret::from_slice(b"password"),
+                "memory",
+                serde_json::Value::Null,
+                axiomvault_crypto::KdfParams::interactive(),
+            )
+            .await
+            .unwrap();
+        let options = MountOptions {
+            allow_other: true,
+            umask: 0o022,
+            ..options
+        };
+        VaultFilesystem::with_access_policy(
+            Arc::new(creation.session),
+            Handle::current(),
+            AccessPolicy::new(OWNER, &options),
+        )
+    }
+
+    /// Create a node the way the `create`/`mkdir` callbacks do.
+    async fn create_as(fs: &VaultFilesystem, caller: Caller, path: &str, is_dir: bool, mode: u32) {
+        let ops = VaultOperations::new(&fs.session).unwrap();
+        let path = VaultPath::parse(path).unwrap();
+        if is_dir {
+            ops.create_directory(&path).await.unwrap();
+        } else {
+            ops.create_file(&path, b"secret").await.unwrap();
+        }
+        fs.record_creation(&ops, &path, is_dir, caller, mode, 0o022)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_control_characters_round_trip_through_mount() {
+        let fs = shared_filesystem(MountOptions::default()).await;
+        let ops = VaultOperations::new(&fs.session).unwrap();
+        // What `create` builds for the name, and what `readdir` and
+        // `lookup` build for the listed entry.
+        let path = child_vault_path("/", "line\nbreak").unwrap();
+        create_as(&fs, OWNER, &path.to_string(), false, 0o100644).await;
+
+        let entries = ops.list_directory(&VaultPath::root()).await.unwrap();
+        let names: Vec<_> = entries.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["line\nbreak"]);
+        let child_path = format!("/{}", names[0]);
+        assert_eq!(VaultPath::parse(&child_path).unwrap(), path);
+        assert!(fs
+            .check_access(OWNER, &child_path, AccessFlags::R_OK)
+            .await
+            .is_ok());
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"secret");
+    }
+
+    #[tokio::test]
+    async fn test_locked_session_degrades_mount_until_reauthenticated() {
+        use crate::watchdog::{MountState, MountWatchdog, WatchdogConfig};
+        use axiomvault_vault::VaultEvent;
+
+        let fs = shared_filesystem(MountOptions {
+            default_permissions: false,
+            ..Default::default()
+        })
+        .await;
+        create_as(&fs, OWNER, "/notes.txt", false, 0o100644).await;
+        let mut events = fs.session.subscribe();
+        fs.open_files.write().await.insert(
+            FileHandle(1),
+            OpenFile {
+                path: "/notes.txt".to_string(),
+                buffer: b"unsaved".to_vec(),
+                dirty: true,
+            },
+        );
+        let mut watchdog = MountWatchdog::new(
+            fs.session.clone(),
+            fs.health(),
+            "/mnt/vault",
+            WatchdogConfig {
+                failure_threshold: 2,
+                ..Default::default()
+            },
+        );
+
+        // Until the watchdog notices, a locked session fails like any other
+        // vault error and dirty buffers are kept.
+        fs.session.lock();
+        assert_eq!(fs.dirty_buffers().flush().await, 1);
+        assert_eq!(
+            errno(
+                fs.check_access(OWNER, "/notes.txt", AccessFlags::R_OK)
+                    .await
+            ),
+            Some(libc::EACCES)
+        );
+        assert_eq!(watchdog.check().await, MountState::Healthy);
+        assert!(matches!(
+            watchdog.check().await,
+            MountState::Degraded { .. }
+        ));
+        assert!(
+            std::iter::from_fn(|| events.try_recv().ok()).any(|event| matches!(
+                event,
+                VaultEvent::MountDegraded { mount_point, .. } if mount_point == "/mnt/vault"
+            ))
+        );
+        assert_eq!(
+            errno(
+                fs.check_access(OWNER, "/notes.txt", AccessFlags::R_OK)
+                    .await
+            ),
+            Some(libc::ENOTCONN)
+        );
+
+        // Unlocking the session brings the mount back with its buffers.
+        fs.session
+            .reauthenticate(&axiomvault_common::Secret::from_slice(b"password"), None)
+            .unwrap();
+        assert_eq!(watchdog.check().await, MountState::Healthy);
+        assert!(fs
+            .check_access(OWNER, "/notes.txt", AccessFlags::R_OK)
+            .await
+            .is_ok());
+        assert_eq!(fs.dirty_buffers().flush().await, 0);
+        let ops = VaultOperations::new(&fs.session).unwrap();
+        let saved = ops
+            .read_file(&VaultPath::parse("/notes.txt").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(saved, b"unsaved");
+    }
+
+    #[tokio::test]
+    async fn test_other_users_are_held_to_node_mode_and_owner() {
+        let fs = shared_filesystem(MountOptions {
+            default_permissions: false,
+            ..Default::default()
+        })
+        .await;
+        create_as(&fs, OWNER, "/private.txt", false, 0o100600).await;
+        create_as(&fs, OWNER, "/shared", true, 0o040755).await;
+
+        // The owner keeps full access.
+        let rw = AccessFlags::R_OK | AccessFlags::W_OK;
+        assert!(fs.check_access(OWNER, "/private.txt", rw).await.is_ok());
+        assert!(fs
+            .check_access(OWNER, "/shared", AccessFlags::W_OK | AccessFlags::X_OK)
+            .await
+            .is_ok());
+
+        // Others are denied what the mode does not grant them.
+        assert_eq!(
+            errno(
+                fs.check_access(OTHER, "/private.txt", AccessFlags::R_OK)
+                    .await
+            ),
+            Some(libc::EACCES)
+        );
+        assert!(fs
+            .check_access(OTHER, "/shared", AccessFlags::R_OK | AccessFlags::X_OK)
+            .await
+            .is_ok());
+        assert_eq!(
+            errno(
+                fs.check_access(OTHER, "/shared", AccessFlags::W_OK | AccessFlags::X_OK)
+                    .await
+            ),
+            Some(libc::EACCES)
+        );
+
+        // Only the owner may chmod, and nobody but root may give a node away.
+        let chmod = NodePermissions {
+            mode: Some(0o644),
+            ..Default::default()
+        };
+        assert_eq!(
+            errno(fs.change_permissions(OTHER, "/private.txt", chmod).await),
+            Some(libc::EPERM)
+        );
+        let chown = NodePermissions {
+            uid: Some(OTHER.uid),
+            ..Default::default()
+        };
+        assert_eq!(
+            errno(fs.change_permissions(OWNER, "/private.txt", chown).await),
+            Some(libc::EPERM)
+        );
+        fs.change_permissions(OWNER, "/private.txt", chmod)
+            .await
+            .unwrap();
+        assert!(fs
+            .check_access(OTHER, "/private.txt", AccessFlags::R_OK)
+            .await
+            .is_ok());
+        assert_eq!(
+            errno(
+                fs.check_access(OTHER, "/private.txt", AccessFlags::W_OK)
+                    .await
+            ),
+            Some(libc::EACCES)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nodes_created_by_other_users_belong_to_them() {
+        let fs = shared_filesystem(MountOptions {
+            default_permissions: false,
+            ..Default::default()
+        })
+        .await;
+        create_as(&fs, OTHER, "/theirs.txt", false, 0o100600).await;
+
+        let ops = VaultOperations::new(&fs.session).unwrap();
+        let stored = ops
+            .permissions(&VaultPath::parse("/theirs.txt").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(stored.uid, Some(OTHER.uid));
+        assert_eq!(stored.mode, Some(0o600));
+
+        assert!(fs
+            .check_access(OTHER, "/theirs.txt", AccessFlags::W_OK)
+            .await
+            .is_ok());
+        assert_eq!(
+            errno(
+                fs.check_access(OWNER, "/theirs.txt", AccessFlags::R_OK)
+                    .await
+            ),
+            Some(libc::EACCES)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_allowed_uids_restrict_shared_mounts() {
+        let fs = shared_filesystem(MountOptions {
+            allowed_uids: vec![OTHER.uid],
+            ..Default::default()
+        })
+        .await;
+        create_as(&fs, OWNER, "/notes.txt", false, 0o100644).await;
+        let stranger = Caller {
+            uid: 3000,
+            gid: 3000,
+        };
+
+        assert!(fs
+            .check_access(OTHER, "/notes.txt", AccessFlags::R_OK)
+            .await
+            .is_ok());
+        assert!(fs
+            .check_access(OWNER, "/notes.txt", AccessFlags::W_OK)
+            .await
+            .is_ok());
+        assert_eq!(
+            errno(fs.check_access(stranger, "/", AccessFlags::X_OK).await),
+            Some(libc::EACCES)
+        );
+        assert_eq!(
+            errno(
+                fs.change_permissions(stranger, "/notes.txt", NodePermissions::default())
+                    .await
+            ),
+            Some(libc::EACCES)
+        );
     }
 }
@@ -10,17 +10,35 @@
 //! # Feature Flags
 //! - `fuse`: Enable FUSE support (requires libfuse3-dev on Linux or macFUSE on macOS)
 
+#[cfg(feature = "fuse")]
+pub mod access;
+
 #[cfg(feature = "fuse")]
 pub mod filesystem;
 
 #[cfg(feature = "fuse")]
 pub mod mount;
 
+pub mod unmount;
+
+#[cfg(feature = "fuse")]
+pub mod watchdog;
+
+#[cfg(feature = "fuse")]
+pub use access::{AccessPolicy, Caller};
+
 #[cfg(feature = "fuse")]
 pub use filesystem::VaultFilesystem;
 
 #[cfg(feature = "fuse")]
-pub use mount::{MountHandle, MountOptions};
+pub use mount::{MountHandle, MountOptions, DEFAULT_MAX_READAHEAD};
+
+pub use unmount::{
+    open_file_holders, process_name, release_mount_point, UnmountMode, UnmountRetry,
+};
+
+#[cfg(feature = "fuse")]
+pub use watchdog::{MountHealth, MountState, MountWatchdog, WatchdogConfig};
 
 /// Stub module for when FUSE is not available.
 #[cfg(not(feature = "fuse"))]
@@ -35,6 +53,9 @@ pub mod mount {
         pub auto_unmount: bool,
         pub read_only: bool,
         pub default_permissions: bool,
+        pub umask: u32,
+        pub allowed_uids: Vec<u32>,
+        pub max_readahead: u32,
     }
 
     /// Mount handle placeholder.
@@ -47,9 +68,17 @@ pub mod mount {
             &self.mount_point
         }
 
+        pub fn is_mounted(&self) -> bool {
+            false
+        }
+
         pub fn unmount(self) {
             drop(self);
         }
+
+        pub async fn unmount_and_wait(self, _deadline: std::time::Duration) -> Result<()> {
+            Ok(())
+        }
     }
 
     /// Check if FUSE is available (always false without feature).
@@ -4,12 +4,15 @@
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use fuser::{BackgroundSession, Config, MountOption, SessionACL};
 use tokio::runtime::Handle;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 
-use crate::filesystem::VaultFilesystem;
+use crate::filesystem::{DirtyBuffers, VaultFilesystem};
+use crate::unmount::{unmount_path, unmount_with_retry, UnmountRetry};
+use crate::watchdog::{MountHealth, MountState, MountWatchdog, WatchdogConfig};
 use axiomvault_common::{Error, Result};
 use axiomvault_vault::VaultSession;
 
@@ -18,14 +21,32 @@ use axiomvault_vault::VaultSession;
 pub struct MountOptions {
     /// Allow other users to access the mount.
     pub allow_other: bool,
-    /// Enable auto unmount when process exits.
+    /// Have the mount helper unmount when the process exits, even if it is
+    /// killed. fuser only allows this together with `allow_other`; owner-only
+    /// mounts rely on the handle being dropped.
     pub auto_unmount: bool,
     /// Read-only mount.
     pub read_only: bool,
-    /// Default permissions.
+    /// Let the kernel check mode bits instead of the filesystem.
     pub default_permissions: bool,
+    /// Bits cleared from the mode of new nodes and of nodes without a
+    /// stored mode.
+    pub umask: u32,
+    /// With `allow_other`, the only users besides the owner and root who
+    /// may use the mount. Empty admits everyone the mode bits allow.
+    pub allowed_uids: Vec<u32>,
+    /// Largest readahead, in bytes, the kernel is asked to use, so that
+    /// sequential readers such as media players issue fewer, larger reads.
+    /// Kernels with a lower limit use theirs.
+    pub max_readahead: u32,
+    /// Health monitoring of the session behind the mount; `None` serves
+    /// requests without checking it.
+    pub watchdog: Option<WatchdogConfig>,
 }
 
+/// Default [`MountOptions::max_readahead`].
+pub const DEFAULT_MAX_READAHEAD: u32 = 8 * 1024 * 1024;
+
 impl Default for MountOptions {
     fn default() -> Self {
         Self {
@@ -33,19 +54,29 @@ impl Default for MountOptions {
             auto_unmount: true,
             read_only: false,
             default_permissions: true,
+            umask: 0o077,
+            allowed_uids: Vec::new(),
+            max_readahead: DEFAULT_MAX_READAHEAD,
+            watchdog: Some(WatchdogConfig::default()),
         }
     }
 }
 
 /// Handle to a mounted FUSE filesystem.
 ///
-/// The mount is automatically unmounted when this handle is dropped.
-/// Internally backed by a `fuser::BackgroundSession` which owns the OS mount
-/// and the FUSE request-servicing thread.
+/// The mount is unmounted when this handle is dropped: the kernel mount is
+/// released and the FUSE request-servicing thread is joined, so dropping
+/// blocks until in-flight requests finish.
 pub struct MountHandle {
     mount_point: PathBuf,
-    /// Keeps the background FUSE thread alive. Dropping this unmounts.
-    _session: BackgroundSession,
+    /// The OS mount and the background FUSE thread; `None` once torn down.
+    session: Option<BackgroundSession>,
+    /// Open-file buffers of the mounted filesystem.
+    buffers: DirtyBuffers,
+    /// Whether the filesystem is serving requests.
+    health: MountHealth,
+    /// Task running the [`MountWatchdog`], if one was configured.
+    watchdog: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl MountHandle {
@@ -54,19 +85,147 @@ impl MountHandle {
         &self.mount_point
     }
 
+    /// Whether the filesystem is still being served.
+    ///
+    /// Turns false once the handle unmounts, or if the mount goes away
+    /// underneath it (e.g. an external `fusermount -u`).
+    pub fn is_mounted(&self) -> bool {
+        self.session
+            .as_ref()
+            .is_some_and(|session| !session.guard.is_finished())
+    }
+
+    /// Whether the filesystem is serving requests or refusing them because
+    /// its session stopped being usable.
+    pub fn state(&self) -> MountState {
+        self.health.state()
+    }
+
     /// Unmount the filesystem.
     ///
     /// This is automatically called when the handle is dropped.
-    pub fn unmount(self) {
-        // Drop will handle unmounting
-        drop(self);
+    pub fn unmount(mut self) {
+        self.teardown();
+    }
+
+    /// Save dirty file buffers, then unmount, waiting at most `deadline`.
+    ///
+    /// Changes written to files that are still open are saved to the vault
+    /// before the kernel mount is released. If flushing runs out of time
+    /// the unmount still goes ahead; if unmounting does too, it finishes on
+    /// a background thread and an error is returned.
+    ///
+    /// # Errors
+    /// - `Error::Vault` if some dirty buffers could not be saved
+    /// - `Error::Cancelled` if the deadline passed before the mount was
+    ///   released
+    /// - `Error::Io` if unmounting failed
+    pub async fn unmount_and_wait(mut self, deadline: Duration) -> Result<()> {
+        let started = Instant::now();
+        let flushed = match tokio::time::timeout(deadline, self.buffers.flush()).await {
+            Ok(0) => Ok(()),
+            Ok(failed) => Err(Error::Vault(format!(
+                "{} open files could not be saved before unmount",
+                failed
+            ))),
+            Err(_) => {
+                warn!("Flushing open files timed out; unmounting anyway");
+                Err(Error::Cancelled(format!(
+                    "open files were not saved within {:?}",
+                    deadline
+                )))
+            }
+        };
+
+        self.stop_watchdog();
+        let Some(session) = self.session.take() else {
+            return flushed;
+        };
+        info!("Unmounting vault");
+        let join = tokio::task::spawn_blocking(move || session.umount_and_join());
+        let remaining = deadline.saturating_sub(started.elapsed());
+        match tokio::time::timeout(remaining, join).await {
+            Ok(Ok(Ok(()))) => flushed,
+            Ok(Ok(Err(e))) => Err(Error::Io(e)),
+            Ok(Err(e)) => Err(Error::Io(std::io::Error::other(e))),
+            Err(_) => Err(Error::Cancelled(format!(
+                "unmount did not finish within {:?}",
+                deadline
+            ))),
+        }
+    }
+
+    /// Save dirty file buffers, then release the mount with the platform
+    /// unmount helper, waiting out processes that still hold files open.
+    ///
+    /// Unlike [`unmount_and_wait`](Self::unmount_and_wait), a busy mount
+    /// is retried with backoff as `retry` allows, and the handle stays
+    /// usable if it is still busy afterwards, so the caller can show the
+    /// holders and try again.
+    ///
+    /// # Errors
+    /// - `Error::MountBusy` with the processes holding files open
+    /// - `Error::Vault` if some dirty buffers could not be saved; the
+    ///   mount is released all the same
+    /// - Whatever the unmount helper fails with
+    pub async fn unmount_with_retry(&mut self, retry: &UnmountRetry) -> Result<()> {
+        let failed = self.buffers.flush().await;
+        if self.session.is_none() {
+            return Ok(());
+        }
+
+        let mount_point = self.mount_point.clone();
+        unmount_with_retry(retry, |mode| {
+            let mount_point = mount_point.clone();
+            async move {
+                tokio::task::spawn_blocking(move || unmount_path(&mount_point, mode))
+                    .await
+                    .map_err(|e| Error::Io(std::io::Error::other(e)))?
+            }
+        })
+        .await?;
+
+        // The kernel mount is gone, so the FUSE thread is finishing.
+        self.stop_watchdog();
+        if let Some(session) = self.session.take() {
+            let joined = tokio::task::spawn_blocking(move || session.join()).await;
+            if !matches!(joined, Ok(Ok(()))) {
+                debug!("FUSE thread did not finish cleanly after unmount");
+            }
+        }
+        info!("Vault unmounted");
+
+        if failed > 0 {
+            return Err(Error::Vault(format!(
+                "{} open files could not be saved before unmount",
+                failed
+            )));
+        }
+        Ok(())
+    }
+
+    fn stop_watchdog(&mut self) {
+        if let Some(watchdog) = self.watchdog.take() {
+            watchdog.abort();
+        }
+    }
+
+    /// Unmount and join the background thread, once.
+    fn teardown(&mut self) {
+        self.stop_watchdog();
+        let Some(session) = self.session.take() else {
+            return;
+        };
+        info!("Unmounting vault");
+        if let Err(e) = session.umount_and_join() {
+            warn!(error = %e, "Failed to unmount vault cleanly");
+        }
     }
 }
 
 impl Drop for MountHandle {
     fn drop(&mut self) {
-        info!("Unmounting vault");
-        // BackgroundSession::drop() joins the background thread and calls umount2.
+        self.teardown();
     }
 }
 
@@ -118,7 +277,9 @@ pub fn mount(
     info!("Mounting vault");
 
     // Create filesystem
-    let fs = VaultFilesystem::new(session, runtime);
+    let fs = VaultFilesystem::with_options(session.clone(), runtime.clone(), &options);
+    let buffers = fs.dirty_buffers();
+    let health = fs.health();
 
     // Configure mount options
     let mut config = Config::default();
@@ -134,8 +295,12 @@ pub fn mount(
         SessionACL::Owner
     };
 
-    if options.auto_unmount {
+    // fuser refuses auto-unmount on owner-only mounts; without it a killed
+    // process leaves the mount behind for `unmount` to release.
+    if options.auto_unmount && options.allow_other {
         config.mount_options.push(MountOption::AutoUnmount);
+    } else if options.auto_unmount {
+        debug!("auto_unmount needs allow_other; unmounting on drop only");
     }
 
     if options.read_only {
@@ -158,9 +323,17 @@ pub fn mount(
 
     info!("Vault mounted successfully");
 
+    let watchdog = options.watchdog.map(|config| {
+        let watchdog = MountWatchdog::new(session, health.clone(), mount_point.clone(), config);
+        runtime.spawn(watchdog.run())
+    });
+
     Ok(MountHandle {
         mount_point,
-        _session: bg_session,
+        session: Some(bg_session),
+        buffers,
+        health,
+        watchdog,
     })
 }
 
@@ -225,6 +398,8 @@ mod tests {
         assert!(opts.auto_unmount);
         assert!(!opts.read_only);
         assert!(opts.default_permissions);
+        assert_eq!(opts.umask, 0o077);
+        assert!(opts.allowed_uids.is_empty());
     }
 
     #[test]
@@ -232,4 +407,44 @@ mod tests {
         let info = fuse_info();
         assert!(!info.is_empty());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dropping_handle_unmounts() {
+        if !is_fuse_available() {
+            eprintln!("skipping: {}", fuse_info());
+            return;
+        }
+
+        let creation = axiomvault_vault::VaultManager::new()
+            .create_vault(
+                axiomvault_common::VaultId::new("fuse-drop").unwrap(),
+                &axiomvault_common::Secret::from_slice(b"password"),
+                "memory",
+                serde_json::Value::Null,
+                axiomvault_crypto::KdfParams::interactive(),
+            )
+            .await
+            .unwrap();
+        let mount_point = tempfile::tempdir().unwrap();
+        let handle = mount(
+            Arc::new(creation.session),
+            mount_point.path(),
+            MountOptions::default(),
+            Handle::current(),
+        )
+        .unwrap();
+        assert!(handle.is_mounted());
+
+        // Blocking file I/O must stay off the runtime serving the mount.
+        let root = mount_point.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            std::fs::write(root.join("inside.txt"), b"mounted").unwrap();
+            assert_eq!(std::fs::read_dir(&root).unwrap().count(), 1);
+            drop(handle);
+            // Back to the plain, empty directory underneath.
+            assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+        })
+        .await
+        .unwrap();
+    }
 }
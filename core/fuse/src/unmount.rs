@@ -0,0 +1,296 @@
+//! Releasing mount points with the platform's unmount helper.
+//!
+//! A mount whose files are still open cannot be released: the helper fails
+//! with "device busy". [`unmount_with_retry`] waits for the holders with
+//! backoff, reports who they are through [`Error::MountBusy`], and can fall
+//! back to a lazy unmount that detaches the mount now and lets the kernel
+//! finish once the last file closes.
+//!
+//! None of this needs libfuse, so it is available without the `fuse`
+//! feature for releasing mounts left behind by another process.
+
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use axiomvault_common::{Error, Result};
+
+/// How an unmount helper is asked to release a mount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnmountMode {
+    /// Release the mount; fails while files on it are open.
+    Normal,
+    /// Detach the mount even if it is busy (`fusermount -uz`, `umount -f`).
+    Lazy,
+}
+
+/// Retry policy for releasing a busy mount.
+#[derive(Debug, Clone)]
+pub struct UnmountRetry {
+    /// Normal unmount attempts before giving up or falling back.
+    pub attempts: u32,
+    /// Wait after the first busy attempt; doubles after each further one.
+    pub initial_delay: Duration,
+    /// Detach the mount lazily once every normal attempt found it busy.
+    pub lazy_fallback: bool,
+}
+
+impl Default for UnmountRetry {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            initial_delay: Duration::from_millis(200),
+            lazy_fallback: false,
+        }
+    }
+}
+
+/// Release `mount_point` with the platform's unmount helper.
+///
+/// Tries `fusermount3` and then `fusermount` on Linux, `umount` on macOS.
+///
+/// # Errors
+/// - `MountBusy` with the processes holding files open if the mount is busy
+/// - `Io` if the helper failed for another reason
+/// - `Unsupported` if no helper is installed
+pub fn unmount_path(mount_point: &Path, mode: UnmountMode) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let helpers: &[(&str, &[&str])] = match mode {
+        UnmountMode::Normal => &[("umount", &[])],
+        UnmountMode::Lazy => &[("umount", &["-f"])],
+    };
+    #[cfg(not(target_os = "macos"))]
+    let helpers: &[(&str, &[&str])] = match mode {
+        UnmountMode::Normal => &[("fusermount3", &["-u"]), ("fusermount", &["-u"])],
+        UnmountMode::Lazy => &[("fusermount3", &["-uz"]), ("fusermount", &["-uz"])],
+    };
+
+    for (program, args) in helpers {
+        let output = match std::process::Command::new(program)
+            .args(*args)
+            .arg(mount_point)
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::Io(e)),
+        };
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.to_lowercase().contains("busy") {
+            return Err(Error::MountBusy(open_file_holders(mount_point)));
+        }
+        return Err(Error::Io(std::io::Error::other(format!(
+            "{} failed: {}",
+            program,
+            stderr.trim()
+        ))));
+    }
+
+    Err(Error::Unsupported(
+        "No unmount helper found. Install the fuse3 package.".to_string(),
+    ))
+}
+
+/// Release `mount_point`, waiting for busy holders as `retry` allows.
+///
+/// Blocking helper calls run off the async workers.
+///
+/// # Errors
+/// As [`unmount_with_retry`].
+pub async fn release_mount_point(mount_point: &Path, retry: &UnmountRetry) -> Result<()> {
+    unmount_with_retry(retry, |mode| {
+        let mount_point = mount_point.to_path_buf();
+        async move {
+            tokio::task::spawn_blocking(move || unmount_path(&mount_point, mode))
+                .await
+                .map_err(|e| Error::Io(std::io::Error::other(e)))?
+        }
+    })
+    .await
+}
+
+/// Run `unmount` until it succeeds or the mount stays busy past `retry`.
+///
+/// Only [`Error::MountBusy`] is retried; any other failure is returned at
+/// once. After the last busy attempt the mount is detached lazily if
+/// `retry.lazy_fallback` is set.
+///
+/// # Errors
+/// - `MountBusy` with the holders seen on the last attempt
+/// - Whatever else `unmount` fails with
+pub async fn unmount_with_retry<F, Fut>(retry: &UnmountRetry, mut unmount: F) -> Result<()>
+where
+    F: FnMut(UnmountMode) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut delay = retry.initial_delay;
+    let mut holders = Vec::new();
+    for attempt in 1..=retry.attempts.max(1) {
+        match unmount(UnmountMode::Normal).await {
+            Ok(()) => return Ok(()),
+            Err(Error::MountBusy(pids)) => {
+                debug!(attempt, holders = ?pids, "Mount point busy");
+                holders = pids;
+            }
+            Err(e) => return Err(e),
+        }
+        if attempt < retry.attempts {
+            tokio::time::sleep(delay).await;
+            delay = delay.saturating_mul(2);
+        }
+    }
+
+    if retry.lazy_fallback {
+        warn!(holders = ?holders, "Mount point still busy; detaching it lazily");
+        unmount(UnmountMode::Lazy).await?;
+        info!("Mount point detached");
+        return Ok(());
+    }
+    Err(Error::MountBusy(holders))
+}
+
+/// Processes with a file, working directory or root under `mount_point`.
+///
+/// Read from `/proc` on Linux, like `lsof` would; processes of other users
+/// are only visible to root. Elsewhere nothing is found.
+pub fn open_file_holders(mount_point: &Path) -> Vec<u32> {
+    #[cfg(target_os = "linux")]
+    {
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+        let mut pids: Vec<u32> = entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter(|pid| process_uses(*pid, mount_point))
+            .collect();
+        pids.sort_unstable();
+        pids
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = mount_point;
+        Vec::new()
+    }
+}
+
+/// The command name of process `pid`, where the platform exposes it.
+pub fn process_name(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|name| name.trim_end().to_string())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_uses(pid: u32, mount_point: &Path) -> bool {
+    let proc_dir = Path::new("/proc").join(pid.to_string());
+    let under =
+        |link: &Path| std::fs::read_link(link).is_ok_and(|target| target.starts_with(mount_point));
+    if under(&proc_dir.join("cwd")) || under(&proc_dir.join("root")) {
+        return true;
+    }
+    std::fs::read_dir(proc_dir.join("fd"))
+        .map(|fds| fds.flatten().any(|fd| under(&fd.path())))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn quick(attempts: u32, lazy_fallback: bool) -> UnmountRetry {
+        UnmountRetry {
+            attempts,
+            initial_delay: Duration::from_millis(1),
+            lazy_fallback,
+        }
+    }
+
+    /// An unmount that reports busy for the first `busy` normal attempts.
+    fn busy_for(
+        busy: usize,
+        calls: &Mutex<Vec<UnmountMode>>,
+    ) -> impl FnMut(UnmountMode) -> std::future::Ready<Result<()>> + '_ {
+        move |mode| {
+            let mut calls = calls.lock().unwrap();
+            calls.push(mode);
+            let normal = calls.iter().filter(|m| **m == UnmountMode::Normal).count();
+            std::future::ready(if mode == UnmountMode::Normal && normal <= busy {
+                Err(Error::MountBusy(vec![4242]))
+            } else {
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_busy_mount_is_retried_until_released() {
+        let calls = Mutex::new(Vec::new());
+        unmount_with_retry(&quick(5, false), busy_for(2, &calls))
+            .await
+            .unwrap();
+        assert_eq!(*calls.lock().unwrap(), vec![UnmountMode::Normal; 3]);
+    }
+
+    #[tokio::test]
+    async fn test_persistently_busy_mount_reports_holders() {
+        let calls = Mutex::new(Vec::new());
+        let result = unmount_with_retry(&quick(3, false), busy_for(usize::MAX, &calls)).await;
+        assert!(matches!(result, Err(Error::MountBusy(pids)) if pids == vec![4242]));
+        assert_eq!(calls.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_busy_mount_falls_back_to_lazy_unmount() {
+        let calls = Mutex::new(Vec::new());
+        unmount_with_retry(&quick(2, true), busy_for(usize::MAX, &calls))
+            .await
+            .unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![UnmountMode::Normal, UnmountMode::Normal, UnmountMode::Lazy]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_other_unmount_failures_are_not_retried() {
+        let mut calls = 0;
+        let result = unmount_with_retry(&quick(5, true), |_| {
+            calls += 1;
+            std::future::ready(Err(Error::Unsupported("no helper".to_string())))
+        })
+        .await;
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+        assert_eq!(calls, 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_open_file_holders_finds_this_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("held.txt");
+        std::fs::write(&path, b"open").unwrap();
+        let before = open_file_holders(dir.path());
+        assert!(!before.contains(&std::process::id()));
+
+        let _file = std::fs::File::open(&path).unwrap();
+        assert!(open_file_holders(dir.path()).contains(&std::process::id()));
+        assert!(process_name(std::process::id()).is_some());
+    }
+}
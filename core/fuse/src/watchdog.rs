@@ -0,0 +1,222 @@
+//! Health monitoring for mounted vaults.
+//!
+//! A mount keeps serving requests for as long as its handle lives, but the
+//! session behind it can stop being usable: it locks (by hand, by idle
+//! timeout or on reaching its byte limit) or its storage stops answering.
+//! Left alone, every request then fails with `EIO` and the mount looks
+//! broken rather than disconnected.
+//!
+//! [`MountWatchdog`] checks the session periodically. After
+//! [`WatchdogConfig::failure_threshold`] failed checks in a row it marks
+//! the mount degraded: requests fail with `ENOTCONN`, and a
+//! [`VaultEvent::MountDegraded`] is emitted on the session. A passing check
+//! marks it healthy again, e.g. after the session was re-authenticated. If
+//! [`WatchdogConfig::unmount_after`] is set and the mount stays degraded
+//! that long, it is released with the platform unmount helper, falling back
+//! to a lazy unmount when busy.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+use crate::unmount::{unmount_path, UnmountMode};
+use axiomvault_common::{Error, VaultPath};
+use axiomvault_vault::{VaultEvent, VaultSession};
+
+/// Whether a mount is serving requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MountState {
+    /// Requests reach the vault.
+    Healthy,
+    /// Requests fail with `ENOTCONN`.
+    Degraded {
+        /// Why the last health check failed.
+        reason: String,
+    },
+}
+
+/// Mount state shared between a filesystem, its watchdog and its handle.
+#[derive(Debug, Clone)]
+pub struct MountHealth {
+    inner: Arc<HealthInner>,
+}
+
+#[derive(Debug)]
+struct HealthInner {
+    /// Mirrors `state` so every request can check it without locking.
+    degraded: AtomicBool,
+    state: Mutex<MountState>,
+}
+
+impl MountHealth {
+    /// A healthy mount.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(HealthInner {
+                degraded: AtomicBool::new(false),
+                state: Mutex::new(MountState::Healthy),
+            }),
+        }
+    }
+
+    /// The current state.
+    pub fn state(&self) -> MountState {
+        self.inner
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Whether requests are being refused.
+    pub fn is_degraded(&self) -> bool {
+        self.inner.degraded.load(Ordering::Acquire)
+    }
+
+    /// Move to `state`; returns whether that changed healthy/degraded.
+    fn set(&self, state: MountState) -> bool {
+        let degraded = matches!(state, MountState::Degraded { .. });
+        *self.inner.state.lock().unwrap_or_else(|e| e.into_inner()) = state;
+        self.inner.degraded.swap(degraded, Ordering::AcqRel) != degraded
+    }
+}
+
+impl Default for MountHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How a mount's session is monitored.
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// Time between health checks.
+    pub interval: Duration,
+    /// Failed checks in a row before the mount is marked degraded.
+    pub failure_threshold: u32,
+    /// Release the mount once it has been degraded this long. `None`
+    /// leaves a degraded mount in place until its handle unmounts it.
+    pub unmount_after: Option<Duration>,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            failure_threshold: 3,
+            unmount_after: None,
+        }
+    }
+}
+
+/// Periodic health check of the session behind a mount.
+pub struct MountWatchdog {
+    session: Arc<VaultSession>,
+    health: MountHealth,
+    mount_point: PathBuf,
+    config: WatchdogConfig,
+    failures: u32,
+}
+
+impl MountWatchdog {
+    /// Watch `session`, mounted at `mount_point`, recording into `health`.
+    pub fn new(
+        session: Arc<VaultSession>,
+        health: MountHealth,
+        mount_point: impl Into<PathBuf>,
+        config: WatchdogConfig,
+    ) -> Self {
+        Self {
+            session,
+            health,
+            mount_point: mount_point.into(),
+            config,
+            failures: 0,
+        }
+    }
+
+    /// Run one health check and return the resulting state.
+    ///
+    /// A check fails if the session is locked or its storage provider
+    /// cannot be reached.
+    pub async fn check(&mut self) -> MountState {
+        let failure = if !self.session.is_active() {
+            Some("vault session is locked".to_string())
+        } else {
+            match self.session.provider().exists(&VaultPath::root()).await {
+                Ok(_) => None,
+                Err(e) => Some(format!("storage health check failed: {}", e)),
+            }
+        };
+
+        match failure {
+            None => {
+                self.failures = 0;
+                if self.health.set(MountState::Healthy) {
+                    info!("Mount recovered");
+                }
+            }
+            Some(reason) => {
+                self.failures += 1;
+                if self.failures >= self.config.failure_threshold.max(1)
+                    && !self.health.is_degraded()
+                {
+                    warn!(reason = %reason, "Mount degraded");
+                    self.health.set(MountState::Degraded {
+                        reason: reason.clone(),
+                    });
+                    self.session.emit(VaultEvent::MountDegraded {
+                        mount_point: self.mount_point.display().to_string(),
+                        reason,
+                    });
+                }
+            }
+        }
+        self.health.state()
+    }
+
+    /// Check every [`interval`](WatchdogConfig::interval) until the mount
+    /// is released for staying degraded past
+    /// [`unmount_after`](WatchdogConfig::unmount_after).
+    ///
+    /// Runs forever otherwise; abort the task when the mount goes away.
+    pub async fn run(mut self) {
+        let mut degraded_since: Option<Instant> = None;
+        loop {
+            tokio::time::sleep(self.config.interval).await;
+            if self.check().await == MountState::Healthy {
+                degraded_since = None;
+                continue;
+            }
+            let since = *degraded_since.get_or_insert_with(Instant::now);
+            let Some(grace) = self.config.unmount_after else {
+                continue;
+            };
+            if since.elapsed() < grace {
+                continue;
+            }
+
+            warn!("Releasing mount that stayed degraded for {:?}", grace);
+            let mount_point = self.mount_point.clone();
+            let released = tokio::task::spawn_blocking(move || {
+                match unmount_path(&mount_point, UnmountMode::Normal) {
+                    Err(Error::MountBusy(holders)) => {
+                        warn!(holders = ?holders, "Degraded mount busy; detaching lazily");
+                        unmount_path(&mount_point, UnmountMode::Lazy)
+                    }
+                    result => result,
+                }
+            })
+            .await;
+            match released {
+                Ok(Ok(())) => info!("Degraded mount released"),
+                Ok(Err(e)) => warn!(error = %e, "Failed to release degraded mount"),
+                Err(e) => warn!(error = %e, "Unmount task failed"),
+            }
+            return;
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! Hook definitions.
+//!
+//! A [`HooksConfig`] is client-side configuration: the CLI keeps it next to
+//! its other per-vault settings and desktop shells keep it with their
+//! settings. It is never written into the vault.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use axiomvault_common::{Error, Result};
+
+use crate::event::HookEvent;
+
+/// Timeout of one hook action when the config gives none.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Minimum time between two runs of one hook when the config gives none.
+pub const DEFAULT_MIN_INTERVAL_SECS: u64 = 1;
+
+/// Hooks of one vault.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Whether command hooks may run. Off unless the user turns it on.
+    #[serde(default)]
+    pub allow_exec_hooks: bool,
+    /// The hooks, in the order they are run.
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+}
+
+impl HooksConfig {
+    /// Check every hook, refusing command hooks unless they are allowed.
+    pub fn validate(&self) -> Result<()> {
+        for (index, hook) in self.hooks.iter().enumerate() {
+            hook.validate().map_err(|e| match e {
+                Error::InvalidInput(reason) => {
+                    Error::InvalidInput(format!("{}: {}", hook.label(index), reason))
+                }
+                e => e,
+            })?;
+            if matches!(hook.action, HookAction::Command { .. }) && !self.allow_exec_hooks {
+                return Err(Error::NotPermitted(format!(
+                    "{} runs a command, but command hooks are not allowed",
+                    hook.label(index)
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One hook: what it reacts to and what it does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hook {
+    /// Name shown in logs and the status query.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Events that trigger the hook.
+    #[serde(default)]
+    pub event_filter: EventFilter,
+    /// What the hook does.
+    pub action: HookAction,
+    /// Minimum seconds between two runs. Events arriving sooner are
+    /// dropped and counted as rate limited.
+    #[serde(default = "default_min_interval_secs")]
+    pub min_interval_secs: u64,
+}
+
+impl Hook {
+    /// A hook running `action` on the events `event_filter` matches.
+    pub fn new(event_filter: EventFilter, action: HookAction) -> Self {
+        Self {
+            name: None,
+            event_filter,
+            action,
+            min_interval_secs: DEFAULT_MIN_INTERVAL_SECS,
+        }
+    }
+
+    /// The hook's name, or its position when it has none.
+    pub fn label(&self, index: usize) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("hook #{}", index + 1),
+        }
+    }
+
+    /// Minimum time between two runs.
+    pub fn min_interval(&self) -> Duration {
+        Duration::from_secs(self.min_interval_secs)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.event_filter.every == 0 {
+            return Err(Error::InvalidInput(
+                "`every` must be at least 1".to_string(),
+            ));
+        }
+        match &self.action {
+            HookAction::Http { url, .. } => {
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    return Err(Error::InvalidInput(format!(
+                        "URL must start with http:// or https://: {}",
+                        url
+                    )));
+                }
+            }
+            HookAction::Command { argv, .. } => {
+                if argv.is_empty() || argv[0].is_empty() {
+                    return Err(Error::InvalidInput("command is empty".to_string()));
+                }
+            }
+        }
+        if self.action.timeout().is_zero() {
+            return Err(Error::InvalidInput("timeout must be positive".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Which events trigger a hook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventFilter {
+    /// Event names, such as `SyncFinished` or `FileCreated`. Empty matches
+    /// every event.
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Run on every Nth matching event only, e.g. after ten changed files.
+    #[serde(default = "default_every")]
+    pub every: u32,
+}
+
+impl EventFilter {
+    /// A filter matching the named events.
+    pub fn events<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            events: names.into_iter().map(Into::into).collect(),
+            every: 1,
+        }
+    }
+
+    /// Whether `event` is one of the filtered events.
+    pub fn matches(&self, event: &HookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event.event)
+    }
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self {
+            events: Vec::new(),
+            every: 1,
+        }
+    }
+}
+
+/// What a hook does when triggered.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HookAction {
+    /// POST the event as JSON to `url`. A failed request or an error
+    /// status is retried once.
+    Http {
+        url: String,
+        /// Sent as `Authorization: Bearer <token>`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bearer_token: Option<String>,
+        #[serde(default = "default_timeout_secs")]
+        timeout_secs: u64,
+    },
+    /// Run a local program. The event name and fields are passed in
+    /// `AXIOM_EVENT*` environment variables; output goes to the log.
+    Command {
+        /// Program and arguments; no shell is involved.
+        argv: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        working_dir: Option<PathBuf>,
+        #[serde(default = "default_timeout_secs")]
+        timeout_secs: u64,
+    },
+}
+
+impl HookAction {
+    /// How long one run may take before it is abandoned.
+    pub fn timeout(&self) -> Duration {
+        match self {
+            HookAction::Http { timeout_secs, .. } | HookAction::Command { timeout_secs, .. } => {
+                Duration::from_secs(*timeout_secs)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for HookAction {
+    // Keeps bearer tokens out of logs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookAction::Http {
+                url,
+                bearer_token,
+                timeout_secs,
+            } => f
+                .debug_struct("Http")
+                .field("url", url)
+                .field("bearer_token", &bearer_token.as_ref().map(|_| "[REDACTED]"))
+                .field("timeout_secs", timeout_secs)
+                .finish(),
+            HookAction::Command {
+                argv,
+                working_dir,
+                timeout_secs,
+            } => f
+                .debug_struct("Command")
+                .field("argv", argv)
+                .field("working_dir", working_dir)
+                .field("timeout_secs", timeout_secs)
+                .finish(),
+        }
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+fn default_min_interval_secs() -> u64 {
+    DEFAULT_MIN_INTERVAL_SECS
+}
+
+fn default_every() -> u32 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parses_with_defaults() {
+        let config: HooksConfig = serde_json::from_str(
+            r#"{
+                "hooks": [
+                    {
+                        "name": "notify",
+                        "event_filter": { "events": ["SyncFinished"] },
+                        "action": { "type": "http", "url": "http://127.0.0.1:9000/hook", "bearer_token": "t0ken" }
+                    },
+                    {
+                        "event_filter": { "events": ["FileUpdated"], "every": 10 },
+                        "action": { "type": "command", "argv": ["backup.sh", "--quick"] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(!config.allow_exec_hooks);
+        assert_eq!(config.hooks[0].min_interval_secs, DEFAULT_MIN_INTERVAL_SECS);
+        assert_eq!(config.hooks[0].event_filter.every, 1);
+        assert_eq!(
+            config.hooks[0].action.timeout(),
+            Duration::from_secs(DEFAULT_TIMEOUT_SECS)
+        );
+        assert_eq!(config.hooks[1].event_filter.every, 10);
+        assert_eq!(config.hooks[1].label(1), "hook #2");
+        assert!(!format!("{:?}", config.hooks[0].action).contains("t0ken"));
+
+        // The command hook needs the explicit toggle.
+        assert!(matches!(config.validate(), Err(Error::NotPermitted(_))));
+        let allowed = HooksConfig {
+            allow_exec_hooks: true,
+            ..config
+        };
+        allowed.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_malformed_hooks() {
+        let invalid = |action: HookAction| HooksConfig {
+            allow_exec_hooks: true,
+            hooks: vec![Hook::new(EventFilter::default(), action)],
+        };
+
+        let ftp = invalid(HookAction::Http {
+            url: "ftp://example.com".to_string(),
+            bearer_token: None,
+            timeout_secs: 5,
+        });
+        assert!(matches!(ftp.validate(), Err(Error::InvalidInput(_))));
+
+        let empty = invalid(HookAction::Command {
+            argv: Vec::new(),
+            working_dir: None,
+            timeout_secs: 5,
+        });
+        assert!(matches!(empty.validate(), Err(Error::InvalidInput(_))));
+
+        let no_timeout = invalid(HookAction::Command {
+            argv: vec!["true".to_string()],
+            working_dir: None,
+            timeout_secs: 0,
+        });
+        assert!(matches!(no_timeout.validate(), Err(Error::InvalidInput(_))));
+    }
+}
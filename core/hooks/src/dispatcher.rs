@@ -0,0 +1,626 @@
+//! Running hooks for incoming events.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, info, warn};
+
+use axiomvault_common::{Error, Result, SharedClock, SystemClock};
+
+use crate::config::{Hook, HookAction, HooksConfig};
+use crate::event::HookEvent;
+use crate::runner::{CommandRunner, CommandSpec, SystemCommandRunner};
+
+/// Counters of one hook, for the status query.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HookStatus {
+    /// The hook's name, or its position.
+    pub name: String,
+    /// Runs started, successful or not.
+    pub runs: u64,
+    /// Runs that failed or timed out.
+    pub failures: u64,
+    /// Matching events dropped because the hook ran too recently.
+    pub rate_limited: u64,
+    /// Error of the most recent failed run.
+    pub last_error: Option<String>,
+}
+
+/// One configured hook and its counters.
+#[derive(Debug)]
+struct Slot {
+    hook: Hook,
+    state: Mutex<SlotState>,
+}
+
+#[derive(Debug, Default)]
+struct SlotState {
+    /// Matching events seen, for `every`.
+    matched: u64,
+    last_run: Option<DateTime<Utc>>,
+    status: HookStatus,
+}
+
+impl Slot {
+    fn lock(&self) -> MutexGuard<'_, SlotState> {
+        // Counters stay usable even if a hook task panicked mid-update.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Runs the configured hooks for the events it is given.
+///
+/// Cloning is cheap; clones share the hooks, their counters and the runs
+/// in flight.
+#[derive(Debug, Clone)]
+pub struct HookDispatcher {
+    slots: Arc<[Arc<Slot>]>,
+    runner: Arc<dyn CommandRunner>,
+    http: reqwest::Client,
+    clock: SharedClock,
+    tasks: Arc<Mutex<JoinSet<()>>>,
+}
+
+impl HookDispatcher {
+    /// A dispatcher for `config`, after checking it with
+    /// [`HooksConfig::validate`].
+    pub fn new(config: HooksConfig) -> Result<Self> {
+        config.validate()?;
+        let slots: Vec<Arc<Slot>> = config
+            .hooks
+            .into_iter()
+            .enumerate()
+            .map(|(index, hook)| {
+                let status = HookStatus {
+                    name: hook.label(index),
+                    ..Default::default()
+                };
+                Arc::new(Slot {
+                    hook,
+                    state: Mutex::new(SlotState {
+                        status,
+                        ..Default::default()
+                    }),
+                })
+            })
+            .collect();
+        Ok(Self {
+            slots: slots.into(),
+            runner: Arc::new(SystemCommandRunner),
+            http: reqwest::Client::new(),
+            clock: SystemClock::shared(),
+            tasks: Arc::default(),
+        })
+    }
+
+    /// Run command hooks with `runner` instead of starting processes.
+    pub fn with_command_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Read the time for rate limiting and payload timestamps from `clock`.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Whether any hook is configured.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Start every hook matching `event` on a task of its own.
+    ///
+    /// Returns at once; must be called within a tokio runtime.
+    pub fn dispatch(&self, event: &HookEvent) {
+        let now = self.clock.now();
+        for slot in self.slots.iter() {
+            let hook = &slot.hook;
+            if !hook.event_filter.matches(event) {
+                continue;
+            }
+            {
+                let mut state = slot.lock();
+                state.matched += 1;
+                if state.matched % u64::from(hook.event_filter.every) != 0 {
+                    continue;
+                }
+                let too_soon = state.last_run.is_some_and(|last| {
+                    (now - last)
+                        .to_std()
+                        .is_ok_and(|elapsed| elapsed < hook.min_interval())
+                });
+                if too_soon {
+                    state.status.rate_limited += 1;
+                    debug!(hook = %state.status.name, event = %event.event, "Hook rate limited");
+                    continue;
+                }
+                state.last_run = Some(now);
+                state.status.runs += 1;
+            }
+
+            let run = run_hook(
+                slot.clone(),
+                event.clone(),
+                now,
+                self.runner.clone(),
+                self.http.clone(),
+            );
+            let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+            while tasks.try_join_next().is_some() {}
+            tasks.spawn(run);
+        }
+    }
+
+    /// Dispatch every event received on `events` until the channel closes.
+    ///
+    /// `to_event` picks and converts the events hooks should see. A
+    /// receiver that falls behind skips the events it missed.
+    pub fn listen<T, F>(&self, mut events: broadcast::Receiver<T>, to_event: F) -> JoinHandle<()>
+    where
+        T: Clone + Send + 'static,
+        F: Fn(&T) -> Option<HookEvent> + Send + 'static,
+    {
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        if let Some(event) = to_event(&event) {
+                            dispatcher.dispatch(&event);
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Hooks skipped {} events they fell behind on", missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Wait for the runs started so far to finish or time out.
+    pub async fn wait_idle(&self) {
+        let mut tasks = std::mem::take(&mut *self.tasks.lock().unwrap_or_else(|e| e.into_inner()));
+        while tasks.join_next().await.is_some() {}
+    }
+
+    /// Counters of every hook, in configuration order.
+    pub fn status(&self) -> Vec<HookStatus> {
+        self.slots
+            .iter()
+            .map(|slot| slot.lock().status.clone())
+            .collect()
+    }
+}
+
+/// Run one hook and record the outcome.
+async fn run_hook(
+    slot: Arc<Slot>,
+    event: HookEvent,
+    at: DateTime<Utc>,
+    runner: Arc<dyn CommandRunner>,
+    http: reqwest::Client,
+) {
+    let name = slot.lock().status.name.clone();
+    let outcome = match &slot.hook.action {
+        HookAction::Http {
+            url, bearer_token, ..
+        } => post_event(&http, url, bearer_token.as_deref(), &event, at, &slot.hook).await,
+        HookAction::Command {
+            argv, working_dir, ..
+        } => {
+            let command = CommandSpec {
+                argv: argv.clone(),
+                env: event.env(at),
+                working_dir: working_dir.clone(),
+            };
+            run_command(runner.as_ref(), &command, &name, slot.hook.action.timeout()).await
+        }
+    };
+
+    match outcome {
+        Ok(()) => debug!(hook = %name, event = %event.event, "Hook ran"),
+        Err(e) => {
+            warn!(hook = %name, event = %event.event, "Hook failed: {}", e);
+            let mut state = slot.lock();
+            state.status.failures += 1;
+            state.status.last_error = Some(e.to_string());
+        }
+    }
+}
+
+/// POST the event, retrying once after a failed request or error status.
+async fn post_event(
+    http: &reqwest::Client,
+    url: &str,
+    bearer_token: Option<&str>,
+    event: &HookEvent,
+    at: DateTime<Utc>,
+    hook: &Hook,
+) -> Result<()> {
+    let body = event.payload(at);
+    let timeout = hook.action.timeout();
+    let mut last_error = None;
+    for attempt in 1..=2 {
+        let mut request = http.post(url).json(&body).timeout(timeout);
+        if let Some(token) = bearer_token {
+            request = request.bearer_auth(token);
+        }
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => Error::Network(format!("{} returned {}", url, response.status())),
+            Err(e) if e.is_timeout() => {
+                Error::Cancelled(format!("no response within {}", describe(timeout)))
+            }
+            Err(e) => Error::Network(e.to_string()),
+        };
+        debug!(attempt, "Hook request failed: {}", error);
+        last_error = Some(error);
+    }
+    Err(last_error.expect("at least one attempt"))
+}
+
+/// Run a command hook within `timeout`, logging its output.
+async fn run_command(
+    runner: &dyn CommandRunner,
+    command: &CommandSpec,
+    name: &str,
+    timeout: Duration,
+) -> Result<()> {
+    let output = tokio::time::timeout(timeout, runner.run(command))
+        .await
+        .map_err(|_| Error::Cancelled(format!("still running after {}", describe(timeout))))??;
+
+    for line in output.stdout.lines() {
+        info!(hook = %name, "stdout: {}", line);
+    }
+    for line in output.stderr.lines() {
+        info!(hook = %name, "stderr: {}", line);
+    }
+    if output.success {
+        Ok(())
+    } else {
+        Err(Error::Vault(match output.code {
+            Some(code) => format!("command exited with code {}", code),
+            None => "command was killed".to_string(),
+        }))
+    }
+}
+
+fn describe(timeout: Duration) -> String {
+    format!("{}s", timeout.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+    use axiomvault_common::{Clock, MockClock};
+    use serde_json::Value;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    use crate::config::EventFilter;
+    use crate::runner::CommandOutput;
+
+    /// Records commands instead of running them.
+    #[derive(Debug, Default)]
+    struct FakeRunner {
+        calls: Mutex<Vec<CommandSpec>>,
+        /// How long each run takes.
+        delay: Duration,
+        /// Exit code returned; zero is success.
+        code: i32,
+    }
+
+    #[async_trait]
+    impl CommandRunner for FakeRunner {
+        async fn run(&self, command: &CommandSpec) -> Result<CommandOutput> {
+            self.calls.lock().unwrap().push(command.clone());
+            tokio::time::sleep(self.delay).await;
+            Ok(CommandOutput {
+                success: self.code == 0,
+                code: Some(self.code),
+                stdout: "done".to_string(),
+                stderr: String::new(),
+            })
+        }
+    }
+
+    fn command_hook(events: &[&str], timeout_secs: u64) -> Hook {
+        Hook {
+            min_interval_secs: 0,
+            ..Hook::new(
+                EventFilter::events(events.iter().copied()),
+                HookAction::Command {
+                    argv: vec!["backup.sh".to_string()],
+                    working_dir: Some("/srv/backup".into()),
+                    timeout_secs,
+                },
+            )
+        }
+    }
+
+    fn dispatcher(hooks: Vec<Hook>, runner: &Arc<FakeRunner>) -> HookDispatcher {
+        HookDispatcher::new(HooksConfig {
+            allow_exec_hooks: true,
+            hooks,
+        })
+        .unwrap()
+        .with_command_runner(runner.clone())
+    }
+
+    /// A request received by the test server.
+    struct Received {
+        head: String,
+        body: Value,
+    }
+
+    /// HTTP server answering requests with `statuses` in turn, then 200.
+    async fn spawn_server(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        let served = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let tx = tx.clone();
+                let status = statuses
+                    .get(served.fetch_add(1, Ordering::SeqCst))
+                    .copied()
+                    .unwrap_or(200);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 8192];
+                    let (head, body) = loop {
+                        let n = socket.read(&mut chunk).await.unwrap_or(0);
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                        let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+                            continue;
+                        };
+                        let head = String::from_utf8_lossy(&buf[..end]).to_lowercase();
+                        let length: usize = head
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length:"))
+                            .map(|v| v.trim().parse().unwrap())
+                            .unwrap_or(0);
+                        if buf.len() >= end + 4 + length {
+                            break (head, buf[end + 4..end + 4 + length].to_vec());
+                        }
+                    };
+                    let _ = tx.send(Received {
+                        head,
+                        body: serde_json::from_slice(&body).unwrap(),
+                    });
+                    let response = format!(
+                        "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn test_only_matching_events_run_hooks() {
+        let runner = Arc::new(FakeRunner::default());
+        let mut every_second = command_hook(&["FileUpdated"], 5);
+        every_second.event_filter.every = 2;
+        let hooks = dispatcher(
+            vec![command_hook(&["SyncFinished"], 5), every_second],
+            &runner,
+        );
+
+        hooks.dispatch(&HookEvent::new("FileUpdated").with_field("path", "/a"));
+        hooks.dispatch(&HookEvent::new("SyncFinished").with_field("files_synced", 4));
+        hooks.dispatch(&HookEvent::new("FileUpdated").with_field("path", "/b"));
+        hooks.wait_idle().await;
+
+        let mut calls = runner.calls.lock().unwrap().clone();
+        calls.sort_by_key(|c| c.env[0].1.clone());
+        assert_eq!(calls.len(), 2);
+        assert_eq!(
+            calls[0].env[0],
+            ("AXIOM_EVENT".into(), "FileUpdated".into())
+        );
+        assert!(calls[0]
+            .env
+            .contains(&("AXIOM_EVENT_PATH".into(), "/b".into())));
+        assert_eq!(
+            calls[1].env[0],
+            ("AXIOM_EVENT".into(), "SyncFinished".into())
+        );
+        assert!(calls[1]
+            .env
+            .contains(&("AXIOM_EVENT_FILES_SYNCED".into(), "4".into())));
+        assert_eq!(calls[1].working_dir, Some("/srv/backup".into()));
+
+        let status = hooks.status();
+        assert_eq!((status[0].runs, status[0].failures), (1, 0));
+        assert_eq!((status[1].runs, status[1].failures), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_http_hook_posts_event_payload() {
+        let (url, mut received) = spawn_server(Vec::new()).await;
+        let clock = MockClock::starting_now();
+        let hooks = HookDispatcher::new(HooksConfig {
+            allow_exec_hooks: false,
+            hooks: vec![Hook::new(
+                EventFilter::events(["ConflictsFound"]),
+                HookAction::Http {
+                    url,
+                    bearer_token: Some("s3cret".to_string()),
+                    timeout_secs: 5,
+                },
+            )],
+        })
+        .unwrap()
+        .with_clock(clock.clone());
+
+        hooks.dispatch(&HookEvent::new("ConflictsFound").with_field("count", 2));
+        hooks.wait_idle().await;
+
+        let request = received.recv().await.unwrap();
+        assert!(request.head.starts_with("post /hook "));
+        assert!(request.head.contains("authorization: bearer s3cret"));
+        assert!(request.head.contains("content-type: application/json"));
+        assert_eq!(
+            request.body,
+            serde_json::json!({
+                "event": "ConflictsFound",
+                "fields": { "count": 2 },
+                "timestamp": clock.now().to_rfc3339(),
+            })
+        );
+        assert_eq!(hooks.status()[0].failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_http_hook_retries_once() {
+        let (url, mut received) = spawn_server(vec![503, 500, 502]).await;
+        let hook = |url: &str| Hook {
+            min_interval_secs: 0,
+            ..Hook::new(
+                EventFilter::default(),
+                HookAction::Http {
+                    url: url.to_string(),
+                    bearer_token: None,
+                    timeout_secs: 5,
+                },
+            )
+        };
+        let hooks = HookDispatcher::new(HooksConfig {
+            allow_exec_hooks: false,
+            hooks: vec![hook(&url)],
+        })
+        .unwrap();
+
+        // 503 then 500: both attempts fail.
+        hooks.dispatch(&HookEvent::new("SyncFailed"));
+        hooks.wait_idle().await;
+        let status = &hooks.status()[0];
+        assert_eq!((status.runs, status.failures), (1, 1));
+        assert!(status.last_error.as_deref().unwrap().contains("500"));
+
+        // 502 then 200: the retry succeeds.
+        hooks.dispatch(&HookEvent::new("SyncFailed"));
+        hooks.wait_idle().await;
+        let status = &hooks.status()[0];
+        assert_eq!((status.runs, status.failures), (2, 1));
+
+        received.close();
+        let mut requests = 0;
+        while received.recv().await.is_some() {
+            requests += 1;
+        }
+        assert_eq!(requests, 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_command_times_out() {
+        let runner = Arc::new(FakeRunner {
+            delay: Duration::from_secs(3600),
+            ..Default::default()
+        });
+        let hooks = dispatcher(vec![command_hook(&[], 2)], &runner);
+
+        hooks.dispatch(&HookEvent::new("SyncFinished"));
+        hooks.wait_idle().await;
+
+        let status = &hooks.status()[0];
+        assert_eq!((status.runs, status.failures), (1, 1));
+        assert!(status.last_error.as_deref().unwrap().contains("after 2s"));
+    }
+
+    #[tokio::test]
+    async fn test_failing_command_is_counted() {
+        let runner = Arc::new(FakeRunner {
+            code: 7,
+            ..Default::default()
+        });
+        let hooks = dispatcher(vec![command_hook(&[], 5)], &runner);
+
+        hooks.dispatch(&HookEvent::new("SyncFinished"));
+        hooks.wait_idle().await;
+
+        let status = &hooks.status()[0];
+        assert_eq!(status.failures, 1);
+        assert!(status.last_error.as_deref().unwrap().contains("code 7"));
+    }
+
+    #[tokio::test]
+    async fn test_runs_are_rate_limited_per_hook() {
+        let runner = Arc::new(FakeRunner::default());
+        let clock = MockClock::starting_now();
+        let mut limited = command_hook(&[], 5);
+        limited.min_interval_secs = 60;
+        let hooks =
+            dispatcher(vec![limited, command_hook(&[], 5)], &runner).with_clock(clock.clone());
+
+        hooks.dispatch(&HookEvent::new("FileCreated"));
+        hooks.dispatch(&HookEvent::new("FileCreated"));
+        clock.advance(chrono::Duration::seconds(61));
+        hooks.dispatch(&HookEvent::new("FileCreated"));
+        hooks.wait_idle().await;
+
+        let status = hooks.status();
+        assert_eq!((status[0].runs, status[0].rate_limited), (2, 1));
+        assert_eq!((status[1].runs, status[1].rate_limited), (3, 0));
+    }
+
+    #[tokio::test]
+    async fn test_failing_hooks_do_not_hold_up_the_sender() {
+        // Nothing listens on this port once the listener is dropped.
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", closed.local_addr().unwrap());
+        drop(closed);
+
+        let hooks = HookDispatcher::new(HooksConfig {
+            allow_exec_hooks: false,
+            hooks: vec![Hook::new(
+                EventFilter::default(),
+                HookAction::Http {
+                    url,
+                    bearer_token: None,
+                    timeout_secs: 5,
+                },
+            )],
+        })
+        .unwrap();
+        let (tx, rx) = broadcast::channel(16);
+        let listener = hooks.listen(rx, |event: &axiomvault_sync::SyncEvent| {
+            Some(HookEvent::from(event))
+        });
+
+        tx.send(axiomvault_sync::SyncEvent::QuotaExhausted {
+            paused: 1,
+            paused_bytes: 10,
+        })
+        .unwrap();
+        drop(tx);
+        listener.await.unwrap();
+        hooks.wait_idle().await;
+
+        let status = &hooks.status()[0];
+        assert_eq!((status.runs, status.failures), (1, 1));
+    }
+}
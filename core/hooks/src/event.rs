@@ -0,0 +1,211 @@
+//! Events as hooks see them.
+//!
+//! A [`HookEvent`] is a name plus flat fields, built from the vault and
+//! sync event buses or from the outcome of a sync. Hooks filter on the name;
+//! HTTP hooks receive it as JSON and command hooks as environment variables.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use axiomvault_sync::{SyncEvent, SyncResult};
+use axiomvault_vault::VaultEvent;
+
+/// Prefix of the environment variables a command hook receives.
+pub const ENV_PREFIX: &str = "AXIOM_EVENT";
+
+/// An event a hook can react to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HookEvent {
+    /// Event name, such as `SyncFinished`.
+    pub event: String,
+    /// Details of the event.
+    pub fields: Map<String, Value>,
+}
+
+impl HookEvent {
+    /// An event without fields.
+    pub fn new(event: impl Into<String>) -> Self {
+        Self {
+            event: event.into(),
+            fields: Map::new(),
+        }
+    }
+
+    /// Add a field.
+    pub fn with_field(mut self, name: &str, value: impl Into<Value>) -> Self {
+        self.fields.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// An event from an enum serialized the serde default way: the variant
+    /// name becomes the event name and its fields the event fields.
+    ///
+    /// Returns `None` for values that do not serialize like an enum.
+    pub fn from_enum<T: Serialize>(value: &T) -> Option<Self> {
+        match serde_json::to_value(value).ok()? {
+            Value::String(event) => Some(Self::new(event)),
+            Value::Object(map) if map.len() == 1 => {
+                let (event, content) = map.into_iter().next()?;
+                let fields = match content {
+                    Value::Object(fields) => fields,
+                    value => Map::from_iter([("value".to_string(), value)]),
+                };
+                Some(Self { event, fields })
+            }
+            _ => None,
+        }
+    }
+
+    /// `SyncFinished`, with the counts of a finished sync.
+    pub fn sync_finished(result: &SyncResult) -> Self {
+        Self::new("SyncFinished")
+            .with_field("files_synced", result.files_synced)
+            .with_field("files_failed", result.files_failed)
+            .with_field("conflicts_found", result.conflicts_found)
+            .with_field("pending_persistence", result.pending_persistence)
+            .with_field("quota_exhausted", result.quota_exhausted)
+            .with_field("uploads_paused", result.uploads_paused)
+            .with_field("duration_ms", result.duration.as_millis() as u64)
+    }
+
+    /// `ConflictsFound`, when a finished sync found any.
+    pub fn conflicts_found(result: &SyncResult) -> Option<Self> {
+        (result.conflicts_found > 0)
+            .then(|| Self::new("ConflictsFound").with_field("count", result.conflicts_found))
+    }
+
+    /// `SyncFailed`, with the error that stopped the sync.
+    pub fn sync_failed(error: &str) -> Self {
+        Self::new("SyncFailed").with_field("error", error)
+    }
+
+    /// The JSON body an HTTP hook posts.
+    pub fn payload(&self, at: DateTime<Utc>) -> Value {
+        json!({
+            "event": self.event,
+            "fields": self.fields,
+            "timestamp": at.to_rfc3339(),
+        })
+    }
+
+    /// Environment variables for a command hook: `AXIOM_EVENT` holds the
+    /// name, `AXIOM_EVENT_<FIELD>` each field and `AXIOM_EVENT_JSON` the
+    /// whole payload.
+    pub fn env(&self, at: DateTime<Utc>) -> Vec<(String, String)> {
+        let mut env = vec![(ENV_PREFIX.to_string(), self.event.clone())];
+        for (name, value) in &self.fields {
+            let value = match value {
+                Value::String(text) => text.clone(),
+                value => value.to_string(),
+            };
+            env.push((env_name(name), value));
+        }
+        env.push((format!("{}_JSON", ENV_PREFIX), self.payload(at).to_string()));
+        env
+    }
+}
+
+/// `AXIOM_EVENT_<NAME>`, upper-cased with anything but letters and digits
+/// replaced by `_`.
+fn env_name(field: &str) -> String {
+    let field: String = field
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}_{}", ENV_PREFIX, field)
+}
+
+impl From<&SyncEvent> for HookEvent {
+    fn from(event: &SyncEvent) -> Self {
+        Self::from_enum(event).unwrap_or_else(|| Self::new("SyncEvent"))
+    }
+}
+
+impl From<&VaultEvent> for HookEvent {
+    fn from(event: &VaultEvent) -> Self {
+        match event {
+            VaultEvent::BlobUpgraded { path, from, to } => Self::new("BlobUpgraded")
+                .with_field("path", path.as_str())
+                .with_field("from", from.to_string())
+                .with_field("to", to.to_string()),
+            VaultEvent::BlobUpgradeDeferred { path } => {
+                Self::new("BlobUpgradeDeferred").with_field("path", path.as_str())
+            }
+            VaultEvent::MaintenanceFinished {
+                upgraded,
+                remaining,
+            } => Self::new("MaintenanceFinished")
+                .with_field("upgraded", *upgraded)
+                .with_field("remaining", *remaining),
+            VaultEvent::DataShardMigrated { shard, moved } => Self::new("DataShardMigrated")
+                .with_field("shard", shard.as_str())
+                .with_field("moved", *moved),
+            VaultEvent::ReauthRequired { bytes_processed } => {
+                Self::new("ReauthRequired").with_field("bytes_processed", *bytes_processed)
+            }
+            VaultEvent::MountDegraded {
+                mount_point,
+                reason,
+            } => Self::new("MountDegraded")
+                .with_field("mount_point", mount_point.as_str())
+                .with_field("reason", reason.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_event_fields_come_from_the_variant() {
+        let event = HookEvent::from(&SyncEvent::QuotaExhausted {
+            paused: 2,
+            paused_bytes: 4096,
+        });
+        assert_eq!(event.event, "QuotaExhausted");
+        assert_eq!(event.fields["paused"], 2);
+        assert_eq!(event.fields["paused_bytes"], 4096);
+
+        #[derive(Serialize)]
+        enum Sample {
+            Bare,
+            Wrapped(String),
+        }
+        assert_eq!(
+            HookEvent::from_enum(&Sample::Bare),
+            Some(HookEvent::new("Bare"))
+        );
+        assert_eq!(
+            HookEvent::from_enum(&Sample::Wrapped("x".into())),
+            Some(HookEvent::new("Wrapped").with_field("value", "x"))
+        );
+        assert_eq!(HookEvent::from_enum(&42), None);
+    }
+
+    #[test]
+    fn test_env_carries_name_fields_and_payload() {
+        let at = Utc::now();
+        let event = HookEvent::new("BlobUpgradeDeferred")
+            .with_field("path", "/docs/a b.txt")
+            .with_field("retry-count", 3);
+        let env = event.env(at);
+
+        assert!(env.contains(&("AXIOM_EVENT".into(), "BlobUpgradeDeferred".into())));
+        assert!(env.contains(&("AXIOM_EVENT_PATH".into(), "/docs/a b.txt".into())));
+        assert!(env.contains(&("AXIOM_EVENT_RETRY_COUNT".into(), "3".into())));
+        let (_, json) = env
+            .iter()
+            .find(|(name, _)| name == "AXIOM_EVENT_JSON")
+            .unwrap();
+        let payload: Value = serde_json::from_str(json).unwrap();
+        assert_eq!(payload, event.payload(at));
+    }
+}
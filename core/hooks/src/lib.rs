@@ -0,0 +1,26 @@
+//! Automation hooks for AxiomVault.
+//!
+//! This module provides:
+//! - Hook definitions kept in client-side configuration, never in the vault
+//! - Filtering of vault and sync events by name, optionally on every Nth
+//!   matching event
+//! - HTTP POST actions carrying the event as JSON, retried once
+//! - Local command actions receiving the event in environment variables,
+//!   only when explicitly allowed
+//! - Per-hook rate limiting and a status query counting runs and failures
+//!
+//! # Architecture
+//! A [`HookDispatcher`] is fed [`HookEvent`]s, either directly or by
+//! listening to an event channel. Every matching hook runs on a task of its
+//! own, so the operation that raised the event never waits for a hook and
+//! never sees its failure.
+
+pub mod config;
+pub mod dispatcher;
+pub mod event;
+pub mod runner;
+
+pub use config::{EventFilter, Hook, HookAction, HooksConfig};
+pub use dispatcher::{HookDispatcher, HookStatus};
+pub use event::HookEvent;
+pub use runner::{CommandOutput, CommandRunner, CommandSpec, SystemCommandRunner};
@@ -0,0 +1,107 @@
+//! Running command hooks.
+//!
+//! The dispatcher hands each command to a [`CommandRunner`];
+//! [`SystemCommandRunner`] starts a real process, tests substitute a fake.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use async_trait::async_trait;
+
+use axiomvault_common::{Error, Result};
+
+/// A command to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    /// Program and arguments.
+    pub argv: Vec<String>,
+    /// Variables added to the inherited environment.
+    pub env: Vec<(String, String)>,
+    /// Directory to run in; the current directory when `None`.
+    pub working_dir: Option<PathBuf>,
+}
+
+/// What a finished command produced.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// Whether the command exited successfully.
+    pub success: bool,
+    /// Exit code, if the command exited rather than being killed.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs command hooks.
+///
+/// The dispatcher applies the hook's timeout by dropping the returned
+/// future, so implementations must stop the command when dropped.
+#[async_trait]
+pub trait CommandRunner: Send + Sync + fmt::Debug {
+    /// Run `command` to completion.
+    async fn run(&self, command: &CommandSpec) -> Result<CommandOutput>;
+}
+
+/// Runs commands as child processes, without a shell.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemCommandRunner;
+
+#[async_trait]
+impl CommandRunner for SystemCommandRunner {
+    async fn run(&self, command: &CommandSpec) -> Result<CommandOutput> {
+        let (program, args) = command
+            .argv
+            .split_first()
+            .ok_or_else(|| Error::InvalidInput("command is empty".to_string()))?;
+
+        let mut process = tokio::process::Command::new(program);
+        process
+            .args(args)
+            .envs(command.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        if let Some(dir) = &command.working_dir {
+            process.current_dir(dir);
+        }
+
+        let output = process.output().await?;
+        Ok(CommandOutput {
+            success: output.status.success(),
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_system_runner_passes_env_and_captures_output() {
+        let dir = std::env::temp_dir();
+        let output = SystemCommandRunner
+            .run(&CommandSpec {
+                argv: vec![
+                    "sh".into(),
+                    "-c".into(),
+                    "echo \"$AXIOM_EVENT in $(pwd)\"; echo oops >&2; exit 3".into(),
+                ],
+                env: vec![("AXIOM_EVENT".into(), "SyncFinished".into())],
+                working_dir: Some(dir.clone()),
+            })
+            .await
+            .unwrap();
+
+        assert!(!output.success);
+        assert_eq!(output.code, Some(3));
+        let cwd = dir.canonicalize().unwrap();
+        assert_eq!(
+            output.stdout.trim(),
+            format!("SyncFinished in {}", cwd.display())
+        );
+        assert_eq!(output.stderr.trim(), "oops");
+    }
+}
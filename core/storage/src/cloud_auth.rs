@@ -6,13 +6,32 @@
 //!
 //! Provider-specific auth managers implement [`TokenRefresher`] to plug into
 //! the generic [`CloudTokenManager`].
+//!
+//! Long-lived sessions can also refresh ahead of expiry in the background
+//! with [`CloudTokenManager::spawn_refresher`], so an operation after a
+//! long idle period does not pay for the refresh.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
-use axiomvault_common::Result;
+use axiomvault_common::{Error, Result, SharedClock, SystemClock};
+
+/// How far ahead of expiry tokens count as expired and get refreshed.
+const REFRESH_AHEAD_MINUTES: i64 = 5;
+
+/// Longest the background refresher sleeps between checks, so tokens
+/// replaced with `update_tokens` are picked up.
+const REFRESH_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Wait before retrying a background refresh that failed transiently.
+const REFRESH_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
 
 /// OAuth2 tokens with expiration tracking.
 ///
@@ -29,6 +48,19 @@ pub struct CloudTokens {
     pub expires_at: DateTime<Utc>,
 }
 
+/// JSON Schema fragment for [`CloudTokens`], shared by provider config schemas.
+pub fn cloud_tokens_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "required": ["access_token", "refresh_token", "expires_at"],
+        "properties": {
+            "access_token": { "type": "string", "minLength": 1 },
+            "refresh_token": { "type": "string" },
+            "expires_at": { "type": "string", "format": "date-time" }
+        }
+    })
+}
+
 impl std::fmt::Debug for CloudTokens {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CloudTokens")
@@ -45,7 +77,19 @@ impl CloudTokens {
     /// Uses a 5-minute buffer to avoid using a token that will expire
     /// during a request.
     pub fn is_expired(&self) -> bool {
-        self.expires_at <= Utc::now() + Duration::minutes(5)
+        self.is_expired_at(Utc::now())
+    }
+
+    /// Like [`is_expired`](Self::is_expired), as of `now`.
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now + Duration::minutes(REFRESH_AHEAD_MINUTES)
+    }
+
+    /// Time from `now` until `is_expired_at` turns true; zero if it already has.
+    fn time_until_refresh(&self, now: DateTime<Utc>) -> std::time::Duration {
+        (self.expires_at - Duration::minutes(REFRESH_AHEAD_MINUTES) - now)
+            .to_std()
+            .unwrap_or_default()
     }
 }
 
@@ -69,6 +113,9 @@ pub trait TokenRefresher: Send + Sync {
 pub struct CloudTokenManager<R: TokenRefresher> {
     refresher: R,
     tokens: tokio::sync::RwLock<CloudTokens>,
+    /// Set when a refresh was refused for good; cleared by `update_tokens`.
+    revoked: AtomicBool,
+    clock: SharedClock,
 }
 
 impl<R: TokenRefresher> CloudTokenManager<R> {
@@ -77,9 +124,23 @@ impl<R: TokenRefresher> CloudTokenManager<R> {
         Self {
             refresher,
             tokens: tokio::sync::RwLock::new(tokens),
+            revoked: AtomicBool::new(false),
+            clock: SystemClock::shared(),
         }
     }
 
+    /// Judge expiry by `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Whether the refresh token was rejected, e.g. because access was
+    /// revoked. The user has to sign in again; `update_tokens` clears it.
+    pub fn is_revoked(&self) -> bool {
+        self.revoked.load(Ordering::SeqCst)
+    }
+
     /// Get a valid access token, refreshing if necessary.
     ///
     /// Uses double-check locking: first acquires a read lock to check
@@ -88,19 +149,27 @@ impl<R: TokenRefresher> CloudTokenManager<R> {
     /// redundant refreshes from concurrent callers.
     pub async fn get_access_token(&self) -> Result<String> {
         let tokens = self.tokens.read().await;
-        if !tokens.is_expired() {
+        if !tokens.is_expired_at(self.clock.now()) {
             return Ok(tokens.access_token.clone());
         }
         drop(tokens);
 
         let mut tokens = self.tokens.write().await;
         // Double-check after acquiring write lock
-        if !tokens.is_expired() {
+        if !tokens.is_expired_at(self.clock.now()) {
             return Ok(tokens.access_token.clone());
         }
 
         tracing::info!("Refreshing expired access token");
-        let new_tokens = self.refresher.refresh(&tokens.refresh_token).await?;
+        let new_tokens = match self.refresher.refresh(&tokens.refresh_token).await {
+            Ok(new_tokens) => new_tokens,
+            Err(e) => {
+                if matches!(e, Error::Authentication(_)) {
+                    self.revoked.store(true, Ordering::SeqCst);
+                }
+                return Err(e);
+            }
+        };
         *tokens = new_tokens;
         Ok(tokens.access_token.clone())
     }
@@ -113,12 +182,62 @@ impl<R: TokenRefresher> CloudTokenManager<R> {
     /// Replace the current tokens (e.g. after manual refresh).
     pub async fn update_tokens(&self, tokens: CloudTokens) {
         *self.tokens.write().await = tokens;
+        self.revoked.store(false, Ordering::SeqCst);
+    }
+}
+
+impl<R: TokenRefresher + 'static> CloudTokenManager<R> {
+    /// Refresh tokens in the background ahead of expiry.
+    ///
+    /// The task on `handle` wakes when the access token is about to
+    /// expire, refreshes it and passes the new tokens to `persist`. It
+    /// stops once the refresh token is rejected (see `is_revoked`) or the
+    /// manager is dropped; transient failures are retried.
+    pub fn spawn_refresher<F>(self: &Arc<Self>, handle: &Handle, persist: F) -> JoinHandle<()>
+    where
+        F: Fn(&CloudTokens) + Send + Sync + 'static,
+    {
+        let manager = Arc::downgrade(self);
+        handle.spawn(async move {
+            loop {
+                let Some(this) = manager.upgrade() else {
+                    return;
+                };
+                let tokens = this.get_tokens().await;
+                let wait = tokens.time_until_refresh(this.clock.now());
+                if !wait.is_zero() {
+                    drop(this);
+                    tokio::time::sleep(wait.min(REFRESH_CHECK_INTERVAL)).await;
+                    continue;
+                }
+
+                match this.get_access_token().await {
+                    Ok(_) => {
+                        let refreshed = this.get_tokens().await;
+                        if refreshed.expires_at != tokens.expires_at {
+                            tracing::debug!("Refreshed access token ahead of expiry");
+                            persist(&refreshed);
+                        }
+                    }
+                    Err(e) if this.is_revoked() => {
+                        tracing::warn!("Refresh token rejected, stopping refresher: {}", e);
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Background token refresh failed: {}", e);
+                        drop(this);
+                        tokio::time::sleep(REFRESH_RETRY_DELAY).await;
+                    }
+                }
+            }
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axiomvault_common::{Clock, MockClock};
 
     #[test]
     fn test_cloud_tokens_expiration() {
@@ -199,6 +318,116 @@ mod tests {
         assert_eq!(token, "refreshed");
     }
 
+    /// Refresher that counts calls and either succeeds or reports the
+    /// refresh token as revoked.
+    struct CountingRefresher {
+        calls: std::sync::atomic::AtomicUsize,
+        revoked: bool,
+    }
+
+    #[async_trait]
+    impl TokenRefresher for CountingRefresher {
+        async fn refresh(&self, refresh_token: &str) -> Result<CloudTokens> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.revoked {
+                return Err(Error::Authentication("invalid_grant".to_string()));
+            }
+            TestRefresher.refresh(refresh_token).await
+        }
+    }
+
+    fn expiring_in(after_buffer: std::time::Duration) -> CloudTokens {
+        CloudTokens {
+            access_token: "old".to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_at: Utc::now()
+                + Duration::minutes(REFRESH_AHEAD_MINUTES)
+                + Duration::from_std(after_buffer).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_background_refresher_refreshes_before_expiry() {
+        let tokens = expiring_in(std::time::Duration::from_millis(300));
+        let expires_at = tokens.expires_at;
+        let manager = Arc::new(CloudTokenManager::new(
+            CountingRefresher {
+                calls: Default::default(),
+                revoked: false,
+            },
+            tokens,
+        ));
+
+        let persisted = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = persisted.clone();
+        let task = manager.spawn_refresher(&Handle::current(), move |tokens| {
+            sink.lock().unwrap().push(tokens.access_token.clone());
+        });
+
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert!(Utc::now() < expires_at);
+        assert_eq!(manager.refresher.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(*persisted.lock().unwrap(), ["refreshed"]);
+        let current = manager.get_tokens().await;
+        assert_eq!(current.access_token, "refreshed");
+        assert!(!current.is_expired());
+        assert!(!manager.is_revoked());
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_background_refresher_flags_revocation() {
+        let manager = Arc::new(CloudTokenManager::new(
+            CountingRefresher {
+                calls: Default::default(),
+                revoked: true,
+            },
+            expiring_in(std::time::Duration::ZERO),
+        ));
+        let task = manager.spawn_refresher(&Handle::current(), |_| {
+            panic!("nothing to persist");
+        });
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), task)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(manager.is_revoked());
+        assert_eq!(manager.refresher.calls.load(Ordering::SeqCst), 1);
+
+        manager
+            .update_tokens(expiring_in(std::time::Duration::from_secs(3600)))
+            .await;
+        assert!(!manager.is_revoked());
+    }
+
+    #[tokio::test]
+    async fn test_cloud_token_manager_refreshes_once_clock_passes_expiry() {
+        let clock = MockClock::starting_now();
+        let tokens = CloudTokens {
+            access_token: "old".to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_at: clock.now() + Duration::hours(1),
+        };
+        let manager = CloudTokenManager::new(
+            CountingRefresher {
+                calls: Default::default(),
+                revoked: false,
+            },
+            tokens,
+        )
+        .with_clock(clock.clone());
+
+        clock.advance(Duration::minutes(54));
+        assert_eq!(manager.get_access_token().await.unwrap(), "old");
+        assert_eq!(manager.refresher.calls.load(Ordering::SeqCst), 0);
+
+        // Inside the refresh-ahead window.
+        clock.advance(Duration::minutes(2));
+        assert_eq!(manager.get_access_token().await.unwrap(), "refreshed");
+        assert_eq!(manager.refresher.calls.load(Ordering::SeqCst), 1);
+    }
+
     #[tokio::test]
     async fn test_cloud_token_manager_update_tokens() {
         let tokens = CloudTokens {
@@ -128,7 +128,7 @@ impl CompositeStorageProvider {
 
     /// Build the shard path for a given file path and shard index.
     pub(crate) fn shard_path(path: &VaultPath, shard_index: usize) -> Result<VaultPath> {
-        let path_str = path.to_string_path();
+        let path_str = path.to_string();
         VaultPath::parse(&format!("{}.shard{}", path_str, shard_index))
     }
 
@@ -225,7 +225,7 @@ impl CompositeStorageProvider {
 
         // Record the shard mapping with only successful backends
         let (ds, ps) = self.erasure_params()?;
-        let path_str = path.to_string_path();
+        let path_str = path.to_string();
         let entry = ShardMap::erasure_entry(
             &path_str,
             original_size as u64,
@@ -371,7 +371,7 @@ impl CompositeStorageProvider {
 
         if any_success {
             // Remove from shard map
-            let path_str = path.to_string_path();
+            let path_str = path.to_string();
             {
                 self.shard_map.write().await.remove(&path_str);
             }
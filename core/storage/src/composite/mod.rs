@@ -23,7 +23,7 @@ use tokio::sync::{OnceCell, RwLock};
 use tracing::warn;
 
 use crate::health::ProviderHealth;
-use crate::provider::{ByteStream, Metadata, StorageProvider};
+use crate::provider::{ByteStream, Metadata, ProviderCapabilities, StorageProvider};
 use crate::shard_map::ShardMap;
 use axiomvault_common::{Error, Result, VaultPath};
 
@@ -198,6 +198,14 @@ impl StorageProvider for CompositeStorageProvider {
         "composite"
     }
 
+    /// Renames fan out to every backend, so they are only native when all
+    /// backends support a native rename.
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            native_rename: self.backends.iter().all(|b| b.capabilities().native_rename),
+        }
+    }
+
     async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
         self.ensure_shard_map_loaded().await?;
         match self.config.mode {
@@ -214,7 +222,7 @@ impl StorageProvider for CompositeStorageProvider {
                     .await?;
 
                 // Record mirror entry with only the backends that succeeded
-                let path_str = path.to_string_path();
+                let path_str = path.to_string();
                 let entry = ShardMap::mirror_entry(
                     &path_str,
                     original_size,
@@ -297,7 +305,7 @@ impl StorageProvider for CompositeStorageProvider {
 
                 // Remove from shard map
                 {
-                    self.shard_map.write().await.remove(&path.to_string_path());
+                    self.shard_map.write().await.remove(&path.to_string());
                 }
                 self.save_shard_map().await?;
 
@@ -446,8 +454,8 @@ impl StorageProvider for CompositeStorageProvider {
                     .await?;
 
                 // Update shard map: rename entry, then remove shards for failed backends
-                let from_str = from.to_string_path();
-                let to_str = to.to_string_path();
+                let from_str = from.to_string();
+                let to_str = to.to_string();
                 {
                     let mut map = self.shard_map.write().await;
                     map.rename(&from_str, &to_str);
@@ -473,8 +481,8 @@ impl StorageProvider for CompositeStorageProvider {
                     .await?;
 
                 // Update shard map
-                let from_str = from.to_string_path();
-                let to_str = to.to_string_path();
+                let from_str = from.to_string();
+                let to_str = to.to_string();
                 {
                     self.shard_map.write().await.rename(&from_str, &to_str);
                 }
@@ -500,8 +508,8 @@ impl StorageProvider for CompositeStorageProvider {
                     .await?;
 
                 // Copy shard map entry for the new path, retaining only succeeded backends
-                let from_str = from.to_string_path();
-                let to_str = to.to_string_path();
+                let from_str = from.to_string();
+                let to_str = to.to_string();
                 {
                     let mut map = self.shard_map.write().await;
                     if let Some(entry) = map.get(&from_str).cloned() {
@@ -532,8 +540,8 @@ impl StorageProvider for CompositeStorageProvider {
                     .await?;
 
                 // Copy shard map entry for the new path
-                let from_str = from.to_string_path();
-                let to_str = to.to_string_path();
+                let from_str = from.to_string();
+                let to_str = to.to_string();
                 {
                     let mut map = self.shard_map.write().await;
                     if let Some(entry) = map.get(&from_str).cloned() {
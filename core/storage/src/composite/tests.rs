@@ -672,7 +672,7 @@ async fn test_erasure_5_backends_3_of_5_knock_out_2() {
 
     // Knock out backends 3 and 4 by deleting their shards
     for (path, _) in &files {
-        let path_str = path.to_string_path();
+        let path_str = path.to_string();
         for shard_idx in [3, 4] {
             let shard_path = VaultPath::parse(&format!("{}.shard{}", path_str, shard_idx)).unwrap();
             backends[shard_idx].delete(&shard_path).await.unwrap();
@@ -12,4 +12,6 @@ pub mod provider;
 
 pub use auth::{DropboxAuthConfig, DropboxAuthManager, DropboxTokenManager, DropboxTokens};
 pub use client::DropboxClient;
-pub use provider::{create_dropbox_provider, DropboxConfig, DropboxProvider};
+pub use provider::{
+    create_dropbox_provider, dropbox_config_schema, DropboxConfig, DropboxProvider,
+};
@@ -7,7 +7,7 @@ use std::sync::Arc;
 
 use axiomvault_common::{Error, Result, VaultPath};
 
-use crate::provider::{ByteStream, Metadata, StorageProvider};
+use crate::provider::{ByteStream, Metadata, ProviderCapabilities, StorageProvider};
 
 use super::auth::{DropboxAuthConfig, DropboxAuthManager, DropboxTokenManager, DropboxTokens};
 use super::client::{DropboxClient, DropboxMetadata};
@@ -66,6 +66,7 @@ impl DropboxProvider {
             is_directory: meta.is_folder(),
             modified: meta.server_modified.unwrap_or_else(chrono::Utc::now),
             etag: meta.rev.clone(),
+            content_hash: None,
             provider_data: Some(serde_json::json!({
                 "dropbox_id": meta.id,
                 "path_display": meta.path_display,
@@ -81,6 +82,12 @@ impl StorageProvider for DropboxProvider {
         "dropbox"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            native_rename: true,
+        }
+    }
+
     async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
         let dbx_path = self.to_dropbox_path(path);
 
@@ -173,6 +180,29 @@ impl StorageProvider for DropboxProvider {
     }
 }
 
+/// JSON Schema describing [`DropboxConfig`].
+pub fn dropbox_config_schema() -> serde_json::Value {
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Dropbox",
+        "type": "object",
+        "required": ["root_path", "tokens"],
+        "properties": {
+            "root_path": { "type": "string", "minLength": 1, "description": "Folder path in Dropbox, e.g. \"/AxiomVault\"." },
+            "tokens": crate::cloud_auth::cloud_tokens_schema(),
+            "auth_config": {
+                "type": ["object", "null"],
+                "required": ["app_key", "app_secret", "redirect_url"],
+                "properties": {
+                    "app_key": { "type": "string" },
+                    "app_secret": { "type": "string" },
+                    "redirect_url": { "type": "string" }
+                }
+            }
+        }
+    })
+}
+
 /// Create a Dropbox provider from configuration.
 pub fn create_dropbox_provider(config: serde_json::Value) -> Result<Arc<dyn StorageProvider>> {
     let dropbox_config: DropboxConfig = serde_json::from_value(config)
@@ -206,7 +206,13 @@ impl TokenRefresher for AuthManager {
             .exchange_refresh_token(&RefreshToken::new(refresh_token_value.clone()))
             .request_async(&http_client)
             .await
-            .map_err(|e| Error::Authentication(format!("Token refresh failed: {}", e)))?;
+            .map_err(|e| match e {
+                // The token endpoint was not reached; the grant may be fine.
+                oauth2::RequestTokenError::Request(e) => {
+                    Error::Network(format!("Token refresh failed: {}", e))
+                }
+                e => Error::Authentication(format!("Token refresh failed: {}", e)),
+            })?;
 
         let access_token = token_result.access_token().secret().clone();
 
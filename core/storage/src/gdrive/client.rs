@@ -5,21 +5,55 @@ use chrono::{DateTime, Utc};
 use futures::{Stream, StreamExt};
 use reqwest::{header, Client, StatusCode};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use axiomvault_common::{Error, Result};
 
 use super::auth::TokenManager;
 use crate::http_client;
+use crate::provider::StorageQuota;
 
 /// Google Drive API base URL.
 const DRIVE_API_BASE: &str = "https://www.googleapis.com/drive/v3";
 /// Google Drive upload API base URL.
 const DRIVE_UPLOAD_BASE: &str = "https://www.googleapis.com/upload/drive/v3";
+/// Google Drive batch API endpoint.
+const DRIVE_BATCH_URL: &str = "https://www.googleapis.com/batch/drive/v3";
+
+/// Most requests Drive accepts in one batch.
+pub const MAX_BATCH_SIZE: usize = 100;
+
+/// Default bound on concurrent Drive requests per client.
+pub const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+/// Largest `pageSize` Drive accepts when listing files.
+const MAX_PAGE_SIZE: usize = 1000;
 
 /// Chunk size for resumable uploads (256KB minimum, must be multiple of 256KB).
 const CHUNK_SIZE: usize = 256 * 1024; // 256KB
 
+/// Chunk size for journaled resumable uploads, a multiple of [`CHUNK_SIZE`].
+///
+/// Larger than `CHUNK_SIZE` so that recording progress after every chunk
+/// stays cheap for multi-gigabyte files.
+pub const RESUMABLE_CHUNK_SIZE: usize = 32 * CHUNK_SIZE; // 8MB
+
+/// Server-side state of a resumable upload session.
+#[derive(Debug, Clone)]
+pub enum ResumableStatus {
+    /// Drive has stored the first `acknowledged` bytes.
+    Incomplete {
+        /// Number of bytes received so far.
+        acknowledged: u64,
+    },
+    /// The upload finished and created or replaced this file.
+    Complete(DriveFile),
+}
+
 /// Google Drive file metadata from API.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -71,6 +105,43 @@ struct FileListResponse {
     next_page_token: Option<String>,
 }
 
+/// Response from `about.get` restricted to `storageQuota`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AboutResponse {
+    storage_quota: DriveStorageQuota,
+}
+
+/// Drive's account quota; Drive encodes the byte counts as strings.
+#[derive(Debug, Deserialize)]
+struct DriveStorageQuota {
+    /// Absent for accounts with unlimited storage.
+    #[serde(default)]
+    limit: Option<String>,
+    #[serde(default)]
+    usage: Option<String>,
+}
+
+/// Error body Drive returns for failed requests.
+#[derive(Debug, Deserialize)]
+struct DriveErrorResponse {
+    error: DriveErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct DriveErrorBody {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    errors: Vec<DriveErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DriveErrorDetail {
+    #[serde(default)]
+    reason: String,
+}
+
 /// Google Drive API client.
 pub struct DriveClient {
     /// HTTP client for streaming uploads and downloads (no total timeout).
@@ -78,6 +149,15 @@ pub struct DriveClient {
     /// HTTP client for short metadata requests (bounded total timeout).
     metadata_http: Client,
     token_manager: std::sync::Arc<TokenManager>,
+    /// Drive metadata API base URL.
+    api_base: String,
+    /// Drive upload API base URL.
+    upload_base: String,
+    /// Drive batch API endpoint.
+    batch_url: String,
+    /// Slots for requests in flight, so parallel callers back off before
+    /// Drive's per-user rate limits do it for them.
+    requests: Arc<Semaphore>,
 }
 
 impl DriveClient {
@@ -87,13 +167,36 @@ impl DriveClient {
             http: http_client::build_http_client()?,
             metadata_http: http_client::build_metadata_http_client()?,
             token_manager,
+            api_base: DRIVE_API_BASE.to_string(),
+            upload_base: DRIVE_UPLOAD_BASE.to_string(),
+            batch_url: DRIVE_BATCH_URL.to_string(),
+            requests: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENCY)),
         })
     }
 
-    /// Escape a value for use in a Google Drive API query string.
-    /// Backslashes must be escaped before quotes to prevent injection.
-    fn escape_query_value(value: &str) -> String {
-        value.replace('\\', "\\\\").replace('\'', "\\'")
+    /// Allow at most `max` requests in flight at once; at least one.
+    pub fn with_max_concurrency(mut self, max: usize) -> Self {
+        self.requests = Arc::new(Semaphore::new(max.max(1)));
+        self
+    }
+
+    /// Wait for a request slot. The slot is taken until the permit is
+    /// dropped, which callers do once the response is consumed.
+    async fn request_slot(&self) -> Result<OwnedSemaphorePermit> {
+        self.requests
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::Cancelled("Drive request limiter closed".to_string()))
+    }
+
+    /// Point the client at a local stand-in for the Drive API.
+    #[cfg(test)]
+    pub(crate) fn with_base_urls(mut self, api_base: &str, upload_base: &str) -> Self {
+        self.api_base = api_base.to_string();
+        self.upload_base = upload_base.to_string();
+        self.batch_url = format!("{}/batch/drive/v3", api_base);
+        self
     }
 
     /// Validate that a string looks like a Google Drive file/folder ID.
@@ -122,8 +225,9 @@ impl DriveClient {
 
     /// Get file metadata by ID.
     pub async fn get_file(&self, file_id: &str) -> Result<DriveFile> {
-        let url = format!("{}/files/{}", DRIVE_API_BASE, file_id);
+        let url = format!("{}/files/{}", self.api_base, file_id);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let response = self
             .metadata_http
@@ -142,8 +246,9 @@ impl DriveClient {
 
     /// Create a folder.
     pub async fn create_folder(&self, name: &str, parent_id: Option<&str>) -> Result<DriveFile> {
-        let url = format!("{}/files", DRIVE_API_BASE);
+        let url = format!("{}/files", self.api_base);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let mut metadata = serde_json::json!({
             "name": name,
@@ -173,43 +278,16 @@ impl DriveClient {
 
     /// List files in a folder.
     pub async fn list_folder(&self, folder_id: &str) -> Result<Vec<DriveFile>> {
-        Self::validate_drive_id(folder_id)?;
-
         let mut all_files = Vec::new();
         let mut page_token: Option<String> = None;
 
         loop {
-            let url = format!("{}/files", DRIVE_API_BASE);
-            let auth = self.auth_header().await?;
-
-            let query = format!(
-                "'{}' in parents and trashed = false",
-                Self::escape_query_value(folder_id)
-            );
-
-            let mut request = self
-                .metadata_http
-                .get(&url)
-                .header(header::AUTHORIZATION, auth)
-                .query(&[
-                    ("q", query.as_str()),
-                    ("fields", "files(id,name,mimeType,size,createdTime,modifiedTime,parents,md5Checksum,trashed),nextPageToken"),
-                    ("pageSize", "1000"),
-                ]);
-
-            if let Some(token) = &page_token {
-                request = request.query(&[("pageToken", token.as_str())]);
-            }
-
-            let response = request
-                .send()
-                .await
-                .map_err(|e| Error::Network(format!("Failed to list folder: {}", e)))?;
-
-            let list_response: FileListResponse = self.handle_response(response).await?;
-            all_files.extend(list_response.files);
+            let (files, next) = self
+                .list_folder_page(folder_id, page_token.as_deref(), MAX_PAGE_SIZE)
+                .await?;
+            all_files.extend(files);
 
-            match list_response.next_page_token {
+            match next {
                 Some(token) => page_token = Some(token),
                 None => break,
             }
@@ -218,17 +296,63 @@ impl DriveClient {
         Ok(all_files)
     }
 
+    /// List one page of a folder, passing Drive's page token through.
+    ///
+    /// `page_size` is capped at what Drive accepts per request. Returns the
+    /// files and the token of the next page, if any.
+    pub async fn list_folder_page(
+        &self,
+        folder_id: &str,
+        page_token: Option<&str>,
+        page_size: usize,
+    ) -> Result<(Vec<DriveFile>, Option<String>)> {
+        Self::validate_drive_id(folder_id)?;
+
+        let url = format!("{}/files", self.api_base);
+        let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
+
+        let query = format!(
+            "'{}' in parents and trashed = false",
+            escape_drive_query(folder_id)
+        );
+        let page_size = page_size.clamp(1, MAX_PAGE_SIZE).to_string();
+
+        let mut request = self
+            .metadata_http
+            .get(&url)
+            .header(header::AUTHORIZATION, auth)
+            .query(&[
+                ("q", query.as_str()),
+                ("fields", "files(id,name,mimeType,size,createdTime,modifiedTime,parents,md5Checksum,trashed),nextPageToken"),
+                ("pageSize", page_size.as_str()),
+            ]);
+
+        if let Some(token) = page_token {
+            request = request.query(&[("pageToken", token)]);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Error::Network(format!("Failed to list folder: {}", e)))?;
+
+        let list_response: FileListResponse = self.handle_response(response).await?;
+        Ok((list_response.files, list_response.next_page_token))
+    }
+
     /// Find a file by name in a folder.
     pub async fn find_file(&self, name: &str, parent_id: &str) -> Result<Option<DriveFile>> {
         Self::validate_drive_id(parent_id)?;
 
-        let url = format!("{}/files", DRIVE_API_BASE);
+        let url = format!("{}/files", self.api_base);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let query = format!(
             "name = '{}' and '{}' in parents and trashed = false",
-            Self::escape_query_value(name),
-            Self::escape_query_value(parent_id)
+            escape_drive_query(name),
+            escape_drive_query(parent_id)
         );
 
         let response = self
@@ -255,8 +379,9 @@ impl DriveClient {
         parent_id: &str,
         data: Vec<u8>,
     ) -> Result<DriveFile> {
-        let url = format!("{}/files?uploadType=multipart", DRIVE_UPLOAD_BASE);
+        let url = format!("{}/files?uploadType=multipart", self.upload_base);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let metadata = serde_json::json!({
             "name": name,
@@ -268,22 +393,7 @@ impl DriveClient {
 
         // Build multipart request with a unique boundary
         let boundary = format!("AxiomVault{}", uuid::Uuid::new_v4().as_simple());
-        let mut body = Vec::new();
-
-        // Metadata part
-        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-        body.extend_from_slice(b"Content-Type: application/json; charset=UTF-8\r\n\r\n");
-        body.extend_from_slice(metadata_json.as_bytes());
-        body.extend_from_slice(b"\r\n");
-
-        // Data part
-        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-        body.extend_from_slice(b"Content-Type: application/octet-stream\r\n\r\n");
-        body.extend_from_slice(&data);
-        body.extend_from_slice(b"\r\n");
-
-        // End boundary
-        body.extend_from_slice(format!("--{}--", boundary).as_bytes());
+        let body = multipart_related(&boundary, &metadata_json, &data);
 
         let response = self
             .http
@@ -307,8 +417,9 @@ impl DriveClient {
 
     /// Update an existing file.
     pub async fn update_file(&self, file_id: &str, data: Vec<u8>) -> Result<DriveFile> {
-        let url = format!("{}/files/{}?uploadType=media", DRIVE_UPLOAD_BASE, file_id);
+        let url = format!("{}/files/{}?uploadType=media", self.upload_base, file_id);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let response = self
             .http
@@ -334,8 +445,9 @@ impl DriveClient {
         parent_id: &str,
         total_size: u64,
     ) -> Result<String> {
-        let url = format!("{}/files?uploadType=resumable", DRIVE_UPLOAD_BASE);
+        let url = format!("{}/files?uploadType=resumable", self.upload_base);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let metadata = serde_json::json!({
             "name": name,
@@ -355,23 +467,64 @@ impl DriveClient {
 
         if !response.status().is_success() {
             let status = response.status();
+            let retry_after = http_client::retry_after(response.headers());
             let body = response.text().await.unwrap_or_default();
-            return Err(Error::Network(format!(
-                "Failed to start resumable upload: {} - {}",
-                status, body
-            )));
+            return Err(drive_error(
+                "Failed to start resumable upload",
+                status,
+                retry_after,
+                &body,
+            ));
         }
 
         // Extract upload URI from Location header
-        let upload_uri = response
+        Self::upload_uri(&response)
+    }
+
+    /// Start a resumable upload session that replaces an existing file.
+    pub async fn start_resumable_update(&self, file_id: &str, total_size: u64) -> Result<String> {
+        Self::validate_drive_id(file_id)?;
+        let url = format!(
+            "{}/files/{}?uploadType=resumable",
+            self.upload_base, file_id
+        );
+        let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
+
+        let response = self
+            .metadata_http
+            .patch(&url)
+            .header(header::AUTHORIZATION, auth)
+            .header("X-Upload-Content-Length", total_size.to_string())
+            .header(header::CONTENT_LENGTH, "0")
+            .send()
+            .await
+            .map_err(|e| Error::Network(format!("Failed to start resumable upload: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let retry_after = http_client::retry_after(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            return Err(drive_error(
+                "Failed to start resumable upload",
+                status,
+                retry_after,
+                &body,
+            ));
+        }
+
+        Self::upload_uri(&response)
+    }
+
+    /// Extract the session URI from a resumable upload start response.
+    fn upload_uri(response: &reqwest::Response) -> Result<String> {
+        Ok(response
             .headers()
             .get(header::LOCATION)
             .ok_or_else(|| Error::Network("No upload URI in response".to_string()))?
             .to_str()
             .map_err(|e| Error::Network(format!("Invalid upload URI: {}", e)))?
-            .to_string();
-
-        Ok(upload_uri)
+            .to_string())
     }
 
     /// Upload a chunk to a resumable upload session.
@@ -382,8 +535,29 @@ impl DriveClient {
         start_byte: u64,
         total_size: u64,
     ) -> Result<Option<DriveFile>> {
+        match self
+            .send_chunk(upload_uri, data, start_byte, total_size)
+            .await?
+        {
+            ResumableStatus::Complete(file) => Ok(Some(file)),
+            ResumableStatus::Incomplete { .. } => Ok(None),
+        }
+    }
+
+    /// Upload a chunk and report how many bytes Drive has acknowledged.
+    ///
+    /// # Errors
+    /// - `NotFound` if the session expired
+    pub async fn send_chunk(
+        &self,
+        upload_uri: &str,
+        data: &[u8],
+        start_byte: u64,
+        total_size: u64,
+    ) -> Result<ResumableStatus> {
         let end_byte = start_byte + data.len() as u64 - 1;
         let content_range = format!("bytes {}-{}/{}", start_byte, end_byte, total_size);
+        let _slot = self.request_slot().await?;
 
         let response = self
             .http
@@ -395,6 +569,38 @@ impl DriveClient {
             .await
             .map_err(|e| Error::Network(format!("Failed to upload chunk: {}", e)))?;
 
+        Self::resumable_status(response, "Chunk upload failed").await
+    }
+
+    /// Ask Drive how many bytes of a resumable upload it has stored.
+    ///
+    /// Sends an empty `PUT` with `Content-Range: bytes */total`.
+    ///
+    /// # Errors
+    /// - `NotFound` if the session expired
+    pub async fn query_resumable_upload(
+        &self,
+        upload_uri: &str,
+        total_size: u64,
+    ) -> Result<ResumableStatus> {
+        let _slot = self.request_slot().await?;
+        let response = self
+            .http
+            .put(upload_uri)
+            .header(header::CONTENT_LENGTH, "0")
+            .header(header::CONTENT_RANGE, format!("bytes */{}", total_size))
+            .send()
+            .await
+            .map_err(|e| Error::Network(format!("Failed to query upload status: {}", e)))?;
+
+        Self::resumable_status(response, "Upload status query failed").await
+    }
+
+    /// Interpret a response from a resumable upload session.
+    async fn resumable_status(
+        response: reqwest::Response,
+        context: &str,
+    ) -> Result<ResumableStatus> {
         let status = response.status();
 
         if status == StatusCode::OK || status == StatusCode::CREATED {
@@ -403,18 +609,22 @@ impl DriveClient {
                 .json()
                 .await
                 .map_err(|e| Error::Network(format!("Failed to parse upload response: {}", e)))?;
-            Ok(Some(file))
-        } else if status == StatusCode::PERMANENT_REDIRECT
-            || status == StatusCode::from_u16(308).unwrap()
-        {
+            Ok(ResumableStatus::Complete(file))
+        } else if status == StatusCode::PERMANENT_REDIRECT {
             // More chunks needed (308 Resume Incomplete)
-            Ok(None)
+            let range = response
+                .headers()
+                .get(header::RANGE)
+                .and_then(|v| v.to_str().ok());
+            Ok(ResumableStatus::Incomplete {
+                acknowledged: parse_acknowledged(range),
+            })
+        } else if status == StatusCode::NOT_FOUND || status == StatusCode::GONE {
+            Err(Error::NotFound("Upload session expired".to_string()))
         } else {
+            let retry_after = http_client::retry_after(response.headers());
             let body = response.text().await.unwrap_or_default();
-            Err(Error::Network(format!(
-                "Chunk upload failed: {} - {}",
-                status, body
-            )))
+            Err(drive_error(context, status, retry_after, &body))
         }
     }
 
@@ -423,13 +633,24 @@ impl DriveClient {
         &self,
         name: &str,
         parent_id: &str,
-        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
         total_size: u64,
     ) -> Result<DriveFile> {
         let upload_uri = self
             .start_resumable_upload(name, parent_id, total_size)
             .await?;
+        self.upload_to_session(&upload_uri, stream, total_size)
+            .await
+    }
 
+    /// Stream all content into a resumable upload session started with
+    /// [`start_resumable_upload`](Self::start_resumable_upload).
+    pub async fn upload_to_session(
+        &self,
+        upload_uri: &str,
+        mut stream: Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>,
+        total_size: u64,
+    ) -> Result<DriveFile> {
         let mut bytes_uploaded = 0u64;
         let mut buffer = Vec::with_capacity(CHUNK_SIZE);
 
@@ -441,7 +662,7 @@ impl DriveClient {
             while buffer.len() >= CHUNK_SIZE {
                 let chunk_to_upload: Vec<u8> = buffer.drain(..CHUNK_SIZE).collect();
                 let result = self
-                    .upload_chunk(&upload_uri, &chunk_to_upload, bytes_uploaded, total_size)
+                    .upload_chunk(upload_uri, &chunk_to_upload, bytes_uploaded, total_size)
                     .await?;
 
                 bytes_uploaded += chunk_to_upload.len() as u64;
@@ -455,7 +676,7 @@ impl DriveClient {
         // Upload remaining bytes
         if !buffer.is_empty() {
             let result = self
-                .upload_chunk(&upload_uri, &buffer, bytes_uploaded, total_size)
+                .upload_chunk(upload_uri, &buffer, bytes_uploaded, total_size)
                 .await?;
 
             if let Some(file) = result {
@@ -466,10 +687,41 @@ impl DriveClient {
         Err(Error::Network("Upload did not complete".to_string()))
     }
 
+    /// Cancel a resumable upload session so Drive discards what it holds.
+    ///
+    /// A session that already expired counts as cancelled.
+    pub async fn cancel_resumable_upload(&self, upload_uri: &str) -> Result<()> {
+        let _slot = self.request_slot().await?;
+        let response = self
+            .metadata_http
+            .delete(upload_uri)
+            .header(header::CONTENT_LENGTH, "0")
+            .send()
+            .await
+            .map_err(|e| Error::Network(format!("Failed to cancel upload: {}", e)))?;
+
+        // Drive answers a successful cancel with 499 Client Closed Request.
+        let status = response.status();
+        if status.is_success()
+            || status.as_u16() == 499
+            || status == StatusCode::NOT_FOUND
+            || status == StatusCode::GONE
+        {
+            Ok(())
+        } else {
+            let body = response.text().await.unwrap_or_default();
+            Err(Error::Network(format!(
+                "Upload cancel failed: {} - {}",
+                status, body
+            )))
+        }
+    }
+
     /// Download file content.
     pub async fn download(&self, file_id: &str) -> Result<Vec<u8>> {
-        let url = format!("{}/files/{}", DRIVE_API_BASE, file_id);
+        let url = format!("{}/files/{}", self.api_base, file_id);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let response = self
             .http
@@ -501,8 +753,9 @@ impl DriveClient {
         &self,
         file_id: &str,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>> {
-        let url = format!("{}/files/{}", DRIVE_API_BASE, file_id);
+        let url = format!("{}/files/{}", self.api_base, file_id);
         let auth = self.auth_header().await?;
+        let slot = self.request_slot().await?;
 
         let response = self
             .http
@@ -522,17 +775,21 @@ impl DriveClient {
             )));
         }
 
-        let stream = response
-            .bytes_stream()
-            .map(|result| result.map_err(|e| Error::Network(format!("Stream read error: {}", e))));
+        // The body is still in flight while the stream is read, so the
+        // stream keeps the slot.
+        let stream = response.bytes_stream().map(move |result| {
+            let _slot = &slot;
+            result.map_err(|e| Error::Network(format!("Stream read error: {}", e)))
+        });
 
         Ok(Box::pin(stream))
     }
 
     /// Delete a file.
     pub async fn delete(&self, file_id: &str) -> Result<()> {
-        let url = format!("{}/files/{}", DRIVE_API_BASE, file_id);
+        let url = format!("{}/files/{}", self.api_base, file_id);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let response = self
             .metadata_http
@@ -554,6 +811,96 @@ impl DriveClient {
         }
     }
 
+    /// Delete many files, grouped into batch requests of at most
+    /// [`MAX_BATCH_SIZE`] deletes each.
+    ///
+    /// Returns the outcome of each id in the order given; an id Drive
+    /// rejects, or one that is not a valid Drive ID, fails on its own.
+    ///
+    /// # Errors
+    /// A batch request that fails as a whole. Files deleted by earlier
+    /// batches stay deleted.
+    pub async fn batch_delete(&self, file_ids: &[String]) -> Result<Vec<(String, Result<()>)>> {
+        let mut results: Vec<Option<Result<()>>> = Vec::with_capacity(file_ids.len());
+        let mut valid = Vec::new();
+        for (position, id) in file_ids.iter().enumerate() {
+            match Self::validate_drive_id(id) {
+                Ok(()) => {
+                    results.push(None);
+                    valid.push((position, id));
+                }
+                Err(e) => results.push(Some(Err(e))),
+            }
+        }
+
+        for batch in valid.chunks(MAX_BATCH_SIZE) {
+            let ids: Vec<String> = batch.iter().map(|(_, id)| id.to_string()).collect();
+            let mut answers = self.send_delete_batch(&ids).await?;
+            for (index, (position, id)) in batch.iter().enumerate() {
+                results[*position] = Some(match answers.remove(&index) {
+                    Some((status, _)) if status.is_success() => Ok(()),
+                    Some((status, body)) => Err(drive_error("API error", status, None, &body)),
+                    None => Err(Error::Network(format!(
+                        "Drive batch response has no answer for {}",
+                        id
+                    ))),
+                });
+            }
+        }
+
+        Ok(file_ids
+            .iter()
+            .cloned()
+            .zip(results.into_iter().flatten())
+            .collect())
+    }
+
+    /// Send one batch of deletes and return the answers by position.
+    async fn send_delete_batch(
+        &self,
+        file_ids: &[String],
+    ) -> Result<HashMap<usize, (StatusCode, String)>> {
+        let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
+
+        let boundary = format!("AxiomVault{}", uuid::Uuid::new_v4().as_simple());
+        let response = self
+            .metadata_http
+            .post(&self.batch_url)
+            .header(header::AUTHORIZATION, auth)
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/mixed; boundary={}", boundary),
+            )
+            .body(batch_delete_body(&boundary, file_ids))
+            .send()
+            .await
+            .map_err(|e| Error::Network(format!("Failed to send delete batch: {}", e)))?;
+
+        let status = response.status();
+        let retry_after = http_client::retry_after(response.headers());
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::Network(format!("Failed to read batch response: {}", e)))?;
+        if !status.is_success() {
+            return Err(drive_error("API error", status, retry_after, &body));
+        }
+        let boundary = multipart_boundary(&content_type).ok_or_else(|| {
+            Error::Network(format!(
+                "Drive batch response is not multipart: {}",
+                content_type
+            ))
+        })?;
+        Ok(parse_batch_response(boundary, &body))
+    }
+
     /// Move/rename a file.
     pub async fn move_file(
         &self,
@@ -562,8 +909,9 @@ impl DriveClient {
         new_parent: Option<&str>,
         current_parent: Option<&str>,
     ) -> Result<DriveFile> {
-        let url = format!("{}/files/{}", DRIVE_API_BASE, file_id);
+        let url = format!("{}/files/{}", self.api_base, file_id);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let mut metadata = serde_json::json!({});
         if let Some(name) = new_name {
@@ -604,8 +952,9 @@ impl DriveClient {
         new_name: &str,
         parent_id: &str,
     ) -> Result<DriveFile> {
-        let url = format!("{}/files/{}/copy", DRIVE_API_BASE, file_id);
+        let url = format!("{}/files/{}/copy", self.api_base, file_id);
         let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
 
         let metadata = serde_json::json!({
             "name": new_name,
@@ -629,19 +978,322 @@ impl DriveClient {
         self.handle_response(response).await
     }
 
+    /// Get the account's storage quota.
+    pub async fn get_quota(&self) -> Result<StorageQuota> {
+        let url = format!("{}/about", self.api_base);
+        let auth = self.auth_header().await?;
+        let _slot = self.request_slot().await?;
+
+        let response = self
+            .metadata_http
+            .get(&url)
+            .header(header::AUTHORIZATION, auth)
+            .query(&[("fields", "storageQuota")])
+            .send()
+            .await
+            .map_err(|e| Error::Network(format!("Failed to get storage quota: {}", e)))?;
+
+        let about: AboutResponse = self.handle_response(response).await?;
+        parse_quota(about.storage_quota)
+    }
+
     /// Handle API response with error checking.
     async fn handle_response<T: serde::de::DeserializeOwned>(
         &self,
         response: reqwest::Response,
     ) -> Result<T> {
-        http_client::handle_json_response(response).await
+        let status = response.status();
+        if status.is_success() {
+            return http_client::handle_json_response(response).await;
+        }
+        let retry_after = http_client::retry_after(response.headers());
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::Network(format!("Failed to read error response: {}", e)))?;
+        Err(drive_error("API error", status, retry_after, &body))
+    }
+}
+
+/// Escape a value for a single-quoted string literal in a Drive `q` query.
+///
+/// Drive's query syntax escapes `\` and `'` with a backslash; every other
+/// character is literal inside quotes. A single pass keeps an escape added
+/// for one character from being escaped again.
+fn escape_drive_query(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Frame file metadata and content as a `multipart/related` body.
+///
+/// The content part is always present, so an empty file is sent as a part
+/// with no bytes between its headers and the closing boundary.
+fn multipart_related(boundary: &str, metadata_json: &str, data: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    // Metadata part
+    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    body.extend_from_slice(b"Content-Type: application/json; charset=UTF-8\r\n\r\n");
+    body.extend_from_slice(metadata_json.as_bytes());
+    body.extend_from_slice(b"\r\n");
+
+    // Data part
+    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    body.extend_from_slice(b"Content-Type: application/octet-stream\r\n\r\n");
+    body.extend_from_slice(data);
+    body.extend_from_slice(b"\r\n");
+
+    // End boundary
+    body.extend_from_slice(format!("--{}--", boundary).as_bytes());
+    body
+}
+
+/// Frame deletes of `file_ids` as a `multipart/mixed` batch body. Each part
+/// is tagged `<item{index}>`, which Drive answers as `<response-item{index}>`.
+fn batch_delete_body(boundary: &str, file_ids: &[String]) -> String {
+    let mut body = String::new();
+    for (index, id) in file_ids.iter().enumerate() {
+        body.push_str(&format!(
+            "--{}\r\nContent-Type: application/http\r\nContent-ID: <item{}>\r\n\r\n\
+             DELETE /drive/v3/files/{}\r\n\r\n",
+            boundary, index, id
+        ));
+    }
+    body.push_str(&format!("--{}--\r\n", boundary));
+    body
+}
+
+/// The `boundary` parameter of a multipart `Content-Type`.
+fn multipart_boundary(content_type: &str) -> Option<&str> {
+    content_type
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))
+        .map(|boundary| boundary.trim_matches('"'))
+        .filter(|boundary| !boundary.is_empty())
+}
+
+/// Status and body of each answer in a batch response, by the index in its
+/// `Content-ID`. Parts without a recognizable index or status are skipped.
+fn parse_batch_response(boundary: &str, body: &str) -> HashMap<usize, (StatusCode, String)> {
+    let body = body.replace("\r\n", "\n");
+    let delimiter = format!("--{}", boundary);
+    let mut answers = HashMap::new();
+    for part in body.split(delimiter.as_str()) {
+        let Some((headers, response)) = part.split_once("\n\n") else {
+            continue;
+        };
+        let index = headers.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if !name.trim().eq_ignore_ascii_case("content-id") {
+                return None;
+            }
+            value
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .strip_prefix("response-item")?
+                .parse::<usize>()
+                .ok()
+        });
+        let status = response
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .and_then(|code| StatusCode::from_u16(code).ok());
+        if let (Some(index), Some(status)) = (index, status) {
+            let content = response
+                .split_once("\n\n")
+                .map_or("", |(_, content)| content.trim());
+            answers.insert(index, (status, content.to_string()));
+        }
     }
+    answers
+}
+
+/// Map a Drive error response to an error, preferring what its `reason`
+/// says over its status. `context` starts the message of a
+/// [`Error::Provider`].
+fn drive_error(
+    context: &str,
+    status: StatusCode,
+    retry_after: Option<Duration>,
+    body: &str,
+) -> Error {
+    drive_error_reason(body)
+        .unwrap_or_else(|| http_client::provider_error(context, status, retry_after, body))
+}
+
+/// Map the `reason` of a Drive error body to an error the status code
+/// alone cannot express.
+///
+/// Drive reports a full account, rate limiting and plain permission
+/// failures all as `403`, so only the reason tells them apart. Returns
+/// `None` for bodies without a recognized reason.
+fn drive_error_reason(body: &str) -> Option<Error> {
+    let response: DriveErrorResponse = serde_json::from_str(body).ok()?;
+    let message = response.error.message;
+    response
+        .error
+        .errors
+        .iter()
+        .find_map(|detail| match detail.reason.as_str() {
+            "storageQuotaExceeded" => Some(Error::QuotaExhausted(message.clone())),
+            "userRateLimitExceeded" | "rateLimitExceeded" => {
+                Some(Error::RateLimited(message.clone()))
+            }
+            _ => None,
+        })
+}
+
+/// Convert Drive's string-encoded quota to byte counts.
+fn parse_quota(quota: DriveStorageQuota) -> Result<StorageQuota> {
+    let parse = |value: Option<String>| -> Result<Option<u64>> {
+        value
+            .map(|v| {
+                v.parse::<u64>()
+                    .map_err(|e| Error::Network(format!("Invalid storage quota '{}': {}", v, e)))
+            })
+            .transpose()
+    };
+    Ok(StorageQuota {
+        used: parse(quota.usage)?.unwrap_or(0),
+        limit: parse(quota.limit)?,
+    })
+}
+
+/// Number of bytes acknowledged according to a 308 `Range` header.
+///
+/// Drive sends `Range: bytes=0-N` once it holds bytes `0..=N` and omits the
+/// header when it has nothing yet.
+fn parse_acknowledged(range: Option<&str>) -> u64 {
+    range
+        .and_then(|r| r.strip_prefix("bytes=0-"))
+        .and_then(|end| end.trim().parse::<u64>().ok())
+        .map_or(0, |end| end + 1)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_escape_drive_query_quotes_and_backslashes() {
+        assert_eq!(escape_drive_query("plain-name_01"), "plain-name_01");
+        assert_eq!(escape_drive_query("it's"), r"it\'s");
+        assert_eq!(escape_drive_query(r"C:\dir"), r"C:\\dir");
+        // A trailing backslash must not swallow the closing quote.
+        assert_eq!(escape_drive_query(r"ends\"), r"ends\\");
+        assert_eq!(escape_drive_query(r"\'"), r"\\\'");
+        assert_eq!(escape_drive_query("' or name != '"), r"\' or name != \'");
+        assert_eq!(escape_drive_query("naïve \"x\""), "naïve \"x\"");
+    }
+
+    #[test]
+    fn test_drive_error_reason_maps_quota_and_rate_limits() {
+        let body = |reason: &str| {
+            format!(
+                r#"{{"error":{{"code":403,"message":"msg","errors":[{{"domain":"global","reason":"{}"}}]}}}}"#,
+                reason
+            )
+        };
+        assert!(matches!(
+            drive_error_reason(&body("storageQuotaExceeded")),
+            Some(Error::QuotaExhausted(m)) if m == "msg"
+        ));
+        assert!(matches!(
+            drive_error_reason(&body("userRateLimitExceeded")),
+            Some(Error::RateLimited(_))
+        ));
+        assert!(matches!(
+            drive_error_reason(&body("rateLimitExceeded")),
+            Some(Error::RateLimited(_))
+        ));
+        assert!(drive_error_reason(&body("insufficientFilePermissions")).is_none());
+        assert!(drive_error_reason("not json").is_none());
+    }
+
+    #[test]
+    fn test_parse_batch_response_matches_answers_by_content_id() {
+        let body = "--batch_x\r\n\
+            Content-Type: application/http\r\n\
+            Content-ID: <response-item1>\r\n\r\n\
+            HTTP/1.1 404 Not Found\r\n\
+            Content-Type: application/json\r\n\r\n\
+            {\"error\":{\"code\":404,\"message\":\"File not found: b\"}}\r\n\
+            --batch_x\r\n\
+            Content-Type: application/http\r\n\
+            Content-ID: <response-item0>\r\n\r\n\
+            HTTP/1.1 204 No Content\r\n\r\n\r\n\
+            --batch_x--\r\n";
+        assert_eq!(
+            multipart_boundary("multipart/mixed; boundary=\"batch_x\""),
+            Some("batch_x")
+        );
+        assert_eq!(multipart_boundary("application/json"), None);
+
+        let answers = parse_batch_response("batch_x", body);
+        assert_eq!(answers.len(), 2);
+        assert_eq!(answers[&0].0, StatusCode::NO_CONTENT);
+        assert_eq!(answers[&1].0, StatusCode::NOT_FOUND);
+        assert!(answers[&1].1.contains("File not found: b"));
+
+        let request = batch_delete_body("b", &["id1".to_string(), "id2".to_string()]);
+        assert_eq!(request.matches("Content-ID: <item").count(), 2);
+        assert!(request.contains("DELETE /drive/v3/files/id2\r\n"));
+        assert!(request.ends_with("--b--\r\n"));
+    }
+
+    #[test]
+    fn test_multipart_body_with_empty_content() {
+        let body = multipart_related("b", r#"{"name":"empty"}"#, b"");
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--b\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{\"name\":\"empty\"}\r\n\
+             --b\r\nContent-Type: application/octet-stream\r\n\r\n\r\n--b--"
+        );
+    }
+
+    #[test]
+    fn test_parse_quota() {
+        let quota = parse_quota(DriveStorageQuota {
+            limit: Some("1000".to_string()),
+            usage: Some("400".to_string()),
+        })
+        .unwrap();
+        assert_eq!(quota.remaining(), Some(600));
+
+        // Unlimited accounts omit the limit.
+        let unlimited = parse_quota(DriveStorageQuota {
+            limit: None,
+            usage: Some("400".to_string()),
+        })
+        .unwrap();
+        assert_eq!(unlimited.limit, None);
+        assert_eq!(unlimited.remaining(), None);
+
+        assert!(parse_quota(DriveStorageQuota {
+            limit: Some("lots".to_string()),
+            usage: None,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_acknowledged_range() {
+        assert_eq!(parse_acknowledged(None), 0);
+        assert_eq!(parse_acknowledged(Some("bytes=0-262143")), 262_144);
+        assert_eq!(parse_acknowledged(Some("bytes=0-0")), 1);
+        assert_eq!(parse_acknowledged(Some("garbage")), 0);
+    }
+
     #[test]
     fn test_drive_file_is_folder() {
         let folder = DriveFile {
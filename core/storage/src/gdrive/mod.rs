@@ -11,5 +11,7 @@ pub mod client;
 pub mod provider;
 
 pub use auth::{AuthConfig, AuthManager, TokenManager, Tokens};
-pub use client::DriveClient;
-pub use provider::{create_gdrive_provider, GDriveConfig, GDriveProvider};
+pub use client::{DriveClient, DEFAULT_MAX_CONCURRENCY, MAX_BATCH_SIZE};
+pub use provider::{
+    create_gdrive_provider, gdrive_config_schema, GDriveConfig, GDriveProvider, IncompleteUpload,
+};
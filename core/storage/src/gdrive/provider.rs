@@ -5,14 +5,20 @@ use futures::{stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
+use tracing::warn;
 
-use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_common::{telemetry, Error, Result, VaultPath};
 
-use crate::provider::{ByteStream, Metadata, StorageProvider};
+use crate::provider::{
+    check_page_limit, ByteStream, HashAlgo, ListPage, Metadata, ProviderCapabilities,
+    ResumableUpload, StorageProvider, StorageQuota, UploadProgress, UploadSession,
+};
 
 use super::auth::{AuthConfig, AuthManager, TokenManager, Tokens};
-use super::client::{DriveClient, DriveFile};
+use super::client::{
+    DriveClient, DriveFile, ResumableStatus, DEFAULT_MAX_CONCURRENCY, RESUMABLE_CHUNK_SIZE,
+};
 
 /// Google Drive provider configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +30,14 @@ pub struct GDriveConfig {
     /// Optional custom OAuth2 configuration.
     #[serde(default)]
     pub auth_config: Option<AuthConfig>,
+    /// Most Drive requests in flight at once, however many operations
+    /// callers run in parallel.
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+fn default_max_concurrency() -> usize {
+    DEFAULT_MAX_CONCURRENCY
 }
 
 /// Google Drive storage provider.
@@ -34,7 +48,86 @@ pub struct GDriveProvider {
     client: DriveClient,
     token_manager: Arc<TokenManager>,
     /// Cache of path to file ID mapping.
-    path_cache: RwLock<HashMap<String, String>>,
+    path_cache: RwLock<PathCache>,
+    /// Resumable sessions that failed and could not be cleaned up yet.
+    incomplete_uploads: Mutex<Vec<IncompleteUpload>>,
+}
+
+/// A resumable upload that failed part-way through.
+///
+/// Drive may already have created the target file, so these are retried by
+/// [`GDriveProvider::cleanup_incomplete_uploads`] until the partial file is
+/// gone.
+#[derive(Debug, Clone)]
+pub struct IncompleteUpload {
+    /// Session URI returned when the upload was started.
+    pub uri: String,
+    /// Vault path the upload was writing.
+    pub path: VaultPath,
+    parent_id: String,
+    name: String,
+}
+
+/// Path to Drive file ID mapping shared by all operations on a provider.
+///
+/// Every invalidation bumps `generation`. Lookups that walk the tree record
+/// the generation they started from and only publish their results if no
+/// rename or delete happened meanwhile, so a slow resolve cannot re-insert
+/// a path that was just invalidated.
+#[derive(Debug, Default)]
+struct PathCache {
+    entries: HashMap<String, String>,
+    generation: u64,
+}
+
+impl PathCache {
+    fn get(&self, path: &str) -> Option<&String> {
+        self.entries.get(path)
+    }
+
+    fn insert(&mut self, path: String, file_id: String) {
+        self.entries.insert(path, file_id);
+    }
+
+    /// Insert only if nothing was invalidated since `generation` was observed.
+    fn insert_if_current(&mut self, generation: u64, path: String, file_id: String) {
+        if self.generation == generation {
+            self.entries.insert(path, file_id);
+        }
+    }
+
+    /// Remove `path` and every cached path below it.
+    fn invalidate_prefix(&mut self, path: &str) {
+        self.generation += 1;
+        if path == "/" {
+            self.entries.retain(|p, _| p == "/");
+            return;
+        }
+        let prefix = format!("{}/", path);
+        self.entries
+            .retain(|p, _| p != path && !p.starts_with(&prefix));
+    }
+
+    /// Re-key `from` and its descendants under `to`.
+    ///
+    /// Drive file IDs survive moves, so descendants keep their IDs. Anything
+    /// previously cached under `to` is dropped first.
+    fn rename_prefix(&mut self, from: &str, to: &str, file_id: &str) {
+        let from_prefix = format!("{}/", from);
+        let moved: Vec<(String, String)> = self
+            .entries
+            .iter()
+            .filter_map(|(p, id)| {
+                p.strip_prefix(&from_prefix)
+                    .map(|rest| (format!("{}/{}", to, rest), id.clone()))
+            })
+            .collect();
+
+        self.invalidate_prefix(from);
+        self.invalidate_prefix(to);
+        self.entries.insert(to.to_string(), file_id.to_string());
+        self.entries.extend(moved);
+    }
 }
 
 impl GDriveProvider {
@@ -55,9 +148,10 @@ impl GDriveProvider {
 
         let auth_manager = AuthManager::new(auth_config)?;
         let token_manager = Arc::new(TokenManager::new(auth_manager, config.tokens.clone()));
-        let client = DriveClient::new(token_manager.clone())?;
+        let client =
+            DriveClient::new(token_manager.clone())?.with_max_concurrency(config.max_concurrency);
 
-        let mut path_cache = HashMap::new();
+        let mut path_cache = PathCache::default();
         // Cache root mapping
         path_cache.insert("/".to_string(), config.folder_id.clone());
 
@@ -66,6 +160,7 @@ impl GDriveProvider {
             client,
             token_manager,
             path_cache: RwLock::new(path_cache),
+            incomplete_uploads: Mutex::new(Vec::new()),
         })
     }
 
@@ -74,36 +169,37 @@ impl GDriveProvider {
         self.token_manager.get_tokens().await
     }
 
+    /// The token manager, e.g. to run `spawn_refresher` for a long-lived
+    /// session.
+    pub fn token_manager(&self) -> Arc<TokenManager> {
+        self.token_manager.clone()
+    }
+
     /// Resolve a VaultPath to a Google Drive file ID.
     async fn resolve_path(&self, path: &VaultPath) -> Result<String> {
         let path_str = path.to_string();
 
         // Check cache first
-        {
+        let generation = {
             let cache = self.path_cache.read().await;
-            if let Some(id) = cache.get(&path_str) {
+            let cached = cache.get(&path_str);
+            telemetry::metrics::cache_lookup("gdrive_path", cached.is_some());
+            if let Some(id) = cached {
                 return Ok(id.clone());
             }
-        }
-
-        // Resolve path by walking the tree
-        let components = path.components();
+            cache.generation
+        };
 
-        if components.is_empty() {
-            // Root path
+        if path.is_root() {
             return Ok(self.config.folder_id.clone());
         }
 
+        // Resolve path by walking the tree
         let mut current_id = self.config.folder_id.clone();
-        let mut current_path = String::from("/");
 
-        for component in components {
-            // Build current path for caching
-            if current_path == "/" {
-                current_path = format!("/{}", component);
-            } else {
-                current_path = format!("{}/{}", current_path, component);
-            }
+        for current in path.ancestors().skip(1).chain([path.clone()]) {
+            let component = current.name().unwrap_or_default();
+            let current_path = current.to_string();
 
             // Check cache for this intermediate path
             {
@@ -125,9 +221,9 @@ impl GDriveProvider {
 
             current_id = file.id.clone();
 
-            // Cache the mapping
+            // Cache the mapping unless a rename or delete raced with this walk
             let mut cache = self.path_cache.write().await;
-            cache.insert(current_path.clone(), current_id.clone());
+            cache.insert_if_current(generation, current_path, current_id.clone());
         }
 
         Ok(current_id)
@@ -158,6 +254,10 @@ impl GDriveProvider {
             size: file.size_bytes(),
             is_directory: file.is_folder(),
             modified: file.modified_time.unwrap_or_else(chrono::Utc::now),
+            content_hash: file
+                .md5_checksum
+                .as_deref()
+                .map(|md5| HashAlgo::Md5.tag(md5)),
             etag: file.md5_checksum.or(Some(file_id.clone())),
             provider_data: Some(serde_json::json!({
                 "drive_id": file_id,
@@ -167,11 +267,17 @@ impl GDriveProvider {
         }
     }
 
-    /// Invalidate cache for a path.
+    /// Invalidate cache for a path and all of its descendants.
     async fn invalidate_cache(&self, path: &VaultPath) {
         let path_str = path.to_string();
         let mut cache = self.path_cache.write().await;
-        cache.remove(&path_str);
+        cache.invalidate_prefix(&path_str);
+    }
+
+    /// Move cached entries for `from` and its descendants under `to`.
+    async fn rename_cache(&self, from: &VaultPath, to: &VaultPath, file_id: &str) {
+        let mut cache = self.path_cache.write().await;
+        cache.rename_prefix(&from.to_string(), &to.to_string(), file_id);
     }
 
     /// Add path to cache.
@@ -199,9 +305,40 @@ impl GDriveProvider {
             // Use resumable upload for large files (>5MB)
             let total_size = data.len() as u64;
             let data_stream = stream::once(async { Ok(data) });
-            self.client
-                .upload_resumable(&name, &parent_id, Box::pin(data_stream), total_size)
-                .await?
+            let uri = self
+                .client
+                .start_resumable_upload(&name, &parent_id, total_size)
+                .await?;
+            let upload = IncompleteUpload {
+                uri,
+                path: path.clone(),
+                parent_id: parent_id.clone(),
+                name: name.clone(),
+            };
+            match self
+                .client
+                .upload_to_session(&upload.uri, Box::pin(data_stream), total_size)
+                .await
+            {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!(
+                        path = %path,
+                        session = %upload.uri,
+                        error = %e,
+                        "Abandoning resumable upload"
+                    );
+                    if let Err(cleanup) = self.clean_up_upload(&upload).await {
+                        warn!(
+                            session = %upload.uri,
+                            error = %cleanup,
+                            "Partial upload left behind; will retry clean-up"
+                        );
+                        self.incomplete_uploads.lock().await.push(upload);
+                    }
+                    return Err(e);
+                }
+            }
         } else {
             // Create new file
             self.client.upload_simple(&name, &parent_id, data).await?
@@ -212,6 +349,153 @@ impl GDriveProvider {
 
         Ok(self.to_metadata(file, path))
     }
+
+    /// Cancel an abandoned session and delete any file it already created.
+    async fn clean_up_upload(&self, upload: &IncompleteUpload) -> Result<()> {
+        self.client.cancel_resumable_upload(&upload.uri).await?;
+        if let Some(file) = self
+            .client
+            .find_file(&upload.name, &upload.parent_id)
+            .await?
+        {
+            self.client.delete(&file.id).await?;
+            self.invalidate_cache(&upload.path).await;
+        }
+        Ok(())
+    }
+
+    /// Retry clean-up of resumable uploads that failed earlier.
+    ///
+    /// Returns how many uploads were cleaned up. Uploads whose clean-up
+    /// fails again stay queued for the next call.
+    pub async fn cleanup_incomplete_uploads(&self) -> Result<usize> {
+        let pending = std::mem::take(&mut *self.incomplete_uploads.lock().await);
+        let mut cleaned = 0;
+        let mut remaining = Vec::new();
+        for upload in pending {
+            match self.clean_up_upload(&upload).await {
+                Ok(()) => cleaned += 1,
+                Err(e) => {
+                    warn!(session = %upload.uri, error = %e, "Partial upload clean-up failed");
+                    remaining.push(upload);
+                }
+            }
+        }
+        self.incomplete_uploads.lock().await.extend(remaining);
+        Ok(cleaned)
+    }
+
+    /// Resumable uploads still waiting for clean-up.
+    pub async fn incomplete_uploads(&self) -> Vec<IncompleteUpload> {
+        self.incomplete_uploads.lock().await.clone()
+    }
+
+    /// Delete a directory and everything below it.
+    ///
+    /// The files are deleted with batch requests instead of one request
+    /// each; the folder then goes with its now empty subfolders. Files that
+    /// are already gone are not an error.
+    ///
+    /// # Errors
+    /// The first file Drive refused to delete; the folder is kept then.
+    pub async fn delete_dir_all(&self, path: &VaultPath) -> Result<()> {
+        let folder_id = self.resolve_path(path).await?;
+
+        let mut files = Vec::new();
+        let mut folders = vec![folder_id.clone()];
+        while let Some(folder) = folders.pop() {
+            for child in self.client.list_folder(&folder).await? {
+                if child.is_folder() {
+                    folders.push(child.id);
+                } else {
+                    files.push(child.id);
+                }
+            }
+        }
+
+        let results = self.client.batch_delete(&files).await;
+        // Some files may be gone even when a later batch failed.
+        self.invalidate_cache(path).await;
+        let failed = results?.into_iter().find_map(|(_, result)| match result {
+            Ok(()) | Err(Error::NotFound(_)) => None,
+            Err(e) => Some(e),
+        });
+        if let Some(e) = failed {
+            return Err(e);
+        }
+
+        self.client.delete(&folder_id).await?;
+        self.invalidate_cache(path).await;
+        Ok(())
+    }
+
+    /// Convert a Drive session status, caching the file once it exists.
+    async fn upload_progress(
+        &self,
+        status: ResumableStatus,
+        path: &VaultPath,
+    ) -> Result<UploadProgress> {
+        match status {
+            ResumableStatus::Incomplete { acknowledged } => {
+                Ok(UploadProgress::InProgress { acknowledged })
+            }
+            ResumableStatus::Complete(file) => {
+                self.cache_path(path, &file.id).await;
+                Ok(UploadProgress::Complete(self.to_metadata(file, path)))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ResumableUpload for GDriveProvider {
+    fn chunk_size(&self) -> usize {
+        RESUMABLE_CHUNK_SIZE
+    }
+
+    async fn start_upload(&self, path: &VaultPath, total_size: u64) -> Result<UploadSession> {
+        let (parent_id, name) = self.resolve_parent(path).await?;
+
+        let uri = match self.client.find_file(&name, &parent_id).await? {
+            Some(existing) => {
+                self.client
+                    .start_resumable_update(&existing.id, total_size)
+                    .await?
+            }
+            None => {
+                self.client
+                    .start_resumable_upload(&name, &parent_id, total_size)
+                    .await?
+            }
+        };
+
+        Ok(UploadSession {
+            uri,
+            path: path.clone(),
+            total_size,
+        })
+    }
+
+    async fn query_upload(&self, session: &UploadSession) -> Result<UploadProgress> {
+        let status = self
+            .client
+            .query_resumable_upload(&session.uri, session.total_size)
+            .await?;
+        self.upload_progress(status, &session.path).await
+    }
+
+    async fn continue_upload(
+        &self,
+        session: &UploadSession,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<UploadProgress> {
+        let status = self
+            .client
+            .send_chunk(&session.uri, data, offset, session.total_size)
+            .await?;
+        self.upload_progress(status, &session.path).await
+    }
 }
 
 #[async_trait]
@@ -220,6 +504,16 @@ impl StorageProvider for GDriveProvider {
         "gdrive"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            native_rename: true,
+        }
+    }
+
+    fn as_resumable(&self) -> Option<&dyn ResumableUpload> {
+        Some(self)
+    }
+
     async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
         self.upload_data(path, data, false).await
     }
@@ -285,6 +579,31 @@ impl StorageProvider for GDriveProvider {
         Ok(results)
     }
 
+    /// The cursor is Drive's own page token, passed through unchanged.
+    async fn list_page(
+        &self,
+        path: &VaultPath,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> Result<ListPage> {
+        check_page_limit(limit)?;
+        let folder_id = self.resolve_path(path).await?;
+        let (files, next) = self
+            .client
+            .list_folder_page(&folder_id, cursor.as_deref(), limit)
+            .await?;
+
+        let mut results = Vec::with_capacity(files.len());
+
+        for file in files {
+            let child_path = path.join(&file.name)?;
+            self.cache_path(&child_path, &file.id).await;
+            results.push(self.to_metadata(file, &child_path));
+        }
+
+        Ok((results, next))
+    }
+
     async fn metadata(&self, path: &VaultPath) -> Result<Metadata> {
         let file_id = self.resolve_path(path).await?;
         let file = self.client.get_file(&file_id).await?;
@@ -363,8 +682,7 @@ impl StorageProvider for GDriveProvider {
             .await?;
 
         // Update cache
-        self.invalidate_cache(from).await;
-        self.cache_path(to, &file.id).await;
+        self.rename_cache(from, to, &file.id).await;
 
         Ok(self.to_metadata(file, to))
     }
@@ -394,6 +712,45 @@ impl StorageProvider for GDriveProvider {
 
         Ok(self.to_metadata(file, to))
     }
+
+    /// Drive copies files with `files.copy`, so the content never leaves
+    /// Google's servers.
+    async fn server_side_copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        self.copy(from, to).await
+    }
+
+    async fn quota(&self) -> Result<Option<StorageQuota>> {
+        self.client.get_quota().await.map(Some)
+    }
+}
+
+/// JSON Schema describing [`GDriveConfig`].
+pub fn gdrive_config_schema() -> serde_json::Value {
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Google Drive",
+        "type": "object",
+        "required": ["folder_id", "tokens"],
+        "properties": {
+            "folder_id": { "type": "string", "minLength": 1, "description": "ID of the Drive folder that holds the vault." },
+            "tokens": crate::cloud_auth::cloud_tokens_schema(),
+            "auth_config": {
+                "type": ["object", "null"],
+                "required": ["client_id", "client_secret", "redirect_url"],
+                "properties": {
+                    "client_id": { "type": "string" },
+                    "client_secret": { "type": "string" },
+                    "redirect_url": { "type": "string" }
+                }
+            },
+            "max_concurrency": {
+                "type": "integer",
+                "minimum": 1,
+                "default": DEFAULT_MAX_CONCURRENCY,
+                "description": "Most Drive requests in flight at once."
+            }
+        }
+    })
 }
 
 /// Create a Google Drive provider from configuration.
@@ -407,6 +764,7 @@ pub fn create_gdrive_provider(config: serde_json::Value) -> Result<Arc<dyn Stora
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gdrive::client::MAX_BATCH_SIZE;
     use chrono::Utc;
 
     fn create_test_config() -> GDriveConfig {
@@ -422,6 +780,7 @@ mod tests {
                 client_secret: "test_secret".to_string(),
                 redirect_url: "http://localhost:8080/callback".to_string(),
             }),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
         }
     }
 
@@ -470,6 +829,7 @@ mod tests {
         assert_eq!(metadata.size, Some(1024));
         assert!(!metadata.is_directory);
         assert_eq!(metadata.etag, Some("md5hash".to_string()));
+        assert_eq!(metadata.content_hash.as_deref(), Some("md5:md5hash"));
     }
 
     #[test]
@@ -498,6 +858,709 @@ mod tests {
         assert!(metadata.is_directory);
     }
 
+    #[tokio::test]
+    async fn test_invalidate_cache_removes_descendants() {
+        let provider = GDriveProvider::new(create_test_config()).unwrap();
+        for (path, id) in [("/a", "a_id"), ("/a/child", "child_id"), ("/ab", "ab_id")] {
+            provider
+                .cache_path(&VaultPath::parse(path).unwrap(), id)
+                .await;
+        }
+
+        provider
+            .invalidate_cache(&VaultPath::parse("/a").unwrap())
+            .await;
+
+        let cache = provider.path_cache.read().await;
+        assert!(cache.get("/a").is_none());
+        assert!(cache.get("/a/child").is_none());
+        assert_eq!(cache.get("/ab").map(String::as_str), Some("ab_id"));
+        assert_eq!(cache.get("/").map(String::as_str), Some("test_folder_id"));
+    }
+
+    #[tokio::test]
+    async fn test_rename_cache_drops_stale_descendants() {
+        let provider = GDriveProvider::new(create_test_config()).unwrap();
+        let a = VaultPath::parse("/a").unwrap();
+        let b = VaultPath::parse("/b").unwrap();
+        provider.cache_path(&a, "a_id").await;
+        provider
+            .cache_path(&VaultPath::parse("/a/child").unwrap(), "child_id")
+            .await;
+        provider
+            .cache_path(&VaultPath::parse("/b/old").unwrap(), "old_id")
+            .await;
+
+        provider.rename_cache(&a, &b, "a_id").await;
+
+        // Resolving /a/child must not hit the stale entry any more.
+        let cache = provider.path_cache.read().await;
+        assert!(cache.get("/a").is_none());
+        assert!(cache.get("/a/child").is_none());
+        assert!(cache.get("/b/old").is_none());
+        assert_eq!(cache.get("/b").map(String::as_str), Some("a_id"));
+        assert_eq!(cache.get("/b/child").map(String::as_str), Some("child_id"));
+    }
+
+    #[test]
+    fn test_path_cache_skips_insert_after_invalidation() {
+        let mut cache = PathCache::default();
+        let generation = cache.generation;
+        cache.invalidate_prefix("/a");
+        cache.insert_if_current(generation, "/a/child".to_string(), "stale".to_string());
+        assert!(cache.get("/a/child").is_none());
+
+        cache.insert_if_current(cache.generation, "/a".to_string(), "a_id".to_string());
+        assert_eq!(cache.get("/a").map(String::as_str), Some("a_id"));
+    }
+
+    /// Minimal stand-in for the Drive API that fails every chunk upload.
+    ///
+    /// Drive has already created the target file by the time the chunk
+    /// fails, so the file listing only shows it once a `PUT` arrived.
+    async fn spawn_failing_upload_server() -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let seen = requests.clone();
+        let session = format!("{}/session/1", base);
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break None;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break Some(pos + 4);
+                    }
+                };
+                let Some(header_end) = header_end else {
+                    continue;
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let content_length = head
+                    .lines()
+                    .find_map(|l| {
+                        let (name, value) = l.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+
+                let request_line = head.lines().next().unwrap_or_default().to_string();
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let target = parts.next().unwrap_or_default().to_string();
+                let route = target.split('?').next().unwrap_or_default().to_string();
+                let chunk_sent = {
+                    let mut seen = seen.lock().unwrap();
+                    seen.push(format!("{} {}", method, route));
+                    seen.iter().any(|r| r.starts_with("PUT "))
+                };
+
+                let (status, headers, body) = match (method.as_str(), route.as_str()) {
+                    ("GET", "/files") if chunk_sent => (
+                        "200 OK",
+                        String::new(),
+                        r#"{"files":[{"id":"partial1","name":"big.bin","mimeType":"application/octet-stream"}]}"#
+                            .to_string(),
+                    ),
+                    ("GET", "/files") => ("200 OK", String::new(), r#"{"files":[]}"#.to_string()),
+                    ("POST", "/files") => (
+                        "200 OK",
+                        format!("Location: {}\r\n", session),
+                        String::new(),
+                    ),
+                    ("PUT", "/session/1") => {
+                        ("503 Service Unavailable", String::new(), String::new())
+                    }
+                    ("DELETE", "/session/1") => {
+                        ("499 Client Closed Request", String::new(), String::new())
+                    }
+                    ("DELETE", "/files/partial1") => ("204 No Content", String::new(), String::new()),
+                    _ => ("404 Not Found", String::new(), String::new()),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (base, requests)
+    }
+
+    #[tokio::test]
+    async fn test_failed_resumable_upload_deletes_partial_file() {
+        let (base, requests) = spawn_failing_upload_server().await;
+        let mut provider = GDriveProvider::new(create_test_config()).unwrap();
+        provider.client = DriveClient::new(provider.token_manager.clone())
+            .unwrap()
+            .with_base_urls(&base, &base);
+
+        let path = VaultPath::parse("/big.bin").unwrap();
+        let data = vec![7u8; 5 * 1024 * 1024 + 1];
+        let result = provider
+            .upload_stream(&path, Box::pin(stream::once(async { Ok(data) })))
+            .await;
+        assert!(result.is_err());
+
+        let requests = requests.lock().unwrap().clone();
+        assert!(requests.contains(&"DELETE /session/1".to_string()));
+        assert!(requests.contains(&"DELETE /files/partial1".to_string()));
+        assert!(provider.incomplete_uploads().await.is_empty());
+        assert_eq!(provider.cleanup_incomplete_uploads().await.unwrap(), 0);
+    }
+
+    /// Answer every request with a file after `latency`, and track the
+    /// most requests the server was handling at once.
+    async fn spawn_slow_server(
+        latency: std::time::Duration,
+    ) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let seen = peak.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let in_flight = in_flight.clone();
+                let peak = seen.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 8192];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        let n = socket.read(&mut chunk).await.unwrap_or(0);
+                        if n == 0 {
+                            return;
+                        }
+                        buf.extend_from_slice(&chunk[..n]);
+                    }
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(latency).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    let body = r#"{"id":"f1","name":"a","mimeType":"application/octet-stream"}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        (base, peak)
+    }
+
+    #[tokio::test]
+    async fn test_requests_in_flight_stay_within_max_concurrency() {
+        let (base, peak) = spawn_slow_server(std::time::Duration::from_millis(50)).await;
+        let config = GDriveConfig {
+            max_concurrency: 3,
+            ..create_test_config()
+        };
+        let mut provider = GDriveProvider::new(config).unwrap();
+        provider.client = DriveClient::new(provider.token_manager.clone())
+            .unwrap()
+            .with_base_urls(&base, &base)
+            .with_max_concurrency(provider.config.max_concurrency);
+
+        let requests = (0..12).map(|_| provider.client.get_file("f1"));
+        for file in futures::future::join_all(requests).await {
+            assert_eq!(file.unwrap().id, "f1");
+        }
+        let peak = peak.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(
+            (2..=3).contains(&peak),
+            "peak of {} requests in flight",
+            peak
+        );
+    }
+
+    /// Stand-in for Drive holding folder `dir1` with 230 files and a
+    /// subfolder `sub1` with 20 more. Batch deletes answer `204` for every
+    /// file but `f7`, which is already gone. Records the size of each batch
+    /// and every other request.
+    async fn spawn_batch_delete_server() -> (
+        String,
+        Arc<std::sync::Mutex<Vec<usize>>>,
+        Arc<std::sync::Mutex<Vec<String>>>,
+    ) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let batches = Arc::new(std::sync::Mutex::new(Vec::<usize>::new()));
+        let requests = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let (seen_batches, seen_requests) = (batches.clone(), requests.clone());
+
+        let listing = |prefix: &str, count: usize, folder: Option<&str>| {
+            let mut files: Vec<String> = (0..count)
+                .map(|i| {
+                    format!(
+                        r#"{{"id":"{}{}","name":"{}{}","mimeType":"application/octet-stream"}}"#,
+                        prefix, i, prefix, i
+                    )
+                })
+                .collect();
+            if let Some(folder) = folder {
+                files.push(format!(
+                    r#"{{"id":"{}","name":"{}","mimeType":"application/vnd.google-apps.folder"}}"#,
+                    folder, folder
+                ));
+            }
+            format!(r#"{{"files":[{}]}}"#, files.join(","))
+        };
+        let top = listing("f", 230, Some("sub1"));
+        let sub = listing("s", 20, None);
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        break None;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break Some(pos + 4);
+                    }
+                };
+                let Some(header_end) = header_end else {
+                    continue;
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let content_length = head
+                    .lines()
+                    .find_map(|l| {
+                        let (name, value) = l.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let body = String::from_utf8_lossy(&buf[header_end..]).to_string();
+
+                let request_line = head.lines().next().unwrap_or_default().to_string();
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let target = parts.next().unwrap_or_default().to_string();
+                let (route, query) = target.split_once('?').unwrap_or((&target, ""));
+
+                let (content_type, response_body) = match (method.as_str(), route) {
+                    ("POST", "/batch/drive/v3") => {
+                        let ids: Vec<&str> = body
+                            .lines()
+                            .filter_map(|l| l.strip_prefix("DELETE /drive/v3/files/"))
+                            .collect();
+                        seen_batches.lock().unwrap().push(ids.len());
+                        let mut answer = String::new();
+                        for (index, id) in ids.iter().enumerate() {
+                            let status = if *id == "f7" {
+                                "404 Not Found"
+                            } else {
+                                "204 No Content"
+                            };
+                            answer.push_str(&format!(
+                                "--batch_resp\r\nContent-Type: application/http\r\nContent-ID: <response-item{}>\r\n\r\nHTTP/1.1 {}\r\n\r\n\r\n",
+                                index, status
+                            ));
+                        }
+                        answer.push_str("--batch_resp--\r\n");
+                        ("multipart/mixed; boundary=batch_resp", answer)
+                    }
+                    ("GET", "/files") if query.contains("sub1") => {
+                        ("application/json", sub.clone())
+                    }
+                    ("GET", "/files") if query.contains("dir1") => {
+                        ("application/json", top.clone())
+                    }
+                    _ => ("application/json", String::new()),
+                };
+                let status = if method == "DELETE" {
+                    "204 No Content"
+                } else {
+                    "200 OK"
+                };
+                seen_requests
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {}", method, route));
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    content_type,
+                    response_body.len(),
+                    response_body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (base, batches, requests)
+    }
+
+    #[tokio::test]
+    async fn test_batch_delete_groups_requests_and_reports_each_id() {
+        let (base, batches, requests) = spawn_batch_delete_server().await;
+        let mut provider = GDriveProvider::new(create_test_config()).unwrap();
+        provider.client = DriveClient::new(provider.token_manager.clone())
+            .unwrap()
+            .with_base_urls(&base, &base);
+
+        let mut ids: Vec<String> = (0..150).map(|i| format!("f{}", i)).collect();
+        ids.insert(3, "not/an id".to_string());
+        let results = provider.client.batch_delete(&ids).await.unwrap();
+        assert_eq!(*batches.lock().unwrap(), vec![100, 50]);
+        assert_eq!(results.len(), ids.len());
+        for ((id, result), expected) in results.iter().zip(&ids) {
+            assert_eq!(id, expected);
+            match id.as_str() {
+                "f7" => assert!(matches!(result, Err(Error::NotFound(_)))),
+                "not/an id" => assert!(matches!(result, Err(Error::InvalidInput(_)))),
+                _ => assert!(result.is_ok(), "{}: {:?}", id, result),
+            }
+        }
+
+        // Recursive delete: 250 files in three batches, then the folder.
+        batches.lock().unwrap().clear();
+        let path = VaultPath::parse("/dir").unwrap();
+        provider.cache_path(&path, "dir1").await;
+        provider.delete_dir_all(&path).await.unwrap();
+
+        let batches = batches.lock().unwrap().clone();
+        assert!(batches.iter().all(|size| *size <= MAX_BATCH_SIZE));
+        assert_eq!(batches.iter().sum::<usize>(), 250);
+        assert_eq!(batches.len(), 3);
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(
+            requests.iter().filter(|r| r.starts_with("DELETE ")).count(),
+            1
+        );
+        assert_eq!(requests.last().unwrap(), "DELETE /files/dir1");
+        assert!(provider.path_cache.read().await.get("/dir").is_none());
+    }
+
+    #[test]
+    fn test_max_concurrency_defaults_when_missing() {
+        let mut json = serde_json::to_value(create_test_config()).unwrap();
+        json.as_object_mut().unwrap().remove("max_concurrency");
+        let config: GDriveConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.max_concurrency, DEFAULT_MAX_CONCURRENCY);
+    }
+
+    /// Serve `GET /files` in two pages linked by the token `page-2`, and
+    /// record the query string of every request.
+    async fn spawn_paged_listing_server() -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let queries = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let seen = queries.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let head = String::from_utf8_lossy(&buf).to_string();
+                let target = head
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_string();
+                let query = target.split_once('?').map(|(_, q)| q).unwrap_or_default();
+                seen.lock().unwrap().push(query.to_string());
+
+                let body = if query.contains("pageToken=page-2") {
+                    r#"{"files":[{"id":"f3","name":"c","mimeType":"application/octet-stream"}]}"#
+                } else {
+                    r#"{"files":[{"id":"f1","name":"a","mimeType":"application/octet-stream"},{"id":"f2","name":"b","mimeType":"application/octet-stream"}],"nextPageToken":"page-2"}"#
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (base, queries)
+    }
+
+    #[tokio::test]
+    async fn test_list_page_passes_drive_page_token_through() {
+        let (base, queries) = spawn_paged_listing_server().await;
+        let mut provider = GDriveProvider::new(create_test_config()).unwrap();
+        provider.client = DriveClient::new(provider.token_manager.clone())
+            .unwrap()
+            .with_base_urls(&base, &base);
+        let root = VaultPath::root();
+
+        let (first, cursor) = provider.list_page(&root, None, 2).await.unwrap();
+        let names: Vec<_> = first.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(cursor.as_deref(), Some("page-2"));
+
+        let (second, cursor) = provider.list_page(&root, cursor, 2).await.unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].name, "c");
+        assert!(cursor.is_none());
+
+        // One request per page, with the page size and token forwarded.
+        let queries = queries.lock().unwrap().clone();
+        assert_eq!(queries.len(), 2);
+        assert!(queries[0].contains("pageSize=2"));
+        assert!(!queries[0].contains("pageToken"));
+        assert!(queries[1].contains("pageToken=page-2"));
+
+        // Listed children are cached like a full listing would.
+        let cached = provider
+            .resolve_path(&VaultPath::parse("/c").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(cached, "f3");
+    }
+
+    /// Serve a Drive account that is out of space: `about` reports the
+    /// quota and every upload fails with `storageQuotaExceeded`.
+    async fn spawn_full_account_server() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break None;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break Some(pos + 4);
+                    }
+                };
+                let Some(header_end) = header_end else {
+                    continue;
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let content_length = head
+                    .lines()
+                    .find_map(|l| {
+                        let (name, value) = l.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+
+                let mut parts = head.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let target = parts.next().unwrap_or_default().to_string();
+                let route = target.split('?').next().unwrap_or_default().to_string();
+
+                let (status, body) = match (method.as_str(), route.as_str()) {
+                    ("GET", "/about") => (
+                        "200 OK",
+                        r#"{"storageQuota":{"limit":"1000","usage":"990","usageInDrive":"990"}}"#,
+                    ),
+                    ("GET", "/files") => ("200 OK", r#"{"files":[]}"#),
+                    ("POST", "/files") => (
+                        "403 Forbidden",
+                        r#"{"error":{"code":403,"message":"The user's Drive storage quota has been exceeded.","errors":[{"domain":"usageLimits","reason":"storageQuotaExceeded"}]}}"#,
+                    ),
+                    _ => ("404 Not Found", ""),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        base
+    }
+
+    #[tokio::test]
+    async fn test_full_account_reports_quota_and_exhausted_uploads() {
+        let base = spawn_full_account_server().await;
+        let mut provider = GDriveProvider::new(create_test_config()).unwrap();
+        provider.client = DriveClient::new(provider.token_manager.clone())
+            .unwrap()
+            .with_base_urls(&base, &base);
+
+        let quota = provider.quota().await.unwrap().unwrap();
+        assert_eq!(quota.used, 990);
+        assert_eq!(quota.limit, Some(1000));
+        assert_eq!(quota.remaining(), Some(10));
+
+        let result = provider
+            .upload(&VaultPath::parse("/file.bin").unwrap(), vec![1, 2, 3])
+            .await;
+        assert!(matches!(result, Err(Error::QuotaExhausted(_))));
+    }
+
+    /// Serve a Drive folder that accepts one simple upload, records its
+    /// body and serves the file back empty.
+    async fn spawn_empty_file_server() -> (String, Arc<std::sync::Mutex<Vec<u8>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let uploaded = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = uploaded.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break None;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break Some(pos + 4);
+                    }
+                };
+                let Some(header_end) = header_end else {
+                    continue;
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let content_length = head
+                    .lines()
+                    .find_map(|l| {
+                        let (name, value) = l.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                while buf.len() < header_end + content_length {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+
+                let mut parts = head.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let target = parts.next().unwrap_or_default().to_string();
+                let route = target.split('?').next().unwrap_or_default().to_string();
+
+                let (status, body) = match (method.as_str(), route.as_str()) {
+                    ("GET", "/files") => ("200 OK", r#"{"files":[]}"#),
+                    ("POST", "/files") => {
+                        *seen.lock().unwrap() = buf[header_end..].to_vec();
+                        (
+                            "200 OK",
+                            r#"{"id":"empty1","name":"empty","mimeType":"application/octet-stream","size":"0"}"#,
+                        )
+                    }
+                    ("GET", "/files/empty1") => ("200 OK", ""),
+                    _ => ("404 Not Found", ""),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        (base, uploaded)
+    }
+
+    #[tokio::test]
+    async fn test_empty_file_roundtrip() {
+        let (base, uploaded) = spawn_empty_file_server().await;
+        let mut provider = GDriveProvider::new(create_test_config()).unwrap();
+        provider.client = DriveClient::new(provider.token_manager.clone())
+            .unwrap()
+            .with_base_urls(&base, &base);
+        let path = VaultPath::parse("/empty").unwrap();
+
+        let metadata = provider.upload(&path, Vec::new()).await.unwrap();
+        assert_eq!(metadata.size, Some(0));
+
+        // The content part is sent with no bytes before the closing boundary.
+        let body = String::from_utf8(uploaded.lock().unwrap().clone()).unwrap();
+        assert!(body.contains("\"name\":\"empty\""));
+        assert!(body.contains("Content-Type: application/octet-stream\r\n\r\n\r\n--"));
+        assert!(body.ends_with("--"));
+
+        assert!(provider.download(&path).await.unwrap().is_empty());
+    }
+
     #[test]
     fn test_create_gdrive_provider_factory() {
         let config = create_test_config();
@@ -519,4 +1582,89 @@ mod tests {
         let result = create_gdrive_provider(invalid_config);
         assert!(result.is_err());
     }
+
+    /// Serve Drive metadata lookups that fail: `busy` with a bare 503,
+    /// `throttled` with a 503 carrying `Retry-After`, anything else 404.
+    async fn spawn_failing_metadata_server() -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 4096];
+                loop {
+                    let n = socket.read(&mut chunk).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        break;
+                    }
+                }
+                let head = String::from_utf8_lossy(&buf).to_string();
+                let target = head.split_whitespace().nth(1).unwrap_or_default();
+                let route = target.split('?').next().unwrap_or_default();
+
+                let (status, extra, body) = match route {
+                    "/files/busy" => ("503 Service Unavailable", "", "backend busy"),
+                    "/files/throttled" => ("503 Service Unavailable", "Retry-After: 7\r\n", ""),
+                    _ => ("404 Not Found", "", "File not found"),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    extra,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+
+        base
+    }
+
+    #[tokio::test]
+    async fn test_drive_errors_keep_status_and_retry_hints() {
+        let base = spawn_failing_metadata_server().await;
+        let provider = GDriveProvider::new(create_test_config()).unwrap();
+        let client = DriveClient::new(provider.token_manager.clone())
+            .unwrap()
+            .with_base_urls(&base, &base);
+
+        match client.get_file("busy").await {
+            Err(Error::Provider {
+                status,
+                retryable,
+                retry_after,
+                message,
+            }) => {
+                assert_eq!(status, Some(503));
+                assert!(retryable);
+                assert_eq!(retry_after, None);
+                assert!(message.contains("backend busy"));
+            }
+            other => panic!("Expected Provider, got: {:?}", other.map(|f| f.id)),
+        }
+
+        assert!(matches!(
+            client.get_file("throttled").await,
+            Err(Error::Provider {
+                retryable: true,
+                retry_after: Some(hint),
+                ..
+            }) if hint == std::time::Duration::from_secs(7)
+        ));
+
+        assert!(matches!(
+            client.get_file("missing").await,
+            Err(Error::NotFound(_))
+        ));
+    }
 }
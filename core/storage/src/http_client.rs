@@ -10,6 +10,7 @@
 
 use std::time::Duration;
 
+use reqwest::header::{self, HeaderMap};
 use reqwest::{Client, StatusCode};
 
 use axiomvault_common::Error;
@@ -88,21 +89,66 @@ pub fn bearer_header(token: &str) -> String {
 /// | 409    | `AlreadyExists` |
 /// | other  | `Network` |
 pub fn map_status_error(status: StatusCode, body: &str) -> Error {
+    semantic_status_error(status, body)
+        .unwrap_or_else(|| Error::Network(format!("API error: {} - {}", status, body)))
+}
+
+/// Map an HTTP error response to an [`Error`], keeping its status and
+/// `Retry-After` hint.
+///
+/// Statuses with a meaning of their own map as in [`map_status_error`];
+/// any other becomes [`Error::Provider`], retryable for 408, 429 and
+/// server errors other than 501. `context` starts the message.
+pub fn provider_error(
+    context: &str,
+    status: StatusCode,
+    retry_after: Option<Duration>,
+    body: &str,
+) -> Error {
+    semantic_status_error(status, body).unwrap_or_else(|| Error::Provider {
+        status: Some(status.as_u16()),
+        retryable: status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS
+            || (status.is_server_error() && status != StatusCode::NOT_IMPLEMENTED),
+        retry_after,
+        message: format!("{}: {} - {}", context, status, body),
+    })
+}
+
+/// The error for a status with a meaning of its own, if it has one.
+fn semantic_status_error(status: StatusCode, body: &str) -> Option<Error> {
     if status == StatusCode::NOT_FOUND {
-        Error::NotFound(format!("Resource not found: {}", body))
+        Some(Error::NotFound(format!("Resource not found: {}", body)))
     } else if status == StatusCode::UNAUTHORIZED {
         // A 401 is the textbook transient auth case: the access token
         // is no longer accepted, but the refresh token is (probably)
         // still good. Surface as `AuthenticationExpired` so the retry
         // executor gives the token manager a chance to refresh.
-        Error::AuthenticationExpired("Invalid or expired token".to_string())
+        Some(Error::AuthenticationExpired(
+            "Invalid or expired token".to_string(),
+        ))
     } else if status == StatusCode::FORBIDDEN {
-        Error::NotPermitted("Access denied".to_string())
+        Some(Error::NotPermitted("Access denied".to_string()))
     } else if status == StatusCode::CONFLICT {
-        Error::AlreadyExists(format!("Resource conflict: {}", body))
+        Some(Error::AlreadyExists(format!("Resource conflict: {}", body)))
     } else {
-        Error::Network(format!("API error: {} - {}", status, body))
+        None
+    }
+}
+
+/// The wait a response's `Retry-After` header asks for, given either as
+/// seconds or as an HTTP date. A date in the past asks for no wait.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
     }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
 }
 
 /// Handle a `reqwest::Response`, deserializing the JSON body on success
@@ -188,6 +234,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_provider_error_service_unavailable_is_retryable() {
+        let err = provider_error("API error", StatusCode::SERVICE_UNAVAILABLE, None, "busy");
+        match err {
+            Error::Provider {
+                status,
+                retryable,
+                retry_after,
+                message,
+            } => {
+                assert_eq!(status, Some(503));
+                assert!(retryable);
+                assert_eq!(retry_after, None);
+                assert!(message.starts_with("API error: 503"));
+                assert!(message.contains("busy"));
+            }
+            other => panic!("Expected Provider, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_provider_error_bad_request_is_final() {
+        let err = provider_error("API error", StatusCode::BAD_REQUEST, None, "bad");
+        assert!(matches!(
+            err,
+            Error::Provider {
+                status: Some(400),
+                retryable: false,
+                ..
+            }
+        ));
+        let err = provider_error("API error", StatusCode::NOT_IMPLEMENTED, None, "");
+        assert!(matches!(
+            err,
+            Error::Provider {
+                retryable: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_provider_error_keeps_semantic_statuses() {
+        let err = provider_error("API error", StatusCode::NOT_FOUND, None, "file gone");
+        assert!(matches!(err, Error::NotFound(_)));
+        let err = provider_error("API error", StatusCode::UNAUTHORIZED, None, "");
+        assert!(matches!(err, Error::AuthenticationExpired(_)));
+        let err = provider_error("API error", StatusCode::FORBIDDEN, None, "");
+        assert!(matches!(err, Error::NotPermitted(_)));
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(header::RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        headers.insert(
+            header::RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(header::RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
+
     #[test]
     fn test_build_http_client() {
         let client = build_http_client();
@@ -6,7 +6,7 @@
 
 pub mod provider;
 
-pub use provider::{create_icloud_provider, ICloudConfig, ICloudProvider};
+pub use provider::{create_icloud_provider, icloud_config_schema, ICloudConfig, ICloudProvider};
 
 /// Detect the iCloud Drive mount point on macOS.
 ///
@@ -9,7 +9,7 @@ use std::sync::Arc;
 use axiomvault_common::{Error, Result, VaultPath};
 
 use crate::local::LocalProvider;
-use crate::provider::{ByteStream, Metadata, StorageProvider};
+use crate::provider::{ByteStream, ListPage, Metadata, ProviderCapabilities, StorageProvider};
 
 /// iCloud Drive provider configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +65,12 @@ impl StorageProvider for ICloudProvider {
         "icloud"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            native_rename: true,
+        }
+    }
+
     async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
         self.local.upload(path, data).await
     }
@@ -93,6 +99,15 @@ impl StorageProvider for ICloudProvider {
         self.local.list(path).await
     }
 
+    async fn list_page(
+        &self,
+        path: &VaultPath,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> Result<ListPage> {
+        self.local.list_page(path, cursor, limit).await
+    }
+
     async fn metadata(&self, path: &VaultPath) -> Result<Metadata> {
         self.local.metadata(path).await
     }
@@ -114,6 +129,25 @@ impl StorageProvider for ICloudProvider {
     }
 }
 
+/// JSON Schema describing [`ICloudConfig`].
+pub fn icloud_config_schema() -> serde_json::Value {
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "iCloud Drive",
+        "type": ["object", "null"],
+        "properties": {
+            "root_path": {
+                "type": ["string", "null"],
+                "description": "Override the auto-detected iCloud Drive path."
+            },
+            "subfolder": {
+                "type": ["string", "null"],
+                "description": "Subfolder within iCloud Drive, e.g. \"AxiomVault\"."
+            }
+        }
+    })
+}
+
 /// Create an iCloud Drive provider from configuration.
 pub fn create_icloud_provider(config: serde_json::Value) -> Result<Arc<dyn StorageProvider>> {
     let icloud_config: ICloudConfig = serde_json::from_value(config)
@@ -0,0 +1,195 @@
+//! Tracing and metrics wrapper for storage providers.
+//!
+//! [`InstrumentedProvider`] forwards every call to an inner provider inside
+//! a `provider_call` span and records call latency and transferred bytes
+//! through [`axiomvault_common::telemetry::metrics`]. Paths in span fields
+//! go through [`path_field`], so they honour path redaction.
+
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{debug_span, Instrument};
+
+use axiomvault_common::telemetry::metrics::{self, Direction};
+use axiomvault_common::telemetry::path_field;
+use axiomvault_common::{Result, VaultPath};
+
+use crate::provider::{
+    ByteStream, ListPage, Metadata, ProviderCapabilities, ResumableUpload, StorageProvider,
+    StorageQuota,
+};
+
+/// Storage provider decorator that traces and times every call.
+pub struct InstrumentedProvider {
+    inner: Arc<dyn StorageProvider>,
+}
+
+impl InstrumentedProvider {
+    /// Wrap a provider.
+    pub fn new(inner: Arc<dyn StorageProvider>) -> Self {
+        Self { inner }
+    }
+
+    /// Wrap a provider and return it as a trait object.
+    pub fn wrap(inner: Arc<dyn StorageProvider>) -> Arc<dyn StorageProvider> {
+        Arc::new(Self::new(inner))
+    }
+
+    async fn call<T, F>(&self, method: &'static str, path: &VaultPath, fut: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let span = debug_span!(
+            "provider_call",
+            provider = self.inner.name(),
+            method,
+            path = %path_field(path),
+        );
+        let started = Instant::now();
+        let result = fut.instrument(span).await;
+        metrics::provider_call(self.inner.name(), method, started.elapsed());
+        result
+    }
+}
+
+#[async_trait]
+impl StorageProvider for InstrumentedProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn as_resumable(&self) -> Option<&dyn ResumableUpload> {
+        self.inner.as_resumable()
+    }
+
+    async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
+        let len = data.len() as u64;
+        let result = self
+            .call("upload", path, self.inner.upload(path, data))
+            .await;
+        if result.is_ok() {
+            metrics::bytes(Direction::Uploaded, len);
+        }
+        result
+    }
+
+    async fn upload_stream(&self, path: &VaultPath, stream: ByteStream) -> Result<Metadata> {
+        self.call(
+            "upload_stream",
+            path,
+            self.inner.upload_stream(path, stream),
+        )
+        .await
+    }
+
+    async fn download(&self, path: &VaultPath) -> Result<Vec<u8>> {
+        let result = self.call("download", path, self.inner.download(path)).await;
+        if let Ok(data) = &result {
+            metrics::bytes(Direction::Downloaded, data.len() as u64);
+        }
+        result
+    }
+
+    async fn download_stream(&self, path: &VaultPath) -> Result<ByteStream> {
+        self.call("download_stream", path, self.inner.download_stream(path))
+            .await
+    }
+
+    async fn download_range(&self, path: &VaultPath, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let result = self
+            .call(
+                "download_range",
+                path,
+                self.inner.download_range(path, offset, len),
+            )
+            .await;
+        if let Ok(data) = &result {
+            metrics::bytes(Direction::Downloaded, data.len() as u64);
+        }
+        result
+    }
+
+    async fn exists(&self, path: &VaultPath) -> Result<bool> {
+        self.call("exists", path, self.inner.exists(path)).await
+    }
+
+    async fn delete(&self, path: &VaultPath) -> Result<()> {
+        self.call("delete", path, self.inner.delete(path)).await
+    }
+
+    async fn list(&self, path: &VaultPath) -> Result<Vec<Metadata>> {
+        self.call("list", path, self.inner.list(path)).await
+    }
+
+    async fn list_page(
+        &self,
+        path: &VaultPath,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> Result<ListPage> {
+        self.call("list_page", path, self.inner.list_page(path, cursor, limit))
+            .await
+    }
+
+    async fn metadata(&self, path: &VaultPath) -> Result<Metadata> {
+        self.call("metadata", path, self.inner.metadata(path)).await
+    }
+
+    async fn create_dir(&self, path: &VaultPath) -> Result<Metadata> {
+        self.call("create_dir", path, self.inner.create_dir(path))
+            .await
+    }
+
+    async fn delete_dir(&self, path: &VaultPath) -> Result<()> {
+        self.call("delete_dir", path, self.inner.delete_dir(path))
+            .await
+    }
+
+    async fn rename(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        self.call("rename", from, self.inner.rename(from, to)).await
+    }
+
+    async fn copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        self.call("copy", from, self.inner.copy(from, to)).await
+    }
+
+    async fn server_side_copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        self.call(
+            "server_side_copy",
+            from,
+            self.inner.server_side_copy(from, to),
+        )
+        .await
+    }
+
+    async fn quota(&self) -> Result<Option<StorageQuota>> {
+        self.inner.quota().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryProvider;
+
+    #[tokio::test]
+    async fn test_instrumented_provider_forwards_calls() {
+        let provider = InstrumentedProvider::wrap(Arc::new(MemoryProvider::new()));
+        let path = VaultPath::parse("/blob").unwrap();
+
+        assert_eq!(provider.name(), "memory");
+        provider.upload(&path, b"data".to_vec()).await.unwrap();
+        assert!(provider.exists(&path).await.unwrap());
+        assert_eq!(provider.download(&path).await.unwrap(), b"data");
+
+        let renamed = VaultPath::parse("/moved").unwrap();
+        provider.rename(&path, &renamed).await.unwrap();
+        assert!(!provider.exists(&path).await.unwrap());
+        provider.delete(&renamed).await.unwrap();
+    }
+}
@@ -17,6 +17,7 @@ pub mod gdrive;
 pub mod health;
 pub mod http_client;
 pub mod icloud;
+pub mod instrumented;
 pub mod local;
 pub mod memory;
 pub mod onedrive;
@@ -24,6 +25,8 @@ pub mod provider;
 pub mod rebuild;
 pub mod registry;
 pub mod shard_map;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 
 pub use cloud_auth::{CloudTokenManager, CloudTokens, TokenRefresher};
 pub use composite::{CompositeConfig, CompositeStorageProvider, RaidMode};
@@ -33,12 +36,20 @@ pub use gdrive::{GDriveConfig, GDriveProvider};
 pub use axiomvault_common::health::HealthStatus;
 pub use health::{HealthConfig, ProviderHealth};
 pub use icloud::{ICloudConfig, ICloudProvider};
+pub use instrumented::InstrumentedProvider;
 pub use local::LocalProvider;
 pub use memory::MemoryProvider;
 pub use onedrive::{OneDriveConfig, OneDriveProvider};
-pub use provider::{ConflictResolution, Metadata, StorageProvider};
+pub use provider::{
+    compare_content_hashes, copy_object, list_all, parse_content_hash, reader_stream,
+    rename_via_copy, stream_copy, stream_reader, ConflictResolution, CopyMechanism, HashAlgo,
+    HashComparison, ListPage, Metadata, MetadataStream, ProviderCapabilities, ResumableUpload,
+    StorageProvider, StorageQuota, UploadProgress, UploadSession, LIST_PAGE_SIZE,
+};
 pub use rebuild::{
     RaidRebuilder, RebuildCheckpoint, RebuildConfig, RebuildProgress, RebuildResult,
 };
-pub use registry::{create_default_registry, ProviderFactory, ProviderRegistry};
+pub use registry::{
+    create_default_registry, validate_against_schema, ProviderFactory, ProviderRegistry,
+};
 pub use shard_map::{ChunkEntry, ErasureParams, ShardLocation, ShardMap};
@@ -3,11 +3,15 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use futures::stream;
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use uuid::Uuid;
 
-use crate::provider::{ByteStream, Metadata, StorageProvider};
+use crate::provider::{
+    check_page_limit, ByteStream, HashAlgo, ListPage, Metadata, ProviderCapabilities,
+    StorageProvider,
+};
 use axiomvault_common::{Error, Result, VaultPath};
 
 /// File mode for vault files (owner read/write only).
@@ -22,6 +26,7 @@ const DIR_MODE: u32 = 0o700;
 /// Stores vault data in a local directory structure.
 pub struct LocalProvider {
     root: PathBuf,
+    hash_algo: Option<HashAlgo>,
 }
 
 impl LocalProvider {
@@ -77,7 +82,20 @@ impl LocalProvider {
             }
         }
 
-        Ok(Self { root })
+        Ok(Self {
+            root,
+            hash_algo: None,
+        })
+    }
+
+    /// Report content hashes computed with `algo`.
+    ///
+    /// The filesystem keeps no hashes, so a file is hashed when it is
+    /// written and whenever its metadata is read on its own. Listings carry
+    /// no hash; hashing every file of a directory would read all of them.
+    pub fn with_hash_algo(mut self, algo: HashAlgo) -> Self {
+        self.hash_algo = Some(algo);
+        self
     }
 
     /// Convert a VaultPath to a filesystem path.
@@ -107,6 +125,7 @@ impl LocalProvider {
             is_directory: fs_meta.is_dir(),
             modified,
             etag: Some(format!("{}-{}", modified.timestamp(), fs_meta.len())),
+            content_hash: None,
             provider_data: None,
         }
     }
@@ -118,6 +137,12 @@ impl StorageProvider for LocalProvider {
         "local"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            native_rename: true,
+        }
+    }
+
     async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
         let fs_path = self.to_fs_path(path);
 
@@ -197,7 +222,9 @@ impl StorageProvider for LocalProvider {
         }
 
         let fs_meta = fs::metadata(&fs_path).await?;
-        Ok(self.create_metadata(path, fs_meta))
+        let mut metadata = self.create_metadata(path, fs_meta);
+        metadata.content_hash = self.hash_algo.map(|algo| algo.hash(&data));
+        Ok(metadata)
     }
 
     async fn upload_stream(&self, path: &VaultPath, mut stream: ByteStream) -> Result<Metadata> {
@@ -231,6 +258,25 @@ impl StorageProvider for LocalProvider {
         Ok(Box::pin(stream))
     }
 
+    async fn download_range(&self, path: &VaultPath, offset: u64, len: u64) -> Result<Vec<u8>> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let fs_path = self.to_fs_path(path);
+        if fs_path.is_dir() {
+            return Err(Error::InvalidInput("Cannot download directory".to_string()));
+        }
+        let mut file = match fs::File::open(&fs_path).await {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::NotFound(format!("File not found: {}", path)));
+            }
+            result => result?,
+        };
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        let mut range = Vec::new();
+        file.take(len).read_to_end(&mut range).await?;
+        Ok(range)
+    }
+
     async fn exists(&self, path: &VaultPath) -> Result<bool> {
         let fs_path = self.to_fs_path(path);
         Ok(fs_path.exists())
@@ -283,6 +329,59 @@ impl StorageProvider for LocalProvider {
         Ok(results)
     }
 
+    /// Walks `read_dir` once per page, keeping only the `limit` smallest
+    /// names after the cursor, so memory stays bounded by the page size
+    /// however large the directory is. The cursor is the last name
+    /// returned, which stays valid while entries come and go.
+    async fn list_page(
+        &self,
+        path: &VaultPath,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> Result<ListPage> {
+        check_page_limit(limit)?;
+        let fs_path = self.to_fs_path(path);
+
+        if !fs_path.exists() {
+            return Err(Error::NotFound(format!("Directory not found: {}", path)));
+        }
+
+        if !fs_path.is_dir() {
+            return Err(Error::InvalidInput("Not a directory".to_string()));
+        }
+
+        let mut names = BTreeSet::new();
+        let mut more = false;
+        let mut entries = fs::read_dir(&fs_path).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if cursor.as_ref().is_some_and(|c| name <= *c) {
+                continue;
+            }
+            names.insert(name);
+            if names.len() > limit {
+                names.pop_last();
+                more = true;
+            }
+        }
+
+        let mut results = Vec::with_capacity(names.len());
+        for name in &names {
+            let child_vault_path = path.join(name)?;
+            // Removed since the scan: skip it like any later removal.
+            let Ok(fs_meta) = fs::metadata(fs_path.join(name)).await else {
+                continue;
+            };
+            results.push(self.create_metadata(&child_vault_path, fs_meta));
+        }
+
+        let next = if more { names.pop_last() } else { None };
+        Ok((results, next))
+    }
+
     async fn metadata(&self, path: &VaultPath) -> Result<Metadata> {
         let fs_path = self.to_fs_path(path);
 
@@ -291,7 +390,12 @@ impl StorageProvider for LocalProvider {
         }
 
         let fs_meta = fs::metadata(&fs_path).await?;
-        Ok(self.create_metadata(path, fs_meta))
+        let is_file = fs_meta.is_file();
+        let mut metadata = self.create_metadata(path, fs_meta);
+        if let Some(algo) = self.hash_algo.filter(|_| is_file) {
+            metadata.content_hash = Some(algo.hash(&fs::read(&fs_path).await?));
+        }
+        Ok(metadata)
     }
 
     async fn create_dir(&self, path: &VaultPath) -> Result<Metadata> {
@@ -480,6 +584,33 @@ mod tests {
         assert_eq!(downloaded, data);
     }
 
+    #[tokio::test]
+    async fn test_local_empty_blob_roundtrip() {
+        use futures::{stream, StreamExt};
+
+        let temp = TempDir::new().unwrap();
+        let provider = LocalProvider::new(temp.path()).unwrap();
+        let path = VaultPath::parse("/empty").unwrap();
+
+        let metadata = provider.upload(&path, Vec::new()).await.unwrap();
+        assert_eq!(metadata.size, Some(0));
+        assert!(provider.exists(&path).await.unwrap());
+        assert!(provider.download(&path).await.unwrap().is_empty());
+
+        let streamed = provider
+            .upload_stream(&path, Box::pin(stream::empty()))
+            .await
+            .unwrap();
+        assert_eq!(streamed.size, Some(0));
+        let chunks: Vec<_> = provider
+            .download_stream(&path)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(chunks.into_iter().all(|chunk| chunk.unwrap().is_empty()));
+    }
+
     #[tokio::test]
     async fn test_local_create_dir() {
         let temp = TempDir::new().unwrap();
@@ -635,4 +766,53 @@ mod tests {
             .unwrap();
         assert_eq!(contents.len(), 2);
     }
+
+    #[tokio::test]
+    async fn test_local_list_page_is_stable_under_changes() {
+        let temp = TempDir::new().unwrap();
+        let provider = LocalProvider::new(temp.path()).unwrap();
+        let dir = VaultPath::parse("/dir").unwrap();
+        provider.create_dir(&dir).await.unwrap();
+        for i in 0..20 {
+            provider
+                .upload(&dir.join(&format!("blob{:02}", i)).unwrap(), vec![i])
+                .await
+                .unwrap();
+        }
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let (page, next) = provider.list_page(&dir, cursor, 6).await.unwrap();
+            assert!(page.len() <= 6);
+            seen.extend(page.into_iter().map(|e| e.name));
+            pages += 1;
+            if pages == 1 {
+                // Churn on both sides of the cursor mid-pagination.
+                provider.delete(&dir.join("blob00").unwrap()).await.unwrap();
+                provider.delete(&dir.join("blob10").unwrap()).await.unwrap();
+                provider
+                    .upload(&dir.join("a-new").unwrap(), vec![])
+                    .await
+                    .unwrap();
+                provider
+                    .upload(&dir.join("z-new").unwrap(), vec![])
+                    .await
+                    .unwrap();
+            }
+            match next {
+                Some(c) => cursor = Some(c),
+                None => break,
+            }
+        }
+
+        let mut expected: Vec<String> = (0..20)
+            .filter(|i| *i != 10)
+            .map(|i| format!("blob{:02}", i))
+            .collect();
+        expected.push("z-new".to_string());
+        assert_eq!(seen, expected);
+        assert_eq!(pages, 4);
+    }
 }
@@ -4,10 +4,10 @@ use async_trait::async_trait;
 use chrono::Utc;
 use futures::stream;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use uuid::Uuid;
 
-use crate::provider::{ByteStream, Metadata, StorageProvider};
+use crate::provider::{ByteStream, HashAlgo, Metadata, ProviderCapabilities, StorageProvider};
 use axiomvault_common::{Error, Result, VaultPath};
 
 /// In-memory storage entry.
@@ -23,12 +23,13 @@ enum Entry {
 /// and lost on drop.
 pub struct MemoryProvider {
     storage: Arc<RwLock<HashMap<String, Entry>>>,
+    hash_algo: HashAlgo,
 }
 
 impl MemoryProvider {
     /// Create a new empty memory provider.
     pub fn new() -> Self {
-        let storage = Arc::new(RwLock::new(HashMap::new()));
+        let mut storage = HashMap::new();
 
         // Create root directory
         let root_meta = Metadata {
@@ -38,21 +39,64 @@ impl MemoryProvider {
             is_directory: true,
             modified: Utc::now(),
             etag: Some(Uuid::new_v4().to_string()),
+            content_hash: None,
             provider_data: None,
         };
 
-        storage.write().unwrap().insert(
+        storage.insert(
             "/".to_string(),
             Entry::Directory {
                 metadata: root_meta,
             },
         );
 
-        Self { storage }
+        Self {
+            storage: Arc::new(RwLock::new(storage)),
+            hash_algo: HashAlgo::default(),
+        }
+    }
+
+    /// Use `algo` for the content hashes of files stored from now on.
+    pub fn with_hash_algo(mut self, algo: HashAlgo) -> Self {
+        self.hash_algo = algo;
+        self
+    }
+
+    /// Flip the bits of the byte at `offset` of a stored file, leaving its
+    /// metadata alone, as silent corruption at rest would.
+    ///
+    /// # Errors
+    /// - `NotFound` if there is no file at `path`
+    /// - `InvalidInput` if `offset` is past its end
+    pub fn corrupt(&self, path: &VaultPath, offset: u64) -> Result<()> {
+        match self.write().get_mut(&Self::path_to_key(path)) {
+            Some(Entry::File { data, .. }) => {
+                let byte = data.get_mut(offset as usize).ok_or_else(|| {
+                    Error::InvalidInput(format!("Offset {} is past the end of {}", offset, path))
+                })?;
+                *byte ^= 0xff;
+                Ok(())
+            }
+            _ => Err(Error::NotFound(format!("File not found: {}", path))),
+        }
+    }
+
+    /// Lock the store for reading.
+    ///
+    /// Every update is a single map operation, so a panic while the lock
+    /// was held cannot leave the map half-changed. Poisoning is therefore
+    /// recovered from instead of failing every later call.
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Entry>> {
+        self.storage.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Lock the store for writing; see `read` for poisoning.
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, Entry>> {
+        self.storage.write().unwrap_or_else(|e| e.into_inner())
     }
 
     fn path_to_key(path: &VaultPath) -> String {
-        path.to_string_path()
+        path.to_string()
     }
 }
 
@@ -68,13 +112,19 @@ impl StorageProvider for MemoryProvider {
         "memory"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            native_rename: true,
+        }
+    }
+
     async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
         let key = Self::path_to_key(path);
 
         // Check parent exists
         if let Some(parent) = path.parent() {
             let parent_key = Self::path_to_key(&parent);
-            let storage = self.storage.read().unwrap();
+            let storage = self.read();
             match storage.get(&parent_key) {
                 Some(Entry::Directory { .. }) => {}
                 Some(Entry::File { .. }) => {
@@ -93,6 +143,7 @@ impl StorageProvider for MemoryProvider {
             is_directory: false,
             modified: Utc::now(),
             etag: Some(Uuid::new_v4().to_string()),
+            content_hash: Some(self.hash_algo.hash(&data)),
             provider_data: None,
         };
 
@@ -101,7 +152,7 @@ impl StorageProvider for MemoryProvider {
             metadata: metadata.clone(),
         };
 
-        self.storage.write().unwrap().insert(key, entry);
+        self.write().insert(key, entry);
 
         Ok(metadata)
     }
@@ -119,7 +170,7 @@ impl StorageProvider for MemoryProvider {
 
     async fn download(&self, path: &VaultPath) -> Result<Vec<u8>> {
         let key = Self::path_to_key(path);
-        let storage = self.storage.read().unwrap();
+        let storage = self.read();
 
         match storage.get(&key) {
             Some(Entry::File { data, .. }) => Ok(data.clone()),
@@ -136,14 +187,31 @@ impl StorageProvider for MemoryProvider {
         Ok(Box::pin(stream))
     }
 
+    async fn download_range(&self, path: &VaultPath, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let key = Self::path_to_key(path);
+        let storage = self.read();
+
+        match storage.get(&key) {
+            Some(Entry::File { data, .. }) => {
+                let start = (offset.min(data.len() as u64)) as usize;
+                let end = (offset.saturating_add(len).min(data.len() as u64)) as usize;
+                Ok(data[start..end].to_vec())
+            }
+            Some(Entry::Directory { .. }) => {
+                Err(Error::InvalidInput("Cannot download directory".to_string()))
+            }
+            None => Err(Error::NotFound(format!("File not found: {}", path))),
+        }
+    }
+
     async fn exists(&self, path: &VaultPath) -> Result<bool> {
         let key = Self::path_to_key(path);
-        Ok(self.storage.read().unwrap().contains_key(&key))
+        Ok(self.read().contains_key(&key))
     }
 
     async fn delete(&self, path: &VaultPath) -> Result<()> {
         let key = Self::path_to_key(path);
-        let mut storage = self.storage.write().unwrap();
+        let mut storage = self.write();
 
         match storage.get(&key) {
             Some(Entry::File { .. }) => {
@@ -159,7 +227,7 @@ impl StorageProvider for MemoryProvider {
 
     async fn list(&self, path: &VaultPath) -> Result<Vec<Metadata>> {
         let key = Self::path_to_key(path);
-        let storage = self.storage.read().unwrap();
+        let storage = self.read();
 
         // Verify path is a directory
         match storage.get(&key) {
@@ -203,7 +271,7 @@ impl StorageProvider for MemoryProvider {
 
     async fn metadata(&self, path: &VaultPath) -> Result<Metadata> {
         let key = Self::path_to_key(path);
-        let storage = self.storage.read().unwrap();
+        let storage = self.read();
 
         match storage.get(&key) {
             Some(Entry::File { metadata, .. }) => Ok(metadata.clone()),
@@ -218,7 +286,7 @@ impl StorageProvider for MemoryProvider {
         // Check parent exists
         if let Some(parent) = path.parent() {
             let parent_key = Self::path_to_key(&parent);
-            let storage = self.storage.read().unwrap();
+            let storage = self.read();
             match storage.get(&parent_key) {
                 Some(Entry::Directory { .. }) => {}
                 Some(Entry::File { .. }) => {
@@ -230,7 +298,7 @@ impl StorageProvider for MemoryProvider {
             }
         }
 
-        let mut storage = self.storage.write().unwrap();
+        let mut storage = self.write();
 
         // Check if already exists
         if storage.contains_key(&key) {
@@ -247,6 +315,7 @@ impl StorageProvider for MemoryProvider {
             is_directory: true,
             modified: Utc::now(),
             etag: Some(Uuid::new_v4().to_string()),
+            content_hash: None,
             provider_data: None,
         };
 
@@ -269,7 +338,7 @@ impl StorageProvider for MemoryProvider {
             return Err(Error::InvalidInput("Directory not empty".to_string()));
         }
 
-        let mut storage = self.storage.write().unwrap();
+        let mut storage = self.write();
         match storage.get(&key) {
             Some(Entry::Directory { .. }) => {
                 storage.remove(&key);
@@ -284,7 +353,7 @@ impl StorageProvider for MemoryProvider {
         let from_key = Self::path_to_key(from);
         let to_key = Self::path_to_key(to);
 
-        let mut storage = self.storage.write().unwrap();
+        let mut storage = self.write();
 
         if storage.contains_key(&to_key) {
             return Err(Error::AlreadyExists(format!(
@@ -326,7 +395,7 @@ impl StorageProvider for MemoryProvider {
         let from_key = Self::path_to_key(from);
         let to_key = Self::path_to_key(to);
 
-        let storage_read = self.storage.read().unwrap();
+        let storage_read = self.read();
 
         if storage_read.contains_key(&to_key) {
             return Err(Error::AlreadyExists(format!(
@@ -348,6 +417,7 @@ impl StorageProvider for MemoryProvider {
                     is_directory: false,
                     modified: Utc::now(),
                     etag: Some(Uuid::new_v4().to_string()),
+                    content_hash: Some(self.hash_algo.hash(data)),
                     provider_data: None,
                 };
                 Entry::File {
@@ -363,6 +433,7 @@ impl StorageProvider for MemoryProvider {
                     is_directory: true,
                     modified: Utc::now(),
                     etag: Some(Uuid::new_v4().to_string()),
+                    content_hash: None,
                     provider_data: None,
                 };
                 Entry::Directory { metadata }
@@ -375,16 +446,40 @@ impl StorageProvider for MemoryProvider {
         };
 
         drop(storage_read);
-        self.storage.write().unwrap().insert(to_key, new_entry);
+        self.write().insert(to_key, new_entry);
 
         Ok(result_metadata)
     }
+
+    async fn server_side_copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        self.copy(from, to).await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_poisoned_lock_does_not_fail_later_calls() {
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/kept.txt").unwrap();
+        provider.upload(&path, b"kept".to_vec()).await.unwrap();
+
+        let storage = provider.storage.clone();
+        let poisoner = std::thread::spawn(move || {
+            let _guard = storage.write().unwrap();
+            panic!("operation panicked while holding the lock");
+        });
+        assert!(poisoner.join().is_err());
+        assert!(provider.storage.is_poisoned());
+
+        assert_eq!(provider.download(&path).await.unwrap(), b"kept");
+        let other = VaultPath::parse("/other.txt").unwrap();
+        provider.upload(&other, b"new".to_vec()).await.unwrap();
+        assert_eq!(provider.list(&VaultPath::root()).await.unwrap().len(), 2);
+    }
+
     #[tokio::test]
     async fn test_upload_download() {
         let provider = MemoryProvider::new();
@@ -397,6 +492,51 @@ mod tests {
         assert_eq!(downloaded, data);
     }
 
+    #[tokio::test]
+    async fn test_corrupt_keeps_metadata() {
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/test.txt").unwrap();
+        provider.upload(&path, b"abc".to_vec()).await.unwrap();
+        let before = provider.metadata(&path).await.unwrap();
+
+        provider.corrupt(&path, 1).unwrap();
+
+        assert_eq!(provider.download(&path).await.unwrap(), b"a\x9dc");
+        let after = provider.metadata(&path).await.unwrap();
+        assert_eq!(after.content_hash, before.content_hash);
+        assert_eq!(after.size, before.size);
+        assert!(matches!(
+            provider.corrupt(&path, 3),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_empty_blob_roundtrip() {
+        use futures::{stream, StreamExt};
+
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/empty").unwrap();
+
+        let metadata = provider.upload(&path, Vec::new()).await.unwrap();
+        assert_eq!(metadata.size, Some(0));
+        assert!(provider.exists(&path).await.unwrap());
+        assert!(provider.download(&path).await.unwrap().is_empty());
+
+        let streamed = provider
+            .upload_stream(&path, Box::pin(stream::empty()))
+            .await
+            .unwrap();
+        assert_eq!(streamed.size, Some(0));
+        let chunks: Vec<_> = provider
+            .download_stream(&path)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert!(chunks.into_iter().all(|chunk| chunk.unwrap().is_empty()));
+    }
+
     #[tokio::test]
     async fn test_exists() {
         let provider = MemoryProvider::new();
@@ -12,4 +12,6 @@ pub mod provider;
 
 pub use auth::{OneDriveAuthConfig, OneDriveAuthManager, OneDriveTokenManager, OneDriveTokens};
 pub use client::{DriveItem, OneDriveClient};
-pub use provider::{create_onedrive_provider, OneDriveConfig, OneDriveProvider};
+pub use provider::{
+    create_onedrive_provider, onedrive_config_schema, OneDriveConfig, OneDriveProvider,
+};
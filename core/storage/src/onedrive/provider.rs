@@ -7,7 +7,7 @@ use std::sync::Arc;
 
 use axiomvault_common::{Error, Result, VaultPath};
 
-use crate::provider::{ByteStream, Metadata, StorageProvider};
+use crate::provider::{ByteStream, Metadata, ProviderCapabilities, StorageProvider};
 
 use super::auth::{OneDriveAuthConfig, OneDriveAuthManager, OneDriveTokenManager, OneDriveTokens};
 use super::client::{DriveItem, OneDriveClient};
@@ -74,6 +74,7 @@ impl OneDriveProvider {
                 .last_modified_date_time
                 .unwrap_or_else(chrono::Utc::now),
             etag: item.etag.clone(),
+            content_hash: None,
             provider_data: Some(serde_json::json!({
                 "onedrive_id": item.id,
                 "etag": item.etag,
@@ -88,6 +89,12 @@ impl StorageProvider for OneDriveProvider {
         "onedrive"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            native_rename: true,
+        }
+    }
+
     async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
         let od_path = self.to_onedrive_path(path);
 
@@ -192,6 +199,29 @@ impl StorageProvider for OneDriveProvider {
     }
 }
 
+/// JSON Schema describing [`OneDriveConfig`].
+pub fn onedrive_config_schema() -> serde_json::Value {
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "OneDrive",
+        "type": "object",
+        "required": ["root_path", "tokens"],
+        "properties": {
+            "root_path": { "type": "string", "minLength": 1, "description": "Folder path in OneDrive, e.g. \"/AxiomVault\"." },
+            "tokens": crate::cloud_auth::cloud_tokens_schema(),
+            "auth_config": {
+                "type": ["object", "null"],
+                "required": ["client_id", "client_secret", "redirect_url"],
+                "properties": {
+                    "client_id": { "type": "string" },
+                    "client_secret": { "type": "string" },
+                    "redirect_url": { "type": "string" }
+                }
+            }
+        }
+    })
+}
+
 /// Create a OneDrive provider from configuration.
 pub fn create_onedrive_provider(config: serde_json::Value) -> Result<Arc<dyn StorageProvider>> {
     let onedrive_config: OneDriveConfig = serde_json::from_value(config)
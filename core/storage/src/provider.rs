@@ -2,11 +2,13 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use futures::Stream;
+use futures::{stream, Stream, TryStreamExt};
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
 
-use axiomvault_common::{Result, VaultPath};
+use axiomvault_common::{Error, Result, VaultPath};
 
 /// Metadata for a stored object.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,10 +25,110 @@ pub struct Metadata {
     pub modified: DateTime<Utc>,
     /// ETag or revision ID for conflict detection.
     pub etag: Option<String>,
+    /// Hash of the content, tagged with its algorithm (see [`HashAlgo`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
     /// Provider-specific metadata.
     pub provider_data: Option<serde_json::Value>,
 }
 
+/// Algorithm used for [`Metadata::content_hash`].
+///
+/// Hashes are stored as `"<id>:<hex digest>"`, so two hashes computed with
+/// different algorithms never compare equal by accident.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    /// MD5, as reported natively by Google Drive. Not collision resistant.
+    Md5,
+    /// SHA-256, for deployments that require FIPS-approved algorithms.
+    #[default]
+    Sha256,
+    /// BLAKE3, the fastest of the three.
+    Blake3,
+}
+
+impl HashAlgo {
+    /// Identifier used as the prefix of tagged hashes.
+    pub fn id(self) -> &'static str {
+        match self {
+            HashAlgo::Md5 => "md5",
+            HashAlgo::Sha256 => "sha256",
+            HashAlgo::Blake3 => "blake3",
+        }
+    }
+
+    /// Algorithm named by `id`, if it is known.
+    pub fn from_id(id: &str) -> Option<Self> {
+        [HashAlgo::Md5, HashAlgo::Sha256, HashAlgo::Blake3]
+            .into_iter()
+            .find(|algo| algo.id() == id)
+    }
+
+    /// Hash `data` and return the tagged hash.
+    pub fn hash(self, data: &[u8]) -> String {
+        let digest: Vec<u8> = match self {
+            HashAlgo::Md5 => {
+                use md5::Digest;
+                md5::Md5::digest(data).to_vec()
+            }
+            HashAlgo::Sha256 => {
+                use sha2::Digest;
+                sha2::Sha256::digest(data).to_vec()
+            }
+            HashAlgo::Blake3 => blake3::hash(data).as_bytes().to_vec(),
+        };
+        self.tag(&hex(&digest))
+    }
+
+    /// Tag a hex digest computed elsewhere, e.g. by the provider itself.
+    pub fn tag(self, hex_digest: &str) -> String {
+        format!("{}:{}", self.id(), hex_digest.to_ascii_lowercase())
+    }
+}
+
+/// Split a tagged hash into its algorithm and digest.
+pub fn parse_content_hash(tagged: &str) -> Option<(HashAlgo, &str)> {
+    let (id, digest) = tagged.split_once(':')?;
+    Some((HashAlgo::from_id(id)?, digest))
+}
+
+/// Outcome of comparing two content hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashComparison {
+    /// Same algorithm, same digest.
+    Equal,
+    /// Same algorithm, different digest.
+    Different,
+    /// A hash is missing or malformed, or the algorithms differ; the hashes
+    /// say nothing about whether the contents match.
+    Unknown,
+}
+
+/// Compare two tagged content hashes.
+///
+/// Only hashes of the same algorithm are compared; anything else is
+/// [`HashComparison::Unknown`], never equal.
+pub fn compare_content_hashes(a: Option<&str>, b: Option<&str>) -> HashComparison {
+    match (
+        a.and_then(parse_content_hash),
+        b.and_then(parse_content_hash),
+    ) {
+        (Some((algo_a, a)), Some((algo_b, b))) if algo_a == algo_b => {
+            if a == b {
+                HashComparison::Equal
+            } else {
+                HashComparison::Different
+            }
+        }
+        _ => HashComparison::Unknown,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 /// Conflict resolution strategy.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConflictResolution {
@@ -41,6 +143,46 @@ pub enum ConflictResolution {
 /// Byte stream type for upload/download operations.
 pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>>> + Send>>;
 
+/// Directory entry stream returned by [`list_all`].
+pub type MetadataStream<'a> = Pin<Box<dyn Stream<Item = Result<Metadata>> + Send + 'a>>;
+
+/// Number of entries [`list_all`] requests per page.
+pub const LIST_PAGE_SIZE: usize = 1000;
+
+/// One page of a directory listing and the cursor of the next page, or
+/// `None` after the last page.
+pub type ListPage = (Vec<Metadata>, Option<String>);
+
+/// Optional features a storage backend supports natively.
+///
+/// Callers consult these flags to decide whether an operation is atomic on
+/// the backend or emulated by the trait's default implementation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderCapabilities {
+    /// Backend renames/moves objects in a single server-side operation.
+    ///
+    /// When `false`, [`StorageProvider::rename`] falls back to
+    /// [`rename_via_copy`], which is not atomic: a crash between the copy
+    /// and the delete leaves the object present at both paths.
+    pub native_rename: bool,
+}
+
+/// Account storage usage as reported by a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageQuota {
+    /// Bytes the account currently uses.
+    pub used: u64,
+    /// Total bytes the account may use, or `None` if unlimited.
+    pub limit: Option<u64>,
+}
+
+impl StorageQuota {
+    /// Bytes still available, or `None` if the account is unlimited.
+    pub fn remaining(&self) -> Option<u64> {
+        self.limit.map(|limit| limit.saturating_sub(self.used))
+    }
+}
+
 /// Storage provider trait for different backends.
 ///
 /// All operations are async and use streams for large data transfers.
@@ -50,6 +192,22 @@ pub trait StorageProvider: Send + Sync {
     /// Get the provider name (e.g., "gdrive", "local", "icloud").
     fn name(&self) -> &str;
 
+    /// Report which operations this backend supports natively.
+    ///
+    /// Providers that override [`StorageProvider::rename`] with a
+    /// server-side move must also report `native_rename: true`.
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::default()
+    }
+
+    /// Resumable upload support, if the backend has it.
+    ///
+    /// Callers that upload large objects probe this and fall back to
+    /// [`StorageProvider::upload`], which always starts from byte zero.
+    fn as_resumable(&self) -> Option<&dyn ResumableUpload> {
+        None
+    }
+
     /// Upload data to the storage.
     ///
     /// # Preconditions
@@ -89,6 +247,39 @@ pub trait StorageProvider: Send + Sync {
     /// For large files, this allows streaming without loading entire file into memory.
     async fn download_stream(&self, path: &VaultPath) -> Result<ByteStream>;
 
+    /// Download at most `len` bytes starting at `offset`.
+    ///
+    /// Returns fewer bytes when the file ends first, and none when `offset`
+    /// is past its end. The default implementation reads
+    /// [`StorageProvider::download_stream`] up to the end of the range, so
+    /// it still transfers everything before `offset`; backends with native
+    /// ranged reads override it.
+    ///
+    /// # Errors
+    /// - Same as [`StorageProvider::download_stream`]
+    async fn download_range(&self, path: &VaultPath, offset: u64, len: u64) -> Result<Vec<u8>> {
+        use futures::StreamExt;
+
+        let mut stream = self.download_stream(path).await?;
+        let end = offset.saturating_add(len);
+        let mut position = 0u64;
+        let mut range = Vec::new();
+        while position < end {
+            let Some(chunk) = stream.next().await else {
+                break;
+            };
+            let chunk = chunk?;
+            let chunk_end = position + chunk.len() as u64;
+            if chunk_end > offset {
+                let from = offset.saturating_sub(position) as usize;
+                let to = (end.min(chunk_end) - position) as usize;
+                range.extend_from_slice(&chunk[from..to]);
+            }
+            position = chunk_end;
+        }
+        Ok(range)
+    }
+
     /// Check if a path exists.
     async fn exists(&self, path: &VaultPath) -> Result<bool>;
 
@@ -108,6 +299,32 @@ pub trait StorageProvider: Send + Sync {
     /// Vector of metadata for each item in the directory.
     async fn list(&self, path: &VaultPath) -> Result<Vec<Metadata>>;
 
+    /// List at most `limit` entries of a directory, starting after `cursor`.
+    ///
+    /// Pass `None` for the first page and afterwards the cursor returned
+    /// with the previous page. Cursors are opaque and only valid for the
+    /// same `path`. Entries added or removed between pages may be missed
+    /// or, on some backends, repeated, but pagination always terminates.
+    ///
+    /// The default implementation pages through the result of
+    /// [`StorageProvider::list`] by name, so it saves no memory; backends
+    /// with a native paged listing override it. Use [`list_all`] to visit
+    /// a whole directory.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `limit` is zero
+    /// - Same as [`StorageProvider::list`]
+    async fn list_page(
+        &self,
+        path: &VaultPath,
+        cursor: Option<String>,
+        limit: usize,
+    ) -> Result<ListPage> {
+        check_page_limit(limit)?;
+        let entries = self.list(path).await?;
+        Ok(page_by_name(entries, cursor.as_deref(), limit))
+    }
+
     /// Get metadata for a path.
     ///
     /// # Errors
@@ -131,10 +348,279 @@ pub trait StorageProvider: Send + Sync {
     async fn delete_dir(&self, path: &VaultPath) -> Result<()>;
 
     /// Move/rename a path.
-    async fn rename(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata>;
+    ///
+    /// The default implementation emulates the move with [`rename_via_copy`]
+    /// for backends that lack a native rename (see
+    /// [`ProviderCapabilities::native_rename`]).
+    ///
+    /// # Errors
+    /// - Source not found
+    /// - Destination already exists
+    async fn rename(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        rename_via_copy(self, from, to).await
+    }
 
     /// Copy a path.
     async fn copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata>;
+
+    /// Copy a file without its content passing through the client.
+    ///
+    /// Backends with a server-side copy (e.g. Drive `files.copy`) override
+    /// this. Callers probe it through [`copy_object`], which falls back to
+    /// streaming the content when it is unsupported.
+    ///
+    /// # Errors
+    /// - `Unsupported` if the backend has no server-side copy
+    /// - Source not found
+    /// - Destination already exists
+    async fn server_side_copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        let _ = (from, to);
+        Err(Error::Unsupported(format!(
+            "{} has no server-side copy",
+            self.name()
+        )))
+    }
+
+    /// Current account quota.
+    ///
+    /// Returns `Ok(None)` for backends that cannot report one; callers
+    /// treat that the same as an unlimited account.
+    async fn quota(&self) -> Result<Option<StorageQuota>> {
+        Ok(None)
+    }
+}
+
+/// Reject a page size of zero, which could never make progress.
+pub(crate) fn check_page_limit(limit: usize) -> Result<()> {
+    if limit == 0 {
+        return Err(Error::InvalidInput(
+            "List page limit must be at least 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Take the `limit` entries whose names sort after `cursor`.
+///
+/// The next cursor is the last name returned, so entries added or removed
+/// between pages never shift the remaining ones.
+fn page_by_name(mut entries: Vec<Metadata>, cursor: Option<&str>, limit: usize) -> ListPage {
+    if let Some(cursor) = cursor {
+        entries.retain(|e| e.name.as_str() > cursor);
+    }
+    entries.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    if entries.len() <= limit {
+        return (entries, None);
+    }
+    entries.truncate(limit);
+    let next = entries.last().map(|e| e.name.clone());
+    (entries, next)
+}
+
+/// Stream every entry of a directory, one page at a time.
+///
+/// Drives [`StorageProvider::list_page`] with [`LIST_PAGE_SIZE`], so at most
+/// one page is held in memory and the first entries arrive before the
+/// directory has been listed in full.
+pub fn list_all<'a, P: StorageProvider + ?Sized>(
+    provider: &'a P,
+    path: &'a VaultPath,
+) -> MetadataStream<'a> {
+    // `None` once the last page has been fetched.
+    let first: Option<Option<String>> = Some(None);
+    let pages = stream::try_unfold(first, move |cursor| async move {
+        let Some(cursor) = cursor else {
+            return Ok::<_, Error>(None);
+        };
+        let (entries, next) = provider.list_page(path, cursor, LIST_PAGE_SIZE).await?;
+        Ok(Some((
+            stream::iter(entries.into_iter().map(Ok)),
+            next.map(Some),
+        )))
+    });
+    Box::pin(pages.try_flatten())
+}
+
+/// How a file's content got to its copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CopyMechanism {
+    /// The backend copied it; no content crossed the client.
+    ServerSide,
+    /// The content was streamed through the client chunk by chunk.
+    Streamed,
+}
+
+/// Copy the file at `from` to `to` on the same provider.
+///
+/// Tries [`StorageProvider::server_side_copy`] first and streams the
+/// content through the client only if the backend does not support it.
+pub async fn copy_object<P: StorageProvider + ?Sized>(
+    provider: &P,
+    from: &VaultPath,
+    to: &VaultPath,
+) -> Result<(Metadata, CopyMechanism)> {
+    match provider.server_side_copy(from, to).await {
+        Ok(metadata) => Ok((metadata, CopyMechanism::ServerSide)),
+        Err(Error::Unsupported(_)) => {
+            let metadata = stream_copy(provider, from, provider, to).await?;
+            Ok((metadata, CopyMechanism::Streamed))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Stream the file at `from` on `source` to `to` on `dest`.
+///
+/// The content is passed on chunk by chunk as the source yields it and is
+/// never held in full by this function.
+pub async fn stream_copy<S, D>(
+    source: &S,
+    from: &VaultPath,
+    dest: &D,
+    to: &VaultPath,
+) -> Result<Metadata>
+where
+    S: StorageProvider + ?Sized,
+    D: StorageProvider + ?Sized,
+{
+    if dest.exists(to).await? {
+        return Err(Error::AlreadyExists(format!(
+            "Destination already exists: {}",
+            to
+        )));
+    }
+    let stream = source.download_stream(from).await?;
+    dest.upload_stream(to, stream).await
+}
+
+/// Read a [`ByteStream`] through [`AsyncRead`].
+///
+/// Chunks are pulled from the stream only as the reader is read, so a slow
+/// reader slows the download rather than buffering it. A stream error
+/// surfaces as an I/O error wrapping it.
+pub fn stream_reader(stream: ByteStream) -> impl AsyncRead + Send + Unpin {
+    StreamReader::new(
+        stream
+            .map_ok(bytes::Bytes::from)
+            .map_err(std::io::Error::other),
+    )
+}
+
+/// Turn an [`AsyncRead`] into a [`ByteStream`] of chunks of up to
+/// `chunk_size` bytes.
+///
+/// The reader is read only as the stream is polled. To upload what an
+/// `AsyncWrite` produces, write into one half of [`tokio::io::duplex`] and
+/// pass the other half here; the pipe's capacity bounds what is buffered.
+pub fn reader_stream<R>(reader: R, chunk_size: usize) -> ByteStream
+where
+    R: AsyncRead + Send + 'static,
+{
+    Box::pin(
+        ReaderStream::with_capacity(reader, chunk_size.max(1))
+            .map_ok(|chunk| chunk.to_vec())
+            .map_err(Error::from),
+    )
+}
+
+/// Move `from` to `to` using only `copy` and `delete`.
+///
+/// Files are copied and then the source is deleted. Directories are
+/// recreated at the destination, their children moved recursively, and the
+/// emptied source directory removed. The source is only deleted after the
+/// copy succeeded, so a failure never loses data, but it may leave the
+/// object present at both paths.
+///
+/// # Errors
+/// - Source not found
+/// - Destination already exists
+pub async fn rename_via_copy<P: StorageProvider + ?Sized>(
+    provider: &P,
+    from: &VaultPath,
+    to: &VaultPath,
+) -> Result<Metadata> {
+    let source = provider.metadata(from).await?;
+    if provider.exists(to).await? {
+        return Err(Error::AlreadyExists(format!(
+            "Destination already exists: {}",
+            to
+        )));
+    }
+
+    if !source.is_directory {
+        let metadata = provider.copy(from, to).await?;
+        provider.delete(from).await?;
+        return Ok(metadata);
+    }
+
+    let metadata = provider.create_dir(to).await?;
+    for child in provider.list(from).await? {
+        let child_from = from.join(&child.name)?;
+        let child_to = to.join(&child.name)?;
+        Box::pin(rename_via_copy(provider, &child_from, &child_to)).await?;
+    }
+    provider.delete_dir(from).await?;
+    Ok(metadata)
+}
+
+/// Server-side state of a resumable upload.
+///
+/// Serializable so callers can journal it and continue the upload from a
+/// different process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadSession {
+    /// Backend-specific session locator (e.g. the Drive upload URI).
+    pub uri: String,
+    /// Destination of the upload.
+    pub path: VaultPath,
+    /// Total number of bytes that will be uploaded.
+    pub total_size: u64,
+}
+
+/// Progress of a resumable upload.
+#[derive(Debug, Clone)]
+pub enum UploadProgress {
+    /// The backend holds the first `acknowledged` bytes.
+    InProgress {
+        /// Number of bytes, counted from zero, the backend has stored.
+        acknowledged: u64,
+    },
+    /// All bytes arrived and the object exists.
+    Complete(Metadata),
+}
+
+/// Uploads that can be continued after an interruption, even from another
+/// process, for as long as the backend keeps the session.
+#[async_trait]
+pub trait ResumableUpload: Send + Sync {
+    /// Preferred number of bytes per [`continue_upload`](Self::continue_upload)
+    /// call. Every chunk except the last must have exactly this size.
+    fn chunk_size(&self) -> usize;
+
+    /// Open a session for uploading `total_size` bytes to `path`.
+    ///
+    /// An existing object at `path` is replaced when the upload completes.
+    async fn start_upload(&self, path: &VaultPath, total_size: u64) -> Result<UploadSession>;
+
+    /// Ask the backend how far the session got.
+    ///
+    /// # Errors
+    /// - `NotFound` if the session expired; start a new one
+    async fn query_upload(&self, session: &UploadSession) -> Result<UploadProgress>;
+
+    /// Send `data`, which starts at byte `offset` of the object.
+    ///
+    /// The returned offset may be lower than `offset + data.len()` if the
+    /// backend stored only part of the chunk.
+    ///
+    /// # Errors
+    /// - `NotFound` if the session expired; start a new one
+    async fn continue_upload(
+        &self,
+        session: &UploadSession,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<UploadProgress>;
 }
 
 /// Extension trait for conflict detection.
@@ -157,6 +643,239 @@ pub trait ConflictAware: StorageProvider {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::memory::MemoryProvider;
+    use crate::test_util::{CountingProvider, NoRenameProvider};
+
+    #[test]
+    fn test_default_capabilities_report_no_native_rename() {
+        let provider = NoRenameProvider::new();
+        assert!(!provider.capabilities().native_rename);
+        assert!(MemoryProvider::new().capabilities().native_rename);
+    }
+
+    #[tokio::test]
+    async fn test_default_list_page_survives_concurrent_changes() {
+        let provider = NoRenameProvider::new();
+        let dir = VaultPath::parse("/dir").unwrap();
+        provider.create_dir(&dir).await.unwrap();
+        for i in 0..10 {
+            provider
+                .upload(&dir.join(&format!("f{:02}", i)).unwrap(), vec![i])
+                .await
+                .unwrap();
+        }
+
+        let (first, cursor) = provider.list_page(&dir, None, 4).await.unwrap();
+        let names: Vec<_> = first.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["f00", "f01", "f02", "f03"]);
+
+        // Remove an entry already returned and one still ahead, and add one
+        // on each side of the cursor.
+        provider.delete(&dir.join("f01").unwrap()).await.unwrap();
+        provider.delete(&dir.join("f05").unwrap()).await.unwrap();
+        provider
+            .upload(&dir.join("f00a").unwrap(), vec![])
+            .await
+            .unwrap();
+        provider
+            .upload(&dir.join("f99").unwrap(), vec![])
+            .await
+            .unwrap();
+
+        let mut rest = Vec::new();
+        let mut cursor = cursor;
+        while let Some(c) = cursor {
+            let (page, next) = provider.list_page(&dir, Some(c), 4).await.unwrap();
+            rest.extend(page.into_iter().map(|e| e.name));
+            cursor = next;
+        }
+        assert_eq!(rest, ["f04", "f06", "f07", "f08", "f09", "f99"]);
+
+        assert!(matches!(
+            provider.list_page(&dir, None, 0).await,
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_all_streams_every_page() {
+        let provider = NoRenameProvider::new();
+        let dir = VaultPath::parse("/dir").unwrap();
+        provider.create_dir(&dir).await.unwrap();
+        let count = LIST_PAGE_SIZE + 5;
+        for i in 0..count {
+            provider
+                .upload(&dir.join(&format!("f{:05}", i)).unwrap(), vec![])
+                .await
+                .unwrap();
+        }
+
+        let names: Vec<String> = list_all(&provider, &dir)
+            .map_ok(|e| e.name)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(names.len(), count);
+        assert!(names.windows(2).all(|w| w[0] < w[1]));
+
+        let missing = VaultPath::parse("/missing").unwrap();
+        let result: Result<Vec<_>> = list_all(&provider, &missing).try_collect().await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rename_fallback_moves_file() {
+        let provider = NoRenameProvider::new();
+        let from = VaultPath::parse("/old.txt").unwrap();
+        let to = VaultPath::parse("/new.txt").unwrap();
+
+        provider.upload(&from, b"payload".to_vec()).await.unwrap();
+        let metadata = provider.rename(&from, &to).await.unwrap();
+
+        assert_eq!(metadata.name, "new.txt");
+        assert!(!provider.exists(&from).await.unwrap());
+        assert_eq!(provider.download(&to).await.unwrap(), b"payload");
+    }
+
+    #[tokio::test]
+    async fn test_default_download_range_matches_native() {
+        let provider = NoRenameProvider::new();
+        let path = VaultPath::parse("/data.bin").unwrap();
+        provider
+            .upload(&path, b"0123456789".to_vec())
+            .await
+            .unwrap();
+
+        for (offset, len) in [(0, 4), (3, 5), (8, 10), (10, 1), (20, 1), (0, 0)] {
+            assert_eq!(
+                provider.download_range(&path, offset, len).await.unwrap(),
+                provider
+                    .inner()
+                    .download_range(&path, offset, len)
+                    .await
+                    .unwrap(),
+                "range {}+{}",
+                offset,
+                len
+            );
+        }
+        assert_eq!(
+            provider.download_range(&path, 3, 5).await.unwrap(),
+            b"34567"
+        );
+        assert!(matches!(
+            provider
+                .download_range(&VaultPath::parse("/missing").unwrap(), 0, 1)
+                .await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rename_fallback_moves_directory_recursively() {
+        let provider = NoRenameProvider::new();
+        let from = VaultPath::parse("/a").unwrap();
+        let to = VaultPath::parse("/b").unwrap();
+
+        provider.create_dir(&from).await.unwrap();
+        provider
+            .create_dir(&VaultPath::parse("/a/sub").unwrap())
+            .await
+            .unwrap();
+        provider
+            .upload(&VaultPath::parse("/a/sub/file").unwrap(), vec![7])
+            .await
+            .unwrap();
+
+        provider.rename(&from, &to).await.unwrap();
+
+        assert!(!provider.exists(&from).await.unwrap());
+        assert_eq!(
+            provider
+                .download(&VaultPath::parse("/b/sub/file").unwrap())
+                .await
+                .unwrap(),
+            vec![7]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_fallback_refuses_existing_destination() {
+        let provider = NoRenameProvider::new();
+        let from = VaultPath::parse("/src").unwrap();
+        let to = VaultPath::parse("/dst").unwrap();
+
+        provider.upload(&from, vec![1]).await.unwrap();
+        provider.upload(&to, vec![2]).await.unwrap();
+
+        let result = provider.rename(&from, &to).await;
+        assert!(matches!(result, Err(Error::AlreadyExists(_))));
+        assert_eq!(provider.download(&from).await.unwrap(), vec![1]);
+        assert_eq!(provider.download(&to).await.unwrap(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_copy_object_uses_server_side_copy() {
+        let provider = NoRenameProvider::new()
+            .with_server_side_copy()
+            .with_download_chunks(4);
+        let from = VaultPath::parse("/a.bin").unwrap();
+        let to = VaultPath::parse("/b.bin").unwrap();
+        provider
+            .upload(&from, b"ciphertext bytes".to_vec())
+            .await
+            .unwrap();
+
+        let (_, mechanism) = copy_object(&provider, &from, &to).await.unwrap();
+
+        assert_eq!(mechanism, CopyMechanism::ServerSide);
+        assert_eq!(provider.downloaded(), 0);
+        assert_eq!(provider.upload_chunks(), 0);
+        assert_eq!(provider.download(&to).await.unwrap(), b"ciphertext bytes");
+    }
+
+    #[tokio::test]
+    async fn test_copy_object_streams_without_server_side_copy() {
+        let provider = NoRenameProvider::new().with_download_chunks(4);
+        let from = VaultPath::parse("/a.bin").unwrap();
+        let to = VaultPath::parse("/b.bin").unwrap();
+        let content = b"ciphertext bytes".to_vec();
+        provider.upload(&from, content.clone()).await.unwrap();
+
+        let (_, mechanism) = copy_object(&provider, &from, &to).await.unwrap();
+
+        assert_eq!(mechanism, CopyMechanism::Streamed);
+        assert_eq!(provider.downloaded(), content.len());
+        // Chunks reach the destination as the source yields them.
+        assert_eq!(provider.upload_chunks(), content.len() / 4);
+        assert_eq!(provider.inner().download(&to).await.unwrap(), content);
+
+        let again = copy_object(&provider, &from, &to).await;
+        assert!(matches!(again, Err(Error::AlreadyExists(_))));
+    }
+
+    #[tokio::test]
+    async fn test_server_side_copy_is_unsupported_by_default() {
+        let provider = CountingProvider::new();
+        let from = VaultPath::parse("/a").unwrap();
+        provider.upload(&from, vec![1]).await.unwrap();
+        let result = provider
+            .server_side_copy(&from, &VaultPath::parse("/b").unwrap())
+            .await;
+        assert!(matches!(result, Err(Error::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn test_quota_is_unknown_by_default() {
+        let provider = CountingProvider::new();
+        assert_eq!(provider.quota().await.unwrap(), None);
+
+        let over = StorageQuota {
+            used: 12,
+            limit: Some(10),
+        };
+        assert_eq!(over.remaining(), Some(0));
+    }
 
     #[test]
     fn test_metadata_serialization() {
@@ -167,6 +886,7 @@ mod tests {
             is_directory: false,
             modified: Utc::now(),
             etag: Some("abc123".to_string()),
+            content_hash: None,
             provider_data: None,
         };
 
@@ -177,4 +897,102 @@ mod tests {
         assert_eq!(deserialized.name, metadata.name);
         assert_eq!(deserialized.size, metadata.size);
     }
+
+    #[test]
+    fn test_same_bytes_under_two_algorithms_are_tagged_apart() {
+        let data = b"same bytes";
+        let sha = HashAlgo::Sha256.hash(data);
+        let blake = HashAlgo::Blake3.hash(data);
+
+        assert!(sha.starts_with("sha256:"));
+        assert!(blake.starts_with("blake3:"));
+        assert_ne!(sha, blake);
+        assert_eq!(
+            HashAlgo::Md5.hash(b""),
+            "md5:d41d8cd98f00b204e9800998ecf8427e"
+        );
+        assert_eq!(HashAlgo::Sha256.hash(data), sha);
+        assert_eq!(parse_content_hash(&blake).unwrap().0, HashAlgo::Blake3);
+    }
+
+    #[test]
+    fn test_cross_algorithm_comparison_is_unknown() {
+        let data = b"same bytes";
+        let sha = HashAlgo::Sha256.hash(data);
+        let blake = HashAlgo::Blake3.hash(data);
+        let other = HashAlgo::Sha256.hash(b"other bytes");
+
+        assert_eq!(
+            compare_content_hashes(Some(&sha), Some(&sha)),
+            HashComparison::Equal
+        );
+        assert_eq!(
+            compare_content_hashes(Some(&sha), Some(&other)),
+            HashComparison::Different
+        );
+        assert_eq!(
+            compare_content_hashes(Some(&sha), Some(&blake)),
+            HashComparison::Unknown
+        );
+        assert_eq!(
+            compare_content_hashes(Some(&sha), None),
+            HashComparison::Unknown
+        );
+        assert_eq!(
+            compare_content_hashes(Some("crc32:00"), Some("crc32:00")),
+            HashComparison::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_pipeline_roundtrip() {
+        use axiomvault_crypto::{AsyncDecryptingStream, AsyncEncryptingStream};
+        use tokio::io::AsyncWriteExt;
+
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/pipeline.bin").unwrap();
+        let key = [9u8; 32];
+        let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+
+        // AsyncRead -> AsyncEncryptingStream -> duplex -> upload_stream.
+        let (tx, rx) = tokio::io::duplex(16 * 1024);
+        let encrypt = async {
+            let mut tx = tx;
+            let n = AsyncEncryptingStream::new(&key)?
+                .encrypt_sized(&data[..], data.len() as u64, &mut tx)
+                .await?;
+            tx.shutdown().await?;
+            Ok::<_, Error>(n)
+        };
+        let upload = provider.upload_stream(&path, reader_stream(rx, 8 * 1024));
+        let (written, metadata) = tokio::try_join!(encrypt, upload).unwrap();
+        assert_eq!(written, data.len() as u64);
+        assert!(metadata.size.unwrap() > data.len() as u64);
+
+        // download_stream -> AsyncRead -> AsyncDecryptingStream.
+        let stream = provider.download_stream(&path).await.unwrap();
+        let mut plaintext = Vec::new();
+        let read = AsyncDecryptingStream::new(&key)
+            .unwrap()
+            .decrypt_stream(stream_reader(stream), &mut plaintext)
+            .await
+            .unwrap();
+        assert_eq!(read, data.len() as u64);
+        assert_eq!(plaintext, data);
+    }
+
+    #[tokio::test]
+    async fn test_stream_reader_surfaces_stream_errors() {
+        use tokio::io::AsyncReadExt;
+
+        let stream: ByteStream = Box::pin(stream::iter(vec![
+            Ok(b"abc".to_vec()),
+            Err(Error::Network("connection reset".to_string())),
+        ]));
+        let mut reader = stream_reader(stream);
+        let mut out = Vec::new();
+        let err = reader.read_to_end(&mut out).await.unwrap_err();
+        assert!(err.to_string().contains("connection reset"));
+        assert_eq!(out, b"abc");
+    }
 }
@@ -682,7 +682,7 @@ impl<'a> RaidRebuilder<'a> {
                 .await
                 .unwrap_or(false)
             {
-                confirmed.push((path, shard_path.to_string_path()));
+                confirmed.push((path, shard_path.to_string()));
             }
         }
 
@@ -1,28 +1,189 @@
 //! Provider registry for dynamic provider resolution.
+//!
+//! Each provider is registered under a name together with a
+//! [`ProviderFactory`], which builds instances from a JSON configuration and
+//! describes that configuration with a JSON Schema. Frontends use the schema
+//! to render forms and [`ProviderRegistry::validate`] to report problems
+//! before any storage is touched.
 
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
-use crate::provider::StorageProvider;
+use crate::instrumented::InstrumentedProvider;
+use crate::provider::{HashAlgo, StorageProvider};
 use axiomvault_common::{Error, Result};
 
-/// Factory function type for creating providers.
-pub type ProviderFactory = Box<dyn Fn(Value) -> Result<Arc<dyn StorageProvider>> + Send + Sync>;
+/// Creates storage providers from JSON configuration.
+///
+/// Implement this trait to describe and check the accepted configuration;
+/// use [`from_fn`] to register a plain closure with no validation.
+pub trait ProviderFactory: Send + Sync {
+    /// Build a provider from `config`.
+    fn create(&self, config: Value) -> Result<Arc<dyn StorageProvider>>;
+
+    /// Check `config` without creating a provider.
+    ///
+    /// # Errors
+    /// - `InvalidInput` describing the first problem found
+    fn validate(&self, _config: &Value) -> Result<()> {
+        Ok(())
+    }
+
+    /// JSON Schema describing the accepted configuration.
+    fn config_schema(&self) -> Value {
+        json!({ "type": "object" })
+    }
+}
+
+/// Factory backed by a closure; see [`from_fn`].
+pub struct FnFactory<F>(F);
+
+impl<F> ProviderFactory for FnFactory<F>
+where
+    F: Fn(Value) -> Result<Arc<dyn StorageProvider>> + Send + Sync,
+{
+    fn create(&self, config: Value) -> Result<Arc<dyn StorageProvider>> {
+        (self.0)(config)
+    }
+}
+
+/// Wrap a closure as a [`ProviderFactory`] that accepts any configuration.
+pub fn from_fn<F>(create: F) -> FnFactory<F>
+where
+    F: Fn(Value) -> Result<Arc<dyn StorageProvider>> + Send + Sync,
+{
+    FnFactory(create)
+}
+
+/// Factory for a built-in provider, validated against its schema.
+struct SchemaFactory {
+    name: &'static str,
+    schema: fn() -> Value,
+    create: fn(Value) -> Result<Arc<dyn StorageProvider>>,
+}
+
+impl ProviderFactory for SchemaFactory {
+    fn create(&self, config: Value) -> Result<Arc<dyn StorageProvider>> {
+        (self.create)(config)
+    }
+
+    fn validate(&self, config: &Value) -> Result<()> {
+        validate_against_schema(self.name, &(self.schema)(), config)
+    }
+
+    fn config_schema(&self) -> Value {
+        (self.schema)()
+    }
+}
+
+/// Validate `config` against a JSON Schema.
+///
+/// Supports the subset used by provider schemas: `type` (single or a list),
+/// `required`, nested `properties`, `additionalProperties: false`, `enum`
+/// and `minLength`. Error messages name the provider and the offending field,
+/// e.g. `Invalid gdrive config: missing field 'folder_id'`.
+///
+/// # Errors
+/// - `InvalidInput` describing the first violation found
+pub fn validate_against_schema(provider: &str, schema: &Value, config: &Value) -> Result<()> {
+    check_value(schema, config, "")
+        .map_err(|problem| Error::InvalidInput(format!("Invalid {} config: {}", provider, problem)))
+}
+
+fn check_value(schema: &Value, value: &Value, path: &str) -> std::result::Result<(), String> {
+    let subject = if path.is_empty() {
+        "configuration".to_string()
+    } else {
+        format!("field '{}'", path)
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| matches_type(t, value)) {
+            return Err(format!("{} must be {}", subject, allowed.join(" or ")));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(format!("{} has an unsupported value", subject));
+        }
+    }
+
+    if let (Some(min), Some(s)) = (
+        schema.get("minLength").and_then(Value::as_u64),
+        value.as_str(),
+    ) {
+        if (s.chars().count() as u64) < min {
+            return Err(format!("{} must not be empty", subject));
+        }
+    }
+
+    let Some(object) = value.as_object() else {
+        return Ok(());
+    };
+    let child_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(key) {
+                return Err(format!("missing field '{}'", child_path(key)));
+            }
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let closed = schema.get("additionalProperties") == Some(&Value::Bool(false));
+    for (key, child) in object {
+        match properties.and_then(|p| p.get(key)) {
+            Some(child_schema) => check_value(child_schema, child, &child_path(key))?,
+            None if closed => return Err(format!("unknown field '{}'", child_path(key))),
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
 
 /// Registry for storage provider factories.
 ///
 /// Allows dynamic registration and resolution of storage providers
-/// by name and configuration.
+/// by name and configuration. Clones share the same set of factories, so a
+/// provider registered through one handle is visible through all of them.
+#[derive(Clone)]
 pub struct ProviderRegistry {
-    factories: HashMap<String, ProviderFactory>,
+    factories: Arc<RwLock<HashMap<String, Arc<dyn ProviderFactory>>>>,
 }
 
 impl ProviderRegistry {
     /// Create a new empty registry.
     pub fn new() -> Self {
         Self {
-            factories: HashMap::new(),
+            factories: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -36,18 +197,37 @@ impl ProviderRegistry {
     ///
     /// # Errors
     /// - Returns error if name is already registered
-    pub fn register(&mut self, name: impl Into<String>, factory: ProviderFactory) -> Result<()> {
+    pub fn register(
+        &self,
+        name: impl Into<String>,
+        factory: impl ProviderFactory + 'static,
+    ) -> Result<()> {
         let name = name.into();
-        if self.factories.contains_key(&name) {
+        let mut factories = self
+            .factories
+            .write()
+            .map_err(|_| Error::Storage("Provider registry lock poisoned".to_string()))?;
+        if factories.contains_key(&name) {
             return Err(Error::AlreadyExists(format!(
                 "Provider '{}' is already registered",
                 name
             )));
         }
-        self.factories.insert(name, factory);
+        factories.insert(name, Arc::new(factory));
         Ok(())
     }
 
+    fn factory(&self, name: &str) -> Result<Arc<dyn ProviderFactory>> {
+        let factories = self
+            .factories
+            .read()
+            .map_err(|_| Error::Storage("Provider registry lock poisoned".to_string()))?;
+        factories
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("Provider '{}' is not registered", name)))
+    }
+
     /// Resolve a provider by name and configuration.
     ///
     /// # Preconditions
@@ -55,27 +235,49 @@ impl ProviderRegistry {
     /// - Configuration must be valid for the provider
     ///
     /// # Postconditions
-    /// - Returns an instance of the provider
+    /// - Returns an instance of the provider, wrapped in an
+    ///   [`InstrumentedProvider`] so every call is traced and timed
     ///
     /// # Errors
     /// - Provider not found
     /// - Configuration invalid
     pub fn resolve(&self, name: &str, config: Value) -> Result<Arc<dyn StorageProvider>> {
-        let factory = self
-            .factories
-            .get(name)
-            .ok_or_else(|| Error::NotFound(format!("Provider '{}' is not registered", name)))?;
-        factory(config)
+        let factory = self.factory(name)?;
+        factory.validate(&config)?;
+        Ok(InstrumentedProvider::wrap(factory.create(config)?))
+    }
+
+    /// Validate a configuration for the named provider without creating it.
+    ///
+    /// # Errors
+    /// - Provider not found
+    /// - `InvalidInput` describing what is wrong with the configuration
+    pub fn validate(&self, name: &str, config: &Value) -> Result<()> {
+        self.factory(name)?.validate(config)
+    }
+
+    /// JSON Schema of the configuration accepted by the named provider.
+    ///
+    /// # Errors
+    /// - Provider not found
+    pub fn config_schema(&self, name: &str) -> Result<Value> {
+        Ok(self.factory(name)?.config_schema())
     }
 
     /// Get list of registered provider names.
     pub fn providers(&self) -> Vec<String> {
-        self.factories.keys().cloned().collect()
+        self.factories
+            .read()
+            .map(|f| f.keys().cloned().collect())
+            .unwrap_or_default()
     }
 
     /// Check if a provider is registered.
     pub fn has_provider(&self, name: &str) -> bool {
-        self.factories.contains_key(name)
+        self.factories
+            .read()
+            .map(|f| f.contains_key(name))
+            .unwrap_or(false)
     }
 }
 
@@ -85,53 +287,117 @@ impl Default for ProviderRegistry {
     }
 }
 
-/// Create a registry with default providers.
-pub fn create_default_registry() -> ProviderRegistry {
-    let mut registry = ProviderRegistry::new();
+fn memory_config_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "In-memory storage",
+        "description": "Volatile storage for tests.",
+        "type": ["object", "null"],
+        "properties": {
+            "hash_algo": hash_algo_schema()
+        }
+    })
+}
 
-    // Register memory provider (for testing)
-    registry
-        .register(
-            "memory",
-            Box::new(|_config| Ok(Arc::new(crate::memory::MemoryProvider::new()))),
-        )
-        .expect("Failed to register memory provider");
+fn hash_algo_schema() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["md5", "sha256", "blake3"],
+        "description": "Algorithm for content hashes."
+    })
+}
 
-    // Register local filesystem provider
-    registry
-        .register(
-            "local",
-            Box::new(|config| {
-                let root = config.get("root").and_then(|v| v.as_str()).ok_or_else(|| {
-                    Error::InvalidInput("Local provider requires 'root' path".to_string())
-                })?;
-                Ok(Arc::new(crate::local::LocalProvider::new(root)?))
-            }),
-        )
-        .expect("Failed to register local provider");
-
-    // Register Google Drive provider
-    registry
-        .register("gdrive", Box::new(crate::gdrive::create_gdrive_provider))
-        .expect("Failed to register gdrive provider");
+/// The `hash_algo` option of a provider config, if set.
+fn hash_algo_option(config: &Value) -> Result<Option<HashAlgo>> {
+    config
+        .get("hash_algo")
+        .map(|algo| {
+            serde_json::from_value(algo.clone())
+                .map_err(|e| Error::InvalidInput(format!("Invalid hash_algo: {}", e)))
+        })
+        .transpose()
+}
 
-    // Register Dropbox provider
-    registry
-        .register("dropbox", Box::new(crate::dropbox::create_dropbox_provider))
-        .expect("Failed to register dropbox provider");
+fn create_memory_provider(config: Value) -> Result<Arc<dyn StorageProvider>> {
+    let provider = crate::memory::MemoryProvider::new();
+    Ok(Arc::new(match hash_algo_option(&config)? {
+        Some(algo) => provider.with_hash_algo(algo),
+        None => provider,
+    }))
+}
 
-    // Register OneDrive provider
-    registry
-        .register(
-            "onedrive",
-            Box::new(crate::onedrive::create_onedrive_provider),
-        )
-        .expect("Failed to register onedrive provider");
+fn local_config_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Local filesystem",
+        "type": "object",
+        "required": ["root"],
+        "properties": {
+            "root": {
+                "type": "string",
+                "minLength": 1,
+                "description": "Directory that holds the vault."
+            },
+            "hash_algo": hash_algo_schema()
+        }
+    })
+}
 
-    // Register iCloud Drive provider
-    registry
-        .register("icloud", Box::new(crate::icloud::create_icloud_provider))
-        .expect("Failed to register icloud provider");
+fn create_local_provider(config: Value) -> Result<Arc<dyn StorageProvider>> {
+    let root = config
+        .get("root")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::InvalidInput("Local provider requires 'root' path".to_string()))?;
+    let provider = crate::local::LocalProvider::new(root)?;
+    Ok(Arc::new(match hash_algo_option(&config)? {
+        Some(algo) => provider.with_hash_algo(algo),
+        None => provider,
+    }))
+}
+
+/// Create a registry with default providers.
+pub fn create_default_registry() -> ProviderRegistry {
+    let registry = ProviderRegistry::new();
+    let builtins = [
+        // Memory provider (for testing)
+        SchemaFactory {
+            name: "memory",
+            schema: memory_config_schema,
+            create: create_memory_provider,
+        },
+        SchemaFactory {
+            name: "local",
+            schema: local_config_schema,
+            create: create_local_provider,
+        },
+        SchemaFactory {
+            name: "gdrive",
+            schema: crate::gdrive::gdrive_config_schema,
+            create: crate::gdrive::create_gdrive_provider,
+        },
+        SchemaFactory {
+            name: "dropbox",
+            schema: crate::dropbox::dropbox_config_schema,
+            create: crate::dropbox::create_dropbox_provider,
+        },
+        SchemaFactory {
+            name: "onedrive",
+            schema: crate::onedrive::onedrive_config_schema,
+            create: crate::onedrive::create_onedrive_provider,
+        },
+        SchemaFactory {
+            name: "icloud",
+            schema: crate::icloud::icloud_config_schema,
+            create: crate::icloud::create_icloud_provider,
+        },
+    ];
+
+    for factory in builtins {
+        let name = factory.name;
+        registry
+            .register(name, factory)
+            .unwrap_or_else(|e| panic!("Failed to register {} provider: {}", name, e));
+    }
 
     registry
 }
@@ -143,10 +409,10 @@ mod tests {
 
     #[test]
     fn test_register_and_resolve() {
-        let mut registry = ProviderRegistry::new();
+        let registry = ProviderRegistry::new();
 
         registry
-            .register("test", Box::new(|_| Ok(Arc::new(MemoryProvider::new()))))
+            .register("test", from_fn(|_| Ok(Arc::new(MemoryProvider::new()))))
             .unwrap();
 
         let provider = registry.resolve("test", Value::Null).unwrap();
@@ -155,13 +421,13 @@ mod tests {
 
     #[test]
     fn test_duplicate_registration_fails() {
-        let mut registry = ProviderRegistry::new();
+        let registry = ProviderRegistry::new();
 
         registry
-            .register("test", Box::new(|_| Ok(Arc::new(MemoryProvider::new()))))
+            .register("test", from_fn(|_| Ok(Arc::new(MemoryProvider::new()))))
             .unwrap();
 
-        let result = registry.register("test", Box::new(|_| Ok(Arc::new(MemoryProvider::new()))));
+        let result = registry.register("test", from_fn(|_| Ok(Arc::new(MemoryProvider::new()))));
         assert!(result.is_err());
     }
 
@@ -174,16 +440,197 @@ mod tests {
 
     #[test]
     fn test_providers_list() {
-        let mut registry = ProviderRegistry::new();
+        let registry = ProviderRegistry::new();
         registry
-            .register("a", Box::new(|_| Ok(Arc::new(MemoryProvider::new()))))
+            .register("a", from_fn(|_| Ok(Arc::new(MemoryProvider::new()))))
             .unwrap();
         registry
-            .register("b", Box::new(|_| Ok(Arc::new(MemoryProvider::new()))))
+            .register("b", from_fn(|_| Ok(Arc::new(MemoryProvider::new()))))
             .unwrap();
 
         let providers = registry.providers();
         assert!(providers.contains(&"a".to_string()));
         assert!(providers.contains(&"b".to_string()));
     }
+
+    /// A third-party factory with its own schema and validation.
+    struct BucketFactory;
+
+    impl ProviderFactory for BucketFactory {
+        fn create(&self, _config: Value) -> Result<Arc<dyn StorageProvider>> {
+            Ok(Arc::new(MemoryProvider::new()))
+        }
+
+        fn validate(&self, config: &Value) -> Result<()> {
+            validate_against_schema("bucket", &self.config_schema(), config)
+        }
+
+        fn config_schema(&self) -> Value {
+            json!({
+                "type": "object",
+                "required": ["bucket"],
+                "properties": { "bucket": { "type": "string" } },
+                "additionalProperties": false
+            })
+        }
+    }
+
+    #[test]
+    fn test_third_party_registration_is_shared_between_clones() {
+        let registry = create_default_registry();
+        let shared = registry.clone();
+
+        shared.register("bucket", BucketFactory).unwrap();
+
+        assert!(registry.has_provider("bucket"));
+        assert_eq!(
+            registry.config_schema("bucket").unwrap()["required"],
+            json!(["bucket"])
+        );
+        registry
+            .resolve("bucket", json!({ "bucket": "vaults" }))
+            .unwrap();
+
+        let err = registry
+            .resolve("bucket", json!({ "bucket": "vaults", "region": "eu" }))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Invalid input: Invalid bucket config: unknown field 'region'"
+        );
+    }
+
+    #[test]
+    fn test_builtin_schemas() {
+        let registry = create_default_registry();
+        for name in ["memory", "local", "gdrive", "dropbox", "onedrive", "icloud"] {
+            let schema = registry.config_schema(name).unwrap();
+            assert!(schema.get("type").is_some(), "{} schema has no type", name);
+            assert!(
+                schema.get("title").is_some(),
+                "{} schema has no title",
+                name
+            );
+        }
+
+        let local = registry.config_schema("local").unwrap();
+        assert_eq!(local["required"], json!(["root"]));
+        let gdrive = registry.config_schema("gdrive").unwrap();
+        assert_eq!(gdrive["required"], json!(["folder_id", "tokens"]));
+        assert!(gdrive["properties"]["tokens"]["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("access_token")));
+
+        assert!(registry.config_schema("unknown").is_err());
+    }
+
+    fn validation_message(registry: &ProviderRegistry, name: &str, config: Value) -> String {
+        match registry.validate(name, &config) {
+            Err(Error::InvalidInput(msg)) => msg,
+            other => panic!("expected InvalidInput for {}, got {:?}", name, other),
+        }
+    }
+
+    #[test]
+    fn test_memory_validation() {
+        let registry = create_default_registry();
+        registry.validate("memory", &Value::Null).unwrap();
+        registry.validate("memory", &json!({})).unwrap();
+        assert_eq!(
+            validation_message(&registry, "memory", json!("volatile")),
+            "Invalid memory config: configuration must be object or null"
+        );
+        assert_eq!(
+            validation_message(&registry, "memory", json!({ "hash_algo": "crc32" })),
+            "Invalid memory config: field 'hash_algo' has an unsupported value"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hash_algo_is_configurable_per_provider() {
+        let registry = create_default_registry();
+        let path = axiomvault_common::VaultPath::parse("/f").unwrap();
+
+        let memory = registry
+            .resolve("memory", json!({ "hash_algo": "blake3" }))
+            .unwrap();
+        let stored = memory.upload(&path, b"data".to_vec()).await.unwrap();
+        assert_eq!(stored.content_hash, Some(HashAlgo::Blake3.hash(b"data")));
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+        let local = registry
+            .resolve("local", json!({ "root": root, "hash_algo": "md5" }))
+            .unwrap();
+        local.upload(&path, b"data".to_vec()).await.unwrap();
+        let metadata = local.metadata(&path).await.unwrap();
+        assert_eq!(metadata.content_hash, Some(HashAlgo::Md5.hash(b"data")));
+    }
+
+    #[test]
+    fn test_local_validation() {
+        let registry = create_default_registry();
+        registry
+            .validate("local", &json!({ "root": "/tmp/vault" }))
+            .unwrap();
+        assert_eq!(
+            validation_message(&registry, "local", json!({})),
+            "Invalid local config: missing field 'root'"
+        );
+        assert_eq!(
+            validation_message(&registry, "local", json!({ "root": 42 })),
+            "Invalid local config: field 'root' must be string"
+        );
+        assert_eq!(
+            validation_message(&registry, "local", json!({ "root": "" })),
+            "Invalid local config: field 'root' must not be empty"
+        );
+        assert_eq!(
+            validation_message(&registry, "local", Value::Null),
+            "Invalid local config: configuration must be object"
+        );
+    }
+
+    #[test]
+    fn test_gdrive_validation() {
+        let registry = create_default_registry();
+        let tokens = json!({
+            "access_token": "access",
+            "refresh_token": "refresh",
+            "expires_at": "2030-01-01T00:00:00Z"
+        });
+
+        registry
+            .validate("gdrive", &json!({ "folder_id": "abc", "tokens": tokens }))
+            .unwrap();
+        assert_eq!(
+            validation_message(&registry, "gdrive", json!({ "tokens": tokens })),
+            "Invalid gdrive config: missing field 'folder_id'"
+        );
+        assert_eq!(
+            validation_message(
+                &registry,
+                "gdrive",
+                json!({ "folder_id": "abc", "tokens": { "access_token": "access" } })
+            ),
+            "Invalid gdrive config: missing field 'tokens.refresh_token'"
+        );
+        assert_eq!(
+            validation_message(
+                &registry,
+                "gdrive",
+                json!({ "folder_id": "abc", "tokens": tokens, "auth_config": { "client_id": 1 } })
+            ),
+            "Invalid gdrive config: missing field 'auth_config.client_secret'"
+        );
+    }
+
+    #[test]
+    fn test_resolve_validates_before_create() {
+        let registry = create_default_registry();
+        let err = registry.resolve("gdrive", json!({})).err().unwrap();
+        assert!(matches!(err, Error::InvalidInput(ref m) if m.contains("'folder_id'")));
+    }
 }
@@ -0,0 +1,615 @@
+//! Storage providers for tests, shared by the crates that build on this one.
+//!
+//! Available to this crate's own tests and, behind the `test-util`
+//! feature, to other crates' tests:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! axiomvault-storage = { path = "../storage", features = ["test-util"] }
+//! ```
+//!
+//! Each provider wraps a [`MemoryProvider`] and records what passes
+//! through it. [`inner`](CountingProvider::inner) reaches the wrapped
+//! provider without being recorded.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use tokio::sync::Semaphore;
+
+use crate::memory::MemoryProvider;
+use crate::provider::{
+    ByteStream, Metadata, ResumableUpload, StorageProvider, StorageQuota, UploadProgress,
+    UploadSession,
+};
+use axiomvault_common::{Error, Result, VaultPath};
+
+/// Memory provider that records uploads, downloads and deletes, and fails,
+/// slows down or fills up on demand.
+pub struct CountingProvider {
+    inner: MemoryProvider,
+    uploads: Mutex<Vec<String>>,
+    downloads: Mutex<Vec<String>>,
+    deletes: AtomicUsize,
+    renames_left: AtomicUsize,
+    etagless: bool,
+    offline: AtomicBool,
+    download_delay: Option<Duration>,
+    downloading: AtomicUsize,
+    peak_downloads: AtomicUsize,
+    failing_uploads: Mutex<Option<String>>,
+    quota: Mutex<Option<StorageQuota>>,
+}
+
+impl CountingProvider {
+    /// An empty provider that records everything and never fails.
+    pub fn new() -> Self {
+        Self {
+            inner: MemoryProvider::new(),
+            uploads: Mutex::new(Vec::new()),
+            downloads: Mutex::new(Vec::new()),
+            deletes: AtomicUsize::new(0),
+            renames_left: AtomicUsize::new(usize::MAX),
+            etagless: false,
+            offline: AtomicBool::new(false),
+            download_delay: None,
+            downloading: AtomicUsize::new(0),
+            peak_downloads: AtomicUsize::new(0),
+            failing_uploads: Mutex::new(None),
+            quota: Mutex::new(None),
+        }
+    }
+
+    /// Report etags and content hashes only in upload responses, like
+    /// Drive's missing `md5Checksum` on zero-length files.
+    pub fn etagless(mut self) -> Self {
+        self.etagless = true;
+        self
+    }
+
+    /// Make every whole download take `delay`; see
+    /// [`peak_downloads`](Self::peak_downloads).
+    pub fn with_download_delay(mut self, delay: Duration) -> Self {
+        self.download_delay = Some(delay);
+        self
+    }
+
+    /// Account uploads against a byte quota of `limit`, rejecting those
+    /// that would overflow it like Drive's `storageQuotaExceeded`.
+    pub fn with_quota(self, limit: u64) -> Self {
+        self.set_quota_limit(limit);
+        self
+    }
+
+    /// Change the quota limit, keeping the bytes used so far.
+    pub fn set_quota_limit(&self, limit: u64) {
+        let mut quota = self.quota.lock().unwrap();
+        let used = quota.map_or(0, |quota| quota.used);
+        *quota = Some(StorageQuota {
+            used,
+            limit: Some(limit),
+        });
+    }
+
+    /// Let `renames` more renames through, then fail every rename with a
+    /// network error.
+    pub fn fail_renames_after(&self, renames: usize) {
+        self.renames_left.store(renames, Ordering::SeqCst);
+    }
+
+    /// Fail downloads with a network error while `offline`.
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.store(offline, Ordering::SeqCst);
+    }
+
+    /// Fail uploads of files called `name` with a storage error, as if the
+    /// process died before writing them; `None` lets them through again.
+    pub fn fail_uploads_named(&self, name: Option<&str>) {
+        *self.failing_uploads.lock().unwrap() = name.map(str::to_string);
+    }
+
+    /// Number of uploads, whole or streamed.
+    pub fn uploads(&self) -> usize {
+        self.uploads.lock().unwrap().len()
+    }
+
+    /// Number of uploads to `path`.
+    pub fn uploads_of(&self, path: &VaultPath) -> usize {
+        let path = path.to_string();
+        self.uploads
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| **p == path)
+            .count()
+    }
+
+    /// Paths downloaded so far, in order, whole or streamed.
+    pub fn downloads(&self) -> Vec<String> {
+        self.downloads.lock().unwrap().clone()
+    }
+
+    /// Forget the downloads recorded so far.
+    pub fn clear_downloads(&self) {
+        self.downloads.lock().unwrap().clear();
+    }
+
+    /// Most whole downloads that were in flight at once.
+    pub fn peak_downloads(&self) -> usize {
+        self.peak_downloads.load(Ordering::SeqCst)
+    }
+
+    /// Number of deleted files.
+    pub fn deletes(&self) -> usize {
+        self.deletes.load(Ordering::SeqCst)
+    }
+
+    /// The wrapped provider.
+    pub fn inner(&self) -> &MemoryProvider {
+        &self.inner
+    }
+
+    fn strip(&self, mut metadata: Metadata) -> Metadata {
+        if self.etagless {
+            metadata.etag = None;
+            metadata.content_hash = None;
+        }
+        metadata
+    }
+
+    fn check_online(&self) -> Result<()> {
+        if self.offline.load(Ordering::SeqCst) {
+            return Err(Error::Network("provider unreachable".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Record an upload of `len` bytes to `path`, or refuse it.
+    fn admit_upload(&self, path: &VaultPath, len: usize) -> Result<()> {
+        self.uploads.lock().unwrap().push(path.to_string());
+        if let Some(name) = self.failing_uploads.lock().unwrap().as_deref() {
+            if path.name() == Some(name) {
+                return Err(Error::Storage(format!("crashed before saving {}", name)));
+            }
+        }
+        if let Some(quota) = self.quota.lock().unwrap().as_mut() {
+            let used = quota.used + len as u64;
+            if quota.limit.is_some_and(|limit| used > limit) {
+                return Err(Error::QuotaExhausted("account is full".to_string()));
+            }
+            quota.used = used;
+        }
+        Ok(())
+    }
+}
+
+impl Default for CountingProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl StorageProvider for CountingProvider {
+    fn name(&self) -> &str {
+        "counting"
+    }
+
+    async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
+        self.admit_upload(path, data.len())?;
+        self.inner.upload(path, data).await
+    }
+
+    async fn upload_stream(&self, path: &VaultPath, mut stream: ByteStream) -> Result<Metadata> {
+        if self.quota.lock().unwrap().is_none() {
+            self.admit_upload(path, 0)?;
+            return self.inner.upload_stream(path, stream).await;
+        }
+        let mut data = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        self.upload(path, data).await
+    }
+
+    async fn download(&self, path: &VaultPath) -> Result<Vec<u8>> {
+        self.check_online()?;
+        self.downloads.lock().unwrap().push(path.to_string());
+        let now = self.downloading.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak_downloads.fetch_max(now, Ordering::SeqCst);
+        if let Some(delay) = self.download_delay {
+            tokio::time::sleep(delay).await;
+        }
+        let result = self.inner.download(path).await;
+        self.downloading.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+
+    async fn download_stream(&self, path: &VaultPath) -> Result<ByteStream> {
+        self.check_online()?;
+        self.downloads.lock().unwrap().push(path.to_string());
+        self.inner.download_stream(path).await
+    }
+
+    async fn exists(&self, path: &VaultPath) -> Result<bool> {
+        self.inner.exists(path).await
+    }
+
+    async fn delete(&self, path: &VaultPath) -> Result<()> {
+        self.deletes.fetch_add(1, Ordering::SeqCst);
+        self.inner.delete(path).await
+    }
+
+    async fn list(&self, path: &VaultPath) -> Result<Vec<Metadata>> {
+        let list = self.inner.list(path).await?;
+        Ok(list.into_iter().map(|m| self.strip(m)).collect())
+    }
+
+    async fn metadata(&self, path: &VaultPath) -> Result<Metadata> {
+        self.inner.metadata(path).await.map(|m| self.strip(m))
+    }
+
+    async fn create_dir(&self, path: &VaultPath) -> Result<Metadata> {
+        self.inner.create_dir(path).await
+    }
+
+    async fn delete_dir(&self, path: &VaultPath) -> Result<()> {
+        self.inner.delete_dir(path).await
+    }
+
+    async fn rename(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        if self
+            .renames_left
+            .try_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_err()
+        {
+            return Err(Error::Network("connection reset".to_string()));
+        }
+        self.inner.rename(from, to).await
+    }
+
+    async fn copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn quota(&self) -> Result<Option<StorageQuota>> {
+        match *self.quota.lock().unwrap() {
+            Some(quota) => Ok(Some(quota)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Memory provider without a native rename, so renames take the copy +
+/// delete fallback. Counts content bytes downloaded and chunks uploaded,
+/// and can offer a server-side copy.
+#[derive(Default)]
+pub struct NoRenameProvider {
+    inner: MemoryProvider,
+    server_copy: bool,
+    download_chunk: Option<usize>,
+    downloaded: Arc<AtomicUsize>,
+    upload_chunks: Arc<AtomicUsize>,
+}
+
+impl NoRenameProvider {
+    /// An empty provider without a server-side copy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer a server-side copy.
+    pub fn with_server_side_copy(mut self) -> Self {
+        self.server_copy = true;
+        self
+    }
+
+    /// Serve streamed downloads in chunks of `size` bytes instead of whole.
+    pub fn with_download_chunks(mut self, size: usize) -> Self {
+        self.download_chunk = Some(size.max(1));
+        self
+    }
+
+    /// Content bytes downloaded so far, whole or streamed.
+    pub fn downloaded(&self) -> usize {
+        self.downloaded.load(Ordering::SeqCst)
+    }
+
+    /// Chunks received by streamed uploads so far.
+    pub fn upload_chunks(&self) -> usize {
+        self.upload_chunks.load(Ordering::SeqCst)
+    }
+
+    /// The wrapped provider.
+    pub fn inner(&self) -> &MemoryProvider {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl StorageProvider for NoRenameProvider {
+    fn name(&self) -> &str {
+        "no-rename"
+    }
+
+    async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
+        self.inner.upload(path, data).await
+    }
+
+    async fn upload_stream(&self, path: &VaultPath, stream: ByteStream) -> Result<Metadata> {
+        let upload_chunks = self.upload_chunks.clone();
+        let stream = stream.inspect(move |_| {
+            upload_chunks.fetch_add(1, Ordering::SeqCst);
+        });
+        self.inner.upload_stream(path, Box::pin(stream)).await
+    }
+
+    async fn download(&self, path: &VaultPath) -> Result<Vec<u8>> {
+        let data = self.inner.download(path).await?;
+        self.downloaded.fetch_add(data.len(), Ordering::SeqCst);
+        Ok(data)
+    }
+
+    async fn download_stream(&self, path: &VaultPath) -> Result<ByteStream> {
+        let stream = match self.download_chunk {
+            Some(size) => {
+                let data = self.inner.download(path).await?;
+                let chunks: Vec<Result<Vec<u8>>> =
+                    data.chunks(size).map(|c| Ok(c.to_vec())).collect();
+                Box::pin(futures::stream::iter(chunks))
+            }
+            None => self.inner.download_stream(path).await?,
+        };
+        let downloaded = self.downloaded.clone();
+        Ok(Box::pin(stream.inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                downloaded.fetch_add(chunk.len(), Ordering::SeqCst);
+            }
+        })))
+    }
+
+    async fn exists(&self, path: &VaultPath) -> Result<bool> {
+        self.inner.exists(path).await
+    }
+
+    async fn delete(&self, path: &VaultPath) -> Result<()> {
+        self.inner.delete(path).await
+    }
+
+    async fn list(&self, path: &VaultPath) -> Result<Vec<Metadata>> {
+        self.inner.list(path).await
+    }
+
+    async fn metadata(&self, path: &VaultPath) -> Result<Metadata> {
+        self.inner.metadata(path).await
+    }
+
+    async fn create_dir(&self, path: &VaultPath) -> Result<Metadata> {
+        self.inner.create_dir(path).await
+    }
+
+    async fn delete_dir(&self, path: &VaultPath) -> Result<()> {
+        self.inner.delete_dir(path).await
+    }
+
+    async fn copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        self.inner.copy(from, to).await
+    }
+
+    async fn server_side_copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        if !self.server_copy {
+            return Err(Error::Unsupported("server-side copy".to_string()));
+        }
+        self.inner.server_side_copy(from, to).await
+    }
+}
+
+/// Chunk size of [`ResumableProvider`] uploads.
+pub const RESUMABLE_CHUNK: usize = 1024;
+
+/// Memory provider with a resumable upload API that fails on demand.
+#[derive(Default)]
+pub struct ResumableProvider {
+    inner: MemoryProvider,
+    /// Bytes received per open session URI.
+    sessions: Mutex<HashMap<String, Vec<u8>>>,
+    sessions_started: AtomicUsize,
+    bytes_received: AtomicUsize,
+    /// Fail after this many more chunks; `true` stores the failing chunk
+    /// before reporting the error, like a lost acknowledgement.
+    fail_after: Mutex<Option<(usize, bool)>>,
+    /// When set, each chunk waits for a permit first.
+    gate: Mutex<Option<Arc<Semaphore>>>,
+    /// Chunks that reached the gate, including one still waiting.
+    chunks_gated: AtomicUsize,
+}
+
+impl ResumableProvider {
+    /// An empty provider whose uploads never fail.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let `chunks` more chunks through, then fail the next one with a
+    /// network error, storing it first if `store_failed_chunk`.
+    pub fn fail_after(&self, chunks: usize, store_failed_chunk: bool) {
+        *self.fail_after.lock().unwrap() = Some((chunks, store_failed_chunk));
+    }
+
+    /// Stop failing chunks.
+    pub fn heal(&self) {
+        *self.fail_after.lock().unwrap() = None;
+    }
+
+    /// Forget every open upload session, as if they timed out.
+    pub fn expire_sessions(&self) {
+        self.sessions.lock().unwrap().clear();
+    }
+
+    /// Make each chunk wait for a permit of `gate`; `None` lets them
+    /// straight through again.
+    pub fn set_gate(&self, gate: Option<Arc<Semaphore>>) {
+        *self.gate.lock().unwrap() = gate;
+    }
+
+    /// Upload sessions started so far.
+    pub fn sessions_started(&self) -> usize {
+        self.sessions_started.load(Ordering::SeqCst)
+    }
+
+    /// Bytes stored by upload sessions so far.
+    pub fn bytes_received(&self) -> usize {
+        self.bytes_received.load(Ordering::SeqCst)
+    }
+
+    /// Chunks that reached the gate, including one still waiting.
+    pub fn chunks_gated(&self) -> usize {
+        self.chunks_gated.load(Ordering::SeqCst)
+    }
+
+    /// The wrapped provider.
+    pub fn inner(&self) -> &MemoryProvider {
+        &self.inner
+    }
+
+    async fn progress(&self, session: &UploadSession, received: Vec<u8>) -> Result<UploadProgress> {
+        if received.len() as u64 == session.total_size {
+            let metadata = self.inner.upload(&session.path, received).await?;
+            Ok(UploadProgress::Complete(metadata))
+        } else {
+            Ok(UploadProgress::InProgress {
+                acknowledged: received.len() as u64,
+            })
+        }
+    }
+}
+
+#[async_trait]
+impl ResumableUpload for ResumableProvider {
+    fn chunk_size(&self) -> usize {
+        RESUMABLE_CHUNK
+    }
+
+    async fn start_upload(&self, path: &VaultPath, total_size: u64) -> Result<UploadSession> {
+        let n = self.sessions_started.fetch_add(1, Ordering::SeqCst);
+        let uri = format!("resumable://session/{}", n);
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(uri.clone(), Vec::new());
+        Ok(UploadSession {
+            uri,
+            path: path.clone(),
+            total_size,
+        })
+    }
+
+    async fn query_upload(&self, session: &UploadSession) -> Result<UploadProgress> {
+        let received = self.sessions.lock().unwrap().get(&session.uri).cloned();
+        match received {
+            Some(received) => self.progress(session, received).await,
+            None => Err(Error::NotFound("Upload session expired".to_string())),
+        }
+    }
+
+    async fn continue_upload(
+        &self,
+        session: &UploadSession,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<UploadProgress> {
+        let gate = self.gate.lock().unwrap().clone();
+        if let Some(gate) = gate {
+            self.chunks_gated.fetch_add(1, Ordering::SeqCst);
+            gate.acquire()
+                .await
+                .map_err(|e| Error::Storage(e.to_string()))?
+                .forget();
+        }
+        let received = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let received = sessions
+                .get_mut(&session.uri)
+                .ok_or_else(|| Error::NotFound("Upload session expired".to_string()))?;
+            assert_eq!(offset, received.len() as u64, "chunk sent out of order");
+
+            let mut fail_after = self.fail_after.lock().unwrap();
+            let store_and_fail = match fail_after.as_mut() {
+                Some((0, store)) => Some(*store),
+                Some((remaining, _)) => {
+                    *remaining -= 1;
+                    None
+                }
+                None => None,
+            };
+            if store_and_fail != Some(false) {
+                received.extend_from_slice(data);
+                self.bytes_received.fetch_add(data.len(), Ordering::SeqCst);
+            }
+            if store_and_fail.is_some() {
+                return Err(Error::Network("connection reset".to_string()));
+            }
+            received.clone()
+        };
+        self.progress(session, received).await
+    }
+}
+
+#[async_trait]
+impl StorageProvider for ResumableProvider {
+    fn name(&self) -> &str {
+        "resumable"
+    }
+
+    fn as_resumable(&self) -> Option<&dyn ResumableUpload> {
+        Some(self)
+    }
+
+    async fn upload(&self, path: &VaultPath, data: Vec<u8>) -> Result<Metadata> {
+        self.inner.upload(path, data).await
+    }
+
+    async fn upload_stream(&self, path: &VaultPath, stream: ByteStream) -> Result<Metadata> {
+        self.inner.upload_stream(path, stream).await
+    }
+
+    async fn download(&self, path: &VaultPath) -> Result<Vec<u8>> {
+        self.inner.download(path).await
+    }
+
+    async fn download_stream(&self, path: &VaultPath) -> Result<ByteStream> {
+        self.inner.download_stream(path).await
+    }
+
+    async fn exists(&self, path: &VaultPath) -> Result<bool> {
+        self.inner.exists(path).await
+    }
+
+    async fn delete(&self, path: &VaultPath) -> Result<()> {
+        self.inner.delete(path).await
+    }
+
+    async fn list(&self, path: &VaultPath) -> Result<Vec<Metadata>> {
+        self.inner.list(path).await
+    }
+
+    async fn metadata(&self, path: &VaultPath) -> Result<Metadata> {
+        self.inner.metadata(path).await
+    }
+
+    async fn create_dir(&self, path: &VaultPath) -> Result<Metadata> {
+        self.inner.create_dir(path).await
+    }
+
+    async fn delete_dir(&self, path: &VaultPath) -> Result<()> {
+        self.inner.delete_dir(path).await
+    }
+
+    async fn copy(&self, from: &VaultPath, to: &VaultPath) -> Result<Metadata> {
+        self.inner.copy(from, to).await
+    }
+}
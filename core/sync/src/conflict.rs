@@ -1,12 +1,22 @@
 //! Conflict detection and resolution.
 
+use std::fmt;
+use std::sync::Arc;
+
+use blake2::{Blake2s256, Digest};
 use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
 
-use axiomvault_common::{Result, VaultPath};
-use axiomvault_storage::{Metadata, StorageProvider};
+use axiomvault_common::{Error, Result, SharedClock, SystemClock, VaultPath};
+use axiomvault_storage::{copy_object, CopyMechanism, Metadata, StorageProvider};
+use tracing::debug;
 
+use crate::planner;
+use crate::staging::StagedBlob;
 use crate::state::SyncEntry;
+use crate::trash::{remove_remote, TrashConfig};
 
 /// Conflict resolution strategy.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,6 +29,147 @@ pub enum ConflictStrategy {
     PreferRemote,
     /// Ask user to resolve manually.
     Manual,
+    /// Ask the client's [`ConflictHandler`] about each conflict as it is
+    /// found. Without a handler conflicts are left for manual resolution.
+    Interactive,
+}
+
+/// What a client chose for one conflict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictDecision {
+    KeepLocal,
+    KeepRemote,
+    KeepBoth,
+    /// Leave the conflict for later.
+    Skip,
+}
+
+impl ConflictDecision {
+    /// Strategy applying this decision; `Manual` leaves it pending.
+    pub fn strategy(self) -> ConflictStrategy {
+        match self {
+            Self::KeepLocal => ConflictStrategy::PreferLocal,
+            Self::KeepRemote => ConflictStrategy::PreferRemote,
+            Self::KeepBoth => ConflictStrategy::KeepBoth,
+            Self::Skip => ConflictStrategy::Manual,
+        }
+    }
+}
+
+/// Client callback deciding conflicts for [`ConflictStrategy::Interactive`].
+///
+/// The returned future is dropped if the prompt is cancelled.
+pub type ConflictHandler =
+    Arc<dyn Fn(ConflictDetails) -> BoxFuture<'static, ConflictDecision> + Send + Sync>;
+
+/// One side of a sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    /// This device.
+    Local,
+    /// The remote storage.
+    Remote,
+}
+
+/// What the two sides of a conflict disagree about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictKind {
+    /// Both sides edited the file.
+    Edit,
+    /// One side deleted the file while the other edited it.
+    DeleteVsEdit { deleted: Side },
+}
+
+/// Default template for [`ConflictNaming`].
+pub const DEFAULT_CONFLICT_TEMPLATE: &str = "{name} (conflicted copy from {device} {timestamp})";
+
+/// Timestamp format substituted for `{timestamp}`.
+const CONFLICT_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H-%M-%S";
+
+/// Highest counter tried before giving up on a free conflict name.
+const MAX_CONFLICT_COUNTER: u32 = 1000;
+
+/// How `KeepBoth` names conflict copies.
+///
+/// The template renders the copy's file name without its extension:
+/// `{name}` is the original name without extension, `{device}` the device
+/// that kept the copy and `{timestamp}` the UTC time of the conflict.
+/// The original extension is appended unchanged. If the name is taken, a
+/// counter is added before the extension: `report (...) (2).pdf`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConflictNaming {
+    /// Name template; see the type docs for placeholders.
+    pub template: String,
+    /// Device name substituted for `{device}`.
+    pub device: String,
+}
+
+impl ConflictNaming {
+    /// Name copies with [`DEFAULT_CONFLICT_TEMPLATE`] for `device`.
+    pub fn new(device: impl Into<String>) -> Self {
+        Self {
+            template: DEFAULT_CONFLICT_TEMPLATE.to_string(),
+            device: device.into(),
+        }
+    }
+
+    /// Render the conflict path for `original`, with `counter` above 1
+    /// added as a numeric suffix.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if the rendered name is empty or contains `/`
+    pub fn render(
+        &self,
+        original: &VaultPath,
+        at: DateTime<Utc>,
+        counter: u32,
+    ) -> Result<VaultPath> {
+        let file_name = original
+            .name()
+            .ok_or_else(|| Error::InvalidInput("Cannot rename the vault root".to_string()))?;
+        let (stem, ext) = split_extension(file_name);
+
+        let timestamp = at.format(CONFLICT_TIMESTAMP_FORMAT).to_string();
+        let mut name = self
+            .template
+            .replace("{device}", &self.device)
+            .replace("{timestamp}", &timestamp)
+            .replace("{name}", stem);
+        if counter > 1 {
+            name.push_str(&format!(" ({counter})"));
+        }
+        name.push_str(ext);
+
+        if name.is_empty() || name.contains('/') {
+            return Err(Error::InvalidInput(format!(
+                "Conflict name template renders an invalid file name: {:?}",
+                name
+            )));
+        }
+        original
+            .parent()
+            .unwrap_or_else(VaultPath::root)
+            .join(&name)
+    }
+}
+
+/// Split a file name into stem and extension (with its dot). A leading dot
+/// does not start an extension, so `.env` has none.
+fn split_extension(file_name: &str) -> (&str, &str) {
+    match file_name.rfind('.').filter(|&pos| pos > 0) {
+        Some(pos) => file_name.split_at(pos),
+        None => (file_name, ""),
+    }
+}
+
+impl ConflictKind {
+    /// Kind of conflict an entry in conflict is in.
+    pub fn of(entry: &SyncEntry) -> Self {
+        match entry.deleted_by {
+            Some(deleted) => Self::DeleteVsEdit { deleted },
+            None => Self::Edit,
+        }
+    }
 }
 
 /// Information about a detected conflict.
@@ -55,6 +206,143 @@ impl ConflictInfo {
     }
 }
 
+/// The local version of a conflicted file, as the vault sealed it.
+///
+/// Built from a [`StagedBlob`], or by the engine from an upload already
+/// staged, so resolving a conflict only ever uploads ciphertext.
+#[derive(Clone)]
+pub struct LocalVersion(Vec<u8>);
+
+impl LocalVersion {
+    /// Bytes of a staged upload, which are ciphertext by the staging
+    /// contract.
+    pub(crate) fn staged(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+
+    /// The encrypted bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl From<StagedBlob> for LocalVersion {
+    fn from(blob: StagedBlob) -> Self {
+        Self(blob.into_ciphertext().into_bytes())
+    }
+}
+
+impl fmt::Debug for LocalVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LocalVersion({} bytes)", self.0.len())
+    }
+}
+
+/// Hash identifying the content of one version of a file.
+pub fn content_hash(data: &[u8]) -> [u8; 32] {
+    Blake2s256::digest(data).into()
+}
+
+/// Most lines a [`DiffPreview`] holds.
+pub const PREVIEW_LINES: usize = 12;
+
+/// One side of a conflicted file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub etag: Option<String>,
+    pub modified: Option<DateTime<Utc>>,
+    /// Encrypted size in bytes, if known.
+    pub size: Option<u64>,
+}
+
+/// Both sides of a conflicted file, for choosing a strategy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictDetails {
+    pub path: VaultPath,
+    pub kind: ConflictKind,
+    /// Latest staged local version. For a local delete, only when the
+    /// file was deleted.
+    pub local: VersionInfo,
+    /// Version currently on the remote. For a remote delete, only when
+    /// the delete was noticed.
+    pub remote: VersionInfo,
+    /// Line diff of the decrypted contents, when both sides are text.
+    pub preview: Option<DiffPreview>,
+}
+
+impl ConflictDetails {
+    /// Attach a preview of how `local` and `remote` plaintext differ.
+    ///
+    /// Left unset if either side is not UTF-8 text.
+    pub fn with_preview(mut self, local: &[u8], remote: &[u8]) -> Self {
+        self.preview = DiffPreview::between(local, remote);
+        self
+    }
+}
+
+/// Short line diff between two text versions.
+///
+/// Common leading and trailing lines are skipped; the differing middle is
+/// shown as `-` (local) and `+` (remote) lines, up to [`PREVIEW_LINES`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffPreview {
+    /// 1-based line number of the first differing line.
+    pub first_line: usize,
+    pub lines: Vec<String>,
+    /// More differing lines exist than are shown.
+    pub truncated: bool,
+}
+
+impl DiffPreview {
+    /// Diff two texts, or `None` if either is binary.
+    pub fn between(local: &[u8], remote: &[u8]) -> Option<Self> {
+        let local = text(local)?;
+        let remote = text(remote)?;
+        let local: Vec<&str> = local.lines().collect();
+        let remote: Vec<&str> = remote.lines().collect();
+
+        let prefix = local
+            .iter()
+            .zip(&remote)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let suffix = local[prefix..]
+            .iter()
+            .rev()
+            .zip(remote[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let removed = &local[prefix..local.len() - suffix];
+        let added = &remote[prefix..remote.len() - suffix];
+        let mut lines: Vec<String> = removed
+            .iter()
+            .map(|l| format!("-{}", l))
+            .chain(added.iter().map(|l| format!("+{}", l)))
+            .collect();
+        let truncated = lines.len() > PREVIEW_LINES;
+        lines.truncate(PREVIEW_LINES);
+
+        Some(Self {
+            first_line: prefix + 1,
+            lines,
+            truncated,
+        })
+    }
+}
+
+/// `data` as text, if it is UTF-8 without NUL bytes.
+fn text(data: &[u8]) -> Option<&str> {
+    if data.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(data).ok()
+}
+
 /// Result of conflict resolution.
 #[derive(Debug)]
 pub enum ResolutionResult {
@@ -67,6 +355,16 @@ pub enum ResolutionResult {
         original_path: VaultPath,
         renamed_path: VaultPath,
         remote_etag: Option<String>,
+        /// How the conflict copy was moved to its final name, or `None` if
+        /// the backend renamed it natively.
+        copy: Option<CopyMechanism>,
+    },
+    /// The file is deleted on both sides. With `restored_path`, the edited
+    /// version was kept under that conflict name.
+    Deleted {
+        path: VaultPath,
+        restored_path: Option<VaultPath>,
+        restored_etag: Option<String>,
     },
     /// Conflict still pending (manual resolution needed).
     Pending,
@@ -76,12 +374,79 @@ pub enum ResolutionResult {
 pub struct ConflictResolver {
     /// Default resolution strategy.
     default_strategy: ConflictStrategy,
+    /// Naming for conflict copies; `None` uses `generate_conflict_path`.
+    naming: Option<ConflictNaming>,
+    /// Callback for `Interactive` resolution.
+    handler: Option<ConflictHandler>,
+    /// Bumped to cancel prompts in flight.
+    prompts: watch::Sender<u64>,
+    /// Time stamped into conflict copy names.
+    clock: SharedClock,
 }
 
 impl ConflictResolver {
     /// Create a new conflict resolver with default strategy.
     pub fn new(default_strategy: ConflictStrategy) -> Self {
-        Self { default_strategy }
+        Self {
+            default_strategy,
+            naming: None,
+            handler: None,
+            prompts: watch::Sender::new(0),
+            clock: SystemClock::shared(),
+        }
+    }
+
+    /// Stamp conflict copies with the time from `clock`.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Name conflict copies with `naming` instead of random suffixes.
+    pub fn with_naming(mut self, naming: ConflictNaming) -> Self {
+        self.naming = Some(naming);
+        self
+    }
+
+    /// Decide `Interactive` conflicts with `handler`.
+    pub fn with_handler(mut self, handler: ConflictHandler) -> Self {
+        self.handler = Some(handler);
+        self
+    }
+
+    /// The handler set with [`with_handler`](Self::with_handler).
+    pub(crate) fn handler(&self) -> Option<ConflictHandler> {
+        self.handler.clone()
+    }
+
+    /// Ask the handler how to resolve a conflict.
+    ///
+    /// Returns the strategy to resolve with, or `None` if the conflict is
+    /// skipped or there is no handler.
+    ///
+    /// # Errors
+    /// - `Cancelled` if [`cancel_prompts`](Self::cancel_prompts) is called
+    ///   while waiting for the decision
+    pub async fn ask(&self, details: ConflictDetails) -> Result<Option<ConflictStrategy>> {
+        let Some(handler) = &self.handler else {
+            return Ok(None);
+        };
+        let mut cancelled = self.prompts.subscribe();
+        debug!(path = %details.path.display_lossy(), "Asking client about conflict");
+        tokio::select! {
+            decision = handler(details) => Ok(match decision {
+                ConflictDecision::Skip => None,
+                decision => Some(decision.strategy()),
+            }),
+            _ = cancelled.changed() => {
+                Err(Error::Cancelled("conflict prompt".to_string()))
+            }
+        }
+    }
+
+    /// Cancel every prompt currently waiting in [`ask`](Self::ask).
+    pub fn cancel_prompts(&self) {
+        self.prompts.send_modify(|generation| *generation += 1);
     }
 
     /// Detect if there's a conflict between local and remote.
@@ -96,22 +461,7 @@ impl ConflictResolver {
         remote_etag: Option<&str>,
         last_known_remote_etag: Option<&str>,
     ) -> bool {
-        // No conflict if etags match
-        if local_etag == remote_etag {
-            return false;
-        }
-
-        // If we have a baseline, conflict only when the remote diverged from
-        // what we last knew *and* the local side also diverged.
-        if let Some(last_known) = last_known_remote_etag {
-            let remote_changed = remote_etag != Some(last_known);
-            let local_changed = local_etag != Some(last_known);
-            return remote_changed && local_changed;
-        }
-
-        // Without a baseline we cannot tell who changed; treat as conflict
-        // only when both sides have an etag and they differ.
-        local_etag.is_some() && remote_etag.is_some() && local_etag != remote_etag
+        planner::both_changed(local_etag, remote_etag, last_known_remote_etag)
     }
 
     /// Generate a conflict-renamed path
@@ -129,7 +479,7 @@ impl ConflictResolver {
         // emit fractional seconds on its own. Build the microseconds suffix
         // explicitly so two conflicts in the same wall-clock second still
         // differ in the timestamp portion (audit M-6).
-        let now = Utc::now();
+        let now = self.clock.now();
         let timestamp = format!(
             "{}_{:06}",
             now.format("%Y%m%d_%H%M%S"),
@@ -150,34 +500,144 @@ impl ConflictResolver {
             None => (String::new(), original_str.as_str()),
         };
 
-        let renamed_file = if let Some(dot_pos) = file_name.rfind('.').filter(|&pos| pos > 0) {
-            let (name, ext) = file_name.split_at(dot_pos);
-            format!("{name}_conflict_{timestamp}_{rand_suffix}{ext}")
-        } else {
-            format!("{file_name}_conflict_{timestamp}_{rand_suffix}")
-        };
+        let (name, ext) = split_extension(file_name);
+        let renamed_file = format!("{name}_conflict_{timestamp}_{rand_suffix}{ext}");
         let new_path = format!("{parent}{renamed_file}");
 
         VaultPath::parse(&new_path)
     }
 
+    /// Pick a name for a conflict copy of `original` that is not taken on
+    /// `provider`.
+    ///
+    /// With a [`ConflictNaming`] the counter goes up until a free name is
+    /// found; otherwise `generate_conflict_path` is used as is.
+    ///
+    /// # Errors
+    /// - `Conflict` if no free name is found
+    pub async fn conflict_path<P: StorageProvider + ?Sized>(
+        &self,
+        original: &VaultPath,
+        provider: &P,
+    ) -> Result<VaultPath> {
+        let Some(naming) = &self.naming else {
+            return self.generate_conflict_path(original);
+        };
+        let now = self.clock.now();
+        for counter in 1..=MAX_CONFLICT_COUNTER {
+            let candidate = naming.render(original, now, counter)?;
+            if !provider.exists(&candidate).await? {
+                return Ok(candidate);
+            }
+        }
+        Err(Error::Conflict(format!(
+            "No free conflict name for {}",
+            original.display_lossy()
+        )))
+    }
+
+    /// Temporary sibling path used while uploading a conflict copy.
+    fn upload_temp_path(target: &VaultPath) -> Result<VaultPath> {
+        let name = target
+            .name()
+            .ok_or_else(|| Error::InvalidInput("Conflict path has no file name".to_string()))?;
+        let temp_name = format!(".{}.upload-{}", name, uuid::Uuid::new_v4());
+        target
+            .parent()
+            .unwrap_or_else(VaultPath::root)
+            .join(&temp_name)
+    }
+
+    /// Move an uploaded temp file to `to`, returning how it was copied if
+    /// the backend has no native rename.
+    async fn move_into_place<P: StorageProvider + ?Sized>(
+        provider: &P,
+        from: &VaultPath,
+        to: &VaultPath,
+    ) -> Result<Option<CopyMechanism>> {
+        if provider.capabilities().native_rename {
+            provider.rename(from, to).await?;
+            return Ok(None);
+        }
+        let (_, mechanism) = copy_object(provider, from, to).await?;
+        provider.delete(from).await?;
+        Ok(Some(mechanism))
+    }
+
+    /// Upload `data` under a fresh conflict name for `path`.
+    ///
+    /// The data goes to a temporary sibling name first and is then moved
+    /// into place, so a half-written conflict copy never appears under its
+    /// final name. Providers without a native rename copy it (server-side
+    /// where possible) and delete the temp.
+    async fn upload_conflict_copy<P: StorageProvider + ?Sized>(
+        &self,
+        path: &VaultPath,
+        data: Vec<u8>,
+        provider: &P,
+    ) -> Result<(VaultPath, Option<CopyMechanism>)> {
+        let renamed_path = self.conflict_path(path, provider).await?;
+        let upload_path = Self::upload_temp_path(&renamed_path)?;
+        provider.upload(&upload_path, data).await?;
+        let copy = match Self::move_into_place(provider, &upload_path, &renamed_path).await {
+            Ok(copy) => copy,
+            Err(e) => {
+                // Best-effort cleanup; the move error is what matters.
+                let _ = provider.delete(&upload_path).await;
+                return Err(e);
+            }
+        };
+        debug!(?copy, "Conflict copy moved into place");
+        Ok((renamed_path, copy))
+    }
+
     /// Get the default resolution strategy.
     pub fn default_strategy(&self) -> ConflictStrategy {
         self.default_strategy
     }
 
+    /// Whether both sides of `conflict` are empty files with equal content.
+    ///
+    /// Differing etags on two zero-byte files (lock files, `.gitkeep`) are
+    /// not a real conflict. Sizes alone are trusted only to decide whether
+    /// to look closer; the remote bytes are fetched and compared by hash.
+    async fn same_empty_content<P: StorageProvider + ?Sized>(
+        conflict: &ConflictInfo,
+        local: &LocalVersion,
+        provider: &P,
+    ) -> Result<bool> {
+        let local_data = local.as_bytes();
+        if !local_data.is_empty() || conflict.remote_size != Some(0) {
+            return Ok(false);
+        }
+        let remote_data = provider.download(&conflict.path).await?;
+        Ok(content_hash(&remote_data) == content_hash(local_data))
+    }
+
     /// Resolve a conflict using the specified strategy.
+    ///
+    /// `local` is uploaded as it is, so it must be the sealed blob and
+    /// never plaintext. Two empty versions with the same content resolve to
+    /// the remote one under every strategy, without uploading or keeping a
+    /// copy.
     pub async fn resolve<P: StorageProvider + ?Sized>(
         &self,
         conflict: &ConflictInfo,
-        local_data: Vec<u8>,
+        local: LocalVersion,
         provider: &P,
         strategy: ConflictStrategy,
     ) -> Result<ResolutionResult> {
+        if Self::same_empty_content(conflict, &local, provider).await? {
+            debug!("Both versions are empty; no conflict to resolve");
+            return Ok(ResolutionResult::UsedRemote {
+                new_local_etag: conflict.remote_etag.clone(),
+            });
+        }
+
         match strategy {
             ConflictStrategy::PreferLocal => {
                 // Upload local version, overwriting remote
-                let metadata = provider.upload(&conflict.path, local_data).await?;
+                let metadata = provider.upload(&conflict.path, local.into_bytes()).await?;
                 Ok(ResolutionResult::UsedLocal {
                     new_remote_etag: metadata.etag,
                 })
@@ -189,23 +649,104 @@ impl ConflictResolver {
                 })
             }
             ConflictStrategy::KeepBoth => {
-                // Rename local and upload as new file
-                let renamed_path = self.generate_conflict_path(&conflict.path)?;
-                let _metadata = provider.upload(&renamed_path, local_data).await?;
+                // Upload local under a temporary sibling name, then move it
+                // into place so a half-written conflict copy never appears
+                // under its final name. Providers without a native rename
+                // copy it (server-side where possible) and delete the temp.
+                let (renamed_path, copy) = self
+                    .upload_conflict_copy(&conflict.path, local.into_bytes(), provider)
+                    .await?;
 
                 // The remote version stays at original path
                 Ok(ResolutionResult::KeptBoth {
                     original_path: conflict.path.clone(),
                     renamed_path,
                     remote_etag: conflict.remote_etag.clone(),
+                    copy,
                 })
             }
-            ConflictStrategy::Manual => {
+            ConflictStrategy::Manual | ConflictStrategy::Interactive => {
                 // User must resolve
                 Ok(ResolutionResult::Pending)
             }
         }
     }
+
+    /// Resolve a conflict between a delete on the `deleted` side and an
+    /// edit on the other.
+    ///
+    /// `local` is the sealed local edit, needed when the remote deleted the
+    /// file. Strategies pick a side as usual: the edit wins by keeping the
+    /// file, the delete wins by removing it. `KeepBoth` removes the file at
+    /// `path` but keeps the edited version under a conflict name. Removals
+    /// go through `trash` when set.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if the local edit is needed but missing
+    pub async fn resolve_delete<P: StorageProvider + ?Sized>(
+        &self,
+        path: &VaultPath,
+        deleted: Side,
+        local: Option<LocalVersion>,
+        provider: &P,
+        strategy: ConflictStrategy,
+        trash: Option<&TrashConfig>,
+    ) -> Result<ResolutionResult> {
+        let local_edit = || {
+            local.map(LocalVersion::into_bytes).ok_or_else(|| {
+                Error::InvalidInput(format!(
+                    "Local version of {} is required",
+                    path.display_lossy()
+                ))
+            })
+        };
+        let deleted_result = |restored: Option<(VaultPath, Option<String>)>| {
+            let (restored_path, restored_etag) = restored.unzip();
+            ResolutionResult::Deleted {
+                path: path.clone(),
+                restored_path,
+                restored_etag: restored_etag.flatten(),
+            }
+        };
+
+        match (deleted, strategy) {
+            (_, ConflictStrategy::Manual | ConflictStrategy::Interactive) => {
+                Ok(ResolutionResult::Pending)
+            }
+            (Side::Remote, ConflictStrategy::PreferLocal) => {
+                // Re-upload the edit over the delete
+                let metadata = provider.upload(path, local_edit()?).await?;
+                Ok(ResolutionResult::UsedLocal {
+                    new_remote_etag: metadata.etag,
+                })
+            }
+            (Side::Remote, ConflictStrategy::PreferRemote) => Ok(deleted_result(None)),
+            (Side::Remote, ConflictStrategy::KeepBoth) => {
+                let (renamed_path, _) = self
+                    .upload_conflict_copy(path, local_edit()?, provider)
+                    .await?;
+                let etag = provider.metadata(&renamed_path).await?.etag;
+                Ok(deleted_result(Some((renamed_path, etag))))
+            }
+            (Side::Local, ConflictStrategy::PreferLocal) => {
+                remove_remote(provider, path, trash).await?;
+                Ok(deleted_result(None))
+            }
+            (Side::Local, ConflictStrategy::PreferRemote) => {
+                let metadata = provider.metadata(path).await?;
+                Ok(ResolutionResult::UsedRemote {
+                    new_local_etag: metadata.etag,
+                })
+            }
+            (Side::Local, ConflictStrategy::KeepBoth) => {
+                // The remote edit moves aside; nothing is lost, so it does
+                // not go through the trash.
+                let renamed_path = self.conflict_path(path, provider).await?;
+                let metadata = provider.rename(path, &renamed_path).await?;
+                Ok(deleted_result(Some((renamed_path, metadata.etag))))
+            }
+        }
+    }
 }
 
 impl Default for ConflictResolver {
@@ -217,6 +758,323 @@ impl Default for ConflictResolver {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axiomvault_common::MockClock;
+    use axiomvault_storage::test_util::NoRenameProvider;
+    use axiomvault_storage::MemoryProvider;
+    use std::sync::Arc;
+
+    fn conflict_for(path: &VaultPath, remote: &Metadata) -> ConflictInfo {
+        ConflictInfo {
+            path: path.clone(),
+            local_etag: Some("local".to_string()),
+            local_modified: Utc::now(),
+            local_size: None,
+            remote_etag: remote.etag.clone(),
+            remote_modified: remote.modified,
+            remote_size: remote.size,
+            detected_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_both_uses_rename_fallback_without_native_rename() {
+        let provider = NoRenameProvider::new();
+        assert!(!provider.capabilities().native_rename);
+
+        let path = VaultPath::parse("/notes.txt").unwrap();
+        let remote = provider.upload(&path, b"remote".to_vec()).await.unwrap();
+        let conflict = conflict_for(&path, &remote);
+
+        let resolver = ConflictResolver::default();
+        let result = resolver
+            .resolve(
+                &conflict,
+                LocalVersion::staged(b"local".to_vec()),
+                &provider,
+                ConflictStrategy::KeepBoth,
+            )
+            .await
+            .unwrap();
+
+        let ResolutionResult::KeptBoth {
+            renamed_path, copy, ..
+        } = result
+        else {
+            panic!("expected KeptBoth, got {:?}", result);
+        };
+        assert_eq!(copy, Some(CopyMechanism::Streamed));
+        assert_eq!(provider.downloaded(), b"local".len());
+        assert_eq!(provider.download(&path).await.unwrap(), b"remote");
+        assert_eq!(provider.download(&renamed_path).await.unwrap(), b"local");
+
+        // Only the original and the conflict copy remain; no temp upload.
+        let listing = provider.list(&VaultPath::root()).await.unwrap();
+        assert_eq!(listing.len(), 2, "unexpected entries: {:?}", listing);
+    }
+
+    #[tokio::test]
+    async fn test_empty_versions_do_not_conflict() {
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/.gitkeep").unwrap();
+        let remote = provider.upload(&path, Vec::new()).await.unwrap();
+        let conflict = conflict_for(&path, &remote);
+        let resolver = ConflictResolver::default();
+
+        for strategy in [
+            ConflictStrategy::KeepBoth,
+            ConflictStrategy::PreferLocal,
+            ConflictStrategy::Manual,
+        ] {
+            let result = resolver
+                .resolve(
+                    &conflict,
+                    LocalVersion::staged(Vec::new()),
+                    &provider,
+                    strategy,
+                )
+                .await
+                .unwrap();
+            assert!(
+                matches!(
+                    &result,
+                    ResolutionResult::UsedRemote { new_local_etag } if *new_local_etag == remote.etag
+                ),
+                "unexpected resolution: {:?}",
+                result
+            );
+        }
+        assert_eq!(provider.list(&VaultPath::root()).await.unwrap().len(), 1);
+
+        // An empty remote against local content is still a conflict.
+        let result = resolver
+            .resolve(
+                &conflict,
+                LocalVersion::staged(b"x".to_vec()),
+                &provider,
+                ConflictStrategy::KeepBoth,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(result, ResolutionResult::KeptBoth { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_keep_both_prefers_server_side_copy() {
+        let provider = NoRenameProvider::new().with_server_side_copy();
+        let path = VaultPath::parse("/notes.txt").unwrap();
+        let remote = provider.upload(&path, b"remote".to_vec()).await.unwrap();
+
+        let result = ConflictResolver::default()
+            .resolve(
+                &conflict_for(&path, &remote),
+                LocalVersion::staged(b"local".to_vec()),
+                &provider,
+                ConflictStrategy::KeepBoth,
+            )
+            .await
+            .unwrap();
+
+        let ResolutionResult::KeptBoth {
+            renamed_path, copy, ..
+        } = result
+        else {
+            panic!("expected KeptBoth, got {:?}", result);
+        };
+        assert_eq!(copy, Some(CopyMechanism::ServerSide));
+        assert_eq!(provider.downloaded(), 0);
+        assert_eq!(
+            provider.inner().download(&renamed_path).await.unwrap(),
+            b"local"
+        );
+        assert_eq!(provider.list(&VaultPath::root()).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_keep_both_renames_natively_when_supported() {
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/notes.txt").unwrap();
+        let remote = provider.upload(&path, b"remote".to_vec()).await.unwrap();
+
+        let result = ConflictResolver::default()
+            .resolve(
+                &conflict_for(&path, &remote),
+                LocalVersion::staged(b"local".to_vec()),
+                &provider,
+                ConflictStrategy::KeepBoth,
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            result,
+            ResolutionResult::KeptBoth { copy: None, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_prompts_stops_waiting_for_a_decision() {
+        let details = ConflictDetails {
+            path: VaultPath::parse("/doc.txt").unwrap(),
+            kind: ConflictKind::Edit,
+            local: VersionInfo {
+                etag: None,
+                modified: None,
+                size: None,
+            },
+            remote: VersionInfo {
+                etag: None,
+                modified: None,
+                size: None,
+            },
+            preview: None,
+        };
+        assert_eq!(
+            ConflictResolver::default()
+                .ask(details.clone())
+                .await
+                .unwrap(),
+            None
+        );
+
+        let resolver = Arc::new(
+            ConflictResolver::new(ConflictStrategy::Interactive)
+                .with_handler(Arc::new(|_| Box::pin(futures::future::pending()))),
+        );
+        let asking = tokio::spawn({
+            let resolver = resolver.clone();
+            async move { resolver.ask(details).await }
+        });
+        tokio::task::yield_now().await;
+        resolver.cancel_prompts();
+        assert!(matches!(asking.await.unwrap(), Err(Error::Cancelled(_))));
+    }
+
+    #[test]
+    fn test_conflict_naming_renders_template() {
+        let naming = ConflictNaming::new("laptop");
+        let at = DateTime::parse_from_rfc3339("2024-01-15T12:34:56Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let render = |path: &str, counter| {
+            naming
+                .render(&VaultPath::parse(path).unwrap(), at, counter)
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            render("/docs/report.tar.gz", 1),
+            "/docs/report.tar (conflicted copy from laptop 2024-01-15 12-34-56).gz"
+        );
+        assert_eq!(
+            render("/docs/report.pdf", 2),
+            "/docs/report (conflicted copy from laptop 2024-01-15 12-34-56) (2).pdf"
+        );
+        assert_eq!(
+            render("/.env", 1),
+            "/.env (conflicted copy from laptop 2024-01-15 12-34-56)"
+        );
+
+        let nested = ConflictNaming {
+            template: "conflicts/{name}".to_string(),
+            device: "laptop".to_string(),
+        };
+        assert!(matches!(
+            nested.render(&VaultPath::parse("/a.txt").unwrap(), at, 1),
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_conflict_path_uses_injected_clock() {
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/docs/report.pdf").unwrap();
+        let clock = MockClock::new(
+            DateTime::parse_from_rfc3339("2024-01-15T12:34:56Z")
+                .unwrap()
+                .with_timezone(&Utc),
+        );
+        let resolver = ConflictResolver::new(ConflictStrategy::KeepBoth)
+            .with_naming(ConflictNaming::new("laptop"))
+            .with_clock(clock.clone());
+
+        let first = resolver.conflict_path(&path, &provider).await.unwrap();
+        assert_eq!(
+            first.to_string(),
+            "/docs/report (conflicted copy from laptop 2024-01-15 12-34-56).pdf"
+        );
+
+        clock.advance(chrono::Duration::days(1));
+        let second = resolver.conflict_path(&path, &provider).await.unwrap();
+        assert_eq!(
+            second.to_string(),
+            "/docs/report (conflicted copy from laptop 2024-01-16 12-34-56).pdf"
+        );
+
+        let random = ConflictResolver::new(ConflictStrategy::KeepBoth)
+            .with_clock(clock)
+            .generate_conflict_path(&path)
+            .unwrap();
+        assert!(random
+            .to_string()
+            .starts_with("/docs/report_conflict_20240116_123456_000000_"));
+    }
+
+    #[tokio::test]
+    async fn test_successive_conflicts_get_distinct_names() {
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/report.pdf").unwrap();
+        let resolver = ConflictResolver::new(ConflictStrategy::KeepBoth)
+            .with_naming(ConflictNaming::new("laptop"));
+
+        let mut copies = Vec::new();
+        for local in [b"first".as_slice(), b"second".as_slice()] {
+            let remote = provider.upload(&path, b"remote".to_vec()).await.unwrap();
+            let result = resolver
+                .resolve(
+                    &conflict_for(&path, &remote),
+                    LocalVersion::staged(local.to_vec()),
+                    &provider,
+                    ConflictStrategy::KeepBoth,
+                )
+                .await
+                .unwrap();
+            let ResolutionResult::KeptBoth { renamed_path, .. } = result else {
+                panic!("expected KeptBoth, got {:?}", result);
+            };
+            let name = renamed_path.to_string();
+            assert!(name.starts_with("/report (conflicted copy from laptop "));
+            assert!(name.ends_with(".pdf"));
+            copies.push((renamed_path, local));
+        }
+
+        assert_ne!(copies[0].0, copies[1].0);
+        for (renamed_path, local) in &copies {
+            assert_eq!(provider.download(renamed_path).await.unwrap(), *local);
+        }
+        assert_eq!(provider.list(&VaultPath::root()).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_conflict_path_skips_taken_names() {
+        let provider = MemoryProvider::new();
+        let path = VaultPath::parse("/notes.txt").unwrap();
+        let naming = ConflictNaming {
+            template: "{name} (copy from {device})".to_string(),
+            device: "laptop".to_string(),
+        };
+        let resolver = ConflictResolver::default().with_naming(naming);
+
+        for expected in [
+            "/notes (copy from laptop).txt",
+            "/notes (copy from laptop) (2).txt",
+            "/notes (copy from laptop) (3).txt",
+        ] {
+            let candidate = resolver.conflict_path(&path, &provider).await.unwrap();
+            assert_eq!(candidate.to_string(), expected);
+            provider.upload(&candidate, Vec::new()).await.unwrap();
+        }
+    }
 
     #[test]
     fn test_conflict_detection_no_conflict() {
@@ -418,4 +1276,20 @@ mod tests {
         }
         assert!(chars.next().is_none());
     }
+
+    #[test]
+    fn test_diff_preview_shows_changed_lines() {
+        let preview = DiffPreview::between(b"a\nb\nc\nd\n", b"a\nB\nx\nd\n").expect("text preview");
+        assert_eq!(preview.first_line, 2);
+        assert_eq!(preview.lines, ["-b", "-c", "+B", "+x"]);
+        assert!(!preview.truncated);
+
+        let long: String = (0..40).map(|i| format!("{}\n", i)).collect();
+        let preview = DiffPreview::between(long.as_bytes(), b"").unwrap();
+        assert_eq!(preview.lines.len(), PREVIEW_LINES);
+        assert!(preview.truncated);
+
+        assert!(DiffPreview::between(b"text", b"\0\x01binary").is_none());
+        assert!(DiffPreview::between(&[0xff, 0xfe], b"text").is_none());
+    }
 }
@@ -1,18 +1,76 @@
 //! Core sync engine that orchestrates all sync operations.
 
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
-use tokio::sync::{Mutex, RwLock};
-use tracing::{debug, error, info, warn};
+use tokio::sync::{broadcast, watch, Mutex, RwLock, RwLockReadGuard};
+use tracing::{debug, error, field, info, info_span, instrument, warn, Instrument, Span};
+
+use axiomvault_common::telemetry::{metrics, path_field};
+use axiomvault_common::{Error, Result, SharedClock, SystemClock, VaultPath};
+use axiomvault_storage::{Metadata, ResumableUpload, StorageProvider, UploadProgress};
+
+use crate::conflict::{
+    ConflictDetails, ConflictHandler, ConflictInfo, ConflictKind, ConflictNaming, ConflictResolver,
+    ConflictStrategy, LocalVersion, ResolutionResult, Side, VersionInfo,
+};
+use crate::environment::{DesktopProbe, EnvironmentProbe};
+use crate::events::{SyncEvent, SyncEventReceiver};
+use crate::planner::{self, PlanInput, PlannedAction, RemoteFile};
+use crate::quota::{self, QuotaShortfall};
+use crate::retry::{RetryConfig, RetryExecutor};
+use crate::scheduler::{
+    SyncConstraints, SyncMode, SyncRequest, SyncResult, SyncScheduler, SyncSchedulerHandle,
+};
+use crate::staging::{ChangeType, StagedBlob, StagedChange, StagingArea, StagingKey, SyncLease};
+use crate::state::{StatusSnapshot, SyncEntry, SyncState, SyncStatus};
+use crate::trash::{remove_remote, TrashConfig, TrashedFile};
+
+/// Remote metadata by path, and the paths the remote reported as gone.
+type RemoteSnapshot = (HashMap<String, Metadata>, HashSet<String>);
+
+/// Capacity of the engine's event channel.
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Tally of one upload pass.
+#[derive(Debug, Default)]
+struct UploadOutcome {
+    synced: usize,
+    failed: usize,
+    conflicts: usize,
+    quota: QuotaTally,
+}
 
-use axiomvault_common::{Error, Result, VaultPath};
-use axiomvault_storage::StorageProvider;
+/// Quota findings of a sync and the uploads paused because of them.
+#[derive(Debug, Default)]
+struct QuotaTally {
+    /// The provider reported the account out of space.
+    exhausted: bool,
+    paused: usize,
+    paused_bytes: u64,
+    shortfall: Option<QuotaShortfall>,
+}
 
-use crate::conflict::{ConflictInfo, ConflictResolver, ConflictStrategy, ResolutionResult};
-use crate::retry::{RetryConfig, RetryExecutor};
-use crate::scheduler::{SyncMode, SyncRequest, SyncResult, SyncScheduler, SyncSchedulerHandle};
-use crate::staging::{ChangeType, StagingArea};
-use crate::state::{SyncEntry, SyncState, SyncStatus};
+impl QuotaTally {
+    /// Leave an upload of `bytes` staged for a later sync.
+    fn pause(&mut self, bytes: u64) {
+        self.paused += 1;
+        self.paused_bytes += bytes;
+    }
+}
+
+/// How [`SyncEngine::shutdown`] ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownOutcome {
+    /// No sync is running any more.
+    Drained,
+    /// A sync was still running at the deadline. Chunked uploads are
+    /// journaled up to the last acknowledged chunk and resume on the next
+    /// start.
+    TimedOut,
+}
 
 /// Configuration for the sync engine.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -27,6 +85,28 @@ pub struct SyncConfig {
     pub batch_size: usize,
     /// Whether to automatically resolve conflicts.
     pub auto_resolve_conflicts: bool,
+    /// Paths whose subtrees are left out of sync. Staged changes below them
+    /// stay staged and remote changes are not checked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<VaultPath>,
+    /// Move files deleted on the remote into a trash instead of removing
+    /// them. `None` deletes permanently.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash: Option<TrashConfig>,
+    /// How `KeepBoth` names conflict copies. `None` appends a timestamp
+    /// and random suffix.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conflict_naming: Option<ConflictNaming>,
+    /// Conditions periodic syncs wait for.
+    #[serde(default)]
+    pub constraints: SyncConstraints,
+}
+
+impl SyncConfig {
+    /// Whether `path` is at or below an excluded path.
+    pub fn is_excluded(&self, path: &VaultPath) -> bool {
+        self.exclude.iter().any(|prefix| path.starts_with(prefix))
+    }
 }
 
 impl Default for SyncConfig {
@@ -37,6 +117,10 @@ impl Default for SyncConfig {
             sync_mode: SyncMode::Manual,
             batch_size: 10,
             auto_resolve_conflicts: false,
+            exclude: Vec::new(),
+            trash: None,
+            conflict_naming: None,
+            constraints: SyncConstraints::default(),
         }
     }
 }
@@ -59,6 +143,27 @@ pub struct SyncEngine<P: StorageProvider + ?Sized> {
     config: SyncConfig,
     /// Guard to prevent concurrent sync operations.
     sync_lock: Arc<Mutex<()>>,
+    /// Publishes [`SyncEvent`]s to subscribers.
+    events: broadcast::Sender<SyncEvent>,
+    /// Latest entry counts by status, for [`status_stream`](Self::status_stream).
+    status: watch::Sender<StatusSnapshot>,
+    /// Set by [`shutdown`](Self::shutdown); running syncs stop at the next
+    /// file or chunk and new ones are refused.
+    stopping: AtomicBool,
+    /// Held shared by every running sync, exclusively by a draining
+    /// shutdown.
+    activity: RwLock<()>,
+    /// Lease on the staging area, shared by this engine's running syncs
+    /// and released when the last of them ends.
+    lease: Mutex<Weak<SyncLease>>,
+    /// Time used for trash retention and conflict copy names.
+    clock: SharedClock,
+}
+
+/// Registration of a running sync with the drain and the staging lease.
+struct ActiveSync<'a> {
+    _activity: RwLockReadGuard<'a, ()>,
+    _lease: Arc<SyncLease>,
 }
 
 impl<P: StorageProvider + 'static> SyncEngine<P> {
@@ -66,40 +171,142 @@ impl<P: StorageProvider + 'static> SyncEngine<P> {
     pub async fn new(
         provider: P,
         staging_dir: impl AsRef<std::path::Path>,
+        staging_key: StagingKey,
         config: SyncConfig,
     ) -> Result<Self> {
-        Self::from_arc(Arc::new(provider), staging_dir, config).await
+        Self::from_arc(Arc::new(provider), staging_dir, staging_key, config).await
     }
 }
 
 impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
     /// Create a new sync engine from an Arc-wrapped provider.
+    ///
+    /// `staging_key` binds the staging area to the vault being synced.
     pub async fn from_arc(
         provider: Arc<P>,
         staging_dir: impl AsRef<std::path::Path>,
+        staging_key: StagingKey,
         config: SyncConfig,
     ) -> Result<Self> {
-        let staging = StagingArea::new(staging_dir).await?;
+        let staging = StagingArea::new(staging_dir, staging_key).await?;
         let retry_config = RetryConfig::new(config.max_retries);
-        let conflict_resolver = ConflictResolver::new(config.conflict_strategy);
+        let mut state = staging.shared_state().await?.unwrap_or_default();
+        state.sync_in_progress = false;
+        let clock = SystemClock::shared();
+        let status = watch::channel(state.count_by_status()).0;
 
         Ok(Self {
             provider,
-            state: Arc::new(RwLock::new(SyncState::new())),
+            state: Arc::new(RwLock::new(state)),
             staging: Arc::new(RwLock::new(staging)),
-            conflict_resolver: Arc::new(conflict_resolver),
+            conflict_resolver: Arc::new(Self::conflict_resolver_for(&config, clock.clone())),
             retry_executor: Arc::new(RetryExecutor::new(retry_config)),
             scheduler: None,
             config,
             sync_lock: Arc::new(Mutex::new(())),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            status,
+            stopping: AtomicBool::new(false),
+            activity: RwLock::new(()),
+            lease: Mutex::new(Weak::new()),
+            clock,
         })
     }
 
+    /// Conflict resolver configured by `config`, reading time from `clock`.
+    fn conflict_resolver_for(config: &SyncConfig, clock: SharedClock) -> ConflictResolver {
+        let resolver = ConflictResolver::new(config.conflict_strategy).with_clock(clock);
+        match &config.conflict_naming {
+            Some(naming) => resolver.with_naming(naming.clone()),
+            None => resolver,
+        }
+    }
+
+    /// Decide conflicts with `handler` under
+    /// [`ConflictStrategy::Interactive`].
+    ///
+    /// Each conflict is marked conflicted before the handler is asked, so a
+    /// skipped or cancelled prompt leaves it for [`resolve_conflict`].
+    ///
+    /// [`resolve_conflict`]: Self::resolve_conflict
+    pub fn set_conflict_handler(&mut self, handler: ConflictHandler) {
+        let resolver =
+            Self::conflict_resolver_for(&self.config, self.clock.clone()).with_handler(handler);
+        self.conflict_resolver = Arc::new(resolver);
+    }
+
+    /// Read the time for trash retention and conflict copy names from
+    /// `clock` instead of the system clock.
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        let mut resolver = Self::conflict_resolver_for(&self.config, clock.clone());
+        if let Some(handler) = self.conflict_resolver.handler() {
+            resolver = resolver.with_handler(handler);
+        }
+        self.conflict_resolver = Arc::new(resolver);
+        self.clock = clock;
+    }
+
+    /// Cancel conflict prompts waiting on the handler.
+    ///
+    /// The sync asking them records a failure for each and moves on.
+    pub fn cancel_conflict_prompts(&self) {
+        self.conflict_resolver.cancel_prompts();
+    }
+
+    /// Subscribe to events published while syncing.
+    pub fn subscribe(&self) -> SyncEventReceiver {
+        self.events.subscribe()
+    }
+
+    /// Publish an event; having no subscribers is not an error.
+    fn emit(&self, event: SyncEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Number of tracked entries in each [`SyncStatus`].
+    pub async fn status_snapshot(&self) -> StatusSnapshot {
+        self.state.read().await.count_by_status()
+    }
+
+    /// Counts by status, updated as the engine stages and syncs files.
+    ///
+    /// The receiver is only woken when a count changes. Changes made
+    /// directly through [`state`](Self::state) show up with the engine's
+    /// next update.
+    pub fn status_stream(&self) -> watch::Receiver<StatusSnapshot> {
+        self.status.subscribe()
+    }
+
+    /// Publish the counts of `state` if they changed.
+    fn publish_status(&self, state: &SyncState) {
+        let counts = state.count_by_status();
+        self.status.send_if_modified(|current| {
+            if *current == counts {
+                return false;
+            }
+            *current = counts;
+            true
+        });
+    }
+
     /// Initialize the scheduler and return a handle for running it.
+    ///
+    /// Periodic syncs check the configured constraints against OS hints
+    /// read by [`DesktopProbe`].
     pub fn init_scheduler(&mut self) -> SyncSchedulerHandle {
+        self.init_scheduler_with_probe(Arc::new(DesktopProbe))
+    }
+
+    /// Initialize the scheduler, checking sync constraints with `probe`.
+    pub fn init_scheduler_with_probe(
+        &mut self,
+        probe: Arc<dyn EnvironmentProbe>,
+    ) -> SyncSchedulerHandle {
         let (scheduler, handle) = SyncScheduler::new(self.config.sync_mode.clone());
         self.scheduler = Some(scheduler);
         handle
+            .with_constraints(self.config.constraints.clone(), probe)
+            .with_state(self.state.clone())
     }
 
     /// Get the scheduler for requesting syncs.
@@ -117,15 +324,106 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
         self.staging.clone()
     }
 
+    /// Stop syncing, giving running syncs until `deadline` to wind down.
+    ///
+    /// New syncs are refused from now on. A running sync finishes the file
+    /// it is transferring, or for a chunked upload the chunk in flight, and
+    /// leaves everything else staged. The staging registry, with the
+    /// resumable-upload journals, is written out before returning, so a
+    /// later engine on the same staging directory picks up where this one
+    /// stopped. Calling it again waits for the drain again.
+    ///
+    /// # Errors
+    /// - Failure writing the staging registry
+    pub async fn shutdown(&self, deadline: std::time::Duration) -> Result<ShutdownOutcome> {
+        self.stopping.store(true, Ordering::SeqCst);
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.shutdown().await;
+        }
+        self.cancel_conflict_prompts();
+
+        let outcome = match tokio::time::timeout(deadline, self.activity.write()).await {
+            Ok(_drained) => ShutdownOutcome::Drained,
+            Err(_) => {
+                warn!(
+                    "Sync still running after {:?}; leaving it journaled",
+                    deadline
+                );
+                ShutdownOutcome::TimedOut
+            }
+        };
+        self.share_state().await;
+        self.staging.write().await.checkpoint().await?;
+        info!("Sync engine shut down: {:?}", outcome);
+        Ok(outcome)
+    }
+
+    /// Whether [`shutdown`](Self::shutdown) was called.
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Register a sync with the drain, unless the engine is shutting down
+    /// or another process is syncing the same staging area.
+    async fn begin_sync(&self) -> Result<ActiveSync<'_>> {
+        let activity = self.activity.read().await;
+        if self.is_stopping() {
+            return Err(Error::Cancelled("Sync engine is shutting down".to_string()));
+        }
+        let lease = self.sync_lease().await?;
+        self.share_state().await;
+        Ok(ActiveSync {
+            _activity: activity,
+            _lease: lease,
+        })
+    }
+
+    /// Exchange work with other processes using the staging directory:
+    /// pick up the changes they staged, and merge sync states both ways.
+    ///
+    /// Failures are logged; the engine carries on with what it has.
+    async fn share_state(&self) {
+        let snapshot = self.state.read().await.clone();
+        let shared = {
+            let mut staging = self.staging.write().await;
+            if let Err(e) = staging.refresh().await {
+                warn!("Failed to reload the staging registry: {}", e);
+            }
+            staging.share_state(snapshot).await
+        };
+        let mut state = self.state.write().await;
+        match shared {
+            Ok(shared) => state.merge(shared),
+            Err(e) => warn!("Failed to share sync state: {}", e),
+        }
+        self.publish_status(&state);
+    }
+
+    /// The staging lease, taken if no running sync of this engine holds it.
+    ///
+    /// # Errors
+    /// - `SyncInProgress` if another process holds it
+    async fn sync_lease(&self) -> Result<Arc<SyncLease>> {
+        let mut held = self.lease.lock().await;
+        if let Some(lease) = held.upgrade() {
+            return Ok(lease);
+        }
+        let lease = Arc::new(self.staging.read().await.try_sync_lease()?);
+        *held = Arc::downgrade(&lease);
+        Ok(lease)
+    }
+
     /// Stage a local file change for sync.
+    ///
+    /// `blob` is produced by the vault, so only ciphertext is staged.
     pub async fn stage_change(
         &self,
         path: &VaultPath,
-        data: Vec<u8>,
+        blob: StagedBlob,
         change_type: ChangeType,
     ) -> Result<String> {
         let mut staging = self.staging.write().await;
-        let change_id = staging.stage_upload(path, data, change_type).await?;
+        let change_id = staging.stage_upload(path, blob, change_type).await?;
 
         // Update sync state
         let mut state = self.state.write().await;
@@ -136,6 +434,9 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
         } else {
             state.insert(SyncEntry::new_local(path.to_string(), etag));
         }
+        drop(state);
+        drop(staging);
+        self.share_state().await;
 
         Ok(change_id)
     }
@@ -148,10 +449,15 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
         // Update sync state
         let mut state = self.state.write().await;
         if let Some(entry) = state.get_mut(path) {
-            entry.mark_local_modified(None);
+            entry.mark_local_deleted();
         } else {
-            state.insert(SyncEntry::new_local(path.to_string(), None));
+            let mut entry = SyncEntry::new_local(path.to_string(), None);
+            entry.mark_local_deleted();
+            state.insert(entry);
         }
+        drop(state);
+        drop(staging);
+        self.share_state().await;
 
         Ok(change_id)
     }
@@ -159,7 +465,10 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
     /// Perform a full sync of all staged changes and fetch remote updates.
     ///
     /// Uses a mutex to prevent concurrent sync operations from racing.
+    #[instrument(name = "sync", skip_all, fields(mode = "full"))]
     pub async fn sync_full(&self) -> Result<SyncResult> {
+        metrics::operation("sync_full");
+        let _active = self.begin_sync().await?;
         // Acquire sync lock — a second concurrent call blocks here instead of racing
         let _guard = self.sync_lock.lock().await;
 
@@ -176,32 +485,57 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
             state.sync_in_progress = true;
         }
 
-        // 1. Upload local changes
-        let upload_result = self.upload_staged_changes().await;
-        files_synced += upload_result.0;
-        files_failed += upload_result.1;
-        conflicts_found += upload_result.2;
+        if let Some(trash) = &self.config.trash {
+            match trash
+                .purge_expired(self.provider.as_ref(), self.clock.now())
+                .await
+            {
+                Ok(0) => {}
+                Ok(purged) => debug!("Purged {} expired files from trash", purged),
+                Err(e) => warn!("Failed to purge trash: {}", e),
+            }
+        }
 
-        // 2. Check for remote changes
-        let remote_result = self.check_remote_changes().await;
-        conflicts_found += remote_result.unwrap_or(0);
+        // 1. Upload local changes
+        let upload_result = self
+            .upload_staged_changes()
+            .instrument(info_span!("sync_phase", phase = "upload"))
+            .await;
+        files_synced += upload_result.synced;
+        files_failed += upload_result.failed;
+        conflicts_found += upload_result.conflicts;
+        let quota = upload_result.quota;
+        self.report_quota_exhaustion(&quota);
+
+        if !self.is_stopping() {
+            // 2. Check for remote changes
+            let remote_result = self
+                .check_remote_changes()
+                .instrument(info_span!("sync_phase", phase = "check_remote"))
+                .await;
+            conflicts_found += remote_result.unwrap_or(0);
 
-        // 3. Download remote changes
-        let download_result = self.download_remote_changes().await;
-        files_synced += download_result.0;
-        files_failed += download_result.1;
-        pending_persistence += download_result.2;
+            // 3. Download remote changes
+            let download_result = self
+                .download_remote_changes()
+                .instrument(info_span!("sync_phase", phase = "download"))
+                .await;
+            files_synced += download_result.0;
+            files_failed += download_result.1;
+            pending_persistence += download_result.2;
+        }
 
         {
             let mut state = self.state.write().await;
             state.sync_in_progress = false;
             state.last_full_sync = Some(chrono::Utc::now());
         }
+        self.share_state().await;
 
         let duration = start.elapsed();
         info!(
-            "Full sync completed in {:?}: {} synced, {} failed, {} conflicts, {} pending persistence",
-            duration, files_synced, files_failed, conflicts_found, pending_persistence
+            "Full sync completed in {:?}: {} synced, {} failed, {} conflicts, {} pending persistence, {} paused",
+            duration, files_synced, files_failed, conflicts_found, pending_persistence, quota.paused
         );
 
         Ok(SyncResult {
@@ -209,12 +543,23 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
             files_failed,
             conflicts_found,
             pending_persistence,
+            quota_exhausted: quota.exhausted,
+            uploads_paused: quota.paused,
+            paused_bytes: quota.paused_bytes,
+            quota_shortfall: quota.shortfall,
             duration,
         })
     }
 
     /// Sync specific paths only.
+    ///
+    /// A path that is not tracked itself but has tracked paths below it is
+    /// treated as a directory and expanded to all of them, so files and
+    /// directories can be mixed.
+    #[instrument(name = "sync", skip_all, fields(mode = "paths", count = paths.len()))]
     pub async fn sync_paths(&self, paths: Vec<String>) -> Result<SyncResult> {
+        metrics::operation("sync_paths");
+        let _active = self.begin_sync().await?;
         let start = Instant::now();
         let mut files_synced = 0;
         let mut files_failed = 0;
@@ -222,15 +567,50 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
 
         info!("Syncing {} specific paths", paths.len());
 
+        let mut requested = Vec::with_capacity(paths.len());
         for path_str in paths {
-            let path = match VaultPath::parse(&path_str) {
-                Ok(p) => p,
+            match VaultPath::parse(&path_str) {
+                Ok(p) => requested.push(p),
                 Err(e) => {
                     warn!("Invalid path: {}", e);
                     files_failed += 1;
+                }
+            }
+        }
+        let tracked = self.tracked_paths().await;
+        let mut expanded = planner::expand_paths(&requested, &tracked);
+        debug!(
+            "Expanded {} requested paths to {}",
+            requested.len(),
+            expanded.len()
+        );
+        expanded.retain(|path| {
+            let excluded = self.config.is_excluded(path);
+            if excluded {
+                debug!("Skipping excluded path");
+            }
+            !excluded
+        });
+
+        let mut quota = QuotaTally::default();
+        let mut needed = 0;
+        for path in &expanded {
+            needed += self.pending_upload_sizes(path).await.iter().sum::<u64>();
+        }
+        quota.shortfall = self.check_quota(needed).await;
+
+        for path in expanded {
+            if self.is_stopping() {
+                debug!("Shutting down; leaving the remaining paths staged");
+                break;
+            }
+            if quota.exhausted {
+                let pending = self.pending_upload_sizes(&path).await;
+                if !pending.is_empty() {
+                    pending.into_iter().for_each(|size| quota.pause(size));
                     continue;
                 }
-            };
+            }
 
             match self.sync_single_path(&path).await {
                 Ok(result) => {
@@ -240,12 +620,21 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
                         files_synced += 1;
                     }
                 }
+                Err(Error::QuotaExhausted(e)) => {
+                    warn!("Provider is out of space, pausing uploads: {}", e);
+                    quota.exhausted = true;
+                    for size in self.pending_upload_sizes(&path).await {
+                        quota.pause(size);
+                    }
+                }
                 Err(e) => {
                     error!("Failed to sync path: {}", e);
                     files_failed += 1;
                 }
             }
         }
+        self.report_quota_exhaustion(&quota);
+        self.share_state().await;
 
         let duration = start.elapsed();
         Ok(SyncResult {
@@ -253,10 +642,78 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
             files_failed,
             conflicts_found,
             pending_persistence: 0,
+            quota_exhausted: quota.exhausted,
+            uploads_paused: quota.paused,
+            paused_bytes: quota.paused_bytes,
+            quota_shortfall: quota.shortfall,
             duration,
         })
     }
 
+    /// Paths known to sync state or with staged changes.
+    async fn tracked_paths(&self) -> Vec<VaultPath> {
+        let mut paths: Vec<VaultPath> = self
+            .state
+            .read()
+            .await
+            .paths()
+            .iter()
+            .filter_map(|p| VaultPath::parse(p).ok())
+            .collect();
+        let staging = self.staging.read().await;
+        paths.extend(staging.all_changes().map(|c| c.vault_path.clone()));
+        paths
+    }
+
+    /// Sizes of the staged uploads for `path` not yet on the remote.
+    async fn pending_upload_sizes(&self, path: &VaultPath) -> Vec<u64> {
+        self.staging
+            .read()
+            .await
+            .changes_for_path(path)
+            .into_iter()
+            .filter(|c| !c.is_uploaded() && c.change_type != ChangeType::Delete)
+            .map(|c| c.size)
+            .collect()
+    }
+
+    /// Compare `needed` upload bytes with the provider's remaining quota,
+    /// publishing [`SyncEvent::QuotaShortfallPredicted`] if they will not
+    /// fit. A provider that cannot report its quota is never short.
+    async fn check_quota(&self, needed: u64) -> Option<QuotaShortfall> {
+        if needed == 0 {
+            return None;
+        }
+        let reported = match self.provider.quota().await {
+            Ok(reported) => reported,
+            Err(e) => {
+                debug!("Could not query storage quota: {}", e);
+                return None;
+            }
+        };
+        let shortfall = quota::shortfall(needed, reported)?;
+        warn!(
+            "Uploads need {} bytes but the provider has only {} bytes free",
+            shortfall.needed, shortfall.remaining
+        );
+        self.emit(SyncEvent::QuotaShortfallPredicted(shortfall));
+        Some(shortfall)
+    }
+
+    /// Publish [`SyncEvent::QuotaExhausted`] if the provider ran out of space.
+    fn report_quota_exhaustion(&self, quota: &QuotaTally) {
+        if quota.exhausted {
+            warn!(
+                "Paused {} uploads ({} bytes) until the provider has space",
+                quota.paused, quota.paused_bytes
+            );
+            self.emit(SyncEvent::QuotaExhausted {
+                paused: quota.paused,
+                paused_bytes: quota.paused_bytes,
+            });
+        }
+    }
+
     /// Process a sync request (for scheduler).
     pub async fn process_request(&self, request: SyncRequest) -> Result<SyncResult> {
         match request {
@@ -268,221 +725,632 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
                 conflicts_found: 0,
                 pending_persistence: 0,
                 duration: Duration::from_secs(0),
+                ..Default::default()
             }),
         }
     }
 
-    /// Upload all staged changes.
-    async fn upload_staged_changes(&self) -> (usize, usize, usize) {
-        let mut synced = 0;
-        let mut failed = 0;
-        let mut conflicts = 0;
+    /// Fetch remote metadata for `paths`, leaving out paths whose metadata
+    /// could not be fetched and listing those the remote reported missing.
+    async fn remote_snapshot(&self, paths: Vec<VaultPath>) -> RemoteSnapshot {
+        let mut remote = HashMap::new();
+        let mut gone = HashSet::new();
+        for path in paths {
+            match self.remote_metadata(&path).await {
+                Ok(metadata) => {
+                    remote.insert(path.to_string(), metadata);
+                }
+                Err(Error::NotFound(_)) => {
+                    gone.insert(path.to_string());
+                }
+                Err(_) => {}
+            }
+        }
+        (remote, gone)
+    }
 
-        let change_ids: Vec<String> = {
-            let staging = self.staging.read().await;
-            staging.all_changes().map(|c| c.id.clone()).collect()
-        };
+    /// Remote state of a single path.
+    async fn remote_file(&self, path: &VaultPath) -> Result<Option<Metadata>> {
+        match self.remote_metadata(path).await {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
 
-        for change_id in change_ids {
-            let change = {
-                let staging = self.staging.read().await;
-                staging.get_change(&change_id).cloned()
-            };
+    /// Fetch remote metadata for a single path, with retries.
+    async fn remote_metadata(&self, path: &VaultPath) -> Result<Metadata> {
+        let provider = self.provider.clone();
+        let path_clone = path.clone();
 
-            let Some(change) = change else {
-                continue;
-            };
+        self.retry_executor
+            .execute(move || {
+                let p = provider.clone();
+                let path = path_clone.clone();
+                async move { p.metadata(&path).await }
+            })
+            .await
+    }
 
-            debug!("Processing staged change: {}", change_id);
+    /// Upload all staged changes.
+    ///
+    /// Changes that reached the remote in an earlier run but were never
+    /// committed are finished first, without touching the remote again.
+    /// Once the provider reports the account out of space, the remaining
+    /// uploads are paused: they stay staged and are not counted as failed.
+    async fn upload_staged_changes(&self) -> UploadOutcome {
+        let mut outcome = UploadOutcome::default();
+
+        let entries: Vec<SyncEntry> = self.state.read().await.entries().cloned().collect();
+        let changes: Vec<StagedChange> = self.staging.read().await.all_changes().cloned().collect();
+        let (remote, gone) = self
+            .remote_snapshot(planner::upload_check_paths(
+                &entries,
+                &changes,
+                &self.config,
+            ))
+            .await;
+        let actions = planner::plan_uploads(&PlanInput {
+            entries: &entries,
+            changes: &changes,
+            remote: &remote,
+            gone: &gone,
+            config: &self.config,
+        });
+
+        // Staged deletes need no space and keep running after exhaustion.
+        let sizes: HashMap<&str, u64> = changes
+            .iter()
+            .filter(|c| c.change_type != ChangeType::Delete)
+            .map(|c| (c.id.as_str(), c.size))
+            .collect();
+        let size_of = |change_id: &str| sizes.get(change_id).copied().unwrap_or(0);
+        let needed = actions
+            .iter()
+            .filter_map(|action| match action {
+                PlannedAction::Upload { change_id, .. } => Some(size_of(change_id)),
+                _ => None,
+            })
+            .sum();
+        outcome.quota.shortfall = self.check_quota(needed).await;
 
-            match change.change_type {
-                ChangeType::Create | ChangeType::Update => {
-                    match self
-                        .upload_staged_file(&change_id, &change.vault_path)
-                        .await
-                    {
-                        Ok(has_conflict) => {
-                            if has_conflict {
-                                conflicts += 1;
-                            } else {
-                                synced += 1;
-                                // Commit the change
-                                if let Err(e) = self.staging.write().await.commit(&change_id).await
-                                {
-                                    warn!("Failed to commit staged change: {}", e);
-                                }
-                            }
+        for action in actions {
+            if self.is_stopping() {
+                debug!("Shutting down; leaving the remaining changes staged");
+                break;
+            }
+            match action {
+                PlannedAction::Upload { change_id, .. }
+                | PlannedAction::Conflict { change_id, .. }
+                    if outcome.quota.exhausted && sizes.contains_key(change_id.as_str()) =>
+                {
+                    outcome.quota.pause(size_of(&change_id));
+                }
+                PlannedAction::Commit { change_id, .. } => {
+                    debug!("Finishing change uploaded before restart: {}", change_id);
+                    match self.commit_uploaded_change(&change_id).await {
+                        Ok(()) => outcome.synced += 1,
+                        Err(e) => warn!("Failed to commit previously uploaded change: {}", e),
+                    }
+                }
+                PlannedAction::Upload { change_id, path } => {
+                    debug!("Uploading staged change: {}", change_id);
+                    match self.transfer_staged_file(&change_id, &path).await {
+                        Ok(()) => {
+                            outcome.synced += 1;
+                            self.commit_staged(&change_id).await;
+                        }
+                        Err(Error::QuotaExhausted(e)) => {
+                            warn!("Provider is out of space, pausing uploads: {}", e);
+                            outcome.quota.exhausted = true;
+                            outcome.quota.pause(size_of(&change_id));
+                        }
+                        Err(Error::Cancelled(e)) if self.is_stopping() => {
+                            debug!("Upload paused: {}", e);
                         }
                         Err(e) => {
                             error!("Failed to upload staged file: {}", e);
-                            failed += 1;
+                            outcome.failed += 1;
                         }
                     }
                 }
-                ChangeType::Delete => match self.delete_remote_file(&change.vault_path).await {
-                    Ok(_) => {
-                        synced += 1;
-                        if let Err(e) = self.staging.write().await.commit(&change_id).await {
-                            warn!("Failed to commit staged change: {}", e);
+                PlannedAction::Conflict { change_id, path } => {
+                    debug!("Staged change conflicts with remote: {}", change_id);
+                    let remote = RemoteFile::of(&path.to_string(), &remote, &gone);
+                    match self.handle_upload_conflict(&change_id, &path, remote).await {
+                        Ok(true) => outcome.conflicts += 1,
+                        Ok(false) => {
+                            outcome.synced += 1;
+                            self.commit_staged(&change_id).await;
+                        }
+                        Err(Error::QuotaExhausted(e)) => {
+                            warn!("Provider is out of space, pausing uploads: {}", e);
+                            outcome.quota.exhausted = true;
+                            outcome.quota.pause(size_of(&change_id));
+                        }
+                        Err(e) => {
+                            error!("Failed to resolve conflict: {}", e);
+                            outcome.failed += 1;
                         }
                     }
-                    Err(e) => {
-                        error!("Failed to delete remote file: {}", e);
-                        failed += 1;
+                }
+                PlannedAction::DeleteRemote { change_id, path } => {
+                    match self.delete_remote_file(&path).await {
+                        Ok(_) => {
+                            outcome.synced += 1;
+                            if let Err(e) = self
+                                .staging
+                                .write()
+                                .await
+                                .mark_uploaded(&change_id, None)
+                                .await
+                            {
+                                warn!("Failed to record staged delete as done: {}", e);
+                            }
+                            self.commit_staged(&change_id).await;
+                        }
+                        Err(e) => {
+                            error!("Failed to delete remote file: {}", e);
+                            outcome.failed += 1;
+                        }
                     }
-                },
+                }
+                PlannedAction::RecordRemoteChange { .. }
+                | PlannedAction::RecordRemoteDelete { .. }
+                | PlannedAction::Download { .. }
+                | PlannedAction::DeleteLocal { .. } => {}
             }
         }
 
-        (synced, failed, conflicts)
+        outcome
+    }
+
+    /// Commit a staged change, logging rather than failing if the registry
+    /// cannot be written.
+    async fn commit_staged(&self, change_id: &str) {
+        if let Err(e) = self.staging.write().await.commit(change_id).await {
+            warn!("Failed to commit staged change: {}", e);
+        }
+    }
+
+    /// Commit a change that already reached the remote and settle its entry.
+    async fn commit_uploaded_change(&self, change_id: &str) -> Result<()> {
+        let change = {
+            let mut staging = self.staging.write().await;
+            let change = staging.get_change(change_id).cloned().ok_or_else(|| {
+                Error::NotFound(format!("Staged change not found: {}", change_id))
+            })?;
+            staging.commit(change_id).await?;
+            change
+        };
+        self.apply_uploaded_change(&change).await;
+        Ok(())
     }
 
-    /// Upload a single staged file.
+    /// Upload a single staged file unless it conflicts with the remote.
+    ///
+    /// Returns `true` if the change was left in conflict.
     async fn upload_staged_file(&self, change_id: &str, path: &VaultPath) -> Result<bool> {
-        let data = {
-            let staging = self.staging.read().await;
-            staging.get_staged_data(change_id).await?
+        let entry = self.state.read().await.get(path).cloned();
+        let remote = match entry {
+            Some(_) => self.remote_file(path).await.ok(),
+            None => None,
         };
+        let remote = Self::as_remote_file(remote.as_ref());
 
-        // Check for conflicts first
-        let local_entry = {
-            let state = self.state.read().await;
-            state.get(path).cloned()
+        match planner::upload_action(change_id, path, entry.as_ref(), remote) {
+            PlannedAction::Conflict { .. } => {
+                self.handle_upload_conflict(change_id, path, remote).await
+            }
+            _ => {
+                self.transfer_staged_file(change_id, path).await?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Push a single staged delete unless it conflicts with a remote edit.
+    ///
+    /// Returns `true` if the change was left in conflict.
+    async fn delete_staged_file(&self, change_id: &str, path: &VaultPath) -> Result<bool> {
+        let entry = self.state.read().await.get(path).cloned();
+        let remote = match entry {
+            Some(_) => self.remote_file(path).await.ok(),
+            None => None,
         };
+        let remote = Self::as_remote_file(remote.as_ref());
 
-        if let Some(ref entry) = local_entry {
-            // Check if remote has changed
-            let provider = self.provider.clone();
-            let path_clone = path.clone();
+        match planner::delete_action(change_id, path, entry.as_ref(), remote) {
+            PlannedAction::Conflict { .. } => {
+                self.handle_upload_conflict(change_id, path, remote).await
+            }
+            _ => {
+                self.delete_remote_file(path).await?;
+                self.staging
+                    .write()
+                    .await
+                    .mark_uploaded(change_id, None)
+                    .await?;
+                Ok(false)
+            }
+        }
+    }
 
-            let remote_metadata = self
-                .retry_executor
-                .execute(|| {
-                    let p = provider.clone();
-                    let path = path_clone.clone();
-                    async move { p.metadata(&path).await }
-                })
-                .await;
+    /// A single-path remote lookup as the planner sees it: `None` if it
+    /// failed, `Some(None)` if the remote has no file.
+    fn as_remote_file(remote: Option<&Option<Metadata>>) -> RemoteFile<'_> {
+        match remote {
+            Some(Some(metadata)) => RemoteFile::Present(metadata),
+            Some(None) => RemoteFile::Gone,
+            None => RemoteFile::Unknown,
+        }
+    }
 
-            if let Ok(remote) = remote_metadata {
-                if self.conflict_resolver.detect_conflict(
-                    entry.local_etag.as_deref(),
-                    remote.etag.as_deref(),
-                    entry.remote_etag.as_deref(),
-                ) {
-                    // Conflict detected
-                    let conflict_info = ConflictInfo::from_entry_and_remote(entry, &remote)?;
-
-                    if self.config.auto_resolve_conflicts {
-                        let result = self
-                            .conflict_resolver
-                            .resolve(
-                                &conflict_info,
-                                data,
-                                self.provider.as_ref(),
-                                self.config.conflict_strategy,
-                            )
-                            .await?;
-
-                        self.handle_resolution_result(path, result).await?;
-                        return Ok(false);
-                    } else {
-                        // Mark as conflicted
-                        let mut state = self.state.write().await;
-                        if let Some(entry) = state.get_mut(path) {
-                            entry.mark_conflicted(remote.etag.clone(), remote.modified);
-                        }
-                        return Ok(true);
+    /// Settle a staged change that conflicts with `remote`.
+    ///
+    /// With automatic resolution the conflict is resolved and the change is
+    /// recorded as uploaded; otherwise the entry is marked conflicted. The
+    /// `Interactive` strategy marks it conflicted, then resolves it as the
+    /// conflict handler decides. A
+    /// staged delete, or an edit of a file the remote deleted, is a
+    /// delete-vs-edit conflict. An edit conflict cannot be resolved without
+    /// remote metadata, so the change stays staged. Returns `true` if the
+    /// conflict remains.
+    async fn handle_upload_conflict(
+        &self,
+        change_id: &str,
+        path: &VaultPath,
+        remote: RemoteFile<'_>,
+    ) -> Result<bool> {
+        let entry = self.state.read().await.get(path).cloned().ok_or_else(|| {
+            Error::NotFound(format!("No sync entry for {}", path.display_lossy()))
+        })?;
+        let change_type = self
+            .staging
+            .read()
+            .await
+            .get_change(change_id)
+            .map(|c| c.change_type)
+            .ok_or_else(|| Error::NotFound(format!("Staged change not found: {}", change_id)))?;
+
+        let deleted = match (change_type, remote) {
+            (ChangeType::Delete, _) => Some(Side::Local),
+            (_, RemoteFile::Gone) => Some(Side::Remote),
+            _ => None,
+        };
+        let Some(kind) = (match (deleted, remote) {
+            (Some(deleted), _) => Some(ConflictKind::DeleteVsEdit { deleted }),
+            (None, RemoteFile::Present(_)) => Some(ConflictKind::Edit),
+            (None, _) => None,
+        }) else {
+            return Ok(true);
+        };
+
+        let interactive = self.config.conflict_strategy == ConflictStrategy::Interactive;
+        let mut strategy = self.config.conflict_strategy;
+        if interactive || !self.config.auto_resolve_conflicts {
+            let mut state = self.state.write().await;
+            if let Some(entry) = state.get_mut(path) {
+                match remote {
+                    RemoteFile::Present(remote) => {
+                        entry.mark_conflicted(remote.etag.clone(), remote.modified)
                     }
+                    RemoteFile::Gone => {
+                        let now = chrono::Utc::now();
+                        entry.mark_remote_deleted(now);
+                        entry.mark_conflicted(None, now);
+                    }
+                    // Only a pending local delete gets here, already in
+                    // conflict from an earlier pass.
+                    RemoteFile::Unknown => {}
                 }
             }
+            self.publish_status(&state);
+            drop(state);
+            if !interactive {
+                return Ok(true);
+            }
+
+            let details = self.conflict_details(path).await?;
+            match self.conflict_resolver.ask(details).await? {
+                Some(decided) => strategy = decided,
+                None => return Ok(true),
+            }
         }
 
-        // No conflict, upload
-        let provider = self.provider.clone();
-        let path_clone = path.clone();
+        let result = match kind {
+            ConflictKind::Edit => {
+                let Some(remote) = remote.metadata() else {
+                    return Ok(true);
+                };
+                let conflict_info = ConflictInfo::from_entry_and_remote(&entry, remote)?;
+                let data = self.staging.read().await.get_staged_data(change_id).await?;
+                self.conflict_resolver
+                    .resolve(
+                        &conflict_info,
+                        LocalVersion::staged(data),
+                        self.provider.as_ref(),
+                        strategy,
+                    )
+                    .await?
+            }
+            ConflictKind::DeleteVsEdit { deleted } => {
+                let data = match deleted {
+                    Side::Remote => Some(LocalVersion::staged(
+                        self.staging.read().await.get_staged_data(change_id).await?,
+                    )),
+                    Side::Local => None,
+                };
+                self.conflict_resolver
+                    .resolve_delete(
+                        path,
+                        deleted,
+                        data,
+                        self.provider.as_ref(),
+                        strategy,
+                        self.config.trash.as_ref(),
+                    )
+                    .await?
+            }
+        };
 
-        let metadata = self
-            .retry_executor
-            .execute(move || {
-                let p = provider.clone();
-                let path = path_clone.clone();
-                let d = data.clone();
-                async move { p.upload(&path, d).await }
-            })
+        self.handle_resolution_result(path, result).await?;
+        self.staging
+            .write()
+            .await
+            .mark_uploaded(change_id, None)
+            .await?;
+        Ok(false)
+    }
+
+    /// Push a staged file to the remote and mark its entry synced.
+    #[instrument(
+        name = "sync_transfer",
+        skip_all,
+        fields(direction = "upload", path = %path_field(path), bytes = field::Empty)
+    )]
+    async fn transfer_staged_file(&self, change_id: &str, path: &VaultPath) -> Result<()> {
+        metrics::operation("sync_upload");
+        let size = {
+            let staging = self.staging.read().await;
+            staging
+                .get_change(change_id)
+                .map(|c| c.size)
+                .ok_or_else(|| Error::NotFound(format!("Staged change not found: {}", change_id)))?
+        };
+        Span::current().record("bytes", size);
+
+        let resumable = self
+            .provider
+            .as_resumable()
+            .filter(|r| size > r.chunk_size() as u64);
+
+        let metadata = match resumable {
+            Some(resumable) => {
+                self.upload_resumable(resumable, change_id, path, size)
+                    .await?
+            }
+            None => {
+                let data = self.staging.read().await.get_staged_data(change_id).await?;
+                let provider = self.provider.clone();
+                let path_clone = path.clone();
+
+                self.retry_executor
+                    .execute(move || {
+                        let p = provider.clone();
+                        let path = path_clone.clone();
+                        let d = data.clone();
+                        async move { p.upload(&path, d).await }
+                    })
+                    .await?
+            }
+        };
+
+        // Record the upload before anything else so a crash from here on
+        // cannot cause the change to be uploaded again.
+        self.staging
+            .write()
+            .await
+            .mark_uploaded(change_id, metadata.etag.clone())
             .await?;
 
         // Update sync state
         let mut state = self.state.write().await;
         if let Some(entry) = state.get_mut(path) {
             entry.mark_synced(metadata.etag.clone(), metadata.modified);
+            entry.remote_content_hash = metadata.content_hash;
+            entry.remote_size = metadata.size;
         } else {
-            state.insert(SyncEntry::new_synced(
-                path.to_string(),
-                metadata.etag,
-                metadata.modified,
-            ));
+            let mut entry =
+                SyncEntry::new_synced(path.to_string(), metadata.etag, metadata.modified);
+            entry.remote_content_hash = metadata.content_hash;
+            entry.remote_size = metadata.size;
+            state.insert(entry);
         }
+        self.publish_status(&state);
 
-        Ok(false)
+        Ok(())
     }
 
-    /// Delete a file from remote storage.
-    async fn delete_remote_file(&self, path: &VaultPath) -> Result<()> {
-        let provider = self.provider.clone();
-        let path_clone = path.clone();
+    /// Upload a staged file in chunks, resuming a session journaled by an
+    /// earlier attempt when the backend still has it.
+    ///
+    /// Progress is recorded in the staging registry after every acknowledged
+    /// chunk. A failed chunk is not retried here; the next sync pass asks the
+    /// backend where the session stands and continues from there. An expired
+    /// session is replaced by a new one starting at byte zero.
+    async fn upload_resumable(
+        &self,
+        resumable: &dyn ResumableUpload,
+        change_id: &str,
+        path: &VaultPath,
+        size: u64,
+    ) -> Result<Metadata> {
+        let journal = {
+            let staging = self.staging.read().await;
+            staging
+                .get_change(change_id)
+                .and_then(|c| c.upload_session.clone())
+        };
 
-        self.retry_executor
-            .execute(move || {
-                let p = provider.clone();
-                let path = path_clone.clone();
-                async move { p.delete(&path).await }
-            })
-            .await?;
+        let resumed = match journal {
+            Some(journal) => match resumable.query_upload(&journal.session).await {
+                Ok(UploadProgress::Complete(metadata)) => return Ok(metadata),
+                Ok(UploadProgress::InProgress { acknowledged }) => {
+                    debug!(
+                        "Resuming upload at byte {} of {} (journal had {})",
+                        acknowledged, size, journal.acknowledged
+                    );
+                    Some((journal.session, acknowledged))
+                }
+                Err(Error::NotFound(_)) => {
+                    debug!("Upload session expired, restarting from byte 0");
+                    None
+                }
+                Err(e) => return Err(e),
+            },
+            None => None,
+        };
 
-        // Remove from sync state
-        let mut state = self.state.write().await;
-        state.remove(path);
+        let (session, mut offset) = match resumed {
+            Some(resumed) => resumed,
+            None => {
+                let session = self
+                    .retry_executor
+                    .execute(|| resumable.start_upload(path, size))
+                    .await?;
+                self.staging
+                    .write()
+                    .await
+                    .record_upload_progress(change_id, session.clone(), 0)
+                    .await?;
+                (session, 0)
+            }
+        };
 
-        Ok(())
+        let chunk_size = resumable.chunk_size();
+        loop {
+            let chunk = {
+                let staging = self.staging.read().await;
+                staging
+                    .read_staged_chunk(change_id, offset, chunk_size)
+                    .await?
+            };
+            if chunk.is_empty() {
+                return Err(Error::Network(format!(
+                    "Upload session ended at byte {} without completing",
+                    offset
+                )));
+            }
+
+            match resumable.continue_upload(&session, offset, &chunk).await? {
+                UploadProgress::Complete(metadata) => return Ok(metadata),
+                UploadProgress::InProgress { acknowledged } => {
+                    if acknowledged <= offset {
+                        return Err(Error::Network(format!(
+                            "Upload made no progress at byte {}",
+                            offset
+                        )));
+                    }
+                    offset = acknowledged;
+                    self.staging
+                        .write()
+                        .await
+                        .record_upload_progress(change_id, session.clone(), offset)
+                        .await?;
+                    if self.is_stopping() {
+                        return Err(Error::Cancelled(format!(
+                            "Upload stopped for shutdown at byte {} of {}",
+                            offset, size
+                        )));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Update sync state for a change that reached the remote in an earlier
+    /// run but was not committed before that run stopped.
+    async fn apply_uploaded_change(&self, change: &StagedChange) {
+        let mut state = self.state.write().await;
+        let entry = state.get(&change.vault_path).cloned();
+        match planner::entry_after_commit(entry, change) {
+            Some(entry) => state.insert(entry),
+            None => {
+                state.remove(&change.vault_path);
+            }
+        }
+        self.publish_status(&state);
+    }
+
+    /// Delete a file from remote storage, through the trash if one is
+    /// configured. A file that is already gone counts as deleted.
+    #[instrument(
+        name = "sync_transfer",
+        skip_all,
+        fields(direction = "delete", path = %path_field(path))
+    )]
+    async fn delete_remote_file(&self, path: &VaultPath) -> Result<()> {
+        metrics::operation("sync_delete");
+        let provider = self.provider.clone();
+        let path_clone = path.clone();
+        let trash = self.config.trash.clone();
+
+        self.retry_executor
+            .execute(move || {
+                let p = provider.clone();
+                let path = path_clone.clone();
+                let trash = trash.clone();
+                async move { remove_remote(p.as_ref(), &path, trash.as_ref()).await }
+            })
+            .await?;
+
+        // Remove from sync state
+        let mut state = self.state.write().await;
+        state.remove(path);
+        self.publish_status(&state);
+
+        Ok(())
     }
 
     /// Check remote for changes.
     async fn check_remote_changes(&self) -> Result<usize> {
         let mut conflicts = 0;
 
-        // Get list of known paths
-        let paths: Vec<String> = {
-            let state = self.state.read().await;
-            state.paths()
-        };
+        let entries: Vec<SyncEntry> = self.state.read().await.entries().cloned().collect();
+        let (remote, gone) = self
+            .remote_snapshot(planner::remote_check_paths(&entries, &self.config))
+            .await;
 
-        for path_str in paths {
-            let path = VaultPath::parse(&path_str)?;
-            let provider = self.provider.clone();
-            let path_clone = path.clone();
-
-            let remote_result = self
-                .retry_executor
-                .execute(move || {
-                    let p = provider.clone();
-                    let path = path_clone.clone();
-                    async move { p.metadata(&path).await }
-                })
-                .await;
-
-            if let Ok(remote) = remote_result {
-                let mut state = self.state.write().await;
-                if let Some(entry) = state.get_mut(&path) {
-                    if entry.remote_etag != remote.etag {
-                        entry.mark_remote_modified(remote.etag.clone(), remote.modified);
-                        if entry.status == SyncStatus::Conflicted {
-                            conflicts += 1;
-                        }
-                    }
+        for action in planner::plan_remote_changes(&entries, &remote, &gone, &self.config) {
+            let mut state = self.state.write().await;
+            let (path, deleted, etag, content_hash, size, modified) = match action {
+                PlannedAction::RecordRemoteChange {
+                    path,
+                    etag,
+                    content_hash,
+                    size,
+                    modified,
+                    ..
+                } => (path, false, etag, content_hash, size, modified),
+                PlannedAction::RecordRemoteDelete { path, .. } => {
+                    (path, true, None, None, None, chrono::Utc::now())
                 }
+                _ => continue,
+            };
+            let Some(entry) = state.get_mut(&path) else {
+                continue;
+            };
+            if deleted {
+                entry.mark_remote_deleted(modified);
+            } else {
+                entry.mark_remote_version(etag, content_hash, size, modified);
+            }
+            if entry.status == SyncStatus::Conflicted {
+                conflicts += 1;
             }
         }
+        self.publish_status(&*self.state.read().await);
 
         Ok(conflicts)
     }
@@ -509,27 +1377,37 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
         let mut failed = 0;
         let mut pending_persistence = 0;
 
-        let entries: Vec<(String, SyncStatus)> = {
-            let state = self.state.read().await;
-            state
-                .entries()
-                .filter(|e| e.status == SyncStatus::RemoteModified)
-                .map(|e| (e.path.clone(), e.status))
-                .collect()
-        };
+        let entries: Vec<SyncEntry> = self.state.read().await.entries().cloned().collect();
 
-        for (path_str, _) in entries {
-            let path = match VaultPath::parse(&path_str) {
-                Ok(p) => p,
-                Err(_) => {
-                    failed += 1;
+        for action in planner::plan_downloads(&entries, &self.config) {
+            if self.is_stopping() {
+                break;
+            }
+            let path = match action {
+                PlannedAction::Download { path } => path,
+                PlannedAction::DeleteLocal { path } => {
+                    // Like downloads, local deletes have nowhere to go yet
+                    // (audit H-1); the entry stays waiting.
+                    warn!(
+                        "path {} was deleted remotely but local deletes are not yet wired up — entry not settled (audit H-1)",
+                        path_field(&path)
+                    );
+                    pending_persistence += 1;
                     continue;
                 }
+                _ => continue,
             };
 
             let provider = self.provider.clone();
             let path_clone = path.clone();
 
+            metrics::operation("sync_download");
+            let span = info_span!(
+                "sync_transfer",
+                direction = "download",
+                path = %path_field(&path),
+                bytes = field::Empty,
+            );
             let download_result = self
                 .retry_executor
                 .execute(move || {
@@ -537,7 +1415,11 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
                     let path = path_clone.clone();
                     async move { p.download(&path).await }
                 })
+                .instrument(span.clone())
                 .await;
+            if let Ok(data) = &download_result {
+                span.record("bytes", data.len());
+            }
 
             match download_result {
                 Ok(data) => {
@@ -548,7 +1430,7 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
                     warn!(
                         "downloaded {} bytes for path {} but persistence is not yet wired up — entry not marked synced (audit H-1)",
                         data.len(),
-                        path
+                        path_field(&path)
                     );
                     pending_persistence += 1;
                 }
@@ -564,44 +1446,42 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
 
     /// Sync a single path.
     async fn sync_single_path(&self, path: &VaultPath) -> Result<SingleSyncResult> {
-        let change_ids: Vec<String> = {
+        let change_ids: Vec<(String, ChangeType)> = {
             let staging = self.staging.read().await;
-            staging
-                .changes_for_path(path)
+            let mut changes = staging.changes_for_path(path);
+            changes.retain(|c| !c.is_uploaded());
+            changes.sort_by(|a, b| a.staged_at.cmp(&b.staged_at).then(a.id.cmp(&b.id)));
+            changes
                 .iter()
-                .map(|c| c.id.clone())
+                .map(|c| (c.id.clone(), c.change_type))
                 .collect()
         };
 
         if !change_ids.is_empty() {
             // Has local changes, upload
-            for change_id in change_ids {
-                let has_conflict = self.upload_staged_file(&change_id, path).await?;
-                if has_conflict {
+            for (change_id, change_type) in change_ids {
+                let conflicted = if change_type == ChangeType::Delete {
+                    self.delete_staged_file(&change_id, path).await?
+                } else {
+                    self.upload_staged_file(&change_id, path).await?
+                };
+                if conflicted {
                     return Ok(SingleSyncResult { has_conflict: true });
                 }
                 self.staging.write().await.commit(&change_id).await?;
             }
         } else {
             // Check remote for updates
-            let provider = self.provider.clone();
-            let path_clone = path.clone();
-
-            let remote_metadata = self
-                .retry_executor
-                .execute(move || {
-                    let p = provider.clone();
-                    let path = path_clone.clone();
-                    async move { p.metadata(&path).await }
-                })
-                .await?;
+            let remote_metadata = self.remote_metadata(path).await?;
 
             let mut state = self.state.write().await;
             if let Some(entry) = state.get_mut(path) {
-                if entry.remote_etag != remote_metadata.etag {
+                if !planner::remote_unchanged(entry, &remote_metadata) {
                     entry.mark_synced(remote_metadata.etag, remote_metadata.modified);
+                    entry.remote_size = remote_metadata.size;
                 }
             }
+            self.publish_status(&state);
         }
 
         Ok(SingleSyncResult {
@@ -632,6 +1512,7 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
                 original_path,
                 renamed_path,
                 remote_etag,
+                ..
             } => {
                 // Update original path to synced with remote
                 if let Some(entry) = state.get_mut(&original_path) {
@@ -645,10 +1526,25 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
                     chrono::Utc::now(),
                 ));
             }
+            ResolutionResult::Deleted {
+                path,
+                restored_path,
+                restored_etag,
+            } => {
+                state.remove(&path);
+                if let Some(restored_path) = restored_path {
+                    state.insert(SyncEntry::new_synced(
+                        restored_path.to_string(),
+                        restored_etag,
+                        chrono::Utc::now(),
+                    ));
+                }
+            }
             ResolutionResult::Pending => {
                 // Nothing to do, conflict remains
             }
         }
+        self.publish_status(&state);
 
         Ok(())
     }
@@ -663,35 +1559,260 @@ impl<P: StorageProvider + ?Sized + 'static> SyncEngine<P> {
             .collect()
     }
 
+    /// Describe both sides of a conflicted path.
+    ///
+    /// The local side comes from the sync state and the latest staged
+    /// upload; the remote side from the provider. No content is downloaded,
+    /// so [`ConflictDetails::preview`] is left for the caller to fill in
+    /// with [`ConflictDetails::with_preview`].
+    ///
+    /// # Errors
+    /// - `NotFound` if the path has no sync entry
+    /// - `InvalidInput` if the path is not in conflict
+    pub async fn conflict_details(&self, path: &VaultPath) -> Result<ConflictDetails> {
+        let entry = self.conflicted_entry(path).await?;
+        let kind = ConflictKind::of(&entry);
+        let deleted = |side| {
+            (kind == ConflictKind::DeleteVsEdit { deleted: side }).then_some(VersionInfo {
+                etag: None,
+                modified: entry.deleted_at,
+                size: None,
+            })
+        };
+
+        let local = match deleted(Side::Local) {
+            Some(version) => version,
+            None => {
+                let staging = self.staging.read().await;
+                let size = staging
+                    .changes_for_path(path)
+                    .into_iter()
+                    .filter(|c| c.change_type != ChangeType::Delete)
+                    .max_by_key(|c| c.staged_at)
+                    .map(|c| c.size);
+                VersionInfo {
+                    etag: entry.local_etag.clone(),
+                    modified: Some(entry.local_modified),
+                    size,
+                }
+            }
+        };
+        let remote = match deleted(Side::Remote) {
+            Some(version) => version,
+            None => {
+                let remote = self.provider.metadata(path).await?;
+                VersionInfo {
+                    etag: remote.etag,
+                    modified: Some(remote.modified),
+                    size: remote.size,
+                }
+            }
+        };
+
+        Ok(ConflictDetails {
+            path: path.clone(),
+            kind,
+            local,
+            remote,
+            preview: None,
+        })
+    }
+
     /// Manually resolve a conflict.
+    ///
+    /// `local` is the local version sealed by
+    /// [`VaultOperations::seal_for_staging`](axiomvault_vault::VaultOperations::seal_for_staging),
+    /// or `None` if the file no longer exists locally. For a delete-vs-edit
+    /// conflict it is only used when the remote deleted the file, and the
+    /// path's staged changes are dropped once it is resolved, since the
+    /// resolution settles both sides.
+    ///
+    /// # Errors
+    /// - `SyncInProgress` if another process is syncing
+    /// - `NotFound` if `local` is `None` but the local version is needed
     pub async fn resolve_conflict(
         &self,
         path: &VaultPath,
-        local_data: Vec<u8>,
+        local: Option<StagedBlob>,
         strategy: ConflictStrategy,
     ) -> Result<()> {
+        self.resolve_shared(path, local.map(LocalVersion::from), strategy)
+            .await
+    }
+
+    /// Resolve one conflict under the staging lease, sharing the state
+    /// with other processes before and after.
+    async fn resolve_shared(
+        &self,
+        path: &VaultPath,
+        local: Option<LocalVersion>,
+        strategy: ConflictStrategy,
+    ) -> Result<()> {
+        let _lease = self.sync_lease().await?;
+        self.share_state().await;
+        let result = self.resolve_leased(path, local, strategy).await;
+        self.share_state().await;
+        result
+    }
+
+    /// [`resolve_conflict`](Self::resolve_conflict) with the staging lease
+    /// held.
+    async fn resolve_leased(
+        &self,
+        path: &VaultPath,
+        local: Option<LocalVersion>,
+        strategy: ConflictStrategy,
+    ) -> Result<()> {
+        let entry = self.conflicted_entry(path).await?;
+        let no_local_version = || {
+            Error::NotFound(format!(
+                "No local version of {} to resolve the conflict with",
+                path.display_lossy()
+            ))
+        };
+
+        let ConflictKind::DeleteVsEdit { deleted } = ConflictKind::of(&entry) else {
+            let local = local.ok_or_else(no_local_version)?;
+            let remote_metadata = self.provider.metadata(path).await?;
+            let conflict_info = ConflictInfo::from_entry_and_remote(&entry, &remote_metadata)?;
+
+            let result = self
+                .conflict_resolver
+                .resolve(&conflict_info, local, self.provider.as_ref(), strategy)
+                .await?;
+
+            return self.handle_resolution_result(path, result).await;
+        };
+
+        let result = self
+            .conflict_resolver
+            .resolve_delete(
+                path,
+                deleted,
+                match deleted {
+                    Side::Remote => Some(local.ok_or_else(no_local_version)?),
+                    Side::Local => None,
+                },
+                self.provider.as_ref(),
+                strategy,
+                self.config.trash.as_ref(),
+            )
+            .await?;
+        if matches!(result, ResolutionResult::Pending) {
+            return Ok(());
+        }
+        self.handle_resolution_result(path, result).await?;
+
+        let mut staging = self.staging.write().await;
+        let pending: Vec<String> = staging
+            .changes_for_path(path)
+            .into_iter()
+            .filter(|c| !c.is_uploaded())
+            .map(|c| c.id.clone())
+            .collect();
+        for change_id in pending {
+            staging.rollback(&change_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Resolve every conflicted path with `strategy`.
+    ///
+    /// `local` supplies the local version of each path the way
+    /// [`resolve_conflict`](Self::resolve_conflict) takes it, normally from
+    /// [`VaultOperations::seal_current_for_staging`](axiomvault_vault::VaultOperations::seal_current_for_staging).
+    /// A path without one fails with `NotFound` and its remote is left
+    /// untouched, unless it was deleted locally. A path that fails to
+    /// resolve is reported with its error and the rest are still tried.
+    ///
+    /// # Errors
+    /// - `SyncInProgress` if another process is syncing; nothing is tried
+    pub async fn resolve_all<F, Fut>(
+        &self,
+        strategy: ConflictStrategy,
+        mut local: F,
+    ) -> Result<Vec<(VaultPath, Result<()>)>>
+    where
+        F: FnMut(VaultPath) -> Fut,
+        Fut: Future<Output = Result<Option<StagedBlob>>>,
+    {
+        let _lease = self.sync_lease().await?;
+        self.share_state().await;
+        let mut outcomes = Vec::new();
+        for path in self.get_conflicts().await {
+            let result = match local(path.clone()).await {
+                Ok(version) => {
+                    self.resolve_shared(&path, version.map(LocalVersion::from), strategy)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = &result {
+                warn!(
+                    "Failed to resolve conflict for {}: {}",
+                    path.display_lossy(),
+                    e
+                );
+            }
+            outcomes.push((path, result));
+        }
+        Ok(outcomes)
+    }
+
+    /// Files in the remote trash, oldest first. Empty without a trash.
+    pub async fn trash_contents(&self) -> Result<Vec<TrashedFile>> {
+        match &self.config.trash {
+            Some(trash) => trash.list(self.provider.as_ref()).await,
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Move a trashed file back to its original path.
+    ///
+    /// The entry waits on the remote afterwards, so the file is downloaded
+    /// again.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if no trash is configured
+    /// - `AlreadyExists` if a file has since been created at the path
+    pub async fn restore_from_trash(&self, file: &TrashedFile) -> Result<()> {
+        let trash = self
+            .config
+            .trash
+            .as_ref()
+            .ok_or_else(|| Error::InvalidInput("No trash is configured".to_string()))?;
+        let metadata = trash.restore(self.provider.as_ref(), file).await?;
+
+        let mut state = self.state.write().await;
+        let mut entry = state
+            .remove(&file.original_path)
+            .unwrap_or_else(|| SyncEntry::new_local(file.original_path.to_string(), None));
+        entry.mark_synced(None, metadata.modified);
+        entry.mark_remote_modified(metadata.etag, metadata.modified);
+        state.insert(entry);
+        self.publish_status(&state);
+        Ok(())
+    }
+
+    /// Sync entry of `path`, which must be in conflict.
+    async fn conflicted_entry(&self, path: &VaultPath) -> Result<SyncEntry> {
         let entry = {
             let state = self.state.read().await;
             state.get(path).cloned()
         };
 
         let Some(entry) = entry else {
-            return Err(Error::NotFound(format!("No sync entry for {}", path)));
+            return Err(Error::NotFound(format!(
+                "No sync entry for {}",
+                path.display_lossy()
+            )));
         };
 
         if entry.status != SyncStatus::Conflicted {
             return Err(Error::InvalidInput("Path is not in conflict".to_string()));
         }
 
-        let remote_metadata = self.provider.metadata(path).await?;
-        let conflict_info = ConflictInfo::from_entry_and_remote(&entry, &remote_metadata)?;
-
-        let result = self
-            .conflict_resolver
-            .resolve(&conflict_info, local_data, self.provider.as_ref(), strategy)
-            .await?;
-
-        self.handle_resolution_result(path, result).await
+        Ok(entry)
     }
 }
 
@@ -703,9 +1824,528 @@ struct SingleSyncResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::conflict::ConflictDecision;
+    use axiomvault_common::MockClock;
+    use axiomvault_storage::test_util::{CountingProvider, ResumableProvider, RESUMABLE_CHUNK};
     use axiomvault_storage::MemoryProvider;
+    use std::collections::HashMap;
     use tempfile::TempDir;
 
+    fn staging_key() -> StagingKey {
+        StagingKey::new(
+            axiomvault_common::VaultId::new("vault").unwrap(),
+            axiomvault_crypto::FileKey::from_bytes([7u8; 32]),
+        )
+    }
+
+    fn sealed(data: &[u8]) -> StagedBlob {
+        StagedBlob::new(
+            axiomvault_crypto::seal(&[9u8; 32], data).unwrap(),
+            axiomvault_common::VaultId::new("vault").unwrap(),
+            "node",
+            axiomvault_vault::BlobFormat::LATEST,
+        )
+    }
+
+    fn chaos_payload() -> Vec<u8> {
+        (0..RESUMABLE_CHUNK * 10 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect()
+    }
+
+    /// An upload cut off mid-way resumes in a fresh engine from the offset
+    /// the backend reports, even when the last acknowledgement was lost.
+    #[tokio::test]
+    async fn test_interrupted_upload_resumes_after_restart() {
+        let provider = Arc::new(ResumableProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        let path = VaultPath::parse("/large.bin").unwrap();
+        let blob = sealed(&chaos_payload());
+        let data = blob.ciphertext().as_bytes().to_vec();
+
+        provider.fail_after(4, true);
+        {
+            let engine = SyncEngine::from_arc(
+                provider.clone(),
+                staging_dir.path(),
+                staging_key(),
+                SyncConfig::default(),
+            )
+            .await
+            .unwrap();
+            engine
+                .stage_change(&path, blob.clone(), ChangeType::Create)
+                .await
+                .unwrap();
+            let result = engine.sync_full().await.unwrap();
+            assert_eq!(result.files_failed, 1);
+
+            let staging = engine.staging.read().await;
+            let change = staging.all_changes().next().unwrap();
+            let journal = change.upload_session.as_ref().unwrap();
+            assert_eq!(journal.acknowledged, 4 * RESUMABLE_CHUNK as u64);
+        }
+        assert_eq!(provider.bytes_received(), 5 * RESUMABLE_CHUNK);
+
+        provider.heal();
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_failed, 0);
+        assert_eq!(result.files_synced, 1);
+
+        assert_eq!(provider.sessions_started(), 1);
+        assert_eq!(provider.bytes_received(), data.len());
+        assert_eq!(provider.download(&path).await.unwrap(), data);
+        assert!(engine.staging.read().await.is_empty());
+    }
+
+    /// Shutdown stops an upload after the chunk in flight, keeps its
+    /// journal, and a fresh engine resumes it where it stopped.
+    #[tokio::test]
+    async fn test_shutdown_checkpoints_upload_for_next_start() {
+        let provider = Arc::new(ResumableProvider::new());
+        let gate = Arc::new(tokio::sync::Semaphore::new(3));
+        provider.set_gate(Some(gate.clone()));
+        let staging_dir = TempDir::new().unwrap();
+        let path = VaultPath::parse("/large.bin").unwrap();
+        let blob = sealed(&chaos_payload());
+        let data = blob.ciphertext().as_bytes().to_vec();
+
+        let engine = Arc::new(
+            SyncEngine::from_arc(
+                provider.clone(),
+                staging_dir.path(),
+                staging_key(),
+                SyncConfig::default(),
+            )
+            .await
+            .unwrap(),
+        );
+        engine
+            .stage_change(&path, blob, ChangeType::Create)
+            .await
+            .unwrap();
+        let sync = tokio::spawn({
+            let engine = engine.clone();
+            async move { engine.sync_full().await }
+        });
+        while provider.chunks_gated() < 4 {
+            tokio::task::yield_now().await;
+        }
+
+        // The fourth chunk is stuck: the deadline passes first.
+        assert_eq!(
+            engine.shutdown(Duration::from_millis(20)).await.unwrap(),
+            ShutdownOutcome::TimedOut
+        );
+        assert!(matches!(engine.sync_full().await, Err(Error::Cancelled(_))));
+
+        // Once it is through, the upload stops and the drain completes.
+        gate.add_permits(1);
+        assert_eq!(
+            engine.shutdown(Duration::from_secs(5)).await.unwrap(),
+            ShutdownOutcome::Drained
+        );
+        let result = sync.await.unwrap().unwrap();
+        assert_eq!(result.files_failed, 0);
+        assert_eq!(result.files_synced, 0);
+        {
+            let staging = engine.staging.read().await;
+            let change = staging.all_changes().next().unwrap();
+            let journal = change.upload_session.as_ref().unwrap();
+            assert_eq!(journal.acknowledged, 4 * RESUMABLE_CHUNK as u64);
+        }
+        drop(engine);
+
+        // No temporary registry is left behind.
+        let mut names = Vec::new();
+        let mut dir = tokio::fs::read_dir(staging_dir.path()).await.unwrap();
+        while let Some(entry) = dir.next_entry().await.unwrap() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        assert!(
+            names.iter().all(|name| !name.ends_with(".tmp")),
+            "{:?}",
+            names
+        );
+
+        provider.set_gate(None);
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_synced, 1);
+        assert_eq!(provider.sessions_started(), 1);
+        assert_eq!(provider.bytes_received(), data.len());
+        assert_eq!(provider.download(&path).await.unwrap(), data);
+        assert!(engine.staging.read().await.is_empty());
+    }
+
+    /// A journaled session the backend no longer knows is replaced by a new
+    /// one that starts from byte zero.
+    #[tokio::test]
+    async fn test_expired_upload_session_restarts_from_zero() {
+        let provider = Arc::new(ResumableProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        let path = VaultPath::parse("/large.bin").unwrap();
+        let blob = sealed(&chaos_payload());
+        let data = blob.ciphertext().as_bytes().to_vec();
+
+        provider.fail_after(3, false);
+        {
+            let engine = SyncEngine::from_arc(
+                provider.clone(),
+                staging_dir.path(),
+                staging_key(),
+                SyncConfig::default(),
+            )
+            .await
+            .unwrap();
+            engine
+                .stage_change(&path, blob.clone(), ChangeType::Create)
+                .await
+                .unwrap();
+            assert_eq!(engine.sync_full().await.unwrap().files_failed, 1);
+        }
+
+        provider.heal();
+        provider.expire_sessions();
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_synced, 1);
+
+        assert_eq!(provider.sessions_started(), 2);
+        assert_eq!(provider.bytes_received(), 3 * RESUMABLE_CHUNK + data.len());
+        assert_eq!(provider.download(&path).await.unwrap(), data);
+    }
+
+    /// Changes below an excluded path stay staged and never reach the remote.
+    #[tokio::test]
+    async fn test_excluded_paths_are_not_synced() {
+        let provider = Arc::new(MemoryProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        let scratch = VaultPath::parse("/scratch").unwrap();
+        let skipped = VaultPath::parse("/scratch/tmp/a.bin").unwrap();
+        let synced = VaultPath::parse("/scratchpad.bin").unwrap();
+        provider.create_dir(&scratch).await.unwrap();
+        provider
+            .create_dir(&scratch.join("tmp").unwrap())
+            .await
+            .unwrap();
+
+        let config = SyncConfig {
+            exclude: vec![scratch],
+            ..Default::default()
+        };
+        let engine =
+            SyncEngine::from_arc(provider.clone(), staging_dir.path(), staging_key(), config)
+                .await
+                .unwrap();
+        engine
+            .stage_change(&skipped, sealed(b"a"), ChangeType::Create)
+            .await
+            .unwrap();
+        engine
+            .stage_change(&synced, sealed(b"b"), ChangeType::Create)
+            .await
+            .unwrap();
+
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_synced, 1);
+        assert!(!provider.exists(&skipped).await.unwrap());
+        assert!(provider.exists(&synced).await.unwrap());
+
+        let staging = engine.staging.read().await;
+        let remaining: Vec<_> = staging.all_changes().map(|c| &c.vault_path).collect();
+        assert_eq!(remaining, vec![&skipped]);
+    }
+
+    #[tokio::test]
+    async fn test_sync_paths_expands_directory_prefixes() {
+        let provider = Arc::new(MemoryProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        for dir in ["/projects", "/projects/sub", "/other"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let descendants = [
+            VaultPath::parse("/projects/a.bin").unwrap(),
+            VaultPath::parse("/projects/sub/b.bin").unwrap(),
+        ];
+        let single = VaultPath::parse("/other/c.bin").unwrap();
+        let untouched = VaultPath::parse("/other/d.bin").unwrap();
+
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        for path in descendants.iter().chain([&single, &untouched]) {
+            engine
+                .stage_change(path, sealed(b"data"), ChangeType::Create)
+                .await
+                .unwrap();
+        }
+
+        let result = engine
+            .sync_paths(vec!["/projects".to_string(), single.to_string()])
+            .await
+            .unwrap();
+        assert_eq!(result.files_synced, 3);
+        assert_eq!(result.files_failed, 0);
+
+        let state = engine.state.read().await;
+        for path in descendants.iter().chain([&single]) {
+            assert!(provider.exists(path).await.unwrap(), "{path}");
+            assert_eq!(state.get(path).unwrap().status, SyncStatus::Synced);
+        }
+        assert!(!provider.exists(&untouched).await.unwrap());
+
+        let staging = engine.staging.read().await;
+        let remaining: Vec<_> = staging.all_changes().map(|c| &c.vault_path).collect();
+        assert_eq!(remaining, vec![&untouched]);
+    }
+
+    #[tokio::test]
+    async fn test_status_stream_follows_staging_and_sync() {
+        let provider = Arc::new(MemoryProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        let engine = SyncEngine::from_arc(
+            provider,
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let mut status = engine.status_stream();
+        assert!(status.borrow_and_update().is_empty());
+
+        for name in ["/a.bin", "/b.bin"] {
+            engine
+                .stage_change(
+                    &VaultPath::parse(name).unwrap(),
+                    sealed(b"data"),
+                    ChangeType::Create,
+                )
+                .await
+                .unwrap();
+        }
+        assert!(status.has_changed().unwrap());
+        let staged = status.borrow_and_update().clone();
+        assert_eq!(staged, HashMap::from([(SyncStatus::LocalModified, 2)]));
+        assert_eq!(engine.status_snapshot().await, staged);
+
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_synced, 2);
+        assert!(status.has_changed().unwrap());
+        let synced = status.borrow_and_update().clone();
+        assert_eq!(synced, HashMap::from([(SyncStatus::Synced, 2)]));
+        assert_eq!(engine.status_snapshot().await, synced);
+
+        // A sync with nothing to do leaves the counts, and the receiver, alone.
+        engine.sync_full().await.unwrap();
+        assert!(!status.has_changed().unwrap());
+    }
+
+    /// The vault seals a change before it is staged: neither the staging
+    /// directory nor the remote ever holds the plaintext or its path, and
+    /// the remote receives exactly the vault's ciphertext.
+    #[tokio::test]
+    async fn test_sealed_change_round_trip() {
+        use axiomvault_vault::{VaultConfig, VaultOperations, VaultSession, VaultTree};
+
+        let password = b"test-password";
+        let creation = VaultConfig::new(
+            axiomvault_common::VaultId::new("vault").unwrap(),
+            password,
+            "memory",
+            serde_json::Value::Null,
+            axiomvault_crypto::KdfParams::moderate(),
+        )
+        .unwrap();
+        let session = VaultSession::unlock(
+            creation.config,
+            &axiomvault_common::Secret::from_slice(password),
+            Arc::new(MemoryProvider::new()),
+            VaultTree::new(),
+        )
+        .unwrap();
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let remote = Arc::new(MemoryProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        let engine = SyncEngine::from_arc(
+            remote.clone(),
+            staging_dir.path(),
+            StagingKey::for_session(&session).unwrap(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let path = VaultPath::parse("/diary-entry.txt").unwrap();
+        let blob = ops
+            .seal_for_staging(&path, b"dear diary, top secret")
+            .await
+            .unwrap();
+        let ciphertext = blob.ciphertext().clone();
+        engine
+            .stage_change(&path, blob, ChangeType::Create)
+            .await
+            .unwrap();
+
+        let markers: [&[u8]; 2] = [b"top secret", b"diary-entry"];
+        let hits = engine
+            .staging
+            .read()
+            .await
+            .scan_for_plaintext(&markers)
+            .await
+            .unwrap();
+        assert!(hits.is_empty(), "plaintext staged in {:?}", hits);
+
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_synced, 1);
+        assert_eq!(remote.download(&path).await.unwrap(), ciphertext.as_bytes());
+        assert!(engine.staging.read().await.is_empty());
+    }
+
+    /// A crash after the remote operation but before `commit` must not
+    /// replay the change: the upload is not repeated and the delete, which
+    /// would now fail with NotFound, is not retried.
+    /// An empty file whose etag the provider omits after the upload syncs
+    /// once; later passes neither re-upload it nor flag a conflict.
+    #[tokio::test]
+    async fn test_etagless_empty_file_is_not_synced_again() {
+        let provider = Arc::new(CountingProvider::new().etagless());
+        let staging_dir = TempDir::new().unwrap();
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let file = VaultPath::parse("/empty.bin").unwrap();
+
+        engine
+            .stage_change(&file, sealed(b""), ChangeType::Create)
+            .await
+            .unwrap();
+        let first = engine.sync_full().await.unwrap();
+        assert_eq!((first.files_synced, first.conflicts_found), (1, 0));
+
+        for _ in 0..2 {
+            let again = engine.sync_full().await.unwrap();
+            assert_eq!(again.files_synced, 0);
+            assert_eq!(again.conflicts_found, 0);
+            let state = engine.state.read().await;
+            assert_eq!(state.get(&file).unwrap().status, SyncStatus::Synced);
+        }
+        assert_eq!(provider.uploads(), 1);
+
+        // Saving the empty file again is pushed, not flagged.
+        engine
+            .stage_change(&file, sealed(b""), ChangeType::Update)
+            .await
+            .unwrap();
+        let resaved = engine.sync_full().await.unwrap();
+        assert_eq!((resaved.files_synced, resaved.conflicts_found), (1, 0));
+        assert_eq!(provider.uploads(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_crash_between_upload_and_commit_does_not_replay() {
+        let provider = Arc::new(CountingProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        let file = VaultPath::parse("/file.bin").unwrap();
+        let gone = VaultPath::parse("/gone.bin").unwrap();
+        provider
+            .inner()
+            .upload(&gone, b"old".to_vec())
+            .await
+            .unwrap();
+
+        {
+            let engine = SyncEngine::from_arc(
+                provider.clone(),
+                staging_dir.path(),
+                staging_key(),
+                SyncConfig::default(),
+            )
+            .await
+            .unwrap();
+            let upload_id = engine
+                .stage_change(&file, sealed(b"ciphertext"), ChangeType::Create)
+                .await
+                .unwrap();
+            let delete_id = engine.stage_delete(&gone).await.unwrap();
+
+            // Run the remote half of both changes, then stop before commit.
+            assert!(!engine.upload_staged_file(&upload_id, &file).await.unwrap());
+            engine.delete_remote_file(&gone).await.unwrap();
+            engine
+                .staging
+                .write()
+                .await
+                .mark_uploaded(&delete_id, None)
+                .await
+                .unwrap();
+        }
+        assert_eq!(provider.uploads(), 1);
+        assert_eq!(provider.deletes(), 1);
+
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(engine.staging.read().await.count(), 2);
+
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_failed, 0);
+        assert_eq!(result.files_synced, 2);
+        assert_eq!(provider.uploads(), 1);
+        assert_eq!(provider.deletes(), 1);
+        assert!(engine.staging.read().await.is_empty());
+
+        let remote = provider.metadata(&file).await.unwrap();
+        let state = engine.state.read().await;
+        let entry = state.get(&file).unwrap();
+        assert_eq!(entry.status, SyncStatus::Synced);
+        assert_eq!(entry.remote_etag, remote.etag);
+        assert!(state.get(&gone).is_none());
+    }
+
     /// Audit H-1: a successful remote download must NOT increment the
     /// `synced` counter and must NOT update the entry's etag/timestamp,
     /// because the engine has no wired-up local destination yet. It must
@@ -723,9 +2363,14 @@ mod tests {
         let original_remote_meta = provider.metadata(&path).await.unwrap();
 
         let staging_dir = TempDir::new().unwrap();
-        let engine = SyncEngine::new(provider, staging_dir.path(), SyncConfig::default())
-            .await
-            .unwrap();
+        let engine = SyncEngine::new(
+            provider,
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
 
         // Pre-seed sync state with an entry whose status is RemoteModified
         // so download_remote_changes will pick it up. Capture the
@@ -779,4 +2424,786 @@ mod tests {
         let post_remote_meta = engine.provider.metadata(&path).await.unwrap();
         assert_eq!(post_remote_meta.etag, original_remote_meta.etag);
     }
+
+    #[tokio::test]
+    async fn test_conflict_details_reports_both_sides() {
+        let provider = Arc::new(MemoryProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let path = VaultPath::parse("/doc.txt").unwrap();
+
+        engine
+            .stage_change(&path, sealed(b"base"), ChangeType::Create)
+            .await
+            .unwrap();
+        engine.sync_full().await.unwrap();
+        assert!(matches!(
+            engine.conflict_details(&path).await,
+            Err(Error::InvalidInput(_))
+        ));
+
+        // Both sides change before the next sync.
+        provider
+            .upload(&path, b"someone else's edit".to_vec())
+            .await
+            .unwrap();
+        let local = sealed(b"my edit");
+        let local_size = local.ciphertext().as_bytes().len() as u64;
+        engine
+            .stage_change(&path, local, ChangeType::Update)
+            .await
+            .unwrap();
+        engine.sync_full().await.unwrap();
+        assert_eq!(engine.get_conflicts().await, std::slice::from_ref(&path));
+
+        let details = engine.conflict_details(&path).await.unwrap();
+        let entry = engine.state.read().await.get(&path).cloned().unwrap();
+        let remote = provider.metadata(&path).await.unwrap();
+        assert_eq!(details.path, path);
+        assert_eq!(
+            details.local,
+            VersionInfo {
+                etag: entry.local_etag,
+                modified: Some(entry.local_modified),
+                size: Some(local_size),
+            }
+        );
+        assert_eq!(
+            details.remote,
+            VersionInfo {
+                etag: remote.etag,
+                modified: Some(remote.modified),
+                size: Some(19),
+            }
+        );
+        assert!(details.preview.is_none());
+
+        let details = details.with_preview(b"my edit\n", b"someone else's edit\n");
+        assert_eq!(
+            details.preview.unwrap().lines,
+            ["-my edit", "+someone else's edit"]
+        );
+
+        assert!(matches!(
+            engine
+                .conflict_details(&VaultPath::parse("/other.txt").unwrap())
+                .await,
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    /// Two engines sharing `provider`, both starting from `/doc.txt`
+    /// synced by `a`.
+    async fn racing_engines(
+        provider: &Arc<MemoryProvider>,
+        a_config: SyncConfig,
+        b_config: SyncConfig,
+    ) -> (
+        SyncEngine<MemoryProvider>,
+        SyncEngine<MemoryProvider>,
+        VaultPath,
+        [TempDir; 2],
+    ) {
+        let dirs = [TempDir::new().unwrap(), TempDir::new().unwrap()];
+        let a = SyncEngine::from_arc(provider.clone(), dirs[0].path(), staging_key(), a_config)
+            .await
+            .unwrap();
+        let b = SyncEngine::from_arc(provider.clone(), dirs[1].path(), staging_key(), b_config)
+            .await
+            .unwrap();
+        let path = VaultPath::parse("/doc.txt").unwrap();
+
+        a.stage_change(&path, sealed(b"base"), ChangeType::Create)
+            .await
+            .unwrap();
+        a.sync_full().await.unwrap();
+        let etag = provider.metadata(&path).await.unwrap().etag;
+        b.state.write().await.insert(SyncEntry::new_synced(
+            path.to_string(),
+            etag,
+            chrono::Utc::now(),
+        ));
+        (a, b, path, dirs)
+    }
+
+    fn resolving(strategy: ConflictStrategy) -> SyncConfig {
+        SyncConfig {
+            conflict_strategy: strategy,
+            auto_resolve_conflicts: true,
+            trash: Some(TrashConfig::default()),
+            ..Default::default()
+        }
+    }
+
+    async fn pending_changes<P: StorageProvider + ?Sized + 'static>(
+        engine: &SyncEngine<P>,
+    ) -> usize {
+        let staging = engine.staging.read().await;
+        staging.all_changes().filter(|c| !c.is_uploaded()).count()
+    }
+
+    #[tokio::test]
+    async fn test_full_sync_purges_trash_by_engine_clock() {
+        let provider = Arc::new(MemoryProvider::new());
+        let trash = TrashConfig::default();
+        let config = SyncConfig {
+            trash: Some(trash.clone()),
+            ..Default::default()
+        };
+        let dir = TempDir::new().unwrap();
+        let mut engine = SyncEngine::from_arc(provider.clone(), dir.path(), staging_key(), config)
+            .await
+            .unwrap();
+        let clock = MockClock::starting_now();
+        engine.set_clock(clock.clone());
+        let path = VaultPath::parse("/doc.txt").unwrap();
+
+        engine
+            .stage_change(&path, sealed(b"doc"), ChangeType::Create)
+            .await
+            .unwrap();
+        engine.sync_full().await.unwrap();
+        engine.stage_delete(&path).await.unwrap();
+        engine.sync_full().await.unwrap();
+        assert_eq!(trash.list(provider.as_ref()).await.unwrap().len(), 1);
+
+        clock.advance(chrono::Duration::days(29));
+        engine.sync_full().await.unwrap();
+        assert_eq!(trash.list(provider.as_ref()).await.unwrap().len(), 1);
+
+        clock.advance(chrono::Duration::days(2));
+        engine.sync_full().await.unwrap();
+        assert!(trash.list(provider.as_ref()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_edit_after_remote_delete() {
+        let trash = TrashConfig::default();
+        for strategy in [
+            ConflictStrategy::PreferLocal,
+            ConflictStrategy::PreferRemote,
+            ConflictStrategy::KeepBoth,
+        ] {
+            let provider = Arc::new(MemoryProvider::new());
+            let (a, b, path, _dirs) =
+                racing_engines(&provider, resolving(strategy), resolving(strategy)).await;
+
+            a.stage_delete(&path).await.unwrap();
+            a.sync_full().await.unwrap();
+            assert!(!provider.exists(&path).await.unwrap());
+            let trashed = trash.list(provider.as_ref()).await.unwrap();
+            assert_eq!(trashed.len(), 1);
+            assert_eq!(trashed[0].original_path, path);
+
+            b.stage_change(&path, sealed(b"edit"), ChangeType::Update)
+                .await
+                .unwrap();
+            let result = b.sync_full().await.unwrap();
+            assert_eq!(result.conflicts_found, 0, "{:?}", strategy);
+            assert_eq!(pending_changes(&b).await, 0);
+
+            let state = b.state.read().await;
+            let copies: Vec<&SyncEntry> = state
+                .entries()
+                .filter(|e| e.path != path.to_string())
+                .collect();
+            match strategy {
+                ConflictStrategy::PreferLocal => {
+                    assert!(provider.exists(&path).await.unwrap());
+                    assert_eq!(state.get(&path).unwrap().status, SyncStatus::Synced);
+                }
+                ConflictStrategy::PreferRemote => {
+                    assert!(!provider.exists(&path).await.unwrap());
+                    assert!(state.get(&path).is_none());
+                }
+                _ => {
+                    assert!(!provider.exists(&path).await.unwrap());
+                    assert!(state.get(&path).is_none());
+                    assert_eq!(copies.len(), 1);
+                    let copy = VaultPath::parse(&copies[0].path).unwrap();
+                    assert!(provider.exists(&copy).await.unwrap());
+                }
+            }
+            // Whatever B chose, A's copy is still in the trash.
+            assert_eq!(trash.list(provider.as_ref()).await.unwrap(), trashed);
+        }
+
+        // Left to the user, the conflict says which side deleted.
+        let provider = Arc::new(MemoryProvider::new());
+        let manual = SyncConfig {
+            trash: Some(TrashConfig::default()),
+            ..Default::default()
+        };
+        let (a, b, path, _dirs) = racing_engines(&provider, manual.clone(), manual).await;
+        a.stage_delete(&path).await.unwrap();
+        a.sync_full().await.unwrap();
+        b.stage_change(&path, sealed(b"edit"), ChangeType::Update)
+            .await
+            .unwrap();
+        assert_eq!(b.sync_full().await.unwrap().conflicts_found, 1);
+
+        let details = b.conflict_details(&path).await.unwrap();
+        assert_eq!(
+            details.kind,
+            ConflictKind::DeleteVsEdit {
+                deleted: Side::Remote
+            }
+        );
+        assert_eq!(details.remote.etag, None);
+        assert!(details.remote.modified.is_some());
+
+        b.resolve_conflict(&path, Some(sealed(b"edit")), ConflictStrategy::PreferLocal)
+            .await
+            .unwrap();
+        assert!(provider.exists(&path).await.unwrap());
+        assert!(b.get_conflicts().await.is_empty());
+        assert_eq!(pending_changes(&b).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_all_prefer_local() {
+        let provider = Arc::new(MemoryProvider::new());
+        let (a, b, first, _dirs) =
+            racing_engines(&provider, SyncConfig::default(), SyncConfig::default()).await;
+        let mut paths = vec![first];
+        for name in ["/b.txt", "/c.txt"] {
+            let path = VaultPath::parse(name).unwrap();
+            a.stage_change(&path, sealed(b"base"), ChangeType::Create)
+                .await
+                .unwrap();
+            a.sync_full().await.unwrap();
+            let etag = provider.metadata(&path).await.unwrap().etag;
+            b.state.write().await.insert(SyncEntry::new_synced(
+                path.to_string(),
+                etag,
+                chrono::Utc::now(),
+            ));
+            paths.push(path);
+        }
+
+        let mut local = HashMap::new();
+        for path in &paths {
+            a.stage_change(path, sealed(b"remote edit"), ChangeType::Update)
+                .await
+                .unwrap();
+            b.stage_change(path, sealed(b"staged edit"), ChangeType::Update)
+                .await
+                .unwrap();
+            local.insert(path.clone(), sealed(b"local edit"));
+        }
+        a.sync_full().await.unwrap();
+        assert_eq!(b.sync_full().await.unwrap().conflicts_found, 3);
+        assert_eq!(b.get_conflicts().await.len(), 3);
+
+        let outcomes = b
+            .resolve_all(ConflictStrategy::PreferLocal, |path| {
+                std::future::ready(Ok(local.get(&path).cloned()))
+            })
+            .await
+            .unwrap();
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes.iter().all(|(_, result)| result.is_ok()));
+        assert!(b.get_conflicts().await.is_empty());
+
+        let state = b.state.read().await;
+        for path in &paths {
+            assert_eq!(state.get(path).unwrap().status, SyncStatus::Synced);
+            assert_eq!(
+                provider.download(path).await.unwrap(),
+                local[path].ciphertext().as_bytes()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_all_without_local_version_leaves_remote() {
+        let provider = Arc::new(MemoryProvider::new());
+        let (a, b, path, _dirs) =
+            racing_engines(&provider, SyncConfig::default(), SyncConfig::default()).await;
+        a.stage_change(&path, sealed(b"remote edit"), ChangeType::Update)
+            .await
+            .unwrap();
+        a.sync_full().await.unwrap();
+        b.stage_change(&path, sealed(b"local edit"), ChangeType::Update)
+            .await
+            .unwrap();
+        assert_eq!(b.sync_full().await.unwrap().conflicts_found, 1);
+        let remote = provider.download(&path).await.unwrap();
+
+        for strategy in [ConflictStrategy::PreferLocal, ConflictStrategy::KeepBoth] {
+            let outcomes = b
+                .resolve_all(strategy, |_| std::future::ready(Ok(None)))
+                .await
+                .unwrap();
+            assert_eq!(outcomes.len(), 1);
+            assert!(
+                matches!(&outcomes[0].1, Err(Error::NotFound(_))),
+                "{:?}: {:?}",
+                strategy,
+                outcomes[0].1
+            );
+            assert_eq!(provider.download(&path).await.unwrap(), remote);
+            assert_eq!(provider.list(&VaultPath::root()).await.unwrap().len(), 1);
+            assert_eq!(b.get_conflicts().await, vec![path.clone()]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_manual_resolution_never_uploads_plaintext() {
+        const PLAINTEXT: &[u8] = b"meeting notes: the launch moves to friday";
+        let creation = axiomvault_vault::VaultManager::new()
+            .create_vault(
+                axiomvault_common::VaultId::new("vault").unwrap(),
+                &axiomvault_common::Secret::from_slice(b"password"),
+                "memory",
+                serde_json::Value::Null,
+                axiomvault_crypto::KdfParams::interactive(),
+            )
+            .await
+            .unwrap();
+        let ops = axiomvault_vault::VaultOperations::new(&creation.session).unwrap();
+        let doc = VaultPath::parse("/doc.txt").unwrap();
+        ops.create_file(&doc, b"draft").await.unwrap();
+        let contains_plaintext =
+            |data: &[u8]| data.windows(PLAINTEXT.len()).any(|w| w == PLAINTEXT);
+
+        for strategy in [ConflictStrategy::PreferLocal, ConflictStrategy::KeepBoth] {
+            let provider = Arc::new(MemoryProvider::new());
+            let (a, b, path, _dirs) =
+                racing_engines(&provider, SyncConfig::default(), SyncConfig::default()).await;
+            a.stage_change(&path, sealed(b"remote edit"), ChangeType::Update)
+                .await
+                .unwrap();
+            a.sync_full().await.unwrap();
+            b.stage_change(&path, sealed(b"local edit"), ChangeType::Update)
+                .await
+                .unwrap();
+            assert_eq!(b.sync_full().await.unwrap().conflicts_found, 1);
+
+            // What `axiomvault sync-resolve` hands in for the local side.
+            let local = ops.seal_for_staging(&path, PLAINTEXT).await.unwrap();
+            b.resolve_conflict(&path, Some(local), strategy)
+                .await
+                .unwrap();
+
+            let listing = provider.list(&VaultPath::root()).await.unwrap();
+            let mut local_copies = 0;
+            for entry in listing.iter().filter(|m| !m.is_directory) {
+                let stored = VaultPath::root().join(&entry.name).unwrap();
+                let data = provider.download(&stored).await.unwrap();
+                assert!(!contains_plaintext(&data), "{:?}: {}", strategy, entry.name);
+                if ops.open_staged_blob(&path, &data).await.ok().as_deref() == Some(PLAINTEXT) {
+                    local_copies += 1;
+                }
+            }
+            assert_eq!(local_copies, 1, "{:?}", strategy);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_delete_after_remote_edit() {
+        for strategy in [
+            ConflictStrategy::PreferLocal,
+            ConflictStrategy::PreferRemote,
+            ConflictStrategy::KeepBoth,
+        ] {
+            let provider = Arc::new(MemoryProvider::new());
+            let (a, b, path, _dirs) =
+                racing_engines(&provider, resolving(strategy), SyncConfig::default()).await;
+
+            b.stage_change(&path, sealed(b"edit"), ChangeType::Update)
+                .await
+                .unwrap();
+            b.sync_full().await.unwrap();
+            let edited = provider.download(&path).await.unwrap();
+
+            a.stage_delete(&path).await.unwrap();
+            let result = a.sync_full().await.unwrap();
+            assert_eq!(result.conflicts_found, 0, "{:?}", strategy);
+            assert_eq!(pending_changes(&a).await, 0);
+
+            let trashed = TrashConfig::default()
+                .list(provider.as_ref())
+                .await
+                .unwrap();
+            let state = a.state.read().await;
+            match strategy {
+                ConflictStrategy::PreferLocal => {
+                    assert!(!provider.exists(&path).await.unwrap());
+                    assert!(state.get(&path).is_none());
+                    assert_eq!(trashed.len(), 1);
+                    assert_eq!(
+                        provider.download(&trashed[0].location).await.unwrap(),
+                        edited
+                    );
+                }
+                ConflictStrategy::PreferRemote => {
+                    assert_eq!(provider.download(&path).await.unwrap(), edited);
+                    assert_eq!(state.get(&path).unwrap().status, SyncStatus::Synced);
+                    assert!(trashed.is_empty());
+                }
+                _ => {
+                    assert!(!provider.exists(&path).await.unwrap());
+                    assert!(trashed.is_empty());
+                    let copy = state
+                        .entries()
+                        .find(|e| e.path != path.to_string())
+                        .unwrap();
+                    let copy = VaultPath::parse(&copy.path).unwrap();
+                    assert_eq!(provider.download(&copy).await.unwrap(), edited);
+                }
+            }
+        }
+    }
+
+    /// The interactive handler is asked about each conflict in turn and
+    /// its decision is applied.
+    #[tokio::test]
+    async fn test_interactive_handler_decides_each_conflict() {
+        let provider = Arc::new(MemoryProvider::new());
+        let interactive = SyncConfig {
+            conflict_strategy: ConflictStrategy::Interactive,
+            ..Default::default()
+        };
+        let (a, mut b, doc, _dirs) =
+            racing_engines(&provider, SyncConfig::default(), interactive).await;
+        let notes = VaultPath::parse("/notes.txt").unwrap();
+        a.stage_change(&notes, sealed(b"base"), ChangeType::Create)
+            .await
+            .unwrap();
+        a.sync_full().await.unwrap();
+        let etag = provider.metadata(&notes).await.unwrap().etag;
+        b.state.write().await.insert(SyncEntry::new_synced(
+            notes.to_string(),
+            etag,
+            chrono::Utc::now(),
+        ));
+
+        let asked = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = asked.clone();
+        b.set_conflict_handler(Arc::new(move |details: ConflictDetails| {
+            let mut seen = seen.lock().unwrap();
+            seen.push(details.path);
+            let decision = if seen.len() % 2 == 1 {
+                ConflictDecision::KeepLocal
+            } else {
+                ConflictDecision::KeepRemote
+            };
+            Box::pin(async move { decision })
+        }));
+
+        let mut local = HashMap::new();
+        for path in [&doc, &notes] {
+            a.stage_change(path, sealed(b"remote edit"), ChangeType::Update)
+                .await
+                .unwrap();
+            let change_id = b
+                .stage_change(path, sealed(b"local edit"), ChangeType::Update)
+                .await
+                .unwrap();
+            let data = b.staging.read().await.get_staged_data(&change_id).await;
+            local.insert(path.clone(), data.unwrap());
+        }
+        a.sync_full().await.unwrap();
+        let mut remote = HashMap::new();
+        for path in [&doc, &notes] {
+            remote.insert(path.clone(), provider.download(path).await.unwrap());
+        }
+
+        let result = b.sync_full().await.unwrap();
+        assert_eq!(result.conflicts_found, 0);
+        assert_eq!(pending_changes(&b).await, 0);
+        let asked = asked.lock().unwrap().clone();
+        assert_eq!(asked.len(), 2);
+        assert_eq!(
+            provider.download(&asked[0]).await.unwrap(),
+            local[&asked[0]]
+        );
+        assert_eq!(
+            provider.download(&asked[1]).await.unwrap(),
+            remote[&asked[1]]
+        );
+        for path in &asked {
+            assert_eq!(
+                b.state.read().await.get(path).unwrap().status,
+                SyncStatus::Synced
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_delete_waits_for_local_persistence() {
+        let provider = Arc::new(MemoryProvider::new());
+        let (a, b, path, _dirs) =
+            racing_engines(&provider, SyncConfig::default(), SyncConfig::default()).await;
+
+        a.stage_delete(&path).await.unwrap();
+        a.sync_full().await.unwrap();
+        assert!(a.state.read().await.get(&path).is_none());
+
+        let result = b.sync_full().await.unwrap();
+        assert_eq!(result.conflicts_found, 0);
+        assert_eq!(result.pending_persistence, 1);
+        let entry = b.state.read().await.get(&path).cloned().unwrap();
+        assert_eq!(entry.deleted_by, Some(Side::Remote));
+        assert_eq!(entry.remote_etag, None);
+    }
+
+    /// Stage `count` equally sized files and return their blob size.
+    async fn stage_files<P: StorageProvider + ?Sized + 'static>(
+        engine: &SyncEngine<P>,
+        count: usize,
+    ) -> u64 {
+        for i in 0..count {
+            let path = VaultPath::parse(&format!("/file{}.bin", i)).unwrap();
+            engine
+                .stage_change(&path, sealed(b"payload"), ChangeType::Create)
+                .await
+                .unwrap();
+        }
+        let staging = engine.staging.read().await;
+        let size = staging.all_changes().next().unwrap().size;
+        size
+    }
+
+    #[tokio::test]
+    async fn test_quota_exhaustion_pauses_remaining_uploads() {
+        let staging_dir = TempDir::new().unwrap();
+        let provider = Arc::new(CountingProvider::new().with_quota(0));
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let mut events = engine.subscribe();
+        let size = stage_files(&engine, 3).await;
+        // Room for one file and a half: the second upload hits the quota.
+        provider.set_quota_limit(size + size / 2);
+
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_synced, 1);
+        assert_eq!(result.files_failed, 0);
+        assert!(result.quota_exhausted);
+        assert_eq!(result.uploads_paused, 2);
+        assert_eq!(result.paused_bytes, 2 * size);
+        // The third upload was never attempted.
+        assert_eq!(provider.uploads(), 2);
+        assert_eq!(pending_changes(&engine).await, 2);
+
+        // Pre-flight: three files against room for one and a half.
+        let shortfall = QuotaShortfall {
+            needed: 3 * size,
+            remaining: size + size / 2,
+        };
+        assert_eq!(result.quota_shortfall, Some(shortfall));
+        assert_eq!(
+            events.try_recv().unwrap(),
+            SyncEvent::QuotaShortfallPredicted(shortfall)
+        );
+        assert_eq!(
+            events.try_recv().unwrap(),
+            SyncEvent::QuotaExhausted {
+                paused: 2,
+                paused_bytes: 2 * size,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sync_paths_pauses_after_quota_exhaustion() {
+        let staging_dir = TempDir::new().unwrap();
+        let provider = Arc::new(CountingProvider::new().with_quota(0));
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let size = stage_files(&engine, 2).await;
+
+        let result = engine
+            .sync_paths(vec!["/file0.bin".to_string(), "/file1.bin".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(result.files_failed, 0);
+        assert!(result.quota_exhausted);
+        assert_eq!(result.uploads_paused, 2);
+        assert_eq!(result.paused_bytes, 2 * size);
+        assert_eq!(
+            result.quota_shortfall,
+            Some(QuotaShortfall {
+                needed: 2 * size,
+                remaining: 0,
+            })
+        );
+        assert_eq!(provider.uploads(), 1);
+        assert_eq!(pending_changes(&engine).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_within_quota_report_nothing() {
+        let staging_dir = TempDir::new().unwrap();
+        let provider = Arc::new(CountingProvider::new().with_quota(u64::MAX));
+        let engine = SyncEngine::from_arc(
+            provider.clone(),
+            staging_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        let mut events = engine.subscribe();
+        stage_files(&engine, 2).await;
+
+        let result = engine.sync_full().await.unwrap();
+        assert_eq!(result.files_synced, 2);
+        assert!(!result.quota_exhausted);
+        assert_eq!(result.uploads_paused, 0);
+        assert_eq!(result.quota_shortfall, None);
+        assert!(events.try_recv().is_err());
+    }
+
+    /// Remote contents and sync statuses after a sequence of operations.
+    async fn outcome<P: StorageProvider + ?Sized + 'static>(
+        provider: &MemoryProvider,
+        engine: &SyncEngine<P>,
+        paths: &[VaultPath],
+    ) -> Vec<(Option<Vec<u8>>, Option<SyncStatus>)> {
+        let state = engine.state.read().await;
+        let mut outcome = Vec::new();
+        for path in paths {
+            outcome.push((
+                provider.download(path).await.ok(),
+                state.get(path).map(|e| e.status),
+            ));
+        }
+        outcome
+    }
+
+    /// Two engines over one staging directory, as the CLI and the desktop
+    /// app on one vault, end where a single engine doing the same steps
+    /// in the same order does.
+    #[tokio::test]
+    async fn test_engines_sharing_staging_match_serial_execution() {
+        let paths: Vec<VaultPath> = ["/a.txt", "/b.txt", "/c.txt"]
+            .iter()
+            .map(|p| VaultPath::parse(p).unwrap())
+            .collect();
+        let [a, b, c] = [&paths[0], &paths[1], &paths[2]];
+        let blobs = [sealed(b"a"), sealed(b"b"), sealed(b"c")];
+
+        let serial_remote = Arc::new(MemoryProvider::new());
+        let serial_dir = TempDir::new().unwrap();
+        let serial = SyncEngine::from_arc(
+            serial_remote.clone(),
+            serial_dir.path(),
+            staging_key(),
+            SyncConfig::default(),
+        )
+        .await
+        .unwrap();
+        serial
+            .stage_change(a, blobs[0].clone(), ChangeType::Create)
+            .await
+            .unwrap();
+        serial
+            .stage_change(b, blobs[1].clone(), ChangeType::Create)
+            .await
+            .unwrap();
+        serial.sync_full().await.unwrap();
+        serial
+            .stage_change(c, blobs[2].clone(), ChangeType::Create)
+            .await
+            .unwrap();
+        serial.stage_delete(a).await.unwrap();
+        serial.sync_full().await.unwrap();
+
+        let shared_remote = Arc::new(MemoryProvider::new());
+        let shared_dir = TempDir::new().unwrap();
+        let open = || {
+            SyncEngine::from_arc(
+                shared_remote.clone(),
+                shared_dir.path(),
+                staging_key(),
+                SyncConfig::default(),
+            )
+        };
+        let (one, two) = (open().await.unwrap(), open().await.unwrap());
+        one.stage_change(a, blobs[0].clone(), ChangeType::Create)
+            .await
+            .unwrap();
+        two.stage_change(b, blobs[1].clone(), ChangeType::Create)
+            .await
+            .unwrap();
+        two.sync_full().await.unwrap();
+        one.stage_change(c, blobs[2].clone(), ChangeType::Create)
+            .await
+            .unwrap();
+        two.stage_delete(a).await.unwrap();
+        let result = one.sync_full().await.unwrap();
+        assert_eq!(result.conflicts_found, 0);
+        two.share_state().await;
+
+        let expected = outcome(&serial_remote, &serial, &paths).await;
+        assert_eq!(expected[0], (None, None));
+        assert_eq!(outcome(&shared_remote, &one, &paths).await, expected);
+        assert_eq!(outcome(&shared_remote, &two, &paths).await, expected);
+        for engine in [&one, &two] {
+            let mut staging = engine.staging.write().await;
+            staging.refresh().await.unwrap();
+            assert!(staging.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_refused_while_another_process_syncs() {
+        let provider = Arc::new(MemoryProvider::new());
+        let staging_dir = TempDir::new().unwrap();
+        let open = || {
+            SyncEngine::from_arc(
+                provider.clone(),
+                staging_dir.path(),
+                staging_key(),
+                SyncConfig::default(),
+            )
+        };
+        let (one, two) = (open().await.unwrap(), open().await.unwrap());
+        let path = VaultPath::parse("/doc.txt").unwrap();
+        two.stage_change(&path, sealed(b"data"), ChangeType::Create)
+            .await
+            .unwrap();
+
+        let lease = one.staging.read().await.try_sync_lease().unwrap();
+        for result in [
+            two.sync_full().await.map(|_| ()),
+            two.sync_paths(vec![path.to_string()]).await.map(|_| ()),
+            two.resolve_all(ConflictStrategy::PreferLocal, |_| {
+                std::future::ready(Ok(None))
+            })
+            .await
+            .map(|_| ()),
+        ] {
+            match result {
+                Err(Error::SyncInProgress(pid)) => assert_eq!(pid, Some(std::process::id())),
+                other => panic!("expected SyncInProgress, got {:?}", other),
+            }
+        }
+        assert!(provider.download(&path).await.is_err());
+
+        drop(lease);
+        assert_eq!(one.sync_full().await.unwrap().files_synced, 1);
+        assert!(provider.download(&path).await.is_ok());
+    }
 }
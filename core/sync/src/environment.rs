@@ -0,0 +1,139 @@
+//! Device conditions that gate periodic sync.
+//!
+//! The scheduler asks an [`EnvironmentProbe`] whether the device is on a
+//! metered connection or running on battery, and what the local time is,
+//! before each periodic sync. Desktop builds read OS hints with
+//! [`DesktopProbe`]; mobile shells know better than the core and push their
+//! state into a [`PushedEnvironment`] instead.
+
+use std::sync::{Arc, RwLock};
+
+use chrono::{Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+/// Connectivity and power state of the device.
+///
+/// Fields missing from pushed JSON take the permissive default, so an
+/// unknown condition never blocks sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceEnvironment {
+    /// Whether the active connection is metered (mobile data, hotspot).
+    pub metered: bool,
+    /// Whether the device is on external power.
+    pub on_power: bool,
+}
+
+impl Default for DeviceEnvironment {
+    fn default() -> Self {
+        Self {
+            metered: false,
+            on_power: true,
+        }
+    }
+}
+
+/// Source of the device conditions the scheduler checks.
+pub trait EnvironmentProbe: Send + Sync {
+    /// Current connectivity and power state.
+    fn environment(&self) -> DeviceEnvironment;
+
+    /// Current local wall-clock time, for quiet hours.
+    fn local_time(&self) -> NaiveTime {
+        Local::now().time()
+    }
+}
+
+/// Environment state pushed in from outside, e.g. by a mobile shell.
+///
+/// Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct PushedEnvironment {
+    state: Arc<RwLock<DeviceEnvironment>>,
+}
+
+impl PushedEnvironment {
+    /// Create with the permissive default state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the current state.
+    pub fn set(&self, environment: DeviceEnvironment) {
+        *self.state.write().unwrap_or_else(|e| e.into_inner()) = environment;
+    }
+}
+
+impl EnvironmentProbe for PushedEnvironment {
+    fn environment(&self) -> DeviceEnvironment {
+        *self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Reads power state from the OS where it is exposed.
+///
+/// On Linux the device counts as on power when any mains supply in
+/// `/sys/class/power_supply` is online, or when it has no battery at all.
+/// Metered connections are not detectable without a network manager, so
+/// they are reported as unmetered; elsewhere the default state is reported.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DesktopProbe;
+
+impl EnvironmentProbe for DesktopProbe {
+    fn environment(&self) -> DeviceEnvironment {
+        DeviceEnvironment {
+            metered: false,
+            on_power: on_external_power(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn on_external_power() -> bool {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return true;
+    };
+    let read = |dir: &std::path::Path, file: &str| {
+        std::fs::read_to_string(dir.join(file))
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut has_battery = false;
+    for supply in supplies.flatten() {
+        let dir = supply.path();
+        match read(&dir, "type").as_str() {
+            "Mains" | "USB" if read(&dir, "online") == "1" => return true,
+            "Battery" => has_battery = true,
+            _ => {}
+        }
+    }
+    !has_battery
+}
+
+#[cfg(not(target_os = "linux"))]
+fn on_external_power() -> bool {
+    DeviceEnvironment::default().on_power
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pushed_environment_is_shared_and_parses_partial_json() {
+        let pushed = PushedEnvironment::new();
+        let probe: Arc<dyn EnvironmentProbe> = Arc::new(pushed.clone());
+        assert_eq!(probe.environment(), DeviceEnvironment::default());
+
+        let update: DeviceEnvironment = serde_json::from_str(r#"{"metered": true}"#).unwrap();
+        pushed.set(update);
+        assert_eq!(
+            probe.environment(),
+            DeviceEnvironment {
+                metered: true,
+                on_power: true,
+            }
+        );
+    }
+}
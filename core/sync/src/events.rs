@@ -0,0 +1,28 @@
+//! Events the sync engine publishes while a sync runs.
+//!
+//! Subscribers receive [`SyncEvent`]s over a tokio broadcast channel via
+//! [`SyncEngine::subscribe`](crate::SyncEngine::subscribe). A slow receiver
+//! lags rather than blocking the sync.
+
+use serde::{Deserialize, Serialize};
+
+use crate::quota::QuotaShortfall;
+
+/// Receiving half of the engine's event channel.
+pub type SyncEventReceiver = tokio::sync::broadcast::Receiver<SyncEvent>;
+
+/// Something a UI should tell the user about while syncing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncEvent {
+    /// The planned uploads need more space than the provider has left.
+    /// Sent before uploading; the sync still runs.
+    QuotaShortfallPredicted(QuotaShortfall),
+    /// The provider ran out of space and the remaining uploads were
+    /// paused. They stay staged for the next sync.
+    QuotaExhausted {
+        /// Uploads left staged.
+        paused: usize,
+        /// Bytes of the paused uploads.
+        paused_bytes: u64,
+    },
+}
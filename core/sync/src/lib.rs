@@ -2,25 +2,50 @@
 //!
 //! This module provides synchronization capabilities for AxiomVault, including:
 //! - Two sync modes: on-demand and periodic
-//! - Local staging area for atomic writes
-//! - Conflict detection and resolution
+//! - Local staging area for atomic writes, shareable by several processes
+//! - Conflict detection and resolution, including delete-vs-edit races
+//! - A remote trash that keeps files deleted by sync recoverable
+//! - Side-effect-free planning of sync actions
 //! - Retry strategy with exponential backoff
+//! - Quota awareness: a pre-flight space check and pausing uploads once
+//!   the provider is full
+//! - Periodic sync constraints: quiet hours, unmetered networks and
+//!   external power
 //! - Background task coordination
 
 pub mod conflict;
 pub mod engine;
+pub mod environment;
+pub mod events;
+pub mod planner;
+pub mod quota;
 pub mod retry;
 pub mod scheduler;
 pub mod staging;
 pub mod state;
+pub mod trash;
 
 // Re-export main types
-pub use conflict::{ConflictInfo, ConflictResolver, ConflictStrategy, ResolutionResult};
-pub use engine::{SyncConfig, SyncEngine};
+pub use conflict::{
+    ConflictDecision, ConflictDetails, ConflictHandler, ConflictInfo, ConflictKind, ConflictNaming,
+    ConflictResolver, ConflictStrategy, DiffPreview, LocalVersion, ResolutionResult, Side,
+    VersionInfo,
+};
+pub use engine::{ShutdownOutcome, SyncConfig, SyncEngine};
+pub use environment::{DesktopProbe, DeviceEnvironment, EnvironmentProbe, PushedEnvironment};
+pub use events::{SyncEvent, SyncEventReceiver};
+pub use planner::{PlanInput, PlannedAction};
+pub use quota::QuotaShortfall;
 pub use retry::{retry, retry_with_config, RetryConfig, RetryExecutor};
-pub use scheduler::{SyncMode, SyncRequest, SyncResult, SyncScheduler, SyncSchedulerHandle};
-pub use staging::{ChangeType, StagedChange, StagingArea};
-pub use state::{SyncEntry, SyncState, SyncStatus};
+pub use scheduler::{
+    SkipReason, SyncConstraints, SyncMode, SyncRequest, SyncResult, SyncScheduler,
+    SyncSchedulerHandle,
+};
+pub use staging::{
+    ChangeType, StagedBlob, StagedChange, StagingArea, StagingKey, SyncLease, UploadJournal,
+};
+pub use state::{SkippedSync, StatusSnapshot, SyncEntry, SyncState, SyncStatus};
+pub use trash::{TrashConfig, TrashedFile};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,1589 @@
+//! Sync planning.
+//!
+//! Decides what a sync pass should do from snapshots of the sync state, the
+//! staged changes and remote metadata. Everything here is synchronous and
+//! free of IO; [`SyncEngine`](crate::SyncEngine) fetches the snapshots,
+//! asks the planner, and executes the resulting actions with retries.
+
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+
+use axiomvault_common::VaultPath;
+use axiomvault_storage::{compare_content_hashes, HashComparison, Metadata};
+
+use crate::conflict::Side;
+use crate::engine::SyncConfig;
+use crate::staging::{ChangeType, StagedChange};
+use crate::state::{SyncEntry, SyncStatus};
+
+/// A single step of a sync pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedAction {
+    /// Finish a change that reached the remote in an earlier run but was
+    /// never committed. The remote is not touched again.
+    Commit { change_id: String, path: VaultPath },
+    /// Upload staged content; the remote has not diverged.
+    Upload { change_id: String, path: VaultPath },
+    /// Delete the remote file for a staged delete.
+    DeleteRemote { change_id: String, path: VaultPath },
+    /// Both sides changed since the last sync, or one side deleted the file
+    /// the other edited; resolve or flag the entry.
+    Conflict { change_id: String, path: VaultPath },
+    /// Record a remote change on an entry that has nothing staged.
+    RecordRemoteChange {
+        path: VaultPath,
+        etag: Option<String>,
+        content_hash: Option<String>,
+        size: Option<u64>,
+        modified: DateTime<Utc>,
+        /// Entry status once the change is recorded.
+        status: SyncStatus,
+    },
+    /// Record that the remote file of an entry with nothing staged is gone.
+    RecordRemoteDelete {
+        path: VaultPath,
+        /// Entry status once the delete is recorded.
+        status: SyncStatus,
+    },
+    /// Fetch the remote version of an entry.
+    Download { path: VaultPath },
+    /// Apply a recorded remote delete locally.
+    DeleteLocal { path: VaultPath },
+}
+
+impl PlannedAction {
+    /// Path the action applies to.
+    pub fn path(&self) -> &VaultPath {
+        match self {
+            PlannedAction::Commit { path, .. }
+            | PlannedAction::Upload { path, .. }
+            | PlannedAction::DeleteRemote { path, .. }
+            | PlannedAction::Conflict { path, .. }
+            | PlannedAction::RecordRemoteChange { path, .. }
+            | PlannedAction::RecordRemoteDelete { path, .. }
+            | PlannedAction::Download { path }
+            | PlannedAction::DeleteLocal { path } => path,
+        }
+    }
+}
+
+/// Snapshots a sync pass is planned from.
+#[derive(Debug, Clone, Copy)]
+pub struct PlanInput<'a> {
+    /// Sync state entries.
+    pub entries: &'a [SyncEntry],
+    /// Staged changes, uploaded or not.
+    pub changes: &'a [StagedChange],
+    /// Remote metadata by path. A path missing here and from `gone` could
+    /// not be checked.
+    pub remote: &'a HashMap<String, Metadata>,
+    /// Paths the remote reported as not existing.
+    pub gone: &'a HashSet<String>,
+    /// Engine configuration.
+    pub config: &'a SyncConfig,
+}
+
+impl PlanInput<'_> {
+    /// What the snapshot says about the remote file at `path`.
+    fn remote_file(&self, path: &str) -> RemoteFile<'_> {
+        RemoteFile::of(path, self.remote, self.gone)
+    }
+}
+
+/// What a snapshot says about the remote copy of one path.
+#[derive(Debug, Clone, Copy)]
+pub enum RemoteFile<'a> {
+    /// The remote has a file there.
+    Present(&'a Metadata),
+    /// The remote reported that there is no file.
+    Gone,
+    /// The remote could not be checked.
+    Unknown,
+}
+
+impl<'a> RemoteFile<'a> {
+    /// Look `path` up in a remote snapshot.
+    pub fn of(path: &str, remote: &'a HashMap<String, Metadata>, gone: &HashSet<String>) -> Self {
+        match remote.get(path) {
+            Some(metadata) => Self::Present(metadata),
+            None if gone.contains(path) => Self::Gone,
+            None => Self::Unknown,
+        }
+    }
+
+    /// Metadata of a present file.
+    pub fn metadata(self) -> Option<&'a Metadata> {
+        match self {
+            Self::Present(metadata) => Some(metadata),
+            Self::Gone | Self::Unknown => None,
+        }
+    }
+}
+
+/// Whether both sides changed since the last known synced state.
+///
+/// If only the local side changed (remote etag still matches the last-known
+/// value) we can safely push; if only the remote side changed we can safely
+/// pull.
+pub fn both_changed(
+    local_etag: Option<&str>,
+    remote_etag: Option<&str>,
+    last_known_remote_etag: Option<&str>,
+) -> bool {
+    if local_etag == remote_etag {
+        return false;
+    }
+
+    // With a baseline, conflict only when the remote diverged from what we
+    // last knew *and* the local side also diverged.
+    if let Some(last_known) = last_known_remote_etag {
+        let remote_changed = remote_etag != Some(last_known);
+        let local_changed = local_etag != Some(last_known);
+        return remote_changed && local_changed;
+    }
+
+    // Without a baseline we cannot tell who changed; treat as conflict only
+    // when both sides have an etag and they differ.
+    local_etag.is_some() && remote_etag.is_some()
+}
+
+/// Whether `remote` is the remote version the entry last recorded.
+pub fn remote_unchanged(entry: &SyncEntry, remote: &Metadata) -> bool {
+    same_remote_version(
+        entry,
+        remote.etag.as_deref(),
+        remote.content_hash.as_deref(),
+        remote.size,
+    )
+}
+
+/// Whether a remote version with `etag`, `content_hash` and `size` is the
+/// one the entry last recorded.
+///
+/// Content hashes of the same algorithm showing identical content decide
+/// first; hashes of different algorithms, or a missing hash, prove
+/// nothing. Then etags decide when both sides have one. Providers may omit
+/// the etag, e.g. Drive's `md5Checksum` of a zero-length file, and with one
+/// missing the recorded size stands in for it; without a recorded size the
+/// etags have to match as they are.
+pub(crate) fn same_remote_version(
+    entry: &SyncEntry,
+    etag: Option<&str>,
+    content_hash: Option<&str>,
+    size: Option<u64>,
+) -> bool {
+    if compare_content_hashes(entry.remote_content_hash.as_deref(), content_hash)
+        == HashComparison::Equal
+    {
+        return true;
+    }
+    match (entry.remote_etag.as_deref(), etag) {
+        (Some(recorded), Some(etag)) => recorded == etag,
+        _ => match entry.remote_size {
+            Some(recorded) => size == Some(recorded),
+            None => entry.remote_etag.as_deref() == etag,
+        },
+    }
+}
+
+/// Whether the entry knew about a remote file, by etag or by size.
+fn knew_remote_file(entry: &SyncEntry) -> bool {
+    entry.remote_etag.is_some() || entry.remote_size.is_some()
+}
+
+/// Status of an entry after a local change is staged.
+pub fn status_after_local_change(status: SyncStatus) -> SyncStatus {
+    match status {
+        SyncStatus::Synced => SyncStatus::LocalModified,
+        other => other,
+    }
+}
+
+/// Status of an entry after a new remote etag is observed.
+pub fn status_after_remote_change(status: SyncStatus) -> SyncStatus {
+    match status {
+        SyncStatus::Synced => SyncStatus::RemoteModified,
+        SyncStatus::LocalModified => SyncStatus::Conflicted,
+        other => other,
+    }
+}
+
+/// Whether the remote deleted a file the entry knew about.
+fn deleted_remotely(entry: &SyncEntry, remote: RemoteFile<'_>) -> bool {
+    matches!(remote, RemoteFile::Gone)
+        && (knew_remote_file(entry) || entry.deleted_by == Some(Side::Remote))
+}
+
+/// Decide how to push a staged create or update for `path`.
+///
+/// An entry already in conflict stays in conflict until it is resolved, so
+/// a later pass cannot silently overwrite the remote version. An edit of a
+/// file the remote deleted is a conflict too, rather than a silent
+/// re-create.
+pub fn upload_action(
+    change_id: &str,
+    path: &VaultPath,
+    entry: Option<&SyncEntry>,
+    remote: RemoteFile<'_>,
+) -> PlannedAction {
+    let change_id = change_id.to_string();
+    let path = path.clone();
+    match (entry, remote) {
+        (Some(entry), _) if entry.status == SyncStatus::Conflicted => {
+            PlannedAction::Conflict { change_id, path }
+        }
+        (Some(entry), RemoteFile::Present(remote))
+            if both_changed(
+                entry.local_etag.as_deref(),
+                remote.etag.as_deref(),
+                entry.remote_etag.as_deref(),
+            ) && !remote_unchanged(entry, remote) =>
+        {
+            PlannedAction::Conflict { change_id, path }
+        }
+        (Some(entry), remote) if deleted_remotely(entry, remote) => {
+            PlannedAction::Conflict { change_id, path }
+        }
+        _ => PlannedAction::Upload { change_id, path },
+    }
+}
+
+/// Decide how to push a staged delete for `path`.
+///
+/// A delete of a file the remote edited since the last sync is a conflict,
+/// so the edit is not destroyed unseen.
+pub fn delete_action(
+    change_id: &str,
+    path: &VaultPath,
+    entry: Option<&SyncEntry>,
+    remote: RemoteFile<'_>,
+) -> PlannedAction {
+    let change_id = change_id.to_string();
+    let path = path.clone();
+    match (entry, remote) {
+        (Some(entry), _) if entry.status == SyncStatus::Conflicted => {
+            PlannedAction::Conflict { change_id, path }
+        }
+        (Some(entry), RemoteFile::Present(remote))
+            if both_changed(None, remote.etag.as_deref(), entry.remote_etag.as_deref())
+                && !remote_unchanged(entry, remote) =>
+        {
+            PlannedAction::Conflict { change_id, path }
+        }
+        _ => PlannedAction::DeleteRemote { change_id, path },
+    }
+}
+
+/// Staged changes in the order they are applied: oldest first, ties broken
+/// by change ID.
+fn ordered_changes(changes: &[StagedChange]) -> Vec<&StagedChange> {
+    let mut ordered: Vec<&StagedChange> = changes.iter().collect();
+    ordered.sort_by(|a, b| a.staged_at.cmp(&b.staged_at).then(a.id.cmp(&b.id)));
+    ordered
+}
+
+/// Entries ordered by path, skipping excluded paths.
+fn ordered_entries<'a>(
+    entries: impl IntoIterator<Item = &'a SyncEntry>,
+    config: &SyncConfig,
+) -> Vec<(VaultPath, &'a SyncEntry)> {
+    let mut ordered: Vec<(VaultPath, &SyncEntry)> = entries
+        .into_iter()
+        .filter_map(|e| VaultPath::parse(&e.path).ok().map(|p| (p, e)))
+        .filter(|(p, _)| !config.is_excluded(p))
+        .collect();
+    ordered.sort_by(|a, b| a.1.path.cmp(&b.1.path));
+    ordered
+}
+
+/// Paths whose remote metadata [`plan_uploads`] needs.
+///
+/// Only pending changes of tracked entries are checked for conflicts;
+/// changes to untracked paths are pushed without looking.
+pub fn upload_check_paths(
+    entries: &[SyncEntry],
+    changes: &[StagedChange],
+    config: &SyncConfig,
+) -> Vec<VaultPath> {
+    let tracked: HashSet<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+    let mut seen = HashSet::new();
+    ordered_changes(changes)
+        .into_iter()
+        .filter(|c| !c.is_uploaded())
+        .filter(|c| !config.is_excluded(&c.vault_path))
+        .filter(|c| tracked.contains(c.vault_path.to_string().as_str()))
+        .filter(|c| seen.insert(c.vault_path.clone()))
+        .map(|c| c.vault_path.clone())
+        .collect()
+}
+
+/// Plan the upload phase: finish already-uploaded changes, then push every
+/// pending change that is not excluded.
+///
+/// Only the first pending change of a path is checked against the remote;
+/// later ones build on what this pass did. Once a path turns
+/// out to be in conflict its remaining changes stay staged until the
+/// conflict is settled.
+pub fn plan_uploads(input: &PlanInput<'_>) -> Vec<PlannedAction> {
+    let entries = committed_entries(input);
+    let changes = ordered_changes(input.changes);
+
+    let mut actions: Vec<PlannedAction> = changes
+        .iter()
+        .filter(|c| c.is_uploaded())
+        .map(|c| PlannedAction::Commit {
+            change_id: c.id.clone(),
+            path: c.vault_path.clone(),
+        })
+        .collect();
+
+    let mut settled: HashSet<VaultPath> = HashSet::new();
+    let mut conflicted: HashSet<VaultPath> = HashSet::new();
+    for change in changes.iter().filter(|c| !c.is_uploaded()) {
+        let path = &change.vault_path;
+        if input.config.is_excluded(path) || conflicted.contains(path) {
+            continue;
+        }
+        let change_id = change.id.clone();
+        let key = path.to_string();
+        let action = match change.change_type {
+            ChangeType::Delete if settled.contains(path) => PlannedAction::DeleteRemote {
+                change_id,
+                path: path.clone(),
+            },
+            ChangeType::Delete => {
+                delete_action(&change_id, path, entries.get(&key), input.remote_file(&key))
+            }
+            ChangeType::Create | ChangeType::Update if settled.contains(path) => {
+                PlannedAction::Upload {
+                    change_id,
+                    path: path.clone(),
+                }
+            }
+            ChangeType::Create | ChangeType::Update => {
+                upload_action(&change_id, path, entries.get(&key), input.remote_file(&key))
+            }
+        };
+        if matches!(action, PlannedAction::Conflict { .. }) {
+            conflicted.insert(path.clone());
+        } else {
+            settled.insert(path.clone());
+        }
+        actions.push(action);
+    }
+
+    actions
+}
+
+/// Paths whose remote metadata [`plan_remote_changes`] needs.
+pub fn remote_check_paths(entries: &[SyncEntry], config: &SyncConfig) -> Vec<VaultPath> {
+    ordered_entries(entries, config)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Plan recording remote changes on tracked entries.
+///
+/// An entry changed remotely unless [`remote_unchanged`] holds, and was
+/// deleted remotely when a file it knew about is gone. Entries whose remote
+/// could not be checked are left alone.
+pub fn plan_remote_changes<'a>(
+    entries: impl IntoIterator<Item = &'a SyncEntry>,
+    remote: &HashMap<String, Metadata>,
+    gone: &HashSet<String>,
+    config: &SyncConfig,
+) -> Vec<PlannedAction> {
+    ordered_entries(entries, config)
+        .into_iter()
+        .filter_map(
+            |(path, entry)| match RemoteFile::of(&entry.path, remote, gone) {
+                RemoteFile::Present(remote) => {
+                    let unchanged = remote_unchanged(entry, remote);
+                    // A new etag is recorded even for the same version; one
+                    // that only went missing is not.
+                    let new_etag = remote.etag.is_some() && entry.remote_etag != remote.etag;
+                    (!unchanged || new_etag).then(|| PlannedAction::RecordRemoteChange {
+                        path,
+                        etag: remote.etag.clone(),
+                        content_hash: remote.content_hash.clone(),
+                        size: remote.size,
+                        modified: remote.modified,
+                        // A rewrite of identical content is not a change.
+                        status: if unchanged {
+                            entry.status
+                        } else {
+                            status_after_remote_change(entry.status)
+                        },
+                    })
+                }
+                RemoteFile::Gone => {
+                    knew_remote_file(entry).then(|| PlannedAction::RecordRemoteDelete {
+                        path,
+                        status: status_after_remote_change(entry.status),
+                    })
+                }
+                RemoteFile::Unknown => None,
+            },
+        )
+        .collect()
+}
+
+/// Action that brings an entry waiting on the remote up to date.
+fn download_action(path: VaultPath, remote_deleted: bool) -> PlannedAction {
+    if remote_deleted {
+        PlannedAction::DeleteLocal { path }
+    } else {
+        PlannedAction::Download { path }
+    }
+}
+
+/// Plan downloads, or local deletes, for every entry that is waiting on a
+/// remote change.
+pub fn plan_downloads<'a>(
+    entries: impl IntoIterator<Item = &'a SyncEntry>,
+    config: &SyncConfig,
+) -> Vec<PlannedAction> {
+    ordered_entries(entries, config)
+        .into_iter()
+        .filter(|(_, entry)| entry.status == SyncStatus::RemoteModified)
+        .map(|(path, entry)| download_action(path, entry.deleted_by == Some(Side::Remote)))
+        .collect()
+}
+
+/// Expand requested paths to the tracked paths they cover.
+///
+/// A requested path that is tracked itself is kept. Otherwise it is treated
+/// as a directory and replaced by every tracked path below it, in path
+/// order; if nothing is tracked below it either, it is kept so the caller
+/// can report on it. Duplicates are dropped.
+pub fn expand_paths(requested: &[VaultPath], tracked: &[VaultPath]) -> Vec<VaultPath> {
+    let mut tracked: Vec<&VaultPath> = tracked.iter().collect();
+    tracked.sort_by_key(|p| p.to_string());
+    let exact: HashSet<&VaultPath> = tracked.iter().copied().collect();
+
+    let mut seen = HashSet::new();
+    let mut expanded = Vec::new();
+    for path in requested {
+        let descendants: Vec<&VaultPath> = if exact.contains(path) {
+            vec![path]
+        } else {
+            tracked
+                .iter()
+                .copied()
+                .filter(|p| p.starts_with(path))
+                .collect()
+        };
+        let covered = if descendants.is_empty() {
+            vec![path]
+        } else {
+            descendants
+        };
+        for p in covered {
+            if seen.insert(p.clone()) {
+                expanded.push(p.clone());
+            }
+        }
+    }
+    expanded
+}
+
+/// Entry for a change's path once the change, already uploaded, is
+/// committed. `None` means the path is no longer tracked.
+pub fn entry_after_commit(entry: Option<SyncEntry>, change: &StagedChange) -> Option<SyncEntry> {
+    match change.change_type {
+        ChangeType::Delete => None,
+        ChangeType::Create | ChangeType::Update => {
+            // Without an etag (conflict resolution) the resolver already
+            // settled the entry.
+            let Some(etag) = change.remote_etag.clone() else {
+                return entry;
+            };
+            let modified = change.uploaded_at.unwrap_or_else(Utc::now);
+            match entry {
+                Some(mut entry) => {
+                    entry.mark_synced(Some(etag), modified);
+                    Some(entry)
+                }
+                None => Some(SyncEntry::new_synced(
+                    change.vault_path.to_string(),
+                    Some(etag),
+                    modified,
+                )),
+            }
+        }
+    }
+}
+
+/// Entries by path as they stand once every already-uploaded change is
+/// committed, which the engine does before anything else.
+fn committed_entries(input: &PlanInput<'_>) -> HashMap<String, SyncEntry> {
+    let mut entries: HashMap<String, SyncEntry> = input
+        .entries
+        .iter()
+        .map(|e| (e.path.clone(), e.clone()))
+        .collect();
+    for change in ordered_changes(input.changes)
+        .into_iter()
+        .filter(|c| c.is_uploaded())
+    {
+        let path = change.vault_path.to_string();
+        if let Some(entry) = entry_after_commit(entries.remove(&path), change) {
+            entries.insert(path, entry);
+        }
+    }
+    entries
+}
+
+/// Plan a full sync pass from a single set of snapshots.
+///
+/// Paths with pending staged changes are settled by the upload phase, so
+/// remote changes and downloads are only planned for the others, as they
+/// stand once already-uploaded changes are committed.
+pub fn plan(input: &PlanInput<'_>) -> Vec<PlannedAction> {
+    let mut actions = plan_uploads(input);
+
+    let committed = committed_entries(input);
+    let pending: HashSet<String> = input
+        .changes
+        .iter()
+        .filter(|c| !c.is_uploaded())
+        .map(|c| c.vault_path.to_string())
+        .collect();
+    let unstaged: Vec<&SyncEntry> = committed
+        .values()
+        .filter(|e| !pending.contains(&e.path))
+        .collect();
+
+    let recorded = plan_remote_changes(
+        unstaged.iter().copied(),
+        input.remote,
+        input.gone,
+        input.config,
+    );
+    // Waiting entries by path, and whether the remote deleted them.
+    let mut pending_download: HashMap<VaultPath, bool> = unstaged
+        .iter()
+        .filter(|e| e.status == SyncStatus::RemoteModified)
+        .filter_map(|e| {
+            VaultPath::parse(&e.path)
+                .ok()
+                .map(|p| (p, e.deleted_by == Some(Side::Remote)))
+        })
+        .collect();
+    for action in &recorded {
+        match action {
+            PlannedAction::RecordRemoteChange { path, status, .. }
+                if *status == SyncStatus::RemoteModified =>
+            {
+                pending_download.insert(path.clone(), false);
+            }
+            PlannedAction::RecordRemoteDelete { path, status }
+                if *status == SyncStatus::RemoteModified =>
+            {
+                pending_download.insert(path.clone(), true);
+            }
+            _ => {}
+        }
+    }
+    actions.extend(recorded);
+
+    let mut downloads: Vec<(VaultPath, bool)> = pending_download
+        .into_iter()
+        .filter(|(p, _)| !input.config.is_excluded(p))
+        .collect();
+    downloads.sort_by_key(|(p, _)| p.to_string());
+    actions.extend(
+        downloads
+            .into_iter()
+            .map(|(path, deleted)| download_action(path, deleted)),
+    );
+
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn vp(path: &str) -> VaultPath {
+        VaultPath::parse(path).unwrap()
+    }
+
+    fn entry(
+        path: &str,
+        status: SyncStatus,
+        local: Option<&str>,
+        remote: Option<&str>,
+    ) -> SyncEntry {
+        SyncEntry {
+            path: path.to_string(),
+            local_etag: local.map(str::to_string),
+            remote_etag: remote.map(str::to_string),
+            remote_content_hash: None,
+            remote_size: None,
+            local_modified: at(0),
+            remote_modified: remote.map(|_| at(0)),
+            status,
+            last_synced: None,
+            failure_count: 0,
+            last_error: None,
+            deleted_at: None,
+            deleted_by: None,
+            updated_at: at(0),
+        }
+    }
+
+    fn change(id: &str, path: &str, change_type: ChangeType, staged: i64) -> StagedChange {
+        StagedChange {
+            id: id.to_string(),
+            vault_path: vp(path),
+            change_type,
+            staged_at: at(staged),
+            staging_file: None,
+            size: 0,
+            node_id: None,
+            format_version: None,
+            uploaded_at: None,
+            remote_etag: None,
+            upload_session: None,
+        }
+    }
+
+    fn uploaded(mut change: StagedChange, etag: Option<&str>) -> StagedChange {
+        change.uploaded_at = Some(change.staged_at);
+        change.remote_etag = etag.map(str::to_string);
+        change
+    }
+
+    fn meta(path: &str, etag: Option<&str>, modified: i64) -> Metadata {
+        Metadata {
+            id: path.to_string(),
+            name: path.rsplit('/').next().unwrap_or_default().to_string(),
+            size: Some(0),
+            is_directory: false,
+            modified: at(modified),
+            etag: etag.map(str::to_string),
+            content_hash: None,
+            provider_data: None,
+        }
+    }
+
+    fn remote_of(files: &[(&str, Option<&str>)]) -> HashMap<String, Metadata> {
+        files
+            .iter()
+            .map(|(path, etag)| (path.to_string(), meta(path, *etag, 10)))
+            .collect()
+    }
+
+    fn run(
+        entries: &[SyncEntry],
+        changes: &[StagedChange],
+        remote: &HashMap<String, Metadata>,
+        config: &SyncConfig,
+    ) -> Vec<PlannedAction> {
+        run_with_gone(entries, changes, remote, &[], config)
+    }
+
+    fn run_with_gone(
+        entries: &[SyncEntry],
+        changes: &[StagedChange],
+        remote: &HashMap<String, Metadata>,
+        gone: &[&str],
+        config: &SyncConfig,
+    ) -> Vec<PlannedAction> {
+        let gone: HashSet<String> = gone.iter().map(|p| p.to_string()).collect();
+        plan(&PlanInput {
+            entries,
+            changes,
+            remote,
+            gone: &gone,
+            config,
+        })
+    }
+
+    fn upload(id: &str, path: &str) -> PlannedAction {
+        PlannedAction::Upload {
+            change_id: id.to_string(),
+            path: vp(path),
+        }
+    }
+
+    fn conflict(id: &str, path: &str) -> PlannedAction {
+        PlannedAction::Conflict {
+            change_id: id.to_string(),
+            path: vp(path),
+        }
+    }
+
+    fn record(path: &str, etag: &str, status: SyncStatus) -> PlannedAction {
+        PlannedAction::RecordRemoteChange {
+            path: vp(path),
+            etag: Some(etag.to_string()),
+            content_hash: None,
+            size: Some(0),
+            modified: at(10),
+            status,
+        }
+    }
+
+    fn download(path: &str) -> PlannedAction {
+        PlannedAction::Download { path: vp(path) }
+    }
+
+    const ALL_STATUSES: [SyncStatus; 6] = [
+        SyncStatus::Synced,
+        SyncStatus::LocalModified,
+        SyncStatus::RemoteModified,
+        SyncStatus::Conflicted,
+        SyncStatus::Syncing,
+        SyncStatus::Failed,
+    ];
+
+    #[test]
+    fn test_both_changed() {
+        let cases = [
+            // (local, remote, last known remote, conflict)
+            (Some("a"), Some("a"), Some("a"), false),
+            (None, None, None, false),
+            (Some("l"), Some("base"), Some("base"), false),
+            (Some("base"), Some("r"), Some("base"), false),
+            (Some("l"), Some("r"), Some("base"), true),
+            (Some("l"), Some("r"), None, true),
+            (Some("l"), None, None, false),
+            (None, Some("r"), None, false),
+            (None, Some("r"), Some("base"), true),
+        ];
+        for (local, remote, base, expected) in cases {
+            assert_eq!(
+                both_changed(local, remote, base),
+                expected,
+                "local={local:?} remote={remote:?} base={base:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        let cases = [
+            // (status, after local change, after remote change)
+            (
+                SyncStatus::Synced,
+                SyncStatus::LocalModified,
+                SyncStatus::RemoteModified,
+            ),
+            (
+                SyncStatus::LocalModified,
+                SyncStatus::LocalModified,
+                SyncStatus::Conflicted,
+            ),
+            (
+                SyncStatus::RemoteModified,
+                SyncStatus::RemoteModified,
+                SyncStatus::RemoteModified,
+            ),
+            (
+                SyncStatus::Conflicted,
+                SyncStatus::Conflicted,
+                SyncStatus::Conflicted,
+            ),
+            (
+                SyncStatus::Syncing,
+                SyncStatus::Syncing,
+                SyncStatus::Syncing,
+            ),
+            (SyncStatus::Failed, SyncStatus::Failed, SyncStatus::Failed),
+        ];
+        assert_eq!(cases.len(), ALL_STATUSES.len());
+        for (status, local, remote) in cases {
+            assert_eq!(status_after_local_change(status), local, "{status:?}");
+            assert_eq!(status_after_remote_change(status), remote, "{status:?}");
+
+            let mut e = entry("/f", status, Some("l"), Some("base"));
+            e.mark_local_modified(Some("l2".into()));
+            assert_eq!(e.status, local, "mark_local_modified from {status:?}");
+
+            let mut e = entry("/f", status, Some("l"), Some("base"));
+            e.mark_remote_modified(Some("r".into()), at(10));
+            assert_eq!(e.status, remote, "mark_remote_modified from {status:?}");
+        }
+    }
+
+    #[test]
+    fn test_remote_change_for_every_status() {
+        let config = SyncConfig::default();
+        for status in ALL_STATUSES {
+            let entries = [entry("/f", status, Some("l"), Some("base"))];
+
+            // Unchanged remote: only an entry already waiting on the remote
+            // is downloaded.
+            let actions = run(&entries, &[], &remote_of(&[("/f", Some("base"))]), &config);
+            let expected = match status {
+                SyncStatus::RemoteModified => vec![download("/f")],
+                _ => vec![],
+            };
+            assert_eq!(actions, expected, "unchanged remote, {status:?}");
+
+            // Changed remote: the change is recorded, then downloaded if the
+            // entry ends up waiting on the remote.
+            let actions = run(&entries, &[], &remote_of(&[("/f", Some("r"))]), &config);
+            let next = status_after_remote_change(status);
+            let mut expected = vec![record("/f", "r", next)];
+            if next == SyncStatus::RemoteModified {
+                expected.push(download("/f"));
+            }
+            assert_eq!(actions, expected, "changed remote, {status:?}");
+
+            // Remote unknown: nothing to record.
+            let actions = run(&entries, &[], &HashMap::new(), &config);
+            let expected = match status {
+                SyncStatus::RemoteModified => vec![download("/f")],
+                _ => vec![],
+            };
+            assert_eq!(actions, expected, "unknown remote, {status:?}");
+        }
+    }
+
+    #[test]
+    fn test_staged_update_for_every_status() {
+        let config = SyncConfig::default();
+        let changes = [change("c1", "/f", ChangeType::Update, 1)];
+        for status in ALL_STATUSES {
+            let entries = [entry("/f", status, Some("l"), Some("base"))];
+            let conflicted = status == SyncStatus::Conflicted;
+
+            let cases = [
+                // Remote untouched since the last sync: push.
+                (remote_of(&[("/f", Some("base"))]), false),
+                // Remote already holds the local version: push.
+                (remote_of(&[("/f", Some("l"))]), false),
+                // Both sides changed: conflict.
+                (remote_of(&[("/f", Some("r"))]), true),
+                // Remote gone or unreachable: push.
+                (HashMap::new(), false),
+            ];
+            for (remote, both) in cases {
+                let actions = run(&entries, &changes, &remote, &config);
+                let expected = if both || conflicted {
+                    conflict("c1", "/f")
+                } else {
+                    upload("c1", "/f")
+                };
+                assert_eq!(
+                    actions,
+                    vec![expected],
+                    "{status:?} remote={:?}",
+                    remote.get("/f").map(|m| &m.etag)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_local_only_and_remote_only() {
+        let config = SyncConfig::default();
+
+        // A new local file nobody has seen remotely.
+        let entries = [entry("/new", SyncStatus::LocalModified, Some("l"), None)];
+        let changes = [change("c1", "/new", ChangeType::Create, 1)];
+        assert_eq!(
+            run(&entries, &changes, &HashMap::new(), &config),
+            vec![upload("c1", "/new")]
+        );
+
+        // The same name appeared remotely before the first upload.
+        assert_eq!(
+            run(
+                &entries,
+                &changes,
+                &remote_of(&[("/new", Some("r"))]),
+                &config
+            ),
+            vec![conflict("c1", "/new")]
+        );
+
+        // A staged file with no sync entry is pushed without looking.
+        assert_eq!(
+            run(&[], &changes, &remote_of(&[("/new", Some("r"))]), &config),
+            vec![upload("c1", "/new")]
+        );
+
+        // A remote-only change on a synced entry.
+        let entries = [entry(
+            "/old",
+            SyncStatus::Synced,
+            Some("base"),
+            Some("base"),
+        )];
+        assert_eq!(
+            run(&entries, &[], &remote_of(&[("/old", Some("r"))]), &config),
+            vec![
+                record("/old", "r", SyncStatus::RemoteModified),
+                download("/old")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_delete_races_and_tombstones() {
+        let config = SyncConfig::default();
+        let entries = [entry("/f", SyncStatus::LocalModified, None, Some("base"))];
+        let delete = change("d1", "/f", ChangeType::Delete, 1);
+        let delete_action = PlannedAction::DeleteRemote {
+            change_id: "d1".into(),
+            path: vp("/f"),
+        };
+
+        // Local delete against a remote edit: a conflict, so the edit is not
+        // destroyed unseen.
+        assert_eq!(
+            run(
+                &entries,
+                std::slice::from_ref(&delete),
+                &remote_of(&[("/f", Some("r"))]),
+                &config
+            ),
+            vec![conflict("d1", "/f")]
+        );
+        assert_eq!(
+            upload_check_paths(&entries, std::slice::from_ref(&delete), &config),
+            [vp("/f")]
+        );
+
+        // Local delete of a file the remote left alone.
+        assert_eq!(
+            run(
+                &entries,
+                std::slice::from_ref(&delete),
+                &remote_of(&[("/f", Some("base"))]),
+                &config
+            ),
+            vec![delete_action.clone()]
+        );
+
+        // Local delete of a file already gone remotely, or not checked.
+        for gone in [&["/f"][..], &[]] {
+            assert_eq!(
+                run_with_gone(
+                    &entries,
+                    std::slice::from_ref(&delete),
+                    &HashMap::new(),
+                    gone,
+                    &config
+                ),
+                vec![delete_action.clone()]
+            );
+        }
+
+        // Delete then re-create: the re-create is not checked against the
+        // remote the delete removes.
+        let recreate = change("c2", "/f", ChangeType::Create, 2);
+        assert_eq!(
+            run(
+                &entries,
+                &[recreate.clone(), delete.clone()],
+                &remote_of(&[("/f", Some("base"))]),
+                &config
+            ),
+            vec![delete_action, upload("c2", "/f")]
+        );
+
+        // A delete that reached the remote before a restart is only committed.
+        let tombstone = uploaded(delete, None);
+        assert_eq!(
+            run(
+                &entries,
+                &[tombstone],
+                &remote_of(&[("/f", Some("r"))]),
+                &config
+            ),
+            vec![PlannedAction::Commit {
+                change_id: "d1".into(),
+                path: vp("/f"),
+            }]
+        );
+
+        // Local edit of a file deleted remotely: a conflict rather than a
+        // silent re-create. A remote that could not be checked is pushed.
+        let entries = [entry(
+            "/g",
+            SyncStatus::LocalModified,
+            Some("l"),
+            Some("base"),
+        )];
+        let edit = change("e1", "/g", ChangeType::Update, 1);
+        assert_eq!(
+            run_with_gone(
+                &entries,
+                std::slice::from_ref(&edit),
+                &HashMap::new(),
+                &["/g"],
+                &config
+            ),
+            vec![conflict("e1", "/g")]
+        );
+        assert_eq!(
+            run(&entries, &[edit], &HashMap::new(), &config),
+            vec![upload("e1", "/g")]
+        );
+
+        // A new file that was never on the remote is pushed.
+        let entries = [entry("/n", SyncStatus::LocalModified, Some("l"), None)];
+        let create = change("c1", "/n", ChangeType::Create, 1);
+        assert_eq!(
+            run_with_gone(&entries, &[create], &HashMap::new(), &["/n"], &config),
+            vec![upload("c1", "/n")]
+        );
+    }
+
+    #[test]
+    fn test_remote_delete_of_unstaged_entry() {
+        let config = SyncConfig::default();
+        let entries = [entry("/f", SyncStatus::Synced, Some("base"), Some("base"))];
+
+        // The delete is recorded, then applied locally.
+        assert_eq!(
+            run_with_gone(&entries, &[], &HashMap::new(), &["/f"], &config),
+            vec![
+                PlannedAction::RecordRemoteDelete {
+                    path: vp("/f"),
+                    status: SyncStatus::RemoteModified,
+                },
+                PlannedAction::DeleteLocal { path: vp("/f") },
+            ]
+        );
+
+        // Once recorded, only the local delete is left.
+        let mut recorded = entries[0].clone();
+        recorded.mark_remote_deleted(at(5));
+        let entries = [recorded];
+        assert_eq!(
+            run_with_gone(&entries, &[], &HashMap::new(), &["/f"], &config),
+            vec![PlannedAction::DeleteLocal { path: vp("/f") }]
+        );
+
+        // A file that reappears is downloaded instead.
+        assert_eq!(
+            run(&entries, &[], &remote_of(&[("/f", Some("r"))]), &config),
+            vec![
+                record("/f", "r", SyncStatus::RemoteModified),
+                download("/f")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changes_after_conflict_stay_staged() {
+        let config = SyncConfig::default();
+        let entries = [entry(
+            "/f",
+            SyncStatus::LocalModified,
+            Some("l"),
+            Some("base"),
+        )];
+        let changes = [
+            change("c2", "/f", ChangeType::Update, 2),
+            change("c1", "/f", ChangeType::Update, 1),
+            change("c3", "/f", ChangeType::Delete, 3),
+        ];
+        assert_eq!(
+            run(
+                &entries,
+                &changes,
+                &remote_of(&[("/f", Some("r"))]),
+                &config
+            ),
+            vec![conflict("c1", "/f")]
+        );
+
+        // Without a conflict, later updates follow the first without another
+        // check.
+        assert_eq!(
+            run(
+                &entries,
+                &changes[..2],
+                &remote_of(&[("/f", Some("base"))]),
+                &config
+            ),
+            vec![upload("c1", "/f"), upload("c2", "/f")]
+        );
+    }
+
+    #[test]
+    fn test_staged_paths_skip_remote_checks() {
+        let config = SyncConfig::default();
+        let entries = [entry(
+            "/f",
+            SyncStatus::RemoteModified,
+            Some("l"),
+            Some("base"),
+        )];
+        let changes = [change("c1", "/f", ChangeType::Update, 1)];
+        // The remote etag still matches what we last saw, so the update is
+        // pushed and no download is planned for the same path.
+        assert_eq!(
+            run(
+                &entries,
+                &changes,
+                &remote_of(&[("/f", Some("base"))]),
+                &config
+            ),
+            vec![upload("c1", "/f")]
+        );
+    }
+
+    #[test]
+    fn test_content_hashes_only_compare_within_an_algorithm() {
+        use axiomvault_storage::HashAlgo;
+
+        let config = SyncConfig::default();
+        let hashed = |status, hash: String| SyncEntry {
+            remote_content_hash: Some(hash),
+            ..entry("/f", status, Some("l"), Some("base"))
+        };
+        let remote_with = |hash: String| {
+            let mut remote = remote_of(&[("/f", Some("r"))]);
+            remote.get_mut("/f").unwrap().content_hash = Some(hash);
+            remote
+        };
+        let changes = [change("c1", "/f", ChangeType::Update, 1)];
+        let sha = HashAlgo::Sha256.hash(b"content");
+        let blake = HashAlgo::Blake3.hash(b"content");
+
+        // The remote was rewritten with identical content: not a conflict.
+        let entries = [hashed(SyncStatus::LocalModified, sha.clone())];
+        assert_eq!(
+            run(&entries, &changes, &remote_with(sha.clone()), &config),
+            vec![upload("c1", "/f")]
+        );
+
+        // The same bytes under another algorithm prove nothing; the etags
+        // decide, and they say both sides changed.
+        assert_eq!(
+            run(&entries, &changes, &remote_with(blake.clone()), &config),
+            vec![conflict("c1", "/f")]
+        );
+
+        // Nothing staged: an identical rewrite is recorded without a
+        // download, a cross-algorithm one is treated as a change.
+        let entries = [hashed(SyncStatus::Synced, sha.clone())];
+        let PlannedAction::RecordRemoteChange { status, .. } =
+            &run(&entries, &[], &remote_with(sha.clone()), &config)[0]
+        else {
+            panic!("expected the new etag to be recorded");
+        };
+        assert_eq!(*status, SyncStatus::Synced);
+        assert_eq!(
+            run(&entries, &[], &remote_with(blake.clone()), &config).last(),
+            Some(&download("/f"))
+        );
+
+        // The entry follows the plan.
+        let mut e = hashed(SyncStatus::Synced, sha.clone());
+        e.mark_remote_version(Some("r".into()), Some(sha.clone()), None, at(10));
+        assert_eq!(e.status, SyncStatus::Synced);
+        assert_eq!(e.remote_etag.as_deref(), Some("r"));
+        e.mark_remote_version(Some("r2".into()), Some(blake.clone()), None, at(20));
+        assert_eq!(e.status, SyncStatus::RemoteModified);
+        assert_eq!(e.remote_content_hash, Some(blake));
+    }
+
+    #[test]
+    fn test_missing_etag_falls_back_to_recorded_size() {
+        let config = SyncConfig::default();
+        // Uploaded with an etag, but the provider omits it for the empty
+        // file afterwards.
+        let sized = |status, local| SyncEntry {
+            remote_size: Some(0),
+            ..entry("/f", status, local, Some("md5"))
+        };
+        let etagless = remote_of(&[("/f", None)]);
+
+        let entries = [sized(SyncStatus::Synced, Some("md5"))];
+        assert!(run(&entries, &[], &etagless, &config).is_empty());
+
+        // A local edit is pushed rather than flagged as a conflict.
+        let entries = [sized(SyncStatus::LocalModified, Some("l"))];
+        let changes = [change("c1", "/f", ChangeType::Update, 1)];
+        assert_eq!(
+            run(&entries, &changes, &etagless, &config),
+            vec![upload("c1", "/f")]
+        );
+
+        // Another size is a change, and so is a remote delete.
+        let mut grown = remote_of(&[("/f", None)]);
+        grown.get_mut("/f").unwrap().size = Some(16);
+        let entries = [sized(SyncStatus::Synced, Some("md5"))];
+        assert_eq!(
+            run(&entries, &[], &grown, &config).last(),
+            Some(&download("/f"))
+        );
+        let entries = [SyncEntry {
+            remote_etag: None,
+            ..sized(SyncStatus::Synced, None)
+        }];
+        assert_eq!(
+            run_with_gone(&entries, &[], &HashMap::new(), &["/f"], &config),
+            vec![
+                PlannedAction::RecordRemoteDelete {
+                    path: vp("/f"),
+                    status: SyncStatus::RemoteModified,
+                },
+                PlannedAction::DeleteLocal { path: vp("/f") },
+            ]
+        );
+
+        // Without a recorded size a missing etag still counts as a change.
+        let entries = [entry("/f", SyncStatus::Synced, Some("md5"), Some("md5"))];
+        assert_eq!(
+            run(&entries, &[], &etagless, &config).last(),
+            Some(&download("/f"))
+        );
+
+        // The entry follows the plan: the etag is kept.
+        let mut e = sized(SyncStatus::Synced, Some("md5"));
+        e.mark_remote_version(None, None, Some(0), at(10));
+        assert_eq!(e.status, SyncStatus::Synced);
+        assert_eq!(e.remote_etag.as_deref(), Some("md5"));
+    }
+
+    #[test]
+    fn test_excluded_paths() {
+        let config = SyncConfig {
+            exclude: vec![vp("/skip")],
+            ..SyncConfig::default()
+        };
+        let entries = [
+            entry(
+                "/skip/a",
+                SyncStatus::RemoteModified,
+                Some("l"),
+                Some("base"),
+            ),
+            entry("/skip/b", SyncStatus::Synced, Some("base"), Some("base")),
+        ];
+        let changes = [
+            change("c1", "/skip/a", ChangeType::Update, 1),
+            uploaded(change("c2", "/skip/c", ChangeType::Create, 2), Some("e")),
+        ];
+        let remote = remote_of(&[("/skip/a", Some("r")), ("/skip/b", Some("r"))]);
+
+        // Only the already-uploaded change is finished.
+        assert_eq!(
+            run(&entries, &changes, &remote, &config),
+            vec![PlannedAction::Commit {
+                change_id: "c2".into(),
+                path: vp("/skip/c"),
+            }]
+        );
+        assert!(upload_check_paths(&entries, &changes, &config).is_empty());
+        assert!(remote_check_paths(&entries, &config).is_empty());
+    }
+
+    #[test]
+    fn test_expand_paths() {
+        let tracked = [
+            vp("/projects/b"),
+            vp("/projects/a/x"),
+            vp("/other"),
+            vp("/projects2/y"),
+        ];
+        let cases: [(&[&str], &[&str]); 6] = [
+            // Exact file.
+            (&["/other"], &["/other"]),
+            // Directory prefix, in path order, not matching `/projects2`.
+            (&["/projects"], &["/projects/a/x", "/projects/b"]),
+            // Mixed file and directory, overlapping.
+            (
+                &["/projects/b", "/projects", "/other"],
+                &["/projects/b", "/projects/a/x", "/other"],
+            ),
+            // Root covers everything.
+            (
+                &["/"],
+                &["/other", "/projects/a/x", "/projects/b", "/projects2/y"],
+            ),
+            // Untracked paths are passed through.
+            (&["/missing"], &["/missing"]),
+            (&[], &[]),
+        ];
+        for (requested, expected) in cases {
+            let requested: Vec<VaultPath> = requested.iter().map(|p| vp(p)).collect();
+            let expected: Vec<VaultPath> = expected.iter().map(|p| vp(p)).collect();
+            assert_eq!(
+                expand_paths(&requested, &tracked),
+                expected,
+                "{requested:?}"
+            );
+        }
+    }
+
+    // --- Properties ---
+
+    const PATHS: [&str; 4] = ["/a", "/b", "/d/c", "/skip/x"];
+    const ETAGS: [&str; 3] = ["e1", "e2", "e3"];
+
+    fn status_strategy() -> impl Strategy<Value = SyncStatus> {
+        prop::sample::select(ALL_STATUSES.to_vec())
+    }
+
+    fn etag_strategy() -> impl Strategy<Value = Option<String>> {
+        prop::option::of(prop::sample::select(ETAGS.to_vec()).prop_map(str::to_string))
+    }
+
+    fn entries_strategy() -> impl Strategy<Value = Vec<SyncEntry>> {
+        prop::collection::btree_map(
+            prop::sample::select(PATHS.to_vec()),
+            (status_strategy(), etag_strategy(), etag_strategy()),
+            0..PATHS.len(),
+        )
+        .prop_map(|entries| {
+            entries
+                .into_iter()
+                .map(|(path, (status, local, remote))| {
+                    entry(path, status, local.as_deref(), remote.as_deref())
+                })
+                .collect()
+        })
+    }
+
+    fn changes_strategy() -> impl Strategy<Value = Vec<StagedChange>> {
+        prop::collection::vec(
+            (
+                prop::sample::select(PATHS.to_vec()),
+                prop::sample::select(vec![
+                    ChangeType::Create,
+                    ChangeType::Update,
+                    ChangeType::Delete,
+                ]),
+                0i64..4,
+                prop::option::of(etag_strategy()),
+            ),
+            0..6,
+        )
+        .prop_map(|changes| {
+            changes
+                .into_iter()
+                .enumerate()
+                .map(|(i, (path, change_type, staged, upload))| {
+                    let c = change(&format!("c{i}"), path, change_type, staged);
+                    match upload {
+                        Some(etag) => uploaded(c, etag.as_deref()),
+                        None => c,
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn remote_strategy() -> impl Strategy<Value = HashMap<String, Metadata>> {
+        prop::collection::btree_map(
+            prop::sample::select(PATHS.to_vec()),
+            etag_strategy(),
+            0..PATHS.len(),
+        )
+        .prop_map(|remote| {
+            remote
+                .into_iter()
+                .map(|(path, etag)| (path.to_string(), meta(path, etag.as_deref(), 10)))
+                .collect()
+        })
+    }
+
+    /// Sync state, staged changes, remote metadata and gone remote paths.
+    type Snapshot = (
+        Vec<SyncEntry>,
+        Vec<StagedChange>,
+        HashMap<String, Metadata>,
+        HashSet<String>,
+    );
+
+    /// Snapshots where the remote reflects every change already uploaded.
+    /// Some paths without a remote file are known to be gone; the rest
+    /// could not be checked.
+    fn snapshot_strategy() -> impl Strategy<Value = Snapshot> {
+        (
+            entries_strategy(),
+            changes_strategy(),
+            remote_strategy(),
+            prop::collection::btree_set(prop::sample::select(PATHS.to_vec()), 0..PATHS.len()),
+        )
+            .prop_map(|(entries, changes, mut remote, checked)| {
+                for change in ordered_changes(&changes)
+                    .into_iter()
+                    .filter(|c| c.is_uploaded())
+                {
+                    let path = change.vault_path.to_string();
+                    match (change.change_type, &change.remote_etag) {
+                        (ChangeType::Delete, _) => {
+                            remote.remove(&path);
+                        }
+                        (_, Some(etag)) => {
+                            remote.insert(path.clone(), meta(&path, Some(etag), 10));
+                        }
+                        (_, None) => {}
+                    }
+                }
+                let gone = checked
+                    .into_iter()
+                    .filter(|p| !remote.contains_key(*p))
+                    .map(str::to_string)
+                    .collect();
+                (entries, changes, remote, gone)
+            })
+    }
+
+    fn run_snapshot(
+        (entries, changes, remote, gone): &Snapshot,
+        config: &SyncConfig,
+    ) -> Vec<PlannedAction> {
+        plan(&PlanInput {
+            entries,
+            changes,
+            remote,
+            gone,
+            config,
+        })
+    }
+
+    /// Snapshots after every action of `actions` succeeded, mirroring what
+    /// the engine does for each of them.
+    fn apply((entries, changes, remote, gone): &Snapshot, actions: &[PlannedAction]) -> Snapshot {
+        let mut state = crate::SyncState::new();
+        for e in entries {
+            state.insert(e.clone());
+        }
+        let mut changes: Vec<StagedChange> = changes.to_vec();
+        let mut remote = remote.clone();
+        let mut gone = gone.clone();
+
+        for action in actions {
+            match action {
+                PlannedAction::Commit { change_id, path } => {
+                    let pos = changes.iter().position(|c| &c.id == change_id).unwrap();
+                    let change = changes.remove(pos);
+                    match (change.change_type, change.remote_etag) {
+                        (ChangeType::Delete, _) => {
+                            state.remove(path);
+                        }
+                        (_, Some(etag)) => match state.get_mut(path) {
+                            Some(e) => e.mark_synced(Some(etag), at(20)),
+                            None => state.insert(SyncEntry::new_synced(
+                                path.to_string(),
+                                Some(etag),
+                                at(20),
+                            )),
+                        },
+                        (_, None) => {}
+                    }
+                }
+                PlannedAction::Upload { change_id, path } => {
+                    changes.retain(|c| &c.id != change_id);
+                    let etag = format!("up-{change_id}");
+                    match state.get_mut(path) {
+                        Some(e) => e.mark_synced(Some(etag.clone()), at(20)),
+                        None => state.insert(SyncEntry::new_synced(
+                            path.to_string(),
+                            Some(etag.clone()),
+                            at(20),
+                        )),
+                    }
+                    remote.insert(path.to_string(), meta(&path.to_string(), Some(&etag), 20));
+                    gone.remove(&path.to_string());
+                }
+                PlannedAction::DeleteRemote { change_id, path } => {
+                    changes.retain(|c| &c.id != change_id);
+                    state.remove(path);
+                    remote.remove(&path.to_string());
+                    gone.insert(path.to_string());
+                }
+                PlannedAction::Conflict { path, .. } => {
+                    let key = path.to_string();
+                    let Some(e) = state.get_mut(path) else {
+                        continue;
+                    };
+                    match RemoteFile::of(&key, &remote, &gone) {
+                        RemoteFile::Present(r) => e.mark_conflicted(r.etag.clone(), r.modified),
+                        RemoteFile::Gone => {
+                            e.mark_remote_deleted(at(20));
+                            e.mark_conflicted(None, at(20));
+                        }
+                        RemoteFile::Unknown => {}
+                    }
+                }
+                PlannedAction::RecordRemoteChange {
+                    path,
+                    etag,
+                    content_hash,
+                    size,
+                    modified,
+                    ..
+                } => {
+                    if let Some(e) = state.get_mut(path) {
+                        e.mark_remote_version(etag.clone(), content_hash.clone(), *size, *modified);
+                    }
+                }
+                PlannedAction::RecordRemoteDelete { path, .. } => {
+                    if let Some(e) = state.get_mut(path) {
+                        e.mark_remote_deleted(at(20));
+                    }
+                }
+                // Downloads and local deletes are not persisted yet (audit
+                // H-1).
+                PlannedAction::Download { .. } | PlannedAction::DeleteLocal { .. } => {}
+            }
+        }
+
+        (state.entries().cloned().collect(), changes, remote, gone)
+    }
+
+    proptest! {
+        /// Property: the plan depends only on the snapshots, not on the
+        /// order they were collected in.
+        #[test]
+        fn plan_is_deterministic(snapshot in snapshot_strategy()) {
+            let config = SyncConfig { exclude: vec![vp("/skip")], ..SyncConfig::default() };
+            let first = run_snapshot(&snapshot, &config);
+            prop_assert_eq!(&first, &run_snapshot(&snapshot, &config));
+
+            let (mut entries, mut changes, remote, gone) = snapshot;
+            entries.reverse();
+            changes.reverse();
+            prop_assert_eq!(&first, &run_snapshot(&(entries, changes, remote, gone), &config));
+        }
+
+        /// Property: once a plan has been carried out, planning again only
+        /// yields work that is outstanding by design (held conflicts, and
+        /// downloads and local deletes that cannot be persisted yet), and
+        /// carrying that out changes nothing.
+        #[test]
+        fn plan_is_idempotent_on_its_output(snapshot in snapshot_strategy()) {
+            let config = SyncConfig { exclude: vec![vp("/skip")], ..SyncConfig::default() };
+            let first = run_snapshot(&snapshot, &config);
+            let snapshot = apply(&snapshot, &first);
+
+            let second = run_snapshot(&snapshot, &config);
+            for action in &second {
+                prop_assert!(
+                    matches!(
+                        action,
+                        PlannedAction::Conflict { .. }
+                            | PlannedAction::Download { .. }
+                            | PlannedAction::DeleteLocal { .. }
+                    ),
+                    "unexpected follow-up action {:?}",
+                    action
+                );
+            }
+
+            let snapshot = apply(&snapshot, &second);
+            prop_assert_eq!(second, run_snapshot(&snapshot, &config));
+        }
+    }
+}
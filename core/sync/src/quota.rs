@@ -0,0 +1,68 @@
+//! Pre-flight check of planned uploads against the provider's quota.
+
+use serde::{Deserialize, Serialize};
+
+use axiomvault_storage::StorageQuota;
+
+/// Planned uploads that will not fit in the space the account has left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaShortfall {
+    /// Bytes the planned uploads need.
+    pub needed: u64,
+    /// Bytes the provider reported as free.
+    pub remaining: u64,
+}
+
+/// Compare the bytes a plan uploads with the space left in `quota`.
+///
+/// Returns `None` when the uploads fit, the account is unlimited or the
+/// provider reported no quota. The estimate ignores that replacing a file
+/// may free the space of its previous version, so it can warn early but
+/// never late.
+pub fn shortfall(needed: u64, quota: Option<StorageQuota>) -> Option<QuotaShortfall> {
+    let remaining = quota?.remaining()?;
+    (needed > remaining).then_some(QuotaShortfall { needed, remaining })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(used: u64, limit: Option<u64>) -> Option<StorageQuota> {
+        Some(StorageQuota { used, limit })
+    }
+
+    #[test]
+    fn test_shortfall_when_plan_exceeds_remaining() {
+        assert_eq!(
+            shortfall(150, quota(900, Some(1000))),
+            Some(QuotaShortfall {
+                needed: 150,
+                remaining: 100
+            })
+        );
+    }
+
+    #[test]
+    fn test_no_shortfall_when_plan_fits() {
+        assert_eq!(shortfall(100, quota(900, Some(1000))), None);
+        assert_eq!(shortfall(0, quota(1000, Some(1000))), None);
+    }
+
+    #[test]
+    fn test_no_shortfall_without_limit() {
+        assert_eq!(shortfall(u64::MAX, quota(900, None)), None);
+        assert_eq!(shortfall(u64::MAX, None), None);
+    }
+
+    #[test]
+    fn test_overdrawn_account_has_nothing_remaining() {
+        assert_eq!(
+            shortfall(1, quota(1200, Some(1000))),
+            Some(QuotaShortfall {
+                needed: 1,
+                remaining: 0
+            })
+        );
+    }
+}
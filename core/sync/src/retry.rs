@@ -144,7 +144,15 @@ impl RetryExecutor {
                         return Err(err);
                     }
 
-                    let delay = self.config.delay_for_attempt(attempt - 1);
+                    // A provider-supplied Retry-After wins over a shorter backoff.
+                    let mut delay = self.config.delay_for_attempt(attempt - 1);
+                    if let Error::Provider {
+                        retry_after: Some(hint),
+                        ..
+                    } = &err
+                    {
+                        delay = delay.max(*hint);
+                    }
                     warn!(
                         "Attempt {} failed: {}. Retrying in {:?}...",
                         attempt, err, delay
@@ -169,6 +177,10 @@ impl RetryExecutor {
     ///   the token manager a chance to refresh on the next attempt
     ///   instead of surfacing a hard failure to the caller. See audit
     ///   finding L-8 (SECURITY_AUDIT_2026-04-21.md).
+    /// - `RateLimited`: the provider asked us to slow down; the exponential
+    ///   backoff between attempts is exactly that.
+    /// - `Provider` with `retryable` set: the response status was transient
+    ///   (408, 429, 5xx). A `retry_after` hint lengthens the next delay.
     ///
     /// Deliberately NOT retried:
     /// - `Authentication`: reserved for permanent auth failures — invalid
@@ -178,10 +190,21 @@ impl RetryExecutor {
     ///   `Authentication` (permanent) and `AuthenticationExpired`
     ///   (transient) replaces the earlier blanket-retry on all auth
     ///   errors.
+    /// - `Cancelled`, `Unsupported`, `Conflict`, `Integrity`: repeating the
+    ///   same request produces the same answer.
+    /// - `QuotaExhausted`: the account stays full until someone frees
+    ///   space; the sync engine pauses its uploads instead.
     fn is_retryable(&self, err: &Error) -> bool {
         matches!(
             err,
-            Error::Network(_) | Error::Io(_) | Error::AuthenticationExpired(_)
+            Error::Network(_)
+                | Error::Io(_)
+                | Error::AuthenticationExpired(_)
+                | Error::RateLimited(_)
+                | Error::Provider {
+                    retryable: true,
+                    ..
+                }
         )
     }
 
@@ -305,6 +328,65 @@ mod tests {
         assert_eq!(attempt_count.load(Ordering::SeqCst), 3);
     }
 
+    #[tokio::test]
+    async fn test_retry_on_retryable_provider_error() {
+        let attempt_count = Arc::new(AtomicU32::new(0));
+        let count_clone = attempt_count.clone();
+
+        let config = RetryConfig::new(3)
+            .with_initial_delay(Duration::from_millis(1))
+            .with_jitter(false);
+        let executor = RetryExecutor::new(config);
+
+        let started = std::time::Instant::now();
+        let result: Result<i32> = executor
+            .execute(move || {
+                let count = count_clone.clone();
+                async move {
+                    let current = count.fetch_add(1, Ordering::SeqCst);
+                    match current {
+                        0 => Err(Error::Provider {
+                            status: Some(503),
+                            retryable: true,
+                            retry_after: Some(Duration::from_millis(50)),
+                            message: "Service Unavailable".to_string(),
+                        }),
+                        _ => Ok(5),
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 5);
+        assert_eq!(attempt_count.load(Ordering::SeqCst), 2);
+        // The Retry-After hint replaced the 1ms backoff.
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_non_retryable_provider_error() {
+        let attempt_count = Arc::new(AtomicU32::new(0));
+        let count_clone = attempt_count.clone();
+
+        let result: Result<i32> = RetryExecutor::default()
+            .execute(move || {
+                let count = count_clone.clone();
+                async move {
+                    count.fetch_add(1, Ordering::SeqCst);
+                    Err(Error::Provider {
+                        status: Some(400),
+                        retryable: false,
+                        retry_after: None,
+                        message: "Bad Request".to_string(),
+                    })
+                }
+            })
+            .await;
+
+        assert!(matches!(result, Err(Error::Provider { .. })));
+        assert_eq!(attempt_count.load(Ordering::SeqCst), 1);
+    }
+
     #[tokio::test]
     async fn test_non_retryable_error() {
         let attempt_count = Arc::new(AtomicU32::new(0));
@@ -414,4 +496,34 @@ mod tests {
         // Permanent Authentication is not retryable: exactly one attempt.
         assert_eq!(attempt_count.load(Ordering::SeqCst), 1);
     }
+
+    /// Rate limiting is transient; cancellation and the other terminal
+    /// variants are attempted exactly once.
+    #[tokio::test]
+    async fn test_classifies_new_error_variants() {
+        let config = RetryConfig::new(2)
+            .with_initial_delay(Duration::from_millis(1))
+            .with_jitter(false);
+        let executor = RetryExecutor::new(config);
+
+        let cases: [(fn() -> Error, u32); 6] = [
+            (|| Error::RateLimited("429".to_string()), 3),
+            (|| Error::Cancelled("user".to_string()), 1),
+            (|| Error::Unsupported("op".to_string()), 1),
+            (|| Error::Conflict("etag".to_string()), 1),
+            (|| Error::Integrity("mac".to_string()), 1),
+            (|| Error::QuotaExhausted("full".to_string()), 1),
+        ];
+        for (make_error, expected_attempts) in cases {
+            let attempts = AtomicU32::new(0);
+            let result: Result<()> = executor
+                .execute(|| {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    async move { Err(make_error()) }
+                })
+                .await;
+            assert!(result.is_err());
+            assert_eq!(attempts.load(Ordering::SeqCst), expected_attempts);
+        }
+    }
 }
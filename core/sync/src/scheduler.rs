@@ -1,5 +1,6 @@
 //! Sync scheduling - on-demand and periodic modes.
 
+use chrono::{NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Duration;
@@ -9,6 +10,13 @@ use tracing::{debug, error, info};
 
 use axiomvault_common::Result;
 
+use crate::environment::{DesktopProbe, EnvironmentProbe};
+use crate::quota::QuotaShortfall;
+use crate::state::{SkippedSync, SyncState};
+
+/// Default number of skipped periodic syncs before one runs anyway.
+const DEFAULT_MAX_SKIPPED_INTERVALS: u32 = 12;
+
 /// Sync mode configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SyncMode {
@@ -22,6 +30,74 @@ pub enum SyncMode {
     Hybrid { interval: Duration },
 }
 
+/// Conditions a periodic sync waits for.
+///
+/// Only periodic syncs are held back; on-demand and manual syncs always
+/// run. After `max_skipped_intervals` skips in a row the next periodic
+/// sync runs regardless, so the constraints cannot starve sync forever.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncConstraints {
+    /// Local times `(start, end)` between which periodic syncs are skipped.
+    /// The window wraps past midnight when `end` is before `start`.
+    pub quiet_hours: Option<(NaiveTime, NaiveTime)>,
+    /// Skip periodic syncs on a metered connection.
+    pub require_unmetered: bool,
+    /// Skip periodic syncs while on battery.
+    pub require_power: bool,
+    /// Consecutive skipped intervals after which a periodic sync runs anyway.
+    pub max_skipped_intervals: u32,
+}
+
+impl Default for SyncConstraints {
+    fn default() -> Self {
+        Self {
+            quiet_hours: None,
+            require_unmetered: false,
+            require_power: false,
+            max_skipped_intervals: DEFAULT_MAX_SKIPPED_INTERVALS,
+        }
+    }
+}
+
+impl SyncConstraints {
+    /// Whether `time` falls in the quiet hours. The start is inclusive and
+    /// the end exclusive; equal bounds mean no quiet hours.
+    pub fn in_quiet_hours(&self, time: NaiveTime) -> bool {
+        match self.quiet_hours {
+            Some((start, end)) if start <= end => start <= time && time < end,
+            Some((start, end)) => time >= start || time < end,
+            None => false,
+        }
+    }
+
+    /// Why a periodic sync should be skipped now, if at all.
+    pub fn skip_reason(&self, probe: &dyn EnvironmentProbe) -> Option<SkipReason> {
+        if self.in_quiet_hours(probe.local_time()) {
+            return Some(SkipReason::QuietHours);
+        }
+        let environment = probe.environment();
+        if self.require_unmetered && environment.metered {
+            return Some(SkipReason::Metered);
+        }
+        if self.require_power && !environment.on_power {
+            return Some(SkipReason::OnBattery);
+        }
+        None
+    }
+}
+
+/// Why a periodic sync was skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkipReason {
+    /// Inside the configured quiet hours.
+    QuietHours,
+    /// The connection is metered.
+    Metered,
+    /// The device is on battery.
+    OnBattery,
+}
+
 /// Sync request types.
 #[derive(Debug)]
 pub enum SyncRequest {
@@ -46,6 +122,15 @@ pub struct SyncResult {
     /// from `files_synced` to avoid silently masquerading dropped data as
     /// success.
     pub pending_persistence: usize,
+    /// Whether the provider ran out of space during this sync. Uploads
+    /// still pending at that point are paused, not failed.
+    pub quota_exhausted: bool,
+    /// Uploads left staged because the provider ran out of space.
+    pub uploads_paused: usize,
+    /// Bytes of the paused uploads.
+    pub paused_bytes: u64,
+    /// Set when the pre-flight check predicted the uploads would not fit.
+    pub quota_shortfall: Option<QuotaShortfall>,
     pub duration: Duration,
 }
 
@@ -77,6 +162,9 @@ impl SyncScheduler {
             request_tx,
             request_rx: Some(request_rx),
             shutdown,
+            constraints: SyncConstraints::default(),
+            probe: Arc::new(DesktopProbe),
+            state: None,
         };
 
         (scheduler, handle)
@@ -137,9 +225,67 @@ pub struct SyncSchedulerHandle {
     request_tx: mpsc::Sender<(SyncRequest, oneshot::Sender<Result<SyncResult>>)>,
     request_rx: Option<mpsc::Receiver<(SyncRequest, oneshot::Sender<Result<SyncResult>>)>>,
     shutdown: Arc<RwLock<bool>>,
+    constraints: SyncConstraints,
+    probe: Arc<dyn EnvironmentProbe>,
+    /// Where skipped periodic syncs are recorded for status display.
+    state: Option<Arc<RwLock<SyncState>>>,
 }
 
 impl SyncSchedulerHandle {
+    /// Hold periodic syncs back until `constraints` are met, checking
+    /// device conditions with `probe`.
+    pub fn with_constraints(
+        mut self,
+        constraints: SyncConstraints,
+        probe: Arc<dyn EnvironmentProbe>,
+    ) -> Self {
+        self.constraints = constraints;
+        self.probe = probe;
+        self
+    }
+
+    /// Record skipped periodic syncs in `state`.
+    pub fn with_state(mut self, state: Arc<RwLock<SyncState>>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Decide whether a due periodic sync runs, counting it in `skipped`
+    /// and recording the outcome in the sync state.
+    async fn admit_periodic(&self, skipped: &mut u32) -> bool {
+        let reason = self.constraints.skip_reason(self.probe.as_ref());
+        let run = match reason {
+            Some(reason) if *skipped < self.constraints.max_skipped_intervals => {
+                *skipped += 1;
+                debug!(?reason, skipped = *skipped, "Skipping periodic sync");
+                false
+            }
+            Some(reason) => {
+                info!(
+                    ?reason,
+                    "Running periodic sync after {} skipped intervals", *skipped
+                );
+                true
+            }
+            None => true,
+        };
+
+        if let Some(state) = &self.state {
+            state.write().await.skipped = match (run, reason) {
+                (false, Some(reason)) => Some(SkippedSync {
+                    reason,
+                    at: Utc::now(),
+                    consecutive: *skipped,
+                }),
+                _ => None,
+            };
+        }
+        if run {
+            *skipped = 0;
+        }
+        run
+    }
+
     /// Run the scheduler background task.
     ///
     /// This should be spawned in a tokio task. The `sync_fn` is called
@@ -152,6 +298,7 @@ impl SyncSchedulerHandle {
         let mut request_rx = self.request_rx.take().expect("Handle can only be run once");
         let mut periodic_interval = self.create_periodic_interval().await;
         let sync_fn = Arc::new(sync_fn);
+        let mut skipped = 0;
 
         info!("Sync scheduler started");
 
@@ -187,7 +334,9 @@ impl SyncSchedulerHandle {
                 _ = Self::wait_for_periodic(&mut periodic_interval) => {
                     let mode = self.mode.read().await.clone();
                     match mode {
-                        SyncMode::Periodic { .. } | SyncMode::Hybrid { .. } => {
+                        SyncMode::Periodic { .. } | SyncMode::Hybrid { .. }
+                            if self.admit_periodic(&mut skipped).await =>
+                        {
                             debug!("Triggering periodic sync");
                             let f = sync_fn.clone();
                             tokio::spawn(async move {
@@ -208,6 +357,7 @@ impl SyncSchedulerHandle {
                                 }
                             });
                         }
+                        SyncMode::Periodic { .. } | SyncMode::Hybrid { .. } => {}
                         _ => {
                             // Update interval in case mode changed
                             periodic_interval = self.create_periodic_interval().await;
@@ -276,7 +426,173 @@ impl SyncSchedulerHandle {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::environment::DeviceEnvironment;
+    use std::collections::VecDeque;
     use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+
+    const TICK: Duration = Duration::from_secs(60);
+
+    fn at(hour: u32, min: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, min, 0).unwrap()
+    }
+
+    /// Probe whose clock starts at `start` and follows tokio time, and
+    /// which reports `script` one state per call, repeating the last.
+    struct FakeProbe {
+        start: NaiveTime,
+        started: tokio::time::Instant,
+        script: Mutex<VecDeque<DeviceEnvironment>>,
+    }
+
+    impl FakeProbe {
+        fn new(start: NaiveTime, script: Vec<DeviceEnvironment>) -> Arc<Self> {
+            Arc::new(Self {
+                start,
+                started: tokio::time::Instant::now(),
+                script: Mutex::new(script.into()),
+            })
+        }
+    }
+
+    impl EnvironmentProbe for FakeProbe {
+        fn environment(&self) -> DeviceEnvironment {
+            let mut script = self.script.lock().unwrap();
+            match script.len() {
+                0 => DeviceEnvironment::default(),
+                1 => script[0],
+                _ => script.pop_front().unwrap(),
+            }
+        }
+
+        fn local_time(&self) -> NaiveTime {
+            let elapsed = chrono::Duration::from_std(self.started.elapsed()).unwrap();
+            self.start + elapsed
+        }
+    }
+
+    /// Run a periodic scheduler under `constraints` for `ticks` intervals,
+    /// returning the scheduler, the number of syncs run and the state.
+    async fn run_periodic(
+        constraints: SyncConstraints,
+        probe: Arc<FakeProbe>,
+        ticks: u32,
+    ) -> (SyncScheduler, Arc<AtomicU32>, Arc<RwLock<SyncState>>) {
+        let (scheduler, handle) = SyncScheduler::new(SyncMode::Periodic { interval: TICK });
+        let state = Arc::new(RwLock::new(SyncState::new()));
+        let handle = handle
+            .with_constraints(constraints, probe)
+            .with_state(state.clone());
+
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        tokio::spawn(handle.run(move |_request| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(SyncResult::default())
+            }
+        }));
+
+        // The first tick fires at once; stop halfway to the one after last.
+        tokio::time::sleep(TICK * (ticks - 1) + TICK / 2).await;
+        (scheduler, runs, state)
+    }
+
+    #[test]
+    fn test_quiet_hours_window() {
+        let mut constraints = SyncConstraints {
+            quiet_hours: Some((at(22, 0), at(7, 0))),
+            ..Default::default()
+        };
+        assert!(constraints.in_quiet_hours(at(22, 0)));
+        assert!(constraints.in_quiet_hours(at(3, 0)));
+        assert!(!constraints.in_quiet_hours(at(7, 0)));
+        assert!(!constraints.in_quiet_hours(at(21, 59)));
+
+        constraints.quiet_hours = Some((at(12, 0), at(13, 0)));
+        assert!(constraints.in_quiet_hours(at(12, 30)));
+        assert!(!constraints.in_quiet_hours(at(13, 0)));
+
+        constraints.quiet_hours = Some((at(12, 0), at(12, 0)));
+        assert!(!constraints.in_quiet_hours(at(12, 0)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_quiet_hours_skip_periodic_but_not_on_demand() {
+        let constraints = SyncConstraints {
+            quiet_hours: Some((at(22, 0), at(22, 3))),
+            ..Default::default()
+        };
+        // Ticks at 21:58, 21:59, 22:00, 22:01, 22:02 and 22:03.
+        let probe = FakeProbe::new(at(21, 58), Vec::new());
+        let (scheduler, runs, state) = run_periodic(constraints, probe, 5).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        let skipped = state.read().await.skipped.clone().unwrap();
+        assert_eq!(skipped.reason, SkipReason::QuietHours);
+        assert_eq!(skipped.consecutive, 3);
+
+        // Requested syncs are not held back.
+        scheduler.request_sync().await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        // The window ends at 22:03, exclusive.
+        tokio::time::sleep(TICK).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        assert!(state.read().await.skipped.is_none());
+        scheduler.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_flapping_constraints_run_only_when_met() {
+        let metered = DeviceEnvironment {
+            metered: true,
+            on_power: true,
+        };
+        let unmetered = DeviceEnvironment::default();
+        let constraints = SyncConstraints {
+            require_unmetered: true,
+            ..Default::default()
+        };
+        let probe = FakeProbe::new(
+            at(12, 0),
+            vec![metered, unmetered, metered, metered, unmetered, metered],
+        );
+
+        let (scheduler, runs, state) = run_periodic(constraints, probe, 6).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        // The run on the fifth tick reset the count of skips in a row.
+        let skipped = state.read().await.skipped.clone().unwrap();
+        assert_eq!(skipped.reason, SkipReason::Metered);
+        assert_eq!(skipped.consecutive, 1);
+        scheduler.shutdown().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_skipped_intervals_cannot_starve_sync() {
+        let on_battery = DeviceEnvironment {
+            metered: false,
+            on_power: false,
+        };
+        let constraints = SyncConstraints {
+            require_power: true,
+            max_skipped_intervals: 3,
+            ..Default::default()
+        };
+        let probe = FakeProbe::new(at(12, 0), vec![on_battery]);
+
+        // Three skips, a forced run, three skips, a forced run.
+        let (scheduler, runs, state) = run_periodic(constraints, probe, 8).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert!(state.read().await.skipped.is_none());
+
+        tokio::time::sleep(TICK).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        let skipped = state.read().await.skipped.clone().unwrap();
+        assert_eq!(skipped.reason, SkipReason::OnBattery);
+        assert_eq!(skipped.consecutive, 1);
+        scheduler.shutdown().await;
+    }
 
     #[tokio::test]
     async fn test_scheduler_creation() {
@@ -319,6 +635,7 @@ mod tests {
                             conflicts_found: 0,
                             pending_persistence: 0,
                             duration: Duration::from_millis(100),
+                            ..Default::default()
                         })
                     }
                 })
@@ -2,26 +2,125 @@
 //!
 //! # Layering contract — MUST READ
 //!
-//! The bytes passed to [`StagingArea::stage_upload`] **MUST be ciphertext**.
-//! This layer is *not* encrypted at rest: it holds whatever the caller
-//! hands it on the local filesystem, with `0600` (file) and `0700`
-//! (directory) permissions on Unix as defense-in-depth, but those modes
-//! do not protect against a same-user attacker (compromised process,
-//! malicious app on a phone) reading the staging tree directly.
-//!
-//! Concretely: the vault MUST encrypt before staging; `stage_upload` is
-//! not allowed to see plaintext. See audit finding M-5 in
+//! The staging area holds **ciphertext only**. [`StagingArea::stage_upload`]
+//! accepts a [`StagedBlob`], whose bytes are an
+//! [`axiomvault_crypto::Ciphertext`] that only the crypto layer can produce;
+//! the vault encrypts before staging
+//! ([`VaultOperations::seal_for_staging`](axiomvault_vault::VaultOperations::seal_for_staging)),
+//! so this crate never sees plaintext. See audit finding M-5 in
 //! `docs/SECURITY_AUDIT_2026-04-21.md`.
+//!
+//! A staging area is bound to one vault through a [`StagingKey`]: blobs of
+//! other vaults are refused, and the registry, which lists the vault paths
+//! of pending changes, is encrypted under a key derived from the vault's
+//! master key. Files are still written with `0600` (file) and `0700`
+//! (directory) permissions on Unix as defense-in-depth.
+//!
+//! # Sharing between processes
+//!
+//! The CLI and the desktop app may open the same staging directory at once.
+//! Every registry update takes an advisory lock on `staging.lock`, reloads
+//! the registry if another process wrote a newer generation of it, and only
+//! then applies its change, so updates merge instead of overwriting each
+//! other. Commits are journaled before they are applied; whichever process
+//! takes the lock next finishes a commit that was interrupted. Syncing
+//! itself is exclusive: a [`SyncLease`] on `sync.lock` names the process
+//! holding it, and others are refused with [`Error::SyncInProgress`].
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fs::TryLockError;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use tokio::fs;
-use tracing::warn;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_common::{Error, Result, VaultId, VaultPath};
+use axiomvault_crypto::{decrypt_with_aad, encrypt_with_aad, FileKey};
+use axiomvault_storage::UploadSession;
+use axiomvault_vault::{BlobFormat, VaultSession};
+
+use crate::state::SyncState;
+
+pub use axiomvault_vault::StagedBlob;
+
+/// Lock file serializing registry updates across processes.
+const REGISTRY_LOCK_FILE: &str = "staging.lock";
+
+/// Lock file held for the duration of a sync.
+const SYNC_LEASE_FILE: &str = "sync.lock";
+
+/// Write-ahead journal of the commit being applied.
+const COMMIT_JOURNAL_FILE: &str = "staging_journal.json";
+
+/// Sync state shared by the engines of all processes, sealed like the
+/// registry.
+const SYNC_STATE_FILE: &str = "sync_state.sealed";
+
+/// Open (creating if needed) a lock file, `0o600` on Unix.
+fn open_lock_file(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Exclusive advisory lock on the staging registry, released on drop.
+struct RegistryLock {
+    _file: std::fs::File,
+}
+
+impl RegistryLock {
+    /// Wait until no other process holds the lock, then take it.
+    async fn acquire(path: &Path) -> Result<Self> {
+        let path = path.to_path_buf();
+        let file = tokio::task::spawn_blocking(move || {
+            let file = open_lock_file(&path)?;
+            file.lock()?;
+            Ok::<_, std::io::Error>(file)
+        })
+        .await
+        .map_err(|e| Error::Io(std::io::Error::other(e)))?
+        .map_err(Error::Io)?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Proof that this process is the one syncing a staging area.
+///
+/// Taken with [`StagingArea::try_sync_lease`]; other processes are refused
+/// until it is dropped. The lock file records the holder's process ID so
+/// the refusal can name it.
+#[derive(Debug)]
+pub struct SyncLease {
+    _file: std::fs::File,
+}
+
+/// The registry as written to disk.
+#[derive(Serialize, Deserialize)]
+struct RegistryFile<C> {
+    /// Incremented by every write, so readers can tell their copy is stale.
+    generation: u64,
+    changes: C,
+}
+
+/// A commit recorded before it is applied.
+///
+/// Written durably before the registry drops the changes, and removed once
+/// their staging files are gone. A journal left behind belongs to a commit
+/// that was interrupted, which the next process to lock the registry
+/// finishes.
+#[derive(Debug, Serialize, Deserialize)]
+struct CommitJournal {
+    change_ids: Vec<String>,
+    files: Vec<PathBuf>,
+}
 
 /// Open `path` for writing with `0o600` permissions on Unix, fail if it
 /// already exists. On non-Unix this falls back to a plain create-new write.
@@ -43,6 +142,7 @@ async fn write_private_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
             .await?;
         file.write_all(data).await?;
         file.flush().await?;
+        file.sync_all().await?;
         Ok(())
     }
 
@@ -56,10 +156,31 @@ async fn write_private_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
             .await?;
         file.write_all(data).await?;
         file.flush().await?;
+        file.sync_all().await?;
         Ok(())
     }
 }
 
+/// Replace `path` with `data` atomically (write-to-temp + rename).
+///
+/// A leftover temp file from a previous crashed write is removed first so
+/// `create_new` succeeds.
+async fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    if tmp_path.exists() {
+        // Best-effort cleanup of a stale temp from a prior crashed write.
+        if let Err(e) = fs::remove_file(&tmp_path).await {
+            warn!("failed to remove stale temp {}: {}", tmp_path.display(), e);
+        }
+    }
+    write_private_file(&tmp_path, data)
+        .await
+        .map_err(Error::Io)?;
+    fs::rename(&tmp_path, path).await.map_err(Error::Io)
+}
+
 /// A staged change waiting to be committed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StagedChange {
@@ -75,6 +196,40 @@ pub struct StagedChange {
     pub staging_file: Option<PathBuf>,
     /// Size of the data.
     pub size: u64,
+    /// Storage name of the file node the staged blob belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_id: Option<String>,
+    /// Format the staged blob is encrypted in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format_version: Option<BlobFormat>,
+    /// When the change reached the remote, if it has.
+    ///
+    /// Set by [`StagingArea::mark_uploaded`] before the change is committed,
+    /// so a crash between the two does not replay the change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uploaded_at: Option<DateTime<Utc>>,
+    /// ETag the provider reported for the uploaded data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_etag: Option<String>,
+    /// Open resumable upload session, if the upload was started in chunks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_session: Option<UploadJournal>,
+}
+
+/// Progress of a chunked upload, persisted so another run can resume it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadJournal {
+    /// Backend session the chunks are sent to.
+    pub session: UploadSession,
+    /// Bytes the backend acknowledged as of the last recorded chunk.
+    pub acknowledged: u64,
+}
+
+impl StagedChange {
+    /// Whether the change already reached the remote and only awaits commit.
+    pub fn is_uploaded(&self) -> bool {
+        self.uploaded_at.is_some()
+    }
 }
 
 /// Type of staged change.
@@ -88,6 +243,74 @@ pub enum ChangeType {
     Delete,
 }
 
+/// Binds a staging area to one vault.
+///
+/// Holds the vault's ID, checked against every staged blob, and the key the
+/// registry is encrypted with.
+#[derive(Debug, Clone)]
+pub struct StagingKey {
+    vault_id: VaultId,
+    key: FileKey,
+}
+
+impl StagingKey {
+    /// Bind to `vault_id`, encrypting the registry under `key`.
+    pub fn new(vault_id: VaultId, key: FileKey) -> Self {
+        Self { vault_id, key }
+    }
+
+    /// Bind to the vault of an unlocked session.
+    pub fn for_session(session: &VaultSession) -> Result<Self> {
+        Ok(Self::new(
+            session.vault_id().clone(),
+            session.staging_key()?,
+        ))
+    }
+
+    /// Vault the staging area belongs to.
+    pub fn vault_id(&self) -> &VaultId {
+        &self.vault_id
+    }
+
+    /// Encrypt the registry JSON, binding it to the vault ID.
+    fn seal_registry(&self, json: &[u8]) -> Result<Vec<u8>> {
+        encrypt_with_aad(self.key.as_bytes(), json, self.vault_id.as_str().as_bytes())
+    }
+
+    fn open_registry(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        decrypt_with_aad(
+            self.key.as_bytes(),
+            bytes,
+            self.vault_id.as_str().as_bytes(),
+        )
+    }
+
+    /// Decode a registry file into its generation and changes.
+    ///
+    /// Registries written before generations were tracked are generation 0.
+    /// The flag is set for a plaintext registry written before registries
+    /// were encrypted.
+    fn decode_registry(
+        &self,
+        bytes: &[u8],
+    ) -> std::result::Result<(u64, HashMap<String, StagedChange>, bool), String> {
+        match self.open_registry(bytes) {
+            Ok(json) => {
+                if let Ok(file) = serde_json::from_slice::<RegistryFile<_>>(&json) {
+                    return Ok((file.generation, file.changes, false));
+                }
+                serde_json::from_slice(&json)
+                    .map(|changes| (0, changes, false))
+                    .map_err(|e| e.to_string())
+            }
+            Err(e) => match serde_json::from_slice(bytes) {
+                Ok(changes) => Ok((0, changes, true)),
+                Err(_) => Err(e.to_string()),
+            },
+        }
+    }
+}
+
 /// Local staging area for managing pending changes.
 pub struct StagingArea {
     /// Base directory for staging files.
@@ -96,6 +319,10 @@ pub struct StagingArea {
     changes: HashMap<String, StagedChange>,
     /// Path to persist the registry.
     registry_path: PathBuf,
+    /// Generation of the registry `changes` was read from or written as.
+    generation: u64,
+    /// Vault binding and registry key.
+    key: StagingKey,
 }
 
 impl StagingArea {
@@ -108,8 +335,11 @@ impl StagingArea {
     /// corrupt file is renamed to `staging_registry.json.corrupt-{ts}` so
     /// the operator can inspect it later, and a fresh empty registry is
     /// started instead of silently dropping all in-flight changes (audit
-    /// L-7).
-    pub async fn new(base_dir: impl AsRef<Path>) -> Result<Self> {
+    /// L-7). A registry that does not decrypt under `key`, such as one of
+    /// another vault, is treated the same way. A plaintext registry written
+    /// before registries were encrypted is loaded and re-written encrypted.
+    /// A commit another process left half done is finished.
+    pub async fn new(base_dir: impl AsRef<Path>, key: StagingKey) -> Result<Self> {
         let base_dir = base_dir.as_ref().to_path_buf();
         let staging_dir = base_dir.join("staging");
         let registry_path = base_dir.join("staging_registry.json");
@@ -127,15 +357,27 @@ impl StagingArea {
                 .map_err(Error::Io)?;
         }
 
+        let mut staging = Self {
+            base_dir: staging_dir,
+            changes: HashMap::new(),
+            registry_path,
+            generation: 0,
+            key,
+        };
+        let _lock = RegistryLock::acquire(&staging.lock_path()).await?;
+
         // Load existing registry if present. Corrupt JSON is preserved on
         // disk (renamed) so it can be inspected, and we start fresh —
         // never silently drop in-flight changes (audit L-7).
-        let changes = if registry_path.exists() {
-            let content = fs::read_to_string(&registry_path)
-                .await
-                .map_err(Error::Io)?;
-            match serde_json::from_str::<HashMap<String, StagedChange>>(&content) {
-                Ok(map) => map,
+        let mut legacy = false;
+        if staging.registry_path.exists() {
+            let content = fs::read(&staging.registry_path).await.map_err(Error::Io)?;
+            match staging.key.decode_registry(&content) {
+                Ok((generation, changes, plaintext)) => {
+                    staging.generation = generation;
+                    staging.changes = changes;
+                    legacy = plaintext;
+                }
                 Err(e) => {
                     // chrono's `%6f` is a width directive for nanoseconds and
                     // does not emit fractional seconds on its own; build the
@@ -155,48 +397,56 @@ impl StagingArea {
                         use std::fmt::Write as _;
                         let _ = write!(&mut rand_suffix, "{byte:02x}");
                     }
-                    let corrupt_path = registry_path.with_file_name(format!(
+                    let corrupt_path = staging.registry_path.with_file_name(format!(
                         "staging_registry.json.corrupt-{}-{}",
                         ts, rand_suffix
                     ));
                     warn!(
                         "staging registry at {} is corrupt ({}); preserving as {} and starting a fresh registry (audit L-7)",
-                        registry_path.display(),
+                        staging.registry_path.display(),
                         e,
                         corrupt_path.display()
                     );
-                    fs::rename(&registry_path, &corrupt_path)
+                    fs::rename(&staging.registry_path, &corrupt_path)
                         .await
                         .map_err(Error::Io)?;
-                    HashMap::new()
                 }
             }
-        } else {
-            HashMap::new()
-        };
+        }
 
-        Ok(Self {
-            base_dir: staging_dir,
-            changes,
-            registry_path,
-        })
+        if legacy {
+            staging.persist_registry().await?;
+        }
+        staging.recover_journal().await?;
+        Ok(staging)
     }
 
-    /// Stage data for upload.
+    /// Stage an encrypted blob for upload.
     ///
-    /// **Contract:** `data` MUST be ciphertext. See the module-level docs
-    /// (audit M-5).
+    /// # Errors
+    /// - `InvalidInput` if the blob belongs to another vault
     pub async fn stage_upload(
         &mut self,
         vault_path: &VaultPath,
-        data: Vec<u8>,
+        blob: StagedBlob,
         change_type: ChangeType,
     ) -> Result<String> {
+        if blob.vault_id() != self.key.vault_id() {
+            return Err(Error::InvalidInput(format!(
+                "Blob of vault {} cannot be staged for vault {}",
+                blob.vault_id(),
+                self.key.vault_id()
+            )));
+        }
+
+        // Lock before writing the file, so another process cleaning up
+        // orphans cannot take it for one.
+        let _lock = self.lock_for_update().await?;
         let change_id = Uuid::new_v4().to_string();
         let staging_file = self.base_dir.join(&change_id);
 
         // Write data to staging file with mode 0o600 on Unix (audit M-5).
-        write_private_file(&staging_file, &data)
+        write_private_file(&staging_file, blob.ciphertext().as_bytes())
             .await
             .map_err(Error::Io)?;
 
@@ -206,7 +456,12 @@ impl StagingArea {
             change_type,
             staged_at: Utc::now(),
             staging_file: Some(staging_file),
-            size: data.len() as u64,
+            size: blob.ciphertext().len() as u64,
+            node_id: Some(blob.node_id().to_string()),
+            format_version: Some(blob.format_version()),
+            uploaded_at: None,
+            remote_etag: None,
+            upload_session: None,
         };
 
         self.changes.insert(change_id.clone(), change);
@@ -217,6 +472,7 @@ impl StagingArea {
 
     /// Stage a delete operation.
     pub async fn stage_delete(&mut self, vault_path: &VaultPath) -> Result<String> {
+        let _lock = self.lock_for_update().await?;
         let change_id = Uuid::new_v4().to_string();
 
         let change = StagedChange {
@@ -226,6 +482,11 @@ impl StagingArea {
             staged_at: Utc::now(),
             staging_file: None,
             size: 0,
+            node_id: None,
+            format_version: None,
+            uploaded_at: None,
+            remote_etag: None,
+            upload_session: None,
         };
 
         self.changes.insert(change_id.clone(), change);
@@ -248,6 +509,39 @@ impl StagingArea {
         fs::read(staging_file).await.map_err(Error::Io)
     }
 
+    /// Read up to `len` bytes of staged data starting at `offset`.
+    ///
+    /// Returns fewer bytes only at the end of the data.
+    pub async fn read_staged_chunk(
+        &self,
+        change_id: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>> {
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        let change = self
+            .changes
+            .get(change_id)
+            .ok_or_else(|| Error::NotFound(format!("Staged change not found: {}", change_id)))?;
+
+        let staging_file = change.staging_file.as_ref().ok_or_else(|| {
+            Error::InvalidInput("No staging file for this change type".to_string())
+        })?;
+
+        let mut file = fs::File::open(staging_file).await.map_err(Error::Io)?;
+        file.seek(std::io::SeekFrom::Start(offset))
+            .await
+            .map_err(Error::Io)?;
+
+        let mut buf = Vec::with_capacity(len);
+        file.take(len as u64)
+            .read_to_end(&mut buf)
+            .await
+            .map_err(Error::Io)?;
+        Ok(buf)
+    }
+
     /// Get a staged change by ID.
     pub fn get_change(&self, change_id: &str) -> Option<&StagedChange> {
         self.changes.get(change_id)
@@ -266,24 +560,103 @@ impl StagingArea {
             .collect()
     }
 
-    /// Commit (remove) a staged change after successful sync.
-    pub async fn commit(&mut self, change_id: &str) -> Result<()> {
+    /// Durably record how far a chunked upload got.
+    ///
+    /// Called when the session opens and after every acknowledged chunk, so
+    /// a restarted engine resumes from `acknowledged` instead of byte zero.
+    pub async fn record_upload_progress(
+        &mut self,
+        change_id: &str,
+        session: UploadSession,
+        acknowledged: u64,
+    ) -> Result<()> {
+        let _lock = self.lock_for_update().await?;
         let change = self
             .changes
-            .remove(change_id)
+            .get_mut(change_id)
             .ok_or_else(|| Error::NotFound(format!("Staged change not found: {}", change_id)))?;
+        change.upload_session = Some(UploadJournal {
+            session,
+            acknowledged,
+        });
+        self.persist_registry().await
+    }
 
-        // Delete the staging file if present
-        if let Some(staging_file) = &change.staging_file {
-            if staging_file.exists() {
-                fs::remove_file(staging_file).await.map_err(Error::Io)?;
-            }
+    /// Forget the upload session of a change, e.g. after it expired.
+    pub async fn clear_upload_progress(&mut self, change_id: &str) -> Result<()> {
+        let _lock = self.lock_for_update().await?;
+        let change = self
+            .changes
+            .get_mut(change_id)
+            .ok_or_else(|| Error::NotFound(format!("Staged change not found: {}", change_id)))?;
+        if change.upload_session.take().is_some() {
+            self.persist_registry().await?;
         }
+        Ok(())
+    }
 
-        self.persist_registry().await?;
+    /// Durably record that a change reached the remote.
+    ///
+    /// Call this after the remote operation succeeds and before
+    /// [`commit`](Self::commit). If the process dies in between, the change
+    /// is still in the registry but marked uploaded, and
+    /// [`drain_uploaded`](Self::drain_uploaded) finishes it on the next run
+    /// instead of replaying it against the remote.
+    pub async fn mark_uploaded(
+        &mut self,
+        change_id: &str,
+        remote_etag: Option<String>,
+    ) -> Result<()> {
+        let _lock = self.lock_for_update().await?;
+        let change = self
+            .changes
+            .get_mut(change_id)
+            .ok_or_else(|| Error::NotFound(format!("Staged change not found: {}", change_id)))?;
+        change.uploaded_at = Some(Utc::now());
+        change.remote_etag = remote_etag;
+        change.upload_session = None;
+        self.persist_registry().await
+    }
+
+    /// Commit (remove) a staged change after successful sync.
+    pub async fn commit(&mut self, change_id: &str) -> Result<()> {
+        self.commit_all(&[change_id.to_string()]).await?;
         Ok(())
     }
 
+    /// Commit several staged changes with a single registry write.
+    ///
+    /// The commit is journaled first, and staging files are removed only
+    /// after the registry no longer lists them, so a crash leaves at worst
+    /// a journal for the next process to finish, never a registry entry
+    /// pointing at missing data.
+    ///
+    /// # Errors
+    /// - `NotFound` if any ID is unknown, including one another process
+    ///   already committed; nothing is committed in that case
+    pub async fn commit_all(&mut self, change_ids: &[String]) -> Result<Vec<StagedChange>> {
+        let _lock = self.lock_for_update().await?;
+        self.commit_locked(change_ids).await
+    }
+
+    /// Commit every change already marked uploaded and return them.
+    ///
+    /// Used on recovery: these changes reached the remote in an earlier run
+    /// that stopped before committing them.
+    pub async fn drain_uploaded(&mut self) -> Result<Vec<StagedChange>> {
+        let _lock = self.lock_for_update().await?;
+        let uploaded: Vec<String> = self
+            .changes
+            .values()
+            .filter(|c| c.is_uploaded())
+            .map(|c| c.id.clone())
+            .collect();
+        if uploaded.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.commit_locked(&uploaded).await
+    }
+
     /// Rollback (remove) a staged change without committing.
     pub async fn rollback(&mut self, change_id: &str) -> Result<()> {
         // Same as commit - just removes from staging
@@ -292,13 +665,191 @@ impl StagingArea {
 
     /// Clear all staged changes.
     pub async fn clear(&mut self) -> Result<()> {
+        let _lock = self.lock_for_update().await?;
         let change_ids: Vec<String> = self.changes.keys().cloned().collect();
-        for change_id in change_ids {
-            self.commit(&change_id).await?;
-        }
+        self.commit_locked(&change_ids).await?;
         Ok(())
     }
 
+    /// Commit `change_ids`; the caller holds the registry lock.
+    async fn commit_locked(&mut self, change_ids: &[String]) -> Result<Vec<StagedChange>> {
+        if let Some(missing) = change_ids.iter().find(|id| !self.changes.contains_key(*id)) {
+            return Err(Error::NotFound(format!(
+                "Staged change not found: {}",
+                missing
+            )));
+        }
+
+        let journal = CommitJournal {
+            change_ids: change_ids.to_vec(),
+            files: change_ids
+                .iter()
+                .filter_map(|id| self.changes[id].staging_file.clone())
+                .collect(),
+        };
+        let json = serde_json::to_vec(&journal).map_err(|e| Error::Serialization(e.to_string()))?;
+        write_atomically(&self.journal_path(), &json).await?;
+
+        let committed: Vec<StagedChange> = change_ids
+            .iter()
+            .filter_map(|id| self.changes.remove(id))
+            .collect();
+        self.persist_registry().await?;
+        self.finish_commit(&journal).await?;
+
+        Ok(committed)
+    }
+
+    /// Finish a commit whose journal another run left behind.
+    ///
+    /// The journal is written before the registry, so the changes it lists
+    /// may still be registered; they are dropped, and their staging files
+    /// removed, exactly as the interrupted commit would have. The caller
+    /// holds the registry lock.
+    async fn recover_journal(&mut self) -> Result<()> {
+        let journal_path = self.journal_path();
+        let content = match fs::read(&journal_path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(Error::Io(e)),
+        };
+        let journal: CommitJournal = serde_json::from_slice(&content)
+            .map_err(|e| Error::Serialization(format!("staging journal: {}", e)))?;
+        info!(
+            "Finishing interrupted commit of {} staged changes",
+            journal.change_ids.len()
+        );
+
+        let before = self.changes.len();
+        for id in &journal.change_ids {
+            self.changes.remove(id);
+        }
+        if self.changes.len() != before {
+            self.persist_registry().await?;
+        }
+        self.finish_commit(&journal).await
+    }
+
+    /// Remove the staging files of a journaled commit, then the journal.
+    async fn finish_commit(&self, journal: &CommitJournal) -> Result<()> {
+        for file in &journal.files {
+            match fs::remove_file(file).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+        fs::remove_file(self.journal_path())
+            .await
+            .map_err(Error::Io)
+    }
+
+    /// Write the registry, with the progress of resumable uploads, to disk.
+    ///
+    /// Every change is written as it happens; this is for shutdown, to
+    /// leave the registry complete whatever happened before.
+    pub async fn checkpoint(&mut self) -> Result<()> {
+        let _lock = self.lock_for_update().await?;
+        self.persist_registry().await
+    }
+
+    /// Pick up changes other processes made to the registry.
+    ///
+    /// Returns whether anything changed. Updates through this area reload
+    /// the registry on their own; this is for readers, such as a sync about
+    /// to plan its uploads.
+    pub async fn refresh(&mut self) -> Result<bool> {
+        let generation = self.generation;
+        let _lock = self.lock_for_update().await?;
+        Ok(self.generation != generation)
+    }
+
+    /// Generation of the registry as last read or written.
+    ///
+    /// Incremented by every registry write of any process.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Merge `state` with the copy other processes shared, write the result
+    /// back as the next generation and return it.
+    ///
+    /// The shared copy is merged only if its generation differs from the
+    /// one `state` was last merged with, that is, if another engine wrote
+    /// it since. A shared copy that cannot be read is replaced: sync state
+    /// can be rebuilt from the remote and the staged changes.
+    pub async fn share_state(&mut self, mut state: SyncState) -> Result<SyncState> {
+        let _lock = RegistryLock::acquire(&self.lock_path()).await?;
+        if let Some(shared) = self.read_shared_state().await? {
+            if shared.generation != state.generation {
+                debug!(
+                    "Merging sync state generation {} into {}",
+                    shared.generation, state.generation
+                );
+                state.merge(shared);
+            }
+        }
+        state.generation += 1;
+
+        let json = serde_json::to_vec(&state).map_err(|e| Error::Serialization(e.to_string()))?;
+        let sealed = self.key.seal_registry(&json)?;
+        write_atomically(&self.state_path(), &sealed).await?;
+        Ok(state)
+    }
+
+    /// The sync state last shared by any process, if there is one.
+    pub async fn shared_state(&self) -> Result<Option<SyncState>> {
+        let _lock = RegistryLock::acquire(&self.lock_path()).await?;
+        self.read_shared_state().await
+    }
+
+    /// Read the shared sync state; the caller holds the registry lock.
+    async fn read_shared_state(&self) -> Result<Option<SyncState>> {
+        let content = match fs::read(self.state_path()).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::Io(e)),
+        };
+        let state = self
+            .key
+            .open_registry(&content)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok());
+        if state.is_none() {
+            warn!(
+                "shared sync state at {} cannot be read; replacing it",
+                self.state_path().display()
+            );
+        }
+        Ok(state)
+    }
+
+    /// Claim the staging area for a sync.
+    ///
+    /// Fails at once, rather than waiting, when another process is
+    /// syncing. The lease is per open file, so two areas over the same
+    /// directory exclude each other even in one process.
+    ///
+    /// # Errors
+    /// - `SyncInProgress` naming the holder's process ID, if it is known
+    pub fn try_sync_lease(&self) -> Result<SyncLease> {
+        let mut file = open_lock_file(&self.sync_lease_path()).map_err(Error::Io)?;
+        match file.try_lock() {
+            Ok(()) => {
+                file.set_len(0).map_err(Error::Io)?;
+                write!(file, "{}", std::process::id()).map_err(Error::Io)?;
+                file.flush().map_err(Error::Io)?;
+                Ok(SyncLease { _file: file })
+            }
+            Err(TryLockError::WouldBlock) => {
+                let mut holder = String::new();
+                file.read_to_string(&mut holder).map_err(Error::Io)?;
+                Err(Error::SyncInProgress(holder.trim().parse().ok()))
+            }
+            Err(TryLockError::Error(e)) => Err(Error::Io(e)),
+        }
+    }
+
     /// Get total size of staged data.
     pub fn total_size(&self) -> u64 {
         self.changes.values().map(|c| c.size).sum()
@@ -314,37 +865,99 @@ impl StagingArea {
         self.changes.is_empty()
     }
 
-    /// Persist the registry to disk atomically (write-to-temp + rename).
+    /// Staging files and registry that contain any of `markers`.
     ///
-    /// On Unix the temp file is created with mode `0o600` so the registry
-    /// (which contains pending change metadata: paths, sizes, change types)
-    /// is not world-readable (audit M-5). A leftover temp file from a
-    /// previous crashed write is removed first so `create_new` succeeds.
-    async fn persist_registry(&self) -> Result<()> {
-        let json = serde_json::to_string_pretty(&self.changes)
-            .map_err(|e| Error::Serialization(e.to_string()))?;
-
-        let tmp_path = self.registry_path.with_extension("json.tmp");
-        if tmp_path.exists() {
-            // Best-effort cleanup of a stale temp from a prior crashed write.
-            if let Err(e) = fs::remove_file(&tmp_path).await {
-                warn!(
-                    "failed to remove stale staging registry temp {}: {}",
-                    tmp_path.display(),
-                    e
-                );
+    /// A check for tests and debugging: with markers taken from known
+    /// plaintext, any hit means plaintext reached the staging directory.
+    pub async fn scan_for_plaintext(&self, markers: &[&[u8]]) -> Result<Vec<PathBuf>> {
+        let mut files = vec![self.registry_path.clone()];
+        let mut entries = fs::read_dir(&self.base_dir).await.map_err(Error::Io)?;
+        while let Some(entry) = entries.next_entry().await.map_err(Error::Io)? {
+            files.push(entry.path());
+        }
+
+        let mut hits = Vec::new();
+        for file in files {
+            let bytes = match fs::read(&file).await {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(Error::Io(e)),
+            };
+            let found = markers.iter().any(|marker| {
+                !marker.is_empty() && bytes.windows(marker.len()).any(|w| w == *marker)
+            });
+            if found {
+                hits.push(file);
             }
         }
-        write_private_file(&tmp_path, json.as_bytes())
-            .await
-            .map_err(Error::Io)?;
-        fs::rename(&tmp_path, &self.registry_path)
-            .await
-            .map_err(Error::Io)
+        Ok(hits)
+    }
+
+    /// Persist the registry to disk atomically as its next generation.
+    ///
+    /// The registry (which contains pending change metadata: paths, sizes,
+    /// change types) is encrypted under the staging key, and on Unix it is
+    /// written with mode `0o600` as well (audit M-5). The caller holds the
+    /// registry lock.
+    async fn persist_registry(&mut self) -> Result<()> {
+        let file = RegistryFile {
+            generation: self.generation + 1,
+            changes: &self.changes,
+        };
+        let json = serde_json::to_vec(&file).map_err(|e| Error::Serialization(e.to_string()))?;
+        let sealed = self.key.seal_registry(&json)?;
+        write_atomically(&self.registry_path, &sealed).await?;
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Lock the registry and bring `changes` up to date with it.
+    ///
+    /// Another process may have written the registry since this one last
+    /// read it; a newer generation on disk replaces the copy held here, so
+    /// the caller's update applies on top of it. A commit left half done is
+    /// finished first.
+    ///
+    /// # Errors
+    /// - `Integrity` if the registry on disk no longer decrypts
+    async fn lock_for_update(&mut self) -> Result<RegistryLock> {
+        let lock = RegistryLock::acquire(&self.lock_path()).await?;
+        match fs::read(&self.registry_path).await {
+            Ok(content) => {
+                let (generation, changes, _) = self.key.decode_registry(&content).map_err(|e| {
+                    Error::Integrity(format!("staging registry cannot be read: {}", e))
+                })?;
+                if generation != self.generation {
+                    self.generation = generation;
+                    self.changes = changes;
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(Error::Io(e)),
+        }
+        self.recover_journal().await?;
+        Ok(lock)
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.registry_path.with_file_name(REGISTRY_LOCK_FILE)
+    }
+
+    fn sync_lease_path(&self) -> PathBuf {
+        self.registry_path.with_file_name(SYNC_LEASE_FILE)
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.registry_path.with_file_name(COMMIT_JOURNAL_FILE)
+    }
+
+    fn state_path(&self) -> PathBuf {
+        self.registry_path.with_file_name(SYNC_STATE_FILE)
     }
 
     /// Clean up orphaned staging files.
     pub async fn cleanup_orphaned(&mut self) -> Result<usize> {
+        let _lock = self.lock_for_update().await?;
         let mut cleaned = 0;
         let mut entries = fs::read_dir(&self.base_dir).await.map_err(Error::Io)?;
 
@@ -369,25 +982,43 @@ impl StagingArea {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axiomvault_crypto::seal;
     use tempfile::TempDir;
 
+    fn test_key() -> StagingKey {
+        StagingKey::new(
+            VaultId::new("vault").unwrap(),
+            FileKey::from_bytes([7u8; 32]),
+        )
+    }
+
+    fn blob(data: &[u8]) -> StagedBlob {
+        let ciphertext = seal(&[9u8; 32], data).unwrap();
+        StagedBlob::new(
+            ciphertext,
+            VaultId::new("vault").unwrap(),
+            "node",
+            BlobFormat::LATEST,
+        )
+    }
+
     #[tokio::test]
     async fn test_staging_area_creation() {
         let temp = TempDir::new().unwrap();
-        let staging = StagingArea::new(temp.path()).await.unwrap();
+        let staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
         assert!(staging.is_empty());
     }
 
     #[tokio::test]
     async fn test_stage_upload() {
         let temp = TempDir::new().unwrap();
-        let mut staging = StagingArea::new(temp.path()).await.unwrap();
+        let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
 
         let path = VaultPath::parse("/test.txt").unwrap();
-        let data = b"Hello, World!".to_vec();
+        let blob = blob(b"Hello, World!");
 
         let change_id = staging
-            .stage_upload(&path, data.clone(), ChangeType::Create)
+            .stage_upload(&path, blob.clone(), ChangeType::Create)
             .await
             .unwrap();
 
@@ -395,13 +1026,16 @@ mod tests {
         assert_eq!(staging.count(), 1);
 
         let retrieved = staging.get_staged_data(&change_id).await.unwrap();
-        assert_eq!(retrieved, data);
+        assert_eq!(retrieved, blob.ciphertext().as_bytes());
+        let change = staging.get_change(&change_id).unwrap();
+        assert_eq!(change.node_id.as_deref(), Some("node"));
+        assert_eq!(change.format_version, Some(BlobFormat::LATEST));
     }
 
     #[tokio::test]
     async fn test_stage_delete() {
         let temp = TempDir::new().unwrap();
-        let mut staging = StagingArea::new(temp.path()).await.unwrap();
+        let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
 
         let path = VaultPath::parse("/test.txt").unwrap();
         let _change_id = staging.stage_delete(&path).await.unwrap();
@@ -414,11 +1048,11 @@ mod tests {
     #[tokio::test]
     async fn test_commit_change() {
         let temp = TempDir::new().unwrap();
-        let mut staging = StagingArea::new(temp.path()).await.unwrap();
+        let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
 
         let path = VaultPath::parse("/test.txt").unwrap();
         let change_id = staging
-            .stage_upload(&path, b"data".to_vec(), ChangeType::Create)
+            .stage_upload(&path, blob(b"data"), ChangeType::Create)
             .await
             .unwrap();
 
@@ -434,21 +1068,249 @@ mod tests {
 
         // Create and stage
         {
-            let mut staging = StagingArea::new(temp.path()).await.unwrap();
+            let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
             let path = VaultPath::parse("/test.txt").unwrap();
             staging
-                .stage_upload(&path, b"data".to_vec(), ChangeType::Create)
+                .stage_upload(&path, blob(b"data"), ChangeType::Create)
                 .await
                 .unwrap();
         }
 
         // Reload and verify
         {
-            let staging = StagingArea::new(temp.path()).await.unwrap();
+            let staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
             assert_eq!(staging.count(), 1);
         }
     }
 
+    #[tokio::test]
+    async fn test_mark_uploaded_survives_restart_and_drains() {
+        let temp = TempDir::new().unwrap();
+        let path = VaultPath::parse("/test.txt").unwrap();
+
+        let (uploaded_id, pending_id) = {
+            let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
+            let uploaded_id = staging
+                .stage_upload(&path, blob(b"data"), ChangeType::Create)
+                .await
+                .unwrap();
+            let pending_id = staging.stage_delete(&path).await.unwrap();
+            staging
+                .mark_uploaded(&uploaded_id, Some("etag-1".to_string()))
+                .await
+                .unwrap();
+            (uploaded_id, pending_id)
+        };
+
+        let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        assert!(staging.get_change(&uploaded_id).unwrap().is_uploaded());
+        assert!(!staging.get_change(&pending_id).unwrap().is_uploaded());
+
+        let drained = staging.drain_uploaded().await.unwrap();
+        assert_eq!(drained.len(), 1);
+        assert_eq!(drained[0].remote_etag.as_deref(), Some("etag-1"));
+        assert!(!temp.path().join("staging").join(&uploaded_id).exists());
+        assert_eq!(staging.count(), 1);
+        assert!(staging.drain_uploaded().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_commit_all_rejects_unknown_ids() {
+        let temp = TempDir::new().unwrap();
+        let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        let path = VaultPath::parse("/test.txt").unwrap();
+        let id = staging.stage_delete(&path).await.unwrap();
+
+        let result = staging
+            .commit_all(&[id.clone(), "missing".to_string()])
+            .await;
+        assert!(matches!(result, Err(Error::NotFound(_))));
+        assert_eq!(staging.count(), 1);
+
+        staging.commit_all(&[id]).await.unwrap();
+        assert!(staging.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_registry_is_encrypted_and_bound_to_key() {
+        let temp = TempDir::new().unwrap();
+        let path = VaultPath::parse("/secret-plans.txt").unwrap();
+        {
+            let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
+            staging
+                .stage_upload(&path, blob(b"launch codes"), ChangeType::Create)
+                .await
+                .unwrap();
+            let hits = staging
+                .scan_for_plaintext(&[b"secret-plans", b"launch codes"])
+                .await
+                .unwrap();
+            assert!(hits.is_empty(), "plaintext found in {:?}", hits);
+            assert_eq!(
+                staging.scan_for_plaintext(&[b"\"id\""]).await.unwrap(),
+                Vec::<PathBuf>::new()
+            );
+        }
+
+        let registry = std::fs::read(temp.path().join("staging_registry.json")).unwrap();
+        assert!(serde_json::from_slice::<serde_json::Value>(&registry).is_err());
+
+        let staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        assert_eq!(staging.changes_for_path(&path).len(), 1);
+
+        // Without the vault's key the registry cannot be read; it is kept
+        // aside rather than dropped.
+        let other = StagingKey::new(
+            VaultId::new("vault").unwrap(),
+            FileKey::from_bytes([8u8; 32]),
+        );
+        let staging = StagingArea::new(temp.path(), other).await.unwrap();
+        assert!(staging.is_empty());
+        assert!(std::fs::read_dir(temp.path()).unwrap().any(|e| e
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .starts_with("staging_registry.json.corrupt-")));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_blob_of_other_vault() {
+        let temp = TempDir::new().unwrap();
+        let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        let foreign = StagedBlob::new(
+            seal(&[9u8; 32], b"data").unwrap(),
+            VaultId::new("other").unwrap(),
+            "node",
+            BlobFormat::LATEST,
+        );
+
+        let path = VaultPath::parse("/test.txt").unwrap();
+        let result = staging
+            .stage_upload(&path, foreign, ChangeType::Create)
+            .await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+        assert!(staging.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_registry_is_reencrypted() {
+        let temp = TempDir::new().unwrap();
+        let path = VaultPath::parse("/test.txt").unwrap();
+        let change = StagedChange {
+            id: "legacy".to_string(),
+            vault_path: path.clone(),
+            change_type: ChangeType::Delete,
+            staged_at: Utc::now(),
+            staging_file: None,
+            size: 0,
+            node_id: None,
+            format_version: None,
+            uploaded_at: None,
+            remote_etag: None,
+            upload_session: None,
+        };
+        let registry_path = temp.path().join("staging_registry.json");
+        let legacy = HashMap::from([("legacy".to_string(), change)]);
+        std::fs::write(&registry_path, serde_json::to_vec(&legacy).unwrap()).unwrap();
+
+        let staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        assert!(staging.get_change("legacy").is_some());
+        let registry = std::fs::read(&registry_path).unwrap();
+        assert!(serde_json::from_slice::<serde_json::Value>(&registry).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_areas_sharing_a_directory_merge_updates() {
+        let temp = TempDir::new().unwrap();
+        let mut a = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        let mut b = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        let path = VaultPath::parse("/test.txt").unwrap();
+
+        let from_a = a
+            .stage_upload(&path, blob(b"data"), ChangeType::Create)
+            .await
+            .unwrap();
+        // `b` has not read the registry since, yet its update keeps `a`'s.
+        let from_b = b.stage_delete(&path).await.unwrap();
+        assert_eq!(b.count(), 2);
+        a.mark_uploaded(&from_a, Some("etag-a".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(a.count(), 2);
+
+        // A change committed through one area cannot be committed again
+        // through the other.
+        b.commit(&from_b).await.unwrap();
+        assert!(matches!(a.commit(&from_b).await, Err(Error::NotFound(_))));
+        assert!(!a.refresh().await.unwrap(), "the failed commit reloaded");
+        assert_eq!(a.generation(), b.generation());
+        assert!(b.get_change(&from_a).unwrap().is_uploaded());
+
+        let reopened = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        let ids: Vec<&str> = reopened.all_changes().map(|c| c.id.as_str()).collect();
+        assert_eq!(ids, [from_a.as_str()]);
+        assert_eq!(
+            reopened.get_change(&from_a).unwrap().remote_etag.as_deref(),
+            Some("etag-a")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_commit_is_finished_by_next_process() {
+        let temp = TempDir::new().unwrap();
+        let path = VaultPath::parse("/test.txt").unwrap();
+        let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        let committed = staging
+            .stage_upload(&path, blob(b"one"), ChangeType::Create)
+            .await
+            .unwrap();
+        let kept = staging
+            .stage_upload(&path, blob(b"two"), ChangeType::Update)
+            .await
+            .unwrap();
+
+        // The process stopped right after journaling the commit.
+        let journal = CommitJournal {
+            change_ids: vec![committed.clone()],
+            files: staging
+                .get_change(&committed)
+                .unwrap()
+                .staging_file
+                .clone()
+                .into_iter()
+                .collect(),
+        };
+        let journal_path = temp.path().join(COMMIT_JOURNAL_FILE);
+        std::fs::write(&journal_path, serde_json::to_vec(&journal).unwrap()).unwrap();
+
+        let other = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        assert!(other.get_change(&committed).is_none());
+        assert!(other.get_change(&kept).is_some());
+        assert!(!temp.path().join("staging").join(&committed).exists());
+        assert!(temp.path().join("staging").join(&kept).exists());
+        assert!(!journal_path.exists());
+
+        // The area that was running picks the finished commit up.
+        assert!(staging.refresh().await.unwrap());
+        assert!(staging.get_change(&committed).is_none());
+        assert_eq!(staging.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sync_lease_is_exclusive_and_names_holder() {
+        let temp = TempDir::new().unwrap();
+        let a = StagingArea::new(temp.path(), test_key()).await.unwrap();
+        let b = StagingArea::new(temp.path(), test_key()).await.unwrap();
+
+        let lease = a.try_sync_lease().unwrap();
+        match b.try_sync_lease() {
+            Err(Error::SyncInProgress(pid)) => assert_eq!(pid, Some(std::process::id())),
+            other => panic!("expected SyncInProgress, got {:?}", other),
+        }
+        drop(lease);
+        b.try_sync_lease().unwrap();
+    }
+
     /// Audit M-5: staged files (and the registry) must be `0o600` on Unix
     /// so other local users cannot read pending ciphertext or metadata.
     #[cfg(unix)]
@@ -457,11 +1319,11 @@ mod tests {
         use std::os::unix::fs::PermissionsExt;
 
         let temp = TempDir::new().unwrap();
-        let mut staging = StagingArea::new(temp.path()).await.unwrap();
+        let mut staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
 
         let path = VaultPath::parse("/secret.bin").unwrap();
         let change_id = staging
-            .stage_upload(&path, b"ciphertext".to_vec(), ChangeType::Create)
+            .stage_upload(&path, blob(b"ciphertext"), ChangeType::Create)
             .await
             .unwrap();
 
@@ -510,7 +1372,7 @@ mod tests {
             .await
             .unwrap();
 
-        let staging = StagingArea::new(temp.path()).await.unwrap();
+        let staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
         // Fresh empty registry.
         assert_eq!(staging.count(), 0);
         // The corrupt file must NOT still be at the original path with the
@@ -560,7 +1422,7 @@ mod tests {
         let registry_path = temp.path().join("staging_registry.json");
         tokio::fs::write(&registry_path, b"{ broken").await.unwrap();
 
-        let _staging = StagingArea::new(temp.path()).await.unwrap();
+        let _staging = StagingArea::new(temp.path(), test_key()).await.unwrap();
 
         let mut suffix: Option<String> = None;
         let mut entries = tokio::fs::read_dir(temp.path()).await.unwrap();
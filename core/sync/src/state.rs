@@ -6,6 +6,10 @@ use std::collections::HashMap;
 
 use axiomvault_common::{Error, Result, VaultPath};
 
+use crate::conflict::Side;
+use crate::planner;
+use crate::scheduler::SkipReason;
+
 /// Sync status for a single file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum SyncStatus {
@@ -23,6 +27,10 @@ pub enum SyncStatus {
     Failed,
 }
 
+/// Number of tracked entries in each [`SyncStatus`]; statuses without
+/// entries are absent.
+pub type StatusSnapshot = HashMap<SyncStatus, usize>;
+
 /// Metadata for tracking sync state of a single item.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncEntry {
@@ -32,6 +40,14 @@ pub struct SyncEntry {
     pub local_etag: Option<String>,
     /// Remote etag/revision (last known).
     pub remote_etag: Option<String>,
+    /// Tagged content hash of the last known remote version, when the
+    /// provider reported one for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_content_hash: Option<String>,
+    /// Size of the last known remote version, when the provider reported
+    /// one for it. Stands in for a missing etag.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_size: Option<u64>,
     /// Local modification time.
     pub local_modified: DateTime<Utc>,
     /// Remote modification time (last known).
@@ -44,6 +60,16 @@ pub struct SyncEntry {
     pub failure_count: u32,
     /// Last error message if failed.
     pub last_error: Option<String>,
+    /// When the file was deleted on one side, until the delete is settled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Side the file was deleted on; set together with `deleted_at`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_by: Option<Side>,
+    /// When the entry last changed. Decides which copy wins when the
+    /// states of two engines are merged.
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
 }
 
 impl SyncEntry {
@@ -53,12 +79,17 @@ impl SyncEntry {
             path: path.into(),
             local_etag,
             remote_etag: None,
+            remote_content_hash: None,
+            remote_size: None,
             local_modified: Utc::now(),
             remote_modified: None,
             status: SyncStatus::LocalModified,
             last_synced: None,
             failure_count: 0,
             last_error: None,
+            deleted_at: None,
+            deleted_by: None,
+            updated_at: Utc::now(),
         }
     }
 
@@ -72,34 +103,46 @@ impl SyncEntry {
             path: path.into(),
             local_etag: etag.clone(),
             remote_etag: etag,
+            remote_content_hash: None,
+            remote_size: None,
             local_modified: modified,
             remote_modified: Some(modified),
             status: SyncStatus::Synced,
             last_synced: Some(Utc::now()),
             failure_count: 0,
             last_error: None,
+            deleted_at: None,
+            deleted_by: None,
+            updated_at: Utc::now(),
         }
     }
 
     /// Mark as syncing.
     pub fn mark_syncing(&mut self) {
+        self.touch();
         self.status = SyncStatus::Syncing;
     }
 
     /// Mark as synced successfully.
     pub fn mark_synced(&mut self, etag: Option<String>, modified: DateTime<Utc>) {
+        self.touch();
         self.local_etag = etag.clone();
         self.remote_etag = etag;
+        self.remote_content_hash = None;
+        self.remote_size = None;
         self.local_modified = modified;
         self.remote_modified = Some(modified);
         self.status = SyncStatus::Synced;
         self.last_synced = Some(Utc::now());
         self.failure_count = 0;
         self.last_error = None;
+        self.deleted_at = None;
+        self.deleted_by = None;
     }
 
     /// Mark as failed.
     pub fn mark_failed(&mut self, error: impl Into<String>) {
+        self.touch();
         self.status = SyncStatus::Failed;
         self.failure_count += 1;
         self.last_error = Some(error.into());
@@ -107,33 +150,105 @@ impl SyncEntry {
 
     /// Mark as conflicted.
     pub fn mark_conflicted(&mut self, remote_etag: Option<String>, remote_modified: DateTime<Utc>) {
+        self.touch();
         self.remote_etag = remote_etag;
+        self.remote_content_hash = None;
+        self.remote_size = None;
         self.remote_modified = Some(remote_modified);
         self.status = SyncStatus::Conflicted;
     }
 
     /// Mark local as modified.
+    ///
+    /// Undoes a pending local delete; a remote delete stays recorded so the
+    /// edit is checked against it.
     pub fn mark_local_modified(&mut self, etag: Option<String>) {
+        self.touch();
         self.local_etag = etag;
         self.local_modified = Utc::now();
-        if self.status == SyncStatus::Synced {
-            self.status = SyncStatus::LocalModified;
+        self.status = planner::status_after_local_change(self.status);
+        if self.deleted_by == Some(Side::Local) {
+            self.deleted_at = None;
+            self.deleted_by = None;
         }
     }
 
+    /// Mark as deleted locally.
+    pub fn mark_local_deleted(&mut self) {
+        self.touch();
+        let now = Utc::now();
+        self.local_etag = None;
+        self.local_modified = now;
+        self.status = planner::status_after_local_change(self.status);
+        self.deleted_at = Some(now);
+        self.deleted_by = Some(Side::Local);
+    }
+
+    /// Mark as deleted on the remote.
+    ///
+    /// The remote etag and size are cleared, so the delete is recorded only
+    /// once.
+    pub fn mark_remote_deleted(&mut self, at: DateTime<Utc>) {
+        self.touch();
+        self.remote_etag = None;
+        self.remote_content_hash = None;
+        self.remote_size = None;
+        self.remote_modified = Some(at);
+        self.status = planner::status_after_remote_change(self.status);
+        self.deleted_at = Some(at);
+        self.deleted_by = Some(Side::Remote);
+    }
+
     /// Mark remote as modified.
+    ///
+    /// A file that reappears on the remote is no longer deleted there.
     pub fn mark_remote_modified(&mut self, etag: Option<String>, modified: DateTime<Utc>) {
         if self.remote_etag != etag {
+            self.touch();
             self.remote_etag = etag;
+            self.remote_content_hash = None;
+            self.remote_size = None;
             self.remote_modified = Some(modified);
-            if self.status == SyncStatus::Synced {
-                self.status = SyncStatus::RemoteModified;
-            } else if self.status == SyncStatus::LocalModified {
-                self.status = SyncStatus::Conflicted;
+            self.status = planner::status_after_remote_change(self.status);
+            if self.deleted_by == Some(Side::Remote) {
+                self.deleted_at = None;
+                self.deleted_by = None;
             }
         }
     }
 
+    /// Record a remote version together with its content hash and size.
+    ///
+    /// A version that [`planner::same_remote_version`] matches with the
+    /// last known one is not a change: a new etag is recorded and the status
+    /// kept. That covers a rewrite of identical content, by content hashes
+    /// of the same algorithm, and an etag the provider omitted for a file of
+    /// the recorded size. Hashes that cannot be compared, e.g. of different
+    /// algorithms, count as a change.
+    pub fn mark_remote_version(
+        &mut self,
+        etag: Option<String>,
+        content_hash: Option<String>,
+        size: Option<u64>,
+        modified: DateTime<Utc>,
+    ) {
+        if planner::same_remote_version(self, etag.as_deref(), content_hash.as_deref(), size) {
+            if etag.is_some() && self.remote_etag != etag {
+                self.touch();
+                self.remote_etag = etag;
+            }
+        } else {
+            self.mark_remote_modified(etag, modified);
+        }
+        self.remote_content_hash = content_hash;
+        self.remote_size = size;
+    }
+
+    /// Record that the entry changed now.
+    fn touch(&mut self) {
+        self.updated_at = Utc::now();
+    }
+
     /// Check if sync should be retried.
     pub fn should_retry(&self, max_retries: u32) -> bool {
         self.status == SyncStatus::Failed && self.failure_count < max_retries
@@ -149,6 +264,32 @@ pub struct SyncState {
     pub last_full_sync: Option<DateTime<Utc>>,
     /// Whether a sync is currently in progress.
     pub sync_in_progress: bool,
+    /// The periodic sync most recently held back by sync constraints;
+    /// cleared once a periodic sync runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<SkippedSync>,
+    /// When each path without an entry had its entry removed, so a merge
+    /// does not bring back an entry removed after it was last written.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    removed: HashMap<String, DateTime<Utc>>,
+    /// Generation of the shared copy this state was last merged with or
+    /// written as.
+    #[serde(default)]
+    pub generation: u64,
+}
+
+/// How long the removal of an entry is remembered for merging.
+const REMOVAL_RETENTION: chrono::TimeDelta = chrono::TimeDelta::days(30);
+
+/// A periodic sync skipped because its constraints were not met.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedSync {
+    /// Why it was skipped.
+    pub reason: SkipReason,
+    /// When it was skipped.
+    pub at: DateTime<Utc>,
+    /// Periodic syncs skipped in a row, including this one.
+    pub consecutive: u32,
 }
 
 impl SyncState {
@@ -158,6 +299,9 @@ impl SyncState {
             entries: HashMap::new(),
             last_full_sync: None,
             sync_in_progress: false,
+            skipped: None,
+            removed: HashMap::new(),
+            generation: 0,
         }
     }
 
@@ -173,12 +317,52 @@ impl SyncState {
 
     /// Insert or update a sync entry.
     pub fn insert(&mut self, entry: SyncEntry) {
+        self.removed.remove(&entry.path);
         self.entries.insert(entry.path.clone(), entry);
     }
 
     /// Remove a sync entry.
     pub fn remove(&mut self, path: &VaultPath) -> Option<SyncEntry> {
-        self.entries.remove(&path.to_string())
+        let key = path.to_string();
+        let entry = self.entries.remove(&key)?;
+        self.removed.insert(key, Utc::now());
+        Some(entry)
+    }
+
+    /// Merge the state another engine wrote.
+    ///
+    /// Per path, whichever side changed it last wins: an entry updated
+    /// later, or a removal made later than the other side's update. Ties
+    /// keep this side. `last_full_sync` becomes the later of the two; the
+    /// in-process flags stay this side's.
+    pub fn merge(&mut self, other: SyncState) {
+        for (path, entry) in other.entries {
+            let newer = match (self.entries.get(&path), self.removed.get(&path)) {
+                (Some(ours), _) => entry.updated_at > ours.updated_at,
+                (None, Some(removed_at)) => entry.updated_at > *removed_at,
+                (None, None) => true,
+            };
+            if newer {
+                self.removed.remove(&path);
+                self.entries.insert(path, entry);
+            }
+        }
+        for (path, removed_at) in other.removed {
+            let newer = match (self.entries.get(&path), self.removed.get(&path)) {
+                (Some(ours), _) => removed_at > ours.updated_at,
+                (None, Some(ours)) => removed_at > *ours,
+                (None, None) => true,
+            };
+            if newer {
+                self.entries.remove(&path);
+                self.removed.insert(path, removed_at);
+            }
+        }
+
+        let cutoff = Utc::now() - REMOVAL_RETENTION;
+        self.removed.retain(|_, removed_at| *removed_at > cutoff);
+        self.last_full_sync = self.last_full_sync.max(other.last_full_sync);
+        self.generation = self.generation.max(other.generation);
     }
 
     /// Get all entries.
@@ -205,7 +389,7 @@ impl SyncState {
     }
 
     /// Count entries by status.
-    pub fn count_by_status(&self) -> HashMap<SyncStatus, usize> {
+    pub fn count_by_status(&self) -> StatusSnapshot {
         let mut counts = HashMap::new();
         for entry in self.entries.values() {
             *counts.entry(entry.status).or_insert(0) += 1;
@@ -277,6 +461,32 @@ mod tests {
         assert_eq!(entry.status, SyncStatus::Conflicted);
     }
 
+    #[test]
+    fn test_delete_markers() {
+        let mut entry = SyncEntry::new_synced("/test.txt", Some("etag1".to_string()), Utc::now());
+        entry.mark_local_deleted();
+        assert_eq!(entry.status, SyncStatus::LocalModified);
+        assert_eq!(entry.deleted_by, Some(Side::Local));
+        assert!(entry.deleted_at.is_some());
+
+        // Re-creating the file undoes the local delete.
+        entry.mark_local_modified(Some("etag2".to_string()));
+        assert!(entry.deleted_at.is_none());
+        assert!(entry.deleted_by.is_none());
+
+        // A remote delete against the local edit is a conflict, and stays
+        // recorded through further local edits.
+        entry.mark_remote_deleted(Utc::now());
+        assert_eq!(entry.status, SyncStatus::Conflicted);
+        assert!(entry.remote_etag.is_none());
+        entry.mark_local_modified(Some("etag3".to_string()));
+        assert_eq!(entry.deleted_by, Some(Side::Remote));
+
+        entry.mark_synced(Some("etag4".to_string()), Utc::now());
+        assert!(entry.deleted_at.is_none());
+        assert!(entry.deleted_by.is_none());
+    }
+
     #[test]
     fn test_sync_state() {
         let mut state = SyncState::new();
@@ -295,6 +505,68 @@ mod tests {
         assert_eq!(*counts.get(&SyncStatus::Synced).unwrap_or(&0), 1);
     }
 
+    #[test]
+    fn test_merge_keeps_latest_change_per_path() {
+        let earlier = Utc::now() - chrono::TimeDelta::minutes(5);
+        let at = |mut entry: SyncEntry, time| {
+            entry.updated_at = time;
+            entry
+        };
+        let a = VaultPath::parse("/a.txt").unwrap();
+        let b = VaultPath::parse("/b.txt").unwrap();
+        let c = VaultPath::parse("/c.txt").unwrap();
+
+        let mut ours = SyncState::new();
+        ours.insert(at(
+            SyncEntry::new_local("/a.txt", Some("ours".into())),
+            earlier,
+        ));
+        ours.insert(SyncEntry::new_local("/b.txt", Some("ours".into())));
+        ours.insert(SyncEntry::new_local("/c.txt", None));
+        ours.remove(&c);
+        ours.generation = 3;
+
+        let mut theirs = SyncState::new();
+        theirs.insert(SyncEntry::new_synced(
+            "/a.txt",
+            Some("theirs".into()),
+            Utc::now(),
+        ));
+        theirs.insert(at(
+            SyncEntry::new_local("/b.txt", Some("theirs".into())),
+            earlier,
+        ));
+        theirs.insert(at(
+            SyncEntry::new_local("/c.txt", Some("theirs".into())),
+            earlier,
+        ));
+        theirs.insert(SyncEntry::new_local("/d.txt", None));
+        theirs.last_full_sync = Some(Utc::now());
+        theirs.generation = 5;
+        let theirs_clone = theirs.clone();
+
+        ours.merge(theirs);
+        assert_eq!(ours.get(&a).unwrap().local_etag.as_deref(), Some("theirs"));
+        assert_eq!(ours.get(&a).unwrap().status, SyncStatus::Synced);
+        assert_eq!(ours.get(&b).unwrap().local_etag.as_deref(), Some("ours"));
+        assert!(ours.get(&c).is_none(), "a later removal wins");
+        assert!(ours.get(&VaultPath::parse("/d.txt").unwrap()).is_some());
+        assert!(ours.last_full_sync.is_some());
+        assert_eq!(ours.generation, 5);
+
+        // Merging is idempotent.
+        let before: HashMap<_, _> = ours
+            .entries()
+            .map(|e| (e.path.clone(), e.updated_at))
+            .collect();
+        ours.merge(theirs_clone);
+        let after: HashMap<_, _> = ours
+            .entries()
+            .map(|e| (e.path.clone(), e.updated_at))
+            .collect();
+        assert_eq!(before, after);
+    }
+
     #[test]
     fn test_state_serialization() {
         let mut state = SyncState::new();
@@ -0,0 +1,258 @@
+//! Remote trash for files deleted by sync.
+//!
+//! With a [`TrashConfig`] set, deletes that sync carries out on the remote
+//! move the file into a trash directory instead of removing it, so a delete
+//! made on one device can be undone from another until the retention window
+//! runs out. Trashed files live side by side in one flat directory; each
+//! name records when the file was trashed and where it came from.
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, NaiveDateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_storage::{Metadata, StorageProvider};
+
+/// Default number of days trashed files are kept.
+const DEFAULT_RETENTION_DAYS: u64 = 30;
+
+/// Timestamp format at the start of a trashed file's name.
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.6fZ";
+
+/// Where sync moves deleted files, and for how long.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashConfig {
+    /// Remote directory holding trashed files.
+    pub dir: VaultPath,
+    /// How long a trashed file is kept before a full sync purges it.
+    pub retention: Duration,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self {
+            dir: VaultPath::from_components(vec![".axiom_trash".to_string()])
+                .expect("static trash path is valid"),
+            retention: Duration::from_secs(DEFAULT_RETENTION_DAYS * 24 * 60 * 60),
+        }
+    }
+}
+
+/// A file in the trash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashedFile {
+    /// Path the file was deleted from.
+    pub original_path: VaultPath,
+    /// When it was moved to the trash.
+    pub trashed_at: DateTime<Utc>,
+    /// Where it is stored now.
+    pub location: VaultPath,
+}
+
+impl TrashConfig {
+    /// Move the file at `path` into the trash.
+    ///
+    /// # Errors
+    /// - `NotFound` if there is no file at `path`
+    pub async fn trash<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &P,
+        path: &VaultPath,
+    ) -> Result<TrashedFile> {
+        self.ensure_dir(provider).await?;
+
+        // Truncated to what the name records, so listings match.
+        let trashed_at = Utc::now().trunc_subsecs(6);
+        let name = format!(
+            "{}.{}.{}",
+            trashed_at.format(TIMESTAMP_FORMAT),
+            &uuid::Uuid::new_v4().as_simple().to_string()[..8],
+            URL_SAFE_NO_PAD.encode(path.to_string())
+        );
+        let location = self.dir.join(&name)?;
+        provider.rename(path, &location).await?;
+        debug!(%location, "Moved deleted file to trash");
+
+        Ok(TrashedFile {
+            original_path: path.clone(),
+            trashed_at,
+            location,
+        })
+    }
+
+    /// Everything in the trash, oldest first.
+    ///
+    /// Names that were not written by [`trash`](Self::trash) are skipped.
+    pub async fn list<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &P,
+    ) -> Result<Vec<TrashedFile>> {
+        let listing = match provider.list(&self.dir).await {
+            Ok(listing) => listing,
+            Err(Error::NotFound(_)) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut files: Vec<TrashedFile> = listing
+            .iter()
+            .filter(|m| !m.is_directory)
+            .filter_map(|m| self.parse(&m.name))
+            .collect();
+        files.sort_by(|a, b| {
+            a.trashed_at
+                .cmp(&b.trashed_at)
+                .then_with(|| a.location.to_string().cmp(&b.location.to_string()))
+        });
+        Ok(files)
+    }
+
+    /// Move a trashed file back to where it was deleted from.
+    ///
+    /// # Errors
+    /// - `AlreadyExists` if a file has since been created there
+    /// - `NotFound` if the trashed file is gone
+    pub async fn restore<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &P,
+        file: &TrashedFile,
+    ) -> Result<Metadata> {
+        if provider.exists(&file.original_path).await? {
+            return Err(Error::AlreadyExists(format!(
+                "Cannot restore over existing file: {}",
+                file.original_path.display_lossy()
+            )));
+        }
+        provider.rename(&file.location, &file.original_path).await
+    }
+
+    /// Permanently delete trashed files older than the retention window.
+    ///
+    /// Returns how many were deleted.
+    pub async fn purge_expired<P: StorageProvider + ?Sized>(
+        &self,
+        provider: &P,
+        now: DateTime<Utc>,
+    ) -> Result<usize> {
+        let retention = chrono::Duration::from_std(self.retention)
+            .map_err(|e| Error::InvalidInput(format!("Invalid trash retention: {}", e)))?;
+
+        let mut purged = 0;
+        for file in self.list(provider).await? {
+            if now - file.trashed_at < retention {
+                break;
+            }
+            provider.delete(&file.location).await?;
+            purged += 1;
+        }
+        Ok(purged)
+    }
+
+    /// Create the trash directory and any missing parents.
+    async fn ensure_dir<P: StorageProvider + ?Sized>(&self, provider: &P) -> Result<()> {
+        for dir in self.dir.ancestors().skip(1).chain([self.dir.clone()]) {
+            if !provider.exists(&dir).await? {
+                provider.create_dir(&dir).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode a trashed file's name.
+    fn parse(&self, name: &str) -> Option<TrashedFile> {
+        // `<date>.<fraction>Z.<tag>.<path>`: the timestamp has a '.' of its
+        // own and base64url has none.
+        let mut parts = name.split('.');
+        let (date, fraction, _tag, encoded) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+
+        let timestamp = format!("{}.{}", date, fraction);
+        let trashed_at = NaiveDateTime::parse_from_str(&timestamp, TIMESTAMP_FORMAT)
+            .ok()?
+            .and_utc();
+        let original = URL_SAFE_NO_PAD.decode(encoded).ok()?;
+        let original_path = VaultPath::parse(std::str::from_utf8(&original).ok()?).ok()?;
+
+        Some(TrashedFile {
+            original_path,
+            trashed_at,
+            location: self.dir.join(name).ok()?,
+        })
+    }
+}
+
+/// Remove the file at `path` from the remote, through the trash if one is
+/// configured.
+///
+/// A file that is already gone counts as removed.
+pub async fn remove_remote<P: StorageProvider + ?Sized>(
+    provider: &P,
+    path: &VaultPath,
+    trash: Option<&TrashConfig>,
+) -> Result<()> {
+    let removed = match trash {
+        Some(trash) => trash.trash(provider, path).await.map(drop),
+        None => provider.delete(path).await,
+    };
+    match removed {
+        Err(Error::NotFound(_)) => Ok(()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axiomvault_storage::MemoryProvider;
+
+    #[tokio::test]
+    async fn test_trash_restore_and_purge() {
+        let provider = MemoryProvider::new();
+        let trash = TrashConfig::default();
+        let path = VaultPath::parse("/notes.txt").unwrap();
+        provider
+            .upload(&path, b"ciphertext".to_vec())
+            .await
+            .unwrap();
+
+        assert!(trash.list(&provider).await.unwrap().is_empty());
+        let trashed = trash.trash(&provider, &path).await.unwrap();
+        assert!(!provider.exists(&path).await.unwrap());
+        assert_eq!(
+            trash.list(&provider).await.unwrap(),
+            std::slice::from_ref(&trashed)
+        );
+        assert_eq!(
+            provider.download(&trashed.location).await.unwrap(),
+            b"ciphertext"
+        );
+
+        // Restoring refuses to overwrite a file created since.
+        provider.upload(&path, b"new".to_vec()).await.unwrap();
+        assert!(matches!(
+            trash.restore(&provider, &trashed).await,
+            Err(Error::AlreadyExists(_))
+        ));
+        provider.delete(&path).await.unwrap();
+        trash.restore(&provider, &trashed).await.unwrap();
+        assert_eq!(provider.download(&path).await.unwrap(), b"ciphertext");
+        assert!(trash.list(&provider).await.unwrap().is_empty());
+
+        // Only files past the retention window are purged.
+        let trashed = trash.trash(&provider, &path).await.unwrap();
+        let purge_at = trashed.trashed_at + chrono::Duration::days(1);
+        assert_eq!(trash.purge_expired(&provider, purge_at).await.unwrap(), 0);
+        let purge_at = trashed.trashed_at + chrono::Duration::days(30);
+        assert_eq!(trash.purge_expired(&provider, purge_at).await.unwrap(), 1);
+        assert!(trash.list(&provider).await.unwrap().is_empty());
+
+        // Removing a file that is already gone is not an error.
+        remove_remote(&provider, &path, Some(&trash)).await.unwrap();
+        remove_remote(&provider, &path, None).await.unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+//! Periodic saving of file access times.
+//!
+//! With [`VaultConfig::track_access_time`](crate::VaultConfig::track_access_time)
+//! on, reads stamp `accessed_at` in the in-memory tree only. Saving the tree
+//! after every read would turn each read into a write, so the times are
+//! batched until the next tree save, and this task saves them on a timer in
+//! between. Closing the session saves whatever is still pending.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::session::VaultSession;
+use axiomvault_common::{Error, Result};
+
+/// Handle to a running access-time flush task.
+pub struct AccessTimeFlushHandle {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl AccessTimeFlushHandle {
+    /// Ask the task to save pending access times once more and stop.
+    pub fn stop(&self) {
+        let _ = self.stop.send(true);
+    }
+
+    /// Whether the task has exited.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Wait for the task to exit.
+    pub async fn join(self) -> Result<()> {
+        self.task
+            .await
+            .map_err(|e| Error::Vault(format!("Access-time flush task failed: {}", e)))
+    }
+}
+
+pub(crate) fn spawn(session: Arc<VaultSession>, interval: Duration) -> AccessTimeFlushHandle {
+    let (stop, mut stopped) = watch::channel(false);
+    let task = tokio::spawn(async move {
+        loop {
+            let stopping = tokio::select! {
+                _ = tokio::time::sleep(interval) => false,
+                _ = stopped.changed() => true,
+            };
+            if !session.is_active() {
+                break;
+            }
+            // A failed save keeps the times pending for the next tick.
+            if let Err(e) = session.flush_access_times().await {
+                warn!("Failed to save access times: {}", e);
+            }
+            if stopping {
+                break;
+            }
+        }
+    });
+    AccessTimeFlushHandle { stop, task }
+}
@@ -0,0 +1,431 @@
+//! Subtrees stored on providers of their own.
+//!
+//! An [`AttachPoint`] maps a directory of the vault to a second provider,
+//! e.g. `/archive` to cheap cold storage. The tree stays on the vault's
+//! provider; the blobs of files at or below the attach point are written
+//! to, read from and deleted on the attached provider, under a data
+//! directory of its own in the vault's layout.
+//! [`VaultSession::blob_provider`] picks the provider for a path.
+//!
+//! Renames across an attach point's boundary cannot stay tree-only. The
+//! blobs are streamed to the other provider first, the tree is saved, and
+//! only then are the originals deleted, so an interruption leaves at worst
+//! an orphaned copy, never a file without its blob.
+
+use std::sync::Arc;
+
+use tracing::{debug, warn};
+
+use crate::config::{AttachPoint, DATA_DIRNAME};
+use crate::layout;
+use crate::parts::{self, BlobPart};
+use crate::session::{BlobInUse, VaultSession};
+use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_storage::StorageProvider;
+
+/// Files and plaintext bytes stored on one provider of a vault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageUsage {
+    /// Attach point served by the provider; `None` for the vault's own.
+    pub attach_point: Option<VaultPath>,
+    /// Storage provider type.
+    pub provider_type: String,
+    /// Files whose blobs are stored there.
+    pub files: u64,
+    /// Plaintext size of those files.
+    pub bytes: u64,
+}
+
+/// Usage of the vault's own provider followed by each attach point's.
+pub(crate) async fn usage(session: &VaultSession) -> Vec<StorageUsage> {
+    let config = session.config();
+    let mut usage: Vec<StorageUsage> = std::iter::once(StorageUsage {
+        attach_point: None,
+        provider_type: config.provider_type.clone(),
+        files: 0,
+        bytes: 0,
+    })
+    .chain(config.attachments.iter().map(|point| StorageUsage {
+        attach_point: Some(point.vault_path.clone()),
+        provider_type: point.provider_type.clone(),
+        files: 0,
+        bytes: 0,
+    }))
+    .collect();
+
+    let tree = session.tree().read().await;
+    for path in tree.files_under(&VaultPath::root()) {
+        let Ok(node) = tree.get_node(&path) else {
+            continue;
+        };
+        let index = config
+            .attachments
+            .iter()
+            .position(|point| path.starts_with(&point.vault_path))
+            .map_or(0, |index| index + 1);
+        usage[index].files += 1;
+        usage[index].bytes += node.metadata.size.unwrap_or(0);
+    }
+    usage
+}
+
+/// Create the data directory on a newly attached provider.
+pub(crate) async fn prepare(provider: &dyn StorageProvider) -> Result<()> {
+    let data_dir = VaultPath::parse(DATA_DIRNAME)?;
+    match provider.create_dir(&data_dir).await {
+        Ok(_) | Err(Error::AlreadyExists(_)) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Files at or below `path`.
+///
+/// # Errors
+/// - `path` not found
+pub(crate) async fn files_below(session: &VaultSession, path: &VaultPath) -> Result<usize> {
+    let tree = session.tree().read().await;
+    tree.get_node(path)?;
+    Ok(tree.files_under(path).len())
+}
+
+/// A blob that changes provider when its file is renamed.
+pub(crate) struct BlobMove<'a> {
+    encrypted_name: String,
+    parts: Vec<BlobPart>,
+    source: Arc<dyn StorageProvider>,
+    dest: Arc<dyn StorageProvider>,
+    _in_use: BlobInUse<'a>,
+}
+
+/// Blobs that have to change provider when `from` is renamed to `to`.
+///
+/// # Errors
+/// - Source not found
+/// - `InvalidInput` if `from` is or contains an attach point
+/// - `NotFound` if a provider involved is not connected
+pub(crate) async fn plan_moves<'a>(
+    session: &'a VaultSession,
+    from: &VaultPath,
+    to: &VaultPath,
+) -> Result<Vec<BlobMove<'a>>> {
+    let config = session.config();
+    if let Some(point) = config
+        .attachments
+        .iter()
+        .find(|point| point.vault_path.starts_with(from))
+    {
+        return Err(Error::InvalidInput(format!(
+            "{} holds the attach point {}; detach it first",
+            from, point.vault_path
+        )));
+    }
+
+    let attach_path = |path: &VaultPath| {
+        config
+            .attachment_for(path)
+            .map(|point: &AttachPoint| point.vault_path.clone())
+    };
+    let tree = session.tree().read().await;
+    tree.get_node(from)?;
+    let mut moves = Vec::new();
+    for path in tree.files_under(from) {
+        let Some(relative) = path.strip_prefix(from) else {
+            continue;
+        };
+        let dest_path = to.join_relative(&relative);
+        if attach_path(&path) == attach_path(&dest_path) {
+            continue;
+        }
+        let metadata = &tree.get_node(&path)?.metadata;
+        moves.push(BlobMove {
+            encrypted_name: metadata.encrypted_name.clone(),
+            parts: metadata.parts.clone(),
+            source: session.blob_provider(&path)?,
+            dest: session.blob_provider(&dest_path)?,
+            _in_use: session.blob_in_use(&metadata.encrypted_name),
+        });
+    }
+    Ok(moves)
+}
+
+/// Stream every planned blob to its new provider. On failure, the copies
+/// made so far are removed again.
+pub(crate) async fn copy_blobs(session: &VaultSession, moves: &[BlobMove<'_>]) -> Result<()> {
+    let data_layout = session.data_layout();
+    for (index, blob) in moves.iter().enumerate() {
+        for name in parts::object_names(&blob.encrypted_name, &blob.parts) {
+            if let Err(e) =
+                layout::copy_between(blob.source.as_ref(), blob.dest.as_ref(), data_layout, &name)
+                    .await
+            {
+                remove_copies(session, &moves[..=index]).await;
+                return Err(e);
+            }
+        }
+    }
+    debug!(blobs = moves.len(), "Blobs copied across attach points");
+    Ok(())
+}
+
+/// Delete the copies of a move that did not go through.
+pub(crate) async fn remove_copies(session: &VaultSession, moves: &[BlobMove<'_>]) {
+    for blob in moves {
+        remove(session, blob.dest.as_ref(), blob).await;
+    }
+}
+
+/// Delete the originals of a completed move.
+pub(crate) async fn remove_sources(session: &VaultSession, moves: &[BlobMove<'_>]) {
+    for blob in moves {
+        remove(session, blob.source.as_ref(), blob).await;
+    }
+}
+
+/// Failures only leave orphans, so they are logged.
+async fn remove(session: &VaultSession, provider: &dyn StorageProvider, blob: &BlobMove<'_>) {
+    for name in parts::object_names(&blob.encrypted_name, &blob.parts) {
+        match layout::delete(provider, session.data_layout(), &name).await {
+            Ok(()) | Err(Error::NotFound(_)) => {}
+            Err(e) => warn!(error = %e, "Failed to remove moved blob"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::VaultManager;
+    use crate::operations::VaultOperations;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::MasterKey;
+    use axiomvault_storage::{MemoryProvider, ProviderRegistry};
+
+    const KEY: [u8; 32] = [7u8; 32];
+
+    struct Fixture {
+        manager: VaultManager,
+        primary: Arc<dyn StorageProvider>,
+        cold: Arc<dyn StorageProvider>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let primary: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+            let cold: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+            let registry = ProviderRegistry::new();
+            for (name, provider) in [("primary", &primary), ("cold", &cold)] {
+                let provider = provider.clone();
+                registry
+                    .register(
+                        name,
+                        axiomvault_storage::registry::from_fn(move |_| Ok(provider.clone())),
+                    )
+                    .unwrap();
+            }
+            Self {
+                manager: VaultManager::with_registry(registry),
+                primary,
+                cold,
+            }
+        }
+
+        async fn create(&self) -> VaultSession {
+            self.manager
+                .create_vault_with_key(
+                    VaultId::new("attached").unwrap(),
+                    MasterKey::from_external(&KEY).unwrap(),
+                    "primary",
+                    serde_json::Value::Null,
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn reopen(&self) -> VaultSession {
+            self.manager
+                .open_vault_with_key(
+                    "primary",
+                    serde_json::Value::Null,
+                    MasterKey::from_external(&KEY).unwrap(),
+                )
+                .await
+                .unwrap()
+        }
+
+        async fn objects(provider: &Arc<dyn StorageProvider>) -> usize {
+            layout::list_objects(provider.as_ref()).await.unwrap().len()
+        }
+    }
+
+    fn path(path: &str) -> VaultPath {
+        VaultPath::parse(path).unwrap()
+    }
+
+    fn archive() -> AttachPoint {
+        AttachPoint {
+            vault_path: path("/archive"),
+            provider_type: "cold".to_string(),
+            provider_config: serde_json::Value::Null,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_attached_subtree_keeps_blobs_on_its_provider() {
+        let fixture = Fixture::new();
+        let mut session = fixture.create().await;
+        fixture
+            .manager
+            .attach(&mut session, archive())
+            .await
+            .unwrap();
+
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_directory(&path("/docs")).await.unwrap();
+        ops.create_file(&path("/docs/a.txt"), b"hot").await.unwrap();
+        ops.create_file(&path("/archive/b.txt"), b"cold data")
+            .await
+            .unwrap();
+        assert_eq!(Fixture::objects(&fixture.primary).await, 1);
+        assert_eq!(Fixture::objects(&fixture.cold).await, 1);
+
+        ops.update_file(&path("/archive/b.txt"), b"colder data")
+            .await
+            .unwrap();
+        assert_eq!(
+            ops.read_file(&path("/archive/b.txt")).await.unwrap(),
+            b"colder data"
+        );
+        let usage = ops.storage_usage().await;
+        assert_eq!(usage.len(), 2);
+        assert_eq!((usage[0].attach_point.as_ref(), usage[0].files), (None, 1));
+        assert_eq!(usage[1].attach_point, Some(path("/archive")));
+        assert_eq!((usage[1].files, usage[1].bytes), (1, 11));
+        assert_eq!(usage[1].provider_type, "cold");
+
+        // The attach point is sealed into the config and reconnected.
+        drop(session);
+        let session = fixture.reopen().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        assert_eq!(
+            ops.read_file(&path("/archive/b.txt")).await.unwrap(),
+            b"colder data"
+        );
+        ops.delete_file(&path("/archive/b.txt")).await.unwrap();
+        assert_eq!(Fixture::objects(&fixture.cold).await, 0);
+        assert_eq!(Fixture::objects(&fixture.primary).await, 1);
+
+        let report = ops.verify(&Default::default()).await.unwrap();
+        assert_eq!(report.failures().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rename_across_attach_point_moves_blobs() {
+        let fixture = Fixture::new();
+        let mut session = fixture.create().await;
+        fixture
+            .manager
+            .attach(&mut session, archive())
+            .await
+            .unwrap();
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_directory(&path("/2024")).await.unwrap();
+        ops.create_file(&path("/2024/a.txt"), b"a").await.unwrap();
+        ops.create_file(&path("/2024/b.txt"), b"b").await.unwrap();
+
+        ops.rename(&path("/2024"), &path("/archive/2024"))
+            .await
+            .unwrap();
+        assert_eq!(Fixture::objects(&fixture.primary).await, 0);
+        assert_eq!(Fixture::objects(&fixture.cold).await, 2);
+        assert_eq!(
+            ops.read_file(&path("/archive/2024/a.txt")).await.unwrap(),
+            b"a"
+        );
+
+        // Within the attach point, nothing moves.
+        ops.rename(&path("/archive/2024/b.txt"), &path("/archive/b.txt"))
+            .await
+            .unwrap();
+        assert_eq!(Fixture::objects(&fixture.cold).await, 2);
+
+        ops.rename(&path("/archive/b.txt"), &path("/b.txt"))
+            .await
+            .unwrap();
+        assert_eq!(Fixture::objects(&fixture.primary).await, 1);
+        assert_eq!(Fixture::objects(&fixture.cold).await, 1);
+        assert_eq!(ops.read_file(&path("/b.txt")).await.unwrap(), b"b");
+
+        // A refused rename copies nothing.
+        assert!(ops
+            .rename(&path("/b.txt"), &path("/archive/2024/a.txt"))
+            .await
+            .is_err());
+        assert_eq!(Fixture::objects(&fixture.cold).await, 1);
+        assert!(matches!(
+            ops.rename(&path("/archive"), &path("/old")).await,
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_detach_refuses_while_files_remain() {
+        let fixture = Fixture::new();
+        let mut session = fixture.create().await;
+        {
+            let ops = VaultOperations::new(&session).unwrap();
+            ops.create_directory(&path("/archive")).await.unwrap();
+            ops.create_file(&path("/archive/a.txt"), b"a")
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            fixture.manager.attach(&mut session, archive()).await,
+            Err(Error::Conflict(_))
+        ));
+        VaultOperations::new(&session)
+            .unwrap()
+            .rename(&path("/archive/a.txt"), &path("/a.txt"))
+            .await
+            .unwrap();
+        fixture
+            .manager
+            .attach(&mut session, archive())
+            .await
+            .unwrap();
+
+        VaultOperations::new(&session)
+            .unwrap()
+            .rename(&path("/a.txt"), &path("/archive/a.txt"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            fixture
+                .manager
+                .detach(&mut session, &path("/archive"))
+                .await,
+            Err(Error::Conflict(_))
+        ));
+        assert_eq!(session.config().attachments.len(), 1);
+
+        VaultOperations::new(&session)
+            .unwrap()
+            .rename(&path("/archive/a.txt"), &path("/a.txt"))
+            .await
+            .unwrap();
+        let point = fixture
+            .manager
+            .detach(&mut session, &path("/archive"))
+            .await
+            .unwrap();
+        assert_eq!(point, archive());
+        assert!(fixture.reopen().await.config().attachments.is_empty());
+        assert_eq!(Fixture::objects(&fixture.cold).await, 0);
+        assert!(matches!(
+            fixture
+                .manager
+                .detach(&mut session, &path("/archive"))
+                .await,
+            Err(Error::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,791 @@
+//! On-disk formats for encrypted file blobs.
+//!
+//! Every file node records the format its blob was written in, so readers
+//! can decrypt any mix of old and new blobs and the maintenance task can
+//! find blobs that still need upgrading.
+//!
+//! Blobs from [`BlobFormat::V3`] on start with a [`BlobHeader`]: the magic
+//! `AXV1` and a flags byte saying whether the body is chunked, compressed
+//! or padded and which cipher sealed it. A blob that is truncated or not a
+//! vault blob at all is refused before any decryption is tried. Earlier
+//! formats are header-less and read as they always were.
+//!
+//! New blobs are sealed with the vault's [`CipherSuite`], whose ID is both
+//! in the header and in front of the body. Blobs framed before cipher
+//! suites carry cipher ID 0 and a bare XChaCha20-Poly1305 body, so a vault
+//! can hold any mix of the two.
+
+use serde::{Deserialize, Serialize};
+
+use axiomvault_common::{Error, Result, VaultId};
+use axiomvault_crypto::stream::is_stream_format;
+use axiomvault_crypto::{
+    decrypt, decrypt_with_aad, decrypt_with_suite, seal, seal_with_aad, seal_with_suite,
+    CipherSuite, Ciphertext, DecryptingReader, DecryptingStream, KeyContext, MasterKey,
+};
+use zeroize::Zeroize;
+
+/// Domain separation prefix for the v2 blob associated data.
+const BLOB_AAD_CONTEXT: &[u8] = b"axiomvault-blob-v2:";
+
+/// Domain separation prefix for the v3 blob associated data.
+const BLOB_V3_AAD_CONTEXT: &[u8] = b"axiomvault-blob-v3:";
+
+/// Magic opening every framed blob; the digit is the framing version.
+pub const BLOB_MAGIC: [u8; 4] = *b"AXV1";
+
+/// Length of a [`BlobHeader`]: the magic plus the flags byte.
+pub const BLOB_HEADER_LEN: usize = BLOB_MAGIC.len() + 1;
+
+/// Flag: the body is in the chunked stream format.
+const FLAG_STREAMED: u8 = 0x01;
+/// Flag: the plaintext was compressed before encryption.
+const FLAG_COMPRESSED: u8 = 0x02;
+/// Flag: the plaintext was padded before encryption.
+const FLAG_PADDED: u8 = 0x04;
+/// Flag bits no format defines yet.
+const FLAG_RESERVED: u8 = 0x08;
+/// The cipher ID lives in the high nibble.
+const CIPHER_SHIFT: u8 = 4;
+
+/// Encrypted file blob format.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(into = "u32", try_from = "u32")]
+pub enum BlobFormat {
+    /// Whole-file XChaCha20-Poly1305 under the per-file key, no associated
+    /// data. Nodes written before formats were tracked use this.
+    #[default]
+    V1,
+    /// Like `V1`, but the blob's storage name is bound in as associated
+    /// data, so a blob swapped in under another name fails to decrypt.
+    V2,
+    /// `V2` behind a [`BlobHeader`], which is bound in as associated data
+    /// along with the storage name.
+    V3,
+}
+
+impl BlobFormat {
+    /// Format written for new and upgraded blobs.
+    pub const LATEST: Self = Self::V3;
+
+    /// Numeric format identifier as stored in the tree.
+    pub fn as_u32(self) -> u32 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+            Self::V3 => 3,
+        }
+    }
+}
+
+impl From<BlobFormat> for u32 {
+    fn from(format: BlobFormat) -> Self {
+        format.as_u32()
+    }
+}
+
+impl TryFrom<u32> for BlobFormat {
+    type Error = Error;
+
+    fn try_from(value: u32) -> Result<Self> {
+        match value {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            3 => Ok(Self::V3),
+            other => Err(Error::Vault(format!("Unknown blob format: {}", other))),
+        }
+    }
+}
+
+impl std::fmt::Display for BlobFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.as_u32())
+    }
+}
+
+impl std::str::FromStr for BlobFormat {
+    type Err = Error;
+
+    /// Parse `latest`, `v2` or `2`.
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("latest") {
+            return Ok(Self::LATEST);
+        }
+        let digits = s.strip_prefix(['v', 'V']).unwrap_or(s);
+        let value = digits
+            .parse::<u32>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid blob format: {}", s)))?;
+        Self::try_from(value)
+            .map_err(|_| Error::InvalidInput(format!("Unknown blob format: {}", s)))
+    }
+}
+
+/// Cipher sealing a framed blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlobCipher {
+    /// XChaCha20-Poly1305 with no suite ID in the body, as blobs were
+    /// sealed before cipher suites.
+    #[default]
+    XChaCha20Poly1305,
+    /// A [`CipherSuite`]; the body opens with the same suite ID.
+    Suite(CipherSuite),
+}
+
+impl BlobCipher {
+    /// Cipher ID as stored in the header.
+    fn id(self) -> u8 {
+        match self {
+            Self::XChaCha20Poly1305 => 0,
+            Self::Suite(suite) => suite.id(),
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::XChaCha20Poly1305),
+            id => CipherSuite::from_id(id).ok().map(Self::Suite),
+        }
+    }
+}
+
+/// Self-describing header at the start of a framed blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlobHeader {
+    /// The body is in the chunked stream format rather than a single
+    /// sealed blob.
+    pub streamed: bool,
+    /// The plaintext was compressed before encryption.
+    pub compressed: bool,
+    /// The plaintext was padded before encryption.
+    pub padded: bool,
+    /// Cipher the body is sealed with.
+    pub cipher: BlobCipher,
+}
+
+impl BlobHeader {
+    /// Header of a blob in the chunked stream format.
+    pub fn streamed() -> Self {
+        Self {
+            streamed: true,
+            ..Self::default()
+        }
+    }
+
+    /// Header of a whole blob sealed with `suite`.
+    pub fn sealed_with(suite: CipherSuite) -> Self {
+        Self {
+            cipher: BlobCipher::Suite(suite),
+            ..Self::default()
+        }
+    }
+
+    /// Encode the header.
+    pub fn to_bytes(self) -> [u8; BLOB_HEADER_LEN] {
+        let mut flags = self.cipher.id() << CIPHER_SHIFT;
+        if self.streamed {
+            flags |= FLAG_STREAMED;
+        }
+        if self.compressed {
+            flags |= FLAG_COMPRESSED;
+        }
+        if self.padded {
+            flags |= FLAG_PADDED;
+        }
+        let mut bytes = [0u8; BLOB_HEADER_LEN];
+        bytes[..BLOB_MAGIC.len()].copy_from_slice(&BLOB_MAGIC);
+        bytes[BLOB_MAGIC.len()] = flags;
+        bytes
+    }
+
+    /// Split a framed blob into its header and body.
+    ///
+    /// # Errors
+    /// - `Integrity` if the blob is too short for a header or does not
+    ///   start with [`BLOB_MAGIC`]
+    /// - `Unsupported` for a reserved flag or an unknown cipher
+    pub fn parse(blob: &[u8]) -> Result<(Self, &[u8])> {
+        let Some((header, body)) = blob.split_first_chunk::<BLOB_HEADER_LEN>() else {
+            return Err(Error::Integrity(format!(
+                "Blob is truncated: {} bytes is shorter than its header",
+                blob.len()
+            )));
+        };
+        if header[..BLOB_MAGIC.len()] != BLOB_MAGIC {
+            return Err(Error::Integrity(
+                "Blob does not start with the AXV1 magic".to_string(),
+            ));
+        }
+        let flags = header[BLOB_MAGIC.len()];
+        if flags & FLAG_RESERVED != 0 {
+            return Err(Error::Unsupported(format!(
+                "blob flags {:#04x} set a reserved bit",
+                flags
+            )));
+        }
+        let cipher = BlobCipher::from_id(flags >> CIPHER_SHIFT)
+            .ok_or_else(|| Error::Unsupported(format!("blob cipher {}", flags >> CIPHER_SHIFT)))?;
+        let header = Self {
+            streamed: flags & FLAG_STREAMED != 0,
+            compressed: flags & FLAG_COMPRESSED != 0,
+            padded: flags & FLAG_PADDED != 0,
+            cipher,
+        };
+        Ok((header, body))
+    }
+
+    /// Fail for encodings this build cannot undo.
+    fn ensure_readable(self) -> Result<()> {
+        if self.compressed {
+            return Err(Error::Unsupported("compressed blobs".to_string()));
+        }
+        if self.padded {
+            return Err(Error::Unsupported("padded blobs".to_string()));
+        }
+        if self.streamed && self.cipher != BlobCipher::XChaCha20Poly1305 {
+            return Err(Error::Unsupported(
+                "streamed blobs under a cipher suite".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// How much of a vault is stored in a given blob format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlobFormatStats {
+    /// Number of files in the vault.
+    pub total_files: usize,
+    /// Plaintext bytes across all files.
+    pub total_bytes: u64,
+    /// Files already at the target format or newer.
+    pub upgraded_files: usize,
+    /// Plaintext bytes in upgraded files.
+    pub upgraded_bytes: u64,
+}
+
+impl BlobFormatStats {
+    /// Share of files at the target format, in percent. An empty vault is
+    /// fully upgraded.
+    pub fn percent_upgraded(&self) -> f64 {
+        if self.total_files == 0 {
+            100.0
+        } else {
+            self.upgraded_files as f64 * 100.0 / self.total_files as f64
+        }
+    }
+
+    /// Files still below the target format.
+    pub fn remaining_files(&self) -> usize {
+        self.total_files - self.upgraded_files
+    }
+}
+
+/// A file blob encrypted by the vault and handed to the sync staging area.
+///
+/// Only the vault produces the ciphertext, so staging never holds
+/// plaintext. The blob names the vault and node it belongs to, so a staging
+/// area bound to another vault can refuse it.
+#[derive(Debug, Clone)]
+pub struct StagedBlob {
+    ciphertext: Ciphertext,
+    vault_id: VaultId,
+    node_id: String,
+    format_version: BlobFormat,
+}
+
+impl StagedBlob {
+    /// Describe `ciphertext` as the blob of `node_id` in `vault_id`.
+    pub fn new(
+        ciphertext: Ciphertext,
+        vault_id: VaultId,
+        node_id: impl Into<String>,
+        format_version: BlobFormat,
+    ) -> Self {
+        Self {
+            ciphertext,
+            vault_id,
+            node_id: node_id.into(),
+            format_version,
+        }
+    }
+
+    /// The encrypted blob.
+    pub fn ciphertext(&self) -> &Ciphertext {
+        &self.ciphertext
+    }
+
+    /// Vault the blob belongs to.
+    pub fn vault_id(&self) -> &VaultId {
+        &self.vault_id
+    }
+
+    /// Storage name of the file node the blob belongs to.
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// Format the blob is encrypted in.
+    pub fn format_version(&self) -> BlobFormat {
+        self.format_version
+    }
+
+    /// Unwrap the encrypted blob.
+    pub fn into_ciphertext(self) -> Ciphertext {
+        self.ciphertext
+    }
+}
+
+/// Encrypt file content for storage under `encrypted_name`.
+///
+/// `suite` applies from [`BlobFormat::V3`] on; earlier formats are always
+/// XChaCha20-Poly1305.
+pub(crate) fn encrypt_blob(
+    format: BlobFormat,
+    suite: CipherSuite,
+    master_key: &MasterKey,
+    encrypted_name: &str,
+    content: &[u8],
+) -> Result<Vec<u8>> {
+    seal_blob(format, suite, master_key, encrypted_name, content).map(Ciphertext::into_bytes)
+}
+
+/// Like [`encrypt_blob`], but keeps the output marked as [`Ciphertext`].
+pub(crate) fn seal_blob(
+    format: BlobFormat,
+    suite: CipherSuite,
+    master_key: &MasterKey,
+    encrypted_name: &str,
+    content: &[u8],
+) -> Result<Ciphertext> {
+    let file_key = master_key.derive_legacy_file_key(&KeyContext::FileContent {
+        name_or_id: encrypted_name.as_bytes(),
+    })?;
+    match format {
+        BlobFormat::V1 => seal(file_key.as_bytes(), content),
+        BlobFormat::V2 => seal_with_aad(file_key.as_bytes(), content, &blob_aad(encrypted_name)),
+        BlobFormat::V3 => {
+            let header = BlobHeader::sealed_with(suite);
+            let aad = blob_v3_aad(&header, encrypted_name);
+            seal_with_suite(suite, file_key.as_bytes(), content, &aad)
+                .map(|body| body.with_prefix(&header.to_bytes()))
+        }
+    }
+}
+
+/// Decrypt a blob stored under `encrypted_name` in the given format.
+pub(crate) fn decrypt_blob(
+    format: BlobFormat,
+    master_key: &MasterKey,
+    encrypted_name: &str,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>> {
+    let file_key = master_key.derive_legacy_file_key(&KeyContext::FileContent {
+        name_or_id: encrypted_name.as_bytes(),
+    })?;
+    match format {
+        BlobFormat::V1 => decrypt(file_key.as_bytes(), ciphertext),
+        BlobFormat::V2 => {
+            decrypt_with_aad(file_key.as_bytes(), ciphertext, &blob_aad(encrypted_name))
+        }
+        BlobFormat::V3 => {
+            let (header, body) = BlobHeader::parse(ciphertext)?;
+            header.ensure_readable()?;
+            let aad = blob_v3_aad(&header, encrypted_name);
+            if header.streamed {
+                let mut plaintext = Vec::new();
+                DecryptingStream::new(file_key.as_bytes())?
+                    .with_aad(&aad)
+                    .decrypt_stream(body, &mut plaintext)?;
+                return Ok(plaintext);
+            }
+            match header.cipher {
+                BlobCipher::XChaCha20Poly1305 => decrypt_with_aad(file_key.as_bytes(), body, &aad),
+                BlobCipher::Suite(suite) => {
+                    if body.first() != Some(&suite.id()) {
+                        return Err(Error::Integrity(format!(
+                            "Blob header names {} but its body does not",
+                            suite
+                        )));
+                    }
+                    decrypt_with_suite(file_key.as_bytes(), body, &aad)
+                }
+            }
+        }
+    }
+}
+
+/// Decrypt a blob straight into `writer`, returning the plaintext length.
+///
+/// Blobs in the chunked stream format are decrypted one chunk at a time, so
+/// at most one chunk of plaintext is held in memory. Single blobs are
+/// decrypted as a whole in the given format and written once. A framed
+/// blob says which it is in its header; a header-less one is recognized by
+/// its layout.
+///
+/// Every chunk of a framed stream authenticates the same associated data
+/// as a single V3 blob, so its header and storage name are bound to it.
+pub(crate) fn decrypt_blob_to_writer<W: std::io::Write>(
+    format: BlobFormat,
+    master_key: &MasterKey,
+    encrypted_name: &str,
+    ciphertext: &[u8],
+    writer: &mut W,
+) -> Result<u64> {
+    let stream = match format {
+        BlobFormat::V1 | BlobFormat::V2 => {
+            is_stream_format(ciphertext).then(|| (ciphertext, Vec::new()))
+        }
+        BlobFormat::V3 => {
+            let (header, body) = BlobHeader::parse(ciphertext)?;
+            header.ensure_readable()?;
+            header
+                .streamed
+                .then(|| (body, blob_v3_aad(&header, encrypted_name)))
+        }
+    };
+    if let Some((ciphertext, aad)) = stream {
+        let file_key = master_key.derive_legacy_file_key(&KeyContext::FileContent {
+            name_or_id: encrypted_name.as_bytes(),
+        })?;
+        let mut reader = DecryptingReader::new(file_key.as_bytes(), ciphertext)?.with_aad(&aad);
+        // The reader reports failed chunks as `InvalidData`.
+        return std::io::copy(&mut reader, writer).map_err(|e| match e.kind() {
+            std::io::ErrorKind::InvalidData => {
+                Error::Crypto(format!("Failed to decrypt file: {}", e))
+            }
+            _ => Error::Io(e),
+        });
+    }
+
+    let mut content = decrypt_blob(format, master_key, encrypted_name, ciphertext)?;
+    let result = writer.write_all(&content).map_err(Error::Io);
+    let len = content.len() as u64;
+    content.zeroize();
+    result.map(|()| len)
+}
+
+fn blob_aad(encrypted_name: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(BLOB_AAD_CONTEXT.len() + encrypted_name.len());
+    aad.extend_from_slice(BLOB_AAD_CONTEXT);
+    aad.extend_from_slice(encrypted_name.as_bytes());
+    aad
+}
+
+/// Associated data of a V3 blob: its header and storage name. Single blobs
+/// authenticate it once, streamed ones with every chunk.
+fn blob_v3_aad(header: &BlobHeader, encrypted_name: &str) -> Vec<u8> {
+    let mut aad =
+        Vec::with_capacity(BLOB_V3_AAD_CONTEXT.len() + BLOB_HEADER_LEN + encrypted_name.len());
+    aad.extend_from_slice(BLOB_V3_AAD_CONTEXT);
+    aad.extend_from_slice(&header.to_bytes());
+    aad.extend_from_slice(encrypted_name.as_bytes());
+    aad
+}
+
+/// `content` as a V3 blob in the chunked stream format.
+#[cfg(test)]
+pub(crate) fn encrypt_streamed_blob(
+    master_key: &MasterKey,
+    encrypted_name: &str,
+    content: &[u8],
+) -> Vec<u8> {
+    let header = BlobHeader::streamed();
+    let file_key = master_key
+        .derive_legacy_file_key(&KeyContext::FileContent {
+            name_or_id: encrypted_name.as_bytes(),
+        })
+        .unwrap();
+    let aad = blob_v3_aad(&header, encrypted_name);
+    let mut blob = header.to_bytes().to_vec();
+    axiomvault_crypto::EncryptingStream::new(file_key.as_bytes())
+        .unwrap()
+        .with_aad(&aad)
+        .encrypt_stream(content, &mut blob)
+        .unwrap();
+    blob
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_each_format() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        for format in [BlobFormat::V1, BlobFormat::V2, BlobFormat::V3] {
+            let blob =
+                encrypt_blob(format, CipherSuite::default(), &key, "name", b"content").unwrap();
+            assert_eq!(
+                decrypt_blob(format, &key, "name", &blob).unwrap(),
+                b"content"
+            );
+        }
+    }
+
+    #[test]
+    fn test_v2_binds_storage_name() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        let blob = encrypt_blob(
+            BlobFormat::V2,
+            CipherSuite::default(),
+            &key,
+            "name",
+            b"content",
+        )
+        .unwrap();
+
+        // Same file key, different storage name: must not decrypt.
+        let file_key = key
+            .derive_legacy_file_key(&KeyContext::FileContent {
+                name_or_id: b"name",
+            })
+            .unwrap();
+        assert!(decrypt(file_key.as_bytes(), &blob).is_err());
+        assert!(decrypt_blob(BlobFormat::V1, &key, "name", &blob).is_err());
+    }
+
+    #[test]
+    fn test_header_roundtrip() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        let blob = encrypt_blob(
+            BlobFormat::V3,
+            CipherSuite::default(),
+            &key,
+            "name",
+            b"content",
+        )
+        .unwrap();
+        let (header, body) = BlobHeader::parse(&blob).unwrap();
+        assert_eq!(header, BlobHeader::sealed_with(CipherSuite::default()));
+        assert_eq!(body.len(), blob.len() - BLOB_HEADER_LEN);
+        assert!(blob.starts_with(b"AXV1"));
+
+        for header in [
+            BlobHeader::streamed(),
+            BlobHeader {
+                compressed: true,
+                padded: true,
+                ..BlobHeader::default()
+            },
+        ] {
+            let bytes = header.to_bytes();
+            assert_eq!(BlobHeader::parse(&bytes).unwrap(), (header, &[][..]));
+        }
+
+        // The header is authenticated: flipping a flag breaks decryption.
+        let mut tampered = blob.clone();
+        tampered[BLOB_MAGIC.len()] |= FLAG_STREAMED;
+        assert!(decrypt_blob(BlobFormat::V3, &key, "name", &tampered).is_err());
+    }
+
+    #[test]
+    fn test_wrong_magic_is_integrity_error() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        let mut blob = encrypt_blob(
+            BlobFormat::V3,
+            CipherSuite::default(),
+            &key,
+            "name",
+            b"content",
+        )
+        .unwrap();
+        blob[..4].copy_from_slice(b"AXV9");
+        assert!(matches!(
+            decrypt_blob(BlobFormat::V3, &key, "name", &blob),
+            Err(Error::Integrity(_))
+        ));
+
+        // A header-less blob is not mistaken for a framed one.
+        let legacy = encrypt_blob(
+            BlobFormat::V2,
+            CipherSuite::default(),
+            &key,
+            "name",
+            b"content",
+        )
+        .unwrap();
+        assert!(matches!(
+            decrypt_blob(BlobFormat::V3, &key, "name", &legacy),
+            Err(Error::Integrity(_))
+        ));
+        assert!(matches!(
+            decrypt_blob_to_writer(BlobFormat::V3, &key, "name", b"AXV", &mut Vec::new()),
+            Err(Error::Integrity(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_flags_are_refused() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        let blob = encrypt_blob(
+            BlobFormat::V3,
+            CipherSuite::default(),
+            &key,
+            "name",
+            b"content",
+        )
+        .unwrap();
+        for flags in [
+            FLAG_RESERVED,
+            FLAG_COMPRESSED,
+            FLAG_PADDED,
+            0x0f << CIPHER_SHIFT,
+            FLAG_STREAMED | CipherSuite::default().id() << CIPHER_SHIFT,
+        ] {
+            let mut unsupported = blob.clone();
+            unsupported[BLOB_MAGIC.len()] = flags;
+            assert!(
+                matches!(
+                    decrypt_blob(BlobFormat::V3, &key, "name", &unsupported),
+                    Err(Error::Unsupported(_))
+                ),
+                "flags {:#04x}",
+                flags
+            );
+            assert!(matches!(
+                decrypt_blob_to_writer(BlobFormat::V3, &key, "name", &unsupported, &mut Vec::new()),
+                Err(Error::Unsupported(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_blob_framed_before_cipher_suites_still_decrypts() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        let file_key = key
+            .derive_legacy_file_key(&KeyContext::FileContent {
+                name_or_id: b"name",
+            })
+            .unwrap();
+        let header = BlobHeader::default();
+        let aad = blob_v3_aad(&header, "name");
+        let blob = seal_with_aad(file_key.as_bytes(), b"content", &aad)
+            .unwrap()
+            .with_prefix(&header.to_bytes())
+            .into_bytes();
+
+        assert_eq!(
+            BlobHeader::parse(&blob).unwrap().0.cipher,
+            BlobCipher::XChaCha20Poly1305
+        );
+        assert_eq!(
+            decrypt_blob(BlobFormat::V3, &key, "name", &blob).unwrap(),
+            b"content"
+        );
+    }
+
+    #[test]
+    fn test_suite_id_must_match_header() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        let blob = encrypt_blob(
+            BlobFormat::V3,
+            CipherSuite::default(),
+            &key,
+            "name",
+            b"content",
+        )
+        .unwrap();
+        assert_eq!(blob[BLOB_HEADER_LEN], CipherSuite::default().id());
+
+        // Relabelling the body, or the header back to bare XChaCha, fails.
+        let mut body_relabelled = blob.clone();
+        body_relabelled[BLOB_HEADER_LEN] = CipherSuite::Aes256GcmSiv.id();
+        assert!(matches!(
+            decrypt_blob(BlobFormat::V3, &key, "name", &body_relabelled),
+            Err(Error::Integrity(_))
+        ));
+        let mut header_relabelled = blob;
+        header_relabelled[BLOB_MAGIC.len()] = 0;
+        assert!(decrypt_blob(BlobFormat::V3, &key, "name", &header_relabelled).is_err());
+    }
+
+    #[cfg(feature = "aes-gcm-siv")]
+    #[test]
+    fn test_mixed_suites_decrypt() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        for suite in CipherSuite::ALL {
+            let blob = encrypt_blob(BlobFormat::V3, suite, &key, "name", b"content").unwrap();
+            let (header, _) = BlobHeader::parse(&blob).unwrap();
+            assert_eq!(header.cipher, BlobCipher::Suite(suite));
+            assert_eq!(
+                decrypt_blob(BlobFormat::V3, &key, "name", &blob).unwrap(),
+                b"content"
+            );
+
+            // The suite in the header is authenticated too.
+            let other = CipherSuite::ALL.into_iter().find(|s| *s != suite).unwrap();
+            let mut relabelled = blob.clone();
+            relabelled[BLOB_MAGIC.len()] = other.id() << CIPHER_SHIFT;
+            relabelled[BLOB_HEADER_LEN] = other.id();
+            assert!(decrypt_blob(BlobFormat::V3, &key, "name", &relabelled).is_err());
+        }
+    }
+
+    #[test]
+    fn test_streamed_header_selects_chunked_body() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        let blob = encrypt_streamed_blob(&key, "name", b"chunked");
+
+        assert_eq!(
+            decrypt_blob(BlobFormat::V3, &key, "name", &blob).unwrap(),
+            b"chunked"
+        );
+        let mut out = Vec::new();
+        decrypt_blob_to_writer(BlobFormat::V3, &key, "name", &blob, &mut out).unwrap();
+        assert_eq!(out, b"chunked");
+
+        // Header-less legacy streams are still recognized by layout.
+        let file_key = key
+            .derive_legacy_file_key(&KeyContext::FileContent {
+                name_or_id: b"name",
+            })
+            .unwrap();
+        let body =
+            axiomvault_crypto::stream::encrypt_bytes(file_key.as_bytes(), b"chunked").unwrap();
+        let mut out = Vec::new();
+        decrypt_blob_to_writer(BlobFormat::V2, &key, "name", &body, &mut out).unwrap();
+        assert_eq!(out, b"chunked");
+    }
+
+    #[test]
+    fn test_streamed_blob_binds_header_and_name() {
+        let key = MasterKey::from_bytes([3u8; 32]);
+        let blob = encrypt_streamed_blob(&key, "name", b"chunked");
+        let refused = |name: &str, blob: &[u8]| {
+            decrypt_blob(BlobFormat::V3, &key, name, blob).is_err()
+                && decrypt_blob_to_writer(BlobFormat::V3, &key, name, blob, &mut Vec::new())
+                    .is_err()
+        };
+
+        // The chunks only open with the header and name as associated data.
+        let file_key = key
+            .derive_legacy_file_key(&KeyContext::FileContent {
+                name_or_id: b"name",
+            })
+            .unwrap();
+        let body = &blob[BLOB_HEADER_LEN..];
+        assert!(axiomvault_crypto::stream::decrypt_bytes(file_key.as_bytes(), body).is_err());
+
+        // A stream sealed without them is refused behind a streamed header.
+        let mut unbound = BlobHeader::streamed().to_bytes().to_vec();
+        unbound.extend(
+            axiomvault_crypto::stream::encrypt_bytes(file_key.as_bytes(), b"chunked").unwrap(),
+        );
+        assert!(refused("name", &unbound));
+        assert!(refused("other", &blob));
+    }
+
+    #[test]
+    fn test_parse_and_serde() {
+        assert_eq!("latest".parse::<BlobFormat>().unwrap(), BlobFormat::LATEST);
+        assert_eq!("v1".parse::<BlobFormat>().unwrap(), BlobFormat::V1);
+        assert_eq!("2".parse::<BlobFormat>().unwrap(), BlobFormat::V2);
+        assert!("v9".parse::<BlobFormat>().is_err());
+
+        assert_eq!("v3".parse::<BlobFormat>().unwrap(), BlobFormat::V3);
+        assert_eq!(serde_json::to_string(&BlobFormat::V2).unwrap(), "2");
+        assert!(serde_json::from_str::<BlobFormat>("7").is_err());
+    }
+}
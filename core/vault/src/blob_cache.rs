@@ -0,0 +1,192 @@
+//! On-device cache of encrypted blobs for offline reads.
+//!
+//! A [`BlobCache`] keeps the ciphertext of files read through
+//! [`VaultOperations::read_file`](crate::VaultOperations::read_file) in a
+//! local directory. When the provider is unreachable the read is served
+//! from the cache, but only if the cached copy was stored under the etag
+//! and blob format the tree currently records for the file, so a stale
+//! copy is never returned. Blobs stay encrypted on disk.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use serde::{Deserialize, Serialize};
+
+use crate::blob::BlobFormat;
+use axiomvault_common::{Error, Result};
+
+/// Which version of a blob a cache entry holds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CacheEntry {
+    etag: String,
+    blob_format: BlobFormat,
+}
+
+/// Directory of cached ciphertext blobs, keyed by encrypted name.
+#[derive(Debug, Clone)]
+pub struct BlobCache {
+    dir: PathBuf,
+}
+
+impl BlobCache {
+    /// Open a cache in `dir`, creating the directory if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Directory holding the cached blobs.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cached blob for `encrypted_name`, if it was stored under `etag`
+    /// and `blob_format`.
+    pub fn get(
+        &self,
+        encrypted_name: &str,
+        etag: &str,
+        blob_format: BlobFormat,
+    ) -> Result<Option<Vec<u8>>> {
+        let (blob_path, entry_path) = self.paths(encrypted_name);
+        let entry: CacheEntry = match fs::read(&entry_path) {
+            Ok(bytes) => {
+                serde_json::from_slice(&bytes).map_err(|e| Error::Serialization(e.to_string()))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if entry.etag != etag || entry.blob_format != blob_format {
+            return Ok(None);
+        }
+        match fs::read(&blob_path) {
+            Ok(blob) => Ok(Some(blob)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Store `blob` as the version of `encrypted_name` with `etag` and
+    /// `blob_format`, replacing any earlier version.
+    pub fn put(
+        &self,
+        encrypted_name: &str,
+        etag: &str,
+        blob_format: BlobFormat,
+        blob: &[u8],
+    ) -> Result<()> {
+        let (blob_path, entry_path) = self.paths(encrypted_name);
+        // Drop the entry first so a crash between the writes leaves a blob
+        // that is never served rather than one served under the wrong etag.
+        remove_if_exists(&entry_path)?;
+        write_atomic(&blob_path, blob)?;
+        let entry = CacheEntry {
+            etag: etag.to_string(),
+            blob_format,
+        };
+        let bytes = serde_json::to_vec(&entry).map_err(|e| Error::Serialization(e.to_string()))?;
+        write_atomic(&entry_path, &bytes)
+    }
+
+    /// Forget the cached blob for `encrypted_name`.
+    pub fn remove(&self, encrypted_name: &str) -> Result<()> {
+        let (blob_path, entry_path) = self.paths(encrypted_name);
+        remove_if_exists(&entry_path)?;
+        remove_if_exists(&blob_path)
+    }
+
+    /// Remove every cached blob.
+    pub fn clear(&self) -> Result<()> {
+        fs::remove_dir_all(&self.dir)?;
+        fs::create_dir_all(&self.dir)?;
+        Ok(())
+    }
+
+    /// Blob and entry file of `encrypted_name`. Encrypted names can exceed
+    /// file name limits, so files are named by a hash of it.
+    fn paths(&self, encrypted_name: &str) -> (PathBuf, PathBuf) {
+        let digest = Blake2b::<U32>::digest(encrypted_name.as_bytes());
+        let key: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        (
+            self.dir.join(format!("{}.blob", key)),
+            self.dir.join(format!("{}.json", key)),
+        )
+    }
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_checks_etag_and_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = BlobCache::open(dir.path().join("blobs")).unwrap();
+
+        assert_eq!(cache.get("name", "e1", BlobFormat::LATEST).unwrap(), None);
+
+        cache
+            .put("name", "e1", BlobFormat::LATEST, b"cipher")
+            .unwrap();
+        assert_eq!(
+            cache.get("name", "e1", BlobFormat::LATEST).unwrap(),
+            Some(b"cipher".to_vec())
+        );
+        assert_eq!(cache.get("name", "e2", BlobFormat::LATEST).unwrap(), None);
+        assert_eq!(cache.get("name", "e1", BlobFormat::V1).unwrap(), None);
+
+        cache
+            .put("name", "e2", BlobFormat::LATEST, b"newer")
+            .unwrap();
+        assert_eq!(cache.get("name", "e1", BlobFormat::LATEST).unwrap(), None);
+        assert_eq!(
+            cache.get("name", "e2", BlobFormat::LATEST).unwrap(),
+            Some(b"newer".to_vec())
+        );
+
+        cache.remove("name").unwrap();
+        assert_eq!(cache.get("name", "e2", BlobFormat::LATEST).unwrap(), None);
+    }
+
+    #[test]
+    fn test_clear_and_long_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = BlobCache::open(dir.path()).unwrap();
+        let long_name = "n".repeat(400);
+
+        cache
+            .put(&long_name, "e", BlobFormat::LATEST, b"x")
+            .unwrap();
+        assert!(cache
+            .get(&long_name, "e", BlobFormat::LATEST)
+            .unwrap()
+            .is_some());
+
+        cache.clear().unwrap();
+        assert!(cache
+            .get(&long_name, "e", BlobFormat::LATEST)
+            .unwrap()
+            .is_none());
+        assert!(cache.dir().exists());
+    }
+}
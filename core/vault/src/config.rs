@@ -4,14 +4,38 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use subtle::ConstantTimeEq;
+use tracing::warn;
 
-use axiomvault_common::{Error, Result, VaultId};
+use crate::migrations::{MigrationRecord, CONFIG_MAC_MIGRATION};
+use crate::policy::EffectivePolicy;
+use axiomvault_common::{Error, Result, Secret, VaultId, VaultPath};
+use axiomvault_crypto::keyfile::{hash_keyfile, keyfile_id};
+use axiomvault_crypto::keys::KEY_LENGTH;
 use axiomvault_crypto::recovery::{
     self, create_recovery_verification, generate_master_key, unwrap_key, wrap_key, RecoveryKey,
 };
-use axiomvault_crypto::{KdfParams, MasterKey, Salt};
+use axiomvault_crypto::{
+    verification_tag, CipherSuite, KdfParams, KeyContext, MasterKey, Salt, SALT_LENGTH,
+};
+use blake2::digest::consts::U32;
 use zeroize::Zeroizing;
 
+/// Known plaintext of the legacy AEAD-based password verification data.
+const LEGACY_VERIFICATION_PLAINTEXT: &[u8] = b"AXIOMVAULT_KEY_VERIFICATION_V1";
+
+/// Format version of blobs produced by [`VaultConfig::wrap_key_for_device`].
+const WRAPPED_KEY_VERSION: u8 = 1;
+
+/// Version byte plus the little-endian key generation.
+const WRAPPED_KEY_HEADER_LEN: usize = 1 + 8;
+
+/// Domain separation label for [`VaultConfig::config_mac`].
+const CONFIG_MAC_CONTEXT: &[u8] = b"axiomvault-config-mac-v1";
+
+/// Domain separation label for the verify salt of a migrated single-salt
+/// config.
+const VERIFY_SALT_CONTEXT: &[u8] = b"axiomvault-verify-salt-v1";
+
 /// Vault format version for migration support.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct VaultVersion {
@@ -24,6 +48,9 @@ impl VaultVersion {
     /// Bumped to 1.1 to indicate recovery key / key-wrapping support.
     pub const CURRENT: Self = Self { major: 1, minor: 1 };
 
+    /// Legacy format, where the password KEK is the master key.
+    pub const LEGACY: Self = Self { major: 1, minor: 0 };
+
     /// Check if this version is compatible with the current version.
     pub fn is_compatible(&self) -> bool {
         self.major == Self::CURRENT.major
@@ -42,6 +69,199 @@ impl Default for VaultVersion {
     }
 }
 
+/// Algorithm used to produce `VaultConfig::key_verification`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyVerificationAlgorithm {
+    /// AEAD encryption of a fixed constant under the password KEK.
+    /// Used by vaults created before verification tags existed.
+    #[default]
+    AeadConstant,
+    /// Blake2b tag over the KEK, salt, KDF parameters and vault id.
+    Blake2bTag,
+}
+
+/// Where the master key of a vault comes from at unlock time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySource {
+    /// Unwrapped with a key derived from the password (and keyfile).
+    #[default]
+    Password,
+    /// Supplied as-is by an external key management system. The config
+    /// holds no wrapped copy of it.
+    External,
+}
+
+impl KeySource {
+    fn is_password(&self) -> bool {
+        *self == KeySource::Password
+    }
+}
+
+/// How blobs are arranged in the data directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataLayout {
+    /// Every blob directly in `d/`. Vaults created before sharding existed
+    /// use this until they are migrated.
+    #[default]
+    Flat,
+    /// Blobs spread over 256 subdirectories of `d/`; see
+    /// [`crate::layout`].
+    Sharded,
+}
+
+impl DataLayout {
+    fn is_flat(&self) -> bool {
+        *self == DataLayout::Flat
+    }
+}
+
+/// What happens to a file whose encrypted name is longer than a
+/// [`NameLimit`] allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LongNamePolicy {
+    /// Refuse to create the file.
+    #[default]
+    Reject,
+    /// Store the blob under a short hash of the encrypted name and keep the
+    /// encrypted name in the tree.
+    Hash,
+}
+
+/// Cap on the length of blob object names.
+///
+/// Encrypted names are base64, so they grow with the cleartext name and can
+/// exceed what a provider accepts (255 bytes on most filesystems and on
+/// Google Drive). The `.partN` suffix of split blobs is not counted, so
+/// leave a few bytes of room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NameLimit {
+    /// Longest allowed object name, in bytes.
+    pub max_len: usize,
+    /// What happens to longer names.
+    #[serde(default)]
+    pub on_overflow: LongNamePolicy,
+}
+
+/// A subtree whose file blobs live on a provider of their own.
+///
+/// The tree stays on the vault's provider; only the blobs of files at or
+/// below `vault_path` are stored on the attached one, under its own data
+/// directory. Like the vault's own `provider_config`, the configuration is
+/// stored in the clear, so credentials belong in the provider's token
+/// store rather than here.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachPoint {
+    /// Directory of the vault the provider is attached at.
+    pub vault_path: VaultPath,
+    /// Storage provider type (e.g., "local", "s3").
+    pub provider_type: String,
+    /// Provider-specific configuration.
+    pub provider_config: serde_json::Value,
+}
+
+/// Longest display name, in characters.
+const MAX_DISPLAY_NAME_CHARS: usize = 64;
+
+/// Longest description, in characters.
+const MAX_DESCRIPTION_CHARS: usize = 500;
+
+/// Longest icon hint, in characters.
+const MAX_ICON_CHARS: usize = 32;
+
+/// User-facing details of a vault.
+///
+/// Independent of the [`VaultId`], which names the vault in paths, keys and
+/// the FFI and never changes. Updated with
+/// [`VaultSession::update_metadata`](crate::VaultSession::update_metadata).
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct VaultMetadata {
+    /// Name shown to users.
+    pub display_name: String,
+    /// Free-form description.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Icon hint for clients, such as an emoji or a symbol name.
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Color hint for clients, as `#rrggbb`.
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+impl VaultMetadata {
+    /// Trim the fields and check them against their limits. Blank optional
+    /// fields become `None`.
+    ///
+    /// # Errors
+    /// - `InvalidInput` for an empty or overlong display name, an overlong
+    ///   description or icon, control characters in the name or icon, or
+    ///   a color that is not `#rrggbb`
+    pub fn validated(self) -> Result<Self> {
+        fn optional(value: Option<String>) -> Option<String> {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        }
+        fn check_len(field: &str, value: &str, max: usize) -> Result<()> {
+            if value.chars().count() > max {
+                return Err(Error::InvalidInput(format!(
+                    "Vault {} is longer than {} characters",
+                    field, max
+                )));
+            }
+            Ok(())
+        }
+        fn check_printable(field: &str, value: &str) -> Result<()> {
+            if value.chars().any(char::is_control) {
+                return Err(Error::InvalidInput(format!(
+                    "Vault {} contains control characters",
+                    field
+                )));
+            }
+            Ok(())
+        }
+
+        let metadata = Self {
+            display_name: self.display_name.trim().to_string(),
+            description: optional(self.description),
+            icon: optional(self.icon),
+            color: optional(self.color),
+        };
+        if metadata.display_name.is_empty() {
+            return Err(Error::InvalidInput(
+                "Vault display name cannot be empty".to_string(),
+            ));
+        }
+        check_len(
+            "display name",
+            &metadata.display_name,
+            MAX_DISPLAY_NAME_CHARS,
+        )?;
+        check_printable("display name", &metadata.display_name)?;
+        if let Some(description) = &metadata.description {
+            check_len("description", description, MAX_DESCRIPTION_CHARS)?;
+        }
+        if let Some(icon) = &metadata.icon {
+            check_len("icon", icon, MAX_ICON_CHARS)?;
+            check_printable("icon", icon)?;
+        }
+        if let Some(color) = &metadata.color {
+            let hex = color.strip_prefix('#').unwrap_or_default();
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(Error::InvalidInput(format!(
+                    "Vault color must be #rrggbb, got {:?}",
+                    color
+                )));
+            }
+        }
+        Ok(metadata)
+    }
+}
+
 /// Encrypted vault configuration.
 ///
 /// This structure is stored at the vault root and contains all
@@ -58,8 +278,48 @@ impl Default for VaultVersion {
 ///    256-bit recovery key via Blake2b.
 ///
 /// The `key_verification` field lets us cheaply verify the password
-/// without unwrapping the master key (AEAD decryption of a known
-/// constant).
+/// without unwrapping the master key. New vaults store a Blake2b tag bound
+/// to the verify salt, KDF parameters and vault id, so it cannot be
+/// transplanted into another vault; older vaults store an AEAD-encrypted
+/// constant.
+///
+/// ## Salts
+///
+/// `kdf_salt` feeds Argon2id and nothing else; `verify_salt` binds the
+/// verification tag and keyfile identifier. Configs written before the
+/// split have a single `salt` for both. It is read as the KDF salt, and
+/// [`split_salts`](Self::split_salts) derives the verify salt from it and
+/// re-issues the tags on the next unlock.
+///
+/// ## Keyfile
+///
+/// A vault may additionally require a keyfile. Its hash is mixed into the
+/// password-derived KEK, and `keyfile_verification` holds a salted
+/// identifier of that hash so a wrong keyfile can be reported separately
+/// from a wrong password. The recovery key bypasses the keyfile entirely.
+///
+/// ## External key
+///
+/// With [`KeySource::External`] the master key is handed in at unlock
+/// time by a key management system. No wrapped copy, recovery key or
+/// keyfile is stored; `key_verification` is a tag over the master key
+/// itself, so a wrong key is refused before anything is decrypted.
+///
+/// ## Tamper evidence
+///
+/// `config_mac` is a keyed Blake2b MAC over every security-relevant field
+/// (everything but the timestamps), keyed from the master key. Only
+/// someone who can unlock the vault can produce it, so it is checked right
+/// after the master key is recovered and before the rest of the config is
+/// trusted. Configs written before the MAC existed are accepted with a
+/// warning and gain one on their next save.
+///
+/// ## Display metadata
+///
+/// The display name, description, icon and color are for people only and
+/// can change at any time; the [`VaultId`] stays fixed. Configs written
+/// before they existed show the ID as the name. `metadata_revision` counts
+/// updates, so an update based on an outdated copy can be detected.
 ///
 /// ## Legacy format (v1.0)
 ///
@@ -73,7 +333,12 @@ pub struct VaultConfig {
     /// Vault format version.
     pub version: VaultVersion,
     /// Salt for password-based key derivation (Argon2id).
-    pub salt: Salt,
+    #[serde(alias = "salt")]
+    pub kdf_salt: Salt,
+    /// Salt for `key_verification` and `keyfile_verification`. `None` in
+    /// single-salt configs, which bind both to `kdf_salt`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_salt: Option<Salt>,
     /// KDF parameters.
     pub kdf_params: KdfParams,
     /// Storage provider type (e.g., "local", "gdrive").
@@ -88,6 +353,11 @@ pub struct VaultConfig {
     /// This is used to verify the password without storing the key.
     pub key_verification: Vec<u8>,
 
+    /// How `key_verification` was computed. Configs written before this
+    /// field existed use the AEAD constant.
+    #[serde(default)]
+    pub key_verification_algorithm: KeyVerificationAlgorithm,
+
     // -- v1.1 fields (key wrapping + recovery) ---------------------------
     /// Master key encrypted (wrapped) with the password-derived KEK.
     /// `None` for legacy v1.0 vaults that have not been migrated.
@@ -109,6 +379,159 @@ pub struct VaultConfig {
     /// re-display it later (requires unlocking with password first).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub encrypted_recovery_key: Option<Vec<u8>>,
+
+    // -- keyfile -----------------------------------------------------------
+    /// Whether unlocking requires a keyfile in addition to the password.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keyfile_required: bool,
+
+    /// Salted identifier of the keyfile hash (never the keyfile content).
+    /// Present whenever `keyfile_required` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyfile_verification: Option<Vec<u8>>,
+
+    // -- key source --------------------------------------------------------
+    /// How the master key is obtained at unlock time.
+    #[serde(default, skip_serializing_if = "KeySource::is_password")]
+    pub key_source: KeySource,
+
+    // -- policies ----------------------------------------------------------
+    /// Policy for paths no directory policy overrides.
+    #[serde(default, skip_serializing_if = "EffectivePolicy::is_default")]
+    pub default_policy: EffectivePolicy,
+
+    // -- wrapped-key unlock ------------------------------------------------
+    /// Bumped whenever the credentials wrapping the master key change.
+    /// Wrapped keys exported for an earlier generation are refused.
+    #[serde(default)]
+    pub key_generation: u64,
+
+    // -- storage layout ----------------------------------------------------
+    /// Arrangement of blobs in the data directory. Switched to `Sharded`
+    /// by [`VaultManager::shard_data_dir`](crate::VaultManager::shard_data_dir)
+    /// once every blob has moved.
+    #[serde(default, skip_serializing_if = "DataLayout::is_flat")]
+    pub data_layout: DataLayout,
+
+    /// Limit on blob object names for new files. `None` leaves names
+    /// unbounded. Existing blobs keep their names when it changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_limit: Option<NameLimit>,
+
+    // -- cipher suites -----------------------------------------------------
+    /// Suite new file blobs are sealed with. See
+    /// [`set_cipher_suite`](Self::set_cipher_suite).
+    #[serde(default, skip_serializing_if = "CipherSuite::is_default")]
+    pub cipher_suite: CipherSuite,
+
+    /// Suites every client of this vault has agreed to read, besides
+    /// XChaCha20-Poly1305, which all can. Writes are refused under a suite
+    /// missing here, so an older reader is never handed a blob it cannot
+    /// open.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reader_suites: Vec<CipherSuite>,
+
+    // -- attached storage --------------------------------------------------
+    /// Subtrees whose blobs are stored on other providers. See
+    /// [`add_attachment`](Self::add_attachment).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<AttachPoint>,
+
+    // -- display metadata --------------------------------------------------
+    /// Name shown to users; empty until one is set. See
+    /// [`display_name`](Self::display_name).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub display_name: String,
+
+    /// Free-form description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Icon hint for clients.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+
+    /// Color hint for clients, as `#rrggbb`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    /// Bumped by every metadata update.
+    #[serde(default)]
+    pub metadata_revision: u64,
+
+    // -- access tracking ---------------------------------------------------
+    /// Record when files are read, for finding stale files with
+    /// [`VaultOperations::list_stale`](crate::VaultOperations::list_stale).
+    /// Off by default: reads then change the tree, which has to be saved
+    /// again. See [`VaultSession::flush_access_times`](crate::VaultSession::flush_access_times).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub track_access_time: bool,
+
+    // -- migrations --------------------------------------------------------
+    /// Every run of a [session migration](crate::migrations), oldest first.
+    /// A run still marked running was interrupted and is resumed by
+    /// applying the migration again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub migrations: Vec<MigrationRecord>,
+
+    // -- tamper evidence ---------------------------------------------------
+    /// MAC over the protected fields; see [`update_mac`](Self::update_mac).
+    /// `None` for configs written before it existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_mac: Option<Vec<u8>>,
+}
+
+/// The fields covered by [`VaultConfig::config_mac`], in a fixed order.
+#[derive(Serialize)]
+struct MacInput<'a> {
+    id: &'a VaultId,
+    version: VaultVersion,
+    // Keeps its old name so single-salt MACs stay valid.
+    #[serde(rename = "salt")]
+    kdf_salt: &'a Salt,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verify_salt: Option<&'a Salt>,
+    kdf_params: &'a KdfParams,
+    provider_type: &'a str,
+    provider_config: &'a serde_json::Value,
+    key_verification: &'a [u8],
+    key_verification_algorithm: KeyVerificationAlgorithm,
+    wrapped_master_key: Option<&'a [u8]>,
+    recovery_wrapped_master_key: Option<&'a [u8]>,
+    recovery_key_verification: Option<&'a [u8]>,
+    encrypted_recovery_key: Option<&'a [u8]>,
+    keyfile_required: bool,
+    keyfile_verification: Option<&'a [u8]>,
+    // Skipped for password vaults so their existing MACs stay valid.
+    #[serde(skip_serializing_if = "KeySource::is_password")]
+    key_source: KeySource,
+    default_policy: &'a EffectivePolicy,
+    key_generation: u64,
+    // Cipher suites are skipped at their defaults so existing MACs stay
+    // valid; covering them stops a downgrade to a weaker suite.
+    #[serde(skip_serializing_if = "CipherSuite::is_default")]
+    cipher_suite: CipherSuite,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    reader_suites: &'a [CipherSuite],
+    // Skipped while empty so existing MACs stay valid; covering them stops
+    // blobs being redirected to another provider.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    attachments: &'a [AttachPoint],
+    // Display metadata is skipped while unset so existing MACs stay valid.
+    #[serde(skip_serializing_if = "str::is_empty")]
+    display_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    icon: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'a str>,
+    #[serde(skip_serializing_if = "is_zero")]
+    metadata_revision: u64,
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
 }
 
 /// Result of creating a new vault configuration.
@@ -138,26 +561,74 @@ impl VaultConfig {
         provider_config: serde_json::Value,
         kdf_params: KdfParams,
     ) -> Result<VaultConfigCreation> {
-        use axiomvault_crypto::{derive_key, encrypt};
+        Self::new_with_keyfile(
+            id,
+            password,
+            None,
+            provider_type,
+            provider_config,
+            kdf_params,
+        )
+    }
 
-        let salt = Salt::generate();
+    /// Create a new vault configuration, optionally protected by a keyfile.
+    ///
+    /// When `keyfile` is given, its content is required together with the
+    /// password on every unlock. The recovery key works without it.
+    pub fn new_with_keyfile(
+        id: VaultId,
+        password: &[u8],
+        keyfile: Option<&[u8]>,
+        provider_type: impl Into<String>,
+        provider_config: serde_json::Value,
+        kdf_params: KdfParams,
+    ) -> Result<VaultConfigCreation> {
+        Self::new_with_recovery_key(
+            id,
+            password,
+            keyfile,
+            &RecoveryKey::generate(),
+            provider_type,
+            provider_config,
+            kdf_params,
+        )
+    }
+
+    /// Create a new vault configuration around an existing recovery key.
+    ///
+    /// Lets a frontend show the recovery words, and have the user confirm
+    /// them, before the vault is created.
+    pub fn new_with_recovery_key(
+        id: VaultId,
+        password: &[u8],
+        keyfile: Option<&[u8]>,
+        recovery_key: &RecoveryKey,
+        provider_type: impl Into<String>,
+        provider_config: serde_json::Value,
+        kdf_params: KdfParams,
+    ) -> Result<VaultConfigCreation> {
+        use axiomvault_crypto::{derive_key_with_keyfile, encrypt};
+
+        let kdf_salt = Salt::generate(SALT_LENGTH);
+        let verify_salt = Salt::generate(SALT_LENGTH);
 
         // 1. Generate a random master key.
         let master_key = generate_master_key();
 
-        // 2. Derive password KEK and wrap the master key.
-        let password_kek = derive_key(password, &salt, &kdf_params)?;
+        // 2. Derive password KEK (mixed with the keyfile, if any) and wrap
+        //    the master key.
+        let password_kek = derive_key_with_keyfile(password, keyfile, &kdf_salt, &kdf_params)?;
+        let keyfile_verification = Self::keyfile_verification_for(keyfile, &verify_salt)?;
         let wrapped_master_key = wrap_key(&master_key, password_kek.as_bytes())?;
 
         // 3. Create password verification data.
-        let verification_plaintext = b"AXIOMVAULT_KEY_VERIFICATION_V1";
-        let key_verification = encrypt(password_kek.as_bytes(), verification_plaintext)?;
+        let key_verification =
+            Self::key_verification_for(&id, &password_kek, &verify_salt, &kdf_params);
 
-        // 4. Generate recovery key and wrap the master key with it.
-        let recovery_key = RecoveryKey::generate();
+        // 4. Wrap the master key with the recovery key.
         let recovery_kek = recovery_key.derive_kek();
         let recovery_wrapped_master_key = wrap_key(&master_key, &recovery_kek)?;
-        let recovery_key_verification = create_recovery_verification(&recovery_key)?;
+        let recovery_key_verification = create_recovery_verification(recovery_key)?;
 
         // 5. Encrypt the recovery key with the master key so it can be
         //    re-displayed later when the vault is unlocked.
@@ -167,21 +638,42 @@ impl VaultConfig {
 
         let now = Utc::now();
 
-        let config = VaultConfig {
+        let mut config = VaultConfig {
             id,
             version: VaultVersion::CURRENT,
-            salt,
+            kdf_salt,
+            verify_salt: Some(verify_salt),
             kdf_params,
             provider_type: provider_type.into(),
             provider_config,
             created_at: now,
             modified_at: now,
             key_verification,
+            key_verification_algorithm: KeyVerificationAlgorithm::Blake2bTag,
             wrapped_master_key: Some(wrapped_master_key),
             recovery_wrapped_master_key: Some(recovery_wrapped_master_key),
             recovery_key_verification: Some(recovery_key_verification),
             encrypted_recovery_key: Some(encrypted_recovery_key),
+            keyfile_required: keyfile.is_some(),
+            keyfile_verification,
+            key_source: KeySource::Password,
+            default_policy: EffectivePolicy::default(),
+            key_generation: 0,
+            data_layout: DataLayout::Flat,
+            name_limit: None,
+            cipher_suite: CipherSuite::default(),
+            reader_suites: Vec::new(),
+            attachments: Vec::new(),
+            display_name: String::new(),
+            description: None,
+            icon: None,
+            color: None,
+            metadata_revision: 0,
+            track_access_time: false,
+            migrations: Vec::new(),
+            config_mac: None,
         };
+        config.update_mac(&master_key)?;
 
         Ok(VaultConfigCreation {
             config,
@@ -190,54 +682,717 @@ impl VaultConfig {
         })
     }
 
-    /// Check whether this config uses the legacy (v1.0) key model where the
-    /// Argon2id output *is* the master key, rather than the wrapped model.
-    pub fn is_legacy_format(&self) -> bool {
-        self.wrapped_master_key.is_none()
+    /// Create a vault configuration for a master key held by an external
+    /// key management system.
+    ///
+    /// Nothing derived from a password is stored, and there is no recovery
+    /// key: losing the external key loses the vault. `kdf_params` keep
+    /// their defaults and are unused.
+    pub fn new_with_external_key(
+        id: VaultId,
+        master_key: &MasterKey,
+        provider_type: impl Into<String>,
+        provider_config: serde_json::Value,
+    ) -> Result<VaultConfig> {
+        let verify_salt = Salt::generate(SALT_LENGTH);
+        let kdf_params = KdfParams::default();
+        let key_verification =
+            Self::key_verification_for(&id, master_key, &verify_salt, &kdf_params);
+        let now = Utc::now();
+
+        let mut config = VaultConfig {
+            id,
+            version: VaultVersion::CURRENT,
+            kdf_salt: Salt::generate(SALT_LENGTH),
+            verify_salt: Some(verify_salt),
+            kdf_params,
+            provider_type: provider_type.into(),
+            provider_config,
+            created_at: now,
+            modified_at: now,
+            key_verification,
+            key_verification_algorithm: KeyVerificationAlgorithm::Blake2bTag,
+            wrapped_master_key: None,
+            recovery_wrapped_master_key: None,
+            recovery_key_verification: None,
+            encrypted_recovery_key: None,
+            keyfile_required: false,
+            keyfile_verification: None,
+            key_source: KeySource::External,
+            default_policy: EffectivePolicy::default(),
+            key_generation: 0,
+            data_layout: DataLayout::Flat,
+            name_limit: None,
+            cipher_suite: CipherSuite::default(),
+            reader_suites: Vec::new(),
+            attachments: Vec::new(),
+            display_name: String::new(),
+            description: None,
+            icon: None,
+            color: None,
+            metadata_revision: 0,
+            track_access_time: false,
+            migrations: Vec::new(),
+            config_mac: None,
+        };
+        config.update_mac(master_key)?;
+        Ok(config)
+    }
+
+    /// Name to show for the vault: its display name, or its ID if none was
+    /// set.
+    pub fn display_name(&self) -> &str {
+        if self.display_name.is_empty() {
+            self.id.as_str()
+        } else {
+            &self.display_name
+        }
+    }
+
+    /// The vault's display metadata, with the ID standing in for a missing
+    /// name.
+    pub fn metadata(&self) -> VaultMetadata {
+        VaultMetadata {
+            display_name: self.display_name().to_string(),
+            description: self.description.clone(),
+            icon: self.icon.clone(),
+            color: self.color.clone(),
+        }
+    }
+
+    /// Replace the display metadata, bump `metadata_revision` and re-seal
+    /// the config.
+    ///
+    /// Only the display fields change; the ID, keys and storage settings
+    /// are untouched.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `metadata` fails [`VaultMetadata::validated`]
+    pub fn set_metadata(&mut self, metadata: VaultMetadata, master_key: &MasterKey) -> Result<()> {
+        let metadata = metadata.validated()?;
+        self.display_name = metadata.display_name;
+        self.description = metadata.description;
+        self.icon = metadata.icon;
+        self.color = metadata.color;
+        self.metadata_revision += 1;
+        self.modified_at = Utc::now();
+        self.update_mac(master_key)
+    }
+
+    /// Take the display metadata and its revision from `other`, a newer
+    /// copy of this config, and re-seal.
+    pub(crate) fn adopt_metadata(
+        &mut self,
+        other: &VaultConfig,
+        master_key: &MasterKey,
+    ) -> Result<()> {
+        self.display_name = other.display_name.clone();
+        self.description = other.description.clone();
+        self.icon = other.icon.clone();
+        self.color = other.color.clone();
+        self.metadata_revision = other.metadata_revision;
+        self.modified_at = self.modified_at.max(other.modified_at);
+        self.update_mac(master_key)
+    }
+
+    /// Whether every client of this vault can read blobs sealed with
+    /// `suite`.
+    pub fn readers_support(&self, suite: CipherSuite) -> bool {
+        suite.is_default() || self.reader_suites.contains(&suite)
+    }
+
+    /// Suite to seal new file blobs with.
+    ///
+    /// # Errors
+    /// - `Unsupported` if the vault's readers have not agreed to read the
+    ///   configured suite, or this build cannot write it
+    pub fn write_suite(&self) -> Result<CipherSuite> {
+        let suite = self.cipher_suite;
+        if !self.readers_support(suite) {
+            return Err(Error::Unsupported(format!(
+                "writing {} blobs: readers of vault {} are not known to support it",
+                suite, self.id
+            )));
+        }
+        if !suite.is_available() {
+            return Err(Error::Unsupported(format!(
+                "writing {} blobs: this build lacks the aes-gcm-siv feature",
+                suite
+            )));
+        }
+        Ok(suite)
+    }
+
+    /// Record that every client of this vault can read `suite`, and
+    /// re-seal the config.
+    pub fn allow_reader_suite(&mut self, suite: CipherSuite, master_key: &MasterKey) -> Result<()> {
+        if !self.readers_support(suite) {
+            self.reader_suites.push(suite);
+            self.modified_at = Utc::now();
+        }
+        self.update_mac(master_key)
+    }
+
+    /// Seal new file blobs with `suite` from now on, and re-seal the config.
+    ///
+    /// Existing blobs keep their suite and stay readable.
+    ///
+    /// # Errors
+    /// - `Unsupported` if the suite is not in the vault's reader suites
+    ///   (see [`allow_reader_suite`](Self::allow_reader_suite)) or this
+    ///   build cannot write it
+    pub fn set_cipher_suite(&mut self, suite: CipherSuite, master_key: &MasterKey) -> Result<()> {
+        let previous = std::mem::replace(&mut self.cipher_suite, suite);
+        if let Err(e) = self.write_suite() {
+            self.cipher_suite = previous;
+            return Err(e);
+        }
+        self.modified_at = Utc::now();
+        self.update_mac(master_key)
+    }
+
+    /// The attach point whose provider stores the blob of the file at
+    /// `path`, or `None` for the vault's own provider.
+    pub fn attachment_for(&self, path: &VaultPath) -> Option<&AttachPoint> {
+        self.attachments
+            .iter()
+            .find(|point| path.starts_with(&point.vault_path))
+    }
+
+    /// Store blobs below `point.vault_path` on its provider from now on,
+    /// and re-seal the config.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if the path is the root, or inside or around
+    ///   another attach point
+    /// - `AlreadyExists` if the path is already an attach point
+    pub fn add_attachment(&mut self, point: AttachPoint, master_key: &MasterKey) -> Result<()> {
+        if point.vault_path.is_root() {
+            return Err(Error::InvalidInput(
+                "Cannot attach a provider at the vault root".to_string(),
+            ));
+        }
+        for existing in &self.attachments {
+            if existing.vault_path == point.vault_path {
+                return Err(Error::AlreadyExists(format!(
+                    "{} is already an attach point",
+                    point.vault_path
+                )));
+            }
+            if point.vault_path.starts_with(&existing.vault_path)
+                || existing.vault_path.starts_with(&point.vault_path)
+            {
+                return Err(Error::InvalidInput(format!(
+                    "{} overlaps the attach point {}",
+                    point.vault_path, existing.vault_path
+                )));
+            }
+        }
+        self.attachments.push(point);
+        self.modified_at = Utc::now();
+        self.update_mac(master_key)
+    }
+
+    /// Store blobs below `vault_path` on the vault's provider again, and
+    /// re-seal the config. Moving the blobs back is up to the caller.
+    ///
+    /// # Errors
+    /// - `NotFound` if `vault_path` is not an attach point
+    pub fn remove_attachment(
+        &mut self,
+        vault_path: &VaultPath,
+        master_key: &MasterKey,
+    ) -> Result<AttachPoint> {
+        let index = self
+            .attachments
+            .iter()
+            .position(|point| &point.vault_path == vault_path)
+            .ok_or_else(|| Error::NotFound(format!("{} is not an attach point", vault_path)))?;
+        let point = self.attachments.remove(index);
+        self.modified_at = Utc::now();
+        self.update_mac(master_key)?;
+        Ok(point)
+    }
+
+    /// Check whether this config uses the legacy (v1.0) key model where the
+    /// Argon2id output *is* the master key, rather than the wrapped model.
+    pub fn is_legacy_format(&self) -> bool {
+        self.key_source.is_password() && self.wrapped_master_key.is_none()
+    }
+
+    /// Check whether the master key is supplied by an external key
+    /// management system rather than unlocked with a password.
+    pub fn uses_external_key(&self) -> bool {
+        self.key_source == KeySource::External
+    }
+
+    fn refuse_external_key(&self) -> Result<()> {
+        if self.uses_external_key() {
+            return Err(Error::NotPermitted(
+                "This vault opens only with its externally supplied key".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Salt binding `key_verification` and `keyfile_verification`.
+    pub fn verify_salt(&self) -> &Salt {
+        self.verify_salt.as_ref().unwrap_or(&self.kdf_salt)
+    }
+
+    /// Check whether this config still uses one salt for both the KDF and
+    /// the verification data.
+    pub fn has_single_salt(&self) -> bool {
+        self.verify_salt.is_none()
+    }
+
+    /// The verify salt given to a single-salt config on migration: a
+    /// Blake2b hash of its KDF salt, so every client migrating the same
+    /// config arrives at the same salt.
+    fn derive_verify_salt(kdf_salt: &Salt) -> Salt {
+        use blake2::{Blake2b, Digest};
+
+        let mut hasher = Blake2b::<U32>::new();
+        hasher.update(VERIFY_SALT_CONTEXT);
+        hasher.update(kdf_salt.as_bytes());
+        Salt::from_bytes(hasher.finalize().to_vec())
+    }
+
+    /// Give a single-salt config its own verify salt and re-issue
+    /// `key_verification` and `keyfile_verification` under it.
+    ///
+    /// `verifier` is the key the verification tag is computed over: the
+    /// password KEK (with `keyfile` mixed in), or the master key of an
+    /// external-key vault. The caller must already have checked it against
+    /// this config. Does nothing on configs that have both salts, or that
+    /// have no MAC yet: those are sealed by the `config-mac` migration,
+    /// which records it in the migration journal, and split on the next
+    /// unlock.
+    ///
+    /// # Returns
+    /// Whether the config changed and should be saved.
+    ///
+    /// # Errors
+    /// - `ConfigTampered` if `master_key` does not match the config MAC
+    pub fn split_salts(
+        &mut self,
+        verifier: &MasterKey,
+        keyfile: Option<&[u8]>,
+        master_key: &MasterKey,
+    ) -> Result<bool> {
+        if !self.has_single_salt() || self.config_mac.is_none() {
+            return Ok(false);
+        }
+        self.verify_mac(master_key)?;
+
+        let verify_salt = Self::derive_verify_salt(&self.kdf_salt);
+        self.key_verification =
+            Self::key_verification_for(&self.id, verifier, &verify_salt, &self.kdf_params);
+        self.key_verification_algorithm = KeyVerificationAlgorithm::Blake2bTag;
+        if self.keyfile_required {
+            self.keyfile_verification = Self::keyfile_verification_for(keyfile, &verify_salt)?;
+        }
+        self.verify_salt = Some(verify_salt);
+        self.modified_at = Utc::now();
+        self.update_mac(master_key)?;
+        Ok(true)
+    }
+
+    /// Check whether unlocking this vault requires a keyfile.
+    pub fn requires_keyfile(&self) -> bool {
+        self.keyfile_required
+    }
+
+    /// Compute the `key_verification` tag for a password KEK, or for the
+    /// master key of an external-key vault.
+    pub(crate) fn key_verification_for(
+        id: &VaultId,
+        key: &MasterKey,
+        salt: &Salt,
+        kdf_params: &KdfParams,
+    ) -> Vec<u8> {
+        verification_tag(key, salt, kdf_params, id.as_str().as_bytes()).to_vec()
+    }
+
+    /// Check a password KEK against the stored `key_verification`.
+    fn check_key_verification(&self, password_kek: &MasterKey) -> bool {
+        use axiomvault_crypto::decrypt;
+        use zeroize::Zeroize;
+
+        match self.key_verification_algorithm {
+            KeyVerificationAlgorithm::Blake2bTag => {
+                let expected = verification_tag(
+                    password_kek,
+                    self.verify_salt(),
+                    &self.kdf_params,
+                    self.id.as_str().as_bytes(),
+                );
+                self.key_verification.len() == expected.len()
+                    && bool::from(self.key_verification.as_slice().ct_eq(&expected))
+            }
+            KeyVerificationAlgorithm::AeadConstant => {
+                match decrypt(password_kek.as_bytes(), &self.key_verification) {
+                    Ok(mut plaintext) => {
+                        let valid = plaintext.len() == LEGACY_VERIFICATION_PLAINTEXT.len()
+                            && bool::from(
+                                plaintext.as_slice().ct_eq(LEGACY_VERIFICATION_PLAINTEXT),
+                            );
+                        plaintext.zeroize();
+                        valid
+                    }
+                    Err(_) => false,
+                }
+            }
+        }
+    }
+
+    /// Compute the `keyfile_verification` entry for a keyfile under `salt`.
+    pub(crate) fn keyfile_verification_for(
+        keyfile: Option<&[u8]>,
+        salt: &Salt,
+    ) -> Result<Option<Vec<u8>>> {
+        keyfile
+            .map(|content| Ok(keyfile_id(&*hash_keyfile(content)?, salt).to_vec()))
+            .transpose()
+    }
+
+    /// Check a supplied keyfile against this configuration.
+    ///
+    /// This is cheap (no KDF) so a missing or wrong keyfile is reported
+    /// before the password is tried.
+    ///
+    /// # Errors
+    /// - `KeyfileRequired` if the vault needs a keyfile and none was given
+    /// - `KeyfileMismatch` if the keyfile does not belong to this vault
+    /// - `InvalidInput` if a keyfile was given for a vault that has none
+    pub fn check_keyfile(&self, keyfile: Option<&[u8]>) -> Result<()> {
+        match (self.keyfile_required, keyfile) {
+            (false, None) => Ok(()),
+            (false, Some(_)) => Err(Error::InvalidInput(
+                "This vault does not use a keyfile".to_string(),
+            )),
+            (true, None) => Err(Error::KeyfileRequired),
+            (true, Some(content)) => {
+                let expected = self.keyfile_verification.as_ref().ok_or_else(|| {
+                    Error::Vault("Keyfile verification missing from config".to_string())
+                })?;
+                let id = keyfile_id(&*hash_keyfile(content)?, self.verify_salt());
+                if id.len() == expected.len() && bool::from(id.as_slice().ct_eq(expected)) {
+                    Ok(())
+                } else {
+                    Err(Error::KeyfileMismatch)
+                }
+            }
+        }
+    }
+
+    /// Compute the MAC over the protected fields.
+    ///
+    /// The MAC key comes from the master key rather than straight from the
+    /// password KDF output. Only the password KEK unwraps the master key,
+    /// so it still takes the password to verify or update the MAC, but the
+    /// MAC also survives password changes and can be checked by every other
+    /// way in: recovery key, keyfile, device-wrapped key and external key
+    /// management, which never see the password KEK.
+    fn compute_mac(&self, master_key: &MasterKey) -> Result<blake2::Blake2bMac<U32>> {
+        use blake2::digest::Mac;
+
+        let input = MacInput {
+            id: &self.id,
+            version: self.version,
+            kdf_salt: &self.kdf_salt,
+            verify_salt: self.verify_salt.as_ref(),
+            kdf_params: &self.kdf_params,
+            provider_type: &self.provider_type,
+            provider_config: &self.provider_config,
+            key_verification: &self.key_verification,
+            key_verification_algorithm: self.key_verification_algorithm,
+            wrapped_master_key: self.wrapped_master_key.as_deref(),
+            recovery_wrapped_master_key: self.recovery_wrapped_master_key.as_deref(),
+            recovery_key_verification: self.recovery_key_verification.as_deref(),
+            encrypted_recovery_key: self.encrypted_recovery_key.as_deref(),
+            keyfile_required: self.keyfile_required,
+            keyfile_verification: self.keyfile_verification.as_deref(),
+            key_source: self.key_source,
+            default_policy: &self.default_policy,
+            key_generation: self.key_generation,
+            cipher_suite: self.cipher_suite,
+            reader_suites: &self.reader_suites,
+            attachments: &self.attachments,
+            display_name: &self.display_name,
+            description: self.description.as_deref(),
+            icon: self.icon.as_deref(),
+            color: self.color.as_deref(),
+            metadata_revision: self.metadata_revision,
+        };
+        let canonical =
+            serde_json::to_vec(&input).map_err(|e| Error::Serialization(e.to_string()))?;
+
+        let mac_key = master_key.derive_file_key(&KeyContext::ConfigMac);
+        let mut mac = <blake2::Blake2bMac<U32> as Mac>::new_from_slice(mac_key.as_bytes())
+            .map_err(|e| Error::Crypto(format!("Invalid config MAC key: {}", e)))?;
+        mac.update(CONFIG_MAC_CONTEXT);
+        mac.update(&canonical);
+        Ok(mac)
+    }
+
+    /// Recompute `config_mac` after a legitimate change.
+    ///
+    /// Must be called before the config is persisted, with the master key
+    /// of this vault.
+    pub fn update_mac(&mut self, master_key: &MasterKey) -> Result<()> {
+        use blake2::digest::Mac;
+
+        let mac = self.compute_mac(master_key)?.finalize().into_bytes();
+        self.config_mac = Some(mac.to_vec());
+        Ok(())
+    }
+
+    /// Check `config_mac` with the master key recovered from this config.
+    ///
+    /// A config without a MAC is accepted with a warning only if nothing in
+    /// it postdates the MAC; see [`written_sealed`](Self::written_sealed).
+    ///
+    /// # Errors
+    /// - `ConfigTampered` if a protected field no longer matches the MAC,
+    ///   or the MAC was removed from a config written with one
+    pub fn verify_mac(&self, master_key: &MasterKey) -> Result<()> {
+        use blake2::digest::Mac;
+
+        let Some(ref stored) = self.config_mac else {
+            if self.written_sealed() {
+                return Err(Error::ConfigTampered);
+            }
+            warn!(
+                "Vault {} has no config MAC; it will be added on the next save",
+                self.id
+            );
+            return Ok(());
+        };
+        self.compute_mac(master_key)?
+            .verify_slice(stored)
+            .map_err(|_| Error::ConfigTampered)
+    }
+
+    /// Whether this config was written by a version that seals configs,
+    /// so a missing MAC can only have been stripped.
+    ///
+    /// Separate verify salts, Blake2b verification tags and password
+    /// changes all came after the MAC, as did every config the `config-mac`
+    /// migration has seen.
+    fn written_sealed(&self) -> bool {
+        self.verify_salt.is_some()
+            || self.key_verification_algorithm == KeyVerificationAlgorithm::Blake2bTag
+            || self.key_generation > 0
+            || self
+                .migrations
+                .iter()
+                .any(|run| run.id == CONFIG_MAC_MIGRATION)
+    }
+
+    /// Serialize for storage with a fresh MAC, leaving `self` untouched.
+    pub fn to_sealed_bytes(&self, master_key: &MasterKey) -> Result<Vec<u8>> {
+        let mut sealed = self.clone();
+        sealed.update_mac(master_key)?;
+        sealed.to_bytes()
+    }
+
+    /// Verify a password against this configuration.
+    ///
+    /// Returns the **master key** on success so the caller does not need
+    /// to derive it a second time (avoids double-KDF cost on unlock).
+    ///
+    /// For v1.1+ vaults the password-derived KEK unwraps the stored
+    /// `wrapped_master_key`. For legacy v1.0 vaults the Argon2id output
+    /// is used directly as the master key.
+    ///
+    /// # Returns
+    /// - `Ok(Some(key))` if password is correct
+    /// - `Ok(None)` if password is incorrect
+    /// - `Err(_)` if verification failed for other reasons
+    pub fn verify_password(&self, password: &Secret) -> Result<Option<MasterKey>> {
+        self.verify_password_with_keyfile(password, None)
+    }
+
+    /// Verify a password and optional keyfile against this configuration.
+    ///
+    /// Behaves like [`verify_password`](Self::verify_password), but fails
+    /// with `KeyfileRequired` / `KeyfileMismatch` when the keyfile is
+    /// missing or wrong.
+    pub fn verify_password_with_keyfile(
+        &self,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+    ) -> Result<Option<MasterKey>> {
+        Ok(self
+            .password_keys(password.expose(), keyfile)?
+            .map(|(_, master_key)| master_key))
+    }
+
+    /// Verify a password and optional keyfile, then move a single-salt
+    /// config to separate salts with [`split_salts`](Self::split_salts).
+    ///
+    /// Returns what [`verify_password_with_keyfile`](Self::verify_password_with_keyfile)
+    /// returns; the config is left alone when the password is wrong.
+    ///
+    /// # Errors
+    /// - `ConfigTampered` if a single-salt config fails its MAC check
+    pub fn unlock_and_split_salts(
+        &mut self,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+    ) -> Result<Option<MasterKey>> {
+        let Some((password_kek, master_key)) = self.password_keys(password.expose(), keyfile)?
+        else {
+            return Ok(None);
+        };
+        self.split_salts(&password_kek, keyfile, &master_key)?;
+        Ok(Some(master_key))
+    }
+
+    /// Derive the password KEK and, if it passes the verification check,
+    /// recover the master key.
+    fn password_keys(
+        &self,
+        password: &[u8],
+        keyfile: Option<&[u8]>,
+    ) -> Result<Option<(MasterKey, MasterKey)>> {
+        use axiomvault_crypto::derive_key_with_keyfile;
+
+        self.refuse_external_key()?;
+        self.check_keyfile(keyfile)?;
+        let password_kek =
+            derive_key_with_keyfile(password, keyfile, &self.kdf_salt, &self.kdf_params)?;
+
+        // First, verify the password against the stored verification data.
+        if !self.check_key_verification(&password_kek) {
+            return Ok(None);
+        }
+
+        // Password is correct. Now obtain the master key.
+        let master_key = if let Some(ref wrapped) = self.wrapped_master_key {
+            // v1.1+: unwrap the master key.
+            unwrap_key(wrapped, password_kek.as_bytes())?
+        } else {
+            // Legacy v1.0: the KEK *is* the master key.
+            password_kek.clone()
+        };
+        Ok(Some((password_kek, master_key)))
+    }
+
+    /// Check an externally supplied master key against this configuration.
+    ///
+    /// # Returns
+    /// - `Ok(true)` if `master_key` is this vault's key
+    /// - `Ok(false)` if it is not
+    ///
+    /// # Errors
+    /// - `NotPermitted` if the vault is unlocked with a password
+    pub fn verify_external_key(&self, master_key: &MasterKey) -> Result<bool> {
+        if !self.uses_external_key() {
+            return Err(Error::NotPermitted(
+                "This vault does not use an external key".to_string(),
+            ));
+        }
+        Ok(self.check_key_verification(master_key))
     }
 
-    /// Verify a password against this configuration.
+    /// Switch a password vault to an externally supplied master key.
     ///
-    /// Returns the **master key** on success so the caller does not need
-    /// to derive it a second time (avoids double-KDF cost on unlock).
+    /// The password wrapping, keyfile and recovery key are dropped, and the
+    /// key generation is bumped so device-wrapped keys must be re-exported.
+    /// `master_key` must be the vault's key; the caller hands it to the key
+    /// management system before persisting the config.
     ///
-    /// For v1.1+ vaults the password-derived KEK unwraps the stored
-    /// `wrapped_master_key`. For legacy v1.0 vaults the Argon2id output
-    /// is used directly as the master key.
+    /// # Errors
+    /// - `InvalidInput` if the vault already uses an external key
+    /// - `ConfigTampered` if `master_key` does not match the config MAC
+    pub fn convert_to_external_key(&mut self, master_key: &MasterKey) -> Result<()> {
+        if self.uses_external_key() {
+            return Err(Error::InvalidInput(
+                "Vault already uses an external key".to_string(),
+            ));
+        }
+        self.verify_mac(master_key)?;
+
+        let verify_salt = Salt::generate(SALT_LENGTH);
+        self.key_verification =
+            Self::key_verification_for(&self.id, master_key, &verify_salt, &self.kdf_params);
+        self.key_verification_algorithm = KeyVerificationAlgorithm::Blake2bTag;
+        self.kdf_salt = Salt::generate(SALT_LENGTH);
+        self.verify_salt = Some(verify_salt);
+        self.version = VaultVersion::CURRENT;
+        self.wrapped_master_key = None;
+        self.recovery_wrapped_master_key = None;
+        self.recovery_key_verification = None;
+        self.encrypted_recovery_key = None;
+        self.keyfile_required = false;
+        self.keyfile_verification = None;
+        self.key_source = KeySource::External;
+        self.key_generation += 1;
+        self.modified_at = Utc::now();
+        self.update_mac(master_key)
+    }
+
+    /// Switch an external-key vault back to password unlock.
+    ///
+    /// Wraps `master_key` under the new password (and keyfile, if given)
+    /// and under a fresh recovery key.
     ///
     /// # Returns
-    /// - `Ok(Some(key))` if password is correct
-    /// - `Ok(None)` if password is incorrect
-    /// - `Err(_)` if verification failed for other reasons
-    pub fn verify_password(&self, password: &[u8]) -> Result<Option<MasterKey>> {
-        use axiomvault_crypto::{decrypt, derive_key};
-        use zeroize::Zeroize;
+    /// The recovery words to show to the user.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if the vault already uses a password, or
+    ///   `new_password` is empty
+    /// - `NotPermitted` if `master_key` is not this vault's key
+    pub fn convert_to_password(
+        &mut self,
+        master_key: &MasterKey,
+        new_password: &[u8],
+        new_keyfile: Option<&[u8]>,
+    ) -> Result<Zeroizing<String>> {
+        use axiomvault_crypto::{derive_key_with_keyfile, encrypt};
 
-        let password_kek = derive_key(password, &self.salt, &self.kdf_params)?;
-
-        // First, verify the password by decrypting the verification constant.
-        match decrypt(password_kek.as_bytes(), &self.key_verification) {
-            Ok(mut plaintext) => {
-                let expected = b"AXIOMVAULT_KEY_VERIFICATION_V1";
-                let valid = plaintext.len() == expected.len()
-                    && bool::from(plaintext.as_slice().ct_eq(expected));
-                plaintext.zeroize();
-                if !valid {
-                    return Ok(None);
-                }
-            }
-            Err(_) => return Ok(None),
+        if !self.uses_external_key() {
+            return Err(Error::InvalidInput(
+                "Vault already uses a password".to_string(),
+            ));
         }
-
-        // Password is correct. Now obtain the master key.
-        if let Some(ref wrapped) = self.wrapped_master_key {
-            // v1.1+: unwrap the master key.
-            let master_key = unwrap_key(wrapped, password_kek.as_bytes())?;
-            Ok(Some(master_key))
-        } else {
-            // Legacy v1.0: the KEK *is* the master key.
-            Ok(Some(password_kek))
+        if new_password.is_empty() {
+            return Err(Error::InvalidInput(
+                "New password cannot be empty".to_string(),
+            ));
+        }
+        if !self.verify_external_key(master_key)? {
+            return Err(Error::NotPermitted("Invalid key".to_string()));
         }
+        self.verify_mac(master_key)?;
+
+        let kdf_salt = Salt::generate(SALT_LENGTH);
+        let verify_salt = Salt::generate(SALT_LENGTH);
+        let password_kek =
+            derive_key_with_keyfile(new_password, new_keyfile, &kdf_salt, &self.kdf_params)?;
+        let recovery_key = RecoveryKey::generate();
+
+        self.key_verification =
+            Self::key_verification_for(&self.id, &password_kek, &verify_salt, &self.kdf_params);
+        self.key_verification_algorithm = KeyVerificationAlgorithm::Blake2bTag;
+        self.keyfile_verification = Self::keyfile_verification_for(new_keyfile, &verify_salt)?;
+        self.keyfile_required = new_keyfile.is_some();
+        self.kdf_salt = kdf_salt;
+        self.verify_salt = Some(verify_salt);
+        self.wrapped_master_key = Some(wrap_key(master_key, password_kek.as_bytes())?);
+        self.recovery_wrapped_master_key = Some(wrap_key(master_key, &recovery_key.derive_kek())?);
+        self.recovery_key_verification = Some(create_recovery_verification(&recovery_key)?);
+        self.encrypted_recovery_key =
+            Some(encrypt(master_key.as_bytes(), recovery_key.as_bytes())?);
+        self.key_source = KeySource::Password;
+        self.key_generation += 1;
+        self.modified_at = Utc::now();
+        self.update_mac(master_key)?;
+
+        recovery_key.to_mnemonic()
     }
 
     /// Verify a recovery key and return the master key on success.
@@ -270,12 +1425,30 @@ impl VaultConfig {
     /// This re-wraps the master key with a new password-derived KEK and
     /// updates the password verification data. The recovery key data
     /// remains unchanged.
+    ///
+    /// Recovery bypasses any keyfile: afterwards the vault opens with the
+    /// new password alone. Use
+    /// [`reset_password_with_keyfile`](Self::reset_password_with_keyfile)
+    /// to set a new keyfile at the same time.
     pub fn reset_password(
         &mut self,
         recovery_key: &RecoveryKey,
         new_password: &[u8],
     ) -> Result<()> {
-        use axiomvault_crypto::{derive_key, encrypt};
+        self.reset_password_with_keyfile(recovery_key, new_password, None)
+    }
+
+    /// Reset the password using a recovery key, optionally requiring a new
+    /// keyfile from now on.
+    ///
+    /// The old keyfile (if any) is not needed.
+    pub fn reset_password_with_keyfile(
+        &mut self,
+        recovery_key: &RecoveryKey,
+        new_password: &[u8],
+        new_keyfile: Option<&[u8]>,
+    ) -> Result<()> {
+        use axiomvault_crypto::derive_key_with_keyfile;
 
         if new_password.is_empty() {
             return Err(Error::InvalidInput(
@@ -287,27 +1460,127 @@ impl VaultConfig {
         let master_key = self
             .verify_recovery_key(recovery_key)?
             .ok_or_else(|| Error::NotPermitted("Invalid recovery key".to_string()))?;
+        self.verify_mac(&master_key)?;
 
         // Derive new password KEK.
-        let new_salt = Salt::generate();
-        let new_kek = derive_key(new_password, &new_salt, &self.kdf_params)?;
+        let new_kdf_salt = Salt::generate(SALT_LENGTH);
+        let new_verify_salt = Salt::generate(SALT_LENGTH);
+        let new_kek =
+            derive_key_with_keyfile(new_password, new_keyfile, &new_kdf_salt, &self.kdf_params)?;
+        let keyfile_verification = Self::keyfile_verification_for(new_keyfile, &new_verify_salt)?;
 
         // Re-wrap master key.
         let new_wrapped = wrap_key(&master_key, new_kek.as_bytes())?;
 
         // Re-create password verification.
-        let verification_plaintext = b"AXIOMVAULT_KEY_VERIFICATION_V1";
-        let new_verification = encrypt(new_kek.as_bytes(), verification_plaintext)?;
+        let new_verification =
+            Self::key_verification_for(&self.id, &new_kek, &new_verify_salt, &self.kdf_params);
 
         // Update config.
-        self.salt = new_salt;
+        self.kdf_salt = new_kdf_salt;
+        self.verify_salt = Some(new_verify_salt);
         self.key_verification = new_verification;
+        self.key_verification_algorithm = KeyVerificationAlgorithm::Blake2bTag;
         self.wrapped_master_key = Some(new_wrapped);
+        self.keyfile_required = new_keyfile.is_some();
+        self.keyfile_verification = keyfile_verification;
+        self.key_generation += 1;
         self.modified_at = Utc::now();
+        self.update_mac(&master_key)?;
+
+        Ok(())
+    }
+
+    /// Wrap the master key with a device-held key (e.g. from a biometric
+    /// keystore) so the vault can later be opened without the password.
+    ///
+    /// The blob records the current [`key_generation`](Self::key_generation)
+    /// and binds it, together with the vault id, as associated data.
+    ///
+    /// Layout: version (1) || generation (u64 LE) || nonce || ciphertext || tag.
+    pub fn wrap_key_for_device(
+        &self,
+        master_key: &MasterKey,
+        wrapping_key: &[u8],
+    ) -> Result<Vec<u8>> {
+        Self::check_wrapping_key(wrapping_key)?;
+
+        let mut header = Vec::with_capacity(WRAPPED_KEY_HEADER_LEN);
+        header.push(WRAPPED_KEY_VERSION);
+        header.extend_from_slice(&self.key_generation.to_le_bytes());
+
+        let sealed = axiomvault_crypto::encrypt_with_aad(
+            wrapping_key,
+            master_key.as_bytes(),
+            &self.wrapped_key_aad(self.key_generation),
+        )?;
+        header.extend_from_slice(&sealed);
+        Ok(header)
+    }
+
+    /// Recover the master key from a blob made by
+    /// [`wrap_key_for_device`](Self::wrap_key_for_device).
+    ///
+    /// # Errors
+    /// - `WrappedKeyStale` if the blob was minted for another key generation
+    /// - `Crypto` if the wrapping key is wrong or the blob was tampered with
+    pub fn unwrap_device_key(&self, wrapped: &[u8], wrapping_key: &[u8]) -> Result<MasterKey> {
+        use zeroize::Zeroize;
+
+        Self::check_wrapping_key(wrapping_key)?;
+
+        if wrapped.len() <= WRAPPED_KEY_HEADER_LEN || wrapped[0] != WRAPPED_KEY_VERSION {
+            return Err(Error::InvalidInput(
+                "Unrecognized wrapped key format".to_string(),
+            ));
+        }
+        let mut generation = [0u8; 8];
+        generation.copy_from_slice(&wrapped[1..WRAPPED_KEY_HEADER_LEN]);
+        let generation = u64::from_le_bytes(generation);
+        if generation != self.key_generation {
+            return Err(Error::WrappedKeyStale(format!(
+                "wrapped for key generation {}, vault is at {}",
+                generation, self.key_generation
+            )));
+        }
+
+        let mut plaintext = axiomvault_crypto::decrypt_with_aad(
+            wrapping_key,
+            &wrapped[WRAPPED_KEY_HEADER_LEN..],
+            &self.wrapped_key_aad(generation),
+        )?;
+        if plaintext.len() != KEY_LENGTH {
+            plaintext.zeroize();
+            return Err(Error::Crypto(format!(
+                "Unwrapped key has wrong length: expected {}, got {}",
+                KEY_LENGTH,
+                plaintext.len()
+            )));
+        }
+        let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+        key.copy_from_slice(&plaintext);
+        plaintext.zeroize();
+
+        Ok(MasterKey::from_bytes(*key))
+    }
 
+    fn check_wrapping_key(wrapping_key: &[u8]) -> Result<()> {
+        if wrapping_key.len() != KEY_LENGTH {
+            return Err(Error::InvalidInput(format!(
+                "Wrapping key must be {} bytes, got {}",
+                KEY_LENGTH,
+                wrapping_key.len()
+            )));
+        }
         Ok(())
     }
 
+    fn wrapped_key_aad(&self, generation: u64) -> Vec<u8> {
+        let mut aad = self.id.as_str().as_bytes().to_vec();
+        aad.extend_from_slice(&generation.to_le_bytes());
+        aad
+    }
+
     /// Retrieve the stored recovery key by decrypting with the master key.
     ///
     /// Requires the vault to be unlocked (master key available).
@@ -344,9 +1617,7 @@ impl VaultConfig {
     ///
     /// # Returns
     /// The recovery words to show to the user.
-    pub fn migrate_to_v1_1(&mut self, password: &[u8]) -> Result<Zeroizing<String>> {
-        use axiomvault_crypto::encrypt;
-
+    pub fn migrate_to_v1_1(&mut self, password: &Secret) -> Result<Zeroizing<String>> {
         if !self.is_legacy_format() {
             return Err(Error::Vault("Vault is already in v1.1 format".to_string()));
         }
@@ -357,13 +1628,35 @@ impl VaultConfig {
         let master_key = self
             .verify_password(password)?
             .ok_or_else(|| Error::NotPermitted("Invalid password".to_string()))?;
+        self.wrap_legacy_master_key(&master_key)
+    }
+
+    /// Move a legacy v1.0 vault to the v1.1 key-wrapping format with the
+    /// master key of an unlocked session.
+    ///
+    /// Does what [`migrate_to_v1_1`](Self::migrate_to_v1_1) does, without
+    /// asking for the password again.
+    ///
+    /// # Returns
+    /// The recovery words to show to the user.
+    ///
+    /// # Errors
+    /// - `Vault` if the vault is not in the legacy format
+    /// - `ConfigTampered` if `master_key` does not match the config MAC
+    pub fn wrap_legacy_master_key(&mut self, master_key: &MasterKey) -> Result<Zeroizing<String>> {
+        use axiomvault_crypto::encrypt;
+
+        if !self.is_legacy_format() {
+            return Err(Error::Vault("Vault is already in v1.1 format".to_string()));
+        }
+        self.verify_mac(master_key)?;
 
         // In legacy format, KEK == master key, so wrap with itself.
-        let wrapped_master_key = wrap_key(&master_key, master_key.as_bytes())?;
+        let wrapped_master_key = wrap_key(master_key, master_key.as_bytes())?;
 
         let recovery_key = RecoveryKey::generate();
         let recovery_kek = recovery_key.derive_kek();
-        let recovery_wrapped = wrap_key(&master_key, &recovery_kek)?;
+        let recovery_wrapped = wrap_key(master_key, &recovery_kek)?;
         let recovery_verification = create_recovery_verification(&recovery_key)?;
         let encrypted_recovery_key = encrypt(master_key.as_bytes(), recovery_key.as_bytes())?;
         let recovery_words = recovery_key.to_mnemonic()?;
@@ -374,10 +1667,45 @@ impl VaultConfig {
         self.recovery_key_verification = Some(recovery_verification);
         self.encrypted_recovery_key = Some(encrypted_recovery_key);
         self.modified_at = Utc::now();
+        self.update_mac(master_key)?;
 
         Ok(recovery_words)
     }
 
+    /// Undo [`wrap_legacy_master_key`](Self::wrap_legacy_master_key),
+    /// returning the vault to the legacy v1.0 format.
+    ///
+    /// Only possible while the password KEK is still the master key, i.e.
+    /// before the password or keyfile was changed. The recovery key minted
+    /// by the migration stops working.
+    ///
+    /// # Errors
+    /// - `Vault` if the vault is already in the legacy format
+    /// - `NotPermitted` if the master key is no longer wrapped under itself
+    /// - `ConfigTampered` if `master_key` does not match the config MAC
+    pub fn restore_legacy_format(&mut self, master_key: &MasterKey) -> Result<()> {
+        let Some(ref wrapped) = self.wrapped_master_key else {
+            return Err(Error::Vault("Vault is already in v1.0 format".to_string()));
+        };
+        self.refuse_external_key()?;
+        self.verify_mac(master_key)?;
+        let self_wrapped = unwrap_key(wrapped, master_key.as_bytes())
+            .is_ok_and(|unwrapped| bool::from(unwrapped.as_bytes().ct_eq(master_key.as_bytes())));
+        if !self_wrapped {
+            return Err(Error::NotPermitted(
+                "The password changed since the vault left the v1.0 format".to_string(),
+            ));
+        }
+
+        self.version = VaultVersion::LEGACY;
+        self.wrapped_master_key = None;
+        self.recovery_wrapped_master_key = None;
+        self.recovery_key_verification = None;
+        self.encrypted_recovery_key = None;
+        self.modified_at = Utc::now();
+        self.update_mac(master_key)
+    }
+
     /// Serialize configuration to JSON.
     pub fn to_json(&self) -> Result<String> {
         serde_json::to_string_pretty(self).map_err(|e| Error::Serialization(e.to_string()))
@@ -443,8 +1771,14 @@ mod tests {
         assert!(config.recovery_wrapped_master_key.is_some());
         assert!(!config.is_legacy_format());
 
-        assert!(config.verify_password(password).unwrap().is_some());
-        assert!(config.verify_password(b"wrong-password").unwrap().is_none());
+        assert!(config
+            .verify_password(&Secret::from_slice(password))
+            .unwrap()
+            .is_some());
+        assert!(config
+            .verify_password(&Secret::from_slice(b"wrong-password"))
+            .unwrap()
+            .is_none());
     }
 
     #[test]
@@ -464,11 +1798,59 @@ mod tests {
         let new_password = b"new-password";
         config.reset_password(&rk, new_password).unwrap();
 
-        assert!(config.verify_password(password).unwrap().is_none());
-        assert!(config.verify_password(new_password).unwrap().is_some());
+        assert!(config
+            .verify_password(&Secret::from_slice(password))
+            .unwrap()
+            .is_none());
+        assert!(config
+            .verify_password(&Secret::from_slice(new_password))
+            .unwrap()
+            .is_some());
         assert!(config.verify_recovery_key(&rk).unwrap().is_some());
     }
 
+    #[test]
+    fn test_device_wrapped_key_is_bound_to_generation() {
+        let id = VaultId::new("test-vault").unwrap();
+        let creation = VaultConfig::new(
+            id,
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let mut config = creation.config;
+        let master_key = creation.master_key;
+        let wrapping_key = [7u8; KEY_LENGTH];
+
+        let wrapped = config
+            .wrap_key_for_device(&master_key, &wrapping_key)
+            .unwrap();
+        let unwrapped = config.unwrap_device_key(&wrapped, &wrapping_key).unwrap();
+        assert_eq!(unwrapped.as_bytes(), master_key.as_bytes());
+        assert!(matches!(
+            config.unwrap_device_key(&wrapped, &[8u8; KEY_LENGTH]),
+            Err(Error::Crypto(_))
+        ));
+
+        let rk = RecoveryKey::from_mnemonic(&creation.recovery_words).unwrap();
+        config.reset_password(&rk, b"new-password").unwrap();
+        assert_eq!(config.key_generation, 1);
+        assert!(matches!(
+            config.unwrap_device_key(&wrapped, &wrapping_key),
+            Err(Error::WrappedKeyStale(_))
+        ));
+
+        // Forging the generation byte does not get past the AEAD.
+        let mut forged = wrapped.clone();
+        forged[1..WRAPPED_KEY_HEADER_LEN].copy_from_slice(&1u64.to_le_bytes());
+        assert!(matches!(
+            config.unwrap_device_key(&forged, &wrapping_key),
+            Err(Error::Crypto(_))
+        ));
+    }
+
     #[test]
     fn test_decrypt_recovery_key() {
         let id = VaultId::new("test-vault").unwrap();
@@ -480,7 +1862,10 @@ mod tests {
         let config = creation.config;
         let recovery_words = creation.recovery_words;
 
-        let master_key = config.verify_password(password).unwrap().unwrap();
+        let master_key = config
+            .verify_password(&Secret::from_slice(password))
+            .unwrap()
+            .unwrap();
         let decrypted_rk = config.decrypt_recovery_key(&master_key).unwrap();
         let decrypted_words = decrypted_rk.to_mnemonic().unwrap();
 
@@ -498,13 +1883,173 @@ mod tests {
         let config = creation.config;
         let recovery_words = creation.recovery_words;
 
-        let mk_from_password = config.verify_password(password).unwrap().unwrap();
+        let mk_from_password = config
+            .verify_password(&Secret::from_slice(password))
+            .unwrap()
+            .unwrap();
         let rk = RecoveryKey::from_mnemonic(&recovery_words).unwrap();
         let mk_from_recovery = config.verify_recovery_key(&rk).unwrap().unwrap();
 
         assert_eq!(mk_from_password.as_bytes(), mk_from_recovery.as_bytes());
     }
 
+    #[test]
+    fn test_keyfile_required_and_checked() {
+        let id = VaultId::new("test-vault").unwrap();
+        let password = b"password";
+        let keyfile = b"keyfile-content";
+        let params = KdfParams::moderate();
+
+        let creation = VaultConfig::new_with_keyfile(
+            id,
+            password,
+            Some(keyfile),
+            "memory",
+            serde_json::Value::Null,
+            params,
+        )
+        .unwrap();
+        let config = VaultConfig::from_json(&creation.config.to_json().unwrap()).unwrap();
+        assert!(config.requires_keyfile());
+
+        assert!(matches!(
+            config.verify_password(&Secret::from_slice(password)),
+            Err(Error::KeyfileRequired)
+        ));
+        assert!(matches!(
+            config.verify_password_with_keyfile(
+                &Secret::from_slice(password),
+                Some(b"other-keyfile")
+            ),
+            Err(Error::KeyfileMismatch)
+        ));
+        assert!(config
+            .verify_password_with_keyfile(&Secret::from_slice(b"wrong"), Some(keyfile))
+            .unwrap()
+            .is_none());
+
+        let master_key = config
+            .verify_password_with_keyfile(&Secret::from_slice(password), Some(keyfile))
+            .unwrap()
+            .unwrap();
+        assert_eq!(master_key.as_bytes(), creation.master_key.as_bytes());
+    }
+
+    #[test]
+    fn test_recovery_bypasses_keyfile() {
+        let id = VaultId::new("test-vault").unwrap();
+        let params = KdfParams::moderate();
+
+        let creation = VaultConfig::new_with_keyfile(
+            id,
+            b"password",
+            Some(b"lost-keyfile"),
+            "memory",
+            serde_json::Value::Null,
+            params,
+        )
+        .unwrap();
+        let mut config = creation.config;
+
+        let rk = RecoveryKey::from_mnemonic(&creation.recovery_words).unwrap();
+        let recovered = config.verify_recovery_key(&rk).unwrap().unwrap();
+        assert_eq!(recovered.as_bytes(), creation.master_key.as_bytes());
+
+        config.reset_password(&rk, b"new-password").unwrap();
+        assert!(!config.requires_keyfile());
+        assert!(config
+            .verify_password(&Secret::from_slice(b"new-password"))
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_verification_blob_cannot_be_transplanted() {
+        let params = KdfParams::moderate();
+        let config_a = VaultConfig::new(
+            VaultId::new("vault-a").unwrap(),
+            b"password-a",
+            "memory",
+            serde_json::Value::Null,
+            params.clone(),
+        )
+        .unwrap()
+        .config;
+        let mut config_b = VaultConfig::new(
+            VaultId::new("vault-b").unwrap(),
+            b"password-b",
+            "memory",
+            serde_json::Value::Null,
+            params,
+        )
+        .unwrap()
+        .config;
+        assert_eq!(
+            config_b.key_verification_algorithm,
+            KeyVerificationAlgorithm::Blake2bTag
+        );
+
+        // Only the verification blob moved: A's password still fails.
+        config_b.key_verification = config_a.key_verification.clone();
+        assert!(config_b
+            .verify_password(&Secret::from_slice(b"password-a"))
+            .unwrap()
+            .is_none());
+        assert!(config_b
+            .verify_password(&Secret::from_slice(b"password-b"))
+            .unwrap()
+            .is_none());
+
+        // Even with A's salt and params, the tag is bound to A's vault id.
+        config_b.kdf_salt = config_a.kdf_salt.clone();
+        config_b.verify_salt = config_a.verify_salt.clone();
+        config_b.kdf_params = config_a.kdf_params.clone();
+        assert!(config_b
+            .verify_password(&Secret::from_slice(b"password-a"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_reset_password_upgrades_legacy_verification() {
+        let id = VaultId::new("test-vault").unwrap();
+        let creation = VaultConfig::new(
+            id,
+            b"old-password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let mut config = creation.config;
+
+        // Rewrite verification data in the pre-tag AEAD format.
+        let kek =
+            axiomvault_crypto::derive_key(b"old-password", &config.kdf_salt, &config.kdf_params)
+                .unwrap();
+        config.key_verification =
+            axiomvault_crypto::encrypt(kek.as_bytes(), LEGACY_VERIFICATION_PLAINTEXT).unwrap();
+        config.key_verification_algorithm = KeyVerificationAlgorithm::AeadConstant;
+        // Configs that old predate the salt split and the MAC as well.
+        config.verify_salt = None;
+        config.config_mac = None;
+        assert!(config
+            .verify_password(&Secret::from_slice(b"old-password"))
+            .unwrap()
+            .is_some());
+
+        let recovery = RecoveryKey::from_mnemonic(&creation.recovery_words).unwrap();
+        config.reset_password(&recovery, b"new-password").unwrap();
+        assert_eq!(
+            config.key_verification_algorithm,
+            KeyVerificationAlgorithm::Blake2bTag
+        );
+        assert!(config
+            .verify_password(&Secret::from_slice(b"new-password"))
+            .unwrap()
+            .is_some());
+    }
+
     #[test]
     fn test_config_serialization() {
         let id = VaultId::new("test-vault").unwrap();
@@ -527,7 +2072,10 @@ mod tests {
         assert_eq!(restored.id.as_str(), config.id.as_str());
         assert_eq!(restored.provider_type, config.provider_type);
         assert!(restored.wrapped_master_key.is_some());
-        assert!(restored.verify_password(password).unwrap().is_some());
+        assert!(restored
+            .verify_password(&Secret::from_slice(password))
+            .unwrap()
+            .is_some());
     }
 
     #[test]
@@ -535,7 +2083,7 @@ mod tests {
         let id = VaultId::new("legacy").unwrap();
         let password = b"password";
         let params = KdfParams::moderate();
-        let salt = Salt::generate();
+        let salt = Salt::generate(SALT_LENGTH);
 
         let master_key = axiomvault_crypto::derive_key(password, &salt, &params).unwrap();
         let verification_plaintext = b"AXIOMVAULT_KEY_VERIFICATION_V1";
@@ -545,22 +2093,48 @@ mod tests {
         let config = VaultConfig {
             id,
             version: VaultVersion { major: 1, minor: 0 },
-            salt,
+            kdf_salt: salt,
+            verify_salt: None,
             kdf_params: params,
             provider_type: "memory".to_string(),
             provider_config: serde_json::Value::Null,
             created_at: Utc::now(),
             modified_at: Utc::now(),
             key_verification,
+            key_verification_algorithm: KeyVerificationAlgorithm::AeadConstant,
             wrapped_master_key: None,
             recovery_wrapped_master_key: None,
             recovery_key_verification: None,
             encrypted_recovery_key: None,
+            keyfile_required: false,
+            keyfile_verification: None,
+            key_source: KeySource::Password,
+            default_policy: EffectivePolicy::default(),
+            key_generation: 0,
+            data_layout: DataLayout::Flat,
+            name_limit: None,
+            cipher_suite: CipherSuite::default(),
+            reader_suites: Vec::new(),
+            attachments: Vec::new(),
+            display_name: String::new(),
+            description: None,
+            icon: None,
+            color: None,
+            metadata_revision: 0,
+            track_access_time: false,
+            migrations: Vec::new(),
+            config_mac: None,
         };
 
         assert!(config.is_legacy_format());
-        assert!(config.verify_password(password).unwrap().is_some());
-        assert!(config.verify_password(b"wrong").unwrap().is_none());
+        assert!(config
+            .verify_password(&Secret::from_slice(password))
+            .unwrap()
+            .is_some());
+        assert!(config
+            .verify_password(&Secret::from_slice(b"wrong"))
+            .unwrap()
+            .is_none());
     }
 
     #[test]
@@ -568,7 +2142,7 @@ mod tests {
         let id = VaultId::new("legacy").unwrap();
         let password = b"password";
         let params = KdfParams::moderate();
-        let salt = Salt::generate();
+        let salt = Salt::generate(SALT_LENGTH);
 
         let master_key = axiomvault_crypto::derive_key(password, &salt, &params).unwrap();
         let verification_plaintext = b"AXIOMVAULT_KEY_VERIFICATION_V1";
@@ -578,28 +2152,576 @@ mod tests {
         let mut config = VaultConfig {
             id,
             version: VaultVersion { major: 1, minor: 0 },
-            salt,
+            kdf_salt: salt,
+            verify_salt: None,
             kdf_params: params,
             provider_type: "memory".to_string(),
             provider_config: serde_json::Value::Null,
             created_at: Utc::now(),
             modified_at: Utc::now(),
             key_verification,
+            key_verification_algorithm: KeyVerificationAlgorithm::AeadConstant,
             wrapped_master_key: None,
             recovery_wrapped_master_key: None,
             recovery_key_verification: None,
             encrypted_recovery_key: None,
+            keyfile_required: false,
+            keyfile_verification: None,
+            key_source: KeySource::Password,
+            default_policy: EffectivePolicy::default(),
+            key_generation: 0,
+            data_layout: DataLayout::Flat,
+            name_limit: None,
+            cipher_suite: CipherSuite::default(),
+            reader_suites: Vec::new(),
+            attachments: Vec::new(),
+            display_name: String::new(),
+            description: None,
+            icon: None,
+            color: None,
+            metadata_revision: 0,
+            track_access_time: false,
+            migrations: Vec::new(),
+            config_mac: None,
         };
 
-        let recovery_words = config.migrate_to_v1_1(password).unwrap();
+        let recovery_words = config
+            .migrate_to_v1_1(&Secret::from_slice(password))
+            .unwrap();
         assert_eq!(recovery_words.split_whitespace().count(), 24);
         assert!(!config.is_legacy_format());
 
-        let mk_after = config.verify_password(password).unwrap().unwrap();
+        let mk_after = config
+            .verify_password(&Secret::from_slice(password))
+            .unwrap()
+            .unwrap();
         assert_eq!(master_key.as_bytes(), mk_after.as_bytes());
 
         let rk = RecoveryKey::from_mnemonic(&recovery_words).unwrap();
         let mk_from_recovery = config.verify_recovery_key(&rk).unwrap().unwrap();
         assert_eq!(master_key.as_bytes(), mk_from_recovery.as_bytes());
     }
+
+    /// A named mutation of one config field.
+    type Tamper = (&'static str, fn(&mut VaultConfig));
+
+    /// Unlock as the session does: password check, then MAC.
+    fn unlock(config: &VaultConfig, password: &[u8]) -> Result<MasterKey> {
+        let master_key = config
+            .verify_password(&Secret::from_slice(password))?
+            .ok_or_else(|| Error::NotPermitted("Invalid password".to_string()))?;
+        config.verify_mac(&master_key)?;
+        Ok(master_key)
+    }
+
+    #[test]
+    fn test_config_mac_detects_tampering_of_each_field() {
+        let creation = VaultConfig::new(
+            VaultId::new("mac-vault").unwrap(),
+            b"password",
+            "local",
+            serde_json::json!({"root": "/vaults/mine"}),
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let config = VaultConfig::from_json(&creation.config.to_json().unwrap()).unwrap();
+        let master_key = creation.master_key;
+        assert!(config.config_mac.is_some());
+        config.verify_mac(&master_key).unwrap();
+
+        // Fields the password check does not cover: only the MAC notices.
+        let mac_only: Vec<Tamper> = vec![
+            ("version", |c| c.version.minor += 1),
+            ("provider_type", |c| c.provider_type = "gdrive".to_string()),
+            ("provider_config", |c| {
+                c.provider_config = serde_json::json!({"root": "/attacker"})
+            }),
+            ("recovery_wrapped_master_key", |c| {
+                c.recovery_wrapped_master_key.as_mut().unwrap()[0] ^= 1
+            }),
+            ("recovery_key_verification", |c| {
+                c.recovery_key_verification = None
+            }),
+            ("encrypted_recovery_key", |c| {
+                c.encrypted_recovery_key.as_mut().unwrap()[0] ^= 1
+            }),
+            ("keyfile_verification", |c| {
+                c.keyfile_verification = Some(vec![0; 32])
+            }),
+            ("default_policy", |c| {
+                c.default_policy.versioning = crate::policy::Versioning::Keep(9)
+            }),
+            ("key_generation", |c| c.key_generation += 1),
+            ("key_source", |c| c.key_source = KeySource::External),
+        ];
+        for (field, tamper) in mac_only {
+            let mut tampered = config.clone();
+            tamper(&mut tampered);
+            assert!(
+                matches!(tampered.verify_mac(&master_key), Err(Error::ConfigTampered)),
+                "tampering with {} went unnoticed",
+                field
+            );
+        }
+
+        // Fields bound into the password check refuse to unlock before the
+        // MAC is even reached.
+        let bound: Vec<Tamper> = vec![
+            ("id", |c| c.id = VaultId::new("other-vault").unwrap()),
+            ("kdf_salt", |c| c.kdf_salt = Salt::generate(SALT_LENGTH)),
+            ("verify_salt", |c| {
+                c.verify_salt = Some(Salt::generate(SALT_LENGTH))
+            }),
+            ("kdf_params", |c| c.kdf_params = KdfParams::interactive()),
+            ("key_verification", |c| c.key_verification[0] ^= 1),
+            ("key_verification_algorithm", |c| {
+                c.key_verification_algorithm = KeyVerificationAlgorithm::AeadConstant
+            }),
+            ("wrapped_master_key", |c| {
+                c.wrapped_master_key.as_mut().unwrap()[0] ^= 1
+            }),
+            ("keyfile_required", |c| c.keyfile_required = true),
+        ];
+        for (field, tamper) in bound {
+            let mut tampered = config.clone();
+            tamper(&mut tampered);
+            assert!(
+                unlock(&tampered, b"password").is_err(),
+                "tampering with {} went unnoticed",
+                field
+            );
+        }
+
+        // Timestamps are not protected.
+        let mut touched = config.clone();
+        touched.modified_at = Utc::now();
+        touched.verify_mac(&master_key).unwrap();
+    }
+
+    #[test]
+    fn test_wrong_password_is_not_reported_as_tampering() {
+        let mut config = VaultConfig::new(
+            VaultId::new("mac-vault").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap()
+        .config;
+        config.provider_type = "gdrive".to_string();
+
+        assert!(matches!(
+            unlock(&config, b"wrong-password"),
+            Err(Error::NotPermitted(_))
+        ));
+        assert!(matches!(
+            unlock(&config, b"password"),
+            Err(Error::ConfigTampered)
+        ));
+    }
+
+    /// Rewrite a config as written before the MAC: one salt, AEAD
+    /// verification and no MAC.
+    fn to_pre_mac(mut config: VaultConfig, password: &[u8]) -> VaultConfig {
+        let kek =
+            axiomvault_crypto::derive_key(password, &config.kdf_salt, &config.kdf_params).unwrap();
+        config.verify_salt = None;
+        config.key_verification =
+            axiomvault_crypto::encrypt(kek.as_bytes(), LEGACY_VERIFICATION_PLAINTEXT).unwrap();
+        config.key_verification_algorithm = KeyVerificationAlgorithm::AeadConstant;
+        config.config_mac = None;
+        config
+    }
+
+    #[test]
+    fn test_config_without_mac_is_accepted_and_sealed_on_save() {
+        let creation = VaultConfig::new(
+            VaultId::new("mac-vault").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let config = to_pre_mac(creation.config, b"password");
+        let master_key = unlock(&config, b"password").unwrap();
+
+        let sealed =
+            VaultConfig::from_bytes(&config.to_sealed_bytes(&master_key).unwrap()).unwrap();
+        assert!(sealed.config_mac.is_some());
+        sealed.verify_mac(&master_key).unwrap();
+
+        let mut stripped = sealed.clone();
+        stripped.config_mac = Some(vec![0; 32]);
+        assert!(matches!(
+            stripped.verify_mac(&master_key),
+            Err(Error::ConfigTampered)
+        ));
+    }
+
+    #[test]
+    fn test_stripped_mac_is_rejected() {
+        let creation = VaultConfig::new(
+            VaultId::new("mac-vault").unwrap(),
+            b"password",
+            "local",
+            serde_json::json!({"root": "/vaults/mine"}),
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let master_key = creation.master_key;
+
+        // Strip the MAC and redirect the provider.
+        let mut config = creation.config.clone();
+        config.config_mac = None;
+        config.provider_config = serde_json::json!({"root": "/attacker"});
+        assert!(matches!(
+            unlock(&config, b"password"),
+            Err(Error::ConfigTampered)
+        ));
+
+        // Each mark of a config written with a MAC is enough on its own.
+        let pre_mac = to_pre_mac(creation.config, b"password");
+        unlock(&pre_mac, b"password").unwrap();
+        let marks: Vec<Tamper> = vec![
+            ("verify_salt", |c| {
+                c.verify_salt = Some(Salt::generate(SALT_LENGTH))
+            }),
+            ("key_verification_algorithm", |c| {
+                c.key_verification_algorithm = KeyVerificationAlgorithm::Blake2bTag
+            }),
+            ("key_generation", |c| c.key_generation = 1),
+            ("migrations", |c| {
+                c.migrations.push(MigrationRecord {
+                    id: CONFIG_MAC_MIGRATION.to_string(),
+                    started_at: Utc::now(),
+                    finished_at: Some(Utc::now()),
+                    outcome: crate::migrations::MigrationOutcome::Applied,
+                })
+            }),
+        ];
+        for (field, mark) in marks {
+            let mut tampered = pre_mac.clone();
+            mark(&mut tampered);
+            tampered.provider_type = "gdrive".to_string();
+            assert!(
+                matches!(tampered.verify_mac(&master_key), Err(Error::ConfigTampered)),
+                "stripped MAC with {} went unnoticed",
+                field
+            );
+        }
+    }
+
+    #[test]
+    fn test_password_reset_keeps_mac_valid() {
+        let creation = VaultConfig::new(
+            VaultId::new("mac-vault").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let mut config = creation.config;
+        let rk = RecoveryKey::from_mnemonic(&creation.recovery_words).unwrap();
+
+        config.reset_password(&rk, b"new-password").unwrap();
+        unlock(&config, b"new-password").unwrap();
+
+        config.provider_type = "gdrive".to_string();
+        assert!(matches!(
+            config.reset_password(&rk, b"newer-password"),
+            Err(Error::ConfigTampered)
+        ));
+    }
+
+    /// Rewrite a config as written before the salt split: one `salt`
+    /// binding the KDF, the verification tag and the keyfile identifier.
+    fn to_single_salt_json(
+        mut config: VaultConfig,
+        password: &[u8],
+        keyfile: Option<&[u8]>,
+        master_key: &MasterKey,
+    ) -> String {
+        let kek = axiomvault_crypto::derive_key_with_keyfile(
+            password,
+            keyfile,
+            &config.kdf_salt,
+            &config.kdf_params,
+        )
+        .unwrap();
+        config.verify_salt = None;
+        config.key_verification = VaultConfig::key_verification_for(
+            &config.id,
+            &kek,
+            &config.kdf_salt,
+            &config.kdf_params,
+        );
+        config.keyfile_verification =
+            VaultConfig::keyfile_verification_for(keyfile, &config.kdf_salt).unwrap();
+        config.update_mac(master_key).unwrap();
+
+        let mut json = serde_json::to_value(&config).unwrap();
+        let object = json.as_object_mut().unwrap();
+        let salt = object.remove("kdf_salt").unwrap();
+        object.insert("salt".to_string(), salt);
+        assert!(!object.contains_key("verify_salt"));
+        json.to_string()
+    }
+
+    #[test]
+    fn test_single_salt_config_opens_and_splits() {
+        let keyfile: &[u8] = b"keyfile content";
+        let creation = VaultConfig::new_with_keyfile(
+            VaultId::new("single-salt").unwrap(),
+            b"password",
+            Some(keyfile),
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let master_key = creation.master_key;
+        let json = to_single_salt_json(creation.config, b"password", Some(keyfile), &master_key);
+
+        let mut config = VaultConfig::from_json(&json).unwrap();
+        assert!(config.has_single_salt());
+        let kdf_salt = config.kdf_salt.clone();
+        assert_eq!(config.verify_salt(), &kdf_salt);
+        config.verify_mac(&master_key).unwrap();
+
+        // A wrong password leaves the config alone.
+        assert!(config
+            .unlock_and_split_salts(&Secret::from_slice(b"wrong"), Some(keyfile))
+            .unwrap()
+            .is_none());
+        assert!(config.has_single_salt());
+
+        let unlocked = config
+            .unlock_and_split_salts(&Secret::from_slice(b"password"), Some(keyfile))
+            .unwrap()
+            .unwrap();
+        assert_eq!(unlocked.as_bytes(), master_key.as_bytes());
+        assert_eq!(config.kdf_salt, kdf_salt);
+        let verify_salt = config.verify_salt.clone().unwrap();
+        assert_ne!(verify_salt, kdf_salt);
+        assert_eq!(verify_salt, VaultConfig::derive_verify_salt(&kdf_salt));
+
+        // The split config round-trips and is covered by the MAC.
+        let restored = VaultConfig::from_json(&config.to_json().unwrap()).unwrap();
+        assert_eq!(restored.verify_salt.as_ref(), Some(&verify_salt));
+        restored.verify_mac(&master_key).unwrap();
+        assert!(restored
+            .verify_password_with_keyfile(&Secret::from_slice(b"password"), Some(keyfile))
+            .unwrap()
+            .is_some());
+        assert!(matches!(
+            restored.check_keyfile(Some(b"other keyfile")),
+            Err(Error::KeyfileMismatch)
+        ));
+
+        // Splitting again is a no-op.
+        let mut again = restored.clone();
+        assert!(!again.split_salts(&master_key, None, &master_key).unwrap());
+        assert_eq!(again.key_verification, restored.key_verification);
+    }
+
+    #[test]
+    fn test_cipher_suite_settings_are_sealed() {
+        let creation = VaultConfig::new(
+            VaultId::new("suites").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let mut config = creation.config;
+        let master_key = unlock(&config, b"password").unwrap();
+        assert_eq!(
+            config.write_suite().unwrap(),
+            CipherSuite::XChaCha20Poly1305
+        );
+        let json = config.to_json().unwrap();
+        assert!(!json.contains("cipher_suite") && !json.contains("reader_suites"));
+
+        // Readers must be known to support a suite before it is written.
+        let suite = CipherSuite::Aes256GcmSiv;
+        assert!(!config.readers_support(suite));
+        assert!(config.set_cipher_suite(suite, &master_key).is_err());
+        assert_eq!(config.cipher_suite, CipherSuite::XChaCha20Poly1305);
+        config.allow_reader_suite(suite, &master_key).unwrap();
+        assert!(config.readers_support(suite));
+        assert_eq!(
+            config.set_cipher_suite(suite, &master_key).is_ok(),
+            suite.is_available()
+        );
+
+        let loaded = VaultConfig::from_bytes(&config.to_bytes().unwrap()).unwrap();
+        loaded.verify_mac(&master_key).unwrap();
+        assert_eq!(loaded.reader_suites, vec![suite]);
+
+        // Dropping a suite without re-sealing is caught.
+        let mut downgraded = loaded;
+        downgraded.reader_suites.clear();
+        downgraded.cipher_suite = CipherSuite::XChaCha20Poly1305;
+        assert!(downgraded.verify_mac(&master_key).is_err());
+    }
+
+    #[test]
+    fn test_attachments_route_subtrees_and_are_sealed() {
+        let creation = VaultConfig::new(
+            VaultId::new("attached").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let mut config = creation.config;
+        let master_key = unlock(&config, b"password").unwrap();
+        assert!(!config.to_json().unwrap().contains("attachments"));
+
+        let point = |path: &str| AttachPoint {
+            vault_path: VaultPath::parse(path).unwrap(),
+            provider_type: "memory".to_string(),
+            provider_config: serde_json::Value::Null,
+        };
+        config
+            .add_attachment(point("/archive"), &master_key)
+            .unwrap();
+        assert!(matches!(
+            config.add_attachment(point("/archive"), &master_key),
+            Err(Error::AlreadyExists(_))
+        ));
+        for overlapping in ["/", "/archive/2024"] {
+            assert!(matches!(
+                config.add_attachment(point(overlapping), &master_key),
+                Err(Error::InvalidInput(_))
+            ));
+        }
+
+        let routed = |path: &str| {
+            config
+                .attachment_for(&VaultPath::parse(path).unwrap())
+                .map(|point| point.vault_path.to_string())
+        };
+        assert_eq!(routed("/archive/2024/a.txt").as_deref(), Some("/archive"));
+        assert_eq!(routed("/archive").as_deref(), Some("/archive"));
+        assert_eq!(routed("/archived.txt"), None);
+        assert_eq!(routed("/docs/a.txt"), None);
+
+        let loaded = VaultConfig::from_bytes(&config.to_bytes().unwrap()).unwrap();
+        loaded.verify_mac(&master_key).unwrap();
+        assert_eq!(loaded.attachments, config.attachments);
+
+        // Redirecting blobs without re-sealing is caught.
+        let mut redirected = loaded;
+        redirected.attachments[0].provider_type = "local".to_string();
+        assert!(redirected.verify_mac(&master_key).is_err());
+
+        config
+            .remove_attachment(&VaultPath::parse("/archive").unwrap(), &master_key)
+            .unwrap();
+        assert!(config.attachments.is_empty());
+        config.verify_mac(&master_key).unwrap();
+        assert!(matches!(
+            config.remove_attachment(&VaultPath::parse("/archive").unwrap(), &master_key),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_metadata_round_trip_and_validation() {
+        let creation = VaultConfig::new(
+            VaultId::new("named-vault").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let mut config = creation.config;
+        let master_key = unlock(&config, b"password").unwrap();
+        assert_eq!(config.display_name(), "named-vault");
+        assert_eq!(config.metadata_revision, 0);
+
+        config
+            .set_metadata(
+                VaultMetadata {
+                    display_name: "  Work  ".to_string(),
+                    description: Some("Contracts".to_string()),
+                    icon: Some(" ".to_string()),
+                    color: Some("#1a2b3c".to_string()),
+                },
+                &master_key,
+            )
+            .unwrap();
+        assert_eq!(config.metadata_revision, 1);
+
+        let loaded = VaultConfig::from_bytes(&config.to_bytes().unwrap()).unwrap();
+        loaded.verify_mac(&master_key).unwrap();
+        let metadata = loaded.metadata();
+        assert_eq!(metadata.display_name, "Work");
+        assert_eq!(metadata.description.as_deref(), Some("Contracts"));
+        assert_eq!(metadata.icon, None);
+        assert_eq!(metadata.color.as_deref(), Some("#1a2b3c"));
+
+        let mut tampered = loaded.clone();
+        tampered.display_name = "Other".to_string();
+        assert!(matches!(
+            tampered.verify_mac(&master_key),
+            Err(Error::ConfigTampered)
+        ));
+
+        for bad in [
+            VaultMetadata::default(),
+            VaultMetadata {
+                display_name: "x".repeat(MAX_DISPLAY_NAME_CHARS + 1),
+                ..Default::default()
+            },
+            VaultMetadata {
+                display_name: "tab\there".to_string(),
+                ..Default::default()
+            },
+            VaultMetadata {
+                display_name: "Work".to_string(),
+                color: Some("red".to_string()),
+                ..Default::default()
+            },
+        ] {
+            assert!(matches!(
+                config.set_metadata(bad, &master_key),
+                Err(Error::InvalidInput(_))
+            ));
+        }
+        assert_eq!(config.metadata_revision, 1);
+    }
+
+    #[test]
+    fn test_config_without_metadata_falls_back_to_id() {
+        let creation = VaultConfig::new(
+            VaultId::new("legacy-names").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let master_key = unlock(&creation.config, b"password").unwrap();
+        let bytes = creation.config.to_sealed_bytes(&master_key).unwrap();
+
+        // Configs written before display metadata existed lack every field.
+        let mut json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        json.as_object_mut().unwrap().remove("metadata_revision");
+        for field in ["display_name", "description", "icon", "color"] {
+            assert!(json.get(field).is_none(), "{} should be omitted", field);
+        }
+
+        let loaded = VaultConfig::from_bytes(&serde_json::to_vec(&json).unwrap()).unwrap();
+        loaded.verify_mac(&master_key).unwrap();
+        assert_eq!(loaded.display_name(), "legacy-names");
+        assert_eq!(loaded.metadata().description, None);
+    }
 }
@@ -0,0 +1,576 @@
+//! Comparison of a vault with another vault or a local directory.
+//!
+//! [`VaultOperations::diff_against`] walks both sides and sorts every path
+//! that differs into a [`DiffKind`]. Trees store no content hashes, so
+//! files of equal size are compared by keyed Blake2b hashes computed while
+//! each side is streamed through the hasher. The key is made for the one
+//! comparison: neither side is held in memory, and the hashes mean nothing
+//! outside the run. [`DiffOptions::fast`] skips the content reads.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use blake2::digest::consts::U32;
+use blake2::digest::Mac;
+use blake2::Blake2bMac;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::operations::VaultOperations;
+use crate::session::VaultSession;
+use crate::tree::{NodePermissions, TreeNode};
+use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_crypto::FileKey;
+
+/// What a vault is compared with.
+#[derive(Clone, Copy)]
+pub enum DiffTarget<'a> {
+    /// Another open vault.
+    Vault(&'a VaultSession),
+    /// A directory of plaintext files, e.g. an export or a restore.
+    LocalDir(&'a Path),
+}
+
+/// Options for [`VaultOperations::diff_against`].
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Compare sizes and etags only, without reading any content. Etags
+    /// are assigned per write, so between independently written vaults
+    /// this reports every file; a local directory has no etags, so only
+    /// sizes are compared against one.
+    pub fast: bool,
+    /// Do not report files that differ only in modification time.
+    pub ignore_timestamps: bool,
+    /// Subtrees left out on both sides.
+    pub ignore: Vec<VaultPath>,
+}
+
+/// How a path differs between the vault (A) and the target (B).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    /// Present only in the vault. Below a directory reported here, nothing
+    /// further is listed.
+    OnlyInA,
+    /// Present only in the target.
+    OnlyInB,
+    /// File content differs, or a file on one side is a directory on the
+    /// other.
+    ContentDiffers,
+    /// Same content, different modification time or permissions.
+    MetadataDiffers,
+}
+
+/// A path reported by a comparison.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry {
+    /// Path relative to the vault root (and the directory, for a local
+    /// target).
+    pub path: VaultPath,
+    /// How it differs.
+    pub kind: DiffKind,
+    /// Whether the entry is a directory (on the side that has one).
+    pub is_directory: bool,
+}
+
+/// Result of [`VaultOperations::diff_against`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffReport {
+    /// Differences, sorted by path.
+    pub entries: Vec<DiffEntry>,
+    /// Paths present on both sides.
+    pub compared: usize,
+    /// Plaintext bytes hashed across both sides.
+    pub bytes_hashed: u64,
+}
+
+impl DiffReport {
+    /// Whether the two sides matched.
+    pub fn is_identical(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Paths reported as `kind`.
+    pub fn paths(&self, kind: DiffKind) -> Vec<&VaultPath> {
+        self.entries
+            .iter()
+            .filter(|e| e.kind == kind)
+            .map(|e| &e.path)
+            .collect()
+    }
+}
+
+/// One side's view of a path.
+struct Node {
+    is_directory: bool,
+    size: u64,
+    modified_at: DateTime<Utc>,
+    etag: Option<String>,
+    /// Mount permissions; local files have none to compare.
+    permissions: Option<NodePermissions>,
+}
+
+/// Everything below the root, keyed by path components so that a
+/// directory sorts directly before its contents.
+type Snapshot = BTreeMap<Vec<String>, Node>;
+
+/// The side a vault is compared with, ready to read.
+enum Other<'a> {
+    Vault(VaultOperations<'a>),
+    LocalDir(&'a Path),
+}
+
+pub(crate) async fn diff_against(
+    ops: &VaultOperations<'_>,
+    session: &VaultSession,
+    target: DiffTarget<'_>,
+    options: &DiffOptions,
+) -> Result<DiffReport> {
+    let ours = vault_snapshot(session, options).await;
+    let (theirs, other) = match target {
+        DiffTarget::Vault(other) => (
+            vault_snapshot(other, options).await,
+            Other::Vault(VaultOperations::new(other)?),
+        ),
+        DiffTarget::LocalDir(dir) => (local_snapshot(dir, options)?, Other::LocalDir(dir)),
+    };
+    let key = FileKey::generate();
+
+    let keys: BTreeSet<&Vec<String>> = ours.keys().chain(theirs.keys()).collect();
+    let mut report = DiffReport::default();
+    // Subtrees already reported as a whole.
+    let mut settled: Vec<VaultPath> = Vec::new();
+
+    for components in keys {
+        let path = VaultPath::from_components(components.clone())?;
+        if settled.iter().any(|done| path.starts_with(done)) {
+            continue;
+        }
+        let (a, b) = match (ours.get(components), theirs.get(components)) {
+            (Some(a), Some(b)) => (a, b),
+            (Some(only), None) | (None, Some(only)) => {
+                let kind = if ours.contains_key(components) {
+                    DiffKind::OnlyInA
+                } else {
+                    DiffKind::OnlyInB
+                };
+                if only.is_directory {
+                    settled.push(path.clone());
+                }
+                report.entries.push(DiffEntry {
+                    path,
+                    kind,
+                    is_directory: only.is_directory,
+                });
+                continue;
+            }
+            (None, None) => continue,
+        };
+        report.compared += 1;
+
+        let kind = if a.is_directory != b.is_directory {
+            settled.push(path.clone());
+            Some(DiffKind::ContentDiffers)
+        } else if a.is_directory {
+            None
+        } else if !same_content(ops, &other, &path, a, b, &key, options, &mut report).await? {
+            Some(DiffKind::ContentDiffers)
+        } else if !same_metadata(a, b, options) {
+            Some(DiffKind::MetadataDiffers)
+        } else {
+            None
+        };
+
+        if let Some(kind) = kind {
+            report.entries.push(DiffEntry {
+                path,
+                kind,
+                is_directory: a.is_directory || b.is_directory,
+            });
+        }
+    }
+
+    debug!(
+        compared = report.compared,
+        differences = report.entries.len(),
+        "Diff complete"
+    );
+    Ok(report)
+}
+
+/// Whether two files hold the same content.
+#[allow(clippy::too_many_arguments)]
+async fn same_content(
+    ops: &VaultOperations<'_>,
+    other: &Other<'_>,
+    path: &VaultPath,
+    a: &Node,
+    b: &Node,
+    key: &FileKey,
+    options: &DiffOptions,
+    report: &mut DiffReport,
+) -> Result<bool> {
+    if a.size != b.size {
+        return Ok(false);
+    }
+    if options.fast {
+        let etags_differ = matches!((&a.etag, &b.etag), (Some(x), Some(y)) if x != y);
+        return Ok(!etags_differ);
+    }
+
+    let mut ours = KeyedHasher::new(key)?;
+    report.bytes_hashed += ops.read_file_to_writer(path, &mut ours).await?;
+    let mut theirs = KeyedHasher::new(key)?;
+    report.bytes_hashed += match other {
+        Other::Vault(other) => other.read_file_to_writer(path, &mut theirs).await?,
+        Other::LocalDir(dir) => {
+            let mut local = dir.to_path_buf();
+            local.extend(path.components());
+            io::copy(&mut File::open(&local)?, &mut theirs)?
+        }
+    };
+    Ok(ours.finish() == theirs.finish())
+}
+
+/// Whether two files with the same content also agree on metadata.
+///
+/// Times are compared to the second, which is what an export preserves.
+fn same_metadata(a: &Node, b: &Node, options: &DiffOptions) -> bool {
+    let times_match =
+        options.ignore_timestamps || a.modified_at.timestamp() == b.modified_at.timestamp();
+    let permissions_match = match (a.permissions, b.permissions) {
+        (Some(x), Some(y)) => x == y,
+        _ => true,
+    };
+    times_match && permissions_match
+}
+
+fn ignored(path: &VaultPath, options: &DiffOptions) -> bool {
+    options.ignore.iter().any(|prefix| path.starts_with(prefix))
+}
+
+async fn vault_snapshot(session: &VaultSession, options: &DiffOptions) -> Snapshot {
+    let tree = session.tree().read().await;
+    let mut snapshot = Snapshot::new();
+    collect(tree.root(), &VaultPath::root(), options, &mut snapshot);
+    snapshot
+}
+
+fn collect(node: &TreeNode, path: &VaultPath, options: &DiffOptions, out: &mut Snapshot) {
+    for child in node.children.values() {
+        let Ok(child_path) = path.join(&child.metadata.name) else {
+            continue;
+        };
+        if ignored(&child_path, options) {
+            continue;
+        }
+        out.insert(
+            child_path.components().to_vec(),
+            Node {
+                is_directory: child.is_directory(),
+                size: child.metadata.size.unwrap_or(0),
+                modified_at: child.metadata.modified_at,
+                etag: child.metadata.etag.clone(),
+                permissions: Some(child.metadata.permissions()),
+            },
+        );
+        collect(child, &child_path, options, out);
+    }
+}
+
+/// Walk `dir`; symlinks and names that are not valid vault names are
+/// skipped with a warning, since no vault entry can correspond to them.
+fn local_snapshot(dir: &Path, options: &DiffOptions) -> Result<Snapshot> {
+    if !dir.is_dir() {
+        return Err(Error::NotFound(format!(
+            "Directory not found: {}",
+            dir.display()
+        )));
+    }
+    let mut snapshot = Snapshot::new();
+    let mut pending = vec![(dir.to_path_buf(), VaultPath::root())];
+    while let Some((local, path)) = pending.pop() {
+        for entry in fs::read_dir(&local)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let child_path = match entry.file_name().to_str().map(|name| path.join(name)) {
+                Some(Ok(child_path)) if !file_type.is_symlink() => child_path,
+                _ => {
+                    warn!(path = %entry.path().display(), "Skipping entry with no vault equivalent");
+                    continue;
+                }
+            };
+            if ignored(&child_path, options) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            snapshot.insert(
+                child_path.components().to_vec(),
+                Node {
+                    is_directory: file_type.is_dir(),
+                    size: if file_type.is_dir() {
+                        0
+                    } else {
+                        metadata.len()
+                    },
+                    modified_at: metadata.modified().map(DateTime::from)?,
+                    etag: None,
+                    permissions: None,
+                },
+            );
+            if file_type.is_dir() {
+                pending.push((entry.path(), child_path));
+            }
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Writer that feeds a keyed Blake2b-256 and discards the data.
+struct KeyedHasher(Blake2bMac<U32>);
+
+impl KeyedHasher {
+    fn new(key: &FileKey) -> Result<Self> {
+        <Blake2bMac<U32> as Mac>::new_from_slice(key.as_bytes())
+            .map(Self)
+            .map_err(|e| Error::Crypto(e.to_string()))
+    }
+
+    fn finish(self) -> [u8; 32] {
+        self.0.finalize().into_bytes().into()
+    }
+}
+
+impl Write for KeyedHasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Mac::update(&mut self.0, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VaultConfig;
+    use crate::tree::VaultTree;
+    use axiomvault_common::Secret;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::KdfParams;
+    use axiomvault_storage::{MemoryProvider, StorageProvider};
+    use chrono::TimeZone;
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    fn vp(path: &str) -> VaultPath {
+        VaultPath::parse(path).unwrap()
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    /// A vault holding `dirs` and `files`, every node modified at `at(0)`.
+    async fn vault(dirs: &[&str], files: &[(&str, &[u8])]) -> VaultSession {
+        let password = b"test-password";
+        let creation = VaultConfig::new(
+            VaultId::new("test").unwrap(),
+            password,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let provider = Arc::new(MemoryProvider::new());
+        for dir in ["/d", "/m"] {
+            provider.create_dir(&vp(dir)).await.unwrap();
+        }
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(password),
+            provider,
+            VaultTree::new(),
+        )
+        .unwrap();
+
+        let ops = VaultOperations::new(&session).unwrap();
+        for dir in dirs {
+            ops.create_directory(&vp(dir)).await.unwrap();
+        }
+        for (path, content) in files {
+            ops.create_file(&vp(path), content).await.unwrap();
+        }
+        {
+            let mut tree = session.tree().write().await;
+            for path in dirs.iter().chain(files.iter().map(|(p, _)| p)) {
+                tree.get_node_mut(&vp(path)).unwrap().metadata.modified_at = at(0);
+            }
+        }
+        session
+    }
+
+    fn sorted(mut paths: Vec<&VaultPath>) -> Vec<String> {
+        paths.sort_by_key(|p| p.to_string());
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    const COMMON: &[(&str, &[u8])] = &[
+        ("/same.txt", b"hello"),
+        ("/docs/kept.txt", b"kept"),
+        ("/meta.txt", b"metadata"),
+        ("/skip/f.txt", b"one"),
+    ];
+
+    async fn divergent_pair() -> (VaultSession, VaultSession) {
+        let mut a_files = COMMON.to_vec();
+        a_files.extend([
+            ("/changed.txt", b"aaa".as_slice()),
+            ("/resized.txt", b"x"),
+            ("/only_a.txt", b"a"),
+            ("/dir_a/inner.txt", b"inner"),
+            ("/kind", b"file"),
+        ]);
+        let a = vault(&["/docs", "/skip", "/dir_a"], &a_files).await;
+
+        let mut b_files = COMMON.to_vec();
+        b_files.retain(|(p, _)| *p != "/skip/f.txt");
+        b_files.extend([
+            ("/changed.txt", b"bbb".as_slice()),
+            ("/resized.txt", b"xyz"),
+            ("/only_b.txt", b"b"),
+            ("/skip/f.txt", b"two"),
+            ("/kind/inside.txt", b"dir"),
+        ]);
+        let b = vault(&["/docs", "/skip", "/dir_b", "/kind"], &b_files).await;
+        b.tree()
+            .write()
+            .await
+            .get_node_mut(&vp("/meta.txt"))
+            .unwrap()
+            .metadata
+            .modified_at = at(60);
+        (a, b)
+    }
+
+    #[tokio::test]
+    async fn test_two_vaults_categorized() {
+        let (a, b) = divergent_pair().await;
+        let ops = VaultOperations::new(&a).unwrap();
+
+        let report = ops
+            .diff_against(DiffTarget::Vault(&b), &DiffOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sorted(report.paths(DiffKind::OnlyInA)),
+            ["/dir_a", "/only_a.txt"]
+        );
+        assert_eq!(
+            sorted(report.paths(DiffKind::OnlyInB)),
+            ["/dir_b", "/only_b.txt"]
+        );
+        assert_eq!(
+            sorted(report.paths(DiffKind::ContentDiffers)),
+            ["/changed.txt", "/kind", "/resized.txt", "/skip/f.txt"]
+        );
+        assert_eq!(
+            sorted(report.paths(DiffKind::MetadataDiffers)),
+            ["/meta.txt"]
+        );
+        assert_eq!(report.entries.len(), 9);
+        // Only equal-sized files are read, on both sides.
+        assert_eq!(report.bytes_hashed, 2 * (5 + 4 + 8 + 3 + 3));
+
+        let options = DiffOptions {
+            ignore_timestamps: true,
+            ignore: vec![vp("/skip"), vp("/kind")],
+            ..Default::default()
+        };
+        let report = ops
+            .diff_against(DiffTarget::Vault(&b), &options)
+            .await
+            .unwrap();
+        assert!(report.paths(DiffKind::MetadataDiffers).is_empty());
+        assert_eq!(
+            sorted(report.paths(DiffKind::ContentDiffers)),
+            ["/changed.txt", "/resized.txt"]
+        );
+
+        let same = ops
+            .diff_against(DiffTarget::Vault(&a), &DiffOptions::default())
+            .await
+            .unwrap();
+        assert!(same.is_identical());
+    }
+
+    #[tokio::test]
+    async fn test_vault_against_local_directory() {
+        let (a, _) = divergent_pair().await;
+        let ops = VaultOperations::new(&a).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for sub in ["docs", "skip", "dir_a", "extra"] {
+            fs::create_dir(root.join(sub)).unwrap();
+        }
+        for (path, content) in [
+            ("same.txt", b"hello".as_slice()),
+            ("docs/kept.txt", b"kept"),
+            ("meta.txt", b"metadata"),
+            ("skip/f.txt", b"one"),
+            ("changed.txt", b"bbb"),
+            ("resized.txt", b"x"),
+            ("only_a.txt", b"a"),
+            ("dir_a/inner.txt", b"inner"),
+            ("kind", b"file"),
+            ("extra/new.txt", b"new"),
+        ] {
+            let file = File::create(root.join(path)).unwrap();
+            std::io::Write::write_all(&mut &file, content).unwrap();
+            let time = if path == "meta.txt" { at(60) } else { at(0) };
+            file.set_modified(SystemTime::from(time)).unwrap();
+        }
+
+        let report = ops
+            .diff_against(DiffTarget::LocalDir(root), &DiffOptions::default())
+            .await
+            .unwrap();
+        assert!(report.paths(DiffKind::OnlyInA).is_empty());
+        assert_eq!(sorted(report.paths(DiffKind::OnlyInB)), ["/extra"]);
+        assert_eq!(
+            sorted(report.paths(DiffKind::ContentDiffers)),
+            ["/changed.txt"]
+        );
+        assert_eq!(
+            sorted(report.paths(DiffKind::MetadataDiffers)),
+            ["/meta.txt"]
+        );
+
+        // Sizes alone cannot tell "aaa" from "bbb".
+        let fast = DiffOptions {
+            fast: true,
+            ..Default::default()
+        };
+        let report = ops
+            .diff_against(DiffTarget::LocalDir(root), &fast)
+            .await
+            .unwrap();
+        assert!(report.paths(DiffKind::ContentDiffers).is_empty());
+        assert_eq!(report.bytes_hashed, 0);
+
+        assert!(matches!(
+            ops.diff_against(
+                DiffTarget::LocalDir(&root.join("missing")),
+                &DiffOptions::default()
+            )
+            .await,
+            Err(Error::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+//! Events emitted by a vault session.
+//!
+//! Subscribers receive [`VaultEvent`]s over a tokio broadcast channel via
+//! [`VaultSession::subscribe`](crate::VaultSession::subscribe). Sending never
+//! blocks; events are dropped when nobody is listening.
+
+use crate::blob::BlobFormat;
+
+/// Broadcast channel receiver for vault events.
+pub type VaultEventReceiver = tokio::sync::broadcast::Receiver<VaultEvent>;
+
+/// Capacity of a session's event channel.
+pub(crate) const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Events emitted by a vault session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VaultEvent {
+    /// A file blob was re-encrypted in a newer format.
+    BlobUpgraded {
+        path: String,
+        from: BlobFormat,
+        to: BlobFormat,
+    },
+
+    /// A file was in use or changed during its upgrade; it will be retried.
+    BlobUpgradeDeferred { path: String },
+
+    /// A maintenance run stopped, either finished or cancelled.
+    MaintenanceFinished { upgraded: usize, remaining: usize },
+
+    /// Flat blobs of one shard were moved into its directory.
+    DataShardMigrated { shard: String, moved: usize },
+
+    /// The session reached `max_bytes_per_session` and locked itself; the
+    /// password must be entered again to continue.
+    ReauthRequired { bytes_processed: u64 },
+
+    /// A FUSE mount of this session stopped serving because the session
+    /// stayed locked or its storage kept failing health checks. The mount
+    /// answers `ENOTCONN` until it recovers or is unmounted.
+    MountDegraded { mount_point: String, reason: String },
+}
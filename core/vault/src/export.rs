@@ -0,0 +1,539 @@
+//! Export of decrypted vault content to a plaintext directory.
+//!
+//! [`VaultOperations::export_tree`] recreates a subtree under a local
+//! directory, streaming each file to disk. Progress is recorded in a
+//! manifest ([`MANIFEST_FILENAME`]) in the destination after every file:
+//! a cancelled or failed run leaves it behind so a rerun skips finished
+//! files, and [`remove_partial_export`] can delete what was written. A run
+//! that completes removes the manifest.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{debug, info};
+
+use crate::operations::VaultOperations;
+use crate::session::VaultSession;
+use crate::tree::TreeNode;
+use axiomvault_common::{Error, Result, VaultPath};
+
+/// Name of the progress manifest written into the export destination.
+pub const MANIFEST_FILENAME: &str = ".axiomvault-export.json";
+
+/// Options for [`VaultOperations::export_tree`].
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Re-read every written file and compare it with the vault content.
+    pub verify: bool,
+    /// Stop after the current file once this turns `true`.
+    pub cancel: Option<watch::Receiver<bool>>,
+}
+
+/// Summary of an export run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    /// Files written in this run.
+    pub files: usize,
+    /// Directories created.
+    pub directories: usize,
+    /// Plaintext bytes written in this run.
+    pub bytes: u64,
+    /// Files skipped because an earlier run already exported them.
+    pub resumed: usize,
+    /// Files re-read and matched against the vault content.
+    pub verified: usize,
+    /// Whether the run stopped early; the manifest is kept for a rerun.
+    pub cancelled: bool,
+}
+
+/// Files finished by an export, keyed by vault path.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportManifest {
+    /// Exported subtree.
+    pub prefix: String,
+    /// Completed files.
+    pub completed: BTreeMap<String, ExportedFile>,
+}
+
+/// One file recorded in an [`ExportManifest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedFile {
+    /// Plaintext size in bytes.
+    pub size: u64,
+    /// Hex Blake2b-256 of the plaintext.
+    pub hash: String,
+}
+
+impl ExportManifest {
+    /// Load the manifest left in `dest_dir`, if any.
+    pub fn load(dest_dir: &Path) -> Result<Option<Self>> {
+        let path = dest_dir.join(MANIFEST_FILENAME);
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| Error::Serialization(e.to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, dest_dir: &Path) -> Result<()> {
+        let bytes = serde_json::to_vec(self).map_err(|e| Error::Serialization(e.to_string()))?;
+        let tmp = dest_dir.join(format!("{}.tmp", MANIFEST_FILENAME));
+        fs::write(&tmp, bytes)?;
+        fs::rename(&tmp, dest_dir.join(MANIFEST_FILENAME))?;
+        Ok(())
+    }
+}
+
+/// Delete the files recorded in the manifest of an interrupted export,
+/// then the manifest itself. Directories left empty are removed too.
+///
+/// Returns the number of files removed.
+pub fn remove_partial_export(dest_dir: &Path) -> Result<usize> {
+    let manifest = ExportManifest::load(dest_dir)?
+        .ok_or_else(|| Error::NotFound("No export manifest in destination".to_string()))?;
+
+    let mut removed = 0;
+    for path in manifest.completed.keys() {
+        let local = local_path(dest_dir, &VaultPath::parse(path)?);
+        match fs::remove_file(&local) {
+            Ok(()) => removed += 1,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        // Prune now-empty parents up to the destination root.
+        let mut parent = local.parent();
+        while let Some(dir) = parent.filter(|d| *d != dest_dir) {
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+            parent = dir.parent();
+        }
+    }
+    fs::remove_file(dest_dir.join(MANIFEST_FILENAME))?;
+    Ok(removed)
+}
+
+/// A node selected for export.
+struct Entry {
+    path: VaultPath,
+    is_file: bool,
+    modified_at: DateTime<Utc>,
+}
+
+pub(crate) async fn export_tree(
+    ops: &VaultOperations<'_>,
+    session: &VaultSession,
+    prefix: &VaultPath,
+    dest_dir: &Path,
+    options: &ExportOptions,
+) -> Result<ExportReport> {
+    let entries = {
+        let tree = session.tree().read().await;
+        let node = tree.get_node(prefix)?;
+        let mut entries = Vec::new();
+        collect(node, prefix, &mut entries);
+        entries
+    };
+
+    fs::create_dir_all(dest_dir)?;
+    let mut manifest = match ExportManifest::load(dest_dir)? {
+        Some(existing) if existing.prefix == prefix.to_string() => existing,
+        Some(existing) => {
+            return Err(Error::AlreadyExists(format!(
+                "Destination holds an unfinished export of {}",
+                existing.prefix
+            )))
+        }
+        None => ExportManifest {
+            prefix: prefix.to_string(),
+            ..Default::default()
+        },
+    };
+    manifest.save(dest_dir)?;
+
+    let mut report = ExportReport::default();
+    for entry in entries.iter().filter(|e| !e.is_file) {
+        fs::create_dir_all(local_path(dest_dir, &entry.path))?;
+        report.directories += 1;
+    }
+
+    for entry in entries.iter().filter(|e| e.is_file) {
+        if options.cancel.as_ref().is_some_and(|c| *c.borrow()) {
+            info!(files = report.files, "Export cancelled");
+            report.cancelled = true;
+            return Ok(report);
+        }
+
+        let key = entry.path.to_string();
+        let local = local_path(dest_dir, &entry.path);
+        if let Some(done) = manifest.completed.get(&key) {
+            if fs::metadata(&local).is_ok_and(|m| m.len() == done.size) {
+                if options.verify {
+                    verify_file(&local, &done.hash, &key)?;
+                    report.verified += 1;
+                }
+                report.resumed += 1;
+                continue;
+            }
+        }
+
+        if let Some(parent) = local.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = HashingWriter::new(BufWriter::new(File::create(&local)?));
+        let size = ops.read_file_to_writer(&entry.path, &mut writer).await?;
+        let (file, hash) = writer.finish()?;
+        let file = file.into_inner().map_err(|e| e.into_error())?;
+        file.set_modified(SystemTime::from(entry.modified_at))?;
+        drop(file);
+
+        if options.verify {
+            verify_file(&local, &hash, &key)?;
+            report.verified += 1;
+        }
+
+        manifest.completed.insert(key, ExportedFile { size, hash });
+        manifest.save(dest_dir)?;
+        report.files += 1;
+        report.bytes += size;
+        debug!(size, "Exported file");
+    }
+
+    // Set directory times last: writing files would bump them again.
+    // Best effort, since not every platform can open a directory as a file.
+    for entry in entries.iter().rev().filter(|e| !e.is_file) {
+        if let Ok(dir) = File::open(local_path(dest_dir, &entry.path)) {
+            let _ = dir.set_modified(SystemTime::from(entry.modified_at));
+        }
+    }
+
+    fs::remove_file(dest_dir.join(MANIFEST_FILENAME))?;
+    info!(
+        files = report.files,
+        resumed = report.resumed,
+        bytes = report.bytes,
+        "Export complete"
+    );
+    Ok(report)
+}
+
+/// Depth-first, sorted so runs visit files in the same order.
+fn collect(node: &TreeNode, path: &VaultPath, out: &mut Vec<Entry>) {
+    out.push(Entry {
+        path: path.clone(),
+        is_file: node.is_file(),
+        modified_at: node.metadata.modified_at,
+    });
+    let mut children: Vec<&TreeNode> = node.children.values().collect();
+    children.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+    for child in children {
+        if let Ok(child_path) = path.join(&child.metadata.name) {
+            collect(child, &child_path, out);
+        }
+    }
+}
+
+/// Components are validated by `VaultPath`, so they cannot escape `dest_dir`.
+fn local_path(dest_dir: &Path, path: &VaultPath) -> PathBuf {
+    let mut local = dest_dir.to_path_buf();
+    local.extend(path.components());
+    local
+}
+
+fn verify_file(local: &Path, expected: &str, path: &str) -> Result<()> {
+    let mut sink = HashingWriter::new(io::sink());
+    io::copy(&mut File::open(local)?, &mut sink)?;
+    let (_, actual) = sink.finish()?;
+    if actual != expected {
+        return Err(Error::Integrity(format!(
+            "Exported copy of {} does not match the vault content",
+            path
+        )));
+    }
+    Ok(())
+}
+
+/// Writer that hashes everything passed through it.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Blake2b<U32>,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Blake2b::new(),
+        }
+    }
+
+    fn finish(mut self) -> io::Result<(W, String)> {
+        self.inner.flush()?;
+        let hash = self
+            .hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        Ok((self.inner, hash))
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VaultConfig;
+    use crate::tree::VaultTree;
+    use axiomvault_common::Secret;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::KdfParams;
+    use axiomvault_storage::{MemoryProvider, StorageProvider};
+    use std::sync::Arc;
+
+    async fn session_with_files() -> VaultSession {
+        let password = b"test-password";
+        let creation = VaultConfig::new(
+            VaultId::new("test").unwrap(),
+            password,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let provider = Arc::new(MemoryProvider::new());
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(password),
+            provider,
+            VaultTree::new(),
+        )
+        .unwrap();
+
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_directory(&VaultPath::parse("/docs").unwrap())
+            .await
+            .unwrap();
+        ops.create_directory(&VaultPath::parse("/docs/deep").unwrap())
+            .await
+            .unwrap();
+        for (path, content) in [
+            ("/docs/a.txt", b"alpha".as_slice()),
+            ("/docs/deep/b.txt", b"bravo"),
+            ("/other.txt", b"outside"),
+        ] {
+            ops.create_file(&VaultPath::parse(path).unwrap(), content)
+                .await
+                .unwrap();
+        }
+        session
+    }
+
+    #[tokio::test]
+    async fn test_export_prefix_with_verify() {
+        let session = session_with_files().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        let options = ExportOptions {
+            verify: true,
+            ..Default::default()
+        };
+
+        let report = ops
+            .export_tree(&VaultPath::parse("/docs").unwrap(), dest.path(), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.files, 2);
+        assert_eq!(report.verified, 2);
+        assert_eq!(report.bytes, 10);
+        assert!(!report.cancelled);
+        assert_eq!(fs::read(dest.path().join("docs/a.txt")).unwrap(), b"alpha");
+        assert_eq!(
+            fs::read(dest.path().join("docs/deep/b.txt")).unwrap(),
+            b"bravo"
+        );
+        assert!(!dest.path().join("other.txt").exists());
+        assert!(!dest.path().join(MANIFEST_FILENAME).exists());
+
+        let tree = session.tree().read().await;
+        let modified_at = tree
+            .get_node(&VaultPath::parse("/docs/a.txt").unwrap())
+            .unwrap()
+            .metadata
+            .modified_at;
+        let written = fs::metadata(dest.path().join("docs/a.txt"))
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert_eq!(
+            DateTime::<Utc>::from(written).timestamp(),
+            modified_at.timestamp()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_names_with_control_characters_round_trip() {
+        let session = session_with_files().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let dir = VaultPath::root().join("tab\there").unwrap();
+        let file = dir.join("a\nb").unwrap();
+        ops.create_directory(&dir).await.unwrap();
+        ops.create_file(&file, b"newline").await.unwrap();
+
+        let listed = ops.list_directory(&dir).await.unwrap();
+        assert_eq!(listed, vec![("a\nb".to_string(), false, Some(7))]);
+        assert_eq!(VaultPath::parse(&file.to_string()).unwrap(), file);
+        assert_eq!(ops.read_file(&file).await.unwrap(), b"newline");
+        assert_eq!(file.display_lossy(), "/tab\\there/a\\nb");
+
+        let dest = tempfile::tempdir().unwrap();
+        let options = ExportOptions {
+            verify: true,
+            ..Default::default()
+        };
+        let report = ops.export_tree(&dir, dest.path(), &options).await.unwrap();
+        assert_eq!(report.files, 1);
+        assert_eq!(report.verified, 1);
+        assert_eq!(
+            fs::read(dest.path().join("tab\there").join("a\nb")).unwrap(),
+            b"newline"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_export_resumes_and_cleans_up() {
+        let session = session_with_files().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let dest = tempfile::tempdir().unwrap();
+
+        // Pretend an earlier run finished `/docs/a.txt` before Ctrl-C.
+        let (stop, cancel) = watch::channel(true);
+        let report = ops
+            .export_tree(
+                &VaultPath::root(),
+                dest.path(),
+                &ExportOptions {
+                    cancel: Some(cancel),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert!(report.cancelled);
+        assert_eq!(report.files, 0);
+        assert!(dest.path().join(MANIFEST_FILENAME).exists());
+        drop(stop);
+
+        let mut manifest = ExportManifest::load(dest.path()).unwrap().unwrap();
+        fs::write(dest.path().join("docs/a.txt"), b"alpha").unwrap();
+        let mut hasher = HashingWriter::new(io::sink());
+        hasher.write_all(b"alpha").unwrap();
+        manifest.completed.insert(
+            "/docs/a.txt".to_string(),
+            ExportedFile {
+                size: 5,
+                hash: hasher.finish().unwrap().1,
+            },
+        );
+        manifest.save(dest.path()).unwrap();
+
+        // A different prefix must not silently reuse the manifest.
+        assert!(matches!(
+            ops.export_tree(
+                &VaultPath::parse("/docs").unwrap(),
+                dest.path(),
+                &ExportOptions::default()
+            )
+            .await,
+            Err(Error::AlreadyExists(_))
+        ));
+
+        assert_eq!(remove_partial_export(dest.path()).unwrap(), 1);
+        assert!(!dest.path().join("docs/a.txt").exists());
+        assert!(!dest.path().join(MANIFEST_FILENAME).exists());
+
+        // Re-record progress and resume to completion.
+        fs::create_dir_all(dest.path().join("docs")).unwrap();
+        fs::write(dest.path().join("docs/a.txt"), b"alpha").unwrap();
+        manifest.save(dest.path()).unwrap();
+        let report = ops
+            .export_tree(
+                &VaultPath::root(),
+                dest.path(),
+                &ExportOptions {
+                    verify: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.resumed, 1);
+        assert_eq!(report.files, 2);
+        assert_eq!(report.verified, 3);
+        assert!(!dest.path().join(MANIFEST_FILENAME).exists());
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_tampered_resumed_file() {
+        let session = session_with_files().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let dest = tempfile::tempdir().unwrap();
+
+        let mut hasher = HashingWriter::new(io::sink());
+        hasher.write_all(b"alpha").unwrap();
+        let mut manifest = ExportManifest {
+            prefix: "/".to_string(),
+            ..Default::default()
+        };
+        manifest.completed.insert(
+            "/docs/a.txt".to_string(),
+            ExportedFile {
+                size: 5,
+                hash: hasher.finish().unwrap().1,
+            },
+        );
+        fs::create_dir_all(dest.path().join("docs")).unwrap();
+        fs::write(dest.path().join("docs/a.txt"), b"ALPHA").unwrap();
+        manifest.save(dest.path()).unwrap();
+
+        let result = ops
+            .export_tree(
+                &VaultPath::root(),
+                dest.path(),
+                &ExportOptions {
+                    verify: true,
+                    ..Default::default()
+                },
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Integrity(_))));
+    }
+}
@@ -8,18 +8,16 @@ use std::collections::HashSet;
 
 use tracing::{debug, warn};
 
-use crate::config::{
-    VaultConfig, VaultVersion, CONFIG_FILENAME, DATA_DIRNAME, META_DIRNAME, TREE_FILENAME,
-};
+use crate::config::{VaultConfig, VaultVersion, CONFIG_FILENAME, META_DIRNAME, TREE_FILENAME};
+use crate::layout;
+use crate::parts::object_names;
+use crate::session::decrypt_tree;
 use crate::tree::{NodeType, TreeNode, VaultTree};
 use axiomvault_common::health::{DiagnosticResult, HealthReport, Severity};
-use axiomvault_common::{Error, Result, VaultPath};
-use axiomvault_crypto::{decrypt, MasterKey};
+use axiomvault_common::{Result, VaultPath};
+use axiomvault_crypto::MasterKey;
 use axiomvault_storage::StorageProvider;
 
-/// Context tag for tree index key derivation (must match session.rs).
-const TREE_KEY_CONTEXT: &[u8] = b"vault_tree_index_v1";
-
 /// Run a shallow health check that does not require a password.
 ///
 /// Checks directory structure, vault.config existence and parsing,
@@ -97,11 +95,10 @@ pub async fn check_vault_structure(
         _ => {}
     }
 
-    // Check data directory and count files
-    let data_path = VaultPath::parse(DATA_DIRNAME)?;
-    match provider.list(&data_path).await {
-        Ok(entries) => {
-            let file_count = entries.len();
+    // Check data directory and count files, flat or sharded
+    match layout::list_objects(provider).await {
+        Ok(objects) => {
+            let file_count = objects.len();
             results.push(DiagnosticResult {
                 check_name: "data_dir".to_string(),
                 severity: Severity::Info,
@@ -158,6 +155,7 @@ pub async fn check_vault_health(
     let mut results = Vec::new();
 
     check_config(config, &mut results);
+    check_config_mac(config, master_key, &mut results);
     check_tree_index(provider, master_key, &mut results).await;
 
     // Only run cross-referencing checks if the tree loaded successfully.
@@ -168,6 +166,19 @@ pub async fn check_vault_health(
             collect_file_encrypted_names(tree.root(), &mut tree_encrypted_names);
 
             check_orphaned_files(provider, &tree_encrypted_names, &mut results).await;
+
+            // Blobs below attach points are stored on other providers.
+            for point in &config.attachments {
+                for path in tree.files_under(&point.vault_path) {
+                    if let Ok(node) = tree.get_node(&path) {
+                        for name in
+                            object_names(&node.metadata.encrypted_name, &node.metadata.parts)
+                        {
+                            tree_encrypted_names.remove(&name);
+                        }
+                    }
+                }
+            }
             check_missing_files(provider, &tree_encrypted_names, &mut results).await;
         }
     }
@@ -223,6 +234,32 @@ fn check_config(config: &VaultConfig, results: &mut Vec<DiagnosticResult>) {
     }
 }
 
+/// Check the configuration against its MAC.
+fn check_config_mac(
+    config: &VaultConfig,
+    master_key: &MasterKey,
+    results: &mut Vec<DiagnosticResult>,
+) {
+    debug!("Running config MAC check");
+
+    if config.verify_mac(master_key).is_err() {
+        results.push(DiagnosticResult {
+            check_name: "config_mac".to_string(),
+            severity: Severity::Error,
+            message: "Configuration does not match its MAC and may have been tampered with"
+                .to_string(),
+            auto_fixable: false,
+        });
+    } else if config.config_mac.is_none() {
+        results.push(DiagnosticResult {
+            check_name: "config_mac".to_string(),
+            severity: Severity::Warning,
+            message: "Configuration has no MAC; it will be added on the next save".to_string(),
+            auto_fixable: false,
+        });
+    }
+}
+
 /// Check the tree index: exists, decryptable, and parseable.
 async fn check_tree_index(
     provider: &dyn StorageProvider,
@@ -299,13 +336,10 @@ async fn check_orphaned_files(
 ) {
     debug!("Running orphaned files check");
 
-    let data_path = match VaultPath::parse(DATA_DIRNAME) {
-        Ok(p) => p,
-        Err(_) => return,
-    };
-
-    let storage_files = match provider.list(&data_path).await {
-        Ok(entries) => entries,
+    // Both layouts are listed, so a half-finished shard migration does not
+    // hide blobs.
+    let objects = match layout::list_objects(provider).await {
+        Ok(objects) => objects,
         Err(e) => {
             results.push(DiagnosticResult {
                 check_name: "orphaned_files".to_string(),
@@ -318,12 +352,9 @@ async fn check_orphaned_files(
     };
 
     let mut orphan_count = 0;
-    for entry in &storage_files {
-        if entry.is_directory {
-            continue;
-        }
-        if !tree_encrypted_names.contains(&entry.name) {
-            warn!(file = %entry.name, "Orphaned file found in data directory");
+    for name in objects {
+        if !tree_encrypted_names.contains(&name) {
+            warn!(file = %name, "Orphaned file found in data directory");
             orphan_count += 1;
         }
     }
@@ -358,12 +389,7 @@ async fn check_missing_files(
 
     let mut missing_count = 0;
     for encrypted_name in tree_encrypted_names {
-        let file_path = match VaultPath::parse(DATA_DIRNAME).and_then(|p| p.join(encrypted_name)) {
-            Ok(p) => p,
-            Err(_) => continue,
-        };
-
-        match provider.exists(&file_path).await {
+        match layout::exists(provider, encrypted_name).await {
             Ok(true) => {}
             Ok(false) => {
                 warn!(file = %encrypted_name, "Missing file referenced by tree");
@@ -404,31 +430,17 @@ async fn load_tree(provider: &dyn StorageProvider, master_key: &MasterKey) -> Re
     }
 
     let encrypted_bytes = provider.download(&tree_path).await?;
-
-    let tree_key = master_key.derive_file_key(TREE_KEY_CONTEXT);
-    let mut tree_bytes = decrypt(tree_key.as_bytes(), &encrypted_bytes).map_err(|e| {
-        Error::Crypto(format!(
-            "Failed to decrypt tree index (wrong password or corrupted vault): {}",
-            e
-        ))
-    })?;
-
-    let tree_json = String::from_utf8(tree_bytes.clone())
-        .map_err(|e| Error::Serialization(format!("Invalid UTF-8 in tree data: {}", e)));
-
-    // Best-effort: wipe decrypted tree bytes (contains vault structure metadata).
-    use zeroize::Zeroize;
-    tree_bytes.zeroize();
-
-    let tree_json = tree_json?;
-
-    VaultTree::from_json(&tree_json)
+    decrypt_tree(&encrypted_bytes, master_key)
 }
 
-/// Recursively collect all encrypted file names from the tree.
+/// Recursively collect the data directory objects of all files in the
+/// tree, including every part of split blobs.
 fn collect_file_encrypted_names(node: &TreeNode, names: &mut HashSet<String>) {
     if node.metadata.node_type == NodeType::File {
-        names.insert(node.metadata.encrypted_name.clone());
+        names.extend(object_names(
+            &node.metadata.encrypted_name,
+            &node.metadata.parts,
+        ));
     }
     for child in node.children.values() {
         collect_file_encrypted_names(child, names);
@@ -440,7 +452,7 @@ mod tests {
     use super::*;
     use crate::config::VaultConfig;
     use axiomvault_common::VaultId;
-    use axiomvault_crypto::KdfParams;
+    use axiomvault_crypto::{KdfParams, KeyContext};
     use axiomvault_storage::MemoryProvider;
     use std::sync::Arc;
 
@@ -486,7 +498,9 @@ mod tests {
             .unwrap();
 
         let tree_json = tree.to_json().unwrap();
-        let tree_key = master_key.derive_file_key(b"vault_tree_index_v1");
+        let tree_key = master_key
+            .derive_legacy_file_key(&KeyContext::Tree)
+            .unwrap();
         let encrypted =
             axiomvault_crypto::encrypt(tree_key.as_bytes(), tree_json.as_bytes()).unwrap();
         let tree_path = VaultPath::parse("m").unwrap().join("tree.json").unwrap();
@@ -511,7 +525,9 @@ mod tests {
             .unwrap();
 
         let tree_json = tree.to_json().unwrap();
-        let tree_key = master_key.derive_file_key(b"vault_tree_index_v1");
+        let tree_key = master_key
+            .derive_legacy_file_key(&KeyContext::Tree)
+            .unwrap();
         let encrypted =
             axiomvault_crypto::encrypt(tree_key.as_bytes(), tree_json.as_bytes()).unwrap();
         let tree_path = VaultPath::parse("m").unwrap().join("tree.json").unwrap();
@@ -534,7 +550,9 @@ mod tests {
 
         let tree = VaultTree::new();
         let tree_json = tree.to_json().unwrap();
-        let tree_key = master_key.derive_file_key(b"vault_tree_index_v1");
+        let tree_key = master_key
+            .derive_legacy_file_key(&KeyContext::Tree)
+            .unwrap();
         let encrypted =
             axiomvault_crypto::encrypt(tree_key.as_bytes(), tree_json.as_bytes()).unwrap();
         let tree_path = VaultPath::parse("m").unwrap().join("tree.json").unwrap();
@@ -0,0 +1,517 @@
+//! Write-ahead journal for file mutations.
+//!
+//! Creating, updating or deleting a file writes storage first and the tree
+//! second. A crash in between leaves a blob without a tree entry, a tree
+//! entry without a blob, or a tree entry describing the previous version.
+//! Before touching storage, each such operation appends its intent to an
+//! encrypted journal in the metadata directory, and removes it again once
+//! the tree is saved. Entries still in the journal when a vault is opened
+//! are reconciled by [`recover`]:
+//!
+//! - a create is rolled back: its blob is deleted unless the tree has it
+//! - an update is rolled forward if the new blob was written completely
+//! - a delete is rolled forward: the tree entry and any objects left go
+//!
+//! Directory operations and renames only change the tree, which is saved
+//! in one write, so they are not journaled.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::blob::{decrypt_blob, BlobFormat};
+use crate::config::META_DIRNAME;
+use crate::layout;
+use crate::parts::{self, BlobPart};
+use crate::session::VaultSession;
+use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_crypto::{decrypt, encrypt, KeyContext, MasterKey};
+
+/// Journal filename in the metadata directory.
+pub const JOURNAL_FILENAME: &str = "journal.log";
+
+/// A storage write about to happen, and what the tree will say about it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum Intent {
+    /// A new blob is stored under `encrypted_name`.
+    Create { encrypted_name: String },
+    /// The blob under `encrypted_name` is replaced by one with this
+    /// metadata.
+    Update {
+        encrypted_name: String,
+        size: u64,
+        etag: String,
+        modified_at: DateTime<Utc>,
+        parts: Vec<BlobPart>,
+        old_parts: Vec<BlobPart>,
+    },
+    /// The blob under `encrypted_name`, stored as `parts`, is deleted.
+    Delete {
+        encrypted_name: String,
+        parts: Vec<BlobPart>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    id: u64,
+    intent: Intent,
+    /// The operation failed; the entry can go once the tree is saved.
+    #[serde(skip)]
+    settled: bool,
+}
+
+/// Pending entries, mirrored to storage on every change.
+#[derive(Debug, Default)]
+pub(crate) struct Journal {
+    entries: Vec<Entry>,
+    next_id: u64,
+}
+
+/// A journaled operation in progress.
+#[must_use = "finish the entry once the tree is saved"]
+pub(crate) struct Ticket(u64);
+
+fn journal_path() -> Result<VaultPath> {
+    VaultPath::parse(META_DIRNAME)?.join(JOURNAL_FILENAME)
+}
+
+/// Record `intent` before any storage is written for it.
+///
+/// # Errors
+/// - Storage failure writing the journal; nothing has been written then
+pub(crate) async fn begin(session: &VaultSession, intent: Intent) -> Result<Ticket> {
+    let mut journal = session.journal().lock().await;
+    let id = journal.next_id;
+    journal.next_id += 1;
+    journal.entries.push(Entry {
+        id,
+        intent,
+        settled: false,
+    });
+    if let Err(e) = write(session, &journal).await {
+        journal.entries.retain(|entry| entry.id != id);
+        return Err(e);
+    }
+    Ok(Ticket(id))
+}
+
+/// Close the entry of an operation.
+///
+/// After success the tree is saved and the entry goes. After a failure
+/// the in-memory tree may already reflect part of the operation, so the
+/// entry stays until the next tree save persists it.
+pub(crate) async fn finish(session: &VaultSession, ticket: Ticket, succeeded: bool) {
+    let mut journal = session.journal().lock().await;
+    if succeeded {
+        journal.entries.retain(|entry| entry.id != ticket.0);
+    } else if let Some(entry) = journal.entries.iter_mut().find(|e| e.id == ticket.0) {
+        entry.settled = true;
+        return;
+    }
+    if let Err(e) = write(session, &journal).await {
+        // Recovery finds the tree already consistent with the entry.
+        warn!("Failed to clear journal entry: {}", e);
+    }
+}
+
+/// Entries of failed operations, to drop once a tree save that started
+/// after them succeeds.
+pub(crate) async fn settled(session: &VaultSession) -> HashSet<u64> {
+    let journal = session.journal().lock().await;
+    journal
+        .entries
+        .iter()
+        .filter(|entry| entry.settled)
+        .map(|entry| entry.id)
+        .collect()
+}
+
+/// Drop the `settled` entries after the tree was saved.
+pub(crate) async fn forget(session: &VaultSession, settled: &HashSet<u64>) {
+    if settled.is_empty() {
+        return;
+    }
+    let mut journal = session.journal().lock().await;
+    journal.entries.retain(|entry| !settled.contains(&entry.id));
+    if let Err(e) = write(session, &journal).await {
+        warn!("Failed to clear journal entries: {}", e);
+    }
+}
+
+async fn write(session: &VaultSession, journal: &Journal) -> Result<()> {
+    let provider = session.provider();
+    let path = journal_path()?;
+    if journal.entries.is_empty() {
+        return match provider.delete(&path).await {
+            Err(Error::NotFound(_)) => Ok(()),
+            result => result,
+        };
+    }
+    let encrypted = encrypt_entries(&journal.entries, &*session.master_key()?)?;
+    provider.upload(&path, encrypted).await?;
+    Ok(())
+}
+
+/// Reconcile storage and tree with the entries left in the journal by an
+/// interrupted session, save the tree, and clear the journal.
+///
+/// Returns the number of entries reconciled.
+pub(crate) async fn recover(session: &VaultSession) -> Result<usize> {
+    let provider = session.provider();
+    let path = journal_path()?;
+    if !provider.exists(&path).await? {
+        return Ok(0);
+    }
+    let entries = decrypt_entries(&provider.download(&path).await?, &*session.master_key()?)?;
+
+    for entry in &entries {
+        match &entry.intent {
+            Intent::Create { encrypted_name } => roll_back_create(session, encrypted_name).await?,
+            Intent::Update { .. } => roll_forward_update(session, &entry.intent).await?,
+            Intent::Delete {
+                encrypted_name,
+                parts,
+            } => roll_forward_delete(session, encrypted_name, parts).await?,
+        }
+    }
+
+    session.save_tree().await?;
+    match provider.delete(&path).await {
+        Err(Error::NotFound(_)) | Ok(()) => {}
+        Err(e) => return Err(e),
+    }
+    info!(entries = entries.len(), "Recovered interrupted operations");
+    Ok(entries.len())
+}
+
+/// Delete the objects of a created blob the tree never recorded.
+async fn roll_back_create(session: &VaultSession, encrypted_name: &str) -> Result<()> {
+    if session
+        .tree()
+        .read()
+        .await
+        .find_file(encrypted_name)
+        .is_some()
+    {
+        return Ok(());
+    }
+    let layout = session.data_layout();
+    delete_object(session, encrypted_name).await?;
+    // The file's path is unknown, so look on every provider.
+    for provider in session.blob_providers() {
+        let mut index = 0;
+        loop {
+            let name = parts::part_name(encrypted_name, index);
+            if !layout::exists(provider.as_ref(), &name).await? {
+                break;
+            }
+            layout::delete(provider.as_ref(), layout, &name).await?;
+            index += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Point the tree at an updated blob once it is known to be complete.
+async fn roll_forward_update(session: &VaultSession, intent: &Intent) -> Result<()> {
+    let Intent::Update {
+        encrypted_name,
+        size,
+        etag,
+        modified_at,
+        parts: new_parts,
+        old_parts,
+    } = intent
+    else {
+        return Ok(());
+    };
+    let Some(path) = session.tree().read().await.find_file(encrypted_name) else {
+        return Ok(());
+    };
+    if session
+        .tree()
+        .read()
+        .await
+        .get_node(&path)?
+        .metadata
+        .etag
+        .as_deref()
+        == Some(etag)
+    {
+        return Ok(());
+    }
+
+    // Storage may still hold the previous version, or an incomplete new
+    // one; only a blob that decrypts to the new size is adopted.
+    let provider = session.blob_provider(&path)?;
+    let layout = session.data_layout();
+    let written = match parts::download(provider.as_ref(), layout, encrypted_name, new_parts).await
+    {
+        Ok(blob) => decrypt_blob(
+            BlobFormat::LATEST,
+            &*session.master_key()?,
+            encrypted_name,
+            &blob,
+        )
+        .is_ok_and(|plaintext| plaintext.len() as u64 == *size),
+        Err(Error::NotFound(_)) | Err(Error::Integrity(_)) => false,
+        Err(e) => return Err(e),
+    };
+    if !written {
+        warn!("Interrupted update did not complete; keeping the previous version");
+        return Ok(());
+    }
+
+    {
+        let mut tree = session.tree().write().await;
+        let node = tree.get_node_mut(&path)?;
+        node.metadata.size = Some(*size);
+        node.metadata.modified_at = *modified_at;
+        node.metadata.etag = Some(etag.clone());
+        node.metadata.blob_format = BlobFormat::LATEST;
+        node.metadata.parts = new_parts.clone();
+        // Recorded on the next full verification.
+        node.metadata.manifest = None;
+    }
+    parts::remove_stale(
+        provider.as_ref(),
+        layout,
+        (encrypted_name, old_parts),
+        (encrypted_name, new_parts),
+    )
+    .await;
+    Ok(())
+}
+
+/// Finish a delete: drop the tree entry and whatever objects remain.
+async fn roll_forward_delete(
+    session: &VaultSession,
+    encrypted_name: &str,
+    parts: &[BlobPart],
+) -> Result<()> {
+    {
+        let mut tree = session.tree().write().await;
+        if let Some(path) = tree.find_file(encrypted_name) {
+            tree.remove(&path)?;
+        }
+    }
+    for name in parts::object_names(encrypted_name, parts) {
+        delete_object(session, &name).await?;
+    }
+    Ok(())
+}
+
+/// Delete a blob object from whichever provider holds it.
+async fn delete_object(session: &VaultSession, name: &str) -> Result<()> {
+    for provider in session.blob_providers() {
+        match layout::delete(provider.as_ref(), session.data_layout(), name).await {
+            Err(Error::NotFound(_)) | Ok(()) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+fn encrypt_entries(entries: &[Entry], master_key: &MasterKey) -> Result<Vec<u8>> {
+    let key = master_key.derive_file_key(&KeyContext::Journal);
+    let json = serde_json::to_vec(entries)
+        .map_err(|e| Error::Serialization(format!("Failed to write journal: {}", e)))?;
+    encrypt(key.as_bytes(), &json)
+}
+
+fn decrypt_entries(encrypted: &[u8], master_key: &MasterKey) -> Result<Vec<Entry>> {
+    let key = master_key.derive_file_key(&KeyContext::Journal);
+    let json = decrypt(key.as_bytes(), encrypted)
+        .map_err(|e| Error::Crypto(format!("Failed to decrypt journal: {}", e)))?;
+    serde_json::from_slice(&json)
+        .map_err(|e| Error::Serialization(format!("Failed to read journal: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{VaultConfig, TREE_FILENAME};
+    use crate::operations::VaultOperations;
+    use crate::tree::VaultTree;
+    use axiomvault_common::Secret;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::KdfParams;
+    use axiomvault_storage::test_util::CountingProvider;
+    use axiomvault_storage::StorageProvider;
+    use std::sync::Arc;
+
+    const PASSWORD: &[u8] = b"test-password";
+
+    async fn reopen(config: VaultConfig, provider: Arc<CountingProvider>) -> VaultSession {
+        let master_key = config
+            .verify_password(&Secret::from_slice(PASSWORD))
+            .unwrap()
+            .unwrap();
+        let tree = VaultSession::load_and_decrypt_tree(
+            &(provider.clone() as Arc<dyn StorageProvider>),
+            &master_key,
+        )
+        .await
+        .unwrap();
+        VaultSession::from_master_key(config, master_key, provider, tree).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_recover_restores_consistency_after_crash() {
+        let creation = VaultConfig::new(
+            VaultId::new("test").unwrap(),
+            PASSWORD,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let config = creation.config;
+        let provider = Arc::new(CountingProvider::new());
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+
+        let updated = VaultPath::parse("/updated.txt").unwrap();
+        let deleted = VaultPath::parse("/deleted.txt").unwrap();
+        let created = VaultPath::parse("/created.txt").unwrap();
+        {
+            let session = VaultSession::unlock(
+                config.clone(),
+                &Secret::from_slice(PASSWORD),
+                provider.clone(),
+                VaultTree::new(),
+            )
+            .unwrap();
+            let ops = VaultOperations::new(&session).unwrap();
+            ops.create_file(&updated, b"old").await.unwrap();
+            ops.create_file(&deleted, b"doomed").await.unwrap();
+
+            provider.fail_uploads_named(Some(TREE_FILENAME));
+            ops.update_file(&updated, b"new content").await.unwrap_err();
+            ops.delete_file(&deleted).await.unwrap_err();
+            ops.create_file(&created, b"orphan").await.unwrap_err();
+            provider.fail_uploads_named(None);
+        }
+        let stored_blobs = crate::layout::list_objects(provider.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(stored_blobs.len(), 2);
+
+        let session = reopen(config.clone(), provider.clone()).await;
+        assert_eq!(session.recover_journal().await.unwrap(), 3);
+        assert!(!provider.exists(&journal_path().unwrap()).await.unwrap());
+
+        let session = reopen(config, provider.clone()).await;
+        let ops = VaultOperations::new(&session).unwrap();
+        assert_eq!(ops.read_file(&updated).await.unwrap(), b"new content");
+        assert_eq!(
+            ops.metadata(&updated).await.unwrap().2,
+            Some(b"new content".len() as u64)
+        );
+        assert!(!ops.exists(&deleted).await);
+        assert!(!ops.exists(&created).await);
+        let stored_blobs = crate::layout::list_objects(provider.as_ref())
+            .await
+            .unwrap();
+        assert_eq!(stored_blobs.len(), 1);
+        assert_eq!(session.recover_journal().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_completed_operations_leave_no_journal() {
+        let creation = VaultConfig::new(
+            VaultId::new("test").unwrap(),
+            PASSWORD,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let provider = Arc::new(CountingProvider::new());
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(PASSWORD),
+            provider.clone(),
+            VaultTree::new(),
+        )
+        .unwrap();
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/file.txt").unwrap();
+        ops.create_file(&path, b"one").await.unwrap();
+        ops.update_file(&path, b"two").await.unwrap();
+        ops.delete_file(&path).await.unwrap();
+
+        assert!(!provider.exists(&journal_path().unwrap()).await.unwrap());
+        assert_eq!(session.recover_journal().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_close_flushes_failed_operations() {
+        let creation = VaultConfig::new(
+            VaultId::new("test").unwrap(),
+            PASSWORD,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let config = creation.config;
+        let provider = Arc::new(CountingProvider::new());
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let path = VaultPath::parse("/file.txt").unwrap();
+
+        // Interrupted while closing: keys go, the journal stays for recovery.
+        let session = VaultSession::unlock(
+            config.clone(),
+            &Secret::from_slice(PASSWORD),
+            provider.clone(),
+            VaultTree::new(),
+        )
+        .unwrap();
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_file(&path, b"old").await.unwrap();
+        provider.fail_uploads_named(Some(TREE_FILENAME));
+        ops.update_file(&path, b"new").await.unwrap_err();
+        assert!(session.close().await.is_err());
+        assert!(!session.is_active());
+        assert!(provider.exists(&journal_path().unwrap()).await.unwrap());
+        provider.fail_uploads_named(None);
+        session.close().await.unwrap();
+
+        let session = reopen(config.clone(), provider.clone()).await;
+        assert_eq!(session.recover_journal().await.unwrap(), 1);
+
+        // A clean close persists the tree the failed operation left behind.
+        let ops = VaultOperations::new(&session).unwrap();
+        provider.fail_uploads_named(Some(TREE_FILENAME));
+        ops.update_file(&path, b"newer").await.unwrap_err();
+        provider.fail_uploads_named(None);
+        session.close().await.unwrap();
+        assert!(!provider.exists(&journal_path().unwrap()).await.unwrap());
+        session.close().await.unwrap();
+
+        let session = reopen(config, provider.clone()).await;
+        assert_eq!(session.recover_journal().await.unwrap(), 0);
+        let ops = VaultOperations::new(&session).unwrap();
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"newer");
+    }
+}
@@ -0,0 +1,321 @@
+//! Placement of blobs in the data directory.
+//!
+//! A flat `d/` directory slows down local filesystems once it holds
+//! hundreds of thousands of entries, and providers page through large
+//! listings slowly. A sharded vault stores each blob object under
+//! `d/<shard>/<name>`, where the shard is the first byte of a BLAKE2b hash
+//! of the blob's encrypted name as two hex digits. Parts of a split blob
+//! share their blob's shard.
+//!
+//! Existing vaults keep the flat layout until
+//! [`VaultManager::shard_data_dir`](crate::VaultManager::shard_data_dir)
+//! moves their blobs. Moves are single renames, so an interrupted
+//! migration leaves every blob whole in one place or the other; reads and
+//! deletes look in the other layout when a blob is not where the config
+//! says, and the next run carries on from whatever is still flat.
+
+use std::collections::BTreeMap;
+
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use futures::TryStreamExt;
+use tracing::{debug, info};
+
+use crate::config::{DataLayout, DATA_DIRNAME};
+use crate::events::VaultEvent;
+use crate::session::VaultSession;
+use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_storage::{list_all, stream_copy, Metadata, StorageProvider};
+
+/// Outcome of moving flat blobs into shards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShardReport {
+    /// Blob objects moved by this run.
+    pub moved: usize,
+    /// Shard directories that received at least one object.
+    pub shards: usize,
+}
+
+/// Shard of a blob object: two lowercase hex digits.
+///
+/// Part objects (`<name>.partN`) are hashed by their blob's name.
+pub fn shard_of(object_name: &str) -> String {
+    let encrypted_name = object_name.split('.').next().unwrap_or(object_name);
+    let digest = Blake2b::<U32>::digest(encrypted_name.as_bytes());
+    format!("{:02x}", digest[0])
+}
+
+/// Storage path of a blob object under `layout`.
+///
+/// The object name is validated because it may come from a provider
+/// listing; the shard name is always two hex digits.
+pub fn blob_path(layout: DataLayout, object_name: &str) -> Result<VaultPath> {
+    let data_dir = VaultPath::parse(DATA_DIRNAME)?;
+    match layout {
+        DataLayout::Flat => data_dir.join(object_name),
+        DataLayout::Sharded => data_dir
+            .join_unchecked(&shard_of(object_name))
+            .join(object_name),
+    }
+}
+
+/// The layout a blob object may still be in while a migration is under way.
+fn other(layout: DataLayout) -> DataLayout {
+    match layout {
+        DataLayout::Flat => DataLayout::Sharded,
+        DataLayout::Sharded => DataLayout::Flat,
+    }
+}
+
+/// Whether a data directory entry is a shard directory.
+fn is_shard_dir(entry: &Metadata) -> bool {
+    entry.is_directory
+        && entry.name.len() == 2
+        && entry
+            .name
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Store a blob object, creating its shard directory on first use.
+pub(crate) async fn upload(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    object_name: &str,
+    data: Vec<u8>,
+) -> Result<Metadata> {
+    let path = blob_path(layout, object_name)?;
+    if layout == DataLayout::Flat {
+        return provider.upload(&path, data).await;
+    }
+    match provider.upload(&path, data.clone()).await {
+        Err(Error::NotFound(_)) => {
+            if let Some(shard_dir) = path.parent() {
+                match provider.create_dir(&shard_dir).await {
+                    Ok(_) | Err(Error::AlreadyExists(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            provider.upload(&path, data).await
+        }
+        result => result,
+    }
+}
+
+/// Fetch a blob object from wherever it is.
+pub(crate) async fn download(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    object_name: &str,
+) -> Result<Vec<u8>> {
+    match provider.download(&blob_path(layout, object_name)?).await {
+        Err(Error::NotFound(_)) => {
+            provider
+                .download(&blob_path(other(layout), object_name)?)
+                .await
+        }
+        result => result,
+    }
+}
+
+/// Fetch `len` bytes at `offset` of a blob object from wherever it is.
+pub(crate) async fn download_range(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    object_name: &str,
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>> {
+    match provider
+        .download_range(&blob_path(layout, object_name)?, offset, len)
+        .await
+    {
+        Err(Error::NotFound(_)) => {
+            provider
+                .download_range(&blob_path(other(layout), object_name)?, offset, len)
+                .await
+        }
+        result => result,
+    }
+}
+
+/// Provider metadata of a blob object from wherever it is.
+pub(crate) async fn metadata(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    object_name: &str,
+) -> Result<Metadata> {
+    match provider.metadata(&blob_path(layout, object_name)?).await {
+        Err(Error::NotFound(_)) => {
+            provider
+                .metadata(&blob_path(other(layout), object_name)?)
+                .await
+        }
+        result => result,
+    }
+}
+
+/// Delete a blob object from wherever it is.
+pub(crate) async fn delete(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    object_name: &str,
+) -> Result<()> {
+    match provider.delete(&blob_path(layout, object_name)?).await {
+        Err(Error::NotFound(_)) => {
+            provider
+                .delete(&blob_path(other(layout), object_name)?)
+                .await
+        }
+        result => result,
+    }
+}
+
+/// Stream a blob object from wherever it is on `source` to its place in
+/// `layout` on `dest`. A copy an interrupted earlier move left on `dest` is
+/// replaced.
+pub(crate) async fn copy_between(
+    source: &dyn StorageProvider,
+    dest: &dyn StorageProvider,
+    layout: DataLayout,
+    object_name: &str,
+) -> Result<Metadata> {
+    let to = blob_path(layout, object_name)?;
+    let from = if source.exists(&to).await? {
+        to.clone()
+    } else {
+        blob_path(other(layout), object_name)?
+    };
+    if layout == DataLayout::Sharded {
+        if let Some(shard_dir) = to.parent() {
+            match dest.create_dir(&shard_dir).await {
+                Ok(_) | Err(Error::AlreadyExists(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    if dest.exists(&to).await? {
+        dest.delete(&to).await?;
+    }
+    stream_copy(source, &from, dest, &to).await
+}
+
+/// Whether a blob object exists in either layout.
+pub(crate) async fn exists(provider: &dyn StorageProvider, object_name: &str) -> Result<bool> {
+    for layout in [DataLayout::Flat, DataLayout::Sharded] {
+        if provider.exists(&blob_path(layout, object_name)?).await? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Names of all blob objects in the data directory, flat and sharded.
+///
+/// Entries that are neither files nor shard directories are skipped.
+pub async fn list_objects(provider: &dyn StorageProvider) -> Result<Vec<String>> {
+    let data_dir = VaultPath::parse(DATA_DIRNAME)?;
+    let mut names = Vec::new();
+    let mut shards = Vec::new();
+    let mut entries = list_all(provider, &data_dir);
+    while let Some(entry) = entries.try_next().await? {
+        if !entry.is_directory {
+            names.push(entry.name);
+        } else if is_shard_dir(&entry) {
+            shards.push(entry.name);
+        }
+    }
+    for shard in shards {
+        let shard_dir = data_dir.join_unchecked(&shard);
+        let mut entries = list_all(provider, &shard_dir);
+        while let Some(entry) = entries.try_next().await? {
+            if !entry.is_directory {
+                names.push(entry.name);
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Move every flat blob object into its shard, one shard at a time.
+///
+/// Emits [`VaultEvent::DataShardMigrated`] after each shard. Safe to run
+/// again after an interruption: objects already moved are no longer flat.
+pub(crate) async fn shard_blobs(session: &VaultSession) -> Result<ShardReport> {
+    let provider = session.provider();
+    let data_dir = VaultPath::parse(DATA_DIRNAME)?;
+
+    let mut by_shard: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut entries = list_all(provider.as_ref(), &data_dir);
+    while let Some(entry) = entries.try_next().await? {
+        if !entry.is_directory {
+            by_shard
+                .entry(shard_of(&entry.name))
+                .or_default()
+                .push(entry.name);
+        }
+    }
+    drop(entries);
+
+    let mut report = ShardReport::default();
+    for (shard, names) in by_shard {
+        let shard_dir = data_dir.join_unchecked(&shard);
+        if !provider.exists(&shard_dir).await? {
+            provider.create_dir(&shard_dir).await?;
+        }
+        for name in &names {
+            let from = blob_path(DataLayout::Flat, name)?;
+            let to = blob_path(DataLayout::Sharded, name)?;
+            match provider.rename(&from, &to).await {
+                Ok(_) => {}
+                // Rewritten while the vault was still flat after an earlier
+                // run had moved it, or a copy-based rename stopped before
+                // removing the source. The flat copy is current either way.
+                Err(Error::AlreadyExists(_)) => {
+                    provider.delete(&to).await?;
+                    provider.rename(&from, &to).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        debug!(%shard, moved = names.len(), "Data shard migrated");
+        report.moved += names.len();
+        report.shards += 1;
+        session.emit(VaultEvent::DataShardMigrated {
+            shard,
+            moved: names.len(),
+        });
+    }
+
+    info!(
+        moved = report.moved,
+        shards = report.shards,
+        "Flat blobs moved into shards"
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_paths() {
+        let shard = shard_of("c2VjcmV0");
+        assert_eq!(shard.len(), 2);
+        assert!(shard.bytes().all(|b| b.is_ascii_hexdigit()));
+        // Parts live beside their blob.
+        assert_eq!(shard_of("c2VjcmV0.part3"), shard);
+
+        assert_eq!(
+            blob_path(DataLayout::Flat, "c2VjcmV0").unwrap().to_string(),
+            "/d/c2VjcmV0"
+        );
+        assert_eq!(
+            blob_path(DataLayout::Sharded, "c2VjcmV0.part3")
+                .unwrap()
+                .to_string(),
+            format!("/d/{}/c2VjcmV0.part3", shard)
+        );
+    }
+}
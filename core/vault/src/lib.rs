@@ -5,25 +5,84 @@
 //! - Encrypted file and directory operations
 //! - Metadata management and persistence
 //! - Session handling with secure key management
+//! - Background re-encryption of outdated blob formats
+//! - Per-directory policies inherited by subtrees
+//! - Resumable export of decrypted content to a local directory
+//! - Templates describing the initial structure of a vault
+//! - An on-device blob cache for reading files while offline
+//! - Sharding of the data directory for vaults with many files
+//! - Repair of the tree from the blobs found in storage
+//! - Opt-in encrypted full-text search over file contents
+//! - A write-ahead journal that repairs interrupted file operations on open
+//! - Integrity verification, with a cheap sampled tier backed by chunk manifests
+//! - A per-vault cipher suite for new file blobs, read alongside older blobs
+//! - Attach points storing the blobs of a subtree on a second provider
+//! - Optional access-time tracking for finding stale files
+//! - Journaled migrations of unlocked vaults, applied on open when safe
 //!
 //! # Architecture
 //! The vault module sits between the user interface and storage providers,
 //! handling all encryption/decryption operations transparently.
 
+pub mod access_time;
+pub mod attach;
+pub mod blob;
+pub mod blob_cache;
 pub mod config;
+pub mod diff;
+pub mod events;
+pub mod export;
 pub mod health;
+pub mod journal;
+pub mod layout;
+pub mod maintenance;
 pub mod manager;
 pub mod migration;
+pub mod migrations;
 pub mod operations;
+pub mod parts;
+pub mod policy;
+pub mod repair;
+#[cfg(feature = "search")]
+pub mod search;
 pub mod session;
+pub mod stream_budget;
+pub mod template;
 pub mod tree;
+pub mod verify;
 
-pub use config::{VaultConfig, VaultVersion};
+pub use access_time::AccessTimeFlushHandle;
+pub use attach::StorageUsage;
+pub use blob::{BlobCipher, BlobFormat, BlobFormatStats, BlobHeader, StagedBlob};
+pub use blob_cache::BlobCache;
+pub use config::{
+    AttachPoint, DataLayout, KeySource, KeyVerificationAlgorithm, LongNamePolicy, NameLimit,
+    VaultConfig, VaultMetadata, VaultVersion,
+};
 // Re-export unified health types from common alongside vault-specific check functions.
 pub use axiomvault_common::health::{DiagnosticResult, HealthReport, HealthStatus, Severity};
+pub use diff::{DiffEntry, DiffKind, DiffOptions, DiffReport, DiffTarget};
+pub use events::{VaultEvent, VaultEventReceiver};
+pub use export::{remove_partial_export, ExportOptions, ExportReport};
 pub use health::{check_vault_health, check_vault_structure};
-pub use manager::{VaultCreation, VaultManager};
+pub use layout::ShardReport;
+pub use maintenance::{MaintenanceHandle, MaintenancePolicy, MaintenanceReport};
+pub use manager::{TreeSummary, VaultCreation, VaultManager, VaultProbe, VaultSummary};
 pub use migration::{check_migration_needed, Migration, MigrationRegistry, MigrationStatus};
-pub use operations::VaultOperations;
-pub use session::{SessionHandle, VaultSession};
-pub use tree::{NodeType, TreeNode, VaultTree};
+pub use migrations::{
+    MigrationKind, MigrationOutcome, MigrationOutput, MigrationPlan, MigrationProgress,
+    MigrationRecord, SessionMigration, SessionMigrationRegistry,
+};
+pub use operations::{DirEntry, VaultOperations};
+pub use parts::BlobPart;
+pub use policy::{DirectoryPolicy, EffectivePolicy, SyncPolicy, Versioning};
+pub use repair::{RepairOptions, RepairReport};
+#[cfg(feature = "search")]
+pub use search::{IndexReport, IndexerHandle, SearchConfig, SearchHit, SearchIndex, TextMatch};
+pub use session::{ChangePasswordPlan, SessionHandle, SessionPolicy, SessionState, VaultSession};
+pub use stream_budget::{StreamBudget, StreamReservation};
+pub use template::{
+    TemplateConflict, TemplateDirectory, TemplateFile, TemplateReport, VaultTemplate,
+};
+pub use tree::{NodePermissions, NodeType, TreeNode, VaultTree};
+pub use verify::{ChunkManifest, FileVerification, VerifyLevel, VerifyOptions, VerifyReport};
@@ -0,0 +1,578 @@
+//! Background re-encryption of outdated file blobs.
+//!
+//! Maintenance walks the tree for files whose blob format is older than the
+//! target and rewrites them one at a time. Upgrades are copy-on-write: the
+//! new blob is uploaded under a fresh storage name and only swapped into the
+//! tree if the file was not touched in the meantime, so a foreground write
+//! always wins and the file is simply retried later.
+//!
+//! Progress lives in the tree itself: each upgraded node records its new
+//! format and the tree is saved after every file, so an interrupted run
+//! resumes where it stopped.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::blob::{decrypt_blob, encrypt_blob, BlobFormat};
+use crate::events::VaultEvent;
+use crate::operations::{BlobName, VaultOperations};
+use crate::parts;
+use crate::session::VaultSession;
+use axiomvault_common::{Error, Result, VaultPath};
+
+/// Rate limits and target for a maintenance run.
+#[derive(Debug, Clone)]
+pub struct MaintenancePolicy {
+    /// Format to upgrade blobs to.
+    pub target: BlobFormat,
+    /// Maximum files upgraded per minute. `None` means unlimited.
+    pub max_files_per_minute: Option<u32>,
+    /// Maximum plaintext bytes re-encrypted per second. `None` means
+    /// unlimited.
+    pub max_bytes_per_second: Option<u64>,
+    /// Delay before retrying files that were deferred.
+    pub retry_delay: Duration,
+}
+
+impl Default for MaintenancePolicy {
+    fn default() -> Self {
+        Self {
+            target: BlobFormat::LATEST,
+            max_files_per_minute: Some(60),
+            max_bytes_per_second: Some(4 * 1024 * 1024),
+            retry_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl MaintenancePolicy {
+    /// Pause to take after upgrading a file of `bytes` plaintext bytes.
+    fn pause_after(&self, bytes: u64) -> Duration {
+        let per_file = self
+            .max_files_per_minute
+            .filter(|n| *n > 0)
+            .map(|n| Duration::from_secs(60) / n)
+            .unwrap_or_default();
+        let per_bytes = self
+            .max_bytes_per_second
+            .filter(|n| *n > 0)
+            .map(|n| Duration::from_secs_f64(bytes as f64 / n as f64))
+            .unwrap_or_default();
+        per_file.max(per_bytes)
+    }
+}
+
+/// Outcome of one or more maintenance passes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Files upgraded.
+    pub upgraded: usize,
+    /// Files skipped because they were in use or changed.
+    pub deferred: usize,
+    /// Files that could not be upgraded (e.g. unreadable blob).
+    pub failed: usize,
+    /// Files still below the target format afterwards.
+    pub remaining: usize,
+}
+
+/// Handle to a running background maintenance task.
+pub struct MaintenanceHandle {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<Result<MaintenanceReport>>,
+}
+
+impl MaintenanceHandle {
+    /// Ask the task to stop after the file it is currently upgrading.
+    pub fn stop(&self) {
+        let _ = self.stop.send(true);
+    }
+
+    /// Whether the task has exited.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Wait for the task to exit and return its combined report.
+    pub async fn join(self) -> Result<MaintenanceReport> {
+        self.task
+            .await
+            .map_err(|e| Error::Vault(format!("Maintenance task failed: {}", e)))?
+    }
+}
+
+/// Result of trying to upgrade a single file.
+enum Upgrade {
+    /// Re-encrypted; carries the plaintext size.
+    Done(u64),
+    /// In use or changed concurrently; retry later.
+    Deferred,
+    /// Removed, moved, or already at the target format.
+    Gone,
+}
+
+pub(crate) fn spawn(session: Arc<VaultSession>, policy: MaintenancePolicy) -> MaintenanceHandle {
+    let (stop, mut stopped) = watch::channel(false);
+    let task = tokio::spawn(async move {
+        let mut total = MaintenanceReport::default();
+        loop {
+            let pass = run_pass(&session, &policy, Some(&mut stopped)).await?;
+            total.upgraded += pass.upgraded;
+            total.deferred += pass.deferred;
+            total.failed += pass.failed;
+            total.remaining = pass.remaining;
+
+            let no_progress = pass.upgraded == 0 && pass.deferred == 0;
+            if pass.remaining == 0 || *stopped.borrow() || no_progress {
+                break;
+            }
+            if !pause(policy.retry_delay, Some(&mut stopped)).await {
+                break;
+            }
+        }
+
+        session.emit(VaultEvent::MaintenanceFinished {
+            upgraded: total.upgraded,
+            remaining: total.remaining,
+        });
+        info!(
+            upgraded = total.upgraded,
+            remaining = total.remaining,
+            "Maintenance finished"
+        );
+        Ok(total)
+    });
+    MaintenanceHandle { stop, task }
+}
+
+/// Upgrade every outdated file once, honouring the policy's rate limits.
+pub(crate) async fn run_pass(
+    session: &VaultSession,
+    policy: &MaintenancePolicy,
+    mut stopped: Option<&mut watch::Receiver<bool>>,
+) -> Result<MaintenanceReport> {
+    let pending = session
+        .tree()
+        .read()
+        .await
+        .files_below_format(policy.target);
+    debug!(pending = pending.len(), "Maintenance pass started");
+
+    let mut report = MaintenanceReport::default();
+    for path in pending {
+        if stopped.as_deref().is_some_and(|rx| *rx.borrow()) {
+            break;
+        }
+        match upgrade_file(session, &path, policy.target).await {
+            Ok(Upgrade::Done(bytes)) => {
+                report.upgraded += 1;
+                if !pause(policy.pause_after(bytes), stopped.as_deref_mut()).await {
+                    break;
+                }
+            }
+            Ok(Upgrade::Deferred) => {
+                report.deferred += 1;
+                session.emit(VaultEvent::BlobUpgradeDeferred {
+                    path: path.to_string(),
+                });
+            }
+            Ok(Upgrade::Gone) => {}
+            Err(e) => {
+                warn!("Failed to upgrade blob: {}", e);
+                report.failed += 1;
+            }
+        }
+    }
+
+    report.remaining = session
+        .tree()
+        .read()
+        .await
+        .format_stats(policy.target)
+        .remaining_files();
+    Ok(report)
+}
+
+/// Sleep for `duration`, returning early with `false` if stopped.
+async fn pause(duration: Duration, stopped: Option<&mut watch::Receiver<bool>>) -> bool {
+    match stopped {
+        None => {
+            tokio::time::sleep(duration).await;
+            true
+        }
+        Some(rx) => {
+            if *rx.borrow() {
+                return false;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(duration) => true,
+                _ = rx.changed() => !*rx.borrow(),
+            }
+        }
+    }
+}
+
+async fn upgrade_file(
+    session: &VaultSession,
+    path: &VaultPath,
+    target: BlobFormat,
+) -> Result<Upgrade> {
+    // Snapshot the node. A foreground operation holding the blob wins.
+    let (name, old_name, old_format, old_parts, modified_at) = {
+        let tree = session.tree().read().await;
+        let Ok(node) = tree.get_node(path) else {
+            return Ok(Upgrade::Gone);
+        };
+        if !node.is_file() || node.metadata.blob_format >= target {
+            return Ok(Upgrade::Gone);
+        }
+        if session.is_blob_busy(&node.metadata.encrypted_name) {
+            return Ok(Upgrade::Deferred);
+        }
+        (
+            node.metadata.name.clone(),
+            node.metadata.encrypted_name.clone(),
+            node.metadata.blob_format,
+            node.metadata.parts.clone(),
+            node.metadata.modified_at,
+        )
+    };
+
+    let provider = session.blob_provider(path)?;
+    let layout = session.data_layout();
+
+    let ciphertext = parts::download(provider.as_ref(), layout, &old_name, &old_parts).await?;
+    let master_key = session.master_key()?;
+    let content = zeroize::Zeroizing::new(decrypt_blob(
+        old_format,
+        &master_key,
+        &old_name,
+        &ciphertext,
+    )?);
+
+    // Write the upgraded blob beside the old one.
+    // A file that predates a rejecting name limit keeps a long name rather
+    // than stalling the upgrade.
+    let ops = VaultOperations::new(session)?;
+    let BlobName {
+        key: new_name,
+        long_name,
+    } = match ops.blob_name(&name) {
+        Err(Error::InvalidInput(_)) => BlobName {
+            key: ops.encrypt_name(&name)?,
+            long_name: None,
+        },
+        result => result?,
+    };
+    let suite = session.config().write_suite()?;
+    let blob = encrypt_blob(target, suite, &master_key, &new_name, &content)?;
+    let uploaded = parts::upload(
+        provider.as_ref(),
+        layout,
+        &new_name,
+        blob,
+        session.part_size(),
+    )
+    .await?;
+    let new_parts = uploaded.parts;
+
+    // Swap it in only if nothing touched the file meanwhile.
+    let swapped = {
+        let mut tree = session.tree().write().await;
+        match tree.get_node_mut(path) {
+            Ok(node)
+                if node.metadata.encrypted_name == old_name
+                    && node.metadata.modified_at == modified_at
+                    && !session.is_blob_busy(&old_name) =>
+            {
+                node.metadata.encrypted_name = new_name.clone();
+                node.metadata.long_encrypted_name = long_name;
+                node.metadata.blob_format = target;
+                node.metadata.parts = new_parts.clone();
+                node.metadata.manifest = Some(uploaded.manifest);
+                true
+            }
+            _ => false,
+        }
+    };
+
+    if !swapped {
+        if let Err(e) = parts::delete(provider.as_ref(), layout, &new_name, &new_parts).await {
+            warn!("Failed to remove unused upgraded blob: {}", e);
+        }
+        return Ok(Upgrade::Deferred);
+    }
+
+    session.save_tree().await?;
+    if let Err(e) = parts::delete(provider.as_ref(), layout, &old_name, &old_parts).await {
+        // Leaves an orphan that the health check reports; data is intact.
+        warn!("Failed to remove superseded blob: {}", e);
+    }
+
+    session.emit(VaultEvent::BlobUpgraded {
+        path: path.to_string(),
+        from: old_format,
+        to: target,
+    });
+    Ok(Upgrade::Done(content.len() as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{VaultConfig, DATA_DIRNAME};
+    use axiomvault_common::Secret;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::{CipherSuite, KdfParams};
+    use axiomvault_storage::{MemoryProvider, StorageProvider};
+    use std::time::Instant;
+
+    const PASSWORD: &[u8] = b"test-password";
+
+    async fn create_vault() -> (VaultConfig, Arc<dyn StorageProvider>) {
+        let id = VaultId::new("test").unwrap();
+        let creation = VaultConfig::new(
+            id,
+            PASSWORD,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let provider: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+        provider
+            .create_dir(&VaultPath::parse("/d").unwrap())
+            .await
+            .unwrap();
+        provider
+            .create_dir(&VaultPath::parse("/m").unwrap())
+            .await
+            .unwrap();
+        (creation.config, provider)
+    }
+
+    async fn open(config: &VaultConfig, provider: &Arc<dyn StorageProvider>) -> Arc<VaultSession> {
+        let master_key = config
+            .verify_password(&Secret::from_slice(PASSWORD))
+            .unwrap()
+            .unwrap();
+        let tree = VaultSession::load_and_decrypt_tree(provider, &master_key)
+            .await
+            .unwrap();
+        Arc::new(
+            VaultSession::from_master_key(config.clone(), master_key, provider.clone(), tree)
+                .unwrap(),
+        )
+    }
+
+    /// Rewrite a file's blob in the legacy V1 format, as older builds did.
+    async fn downgrade(session: &VaultSession, path: &VaultPath) {
+        let content = VaultOperations::new(session)
+            .unwrap()
+            .read_file(path)
+            .await
+            .unwrap();
+        let name = {
+            let mut tree = session.tree().write().await;
+            let node = tree.get_node_mut(path).unwrap();
+            node.metadata.blob_format = BlobFormat::V1;
+            node.metadata.encrypted_name.clone()
+        };
+        let blob = encrypt_blob(
+            BlobFormat::V1,
+            CipherSuite::default(),
+            &session.master_key().unwrap(),
+            &name,
+            &content,
+        )
+        .unwrap();
+        let storage_path = VaultPath::parse(DATA_DIRNAME).unwrap().join(&name).unwrap();
+        session
+            .provider()
+            .upload(&storage_path, blob)
+            .await
+            .unwrap();
+        session.save_tree().await.unwrap();
+    }
+
+    /// Create `count` files, downgrading every one except the first.
+    async fn populate_mixed(session: &VaultSession, count: usize) -> Vec<(VaultPath, Vec<u8>)> {
+        let ops = VaultOperations::new(session).unwrap();
+        ops.create_directory(&VaultPath::parse("/docs").unwrap())
+            .await
+            .unwrap();
+        let mut files = Vec::new();
+        for i in 0..count {
+            let path = VaultPath::parse(&format!("/docs/file-{}.txt", i)).unwrap();
+            let content = format!("content of file {}", i).repeat(i + 1).into_bytes();
+            ops.create_file(&path, &content).await.unwrap();
+            if i > 0 {
+                downgrade(session, &path).await;
+            }
+            files.push((path, content));
+        }
+        files
+    }
+
+    fn unthrottled() -> MaintenancePolicy {
+        MaintenancePolicy {
+            target: BlobFormat::LATEST,
+            max_files_per_minute: None,
+            max_bytes_per_second: None,
+            retry_delay: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resumes_after_interruption_without_data_loss() {
+        let (config, provider) = create_vault().await;
+        let session = open(&config, &provider).await;
+        let files = populate_mixed(&session, 6).await;
+
+        let stats = session.tree().read().await.format_stats(BlobFormat::LATEST);
+        assert_eq!(stats.total_files, 6);
+        assert_eq!(stats.upgraded_files, 1);
+
+        // Interrupt after the second upgrade.
+        let mut events = session.subscribe();
+        let handle = session.start_maintenance(MaintenancePolicy {
+            max_files_per_minute: Some(600),
+            ..unthrottled()
+        });
+        let mut seen = 0;
+        while seen < 2 {
+            if let VaultEvent::BlobUpgraded { from, to, .. } = events.recv().await.unwrap() {
+                assert_eq!((from, to), (BlobFormat::V1, BlobFormat::LATEST));
+                seen += 1;
+            }
+        }
+        handle.stop();
+        let first = handle.join().await.unwrap();
+        assert!(first.upgraded >= 2);
+        assert!(first.remaining > 0);
+        drop(session);
+
+        // A new session picks up where the last one stopped.
+        let session = open(&config, &provider).await;
+        let stats = session.tree().read().await.format_stats(BlobFormat::LATEST);
+        assert_eq!(stats.upgraded_files, 1 + first.upgraded);
+
+        let second = session
+            .start_maintenance(unthrottled())
+            .join()
+            .await
+            .unwrap();
+        assert_eq!(second.upgraded, 5 - first.upgraded);
+        assert_eq!(second.remaining, 0);
+
+        let ops = VaultOperations::new(&session).unwrap();
+        for (path, content) in &files {
+            assert_eq!(&ops.read_file(path).await.unwrap(), content);
+        }
+
+        // Superseded blobs were removed: one blob per file.
+        let blobs = provider
+            .list(&VaultPath::parse(DATA_DIRNAME).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(blobs.len(), files.len());
+    }
+
+    #[tokio::test]
+    async fn test_foreground_use_defers_upgrade() {
+        let (config, provider) = create_vault().await;
+        let session = open(&config, &provider).await;
+        let files = populate_mixed(&session, 2).await;
+        let (path, content) = &files[1];
+
+        let name = session
+            .tree()
+            .read()
+            .await
+            .get_node(path)
+            .unwrap()
+            .metadata
+            .encrypted_name
+            .clone();
+        let in_use = session.blob_in_use(&name);
+
+        let report = session.run_maintenance_pass(&unthrottled()).await.unwrap();
+        assert_eq!(report.upgraded, 0);
+        assert_eq!(report.deferred, 1);
+        assert_eq!(report.remaining, 1);
+
+        drop(in_use);
+        let report = session.run_maintenance_pass(&unthrottled()).await.unwrap();
+        assert_eq!(report.upgraded, 1);
+        assert_eq!(report.remaining, 0);
+
+        let ops = VaultOperations::new(&session).unwrap();
+        assert_eq!(&ops.read_file(path).await.unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn test_foreground_update_upgrades_format() {
+        let (config, provider) = create_vault().await;
+        let session = open(&config, &provider).await;
+        let files = populate_mixed(&session, 2).await;
+        let (path, _) = &files[1];
+
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.update_file(path, b"rewritten").await.unwrap();
+
+        let tree = session.tree().read().await;
+        assert_eq!(
+            tree.get_node(path).unwrap().metadata.blob_format,
+            BlobFormat::LATEST
+        );
+        assert!(tree.files_below_format(BlobFormat::LATEST).is_empty());
+        drop(tree);
+        assert_eq!(ops.read_file(path).await.unwrap(), b"rewritten");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_foreground_reads_stay_fast_during_maintenance() {
+        let (config, provider) = create_vault().await;
+        let session = open(&config, &provider).await;
+        let files = populate_mixed(&session, 12).await;
+
+        let handle = session.start_maintenance(MaintenancePolicy {
+            max_files_per_minute: Some(1200),
+            ..unthrottled()
+        });
+
+        let ops = VaultOperations::new(&session).unwrap();
+        let mut slowest = Duration::ZERO;
+        while !handle.is_finished() {
+            for (path, content) in &files {
+                let started = Instant::now();
+                assert_eq!(&ops.read_file(path).await.unwrap(), content);
+                slowest = slowest.max(started.elapsed());
+            }
+        }
+        let report = handle.join().await.unwrap();
+
+        assert_eq!(report.remaining, 0);
+        assert!(
+            slowest < Duration::from_millis(500),
+            "foreground read took {:?}",
+            slowest
+        );
+    }
+
+    #[test]
+    fn test_pause_respects_both_limits() {
+        let policy = MaintenancePolicy {
+            target: BlobFormat::LATEST,
+            max_files_per_minute: Some(60),
+            max_bytes_per_second: Some(1000),
+            retry_delay: Duration::ZERO,
+        };
+        assert_eq!(policy.pause_after(10), Duration::from_secs(1));
+        assert_eq!(policy.pause_after(5000), Duration::from_secs(5));
+        assert_eq!(unthrottled().pause_after(u64::MAX), Duration::ZERO);
+    }
+}
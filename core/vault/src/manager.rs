@@ -2,12 +2,24 @@
 
 use std::sync::Arc;
 
-use crate::config::{VaultConfig, CONFIG_FILENAME, DATA_DIRNAME, META_DIRNAME};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::attach;
+use crate::config::{
+    AttachPoint, DataLayout, KeySource, VaultConfig, VaultMetadata, VaultVersion, CONFIG_FILENAME,
+    DATA_DIRNAME, META_DIRNAME,
+};
+use crate::layout::{self, ShardReport};
+use crate::migrations::{MigrationKind, SessionMigrationRegistry};
+use crate::operations::VaultOperations;
 use crate::session::VaultSession;
+use crate::template::{TemplateConflict, VaultTemplate};
 use crate::tree::VaultTree;
-use axiomvault_common::{Error, Result, VaultId, VaultPath};
+use axiomvault_common::{Error, Result, Secret, VaultId, VaultPath};
 use axiomvault_crypto::recovery::RecoveryKey;
-use axiomvault_crypto::KdfParams;
+use axiomvault_crypto::{KdfParams, MasterKey};
 use axiomvault_storage::{create_default_registry, ProviderRegistry, StorageProvider};
 use zeroize::Zeroizing;
 
@@ -19,7 +31,89 @@ pub struct VaultCreation {
     pub recovery_words: Zeroizing<String>,
 }
 
+/// What a vault's configuration reveals before it is unlocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultProbe {
+    /// Vault identifier.
+    pub vault_id: VaultId,
+    /// On-disk format version.
+    pub version: VaultVersion,
+    /// Provider type recorded at creation.
+    pub provider_type: String,
+    /// Parameters an unlock will derive the key with.
+    pub kdf_params: KdfParams,
+}
+
+/// Non-secret facts about a vault, read from its configuration.
+///
+/// Holds nothing that helps an attacker beyond what the config already
+/// exposes to anyone with storage access: no salt, verification tag,
+/// wrapped key, or provider settings (which may contain tokens).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VaultSummary {
+    /// Vault identifier.
+    pub vault_id: VaultId,
+    /// Display name, description, icon and color. Read before unlocking,
+    /// so not yet checked against the config MAC.
+    pub metadata: VaultMetadata,
+    /// On-disk format version.
+    pub version: VaultVersion,
+    /// Provider type recorded at creation.
+    pub provider_type: String,
+    /// When the vault was created.
+    pub created_at: DateTime<Utc>,
+    /// When the configuration last changed.
+    pub modified_at: DateTime<Utc>,
+    /// Parameters an unlock will derive the key with.
+    pub kdf_params: KdfParams,
+    /// Whether the vault is unlocked with a password or an external key.
+    pub key_source: KeySource,
+    /// Whether unlocking needs a keyfile.
+    pub keyfile_required: bool,
+    /// Whether a recovery key can reset the password.
+    pub recovery_key_configured: bool,
+    /// Whether the vault still uses the legacy (v1.0) key model.
+    pub legacy_format: bool,
+    /// Whether the configuration carries a tamper-evident MAC.
+    pub config_mac_present: bool,
+    /// Tree statistics, only filled in by
+    /// [`VaultManager::inspect_with_password`].
+    pub tree: Option<TreeSummary>,
+}
+
+impl VaultSummary {
+    fn from_config(config: &VaultConfig) -> Self {
+        Self {
+            vault_id: config.id.clone(),
+            metadata: config.metadata(),
+            version: config.version,
+            provider_type: config.provider_type.clone(),
+            created_at: config.created_at,
+            modified_at: config.modified_at,
+            kdf_params: config.kdf_params.clone(),
+            key_source: config.key_source,
+            keyfile_required: config.keyfile_required,
+            recovery_key_configured: config.recovery_wrapped_master_key.is_some(),
+            legacy_format: config.is_legacy_format(),
+            config_mac_present: config.config_mac.is_some(),
+            tree: None,
+        }
+    }
+}
+
+/// Size of a vault's content, from its decrypted tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TreeSummary {
+    /// Number of files.
+    pub file_count: usize,
+    /// Plaintext bytes across all files.
+    pub total_size: u64,
+}
+
 /// Vault manager for creating and opening vaults.
+///
+/// Clones share the same provider registry.
+#[derive(Clone)]
 pub struct VaultManager {
     registry: ProviderRegistry,
 }
@@ -47,6 +141,27 @@ impl VaultManager {
         &mut self.registry
     }
 
+    /// Validate a provider configuration before creating or opening a vault.
+    ///
+    /// Frontends call this to report configuration problems up front; the
+    /// create and open paths run the same check when resolving the provider.
+    ///
+    /// # Errors
+    /// - `NotFound` if the provider type is not registered
+    /// - `InvalidInput` describing what is wrong with the configuration
+    pub fn validate_provider_config(
+        &self,
+        provider_type: &str,
+        provider_config: &serde_json::Value,
+    ) -> Result<()> {
+        self.registry.validate(provider_type, provider_config)
+    }
+
+    /// JSON Schema of the configuration accepted by a provider type.
+    pub fn provider_config_schema(&self, provider_type: &str) -> Result<serde_json::Value> {
+        self.registry.config_schema(provider_type)
+    }
+
     /// Create a new vault.
     ///
     /// # Returns
@@ -54,7 +169,57 @@ impl VaultManager {
     pub async fn create_vault(
         &self,
         vault_id: VaultId,
-        password: &[u8],
+        password: &Secret,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        kdf_params: KdfParams,
+    ) -> Result<VaultCreation> {
+        self.create_vault_with_keyfile(
+            vault_id,
+            password,
+            None,
+            provider_type,
+            provider_config,
+            kdf_params,
+        )
+        .await
+    }
+
+    /// Create a new vault that requires a keyfile in addition to the password.
+    ///
+    /// With `keyfile` set to `None` this is the same as `create_vault`.
+    pub async fn create_vault_with_keyfile(
+        &self,
+        vault_id: VaultId,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        kdf_params: KdfParams,
+    ) -> Result<VaultCreation> {
+        self.create_vault_with_recovery_key(
+            vault_id,
+            password,
+            keyfile,
+            &RecoveryKey::generate(),
+            provider_type,
+            provider_config,
+            kdf_params,
+        )
+        .await
+    }
+
+    /// Create a new vault whose recovery key was generated beforehand.
+    ///
+    /// Nothing is written to storage before this call, so a frontend can
+    /// show the recovery words and have them confirmed first.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_vault_with_recovery_key(
+        &self,
+        vault_id: VaultId,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+        recovery_key: &RecoveryKey,
         provider_type: &str,
         provider_config: serde_json::Value,
         kdf_params: KdfParams,
@@ -63,9 +228,11 @@ impl VaultManager {
             .registry
             .resolve(provider_type, provider_config.clone())?;
 
-        let creation = VaultConfig::new(
+        let creation = VaultConfig::new_with_recovery_key(
             vault_id,
-            password,
+            password.expose(),
+            keyfile,
+            recovery_key,
             provider_type,
             provider_config,
             kdf_params,
@@ -88,6 +255,76 @@ impl VaultManager {
         })
     }
 
+    /// Create a new vault around a master key held by an external key
+    /// management system.
+    ///
+    /// The vault has no password and no recovery key; it opens only with
+    /// [`open_vault_with_key`](Self::open_vault_with_key).
+    pub async fn create_vault_with_key(
+        &self,
+        vault_id: VaultId,
+        master_key: MasterKey,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+    ) -> Result<VaultSession> {
+        let provider = self
+            .registry
+            .resolve(provider_type, provider_config.clone())?;
+
+        let config = VaultConfig::new_with_external_key(
+            vault_id,
+            &master_key,
+            provider_type,
+            provider_config,
+        )?;
+
+        self.initialize_vault_structure(&provider, &config).await?;
+
+        VaultSession::from_master_key(config, master_key, provider, VaultTree::new())
+    }
+
+    /// Create a new vault and lay out `template` in it.
+    ///
+    /// The template is validated before anything is created, and its
+    /// entries are written with a single tree save.
+    ///
+    /// # Errors
+    /// - `InvalidInput` naming the manifest entry that failed validation
+    /// - Any error of `create_vault_with_keyfile`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_vault_from_template(
+        &self,
+        vault_id: VaultId,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        kdf_params: KdfParams,
+        template: &VaultTemplate,
+    ) -> Result<VaultCreation> {
+        template.validate()?;
+
+        let mut creation = self
+            .create_vault_with_keyfile(
+                vault_id,
+                password,
+                keyfile,
+                provider_type,
+                provider_config,
+                kdf_params,
+            )
+            .await?;
+
+        if template.apply_defaults(creation.session.config_mut(), TemplateConflict::Error)? {
+            self.save_config(&creation.session).await?;
+        }
+        VaultOperations::new(&creation.session)?
+            .apply_template(template, TemplateConflict::Error)
+            .await?;
+
+        Ok(creation)
+    }
+
     /// Initialize vault directory structure.
     async fn initialize_vault_structure(
         &self,
@@ -116,7 +353,61 @@ impl VaultManager {
         &self,
         provider_type: &str,
         provider_config: serde_json::Value,
-        password: &[u8],
+        password: &Secret,
+    ) -> Result<VaultSession> {
+        self.open_vault_with_keyfile(provider_type, provider_config, password, None)
+            .await
+    }
+
+    /// Open an existing vault, supplying its keyfile if it has one.
+    ///
+    /// Migrations the vault needs are applied on the spot if they are
+    /// [automatic](MigrationKind::Automatic).
+    ///
+    /// # Errors
+    /// - `KeyfileRequired` if the vault needs a keyfile and none was given
+    /// - `KeyfileMismatch` if the keyfile does not belong to this vault
+    /// - `MigrationRequired` if the vault needs migrations the user must
+    ///   apply first; see [`open_vault_for_migration`](Self::open_vault_for_migration)
+    pub async fn open_vault_with_keyfile(
+        &self,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+    ) -> Result<VaultSession> {
+        let mut session = self
+            .unlock_with_password(provider_type, provider_config, password, keyfile)
+            .await?;
+        Self::migrate_on_open(&mut session).await?;
+        Ok(session)
+    }
+
+    /// Open an existing vault to migrate it, whatever migrations are
+    /// pending. None are applied; see [`SessionMigrationRegistry`].
+    ///
+    /// # Errors
+    /// Same as [`open_vault_with_keyfile`](Self::open_vault_with_keyfile),
+    /// except for `MigrationRequired`
+    pub async fn open_vault_for_migration(
+        &self,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+    ) -> Result<VaultSession> {
+        self.unlock_with_password(provider_type, provider_config, password, keyfile)
+            .await
+    }
+
+    /// Unlock a vault with its password and recover interrupted
+    /// operations, without looking at its migrations.
+    async fn unlock_with_password(
+        &self,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
     ) -> Result<VaultSession> {
         let provider = self.registry.resolve(provider_type, provider_config)?;
 
@@ -126,21 +417,90 @@ impl VaultManager {
         }
 
         let config_bytes = provider.download(&config_path).await?;
-        let config = VaultConfig::from_bytes(&config_bytes)?;
+        let mut config = VaultConfig::from_bytes(&config_bytes)?;
 
+        let single_salt = config.has_single_salt();
         let master_key = config
-            .verify_password(password)?
+            .unlock_and_split_salts(password, keyfile)?
             .ok_or_else(|| Error::NotPermitted("Invalid password".to_string()))?;
 
-        let tree = VaultSession::load_and_decrypt_tree(&provider, &master_key).await?;
+        let session = self.start_session(config, master_key, provider).await?;
+        if single_salt && !session.config().has_single_salt() {
+            self.save_split_salts(&session).await;
+        }
+        Self::recover_interrupted(&session).await;
+        Ok(session)
+    }
+
+    /// Open an existing vault with the master key from an external key
+    /// management system.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if the vault is unlocked with a password, or
+    ///   `master_key` is not its key
+    pub async fn open_vault_with_key(
+        &self,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        master_key: MasterKey,
+    ) -> Result<VaultSession> {
+        let provider = self.registry.resolve(provider_type, provider_config)?;
+        let mut config = Self::load_config(provider.as_ref()).await?;
+
+        if !config.verify_external_key(&master_key)? {
+            return Err(Error::NotPermitted("Invalid key".to_string()));
+        }
+        let split = config.split_salts(&master_key, None, &master_key)?;
+
+        let mut session = self.start_session(config, master_key, provider).await?;
+        if split {
+            self.save_split_salts(&session).await;
+        }
+        Self::recover_interrupted(&session).await;
+        Self::migrate_on_open(&mut session).await?;
+        Ok(session)
+    }
+
+    /// Open an existing vault with a wrapped key exported by
+    /// [`VaultConfig::wrap_key_for_device`].
+    ///
+    /// # Errors
+    /// - `WrappedKeyStale` if the password changed since the key was wrapped
+    /// - `Crypto` if `wrapping_key` does not match the blob
+    pub async fn open_vault_with_wrapped_key(
+        &self,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        wrapping_key: &[u8],
+        wrapped_key: &[u8],
+    ) -> Result<VaultSession> {
+        let provider = self.registry.resolve(provider_type, provider_config)?;
+
+        let config_path = VaultPath::parse(CONFIG_FILENAME)?;
+        if !provider.exists(&config_path).await? {
+            return Err(Error::NotFound("Vault configuration not found".to_string()));
+        }
+
+        let config_bytes = provider.download(&config_path).await?;
+        let config = VaultConfig::from_bytes(&config_bytes)?;
+
+        let master_key = config.unwrap_device_key(wrapped_key, wrapping_key)?;
 
-        VaultSession::from_master_key(config, master_key, provider, tree)
+        let mut session = self.start_session(config, master_key, provider).await?;
+        Self::recover_interrupted(&session).await;
+        Self::migrate_on_open(&mut session).await?;
+        Ok(session)
     }
 
     /// Reset vault password using recovery key words.
     ///
+    /// The recovery key bypasses any keyfile, so a vault whose keyfile was
+    /// lost can still be recovered. Afterwards the vault opens with
+    /// `new_password` alone.
+    ///
     /// # Postconditions
     /// - Vault password is changed to new_password
+    /// - Keyfile requirement is removed
     /// - Recovery key is unchanged
     /// - Returns an active session
     pub async fn recover_vault(
@@ -148,7 +508,7 @@ impl VaultManager {
         provider_type: &str,
         provider_config: serde_json::Value,
         recovery_words: &str,
-        new_password: &[u8],
+        new_password: &Secret,
     ) -> Result<VaultSession> {
         let provider = self.registry.resolve(provider_type, provider_config)?;
 
@@ -168,17 +528,74 @@ impl VaultManager {
             .ok_or_else(|| Error::NotPermitted("Invalid recovery key".to_string()))?;
 
         // Load the tree with the master key before resetting the password.
-        let tree = VaultSession::load_and_decrypt_tree(&provider, &master_key).await?;
+        let (tree, legacy_tree) = VaultSession::load_tree(&provider, &master_key).await?;
 
         // Reset password in config. The master key itself doesn't change.
-        config.reset_password(&recovery_key, new_password)?;
+        config.reset_password(&recovery_key, new_password.expose())?;
 
         // Save updated config.
         let config_bytes = config.to_bytes()?;
         provider.upload(&config_path, config_bytes).await?;
 
         // Reuse the master key from recovery — no need for a second Argon2id round.
-        VaultSession::from_master_key(config, master_key, provider, tree)
+        let mut session = VaultSession::from_master_key(config, master_key, provider, tree)?;
+        session.set_legacy_tree(legacy_tree);
+        session.connect_attachments(&self.registry)?;
+        Self::recover_interrupted(&session).await;
+        Self::migrate_on_open(&mut session).await?;
+        Ok(session)
+    }
+
+    /// Load the tree and start a session on it.
+    async fn start_session(
+        &self,
+        config: VaultConfig,
+        master_key: MasterKey,
+        provider: Arc<dyn StorageProvider>,
+    ) -> Result<VaultSession> {
+        let (tree, legacy_tree) = VaultSession::load_tree(&provider, &master_key).await?;
+        let session = VaultSession::from_master_key(config, master_key, provider, tree)?;
+        session.set_legacy_tree(legacy_tree);
+        session.connect_attachments(&self.registry)?;
+        Ok(session)
+    }
+
+    /// Apply the automatic migrations a just-opened vault needs, and refuse
+    /// it if others are pending.
+    ///
+    /// A failed automatic migration only postpones it to the next open.
+    async fn migrate_on_open(session: &mut VaultSession) -> Result<()> {
+        let migrations = SessionMigrationRegistry::with_defaults();
+        let mut required = Vec::new();
+        for plan in migrations.pending(session).await? {
+            match plan.kind {
+                MigrationKind::Automatic => {
+                    if let Err(e) = migrations.apply(session, plan.id, &|_| {}).await {
+                        warn!(
+                            "Could not apply migration {} to vault {}: {}",
+                            plan.id,
+                            session.vault_id(),
+                            e
+                        );
+                    }
+                }
+                MigrationKind::Explicit => required.push(plan.id.to_string()),
+            }
+        }
+        if !required.is_empty() {
+            return Err(Error::MigrationRequired(required));
+        }
+        Ok(())
+    }
+
+    /// Reconcile operations a previous session was interrupted in.
+    ///
+    /// A failure leaves the journal in place for the next open rather than
+    /// refusing access to the vault.
+    async fn recover_interrupted(session: &VaultSession) {
+        if let Err(e) = session.recover_journal().await {
+            warn!("Failed to recover interrupted file operations: {}", e);
+        }
     }
 
     /// Check if a vault exists at the given location.
@@ -192,17 +609,225 @@ impl VaultManager {
         provider.exists(&config_path).await
     }
 
-    /// Save vault configuration to storage.
-    pub async fn save_config(&self, session: &VaultSession) -> Result<()> {
+    /// Read a vault's configuration without unlocking it.
+    ///
+    /// Lets a frontend confirm a location holds a vault, and which format
+    /// version, before prompting for a password. No key is derived.
+    ///
+    /// # Errors
+    /// - `NotFound` if there is no vault configuration at the location
+    /// - `Serialization` if the configuration cannot be parsed
+    pub async fn probe(
+        &self,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+    ) -> Result<VaultProbe> {
+        let provider = self.registry.resolve(provider_type, provider_config)?;
+        let config = Self::load_config(provider.as_ref()).await?;
+        Ok(VaultProbe {
+            vault_id: config.id,
+            version: config.version,
+            provider_type: config.provider_type,
+            kdf_params: config.kdf_params,
+        })
+    }
+
+    /// Summarize a vault from its configuration alone.
+    ///
+    /// Only the config object is downloaded; no password is needed and no
+    /// key is derived, so a frontend can show this on its unlock screen.
+    ///
+    /// # Errors
+    /// - `NotFound` if there is no vault configuration at the location
+    /// - `Serialization` if the configuration cannot be parsed
+    pub async fn inspect(
+        &self,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+    ) -> Result<VaultSummary> {
+        let provider = self.registry.resolve(provider_type, provider_config)?;
+        let config = Self::load_config(provider.as_ref()).await?;
+        Ok(VaultSummary::from_config(&config))
+    }
+
+    /// Summarize a vault and its tree after checking the password.
+    ///
+    /// Derives the key and decrypts the tree for its statistics, but builds
+    /// no session; the key is dropped before returning.
+    ///
+    /// # Errors
+    /// Same as [`inspect`](Self::inspect), plus
+    /// - `NotPermitted` if the password is wrong
+    /// - `KeyfileRequired` / `KeyfileMismatch` for the keyfile
+    /// - `ConfigTampered` if the config does not match its MAC
+    pub async fn inspect_with_password(
+        &self,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+    ) -> Result<VaultSummary> {
+        let provider = self.registry.resolve(provider_type, provider_config)?;
+        let config = Self::load_config(provider.as_ref()).await?;
+
+        let master_key = config
+            .verify_password_with_keyfile(password, keyfile)?
+            .ok_or_else(|| Error::NotPermitted("Invalid password".to_string()))?;
+        config.verify_mac(&master_key)?;
+        let tree = VaultSession::load_and_decrypt_tree(&provider, &master_key).await?;
+
+        let mut summary = VaultSummary::from_config(&config);
+        summary.tree = Some(TreeSummary {
+            file_count: tree.count_files(),
+            total_size: tree.total_size(),
+        });
+        Ok(summary)
+    }
+
+    /// Download and parse the vault configuration.
+    async fn load_config(provider: &dyn StorageProvider) -> Result<VaultConfig> {
         let config_path = VaultPath::parse(CONFIG_FILENAME)?;
-        let config_bytes = session.config().to_bytes()?;
-        session
-            .provider()
-            .upload(&config_path, config_bytes)
-            .await?;
+        if !provider.exists(&config_path).await? {
+            return Err(Error::NotFound("Vault configuration not found".to_string()));
+        }
+
+        let config_bytes = provider.download(&config_path).await?;
+        VaultConfig::from_bytes(&config_bytes).map_err(|e| match e {
+            Error::Serialization(msg) => {
+                Error::Serialization(format!("Invalid vault configuration: {}", msg))
+            }
+            other => other,
+        })
+    }
+
+    /// Persist a config that was just given separate salts. A failure only
+    /// postpones the migration: the old config still opens.
+    async fn save_split_salts(&self, session: &VaultSession) {
+        if let Err(e) = self.save_config(session).await {
+            warn!(
+                "Could not save separate salts for vault {}: {}",
+                session.vault_id(),
+                e
+            );
+        }
+    }
+
+    /// Save vault configuration to storage, sealing it with a fresh MAC.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if the session is locked
+    pub async fn save_config(&self, session: &VaultSession) -> Result<()> {
+        session.upload_config().await
+    }
+
+    /// Move a flat vault's blobs into shard directories, then record the
+    /// sharded layout in its configuration.
+    ///
+    /// Each blob moves with a single rename, so files stay readable if the
+    /// run is interrupted, and calling this again carries on from the
+    /// blobs that are still flat. On an already sharded vault it moves any
+    /// stragglers.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if the session is locked
+    /// - Storage failures; the layout stays flat until a run completes
+    pub async fn shard_data_dir(&self, session: &mut VaultSession) -> Result<ShardReport> {
+        session.master_key()?;
+        let report = layout::shard_blobs(session).await?;
+
+        if session.data_layout() == DataLayout::Flat {
+            session.config_mut().data_layout = DataLayout::Sharded;
+            session.config_mut().modified_at = Utc::now();
+            if let Err(e) = self.save_config(session).await {
+                session.config_mut().data_layout = DataLayout::Flat;
+                return Err(e);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Attach the provider of `point` at its vault path, so files written
+    /// below it from now on keep their blobs there, and save the config.
+    ///
+    /// The directory is created if it does not exist yet.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if the session is locked
+    /// - `Conflict` if files already exist below the path
+    /// - `InvalidInput` or `AlreadyExists` if the path is the root or
+    ///   overlaps another attach point
+    /// - The provider is not registered or its configuration is invalid
+    pub async fn attach(&self, session: &mut VaultSession, point: AttachPoint) -> Result<()> {
+        let master_key = session.master_key()?;
+        let provider = self
+            .registry
+            .resolve(&point.provider_type, point.provider_config.clone())?;
+        let mut config = session.config().clone();
+        config.add_attachment(point.clone(), &master_key)?;
+
+        let exists = session.tree().read().await.exists(&point.vault_path);
+        if exists {
+            let files = attach::files_below(session, &point.vault_path).await?;
+            if files > 0 {
+                return Err(Error::Conflict(format!(
+                    "{} already holds {} files; attach at an empty directory",
+                    point.vault_path, files
+                )));
+            }
+        }
+        attach::prepare(provider.as_ref()).await?;
+        if !exists {
+            VaultOperations::new(session)?
+                .create_directory(&point.vault_path)
+                .await?;
+        }
+
+        let previous = std::mem::replace(session.config_mut(), config);
+        session.set_attached_provider(point.vault_path.clone(), Some(provider));
+        if let Err(e) = self.save_config(session).await {
+            *session.config_mut() = previous;
+            session.set_attached_provider(point.vault_path, None);
+            return Err(e);
+        }
         Ok(())
     }
 
+    /// Detach the provider at `vault_path` and save the config.
+    ///
+    /// Refused while files below it keep their blobs there: move them out
+    /// first, which carries their blobs to the other provider, or delete
+    /// them. The directory itself stays.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if the session is locked
+    /// - `NotFound` if `vault_path` is not an attach point
+    /// - `Conflict` if files remain below it
+    pub async fn detach(
+        &self,
+        session: &mut VaultSession,
+        vault_path: &VaultPath,
+    ) -> Result<AttachPoint> {
+        let master_key = session.master_key()?;
+        let mut config = session.config().clone();
+        let point = config.remove_attachment(vault_path, &master_key)?;
+
+        let files = session.tree().read().await.files_under(vault_path).len();
+        if files > 0 {
+            return Err(Error::Conflict(format!(
+                "{} still holds {} files; move or delete them before detaching",
+                vault_path, files
+            )));
+        }
+
+        let previous = std::mem::replace(session.config_mut(), config);
+        if let Err(e) = self.save_config(session).await {
+            *session.config_mut() = previous;
+            return Err(e);
+        }
+        session.set_attached_provider(vault_path.clone(), None);
+        Ok(point)
+    }
+
     /// Save vault tree to storage (encrypted).
     pub async fn save_tree(&self, session: &VaultSession) -> Result<()> {
         session.save_tree().await
@@ -218,6 +843,7 @@ impl Default for VaultManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axiomvault_storage::test_util::CountingProvider;
 
     #[tokio::test]
     async fn test_create_vault() {
@@ -228,7 +854,7 @@ mod tests {
         let creation = manager
             .create_vault(
                 vault_id.clone(),
-                password,
+                &Secret::from_slice(password),
                 "memory",
                 serde_json::Value::Null,
                 KdfParams::moderate(),
@@ -250,7 +876,7 @@ mod tests {
         let creation = manager
             .create_vault(
                 vault_id.clone(),
-                password,
+                &Secret::from_slice(password),
                 "memory",
                 serde_json::Value::Null,
                 KdfParams::moderate(),
@@ -266,7 +892,7 @@ mod tests {
         let config = VaultConfig::from_bytes(&config_bytes).unwrap();
 
         let master_key = config
-            .verify_password(password)
+            .verify_password(&Secret::from_slice(password))
             .unwrap()
             .expect("password should be correct");
 
@@ -279,6 +905,423 @@ mod tests {
         assert_eq!(reopened.vault_id().as_str(), vault_id.as_str());
     }
 
+    #[tokio::test]
+    async fn test_open_vault_with_keyfile() {
+        // Every resolve must hit the same in-memory store for reopen to work.
+        let provider: Arc<dyn StorageProvider> =
+            Arc::new(axiomvault_storage::MemoryProvider::new());
+        let registry = ProviderRegistry::new();
+        registry
+            .register(
+                "shared",
+                axiomvault_storage::registry::from_fn(move |_| Ok(provider.clone())),
+            )
+            .unwrap();
+        let manager = VaultManager::with_registry(registry);
+        let keyfile = b"keyfile-content";
+
+        let creation = manager
+            .create_vault_with_keyfile(
+                VaultId::new("keyfile-vault").unwrap(),
+                &Secret::from_slice(b"password"),
+                Some(keyfile),
+                "shared",
+                serde_json::Value::Null,
+                KdfParams::moderate(),
+            )
+            .await
+            .unwrap();
+        let recovery_words = creation.recovery_words;
+        drop(creation.session);
+
+        let missing = manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+            )
+            .await;
+        assert!(matches!(missing, Err(Error::KeyfileRequired)));
+
+        let wrong = manager
+            .open_vault_with_keyfile(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+                Some(b"wrong-keyfile"),
+            )
+            .await;
+        assert!(matches!(wrong, Err(Error::KeyfileMismatch)));
+
+        let session = manager
+            .open_vault_with_keyfile(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+                Some(keyfile),
+            )
+            .await
+            .unwrap();
+        assert!(session.is_active());
+        drop(session);
+
+        // Recovery works without the keyfile and removes the requirement.
+        manager
+            .recover_vault(
+                "shared",
+                serde_json::Value::Null,
+                &recovery_words,
+                &Secret::from_slice(b"new-password"),
+            )
+            .await
+            .unwrap();
+        let session = manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"new-password"),
+            )
+            .await
+            .unwrap();
+        assert!(!session.config().requires_keyfile());
+    }
+
+    fn shared_manager() -> VaultManager {
+        let provider: Arc<dyn StorageProvider> =
+            Arc::new(axiomvault_storage::MemoryProvider::new());
+        let registry = ProviderRegistry::new();
+        registry
+            .register(
+                "shared",
+                axiomvault_storage::registry::from_fn(move |_| Ok(provider.clone())),
+            )
+            .unwrap();
+        VaultManager::with_registry(registry)
+    }
+
+    #[tokio::test]
+    async fn test_external_key_vault_round_trip() {
+        let manager = shared_manager();
+        let key = [42u8; 32];
+        let path = VaultPath::parse("/report.txt").unwrap();
+
+        let session = manager
+            .create_vault_with_key(
+                VaultId::new("byok-vault").unwrap(),
+                MasterKey::from_external(&key).unwrap(),
+                "shared",
+                serde_json::Value::Null,
+            )
+            .await
+            .unwrap();
+        assert!(session.config().uses_external_key());
+        assert!(session.config().wrapped_master_key.is_none());
+        assert!(session.config().recovery_wrapped_master_key.is_none());
+        assert!(!session.config().is_legacy_format());
+        VaultOperations::new(&session)
+            .unwrap()
+            .create_file(&path, b"kms-protected")
+            .await
+            .unwrap();
+        drop(session);
+
+        let wrong = manager
+            .open_vault_with_key(
+                "shared",
+                serde_json::Value::Null,
+                MasterKey::from_external(&[7u8; 32]).unwrap(),
+            )
+            .await;
+        assert!(matches!(wrong, Err(Error::NotPermitted(_))));
+        let password = manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+            )
+            .await;
+        assert!(matches!(password, Err(Error::NotPermitted(_))));
+
+        let session = manager
+            .open_vault_with_key(
+                "shared",
+                serde_json::Value::Null,
+                MasterKey::from_external(&key).unwrap(),
+            )
+            .await
+            .unwrap();
+        let ops = VaultOperations::new(&session).unwrap();
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"kms-protected");
+        ops.update_file(&path, b"rewritten").await.unwrap();
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"rewritten");
+
+        let summary = manager
+            .inspect("shared", serde_json::Value::Null)
+            .await
+            .unwrap();
+        assert_eq!(summary.key_source, KeySource::External);
+        assert!(!summary.legacy_format);
+    }
+
+    #[tokio::test]
+    async fn test_external_key_vault_refuses_password_change() {
+        let manager = shared_manager();
+        let mut session = manager
+            .create_vault_with_key(
+                VaultId::new("byok-vault").unwrap(),
+                MasterKey::from_external(&[42u8; 32]).unwrap(),
+                "shared",
+                serde_json::Value::Null,
+            )
+            .await
+            .unwrap();
+
+        let result =
+            session.change_password(&Secret::from_slice(b"old"), &Secret::from_slice(b"new"));
+        assert!(
+            matches!(&result, Err(Error::NotPermitted(msg)) if msg.contains("externally supplied key"))
+        );
+        assert!(session.config().uses_external_key());
+    }
+
+    #[tokio::test]
+    async fn test_convert_between_password_and_external_key() {
+        let manager = shared_manager();
+        let path = VaultPath::parse("/notes.txt").unwrap();
+
+        let mut creation = manager
+            .create_vault(
+                VaultId::new("convert-vault").unwrap(),
+                &Secret::from_slice(b"password"),
+                "shared",
+                serde_json::Value::Null,
+                KdfParams::moderate(),
+            )
+            .await
+            .unwrap();
+        VaultOperations::new(&creation.session)
+            .unwrap()
+            .create_file(&path, b"carried over")
+            .await
+            .unwrap();
+        let key = *creation.session.master_key().unwrap().as_bytes();
+        let generation = creation.session.config().key_generation;
+
+        creation.session.convert_to_external_key().unwrap();
+        manager.save_config(&creation.session).await.unwrap();
+        assert!(creation.session.config().key_generation > generation);
+        drop(creation);
+
+        assert!(manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password")
+            )
+            .await
+            .is_err());
+        let mut session = manager
+            .open_vault_with_key(
+                "shared",
+                serde_json::Value::Null,
+                MasterKey::from_external(&key).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            VaultOperations::new(&session)
+                .unwrap()
+                .read_file(&path)
+                .await
+                .unwrap(),
+            b"carried over"
+        );
+
+        let recovery_words = session
+            .convert_to_password(&Secret::from_slice(b"new-password"), None)
+            .unwrap();
+        manager.save_config(&session).await.unwrap();
+        drop(session);
+
+        assert!(manager
+            .open_vault_with_key(
+                "shared",
+                serde_json::Value::Null,
+                MasterKey::from_external(&key).unwrap(),
+            )
+            .await
+            .is_err());
+        let session = manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"new-password"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            VaultOperations::new(&session)
+                .unwrap()
+                .read_file(&path)
+                .await
+                .unwrap(),
+            b"carried over"
+        );
+        drop(session);
+
+        manager
+            .recover_vault(
+                "shared",
+                serde_json::Value::Null,
+                &recovery_words,
+                &Secret::from_slice(b"recovered"),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_open_vault_rejects_tampered_config() {
+        let provider: Arc<dyn StorageProvider> =
+            Arc::new(axiomvault_storage::MemoryProvider::new());
+        let shared = provider.clone();
+        let registry = ProviderRegistry::new();
+        registry
+            .register(
+                "shared",
+                axiomvault_storage::registry::from_fn(move |_| Ok(shared.clone())),
+            )
+            .unwrap();
+        let manager = VaultManager::with_registry(registry);
+
+        let mut session = manager
+            .create_vault(
+                VaultId::new("mac-vault").unwrap(),
+                &Secret::from_slice(b"password"),
+                "shared",
+                serde_json::Value::Null,
+                KdfParams::moderate(),
+            )
+            .await
+            .unwrap()
+            .session;
+
+        // A legitimate change made through the session is re-sealed on save.
+        session.config_mut().default_policy.versioning = crate::policy::Versioning::Keep(3);
+        manager.save_config(&session).await.unwrap();
+        drop(session);
+        manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+            )
+            .await
+            .unwrap();
+
+        // The same kind of change made directly in storage is not.
+        let config_path = VaultPath::parse(CONFIG_FILENAME).unwrap();
+        let mut config =
+            VaultConfig::from_bytes(&provider.download(&config_path).await.unwrap()).unwrap();
+        config.default_policy.versioning = crate::policy::Versioning::Keep(0);
+        provider
+            .upload(&config_path, config.to_bytes().unwrap())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            manager
+                .open_vault(
+                    "shared",
+                    serde_json::Value::Null,
+                    &Secret::from_slice(b"wrong")
+                )
+                .await,
+            Err(Error::NotPermitted(_))
+        ));
+        assert!(matches!(
+            manager
+                .open_vault(
+                    "shared",
+                    serde_json::Value::Null,
+                    &Secret::from_slice(b"password")
+                )
+                .await,
+            Err(Error::ConfigTampered)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_open_vault_saves_split_salts() {
+        let provider: Arc<dyn StorageProvider> =
+            Arc::new(axiomvault_storage::MemoryProvider::new());
+        let shared = provider.clone();
+        let registry = ProviderRegistry::new();
+        registry
+            .register(
+                "shared",
+                axiomvault_storage::registry::from_fn(move |_| Ok(shared.clone())),
+            )
+            .unwrap();
+        let manager = VaultManager::with_registry(registry);
+
+        let session = manager
+            .create_vault(
+                VaultId::new("single-salt").unwrap(),
+                &Secret::from_slice(b"password"),
+                "shared",
+                serde_json::Value::Null,
+                KdfParams::moderate(),
+            )
+            .await
+            .unwrap()
+            .session;
+        let master_key = session.master_key().unwrap().clone();
+        drop(session);
+
+        // Store the config the way single-salt vaults have it.
+        let config_path = VaultPath::parse(CONFIG_FILENAME).unwrap();
+        let mut config =
+            VaultConfig::from_bytes(&provider.download(&config_path).await.unwrap()).unwrap();
+        let kek = axiomvault_crypto::derive_key(b"password", &config.kdf_salt, &config.kdf_params)
+            .unwrap();
+        config.verify_salt = None;
+        config.key_verification = VaultConfig::key_verification_for(
+            &config.id,
+            &kek,
+            &config.kdf_salt,
+            &config.kdf_params,
+        );
+        provider
+            .upload(&config_path, config.to_sealed_bytes(&master_key).unwrap())
+            .await
+            .unwrap();
+
+        let session = manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+            )
+            .await
+            .unwrap();
+        assert!(!session.config().has_single_salt());
+        drop(session);
+
+        let stored =
+            VaultConfig::from_bytes(&provider.download(&config_path).await.unwrap()).unwrap();
+        assert_eq!(stored.kdf_salt, config.kdf_salt);
+        assert!(!stored.has_single_salt());
+        manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+            )
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn test_vault_exists() {
         let manager = VaultManager::new();
@@ -288,4 +1331,305 @@ mod tests {
             .await;
         assert!(exists.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_probe_reads_config_without_unlocking() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = VaultManager::new();
+        let provider_config = serde_json::json!({ "root": dir.path() });
+
+        assert!(matches!(
+            manager.probe("local", provider_config.clone()).await,
+            Err(Error::NotFound(_))
+        ));
+
+        let params = KdfParams::moderate();
+        manager
+            .create_vault(
+                VaultId::new("probed").unwrap(),
+                &Secret::from_slice(b"password"),
+                "local",
+                provider_config.clone(),
+                params.clone(),
+            )
+            .await
+            .unwrap();
+
+        let probe = manager.probe("local", provider_config).await.unwrap();
+        assert_eq!(probe.vault_id.as_str(), "probed");
+        assert_eq!(probe.version, VaultVersion::CURRENT);
+        assert_eq!(probe.provider_type, "local");
+        assert_eq!(probe.kdf_params, params);
+    }
+
+    /// Manager whose "counting" provider always resolves to `provider`.
+    fn counting_manager(provider: Arc<CountingProvider>) -> VaultManager {
+        let registry = ProviderRegistry::new();
+        registry
+            .register(
+                "counting",
+                axiomvault_storage::registry::from_fn(move |_| {
+                    Ok(provider.clone() as Arc<dyn StorageProvider>)
+                }),
+            )
+            .unwrap();
+        VaultManager::with_registry(registry)
+    }
+
+    #[tokio::test]
+    async fn test_inspect_reads_only_the_config() {
+        let provider = Arc::new(CountingProvider::new());
+        let manager = counting_manager(provider.clone());
+
+        let params = KdfParams::moderate();
+        let session = manager
+            .create_vault(
+                VaultId::new("inspected").unwrap(),
+                &Secret::from_slice(b"password"),
+                "counting",
+                serde_json::Value::Null,
+                params.clone(),
+            )
+            .await
+            .unwrap()
+            .session;
+        VaultOperations::new(&session)
+            .unwrap()
+            .create_file(&VaultPath::parse("/a.txt").unwrap(), b"hello")
+            .await
+            .unwrap();
+        session.save_tree().await.unwrap();
+        drop(session);
+        provider.clear_downloads();
+
+        let summary = manager
+            .inspect("counting", serde_json::Value::Null)
+            .await
+            .unwrap();
+        assert_eq!(summary.vault_id.as_str(), "inspected");
+        assert_eq!(summary.version, VaultVersion::CURRENT);
+        assert_eq!(summary.kdf_params, params);
+        assert!(summary.recovery_key_configured);
+        assert!(!summary.keyfile_required);
+        assert!(summary.tree.is_none());
+        assert_eq!(provider.downloads(), [format!("/{}", CONFIG_FILENAME)]);
+
+        // Nothing secret ends up in the serialized summary.
+        let config = VaultConfig::from_bytes(
+            &provider
+                .inner()
+                .download(&VaultPath::parse(CONFIG_FILENAME).unwrap())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        let json = serde_json::to_string(&summary).unwrap();
+        for field in ["salt", "key_verification", "wrapped", "provider_config"] {
+            assert!(!json.contains(field), "summary exposes {}", field);
+        }
+        assert!(!json.contains(&serde_json::to_string(&config.kdf_salt).unwrap()));
+
+        assert!(matches!(
+            manager
+                .inspect_with_password(
+                    "counting",
+                    serde_json::Value::Null,
+                    &Secret::from_slice(b"wrong"),
+                    None
+                )
+                .await,
+            Err(Error::NotPermitted(_))
+        ));
+        let detailed = manager
+            .inspect_with_password(
+                "counting",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            detailed.tree,
+            Some(TreeSummary {
+                file_count: 1,
+                total_size: 5
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inspect_reports_corrupted_config() {
+        let provider = Arc::new(CountingProvider::new());
+        let manager = counting_manager(provider.clone());
+
+        provider
+            .upload(
+                &VaultPath::parse(CONFIG_FILENAME).unwrap(),
+                b"{\"id\": \"broken\", \"version\":".to_vec(),
+            )
+            .await
+            .unwrap();
+
+        match manager.inspect("counting", serde_json::Value::Null).await {
+            Err(Error::Serialization(msg)) => {
+                assert!(msg.starts_with("Invalid vault configuration: "), "{}", msg);
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_provider_config_is_rejected_before_create() {
+        let manager = VaultManager::new();
+
+        let err = manager
+            .validate_provider_config("local", &serde_json::json!({}))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid input: Invalid local config: missing field 'root'"
+        );
+        assert!(matches!(
+            manager.validate_provider_config("nope", &serde_json::Value::Null),
+            Err(Error::NotFound(_))
+        ));
+
+        let result = manager
+            .create_vault(
+                VaultId::new("bad-config").unwrap(),
+                &Secret::from_slice(b"password"),
+                "local",
+                serde_json::json!({ "root": 7 }),
+                KdfParams::moderate(),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn test_shard_data_dir_survives_interruptions() {
+        use crate::config::DataLayout;
+        use axiomvault_common::health::Severity;
+
+        async fn assert_readable(session: &VaultSession, files: &[(VaultPath, Vec<u8>)]) {
+            let ops = VaultOperations::new(session).unwrap();
+            for (path, content) in files {
+                assert_eq!(&ops.read_file(path).await.unwrap(), content, "{}", path);
+            }
+        }
+
+        let provider = Arc::new(CountingProvider::new());
+        let manager = counting_manager(provider.clone());
+        let mut session = manager
+            .create_vault(
+                VaultId::new("sharded").unwrap(),
+                &Secret::from_slice(b"password"),
+                "counting",
+                serde_json::Value::Null,
+                KdfParams::moderate(),
+            )
+            .await
+            .unwrap()
+            .session;
+        assert_eq!(session.data_layout(), DataLayout::Flat);
+
+        // One file is large enough to be split into parts.
+        session.set_part_size(Some(256));
+        let mut files: Vec<(VaultPath, Vec<u8>)> = (0..8)
+            .map(|i| {
+                let path = VaultPath::parse(&format!("/file{}.txt", i)).unwrap();
+                (path, format!("version 0 of file {}", i).into_bytes())
+            })
+            .collect();
+        files[0].1 = vec![7; 1000];
+        {
+            let ops = VaultOperations::new(&session).unwrap();
+            for (path, content) in &files {
+                ops.create_file(path, content).await.unwrap();
+            }
+        }
+
+        // Each run is cut off after a few moves. The vault stays flat and
+        // keeps working: every file is rewritten, including ones whose
+        // blobs already moved, and new files arrive.
+        for (round, budget) in [0, 3, 5].into_iter().enumerate() {
+            provider.fail_renames_after(budget);
+            assert!(matches!(
+                manager.shard_data_dir(&mut session).await,
+                Err(Error::Network(_))
+            ));
+            assert_eq!(session.data_layout(), DataLayout::Flat);
+            assert_readable(&session, &files).await;
+
+            let ops = VaultOperations::new(&session).unwrap();
+            for (path, content) in &mut files {
+                content.extend_from_slice(format!(" +{}", round).as_bytes());
+                ops.update_file(path, content).await.unwrap();
+            }
+            let path = VaultPath::parse(&format!("/new{}.txt", round)).unwrap();
+            ops.create_file(&path, b"added mid-migration")
+                .await
+                .unwrap();
+            files.push((path, b"added mid-migration".to_vec()));
+            assert_readable(&session, &files).await;
+        }
+
+        provider.fail_renames_after(usize::MAX);
+        let report = manager.shard_data_dir(&mut session).await.unwrap();
+        assert!(report.moved > 0);
+        assert_eq!(session.data_layout(), DataLayout::Sharded);
+        assert_readable(&session, &files).await;
+
+        // Nothing is left flat.
+        let data_dir = provider
+            .inner()
+            .list(&VaultPath::parse(DATA_DIRNAME).unwrap())
+            .await
+            .unwrap();
+        assert!(data_dir.iter().all(|entry| entry.is_directory));
+
+        // The layout survives reopening, new blobs go to their shard, and
+        // the health check finds neither orphans nor missing blobs.
+        session.save_tree().await.unwrap();
+        drop(session);
+        let session = manager
+            .open_vault(
+                "counting",
+                serde_json::Value::Null,
+                &Secret::from_slice(b"password"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(session.data_layout(), DataLayout::Sharded);
+        assert_readable(&session, &files).await;
+        VaultOperations::new(&session)
+            .unwrap()
+            .create_file(&VaultPath::parse("/after.txt").unwrap(), b"sharded")
+            .await
+            .unwrap();
+        let data_dir = provider
+            .inner()
+            .list(&VaultPath::parse(DATA_DIRNAME).unwrap())
+            .await
+            .unwrap();
+        assert!(data_dir.iter().all(|entry| entry.is_directory));
+
+        let report = crate::health::check_vault_health(
+            provider.as_ref(),
+            session.config(),
+            &session.master_key().unwrap(),
+            "counting",
+        )
+        .await
+        .unwrap();
+        for check in ["orphaned_files", "missing_files"] {
+            let result = report
+                .results
+                .iter()
+                .find(|r| r.check_name == check)
+                .unwrap();
+            assert_eq!(result.severity, Severity::Info, "{}", result.message);
+        }
+    }
 }
@@ -101,22 +101,17 @@ impl MigrationRegistry {
         let mut current = *from;
 
         while current != *to {
-            let next = self
+            let migration = self
                 .migrations
                 .iter()
-                .find(|m| m.source_version() == current);
-            match next {
-                Some(migration) => {
-                    let target = migration.target_version();
-                    // Ensure we're moving forward and not past our target.
-                    if target.minor > to.minor || target.major != to.major {
-                        return None;
-                    }
-                    path.push(migration.as_ref());
-                    current = target;
-                }
-                None => return None,
+                .find(|m| m.source_version() == current)?;
+            let target = migration.target_version();
+            // Ensure we're moving forward and not past our target.
+            if target.minor > to.minor || target.major != to.major {
+                return None;
             }
+            path.push(migration.as_ref());
+            current = target;
         }
 
         Some(path)
@@ -126,6 +121,10 @@ impl MigrationRegistry {
     ///
     /// Backs up the vault config before starting. If any step fails, the backup
     /// is restored and the error is returned.
+    ///
+    /// Migrations run without the vault's keys, so they cannot reseal the
+    /// config. A sealed config is refused: opening it with its password
+    /// migrates it instead.
     pub fn migrate(
         &self,
         vault_path: &Path,
@@ -141,6 +140,12 @@ impl MigrationRegistry {
             info!("Vault is already at version {}", target);
             return Ok(());
         }
+        if config.config_mac.is_some() {
+            return Err(Error::NotPermitted(
+                "Vault configuration is sealed; open the vault with its password to migrate it"
+                    .to_string(),
+            ));
+        }
 
         info!(
             "Migrating vault from {} to {} ({} step(s))",
@@ -324,6 +329,8 @@ mod tests {
             VaultConfig::new(id, password, "local", serde_json::Value::Null, params).unwrap();
         let mut config = creation.config;
         config.version = version;
+        // Written before configs were sealed.
+        config.config_mac = None;
         config
     }
 
@@ -405,6 +412,32 @@ mod tests {
         assert!(!backup_path.exists());
     }
 
+    #[test]
+    fn test_migration_refuses_sealed_config() {
+        let id = VaultId::new("test-vault").unwrap();
+        let creation = VaultConfig::new(
+            id,
+            b"test",
+            "local",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let mut config = creation.config;
+        config.version = VaultVersion { major: 1, minor: 0 };
+        config.update_mac(&creation.master_key).unwrap();
+        let dir = setup_vault_dir(&config);
+
+        let result = MigrationRegistry::with_defaults().migrate(
+            dir.path(),
+            &mut config,
+            &VaultVersion { major: 1, minor: 1 },
+        );
+        assert!(matches!(result, Err(Error::NotPermitted(_))));
+        assert_eq!(config.version, VaultVersion { major: 1, minor: 0 });
+        assert!(!dir.path().join(CONFIG_BACKUP_FILENAME).exists());
+    }
+
     #[test]
     fn test_migration_already_at_target() {
         let mut config = make_test_config(VaultVersion { major: 1, minor: 1 });
@@ -0,0 +1,901 @@
+//! Migrations that run against an unlocked vault.
+//!
+//! [`crate::migration`] steps a local config between format versions
+//! without the vault's keys. The migrations here need an open session,
+//! because they rewrite key material or encrypted objects. Each one says
+//! what it will change before it runs, and every run is recorded in the
+//! config's migration journal, [`VaultConfig::migrations`].
+//!
+//! [`VaultManager`](crate::VaultManager) checks for pending migrations when
+//! a vault is opened. [Automatic](MigrationKind::Automatic) ones are applied
+//! on the spot; any other makes the open fail with
+//! [`Error::MigrationRequired`] until it is applied on request, after
+//! [`open_vault_for_migration`](crate::VaultManager::open_vault_for_migration).
+//!
+//! Migrations are idempotent: one interrupted part way is finished by
+//! applying it again.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use zeroize::Zeroizing;
+
+use crate::config::{VaultConfig, VaultVersion};
+use crate::session::VaultSession;
+use axiomvault_common::{Error, Result};
+
+/// Whether a migration may run without asking the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationKind {
+    /// Changes nothing an ordinary save would not; applied when the vault
+    /// is opened.
+    Automatic,
+    /// Rewrites key material or has output the user must keep; applied
+    /// only on request.
+    Explicit,
+}
+
+/// What a pending migration will do, from [`SessionMigration::check`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MigrationPlan {
+    /// Stable identifier, as accepted by `axiomvault migrate --apply`.
+    pub id: &'static str,
+    /// One-line summary.
+    pub description: &'static str,
+    /// Format the vault is in now.
+    pub from: String,
+    /// Format the vault is in afterwards.
+    pub to: String,
+    /// What will change, one entry per change.
+    pub changes: Vec<String>,
+    /// Stored objects the migration rewrites, as an estimate of its work.
+    pub objects: u64,
+    /// Whether [`SessionMigration::rollback`] can undo it.
+    pub reversible: bool,
+    /// Whether it runs without asking.
+    pub kind: MigrationKind,
+    /// Whether an earlier run was interrupted or failed. Applying the
+    /// migration again resumes it.
+    pub interrupted: bool,
+}
+
+/// How far a running migration has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationProgress {
+    /// Migration being applied.
+    pub id: &'static str,
+    /// Objects rewritten so far.
+    pub done: u64,
+    /// Objects to rewrite in total.
+    pub total: u64,
+}
+
+/// Receives [`MigrationProgress`] while a migration runs.
+pub type ProgressSink<'a> = &'a (dyn Fn(MigrationProgress) + Send + Sync);
+
+/// What an applied migration hands back.
+#[derive(Default)]
+pub struct MigrationOutput {
+    /// Recovery words created by the migration. Must be shown to the
+    /// user once.
+    pub recovery_words: Option<Zeroizing<String>>,
+}
+
+/// One run of a migration in the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationRecord {
+    /// Migration ID.
+    pub id: String,
+    /// When the run started.
+    pub started_at: DateTime<Utc>,
+    /// When it finished or was rolled back; `None` while running.
+    pub finished_at: Option<DateTime<Utc>>,
+    /// How it ended.
+    pub outcome: MigrationOutcome,
+}
+
+/// How a migration run ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum MigrationOutcome {
+    /// Started, and not known to have finished: the run was interrupted
+    /// if no session is applying it.
+    Running,
+    /// Finished successfully.
+    Applied,
+    /// Stopped with an error.
+    Failed {
+        /// The error's message.
+        error: String,
+    },
+    /// Applied, then undone.
+    RolledBack,
+}
+
+/// A migration of an unlocked vault.
+#[async_trait]
+pub trait SessionMigration: Send + Sync {
+    /// Stable identifier, recorded in the journal.
+    fn id(&self) -> &'static str;
+
+    /// Report what the migration would change, or `None` if the vault
+    /// does not need it. Changes nothing.
+    async fn check(&self, session: &VaultSession) -> Result<Option<MigrationPlan>>;
+
+    /// Migrate the vault. Changes to the config are kept in the session;
+    /// [`SessionMigrationRegistry::apply`] saves them with the journal.
+    async fn apply(
+        &self,
+        session: &mut VaultSession,
+        progress: ProgressSink<'_>,
+    ) -> Result<MigrationOutput>;
+
+    /// Undo an applied migration.
+    ///
+    /// # Errors
+    /// - `Unsupported` unless the plan said the migration is reversible
+    async fn rollback(&self, _session: &mut VaultSession) -> Result<()> {
+        Err(Error::Unsupported(format!(
+            "Migration {} cannot be rolled back",
+            self.id()
+        )))
+    }
+}
+
+/// The session migrations known to this version, in the order they apply.
+pub struct SessionMigrationRegistry {
+    migrations: Vec<Box<dyn SessionMigration>>,
+}
+
+impl SessionMigrationRegistry {
+    /// Create a new empty registry.
+    pub fn new() -> Self {
+        Self {
+            migrations: Vec::new(),
+        }
+    }
+
+    /// Create a registry holding every built-in migration.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(SealConfig));
+        registry.register(Box::new(StreamTree));
+        registry.register(Box::new(WrapLegacyKey));
+        registry
+    }
+
+    /// Register a migration; it applies after those already registered.
+    pub fn register(&mut self, migration: Box<dyn SessionMigration>) {
+        self.migrations.push(migration);
+    }
+
+    /// Look up a migration by ID.
+    pub fn get(&self, id: &str) -> Option<&dyn SessionMigration> {
+        self.migrations
+            .iter()
+            .find(|m| m.id() == id)
+            .map(|m| m.as_ref())
+    }
+
+    /// Plans of the migrations the vault needs, in the order they apply.
+    pub async fn pending(&self, session: &VaultSession) -> Result<Vec<MigrationPlan>> {
+        let mut plans = Vec::new();
+        for migration in &self.migrations {
+            if let Some(mut plan) = migration.check(session).await? {
+                plan.interrupted = last_run(session.config(), plan.id).is_some_and(|run| {
+                    matches!(
+                        run.outcome,
+                        MigrationOutcome::Running | MigrationOutcome::Failed { .. }
+                    )
+                });
+                plans.push(plan);
+            }
+        }
+        Ok(plans)
+    }
+
+    /// Apply one migration, recording the run in the journal.
+    ///
+    /// The run is saved as running before the migration starts, and its
+    /// outcome together with the migrated config afterwards, so a run cut
+    /// off half way shows up in [`pending`](Self::pending) as interrupted.
+    ///
+    /// # Errors
+    /// - `NotFound` if no migration has this ID
+    /// - `InvalidInput` if the vault does not need it
+    /// - Any error of the migration, which is recorded as failed
+    pub async fn apply(
+        &self,
+        session: &mut VaultSession,
+        id: &str,
+        progress: ProgressSink<'_>,
+    ) -> Result<MigrationOutput> {
+        let migration = self
+            .get(id)
+            .ok_or_else(|| Error::NotFound(format!("Unknown migration {}", id)))?;
+        let id = migration.id();
+
+        if migration.check(session).await?.is_none() {
+            // A run that finished without getting to record it.
+            if let Some(run) = last_run_mut(session.config_mut(), id) {
+                if run.outcome == MigrationOutcome::Running {
+                    run.outcome = MigrationOutcome::Applied;
+                    run.finished_at = Some(Utc::now());
+                    session.upload_config().await?;
+                    return Ok(MigrationOutput::default());
+                }
+            }
+            return Err(Error::InvalidInput(format!(
+                "Vault does not need migration {}",
+                id
+            )));
+        }
+
+        info!("Applying migration {} to vault {}", id, session.vault_id());
+        if let Some(run) = last_run_mut(session.config_mut(), id) {
+            if run.outcome == MigrationOutcome::Running {
+                run.outcome = MigrationOutcome::Failed {
+                    error: "Interrupted".to_string(),
+                };
+            }
+        }
+        session.config_mut().migrations.push(MigrationRecord {
+            id: id.to_string(),
+            started_at: Utc::now(),
+            finished_at: None,
+            outcome: MigrationOutcome::Running,
+        });
+        if let Err(e) = session.upload_config().await {
+            session.config_mut().migrations.pop();
+            return Err(e);
+        }
+
+        let result = migration.apply(session, progress).await;
+        if let Some(run) = last_run_mut(session.config_mut(), id) {
+            run.finished_at = Some(Utc::now());
+            run.outcome = match &result {
+                Ok(_) => MigrationOutcome::Applied,
+                Err(e) => MigrationOutcome::Failed {
+                    error: e.to_string(),
+                },
+            };
+        }
+        let saved = session.upload_config().await;
+        match result {
+            Ok(output) => {
+                saved?;
+                Ok(output)
+            }
+            Err(e) => {
+                if let Err(save_err) = saved {
+                    warn!("Could not record failed migration {}: {}", id, save_err);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Apply every pending migration in order, stopping at the first
+    /// failure.
+    pub async fn apply_all(
+        &self,
+        session: &mut VaultSession,
+        progress: ProgressSink<'_>,
+    ) -> Result<Vec<(&'static str, MigrationOutput)>> {
+        let mut outputs = Vec::new();
+        for plan in self.pending(session).await? {
+            let output = self.apply(session, plan.id, progress).await?;
+            outputs.push((plan.id, output));
+        }
+        Ok(outputs)
+    }
+
+    /// Undo the last run of a migration and record it as rolled back.
+    ///
+    /// # Errors
+    /// - `NotFound` if no migration has this ID
+    /// - `InvalidInput` if its last run did not apply it
+    /// - `Unsupported` if it cannot be rolled back
+    pub async fn rollback(&self, session: &mut VaultSession, id: &str) -> Result<()> {
+        let migration = self
+            .get(id)
+            .ok_or_else(|| Error::NotFound(format!("Unknown migration {}", id)))?;
+        let id = migration.id();
+        if last_run(session.config(), id).map(|run| &run.outcome)
+            != Some(&MigrationOutcome::Applied)
+        {
+            return Err(Error::InvalidInput(format!(
+                "Migration {} is not applied",
+                id
+            )));
+        }
+
+        migration.rollback(session).await?;
+        if let Some(run) = last_run_mut(session.config_mut(), id) {
+            run.outcome = MigrationOutcome::RolledBack;
+            run.finished_at = Some(Utc::now());
+        }
+        session.upload_config().await?;
+        info!(
+            "Rolled back migration {} of vault {}",
+            id,
+            session.vault_id()
+        );
+        Ok(())
+    }
+}
+
+impl Default for SessionMigrationRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+/// The journal's latest run of migration `id`.
+fn last_run<'a>(config: &'a VaultConfig, id: &str) -> Option<&'a MigrationRecord> {
+    config.migrations.iter().rev().find(|run| run.id == id)
+}
+
+fn last_run_mut<'a>(config: &'a mut VaultConfig, id: &str) -> Option<&'a mut MigrationRecord> {
+    config.migrations.iter_mut().rev().find(|run| run.id == id)
+}
+
+/// ID of the migration sealing configs written before the config MAC.
+pub(crate) const CONFIG_MAC_MIGRATION: &str = "config-mac";
+
+/// Seal a config written before the config MAC existed.
+struct SealConfig;
+
+#[async_trait]
+impl SessionMigration for SealConfig {
+    fn id(&self) -> &'static str {
+        CONFIG_MAC_MIGRATION
+    }
+
+    async fn check(&self, session: &VaultSession) -> Result<Option<MigrationPlan>> {
+        if session.config().config_mac.is_some() {
+            return Ok(None);
+        }
+        Ok(Some(MigrationPlan {
+            id: self.id(),
+            description: "Protect the vault configuration with a MAC",
+            from: "configuration without MAC".to_string(),
+            to: "configuration with MAC".to_string(),
+            changes: vec![
+                "Seal the configuration so changes made without the vault's keys are detected"
+                    .to_string(),
+            ],
+            objects: 1,
+            reversible: false,
+            kind: MigrationKind::Automatic,
+            interrupted: false,
+        }))
+    }
+
+    async fn apply(
+        &self,
+        session: &mut VaultSession,
+        progress: ProgressSink<'_>,
+    ) -> Result<MigrationOutput> {
+        let master_key = session.master_key()?;
+        session.config_mut().update_mac(&master_key)?;
+        progress(MigrationProgress {
+            id: self.id(),
+            done: 1,
+            total: 1,
+        });
+        Ok(MigrationOutput::default())
+    }
+}
+
+/// Rewrite a tree index stored as one encrypted blob in the chunked
+/// stream format.
+struct StreamTree;
+
+#[async_trait]
+impl SessionMigration for StreamTree {
+    fn id(&self) -> &'static str {
+        "tree-stream"
+    }
+
+    async fn check(&self, session: &VaultSession) -> Result<Option<MigrationPlan>> {
+        if !session.has_legacy_tree() {
+            return Ok(None);
+        }
+        Ok(Some(MigrationPlan {
+            id: self.id(),
+            description: "Store the tree index in the chunked stream format",
+            from: "single-blob tree index".to_string(),
+            to: "chunked stream tree index".to_string(),
+            changes: vec!["Re-encrypt the tree index in chunks".to_string()],
+            objects: 1,
+            reversible: false,
+            kind: MigrationKind::Automatic,
+            interrupted: false,
+        }))
+    }
+
+    async fn apply(
+        &self,
+        session: &mut VaultSession,
+        progress: ProgressSink<'_>,
+    ) -> Result<MigrationOutput> {
+        session.save_tree().await?;
+        progress(MigrationProgress {
+            id: self.id(),
+            done: 1,
+            total: 1,
+        });
+        Ok(MigrationOutput::default())
+    }
+}
+
+/// Move a v1.0 vault, whose password KEK is its master key, to the
+/// key-wrapping format with a recovery key.
+struct WrapLegacyKey;
+
+#[async_trait]
+impl SessionMigration for WrapLegacyKey {
+    fn id(&self) -> &'static str {
+        "legacy-key-wrap"
+    }
+
+    async fn check(&self, session: &VaultSession) -> Result<Option<MigrationPlan>> {
+        if !session.config().is_legacy_format() {
+            return Ok(None);
+        }
+        Ok(Some(MigrationPlan {
+            id: self.id(),
+            description: "Wrap the master key and add a recovery key",
+            from: format!("{} (password-derived master key)", session.config().version),
+            to: format!("{} (wrapped master key)", VaultVersion::CURRENT),
+            changes: vec![
+                "Wrap the master key under the password".to_string(),
+                "Create a recovery key; its words are shown once".to_string(),
+            ],
+            objects: 1,
+            reversible: true,
+            kind: MigrationKind::Explicit,
+            interrupted: false,
+        }))
+    }
+
+    async fn apply(
+        &self,
+        session: &mut VaultSession,
+        progress: ProgressSink<'_>,
+    ) -> Result<MigrationOutput> {
+        let master_key = session.master_key()?;
+        let recovery_words = session.config_mut().wrap_legacy_master_key(&master_key)?;
+        progress(MigrationProgress {
+            id: self.id(),
+            done: 1,
+            total: 1,
+        });
+        Ok(MigrationOutput {
+            recovery_words: Some(recovery_words),
+        })
+    }
+
+    async fn rollback(&self, session: &mut VaultSession) -> Result<()> {
+        let master_key = session.master_key()?;
+        session.config_mut().restore_legacy_format(&master_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CONFIG_FILENAME, META_DIRNAME, TREE_FILENAME};
+    use crate::manager::VaultManager;
+    use crate::operations::VaultOperations;
+    use axiomvault_common::{Secret, VaultPath};
+    use axiomvault_storage::{MemoryProvider, ProviderRegistry, StorageProvider};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const PASSWORD: &[u8] = b"password";
+
+    /// A manager whose "shared" provider is always `provider`.
+    fn shared_manager(provider: Arc<dyn StorageProvider>) -> VaultManager {
+        let registry = ProviderRegistry::new();
+        registry
+            .register(
+                "shared",
+                axiomvault_storage::registry::from_fn(move |_| Ok(provider.clone())),
+            )
+            .unwrap();
+        VaultManager::with_registry(registry)
+    }
+
+    async fn open(manager: &VaultManager) -> Result<VaultSession> {
+        manager
+            .open_vault(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(PASSWORD),
+            )
+            .await
+    }
+
+    async fn open_for_migration(manager: &VaultManager) -> VaultSession {
+        manager
+            .open_vault_for_migration(
+                "shared",
+                serde_json::Value::Null,
+                &Secret::from_slice(PASSWORD),
+                None,
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn stored_config(provider: &Arc<dyn StorageProvider>) -> VaultConfig {
+        let config_path = VaultPath::parse(CONFIG_FILENAME).unwrap();
+        VaultConfig::from_bytes(&provider.download(&config_path).await.unwrap()).unwrap()
+    }
+
+    fn tree_path() -> VaultPath {
+        VaultPath::parse(META_DIRNAME)
+            .unwrap()
+            .join(TREE_FILENAME)
+            .unwrap()
+    }
+
+    /// Upload the fixture vault `name` from `fixtures/` byte for byte.
+    ///
+    /// Every fixture holds `/a.txt` with the content `legacy`; see
+    /// `fixtures/README.md` for their formats.
+    async fn load_fixture(name: &str, provider: &Arc<dyn StorageProvider>) {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures")
+            .join(name);
+        let mut dirs = vec![root.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                let relative = path.strip_prefix(&root).unwrap().to_str().unwrap();
+                let vault_path = VaultPath::parse(relative).unwrap();
+                if path.is_dir() {
+                    provider.create_dir(&vault_path).await.unwrap();
+                    dirs.push(path);
+                } else {
+                    let bytes = std::fs::read(&path).unwrap();
+                    provider.upload(&vault_path, bytes).await.unwrap();
+                }
+            }
+        }
+    }
+
+    async fn read_a(session: &VaultSession) -> Vec<u8> {
+        VaultOperations::new(session)
+            .unwrap()
+            .read_file(&VaultPath::parse("/a.txt").unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn outcomes(config: &VaultConfig) -> Vec<(&str, &MigrationOutcome)> {
+        config
+            .migrations
+            .iter()
+            .map(|run| (run.id.as_str(), &run.outcome))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_legacy_vault_migrates_in_order() {
+        let provider: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+        let manager = shared_manager(provider.clone());
+        load_fixture("v1.0", &provider).await;
+        assert!(!axiomvault_crypto::stream::is_stream_format(
+            &provider.download(&tree_path()).await.unwrap()
+        ));
+
+        assert!(matches!(
+            open(&manager).await,
+            Err(Error::MigrationRequired(ids)) if ids == ["legacy-key-wrap"]
+        ));
+
+        // Refusing the open still applied the automatic migrations.
+        let stored = stored_config(&provider).await;
+        assert!(stored.config_mac.is_some());
+        assert!(stored.is_legacy_format());
+        assert!(axiomvault_crypto::stream::is_stream_format(
+            &provider.download(&tree_path()).await.unwrap()
+        ));
+        assert_eq!(
+            outcomes(&stored),
+            [
+                ("config-mac", &MigrationOutcome::Applied),
+                ("tree-stream", &MigrationOutcome::Applied),
+            ]
+        );
+
+        let registry = SessionMigrationRegistry::with_defaults();
+        let mut session = open_for_migration(&manager).await;
+        let pending = registry.pending(&session).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, "legacy-key-wrap");
+        assert_eq!(pending[0].kind, MigrationKind::Explicit);
+        assert!(pending[0].reversible);
+        assert!(!pending[0].interrupted);
+        assert!(matches!(
+            registry.rollback(&mut session, "config-mac").await,
+            Err(Error::Unsupported(_))
+        ));
+
+        let seen = Mutex::new(Vec::new());
+        let outputs = registry
+            .apply_all(&mut session, &|p| seen.lock().unwrap().push(p))
+            .await
+            .unwrap();
+        assert_eq!(outputs.len(), 1);
+        let words = outputs[0].1.recovery_words.as_ref().unwrap();
+        assert_eq!(words.split_whitespace().count(), 24);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [MigrationProgress {
+                id: "legacy-key-wrap",
+                done: 1,
+                total: 1
+            }]
+        );
+        assert!(registry.pending(&session).await.unwrap().is_empty());
+        drop(session);
+
+        let session = open(&manager).await.unwrap();
+        assert!(!session.config().is_legacy_format());
+        assert_eq!(session.config().version, VaultVersion::CURRENT);
+        assert_eq!(read_a(&session).await, b"legacy");
+        assert_eq!(
+            outcomes(session.config()).last(),
+            Some(&("legacy-key-wrap", &MigrationOutcome::Applied))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_legacy_key_format() {
+        let provider: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+        let manager = shared_manager(provider.clone());
+        load_fixture("v1.0", &provider).await;
+
+        let registry = SessionMigrationRegistry::with_defaults();
+        let mut session = open_for_migration(&manager).await;
+        assert!(matches!(
+            registry.rollback(&mut session, "legacy-key-wrap").await,
+            Err(Error::InvalidInput(_))
+        ));
+        registry
+            .apply(&mut session, "legacy-key-wrap", &|_| {})
+            .await
+            .unwrap();
+        registry
+            .rollback(&mut session, "legacy-key-wrap")
+            .await
+            .unwrap();
+        drop(session);
+
+        let stored = stored_config(&provider).await;
+        assert!(stored.is_legacy_format());
+        assert_eq!(stored.version, VaultVersion::LEGACY);
+        assert!(stored
+            .verify_password(&Secret::from_slice(PASSWORD))
+            .unwrap()
+            .is_some());
+        assert_eq!(
+            outcomes(&stored).last(),
+            Some(&("legacy-key-wrap", &MigrationOutcome::RolledBack))
+        );
+        assert!(matches!(
+            open(&manager).await,
+            Err(Error::MigrationRequired(_))
+        ));
+        assert_eq!(read_a(&open_for_migration(&manager).await).await, b"legacy");
+    }
+
+    #[tokio::test]
+    async fn test_pre_mac_vault_migrates_on_open() {
+        let provider: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+        let manager = shared_manager(provider.clone());
+        load_fixture("v1.1-pre-mac", &provider).await;
+        assert!(stored_config(&provider).await.config_mac.is_none());
+
+        let session = open(&manager).await.unwrap();
+        assert_eq!(read_a(&session).await, b"legacy");
+        drop(session);
+
+        let stored = stored_config(&provider).await;
+        assert!(stored.config_mac.is_some());
+        assert!(!stored.is_legacy_format());
+        assert!(axiomvault_crypto::stream::is_stream_format(
+            &provider.download(&tree_path()).await.unwrap()
+        ));
+        assert_eq!(
+            outcomes(&stored),
+            [
+                ("config-mac", &MigrationOutcome::Applied),
+                ("tree-stream", &MigrationOutcome::Applied),
+            ]
+        );
+
+        // Sealed and restreamed, it opens again without migrating.
+        let session = open(&manager).await.unwrap();
+        assert!(SessionMigrationRegistry::with_defaults()
+            .pending(&session)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(read_a(&session).await, b"legacy");
+    }
+
+    #[tokio::test]
+    async fn test_rollback_refused_after_password_change() {
+        let provider: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+        let manager = shared_manager(provider.clone());
+        load_fixture("v1.0", &provider).await;
+
+        let registry = SessionMigrationRegistry::with_defaults();
+        let mut session = open_for_migration(&manager).await;
+        registry
+            .apply(&mut session, "legacy-key-wrap", &|_| {})
+            .await
+            .unwrap();
+        session
+            .change_password(
+                &Secret::from_slice(PASSWORD),
+                &Secret::from_slice(b"new-password"),
+            )
+            .unwrap();
+
+        assert!(matches!(
+            registry.rollback(&mut session, "legacy-key-wrap").await,
+            Err(Error::NotPermitted(_))
+        ));
+        assert!(!session.config().is_legacy_format());
+    }
+
+    /// Writes a marker object per step, failing once its budget of writes
+    /// runs out.
+    struct Markers {
+        steps: u64,
+        budget: AtomicUsize,
+    }
+
+    impl Markers {
+        fn path(step: u64) -> VaultPath {
+            VaultPath::parse(&format!("/{}/marker-{}", META_DIRNAME, step)).unwrap()
+        }
+
+        async fn missing(&self, session: &VaultSession) -> Vec<u64> {
+            let mut missing = Vec::new();
+            for step in 0..self.steps {
+                if !session.provider().exists(&Self::path(step)).await.unwrap() {
+                    missing.push(step);
+                }
+            }
+            missing
+        }
+    }
+
+    #[async_trait]
+    impl SessionMigration for Markers {
+        fn id(&self) -> &'static str {
+            "markers"
+        }
+
+        async fn check(&self, session: &VaultSession) -> Result<Option<MigrationPlan>> {
+            let missing = self.missing(session).await;
+            if missing.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(MigrationPlan {
+                id: self.id(),
+                description: "Write markers",
+                from: "unmarked".to_string(),
+                to: "marked".to_string(),
+                changes: Vec::new(),
+                objects: missing.len() as u64,
+                reversible: false,
+                kind: MigrationKind::Explicit,
+                interrupted: false,
+            }))
+        }
+
+        async fn apply(
+            &self,
+            session: &mut VaultSession,
+            progress: ProgressSink<'_>,
+        ) -> Result<MigrationOutput> {
+            let missing = self.missing(session).await;
+            for (done, step) in missing.iter().enumerate() {
+                if self.budget.fetch_sub(1, Ordering::SeqCst) == 0 {
+                    return Err(Error::Network("connection reset".to_string()));
+                }
+                session
+                    .provider()
+                    .upload(&Self::path(*step), Vec::new())
+                    .await?;
+                progress(MigrationProgress {
+                    id: self.id(),
+                    done: done as u64 + 1,
+                    total: missing.len() as u64,
+                });
+            }
+            Ok(MigrationOutput::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_migration_resumes() {
+        let provider: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+        let manager = shared_manager(provider.clone());
+        load_fixture("v1.1-pre-mac", &provider).await;
+
+        let mut registry = SessionMigrationRegistry::new();
+        registry.register(Box::new(Markers {
+            steps: 5,
+            budget: AtomicUsize::new(2),
+        }));
+        let mut session = open(&manager).await.unwrap();
+        assert!(matches!(
+            registry.apply(&mut session, "markers", &|_| {}).await,
+            Err(Error::Network(_))
+        ));
+        drop(session);
+
+        // Opening the fixture applied its own migrations first.
+        let stored = stored_config(&provider).await;
+        assert!(matches!(
+            outcomes(&stored)[..],
+            [
+                ("config-mac", MigrationOutcome::Applied),
+                ("tree-stream", MigrationOutcome::Applied),
+                ("markers", MigrationOutcome::Failed { .. })
+            ]
+        ));
+
+        // Pretend the process died mid-run instead.
+        let mut session = open(&manager).await.unwrap();
+        last_run_mut(session.config_mut(), "markers")
+            .unwrap()
+            .outcome = MigrationOutcome::Running;
+        let pending = registry.pending(&session).await.unwrap();
+        assert_eq!(pending[0].objects, 3);
+        assert!(pending[0].interrupted);
+
+        registry.migrations[0] = Box::new(Markers {
+            steps: 5,
+            budget: AtomicUsize::new(usize::MAX),
+        });
+        let seen = Mutex::new(Vec::new());
+        registry
+            .apply(&mut session, "markers", &|p| {
+                seen.lock().unwrap().push(p.done)
+            })
+            .await
+            .unwrap();
+        assert_eq!(*seen.lock().unwrap(), [1, 2, 3]);
+        assert!(registry.pending(&session).await.unwrap().is_empty());
+        assert!(matches!(
+            registry.apply(&mut session, "markers", &|_| {}).await,
+            Err(Error::InvalidInput(_))
+        ));
+        drop(session);
+
+        let stored = stored_config(&provider).await;
+        assert!(matches!(
+            outcomes(&stored)[2..],
+            [
+                ("markers", MigrationOutcome::Failed { .. }),
+                ("markers", MigrationOutcome::Applied)
+            ]
+        ));
+
+        // The fixture's own data survived the runs.
+        assert_eq!(read_a(&open(&manager).await.unwrap()).await, b"legacy");
+    }
+}
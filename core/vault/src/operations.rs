@@ -1,13 +1,48 @@
 //! Vault file operations with encryption/decryption.
 
-use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
-use tracing::{debug, info};
+use std::io::Write;
+use std::path::Path;
 
-use crate::config::DATA_DIRNAME;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use chrono::{DateTime, Utc};
+use tracing::{debug, field, info, instrument, warn, Span};
+
+use crate::attach::{self, StorageUsage};
+use crate::blob::{
+    decrypt_blob, decrypt_blob_to_writer, encrypt_blob, seal_blob, BlobFormat, StagedBlob,
+    BLOB_MAGIC,
+};
+use crate::config::LongNamePolicy;
+use crate::diff::{DiffOptions, DiffReport, DiffTarget};
+use crate::export::{ExportOptions, ExportReport};
+use crate::journal::{self, Intent};
+use crate::parts::{self, BlobPart};
+use crate::policy::{DirectoryPolicy, EffectivePolicy, SyncPolicy};
+use crate::repair::{RepairOptions, RepairReport};
+#[cfg(feature = "search")]
+use crate::search::{self, SearchHit};
 use crate::session::VaultSession;
-use axiomvault_common::{Error, Result, VaultPath};
+use crate::template::{TemplateConflict, TemplateReport, VaultTemplate};
+use crate::tree::NodePermissions;
+use crate::verify::{VerifyOptions, VerifyReport};
+use axiomvault_common::telemetry::{metrics, path_field};
+use axiomvault_common::{escape_lossy, Error, Result, VaultPath};
+use axiomvault_crypto::stream::DEFAULT_CHUNK_SIZE;
 use axiomvault_crypto::{decrypt, encrypt};
 
+/// A directory entry: name, whether it is a directory, and size.
+pub type DirEntry = (String, bool, Option<u64>);
+
+/// Storage name of a new file's blob.
+pub(crate) struct BlobName {
+    /// Name the blob is stored and keyed under.
+    pub(crate) key: String,
+    /// The full encrypted name when `key` is a hash of it.
+    pub(crate) long_name: Option<String>,
+}
+
 /// Vault operations handler.
 ///
 /// Provides encrypted file operations using an active session.
@@ -25,13 +60,123 @@ impl<'a> VaultOperations<'a> {
     }
 
     /// Encrypt a filename.
-    fn encrypt_name(&self, name: &str) -> Result<String> {
+    pub(crate) fn encrypt_name(&self, name: &str) -> Result<String> {
         let master_key = self.session.master_key()?;
-        let dir_key = master_key.derive_directory_key(b"names");
+        let dir_key = master_key.derive_names_key();
         let encrypted = encrypt(dir_key.as_bytes(), name.as_bytes())?;
         Ok(URL_SAFE_NO_PAD.encode(encrypted))
     }
 
+    /// Recover a name from its encrypted form.
+    ///
+    /// # Errors
+    /// - `Crypto` if the name was not encrypted by this vault, or is a
+    ///   hashed long name
+    pub(crate) fn decrypt_name(&self, encrypted_name: &str) -> Result<String> {
+        let ciphertext = URL_SAFE_NO_PAD
+            .decode(encrypted_name)
+            .map_err(|e| Error::Crypto(format!("Encrypted name is not base64: {}", e)))?;
+        let master_key = self.session.master_key()?;
+        let dir_key = master_key.derive_names_key();
+        let name = decrypt(dir_key.as_bytes(), &ciphertext)?;
+        String::from_utf8(name)
+            .map_err(|_| Error::Crypto("Decrypted name is not UTF-8".to_string()))
+    }
+
+    /// Encrypt the name of a new file, keeping its blob name within the
+    /// vault's [`NameLimit`](crate::NameLimit).
+    ///
+    /// # Errors
+    /// - The encrypted name is too long and the vault rejects long names
+    /// - The limit is too short even for a hashed name
+    pub(crate) fn blob_name(&self, name: &str) -> Result<BlobName> {
+        let encrypted_name = self.encrypt_name(name)?;
+        let limit = match self.session.name_limit() {
+            Some(limit) if encrypted_name.len() > limit.max_len => limit,
+            _ => {
+                return Ok(BlobName {
+                    key: encrypted_name,
+                    long_name: None,
+                })
+            }
+        };
+        match limit.on_overflow {
+            LongNamePolicy::Reject => Err(Error::InvalidInput(format!(
+                "Name '{}' encrypts to {} bytes, over the vault's limit of {}",
+                escape_lossy(name),
+                encrypted_name.len(),
+                limit.max_len
+            ))),
+            LongNamePolicy::Hash => {
+                let key = URL_SAFE_NO_PAD.encode(Blake2b::<U32>::digest(encrypted_name.as_bytes()));
+                if key.len() > limit.max_len {
+                    return Err(Error::InvalidInput(format!(
+                        "Name limit of {} bytes is shorter than a hashed name",
+                        limit.max_len
+                    )));
+                }
+                debug!(len = encrypted_name.len(), "Hashing long blob name");
+                Ok(BlobName {
+                    key,
+                    long_name: Some(encrypted_name),
+                })
+            }
+        }
+    }
+
+    /// Download the blob of a file, reassembling it if it is split into
+    /// `parts`.
+    ///
+    /// With a blob cache set, a downloaded blob is cached under the file's
+    /// etag, and a `Network` error is answered from the cache when it holds
+    /// the version the tree expects. Uncached files still fail.
+    async fn download_blob(
+        &self,
+        path: &VaultPath,
+        encrypted_name: &str,
+        etag: Option<&str>,
+        blob_format: BlobFormat,
+        parts: &[BlobPart],
+    ) -> Result<Vec<u8>> {
+        let cache = self.session.blob_cache().zip(etag);
+        let provider = self.session.blob_provider(path)?;
+        match parts::download(
+            provider.as_ref(),
+            self.session.data_layout(),
+            encrypted_name,
+            parts,
+        )
+        .await
+        {
+            Ok(blob) => {
+                if let Some((cache, etag)) = cache {
+                    if let Err(e) = cache.put(encrypted_name, etag, blob_format, &blob) {
+                        warn!(error = %e, "Failed to cache blob");
+                    }
+                }
+                Ok(blob)
+            }
+            Err(Error::Network(reason)) => {
+                let cached = cache.and_then(|(cache, etag)| {
+                    cache
+                        .get(encrypted_name, etag, blob_format)
+                        .unwrap_or_else(|e| {
+                            warn!(error = %e, "Failed to read blob cache");
+                            None
+                        })
+                });
+                match cached {
+                    Some(blob) => {
+                        info!("Provider unreachable, serving cached blob");
+                        Ok(blob)
+                    }
+                    None => Err(Error::Network(reason)),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     /// Create a new file with encrypted content.
     ///
     /// # Preconditions
@@ -48,36 +193,189 @@ impl<'a> VaultOperations<'a> {
     /// - File already exists
     /// - Encryption failure
     /// - Storage failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "create_file",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+            bytes = content.len(),
+        )
+    )]
     pub async fn create_file(&self, path: &VaultPath, content: &[u8]) -> Result<()> {
+        metrics::operation("create_file");
+        self.create_file_with_times(path, content, None).await
+    }
+
+    /// Create a file, optionally with the given creation and modification
+    /// times instead of now, in a single journaled tree save.
+    async fn create_file_with_times(
+        &self,
+        path: &VaultPath,
+        content: &[u8],
+        times: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<()> {
         let name = path
             .name()
             .ok_or_else(|| Error::InvalidInput("Invalid file path".to_string()))?;
 
         debug!("Creating encrypted file");
 
-        let encrypted_name = self.encrypt_name(name)?;
+        let BlobName {
+            key: encrypted_name,
+            long_name,
+        } = self.blob_name(name)?;
 
+        let suite = self.session.config().write_suite()?;
         let master_key = self.session.master_key()?;
-        let file_key = master_key.derive_file_key(encrypted_name.as_bytes());
-        let encrypted_content = encrypt(file_key.as_bytes(), content)?;
-
-        {
-            let mut tree = self.session.tree().write().await;
-            tree.create_file(path, &encrypted_name, content.len() as u64)?;
-        }
+        let encrypted_content = encrypt_blob(
+            BlobFormat::LATEST,
+            suite,
+            &master_key,
+            &encrypted_name,
+            content,
+        )?;
+
+        let ticket = journal::begin(
+            self.session,
+            Intent::Create {
+                encrypted_name: encrypted_name.clone(),
+            },
+        )
+        .await?;
+        let result = async {
+            {
+                let mut tree = self.session.tree().write().await;
+                tree.create_file(path, &encrypted_name, content.len() as u64)?;
+                let metadata = &mut tree.get_node_mut(path)?.metadata;
+                metadata.long_encrypted_name = long_name;
+                if let Some((created_at, modified_at)) = times {
+                    metadata.created_at = created_at;
+                    metadata.modified_at = modified_at;
+                }
+            }
 
-        let storage_path = VaultPath::parse(DATA_DIRNAME)?.join(&encrypted_name)?;
-        self.session
-            .provider()
-            .upload(&storage_path, encrypted_content)
+            let provider = self.session.blob_provider(path)?;
+            let uploaded = parts::upload(
+                provider.as_ref(),
+                self.session.data_layout(),
+                &encrypted_name,
+                encrypted_content,
+                self.session.part_size(),
+            )
             .await?;
+            {
+                let mut tree = self.session.tree().write().await;
+                let metadata = &mut tree.get_node_mut(path)?.metadata;
+                metadata.parts = uploaded.parts;
+                metadata.manifest = Some(uploaded.manifest);
+            }
 
-        self.session.save_tree().await?;
+            self.session.save_tree().await
+        }
+        .await;
+        journal::finish(self.session, ticket, result.is_ok()).await;
+        result?;
+
+        self.session.record_bytes(content.len() as u64);
+        #[cfg(feature = "search")]
+        self.session.content_changed(path);
 
         info!(size = content.len(), "File created");
         Ok(())
     }
 
+    /// Encrypt `content` as the next blob of the file at `path`, for the
+    /// sync staging area.
+    ///
+    /// An existing file keeps its storage name; a new one gets a fresh name.
+    /// The tree is not changed.
+    ///
+    /// # Errors
+    /// - `path` names a directory or the root
+    /// - Encryption failure
+    pub async fn seal_for_staging(&self, path: &VaultPath, content: &[u8]) -> Result<StagedBlob> {
+        let name = path
+            .name()
+            .ok_or_else(|| Error::InvalidInput("Invalid file path".to_string()))?;
+        let existing = {
+            let tree = self.session.tree().read().await;
+            match tree.get_node(path) {
+                Ok(node) if node.is_file() => Some(node.metadata.encrypted_name.clone()),
+                Ok(_) => return Err(Error::InvalidInput("Not a file".to_string())),
+                Err(_) => None,
+            }
+        };
+        let encrypted_name = match existing {
+            Some(name) => name,
+            None => self.blob_name(name)?.key,
+        };
+
+        let suite = self.session.config().write_suite()?;
+        let master_key = self.session.master_key()?;
+        let ciphertext = seal_blob(
+            BlobFormat::LATEST,
+            suite,
+            &master_key,
+            &encrypted_name,
+            content,
+        )?;
+        Ok(StagedBlob::new(
+            ciphertext,
+            self.session.vault_id().clone(),
+            encrypted_name,
+            BlobFormat::LATEST,
+        ))
+    }
+
+    /// Encrypt the current content of the file at `path` for the sync
+    /// staging area, as [`seal_for_staging`](Self::seal_for_staging) would.
+    ///
+    /// Returns `None` if there is no file at `path`. The decrypted content
+    /// is wiped once sealed.
+    ///
+    /// # Errors
+    /// - `path` names a directory
+    /// - Decryption or encryption failure
+    pub async fn seal_current_for_staging(&self, path: &VaultPath) -> Result<Option<StagedBlob>> {
+        let content = match self.read_file(path).await {
+            Ok(content) => zeroize::Zeroizing::new(content),
+            Err(Error::NotFound(_)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        self.seal_for_staging(path, &content).await.map(Some)
+    }
+
+    /// Decrypt a blob of the file at `path` fetched back from a sync remote.
+    ///
+    /// The blob must have been sealed by
+    /// [`seal_for_staging`](Self::seal_for_staging) for the same file. A
+    /// header-less blob staged by a build that predates blob headers is
+    /// read as [`BlobFormat::V2`].
+    ///
+    /// # Errors
+    /// - File not found, or `path` names a directory
+    /// - Decryption failure
+    pub async fn open_staged_blob(&self, path: &VaultPath, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let encrypted_name = {
+            let tree = self.session.tree().read().await;
+            let node = tree.get_node(path)?;
+            if !node.is_file() {
+                return Err(Error::InvalidInput("Not a file".to_string()));
+            }
+            node.metadata.encrypted_name.clone()
+        };
+
+        let format = if ciphertext.starts_with(&BLOB_MAGIC) {
+            BlobFormat::LATEST
+        } else {
+            BlobFormat::V2
+        };
+        let master_key = self.session.master_key()?;
+        decrypt_blob(format, &master_key, &encrypted_name, ciphertext)
+    }
+
     /// Read and decrypt file content.
     ///
     /// # Preconditions
@@ -91,29 +389,234 @@ impl<'a> VaultOperations<'a> {
     /// - File not found
     /// - Decryption failure
     /// - Storage failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "read_file",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+            bytes = field::Empty,
+        )
+    )]
     pub async fn read_file(&self, path: &VaultPath) -> Result<Vec<u8>> {
+        metrics::operation("read_file");
         debug!("Reading encrypted file");
 
-        let encrypted_name = {
+        let (encrypted_name, blob_format, etag, parts, _in_use) = {
             let tree = self.session.tree().read().await;
             let node = tree.get_node(path)?;
             if !node.is_file() {
                 return Err(Error::InvalidInput("Not a file".to_string()));
             }
-            node.metadata.encrypted_name.clone()
+            let name = node.metadata.encrypted_name.clone();
+            let in_use = self.session.blob_in_use(&name);
+            (
+                name,
+                node.metadata.blob_format,
+                node.metadata.etag.clone(),
+                node.metadata.parts.clone(),
+                in_use,
+            )
         };
 
-        let storage_path = VaultPath::parse(DATA_DIRNAME)?.join(&encrypted_name)?;
-        let encrypted_content = self.session.provider().download(&storage_path).await?;
+        let encrypted_content = self
+            .download_blob(path, &encrypted_name, etag.as_deref(), blob_format, &parts)
+            .await?;
 
         let master_key = self.session.master_key()?;
-        let file_key = master_key.derive_file_key(encrypted_name.as_bytes());
-        let content = decrypt(file_key.as_bytes(), &encrypted_content)?;
+        let content = decrypt_blob(
+            blob_format,
+            &master_key,
+            &encrypted_name,
+            &encrypted_content,
+        )?;
+        self.session.record_bytes(content.len() as u64);
+        self.session.record_access(path).await;
+        Span::current().record("bytes", content.len());
 
         debug!(size = content.len(), "File read");
         Ok(content)
     }
 
+    /// Decrypt file content straight into `writer`.
+    ///
+    /// Unlike [`read_file`](Self::read_file), the plaintext is never
+    /// collected in one buffer for blobs in the chunked stream format: each
+    /// chunk is decrypted and written before the next is opened. Single-blob
+    /// files are decrypted once and written in one call.
+    ///
+    /// With a [`StreamBudget`](crate::StreamBudget) set on the session, the
+    /// read first waits for its share of the budget.
+    ///
+    /// Returns the number of plaintext bytes written.
+    ///
+    /// # Preconditions
+    /// - File must exist
+    /// - Session must be active
+    ///
+    /// # Errors
+    /// - File not found
+    /// - Decryption failure
+    /// - Storage failure
+    /// - Write failure from `writer`
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "read_file_to_writer",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+            bytes = field::Empty,
+        )
+    )]
+    pub async fn read_file_to_writer<W: Write>(
+        &self,
+        path: &VaultPath,
+        mut writer: W,
+    ) -> Result<u64> {
+        metrics::operation("read_file");
+        debug!("Reading encrypted file into writer");
+
+        let (encrypted_name, blob_format, etag, size, parts, _in_use) = {
+            let tree = self.session.tree().read().await;
+            let node = tree.get_node(path)?;
+            if !node.is_file() {
+                return Err(Error::InvalidInput("Not a file".to_string()));
+            }
+            let name = node.metadata.encrypted_name.clone();
+            let in_use = self.session.blob_in_use(&name);
+            (
+                name,
+                node.metadata.blob_format,
+                node.metadata.etag.clone(),
+                node.metadata.size.unwrap_or(0),
+                node.metadata.parts.clone(),
+                in_use,
+            )
+        };
+
+        // The read holds the whole ciphertext plus one decrypted chunk.
+        let _reservation = match self.session.stream_budget() {
+            Some(budget) => Some(budget.reserve(size + DEFAULT_CHUNK_SIZE as u64).await?),
+            None => None,
+        };
+
+        let encrypted_content = self
+            .download_blob(path, &encrypted_name, etag.as_deref(), blob_format, &parts)
+            .await?;
+
+        let master_key = self.session.master_key()?;
+        let written = decrypt_blob_to_writer(
+            blob_format,
+            &master_key,
+            &encrypted_name,
+            &encrypted_content,
+            &mut writer,
+        )?;
+        self.session.record_bytes(written);
+        self.session.record_access(path).await;
+        Span::current().record("bytes", written);
+
+        debug!(size = written, "File read");
+        Ok(written)
+    }
+
+    /// Decrypt the subtree at `prefix` into `dest_dir`.
+    ///
+    /// Files keep their vault-relative layout and modification times and
+    /// are streamed to disk one at a time. See [`crate::export`] for how an
+    /// interrupted run is resumed.
+    ///
+    /// # Preconditions
+    /// - `prefix` must exist
+    /// - Session must be active
+    ///
+    /// # Errors
+    /// - Path not found
+    /// - `dest_dir` holds an unfinished export of a different prefix
+    /// - `Integrity` if `options.verify` finds a mismatching file
+    /// - Decryption, storage or local write failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "export_tree",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(prefix),
+        )
+    )]
+    pub async fn export_tree(
+        &self,
+        prefix: &VaultPath,
+        dest_dir: &Path,
+        options: &ExportOptions,
+    ) -> Result<ExportReport> {
+        metrics::operation("export_tree");
+        crate::export::export_tree(self, self.session, prefix, dest_dir, options).await
+    }
+
+    /// Compare this vault with another vault or a local directory.
+    ///
+    /// Reports paths found on one side only, files whose content differs
+    /// and files that differ only in metadata. See [`crate::diff`] for how
+    /// content is compared without holding files in memory.
+    ///
+    /// # Errors
+    /// - `NotFound` if a local target directory does not exist
+    /// - Decryption, storage or local read failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(op = "diff_against", vault_id = %self.session.vault_id())
+    )]
+    pub async fn diff_against(
+        &self,
+        target: DiffTarget<'_>,
+        options: &DiffOptions,
+    ) -> Result<DiffReport> {
+        metrics::operation("diff_against");
+        crate::diff::diff_against(self, self.session, target, options).await
+    }
+
+    /// Reconcile the tree with the blobs in storage.
+    ///
+    /// Blobs no file references are re-added under
+    /// [`RECOVERY_DIR`](crate::repair::RECOVERY_DIR) when their name and
+    /// content decrypt, and reported otherwise. See [`crate::repair`].
+    ///
+    /// # Errors
+    /// - Listing the data directory or saving the tree fails
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(op = "repair", vault_id = %self.session.vault_id())
+    )]
+    pub async fn repair(&self, options: &RepairOptions) -> Result<RepairReport> {
+        metrics::operation("repair");
+        crate::repair::repair(self, self.session, options).await
+    }
+
+    /// Check that every file's blob is intact.
+    ///
+    /// A full run downloads and decrypts every blob. A fast run compares
+    /// provider metadata and a sample of chunks with each file's manifest,
+    /// and verifies a file in full only when they disagree. See
+    /// [`crate::verify`].
+    ///
+    /// # Errors
+    /// - Storage failure other than a missing or damaged blob, which is
+    ///   reported per file
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(op = "verify", vault_id = %self.session.vault_id(), fast = options.fast)
+    )]
+    pub async fn verify(&self, options: &VerifyOptions) -> Result<VerifyReport> {
+        metrics::operation("verify");
+        crate::verify::verify(self.session, options).await
+    }
+
     /// Update file with new encrypted content.
     ///
     /// # Preconditions
@@ -128,41 +631,147 @@ impl<'a> VaultOperations<'a> {
     /// - File not found
     /// - Encryption failure
     /// - Storage failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "update_file",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+            bytes = content.len(),
+        )
+    )]
     pub async fn update_file(&self, path: &VaultPath, content: &[u8]) -> Result<()> {
+        metrics::operation("update_file");
         debug!("Updating encrypted file");
 
-        let encrypted_name = {
+        let (encrypted_name, old_parts, _in_use) = {
             let tree = self.session.tree().read().await;
             let node = tree.get_node(path)?;
             if !node.is_file() {
                 return Err(Error::InvalidInput("Not a file".to_string()));
             }
-            node.metadata.encrypted_name.clone()
+            let name = node.metadata.encrypted_name.clone();
+            let in_use = self.session.blob_in_use(&name);
+            (name, node.metadata.parts.clone(), in_use)
         };
 
+        // A full rewrite always produces the latest blob format.
+        let suite = self.session.config().write_suite()?;
         let master_key = self.session.master_key()?;
-        let file_key = master_key.derive_file_key(encrypted_name.as_bytes());
-        let encrypted_content = encrypt(file_key.as_bytes(), content)?;
-
-        {
-            let mut tree = self.session.tree().write().await;
-            let node = tree.get_node_mut(path)?;
-            node.metadata.size = Some(content.len() as u64);
-            node.metadata.modified_at = chrono::Utc::now();
-        }
-
-        let storage_path = VaultPath::parse(DATA_DIRNAME)?.join(&encrypted_name)?;
-        self.session
-            .provider()
-            .upload(&storage_path, encrypted_content)
+        let encrypted_content = encrypt_blob(
+            BlobFormat::LATEST,
+            suite,
+            &master_key,
+            &encrypted_name,
+            content,
+        )?;
+
+        let part_size = self.session.part_size();
+        let modified_at = chrono::Utc::now();
+        let etag = uuid::Uuid::new_v4().to_string();
+        let ticket = journal::begin(
+            self.session,
+            Intent::Update {
+                encrypted_name: encrypted_name.clone(),
+                size: content.len() as u64,
+                etag: etag.clone(),
+                modified_at,
+                parts: parts::plan(&encrypted_name, encrypted_content.len(), part_size),
+                old_parts: old_parts.clone(),
+            },
+        )
+        .await?;
+
+        let provider = self.session.blob_provider(path)?;
+        let result: Result<Vec<BlobPart>> = async {
+            let uploaded = parts::upload(
+                provider.as_ref(),
+                self.session.data_layout(),
+                &encrypted_name,
+                encrypted_content,
+                part_size,
+            )
             .await?;
+            let new_parts = uploaded.parts;
+
+            {
+                let mut tree = self.session.tree().write().await;
+                let node = tree.get_node_mut(path)?;
+                node.metadata.size = Some(content.len() as u64);
+                node.metadata.modified_at = modified_at;
+                node.metadata.etag = Some(etag);
+                node.metadata.blob_format = BlobFormat::LATEST;
+                node.metadata.parts = new_parts.clone();
+                node.metadata.manifest = Some(uploaded.manifest);
+            }
 
-        self.session.save_tree().await?;
+            self.session.save_tree().await?;
+            Ok(new_parts)
+        }
+        .await;
+        journal::finish(self.session, ticket, result.is_ok()).await;
+        let new_parts = result?;
+
+        parts::remove_stale(
+            provider.as_ref(),
+            self.session.data_layout(),
+            (&encrypted_name, &old_parts),
+            (&encrypted_name, &new_parts),
+        )
+        .await;
+        self.session.record_bytes(content.len() as u64);
+        #[cfg(feature = "search")]
+        self.session.content_changed(path);
 
         info!(size = content.len(), "File updated");
         Ok(())
     }
 
+    /// Write a file, creating it when absent and updating it when present.
+    ///
+    /// With `create_parents`, missing parent directories are created first,
+    /// like `mkdir -p`; otherwise the parent must exist.
+    ///
+    /// # Returns
+    /// Whether the file was created rather than updated.
+    ///
+    /// # Errors
+    /// - Parent not found, without `create_parents`
+    /// - `path` or one of its parents is of the wrong kind
+    /// - Encryption failure
+    /// - Storage failure
+    pub async fn write_file(
+        &self,
+        path: &VaultPath,
+        content: &[u8],
+        create_parents: bool,
+    ) -> Result<bool> {
+        if create_parents {
+            if let Some(parent) = path.parent() {
+                self.create_directory_all(&parent).await?;
+            }
+        }
+
+        let exists = {
+            let tree = self.session.tree().read().await;
+            match tree.get_node(path) {
+                Ok(node) if node.is_file() => true,
+                Ok(_) => return Err(Error::InvalidInput("Not a file".to_string())),
+                Err(_) => false,
+            }
+        };
+        if exists {
+            return self.update_file(path, content).await.map(|()| false);
+        }
+        match self.create_file(path, content).await {
+            Ok(()) => Ok(true),
+            // Another writer created it in the meantime.
+            Err(Error::AlreadyExists(_)) => self.update_file(path, content).await.map(|()| false),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Delete a file.
     ///
     /// # Preconditions
@@ -175,24 +784,63 @@ impl<'a> VaultOperations<'a> {
     /// # Errors
     /// - File not found
     /// - Storage failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "delete_file",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+        )
+    )]
     pub async fn delete_file(&self, path: &VaultPath) -> Result<()> {
+        metrics::operation("delete_file");
         debug!("Deleting file");
 
-        let encrypted_name = {
-            let mut tree = self.session.tree().write().await;
+        let (encrypted_name, parts) = {
+            let tree = self.session.tree().read().await;
             let node = tree.get_node(path)?;
             if !node.is_file() {
                 return Err(Error::InvalidInput("Not a file".to_string()));
             }
-            let name = node.metadata.encrypted_name.clone();
-            tree.remove(path)?;
-            name
+            (
+                node.metadata.encrypted_name.clone(),
+                node.metadata.parts.clone(),
+            )
         };
 
-        let storage_path = VaultPath::parse(DATA_DIRNAME)?.join(&encrypted_name)?;
-        self.session.provider().delete(&storage_path).await?;
+        let ticket = journal::begin(
+            self.session,
+            Intent::Delete {
+                encrypted_name: encrypted_name.clone(),
+                parts: parts.clone(),
+            },
+        )
+        .await?;
+        let result = async {
+            let provider = self.session.blob_provider(path)?;
+            self.session.tree().write().await.remove(path)?;
+
+            parts::delete(
+                provider.as_ref(),
+                self.session.data_layout(),
+                &encrypted_name,
+                &parts,
+            )
+            .await?;
+            if let Some(cache) = self.session.blob_cache() {
+                if let Err(e) = cache.remove(&encrypted_name) {
+                    warn!(error = %e, "Failed to drop cached blob");
+                }
+            }
 
-        self.session.save_tree().await?;
+            self.session.save_tree().await
+        }
+        .await;
+        journal::finish(self.session, ticket, result.is_ok()).await;
+        result?;
+        #[cfg(feature = "search")]
+        self.session.content_changed(path);
 
         info!("File deleted");
         Ok(())
@@ -211,7 +859,17 @@ impl<'a> VaultOperations<'a> {
     /// # Errors
     /// - Parent not found
     /// - Already exists
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "create_directory",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+        )
+    )]
     pub async fn create_directory(&self, path: &VaultPath) -> Result<()> {
+        metrics::operation("create_directory");
         let name = path
             .name()
             .ok_or_else(|| Error::InvalidInput("Invalid directory path".to_string()))?;
@@ -231,6 +889,40 @@ impl<'a> VaultOperations<'a> {
         Ok(())
     }
 
+    /// Create a directory and any missing ancestors, like `mkdir -p`.
+    ///
+    /// Each missing directory gets its own encrypted name. Directories that
+    /// already exist are left alone, so creating an existing directory is a
+    /// no-op.
+    ///
+    /// # Postconditions
+    /// - Every directory on the way to `path` exists in the tree
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `path` or one of its ancestors is a file
+    pub async fn create_directory_all(&self, path: &VaultPath) -> Result<()> {
+        for current in path.ancestors().skip(1).chain([path.clone()]) {
+            let kind = {
+                let tree = self.session.tree().read().await;
+                tree.get_node(&current).map(|node| node.is_file())
+            };
+            match kind {
+                Ok(false) => continue,
+                Ok(true) => {
+                    return Err(Error::InvalidInput(format!(
+                        "{} is a file",
+                        current.display_lossy()
+                    )))
+                }
+                Err(_) => match self.create_directory(&current).await {
+                    Ok(()) | Err(Error::AlreadyExists(_)) => {}
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+        Ok(())
+    }
+
     /// List directory contents.
     ///
     /// # Preconditions
@@ -238,10 +930,17 @@ impl<'a> VaultOperations<'a> {
     ///
     /// # Returns
     /// List of (name, is_directory, size) tuples.
-    pub async fn list_directory(
-        &self,
-        path: &VaultPath,
-    ) -> Result<Vec<(String, bool, Option<u64>)>> {
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "list_directory",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+        )
+    )]
+    pub async fn list_directory(&self, path: &VaultPath) -> Result<Vec<DirEntry>> {
+        metrics::operation("list_directory");
         let tree = self.session.tree().read().await;
         let contents = tree.list(path)?;
 
@@ -257,6 +956,46 @@ impl<'a> VaultOperations<'a> {
             .collect())
     }
 
+    /// List one page of a directory, sorted by name.
+    ///
+    /// Served from the in-memory tree, so paging through a large directory
+    /// never touches the provider.
+    ///
+    /// # Returns
+    /// Up to `limit` (name, is_directory, size) tuples starting at `offset`,
+    /// and whether more entries follow.
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "list_directory_page",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+        )
+    )]
+    pub async fn list_directory_page(
+        &self,
+        path: &VaultPath,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<DirEntry>, bool)> {
+        metrics::operation("list_directory_page");
+        let tree = self.session.tree().read().await;
+        let (contents, has_more) = tree.list_page(path, offset, limit)?;
+
+        let entries = contents
+            .iter()
+            .map(|node| {
+                (
+                    node.metadata.name.clone(),
+                    node.is_directory(),
+                    node.metadata.size,
+                )
+            })
+            .collect();
+        Ok((entries, has_more))
+    }
+
     /// Delete an empty directory.
     ///
     /// # Preconditions
@@ -266,7 +1005,17 @@ impl<'a> VaultOperations<'a> {
     /// # Errors
     /// - Not a directory
     /// - Directory not empty
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "delete_directory",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+        )
+    )]
     pub async fn delete_directory(&self, path: &VaultPath) -> Result<()> {
+        metrics::operation("delete_directory");
         debug!("Deleting directory");
 
         {
@@ -282,6 +1031,7 @@ impl<'a> VaultOperations<'a> {
             }
 
             tree.remove(path)?;
+            self.session.invalidate_directory_policies_below(path);
         }
 
         self.session.save_tree().await?;
@@ -290,41 +1040,421 @@ impl<'a> VaultOperations<'a> {
         Ok(())
     }
 
-    /// Check if path exists.
-    pub async fn exists(&self, path: &VaultPath) -> bool {
-        let tree = self.session.tree().read().await;
-        tree.exists(path)
-    }
+    /// Rename or move a file or directory.
+    ///
+    /// Within one provider only the tree changes. Ciphertext blobs live flat
+    /// under the data directory, addressed by their encrypted storage name,
+    /// and that name also seeds the file key, so a blob keeps its storage
+    /// path across renames and no provider-side move is needed, so neither
+    /// [`axiomvault_storage::StorageProvider::rename`] nor its copy + delete
+    /// fallback is called here. Blobs that cross an attach point's boundary are copied to the
+    /// other provider and deleted once the tree is saved; see
+    /// [`crate::attach`].
+    ///
+    /// # Errors
+    /// - Source not found
+    /// - Destination already exists
+    /// - Destination parent missing or not a directory
+    /// - `InvalidInput` if the source is or contains an attach point
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "rename",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(from),
+            to = %path_field(to),
+        )
+    )]
+    pub async fn rename(&self, from: &VaultPath, to: &VaultPath) -> Result<()> {
+        metrics::operation("rename");
+        debug!("Renaming path");
+
+        let moves = attach::plan_moves(self.session, from, to).await?;
+        if !moves.is_empty() {
+            // Check the tree would take the rename before copying anything.
+            self.session.tree().read().await.clone().rename(from, to)?;
+            attach::copy_blobs(self.session, &moves).await?;
+        }
+        if let Err(e) = self.session.tree().write().await.rename(from, to) {
+            attach::remove_copies(self.session, &moves).await;
+            return Err(e);
+        }
+        self.session.invalidate_directory_policies_below(from);
+
+        if let Err(e) = self.session.save_tree().await {
+            if !moves.is_empty() {
+                // The copies go again, so the tree must point at the
+                // originals.
+                let _ = self.session.tree().write().await.rename(to, from);
+                attach::remove_copies(self.session, &moves).await;
+            }
+            return Err(e);
+        }
+        attach::remove_sources(self.session, &moves).await;
+        #[cfg(feature = "search")]
+        self.session.content_moved(from, to);
 
-    /// Get metadata for a path.
-    pub async fn metadata(&self, path: &VaultPath) -> Result<(String, bool, Option<u64>)> {
-        let tree = self.session.tree().read().await;
-        let node = tree.get_node(path)?;
-        Ok((
-            node.metadata.name.clone(),
-            node.is_directory(),
-            node.metadata.size,
-        ))
+        info!("Path renamed");
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::VaultConfig;
-    use axiomvault_common::VaultId;
-    use axiomvault_crypto::KdfParams;
-    use axiomvault_storage::{MemoryProvider, StorageProvider};
-    use std::sync::Arc;
+    /// Copy a file from one vault to another, optionally deleting the
+    /// source.
+    ///
+    /// The content is decrypted with `src`'s keys and encrypted again under
+    /// `dst`'s, so the two vaults may use different passwords and KDF
+    /// parameters. The destination keeps the source's creation and
+    /// modification times. The source is only deleted once the destination
+    /// has been saved. Within a single vault, moving is a rename.
+    ///
+    /// # Errors
+    /// - Source not found, or not a file
+    /// - Destination parent not found, or destination already exists
+    /// - Decryption, encryption or storage failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "transfer",
+            vault_id = %src.session.vault_id(),
+            path = %path_field(src_path),
+            to_vault = %dst.session.vault_id(),
+            to = %path_field(dst_path),
+        )
+    )]
+    pub async fn transfer(
+        src: &VaultOperations<'_>,
+        src_path: &VaultPath,
+        dst: &VaultOperations<'_>,
+        dst_path: &VaultPath,
+        delete_src: bool,
+    ) -> Result<()> {
+        metrics::operation("transfer");
+        if delete_src && std::ptr::eq(src.session, dst.session) {
+            return src.rename(src_path, dst_path).await;
+        }
 
-    async fn create_test_session() -> VaultSession {
-        let id = VaultId::new("test").unwrap();
-        let password = b"test-password";
-        let params = KdfParams::moderate();
-        let creation =
-            VaultConfig::new(id, password, "memory", serde_json::Value::Null, params).unwrap();
+        let (created_at, modified_at) = {
+            let tree = src.session.tree().read().await;
+            let node = tree.get_node(src_path)?;
+            if !node.is_file() {
+                return Err(Error::InvalidInput("Not a file".to_string()));
+            }
+            (node.metadata.created_at, node.metadata.modified_at)
+        };
 
-        let provider = Arc::new(MemoryProvider::new());
+        let content = zeroize::Zeroizing::new(src.read_file(src_path).await?);
+        dst.create_file_with_times(dst_path, &content[..], Some((created_at, modified_at)))
+            .await?;
+
+        if delete_src {
+            src.delete_file(src_path).await?;
+        }
+
+        info!(size = content.len(), moved = delete_src, "File transferred");
+        Ok(())
+    }
+
+    /// Files and bytes stored on the vault's provider and on each attach
+    /// point's, in that order.
+    pub async fn storage_usage(&self) -> Vec<StorageUsage> {
+        attach::usage(self.session).await
+    }
+
+    /// Set the policy overrides of a directory, replacing earlier ones.
+    ///
+    /// An empty policy removes the overrides, so the directory inherits
+    /// everything from its parent again.
+    ///
+    /// # Errors
+    /// - Path not found
+    /// - Not a directory
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "set_directory_policy",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+        )
+    )]
+    pub async fn set_directory_policy(
+        &self,
+        path: &VaultPath,
+        policy: DirectoryPolicy,
+    ) -> Result<()> {
+        metrics::operation("set_directory_policy");
+        {
+            let mut tree = self.session.tree().write().await;
+            let node = tree.get_node_mut(path)?;
+            if !node.is_directory() {
+                return Err(Error::InvalidInput("Not a directory".to_string()));
+            }
+            node.metadata.policy = (!policy.is_empty()).then_some(policy);
+            self.session.invalidate_directory_policies_below(path);
+        }
+
+        self.session.save_tree().await?;
+
+        info!("Directory policy set");
+        Ok(())
+    }
+
+    /// Policy overrides set directly on a directory, if any.
+    pub async fn directory_policy(&self, path: &VaultPath) -> Result<Option<DirectoryPolicy>> {
+        let tree = self.session.tree().read().await;
+        let node = tree.get_node(path)?;
+        if !node.is_directory() {
+            return Err(Error::InvalidInput("Not a directory".to_string()));
+        }
+        Ok(node.metadata.policy)
+    }
+
+    /// Policy in effect for a file or directory.
+    ///
+    /// A file follows its parent directory. Settings no ancestor overrides
+    /// come from the vault's default policy.
+    ///
+    /// # Errors
+    /// - Path not found
+    pub async fn effective_policy(&self, path: &VaultPath) -> Result<EffectivePolicy> {
+        let tree = self.session.tree().read().await;
+        let node = tree.get_node(path)?;
+        let dir = match path.parent() {
+            Some(parent) if node.is_file() => parent,
+            _ => path.clone(),
+        };
+        self.session.effective_directory_policy(&tree, &dir)
+    }
+
+    /// Top-most directories whose effective policy excludes them from sync.
+    ///
+    /// Everything below a returned path is excluded as well, so the list can
+    /// be handed to the sync layer as a set of excluded prefixes.
+    pub async fn sync_excluded_paths(&self) -> Result<Vec<VaultPath>> {
+        let tree = self.session.tree().read().await;
+        let mut excluded = Vec::new();
+        let mut pending = vec![VaultPath::root()];
+        while let Some(dir) = pending.pop() {
+            if self.session.effective_directory_policy(&tree, &dir)?.sync == SyncPolicy::Exclude {
+                excluded.push(dir);
+                continue;
+            }
+            for child in tree.get_node(&dir)?.children.values() {
+                if child.is_directory() {
+                    pending.push(dir.join(&child.metadata.name)?);
+                }
+            }
+        }
+        excluded.sort_by_key(|p| p.to_string());
+        Ok(excluded)
+    }
+
+    /// Create the directories, policies and seed files of `template`.
+    ///
+    /// Every entry is checked against the vault first, so in
+    /// [`TemplateConflict::Error`] mode a conflict fails the call before
+    /// anything changes. The tree is saved once for the whole template.
+    /// Vault-wide defaults are not applied here; see
+    /// [`VaultTemplate::apply_defaults`].
+    ///
+    /// # Errors
+    /// - `InvalidInput` naming the manifest entry that failed validation
+    /// - `AlreadyExists` naming the entry that conflicts with the vault
+    /// - Storage failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "apply_template",
+            vault_id = %self.session.vault_id(),
+            on_conflict = ?on_conflict,
+        )
+    )]
+    pub async fn apply_template(
+        &self,
+        template: &VaultTemplate,
+        on_conflict: TemplateConflict,
+    ) -> Result<TemplateReport> {
+        metrics::operation("apply_template");
+        crate::template::apply_template(self, self.session, template, on_conflict).await
+    }
+
+    /// Describe this vault's structure as a template.
+    ///
+    /// Lists every directory with its policy and the vault-wide default
+    /// policy. File contents are only included with `include_files` set,
+    /// for files no larger than that many bytes (and never above
+    /// [`MAX_SEED_FILE_SIZE`](crate::template::MAX_SEED_FILE_SIZE)).
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(op = "export_template", vault_id = %self.session.vault_id())
+    )]
+    pub async fn export_template(&self, include_files: Option<u64>) -> Result<VaultTemplate> {
+        metrics::operation("export_template");
+        crate::template::export_template(self, self.session, include_files).await
+    }
+
+    /// Ownership and mode stored for a file or directory.
+    ///
+    /// # Errors
+    /// - Path not found
+    pub async fn permissions(&self, path: &VaultPath) -> Result<NodePermissions> {
+        let tree = self.session.tree().read().await;
+        Ok(tree.get_node(path)?.metadata.permissions())
+    }
+
+    /// Store ownership and mode for a file or directory.
+    ///
+    /// Only the fields set in `permissions` change; the others keep their
+    /// stored value.
+    ///
+    /// # Errors
+    /// - Path not found
+    /// - Storage failure
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(
+            op = "set_permissions",
+            vault_id = %self.session.vault_id(),
+            path = %path_field(path),
+        )
+    )]
+    pub async fn set_permissions(
+        &self,
+        path: &VaultPath,
+        permissions: NodePermissions,
+    ) -> Result<()> {
+        metrics::operation("set_permissions");
+        {
+            let mut tree = self.session.tree().write().await;
+            let metadata = &mut tree.get_node_mut(path)?.metadata;
+            if let Some(mode) = permissions.mode {
+                metadata.mode = Some(mode & 0o7777);
+            }
+            if permissions.uid.is_some() {
+                metadata.uid = permissions.uid;
+            }
+            if permissions.gid.is_some() {
+                metadata.gid = permissions.gid;
+            }
+        }
+
+        self.session.save_tree().await?;
+
+        debug!("Permissions set");
+        Ok(())
+    }
+
+    /// Find files whose contents contain `query` as a phrase.
+    ///
+    /// Words are matched case-insensitively, ignoring punctuation. Returns
+    /// at most `limit` files in path order, each with the offsets and
+    /// snippets of its matches, cut from the decrypted text at query time.
+    /// Files not yet indexed are not found.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if search is not enabled
+    /// - `InvalidInput` if the query has no words of two or more characters
+    #[cfg(feature = "search")]
+    #[instrument(
+        name = "vault_op",
+        skip_all,
+        fields(op = "search_content", vault_id = %self.session.vault_id())
+    )]
+    pub async fn search_content(&self, query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+        metrics::operation("search_content");
+        let index = self.session.require_search_index()?;
+        search::search(self, &index, query, limit).await
+    }
+
+    /// Files neither read nor written since `older_than`, sorted by path.
+    ///
+    /// Reads only count when the vault tracks access times; otherwise this
+    /// lists the files not modified since `older_than`.
+    pub async fn list_stale(&self, older_than: DateTime<Utc>) -> Vec<VaultPath> {
+        self.session
+            .tree()
+            .read()
+            .await
+            .files_unused_since(older_than)
+    }
+
+    /// Check if path exists.
+    pub async fn exists(&self, path: &VaultPath) -> bool {
+        let tree = self.session.tree().read().await;
+        tree.exists(path)
+    }
+
+    /// Get the entry for a path, or `None` if it does not exist.
+    ///
+    /// Unlike `exists` followed by `metadata`, both answers come from a
+    /// single tree lock, so the entry cannot change in between.
+    pub async fn exists_detailed(&self, path: &VaultPath) -> Option<DirEntry> {
+        let tree = self.session.tree().read().await;
+        let node = tree.get_node(path).ok()?;
+        Some((
+            node.metadata.name.clone(),
+            node.is_directory(),
+            node.metadata.size,
+        ))
+    }
+
+    /// Get metadata for a path.
+    pub async fn metadata(&self, path: &VaultPath) -> Result<(String, bool, Option<u64>)> {
+        let tree = self.session.tree().read().await;
+        let node = tree.get_node(path)?;
+        Ok((
+            node.metadata.name.clone(),
+            node.is_directory(),
+            node.metadata.size,
+        ))
+    }
+
+    /// Get metadata for several paths under a single tree lock.
+    ///
+    /// The results line up with `paths` and all come from the same
+    /// snapshot of the tree, so no entry can change between lookups.
+    pub async fn read_metadata_bulk(&self, paths: &[VaultPath]) -> Vec<Result<DirEntry>> {
+        let tree = self.session.tree().read().await;
+        paths
+            .iter()
+            .map(|path| {
+                let node = tree.get_node(path)?;
+                Ok((
+                    node.metadata.name.clone(),
+                    node.is_directory(),
+                    node.metadata.size,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{VaultConfig, DATA_DIRNAME, META_DIRNAME, TREE_FILENAME};
+    use axiomvault_common::Secret;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::{CipherSuite, KdfParams};
+    use axiomvault_storage::test_util::CountingProvider;
+    use axiomvault_storage::{MemoryProvider, StorageProvider};
+    use std::sync::Arc;
+
+    async fn create_test_session() -> VaultSession {
+        create_session("test", b"test-password", KdfParams::moderate()).await
+    }
+
+    async fn create_session(id: &str, password: &[u8], params: KdfParams) -> VaultSession {
+        let id = VaultId::new(id).unwrap();
+        let creation =
+            VaultConfig::new(id, password, "memory", serde_json::Value::Null, params).unwrap();
+
+        let provider = Arc::new(MemoryProvider::new());
 
         provider
             .create_dir(&VaultPath::parse("/d").unwrap())
@@ -337,7 +1467,13 @@ mod tests {
             .unwrap();
 
         use crate::tree::VaultTree;
-        VaultSession::unlock(creation.config, password, provider, VaultTree::new()).unwrap()
+        VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(password),
+            provider,
+            VaultTree::new(),
+        )
+        .unwrap()
     }
 
     #[tokio::test]
@@ -367,6 +1503,366 @@ mod tests {
         assert_eq!(content, b"updated");
     }
 
+    #[tokio::test]
+    async fn test_write_file_creates_then_overwrites() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/notes.txt").unwrap();
+
+        assert!(ops.write_file(&path, b"first", false).await.unwrap());
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"first");
+
+        assert!(!ops
+            .write_file(&path, b"second, longer", false)
+            .await
+            .unwrap());
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"second, longer");
+        assert_eq!(
+            ops.list_directory(&VaultPath::root()).await.unwrap().len(),
+            1
+        );
+
+        // A directory is not overwritten.
+        let dir = VaultPath::parse("/dir").unwrap();
+        ops.create_directory(&dir).await.unwrap();
+        assert!(matches!(
+            ops.write_file(&dir, b"x", false).await,
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_write_file_creates_missing_parents_on_request() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_directory(&VaultPath::parse("/a").unwrap())
+            .await
+            .unwrap();
+        let path = VaultPath::parse("/a/b/c/file.txt").unwrap();
+
+        assert!(ops.write_file(&path, b"deep", false).await.is_err());
+        assert!(!ops.exists(&VaultPath::parse("/a/b").unwrap()).await);
+
+        assert!(ops.write_file(&path, b"deep", true).await.unwrap());
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"deep");
+        let (_, is_dir, _) = ops
+            .metadata(&VaultPath::parse("/a/b/c").unwrap())
+            .await
+            .unwrap();
+        assert!(is_dir);
+
+        // A file in the way of a parent is an error, not replaced.
+        let blocked = VaultPath::parse("/a/b/c/file.txt/inner").unwrap();
+        assert!(matches!(
+            ops.write_file(&blocked, b"x", true).await,
+            Err(Error::InvalidInput(_))
+        ));
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"deep");
+    }
+
+    #[tokio::test]
+    async fn test_empty_file_lifecycle() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/.gitkeep").unwrap();
+
+        ops.create_file(&path, b"").await.unwrap();
+        assert!(ops.read_file(&path).await.unwrap().is_empty());
+        assert_eq!(ops.metadata(&path).await.unwrap().2, Some(0));
+
+        ops.update_file(&path, b"content").await.unwrap();
+        ops.update_file(&path, b"").await.unwrap();
+        assert!(ops.read_file(&path).await.unwrap().is_empty());
+        assert_eq!(ops.metadata(&path).await.unwrap().2, Some(0));
+
+        // A header-only stream blob also reads back as an empty file.
+        let encrypted_name = {
+            let tree = session.tree().read().await;
+            tree.get_node(&path)
+                .unwrap()
+                .metadata
+                .encrypted_name
+                .clone()
+        };
+        let storage_path = VaultPath::parse(DATA_DIRNAME)
+            .unwrap()
+            .join(&encrypted_name)
+            .unwrap();
+        session
+            .provider()
+            .upload(
+                &storage_path,
+                crate::blob::encrypt_streamed_blob(
+                    &session.master_key().unwrap(),
+                    &encrypted_name,
+                    b"",
+                ),
+            )
+            .await
+            .unwrap();
+        let mut out = Vec::new();
+        assert_eq!(ops.read_file_to_writer(&path, &mut out).await.unwrap(), 0);
+        assert!(out.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_seal_for_staging() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/notes.txt").unwrap();
+
+        let fresh = ops.seal_for_staging(&path, b"draft").await.unwrap();
+        assert_eq!(fresh.vault_id(), session.vault_id());
+        assert_eq!(fresh.format_version(), BlobFormat::LATEST);
+        assert!(!ops.exists(&path).await);
+
+        ops.create_file(&path, b"v1").await.unwrap();
+        let encrypted_name = {
+            let tree = session.tree().read().await;
+            tree.get_node(&path)
+                .unwrap()
+                .metadata
+                .encrypted_name
+                .clone()
+        };
+        let staged = ops.seal_for_staging(&path, b"v2").await.unwrap();
+        assert_eq!(staged.node_id(), encrypted_name);
+        let master_key = session.master_key().unwrap();
+        assert_eq!(
+            decrypt_blob(
+                staged.format_version(),
+                &master_key,
+                staged.node_id(),
+                staged.ciphertext().as_bytes(),
+            )
+            .unwrap(),
+            b"v2"
+        );
+        assert_eq!(
+            ops.open_staged_blob(&path, staged.ciphertext().as_bytes())
+                .await
+                .unwrap(),
+            b"v2"
+        );
+
+        let current = ops.seal_current_for_staging(&path).await.unwrap().unwrap();
+        assert_eq!(current.node_id(), encrypted_name);
+        assert_eq!(
+            ops.open_staged_blob(&path, current.ciphertext().as_bytes())
+                .await
+                .unwrap(),
+            b"v1"
+        );
+        let missing = VaultPath::parse("/missing.txt").unwrap();
+        assert!(ops
+            .seal_current_for_staging(&missing)
+            .await
+            .unwrap()
+            .is_none());
+
+        let dir = VaultPath::parse("/dir").unwrap();
+        ops.create_directory(&dir).await.unwrap();
+        assert!(ops.seal_for_staging(&dir, b"x").await.is_err());
+        assert!(ops.open_staged_blob(&dir, b"x").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_directory_policy_inheritance() {
+        use crate::policy::Versioning;
+
+        let mut session = create_test_session().await;
+        session.config_mut().default_policy.versioning = Versioning::Keep(2);
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let docs = VaultPath::parse("/documents").unwrap();
+        let archive = docs.join("archive").unwrap();
+        let old = archive.join("2019").unwrap();
+        for dir in [&docs, &archive, &old] {
+            ops.create_directory(dir).await.unwrap();
+        }
+        let report = old.join("report.pdf").unwrap();
+        ops.create_file(&report, b"pdf").await.unwrap();
+
+        // Only the vault default applies so far.
+        let effective = ops.effective_policy(&report).await.unwrap();
+        assert_eq!(effective.versioning, Versioning::Keep(2));
+        assert_eq!(effective.sync, SyncPolicy::Include);
+
+        ops.set_directory_policy(
+            &docs,
+            DirectoryPolicy {
+                versioning: Some(Versioning::Keep(10)),
+                compression: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        ops.set_directory_policy(
+            &old,
+            DirectoryPolicy {
+                versioning: Some(Versioning::Off),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        // The nearest ancestor wins per setting; the rest is inherited.
+        let effective = ops.effective_policy(&archive).await.unwrap();
+        assert_eq!(effective.versioning, Versioning::Keep(10));
+        assert!(effective.compression);
+        let effective = ops.effective_policy(&report).await.unwrap();
+        assert_eq!(effective.versioning, Versioning::Off);
+        assert!(effective.compression);
+        assert_eq!(
+            ops.effective_policy(&VaultPath::root())
+                .await
+                .unwrap()
+                .versioning,
+            Versioning::Keep(2)
+        );
+
+        // Clearing the overrides restores inheritance.
+        ops.set_directory_policy(&old, DirectoryPolicy::default())
+            .await
+            .unwrap();
+        assert_eq!(ops.directory_policy(&old).await.unwrap(), None);
+        assert_eq!(
+            ops.effective_policy(&report).await.unwrap().versioning,
+            Versioning::Keep(10)
+        );
+        assert!(ops
+            .set_directory_policy(&report, DirectoryPolicy::default())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_directory_policy_follows_rename() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let scratch = VaultPath::parse("/scratch").unwrap();
+        let photos = VaultPath::parse("/photos").unwrap();
+        let tmp = scratch.join("tmp").unwrap();
+        for dir in [&scratch, &photos, &tmp] {
+            ops.create_directory(dir).await.unwrap();
+        }
+        ops.set_directory_policy(
+            &scratch,
+            DirectoryPolicy {
+                sync: Some(SyncPolicy::Exclude),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            ops.effective_policy(&tmp).await.unwrap().sync,
+            SyncPolicy::Exclude
+        );
+        assert_eq!(ops.sync_excluded_paths().await.unwrap(), vec![scratch]);
+
+        // Moving out of the excluded subtree must not serve a cached result.
+        let moved = photos.join("tmp").unwrap();
+        ops.rename(&tmp, &moved).await.unwrap();
+        assert_eq!(
+            ops.effective_policy(&moved).await.unwrap().sync,
+            SyncPolicy::Include
+        );
+    }
+
+    /// Writer that compares against the expected plaintext as it goes and
+    /// rejects any single write above `limit` bytes.
+    struct BoundedSink<'a> {
+        expected: &'a [u8],
+        limit: usize,
+        position: usize,
+    }
+
+    impl std::io::Write for BoundedSink<'_> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if buf.len() > self.limit {
+                return Err(std::io::Error::other(format!(
+                    "write of {} bytes exceeds bound of {}",
+                    buf.len(),
+                    self.limit
+                )));
+            }
+            let end = self.position + buf.len();
+            assert_eq!(buf, &self.expected[self.position..end]);
+            self.position = end;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_stream_blob_to_bounded_writer() {
+        use axiomvault_crypto::stream::DEFAULT_CHUNK_SIZE;
+
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/large.bin").unwrap();
+        let content: Vec<u8> = (0..DEFAULT_CHUNK_SIZE * 5 + 123)
+            .map(|i| (i % 241) as u8)
+            .collect();
+
+        // Replace the blob with the same content in the chunked stream format.
+        ops.create_file(&path, &content).await.unwrap();
+        let encrypted_name = {
+            let tree = session.tree().read().await;
+            tree.get_node(&path)
+                .unwrap()
+                .metadata
+                .encrypted_name
+                .clone()
+        };
+        let storage_path = VaultPath::parse(DATA_DIRNAME)
+            .unwrap()
+            .join(&encrypted_name)
+            .unwrap();
+        session
+            .provider()
+            .upload(
+                &storage_path,
+                crate::blob::encrypt_streamed_blob(
+                    &session.master_key().unwrap(),
+                    &encrypted_name,
+                    &content,
+                ),
+            )
+            .await
+            .unwrap();
+
+        let mut sink = BoundedSink {
+            expected: &content,
+            limit: DEFAULT_CHUNK_SIZE,
+            position: 0,
+        };
+        let written = ops.read_file_to_writer(&path, &mut sink).await.unwrap();
+        assert_eq!(written, content.len() as u64);
+        assert_eq!(sink.position, content.len());
+
+        // A single blob of the same size is written in one piece.
+        ops.update_file(&path, &content).await.unwrap();
+        let mut sink = BoundedSink {
+            expected: &content,
+            limit: DEFAULT_CHUNK_SIZE,
+            position: 0,
+        };
+        assert!(ops.read_file_to_writer(&path, &mut sink).await.is_err());
+
+        let mut out = Vec::new();
+        ops.read_file_to_writer(&path, &mut out).await.unwrap();
+        assert_eq!(out, content);
+    }
+
     #[tokio::test]
     async fn test_delete_file() {
         let session = create_test_session().await;
@@ -393,6 +1889,42 @@ mod tests {
         assert!(is_dir);
     }
 
+    #[tokio::test]
+    async fn test_create_directory_all_creates_missing_ancestors() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/a/b/c").unwrap();
+
+        assert!(ops.create_directory(&path).await.is_err());
+        ops.create_directory_all(&path).await.unwrap();
+
+        for dir in ["/a", "/a/b", "/a/b/c"] {
+            let dir = VaultPath::parse(dir).unwrap();
+            let encrypted_name = {
+                let tree = session.tree().read().await;
+                let node = tree.get_node(&dir).unwrap();
+                assert!(!node.is_file());
+                node.metadata.encrypted_name.clone()
+            };
+            assert_ne!(encrypted_name, dir.name().unwrap());
+            assert_eq!(
+                ops.decrypt_name(&encrypted_name).unwrap(),
+                dir.name().unwrap()
+            );
+        }
+
+        // Existing directories are a no-op; a file on the way is an error.
+        ops.create_directory_all(&path).await.unwrap();
+        ops.create_file(&VaultPath::parse("/a/b/f").unwrap(), b"x")
+            .await
+            .unwrap();
+        assert!(matches!(
+            ops.create_directory_all(&VaultPath::parse("/a/b/f/g").unwrap())
+                .await,
+            Err(Error::InvalidInput(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_list_directory() {
         let session = create_test_session().await;
@@ -414,4 +1946,832 @@ mod tests {
             .unwrap();
         assert_eq!(contents.len(), 2);
     }
+
+    #[tokio::test]
+    async fn test_read_file_records_access_time_when_enabled() {
+        let mut session = create_test_session().await;
+        let path = VaultPath::parse("/read.txt").unwrap();
+        let accessed_at =
+            |tree: &crate::VaultTree| tree.get_node(&path).unwrap().metadata.accessed_at;
+
+        // Off by default: reads leave the tree alone.
+        {
+            let ops = VaultOperations::new(&session).unwrap();
+            ops.create_file(&path, b"content").await.unwrap();
+            ops.read_file(&path).await.unwrap();
+        }
+        assert_eq!(accessed_at(&*session.tree().read().await), None);
+        assert!(!session.flush_access_times().await.unwrap());
+
+        session.config_mut().track_access_time = true;
+        let before = Utc::now();
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.read_file(&path).await.unwrap();
+        let read_at = accessed_at(&*session.tree().read().await).unwrap();
+        assert!(read_at >= before);
+
+        // Batched in memory until flushed, then saved once.
+        let master_key = session.master_key().unwrap();
+        let stored = VaultSession::load_and_decrypt_tree(&session.provider(), &master_key)
+            .await
+            .unwrap();
+        assert_eq!(accessed_at(&stored), None);
+        assert!(session.flush_access_times().await.unwrap());
+        assert!(!session.flush_access_times().await.unwrap());
+        let stored = VaultSession::load_and_decrypt_tree(&session.provider(), &master_key)
+            .await
+            .unwrap();
+        assert_eq!(accessed_at(&stored), Some(read_at));
+    }
+
+    #[tokio::test]
+    async fn test_list_stale_uses_last_read_or_write() {
+        let mut session = create_test_session().await;
+        session.config_mut().track_access_time = true;
+        let ops = VaultOperations::new(&session).unwrap();
+        for name in ["/old.txt", "/read.txt", "/new.txt"] {
+            ops.create_file(&VaultPath::parse(name).unwrap(), b"x")
+                .await
+                .unwrap();
+        }
+        let now = Utc::now();
+        {
+            let mut tree = session.tree().write().await;
+            for name in ["/old.txt", "/read.txt"] {
+                let node = tree.get_node_mut(&VaultPath::parse(name).unwrap()).unwrap();
+                node.metadata.modified_at = now - chrono::Duration::days(30);
+            }
+        }
+        let cutoff = now - chrono::Duration::days(7);
+        assert_eq!(
+            ops.list_stale(cutoff).await,
+            vec![
+                VaultPath::parse("/old.txt").unwrap(),
+                VaultPath::parse("/read.txt").unwrap()
+            ]
+        );
+
+        ops.read_file(&VaultPath::parse("/read.txt").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            ops.list_stale(cutoff).await,
+            vec![VaultPath::parse("/old.txt").unwrap()]
+        );
+        assert!(ops
+            .list_stale(now - chrono::Duration::days(60))
+            .await
+            .is_empty());
+    }
+
+    fn set_name_limit(session: &mut VaultSession, on_overflow: LongNamePolicy) {
+        session.config_mut().name_limit = Some(crate::NameLimit {
+            max_len: 255,
+            on_overflow,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_long_name_rejected_over_limit() {
+        let mut session = create_test_session().await;
+        set_name_limit(&mut session, LongNamePolicy::Reject);
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let long = VaultPath::root().join(&"n".repeat(200)).unwrap();
+        let err = ops.create_file(&long, b"data").await.unwrap_err();
+        assert!(matches!(err, Error::InvalidInput(ref msg) if msg.contains("limit of 255")));
+        assert!(!ops.exists(&long).await);
+        assert!(crate::layout::list_objects(session.provider().as_ref())
+            .await
+            .unwrap()
+            .is_empty());
+
+        let short = VaultPath::parse("/short.txt").unwrap();
+        ops.create_file(&short, b"data").await.unwrap();
+        let tree = session.tree().read().await;
+        assert_eq!(
+            tree.get_node(&short).unwrap().metadata.long_encrypted_name,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_long_name_hashed_over_limit() {
+        let mut session = create_test_session().await;
+        set_name_limit(&mut session, LongNamePolicy::Hash);
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let name = "n".repeat(200);
+        let path = VaultPath::root().join(&name).unwrap();
+        ops.create_file(&path, b"first").await.unwrap();
+
+        let (key, long_name) = {
+            let tree = session.tree().read().await;
+            let metadata = &tree.get_node(&path).unwrap().metadata;
+            (
+                metadata.encrypted_name.clone(),
+                metadata.long_encrypted_name.clone().unwrap(),
+            )
+        };
+        assert!(key.len() <= 255);
+        assert!(long_name.len() > 255);
+        let objects = crate::layout::list_objects(session.provider().as_ref())
+            .await
+            .unwrap();
+        assert_eq!(objects, vec![key]);
+
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"first");
+        ops.update_file(&path, b"second").await.unwrap();
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"second");
+        let listed = ops.list_directory(&VaultPath::root()).await.unwrap();
+        assert_eq!(listed, vec![(name, false, Some(6))]);
+
+        ops.delete_file(&path).await.unwrap();
+        assert!(crate::layout::list_objects(session.provider().as_ref())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_metadata_bulk() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let dir = VaultPath::parse("/dir").unwrap();
+        let file = dir.join("a.txt").unwrap();
+        ops.create_directory(&dir).await.unwrap();
+        ops.create_file(&file, b"hello").await.unwrap();
+
+        let missing = VaultPath::parse("/missing.txt").unwrap();
+        let results = ops.read_metadata_bulk(&[file, missing, dir]).await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &("a.txt".to_string(), false, Some(5))
+        );
+        assert!(matches!(results[1], Err(Error::NotFound(_))));
+        assert_eq!(results[2].as_ref().unwrap().0, "dir");
+        assert!(results[2].as_ref().unwrap().1);
+    }
+
+    #[tokio::test]
+    async fn test_exists_detailed_matches_exists_and_metadata() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let dir = VaultPath::parse("/dir").unwrap();
+        let file = dir.join("a.txt").unwrap();
+        ops.create_directory(&dir).await.unwrap();
+        ops.create_file(&file, b"hello").await.unwrap();
+        let missing = VaultPath::parse("/missing.txt").unwrap();
+
+        for path in [&file, &dir] {
+            let entry = ops.exists_detailed(path).await;
+            assert!(ops.exists(path).await);
+            assert_eq!(entry, Some(ops.metadata(path).await.unwrap()));
+        }
+        assert_eq!(
+            ops.exists_detailed(&file).await,
+            Some(("a.txt".to_string(), false, Some(5)))
+        );
+        assert!(ops.exists_detailed(&dir).await.unwrap().1);
+
+        assert!(!ops.exists(&missing).await);
+        assert!(ops.metadata(&missing).await.is_err());
+        assert_eq!(ops.exists_detailed(&missing).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_list_directory_page() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let dir = VaultPath::parse("/dir").unwrap();
+        ops.create_directory(&dir).await.unwrap();
+        for i in 0..25 {
+            ops.create_file(&dir.join(&format!("f{:02}", i)).unwrap(), b"x")
+                .await
+                .unwrap();
+        }
+
+        let mut names = Vec::new();
+        let mut flags = Vec::new();
+        for page in 0..3 {
+            let (entries, has_more) = ops.list_directory_page(&dir, page * 10, 10).await.unwrap();
+            names.extend(entries.into_iter().map(|(name, _, _)| name));
+            flags.push(has_more);
+        }
+
+        assert_eq!(flags, [true, true, false]);
+        let expected: Vec<_> = (0..25).map(|i| format!("f{:02}", i)).collect();
+        assert_eq!(names, expected);
+
+        let (past_end, has_more) = ops.list_directory_page(&dir, 30, 10).await.unwrap();
+        assert!(past_end.is_empty());
+        assert!(!has_more);
+    }
+
+    #[tokio::test]
+    async fn test_rename_file_keeps_content() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let from = VaultPath::parse("/old.txt").unwrap();
+        let to = VaultPath::parse("/dir/new.txt").unwrap();
+        ops.create_directory(&VaultPath::parse("/dir").unwrap())
+            .await
+            .unwrap();
+        ops.create_file(&from, b"content").await.unwrap();
+
+        ops.rename(&from, &to).await.unwrap();
+
+        assert!(!ops.exists(&from).await);
+        assert_eq!(ops.read_file(&to).await.unwrap(), b"content");
+    }
+
+    #[tokio::test]
+    async fn test_transfer_between_vaults() {
+        let personal = create_test_session().await;
+        let shared = create_session("shared", b"other-password", KdfParams::interactive()).await;
+        let src = VaultOperations::new(&personal).unwrap();
+        let dst = VaultOperations::new(&shared).unwrap();
+
+        let from = VaultPath::parse("/report.txt").unwrap();
+        let copied = VaultPath::parse("/copy.txt").unwrap();
+        let moved = VaultPath::parse("/docs/report.txt").unwrap();
+        let content: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        src.create_file(&from, &content).await.unwrap();
+        dst.create_directory(&VaultPath::parse("/docs").unwrap())
+            .await
+            .unwrap();
+        let created_at = chrono::Utc::now() - chrono::Duration::days(30);
+        let modified_at = chrono::Utc::now() - chrono::Duration::days(2);
+        {
+            let mut tree = personal.tree().write().await;
+            let node = tree.get_node_mut(&from).unwrap();
+            node.metadata.created_at = created_at;
+            node.metadata.modified_at = modified_at;
+        }
+
+        VaultOperations::transfer(&src, &from, &dst, &copied, false)
+            .await
+            .unwrap();
+        assert_eq!(src.read_file(&from).await.unwrap(), content);
+        assert_eq!(dst.read_file(&copied).await.unwrap(), content);
+
+        VaultOperations::transfer(&src, &from, &dst, &moved, true)
+            .await
+            .unwrap();
+        assert!(!src.exists(&from).await);
+        assert_eq!(dst.read_file(&moved).await.unwrap(), content);
+
+        let tree = shared.tree().read().await;
+        for path in [&copied, &moved] {
+            let metadata = &tree.get_node(path).unwrap().metadata;
+            assert_eq!(metadata.created_at, created_at);
+            assert_eq!(metadata.modified_at, modified_at);
+        }
+        drop(tree);
+
+        // An existing destination is not overwritten.
+        assert!(matches!(
+            VaultOperations::transfer(&dst, &moved, &dst, &copied, false).await,
+            Err(Error::AlreadyExists(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_saves_destination_tree_once() {
+        let personal = create_test_session().await;
+        let password = b"other-password";
+        let creation = VaultConfig::new(
+            VaultId::new("shared").unwrap(),
+            password,
+            "counting",
+            serde_json::Value::Null,
+            KdfParams::interactive(),
+        )
+        .unwrap();
+        let provider = Arc::new(CountingProvider::new());
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let shared = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(password),
+            provider.clone(),
+            crate::tree::VaultTree::new(),
+        )
+        .unwrap();
+        let src = VaultOperations::new(&personal).unwrap();
+        let dst = VaultOperations::new(&shared).unwrap();
+
+        let from = VaultPath::parse("/report.txt").unwrap();
+        src.create_file(&from, b"report").await.unwrap();
+        VaultOperations::transfer(&src, &from, &dst, &from, false)
+            .await
+            .unwrap();
+
+        let tree_path = VaultPath::parse(META_DIRNAME)
+            .unwrap()
+            .join(TREE_FILENAME)
+            .unwrap();
+        assert_eq!(provider.uploads_of(&tree_path), 1);
+        assert_eq!(dst.read_file(&from).await.unwrap(), b"report");
+    }
+
+    /// Span captured by [`CaptureLayer`].
+    #[derive(Debug, Default, Clone)]
+    struct CapturedSpan {
+        name: String,
+        fields: Fields,
+        elapsed: Option<std::time::Duration>,
+    }
+
+    impl CapturedSpan {
+        fn field(&self, name: &str) -> Option<&str> {
+            self.fields
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        }
+    }
+
+    type Fields = Vec<(String, String)>;
+
+    struct FieldVisitor<'a>(&'a mut Fields);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    /// Test layer that records every span's fields and its duration on close,
+    /// plus the fields of every event.
+    #[derive(Default, Clone)]
+    struct CaptureLayer {
+        closed: Arc<std::sync::Mutex<Vec<CapturedSpan>>>,
+        events: Arc<std::sync::Mutex<Vec<Fields>>>,
+    }
+
+    impl<S> tracing_subscriber::Layer<S> for CaptureLayer
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut span = CapturedSpan {
+                name: attrs.metadata().name().to_string(),
+                ..CapturedSpan::default()
+            };
+            attrs.record(&mut FieldVisitor(&mut span.fields));
+            if let Some(data) = ctx.span(id) {
+                data.extensions_mut()
+                    .insert((span, std::time::Instant::now()));
+            }
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if let Some(data) = ctx.span(id) {
+                if let Some((span, _)) = data
+                    .extensions_mut()
+                    .get_mut::<(CapturedSpan, std::time::Instant)>()
+                {
+                    values.record(&mut FieldVisitor(&mut span.fields));
+                }
+            }
+        }
+
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = Vec::new();
+            event.record(&mut FieldVisitor(&mut fields));
+            self.events.lock().unwrap().push(fields);
+        }
+
+        fn on_close(&self, id: tracing::span::Id, ctx: tracing_subscriber::layer::Context<'_, S>) {
+            if let Some(data) = ctx.span(&id) {
+                if let Some((mut span, opened)) = data
+                    .extensions_mut()
+                    .remove::<(CapturedSpan, std::time::Instant)>()
+                {
+                    span.elapsed = Some(opened.elapsed());
+                    self.closed.lock().unwrap().push(span);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_operation_spans_are_timed_and_redacted() {
+        use axiomvault_common::telemetry::set_redact_paths;
+        use axiomvault_storage::InstrumentedProvider;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let layer = CaptureLayer::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+        set_redact_paths(true);
+
+        let id = VaultId::new("span-test").unwrap();
+        let creation = VaultConfig::new(
+            id,
+            b"test-password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::interactive(),
+        )
+        .unwrap();
+        let provider = InstrumentedProvider::wrap(Arc::new(MemoryProvider::new()));
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(b"test-password"),
+            provider,
+            crate::tree::VaultTree::new(),
+        )
+        .unwrap();
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let dir = VaultPath::parse("/quarterly-secrets").unwrap();
+        let file = dir.join("salary-report.txt").unwrap();
+        ops.create_directory(&dir).await.unwrap();
+        ops.create_file(&file, b"top secret").await.unwrap();
+        ops.read_file(&file).await.unwrap();
+        ops.rename(&file, &dir.join("renamed-report.txt").unwrap())
+            .await
+            .unwrap();
+        set_redact_paths(false);
+
+        let closed = layer.closed.lock().unwrap().clone();
+        let ops_spans: Vec<&CapturedSpan> =
+            closed.iter().filter(|s| s.name == "vault_op").collect();
+        assert_eq!(ops_spans.len(), 4);
+        for span in &ops_spans {
+            assert!(span.elapsed.is_some());
+            assert_eq!(span.field("vault_id"), Some("span-test"));
+            assert!(span.field("path").unwrap().starts_with("<redacted:"));
+        }
+        let read = ops_spans
+            .iter()
+            .find(|s| s.field("op") == Some("\"read_file\""))
+            .unwrap();
+        assert_eq!(read.field("bytes"), Some("10"));
+        assert!(closed.iter().any(|s| s.name == "provider_call"));
+
+        let events = layer.events.lock().unwrap().clone();
+        let recorded = closed
+            .iter()
+            .flat_map(|s| s.fields.iter())
+            .chain(events.iter().flatten());
+        for (_, value) in recorded {
+            for secret in [
+                "quarterly-secrets",
+                "salary-report",
+                "renamed-report",
+                "top secret",
+            ] {
+                assert!(!value.contains(secret), "{} leaked into {}", secret, value);
+            }
+        }
+    }
+
+    async fn flaky_session() -> (VaultSession, Arc<CountingProvider>) {
+        flaky_session_with(CountingProvider::new()).await
+    }
+
+    async fn flaky_session_with(
+        provider: CountingProvider,
+    ) -> (VaultSession, Arc<CountingProvider>) {
+        let password = b"test-password";
+        let creation = VaultConfig::new(
+            VaultId::new("offline").unwrap(),
+            password,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let provider = Arc::new(provider);
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(password),
+            provider.clone(),
+            crate::tree::VaultTree::new(),
+        )
+        .unwrap();
+        (session, provider)
+    }
+
+    #[tokio::test]
+    async fn test_read_file_serves_cached_blob_while_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let (session, provider) = flaky_session().await;
+        session.set_blob_cache(Some(crate::BlobCache::open(dir.path()).unwrap()));
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let cached = VaultPath::parse("/cached.txt").unwrap();
+        let uncached = VaultPath::parse("/uncached.txt").unwrap();
+        ops.create_file(&cached, b"read before").await.unwrap();
+        ops.create_file(&uncached, b"never read").await.unwrap();
+        ops.read_file(&cached).await.unwrap();
+
+        provider.set_offline(true);
+        assert_eq!(ops.read_file(&cached).await.unwrap(), b"read before");
+        let mut out = Vec::new();
+        ops.read_file_to_writer(&cached, &mut out).await.unwrap();
+        assert_eq!(out, b"read before");
+        assert!(matches!(
+            ops.read_file(&uncached).await,
+            Err(Error::Network(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cached_blob_is_not_served_after_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let (session, provider) = flaky_session().await;
+        session.set_blob_cache(Some(crate::BlobCache::open(dir.path()).unwrap()));
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let path = VaultPath::parse("/notes.txt").unwrap();
+        ops.create_file(&path, b"v1").await.unwrap();
+        ops.read_file(&path).await.unwrap();
+        ops.update_file(&path, b"v2").await.unwrap();
+
+        // The cached copy holds v1; the tree's etag now names v2.
+        provider.set_offline(true);
+        assert!(matches!(ops.read_file(&path).await, Err(Error::Network(_))));
+
+        provider.set_offline(false);
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"v2");
+        provider.set_offline(true);
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"v2");
+    }
+
+    #[tokio::test]
+    async fn test_offline_read_without_cache_fails() {
+        let (session, provider) = flaky_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let path = VaultPath::parse("/a.txt").unwrap();
+        ops.create_file(&path, b"a").await.unwrap();
+        ops.read_file(&path).await.unwrap();
+
+        provider.set_offline(true);
+        assert!(matches!(ops.read_file(&path).await, Err(Error::Network(_))));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_streaming_reads_stay_within_budget() {
+        use axiomvault_crypto::stream::DEFAULT_CHUNK_SIZE;
+
+        let (session, provider) = flaky_session_with(
+            CountingProvider::new().with_download_delay(std::time::Duration::from_millis(20)),
+        )
+        .await;
+        let ops = VaultOperations::new(&session).unwrap();
+
+        // Each read reserves its size plus one chunk; the budget fits two.
+        let file_size = DEFAULT_CHUNK_SIZE;
+        let budget = Arc::new(crate::StreamBudget::new(4 * DEFAULT_CHUNK_SIZE));
+        let mut paths = Vec::new();
+        for i in 0..10u8 {
+            let path = VaultPath::parse(&format!("/f{}.bin", i)).unwrap();
+            ops.create_file(&path, &vec![i; file_size]).await.unwrap();
+            paths.push(path);
+        }
+        session.set_stream_budget(Some(budget.clone()));
+
+        let reads = paths.iter().map(|path| {
+            let ops = &ops;
+            async move {
+                let mut out = Vec::new();
+                ops.read_file_to_writer(path, &mut out).await.unwrap();
+                out
+            }
+        });
+        let contents = futures::future::join_all(reads).await;
+
+        for (i, content) in contents.iter().enumerate() {
+            assert_eq!(content, &vec![i as u8; file_size]);
+        }
+        assert_eq!(provider.peak_downloads(), 2);
+        assert!(budget.peak() <= budget.limit());
+        assert_eq!(budget.peak(), 4 * DEFAULT_CHUNK_SIZE);
+        assert_eq!(budget.outstanding(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_permissions_merges_fields_and_survives_update() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/owned.txt").unwrap();
+        ops.create_file(&path, b"x").await.unwrap();
+
+        assert_eq!(
+            ops.permissions(&path).await.unwrap(),
+            NodePermissions::default()
+        );
+
+        ops.set_permissions(
+            &path,
+            NodePermissions {
+                mode: Some(0o100640),
+                uid: Some(1000),
+                gid: Some(100),
+            },
+        )
+        .await
+        .unwrap();
+        ops.set_permissions(
+            &path,
+            NodePermissions {
+                gid: Some(200),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        ops.update_file(&path, b"changed").await.unwrap();
+
+        assert_eq!(
+            ops.permissions(&path).await.unwrap(),
+            NodePermissions {
+                mode: Some(0o640),
+                uid: Some(1000),
+                gid: Some(200),
+            }
+        );
+    }
+
+    /// Objects in the data directory, sorted.
+    async fn data_objects(session: &VaultSession) -> Vec<String> {
+        let mut names: Vec<String> = session
+            .provider()
+            .list(&VaultPath::parse(DATA_DIRNAME).unwrap())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[tokio::test]
+    async fn test_large_file_is_split_into_parts() {
+        let session = create_test_session().await;
+        session.set_part_size(Some(4096));
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/big.bin").unwrap();
+        let content: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+
+        ops.create_file(&path, &content).await.unwrap();
+
+        let (encrypted_name, parts) = {
+            let tree = session.tree().read().await;
+            let metadata = &tree.get_node(&path).unwrap().metadata;
+            (metadata.encrypted_name.clone(), metadata.parts.clone())
+        };
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.size <= 4096));
+        let expected: Vec<String> = (0..parts.len())
+            .map(|i| parts::part_name(&encrypted_name, i))
+            .collect();
+        assert_eq!(data_objects(&session).await, expected);
+
+        assert_eq!(ops.read_file(&path).await.unwrap(), content);
+        let mut streamed = Vec::new();
+        ops.read_file_to_writer(&path, &mut streamed).await.unwrap();
+        assert_eq!(streamed, content);
+
+        // Another session reads the layout from the tree, whatever its own
+        // part size.
+        session.set_part_size(None);
+        assert_eq!(ops.read_file(&path).await.unwrap(), content);
+
+        ops.delete_file(&path).await.unwrap();
+        assert!(data_objects(&session).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_switches_between_split_and_single_blob() {
+        let session = create_test_session().await;
+        session.set_part_size(Some(1024));
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/grow.bin").unwrap();
+
+        ops.create_file(&path, b"small").await.unwrap();
+        let single = data_objects(&session).await;
+        assert_eq!(single.len(), 1);
+
+        ops.update_file(&path, &[7u8; 5000]).await.unwrap();
+        let split = data_objects(&session).await;
+        assert!(split.len() > 1);
+        assert!(split.iter().all(|name| name.contains(".part")));
+        assert_eq!(ops.read_file(&path).await.unwrap(), vec![7u8; 5000]);
+
+        ops.update_file(&path, &[8u8; 2000]).await.unwrap();
+        let fewer = data_objects(&session).await;
+        assert!(fewer.len() < split.len());
+        assert_eq!(ops.read_file(&path).await.unwrap(), vec![8u8; 2000]);
+
+        ops.update_file(&path, b"small again").await.unwrap();
+        assert_eq!(data_objects(&session).await, single);
+        assert_eq!(ops.read_file(&path).await.unwrap(), b"small again");
+    }
+
+    #[tokio::test]
+    async fn test_write_refuses_suite_readers_do_not_support() {
+        let mut session = create_test_session().await;
+        session.config_mut().cipher_suite = CipherSuite::Aes256GcmSiv;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/refused.txt").unwrap();
+
+        assert!(matches!(
+            ops.create_file(&path, b"content").await,
+            Err(Error::Unsupported(_))
+        ));
+        assert!(!ops.exists(&path).await);
+    }
+
+    #[cfg(feature = "aes-gcm-siv")]
+    #[tokio::test]
+    async fn test_mixed_suites_in_one_vault() {
+        use crate::blob::{BlobCipher, BlobHeader};
+
+        let mut session = create_test_session().await;
+        let old = VaultPath::parse("/old.txt").unwrap();
+        let new = VaultPath::parse("/new.txt").unwrap();
+        VaultOperations::new(&session)
+            .unwrap()
+            .create_file(&old, b"sealed with xchacha")
+            .await
+            .unwrap();
+
+        let master_key = session.master_key().unwrap();
+        let config = session.config_mut();
+        let suite = CipherSuite::Aes256GcmSiv;
+        assert!(config.set_cipher_suite(suite, &master_key).is_err());
+        config.allow_reader_suite(suite, &master_key).unwrap();
+        config.set_cipher_suite(suite, &master_key).unwrap();
+        config.verify_mac(&master_key).unwrap();
+
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_file(&new, b"sealed with aes").await.unwrap();
+        assert_eq!(ops.read_file(&old).await.unwrap(), b"sealed with xchacha");
+        assert_eq!(ops.read_file(&new).await.unwrap(), b"sealed with aes");
+
+        for (path, expected) in [
+            (&old, CipherSuite::XChaCha20Poly1305),
+            (&new, CipherSuite::Aes256GcmSiv),
+        ] {
+            let name = session
+                .tree()
+                .read()
+                .await
+                .get_node(path)
+                .unwrap()
+                .metadata
+                .encrypted_name
+                .clone();
+            let blob = session
+                .provider()
+                .download(&VaultPath::parse(DATA_DIRNAME).unwrap().join(&name).unwrap())
+                .await
+                .unwrap();
+            let (header, _) = BlobHeader::parse(&blob).unwrap();
+            assert_eq!(header.cipher, BlobCipher::Suite(expected));
+        }
+    }
 }
@@ -0,0 +1,305 @@
+//! Splitting of large blobs across several provider objects.
+//!
+//! Some providers cap the size of a single object. With a part size set
+//! on the session ([`VaultSession::set_part_size`](crate::VaultSession::set_part_size)),
+//! a blob larger than it is stored as `<name>.part0`, `<name>.part1`, …
+//! in the data directory (in the blob's shard, if sharded), and the file's tree node lists the parts. The
+//! ciphertext is cut at fixed offsets: it is indistinguishable from random
+//! data, so content-defined cut points would not line up between versions
+//! anyway. Each part is transferred with its own retries.
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::config::DataLayout;
+use crate::layout;
+use crate::verify::{ChunkManifest, MANIFEST_CHUNK_SIZE};
+use axiomvault_common::{Error, Result};
+use axiomvault_storage::StorageProvider;
+
+/// Attempts per part before a network failure is reported.
+const PART_ATTEMPTS: usize = 3;
+
+/// One provider object holding a slice of a file's ciphertext.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobPart {
+    /// Object name in the data directory.
+    pub name: String,
+    /// Bytes of ciphertext in this part.
+    pub size: u64,
+}
+
+/// Name of part `index` of the blob stored under `encrypted_name`.
+///
+/// Encrypted names are unpadded URL-safe base64, so the `.` cannot clash
+/// with another file's name.
+pub fn part_name(encrypted_name: &str, index: usize) -> String {
+    format!("{}.part{}", encrypted_name, index)
+}
+
+/// Provider objects holding a blob: its parts, or the single object named
+/// after it when it is not split.
+pub fn object_names(encrypted_name: &str, parts: &[BlobPart]) -> Vec<String> {
+    if parts.is_empty() {
+        vec![encrypted_name.to_string()]
+    } else {
+        parts.iter().map(|part| part.name.clone()).collect()
+    }
+}
+
+/// Parts [`upload`] writes for a blob of `blob_len` bytes: empty when it
+/// fits in one object.
+pub(crate) fn plan(encrypted_name: &str, blob_len: usize, part_size: Option<u64>) -> Vec<BlobPart> {
+    let part_size = match part_size {
+        Some(size) if (blob_len as u64) > size => size,
+        _ => return Vec::new(),
+    };
+    let count = (blob_len as u64).div_ceil(part_size) as usize;
+    (0..count)
+        .map(|index| BlobPart {
+            name: part_name(encrypted_name, index),
+            size: part_size.min(blob_len as u64 - index as u64 * part_size),
+        })
+        .collect()
+}
+
+/// A blob as [`upload`] stored it.
+pub(crate) struct Uploaded {
+    /// Parts written, empty for a single object.
+    pub(crate) parts: Vec<BlobPart>,
+    /// Digests of the ciphertext, for the file's tree node.
+    pub(crate) manifest: ChunkManifest,
+}
+
+/// Store `blob` under `encrypted_name`, split into parts of `part_size`
+/// bytes if it is larger than that.
+///
+/// Objects of an earlier version of the blob are left alone; see
+/// [`remove_stale`].
+pub(crate) async fn upload(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    encrypted_name: &str,
+    blob: Vec<u8>,
+    part_size: Option<u64>,
+) -> Result<Uploaded> {
+    let manifest = ChunkManifest::compute(&blob, MANIFEST_CHUNK_SIZE);
+    let parts = plan(encrypted_name, blob.len(), part_size);
+    if parts.is_empty() {
+        let metadata = layout::upload(provider, layout, encrypted_name, blob).await?;
+        return Ok(Uploaded {
+            parts,
+            manifest: manifest.with_object_hashes(vec![metadata.content_hash]),
+        });
+    }
+
+    let mut offset = 0;
+    let mut hashes = Vec::with_capacity(parts.len());
+    for part in &parts {
+        let chunk = &blob[offset..offset + part.size as usize];
+        let metadata =
+            with_retries(|| layout::upload(provider, layout, &part.name, chunk.to_vec())).await?;
+        hashes.push(metadata.content_hash);
+        offset += part.size as usize;
+    }
+    Ok(Uploaded {
+        parts,
+        manifest: manifest.with_object_hashes(hashes),
+    })
+}
+
+/// Fetch the blob stored under `encrypted_name` as `parts`.
+///
+/// # Errors
+/// - `Integrity` if a part does not have the recorded size
+/// - Storage failure, including `Network` once a part's retries are used up
+pub(crate) async fn download(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    encrypted_name: &str,
+    parts: &[BlobPart],
+) -> Result<Vec<u8>> {
+    if parts.is_empty() {
+        return layout::download(provider, layout, encrypted_name).await;
+    }
+
+    let total = parts.iter().map(|part| part.size).sum::<u64>();
+    let mut blob = Vec::with_capacity(total as usize);
+    for part in parts {
+        let bytes = with_retries(|| layout::download(provider, layout, &part.name)).await?;
+        if bytes.len() as u64 != part.size {
+            return Err(Error::Integrity(format!(
+                "blob part has {} bytes, expected {}",
+                bytes.len(),
+                part.size
+            )));
+        }
+        blob.extend_from_slice(&bytes);
+    }
+    Ok(blob)
+}
+
+/// Fetch `len` bytes at `offset` of the blob stored under `encrypted_name`
+/// as `parts`, from whichever parts hold them.
+pub(crate) async fn download_range(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    encrypted_name: &str,
+    parts: &[BlobPart],
+    offset: u64,
+    len: u64,
+) -> Result<Vec<u8>> {
+    if parts.is_empty() {
+        return layout::download_range(provider, layout, encrypted_name, offset, len).await;
+    }
+
+    let end = offset.saturating_add(len);
+    let mut range = Vec::new();
+    let mut part_start = 0u64;
+    for part in parts {
+        let part_end = part_start + part.size;
+        if part_end > offset && part_start < end {
+            let from = offset.saturating_sub(part_start);
+            let to = end.min(part_end) - part_start;
+            let bytes = with_retries(|| {
+                layout::download_range(provider, layout, &part.name, from, to - from)
+            })
+            .await?;
+            range.extend_from_slice(&bytes);
+        }
+        part_start = part_end;
+    }
+    Ok(range)
+}
+
+/// Delete every object of the blob stored under `encrypted_name` as `parts`.
+pub(crate) async fn delete(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    encrypted_name: &str,
+    parts: &[BlobPart],
+) -> Result<()> {
+    for name in object_names(encrypted_name, parts) {
+        layout::delete(provider, layout, &name).await?;
+    }
+    Ok(())
+}
+
+/// Delete objects of an old version of a blob that the new version no
+/// longer uses. Failures only leave orphans, so they are logged.
+pub(crate) async fn remove_stale(
+    provider: &dyn StorageProvider,
+    layout: DataLayout,
+    old: (&str, &[BlobPart]),
+    new: (&str, &[BlobPart]),
+) {
+    let keep = object_names(new.0, new.1);
+    for name in object_names(old.0, old.1) {
+        if keep.contains(&name) {
+            continue;
+        }
+        if let Err(e) = layout::delete(provider, layout, &name).await {
+            warn!("Failed to remove superseded blob object: {}", e);
+        }
+    }
+}
+
+/// Run a part transfer, retrying network failures.
+async fn with_retries<T, F, Fut>(mut transfer: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match transfer().await {
+            Err(Error::Network(reason)) if attempt < PART_ATTEMPTS => {
+                warn!(attempt, "Blob part transfer failed, retrying: {}", reason);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DATA_DIRNAME;
+    use axiomvault_common::VaultPath;
+    use axiomvault_storage::MemoryProvider;
+
+    #[tokio::test]
+    async fn test_small_blob_stays_single_object() {
+        let provider = MemoryProvider::new();
+        provider
+            .create_dir(&VaultPath::parse(DATA_DIRNAME).unwrap())
+            .await
+            .unwrap();
+
+        let parts = upload(&provider, DataLayout::Flat, "blob", vec![1; 10], Some(10))
+            .await
+            .unwrap()
+            .parts;
+        assert!(parts.is_empty());
+        assert_eq!(object_names("blob", &parts), vec!["blob".to_string()]);
+        assert_eq!(
+            download(&provider, DataLayout::Flat, "blob", &parts)
+                .await
+                .unwrap(),
+            vec![1; 10]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_blob_round_trips_and_detects_truncation() {
+        let provider = MemoryProvider::new();
+        let data_dir = VaultPath::parse(DATA_DIRNAME).unwrap();
+        provider.create_dir(&data_dir).await.unwrap();
+        let blob: Vec<u8> = (0..25u8).collect();
+
+        // Parts land in their blob's shard, created on first use.
+        let parts = upload(
+            &provider,
+            DataLayout::Sharded,
+            "blob",
+            blob.clone(),
+            Some(10),
+        )
+        .await
+        .unwrap()
+        .parts;
+        let sizes: Vec<u64> = parts.iter().map(|p| p.size).collect();
+        assert_eq!(sizes, vec![10, 10, 5]);
+        assert_eq!(parts[2].name, "blob.part2");
+        assert_eq!(
+            download(&provider, DataLayout::Sharded, "blob", &parts)
+                .await
+                .unwrap(),
+            blob
+        );
+        for (offset, len) in [(0, 25), (8, 4), (10, 10), (19, 3), (22, 10), (30, 5)] {
+            let end = (offset + len).min(25);
+            assert_eq!(
+                download_range(&provider, DataLayout::Sharded, "blob", &parts, offset, len)
+                    .await
+                    .unwrap(),
+                blob[offset.min(25) as usize..end as usize],
+                "range {}+{}",
+                offset,
+                len
+            );
+        }
+
+        let part1 = layout::blob_path(DataLayout::Sharded, "blob.part1").unwrap();
+        assert_eq!(
+            part1.parent(),
+            Some(data_dir.join(&layout::shard_of("blob")).unwrap())
+        );
+        provider.upload(&part1, vec![0; 3]).await.unwrap();
+        assert!(matches!(
+            download(&provider, DataLayout::Sharded, "blob", &parts).await,
+            Err(Error::Integrity(_))
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+//! Per-directory policies.
+//!
+//! A directory may carry a [`DirectoryPolicy`] that overrides individual
+//! settings for itself and everything below it. The [`EffectivePolicy`] of
+//! a path starts from the vault-wide defaults in
+//! [`VaultConfig`](crate::config::VaultConfig) and applies the overrides of
+//! each ancestor from the root down, so the nearest ancestor wins.
+
+use serde::{Deserialize, Serialize};
+
+use axiomvault_common::{Error, Result};
+
+/// How many earlier versions of a file to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Versioning {
+    /// Keep only the current content.
+    #[default]
+    Off,
+    /// Keep up to this many earlier versions.
+    Keep(u32),
+}
+
+impl std::fmt::Display for Versioning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Off => write!(f, "off"),
+            Self::Keep(n) => write!(f, "keep {}", n),
+        }
+    }
+}
+
+impl std::str::FromStr for Versioning {
+    type Err = Error;
+
+    /// Parse `off` or a number of versions to keep (`0` means `off`).
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("off") {
+            return Ok(Self::Off);
+        }
+        match s.parse::<u32>() {
+            Ok(0) => Ok(Self::Off),
+            Ok(n) => Ok(Self::Keep(n)),
+            Err(_) => Err(Error::InvalidInput(format!("Invalid versioning: {}", s))),
+        }
+    }
+}
+
+/// Whether a subtree takes part in sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPolicy {
+    /// Synced like any other path.
+    #[default]
+    Include,
+    /// Treated like a configured sync exclude.
+    Exclude,
+}
+
+impl std::fmt::Display for SyncPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Include => write!(f, "include"),
+            Self::Exclude => write!(f, "exclude"),
+        }
+    }
+}
+
+/// Overrides stored on one directory. Unset fields are inherited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryPolicy {
+    /// Version retention for files in this subtree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub versioning: Option<Versioning>,
+    /// Whether file content is compressed before encryption.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<bool>,
+    /// Whether this subtree is synced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync: Option<SyncPolicy>,
+    /// Whether identical content is stored once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dedup: Option<bool>,
+}
+
+impl DirectoryPolicy {
+    /// Whether no setting is overridden.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Fully resolved policy that applies to a path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectivePolicy {
+    /// Version retention.
+    pub versioning: Versioning,
+    /// Compression before encryption.
+    pub compression: bool,
+    /// Sync participation.
+    pub sync: SyncPolicy,
+    /// Content deduplication.
+    pub dedup: bool,
+}
+
+impl EffectivePolicy {
+    /// This policy with the overrides of `policy` applied on top.
+    pub fn apply(self, policy: &DirectoryPolicy) -> Self {
+        Self {
+            versioning: policy.versioning.unwrap_or(self.versioning),
+            compression: policy.compression.unwrap_or(self.compression),
+            sync: policy.sync.unwrap_or(self.sync),
+            dedup: policy.dedup.unwrap_or(self.dedup),
+        }
+    }
+
+    /// Whether this is the built-in default.
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_overrides_only_set_fields() {
+        let base = EffectivePolicy {
+            versioning: Versioning::Keep(3),
+            compression: true,
+            sync: SyncPolicy::Include,
+            dedup: false,
+        };
+        let policy = DirectoryPolicy {
+            sync: Some(SyncPolicy::Exclude),
+            dedup: Some(true),
+            ..Default::default()
+        };
+
+        let effective = base.apply(&policy);
+        assert_eq!(effective.versioning, Versioning::Keep(3));
+        assert!(effective.compression);
+        assert_eq!(effective.sync, SyncPolicy::Exclude);
+        assert!(effective.dedup);
+        assert_eq!(base.apply(&DirectoryPolicy::default()), base);
+    }
+
+    #[test]
+    fn test_parse_versioning() {
+        assert_eq!("off".parse::<Versioning>().unwrap(), Versioning::Off);
+        assert_eq!("0".parse::<Versioning>().unwrap(), Versioning::Off);
+        assert_eq!("10".parse::<Versioning>().unwrap(), Versioning::Keep(10));
+        assert!("ten".parse::<Versioning>().is_err());
+    }
+
+    #[test]
+    fn test_serde_omits_unset_fields() {
+        let policy = DirectoryPolicy {
+            versioning: Some(Versioning::Keep(10)),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(json, r#"{"versioning":{"keep":10}}"#);
+        assert_eq!(
+            serde_json::from_str::<DirectoryPolicy>(&json).unwrap(),
+            policy
+        );
+        assert!(serde_json::from_str::<DirectoryPolicy>("{}")
+            .unwrap()
+            .is_empty());
+    }
+}
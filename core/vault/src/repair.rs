@@ -0,0 +1,503 @@
+//! Reconciliation of the tree with the blobs in storage.
+//!
+//! After a crash or a sync between devices the tree can disagree with the
+//! data directory. [`VaultOperations::repair`] lists the blobs and compares
+//! them with the tree:
+//!
+//! - A blob no file references is decrypted. If both its content and its
+//!   storage name decrypt, it is re-added under [`RECOVERY_DIR`]; a blob
+//!   does not record the directory it was in.
+//! - A blob whose content does not decrypt, or whose name is a hash of a
+//!   long name and cannot be recovered, is only reported.
+//! - A file whose blob is gone is reported, and removed from the tree with
+//!   [`RepairOptions::prune_missing`].
+//!
+//! Blobs of files below an attach point are looked for on its provider;
+//! only the vault's own data directory is searched for unreferenced blobs.
+//!
+//! Blobs are examined at a limited rate. The tree is saved after every
+//! restored file, so a cancelled or failed run resumes where it stopped:
+//! restored blobs are referenced again and skipped.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use crate::blob::{decrypt_blob_to_writer, BlobFormat};
+use crate::layout;
+use crate::operations::VaultOperations;
+use crate::parts::BlobPart;
+use crate::session::VaultSession;
+use crate::tree::TreeNode;
+use axiomvault_common::{escape_lossy, Error, Result, VaultPath};
+
+/// Directory restored files are added to.
+pub const RECOVERY_DIR: &str = "/lost+found";
+
+/// Options for [`VaultOperations::repair`].
+#[derive(Debug, Clone)]
+pub struct RepairOptions {
+    /// Remove files whose blobs are gone from the tree. Only safe while no
+    /// other client is writing to the vault: a file being created is in
+    /// the tree before its blob is uploaded.
+    pub prune_missing: bool,
+    /// Maximum unreferenced blobs examined per second. `None` means
+    /// unlimited.
+    pub max_blobs_per_second: Option<u32>,
+    /// Stop after the current blob once this turns `true`.
+    pub cancel: Option<watch::Receiver<bool>>,
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self {
+            prune_missing: false,
+            max_blobs_per_second: Some(10),
+            cancel: None,
+        }
+    }
+}
+
+/// Outcome of a repair run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Unreferenced blobs examined.
+    pub scanned: usize,
+    /// Files re-added to the tree, by their new path.
+    pub restored: Vec<VaultPath>,
+    /// Blobs that did not decrypt with this vault's keys, or whose parts
+    /// are incomplete, by storage name.
+    pub undecryptable: Vec<String>,
+    /// Blobs whose content decrypts but whose name cannot be recovered.
+    pub unnamed: Vec<String>,
+    /// Blobs skipped because a foreground operation was using them.
+    pub deferred: usize,
+    /// Blobs that could not be examined, e.g. because a download failed.
+    pub failed: usize,
+    /// Files whose blobs are missing from storage.
+    pub missing: Vec<VaultPath>,
+    /// Missing files removed from the tree.
+    pub pruned: usize,
+    /// Whether the run stopped early.
+    pub cancelled: bool,
+}
+
+/// Objects of one unreferenced blob: a single object, or its parts by index.
+#[derive(Debug, Default)]
+struct BlobObjects {
+    single: bool,
+    parts: BTreeMap<usize, String>,
+}
+
+/// Result of examining one unreferenced blob.
+enum Examined {
+    Restored(VaultPath),
+    Undecryptable,
+    Unnamed,
+    Deferred,
+    /// Referenced by the tree by the time it was examined.
+    Claimed,
+}
+
+pub(crate) async fn repair(
+    ops: &VaultOperations<'_>,
+    session: &VaultSession,
+    options: &RepairOptions,
+) -> Result<RepairReport> {
+    let provider = session.provider();
+    let objects = layout::list_objects(provider.as_ref()).await?;
+    let stored: HashSet<&str> = objects.iter().map(String::as_str).collect();
+    let mut attached_objects = HashMap::new();
+    for point in &session.config().attachments {
+        let provider = session.attached_provider(point)?;
+        let names: HashSet<String> = layout::list_objects(provider.as_ref())
+            .await?
+            .into_iter()
+            .collect();
+        attached_objects.insert(point.vault_path.clone(), names);
+    }
+    let owners = session.tree().read().await.object_owners();
+
+    let mut report = RepairReport::default();
+
+    // Files whose objects are gone.
+    let is_stored = |name: &str, path: &VaultPath| match session.config().attachment_for(path) {
+        Some(point) => attached_objects[&point.vault_path].contains(name),
+        None => stored.contains(name),
+    };
+    let mut missing: Vec<VaultPath> = owners
+        .iter()
+        .filter(|(name, path)| !is_stored(name, path))
+        .map(|(_, path)| path.clone())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    missing.sort_by_key(|p| p.to_string());
+    report.missing = missing;
+
+    // Unreferenced objects, grouped by blob.
+    let mut blobs: BTreeMap<String, BlobObjects> = BTreeMap::new();
+    for name in objects.iter().filter(|n| !owners.contains_key(n.as_str())) {
+        match name.split_once('.') {
+            None => blobs.entry(name.clone()).or_default().single = true,
+            Some((base, suffix)) => match suffix
+                .strip_prefix("part")
+                .and_then(|i| i.parse::<usize>().ok())
+            {
+                Some(index) => {
+                    blobs
+                        .entry(base.to_string())
+                        .or_default()
+                        .parts
+                        .insert(index, name.clone());
+                }
+                None => {
+                    warn!(object = %escape_lossy(name), "Unrecognized object in data directory");
+                    report.undecryptable.push(name.clone());
+                }
+            },
+        }
+    }
+
+    let pause = options
+        .max_blobs_per_second
+        .filter(|n| *n > 0)
+        .map(|n| Duration::from_secs(1) / n)
+        .unwrap_or_default();
+
+    for (encrypted_name, objects) in blobs {
+        if options.cancel.as_ref().is_some_and(|c| *c.borrow()) {
+            info!(restored = report.restored.len(), "Repair cancelled");
+            report.cancelled = true;
+            return Ok(report);
+        }
+        report.scanned += 1;
+        match examine(ops, session, &encrypted_name, &objects).await {
+            Ok(Examined::Restored(path)) => report.restored.push(path),
+            Ok(Examined::Undecryptable) => report.undecryptable.push(encrypted_name),
+            Ok(Examined::Unnamed) => report.unnamed.push(encrypted_name),
+            Ok(Examined::Deferred) => report.deferred += 1,
+            Ok(Examined::Claimed) => {}
+            Err(e) => {
+                warn!("Failed to examine blob: {}", e);
+                report.failed += 1;
+            }
+        }
+        if !pause.is_zero() {
+            tokio::time::sleep(pause).await;
+        }
+    }
+
+    if options.prune_missing && !report.missing.is_empty() {
+        report.pruned = prune(session, &report.missing).await?;
+    }
+
+    info!(
+        scanned = report.scanned,
+        restored = report.restored.len(),
+        undecryptable = report.undecryptable.len(),
+        missing = report.missing.len(),
+        "Repair finished"
+    );
+    Ok(report)
+}
+
+/// Decrypt an unreferenced blob and re-add it to the tree if possible.
+async fn examine(
+    ops: &VaultOperations<'_>,
+    session: &VaultSession,
+    encrypted_name: &str,
+    objects: &BlobObjects,
+) -> Result<Examined> {
+    if session.is_blob_busy(encrypted_name) {
+        return Ok(Examined::Deferred);
+    }
+
+    // A split blob needs every part from zero up.
+    let contiguous = objects.parts.keys().copied().eq(0..objects.parts.len());
+    if !objects.single && !contiguous {
+        return Ok(Examined::Undecryptable);
+    }
+
+    let provider = session.provider();
+    let data_layout = session.data_layout();
+    let mut parts = Vec::new();
+    let ciphertext = if objects.single {
+        layout::download(provider.as_ref(), data_layout, encrypted_name).await?
+    } else {
+        let mut blob = Vec::new();
+        for name in objects.parts.values() {
+            let bytes = layout::download(provider.as_ref(), data_layout, name).await?;
+            parts.push(BlobPart {
+                name: name.clone(),
+                size: bytes.len() as u64,
+            });
+            blob.extend_from_slice(&bytes);
+        }
+        blob
+    };
+
+    let master_key = session.master_key()?;
+    let decrypted = [BlobFormat::V3, BlobFormat::V2, BlobFormat::V1]
+        .into_iter()
+        .find_map(|format| {
+            decrypt_blob_to_writer(
+                format,
+                &master_key,
+                encrypted_name,
+                &ciphertext,
+                &mut std::io::sink(),
+            )
+            .ok()
+            .map(|size| (format, size))
+        });
+    let Some((format, size)) = decrypted else {
+        debug!(blob = %encrypted_name, "Blob does not decrypt");
+        return Ok(Examined::Undecryptable);
+    };
+    let Ok(name) = ops.decrypt_name(encrypted_name) else {
+        debug!(blob = %encrypted_name, "Blob name cannot be recovered");
+        return Ok(Examined::Unnamed);
+    };
+
+    let recovery_dir = VaultPath::parse(RECOVERY_DIR)?;
+    let recovery_name = ops.encrypt_name(recovery_dir.name().unwrap_or_default())?;
+    let path = {
+        let mut tree = session.tree().write().await;
+        // A foreground write may have claimed the blob since the listing.
+        let owners = tree.object_owners();
+        if owners.contains_key(encrypted_name) || parts.iter().any(|p| owners.contains_key(&p.name))
+        {
+            return Ok(Examined::Claimed);
+        }
+        if !tree.exists(&recovery_dir) {
+            tree.create_directory(&recovery_dir, recovery_name)?;
+        }
+        let dir = tree.get_node_mut(&recovery_dir)?;
+        if !dir.is_directory() {
+            return Err(Error::AlreadyExists(format!(
+                "{} exists and is not a directory",
+                RECOVERY_DIR
+            )));
+        }
+        let file_name = unique_name(dir, &name);
+        let mut node = TreeNode::new_file(file_name.as_str(), encrypted_name, size);
+        node.metadata.blob_format = format;
+        node.metadata.parts = parts;
+        dir.add_child(node)?;
+        recovery_dir.join(&file_name)?
+    };
+    session.save_tree().await?;
+
+    info!(size, "Restored file from unreferenced blob");
+    Ok(Examined::Restored(path))
+}
+
+/// `name`, or `name (n)` for the first `n` not already taken in `dir`.
+fn unique_name(dir: &TreeNode, name: &str) -> String {
+    if dir.get_child(name).is_none() {
+        return name.to_string();
+    }
+    (1..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| dir.get_child(candidate).is_none())
+        .unwrap_or_default()
+}
+
+/// Remove files whose blobs are still gone from the tree.
+async fn prune(session: &VaultSession, missing: &[VaultPath]) -> Result<usize> {
+    let mut gone = Vec::new();
+    for path in missing {
+        let provider = session.blob_provider(path)?;
+        let objects = {
+            let tree = session.tree().read().await;
+            match tree.get_node(path) {
+                Ok(node) if node.is_file() => {
+                    crate::parts::object_names(&node.metadata.encrypted_name, &node.metadata.parts)
+                }
+                _ => continue,
+            }
+        };
+        // Look again: the blob may have been uploaded since the listing.
+        let mut present = false;
+        for name in &objects {
+            present |= layout::exists(provider.as_ref(), name).await?;
+        }
+        if !present {
+            gone.push(path.clone());
+        }
+    }
+
+    if gone.is_empty() {
+        return Ok(0);
+    }
+    {
+        let mut tree = session.tree().write().await;
+        for path in &gone {
+            tree.remove(path)?;
+        }
+    }
+    session.save_tree().await?;
+    info!(pruned = gone.len(), "Removed files with missing blobs");
+    Ok(gone.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{VaultConfig, DATA_DIRNAME};
+    use crate::tree::VaultTree;
+    use axiomvault_common::Secret;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::KdfParams;
+    use axiomvault_storage::{MemoryProvider, StorageProvider};
+    use std::sync::Arc;
+
+    async fn create_session() -> VaultSession {
+        let password = b"test-password";
+        let creation = VaultConfig::new(
+            VaultId::new("test").unwrap(),
+            password,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let provider = Arc::new(MemoryProvider::new());
+        provider
+            .create_dir(&VaultPath::parse("/d").unwrap())
+            .await
+            .unwrap();
+        provider
+            .create_dir(&VaultPath::parse("/m").unwrap())
+            .await
+            .unwrap();
+        VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(password),
+            provider,
+            VaultTree::new(),
+        )
+        .unwrap()
+    }
+
+    fn unthrottled() -> RepairOptions {
+        RepairOptions {
+            max_blobs_per_second: None,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repair_restores_missing_tree_entry() {
+        let session = create_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let kept = VaultPath::parse("/kept.txt").unwrap();
+        let lost = VaultPath::parse("/docs/lost.txt").unwrap();
+        ops.create_directory(&VaultPath::parse("/docs").unwrap())
+            .await
+            .unwrap();
+        ops.create_file(&kept, b"kept").await.unwrap();
+        ops.create_file(&lost, b"lost content").await.unwrap();
+
+        // The tree forgets the file; its blob stays in storage.
+        session.tree().write().await.remove(&lost).unwrap();
+        session.save_tree().await.unwrap();
+
+        let report = ops.repair(&unthrottled()).await.unwrap();
+        let restored = VaultPath::parse("/lost+found/lost.txt").unwrap();
+        assert_eq!(report.scanned, 1);
+        assert_eq!(report.restored, vec![restored.clone()]);
+        assert!(report.undecryptable.is_empty());
+        assert!(report.missing.is_empty());
+        assert_eq!(ops.read_file(&restored).await.unwrap(), b"lost content");
+        assert_eq!(ops.read_file(&kept).await.unwrap(), b"kept");
+
+        // A second run finds nothing left to do.
+        let again = ops.repair(&unthrottled()).await.unwrap();
+        assert_eq!(again.scanned, 0);
+        assert!(again.restored.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_repair_flags_undecryptable_blobs_and_prunes_missing() {
+        let session = create_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let gone = VaultPath::parse("/gone.txt").unwrap();
+        ops.create_file(&gone, b"data").await.unwrap();
+        let gone_name = session
+            .tree()
+            .read()
+            .await
+            .get_node(&gone)
+            .unwrap()
+            .metadata
+            .encrypted_name
+            .clone();
+        let data_dir = VaultPath::parse(DATA_DIRNAME).unwrap();
+        let provider = session.provider();
+        provider
+            .delete(&data_dir.join(&gone_name).unwrap())
+            .await
+            .unwrap();
+        provider
+            .upload(&data_dir.join("Zm9yZWlnbg").unwrap(), vec![0u8; 64])
+            .await
+            .unwrap();
+
+        let report = ops.repair(&unthrottled()).await.unwrap();
+        assert_eq!(report.undecryptable, vec!["Zm9yZWlnbg".to_string()]);
+        assert_eq!(report.missing, vec![gone.clone()]);
+        assert_eq!(report.pruned, 0);
+        assert!(ops.exists(&gone).await);
+
+        let options = RepairOptions {
+            prune_missing: true,
+            ..unthrottled()
+        };
+        let report = ops.repair(&options).await.unwrap();
+        assert_eq!(report.pruned, 1);
+        assert!(!ops.exists(&gone).await);
+    }
+
+    #[tokio::test]
+    async fn test_restored_name_does_not_clobber_existing_file() {
+        let session = create_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let lost = VaultPath::parse("/a.txt").unwrap();
+        ops.create_file(&lost, b"old").await.unwrap();
+        session.tree().write().await.remove(&lost).unwrap();
+        ops.create_directory(&VaultPath::parse(RECOVERY_DIR).unwrap())
+            .await
+            .unwrap();
+        let taken = VaultPath::parse("/lost+found/a.txt").unwrap();
+        ops.create_file(&taken, b"new").await.unwrap();
+
+        let report = ops.repair(&unthrottled()).await.unwrap();
+        let restored = VaultPath::parse("/lost+found/a.txt (1)").unwrap();
+        assert_eq!(report.restored, vec![restored.clone()]);
+        assert_eq!(ops.read_file(&restored).await.unwrap(), b"old");
+        assert_eq!(ops.read_file(&taken).await.unwrap(), b"new");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_repair_stops_before_examining() {
+        let session = create_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let lost = VaultPath::parse("/a.txt").unwrap();
+        ops.create_file(&lost, b"x").await.unwrap();
+        session.tree().write().await.remove(&lost).unwrap();
+
+        let (_stop, cancel) = watch::channel(true);
+        let options = RepairOptions {
+            cancel: Some(cancel),
+            ..unthrottled()
+        };
+        let report = ops.repair(&options).await.unwrap();
+        assert!(report.cancelled);
+        assert_eq!(report.scanned, 0);
+        assert!(report.restored.is_empty());
+    }
+}
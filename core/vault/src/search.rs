@@ -0,0 +1,950 @@
+//! Opt-in full-text search over file contents.
+//!
+//! A [`SearchIndex`] maps terms to the files containing them. While
+//! enabled it lives in memory on the session and is stored as a single
+//! encrypted blob under `/.index/`, sealed with its own key derived from
+//! the master key, so the stored bytes reveal neither terms nor paths.
+//! Only postings are kept; snippets are cut from the decrypted file when a
+//! query runs.
+//!
+//! Foreground writes only queue the changed path. The indexer task reads,
+//! tokenizes and saves in the background within the configured budgets,
+//! and files changed elsewhere (another device, an earlier session with
+//! search off) are picked up by comparing modification times on enable.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::operations::VaultOperations;
+use crate::session::VaultSession;
+use crate::tree::VaultTree;
+use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_crypto::{DecryptingReader, EncryptingWriter, KeyContext, MasterKey};
+use axiomvault_storage::StorageProvider;
+
+/// Storage directory holding the encrypted index.
+pub const INDEX_DIRNAME: &str = ".index";
+
+/// Name of the index blob inside [`INDEX_DIRNAME`].
+pub const INDEX_FILENAME: &str = "terms";
+
+/// Shortest and longest terms that are indexed, in characters.
+const MIN_TERM_CHARS: usize = 2;
+const MAX_TERM_CHARS: usize = 64;
+
+/// Most matches reported per file.
+pub const MAX_MATCHES_PER_HIT: usize = 5;
+
+/// Bytes of context on each side of a match in a snippet.
+const SNIPPET_CONTEXT: usize = 40;
+
+/// Which files are indexed and how much work indexing may do.
+#[derive(Debug, Clone)]
+pub struct SearchConfig {
+    /// File extensions to index, lowercase and without the dot.
+    pub extensions: Vec<String>,
+    /// Larger files are left out of the index.
+    pub max_file_bytes: u64,
+    /// Files that would grow the index past this estimate are left out.
+    pub max_index_bytes: u64,
+    /// Maximum plaintext bytes indexed per second. `None` means unlimited.
+    pub max_bytes_per_second: Option<u64>,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        let mut extensions: Vec<String> = [
+            "txt", "md", "markdown", "csv", "log", "json", "xml", "html", "yaml", "yml", "toml",
+            "rst", "org",
+        ]
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+        if cfg!(feature = "search-pdf") {
+            extensions.push("pdf".to_string());
+        }
+        Self {
+            extensions,
+            max_file_bytes: 4 * 1024 * 1024,
+            max_index_bytes: 64 * 1024 * 1024,
+            max_bytes_per_second: Some(8 * 1024 * 1024),
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Whether a file at `path` of `size` bytes is indexed.
+    fn indexes(&self, path: &VaultPath, size: u64) -> bool {
+        size <= self.max_file_bytes
+            && extension(path).is_some_and(|ext| self.extensions.contains(&ext))
+    }
+
+    /// Pause to take after indexing `bytes` plaintext bytes.
+    fn pause_after(&self, bytes: u64) -> Duration {
+        self.max_bytes_per_second
+            .filter(|n| *n > 0)
+            .map(|n| Duration::from_secs_f64(bytes as f64 / n as f64))
+            .unwrap_or_default()
+    }
+}
+
+/// Lowercase extension of the file name of `path`.
+fn extension(path: &VaultPath) -> Option<String> {
+    let (stem, ext) = path.name()?.rsplit_once('.')?;
+    (!stem.is_empty()).then(|| ext.to_lowercase())
+}
+
+/// A file matching a query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub path: VaultPath,
+    /// Where the phrase occurs, at most [`MAX_MATCHES_PER_HIT`].
+    pub matches: Vec<TextMatch>,
+}
+
+/// One occurrence of the query in a file's text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextMatch {
+    /// Byte offset into the extracted text.
+    pub offset: usize,
+    /// Length in bytes.
+    pub len: usize,
+    /// The match with surrounding text, on one line.
+    pub snippet: String,
+}
+
+/// Outcome of one or more indexing passes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IndexReport {
+    /// Files added or re-indexed.
+    pub indexed: usize,
+    /// Files dropped from the index.
+    pub removed: usize,
+    /// Files left out because the index is at its size budget.
+    pub skipped: usize,
+    /// Files that could not be read.
+    pub failed: usize,
+}
+
+/// An indexed file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    path: VaultPath,
+    /// Modification time of the version that was indexed.
+    modified_at: DateTime<Utc>,
+}
+
+/// The stored part of the index.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexData {
+    files: BTreeMap<u32, IndexedFile>,
+    /// Ids of the files containing each term.
+    postings: BTreeMap<String, BTreeSet<u32>>,
+    next_id: u32,
+    /// File id by path, rebuilt on load.
+    #[serde(skip)]
+    ids: HashMap<VaultPath, u32>,
+    /// Estimated stored size, rebuilt on load.
+    #[serde(skip)]
+    bytes: u64,
+}
+
+/// Rough stored cost of a term, a posting and a file entry.
+fn term_cost(term: &str) -> u64 {
+    term.len() as u64 + 8
+}
+const POSTING_COST: u64 = 6;
+fn file_cost(path: &VaultPath) -> u64 {
+    path.to_string().len() as u64 + 48
+}
+
+impl IndexData {
+    /// Rebuild the fields that are not stored.
+    fn rebuild_derived(&mut self) {
+        self.ids = self
+            .files
+            .iter()
+            .map(|(id, file)| (file.path.clone(), *id))
+            .collect();
+        self.bytes = self.files.values().map(|f| file_cost(&f.path)).sum::<u64>()
+            + self
+                .postings
+                .iter()
+                .map(|(term, ids)| term_cost(term) + POSTING_COST * ids.len() as u64)
+                .sum::<u64>();
+    }
+
+    /// Estimated size after adding a file at `path` with `terms`.
+    fn bytes_with(&self, path: &VaultPath, terms: &BTreeSet<String>) -> u64 {
+        let added: u64 = terms
+            .iter()
+            .map(|t| POSTING_COST + self.postings.get(t).map_or(term_cost(t), |_| 0))
+            .sum();
+        self.bytes + file_cost(path) + added
+    }
+
+    fn remove(&mut self, path: &VaultPath) -> bool {
+        let Some(id) = self.ids.remove(path) else {
+            return false;
+        };
+        self.files.remove(&id);
+        self.bytes = self.bytes.saturating_sub(file_cost(path));
+        let mut emptied = Vec::new();
+        for (term, ids) in self.postings.iter_mut() {
+            if ids.remove(&id) {
+                self.bytes = self.bytes.saturating_sub(POSTING_COST);
+                if ids.is_empty() {
+                    emptied.push(term.clone());
+                }
+            }
+        }
+        for term in emptied {
+            self.bytes = self.bytes.saturating_sub(term_cost(&term));
+            self.postings.remove(&term);
+        }
+        true
+    }
+
+    fn insert(&mut self, path: VaultPath, modified_at: DateTime<Utc>, terms: BTreeSet<String>) {
+        self.bytes = self.bytes_with(&path, &terms);
+        let id = self.next_id;
+        self.next_id += 1;
+        for term in terms {
+            self.postings.entry(term).or_default().insert(id);
+        }
+        self.ids.insert(path.clone(), id);
+        self.files.insert(id, IndexedFile { path, modified_at });
+    }
+}
+
+/// Paths waiting to be (re-)indexed, in arrival order.
+#[derive(Debug, Default)]
+struct Pending {
+    order: VecDeque<VaultPath>,
+    queued: HashSet<VaultPath>,
+}
+
+/// In-memory full-text index of a vault.
+///
+/// Created by [`VaultSession::enable_search`].
+#[derive(Debug)]
+pub struct SearchIndex {
+    config: SearchConfig,
+    data: RwLock<IndexData>,
+    pending: Mutex<Pending>,
+    /// Wakes the indexer task when paths are queued.
+    wake: Notify,
+    /// Whether `data` changed since it was last saved.
+    dirty: AtomicBool,
+}
+
+impl SearchIndex {
+    fn new(config: SearchConfig, mut data: IndexData) -> Self {
+        data.rebuild_derived();
+        Self {
+            config,
+            data: RwLock::new(data),
+            pending: Mutex::new(Pending::default()),
+            wake: Notify::new(),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// The configuration in use.
+    pub fn config(&self) -> &SearchConfig {
+        &self.config
+    }
+
+    /// Number of files in the index.
+    pub fn indexed_files(&self) -> usize {
+        self.read_data().files.len()
+    }
+
+    /// Number of paths waiting to be indexed.
+    pub fn pending(&self) -> usize {
+        self.lock_pending().order.len()
+    }
+
+    /// Estimated stored size of the index in bytes.
+    pub fn size_estimate(&self) -> u64 {
+        self.read_data().bytes
+    }
+
+    fn read_data(&self) -> std::sync::RwLockReadGuard<'_, IndexData> {
+        self.data.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_data(&self) -> std::sync::RwLockWriteGuard<'_, IndexData> {
+        self.data.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, Pending> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue `path` for indexing; a directory queues the files below it.
+    pub(crate) fn queue(&self, path: VaultPath) {
+        let mut pending = self.lock_pending();
+        if pending.queued.insert(path.clone()) {
+            pending.order.push_back(path);
+        }
+        drop(pending);
+        self.wake.notify_one();
+    }
+
+    /// Queue both sides of a rename, including indexed files below `from`.
+    pub(crate) fn queue_rename(&self, from: &VaultPath, to: &VaultPath) {
+        let moved: Vec<VaultPath> = self
+            .read_data()
+            .ids
+            .keys()
+            .filter(|p| p.starts_with(from))
+            .cloned()
+            .collect();
+        for path in moved {
+            self.queue(path);
+        }
+        self.queue(to.clone());
+    }
+
+    fn next_pending(&self) -> Option<VaultPath> {
+        let mut pending = self.lock_pending();
+        let path = pending.order.pop_front()?;
+        pending.queued.remove(&path);
+        Some(path)
+    }
+
+    /// Queue every file whose index entry is missing, stale or unwanted.
+    fn queue_outdated(&self, tree: &VaultTree) -> usize {
+        let mut outdated = Vec::new();
+        {
+            let data = self.read_data();
+            let mut seen = HashSet::new();
+            for path in tree.files_under(&VaultPath::root()) {
+                let Ok(node) = tree.get_node(&path) else {
+                    continue;
+                };
+                let wanted = self.config.indexes(&path, node.metadata.size.unwrap_or(0));
+                let indexed = data.ids.get(&path).and_then(|id| data.files.get(id));
+                let stale = match indexed {
+                    Some(file) => !wanted || file.modified_at != node.metadata.modified_at,
+                    None => wanted,
+                };
+                if stale {
+                    outdated.push(path.clone());
+                }
+                seen.insert(path);
+            }
+            outdated.extend(data.ids.keys().filter(|p| !seen.contains(*p)).cloned());
+        }
+        let count = outdated.len();
+        for path in outdated {
+            self.queue(path);
+        }
+        count
+    }
+
+    /// Drop everything and queue every file, returning how many.
+    pub(crate) fn rebuild(&self, tree: &VaultTree) -> usize {
+        *self.write_data() = IndexData::default();
+        self.dirty.store(true, Ordering::SeqCst);
+        self.queue_outdated(tree)
+    }
+
+    /// Indexed paths containing every term, sorted by path.
+    fn candidates(&self, terms: &BTreeSet<String>) -> Vec<VaultPath> {
+        let data = self.read_data();
+        let mut ids: Option<BTreeSet<u32>> = None;
+        for term in terms {
+            let Some(postings) = data.postings.get(term) else {
+                return Vec::new();
+            };
+            ids = Some(match ids {
+                None => postings.clone(),
+                Some(ids) => ids.intersection(postings).copied().collect(),
+            });
+        }
+        let mut paths: Vec<VaultPath> = ids
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| data.files.get(id).map(|f| f.path.clone()))
+            .collect();
+        paths.sort_by_key(|p| p.to_string());
+        paths
+    }
+}
+
+/// Words of `text` with their byte ranges, lowercased.
+fn tokens(text: &str) -> impl Iterator<Item = (usize, usize, String)> + '_ {
+    let mut rest = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        while rest.next_if(|(_, c)| !c.is_alphanumeric()).is_some() {}
+        let (start, _) = *rest.peek()?;
+        let mut end = start;
+        while let Some((i, c)) = rest.next_if(|(_, c)| c.is_alphanumeric()) {
+            end = i + c.len_utf8();
+        }
+        Some((start, end, text[start..end].to_lowercase()))
+    })
+}
+
+/// Whether a token is stored in the index.
+fn is_term(token: &str) -> bool {
+    (MIN_TERM_CHARS..=MAX_TERM_CHARS).contains(&token.chars().count())
+}
+
+/// Distinct indexable terms of `text`.
+fn terms(text: &str) -> BTreeSet<String> {
+    tokens(text)
+        .map(|(_, _, t)| t)
+        .filter(|t| is_term(t))
+        .collect()
+}
+
+/// Occurrences of the word sequence `phrase` in `text`.
+fn phrase_matches(text: &str, phrase: &[String]) -> Vec<TextMatch> {
+    let words: Vec<(usize, usize, String)> = tokens(text).collect();
+    let mut matches = Vec::new();
+    for window in words.windows(phrase.len()) {
+        if window.iter().map(|(_, _, w)| w).eq(phrase.iter()) {
+            let (offset, end) = (window[0].0, window[window.len() - 1].1);
+            matches.push(TextMatch {
+                offset,
+                len: end - offset,
+                snippet: snippet(text, offset, end),
+            });
+            if matches.len() == MAX_MATCHES_PER_HIT {
+                break;
+            }
+        }
+    }
+    matches
+}
+
+/// `text[start..end]` with up to [`SNIPPET_CONTEXT`] bytes either side.
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let mut from = start.saturating_sub(SNIPPET_CONTEXT);
+    while !text.is_char_boundary(from) {
+        from -= 1;
+    }
+    let mut to = (end + SNIPPET_CONTEXT).min(text.len());
+    while !text.is_char_boundary(to) {
+        to += 1;
+    }
+    text[from..to]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Searchable text of a file, if it has any.
+fn extract_text(path: &VaultPath, content: &[u8]) -> Option<String> {
+    if extension(path).as_deref() == Some("pdf") {
+        return extract_pdf_text(content);
+    }
+    if content.contains(&0) {
+        return None;
+    }
+    std::str::from_utf8(content).ok().map(str::to_string)
+}
+
+#[cfg(feature = "search-pdf")]
+fn extract_pdf_text(content: &[u8]) -> Option<String> {
+    pdf_extract::extract_text_from_mem(content)
+        .map_err(|e| debug!("No text extracted from PDF: {}", e))
+        .ok()
+}
+
+#[cfg(not(feature = "search-pdf"))]
+fn extract_pdf_text(_content: &[u8]) -> Option<String> {
+    None
+}
+
+fn index_path() -> Result<VaultPath> {
+    VaultPath::parse(INDEX_DIRNAME)?.join(INDEX_FILENAME)
+}
+
+/// Load the stored index, or an empty one, and queue outdated files.
+pub(crate) async fn load(session: &VaultSession, config: SearchConfig) -> Result<SearchIndex> {
+    let provider = session.provider();
+    let path = index_path()?;
+    let data = if provider.exists(&path).await? {
+        let encrypted = provider.download(&path).await?;
+        match decrypt_index(&encrypted, &*session.master_key()?) {
+            Ok(data) => data,
+            Err(e) => {
+                // The index only mirrors the vault; a fresh one is rebuilt.
+                warn!("Discarding unreadable search index: {}", e);
+                IndexData::default()
+            }
+        }
+    } else {
+        IndexData::default()
+    };
+
+    let index = SearchIndex::new(config, data);
+    let queued = index.queue_outdated(&*session.tree().read().await);
+    info!(
+        indexed = index.indexed_files(),
+        queued, "Search index loaded"
+    );
+    Ok(index)
+}
+
+/// Save the index if it changed since the last save.
+pub(crate) async fn save(session: &VaultSession, index: &SearchIndex) -> Result<()> {
+    if !index.dirty.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let result = async {
+        let chunks = {
+            let data = index.read_data();
+            encrypt_index(&data, &*session.master_key()?)?
+        };
+        let provider = session.provider();
+        let dir = VaultPath::parse(INDEX_DIRNAME)?;
+        if !provider.exists(&dir).await? {
+            provider.create_dir(&dir).await?;
+        }
+        let stream = futures::stream::iter(chunks.into_iter().map(Ok));
+        provider
+            .upload_stream(&index_path()?, Box::pin(stream))
+            .await
+    }
+    .await;
+    if let Err(e) = result {
+        // Saved again after the next change or pass.
+        index.dirty.store(true, Ordering::SeqCst);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Delete the stored index.
+pub(crate) async fn purge(provider: &dyn StorageProvider) -> Result<()> {
+    let path = index_path()?;
+    if provider.exists(&path).await? {
+        provider.delete(&path).await?;
+    }
+    let dir = VaultPath::parse(INDEX_DIRNAME)?;
+    if provider.exists(&dir).await? {
+        provider.delete_dir(&dir).await?;
+    }
+    Ok(())
+}
+
+fn encrypt_index(data: &IndexData, master_key: &MasterKey) -> Result<Vec<Vec<u8>>> {
+    let key = master_key.derive_file_key(&KeyContext::SearchIndex);
+    let mut writer = EncryptingWriter::new(key.as_bytes())?;
+    serde_json::to_writer(&mut writer, data)
+        .map_err(|e| Error::Serialization(format!("Failed to write search index: {}", e)))?;
+    writer
+        .finish()
+        .map_err(|e| Error::Crypto(format!("Failed to encrypt search index: {}", e)))
+}
+
+fn decrypt_index(encrypted: &[u8], master_key: &MasterKey) -> Result<IndexData> {
+    let key = master_key.derive_file_key(&KeyContext::SearchIndex);
+    let reader = DecryptingReader::new(key.as_bytes(), encrypted)?;
+    serde_json::from_reader(reader)
+        .map_err(|e| Error::Crypto(format!("Failed to decrypt search index: {}", e)))
+}
+
+/// Result of refreshing one queued path.
+enum Refresh {
+    /// Indexed; carries the plaintext size.
+    Indexed(u64),
+    /// Dropped from the index.
+    Removed,
+    /// Left out to stay within the size budget.
+    Skipped,
+    /// Nothing to do, or a directory whose files were queued.
+    Unchanged,
+}
+
+async fn refresh(session: &VaultSession, index: &SearchIndex, path: &VaultPath) -> Result<Refresh> {
+    let modified_at = {
+        let tree = session.tree().read().await;
+        match tree.get_node(path) {
+            Ok(node) if node.is_directory() => {
+                for file in tree.files_under(path) {
+                    index.queue(file);
+                }
+                return Ok(Refresh::Unchanged);
+            }
+            Ok(node) if index.config.indexes(path, node.metadata.size.unwrap_or(0)) => {
+                node.metadata.modified_at
+            }
+            _ => {
+                return Ok(if index.write_data().remove(path) {
+                    index.dirty.store(true, Ordering::SeqCst);
+                    Refresh::Removed
+                } else {
+                    Refresh::Unchanged
+                });
+            }
+        }
+    };
+
+    let content = VaultOperations::new(session)?.read_file(path).await?;
+    let size = content.len() as u64;
+    let terms = extract_text(path, &content)
+        .map(|text| terms(&text))
+        .unwrap_or_default();
+
+    let mut data = index.write_data();
+    data.remove(path);
+    index.dirty.store(true, Ordering::SeqCst);
+    if data.bytes_with(path, &terms) > index.config.max_index_bytes {
+        debug!("Search index is full, leaving file out");
+        return Ok(Refresh::Skipped);
+    }
+    data.insert(path.clone(), modified_at, terms);
+    Ok(Refresh::Indexed(size))
+}
+
+/// Index every queued path, then save the index.
+pub(crate) async fn run_pass(
+    session: &VaultSession,
+    index: &SearchIndex,
+    mut stopped: Option<&mut watch::Receiver<bool>>,
+) -> Result<IndexReport> {
+    let mut report = IndexReport::default();
+    while session.is_active() && !stopped.as_deref().is_some_and(|rx| *rx.borrow()) {
+        let Some(path) = index.next_pending() else {
+            break;
+        };
+        match refresh(session, index, &path).await {
+            Ok(Refresh::Indexed(bytes)) => {
+                report.indexed += 1;
+                let pause = index.config.pause_after(bytes);
+                if !pause.is_zero() {
+                    match stopped.as_deref_mut() {
+                        Some(rx) => {
+                            tokio::select! {
+                                _ = tokio::time::sleep(pause) => {}
+                                _ = rx.changed() => {}
+                            }
+                        }
+                        None => tokio::time::sleep(pause).await,
+                    }
+                }
+            }
+            Ok(Refresh::Removed) => report.removed += 1,
+            Ok(Refresh::Skipped) => report.skipped += 1,
+            Ok(Refresh::Unchanged) => {}
+            Err(Error::NotFound(_)) => {
+                // Deleted since it was queued; the delete queued it again.
+            }
+            Err(e) => {
+                warn!("Failed to index file: {}", e);
+                report.failed += 1;
+            }
+        }
+    }
+    if session.is_active() {
+        save(session, index).await?;
+    }
+    debug!(?report, "Indexing pass finished");
+    Ok(report)
+}
+
+/// Handle to a running background indexer.
+pub struct IndexerHandle {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<Result<IndexReport>>,
+}
+
+impl IndexerHandle {
+    /// Ask the task to stop after the file it is currently indexing.
+    pub fn stop(&self) {
+        let _ = self.stop.send(true);
+    }
+
+    /// Whether the task has exited.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Wait for the task to exit and return its combined report.
+    pub async fn join(self) -> Result<IndexReport> {
+        self.task
+            .await
+            .map_err(|e| Error::Vault(format!("Indexer task failed: {}", e)))?
+    }
+}
+
+/// Index queued paths as they arrive until stopped, the session locks, or
+/// search is disabled.
+pub(crate) fn spawn(session: Arc<VaultSession>, index: Arc<SearchIndex>) -> IndexerHandle {
+    let (stop, mut stopped) = watch::channel(false);
+    let task = tokio::spawn(async move {
+        let mut total = IndexReport::default();
+        loop {
+            let current = session
+                .search_index()
+                .is_some_and(|i| Arc::ptr_eq(&i, &index));
+            if !current || !session.is_active() || *stopped.borrow() {
+                break;
+            }
+            let pass = run_pass(&session, &index, Some(&mut stopped)).await?;
+            total.indexed += pass.indexed;
+            total.removed += pass.removed;
+            total.skipped += pass.skipped;
+            total.failed += pass.failed;
+
+            tokio::select! {
+                _ = index.wake.notified() => {}
+                _ = stopped.changed() => {}
+            }
+        }
+        Ok(total)
+    });
+    IndexerHandle { stop, task }
+}
+
+/// Search `index` for files containing `query` as a phrase.
+///
+/// Candidates come from the index; each is decrypted to confirm the phrase
+/// and cut snippets, so stale entries never produce hits.
+pub(crate) async fn search(
+    ops: &VaultOperations<'_>,
+    index: &SearchIndex,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchHit>> {
+    let phrase: Vec<String> = tokens(query).map(|(_, _, t)| t).collect();
+    let query_terms: BTreeSet<String> = phrase.iter().filter(|t| is_term(t)).cloned().collect();
+    if query_terms.is_empty() {
+        return Err(Error::InvalidInput(
+            "Query has no searchable words".to_string(),
+        ));
+    }
+
+    let mut hits = Vec::new();
+    for path in index.candidates(&query_terms) {
+        if hits.len() >= limit {
+            break;
+        }
+        let content = match ops.read_file(&path).await {
+            Ok(content) => content,
+            Err(Error::NotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        let Some(text) = extract_text(&path, &content) else {
+            continue;
+        };
+        let matches = phrase_matches(&text, &phrase);
+        if !matches.is_empty() {
+            hits.push(SearchHit { path, matches });
+        }
+    }
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VaultConfig;
+    use axiomvault_common::Secret;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::KdfParams;
+    use axiomvault_storage::MemoryProvider;
+
+    const PASSWORD: &[u8] = b"test-password";
+
+    async fn open_session() -> (Arc<VaultSession>, Arc<dyn StorageProvider>) {
+        let creation = VaultConfig::new(
+            VaultId::new("test").unwrap(),
+            PASSWORD,
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let provider: Arc<dyn StorageProvider> = Arc::new(MemoryProvider::new());
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let master_key = creation
+            .config
+            .verify_password(&Secret::from_slice(PASSWORD))
+            .unwrap()
+            .unwrap();
+        let tree = VaultSession::load_and_decrypt_tree(&provider, &master_key)
+            .await
+            .unwrap();
+        let session =
+            VaultSession::from_master_key(creation.config, master_key, provider.clone(), tree)
+                .unwrap();
+        (Arc::new(session), provider)
+    }
+
+    fn unthrottled() -> SearchConfig {
+        SearchConfig {
+            max_bytes_per_second: None,
+            ..SearchConfig::default()
+        }
+    }
+
+    fn p(path: &str) -> VaultPath {
+        VaultPath::parse(path).unwrap()
+    }
+
+    async fn hits(session: &VaultSession, query: &str) -> Vec<String> {
+        VaultOperations::new(session)
+            .unwrap()
+            .search_content(query, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|h| h.path.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_phrase_matches_report_offsets_and_snippets() {
+        let text = "Paid: Invoice 2024-03\nfor hosting.\nInvoice  2024 again";
+        let phrase = vec!["invoice".to_string(), "2024".to_string()];
+        let matches = phrase_matches(text, &phrase);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(&text[matches[0].offset..][..matches[0].len], "Invoice 2024");
+        assert_eq!(
+            &text[matches[1].offset..][..matches[1].len],
+            "Invoice  2024"
+        );
+        assert!(matches[0]
+            .snippet
+            .contains("Paid: Invoice 2024-03 for hosting."));
+        assert_eq!(
+            terms("Grüße, GRÜSSE a b"),
+            ["grüsse", "grüße"].map(String::from).into()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_follows_create_update_rename_and_delete() {
+        let (session, _) = open_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_directory(&p("/docs")).await.unwrap();
+        ops.create_file(&p("/docs/a.txt"), b"The invoice 2024 is attached")
+            .await
+            .unwrap();
+        ops.create_file(&p("/docs/b.md"), b"Meeting notes, no invoice here")
+            .await
+            .unwrap();
+        ops.create_file(&p("/docs/c.bin"), b"invoice 2024")
+            .await
+            .unwrap();
+
+        let index = session.enable_search(unthrottled()).await.unwrap();
+        assert_eq!(index.pending(), 2);
+        session.run_index_pass().await.unwrap();
+        assert_eq!(index.indexed_files(), 2);
+        assert_eq!(hits(&session, "invoice 2024").await, ["/docs/a.txt"]);
+        assert_eq!(
+            hits(&session, "INVOICE").await,
+            ["/docs/a.txt", "/docs/b.md"]
+        );
+
+        ops.update_file(&p("/docs/a.txt"), b"Nothing left to pay")
+            .await
+            .unwrap();
+        ops.update_file(&p("/docs/b.md"), b"Invoice 2024, second copy")
+            .await
+            .unwrap();
+        let report = session.run_index_pass().await.unwrap();
+        assert_eq!(report.indexed, 2);
+        assert_eq!(hits(&session, "invoice 2024").await, ["/docs/b.md"]);
+        assert!(hits(&session, "attached").await.is_empty());
+
+        ops.rename(&p("/docs"), &p("/archive")).await.unwrap();
+        session.run_index_pass().await.unwrap();
+        assert_eq!(hits(&session, "invoice 2024").await, ["/archive/b.md"]);
+
+        ops.delete_file(&p("/archive/b.md")).await.unwrap();
+        let report = session.run_index_pass().await.unwrap();
+        assert_eq!(report.removed, 1);
+        assert!(hits(&session, "invoice").await.is_empty());
+        assert_eq!(index.indexed_files(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stored_index_reveals_no_plaintext_and_reloads() {
+        let (session, provider) = open_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_file(&p("/ledger.txt"), b"zanzibar quarterly reconciliation")
+            .await
+            .unwrap();
+        session.enable_search(unthrottled()).await.unwrap();
+        session.run_index_pass().await.unwrap();
+
+        let stored = provider.download(&index_path().unwrap()).await.unwrap();
+        for needle in ["zanzibar", "quarterly", "reconciliation", "ledger"] {
+            assert!(
+                !stored
+                    .windows(needle.len())
+                    .any(|w| w.eq_ignore_ascii_case(needle.as_bytes())),
+                "{needle} leaked into the stored index"
+            );
+        }
+
+        // Enabling again loads the stored index without re-reading files.
+        let index = session.enable_search(unthrottled()).await.unwrap();
+        assert_eq!(index.pending(), 0);
+        assert_eq!(
+            hits(&session, "quarterly reconciliation").await,
+            ["/ledger.txt"]
+        );
+
+        session.disable_search().await.unwrap();
+        assert!(!provider.exists(&index_path().unwrap()).await.unwrap());
+        assert!(matches!(
+            ops.search_content("zanzibar", 10).await,
+            Err(Error::NotPermitted(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_size_budget_and_background_indexer() {
+        let (session, _) = open_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let index = session
+            .enable_search(SearchConfig {
+                max_index_bytes: 200,
+                ..unthrottled()
+            })
+            .await
+            .unwrap();
+        let indexer = session.start_indexing().unwrap();
+
+        ops.create_file(&p("/small.txt"), b"alpha beta")
+            .await
+            .unwrap();
+        let long: String = (0..50).map(|i| format!("word{} ", i)).collect();
+        ops.create_file(&p("/large.txt"), long.as_bytes())
+            .await
+            .unwrap();
+
+        while index.pending() > 0 || index.indexed_files() == 0 {
+            tokio::task::yield_now().await;
+        }
+        indexer.stop();
+        let report = indexer.join().await.unwrap();
+        assert_eq!(report.indexed, 1);
+        assert_eq!(report.skipped, 1);
+        assert!(index.size_estimate() <= 200);
+        assert_eq!(hits(&session, "alpha").await, ["/small.txt"]);
+    }
+}
@@ -3,19 +3,36 @@
 //! Sessions hold decrypted keys in memory and provide access to vault operations.
 //! Keys are automatically zeroized when the session is dropped.
 
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
 
-use crate::config::{VaultConfig, META_DIRNAME, TREE_FILENAME};
+use crate::access_time::{self, AccessTimeFlushHandle};
+use crate::blob_cache::BlobCache;
+use crate::config::{
+    AttachPoint, DataLayout, KeyVerificationAlgorithm, NameLimit, VaultConfig, VaultMetadata,
+    CONFIG_FILENAME, META_DIRNAME, TREE_FILENAME,
+};
+use crate::events::{VaultEvent, VaultEventReceiver, EVENT_CHANNEL_CAPACITY};
+use crate::journal::{self, Journal};
+use crate::maintenance::{self, MaintenanceHandle, MaintenancePolicy, MaintenanceReport};
+use crate::policy::EffectivePolicy;
+#[cfg(feature = "search")]
+use crate::search::{self, IndexReport, IndexerHandle, SearchConfig, SearchIndex};
+use crate::stream_budget::StreamBudget;
 use crate::tree::VaultTree;
-use axiomvault_common::{Error, Result, VaultId, VaultPath};
+use axiomvault_common::{Error, Result, Secret, VaultId, VaultPath};
 use axiomvault_crypto::recovery::RecoveryKey;
-use axiomvault_crypto::{decrypt, derive_key, encrypt, MasterKey};
-use axiomvault_storage::StorageProvider;
-
-/// Context tag for tree index key derivation. Changing this invalidates all existing vaults.
-const TREE_KEY_CONTEXT: &[u8] = b"vault_tree_index_v1";
+use axiomvault_crypto::stream::is_stream_format;
+use axiomvault_crypto::{
+    decrypt, derive_key_with_keyfile, DecryptingReader, EncryptingWriter, FileKey, KdfParams,
+    KeyContext, MasterKey, Salt, SALT_LENGTH,
+};
+use axiomvault_storage::{ProviderRegistry, StorageProvider};
+use zeroize::Zeroizing;
 
 /// Session handle for tracking active sessions.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -48,6 +65,56 @@ pub enum SessionState {
     Locked,
 }
 
+/// Key hygiene limits for long-lived sessions (e.g. a FUSE mount left
+/// running for weeks).
+#[derive(Debug, Clone, Default)]
+pub struct SessionPolicy {
+    /// Move the working master key into freshly allocated memory through
+    /// the key-wrap layer once it is this old. The previous copy is
+    /// zeroized as soon as no operation still uses it.
+    pub rekey_interval: Option<Duration>,
+    /// Lock the session once this many plaintext bytes have been read or
+    /// written; continuing requires [`VaultSession::reauthenticate`].
+    pub max_bytes_per_session: Option<u64>,
+}
+
+/// Working key material, replaced as a whole when the session re-keys.
+struct WorkingKeys {
+    /// Master key; operations hold their own `Arc` so a swap never
+    /// invalidates a key that is in use.
+    master_key: Arc<MasterKey>,
+    /// Number of re-keys since the session was unlocked.
+    generation: u64,
+    /// When `master_key` was last refreshed.
+    refreshed_at: Instant,
+}
+
+impl WorkingKeys {
+    fn new(master_key: MasterKey) -> Self {
+        Self {
+            master_key: Arc::new(master_key),
+            generation: 0,
+            refreshed_at: Instant::now(),
+        }
+    }
+}
+
+/// Work a password change will have to do, from
+/// [`VaultSession::change_password_plan`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangePasswordPlan {
+    /// Re-wrap the master key under the new password. No file is touched.
+    Rewrap,
+    /// The vault uses the legacy key model, where the master key is the
+    /// password's Argon2id output, so every file must be re-encrypted.
+    Reencrypt {
+        /// Number of files to re-encrypt.
+        files: usize,
+        /// Plaintext bytes across those files.
+        bytes: u64,
+    },
+}
+
 /// Active vault session.
 ///
 /// Holds the master key and provides access to vault operations.
@@ -57,14 +124,60 @@ pub struct VaultSession {
     handle: SessionHandle,
     /// Vault configuration.
     config: VaultConfig,
-    /// Master key (zeroized on drop).
-    master_key: Option<MasterKey>,
+    /// Working keys; `None` while locked (zeroized on drop).
+    keys: std::sync::RwLock<Option<WorkingKeys>>,
+    /// Key hygiene limits.
+    policy: SessionPolicy,
+    /// Plaintext bytes read or written since the last unlock.
+    bytes_processed: AtomicU64,
     /// Storage provider.
     provider: Arc<dyn StorageProvider>,
+    /// Providers of the config's attach points, by attach path.
+    attached: Mutex<HashMap<VaultPath, Arc<dyn StorageProvider>>>,
     /// Cached vault tree.
     tree: Arc<RwLock<VaultTree>>,
-    /// Session state.
-    state: SessionState,
+    /// Event channel for background work.
+    events: broadcast::Sender<VaultEvent>,
+    /// Blobs currently read or written by foreground operations, by
+    /// encrypted name, with a use count.
+    busy_blobs: Mutex<HashMap<String, usize>>,
+    /// Effective policy per directory, cleared whenever a directory
+    /// policy, the tree's shape, or the vault defaults change.
+    directory_policies: Mutex<HashMap<VaultPath, EffectivePolicy>>,
+    /// Local copies of read blobs, served when the provider is offline.
+    blob_cache: Mutex<Option<Arc<BlobCache>>>,
+    /// Memory budget shared by streaming reads, if any.
+    stream_budget: Mutex<Option<Arc<StreamBudget>>>,
+    /// Largest provider object a blob is written as, if limited.
+    part_size: Mutex<Option<u64>>,
+    /// Full-text index of file contents, while search is enabled.
+    #[cfg(feature = "search")]
+    search_index: Mutex<Option<Arc<SearchIndex>>>,
+    /// Mutations whose storage writes may not be reflected in the saved
+    /// tree yet.
+    journal: tokio::sync::Mutex<Journal>,
+    /// Whether access times changed since the tree was last saved.
+    access_times_dirty: AtomicBool,
+    /// Whether the stored tree is still in the single-blob format.
+    legacy_tree: AtomicBool,
+}
+
+/// Marks a blob as in use by a foreground operation until dropped.
+pub(crate) struct BlobInUse<'a> {
+    busy_blobs: &'a Mutex<HashMap<String, usize>>,
+    encrypted_name: String,
+}
+
+impl Drop for BlobInUse<'_> {
+    fn drop(&mut self) {
+        let mut busy = self.busy_blobs.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = busy.get_mut(&self.encrypted_name) {
+            *count -= 1;
+            if *count == 0 {
+                busy.remove(&self.encrypted_name);
+            }
+        }
+    }
 }
 
 impl VaultSession {
@@ -76,6 +189,7 @@ impl VaultSession {
     ///
     /// # Errors
     /// - Incompatible vault version
+    /// - `ConfigTampered` if the config does not match its MAC
     pub fn from_master_key(
         config: VaultConfig,
         master_key: MasterKey,
@@ -88,14 +202,28 @@ impl VaultSession {
                 config.version
             )));
         }
+        config.verify_mac(&master_key)?;
 
         Ok(Self {
             handle: SessionHandle::new(),
             config,
-            master_key: Some(master_key),
+            keys: std::sync::RwLock::new(Some(WorkingKeys::new(master_key))),
+            policy: SessionPolicy::default(),
+            bytes_processed: AtomicU64::new(0),
             provider,
+            attached: Mutex::new(HashMap::new()),
             tree: Arc::new(RwLock::new(tree)),
-            state: SessionState::Active,
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            busy_blobs: Mutex::new(HashMap::new()),
+            directory_policies: Mutex::new(HashMap::new()),
+            blob_cache: Mutex::new(None),
+            stream_budget: Mutex::new(None),
+            part_size: Mutex::new(None),
+            #[cfg(feature = "search")]
+            search_index: Mutex::new(None),
+            journal: tokio::sync::Mutex::new(Journal::default()),
+            access_times_dirty: AtomicBool::new(false),
+            legacy_tree: AtomicBool::new(false),
         })
     }
 
@@ -103,55 +231,77 @@ impl VaultSession {
     ///
     /// Derives the master key via Argon2id. Prefer `from_master_key` when the
     /// key has already been derived (e.g. in `open_vault`), to avoid a double KDF.
+    /// A single-salt config is given separate salts, which reach storage
+    /// with the next config save.
     pub fn unlock(
-        config: VaultConfig,
-        password: &[u8],
+        mut config: VaultConfig,
+        password: &Secret,
         provider: Arc<dyn StorageProvider>,
         tree: VaultTree,
     ) -> Result<Self> {
         let master_key = config
-            .verify_password(password)?
+            .unlock_and_split_salts(password, None)?
             .ok_or_else(|| Error::NotPermitted("Invalid password".to_string()))?;
 
         Self::from_master_key(config, master_key, provider, tree)
     }
 
+    /// Create a new vault session with a master key supplied by an external
+    /// key management system.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if the vault is unlocked with a password, or
+    ///   `master_key` is not its key
+    /// - Any error of [`from_master_key`](Self::from_master_key)
+    pub fn unlock_with_key(
+        mut config: VaultConfig,
+        master_key: MasterKey,
+        provider: Arc<dyn StorageProvider>,
+        tree: VaultTree,
+    ) -> Result<Self> {
+        if !config.verify_external_key(&master_key)? {
+            return Err(Error::NotPermitted("Invalid key".to_string()));
+        }
+        config.split_salts(&master_key, None, &master_key)?;
+
+        Self::from_master_key(config, master_key, provider, tree)
+    }
+
     /// Load and decrypt the vault tree index from storage.
     pub async fn load_and_decrypt_tree(
         provider: &Arc<dyn StorageProvider>,
         master_key: &MasterKey,
     ) -> Result<VaultTree> {
+        Ok(Self::load_tree(provider, master_key).await?.0)
+    }
+
+    /// Load and decrypt the tree index, and tell whether it is stored in
+    /// the single-blob format of earlier versions.
+    pub(crate) async fn load_tree(
+        provider: &Arc<dyn StorageProvider>,
+        master_key: &MasterKey,
+    ) -> Result<(VaultTree, bool)> {
         let tree_path = VaultPath::parse(META_DIRNAME)?.join(TREE_FILENAME)?;
 
         if !provider.exists(&tree_path).await? {
-            return Ok(VaultTree::new());
+            return Ok((VaultTree::new(), false));
         }
 
         let encrypted_bytes = provider.download(&tree_path).await?;
+        let legacy = !is_stream_format(&encrypted_bytes);
+        Ok((decrypt_tree(&encrypted_bytes, master_key)?, legacy))
+    }
 
-        let tree_key = master_key.derive_file_key(TREE_KEY_CONTEXT);
-        let tree_bytes = decrypt(tree_key.as_bytes(), &encrypted_bytes).map_err(|e| {
-            Error::Crypto(format!(
-                "Failed to decrypt tree index (wrong password or corrupted vault): {}",
-                e
-            ))
-        })?;
-
-        let mut tree_json = String::from_utf8(tree_bytes).map_err(|e| {
-            // Zeroize the bytes recovered from the conversion error.
-            use zeroize::Zeroize;
-            let mut bytes = e.into_bytes();
-            bytes.zeroize();
-            Error::Serialization("Invalid UTF-8 in tree data".to_string())
-        })?;
-
-        let tree = VaultTree::from_json(&tree_json);
-
-        // Zeroize the JSON string containing decrypted filenames/metadata.
-        use zeroize::Zeroize;
-        tree_json.zeroize();
+    /// Note whether the tree this session was opened with is stored in
+    /// the single-blob format. Cleared by the next tree save.
+    pub(crate) fn set_legacy_tree(&self, legacy: bool) {
+        self.legacy_tree.store(legacy, Ordering::SeqCst);
+    }
 
-        tree
+    /// Whether the stored tree is still in the single-blob format, as far
+    /// as this session knows.
+    pub(crate) fn has_legacy_tree(&self) -> bool {
+        self.legacy_tree.load(Ordering::SeqCst)
     }
 
     /// Get the session handle.
@@ -169,8 +319,20 @@ impl VaultSession {
         &self.config
     }
 
+    /// Arrangement of blobs in the data directory.
+    pub fn data_layout(&self) -> DataLayout {
+        self.config.data_layout
+    }
+
+    /// Limit on blob object names for new files, if any.
+    pub fn name_limit(&self) -> Option<NameLimit> {
+        self.config.name_limit
+    }
+
     /// Get mutable reference to the vault configuration.
     pub fn config_mut(&mut self) -> &mut VaultConfig {
+        // The caller may change the default policy.
+        self.invalidate_directory_policies();
         &mut self.config
     }
 
@@ -179,38 +341,521 @@ impl VaultSession {
         self.provider.clone()
     }
 
+    /// Resolve a provider for every attach point of the config from
+    /// `registry`.
+    ///
+    /// # Errors
+    /// - An attach point's provider is not registered or its configuration
+    ///   is invalid
+    pub fn connect_attachments(&self, registry: &ProviderRegistry) -> Result<()> {
+        for point in &self.config.attachments {
+            let provider = registry.resolve(&point.provider_type, point.provider_config.clone())?;
+            self.set_attached_provider(point.vault_path.clone(), Some(provider));
+        }
+        Ok(())
+    }
+
+    /// Use `provider` for the blobs below the attach point at `vault_path`.
+    /// `None` disconnects it.
+    pub fn set_attached_provider(
+        &self,
+        vault_path: VaultPath,
+        provider: Option<Arc<dyn StorageProvider>>,
+    ) {
+        let mut attached = self.attached.lock().unwrap_or_else(|e| e.into_inner());
+        match provider {
+            Some(provider) => attached.insert(vault_path, provider),
+            None => attached.remove(&vault_path),
+        };
+    }
+
+    /// The provider storing the blob of the file at `path`: its attach
+    /// point's, or the vault's own.
+    ///
+    /// # Errors
+    /// - `NotFound` if the attach point's provider is not connected
+    pub fn blob_provider(&self, path: &VaultPath) -> Result<Arc<dyn StorageProvider>> {
+        match self.config.attachment_for(path) {
+            Some(point) => self.attached_provider(point),
+            None => Ok(self.provider.clone()),
+        }
+    }
+
+    /// The vault's provider followed by every connected attach point's.
+    pub(crate) fn blob_providers(&self) -> Vec<Arc<dyn StorageProvider>> {
+        let attached = self.attached.lock().unwrap_or_else(|e| e.into_inner());
+        std::iter::once(self.provider.clone())
+            .chain(attached.values().cloned())
+            .collect()
+    }
+
+    /// The connected provider of `point`.
+    ///
+    /// # Errors
+    /// - `NotFound` if it is not connected
+    pub fn attached_provider(&self, point: &AttachPoint) -> Result<Arc<dyn StorageProvider>> {
+        self.attached
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&point.vault_path)
+            .cloned()
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "No {} provider connected at attach point {}",
+                    point.provider_type, point.vault_path
+                ))
+            })
+    }
+
     /// Get reference to the vault tree.
     pub fn tree(&self) -> &Arc<RwLock<VaultTree>> {
         &self.tree
     }
 
+    /// Apply key hygiene limits to this session.
+    pub fn with_policy(mut self, policy: SessionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Cache blobs read through this session in `cache`, and serve reads
+    /// from it while the provider is unreachable. `None` stops caching.
+    pub fn set_blob_cache(&self, cache: Option<BlobCache>) {
+        *self.blob_cache.lock().unwrap_or_else(|e| e.into_inner()) = cache.map(Arc::new);
+    }
+
+    /// The blob cache in use, if any.
+    pub fn blob_cache(&self) -> Option<Arc<BlobCache>> {
+        self.blob_cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Bound the buffers of streaming reads through this session by
+    /// `budget`. Pass the same budget to several sessions to share one
+    /// bound across them. `None` removes the bound.
+    pub fn set_stream_budget(&self, budget: Option<Arc<StreamBudget>>) {
+        *self.stream_budget.lock().unwrap_or_else(|e| e.into_inner()) = budget;
+    }
+
+    /// The streaming memory budget in use, if any.
+    pub fn stream_budget(&self) -> Option<Arc<StreamBudget>> {
+        self.stream_budget
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Split blobs written through this session into provider objects of
+    /// at most `part_size` bytes. `None` writes every blob as one object.
+    /// Already stored blobs keep their layout until rewritten.
+    pub fn set_part_size(&self, part_size: Option<u64>) {
+        *self.part_size.lock().unwrap_or_else(|e| e.into_inner()) =
+            part_size.filter(|&size| size > 0);
+    }
+
+    /// The part size in use, if any.
+    pub fn part_size(&self) -> Option<u64> {
+        *self.part_size.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get the key hygiene limits in effect.
+    pub fn policy(&self) -> &SessionPolicy {
+        &self.policy
+    }
+
+    /// Key the sync staging area encrypts its registry with.
+    pub fn staging_key(&self) -> Result<FileKey> {
+        Ok(self.master_key()?.derive_file_key(&KeyContext::SyncStaging))
+    }
+
     /// Get the master key, if session is active.
-    pub fn master_key(&self) -> Result<&MasterKey> {
-        match self.state {
-            SessionState::Active => self
-                .master_key
-                .as_ref()
-                .ok_or_else(|| Error::Vault("Master key not available".to_string())),
-            SessionState::Locked => Err(Error::NotPermitted("Session is locked".to_string())),
+    ///
+    /// The returned handle stays valid across a re-key; callers should hold
+    /// it only for the duration of one operation. Re-keys the session first
+    /// if the policy's `rekey_interval` has elapsed.
+    pub fn master_key(&self) -> Result<Arc<MasterKey>> {
+        {
+            let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+            let working = keys.as_ref().ok_or_else(Self::locked_error)?;
+            let due = self
+                .policy
+                .rekey_interval
+                .is_some_and(|interval| working.refreshed_at.elapsed() >= interval);
+            if !due {
+                return Ok(working.master_key.clone());
+            }
         }
+        self.rekey()?;
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        keys.as_ref()
+            .map(|working| working.master_key.clone())
+            .ok_or_else(Self::locked_error)
+    }
+
+    fn locked_error() -> Error {
+        Error::NotPermitted("Session is locked".to_string())
+    }
+
+    /// Move the working master key into fresh memory.
+    ///
+    /// The key is wrapped under a random one-time KEK and unwrapped into a
+    /// new allocation, which then replaces the old copy; no Argon2id run is
+    /// needed. The old copy is zeroized once the last operation holding it
+    /// finishes, so a later memory snapshot cannot recover it from there.
+    /// The key value itself is unchanged: it is what all vault data is
+    /// encrypted under.
+    ///
+    /// # Errors
+    /// - Session is locked
+    /// - Cryptographic operation fails
+    pub fn rekey(&self) -> Result<()> {
+        use axiomvault_crypto::recovery::{generate_master_key, unwrap_key, wrap_key};
+
+        let mut keys = self.keys.write().unwrap_or_else(|e| e.into_inner());
+        let working = keys.as_mut().ok_or_else(Self::locked_error)?;
+
+        let one_time_kek = generate_master_key();
+        let wrapped = wrap_key(&working.master_key, one_time_kek.as_bytes())?;
+        let fresh = unwrap_key(&wrapped, one_time_kek.as_bytes())?;
+        if fresh.as_bytes() != working.master_key.as_bytes() {
+            return Err(Error::Crypto(
+                "Master key changed while re-keying; keeping the current key".to_string(),
+            ));
+        }
+
+        working.master_key = Arc::new(fresh);
+        working.generation += 1;
+        working.refreshed_at = Instant::now();
+        Ok(())
+    }
+
+    /// Number of times the working key has been refreshed since unlock.
+    pub fn key_generation(&self) -> u64 {
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        keys.as_ref().map_or(0, |working| working.generation)
+    }
+
+    /// Plaintext bytes read or written since the last unlock.
+    pub fn bytes_processed(&self) -> u64 {
+        self.bytes_processed.load(Ordering::Relaxed)
+    }
+
+    /// Count plaintext bytes handled by a foreground operation.
+    ///
+    /// Once `max_bytes_per_session` is reached the session locks itself and
+    /// emits [`VaultEvent::ReauthRequired`]; the operation that crossed the
+    /// limit has already completed.
+    pub(crate) fn record_bytes(&self, bytes: u64) {
+        let total = self.bytes_processed.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let Some(limit) = self.policy.max_bytes_per_session else {
+            return;
+        };
+        if total >= limit {
+            let was_unlocked = self
+                .keys
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .take()
+                .is_some();
+            if was_unlocked {
+                self.emit(VaultEvent::ReauthRequired {
+                    bytes_processed: total,
+                });
+            }
+        }
+    }
+
+    /// Unlock the session again after it was locked, e.g. by
+    /// `max_bytes_per_session`. Resets the byte counter.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if the password is wrong
+    /// - `KeyfileRequired` / `KeyfileMismatch` for the keyfile
+    pub fn reauthenticate(&self, password: &Secret, keyfile: Option<&[u8]>) -> Result<()> {
+        let master_key = self
+            .config
+            .verify_password_with_keyfile(password, keyfile)?
+            .ok_or_else(|| Error::NotPermitted("Invalid password".to_string()))?;
+        self.install_keys(master_key);
+        Ok(())
+    }
+
+    fn install_keys(&self, master_key: MasterKey) {
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = Some(WorkingKeys::new(master_key));
+        self.bytes_processed.store(0, Ordering::Relaxed);
     }
 
     /// Get the current session state.
     pub fn state(&self) -> SessionState {
-        self.state
+        let keys = self.keys.read().unwrap_or_else(|e| e.into_inner());
+        if keys.is_some() {
+            SessionState::Active
+        } else {
+            SessionState::Locked
+        }
     }
 
     /// Check if session is active.
     pub fn is_active(&self) -> bool {
-        self.state == SessionState::Active
+        self.state() == SessionState::Active
+    }
+
+    /// Subscribe to events from background work on this session.
+    pub fn subscribe(&self) -> VaultEventReceiver {
+        self.events.subscribe()
+    }
+
+    /// Broadcast an event; dropped if there are no subscribers.
+    pub fn emit(&self, event: VaultEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Mark a blob as in use by a foreground operation.
+    ///
+    /// Call while holding the tree lock that resolved `encrypted_name`, so
+    /// maintenance cannot swap the blob between the lookup and the mark.
+    pub(crate) fn blob_in_use(&self, encrypted_name: &str) -> BlobInUse<'_> {
+        let mut busy = self.busy_blobs.lock().unwrap_or_else(|e| e.into_inner());
+        *busy.entry(encrypted_name.to_string()).or_insert(0) += 1;
+        BlobInUse {
+            busy_blobs: &self.busy_blobs,
+            encrypted_name: encrypted_name.to_string(),
+        }
+    }
+
+    /// Whether a foreground operation is using the blob.
+    pub(crate) fn is_blob_busy(&self, encrypted_name: &str) -> bool {
+        let busy = self.busy_blobs.lock().unwrap_or_else(|e| e.into_inner());
+        busy.contains_key(encrypted_name)
+    }
+
+    /// Effective policy of the directory at `dir`.
+    ///
+    /// Resolved from the parent's effective policy and memoized, so a
+    /// lookup costs one map access once the directory has been seen.
+    ///
+    /// # Errors
+    /// - Directory not found
+    pub(crate) fn effective_directory_policy(
+        &self,
+        tree: &VaultTree,
+        dir: &VaultPath,
+    ) -> Result<EffectivePolicy> {
+        let cached = self
+            .directory_policies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(dir)
+            .copied();
+        if let Some(effective) = cached {
+            return Ok(effective);
+        }
+
+        let node = tree.get_node(dir)?;
+        let inherited = match dir.parent() {
+            Some(parent) => self.effective_directory_policy(tree, &parent)?,
+            None => self.config.default_policy,
+        };
+        let effective = match &node.metadata.policy {
+            Some(policy) => inherited.apply(policy),
+            None => inherited,
+        };
+
+        self.directory_policies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(dir.clone(), effective);
+        Ok(effective)
+    }
+
+    /// Forget all memoized directory policies.
+    pub(crate) fn invalidate_directory_policies(&self) {
+        self.directory_policies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Forget memoized policies of `dir` and every directory below it.
+    ///
+    /// Policies elsewhere do not inherit from the subtree, so they stay.
+    pub(crate) fn invalidate_directory_policies_below(&self, dir: &VaultPath) {
+        self.directory_policies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|cached, _| !cached.starts_with(dir));
+    }
+
+    /// Start re-encrypting outdated blobs in the background.
+    ///
+    /// The task upgrades files below `policy.target` at the configured rate,
+    /// saving the tree after each file so progress survives restarts, and
+    /// keeps retrying deferred files until everything is upgraded or the
+    /// handle is stopped. Must be called from within a tokio runtime.
+    pub fn start_maintenance(self: &Arc<Self>, policy: MaintenancePolicy) -> MaintenanceHandle {
+        maintenance::spawn(self.clone(), policy)
+    }
+
+    /// Run a single maintenance pass over all outdated blobs.
+    ///
+    /// Files in use by foreground operations are skipped, not retried.
+    pub async fn run_maintenance_pass(
+        &self,
+        policy: &MaintenancePolicy,
+    ) -> Result<MaintenanceReport> {
+        maintenance::run_pass(self, policy, None).await
+    }
+
+    /// Enable full-text search of file contents.
+    ///
+    /// Loads the stored index, or starts an empty one, and queues files
+    /// that changed since it was saved. Enabling again reloads it with the
+    /// new `config`. Queued files are indexed by
+    /// [`start_indexing`](Self::start_indexing) or
+    /// [`run_index_pass`](Self::run_index_pass).
+    #[cfg(feature = "search")]
+    pub async fn enable_search(&self, config: SearchConfig) -> Result<Arc<SearchIndex>> {
+        let index = Arc::new(search::load(self, config).await?);
+        *self.search_index.lock().unwrap_or_else(|e| e.into_inner()) = Some(index.clone());
+        Ok(index)
+    }
+
+    /// Disable full-text search and delete the stored index.
+    #[cfg(feature = "search")]
+    pub async fn disable_search(&self) -> Result<()> {
+        self.search_index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        search::purge(self.provider.as_ref()).await
+    }
+
+    /// The search index, if search is enabled.
+    #[cfg(feature = "search")]
+    pub fn search_index(&self) -> Option<Arc<SearchIndex>> {
+        self.search_index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Drop the index contents and queue every file to be indexed again.
+    /// Returns the number of files queued.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if search is not enabled
+    #[cfg(feature = "search")]
+    pub async fn reindex(&self) -> Result<usize> {
+        let index = self.require_search_index()?;
+        Ok(index.rebuild(&*self.tree.read().await))
+    }
+
+    /// Start indexing queued files in the background.
+    ///
+    /// The task indexes files as writes queue them, within the index's
+    /// CPU budget, until stopped, the session locks, or search is disabled.
+    /// Must be called from within a tokio runtime.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if search is not enabled
+    #[cfg(feature = "search")]
+    pub fn start_indexing(self: &Arc<Self>) -> Result<IndexerHandle> {
+        let index = self.require_search_index()?;
+        Ok(search::spawn(self.clone(), index))
+    }
+
+    /// Index every queued file once and save the index.
+    ///
+    /// # Errors
+    /// - `NotPermitted` if search is not enabled
+    #[cfg(feature = "search")]
+    pub async fn run_index_pass(&self) -> Result<IndexReport> {
+        let index = self.require_search_index()?;
+        search::run_pass(self, &index, None).await
+    }
+
+    #[cfg(feature = "search")]
+    pub(crate) fn require_search_index(&self) -> Result<Arc<SearchIndex>> {
+        self.search_index()
+            .ok_or_else(|| Error::NotPermitted("Content search is not enabled".to_string()))
+    }
+
+    /// Queue `path` for re-indexing after its content or existence changed.
+    #[cfg(feature = "search")]
+    pub(crate) fn content_changed(&self, path: &VaultPath) {
+        if let Some(index) = self.search_index() {
+            index.queue(path.clone());
+        }
+    }
+
+    /// Queue both sides of a rename for re-indexing.
+    #[cfg(feature = "search")]
+    pub(crate) fn content_moved(&self, from: &VaultPath, to: &VaultPath) {
+        if let Some(index) = self.search_index() {
+            index.queue_rename(from, to);
+        }
     }
 
     /// Lock the session, clearing all keys from memory.
-    pub fn lock(&mut self) {
-        if let Some(key) = self.master_key.take() {
-            drop(key);
+    ///
+    /// The in-memory search index holds plaintext terms, so it is dropped
+    /// as well; the stored copy stays for the next unlock.
+    pub fn lock(&self) {
+        let keys = self.keys.write().unwrap_or_else(|e| e.into_inner()).take();
+        drop(keys);
+        #[cfg(feature = "search")]
+        self.search_index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+    }
+
+    /// Close the session: persist what is still pending, then lock.
+    ///
+    /// Failed operations leave journal entries until a tree save covers
+    /// them; if any are waiting, or access times changed, the tree is saved
+    /// now, so the next open has nothing to recover. The search index is saved if it changed. Keys
+    /// are cleared even when a flush fails, and the journal on storage
+    /// still lets the next open reconcile what was not flushed. Closing a
+    /// locked session does nothing.
+    ///
+    /// # Errors
+    /// - Storage failure while flushing
+    pub async fn close(&self) -> Result<()> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        let result = self.flush().await;
+        self.lock();
+        result
+    }
+
+    async fn flush(&self) -> Result<()> {
+        if self.access_times_dirty.load(Ordering::SeqCst)
+            || !journal::settled(self).await.is_empty()
+        {
+            self.save_tree().await?;
+        }
+        #[cfg(feature = "search")]
+        if let Some(index) = self.search_index() {
+            search::save(self, &index).await?;
+        }
+        Ok(())
+    }
+
+    /// Report what [`change_password`](Self::change_password) will cost,
+    /// without changing anything.
+    pub async fn change_password_plan(&self) -> ChangePasswordPlan {
+        if !self.config.is_legacy_format() {
+            return ChangePasswordPlan::Rewrap;
+        }
+        let tree = self.tree.read().await;
+        ChangePasswordPlan::Reencrypt {
+            files: tree.count_files(),
+            bytes: tree.total_size(),
         }
-        self.state = SessionState::Locked;
     }
 
     /// Change the vault password.
@@ -222,16 +867,91 @@ impl VaultSession {
     ///
     /// # Errors
     /// - Session is locked
+    /// - `NotPermitted` if the vault uses an external key
     /// - Old password is incorrect
     /// - New password is empty
     /// - Cryptographic operation fails
     /// - Self-verification of the new wrapping fails (should never happen;
     ///   indicates a serious bug)
-    pub fn change_password(&mut self, old_password: &[u8], new_password: &[u8]) -> Result<()> {
+    pub fn change_password(&mut self, old_password: &Secret, new_password: &Secret) -> Result<()> {
+        self.change_password_with_keyfile(old_password, None, new_password, None)
+    }
+
+    /// Change the vault password and keyfile together.
+    ///
+    /// `old_keyfile` must match the current keyfile (if the vault has one).
+    /// `new_keyfile` becomes required from now on; pass `None` to drop the
+    /// keyfile requirement.
+    ///
+    /// # Errors
+    /// Same as [`change_password`](Self::change_password), plus
+    /// `KeyfileRequired` / `KeyfileMismatch` for the old keyfile.
+    pub fn change_password_with_keyfile(
+        &mut self,
+        old_password: &Secret,
+        old_keyfile: Option<&[u8]>,
+        new_password: &Secret,
+        new_keyfile: Option<&[u8]>,
+    ) -> Result<()> {
+        let kdf_params = self.config.kdf_params.clone();
+        self.rewrap(
+            old_password,
+            old_keyfile,
+            new_password,
+            new_keyfile,
+            kdf_params,
+        )
+    }
+
+    /// Change the Argon2id parameters while keeping the password.
+    ///
+    /// Re-derives the KEK with `new_params` and re-wraps the master key, so
+    /// no file is re-encrypted. A legacy vault is moved to the wrapped key
+    /// model on the way, keeping its current master key.
+    ///
+    /// # Errors
+    /// Same as [`change_password`](Self::change_password).
+    pub fn rekdf(&mut self, password: &Secret, new_params: KdfParams) -> Result<()> {
+        self.rekdf_with_keyfile(password, None, new_params)
+    }
+
+    /// Change the Argon2id parameters of a vault that may need a keyfile.
+    ///
+    /// The keyfile requirement is left as it is.
+    ///
+    /// # Errors
+    /// Same as [`change_password_with_keyfile`](Self::change_password_with_keyfile).
+    pub fn rekdf_with_keyfile(
+        &mut self,
+        password: &Secret,
+        keyfile: Option<&[u8]>,
+        new_params: KdfParams,
+    ) -> Result<()> {
+        self.rewrap(password, keyfile, password, keyfile, new_params)
+    }
+
+    /// Re-wrap the master key under a new password, keyfile and KDF
+    /// parameters after checking the current credentials.
+    fn rewrap(
+        &mut self,
+        old_password: &Secret,
+        old_keyfile: Option<&[u8]>,
+        new_password: &Secret,
+        new_keyfile: Option<&[u8]>,
+        kdf_params: KdfParams,
+    ) -> Result<()> {
         use axiomvault_crypto::recovery::{unwrap_key, wrap_key};
 
-        if self.state != SessionState::Active {
-            return Err(Error::NotPermitted("Session is locked".to_string()));
+        if !self.is_active() {
+            return Err(Self::locked_error());
+        }
+
+        if self.config.uses_external_key() {
+            return Err(Error::NotPermitted(
+                "This vault uses an externally supplied key; rotate it in the key management \
+                 system instead"
+                    .to_string(),
+            ));
         }
 
         if new_password.is_empty() {
@@ -242,16 +962,24 @@ impl VaultSession {
 
         // Retrieve the master key from the session. This is the stable,
         // randomly-generated key that all data is encrypted under.
-        let master_key = self.master_key()?.clone();
+        let master_key = self.master_key()?;
 
         // Verify the old password is correct before proceeding.
         self.config
-            .verify_password(old_password)?
+            .verify_password_with_keyfile(old_password, old_keyfile)?
             .ok_or_else(|| Error::NotPermitted("Invalid old password".to_string()))?;
 
         // Generate new salt and derive new password KEK.
-        let new_salt = axiomvault_crypto::Salt::generate();
-        let new_kek = derive_key(new_password, &new_salt, &self.config.kdf_params)?;
+        let new_kdf_salt = Salt::generate(SALT_LENGTH);
+        let new_verify_salt = Salt::generate(SALT_LENGTH);
+        let new_kek = derive_key_with_keyfile(
+            new_password.expose(),
+            new_keyfile,
+            &new_kdf_salt,
+            &kdf_params,
+        )?;
+        let keyfile_verification =
+            VaultConfig::keyfile_verification_for(new_keyfile, &new_verify_salt)?;
 
         // Re-wrap the master key with the new KEK.
         let new_wrapped = wrap_key(&master_key, new_kek.as_bytes())?;
@@ -268,15 +996,26 @@ impl VaultSession {
         }
 
         // Re-create password verification.
-        let verification_plaintext = b"AXIOMVAULT_KEY_VERIFICATION_V1";
-        let new_verification = encrypt(new_kek.as_bytes(), verification_plaintext)?;
+        let new_verification = VaultConfig::key_verification_for(
+            &self.config.id,
+            &new_kek,
+            &new_verify_salt,
+            &kdf_params,
+        );
 
-        self.config.salt = new_salt;
+        self.config.kdf_params = kdf_params;
+        self.config.kdf_salt = new_kdf_salt;
+        self.config.verify_salt = Some(new_verify_salt);
         self.config.key_verification = new_verification;
+        self.config.key_verification_algorithm = KeyVerificationAlgorithm::Blake2bTag;
         self.config.wrapped_master_key = Some(new_wrapped);
+        self.config.keyfile_required = new_keyfile.is_some();
+        self.config.keyfile_verification = keyfile_verification;
+        self.config.key_generation += 1;
         self.config.modified_at = chrono::Utc::now();
+        self.config.update_mac(&master_key)?;
 
-        // The master key in self.master_key is unchanged -- all existing
+        // The working master key is unchanged -- all existing
         // encrypted data remains decryptable without re-encryption.
 
         Ok(())
@@ -289,7 +1028,7 @@ impl VaultSession {
     pub fn reset_password_with_recovery(
         &mut self,
         recovery_key: &RecoveryKey,
-        new_password: &[u8],
+        new_password: &Secret,
     ) -> Result<()> {
         // Get the master key from recovery before resetting password.
         // This avoids a second Argon2id round after reset_password.
@@ -298,26 +1037,225 @@ impl VaultSession {
             .verify_recovery_key(recovery_key)?
             .ok_or_else(|| Error::NotPermitted("Invalid recovery key".to_string()))?;
 
-        self.config.reset_password(recovery_key, new_password)?;
-        self.master_key = Some(master_key);
-        self.state = SessionState::Active;
+        self.config
+            .reset_password(recovery_key, new_password.expose())?;
+        self.install_keys(master_key);
 
         Ok(())
     }
 
+    /// Switch the vault to a master key supplied by an external key
+    /// management system.
+    ///
+    /// The caller must hand the session's master key to that system, then
+    /// persist the config. See [`VaultConfig::convert_to_external_key`].
+    ///
+    /// # Errors
+    /// - Session is locked
+    /// - `InvalidInput` if the vault already uses an external key
+    pub fn convert_to_external_key(&mut self) -> Result<()> {
+        let master_key = self.master_key()?;
+        self.config.convert_to_external_key(&master_key)
+    }
+
+    /// Switch an external-key vault to password unlock, returning the
+    /// recovery words of its new recovery key.
+    ///
+    /// The caller must persist the config. See
+    /// [`VaultConfig::convert_to_password`].
+    ///
+    /// # Errors
+    /// - Session is locked
+    /// - `InvalidInput` if the vault already uses a password, or
+    ///   `new_password` is empty
+    pub fn convert_to_password(
+        &mut self,
+        new_password: &Secret,
+        new_keyfile: Option<&[u8]>,
+    ) -> Result<Zeroizing<String>> {
+        let master_key = self.master_key()?;
+        self.config
+            .convert_to_password(&master_key, new_password.expose(), new_keyfile)
+    }
+
+    /// Change the vault's display name, description, icon and color.
+    ///
+    /// The change is applied to the configuration in storage rather than
+    /// to this session's copy, so whatever another client saved meanwhile,
+    /// such as a new password, is kept. Only the configuration is written:
+    /// the vault ID, storage paths and keys stay as they are.
+    ///
+    /// # Errors
+    /// - Session is locked
+    /// - `InvalidInput` if `metadata` is invalid
+    /// - `Conflict` if another client changed the metadata since this
+    ///   session read it; the session then shows their version, and the
+    ///   update can be retried on top of it
+    /// - `ConfigTampered` if the stored config does not match its MAC
+    pub async fn update_metadata(&mut self, metadata: VaultMetadata) -> Result<()> {
+        let metadata = metadata.validated()?;
+        let master_key = self.master_key()?;
+        let config_path = VaultPath::parse(CONFIG_FILENAME)?;
+        let mut stored = VaultConfig::from_bytes(&self.provider.download(&config_path).await?)?;
+        if stored.id != self.config.id {
+            return Err(Error::Vault(format!(
+                "Stored configuration belongs to vault {}",
+                stored.id
+            )));
+        }
+        stored.verify_mac(&master_key)?;
+
+        if stored.metadata_revision != self.config.metadata_revision {
+            self.config.adopt_metadata(&stored, &master_key)?;
+            return Err(Error::Conflict(
+                "Vault details were changed by another client".to_string(),
+            ));
+        }
+
+        stored.set_metadata(metadata, &master_key)?;
+        self.provider
+            .upload(&config_path, stored.to_bytes()?)
+            .await?;
+        self.config.adopt_metadata(&stored, &master_key)
+    }
+
     /// Save the current tree state to storage (encrypted).
     pub async fn save_tree(&self) -> Result<()> {
-        let tree = self.tree.read().await;
-        let tree_json = tree.to_json()?;
+        // Failed operations finished before this snapshot, so it covers them.
+        let settled = journal::settled(self).await;
+        let access_times = self.access_times_dirty.swap(false, Ordering::SeqCst);
+        let result = self.upload_tree().await;
+        if result.is_err() && access_times {
+            self.access_times_dirty.store(true, Ordering::SeqCst);
+        }
+        result?;
+        journal::forget(self, &settled).await;
+        Ok(())
+    }
 
-        let tree_key = self.master_key()?.derive_file_key(TREE_KEY_CONTEXT);
-        let encrypted = encrypt(tree_key.as_bytes(), tree_json.as_bytes())
-            .map_err(|e| Error::Crypto(format!("Failed to encrypt tree index: {}", e)))?;
+    async fn upload_tree(&self) -> Result<()> {
+        let chunks = {
+            let tree = self.tree.read().await;
+            let master_key = self.master_key()?;
+            encrypt_tree(&tree, &master_key)?
+        };
 
         let tree_path = VaultPath::parse(META_DIRNAME)?.join(TREE_FILENAME)?;
-        self.provider.upload(&tree_path, encrypted).await?;
+        let stream = futures::stream::iter(chunks.into_iter().map(Ok));
+        self.provider
+            .upload_stream(&tree_path, Box::pin(stream))
+            .await?;
+        self.legacy_tree.store(false, Ordering::SeqCst);
         Ok(())
     }
+
+    /// Write the configuration to storage, sealed with a fresh MAC.
+    pub(crate) async fn upload_config(&self) -> Result<()> {
+        let config_path = VaultPath::parse(CONFIG_FILENAME)?;
+        let config_bytes = self.config.to_sealed_bytes(&*self.master_key()?)?;
+        self.provider.upload(&config_path, config_bytes).await?;
+        Ok(())
+    }
+
+    /// Note that the file at `path` was just read, if the vault tracks
+    /// access times. Only the in-memory tree changes; it is saved by the
+    /// next tree save, [`flush_access_times`](Self::flush_access_times), or
+    /// [`close`](Self::close).
+    pub(crate) async fn record_access(&self, path: &VaultPath) {
+        if !self.config.track_access_time {
+            return;
+        }
+        let mut tree = self.tree.write().await;
+        if let Ok(node) = tree.get_node_mut(path) {
+            node.metadata.accessed_at = Some(chrono::Utc::now());
+            self.access_times_dirty.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Save the tree if access times changed since it was last saved.
+    ///
+    /// Returns whether it was saved. Reads only batch their access times in
+    /// memory, so call this periodically, or use
+    /// [`start_access_time_flush`](Self::start_access_time_flush).
+    ///
+    /// # Errors
+    /// - Storage failure; the access times stay pending
+    pub async fn flush_access_times(&self) -> Result<bool> {
+        if !self.access_times_dirty.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        self.save_tree().await?;
+        Ok(true)
+    }
+
+    /// Flush access times every `interval` in the background, until
+    /// stopped or the session locks. Must be called from within a tokio
+    /// runtime.
+    pub fn start_access_time_flush(self: &Arc<Self>, interval: Duration) -> AccessTimeFlushHandle {
+        access_time::spawn(self.clone(), interval)
+    }
+
+    /// Finish or undo file operations an earlier session was interrupted
+    /// in, as recorded in the vault's journal, and save the tree.
+    ///
+    /// Called when a vault is opened. Returns the number of operations
+    /// reconciled.
+    ///
+    /// # Errors
+    /// - Session is locked
+    /// - The journal cannot be decrypted
+    /// - Storage failure
+    pub async fn recover_journal(&self) -> Result<usize> {
+        journal::recover(self).await
+    }
+
+    pub(crate) fn journal(&self) -> &tokio::sync::Mutex<Journal> {
+        &self.journal
+    }
+}
+
+/// Encrypt the tree index, serializing straight into the chunked cipher.
+///
+/// Returns the stream header followed by the encrypted chunks, ready for
+/// `upload_stream`. The plaintext JSON is never materialized as a whole.
+pub(crate) fn encrypt_tree(tree: &VaultTree, master_key: &MasterKey) -> Result<Vec<Vec<u8>>> {
+    let tree_key = master_key.derive_legacy_file_key(&KeyContext::Tree)?;
+    let mut writer = EncryptingWriter::new(tree_key.as_bytes())?;
+    tree.write_json(&mut writer)?;
+    writer
+        .finish()
+        .map_err(|e| Error::Crypto(format!("Failed to encrypt tree index: {}", e)))
+}
+
+/// Decrypt and deserialize the tree index.
+///
+/// Reads both the chunked stream format written by [`encrypt_tree`] and the
+/// single-blob format used by earlier versions.
+pub(crate) fn decrypt_tree(encrypted: &[u8], master_key: &MasterKey) -> Result<VaultTree> {
+    let tree_key = master_key.derive_legacy_file_key(&KeyContext::Tree)?;
+    let wrap = |e: Error| {
+        Error::Crypto(format!(
+            "Failed to decrypt tree index (wrong password or corrupted vault): {}",
+            e
+        ))
+    };
+
+    if is_stream_format(encrypted) {
+        let reader = DecryptingReader::new(tree_key.as_bytes(), encrypted).map_err(wrap)?;
+        return VaultTree::read_json(reader).map_err(|e| match e {
+            Error::Io(_) => wrap(e),
+            other => other,
+        });
+    }
+
+    let mut tree_bytes = decrypt(tree_key.as_bytes(), encrypted).map_err(wrap)?;
+    let tree = VaultTree::read_json(&tree_bytes[..]);
+
+    // Zeroize the bytes containing decrypted filenames/metadata.
+    use zeroize::Zeroize;
+    tree_bytes.zeroize();
+
+    tree
 }
 
 impl Drop for VaultSession {
@@ -347,8 +1285,13 @@ mod tests {
         let config = creation.config;
         let password = b"test-password";
 
-        let session =
-            VaultSession::unlock(config.clone(), password, provider, VaultTree::new()).unwrap();
+        let session = VaultSession::unlock(
+            config.clone(),
+            &Secret::from_slice(password),
+            provider,
+            VaultTree::new(),
+        )
+        .unwrap();
 
         (session, config)
     }
@@ -362,7 +1305,7 @@ mod tests {
 
     #[test]
     fn test_session_lock() {
-        let (mut session, _) = create_test_session();
+        let (session, _) = create_test_session();
         session.lock();
 
         assert!(!session.is_active());
@@ -379,7 +1322,12 @@ mod tests {
             VaultConfig::new(id, password, "memory", serde_json::Value::Null, params).unwrap();
 
         let provider = Arc::new(MemoryProvider::new());
-        let result = VaultSession::unlock(creation.config, b"wrong", provider, VaultTree::new());
+        let result = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(b"wrong"),
+            provider,
+            VaultTree::new(),
+        );
 
         assert!(result.is_err());
     }
@@ -390,24 +1338,67 @@ mod tests {
         let old_password = b"test-password";
         let new_password = b"new-password";
 
-        session.change_password(old_password, new_password).unwrap();
+        session
+            .change_password(
+                &Secret::from_slice(old_password),
+                &Secret::from_slice(new_password),
+            )
+            .unwrap();
 
         assert!(session
             .config()
-            .verify_password(new_password)
+            .verify_password(&Secret::from_slice(new_password))
             .unwrap()
             .is_some());
         assert!(session
             .config()
-            .verify_password(old_password)
+            .verify_password(&Secret::from_slice(old_password))
             .unwrap()
             .is_none());
     }
 
+    #[tokio::test]
+    async fn test_change_password_plan_rewraps_wrapped_vault() {
+        let (session, _) = create_test_session();
+        assert_eq!(
+            session.change_password_plan().await,
+            ChangePasswordPlan::Rewrap
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_password_plan_reencrypts_legacy_vault() {
+        let (creation, provider) = create_test_config();
+        let mut config = creation.config;
+        config.wrapped_master_key = None;
+        config.update_mac(&creation.master_key).unwrap();
+
+        let mut tree = VaultTree::new();
+        tree.create_directory(&VaultPath::parse("/docs").unwrap(), "enc-docs")
+            .unwrap();
+        tree.create_file(&VaultPath::parse("/docs/a.txt").unwrap(), "enc-a", 10)
+            .unwrap();
+        tree.create_file(&VaultPath::parse("/b.bin").unwrap(), "enc-b", 32)
+            .unwrap();
+
+        let session =
+            VaultSession::from_master_key(config, creation.master_key, provider, tree).unwrap();
+        assert_eq!(
+            session.change_password_plan().await,
+            ChangePasswordPlan::Reencrypt {
+                files: 2,
+                bytes: 42
+            }
+        );
+    }
+
     #[test]
     fn test_change_password_empty_rejected() {
         let (mut session, _) = create_test_session();
-        let result = session.change_password(b"test-password", b"");
+        let result = session.change_password(
+            &Secret::from_slice(b"test-password"),
+            &Secret::from_slice(b""),
+        );
         assert!(result.is_err());
     }
 
@@ -420,7 +1411,12 @@ mod tests {
         // Capture master key bytes before password change.
         let mk_before = session.master_key().unwrap().as_bytes().to_owned();
 
-        session.change_password(old_password, new_password).unwrap();
+        session
+            .change_password(
+                &Secret::from_slice(old_password),
+                &Secret::from_slice(new_password),
+            )
+            .unwrap();
 
         // Master key in the session must be identical.
         let mk_after = session.master_key().unwrap().as_bytes().to_owned();
@@ -429,7 +1425,7 @@ mod tests {
         // Unwrapping with the new password must yield the same master key.
         let mk_from_new = session
             .config()
-            .verify_password(new_password)
+            .verify_password(&Secret::from_slice(new_password))
             .unwrap()
             .expect("new password should verify");
         assert_eq!(
@@ -439,6 +1435,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_change_password_with_keyfile() {
+        let (mut session, _) = create_test_session();
+        let mk_before = session.master_key().unwrap().as_bytes().to_owned();
+
+        session
+            .change_password_with_keyfile(
+                &Secret::from_slice(b"test-password"),
+                None,
+                &Secret::from_slice(b"new-password"),
+                Some(b"kf1"),
+            )
+            .unwrap();
+        assert!(session.config().requires_keyfile());
+        assert!(matches!(
+            session
+                .config()
+                .verify_password(&Secret::from_slice(b"new-password")),
+            Err(Error::KeyfileRequired)
+        ));
+
+        // Rotating the keyfile needs the old one.
+        assert!(matches!(
+            session.change_password_with_keyfile(
+                &Secret::from_slice(b"new-password"),
+                Some(b"kf2"),
+                &Secret::from_slice(b"newer-password"),
+                Some(b"kf2"),
+            ),
+            Err(Error::KeyfileMismatch)
+        ));
+        session
+            .change_password_with_keyfile(
+                &Secret::from_slice(b"new-password"),
+                Some(b"kf1"),
+                &Secret::from_slice(b"newer-password"),
+                Some(b"kf2"),
+            )
+            .unwrap();
+
+        let mk_after = session
+            .config()
+            .verify_password_with_keyfile(&Secret::from_slice(b"newer-password"), Some(b"kf2"))
+            .unwrap()
+            .expect("new credentials should verify");
+        assert_eq!(mk_before, mk_after.as_bytes().to_owned());
+    }
+
     #[test]
     fn test_change_password_config_round_trip() {
         let (mut session, _) = create_test_session();
@@ -446,7 +1490,12 @@ mod tests {
         let new_password = b"new-password";
 
         let mk_before = session.master_key().unwrap().as_bytes().to_owned();
-        session.change_password(old_password, new_password).unwrap();
+        session
+            .change_password(
+                &Secret::from_slice(old_password),
+                &Secret::from_slice(new_password),
+            )
+            .unwrap();
 
         // Serialize and deserialize the config (simulates save_config + reopen).
         let json = session.config().to_json().unwrap();
@@ -455,13 +1504,16 @@ mod tests {
         // The restored config must accept the new password and yield
         // the same master key.
         let mk_restored = restored
-            .verify_password(new_password)
+            .verify_password(&Secret::from_slice(new_password))
             .unwrap()
             .expect("new password should work after config round-trip");
         assert_eq!(mk_before, mk_restored.as_bytes().to_owned());
 
         // The old password must be rejected.
-        assert!(restored.verify_password(old_password).unwrap().is_none());
+        assert!(restored
+            .verify_password(&Secret::from_slice(old_password))
+            .unwrap()
+            .is_none());
     }
 
     #[test]
@@ -470,13 +1522,21 @@ mod tests {
         let config = creation.config;
         let recovery_words = creation.recovery_words;
 
-        let mut session =
-            VaultSession::unlock(config, b"test-password", provider, VaultTree::new()).unwrap();
+        let mut session = VaultSession::unlock(
+            config,
+            &Secret::from_slice(b"test-password"),
+            provider,
+            VaultTree::new(),
+        )
+        .unwrap();
 
         let mk_before = session.master_key().unwrap().as_bytes().to_owned();
 
         session
-            .change_password(b"test-password", b"rotated")
+            .change_password(
+                &Secret::from_slice(b"test-password"),
+                &Secret::from_slice(b"rotated"),
+            )
             .unwrap();
 
         // Recovery key must still unwrap the same master key.
@@ -506,9 +1566,13 @@ mod tests {
             .await
             .unwrap();
 
-        let mut session =
-            VaultSession::unlock(config, b"test-password", provider.clone(), VaultTree::new())
-                .unwrap();
+        let mut session = VaultSession::unlock(
+            config,
+            &Secret::from_slice(b"test-password"),
+            provider.clone(),
+            VaultTree::new(),
+        )
+        .unwrap();
 
         let file_a = VaultPath::parse("/secret.txt").unwrap();
         let file_b = VaultPath::parse("/photo.bin").unwrap();
@@ -524,7 +1588,10 @@ mod tests {
 
         // Rotate the password.
         session
-            .change_password(b"test-password", b"rotated-pw")
+            .change_password(
+                &Secret::from_slice(b"test-password"),
+                &Secret::from_slice(b"rotated-pw"),
+            )
             .unwrap();
 
         // All previously encrypted files must still be readable in the
@@ -542,7 +1609,7 @@ mod tests {
 
         let config2 = VaultConfig::from_json(&config_json).unwrap();
         let mk2 = config2
-            .verify_password(b"rotated-pw")
+            .verify_password(&Secret::from_slice(b"rotated-pw"))
             .unwrap()
             .expect("new password must verify");
         let tree2 = VaultSession::load_and_decrypt_tree(&(provider.clone() as Arc<_>), &mk2)
@@ -562,4 +1629,438 @@ mod tests {
             "file B must be decryptable after reopen with new password"
         );
     }
+
+    #[tokio::test]
+    async fn test_rekdf_upgrades_params_and_keeps_password() {
+        use crate::operations::VaultOperations;
+        use axiomvault_crypto::KdfParams;
+
+        let id = VaultId::new("rekdf").unwrap();
+        let password = Secret::from_slice(b"test-password");
+        let creation = VaultConfig::new(
+            id,
+            password.expose(),
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::interactive(),
+        )
+        .unwrap();
+        let provider = Arc::new(MemoryProvider::new());
+        provider
+            .create_dir(&VaultPath::parse("/d").unwrap())
+            .await
+            .unwrap();
+        provider
+            .create_dir(&VaultPath::parse("/m").unwrap())
+            .await
+            .unwrap();
+
+        let mut session = VaultSession::from_master_key(
+            creation.config,
+            creation.master_key,
+            provider.clone(),
+            VaultTree::new(),
+        )
+        .unwrap();
+        let file = VaultPath::parse("/notes.txt").unwrap();
+        VaultOperations::new(&session)
+            .unwrap()
+            .create_file(&file, b"kept across rekdf")
+            .await
+            .unwrap();
+        let generation = session.config().key_generation;
+
+        assert!(session
+            .rekdf(&Secret::from_slice(b"wrong"), KdfParams::sensitive())
+            .is_err());
+        session.rekdf(&password, KdfParams::sensitive()).unwrap();
+        assert_eq!(session.config().kdf_params, KdfParams::sensitive());
+        assert_eq!(session.config().key_generation, generation + 1);
+
+        // Reopen from the saved config with the unchanged password.
+        let config = VaultConfig::from_json(&session.config().to_json().unwrap()).unwrap();
+        drop(session);
+        assert_eq!(config.kdf_params, KdfParams::sensitive());
+        let master_key = config
+            .verify_password(&password)
+            .unwrap()
+            .expect("password must still unlock the vault");
+        let tree = VaultSession::load_and_decrypt_tree(&(provider.clone() as Arc<_>), &master_key)
+            .await
+            .unwrap();
+        let reopened = VaultSession::from_master_key(config, master_key, provider, tree).unwrap();
+        assert_eq!(
+            VaultOperations::new(&reopened)
+                .unwrap()
+                .read_file(&file)
+                .await
+                .unwrap(),
+            b"kept across rekdf"
+        );
+    }
+
+    /// Writer that rejects any single write above `limit` bytes, so a
+    /// serializer that builds the whole document first cannot pass.
+    struct BoundedWriter {
+        limit: usize,
+        total: usize,
+    }
+
+    impl std::io::Write for BoundedWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if buf.len() > self.limit {
+                return Err(std::io::Error::other(format!(
+                    "write of {} bytes exceeds bound of {}",
+                    buf.len(),
+                    self.limit
+                )));
+            }
+            self.total += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn large_tree(dirs: usize, files_per_dir: usize) -> VaultTree {
+        let mut tree = VaultTree::new();
+        for d in 0..dirs {
+            let dir = VaultPath::parse(&format!("/dir-{}", d)).unwrap();
+            tree.create_directory(&dir, format!("enc-dir-{}", d))
+                .unwrap();
+            for f in 0..files_per_dir {
+                let file = dir.join(&format!("file-{}.txt", f)).unwrap();
+                tree.create_file(&file, format!("enc-{}-{}", d, f), (d * f) as u64)
+                    .unwrap();
+            }
+        }
+        tree
+    }
+
+    #[tokio::test]
+    async fn test_large_tree_streams_through_cipher() {
+        let tree = large_tree(40, 100);
+
+        let mut bounded = BoundedWriter {
+            limit: 1024,
+            total: 0,
+        };
+        tree.write_json(&mut bounded).unwrap();
+        assert!(bounded.total > 64 * bounded.limit);
+
+        let (creation, provider) = create_test_config();
+        provider
+            .create_dir(&VaultPath::parse("/m").unwrap())
+            .await
+            .unwrap();
+        let expected = serde_json::to_value(&tree).unwrap();
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(b"test-password"),
+            provider.clone(),
+            tree,
+        )
+        .unwrap();
+        session.save_tree().await.unwrap();
+
+        let tree_path = VaultPath::parse(META_DIRNAME)
+            .unwrap()
+            .join(TREE_FILENAME)
+            .unwrap();
+        let stored = provider.download(&tree_path).await.unwrap();
+        assert!(is_stream_format(&stored));
+        assert!(stored.len() > 2 * axiomvault_crypto::stream::DEFAULT_CHUNK_SIZE);
+
+        let provider: Arc<dyn StorageProvider> = provider;
+        let loaded = VaultSession::load_and_decrypt_tree(&provider, &session.master_key().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(loaded.count_files(), 4000);
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_load_legacy_single_blob_tree() {
+        let (session, _) = create_test_session();
+        let tree = large_tree(2, 3);
+        let tree_json = tree.to_json().unwrap();
+
+        let tree_key = session
+            .master_key()
+            .unwrap()
+            .derive_legacy_file_key(&KeyContext::Tree)
+            .unwrap();
+        let legacy = axiomvault_crypto::encrypt(tree_key.as_bytes(), tree_json.as_bytes()).unwrap();
+        assert!(!is_stream_format(&legacy));
+
+        let loaded = decrypt_tree(&legacy, &session.master_key().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&tree).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_tree_decrypt_with_wrong_key_is_crypto_error() {
+        let (session, _) = create_test_session();
+        let chunks = encrypt_tree(&large_tree(1, 1), &session.master_key().unwrap()).unwrap();
+
+        let other = MasterKey::from_bytes([7u8; axiomvault_crypto::keys::KEY_LENGTH]);
+        let result = decrypt_tree(&chunks.concat(), &other);
+        assert!(matches!(result, Err(Error::Crypto(_))));
+    }
+
+    async fn create_initialized_session(policy: SessionPolicy) -> Arc<VaultSession> {
+        let (creation, provider) = create_test_config();
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(b"test-password"),
+            provider,
+            VaultTree::new(),
+        )
+        .unwrap()
+        .with_policy(policy);
+        Arc::new(session)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_rekey_is_invisible_to_concurrent_operations() {
+        use crate::operations::VaultOperations;
+
+        let session = create_initialized_session(SessionPolicy::default()).await;
+        {
+            let ops = VaultOperations::new(&session).unwrap();
+            for i in 0..6 {
+                let path = VaultPath::parse(&format!("/f{}.txt", i)).unwrap();
+                ops.create_file(&path, format!("v0-{}", i).as_bytes())
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // A key handle taken before a swap keeps working and matches the new one.
+        let held = session.master_key().unwrap();
+        session.rekey().unwrap();
+        let current = session.master_key().unwrap();
+        assert!(!Arc::ptr_eq(&held, &current));
+        assert_eq!(held.as_bytes(), current.as_bytes());
+        drop((held, current));
+
+        let rekeyer = {
+            let session = session.clone();
+            tokio::spawn(async move {
+                for _ in 0..50 {
+                    session.rekey().unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        };
+
+        let mut workers = Vec::new();
+        for i in 0..6 {
+            let session = session.clone();
+            workers.push(tokio::spawn(async move {
+                let ops = VaultOperations::new(&session).unwrap();
+                let path = VaultPath::parse(&format!("/f{}.txt", i)).unwrap();
+                for round in 1..=10 {
+                    let content = format!("v{}-{}", round, i);
+                    ops.update_file(&path, content.as_bytes()).await.unwrap();
+                    assert_eq!(ops.read_file(&path).await.unwrap(), content.as_bytes());
+                }
+            }));
+        }
+
+        rekeyer.await.unwrap();
+        for worker in workers {
+            worker.await.unwrap();
+        }
+        assert_eq!(session.key_generation(), 51);
+
+        // Everything written across swaps decrypts from a reloaded tree.
+        let provider = session.provider();
+        let tree = VaultSession::load_and_decrypt_tree(&provider, &session.master_key().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(tree.count_files(), 6);
+        let ops = VaultOperations::new(&session).unwrap();
+        for i in 0..6 {
+            let path = VaultPath::parse(&format!("/f{}.txt", i)).unwrap();
+            assert_eq!(
+                ops.read_file(&path).await.unwrap(),
+                format!("v10-{}", i).as_bytes()
+            );
+        }
+    }
+
+    #[test]
+    fn test_rekey_interval_refreshes_on_access() {
+        let (session, _) = create_test_session();
+        let session = session.with_policy(SessionPolicy {
+            rekey_interval: Some(Duration::ZERO),
+            ..SessionPolicy::default()
+        });
+        let before = session.master_key().unwrap();
+        assert_eq!(session.key_generation(), 1);
+        let after = session.master_key().unwrap();
+        assert_eq!(session.key_generation(), 2);
+        assert_eq!(before.as_bytes(), after.as_bytes());
+
+        session.lock();
+        assert_eq!(session.key_generation(), 0);
+        assert!(matches!(session.rekey(), Err(Error::NotPermitted(_))));
+    }
+
+    #[tokio::test]
+    async fn test_byte_limit_requires_reauthentication() {
+        use crate::operations::VaultOperations;
+
+        let session = create_initialized_session(SessionPolicy {
+            max_bytes_per_session: Some(100),
+            ..SessionPolicy::default()
+        })
+        .await;
+        let mut events = session.subscribe();
+        let path = VaultPath::parse("/data.bin").unwrap();
+        let content = vec![7u8; 60];
+
+        {
+            let ops = VaultOperations::new(&session).unwrap();
+            ops.create_file(&path, &content).await.unwrap();
+            assert!(session.is_active());
+
+            // Crossing the limit completes the read, then locks.
+            assert_eq!(ops.read_file(&path).await.unwrap(), content);
+            assert_eq!(session.bytes_processed(), 120);
+            assert_eq!(session.state(), SessionState::Locked);
+            assert!(matches!(
+                ops.read_file(&path).await,
+                Err(Error::NotPermitted(_))
+            ));
+        }
+        assert_eq!(
+            events.try_recv().unwrap(),
+            VaultEvent::ReauthRequired {
+                bytes_processed: 120
+            }
+        );
+        assert!(VaultOperations::new(&session).is_err());
+
+        assert!(session
+            .reauthenticate(&Secret::from_slice(b"wrong-password"), None)
+            .is_err());
+        session
+            .reauthenticate(&Secret::from_slice(b"test-password"), None)
+            .unwrap();
+        assert!(session.is_active());
+        assert_eq!(session.bytes_processed(), 0);
+        let ops = VaultOperations::new(&session).unwrap();
+        assert_eq!(ops.read_file(&path).await.unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_metadata_update_conflicts_then_retries() {
+        let (creation, provider) = create_test_config();
+        let config = creation.config;
+        let unlock = |config: &VaultConfig| {
+            VaultSession::unlock(
+                config.clone(),
+                &Secret::from_slice(b"test-password"),
+                provider.clone(),
+                VaultTree::new(),
+            )
+            .unwrap()
+        };
+        let mut first = unlock(&config);
+        let mut second = unlock(&config);
+        let config_path = VaultPath::parse(CONFIG_FILENAME).unwrap();
+        provider
+            .upload(
+                &config_path,
+                config
+                    .to_sealed_bytes(&first.master_key().unwrap())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let named = |name: &str| VaultMetadata {
+            display_name: name.to_string(),
+            ..Default::default()
+        };
+        first.update_metadata(named("Work")).await.unwrap();
+        assert_eq!(first.config().display_name(), "Work");
+
+        // The second session read revision 0 and must not overwrite "Work".
+        let err = second.update_metadata(named("Home")).await.unwrap_err();
+        assert!(matches!(err, Error::Conflict(_)));
+        assert_eq!(second.config().display_name(), "Work");
+
+        second.update_metadata(named("Home")).await.unwrap();
+        let stored =
+            VaultConfig::from_bytes(&provider.download(&config_path).await.unwrap()).unwrap();
+        stored.verify_mac(&second.master_key().unwrap()).unwrap();
+        assert_eq!(stored.display_name(), "Home");
+        assert_eq!(stored.metadata_revision, 2);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_update_keeps_password_change_by_another_session() {
+        let (creation, provider) = create_test_config();
+        let config = creation.config;
+        let config_path = VaultPath::parse(CONFIG_FILENAME).unwrap();
+        let mut renamer = VaultSession::unlock(
+            config.clone(),
+            &Secret::from_slice(b"test-password"),
+            provider.clone(),
+            VaultTree::new(),
+        )
+        .unwrap();
+        let mut rotator = VaultSession::unlock(
+            config,
+            &Secret::from_slice(b"test-password"),
+            provider.clone(),
+            VaultTree::new(),
+        )
+        .unwrap();
+
+        rotator
+            .change_password(
+                &Secret::from_slice(b"test-password"),
+                &Secret::from_slice(b"rotated-pw"),
+            )
+            .unwrap();
+        let rotated = rotator
+            .config()
+            .to_sealed_bytes(&rotator.master_key().unwrap())
+            .unwrap();
+        provider.upload(&config_path, rotated).await.unwrap();
+
+        renamer
+            .update_metadata(VaultMetadata {
+                display_name: "Renamed".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let stored =
+            VaultConfig::from_bytes(&provider.download(&config_path).await.unwrap()).unwrap();
+        assert_eq!(stored.display_name(), "Renamed");
+        assert!(stored
+            .verify_password(&Secret::from_slice(b"rotated-pw"))
+            .unwrap()
+            .is_some());
+        assert!(stored
+            .verify_password(&Secret::from_slice(b"test-password"))
+            .unwrap()
+            .is_none());
+    }
 }
@@ -0,0 +1,134 @@
+//! Shared memory budget for streaming reads.
+//!
+//! A [`StreamBudget`] caps the bytes that concurrent
+//! [`VaultOperations::read_file_to_writer`](crate::VaultOperations::read_file_to_writer)
+//! calls hold in buffers at once. Each read reserves its buffer size before
+//! downloading and waits while the budget is exhausted. A read larger than
+//! the whole budget reserves all of it, so it runs alone instead of failing.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use axiomvault_common::{Error, Result};
+
+/// Granularity of reservations. Semaphore permits are counted in units of
+/// this size so budgets up to terabytes fit the `u32` permit count.
+const UNIT: usize = 1024;
+
+/// Bound on the total buffer memory of concurrent streaming reads.
+#[derive(Debug)]
+pub struct StreamBudget {
+    units: u32,
+    semaphore: Arc<Semaphore>,
+    outstanding: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// Reserved share of a [`StreamBudget`], returned to it on drop.
+#[derive(Debug)]
+pub struct StreamReservation {
+    budget: Arc<StreamBudget>,
+    bytes: usize,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl StreamBudget {
+    /// Budget of `limit` bytes, rounded up to a whole KiB.
+    pub fn new(limit: usize) -> Self {
+        let units = limit.div_ceil(UNIT).clamp(1, u32::MAX as usize) as u32;
+        Self {
+            units,
+            semaphore: Arc::new(Semaphore::new(units as usize)),
+            outstanding: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Size of the budget in bytes.
+    pub fn limit(&self) -> usize {
+        self.units as usize * UNIT
+    }
+
+    /// Bytes currently reserved.
+    pub fn outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::SeqCst)
+    }
+
+    /// Most bytes ever reserved at once.
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+
+    /// Reserve `bytes`, waiting until enough of the budget is free.
+    pub async fn reserve(self: &Arc<Self>, bytes: u64) -> Result<StreamReservation> {
+        let wanted = usize::try_from(bytes).unwrap_or(usize::MAX).div_ceil(UNIT);
+        let units = wanted.clamp(1, self.units as usize) as u32;
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_many_owned(units)
+            .await
+            .map_err(|_| Error::Cancelled("stream budget closed".to_string()))?;
+
+        let bytes = units as usize * UNIT;
+        let now = self.outstanding.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+        Ok(StreamReservation {
+            budget: self.clone(),
+            bytes,
+            _permit: permit,
+        })
+    }
+}
+
+impl StreamReservation {
+    /// Bytes held by this reservation.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Drop for StreamReservation {
+    fn drop(&mut self) {
+        self.budget
+            .outstanding
+            .fetch_sub(self.bytes, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reservations_wait_for_budget() {
+        let budget = Arc::new(StreamBudget::new(4 * UNIT));
+        let first = budget.reserve(3 * UNIT as u64).await.unwrap();
+        assert_eq!(budget.outstanding(), 3 * UNIT);
+
+        let waiting = {
+            let budget = budget.clone();
+            tokio::spawn(async move { budget.reserve(2 * UNIT as u64).await.unwrap() })
+        };
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        let second = waiting.await.unwrap();
+        assert_eq!(second.bytes(), 2 * UNIT);
+        assert_eq!(budget.peak(), 3 * UNIT);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_reservation_takes_whole_budget() {
+        let budget = Arc::new(StreamBudget::new(10_000));
+        assert_eq!(budget.limit(), 10 * UNIT);
+
+        let all = budget.reserve(1 << 30).await.unwrap();
+        assert_eq!(all.bytes(), budget.limit());
+        drop(all);
+        assert_eq!(budget.outstanding(), 0);
+    }
+}
@@ -0,0 +1,959 @@
+//! Vault templates.
+//!
+//! A [`VaultTemplate`] is a JSON manifest describing the structure a vault
+//! should start with: directories with optional policies, small seed files,
+//! directories left out of sync and vault-wide defaults. It can be applied
+//! when a vault is created ([`VaultManager::create_vault_from_template`]),
+//! applied to an existing vault ([`VaultOperations::apply_template`]) and
+//! generated from one ([`VaultOperations::export_template`]).
+//!
+//! Validation errors name the offending manifest entry, e.g.
+//! `files[2] (/docs/README.md): ...`, so a broken manifest can be fixed
+//! without guessing.
+//!
+//! [`VaultManager::create_vault_from_template`]: crate::VaultManager::create_vault_from_template
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::blob::{encrypt_blob, BlobFormat};
+use crate::config::VaultConfig;
+use crate::operations::{BlobName, VaultOperations};
+use crate::parts;
+use crate::policy::{DirectoryPolicy, EffectivePolicy, SyncPolicy};
+use crate::session::VaultSession;
+use crate::tree::{TreeNode, VaultTree};
+use axiomvault_common::{Error, Result, VaultPath};
+
+/// Largest seed file a template may carry.
+pub const MAX_SEED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Manifest describing the initial structure of a vault.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultTemplate {
+    /// Free-form description shown to whoever applies the template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Directories to create. Missing parents are created implicitly.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<TemplateDirectory>,
+    /// Seed files to create. Missing parents are created implicitly.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<TemplateFile>,
+    /// Directories excluded from sync. Each gets a `sync = exclude`
+    /// policy and is created if the template does not list it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Vault-wide policy for paths no directory policy overrides.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_policy: Option<EffectivePolicy>,
+    /// Default sync settings, kept as-is for the sync layer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync: Option<serde_json::Value>,
+}
+
+/// A directory in a [`VaultTemplate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateDirectory {
+    /// Vault path of the directory.
+    pub path: String,
+    /// Policy overrides for the directory.
+    #[serde(default, skip_serializing_if = "DirectoryPolicy::is_empty")]
+    pub policy: DirectoryPolicy,
+}
+
+/// A seed file in a [`VaultTemplate`]. Exactly one content field is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateFile {
+    /// Vault path of the file.
+    pub path: String,
+    /// Inline UTF-8 content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Inline binary content, base64 encoded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_base64: Option<String>,
+    /// Local file holding the content. Relative paths are resolved against
+    /// the manifest's directory by [`VaultTemplate::load`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<PathBuf>,
+}
+
+impl TemplateFile {
+    /// A file entry with the given content, inline as text when it is
+    /// valid UTF-8 and as base64 otherwise.
+    pub fn inline(path: impl Into<String>, content: Vec<u8>) -> Self {
+        let (content, content_base64) = match String::from_utf8(content) {
+            Ok(text) => (Some(text), None),
+            Err(e) => (None, Some(STANDARD.encode(e.into_bytes()))),
+        };
+        Self {
+            path: path.into(),
+            content,
+            content_base64,
+            source: None,
+        }
+    }
+}
+
+/// What to do when a template entry collides with an existing path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateConflict {
+    /// Fail before anything is changed.
+    #[default]
+    Error,
+    /// Leave the existing path untouched. When its type differs from the
+    /// template entry, everything the template puts below it is skipped
+    /// as well.
+    Skip,
+    /// Replace file content and directory policies. A path whose type
+    /// differs from the template entry is still an error.
+    Overwrite,
+}
+
+/// Summary of applying a template.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateReport {
+    /// Directories created.
+    pub directories_created: usize,
+    /// Directory policies set or replaced.
+    pub policies_set: usize,
+    /// Files created.
+    pub files_created: usize,
+    /// Existing files whose content was replaced.
+    pub files_overwritten: usize,
+    /// Manifest entries left alone because of a conflict.
+    pub skipped: Vec<String>,
+}
+
+/// A validated template entry.
+#[derive(Debug)]
+enum Entry {
+    Directory {
+        label: String,
+        path: VaultPath,
+        policy: Option<DirectoryPolicy>,
+    },
+    File {
+        label: String,
+        path: VaultPath,
+        content: Vec<u8>,
+    },
+}
+
+impl Entry {
+    fn label(&self) -> &str {
+        match self {
+            Entry::Directory { label, .. } | Entry::File { label, .. } => label,
+        }
+    }
+
+    fn path(&self) -> &VaultPath {
+        match self {
+            Entry::Directory { path, .. } | Entry::File { path, .. } => path,
+        }
+    }
+}
+
+/// What applying an entry does to the vault.
+enum Step {
+    CreateDirectory,
+    SetPolicy,
+    CreateFile,
+    OverwriteFile,
+    Nothing,
+    Skip,
+}
+
+fn entry_error(label: &str, problem: impl std::fmt::Display) -> Error {
+    Error::InvalidInput(format!("{}: {}", label, problem))
+}
+
+fn parse_entry_path(label: &str, path: &str) -> Result<VaultPath> {
+    let parsed = VaultPath::parse(path).map_err(|e| entry_error(label, e))?;
+    if parsed.is_root() {
+        return Err(entry_error(
+            label,
+            "the vault root cannot be a template entry",
+        ));
+    }
+    Ok(parsed)
+}
+
+impl VaultTemplate {
+    /// Parse a manifest from JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::InvalidInput(format!("Invalid template manifest: {}", e)))
+    }
+
+    /// Serialize the manifest as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    /// Load a manifest from a local file and validate it.
+    ///
+    /// Relative `source` paths are made absolute against the manifest's
+    /// directory, so the loaded template can be applied from anywhere.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let mut template = Self::from_json(&json)?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        for file in &mut template.files {
+            if let Some(source) = file.source.as_mut().filter(|s| s.is_relative()) {
+                *source = base.join(&*source);
+            }
+        }
+        template.validate()?;
+        Ok(template)
+    }
+
+    /// Check every entry, reading referenced seed files.
+    pub fn validate(&self) -> Result<()> {
+        self.entries().map(|_| ())
+    }
+
+    /// Apply the vault-wide defaults to `config`.
+    ///
+    /// Returns whether `config` changed. A config that already has a
+    /// different non-default policy is a conflict.
+    pub fn apply_defaults(
+        &self,
+        config: &mut VaultConfig,
+        on_conflict: TemplateConflict,
+    ) -> Result<bool> {
+        let Some(policy) = self.default_policy else {
+            return Ok(false);
+        };
+        if config.default_policy == policy {
+            return Ok(false);
+        }
+        if !config.default_policy.is_default() {
+            match on_conflict {
+                TemplateConflict::Error => {
+                    return Err(Error::AlreadyExists(
+                        "default_policy: the vault already has a different default policy"
+                            .to_string(),
+                    ))
+                }
+                TemplateConflict::Skip => return Ok(false),
+                TemplateConflict::Overwrite => {}
+            }
+        }
+        config.default_policy = policy;
+        Ok(true)
+    }
+
+    /// Validated entries: directories parents-first, then files.
+    fn entries(&self) -> Result<Vec<Entry>> {
+        let mut directories: BTreeMap<Vec<String>, Entry> = BTreeMap::new();
+        let mut files = Vec::with_capacity(self.files.len());
+        let mut file_paths = HashSet::new();
+
+        for (i, dir) in self.directories.iter().enumerate() {
+            let label = format!("directories[{}] ({})", i, dir.path);
+            let path = parse_entry_path(&label, &dir.path)?;
+            if directories.contains_key(path.components()) {
+                return Err(entry_error(&label, "directory is listed twice"));
+            }
+            let policy = (!dir.policy.is_empty()).then_some(dir.policy);
+            directories.insert(
+                path.components().to_vec(),
+                Entry::Directory {
+                    label,
+                    path,
+                    policy,
+                },
+            );
+        }
+
+        for (i, ignored) in self.ignore.iter().enumerate() {
+            let label = format!("ignore[{}] ({})", i, ignored);
+            let path = parse_entry_path(&label, ignored)?;
+            match directories.get_mut(path.components()) {
+                Some(Entry::Directory { policy, .. }) => {
+                    let policy = policy.get_or_insert_with(DirectoryPolicy::default);
+                    if policy.sync == Some(SyncPolicy::Include) {
+                        return Err(entry_error(
+                            &label,
+                            "directory is ignored but its policy includes it in sync",
+                        ));
+                    }
+                    policy.sync = Some(SyncPolicy::Exclude);
+                }
+                _ => {
+                    directories.insert(
+                        path.components().to_vec(),
+                        Entry::Directory {
+                            label,
+                            path,
+                            policy: Some(DirectoryPolicy {
+                                sync: Some(SyncPolicy::Exclude),
+                                ..Default::default()
+                            }),
+                        },
+                    );
+                }
+            }
+        }
+
+        for (i, file) in self.files.iter().enumerate() {
+            let label = format!("files[{}] ({})", i, file.path);
+            let path = parse_entry_path(&label, &file.path)?;
+            if directories.contains_key(path.components()) {
+                return Err(entry_error(&label, "path is also listed as a directory"));
+            }
+            if !file_paths.insert(path.components().to_vec()) {
+                return Err(entry_error(&label, "file is listed twice"));
+            }
+            let content = Self::file_content(&label, file)?;
+            files.push(Entry::File {
+                label,
+                path,
+                content,
+            });
+        }
+
+        // Create missing parents implicitly.
+        let listed: Vec<(String, VaultPath)> = directories
+            .values()
+            .chain(&files)
+            .map(|entry| (entry.label().to_string(), entry.path().clone()))
+            .collect();
+        for (label, path) in listed {
+            let mut parent = path.parent();
+            while let Some(dir) = parent.filter(|p| !p.is_root()) {
+                if file_paths.contains(dir.components()) {
+                    return Err(entry_error(
+                        &label,
+                        format!("parent {} is listed as a file", dir),
+                    ));
+                }
+                parent = dir.parent();
+                directories
+                    .entry(dir.components().to_vec())
+                    .or_insert_with(|| Entry::Directory {
+                        label: format!("parent of {}", label),
+                        path: dir,
+                        policy: None,
+                    });
+            }
+        }
+
+        // Component order puts every parent before its children.
+        let mut entries: Vec<Entry> = directories.into_values().collect();
+        entries.extend(files);
+        Ok(entries)
+    }
+
+    fn file_content(label: &str, file: &TemplateFile) -> Result<Vec<u8>> {
+        let content = match (&file.content, &file.content_base64, &file.source) {
+            (Some(text), None, None) => text.as_bytes().to_vec(),
+            (None, Some(encoded), None) => STANDARD
+                .decode(encoded)
+                .map_err(|e| entry_error(label, format!("invalid content_base64: {}", e)))?,
+            (None, None, Some(source)) => std::fs::read(source).map_err(|e| {
+                entry_error(label, format!("cannot read {}: {}", source.display(), e))
+            })?,
+            _ => {
+                return Err(entry_error(
+                    label,
+                    "exactly one of content, content_base64 or source must be set",
+                ))
+            }
+        };
+        if content.len() as u64 > MAX_SEED_FILE_SIZE {
+            return Err(entry_error(
+                label,
+                format!(
+                    "seed file is {} bytes, the limit is {}",
+                    content.len(),
+                    MAX_SEED_FILE_SIZE
+                ),
+            ));
+        }
+        Ok(content)
+    }
+}
+
+/// How a template entry collides with an existing path.
+enum Collision {
+    /// Overwrite mode can replace what is there.
+    Replaceable(&'static str),
+    /// The existing path has the other type; only skipping helps.
+    TypeMismatch(&'static str),
+}
+
+/// Decide what each entry does. Fails on the first conflict that the
+/// conflict mode does not resolve, before anything is changed.
+fn plan(tree: &VaultTree, entries: &[Entry], on_conflict: TemplateConflict) -> Result<Vec<Step>> {
+    let mut mismatched: Vec<&VaultPath> = Vec::new();
+    let mut steps = Vec::with_capacity(entries.len());
+
+    for entry in entries {
+        let path = entry.path();
+        if mismatched.iter().any(|p| path.starts_with(p)) {
+            steps.push(Step::Skip);
+            continue;
+        }
+
+        let (step, collision) = match (entry, tree.get_node(path).ok()) {
+            (Entry::Directory { .. }, None) => (Step::CreateDirectory, None),
+            (Entry::File { .. }, None) => (Step::CreateFile, None),
+            (Entry::Directory { policy, .. }, Some(node)) if node.is_directory() => match policy {
+                Some(policy) if node.metadata.policy != Some(*policy) => (
+                    Step::SetPolicy,
+                    Some(Collision::Replaceable(
+                        "directory already has a different policy",
+                    )),
+                ),
+                _ => (Step::Nothing, None),
+            },
+            (Entry::File { .. }, Some(node)) if node.is_file() => (
+                Step::OverwriteFile,
+                Some(Collision::Replaceable("file already exists")),
+            ),
+            (Entry::Directory { .. }, Some(_)) => (
+                Step::Skip,
+                Some(Collision::TypeMismatch(
+                    "a file exists where the template has a directory",
+                )),
+            ),
+            (Entry::File { .. }, Some(_)) => (
+                Step::Skip,
+                Some(Collision::TypeMismatch(
+                    "a directory exists where the template has a file",
+                )),
+            ),
+        };
+
+        let step = match (collision, on_conflict) {
+            (None, _) | (Some(Collision::Replaceable(_)), TemplateConflict::Overwrite) => step,
+            (Some(Collision::Replaceable(_)), TemplateConflict::Skip) => Step::Skip,
+            (Some(Collision::TypeMismatch(_)), TemplateConflict::Skip) => {
+                mismatched.push(path);
+                Step::Skip
+            }
+            (Some(Collision::Replaceable(problem) | Collision::TypeMismatch(problem)), _) => {
+                return Err(Error::AlreadyExists(format!(
+                    "{}: {}",
+                    entry.label(),
+                    problem
+                )))
+            }
+        };
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+pub(crate) async fn apply_template(
+    ops: &VaultOperations<'_>,
+    session: &VaultSession,
+    template: &VaultTemplate,
+    on_conflict: TemplateConflict,
+) -> Result<TemplateReport> {
+    let entries = template.entries()?;
+    let steps = {
+        let tree = session.tree().read().await;
+        plan(&tree, &entries, on_conflict)?
+    };
+
+    let mut report = TemplateReport::default();
+    let result = apply_steps(ops, session, &entries, &steps, &mut report).await;
+
+    // One tree save covers every entry applied, including those before a
+    // failed upload.
+    session.invalidate_directory_policies();
+    session.save_tree().await?;
+    result?;
+
+    info!(
+        directories = report.directories_created,
+        files = report.files_created,
+        overwritten = report.files_overwritten,
+        skipped = report.skipped.len(),
+        "Template applied"
+    );
+    Ok(report)
+}
+
+async fn apply_steps(
+    ops: &VaultOperations<'_>,
+    session: &VaultSession,
+    entries: &[Entry],
+    steps: &[Step],
+    report: &mut TemplateReport,
+) -> Result<()> {
+    let part_size = session.part_size();
+    let layout = session.data_layout();
+
+    for (entry, step) in entries.iter().zip(steps) {
+        debug!(entry = entry.label(), "Applying template entry");
+        match (entry, step) {
+            (_, Step::Nothing) => {}
+            (_, Step::Skip) => report.skipped.push(entry.label().to_string()),
+            (Entry::Directory { path, policy, .. }, Step::CreateDirectory) => {
+                let name = path.name().unwrap_or_default();
+                let encrypted_name = ops.encrypt_name(name)?;
+                let mut tree = session.tree().write().await;
+                tree.create_directory(path, &encrypted_name)?;
+                tree.get_node_mut(path)?.metadata.policy = *policy;
+                report.directories_created += 1;
+                report.policies_set += usize::from(policy.is_some());
+            }
+            (Entry::Directory { path, policy, .. }, Step::SetPolicy) => {
+                let mut tree = session.tree().write().await;
+                tree.get_node_mut(path)?.metadata.policy = *policy;
+                report.policies_set += 1;
+            }
+            (Entry::File { path, content, .. }, Step::CreateFile) => {
+                let name = path.name().unwrap_or_default();
+                let BlobName {
+                    key: encrypted_name,
+                    long_name,
+                } = ops.blob_name(name)?;
+                let suite = session.config().write_suite()?;
+                let master_key = session.master_key()?;
+                let blob = encrypt_blob(
+                    BlobFormat::LATEST,
+                    suite,
+                    &master_key,
+                    &encrypted_name,
+                    content,
+                )?;
+                let provider = session.blob_provider(path)?;
+                let uploaded =
+                    parts::upload(provider.as_ref(), layout, &encrypted_name, blob, part_size)
+                        .await?;
+                let mut tree = session.tree().write().await;
+                tree.create_file(path, &encrypted_name, content.len() as u64)?;
+                let metadata = &mut tree.get_node_mut(path)?.metadata;
+                metadata.parts = uploaded.parts;
+                metadata.manifest = Some(uploaded.manifest);
+                metadata.long_encrypted_name = long_name;
+                session.record_bytes(content.len() as u64);
+                report.files_created += 1;
+                #[cfg(feature = "search")]
+                session.content_changed(path);
+            }
+            (Entry::File { path, content, .. }, Step::OverwriteFile) => {
+                let (encrypted_name, old_parts) = {
+                    let tree = session.tree().read().await;
+                    let metadata = &tree.get_node(path)?.metadata;
+                    (metadata.encrypted_name.clone(), metadata.parts.clone())
+                };
+                let suite = session.config().write_suite()?;
+                let master_key = session.master_key()?;
+                let blob = encrypt_blob(
+                    BlobFormat::LATEST,
+                    suite,
+                    &master_key,
+                    &encrypted_name,
+                    content,
+                )?;
+                let provider = session.blob_provider(path)?;
+                let uploaded =
+                    parts::upload(provider.as_ref(), layout, &encrypted_name, blob, part_size)
+                        .await?;
+                let new_parts = uploaded.parts;
+                {
+                    let mut tree = session.tree().write().await;
+                    let node = tree.get_node_mut(path)?;
+                    node.metadata.size = Some(content.len() as u64);
+                    node.metadata.modified_at = chrono::Utc::now();
+                    node.metadata.etag = Some(uuid::Uuid::new_v4().to_string());
+                    node.metadata.blob_format = BlobFormat::LATEST;
+                    node.metadata.parts = new_parts.clone();
+                    node.metadata.manifest = Some(uploaded.manifest);
+                }
+                parts::remove_stale(
+                    provider.as_ref(),
+                    layout,
+                    (&encrypted_name, &old_parts),
+                    (&encrypted_name, &new_parts),
+                )
+                .await;
+                session.record_bytes(content.len() as u64);
+                report.files_overwritten += 1;
+                #[cfg(feature = "search")]
+                session.content_changed(path);
+            }
+            _ => unreachable!("planned step does not match entry kind"),
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn export_template(
+    ops: &VaultOperations<'_>,
+    session: &VaultSession,
+    include_files: Option<u64>,
+) -> Result<VaultTemplate> {
+    let mut directories = Vec::new();
+    let mut files = Vec::new();
+    {
+        let tree = session.tree().read().await;
+        collect(
+            tree.root(),
+            &VaultPath::root(),
+            &mut directories,
+            &mut files,
+        )?;
+    }
+
+    let mut template = VaultTemplate {
+        directories,
+        ..Default::default()
+    };
+    if let Some(cap) = include_files {
+        for (path, size) in files {
+            if size > cap.min(MAX_SEED_FILE_SIZE) {
+                debug!(path = %path, size, "Leaving large file out of template");
+                continue;
+            }
+            let content = ops.read_file(&path).await?;
+            template
+                .files
+                .push(TemplateFile::inline(path.to_string(), content));
+        }
+    }
+    let default_policy = session.config().default_policy;
+    template.default_policy = (!default_policy.is_default()).then_some(default_policy);
+    Ok(template)
+}
+
+/// Collect directories and (path, size) of files below `node`, sorted by
+/// name so exports are stable.
+fn collect(
+    node: &TreeNode,
+    path: &VaultPath,
+    directories: &mut Vec<TemplateDirectory>,
+    files: &mut Vec<(VaultPath, u64)>,
+) -> Result<()> {
+    let mut children: Vec<&TreeNode> = node.children.values().collect();
+    children.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+    for child in children {
+        let child_path = path.join(&child.metadata.name)?;
+        if child.is_directory() {
+            directories.push(TemplateDirectory {
+                path: child_path.to_string(),
+                policy: child.metadata.policy.unwrap_or_default(),
+            });
+            collect(child, &child_path, directories, files)?;
+        } else {
+            files.push((child_path, child.metadata.size.unwrap_or(0)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::{VaultCreation, VaultManager};
+    use crate::policy::Versioning;
+    use axiomvault_common::Secret;
+    use axiomvault_common::VaultId;
+    use axiomvault_crypto::KdfParams;
+
+    const TEAM_TEMPLATE: &str = r##"{
+        "description": "Team vault",
+        "directories": [
+            { "path": "/projects" },
+            { "path": "/archive", "policy": { "versioning": { "keep": 5 }, "compression": true } }
+        ],
+        "files": [
+            { "path": "/README.md", "content": "# Team vault\n" },
+            { "path": "/projects/logo.bin", "content_base64": "AAEC/w==" }
+        ],
+        "ignore": ["/scratch"],
+        "default_policy": { "versioning": { "keep": 2 }, "compression": false, "sync": "include", "dedup": true }
+    }"##;
+
+    async fn create_from(template: &VaultTemplate) -> Result<VaultCreation> {
+        VaultManager::new()
+            .create_vault_from_template(
+                VaultId::new("templated").unwrap(),
+                &Secret::from_slice(b"password"),
+                None,
+                "memory",
+                serde_json::Value::Null,
+                KdfParams::moderate(),
+                template,
+            )
+            .await
+    }
+
+    fn path(p: &str) -> VaultPath {
+        VaultPath::parse(p).unwrap()
+    }
+
+    fn invalid_message(json: &str) -> String {
+        match VaultTemplate::from_json(json).unwrap().validate() {
+            Err(Error::InvalidInput(message)) => message,
+            other => panic!("expected InvalidInput, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_vault_from_template() {
+        let template = VaultTemplate::from_json(TEAM_TEMPLATE).unwrap();
+        let creation = create_from(&template).await.unwrap();
+        let session = &creation.session;
+        let ops = VaultOperations::new(session).unwrap();
+
+        assert_eq!(
+            ops.read_file(&path("/README.md")).await.unwrap(),
+            b"# Team vault\n"
+        );
+        assert_eq!(
+            ops.read_file(&path("/projects/logo.bin")).await.unwrap(),
+            [0, 1, 2, 255]
+        );
+        let archive = ops
+            .directory_policy(&path("/archive"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(archive.versioning, Some(Versioning::Keep(5)));
+        assert_eq!(archive.compression, Some(true));
+        assert_eq!(
+            ops.sync_excluded_paths().await.unwrap(),
+            vec![path("/scratch")]
+        );
+        assert_eq!(
+            session.config().default_policy.versioning,
+            Versioning::Keep(2)
+        );
+        assert!(session.config().default_policy.dedup);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_template_creates_nothing() {
+        let template = VaultTemplate::from_json(
+            r#"{ "files": [{ "path": "/a.txt", "content": "a", "content_base64": "YQ==" }] }"#,
+        )
+        .unwrap();
+        let err = create_from(&template).await.err().unwrap();
+        assert!(err.to_string().contains("files[0] (/a.txt)"), "{}", err);
+    }
+
+    #[test]
+    fn test_validation_errors_name_the_entry() {
+        let cases = [
+            (
+                r#"{ "directories": [{ "path": "/a" }, { "path": "/a" }] }"#,
+                "directories[1] (/a): directory is listed twice",
+            ),
+            (
+                r#"{ "directories": [{ "path": "/" }] }"#,
+                "directories[0] (/): the vault root cannot be a template entry",
+            ),
+            (
+                r#"{ "files": [{ "path": "/a.txt" }] }"#,
+                "files[0] (/a.txt): exactly one of",
+            ),
+            (
+                r#"{ "files": [{ "path": "/a", "content": "" }, { "path": "/a/b", "content": "" }] }"#,
+                "files[1] (/a/b): parent /a is listed as a file",
+            ),
+            (
+                r#"{ "directories": [{ "path": "/x" }], "files": [{ "path": "/x", "content": "" }] }"#,
+                "files[0] (/x): path is also listed as a directory",
+            ),
+            (
+                r#"{ "directories": [{ "path": "/s", "policy": { "sync": "include" } }], "ignore": ["/s"] }"#,
+                "ignore[0] (/s): directory is ignored",
+            ),
+            (
+                r#"{ "files": [{ "path": "/b.bin", "content_base64": "!!" }] }"#,
+                "files[0] (/b.bin): invalid content_base64",
+            ),
+            (
+                r#"{ "files": [{ "path": "/gone", "source": "/nonexistent/seed.txt" }] }"#,
+                "files[0] (/gone): cannot read /nonexistent/seed.txt",
+            ),
+        ];
+        for (json, expected) in cases {
+            let message = invalid_message(json);
+            assert!(message.starts_with(expected), "{} vs {}", message, expected);
+        }
+
+        let big = "x".repeat(MAX_SEED_FILE_SIZE as usize + 1);
+        let template = VaultTemplate {
+            files: vec![TemplateFile::inline("/big.txt", big.into_bytes())],
+            ..Default::default()
+        };
+        assert!(matches!(
+            template.validate(),
+            Err(Error::InvalidInput(m)) if m.starts_with("files[0] (/big.txt): seed file is")
+        ));
+
+        assert!(VaultTemplate::from_json(r#"{ "folders": [] }"#).is_err());
+    }
+
+    #[test]
+    fn test_load_resolves_sources_next_to_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("seeds")).unwrap();
+        std::fs::write(dir.path().join("seeds/welcome.txt"), b"hello").unwrap();
+        let manifest = dir.path().join("team.vaulttmpl");
+        std::fs::write(
+            &manifest,
+            r#"{ "files": [{ "path": "/welcome.txt", "source": "seeds/welcome.txt" }] }"#,
+        )
+        .unwrap();
+
+        let template = VaultTemplate::load(&manifest).unwrap();
+        assert_eq!(
+            template.files[0].source.as_deref(),
+            Some(dir.path().join("seeds/welcome.txt").as_path())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_round_trips_through_create() {
+        let template = VaultTemplate::from_json(TEAM_TEMPLATE).unwrap();
+        let original = create_from(&template).await.unwrap();
+        let ops = VaultOperations::new(&original.session).unwrap();
+        ops.create_file(&path("/projects/big.dat"), &[7; 64])
+            .await
+            .unwrap();
+
+        let structure_only = ops.export_template(None).await.unwrap();
+        assert!(structure_only.files.is_empty());
+
+        let exported = ops.export_template(Some(32)).await.unwrap();
+        let files: Vec<_> = exported.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(files, ["/README.md", "/projects/logo.bin"]);
+
+        let json = exported.to_json().unwrap();
+        let copy = create_from(&VaultTemplate::from_json(&json).unwrap())
+            .await
+            .unwrap();
+        let copy_ops = VaultOperations::new(&copy.session).unwrap();
+        assert_eq!(copy_ops.export_template(Some(32)).await.unwrap(), exported);
+        assert_eq!(
+            copy_ops.sync_excluded_paths().await.unwrap(),
+            vec![path("/scratch")]
+        );
+        assert_eq!(
+            copy.session.config().default_policy,
+            original.session.config().default_policy
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_conflicts() {
+        let creation = create_from(&VaultTemplate::default()).await.unwrap();
+        let ops = VaultOperations::new(&creation.session).unwrap();
+        ops.create_file(&path("/README.md"), b"ours").await.unwrap();
+        ops.create_directory(&path("/archive")).await.unwrap();
+        ops.create_file(&path("/projects"), b"a file")
+            .await
+            .unwrap();
+
+        let template = VaultTemplate::from_json(TEAM_TEMPLATE).unwrap();
+
+        // Error mode names the first conflict and changes nothing.
+        let err = ops
+            .apply_template(&template, TemplateConflict::Error)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, Error::AlreadyExists(m) if m.starts_with("directories[1] (/archive)")),
+            "{}",
+            err
+        );
+        assert!(!ops.exists(&path("/scratch")).await);
+        assert_eq!(ops.directory_policy(&path("/archive")).await.unwrap(), None);
+
+        // Skip keeps existing paths and everything below a type mismatch.
+        let report = ops
+            .apply_template(&template, TemplateConflict::Skip)
+            .await
+            .unwrap();
+        assert_eq!(report.directories_created, 1);
+        assert_eq!(report.files_created, 0);
+        assert_eq!(
+            report.skipped,
+            [
+                "directories[1] (/archive)",
+                "directories[0] (/projects)",
+                "files[0] (/README.md)",
+                "files[1] (/projects/logo.bin)",
+            ]
+        );
+        assert_eq!(ops.read_file(&path("/README.md")).await.unwrap(), b"ours");
+        assert_eq!(ops.directory_policy(&path("/archive")).await.unwrap(), None);
+        assert!(ops.exists(&path("/scratch")).await);
+
+        // Overwrite still refuses to turn a file into a directory.
+        let err = ops
+            .apply_template(&template, TemplateConflict::Overwrite)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("directories[0] (/projects)"),
+            "{}",
+            err
+        );
+
+        ops.delete_file(&path("/projects")).await.unwrap();
+        let report = ops
+            .apply_template(&template, TemplateConflict::Overwrite)
+            .await
+            .unwrap();
+        assert_eq!(report.files_overwritten, 1);
+        assert_eq!(report.files_created, 1);
+        assert_eq!(report.policies_set, 1);
+        assert!(report.skipped.is_empty());
+        assert_eq!(
+            ops.read_file(&path("/README.md")).await.unwrap(),
+            b"# Team vault\n"
+        );
+        assert!(ops
+            .directory_policy(&path("/archive"))
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_apply_defaults_conflicts() {
+        let template = VaultTemplate::from_json(TEAM_TEMPLATE).unwrap();
+        let creation = VaultConfig::new(
+            VaultId::new("defaults").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let mut config = creation.config;
+        config.default_policy.compression = true;
+
+        assert!(matches!(
+            template.apply_defaults(&mut config, TemplateConflict::Error),
+            Err(Error::AlreadyExists(m)) if m.starts_with("default_policy")
+        ));
+        assert!(!template
+            .apply_defaults(&mut config, TemplateConflict::Skip)
+            .unwrap());
+        assert!(config.default_policy.compression);
+        assert!(template
+            .apply_defaults(&mut config, TemplateConflict::Overwrite)
+            .unwrap());
+        assert_eq!(config.default_policy, template.default_policy.unwrap());
+    }
+}
@@ -6,9 +6,14 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::io::{Read, Write};
 use uuid::Uuid;
 
-use axiomvault_common::{Error, Result, VaultPath};
+use crate::blob::{BlobFormat, BlobFormatStats};
+use crate::parts::{object_names, BlobPart};
+use crate::policy::DirectoryPolicy;
+use crate::verify::ChunkManifest;
+use axiomvault_common::{escape_lossy, Error, Result, VaultPath};
 
 /// Type of tree node.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -24,6 +29,11 @@ pub struct NodeMetadata {
     pub name: String,
     /// Encrypted name used in storage.
     pub encrypted_name: String,
+    /// Full encrypted name of a file whose blob is stored under a hash of
+    /// it because the name exceeded the vault's
+    /// [`NameLimit`](crate::NameLimit); `encrypted_name` then holds the hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub long_encrypted_name: Option<String>,
     /// Node type.
     pub node_type: NodeType,
     /// File size (only for files).
@@ -32,14 +42,75 @@ pub struct NodeMetadata {
     pub created_at: DateTime<Utc>,
     /// Last modification time.
     pub modified_at: DateTime<Utc>,
+    /// Last read, if the vault tracks access times and the file has been
+    /// read since.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessed_at: Option<DateTime<Utc>>,
     /// ETag for conflict detection.
     pub etag: Option<String>,
+    /// Format of the encrypted blob (only meaningful for files).
+    /// Nodes written before formats were tracked deserialize as `V1`.
+    #[serde(default)]
+    pub blob_format: BlobFormat,
+    /// Policy overrides inherited by the subtree (only for directories).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<DirectoryPolicy>,
+    /// Provider objects the ciphertext is split across, in order. Empty
+    /// when it is stored as one object named `encrypted_name`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parts: Vec<BlobPart>,
+    /// Digests of the stored ciphertext for cheap verification (only for
+    /// files). `None` for blobs written before manifests were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<ChunkManifest>,
+    /// POSIX permission bits set through a mount, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    /// Owning user id set through a mount, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    /// Owning group id set through a mount, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+}
+
+/// POSIX ownership and mode of a node.
+///
+/// Unset fields fall back to defaults chosen by whoever presents the vault,
+/// typically the mounting user and a umask.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodePermissions {
+    /// Permission bits (`0o7777` mask).
+    pub mode: Option<u32>,
+    /// Owning user id.
+    pub uid: Option<u32>,
+    /// Owning group id.
+    pub gid: Option<u32>,
+}
+
+impl NodeMetadata {
+    /// When the node was last read or written: the later of its access and
+    /// modification times.
+    pub fn last_used(&self) -> DateTime<Utc> {
+        self.accessed_at
+            .map_or(self.modified_at, |at| at.max(self.modified_at))
+    }
+
+    /// Ownership and mode stored for this node.
+    pub fn permissions(&self) -> NodePermissions {
+        NodePermissions {
+            mode: self.mode,
+            uid: self.uid,
+            gid: self.gid,
+        }
+    }
 }
 
 /// A node in the vault tree.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TreeNode {
-    /// Unique node identifier.
+    /// Unique node identifier. Stays the same when the node is renamed or
+    /// moved, so it can be used to refer to the node from outside the tree.
     pub id: String,
     /// Node metadata.
     pub metadata: NodeMetadata,
@@ -63,11 +134,20 @@ impl TreeNode {
             metadata: NodeMetadata {
                 name: name.clone(),
                 encrypted_name: encrypted_name.into(),
+                long_encrypted_name: None,
                 node_type,
                 size,
                 created_at: now,
                 modified_at: now,
+                accessed_at: None,
                 etag: Some(Uuid::new_v4().to_string()),
+                blob_format: BlobFormat::LATEST,
+                policy: None,
+                parts: Vec::new(),
+                manifest: None,
+                mode: None,
+                uid: None,
+                gid: None,
             },
             children: HashMap::new(),
         }
@@ -113,7 +193,7 @@ impl TreeNode {
         if self.children.contains_key(&name) {
             return Err(Error::AlreadyExists(format!(
                 "Child '{}' already exists",
-                name
+                escape_lossy(&name)
             )));
         }
 
@@ -126,7 +206,7 @@ impl TreeNode {
     pub fn remove_child(&mut self, name: &str) -> Result<TreeNode> {
         self.children
             .remove(name)
-            .ok_or_else(|| Error::NotFound(format!("Child '{}' not found", name)))
+            .ok_or_else(|| Error::NotFound(format!("Child '{}' not found", escape_lossy(name))))
     }
 
     /// List children names.
@@ -168,9 +248,9 @@ impl VaultTree {
 
         let mut current = &self.root;
         for component in path.components() {
-            current = current
-                .get_child(component)
-                .ok_or_else(|| Error::NotFound(format!("Path not found: {}", path)))?;
+            current = current.get_child(component).ok_or_else(|| {
+                Error::NotFound(format!("Path not found: {}", path.display_lossy()))
+            })?;
         }
 
         Ok(current)
@@ -186,9 +266,9 @@ impl VaultTree {
         let mut current = &mut self.root;
 
         for component in &components {
-            current = current
-                .get_child_mut(component)
-                .ok_or_else(|| Error::NotFound(format!("Path not found: {}", path)))?;
+            current = current.get_child_mut(component).ok_or_else(|| {
+                Error::NotFound(format!("Path not found: {}", path.display_lossy()))
+            })?;
         }
 
         Ok(current)
@@ -256,6 +336,50 @@ impl VaultTree {
         parent.remove_child(name)
     }
 
+    /// Move a node to a new path, renaming it if the final component changes.
+    ///
+    /// The node keeps its id, encrypted name, timestamps, and children, so
+    /// [`find_by_id`](Self::find_by_id) finds it at `to` afterwards.
+    ///
+    /// # Errors
+    /// - Source not found
+    /// - Destination already exists
+    /// - Destination parent missing or not a directory
+    /// - Moving the root, or a directory into its own subtree
+    pub fn rename(&mut self, from: &VaultPath, to: &VaultPath) -> Result<()> {
+        if from.is_root() || to.is_root() {
+            return Err(Error::InvalidInput("Cannot rename root".to_string()));
+        }
+
+        self.get_node(from)?;
+        if from == to {
+            return Ok(());
+        }
+        if to.starts_with(from) {
+            return Err(Error::InvalidInput(
+                "Cannot move a directory into itself".to_string(),
+            ));
+        }
+        if self.exists(to) {
+            return Err(Error::AlreadyExists(format!(
+                "Path already exists: {}",
+                to.display_lossy()
+            )));
+        }
+        if !self.get_parent(to)?.is_directory() {
+            return Err(Error::InvalidInput(
+                "Destination parent is not a directory".to_string(),
+            ));
+        }
+
+        let new_name = to
+            .name()
+            .ok_or_else(|| Error::InvalidInput("Cannot rename root".to_string()))?;
+        let mut node = self.remove(from)?;
+        node.metadata.name = new_name.to_string();
+        self.get_parent_mut(to)?.add_child(node)
+    }
+
     /// List contents of a directory.
     pub fn list(&self, path: &VaultPath) -> Result<Vec<&TreeNode>> {
         let node = self.get_node(path)?;
@@ -266,6 +390,33 @@ impl VaultTree {
         Ok(node.children.values().collect())
     }
 
+    /// List up to `limit` entries of a directory, sorted by name, skipping
+    /// the first `offset`.
+    ///
+    /// Returns the entries and whether more follow.
+    ///
+    /// # Errors
+    /// - `InvalidInput` if `limit` is zero or `path` is not a directory
+    pub fn list_page(
+        &self,
+        path: &VaultPath,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<&TreeNode>, bool)> {
+        if limit == 0 {
+            return Err(Error::InvalidInput(
+                "List page limit must be at least 1".to_string(),
+            ));
+        }
+        let mut entries = self.list(path)?;
+        entries.sort_unstable_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+        let has_more = entries.len() > offset.saturating_add(limit);
+        Ok((
+            entries.into_iter().skip(offset).take(limit).collect(),
+            has_more,
+        ))
+    }
+
     /// Serialize tree to JSON.
     pub fn to_json(&self) -> Result<String> {
         serde_json::to_string_pretty(self).map_err(|e| Error::Serialization(e.to_string()))
@@ -276,6 +427,19 @@ impl VaultTree {
         serde_json::from_str(json).map_err(|e| Error::Serialization(e.to_string()))
     }
 
+    /// Serialize tree as compact JSON into `writer`.
+    ///
+    /// Unlike [`to_json`](Self::to_json) the document is never held in
+    /// memory as a whole; it is emitted piecewise as the tree is walked.
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer(writer, self).map_err(json_error)
+    }
+
+    /// Deserialize tree from a JSON reader.
+    pub fn read_json<R: Read>(reader: R) -> Result<Self> {
+        serde_json::from_reader(reader).map_err(json_error)
+    }
+
     /// Count the total number of files in the tree.
     pub fn count_files(&self) -> usize {
         Self::count_files_recursive(&self.root)
@@ -311,6 +475,123 @@ impl VaultTree {
         }
         size
     }
+
+    /// Paths of all files whose blob format is older than `target`.
+    pub fn files_below_format(&self, target: BlobFormat) -> Vec<VaultPath> {
+        self.files_where(|node| node.metadata.blob_format < target)
+    }
+
+    /// Paths of all files modified strictly after `cutoff`, sorted by path.
+    pub fn files_modified_since(&self, cutoff: DateTime<Utc>) -> Vec<VaultPath> {
+        let mut paths = self.files_where(|node| node.metadata.modified_at > cutoff);
+        paths.sort_by_key(|p| p.to_string());
+        paths
+    }
+
+    /// Paths of all files neither read nor written since `cutoff`, sorted by
+    /// path.
+    pub fn files_unused_since(&self, cutoff: DateTime<Utc>) -> Vec<VaultPath> {
+        let mut paths = self.files_where(|node| node.metadata.last_used() < cutoff);
+        paths.sort_by_key(|p| p.to_string());
+        paths
+    }
+
+    /// Paths of all files at or below `path`.
+    pub fn files_under(&self, path: &VaultPath) -> Vec<VaultPath> {
+        self.files_where(|_| true)
+            .into_iter()
+            .filter(|p| p.starts_with(path))
+            .collect()
+    }
+
+    /// Path of the file stored under `encrypted_name`, if any.
+    pub fn find_file(&self, encrypted_name: &str) -> Option<VaultPath> {
+        self.files_where(|node| node.metadata.encrypted_name == encrypted_name)
+            .into_iter()
+            .next()
+    }
+
+    /// Path and node of the file or directory with `id`.
+    pub fn find_by_id(&self, id: &str) -> Option<(VaultPath, &TreeNode)> {
+        Self::find_by_id_recursive(&self.root, VaultPath::root(), id)
+    }
+
+    fn find_by_id_recursive<'a>(
+        node: &'a TreeNode,
+        path: VaultPath,
+        id: &str,
+    ) -> Option<(VaultPath, &'a TreeNode)> {
+        if node.id == id {
+            return Some((path, node));
+        }
+        node.children.values().find_map(|child| {
+            let child_path = path.join(&child.metadata.name).ok()?;
+            Self::find_by_id_recursive(child, child_path, id)
+        })
+    }
+
+    /// Every provider object a file references, mapped to the file's path.
+    pub fn object_owners(&self) -> HashMap<String, VaultPath> {
+        let mut owners = HashMap::new();
+        for path in self.files_where(|_| true) {
+            if let Ok(node) = self.get_node(&path) {
+                for name in object_names(&node.metadata.encrypted_name, &node.metadata.parts) {
+                    owners.insert(name, path.clone());
+                }
+            }
+        }
+        owners
+    }
+
+    /// Paths of all files matching `predicate`.
+    fn files_where(&self, predicate: impl Fn(&TreeNode) -> bool) -> Vec<VaultPath> {
+        let mut paths = Vec::new();
+        Self::collect_files(&self.root, &VaultPath::root(), &predicate, &mut paths);
+        paths
+    }
+
+    fn collect_files(
+        node: &TreeNode,
+        path: &VaultPath,
+        predicate: &dyn Fn(&TreeNode) -> bool,
+        out: &mut Vec<VaultPath>,
+    ) {
+        for child in node.children.values() {
+            let Ok(child_path) = path.join(&child.metadata.name) else {
+                continue;
+            };
+            if child.is_file() {
+                if predicate(child) {
+                    out.push(child_path);
+                }
+            } else {
+                Self::collect_files(child, &child_path, predicate, out);
+            }
+        }
+    }
+
+    /// Count files and bytes, and how many are already at `target` or newer.
+    pub fn format_stats(&self, target: BlobFormat) -> BlobFormatStats {
+        let mut stats = BlobFormatStats::default();
+        Self::format_stats_recursive(&self.root, target, &mut stats);
+        stats
+    }
+
+    fn format_stats_recursive(node: &TreeNode, target: BlobFormat, stats: &mut BlobFormatStats) {
+        for child in node.children.values() {
+            if child.is_file() {
+                let size = child.metadata.size.unwrap_or(0);
+                stats.total_files += 1;
+                stats.total_bytes += size;
+                if child.metadata.blob_format >= target {
+                    stats.upgraded_files += 1;
+                    stats.upgraded_bytes += size;
+                }
+            } else {
+                Self::format_stats_recursive(child, target, stats);
+            }
+        }
+    }
 }
 
 impl Default for VaultTree {
@@ -319,6 +600,15 @@ impl Default for VaultTree {
     }
 }
 
+/// Map a serde_json error, keeping I/O failures from the underlying stream distinct.
+fn json_error(e: serde_json::Error) -> Error {
+    if e.is_io() {
+        Error::Io(e.into())
+    } else {
+        Error::Serialization(e.to_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -465,6 +755,133 @@ mod tests {
         assert!(!tree.exists(&path));
     }
 
+    #[test]
+    fn test_rename_moves_subtree() {
+        let mut tree = VaultTree::new();
+        tree.create_directory(&VaultPath::parse("/a").unwrap(), "d1")
+            .unwrap();
+        tree.create_directory(&VaultPath::parse("/b").unwrap(), "d2")
+            .unwrap();
+        tree.create_file(&VaultPath::parse("/a/file.txt").unwrap(), "f1", 5)
+            .unwrap();
+        let id = tree
+            .get_node(&VaultPath::parse("/a").unwrap())
+            .unwrap()
+            .id
+            .clone();
+
+        tree.rename(
+            &VaultPath::parse("/a").unwrap(),
+            &VaultPath::parse("/b/c").unwrap(),
+        )
+        .unwrap();
+
+        assert!(!tree.exists(&VaultPath::parse("/a").unwrap()));
+        let moved = tree.get_node(&VaultPath::parse("/b/c").unwrap()).unwrap();
+        assert_eq!(moved.id, id);
+        assert_eq!(moved.metadata.name, "c");
+        let file = tree
+            .get_node(&VaultPath::parse("/b/c/file.txt").unwrap())
+            .unwrap();
+        assert_eq!(file.metadata.encrypted_name, "f1");
+    }
+
+    #[test]
+    fn test_find_by_id_follows_renames() {
+        let mut tree = VaultTree::new();
+        let file = VaultPath::parse("/a/file.txt").unwrap();
+        tree.create_directory(&VaultPath::parse("/a").unwrap(), "d1")
+            .unwrap();
+        tree.create_directory(&VaultPath::parse("/b").unwrap(), "d2")
+            .unwrap();
+        tree.create_file(&file, "f1", 5).unwrap();
+        let id = tree.get_node(&file).unwrap().id.clone();
+        let dir_id = tree
+            .get_node(&VaultPath::parse("/b").unwrap())
+            .unwrap()
+            .id
+            .clone();
+
+        let (path, node) = tree.find_by_id(&id).unwrap();
+        assert_eq!(path, file);
+        assert_eq!(node.metadata.encrypted_name, "f1");
+
+        let renamed = VaultPath::parse("/b/renamed.txt").unwrap();
+        tree.rename(&file, &renamed).unwrap();
+        let (path, node) = tree.find_by_id(&id).unwrap();
+        assert_eq!(path, renamed);
+        assert_eq!(node.id, id);
+
+        tree.rename(
+            &VaultPath::parse("/b").unwrap(),
+            &VaultPath::parse("/a/b").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            tree.find_by_id(&id).unwrap().0,
+            VaultPath::parse("/a/b/renamed.txt").unwrap()
+        );
+        assert_eq!(
+            tree.find_by_id(&dir_id).unwrap().0,
+            VaultPath::parse("/a/b").unwrap()
+        );
+        let root_id = tree.root().id.clone();
+        assert!(tree.find_by_id(&root_id).unwrap().0.is_root());
+        assert!(tree.find_by_id("missing").is_none());
+    }
+
+    #[test]
+    fn test_rename_rejects_invalid_targets() {
+        let mut tree = VaultTree::new();
+        let dir = VaultPath::parse("/dir").unwrap();
+        let file = VaultPath::parse("/file").unwrap();
+        tree.create_directory(&dir, "d").unwrap();
+        tree.create_file(&file, "f", 1).unwrap();
+
+        assert!(matches!(
+            tree.rename(&file, &dir),
+            Err(Error::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            tree.rename(&dir, &VaultPath::parse("/dir/inner").unwrap()),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            tree.rename(&dir, &VaultPath::parse("/file/inner").unwrap()),
+            Err(Error::InvalidInput(_))
+        ));
+        assert!(matches!(
+            tree.rename(&VaultPath::parse("/missing").unwrap(), &dir),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_control_characters_round_trip() {
+        let mut tree = VaultTree::new();
+        let dir = VaultPath::parse("/tab\there").unwrap();
+        let file = dir.join("line\nbreak").unwrap();
+        tree.create_directory(&dir, "d").unwrap();
+        tree.create_file(&file, "f", 1).unwrap();
+
+        let names: Vec<_> = tree
+            .list(&dir)
+            .unwrap()
+            .iter()
+            .map(|node| node.metadata.name.clone())
+            .collect();
+        assert_eq!(names, vec!["line\nbreak"]);
+
+        let renamed = dir.join("bell\x07").unwrap();
+        tree.rename(&file, &renamed).unwrap();
+        let restored = VaultTree::from_json(&tree.to_json().unwrap()).unwrap();
+        assert_eq!(restored.find_file("f"), Some(renamed.clone()));
+        assert_eq!(
+            restored.get_node(&renamed).unwrap().metadata.name,
+            "bell\x07"
+        );
+    }
+
     #[test]
     fn test_tree_serialization() {
         let mut tree = VaultTree::new();
@@ -478,4 +895,74 @@ mod tests {
 
         assert!(restored.exists(&VaultPath::parse("/dir/f").unwrap()));
     }
+
+    #[test]
+    fn test_blob_format_tracking() {
+        let mut tree = VaultTree::new();
+        tree.create_directory(&VaultPath::parse("/dir").unwrap(), "enc_dir")
+            .unwrap();
+        tree.create_file(&VaultPath::parse("/a.txt").unwrap(), "enc_a", 10)
+            .unwrap();
+        tree.create_file(&VaultPath::parse("/dir/b.txt").unwrap(), "enc_b", 30)
+            .unwrap();
+
+        let b = VaultPath::parse("/dir/b.txt").unwrap();
+        assert!(tree.files_below_format(BlobFormat::LATEST).is_empty());
+        tree.get_node_mut(&b).unwrap().metadata.blob_format = BlobFormat::V1;
+
+        assert_eq!(tree.files_below_format(BlobFormat::LATEST), vec![b]);
+        let stats = tree.format_stats(BlobFormat::LATEST);
+        assert_eq!(stats.total_files, 2);
+        assert_eq!(stats.total_bytes, 40);
+        assert_eq!(stats.upgraded_files, 1);
+        assert_eq!(stats.upgraded_bytes, 10);
+        assert_eq!(stats.percent_upgraded(), 50.0);
+    }
+
+    #[test]
+    fn test_files_modified_since() {
+        let mut tree = VaultTree::new();
+        let dir = VaultPath::parse("/dir").unwrap();
+        tree.create_directory(&dir, "enc_dir").unwrap();
+        for name in ["/a.txt", "/dir/b.txt", "/dir/c.txt"] {
+            tree.create_file(&VaultPath::parse(name).unwrap(), name, 1)
+                .unwrap();
+        }
+
+        let cutoff = Utc::now() + chrono::Duration::seconds(10);
+        assert!(tree.files_modified_since(cutoff).is_empty());
+
+        for name in ["/dir/c.txt", "/a.txt"] {
+            let path = VaultPath::parse(name).unwrap();
+            tree.get_node_mut(&path).unwrap().metadata.modified_at =
+                cutoff + chrono::Duration::seconds(1);
+        }
+        // Directory timestamps do not select anything on their own.
+        tree.get_node_mut(&dir).unwrap().metadata.modified_at =
+            cutoff + chrono::Duration::seconds(1);
+
+        assert_eq!(
+            tree.files_modified_since(cutoff),
+            vec![
+                VaultPath::parse("/a.txt").unwrap(),
+                VaultPath::parse("/dir/c.txt").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_untracked_blob_format_is_legacy() {
+        let mut tree = VaultTree::new();
+        tree.create_file(&VaultPath::parse("/a.txt").unwrap(), "enc_a", 1)
+            .unwrap();
+        let tracked = format!("\"blob_format\": {}", BlobFormat::LATEST.as_u32());
+        let json = tree.to_json().unwrap();
+        assert!(json.contains(&tracked));
+        let json = json.replace(&tracked, "\"x\": 0");
+        let restored = VaultTree::from_json(&json).unwrap();
+        let node = restored
+            .get_node(&VaultPath::parse("/a.txt").unwrap())
+            .unwrap();
+        assert_eq!(node.metadata.blob_format, BlobFormat::V1);
+    }
 }
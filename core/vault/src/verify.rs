@@ -0,0 +1,708 @@
+//! Integrity verification of file blobs.
+//!
+//! A full verification downloads every blob and decrypts it, which costs a
+//! whole vault of egress. To make routine audits cheaper, every blob
+//! written records a [`ChunkManifest`] in its tree node: the BLAKE3 digest
+//! of each [`MANIFEST_CHUNK_SIZE`] slice of the stored ciphertext, its total
+//! length, and the content hash the provider reported for each object.
+//! The digests cover ciphertext only and reveal nothing beyond the lengths
+//! the provider already sees.
+//!
+//! A fast verification ([`VerifyOptions::fast`]) checks each file in
+//! escalating steps:
+//!
+//! 1. Provider metadata: every object must have its recorded size, and its
+//!    content hash must match the one recorded at upload where the provider
+//!    supplies one.
+//! 2. A sample of [`VerifyOptions::sample_percent`] of the chunks is fetched
+//!    with ranged downloads and compared with the manifest.
+//! 3. Only when a cheaper check disagrees, or the file has no manifest, is
+//!    the blob downloaded and decrypted in full.
+//!
+//! The sample is drawn from a seeded generator, so a run can be repeated
+//! exactly; vary the seed between runs to cover different chunks. A file
+//! verified in full whose manifest is missing or stale gets a fresh one, so
+//! later fast runs stay cheap.
+
+use std::io;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::blob::{decrypt_blob_to_writer, BlobFormat};
+use crate::layout;
+use crate::parts::{self, BlobPart};
+use crate::session::VaultSession;
+use axiomvault_common::{Error, Result, VaultPath};
+use axiomvault_storage::{compare_content_hashes, HashComparison};
+
+/// Bytes of ciphertext covered by one manifest digest.
+pub const MANIFEST_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Digests of a stored blob's ciphertext, recorded when it is written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    /// Bytes per chunk; the last chunk may be shorter.
+    pub chunk_size: u64,
+    /// Total ciphertext length.
+    pub length: u64,
+    /// Unpadded URL-safe base64 BLAKE3 digest of each chunk, in order.
+    pub digests: Vec<String>,
+    /// Content hash the provider reported for each object of the blob when
+    /// it was uploaded, in object order. Empty when it reported none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub object_hashes: Vec<Option<String>>,
+}
+
+impl ChunkManifest {
+    /// Digest `ciphertext` in chunks of `chunk_size` bytes.
+    pub fn compute(ciphertext: &[u8], chunk_size: u64) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            chunk_size,
+            length: ciphertext.len() as u64,
+            digests: ciphertext.chunks(chunk_size as usize).map(digest).collect(),
+            object_hashes: Vec::new(),
+        }
+    }
+
+    /// Record the content hashes the provider reported on upload. Dropped
+    /// when it reported none.
+    pub(crate) fn with_object_hashes(mut self, hashes: Vec<Option<String>>) -> Self {
+        self.object_hashes = if hashes.iter().any(Option::is_some) {
+            hashes
+        } else {
+            Vec::new()
+        };
+        self
+    }
+
+    /// Number of chunks.
+    pub fn chunk_count(&self) -> usize {
+        self.digests.len()
+    }
+
+    /// Offset and length of chunk `index`.
+    fn chunk_range(&self, index: usize) -> (u64, u64) {
+        let offset = index as u64 * self.chunk_size;
+        (offset, self.chunk_size.min(self.length - offset))
+    }
+
+    /// Whether `bytes` are chunk `index` as recorded.
+    fn matches_chunk(&self, index: usize, bytes: &[u8]) -> bool {
+        self.digests.get(index).map(String::as_str) == Some(digest(bytes).as_str())
+    }
+
+    /// Index of the first chunk of `ciphertext` that differs, if any.
+    fn first_mismatch(&self, ciphertext: &[u8]) -> Option<usize> {
+        if ciphertext.len() as u64 != self.length {
+            return Some(0);
+        }
+        ciphertext
+            .chunks(self.chunk_size as usize)
+            .enumerate()
+            .position(|(index, chunk)| !self.matches_chunk(index, chunk))
+    }
+}
+
+fn digest(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(blake3::hash(bytes).as_bytes())
+}
+
+/// Options for [`VaultOperations::verify`](crate::VaultOperations::verify).
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    /// Check metadata and a sample of chunks instead of decrypting every
+    /// blob.
+    pub fast: bool,
+    /// Percentage of each file's chunks fetched in fast mode, 0 to 100.
+    /// At least one chunk is fetched unless this is 0.
+    pub sample_percent: u8,
+    /// Seed of the chunk sampler.
+    pub seed: u64,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        Self {
+            fast: false,
+            sample_percent: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// How thoroughly a file was checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyLevel {
+    /// Only object sizes and provider content hashes were compared.
+    Metadata,
+    /// Metadata and a sample of chunks were compared with the manifest.
+    Sampled,
+    /// The whole blob was downloaded and decrypted.
+    Full,
+}
+
+/// Result of verifying one file.
+#[derive(Debug, Clone)]
+pub struct FileVerification {
+    /// Path of the file.
+    pub path: VaultPath,
+    /// Deepest check that ran.
+    pub level: VerifyLevel,
+    /// Whether a cheaper check disagreed and the file was verified in full.
+    pub escalated: bool,
+    /// Ciphertext bytes downloaded for this file.
+    pub bytes_downloaded: u64,
+    /// Why the file failed verification, or `None` if it passed.
+    pub error: Option<String>,
+}
+
+impl FileVerification {
+    /// Whether the file passed.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Outcome of [`VaultOperations::verify`](crate::VaultOperations::verify).
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    /// Every file checked, in tree order.
+    pub files: Vec<FileVerification>,
+    /// Ciphertext bytes downloaded in total.
+    pub bytes_downloaded: u64,
+    /// Manifests recorded for files verified in full whose manifest was
+    /// missing or stale.
+    pub manifests_recorded: usize,
+}
+
+impl VerifyReport {
+    /// Files that failed verification.
+    pub fn failures(&self) -> impl Iterator<Item = &FileVerification> {
+        self.files.iter().filter(|file| !file.is_ok())
+    }
+
+    /// Whether every file passed.
+    pub fn is_ok(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+/// Indices of the chunks to fetch out of `chunk_count`, ascending.
+///
+/// Picks `ceil(chunk_count * percent / 100)` distinct chunks, at least one
+/// unless `percent` is 0, with a partial Fisher-Yates shuffle driven by
+/// `seed`. The same arguments always give the same sample.
+pub fn sample_chunks(chunk_count: usize, percent: u8, seed: u64) -> Vec<usize> {
+    let percent = u64::from(percent.min(100));
+    if chunk_count == 0 || percent == 0 {
+        return Vec::new();
+    }
+    let take = ((chunk_count as u64 * percent).div_ceil(100) as usize).clamp(1, chunk_count);
+    let mut indices: Vec<usize> = (0..chunk_count).collect();
+    let mut rng = SplitMix64(seed);
+    for i in 0..take {
+        let j = i + (rng.next() % (chunk_count - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    indices.truncate(take);
+    indices.sort_unstable();
+    indices
+}
+
+/// Sampler seed of the blob stored under `encrypted_name`, so files of one
+/// run are sampled independently.
+pub(crate) fn file_seed(seed: u64, encrypted_name: &str) -> u64 {
+    let hash = blake3::hash(encrypted_name.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.as_bytes()[..8]);
+    seed ^ u64::from_le_bytes(bytes)
+}
+
+/// SplitMix64; small, fast and good enough to spread samples.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Blob of a file as the tree recorded it.
+struct Snapshot {
+    encrypted_name: String,
+    format: BlobFormat,
+    parts: Vec<BlobPart>,
+    manifest: Option<ChunkManifest>,
+}
+
+/// Verify every file of the vault.
+pub(crate) async fn verify(
+    session: &VaultSession,
+    options: &VerifyOptions,
+) -> Result<VerifyReport> {
+    let files = session.tree().read().await.files_under(&VaultPath::root());
+    info!(
+        files = files.len(),
+        fast = options.fast,
+        "Verification started"
+    );
+
+    let mut report = VerifyReport::default();
+    for path in files {
+        let snapshot = {
+            let tree = session.tree().read().await;
+            let Ok(node) = tree.get_node(&path) else {
+                continue;
+            };
+            Snapshot {
+                encrypted_name: node.metadata.encrypted_name.clone(),
+                format: node.metadata.blob_format,
+                parts: node.metadata.parts.clone(),
+                manifest: node.metadata.manifest.clone(),
+            }
+        };
+        let (file, recorded) = verify_file(session, path, &snapshot, options).await?;
+        if let Some(manifest) = recorded {
+            if record_manifest(session, &file.path, &snapshot, manifest).await {
+                report.manifests_recorded += 1;
+            }
+        }
+        report.bytes_downloaded += file.bytes_downloaded;
+        report.files.push(file);
+    }
+
+    if report.manifests_recorded > 0 {
+        session.save_tree().await?;
+    }
+    info!(
+        files = report.files.len(),
+        failed = report.failures().count(),
+        bytes = report.bytes_downloaded,
+        "Verification finished"
+    );
+    Ok(report)
+}
+
+/// Verify one file, returning a manifest to record when a full check
+/// passed on a file whose manifest is missing or stale.
+async fn verify_file(
+    session: &VaultSession,
+    path: VaultPath,
+    snapshot: &Snapshot,
+    options: &VerifyOptions,
+) -> Result<(FileVerification, Option<ChunkManifest>)> {
+    let mut file = FileVerification {
+        path,
+        level: VerifyLevel::Metadata,
+        escalated: false,
+        bytes_downloaded: 0,
+        error: None,
+    };
+
+    if options.fast {
+        if let Some(manifest) = &snapshot.manifest {
+            match check_cheaply(session, snapshot, manifest, options, &mut file).await {
+                Ok(true) => return Ok((file, None)),
+                Ok(false) => file.escalated = true,
+                Err(e) if is_verdict(&e) => {
+                    debug!(path = %file.path, "Cheap check failed: {}", e);
+                    file.escalated = true;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    file.level = VerifyLevel::Full;
+    match check_fully(session, snapshot, &mut file).await {
+        Ok(recorded) => Ok((file, recorded)),
+        Err(e) if is_verdict(&e) => {
+            warn!(path = %file.path, "File failed verification: {}", e);
+            file.error = Some(e.to_string());
+            Ok((file, None))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Errors that say the blob is damaged or gone, rather than that checking
+/// it failed.
+fn is_verdict(e: &Error) -> bool {
+    matches!(
+        e,
+        Error::NotFound(_) | Error::Integrity(_) | Error::Crypto(_) | Error::Unsupported(_)
+    )
+}
+
+/// Compare provider metadata, then a sample of chunks, with the manifest.
+/// `Ok(false)` means they disagree.
+async fn check_cheaply(
+    session: &VaultSession,
+    snapshot: &Snapshot,
+    manifest: &ChunkManifest,
+    options: &VerifyOptions,
+    file: &mut FileVerification,
+) -> Result<bool> {
+    let provider = session.blob_provider(&file.path)?;
+    let data_layout = session.data_layout();
+
+    let objects = parts::object_names(&snapshot.encrypted_name, &snapshot.parts);
+    let sizes: Vec<u64> = if snapshot.parts.is_empty() {
+        vec![manifest.length]
+    } else {
+        snapshot.parts.iter().map(|part| part.size).collect()
+    };
+    for (index, object) in objects.iter().enumerate() {
+        let metadata = layout::metadata(provider.as_ref(), data_layout, object).await?;
+        if metadata.size != Some(sizes[index]) {
+            debug!(object = %object, "Object size differs from the manifest");
+            return Ok(false);
+        }
+        let recorded = manifest.object_hashes.get(index).and_then(Option::as_deref);
+        if compare_content_hashes(recorded, metadata.content_hash.as_deref())
+            == HashComparison::Different
+        {
+            debug!(object = %object, "Provider content hash differs from the manifest");
+            return Ok(false);
+        }
+    }
+
+    let sample = sample_chunks(
+        manifest.chunk_count(),
+        options.sample_percent,
+        file_seed(options.seed, &snapshot.encrypted_name),
+    );
+    if !sample.is_empty() {
+        file.level = VerifyLevel::Sampled;
+    }
+    for index in sample {
+        let (offset, len) = manifest.chunk_range(index);
+        let bytes = parts::download_range(
+            provider.as_ref(),
+            data_layout,
+            &snapshot.encrypted_name,
+            &snapshot.parts,
+            offset,
+            len,
+        )
+        .await?;
+        file.bytes_downloaded += bytes.len() as u64;
+        if !manifest.matches_chunk(index, &bytes) {
+            debug!(chunk = index, "Sampled chunk differs from the manifest");
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Download and decrypt the whole blob. Returns a new manifest when the
+/// file has none or its manifest is stale.
+async fn check_fully(
+    session: &VaultSession,
+    snapshot: &Snapshot,
+    file: &mut FileVerification,
+) -> Result<Option<ChunkManifest>> {
+    let provider = session.blob_provider(&file.path)?;
+    let ciphertext = parts::download(
+        provider.as_ref(),
+        session.data_layout(),
+        &snapshot.encrypted_name,
+        &snapshot.parts,
+    )
+    .await?;
+    file.bytes_downloaded += ciphertext.len() as u64;
+
+    decrypt_blob_to_writer(
+        snapshot.format,
+        &*session.master_key()?,
+        &snapshot.encrypted_name,
+        &ciphertext,
+        &mut io::sink(),
+    )?;
+
+    // The blob authenticated, so a manifest that disagrees is stale.
+    match &snapshot.manifest {
+        Some(manifest) if manifest.first_mismatch(&ciphertext).is_none() => Ok(None),
+        _ => Ok(Some(ChunkManifest::compute(
+            &ciphertext,
+            MANIFEST_CHUNK_SIZE,
+        ))),
+    }
+}
+
+/// Store `manifest` on the file at `path` unless its blob or manifest
+/// changed since `snapshot` was taken. Returns whether it was stored.
+async fn record_manifest(
+    session: &VaultSession,
+    path: &VaultPath,
+    snapshot: &Snapshot,
+    manifest: ChunkManifest,
+) -> bool {
+    let mut tree = session.tree().write().await;
+    match tree.get_node_mut(path) {
+        Ok(node)
+            if node.metadata.encrypted_name == snapshot.encrypted_name
+                && node.metadata.parts == snapshot.parts
+                && node.metadata.manifest == snapshot.manifest =>
+        {
+            node.metadata.manifest = Some(manifest);
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::VaultConfig;
+    use crate::operations::VaultOperations;
+    use crate::tree::VaultTree;
+    use axiomvault_common::{Secret, VaultId};
+    use axiomvault_crypto::KdfParams;
+    use axiomvault_storage::{MemoryProvider, StorageProvider};
+    use std::sync::Arc;
+
+    async fn create_session() -> (VaultSession, Arc<MemoryProvider>) {
+        let creation = VaultConfig::new(
+            VaultId::new("verify").unwrap(),
+            b"password",
+            "memory",
+            serde_json::Value::Null,
+            KdfParams::moderate(),
+        )
+        .unwrap();
+        let provider = Arc::new(MemoryProvider::new());
+        for dir in ["/d", "/m"] {
+            provider
+                .create_dir(&VaultPath::parse(dir).unwrap())
+                .await
+                .unwrap();
+        }
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(b"password"),
+            provider.clone(),
+            VaultTree::new(),
+        )
+        .unwrap();
+        (session, provider)
+    }
+
+    /// A file of a little over three and a half manifest chunks.
+    async fn create_large_file(session: &VaultSession, path: &VaultPath) -> Snapshot {
+        let content: Vec<u8> = (0..(7 * MANIFEST_CHUNK_SIZE / 2))
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let ops = VaultOperations::new(session).unwrap();
+        ops.create_file(path, &content).await.unwrap();
+        let tree = session.tree().read().await;
+        let node = tree.get_node(path).unwrap();
+        Snapshot {
+            encrypted_name: node.metadata.encrypted_name.clone(),
+            format: node.metadata.blob_format,
+            parts: node.metadata.parts.clone(),
+            manifest: node.metadata.manifest.clone(),
+        }
+    }
+
+    fn fast(sample_percent: u8, seed: u64) -> VerifyOptions {
+        VerifyOptions {
+            fast: true,
+            sample_percent,
+            seed,
+        }
+    }
+
+    #[test]
+    fn test_sample_chunks_is_deterministic_and_sized() {
+        assert_eq!(sample_chunks(40, 10, 7), sample_chunks(40, 10, 7));
+        assert_ne!(sample_chunks(40, 10, 7), sample_chunks(40, 10, 8));
+
+        assert_eq!(sample_chunks(40, 10, 1).len(), 4);
+        assert_eq!(sample_chunks(41, 10, 1).len(), 5);
+        assert_eq!(sample_chunks(3, 1, 1).len(), 1);
+        assert!(sample_chunks(3, 0, 1).is_empty());
+        assert!(sample_chunks(0, 50, 1).is_empty());
+        assert_eq!(sample_chunks(5, 100, 1), vec![0, 1, 2, 3, 4]);
+        assert_eq!(sample_chunks(5, 255, 1), vec![0, 1, 2, 3, 4]);
+
+        let sample = sample_chunks(1000, 30, 99);
+        assert!(sample.windows(2).all(|w| w[0] < w[1]));
+        assert!(sample.iter().all(|&i| i < 1000));
+    }
+
+    #[test]
+    fn test_single_corrupt_chunk_is_caught_at_the_sampling_rate() {
+        // With k of n chunks sampled, one bad chunk is caught with
+        // probability k/n.
+        let (chunks, corrupt, percent) = (20, 7, 25);
+        let runs = 4000;
+        let caught = (0..runs)
+            .filter(|&seed| sample_chunks(chunks, percent, seed).contains(&corrupt))
+            .count();
+        let rate = caught as f64 / runs as f64;
+        assert!((rate - 0.25).abs() < 0.03, "caught {:.3}", rate);
+
+        // Sampling every chunk always catches it.
+        assert!((0..100).all(|seed| sample_chunks(chunks, 100, seed).contains(&corrupt)));
+    }
+
+    #[test]
+    fn test_manifest_chunks() {
+        let blob: Vec<u8> = (0..25u8).collect();
+        let manifest = ChunkManifest::compute(&blob, 10);
+        assert_eq!(manifest.chunk_count(), 3);
+        assert_eq!(manifest.chunk_range(2), (20, 5));
+        assert!(manifest.matches_chunk(1, &blob[10..20]));
+        assert!(!manifest.matches_chunk(1, &blob[11..21]));
+        assert_eq!(manifest.first_mismatch(&blob), None);
+
+        let mut damaged = blob.clone();
+        damaged[23] ^= 1;
+        assert_eq!(manifest.first_mismatch(&damaged), Some(2));
+        assert_eq!(manifest.first_mismatch(&blob[..24]), Some(0));
+
+        assert!(manifest
+            .clone()
+            .with_object_hashes(vec![None])
+            .object_hashes
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fast_verify_catches_sampled_corruption() {
+        let (session, provider) = create_session().await;
+        let path = VaultPath::parse("/large.bin").unwrap();
+        let snapshot = create_large_file(&session, &path).await;
+        let manifest = snapshot.manifest.clone().unwrap();
+        assert_eq!(manifest.chunk_count(), 4);
+        let ops = VaultOperations::new(&session).unwrap();
+
+        // An intact vault passes on one sampled chunk.
+        let seed = 42;
+        let sampled = sample_chunks(4, 25, file_seed(seed, &snapshot.encrypted_name));
+        assert_eq!(sampled.len(), 1);
+        let report = ops.verify(&fast(25, seed)).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.files[0].level, VerifyLevel::Sampled);
+        assert!(!report.files[0].escalated);
+        assert_eq!(report.bytes_downloaded, manifest.chunk_range(sampled[0]).1);
+
+        let unsampled = (0..4).find(|i| !sampled.contains(i)).unwrap();
+        let object = layout::blob_path(session.data_layout(), &snapshot.encrypted_name).unwrap();
+
+        // Damage outside the sample goes unnoticed by this seed...
+        let (offset, _) = manifest.chunk_range(unsampled);
+        provider.corrupt(&object, offset + 100).unwrap();
+        let report = ops.verify(&fast(25, seed)).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.files[0].level, VerifyLevel::Sampled);
+
+        // ...but not by a full run.
+        let report = ops.verify(&VerifyOptions::default()).await.unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.files[0].level, VerifyLevel::Full);
+        assert_eq!(report.bytes_downloaded, manifest.length);
+
+        // Damage inside the sample escalates the file to a full check.
+        provider.corrupt(&object, offset + 100).unwrap();
+        let (offset, _) = manifest.chunk_range(sampled[0]);
+        provider.corrupt(&object, offset + 7).unwrap();
+        let report = ops.verify(&fast(25, seed)).await.unwrap();
+        let file = &report.files[0];
+        assert!(!file.is_ok());
+        assert!(file.escalated);
+        assert_eq!(file.level, VerifyLevel::Full);
+        assert_eq!(
+            file.bytes_downloaded,
+            manifest.chunk_range(sampled[0]).1 + manifest.length
+        );
+        assert_eq!(report.manifests_recorded, 0);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_mismatch_escalates_without_sampling() {
+        let (session, provider) = create_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/small.txt").unwrap();
+        ops.create_file(&path, b"small file").await.unwrap();
+        let encrypted_name = session
+            .tree()
+            .read()
+            .await
+            .get_node(&path)
+            .unwrap()
+            .metadata
+            .encrypted_name
+            .clone();
+
+        let report = ops.verify(&fast(0, 1)).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.files[0].level, VerifyLevel::Metadata);
+        assert_eq!(report.bytes_downloaded, 0);
+
+        // A replaced object of the same size has a different content hash.
+        let object = layout::blob_path(session.data_layout(), &encrypted_name).unwrap();
+        let mut blob = provider.download(&object).await.unwrap();
+        let last = blob.len() - 1;
+        blob[last] ^= 1;
+        provider.upload(&object, blob).await.unwrap();
+
+        let report = ops.verify(&fast(0, 1)).await.unwrap();
+        let file = &report.files[0];
+        assert!(!file.is_ok());
+        assert!(file.escalated);
+        assert_eq!(file.level, VerifyLevel::Full);
+
+        // A missing blob is a failure, not an error of the run.
+        provider.delete(&object).await.unwrap();
+        let report = ops.verify(&fast(10, 1)).await.unwrap();
+        assert!(report.files[0].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_full_check_records_missing_manifest() {
+        let (session, _provider) = create_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        let path = VaultPath::parse("/legacy.txt").unwrap();
+        ops.create_file(&path, b"written before manifests")
+            .await
+            .unwrap();
+        let recorded = {
+            let mut tree = session.tree().write().await;
+            let metadata = &mut tree.get_node_mut(&path).unwrap().metadata;
+            metadata.manifest.take().unwrap()
+        };
+
+        let report = ops.verify(&fast(100, 1)).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.files[0].level, VerifyLevel::Full);
+        assert!(!report.files[0].escalated);
+        assert_eq!(report.manifests_recorded, 1);
+
+        let manifest = session
+            .tree()
+            .read()
+            .await
+            .get_node(&path)
+            .unwrap()
+            .metadata
+            .manifest
+            .clone()
+            .unwrap();
+        assert_eq!(manifest.digests, recorded.digests);
+
+        let report = ops.verify(&fast(100, 1)).await.unwrap();
+        assert_eq!(report.files[0].level, VerifyLevel::Sampled);
+        assert_eq!(report.manifests_recorded, 0);
+    }
+}
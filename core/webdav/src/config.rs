@@ -1,7 +1,12 @@
 //! WebDAV server configuration.
 
+use std::sync::Arc;
+
+use base64::Engine;
+use zeroize::Zeroizing;
+
 /// Configuration for the WebDAV server.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct WebDavConfig {
     /// Address to bind to. Must be loopback only for security.
     pub bind_address: String,
@@ -9,6 +14,9 @@ pub struct WebDavConfig {
     pub port: u16,
     /// Maximum request body size in bytes (default: 256 MiB).
     pub max_body_size: usize,
+    /// Password clients must present via HTTP Basic auth (any user name).
+    /// `None` disables authentication.
+    pub auth_token: Option<Arc<Zeroizing<String>>>,
 }
 
 impl Default for WebDavConfig {
@@ -17,13 +25,53 @@ impl Default for WebDavConfig {
             bind_address: "127.0.0.1".to_string(),
             port: 8080,
             max_body_size: 256 * 1024 * 1024, // 256 MiB
+            auth_token: None,
         }
     }
 }
 
+impl std::fmt::Debug for WebDavConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebDavConfig")
+            .field("bind_address", &self.bind_address)
+            .field("port", &self.port)
+            .field("max_body_size", &self.max_body_size)
+            .field(
+                "auth_token",
+                &self.auth_token.as_ref().map(|_| "[REDACTED]"),
+            )
+            .finish()
+    }
+}
+
 impl WebDavConfig {
     /// Build a socket address string from bind_address and port.
     pub fn socket_addr(&self) -> String {
         format!("{}:{}", self.bind_address, self.port)
     }
+
+    /// Generate a random per-session auth token.
+    pub fn generate_token() -> Zeroizing<String> {
+        use rand::RngExt;
+        let mut bytes = Zeroizing::new([0u8; 24]);
+        rand::rng().fill(&mut bytes[..]);
+        Zeroizing::new(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&bytes[..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_redacts_token() {
+        let token = WebDavConfig::generate_token();
+        let config = WebDavConfig {
+            auth_token: Some(Arc::new(token.clone())),
+            ..Default::default()
+        };
+        let s = format!("{:?}", config);
+        assert!(!s.contains(token.as_str()));
+        assert!(s.contains("[REDACTED]"));
+    }
 }
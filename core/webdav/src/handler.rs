@@ -10,10 +10,13 @@ use axum::body::Body;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use http_body_util::BodyExt;
+use base64::Engine;
+use http_body_util::{BodyExt, Limited};
+use subtle::ConstantTimeEq;
 use tracing::debug;
+use zeroize::Zeroizing;
 
-use axiomvault_common::VaultPath;
+use axiomvault_common::{Result as VaultResult, VaultPath};
 use axiomvault_vault::{VaultOperations, VaultSession};
 
 use crate::xml::{self, PropEntry};
@@ -23,6 +26,8 @@ use crate::xml::{self, PropEntry};
 pub struct AppState {
     pub session: Arc<VaultSession>,
     pub max_body_size: usize,
+    /// Password expected in HTTP Basic auth. `None` disables authentication.
+    pub auth_token: Option<Arc<Zeroizing<String>>>,
 }
 
 /// Top-level router: dispatches by HTTP method.
@@ -30,6 +35,12 @@ pub async fn handle_request(
     State(state): State<AppState>,
     req: axum::extract::Request,
 ) -> Response {
+    if let Some(ref token) = state.auth_token {
+        if !is_authorized(req.headers(), token) {
+            return unauthorized();
+        }
+    }
+
     let method = req.method().clone();
     let path = req.uri().path().to_string();
     let depth = req
@@ -47,27 +58,67 @@ pub async fn handle_request(
         "PUT" => handle_put(&state, req, &path).await,
         "MKCOL" => handle_mkcol(&state, &path).await,
         "DELETE" => handle_delete(&state, &path).await,
-        "MOVE" | "COPY" | "LOCK" | "UNLOCK" => not_implemented(),
+        "MOVE" => handle_move(&state, req.headers(), &path).await,
+        "COPY" => handle_copy(&state, req.headers(), &path).await,
+        "LOCK" => handle_lock(&state, &path).await,
+        "UNLOCK" => StatusCode::NO_CONTENT.into_response(),
         _ => (StatusCode::METHOD_NOT_ALLOWED, "Method not allowed").into_response(),
     }
 }
 
+/// Check HTTP Basic credentials against the session token.
+///
+/// The user name is ignored; only the password has to match.
+fn is_authorized(headers: &http::HeaderMap, token: &str) -> bool {
+    let Some(encoded) = headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Basic "))
+    else {
+        return false;
+    };
+
+    let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(encoded.trim()) else {
+        return false;
+    };
+    let decoded = Zeroizing::new(decoded);
+
+    match decoded.iter().position(|&b| b == b':') {
+        Some(idx) => {
+            let password = &decoded[idx + 1..];
+            password.len() == token.len() && bool::from(password.ct_eq(token.as_bytes()))
+        }
+        None => false,
+    }
+}
+
+/// 401 with a Basic challenge so clients prompt for credentials.
+fn unauthorized() -> Response {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("WWW-Authenticate", "Basic realm=\"AxiomVault\"")
+        .body(Body::from("Authentication required"))
+        .unwrap()
+}
+
 /// OPTIONS — return allowed methods and DAV compliance headers.
+///
+/// Class 2 is advertised so Windows and macOS clients will mount read-write;
+/// locks are accepted but not enforced.
 fn handle_options() -> Response {
     Response::builder()
         .status(StatusCode::OK)
-        .header("Allow", "OPTIONS, PROPFIND, GET, HEAD, PUT, MKCOL, DELETE")
-        .header("DAV", "1")
+        .header(
+            "Allow",
+            "OPTIONS, PROPFIND, GET, HEAD, PUT, MKCOL, DELETE, MOVE, COPY, LOCK, UNLOCK",
+        )
+        .header("DAV", "1, 2")
+        .header("MS-Author-Via", "DAV")
         .header("Content-Length", "0")
         .body(Body::empty())
         .unwrap()
 }
 
-/// 501 Not Implemented for unsupported methods.
-fn not_implemented() -> Response {
-    (StatusCode::NOT_IMPLEMENTED, "Not implemented").into_response()
-}
-
 /// PROPFIND — list directory or return metadata for a single resource.
 async fn handle_propfind(state: &AppState, depth: &str, path: &str) -> Response {
     debug!("PROPFIND request");
@@ -298,36 +349,30 @@ async fn handle_put(state: &AppState, req: axum::extract::Request, path: &str) -
         Err(status) => return error_response(status, "Invalid path"),
     };
 
-    // Read request body with size limit
-    let body = match req.into_body().collect().await {
+    // Read request body, refusing to buffer more than the size limit
+    let body = match Limited::new(req.into_body(), state.max_body_size)
+        .collect()
+        .await
+    {
         Ok(collected) => collected.to_bytes(),
+        Err(e) if e.is::<http_body_util::LengthLimitError>() => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Request body exceeds maximum size",
+            );
+        }
         Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
     };
 
-    if body.len() > state.max_body_size {
-        return error_response(
-            StatusCode::PAYLOAD_TOO_LARGE,
-            "Request body exceeds maximum size",
-        );
-    }
-
     let ops = match VaultOperations::new(&state.session) {
         Ok(ops) => ops,
         Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
     };
 
-    let exists = ops.exists(&vault_path).await;
-
-    if exists {
-        match ops.update_file(&vault_path, &body).await {
-            Ok(()) => StatusCode::NO_CONTENT.into_response(),
-            Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
-        }
-    } else {
-        match ops.create_file(&vault_path, &body).await {
-            Ok(()) => StatusCode::CREATED.into_response(),
-            Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
-        }
+    match ops.write_file(&vault_path, &body, false).await {
+        Ok(true) => StatusCode::CREATED.into_response(),
+        Ok(false) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
     }
 }
 
@@ -374,18 +419,242 @@ async fn handle_delete(state: &AppState, path: &str) -> Response {
         Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
     };
 
-    // Check if it's a file or directory
-    match ops.metadata(&vault_path).await {
-        Ok((_, true, _)) => match ops.delete_directory(&vault_path).await {
-            Ok(()) => StatusCode::NO_CONTENT.into_response(),
-            Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
-        },
-        Ok((_, false, _)) => match ops.delete_file(&vault_path).await {
-            Ok(()) => StatusCode::NO_CONTENT.into_response(),
-            Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
-        },
-        Err(_) => error_response(StatusCode::NOT_FOUND, "Resource not found"),
+    if vault_path.is_root() {
+        return error_response(StatusCode::FORBIDDEN, "Cannot delete the root collection");
+    }
+
+    if !ops.exists(&vault_path).await {
+        return error_response(StatusCode::NOT_FOUND, "Resource not found");
+    }
+
+    // Collections are deleted with Depth: infinity semantics
+    match remove_recursive(&ops, &vault_path).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// MOVE — rename or move a resource within the vault.
+async fn handle_move(state: &AppState, headers: &http::HeaderMap, path: &str) -> Response {
+    debug!("MOVE request");
+
+    let (from, to, overwrite) = match transfer_paths(headers, path) {
+        Ok(paths) => paths,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+
+    let ops = match VaultOperations::new(&state.session) {
+        Ok(ops) => ops,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+
+    if !ops.exists(&from).await {
+        return error_response(StatusCode::NOT_FOUND, "Resource not found");
+    }
+    if from == to || to.starts_with(&from) {
+        return error_response(StatusCode::FORBIDDEN, "Cannot move a resource into itself");
+    }
+
+    let replaced = match prepare_destination(&ops, &to, overwrite).await {
+        Ok(replaced) => replaced,
+        Err((status, msg)) => return error_response(status, &msg),
+    };
+
+    match ops.rename(&from, &to).await {
+        Ok(()) => transfer_status(replaced),
+        Err(e) => transfer_error(e),
+    }
+}
+
+/// COPY — duplicate a file or collection within the vault.
+async fn handle_copy(state: &AppState, headers: &http::HeaderMap, path: &str) -> Response {
+    debug!("COPY request");
+
+    let (from, to, overwrite) = match transfer_paths(headers, path) {
+        Ok(paths) => paths,
+        Err((status, msg)) => return error_response(status, msg),
+    };
+    let shallow = headers
+        .get("Depth")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|d| d == "0");
+
+    let ops = match VaultOperations::new(&state.session) {
+        Ok(ops) => ops,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+
+    if !ops.exists(&from).await {
+        return error_response(StatusCode::NOT_FOUND, "Resource not found");
+    }
+    if from == to || to.starts_with(&from) {
+        return error_response(StatusCode::FORBIDDEN, "Cannot copy a resource into itself");
+    }
+
+    let replaced = match prepare_destination(&ops, &to, overwrite).await {
+        Ok(replaced) => replaced,
+        Err((status, msg)) => return error_response(status, &msg),
+    };
+
+    match copy_recursive(&ops, &from, &to, shallow).await {
+        Ok(()) => transfer_status(replaced),
+        Err(e) => transfer_error(e),
+    }
+}
+
+/// LOCK — accept the lock without enforcing it.
+///
+/// Native clients refuse to write to class-2 servers that reject LOCK, so
+/// this hands out a fresh token and, like a real lock-null request, creates
+/// an empty file when the target does not exist yet.
+async fn handle_lock(state: &AppState, path: &str) -> Response {
+    debug!("LOCK request");
+
+    let vault_path = match normalize_vault_path(path) {
+        Ok(p) => p,
+        Err(status) => return error_response(status, "Invalid path"),
+    };
+
+    let ops = match VaultOperations::new(&state.session) {
+        Ok(ops) => ops,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+
+    let status = if ops.exists(&vault_path).await {
+        StatusCode::OK
+    } else {
+        match ops.create_file(&vault_path, &[]).await {
+            Ok(()) => StatusCode::CREATED,
+            Err(e) => return transfer_error(e),
+        }
+    };
+
+    let token = format!("opaquelocktoken:{}", uuid::Uuid::new_v4());
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/xml; charset=utf-8")
+        .header("Lock-Token", format!("<{}>", token))
+        .body(Body::from(xml::build_lock_discovery(path, &token)))
+        .unwrap()
+}
+
+/// Resolve source, destination and `Overwrite` for MOVE/COPY.
+fn transfer_paths(
+    headers: &http::HeaderMap,
+    path: &str,
+) -> Result<(VaultPath, VaultPath, bool), (StatusCode, &'static str)> {
+    let from = normalize_vault_path(path).map_err(|s| (s, "Invalid path"))?;
+
+    let destination = headers
+        .get("Destination")
+        .and_then(|v| v.to_str().ok())
+        .ok_or((StatusCode::BAD_REQUEST, "Missing Destination header"))?;
+
+    // Destination is an absolute URI or an absolute path
+    let dest_path = destination
+        .parse::<http::Uri>()
+        .map(|uri| uri.path().to_string())
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid Destination header"))?;
+    let to = normalize_vault_path(&dest_path).map_err(|s| (s, "Invalid Destination header"))?;
+
+    if from.is_root() || to.is_root() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "Cannot move or copy the root collection",
+        ));
+    }
+
+    let overwrite = !headers
+        .get("Overwrite")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("F"));
+
+    Ok((from, to, overwrite))
+}
+
+/// Clear the MOVE/COPY destination if allowed. Returns whether it existed.
+async fn prepare_destination(
+    ops: &VaultOperations<'_>,
+    to: &VaultPath,
+    overwrite: bool,
+) -> Result<bool, (StatusCode, String)> {
+    if !ops.exists(to).await {
+        return Ok(false);
+    }
+    if !overwrite {
+        return Err((
+            StatusCode::PRECONDITION_FAILED,
+            "Destination exists and Overwrite is F".to_string(),
+        ));
+    }
+    remove_recursive(ops, to)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(true)
+}
+
+/// 204 when an existing destination was replaced, 201 otherwise.
+fn transfer_status(replaced: bool) -> Response {
+    if replaced {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::CREATED.into_response()
+    }
+}
+
+/// Map a vault error from MOVE/COPY/LOCK to a WebDAV status.
+fn transfer_error(e: axiomvault_common::Error) -> Response {
+    use axiomvault_common::Error;
+    match e {
+        // Missing or non-collection destination parent
+        Error::NotFound(_) | Error::InvalidInput(_) => {
+            error_response(StatusCode::CONFLICT, &e.to_string())
+        }
+        Error::AlreadyExists(_) => error_response(StatusCode::PRECONDITION_FAILED, &e.to_string()),
+        _ => error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    }
+}
+
+/// Delete a file, or a directory and everything below it.
+async fn remove_recursive(ops: &VaultOperations<'_>, path: &VaultPath) -> VaultResult<()> {
+    let (_, is_dir, _) = ops.metadata(path).await?;
+    if !is_dir {
+        return ops.delete_file(path).await;
+    }
+
+    for (name, _, _) in ops.list_directory(path).await? {
+        Box::pin(remove_recursive(ops, &path.join(&name)?)).await?;
+    }
+    ops.delete_directory(path).await
+}
+
+/// Copy a file, or a directory tree unless `shallow` is set.
+async fn copy_recursive(
+    ops: &VaultOperations<'_>,
+    from: &VaultPath,
+    to: &VaultPath,
+    shallow: bool,
+) -> VaultResult<()> {
+    let (_, is_dir, _) = ops.metadata(from).await?;
+    if !is_dir {
+        let content = Zeroizing::new(ops.read_file(from).await?);
+        return ops.create_file(to, &content).await;
+    }
+
+    ops.create_directory(to).await?;
+    if shallow {
+        return Ok(());
+    }
+    for (name, _, _) in ops.list_directory(from).await? {
+        Box::pin(copy_recursive(
+            ops,
+            &from.join(&name)?,
+            &to.join(&name)?,
+            false,
+        ))
+        .await?;
     }
+    Ok(())
 }
 
 /// Convert a URL path to a VaultPath, handling percent-decoding and normalization.
@@ -4,9 +4,13 @@
 //! fallback when FUSE is unavailable. Files are transparently encrypted
 //! on write and decrypted on read.
 //!
+//! Supports the methods rclone and the Windows/macOS native clients need:
+//! PROPFIND (depth 0/1), GET/HEAD/PUT, MKCOL, DELETE, MOVE, COPY, and
+//! advisory LOCK/UNLOCK (DAV class 2).
+//!
 //! # Security
-//! - Binds only to `127.0.0.1` (never `0.0.0.0`)
-//! - No authentication layer (vault is already password-protected)
+//! - Binds only to `127.0.0.1` (never `0.0.0.0`), so plain HTTP is used
+//! - Optional per-session token, presented as the HTTP Basic password
 //! - All operations go through `VaultOperations` which handles encryption
 
 pub mod config;
@@ -45,6 +49,7 @@ impl WebDavServer {
         let state = AppState {
             session: self.session.clone(),
             max_body_size: self.config.max_body_size,
+            auth_token: self.config.auth_token.clone(),
         };
 
         let app = Router::new()
@@ -73,7 +78,7 @@ impl WebDavServer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use axiomvault_common::VaultId;
+    use axiomvault_common::{Secret, VaultId};
     use axiomvault_crypto::KdfParams;
     use axiomvault_storage::{MemoryProvider, StorageProvider};
     use axiomvault_vault::{VaultConfig, VaultOperations, VaultTree};
@@ -99,8 +104,13 @@ mod tests {
             .await
             .unwrap();
 
-        let session =
-            VaultSession::unlock(creation.config, password, provider, VaultTree::new()).unwrap();
+        let session = VaultSession::unlock(
+            creation.config,
+            &Secret::from_slice(password),
+            provider,
+            VaultTree::new(),
+        )
+        .unwrap();
         Arc::new(session)
     }
 
@@ -112,10 +122,19 @@ mod tests {
 
     /// Start a WebDAV server in the background and return its base URL.
     async fn start_test_server(session: Arc<VaultSession>) -> String {
+        start_test_server_with_token(session, None).await
+    }
+
+    /// Start a WebDAV server that optionally requires an auth token.
+    async fn start_test_server_with_token(
+        session: Arc<VaultSession>,
+        auth_token: Option<&str>,
+    ) -> String {
         let port = free_port().await;
         let config = WebDavConfig {
             bind_address: "127.0.0.1".to_string(),
             port,
+            auth_token: auth_token.map(|t| Arc::new(zeroize::Zeroizing::new(t.to_string()))),
             ..Default::default()
         };
         let server = WebDavServer::new(session, config);
@@ -326,31 +345,316 @@ mod tests {
         assert!(allow.contains("PROPFIND"));
         assert!(allow.contains("GET"));
         assert!(allow.contains("PUT"));
+        assert!(allow.contains("MOVE"));
+        assert!(allow.contains("LOCK"));
         let dav = resp.headers().get("dav").unwrap().to_str().unwrap();
-        assert_eq!(dav, "1");
+        assert_eq!(dav, "1, 2");
     }
 
     #[tokio::test]
-    async fn test_unsupported_methods_return_501() {
+    async fn test_propfind_depth_zero_lists_only_target() {
         let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_directory(&axiomvault_common::VaultPath::parse("/dir").unwrap())
+            .await
+            .unwrap();
+        ops.create_file(
+            &axiomvault_common::VaultPath::parse("/dir/inner.txt").unwrap(),
+            b"12345",
+        )
+        .await
+        .unwrap();
+
         let base_url = start_test_server(session).await;
+        let client = reqwest::Client::new();
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+
+        let body = client
+            .request(propfind.clone(), format!("{}/dir", base_url))
+            .header("Depth", "0")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("<D:href>/dir/</D:href>"));
+        assert!(!body.contains("inner.txt"));
+
+        let body = client
+            .request(propfind, format!("{}/dir", base_url))
+            .header("Depth", "1")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("<D:href>/dir/inner.txt</D:href>"));
+        assert!(body.contains("<D:getcontentlength>5</D:getcontentlength>"));
+        assert!(body.contains("<D:getlastmodified>"));
+    }
 
+    #[tokio::test]
+    async fn test_move_file() {
+        let session = create_test_session().await;
+        let base_url = start_test_server(session).await;
         let client = reqwest::Client::new();
 
-        for method_name in &["MOVE", "COPY", "LOCK", "UNLOCK"] {
-            let resp = client
-                .request(
-                    Method::from_bytes(method_name.as_bytes()).unwrap(),
-                    format!("{}/test", base_url),
-                )
+        client
+            .put(format!("{}/a.txt", base_url))
+            .body("moved content")
+            .send()
+            .await
+            .unwrap();
+        client
+            .request(
+                Method::from_bytes(b"MKCOL").unwrap(),
+                format!("{}/sub", base_url),
+            )
+            .send()
+            .await
+            .unwrap();
+
+        let resp = client
+            .request(
+                Method::from_bytes(b"MOVE").unwrap(),
+                format!("{}/a.txt", base_url),
+            )
+            .header("Destination", format!("{}/sub/b.txt", base_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 201);
+
+        let resp = client
+            .get(format!("{}/a.txt", base_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 404);
+
+        let resp = client
+            .get(format!("{}/sub/b.txt", base_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.bytes().await.unwrap().as_ref(), b"moved content");
+    }
+
+    #[tokio::test]
+    async fn test_move_respects_overwrite_header() {
+        let session = create_test_session().await;
+        let base_url = start_test_server(session).await;
+        let client = reqwest::Client::new();
+        let move_method = Method::from_bytes(b"MOVE").unwrap();
+
+        for (name, body) in [("src.txt", "source"), ("dst.txt", "destination")] {
+            client
+                .put(format!("{}/{}", base_url, name))
+                .body(body)
                 .send()
                 .await
                 .unwrap();
+        }
+
+        let resp = client
+            .request(move_method.clone(), format!("{}/src.txt", base_url))
+            .header("Destination", "/dst.txt")
+            .header("Overwrite", "F")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 412);
+
+        let resp = client
+            .request(move_method, format!("{}/src.txt", base_url))
+            .header("Destination", "/dst.txt")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 204);
+
+        let resp = client
+            .get(format!("{}/dst.txt", base_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.bytes().await.unwrap().as_ref(), b"source");
+    }
+
+    #[tokio::test]
+    async fn test_move_into_missing_parent_conflicts() {
+        let session = create_test_session().await;
+        let base_url = start_test_server(session).await;
+        let client = reqwest::Client::new();
 
-            assert_eq!(resp.status(), 501, "{} should return 501", method_name,);
+        client
+            .put(format!("{}/file.txt", base_url))
+            .body("x")
+            .send()
+            .await
+            .unwrap();
+
+        let resp = client
+            .request(
+                Method::from_bytes(b"MOVE").unwrap(),
+                format!("{}/file.txt", base_url),
+            )
+            .header("Destination", "/missing/file.txt")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 409);
+    }
+
+    #[tokio::test]
+    async fn test_copy_directory_recursively() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_directory(&axiomvault_common::VaultPath::parse("/src").unwrap())
+            .await
+            .unwrap();
+        ops.create_file(
+            &axiomvault_common::VaultPath::parse("/src/file.txt").unwrap(),
+            b"copied",
+        )
+        .await
+        .unwrap();
+
+        let base_url = start_test_server(session).await;
+        let client = reqwest::Client::new();
+
+        let resp = client
+            .request(
+                Method::from_bytes(b"COPY").unwrap(),
+                format!("{}/src/", base_url),
+            )
+            .header("Destination", format!("{}/dst/", base_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 201);
+
+        for path in ["src/file.txt", "dst/file.txt"] {
+            let resp = client
+                .get(format!("{}/{}", base_url, path))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.bytes().await.unwrap().as_ref(), b"copied");
         }
     }
 
+    #[tokio::test]
+    async fn test_delete_directory_recursively() {
+        let session = create_test_session().await;
+        let ops = VaultOperations::new(&session).unwrap();
+        ops.create_directory(&axiomvault_common::VaultPath::parse("/dir").unwrap())
+            .await
+            .unwrap();
+        ops.create_file(
+            &axiomvault_common::VaultPath::parse("/dir/file.txt").unwrap(),
+            b"x",
+        )
+        .await
+        .unwrap();
+
+        let base_url = start_test_server(session).await;
+        let client = reqwest::Client::new();
+
+        let resp = client
+            .delete(format!("{}/dir/", base_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 204);
+
+        let resp = client
+            .get(format!("{}/dir/file.txt", base_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_lock_and_unlock() {
+        let session = create_test_session().await;
+        let base_url = start_test_server(session).await;
+        let client = reqwest::Client::new();
+
+        // Locking a missing resource creates it empty, like a lock-null resource.
+        let resp = client
+            .request(
+                Method::from_bytes(b"LOCK").unwrap(),
+                format!("{}/locked.txt", base_url),
+            )
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 201);
+        let token = resp
+            .headers()
+            .get("lock-token")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(token.starts_with("<opaquelocktoken:"));
+        assert!(resp.text().await.unwrap().contains("<D:lockdiscovery>"));
+
+        let resp = client
+            .request(
+                Method::from_bytes(b"UNLOCK").unwrap(),
+                format!("{}/locked.txt", base_url),
+            )
+            .header("Lock-Token", token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 204);
+
+        let resp = client
+            .get(format!("{}/locked.txt", base_url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_required() {
+        let session = create_test_session().await;
+        let base_url = start_test_server_with_token(session, Some("session-token")).await;
+        let client = reqwest::Client::new();
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+
+        let resp = client
+            .request(propfind.clone(), &base_url)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 401);
+        assert!(resp.headers().contains_key("www-authenticate"));
+
+        let resp = client
+            .request(propfind.clone(), &base_url)
+            .basic_auth("anyone", Some("wrong-token"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 401);
+
+        let resp = client
+            .request(propfind, &base_url)
+            .basic_auth("anyone", Some("session-token"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 207);
+    }
+
     #[tokio::test]
     async fn test_head_file() {
         let session = create_test_session().await;
@@ -96,6 +96,32 @@ pub fn build_multistatus(entries: &[PropEntry]) -> String {
     xml
 }
 
+/// Build the `lockdiscovery` body returned from a successful LOCK.
+///
+/// Locks are advisory only, so the response always describes an exclusive
+/// write lock with a one-hour timeout.
+pub fn build_lock_discovery(href: &str, token: &str) -> String {
+    let mut xml = String::with_capacity(512);
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<D:prop xmlns:D=\"DAV:\">\n");
+    xml.push_str("  <D:lockdiscovery>\n");
+    xml.push_str("    <D:activelock>\n");
+    xml.push_str("      <D:locktype><D:write/></D:locktype>\n");
+    xml.push_str("      <D:lockscope><D:exclusive/></D:lockscope>\n");
+    xml.push_str("      <D:depth>infinity</D:depth>\n");
+    xml.push_str("      <D:timeout>Second-3600</D:timeout>\n");
+    xml.push_str("      <D:locktoken><D:href>");
+    xml.push_str(&xml_escape(token));
+    xml.push_str("</D:href></D:locktoken>\n");
+    xml.push_str("      <D:lockroot><D:href>");
+    xml.push_str(&xml_escape(href));
+    xml.push_str("</D:href></D:lockroot>\n");
+    xml.push_str("    </D:activelock>\n");
+    xml.push_str("  </D:lockdiscovery>\n");
+    xml.push_str("</D:prop>\n");
+    xml
+}
+
 /// Escape special XML characters.
 fn xml_escape(s: &str) -> String {
     s.replace('&', "&amp;")
@@ -136,6 +162,14 @@ mod tests {
         assert!(xml.contains("<D:getetag>\"abc123\"</D:getetag>"));
     }
 
+    #[test]
+    fn test_build_lock_discovery() {
+        let xml = build_lock_discovery("/a&b.txt", "opaquelocktoken:1234");
+        assert!(xml.contains("<D:lockdiscovery>"));
+        assert!(xml.contains("<D:href>opaquelocktoken:1234</D:href>"));
+        assert!(xml.contains("<D:href>/a&amp;b.txt</D:href>"));
+    }
+
     #[test]
     fn test_build_multistatus_collection() {
         let now = Utc::now();
@@ -9,25 +9,37 @@ use clap_complete::Shell;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpListener;
 use tracing::{info, Level};
+use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::FmtSubscriber;
 use url::Url;
 use zeroize::{Zeroize, Zeroizing};
 
-use axiomvault_common::{VaultId, VaultPath};
+use axiomvault_common::shred::{self, ShredOutcome};
+use axiomvault_common::{escape_lossy, telemetry, Secret, VaultId, VaultPath};
 use axiomvault_crypto::recovery::RecoveryKey;
-use axiomvault_crypto::KdfParams;
-use axiomvault_storage::gdrive::{AuthConfig, AuthManager, GDriveConfig, Tokens};
+use axiomvault_crypto::{KdfParams, MasterKey};
+use axiomvault_hooks::{HookAction, HookDispatcher, HookEvent, HooksConfig};
+use axiomvault_storage::gdrive::{self, AuthConfig, AuthManager, GDriveConfig, Tokens};
 use axiomvault_storage::{
     create_default_registry, CompositeConfig, CompositeStorageProvider, HealthStatus, RaidMode,
     RaidRebuilder, RebuildConfig, RebuildResult,
 };
-use axiomvault_sync::{ConflictStrategy, SyncConfig, SyncEngine, SyncMode, SyncState};
+use axiomvault_sync::{
+    ConflictDetails, ConflictKind, ConflictStrategy, ShutdownOutcome, Side, StagingKey, SyncConfig,
+    SyncEngine, SyncMode, SyncState, VersionInfo,
+};
 use axiomvault_vault::{
-    check_migration_needed, check_vault_health, check_vault_structure, MigrationRegistry,
-    MigrationStatus, VaultConfig, VaultManager, VaultOperations, VaultVersion,
+    check_migration_needed, check_vault_health, check_vault_structure, remove_partial_export,
+    AttachPoint, BlobFormat, ChangePasswordPlan, DiffKind, DiffOptions, DiffTarget,
+    DirectoryPolicy, ExportOptions, MaintenancePolicy, MigrationKind, MigrationOutcome,
+    MigrationPlan, MigrationProgress, MigrationRegistry, MigrationStatus, SearchConfig,
+    SessionMigrationRegistry, SyncPolicy, TemplateConflict, VaultConfig, VaultEvent, VaultManager,
+    VaultMetadata, VaultOperations, VaultSession, VaultTemplate, VaultVersion, VerifyLevel,
+    VerifyOptions, Versioning,
 };
 
 /// KDF strength level for key derivation.
@@ -65,7 +77,36 @@ enum SyncModeArg {
     Hybrid,
 }
 
+/// On/off setting of a directory policy.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum PolicyToggleArg {
+    On,
+    Off,
+    /// Remove the override and inherit from the parent.
+    Inherit,
+}
+
+/// Sync setting of a directory policy.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum PolicySyncArg {
+    Include,
+    Exclude,
+    /// Remove the override and inherit from the parent.
+    Inherit,
+}
+
 /// RAID mode for CLI configuration.
+/// How `template apply` handles template entries that already exist.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TemplateConflictArg {
+    /// Fail without changing anything.
+    Error,
+    /// Leave existing paths untouched.
+    Skip,
+    /// Replace existing file content and directory policies.
+    Overwrite,
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 enum RaidModeArg {
     /// Mirror (RAID 1): replicate to all backends.
@@ -113,6 +154,40 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    /// Keyfile required together with the password to unlock the vault.
+    #[arg(long, global = true, value_name = "PATH")]
+    keyfile: Option<PathBuf>,
+
+    /// Expert: read a hex-encoded 256-bit master key from this file instead
+    /// of deriving it from a password. Only for vaults created with an
+    /// external key; losing the key loses the vault.
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        conflicts_with_all = ["keyfile", "key_from_env"]
+    )]
+    key_file_hex: Option<PathBuf>,
+
+    /// Expert: read a hex-encoded 256-bit master key from this environment
+    /// variable instead of deriving it from a password.
+    #[arg(
+        long,
+        global = true,
+        value_name = "VAR",
+        conflicts_with_all = ["keyfile", "key_file_hex"]
+    )]
+    key_from_env: Option<String>,
+
+    /// Replace vault paths in log output and trace spans with opaque tags.
+    #[arg(long, global = true)]
+    redact_paths: bool,
+
+    /// Let hooks in `<vault>/.axiomvault/hooks.json` run local commands.
+    /// HTTP hooks run without it.
+    #[arg(long, global = true)]
+    allow_exec_hooks: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -132,6 +207,10 @@ enum Commands {
         /// KDF strength level.
         #[arg(short, long, value_enum, default_value_t = KdfStrength::Moderate)]
         strength: KdfStrength,
+
+        /// Lay out the new vault from a template manifest.
+        #[arg(long, value_name = "MANIFEST")]
+        template: Option<PathBuf>,
     },
 
     /// Open an existing vault and start interactive session.
@@ -165,6 +244,12 @@ enum Commands {
         /// Destination path in vault.
         #[arg(short, long)]
         dest: String,
+
+        /// Overwrite and delete the source file once the vault copy has been
+        /// read back and matches it. Not reliable on SSDs or copy-on-write
+        /// filesystems.
+        #[arg(long)]
+        shred_source: bool,
     },
 
     /// Extract a file from the vault.
@@ -209,6 +294,36 @@ enum Commands {
         /// Path to the vault.
         #[arg(short, long)]
         path: PathBuf,
+
+        /// Unlock the vault to also report file counts and sizes.
+        #[arg(long)]
+        detailed: bool,
+    },
+
+    /// Change the vault's display name, description, icon or color.
+    ///
+    /// The vault ID, storage paths and keys stay the same. Options left out
+    /// keep their current value; pass an empty string to clear one.
+    Describe {
+        /// Path to the vault.
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// New display name.
+        #[arg(long)]
+        name: Option<String>,
+
+        /// New description.
+        #[arg(long)]
+        description: Option<String>,
+
+        /// New icon hint, such as an emoji.
+        #[arg(long)]
+        icon: Option<String>,
+
+        /// New color hint, as #rrggbb.
+        #[arg(long)]
+        color: Option<String>,
     },
 
     /// Change vault password.
@@ -216,6 +331,19 @@ enum Commands {
         /// Path to the vault.
         #[arg(short, long)]
         path: PathBuf,
+
+        /// Require this keyfile from now on (replaces any current keyfile).
+        #[arg(long, value_name = "PATH")]
+        new_keyfile: Option<PathBuf>,
+
+        /// Stop requiring a keyfile.
+        #[arg(long, conflicts_with = "new_keyfile")]
+        remove_keyfile: bool,
+
+        /// Only report whether the change re-wraps the key or re-encrypts
+        /// every file, without changing the password.
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Show recovery key for a vault (requires password).
@@ -226,6 +354,9 @@ enum Commands {
     },
 
     /// Reset vault password using recovery key words.
+    ///
+    /// The recovery key bypasses the keyfile; afterwards the vault opens
+    /// with the new password alone.
     ResetPassword {
         /// Path to the vault.
         #[arg(short, long)]
@@ -240,6 +371,7 @@ enum Commands {
     },
 
     /// Check vault health and integrity.
+    #[command(alias = "doctor")]
     Check {
         /// Path to the vault.
         #[arg(short, long)]
@@ -250,6 +382,183 @@ enum Commands {
         shallow: bool,
     },
 
+    /// Re-encrypt outdated file blobs in a newer format.
+    ///
+    /// Progress is saved after every file, so an interrupted run resumes
+    /// where it stopped.
+    Maintain {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Blob format to upgrade to ("latest", "v3", ...).
+        #[arg(long, default_value = "latest")]
+        target_format: String,
+
+        /// Make a single pass instead of retrying files that were in use.
+        #[arg(long)]
+        once: bool,
+
+        /// Maximum files upgraded per minute (0 = unlimited).
+        #[arg(long, default_value_t = 60)]
+        files_per_minute: u32,
+
+        /// Maximum bytes re-encrypted per second (0 = unlimited).
+        #[arg(long, default_value_t = 4 * 1024 * 1024)]
+        bytes_per_second: u64,
+    },
+
+    /// Check that every file's blob is intact.
+    ///
+    /// Exits with status 1 when a file fails.
+    Verify {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Compare provider metadata and a sample of chunks with each
+        /// file's manifest, downloading a file in full only when they
+        /// disagree.
+        #[arg(long)]
+        fast: bool,
+
+        /// Percentage of each file's chunks downloaded with --fast.
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(0..=100))]
+        sample_percent: u8,
+
+        /// Seed of the chunk sampler, to repeat a run; random by default.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Show vault statistics, including blob format upgrade progress.
+    Stats {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+    },
+
+    /// Compare a vault with another vault or a local directory.
+    ///
+    /// Exits with status 1 when differences are found.
+    Diff {
+        /// Path to the vault (A).
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Vault to compare with (B); prompts for its password.
+        #[arg(
+            long,
+            required_unless_present = "local_dir",
+            conflicts_with = "local_dir"
+        )]
+        other_vault_path: Option<PathBuf>,
+
+        /// Keyfile of the other vault.
+        #[arg(long, value_name = "PATH", requires = "other_vault_path")]
+        other_keyfile: Option<PathBuf>,
+
+        /// Directory of plaintext files to compare with (B), e.g. a restore.
+        #[arg(long)]
+        local_dir: Option<PathBuf>,
+
+        /// Compare sizes and etags only, without reading file contents.
+        #[arg(long)]
+        fast: bool,
+
+        /// Do not report files that differ only in modification time.
+        #[arg(long)]
+        ignore_timestamps: bool,
+
+        /// Leave out this subtree on both sides (repeatable).
+        #[arg(long = "ignore", value_name = "PATH")]
+        ignore: Vec<String>,
+
+        /// Print the report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Search file contents using the vault's encrypted search index.
+    ///
+    /// Enables search if needed and brings the index up to date first.
+    Grep {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Words to search for, matched as a phrase.
+        query: String,
+
+        /// Maximum number of files to list.
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Rebuild the encrypted search index from scratch.
+    Reindex {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Disable search and delete the stored index instead.
+        #[arg(long)]
+        disable: bool,
+    },
+
+    /// Export files modified after a point in time (incremental backup).
+    ///
+    /// Files keep their vault-relative layout under the output directory.
+    Backup {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Only export files modified after this RFC 3339 timestamp
+        /// (e.g. "2024-05-01T00:00:00Z").
+        #[arg(long, value_parser = parse_rfc3339)]
+        since: chrono::DateTime<chrono::Utc>,
+
+        /// Directory to write the exported files into.
+        #[arg(short, long)]
+        out: PathBuf,
+    },
+
+    /// Decrypt a vault subtree into a plain directory.
+    ///
+    /// Writes secrets in cleartext, so it requires --i-understand-plaintext.
+    /// An interrupted run leaves a manifest in the destination; run again
+    /// to resume, or with --clean-up to delete what was written.
+    ExportPlaintext {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Directory to write the decrypted files into.
+        #[arg(short, long)]
+        dest: PathBuf,
+
+        /// Only export this vault directory or file.
+        #[arg(long, default_value = "/")]
+        prefix: String,
+
+        /// Confirm that decrypted files may be written to disk.
+        #[arg(long)]
+        i_understand_plaintext: bool,
+
+        /// Re-read every written file and compare it with the vault content.
+        #[arg(long)]
+        verify: bool,
+
+        /// Export even if the destination looks like a cloud-synced folder.
+        #[arg(long)]
+        allow_synced_dest: bool,
+
+        /// Delete the files of an interrupted export instead of resuming it.
+        #[arg(long)]
+        clean_up: bool,
+    },
+
     /// Authenticate with Google Drive and get tokens.
     GdriveAuth {
         /// Optional custom client ID.
@@ -304,6 +613,11 @@ enum Commands {
         /// Conflict resolution strategy.
         #[arg(short, long, value_enum, default_value_t = ConflictStrategyArg::KeepBoth)]
         strategy: ConflictStrategyArg,
+
+        /// Only sync this file or directory (repeatable). Directories cover
+        /// every tracked file below them.
+        #[arg(long = "path", value_name = "VAULT_PATH")]
+        paths: Vec<String>,
     },
 
     /// Show sync status for the vault.
@@ -320,19 +634,28 @@ enum Commands {
         vault_path: PathBuf,
     },
 
-    /// Resolve a sync conflict for a specific file.
+    /// Resolve a sync conflict for a specific file, or all of them with --all.
     SyncResolve {
         /// Path to the vault.
         #[arg(short = 'p', long)]
         vault_path: PathBuf,
 
         /// File path in vault to resolve.
-        #[arg(short, long)]
-        file: String,
+        #[arg(short, long, required_unless_present = "all", conflicts_with = "all")]
+        file: Option<String>,
+
+        /// Resolve every conflict with --strategy.
+        #[arg(long, requires = "strategy", conflicts_with = "show")]
+        all: bool,
 
         /// Resolution strategy.
-        #[arg(short, long, value_enum)]
-        strategy: ConflictStrategyArg,
+        #[arg(short, long, value_enum, required_unless_present = "show")]
+        strategy: Option<ConflictStrategyArg>,
+
+        /// Show both versions of the file before resolving; without
+        /// --strategy, only show them.
+        #[arg(long)]
+        show: bool,
     },
 
     /// Configure sync mode for the vault.
@@ -351,14 +674,32 @@ enum Commands {
     },
 
     /// Migrate vault to the latest format version.
+    ///
+    /// Without options, applies every pending migration.
     Migrate {
         /// Path to the vault.
         #[arg(short, long)]
         path: PathBuf,
 
+        /// List pending migrations and the migrations already run.
+        #[arg(long, conflicts_with_all = ["dry_run", "apply", "all", "rollback"])]
+        list: bool,
+
         /// Only show what migrations would run, without executing them.
-        #[arg(long)]
+        #[arg(long, conflicts_with_all = ["apply", "rollback"])]
         dry_run: bool,
+
+        /// Apply one migration, by ID.
+        #[arg(long, value_name = "ID", conflicts_with_all = ["all", "rollback"])]
+        apply: Option<String>,
+
+        /// Apply every pending migration.
+        #[arg(long, conflicts_with = "rollback")]
+        all: bool,
+
+        /// Undo an applied migration, by ID, if it is reversible.
+        #[arg(long, value_name = "ID")]
+        rollback: Option<String>,
     },
 
     /// Generate shell completions.
@@ -417,7 +758,12 @@ enum Commands {
     },
 
     /// Serve vault contents over WebDAV on the loopback interface.
-    Webdav {
+    ///
+    /// Clients authenticate with any user name and the per-session token
+    /// printed at startup as the password.
+    #[cfg(feature = "webdav")]
+    #[command(alias = "webdav")]
+    ServeWebdav {
         /// Path to the vault.
         #[arg(short, long)]
         path: PathBuf,
@@ -425,6 +771,72 @@ enum Commands {
         /// Port to listen on (default: 8080).
         #[arg(long, default_value_t = 8080)]
         port: u16,
+
+        /// Also serve operation metrics in the Prometheus text format on this
+        /// loopback port (requires the `metrics` feature).
+        #[arg(long)]
+        metrics_port: Option<u16>,
+    },
+
+    /// Mount the vault as a filesystem until interrupted with Ctrl+C.
+    ///
+    /// Requires a build with the `fuse` feature.
+    Mount {
+        /// Path to the vault.
+        #[arg(short, long)]
+        path: PathBuf,
+
+        /// Empty directory to mount the vault at.
+        #[arg(long, value_name = "MOUNTPOINT")]
+        at: PathBuf,
+
+        /// Reject writes through the mount.
+        #[arg(long)]
+        read_only: bool,
+
+        /// Let other local users access the mount.
+        #[arg(long)]
+        allow_other: bool,
+    },
+
+    /// Release a mount left behind by an interrupted `mount`.
+    Unmount {
+        /// Directory the vault is mounted at.
+        #[arg(long, value_name = "MOUNTPOINT")]
+        at: PathBuf,
+        /// Detach the mount even if files on it stay open.
+        #[arg(long)]
+        lazy: bool,
+    },
+
+    /// Keyfile management.
+    Keyfile {
+        #[command(subcommand)]
+        action: KeyfileCommands,
+    },
+
+    /// Per-directory policies inherited by subdirectories.
+    Policy {
+        #[command(subcommand)]
+        action: PolicyCommands,
+    },
+
+    /// Vault templates describing an initial structure.
+    Template {
+        #[command(subcommand)]
+        action: TemplateCommands,
+    },
+
+    /// Subtrees whose files are stored on another provider.
+    Attach {
+        #[command(subcommand)]
+        action: AttachCommands,
+    },
+
+    /// Automation hooks run on sync and vault events.
+    Hooks {
+        #[command(subcommand)]
+        action: HooksCommands,
     },
 
     /// Configure or change the RAID mode.
@@ -447,6 +859,155 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum KeyfileCommands {
+    /// Generate a new random keyfile.
+    Generate {
+        /// Where to write the keyfile.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateCommands {
+    /// Apply a template manifest to an existing vault.
+    Apply {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Template manifest.
+        template: PathBuf,
+
+        /// What to do with template entries that already exist.
+        #[arg(long, value_enum, default_value_t = TemplateConflictArg::Error)]
+        on_conflict: TemplateConflictArg,
+    },
+
+    /// Generate a template manifest from a vault's structure.
+    Export {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Where to write the manifest. Printed to stdout if omitted.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Include the content of files up to --max-file-size.
+        #[arg(long)]
+        include_files: bool,
+
+        /// Largest file included by --include-files, in bytes.
+        #[arg(long, default_value_t = 64 * 1024, requires = "include_files")]
+        max_file_size: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum AttachCommands {
+    /// Store files written below a directory on another provider.
+    Add {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Directory in the vault; created if missing, must hold no files.
+        dir: String,
+
+        /// Storage provider type, e.g. `local`.
+        #[arg(long)]
+        provider: String,
+
+        /// Provider configuration as JSON, e.g. `{"root": "/mnt/cold"}`.
+        #[arg(long, default_value = "{}")]
+        config: String,
+    },
+
+    /// List the vault's attach points.
+    List {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+    },
+
+    /// Detach a directory. Its files must be moved out or deleted first.
+    Remove {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Attached directory in the vault.
+        dir: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum HooksCommands {
+    /// List the hooks configured in `<vault>/.axiomvault/hooks.json`.
+    List {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+    },
+
+    /// Send a test event to the hooks it matches and report the outcome.
+    Test {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Event name, e.g. `SyncFinished`.
+        #[arg(long, default_value = "SyncFinished")]
+        event: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum PolicyCommands {
+    /// Override policy settings on a directory. Settings not given keep
+    /// their current override.
+    Set {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// Directory in the vault.
+        dir: String,
+
+        /// Earlier versions to keep: `off`, a count, or `inherit`.
+        #[arg(long)]
+        versioning: Option<String>,
+
+        /// Compress file content before encryption.
+        #[arg(long, value_enum)]
+        compression: Option<PolicyToggleArg>,
+
+        /// Include or exclude the subtree from sync.
+        #[arg(long, value_enum)]
+        sync: Option<PolicySyncArg>,
+
+        /// Store identical content once.
+        #[arg(long, value_enum)]
+        dedup: Option<PolicyToggleArg>,
+
+        /// Remove all overrides before applying the settings above.
+        #[arg(long)]
+        clear: bool,
+    },
+
+    /// Show the policy in effect for a file or directory.
+    Show {
+        /// Path to the vault.
+        #[arg(short = 'p', long)]
+        vault_path: PathBuf,
+
+        /// File or directory in the vault.
+        path: String,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -458,54 +1019,212 @@ async fn main() -> Result<()> {
         Level::INFO
     };
 
+    // In verbose mode, span close events report how long each operation took.
+    let span_events = if cli.verbose {
+        FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    };
+
     let subscriber = FmtSubscriber::builder()
         .with_max_level(level)
         .with_target(false)
+        .with_span_events(span_events)
         .compact()
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
+    telemetry::set_redact_paths(cli.redact_paths);
+    #[cfg(feature = "metrics")]
+    telemetry::metrics::install();
+
+    let unlock = Unlock {
+        keyfile: cli.keyfile,
+        key_file_hex: cli.key_file_hex,
+        key_from_env: cli.key_from_env,
+    };
+    let keyfile = unlock.keyfile.as_deref();
 
     match cli.command {
         Commands::Create {
             name,
             path,
             strength,
-        } => cmd_create(&name, &path, strength).await,
+            template,
+        } => cmd_create(&name, &path, strength, template.as_deref(), &unlock).await,
 
-        Commands::Open { path } => cmd_open(&path).await,
+        Commands::Open { path } => cmd_open(&path, &unlock).await,
 
-        Commands::List { vault_path, dir } => cmd_list(&vault_path, &dir).await,
+        Commands::List { vault_path, dir } => cmd_list(&vault_path, &dir, &unlock).await,
 
         Commands::Add {
             vault_path,
             source,
             dest,
-        } => cmd_add(&vault_path, &source, &dest).await,
+            shred_source,
+        } => cmd_add(&vault_path, &source, &dest, shred_source, &unlock).await,
 
         Commands::Extract {
             vault_path,
             source,
             dest,
-        } => cmd_extract(&vault_path, &source, &dest).await,
+        } => cmd_extract(&vault_path, &source, &dest, &unlock).await,
 
-        Commands::Mkdir { vault_path, dir } => cmd_mkdir(&vault_path, &dir).await,
+        Commands::Mkdir { vault_path, dir } => cmd_mkdir(&vault_path, &dir, &unlock).await,
 
-        Commands::Remove { vault_path, file } => cmd_remove(&vault_path, &file).await,
+        Commands::Remove { vault_path, file } => cmd_remove(&vault_path, &file, &unlock).await,
 
-        Commands::Info { path } => cmd_info(&path).await,
+        Commands::Info { path, detailed } => cmd_info(&path, keyfile, detailed).await,
 
-        Commands::ChangePassword { path } => cmd_change_password(&path).await,
+        Commands::Describe {
+            path,
+            name,
+            description,
+            icon,
+            color,
+        } => {
+            let update = DescribeArgs {
+                name,
+                description,
+                icon,
+                color,
+            };
+            cmd_describe(&path, update, &unlock).await
+        }
+
+        Commands::ChangePassword {
+            path,
+            new_keyfile,
+            remove_keyfile,
+            dry_run,
+        } => {
+            let new_keyfile = match (new_keyfile.as_deref(), remove_keyfile) {
+                (Some(new), _) => Some(new),
+                (None, true) => None,
+                (None, false) => keyfile,
+            };
+            cmd_change_password(&path, keyfile, new_keyfile, dry_run).await
+        }
 
-        Commands::ShowRecoveryKey { path } => cmd_show_recovery_key(&path).await,
+        Commands::ShowRecoveryKey { path } => cmd_show_recovery_key(&path, &unlock).await,
 
         Commands::ResetPassword { path } => cmd_reset_password(&path).await,
 
-        Commands::MigrateVault { path } => cmd_migrate_vault(&path).await,
+        Commands::MigrateVault { path } => cmd_migrate_vault(&path, &unlock).await,
 
-        Commands::Check { path, shallow } => cmd_check(&path, shallow).await,
+        Commands::Check { path, shallow } => cmd_check(&path, shallow, &unlock).await,
 
-        Commands::GdriveAuth {
-            client_id,
+        Commands::Maintain {
+            vault_path,
+            target_format,
+            once,
+            files_per_minute,
+            bytes_per_second,
+        } => {
+            cmd_maintain(
+                &vault_path,
+                &target_format,
+                once,
+                files_per_minute,
+                bytes_per_second,
+                &unlock,
+            )
+            .await
+        }
+
+        Commands::Verify {
+            vault_path,
+            fast,
+            sample_percent,
+            seed,
+        } => {
+            let options = VerifyOptions {
+                fast,
+                sample_percent,
+                seed: seed.unwrap_or_else(|| {
+                    std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_nanos() as u64)
+                        .unwrap_or_default()
+                }),
+            };
+            cmd_verify(&vault_path, &options, &unlock).await
+        }
+
+        Commands::Stats { vault_path } => cmd_stats(&vault_path, &unlock).await,
+
+        Commands::Diff {
+            vault_path,
+            other_vault_path,
+            other_keyfile,
+            local_dir,
+            fast,
+            ignore_timestamps,
+            ignore,
+            json,
+        } => {
+            let target = match (other_vault_path, local_dir) {
+                (Some(path), _) => DiffWith::Vault {
+                    path,
+                    keyfile: other_keyfile,
+                },
+                (None, Some(dir)) => DiffWith::LocalDir(dir),
+                (None, None) => unreachable!("clap requires one of them"),
+            };
+            cmd_diff(
+                &vault_path,
+                target,
+                fast,
+                ignore_timestamps,
+                &ignore,
+                json,
+                &unlock,
+            )
+            .await
+        }
+
+        Commands::Grep {
+            vault_path,
+            query,
+            limit,
+        } => cmd_grep(&vault_path, &query, limit, &unlock).await,
+
+        Commands::Reindex {
+            vault_path,
+            disable,
+        } => cmd_reindex(&vault_path, disable, &unlock).await,
+
+        Commands::Backup {
+            vault_path,
+            since,
+            out,
+        } => cmd_backup(&vault_path, since, &out, &unlock).await,
+
+        Commands::ExportPlaintext {
+            vault_path,
+            dest,
+            prefix,
+            i_understand_plaintext,
+            verify,
+            allow_synced_dest,
+            clean_up,
+        } => {
+            cmd_export_plaintext(
+                &vault_path,
+                &dest,
+                &prefix,
+                ExportPlaintextFlags {
+                    i_understand_plaintext,
+                    verify,
+                    allow_synced_dest,
+                    clean_up,
+                },
+                &unlock,
+            )
+            .await
+        }
+
+        Commands::GdriveAuth {
+            client_id,
             client_secret,
             output,
         } => cmd_gdrive_auth(client_id, client_secret, &output).await,
@@ -515,14 +1234,17 @@ async fn main() -> Result<()> {
             folder_id,
             tokens,
             strength,
-        } => cmd_gdrive_create(&name, &folder_id, &tokens, strength).await,
+        } => cmd_gdrive_create(&name, &folder_id, &tokens, strength, keyfile).await,
 
-        Commands::GdriveOpen { folder_id, tokens } => cmd_gdrive_open(&folder_id, &tokens).await,
+        Commands::GdriveOpen { folder_id, tokens } => {
+            cmd_gdrive_open(&folder_id, &tokens, &unlock).await
+        }
 
         Commands::Sync {
             vault_path,
             strategy,
-        } => cmd_sync(&vault_path, strategy).await,
+            paths,
+        } => cmd_sync(&vault_path, strategy, paths, &unlock, cli.allow_exec_hooks).await,
 
         Commands::SyncStatus { vault_path } => cmd_sync_status(&vault_path).await,
 
@@ -531,8 +1253,18 @@ async fn main() -> Result<()> {
         Commands::SyncResolve {
             vault_path,
             file,
+            all,
             strategy,
-        } => cmd_sync_resolve(&vault_path, &file, strategy).await,
+            show,
+        } => match (file, strategy) {
+            (Some(file), strategy) => {
+                cmd_sync_resolve(&vault_path, &file, strategy, show, &unlock).await
+            }
+            (None, Some(strategy)) if all => {
+                cmd_sync_resolve_all(&vault_path, strategy, &unlock).await
+            }
+            _ => unreachable!("clap requires --file, or --all with --strategy"),
+        },
 
         Commands::SyncConfigure {
             vault_path,
@@ -540,7 +1272,27 @@ async fn main() -> Result<()> {
             interval,
         } => cmd_sync_configure(&vault_path, mode, interval).await,
 
-        Commands::Migrate { path, dry_run } => cmd_migrate(&path, dry_run).await,
+        Commands::Migrate {
+            path,
+            list,
+            dry_run,
+            apply,
+            all: _,
+            rollback,
+        } => {
+            let action = if list {
+                MigrateAction::List
+            } else if dry_run {
+                MigrateAction::DryRun
+            } else if let Some(id) = apply {
+                MigrateAction::Apply(id)
+            } else if let Some(id) = rollback {
+                MigrateAction::Rollback(id)
+            } else {
+                MigrateAction::All
+            };
+            cmd_migrate(&path, action, &unlock).await
+        }
 
         Commands::Completions { shell, install } => {
             if install {
@@ -577,7 +1329,86 @@ async fn main() -> Result<()> {
             parity_shards,
         } => cmd_raid_configure(&vault_path, mode, data_shards, parity_shards).await,
 
-        Commands::Webdav { path, port } => cmd_webdav(&path, port).await,
+        #[cfg(feature = "webdav")]
+        Commands::ServeWebdav {
+            path,
+            port,
+            metrics_port,
+        } => cmd_serve_webdav(&path, port, metrics_port, &unlock, cli.allow_exec_hooks).await,
+
+        Commands::Mount {
+            path,
+            at,
+            read_only,
+            allow_other,
+        } => cmd_mount(&path, &at, read_only, allow_other, &unlock).await,
+
+        Commands::Unmount { at, lazy } => cmd_unmount(&at, lazy).await,
+
+        Commands::Keyfile { action } => match action {
+            KeyfileCommands::Generate { output } => cmd_keyfile_generate(&output),
+        },
+
+        Commands::Policy { action } => match action {
+            PolicyCommands::Set {
+                vault_path,
+                dir,
+                versioning,
+                compression,
+                sync,
+                dedup,
+                clear,
+            } => {
+                let update = PolicyUpdate {
+                    versioning,
+                    compression,
+                    sync,
+                    dedup,
+                    clear,
+                };
+                cmd_policy_set(&vault_path, &dir, update, &unlock).await
+            }
+            PolicyCommands::Show { vault_path, path } => {
+                cmd_policy_show(&vault_path, &path, &unlock).await
+            }
+        },
+
+        Commands::Template { action } => match action {
+            TemplateCommands::Apply {
+                vault_path,
+                template,
+                on_conflict,
+            } => cmd_template_apply(&vault_path, &template, on_conflict, &unlock).await,
+            TemplateCommands::Export {
+                vault_path,
+                output,
+                include_files,
+                max_file_size,
+            } => {
+                let include_files = include_files.then_some(max_file_size);
+                cmd_template_export(&vault_path, output.as_deref(), include_files, &unlock).await
+            }
+        },
+
+        Commands::Attach { action } => match action {
+            AttachCommands::Add {
+                vault_path,
+                dir,
+                provider,
+                config,
+            } => cmd_attach_add(&vault_path, &dir, provider, &config, &unlock).await,
+            AttachCommands::List { vault_path } => cmd_attach_list(&vault_path, &unlock).await,
+            AttachCommands::Remove { vault_path, dir } => {
+                cmd_attach_remove(&vault_path, &dir, &unlock).await
+            }
+        },
+
+        Commands::Hooks { action } => match action {
+            HooksCommands::List { vault_path } => cmd_hooks_list(&vault_path).await,
+            HooksCommands::Test { vault_path, event } => {
+                cmd_hooks_test(&vault_path, &event, cli.allow_exec_hooks).await
+            }
+        },
     }
 }
 
@@ -585,7 +1416,7 @@ async fn main() -> Result<()> {
 const MIN_PASSWORD_LENGTH: usize = 8;
 
 /// Validate that a password meets the minimum length requirement.
-fn validate_password_strength(password: &[u8]) -> Result<()> {
+fn validate_password_strength(password: &Secret) -> Result<()> {
     if password.len() < MIN_PASSWORD_LENGTH {
         anyhow::bail!(
             "Password must be at least {} characters",
@@ -596,18 +1427,137 @@ fn validate_password_strength(password: &[u8]) -> Result<()> {
 }
 
 /// Prompt for password securely.
-fn prompt_password(prompt: &str) -> Result<Zeroizing<Vec<u8>>> {
+///
+/// The entered string's buffer becomes the [`Secret`], so no unwiped copy
+/// is left behind.
+fn prompt_password(prompt: &str) -> Result<Secret> {
     // Allow non-interactive use via environment variable (useful for scripting/testing)
-    if let Ok(mut pw) = std::env::var("AXIOMVAULT_PASSWORD") {
+    if let Ok(pw) = std::env::var("AXIOMVAULT_PASSWORD") {
         if !pw.is_empty() {
-            let bytes = Zeroizing::new(pw.as_bytes().to_vec());
-            pw.zeroize();
-            return Ok(bytes);
+            return Ok(Secret::from(pw));
         }
     }
-    let mut password = rpassword::prompt_password(prompt).context("Failed to read password")?;
-    let bytes = Zeroizing::new(password.as_bytes().to_vec());
-    password.zeroize();
+    let password = rpassword::prompt_password(prompt).context("Failed to read password")?;
+    Ok(Secret::from(password))
+}
+
+/// Read keyfile content from the `--keyfile` path, if one was given.
+fn read_keyfile(path: Option<&Path>) -> Result<Option<Zeroizing<Vec<u8>>>> {
+    path.map(|p| {
+        std::fs::read(p)
+            .map(Zeroizing::new)
+            .with_context(|| format!("Failed to read keyfile {}", p.display()))
+    })
+    .transpose()
+}
+
+/// How to unlock a vault: password (plus optional keyfile) or an externally
+/// supplied master key.
+struct Unlock {
+    keyfile: Option<PathBuf>,
+    key_file_hex: Option<PathBuf>,
+    key_from_env: Option<String>,
+}
+
+impl Unlock {
+    /// The external master key from `--key-file-hex` or `--key-from-env`, if
+    /// either was given.
+    fn external_key(&self) -> Result<Option<MasterKey>> {
+        let mut encoded = match (&self.key_file_hex, &self.key_from_env) {
+            (Some(path), _) => Zeroizing::new(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read key file {}", path.display()))?,
+            ),
+            (None, Some(var)) => Zeroizing::new(
+                std::env::var(var)
+                    .with_context(|| format!("Environment variable {var} is not set"))?,
+            ),
+            (None, None) => return Ok(None),
+        };
+        let bytes = decode_hex(encoded.trim());
+        encoded.zeroize();
+        let bytes = bytes?;
+        let key = MasterKey::from_external(&bytes).context("Unusable external key")?;
+        Ok(Some(key))
+    }
+
+    /// Open the vault at `provider_config`, prompting for the password
+    /// unless an external key was supplied.
+    async fn open(
+        &self,
+        manager: &VaultManager,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+    ) -> Result<VaultSession> {
+        let session = match self.external_key()? {
+            Some(key) => {
+                manager
+                    .open_vault_with_key(provider_type, provider_config, key)
+                    .await
+            }
+            None => {
+                let keyfile = read_keyfile(self.keyfile.as_deref())?;
+                let password = prompt_password("Enter password: ")?;
+                manager
+                    .open_vault_with_keyfile(
+                        provider_type,
+                        provider_config,
+                        &password,
+                        keyfile.as_deref().map(Vec::as_slice),
+                    )
+                    .await
+            }
+        };
+        session.map_err(|err| match err {
+            axiomvault_common::Error::MigrationRequired(_) => {
+                anyhow::anyhow!("{}; apply with `axiomvault migrate`", err)
+            }
+            err => anyhow::Error::new(err).context("Failed to open vault"),
+        })
+    }
+
+    /// Open the vault at `provider_config` to migrate it, whatever
+    /// migrations are pending.
+    async fn open_for_migration(
+        &self,
+        manager: &VaultManager,
+        provider_type: &str,
+        provider_config: serde_json::Value,
+    ) -> Result<VaultSession> {
+        let session = match self.external_key()? {
+            // Only password vaults have migrations that need asking.
+            Some(key) => {
+                manager
+                    .open_vault_with_key(provider_type, provider_config, key)
+                    .await
+            }
+            None => {
+                let keyfile = read_keyfile(self.keyfile.as_deref())?;
+                let password = prompt_password("Enter password: ")?;
+                manager
+                    .open_vault_for_migration(
+                        provider_type,
+                        provider_config,
+                        &password,
+                        keyfile.as_deref().map(Vec::as_slice),
+                    )
+                    .await
+            }
+        };
+        session.context("Failed to open vault")
+    }
+}
+
+/// Decode a hex string into a zeroizing buffer.
+fn decode_hex(encoded: &str) -> Result<Zeroizing<Vec<u8>>> {
+    if !encoded.bytes().all(|b| b.is_ascii_hexdigit()) || !encoded.len().is_multiple_of(2) {
+        anyhow::bail!("Key must be an even number of hex digits");
+    }
+    let mut bytes = Zeroizing::new(Vec::with_capacity(encoded.len() / 2));
+    for pair in encoded.as_bytes().chunks(2) {
+        let nibble = |b: u8| (b as char).to_digit(16).unwrap_or(0) as u8;
+        bytes.push(nibble(pair[0]) << 4 | nibble(pair[1]));
+    }
     Ok(bytes)
 }
 
@@ -720,15 +1670,36 @@ fn install_completions(shell: Shell) -> Result<()> {
 }
 
 /// Create a new vault.
-async fn cmd_create(name: &str, path: &Path, strength: KdfStrength) -> Result<()> {
+async fn cmd_create(
+    name: &str,
+    path: &Path,
+    strength: KdfStrength,
+    template: Option<&Path>,
+    unlock: &Unlock,
+) -> Result<()> {
     info!("Creating new vault");
 
+    if let Some(key) = unlock.external_key()? {
+        if template.is_some() {
+            anyhow::bail!("Templates cannot be applied to vaults with an external key");
+        }
+        return cmd_create_with_key(name, path, key).await;
+    }
+
+    let keyfile = read_keyfile(unlock.keyfile.as_deref())?;
+    let template = template.map(load_template).transpose()?;
+    let template_sync = template
+        .as_ref()
+        .map(template_sync_config)
+        .transpose()?
+        .flatten();
+
     let kdf_params = kdf_params_from(strength);
 
     let password = prompt_password("Enter password: ")?;
     let confirm = prompt_password("Confirm password: ")?;
 
-    if password != confirm {
+    if password.expose() != confirm.expose() {
         anyhow::bail!("Passwords do not match");
     }
 
@@ -742,25 +1713,80 @@ async fn cmd_create(name: &str, path: &Path, strength: KdfStrength) -> Result<()
         "root": vault_path
     });
 
-    let creation = manager
-        .create_vault(vault_id, &password, "local", provider_config, kdf_params)
-        .await
-        .context("Failed to create vault")?;
+    let keyfile = keyfile.as_deref().map(Vec::as_slice);
+    let creation = match &template {
+        Some(template) => {
+            manager
+                .create_vault_from_template(
+                    vault_id,
+                    &password,
+                    keyfile,
+                    "local",
+                    provider_config,
+                    kdf_params,
+                    template,
+                )
+                .await
+        }
+        None => {
+            manager
+                .create_vault_with_keyfile(
+                    vault_id,
+                    &password,
+                    keyfile,
+                    "local",
+                    provider_config,
+                    kdf_params,
+                )
+                .await
+        }
+    }
+    .context("Failed to create vault")?;
+    if let Some(config) = template_sync {
+        write_sync_config(path, &config).await?;
+    }
 
     println!("Vault created successfully!");
+    println!("  Name: {}", creation.session.config().display_name());
     println!("  ID: {}", creation.session.vault_id());
     println!("  Location: {}", path.display());
     println!("  Provider: {}", creation.session.config().provider_type);
+    if template.is_some() {
+        println!("  Template: applied");
+    }
     display_recovery_words(&creation.recovery_words);
 
     Ok(())
 }
 
 /// Open vault for interactive session.
-async fn cmd_open(path: &Path) -> Result<()> {
+/// Create a new vault whose master key is supplied externally.
+async fn cmd_create_with_key(name: &str, path: &Path, key: MasterKey) -> Result<()> {
+    let vault_id = VaultId::new(name).context("Invalid vault name")?;
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path.to_string_lossy()
+    });
+
+    let session = manager
+        .create_vault_with_key(vault_id, key, "local", provider_config)
+        .await
+        .context("Failed to create vault")?;
+
+    println!("Vault created successfully!");
+    println!("  ID: {}", session.vault_id());
+    println!("  Location: {}", path.display());
+    println!("  Provider: {}", session.config().provider_type);
+    println!();
+    println!("This vault has no password and no recovery key.");
+    println!("It opens only with the key you supplied; keep that key safe.");
+
+    Ok(())
+}
+
+async fn cmd_open(path: &Path, unlock: &Unlock) -> Result<()> {
     info!("Opening vault");
 
-    let password = prompt_password("Enter password: ")?;
     let vault_path = path.to_string_lossy().to_string();
 
     let manager = VaultManager::new();
@@ -768,12 +1794,10 @@ async fn cmd_open(path: &Path) -> Result<()> {
         "root": vault_path
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     println!("Vault opened successfully!");
+    println!("  Name: {}", session.config().display_name());
     println!("  ID: {}", session.vault_id());
     println!("  Session: {}", session.handle().as_str());
 
@@ -785,8 +1809,7 @@ async fn cmd_open(path: &Path) -> Result<()> {
 }
 
 /// List directory contents.
-async fn cmd_list(vault_path: &Path, dir: &str) -> Result<()> {
-    let password = prompt_password("Enter password: ")?;
+async fn cmd_list(vault_path: &Path, dir: &str, unlock: &Unlock) -> Result<()> {
     let path_str = vault_path.to_string_lossy().to_string();
 
     let manager = VaultManager::new();
@@ -794,10 +1817,7 @@ async fn cmd_list(vault_path: &Path, dir: &str) -> Result<()> {
         "root": path_str
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     let ops = VaultOperations::new(&session).context("Failed to create operations handler")?;
     let vault_dir = VaultPath::parse(dir).context("Invalid directory path")?;
@@ -810,13 +1830,13 @@ async fn cmd_list(vault_path: &Path, dir: &str) -> Result<()> {
     if contents.is_empty() {
         println!("Directory is empty.");
     } else {
-        println!("Contents of {}:", dir);
+        println!("Contents of {}:", vault_dir.display_lossy());
         for (name, is_dir, size) in contents {
             if is_dir {
-                println!("  [DIR]  {}/", name);
+                println!("  [DIR]  {}/", escape_lossy(&name));
             } else {
                 let size_str = size.map(|s| format!("{} bytes", s)).unwrap_or_default();
-                println!("  [FILE] {} ({})", name, size_str);
+                println!("  [FILE] {} ({})", escape_lossy(&name), size_str);
             }
         }
     }
@@ -825,12 +1845,27 @@ async fn cmd_list(vault_path: &Path, dir: &str) -> Result<()> {
 }
 
 /// Add a file to the vault.
-async fn cmd_add(vault_path: &Path, source: &Path, dest: &str) -> Result<()> {
+async fn cmd_add(
+    vault_path: &Path,
+    source: &Path,
+    dest: &str,
+    shred_source: bool,
+    unlock: &Unlock,
+) -> Result<()> {
     info!("Adding file to vault");
 
-    let password = prompt_password("Enter password: ")?;
     let path_str = vault_path.to_string_lossy().to_string();
 
+    // Refuse symlinks and hard-linked files before anything is written.
+    let shred_sources = [source.to_path_buf()];
+    if shred_source {
+        shred::check_shreddable(source, &shred_sources).context("Cannot shred source file")?;
+        eprintln!(
+            "Warning: shredding cannot guarantee the old data is gone on SSDs or \
+             copy-on-write filesystems; use full-disk encryption to protect them."
+        );
+    }
+
     // Read source file
     let content = tokio::fs::read(source)
         .await
@@ -841,10 +1876,7 @@ async fn cmd_add(vault_path: &Path, source: &Path, dest: &str) -> Result<()> {
         "root": path_str
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     let ops = VaultOperations::new(&session)?;
     let dest_path = VaultPath::parse(dest).context("Invalid destination path")?;
@@ -855,18 +1887,39 @@ async fn cmd_add(vault_path: &Path, source: &Path, dest: &str) -> Result<()> {
 
     println!(
         "File added successfully: {} ({} bytes)",
-        dest,
+        dest_path.display_lossy(),
         content.len()
     );
 
+    if shred_source {
+        // Only shred against what the vault actually returns.
+        let vault_copy = ops
+            .read_file(&dest_path)
+            .await
+            .context("Failed to read back added file; source left intact")?;
+        match shred::shred_file(source, &shred_sources, &vault_copy)
+            .context("Failed to shred source file")?
+        {
+            ShredOutcome::Overwritten { .. } => {
+                println!("Source shredded: {}", source.display());
+            }
+            ShredOutcome::Deleted { filesystem } => {
+                println!(
+                    "Source deleted without overwriting: {} is on {}, which is copy-on-write",
+                    source.display(),
+                    filesystem
+                );
+            }
+        }
+    }
+
     Ok(())
 }
 
 /// Extract a file from the vault.
-async fn cmd_extract(vault_path: &Path, source: &str, dest: &Path) -> Result<()> {
+async fn cmd_extract(vault_path: &Path, source: &str, dest: &Path, unlock: &Unlock) -> Result<()> {
     info!("Extracting file from vault");
 
-    let password = prompt_password("Enter password: ")?;
     let path_str = vault_path.to_string_lossy().to_string();
 
     let manager = VaultManager::new();
@@ -874,37 +1927,40 @@ async fn cmd_extract(vault_path: &Path, source: &str, dest: &Path) -> Result<()>
         "root": path_str
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     let ops = VaultOperations::new(&session)?;
     let source_path = VaultPath::parse(source).context("Invalid source path")?;
 
-    let content = ops
-        .read_file(&source_path)
-        .await
-        .context("Failed to read file from vault")?;
+    use std::io::Write;
 
-    tokio::fs::write(dest, &content)
-        .await
-        .context("Failed to write output file")?;
+    // Decrypt straight into the output file rather than buffering the
+    // plaintext; a partial file is removed if extraction fails.
+    let file = std::fs::File::create(dest).context("Failed to create output file")?;
+    let mut writer = std::io::BufWriter::new(file);
+    let written = match ops.read_file_to_writer(&source_path, &mut writer).await {
+        Ok(written) => written,
+        Err(e) => {
+            drop(writer);
+            let _ = std::fs::remove_file(dest);
+            return Err(e).context("Failed to read file from vault");
+        }
+    };
+    writer.flush().context("Failed to write output file")?;
 
     println!(
         "File extracted successfully: {} ({} bytes)",
         dest.display(),
-        content.len()
+        written
     );
 
     Ok(())
 }
 
 /// Create a directory in the vault.
-async fn cmd_mkdir(vault_path: &Path, dir: &str) -> Result<()> {
+async fn cmd_mkdir(vault_path: &Path, dir: &str, unlock: &Unlock) -> Result<()> {
     info!("Creating directory");
 
-    let password = prompt_password("Enter password: ")?;
     let path_str = vault_path.to_string_lossy().to_string();
 
     let manager = VaultManager::new();
@@ -912,10 +1968,7 @@ async fn cmd_mkdir(vault_path: &Path, dir: &str) -> Result<()> {
         "root": path_str
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     let ops = VaultOperations::new(&session)?;
     let dir_path = VaultPath::parse(dir).context("Invalid directory path")?;
@@ -924,16 +1977,79 @@ async fn cmd_mkdir(vault_path: &Path, dir: &str) -> Result<()> {
         .await
         .context("Failed to create directory")?;
 
-    println!("Directory created: {}", dir);
+    println!("Directory created: {}", dir_path.display_lossy());
 
     Ok(())
 }
 
-/// Remove a file from the vault.
-async fn cmd_remove(vault_path: &Path, file: &str) -> Result<()> {
-    info!("Removing file from vault");
+/// Policy changes requested on the command line.
+struct PolicyUpdate {
+    versioning: Option<String>,
+    compression: Option<PolicyToggleArg>,
+    sync: Option<PolicySyncArg>,
+    dedup: Option<PolicyToggleArg>,
+    clear: bool,
+}
+
+impl PolicyUpdate {
+    /// Apply the requested changes to the current overrides.
+    fn apply(&self, current: Option<DirectoryPolicy>) -> Result<DirectoryPolicy> {
+        let mut policy = if self.clear {
+            DirectoryPolicy::default()
+        } else {
+            current.unwrap_or_default()
+        };
+
+        if let Some(versioning) = &self.versioning {
+            policy.versioning = if versioning.eq_ignore_ascii_case("inherit") {
+                None
+            } else {
+                Some(versioning.parse::<Versioning>()?)
+            };
+        }
+        if let Some(compression) = self.compression {
+            policy.compression = toggle_from(compression);
+        }
+        if let Some(sync) = self.sync {
+            policy.sync = match sync {
+                PolicySyncArg::Include => Some(SyncPolicy::Include),
+                PolicySyncArg::Exclude => Some(SyncPolicy::Exclude),
+                PolicySyncArg::Inherit => None,
+            };
+        }
+        if let Some(dedup) = self.dedup {
+            policy.dedup = toggle_from(dedup);
+        }
+        Ok(policy)
+    }
+}
+
+/// Convert a policy toggle to an override (`None` inherits).
+fn toggle_from(arg: PolicyToggleArg) -> Option<bool> {
+    match arg {
+        PolicyToggleArg::On => Some(true),
+        PolicyToggleArg::Off => Some(false),
+        PolicyToggleArg::Inherit => None,
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Set policy overrides on a vault directory.
+async fn cmd_policy_set(
+    vault_path: &Path,
+    dir: &str,
+    update: PolicyUpdate,
+    unlock: &Unlock,
+) -> Result<()> {
+    info!("Setting directory policy");
 
-    let password = prompt_password("Enter password: ")?;
     let path_str = vault_path.to_string_lossy().to_string();
 
     let manager = VaultManager::new();
@@ -941,251 +2057,1421 @@ async fn cmd_remove(vault_path: &Path, file: &str) -> Result<()> {
         "root": path_str
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     let ops = VaultOperations::new(&session)?;
-    let file_path = VaultPath::parse(file).context("Invalid file path")?;
+    let dir_path = VaultPath::parse(dir).context("Invalid directory path")?;
 
-    ops.delete_file(&file_path)
+    let current = ops
+        .directory_policy(&dir_path)
         .await
-        .context("Failed to remove file")?;
+        .context("Failed to read directory policy")?;
+    let policy = update.apply(current)?;
+    ops.set_directory_policy(&dir_path, policy)
+        .await
+        .context("Failed to set directory policy")?;
 
-    println!("File removed: {}", file);
+    let effective = ops.effective_policy(&dir_path).await?;
+    println!("Policy set on {}", dir_path.display_lossy());
+    println!("  Versioning:  {}", effective.versioning);
+    println!("  Compression: {}", on_off(effective.compression));
+    println!("  Sync:        {}", effective.sync);
+    println!("  Dedup:       {}", on_off(effective.dedup));
 
     Ok(())
 }
 
-/// Show vault information.
-async fn cmd_info(path: &Path) -> Result<()> {
-    info!("Getting vault info");
-
-    let password = prompt_password("Enter password: ")?;
-    let path_str = path.to_string_lossy().to_string();
+/// Attach a provider at a directory of the vault.
+async fn cmd_attach_add(
+    vault_path: &Path,
+    dir: &str,
+    provider_type: String,
+    config: &str,
+    unlock: &Unlock,
+) -> Result<()> {
+    info!("Attaching provider");
 
+    let path_str = vault_path.to_string_lossy().to_string();
     let manager = VaultManager::new();
-    let provider_config = serde_json::json!({
-        "root": path_str
-    });
-
-    let session = manager
-        .open_vault("local", provider_config, &password)
+    let mut session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
+
+    let point = AttachPoint {
+        vault_path: VaultPath::parse(dir).context("Invalid directory path")?,
+        provider_type,
+        provider_config: serde_json::from_str(config).context("Invalid provider config")?,
+    };
+    let dir_path = point.vault_path.clone();
+    let provider_type = point.provider_type.clone();
+    manager
+        .attach(&mut session, point)
         .await
-        .context("Failed to open vault")?;
-
-    let config = session.config();
+        .context("Failed to attach provider")?;
 
-    println!("Vault Information:");
-    println!("  ID: {}", config.id);
     println!(
-        "  Version: {}.{}",
-        config.version.major, config.version.minor
+        "Attached {} provider at {}",
+        provider_type,
+        dir_path.display_lossy()
     );
-    println!("  Provider: {}", config.provider_type);
-    println!("  Created: {}", config.created_at);
-    println!("  Modified: {}", config.modified_at);
-    println!("  KDF Parameters:");
-    println!("    Memory: {} KiB", config.kdf_params.memory_cost);
-    println!("    Time: {} iterations", config.kdf_params.time_cost);
-    println!("    Parallelism: {}", config.kdf_params.parallelism);
-
     Ok(())
 }
 
-/// Change vault password.
-async fn cmd_change_password(path: &Path) -> Result<()> {
-    info!("Changing vault password");
-
-    let old_password = prompt_password("Enter current password: ")?;
-    let new_password = prompt_password("Enter new password: ")?;
-    let confirm = prompt_password("Confirm new password: ")?;
+/// List the attach points of a vault.
+async fn cmd_attach_list(vault_path: &Path, unlock: &Unlock) -> Result<()> {
+    let path_str = vault_path.to_string_lossy().to_string();
+    let manager = VaultManager::new();
+    let session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
 
-    if new_password != confirm {
-        anyhow::bail!("New passwords do not match");
+    let config = session.config();
+    if config.attachments.is_empty() {
+        println!("No attach points.");
+        return Ok(());
     }
+    for point in &config.attachments {
+        println!(
+            "{}  {}  {}",
+            point.vault_path.display_lossy(),
+            point.provider_type,
+            point.provider_config
+        );
+    }
+    Ok(())
+}
 
-    validate_password_strength(&new_password)?;
-
-    let path_str = path.to_string_lossy().to_string();
+/// Detach the provider at a directory of the vault.
+async fn cmd_attach_remove(vault_path: &Path, dir: &str, unlock: &Unlock) -> Result<()> {
+    info!("Detaching provider");
 
+    let path_str = vault_path.to_string_lossy().to_string();
     let manager = VaultManager::new();
-    let provider_config = serde_json::json!({
-        "root": path_str
-    });
+    let mut session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
 
-    let mut session = manager
-        .open_vault("local", provider_config, &old_password)
+    let dir_path = VaultPath::parse(dir).context("Invalid directory path")?;
+    let point = manager
+        .detach(&mut session, &dir_path)
         .await
-        .context("Failed to open vault")?;
-
-    session
-        .change_password(&old_password, &new_password)
-        .context("Failed to change password")?;
-
-    // Save updated config
-    manager.save_config(&session).await?;
-
-    println!("Password changed successfully!");
+        .context("Failed to detach provider")?;
 
+    println!(
+        "Detached {} provider from {}",
+        point.provider_type,
+        point.vault_path.display_lossy()
+    );
     Ok(())
 }
 
-/// Show recovery key for a vault.
-async fn cmd_show_recovery_key(path: &Path) -> Result<()> {
-    info!("Showing recovery key");
+/// List the hooks configured for a vault.
+async fn cmd_hooks_list(vault_path: &Path) -> Result<()> {
+    let hooks = load_hooks_config(vault_path)
+        .await?
+        .map(|config| config.hooks)
+        .unwrap_or_default();
+    if hooks.is_empty() {
+        println!(
+            "No hooks configured in {}",
+            hooks_config_path(vault_path).display()
+        );
+        return Ok(());
+    }
 
-    let password = prompt_password("Enter password: ")?;
-    let path_str = path.to_string_lossy().to_string();
+    for (index, hook) in hooks.iter().enumerate() {
+        let events = if hook.event_filter.events.is_empty() {
+            "all events".to_string()
+        } else {
+            hook.event_filter.events.join(", ")
+        };
+        let action = match &hook.action {
+            HookAction::Http { url, .. } => format!("POST {}", url),
+            HookAction::Command { argv, .. } => {
+                format!("run {} (needs --allow-exec-hooks)", argv.join(" "))
+            }
+        };
+        println!("{}: on {} -> {}", hook.label(index), events, action);
+        if hook.event_filter.every > 1 {
+            println!("    every {} matching events", hook.event_filter.every);
+        }
+        println!(
+            "    at most once per {}s, timeout {}s",
+            hook.min_interval_secs,
+            hook.action.timeout().as_secs()
+        );
+    }
+    Ok(())
+}
+
+/// Fire a test event at a vault's hooks and report how they did.
+async fn cmd_hooks_test(vault_path: &Path, event: &str, allow_exec_hooks: bool) -> Result<()> {
+    let Some(hooks) = load_hooks(vault_path, allow_exec_hooks).await? else {
+        anyhow::bail!(
+            "No hooks configured in {}",
+            hooks_config_path(vault_path).display()
+        );
+    };
+
+    hooks.dispatch(&HookEvent::new(event).with_field("test", true));
+    hooks.wait_idle().await;
+
+    let mut failed = false;
+    for status in hooks.status() {
+        match (status.runs, &status.last_error) {
+            (0, _) => println!("{}: not triggered", status.name),
+            (_, Some(error)) => {
+                failed = true;
+                println!("{}: failed: {}", status.name, error);
+            }
+            (_, None) => println!("{}: ok", status.name),
+        }
+    }
+    if failed {
+        anyhow::bail!("Some hooks failed");
+    }
+    Ok(())
+}
+
+/// Show the effective policy of a vault path.
+async fn cmd_policy_show(vault_path: &Path, path: &str, unlock: &Unlock) -> Result<()> {
+    info!("Showing effective policy");
+
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let session = unlock.open(&manager, "local", provider_config).await?;
+
+    let ops = VaultOperations::new(&session)?;
+    let vault_path = VaultPath::parse(path).context("Invalid path")?;
+
+    let effective = ops
+        .effective_policy(&vault_path)
+        .await
+        .context("Failed to resolve policy")?;
+
+    // Report where each setting comes from, nearest ancestor first.
+    let mut overrides = Vec::new();
+    let mut dir = Some(vault_path.clone());
+    while let Some(current) = dir {
+        if let Ok(Some(policy)) = ops.directory_policy(&current).await {
+            overrides.push((current.clone(), policy));
+        }
+        dir = current.parent();
+    }
+
+    println!("Effective policy for {}:", vault_path.display_lossy());
+    println!("  Versioning:  {}", effective.versioning);
+    println!("  Compression: {}", on_off(effective.compression));
+    println!("  Sync:        {}", effective.sync);
+    println!("  Dedup:       {}", on_off(effective.dedup));
+
+    if overrides.is_empty() {
+        println!("\nNo directory overrides; vault defaults apply.");
+    } else {
+        println!("\nOverrides (nearest first):");
+        for (dir, policy) in overrides {
+            let json = serde_json::to_string(&policy)?;
+            println!("  {}  {}", dir.display_lossy(), json);
+        }
+    }
+
+    Ok(())
+}
+
+/// Load and validate a template manifest.
+fn load_template(path: &Path) -> Result<VaultTemplate> {
+    VaultTemplate::load(path)
+        .with_context(|| format!("Invalid template manifest {}", path.display()))
+}
+
+/// Sync settings carried by a template, checked against `SyncConfig`.
+fn template_sync_config(template: &VaultTemplate) -> Result<Option<SyncConfig>> {
+    template
+        .sync
+        .clone()
+        .map(serde_json::from_value)
+        .transpose()
+        .context("sync: invalid sync settings in template")
+}
+
+/// Sync settings file of a local vault, as written by `sync-configure`.
+fn sync_config_path(vault_path: &Path) -> PathBuf {
+    vault_path.join(".axiom_sync").join("sync_config.json")
+}
+
+async fn write_sync_config(vault_path: &Path, config: &SyncConfig) -> Result<()> {
+    let config_file = sync_config_path(vault_path);
+    if let Some(dir) = config_file.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .context("Failed to create sync directory")?;
+    }
+    let config_json = serde_json::to_string_pretty(config).context("Failed to serialize config")?;
+    tokio::fs::write(&config_file, config_json)
+        .await
+        .context("Failed to write sync config")
+}
+
+/// Apply a template manifest to an existing vault.
+async fn cmd_template_apply(
+    vault_path: &Path,
+    template: &Path,
+    on_conflict: TemplateConflictArg,
+    unlock: &Unlock,
+) -> Result<()> {
+    info!("Applying vault template");
+
+    let template = load_template(template)?;
+    let sync_config = template_sync_config(&template)?;
+    let on_conflict = match on_conflict {
+        TemplateConflictArg::Error => TemplateConflict::Error,
+        TemplateConflictArg::Skip => TemplateConflict::Skip,
+        TemplateConflictArg::Overwrite => TemplateConflict::Overwrite,
+    };
+
+    let sync_exists = sync_config_path(vault_path).exists();
+    if sync_config.is_some() && sync_exists && on_conflict == TemplateConflict::Error {
+        anyhow::bail!("sync: the vault already has sync settings");
+    }
+
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let mut session = unlock.open(&manager, "local", provider_config).await?;
+
+    // Check the defaults first so a conflict there changes nothing.
+    let mut config = session.config().clone();
+    let defaults_changed = template
+        .apply_defaults(&mut config, on_conflict)
+        .context("Failed to apply template")?;
+
+    let report = VaultOperations::new(&session)?
+        .apply_template(&template, on_conflict)
+        .await
+        .context("Failed to apply template")?;
+    if defaults_changed {
+        session.config_mut().default_policy = config.default_policy;
+        manager
+            .save_config(&session)
+            .await
+            .context("Failed to save vault config")?;
+    }
+    let sync_written =
+        sync_config.is_some() && (!sync_exists || on_conflict == TemplateConflict::Overwrite);
+    if let Some(config) = sync_config.filter(|_| sync_written) {
+        write_sync_config(vault_path, &config).await?;
+    }
+
+    println!("Template applied.");
+    println!("  Directories created: {}", report.directories_created);
+    println!("  Files created:       {}", report.files_created);
+    println!("  Files overwritten:   {}", report.files_overwritten);
+    println!("  Policies set:        {}", report.policies_set);
+    if defaults_changed {
+        println!("  Default policy updated");
+    }
+    if sync_written {
+        println!("  Sync settings written");
+    }
+    if !report.skipped.is_empty() {
+        println!("  Skipped {} existing entries:", report.skipped.len());
+        for entry in &report.skipped {
+            println!("    {}", escape_lossy(entry));
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a template manifest describing a vault's structure.
+async fn cmd_template_export(
+    vault_path: &Path,
+    output: Option<&Path>,
+    include_files: Option<u64>,
+    unlock: &Unlock,
+) -> Result<()> {
+    info!("Exporting vault template");
+
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let session = unlock.open(&manager, "local", provider_config).await?;
+
+    let mut template = VaultOperations::new(&session)?
+        .export_template(include_files)
+        .await
+        .context("Failed to export template")?;
+
+    let sync_file = sync_config_path(vault_path);
+    if sync_file.exists() {
+        let json = tokio::fs::read_to_string(&sync_file)
+            .await
+            .context("Failed to read sync config")?;
+        template.sync = Some(serde_json::from_str(&json).context("Failed to parse sync config")?);
+    }
+
+    let json = template.to_json()?;
+    match output {
+        Some(output) => {
+            tokio::fs::write(output, json)
+                .await
+                .context("Failed to write template")?;
+            println!("Template written to {}", output.display());
+            println!("  Directories: {}", template.directories.len());
+            println!("  Files:       {}", template.files.len());
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
+
+/// Remove a file from the vault.
+async fn cmd_remove(vault_path: &Path, file: &str, unlock: &Unlock) -> Result<()> {
+    info!("Removing file from vault");
+
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let session = unlock.open(&manager, "local", provider_config).await?;
+
+    let ops = VaultOperations::new(&session)?;
+    let file_path = VaultPath::parse(file).context("Invalid file path")?;
+
+    ops.delete_file(&file_path)
+        .await
+        .context("Failed to remove file")?;
+
+    println!("File removed: {}", file_path.display_lossy());
+
+    Ok(())
+}
+
+/// Show vault information.
+async fn cmd_info(path: &Path, keyfile: Option<&Path>, detailed: bool) -> Result<()> {
+    info!("Getting vault info");
+
+    let path_str = path.to_string_lossy().to_string();
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    // Without --detailed only the config is read; no password is needed.
+    let summary = if detailed {
+        let keyfile = read_keyfile(keyfile)?;
+        let password = prompt_password("Enter password: ")?;
+        manager
+            .inspect_with_password(
+                "local",
+                provider_config,
+                &password,
+                keyfile.as_deref().map(Vec::as_slice),
+            )
+            .await
+            .context("Failed to open vault")?
+    } else {
+        manager
+            .inspect("local", provider_config)
+            .await
+            .context("Failed to read vault configuration")?
+    };
+
+    println!("Vault Information:");
+    println!("  Name: {}", summary.metadata.display_name);
+    println!("  ID: {}", summary.vault_id);
+    if let Some(description) = &summary.metadata.description {
+        println!("  Description: {}", description);
+    }
+    if let Some(icon) = &summary.metadata.icon {
+        println!("  Icon: {}", icon);
+    }
+    if let Some(color) = &summary.metadata.color {
+        println!("  Color: {}", color);
+    }
+    println!(
+        "  Version: {}.{}{}",
+        summary.version.major,
+        summary.version.minor,
+        if summary.legacy_format {
+            " (legacy format)"
+        } else {
+            ""
+        }
+    );
+    println!("  Provider: {}", summary.provider_type);
+    println!("  Created: {}", summary.created_at);
+    println!("  Modified: {}", summary.modified_at);
+    println!("  KDF Parameters:");
+    println!("    Memory: {} KiB", summary.kdf_params.memory_cost);
+    println!("    Time: {} iterations", summary.kdf_params.time_cost);
+    println!("    Parallelism: {}", summary.kdf_params.parallelism);
+    println!(
+        "  Keyfile required: {}",
+        if summary.keyfile_required {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    println!(
+        "  Recovery key: {}",
+        if summary.recovery_key_configured {
+            "configured"
+        } else {
+            "not configured"
+        }
+    );
+    if let Some(tree) = summary.tree {
+        println!("  Files: {}", tree.file_count);
+        println!("  Total size: {} bytes", tree.total_size);
+    }
+
+    Ok(())
+}
+
+/// Fields given to `describe`; `None` keeps the current value.
+struct DescribeArgs {
+    name: Option<String>,
+    description: Option<String>,
+    icon: Option<String>,
+    color: Option<String>,
+}
+
+impl DescribeArgs {
+    /// Apply the given fields to `metadata`. An empty string clears an
+    /// optional field.
+    fn apply(self, mut metadata: VaultMetadata) -> VaultMetadata {
+        let clearable = |value: String| Some(value).filter(|v| !v.is_empty());
+        if let Some(name) = self.name {
+            metadata.display_name = name;
+        }
+        if let Some(description) = self.description {
+            metadata.description = clearable(description);
+        }
+        if let Some(icon) = self.icon {
+            metadata.icon = clearable(icon);
+        }
+        if let Some(color) = self.color {
+            metadata.color = clearable(color);
+        }
+        metadata
+    }
+}
+
+/// Change the vault's display metadata.
+async fn cmd_describe(path: &Path, update: DescribeArgs, unlock: &Unlock) -> Result<()> {
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path.to_string_lossy()
+    });
+    let mut session = unlock.open(&manager, "local", provider_config).await?;
+
+    let metadata = update.apply(session.config().metadata());
+    session
+        .update_metadata(metadata)
+        .await
+        .context("Failed to update vault details")?;
+
+    println!("Vault details updated.");
+    println!("  Name: {}", session.config().display_name());
+    println!("  ID: {}", session.vault_id());
+    Ok(())
+}
+
+/// Change vault password.
+async fn cmd_change_password(
+    path: &Path,
+    keyfile: Option<&Path>,
+    new_keyfile: Option<&Path>,
+    dry_run: bool,
+) -> Result<()> {
+    info!("Changing vault password");
+
+    let keyfile = read_keyfile(keyfile)?;
+    let new_keyfile = read_keyfile(new_keyfile)?;
+
+    let old_password = prompt_password("Enter current password: ")?;
+
+    let path_str = path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let mut session = manager
+        .open_vault_with_keyfile(
+            "local",
+            provider_config,
+            &old_password,
+            keyfile.as_deref().map(Vec::as_slice),
+        )
+        .await
+        .context("Failed to open vault")?;
+
+    match session.change_password_plan().await {
+        ChangePasswordPlan::Rewrap => {
+            println!("Changing the password re-wraps the vault key; no files are re-encrypted.");
+        }
+        ChangePasswordPlan::Reencrypt { files, bytes } => {
+            println!(
+                "Warning: this vault uses the legacy key format. Changing the password \
+                 re-encrypts {} files ({} bytes).",
+                files, bytes
+            );
+        }
+    }
+
+    if dry_run {
+        println!("\nDry run complete. No changes were made.");
+        return Ok(());
+    }
+
+    let new_password = prompt_password("Enter new password: ")?;
+    let confirm = prompt_password("Confirm new password: ")?;
+
+    if new_password.expose() != confirm.expose() {
+        anyhow::bail!("New passwords do not match");
+    }
+
+    validate_password_strength(&new_password)?;
+
+    session
+        .change_password_with_keyfile(
+            &old_password,
+            keyfile.as_deref().map(Vec::as_slice),
+            &new_password,
+            new_keyfile.as_deref().map(Vec::as_slice),
+        )
+        .context("Failed to change password")?;
+
+    // Save updated config
+    manager.save_config(&session).await?;
+
+    println!("Password changed successfully!");
+
+    Ok(())
+}
+
+/// Show recovery key for a vault.
+async fn cmd_show_recovery_key(path: &Path, unlock: &Unlock) -> Result<()> {
+    info!("Showing recovery key");
+
+    let path_str = path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let session = unlock.open(&manager, "local", provider_config).await?;
+
+    let master_key = session.master_key().context("Session not active")?;
+    let recovery_key = session
+        .config()
+        .decrypt_recovery_key(&master_key)
+        .context("Failed to decrypt recovery key. Vault may not have a recovery key.")?;
+
+    let words = recovery_key
+        .to_mnemonic()
+        .context("Failed to encode recovery key")?;
+
+    display_recovery_words(&words);
+
+    Ok(())
+}
+
+/// Reset vault password using recovery key.
+async fn cmd_reset_password(path: &Path) -> Result<()> {
+    info!("Resetting vault password using recovery key");
+
+    println!("Enter your 24-word recovery key (space-separated):");
+    let mut recovery_input = String::new();
+    std::io::stdin()
+        .read_line(&mut recovery_input)
+        .context("Failed to read recovery key")?;
+    let recovery_words = recovery_input.trim();
+
+    // Validate the recovery key format first.
+    RecoveryKey::from_mnemonic(recovery_words)
+        .context("Invalid recovery key. Please check your words and try again.")?;
+
+    let new_password = prompt_password("Enter new password: ")?;
+    let confirm = prompt_password("Confirm new password: ")?;
+
+    if new_password.expose() != confirm.expose() {
+        anyhow::bail!("Passwords do not match");
+    }
+
+    validate_password_strength(&new_password)?;
+
+    let path_str = path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let _session = manager
+        .recover_vault("local", provider_config, recovery_words, &new_password)
+        .await
+        .context("Failed to reset password. Recovery key may be incorrect.")?;
+
+    recovery_input.zeroize();
+
+    println!("Password reset successfully!");
+    println!("Any keyfile requirement has been removed; the vault now opens with the new password alone.");
+
+    Ok(())
+}
+
+/// Generate a new random keyfile.
+fn cmd_keyfile_generate(output: &Path) -> Result<()> {
+    if output.exists() {
+        anyhow::bail!("Refusing to overwrite existing file {}", output.display());
+    }
+
+    let content = axiomvault_crypto::keyfile::generate_keyfile();
+    std::fs::write(output, &*content)
+        .with_context(|| format!("Failed to write keyfile {}", output.display()))?;
+
+    println!("Keyfile written to {}", output.display());
+    println!("Keep it safe: the vault cannot be opened with the password alone.");
+
+    Ok(())
+}
+
+/// Migrate a legacy vault to support recovery keys.
+async fn cmd_migrate_vault(path: &Path, unlock: &Unlock) -> Result<()> {
+    info!("Migrating vault to v1.1 format");
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path.to_string_lossy()
+    });
+    let mut session = unlock
+        .open_for_migration(&manager, "local", provider_config)
+        .await?;
+
+    if !session.config().is_legacy_format() {
+        println!("Vault is already in v1.1 format with recovery key support.");
+        return Ok(());
+    }
+
+    let output = SessionMigrationRegistry::with_defaults()
+        .apply(&mut session, "legacy-key-wrap", &|_| {})
+        .await
+        .context("Failed to migrate vault")?;
+
+    println!("Vault migrated successfully to v1.1 format!");
+    if let Some(words) = output.recovery_words {
+        display_recovery_words(&words);
+    }
+
+    Ok(())
+}
+
+/// Check vault health and integrity.
+async fn cmd_check(path: &Path, shallow: bool, unlock: &Unlock) -> Result<()> {
+    let path_str = path.to_string_lossy().to_string();
+
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let manager = VaultManager::new();
+    let provider = manager
+        .registry()
+        .resolve("local", provider_config.clone())
+        .context("Failed to create storage provider")?;
+
+    if shallow {
+        info!("Running shallow vault check (no password required)");
+        let report = check_vault_structure(provider.as_ref(), &path_str)
+            .await
+            .context("Failed to run shallow health check")?;
+
+        print_health_report(&report);
+        print_metrics();
+        return Ok(());
+    }
+
+    info!("Running full vault health check");
+    let session = unlock.open(&manager, "local", provider_config).await?;
+
+    let master_key = session.master_key().context("Session not active")?;
+
+    let report = check_vault_health(provider.as_ref(), session.config(), &master_key, &path_str)
+        .await
+        .context("Failed to run health check")?;
+
+    print_health_report(&report);
+    print_metrics();
+
+    Ok(())
+}
+
+/// Print the operation metrics collected during this run.
+#[cfg(feature = "metrics")]
+fn print_metrics() {
+    let text = telemetry::metrics::render();
+    if text.is_empty() {
+        return;
+    }
+    println!("\nMetrics:");
+    for line in text.lines() {
+        println!("  {}", line);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+fn print_metrics() {}
+
+/// Re-encrypt outdated blobs.
+async fn cmd_grep(vault_path: &Path, query: &str, limit: usize, unlock: &Unlock) -> Result<()> {
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
+
+    session
+        .enable_search(SearchConfig::default())
+        .await
+        .context("Failed to load search index")?;
+    let report = session.run_index_pass().await.context("Indexing failed")?;
+    if report.indexed > 0 || report.removed > 0 {
+        info!(
+            "Indexed {} files, removed {} from the index",
+            report.indexed, report.removed
+        );
+    }
+    if report.skipped > 0 {
+        println!(
+            "Warning: {} files were not indexed (index size budget reached)",
+            report.skipped
+        );
+    }
+
+    let ops = VaultOperations::new(&session)?;
+    let hits = ops
+        .search_content(query, limit)
+        .await
+        .context("Search failed")?;
+    if hits.is_empty() {
+        println!("No matches.");
+        return Ok(());
+    }
+    for hit in &hits {
+        let path = hit.path.to_string();
+        for m in &hit.matches {
+            println!(
+                "{}:{}: {}",
+                escape_lossy(&path),
+                m.offset,
+                escape_lossy(&m.snippet)
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn cmd_reindex(vault_path: &Path, disable: bool, unlock: &Unlock) -> Result<()> {
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
+
+    if disable {
+        session
+            .disable_search()
+            .await
+            .context("Failed to delete search index")?;
+        println!("Search disabled; the index was deleted.");
+        return Ok(());
+    }
+
+    session
+        .enable_search(SearchConfig::default())
+        .await
+        .context("Failed to load search index")?;
+    let queued = session.reindex().await.context("Failed to reset index")?;
+    println!("Re-indexing {} files...", queued);
+    let report = session.run_index_pass().await.context("Indexing failed")?;
+    println!(
+        "Indexed {} files ({} skipped, {} unreadable)",
+        report.indexed, report.skipped, report.failed
+    );
+    Ok(())
+}
+
+async fn cmd_maintain(
+    vault_path: &Path,
+    target_format: &str,
+    once: bool,
+    files_per_minute: u32,
+    bytes_per_second: u64,
+    unlock: &Unlock,
+) -> Result<()> {
+    let target: BlobFormat = target_format.parse().context("Invalid --target-format")?;
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
+
+    let policy = MaintenancePolicy {
+        target,
+        max_files_per_minute: Some(files_per_minute),
+        max_bytes_per_second: Some(bytes_per_second),
+        ..MaintenancePolicy::default()
+    };
+
+    let before = session.tree().read().await.format_stats(target);
+    println!(
+        "{} of {} files need upgrading to {}",
+        before.remaining_files(),
+        before.total_files,
+        target
+    );
 
-    let manager = VaultManager::new();
-    let provider_config = serde_json::json!({
-        "root": path_str
-    });
+    let report = if once {
+        session
+            .run_maintenance_pass(&policy)
+            .await
+            .context("Maintenance failed")?
+    } else {
+        let session = Arc::new(session);
+        let mut events = session.subscribe();
+        let handle = session.start_maintenance(policy);
+        let printer = tokio::spawn(async move {
+            while let Ok(event) = events.recv().await {
+                match event {
+                    VaultEvent::BlobUpgraded { path, from, to } => {
+                        println!("  upgraded {} ({} -> {})", escape_lossy(&path), from, to)
+                    }
+                    VaultEvent::BlobUpgradeDeferred { path } => {
+                        println!("  deferred {} (in use)", escape_lossy(&path))
+                    }
+                    VaultEvent::MaintenanceFinished { .. } => break,
+                    VaultEvent::ReauthRequired { .. }
+                    | VaultEvent::DataShardMigrated { .. }
+                    | VaultEvent::MountDegraded { .. } => {}
+                }
+            }
+        });
+        let report = handle.join().await.context("Maintenance failed")?;
+        let _ = printer.await;
+        report
+    };
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    println!(
+        "Upgraded {} files, {} deferred, {} failed, {} remaining",
+        report.upgraded, report.deferred, report.failed, report.remaining
+    );
+    Ok(())
+}
 
-    let master_key = session.master_key().context("Session not active")?;
-    let recovery_key = session
-        .config()
-        .decrypt_recovery_key(master_key)
-        .context("Failed to decrypt recovery key. Vault may not have a recovery key.")?;
+/// Show vault statistics.
+async fn cmd_stats(vault_path: &Path, unlock: &Unlock) -> Result<()> {
+    let path_str = vault_path.to_string_lossy().to_string();
 
-    let words = recovery_key
-        .to_mnemonic()
-        .context("Failed to encode recovery key")?;
+    let manager = VaultManager::new();
+    let session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
 
-    display_recovery_words(&words);
+    let stats = session.tree().read().await.format_stats(BlobFormat::LATEST);
+
+    println!("Vault Statistics:");
+    println!("  Files: {}", stats.total_files);
+    println!("  Size: {} bytes", stats.total_bytes);
+    println!(
+        "  Upgraded to {}: {}/{} files ({:.1}%)",
+        BlobFormat::LATEST,
+        stats.upgraded_files,
+        stats.total_files,
+        stats.percent_upgraded()
+    );
+
+    let usage = VaultOperations::new(&session)?.storage_usage().await;
+    if usage.len() > 1 {
+        println!("  Storage:");
+        for provider in usage {
+            let location = provider
+                .attach_point
+                .map_or_else(|| "(vault)".to_string(), |path| path.display_lossy());
+            println!(
+                "    {} [{}]: {} files, {} bytes",
+                location, provider.provider_type, provider.files, provider.bytes
+            );
+        }
+    }
 
     Ok(())
 }
 
-/// Reset vault password using recovery key.
-async fn cmd_reset_password(path: &Path) -> Result<()> {
-    info!("Resetting vault password using recovery key");
-
-    println!("Enter your 24-word recovery key (space-separated):");
-    let mut recovery_input = String::new();
-    std::io::stdin()
-        .read_line(&mut recovery_input)
-        .context("Failed to read recovery key")?;
-    let recovery_words = recovery_input.trim();
+/// Verify every blob of a vault and exit with status 1 if a file fails.
+async fn cmd_verify(vault_path: &Path, options: &VerifyOptions, unlock: &Unlock) -> Result<()> {
+    let manager = VaultManager::new();
+    let session = unlock
+        .open(
+            &manager,
+            "local",
+            serde_json::json!({ "root": vault_path.to_string_lossy() }),
+        )
+        .await?;
+    let ops = VaultOperations::new(&session)?;
 
-    // Validate the recovery key format first.
-    RecoveryKey::from_mnemonic(recovery_words)
-        .context("Invalid recovery key. Please check your words and try again.")?;
+    let report = ops.verify(options).await.context("Verification failed")?;
+    for file in report.failures() {
+        println!(
+            "FAILED {}: {}",
+            file.path.display_lossy(),
+            file.error.as_deref().unwrap_or_default()
+        );
+    }
 
-    let new_password = prompt_password("Enter new password: ")?;
-    let confirm = prompt_password("Confirm new password: ")?;
+    let count = |level| report.files.iter().filter(|f| f.level == level).count();
+    println!("Verified {} file(s):", report.files.len());
+    println!("  Metadata only: {}", count(VerifyLevel::Metadata));
+    println!("  Sampled: {}", count(VerifyLevel::Sampled));
+    println!(
+        "  Full: {} ({} escalated)",
+        count(VerifyLevel::Full),
+        report.files.iter().filter(|f| f.escalated).count()
+    );
+    println!("  Downloaded: {} bytes", report.bytes_downloaded);
+    if options.fast {
+        println!("  Seed: {}", options.seed);
+    }
+    if report.manifests_recorded > 0 {
+        println!("  Manifests recorded: {}", report.manifests_recorded);
+    }
+    println!("  Failed: {}", report.failures().count());
 
-    if new_password != confirm {
-        anyhow::bail!("Passwords do not match");
+    if !report.is_ok() {
+        std::process::exit(1);
     }
+    Ok(())
+}
 
-    validate_password_strength(&new_password)?;
+/// What `diff` compares the vault with.
+enum DiffWith {
+    Vault {
+        path: PathBuf,
+        keyfile: Option<PathBuf>,
+    },
+    LocalDir(PathBuf),
+}
 
-    let path_str = path.to_string_lossy().to_string();
+/// Compare a vault with another vault or a local directory and exit with
+/// status 1 if they differ.
+async fn cmd_diff(
+    vault_path: &Path,
+    target: DiffWith,
+    fast: bool,
+    ignore_timestamps: bool,
+    ignore: &[String],
+    json: bool,
+    unlock: &Unlock,
+) -> Result<()> {
+    let options = DiffOptions {
+        fast,
+        ignore_timestamps,
+        ignore: ignore
+            .iter()
+            .map(|p| VaultPath::parse(p))
+            .collect::<axiomvault_common::Result<_>>()
+            .context("Invalid --ignore path")?,
+    };
 
     let manager = VaultManager::new();
-    let provider_config = serde_json::json!({
-        "root": path_str
-    });
-
-    let _session = manager
-        .recover_vault("local", provider_config, recovery_words, &new_password)
-        .await
-        .context("Failed to reset password. Recovery key may be incorrect.")?;
+    let session = unlock
+        .open(
+            &manager,
+            "local",
+            serde_json::json!({ "root": vault_path.to_string_lossy() }),
+        )
+        .await?;
+    let ops = VaultOperations::new(&session)?;
 
-    recovery_input.zeroize();
+    let report = match &target {
+        DiffWith::Vault { path, keyfile } => {
+            let keyfile = read_keyfile(keyfile.as_deref())?;
+            let password = prompt_password("Enter password for other vault: ")?;
+            let other = manager
+                .open_vault_with_keyfile(
+                    "local",
+                    serde_json::json!({ "root": path.to_string_lossy() }),
+                    &password,
+                    keyfile.as_deref().map(Vec::as_slice),
+                )
+                .await
+                .context("Failed to open other vault")?;
+            ops.diff_against(DiffTarget::Vault(&other), &options).await
+        }
+        DiffWith::LocalDir(dir) => ops.diff_against(DiffTarget::LocalDir(dir), &options).await,
+    }
+    .context("Failed to compare")?;
 
-    println!("Password reset successfully!");
+    if json {
+        let differences: Vec<_> = report
+            .entries
+            .iter()
+            .map(|e| {
+                serde_json::json!({
+                    "path": e.path.to_string(),
+                    "kind": e.kind,
+                    "is_directory": e.is_directory,
+                })
+            })
+            .collect();
+        let out = serde_json::json!({
+            "identical": report.is_identical(),
+            "compared": report.compared,
+            "differences": differences,
+        });
+        println!("{}", serde_json::to_string_pretty(&out)?);
+    } else {
+        for entry in &report.entries {
+            let label = match entry.kind {
+                DiffKind::OnlyInA => "only in A",
+                DiffKind::OnlyInB => "only in B",
+                DiffKind::ContentDiffers => "content differs",
+                DiffKind::MetadataDiffers => "metadata differs",
+            };
+            let suffix = if entry.is_directory { "/" } else { "" };
+            println!("{:<17} {}{}", label, entry.path.display_lossy(), suffix);
+        }
+        println!(
+            "{} difference(s) among {} path(s) present on both sides",
+            report.entries.len(),
+            report.compared
+        );
+    }
 
+    if !report.is_identical() {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
-/// Migrate a legacy vault to support recovery keys.
-async fn cmd_migrate_vault(path: &Path) -> Result<()> {
-    info!("Migrating vault to v1.1 format");
+/// Parse an RFC 3339 timestamp argument into UTC.
+fn parse_rfc3339(value: &str) -> std::result::Result<chrono::DateTime<chrono::Utc>, String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&chrono::Utc))
+        .map_err(|e| format!("invalid RFC 3339 timestamp '{}': {}", value, e))
+}
 
-    let password = prompt_password("Enter password: ")?;
-    let path_str = path.to_string_lossy().to_string();
+/// A file written by `backup`.
+struct BackupEntry {
+    path: VaultPath,
+    size: u64,
+    modified_at: chrono::DateTime<chrono::Utc>,
+}
 
-    let manager = VaultManager::new();
-    let provider_config = serde_json::json!({
-        "root": path_str
-    });
+/// Export every file modified after `since` into `out`, preserving the
+/// vault's directory structure. Entries are returned sorted by path.
+async fn backup_since(
+    session: &axiomvault_vault::VaultSession,
+    since: chrono::DateTime<chrono::Utc>,
+    out: &Path,
+) -> Result<Vec<BackupEntry>> {
+    let selected: Vec<(VaultPath, chrono::DateTime<chrono::Utc>)> = {
+        let tree = session.tree().read().await;
+        tree.files_modified_since(since)
+            .into_iter()
+            .filter_map(|path| {
+                let modified_at = tree.get_node(&path).ok()?.metadata.modified_at;
+                Some((path, modified_at))
+            })
+            .collect()
+    };
 
-    let mut session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let ops = VaultOperations::new(session)?;
+    let mut entries = Vec::with_capacity(selected.len());
+    for (path, modified_at) in selected {
+        let content = ops
+            .read_file(&path)
+            .await
+            .with_context(|| format!("Failed to read {}", path))?;
 
-    if !session.config().is_legacy_format() {
-        println!("Vault is already in v1.1 format with recovery key support.");
-        return Ok(());
+        // Components are validated by `VaultPath`, so they cannot escape `out`.
+        let mut dest = out.to_path_buf();
+        dest.extend(path.components());
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        tokio::fs::write(&dest, &content)
+            .await
+            .with_context(|| format!("Failed to write {}", dest.display()))?;
+
+        entries.push(BackupEntry {
+            path,
+            size: content.len() as u64,
+            modified_at,
+        });
     }
 
-    let recovery_words = session
-        .config_mut()
-        .migrate_to_v1_1(&password)
-        .context("Failed to migrate vault")?;
+    Ok(entries)
+}
 
-    // Save updated config.
-    manager
-        .save_config(&session)
+/// Export files modified since a timestamp and print a manifest.
+async fn cmd_backup(
+    vault_path: &Path,
+    since: chrono::DateTime<chrono::Utc>,
+    out: &Path,
+    unlock: &Unlock,
+) -> Result<()> {
+    info!("Backing up files modified since {}", since.to_rfc3339());
+
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
+
+    tokio::fs::create_dir_all(out)
         .await
-        .context("Failed to save migrated config")?;
+        .context("Failed to create output directory")?;
+    let entries = backup_since(&session, since, out).await?;
 
-    println!("Vault migrated successfully to v1.1 format!");
-    display_recovery_words(&recovery_words);
+    println!("Backup manifest (modified since {}):", since.to_rfc3339());
+    for entry in &entries {
+        println!(
+            "  {}  {:>10} bytes  {}",
+            entry.modified_at.to_rfc3339(),
+            entry.size,
+            entry.path.display_lossy()
+        );
+    }
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    println!(
+        "{} file(s), {} bytes written to {}",
+        entries.len(),
+        total,
+        out.display()
+    );
 
     Ok(())
 }
 
-/// Check vault health and integrity.
-async fn cmd_check(path: &Path, shallow: bool) -> Result<()> {
-    let path_str = path.to_string_lossy().to_string();
+/// Switches of `export-plaintext`.
+struct ExportPlaintextFlags {
+    i_understand_plaintext: bool,
+    verify: bool,
+    allow_synced_dest: bool,
+    clean_up: bool,
+}
 
-    let provider_config = serde_json::json!({
-        "root": path_str
-    });
+/// Path components that suggest a folder synced to a cloud service.
+const CLOUD_SYNC_MARKERS: &[&str] = &[
+    "dropbox",
+    "onedrive",
+    "google drive",
+    "googledrive",
+    "icloud drive",
+    "mobile documents",
+    "box sync",
+    "nextcloud",
+    "pcloud",
+];
+
+/// Cloud-sync marker found in `path`, if any. Best effort: it only looks at
+/// folder names, not at the sync client's own configuration.
+fn cloud_sync_marker(path: &Path) -> Option<&'static str> {
+    path.components().find_map(|component| {
+        let name = component.as_os_str().to_string_lossy().to_lowercase();
+        CLOUD_SYNC_MARKERS
+            .iter()
+            .find(|marker| name.starts_with(*marker))
+            .copied()
+    })
+}
 
-    let manager = VaultManager::new();
-    let provider = manager
-        .registry()
-        .resolve("local", provider_config.clone())
-        .context("Failed to create storage provider")?;
+/// Absolute form of `path` with `..` and symlinks resolved, also for paths
+/// that do not exist yet (the missing tail is appended to the resolved
+/// ancestor).
+fn resolve_dest(path: &Path) -> Result<PathBuf> {
+    let mut absolute = PathBuf::new();
+    for component in std::path::absolute(path)
+        .context("Failed to resolve destination")?
+        .components()
+    {
+        match component {
+            std::path::Component::ParentDir => {
+                absolute.pop();
+            }
+            std::path::Component::CurDir => {}
+            other => absolute.push(other),
+        }
+    }
+    let mut existing = absolute.as_path();
+    let mut tail = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return Ok(tail.iter().rev().fold(resolved, |acc, c| acc.join(c)));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                tail.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return Ok(absolute),
+        }
+    }
+}
 
-    if shallow {
-        info!("Running shallow vault check (no password required)");
-        let report = check_vault_structure(provider.as_ref(), &path_str)
-            .await
-            .context("Failed to run shallow health check")?;
+/// Mount points of AxiomVault FUSE mounts on this machine (Linux only).
+fn vault_mount_points() -> Vec<PathBuf> {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return Vec::new();
+    };
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let (source, target, fs_type) = (fields.next()?, fields.next()?, fields.next()?);
+            (source == "axiomvault" || fs_type == "fuse.axiomvault")
+                .then(|| PathBuf::from(target.replace("\\040", " ")))
+        })
+        .collect()
+}
 
-        print_health_report(&report);
-        return Ok(());
+/// Refuse export destinations inside the vault storage or a mounted vault.
+fn check_export_destination(dest: &Path, vault_root: &Path, mounts: &[PathBuf]) -> Result<()> {
+    let dest = resolve_dest(dest)?;
+    let vault_root = resolve_dest(vault_root)?;
+    if dest.starts_with(&vault_root) {
+        anyhow::bail!(
+            "Refusing to export into the vault's own storage ({})",
+            vault_root.display()
+        );
+    }
+    for mount in mounts {
+        if dest.starts_with(resolve_dest(mount)?) {
+            anyhow::bail!(
+                "Refusing to export into a mounted vault ({})",
+                mount.display()
+            );
+        }
     }
+    Ok(())
+}
 
-    info!("Running full vault health check");
-    let password = prompt_password("Enter password: ")?;
+/// Decrypt a vault subtree into a local directory.
+async fn cmd_export_plaintext(
+    vault_path: &Path,
+    dest: &Path,
+    prefix: &str,
+    flags: ExportPlaintextFlags,
+    unlock: &Unlock,
+) -> Result<()> {
+    if flags.clean_up {
+        let removed = remove_partial_export(dest).context("Failed to clean up export")?;
+        println!(
+            "Removed {} exported file(s) from {}",
+            removed,
+            dest.display()
+        );
+        return Ok(());
+    }
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    if !flags.i_understand_plaintext {
+        anyhow::bail!(
+            "export-plaintext writes decrypted files to disk; \
+             pass --i-understand-plaintext to continue"
+        );
+    }
+    check_export_destination(dest, vault_path, &vault_mount_points())?;
+    if let Some(marker) = cloud_sync_marker(&resolve_dest(dest)?) {
+        if !flags.allow_synced_dest {
+            anyhow::bail!(
+                "Destination looks like a cloud-synced folder ({}); decrypted files \
+                 would be uploaded. Pass --allow-synced-dest to export anyway",
+                marker
+            );
+        }
+        eprintln!(
+            "Warning: exporting into what looks like a cloud-synced folder ({})",
+            marker
+        );
+    }
 
-    let master_key = session.master_key().context("Session not active")?;
+    let prefix = VaultPath::parse(prefix).context("Invalid --prefix")?;
+    let path_str = vault_path.to_string_lossy().to_string();
 
-    let report = check_vault_health(provider.as_ref(), session.config(), master_key, &path_str)
-        .await
-        .context("Failed to run health check")?;
+    let manager = VaultManager::new();
+    let session = unlock
+        .open(&manager, "local", serde_json::json!({ "root": path_str }))
+        .await?;
+
+    // Ctrl-C stops after the current file and keeps the manifest.
+    let (stop, cancel) = tokio::sync::watch::channel(false);
+    let interrupt = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("Interrupted; stopping after the current file...");
+            let _ = stop.send(true);
+        }
+    });
 
-    print_health_report(&report);
+    let ops = VaultOperations::new(&session)?;
+    let options = ExportOptions {
+        verify: flags.verify,
+        cancel: Some(cancel),
+    };
+    let result = ops.export_tree(&prefix, dest, &options).await;
+    interrupt.abort();
+    let report = result.context("Export failed")?;
 
+    println!(
+        "{} file(s) in {} folder(s) exported to {} ({} bytes)",
+        report.files,
+        report.directories,
+        dest.display(),
+        report.bytes
+    );
+    if report.resumed > 0 {
+        println!(
+            "{} file(s) were already exported by an earlier run",
+            report.resumed
+        );
+    }
+    if flags.verify {
+        println!("{} file(s) verified against the vault", report.verified);
+    }
+    if report.cancelled {
+        println!(
+            "Export interrupted. Run again to resume, or with --clean-up to delete \
+             the exported files."
+        );
+    }
     Ok(())
 }
 
@@ -1386,15 +3672,18 @@ async fn cmd_gdrive_create(
     folder_id: &str,
     tokens_path: &Path,
     strength: KdfStrength,
+    keyfile: Option<&Path>,
 ) -> Result<()> {
     info!("Creating new vault on Google Drive: {}", name);
 
+    let keyfile = read_keyfile(keyfile)?;
+
     let kdf_params = kdf_params_from(strength);
 
     let password = prompt_password("Enter password: ")?;
     let confirm = prompt_password("Confirm password: ")?;
 
-    if password != confirm {
+    if password.expose() != confirm.expose() {
         anyhow::bail!("Passwords do not match");
     }
 
@@ -1415,13 +3704,21 @@ async fn cmd_gdrive_create(
         folder_id: folder_id.to_string(),
         tokens,
         auth_config: None,
+        max_concurrency: gdrive::DEFAULT_MAX_CONCURRENCY,
     };
 
     let provider_config =
         serde_json::to_value(gdrive_config).context("Failed to serialize GDrive config")?;
 
     let creation = manager
-        .create_vault(vault_id, &password, "gdrive", provider_config, kdf_params)
+        .create_vault_with_keyfile(
+            vault_id,
+            &password,
+            keyfile.as_deref().map(Vec::as_slice),
+            "gdrive",
+            provider_config,
+            kdf_params,
+        )
         .await
         .context("Failed to create vault on Google Drive")?;
 
@@ -1435,11 +3732,9 @@ async fn cmd_gdrive_create(
 }
 
 /// Open a vault on Google Drive.
-async fn cmd_gdrive_open(folder_id: &str, tokens_path: &Path) -> Result<()> {
+async fn cmd_gdrive_open(folder_id: &str, tokens_path: &Path, unlock: &Unlock) -> Result<()> {
     info!("Opening vault on Google Drive");
 
-    let password = prompt_password("Enter password: ")?;
-
     // Load tokens
     let tokens_json = tokio::fs::read_to_string(tokens_path)
         .await
@@ -1451,6 +3746,7 @@ async fn cmd_gdrive_open(folder_id: &str, tokens_path: &Path) -> Result<()> {
         folder_id: folder_id.to_string(),
         tokens,
         auth_config: None,
+        max_concurrency: gdrive::DEFAULT_MAX_CONCURRENCY,
     };
 
     let provider_config =
@@ -1458,10 +3754,7 @@ async fn cmd_gdrive_open(folder_id: &str, tokens_path: &Path) -> Result<()> {
 
     let manager = VaultManager::new();
 
-    let session = manager
-        .open_vault("gdrive", provider_config, &password)
-        .await
-        .context("Failed to open vault on Google Drive")?;
+    let session = unlock.open(&manager, "gdrive", provider_config).await?;
 
     println!("Vault opened successfully from Google Drive!");
     println!("  ID: {}", session.vault_id());
@@ -1472,11 +3765,18 @@ async fn cmd_gdrive_open(folder_id: &str, tokens_path: &Path) -> Result<()> {
 }
 
 /// Sync vault with remote storage.
-async fn cmd_sync(vault_path: &Path, strategy: ConflictStrategyArg) -> Result<()> {
+async fn cmd_sync(
+    vault_path: &Path,
+    strategy: ConflictStrategyArg,
+    paths: Vec<String>,
+    unlock: &Unlock,
+    allow_exec_hooks: bool,
+) -> Result<()> {
     info!("Starting vault sync");
 
+    let hooks = load_hooks(vault_path, allow_exec_hooks).await?;
+
     let conflict_strategy = conflict_strategy_from(strategy);
-    let password = prompt_password("Enter password: ")?;
     let path_str = vault_path.to_string_lossy().to_string();
 
     let manager = VaultManager::new();
@@ -1484,10 +3784,7 @@ async fn cmd_sync(vault_path: &Path, strategy: ConflictStrategyArg) -> Result<()
         "root": path_str
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     let sync_config = SyncConfig {
         conflict_strategy,
@@ -1495,24 +3792,127 @@ async fn cmd_sync(vault_path: &Path, strategy: ConflictStrategyArg) -> Result<()
         ..Default::default()
     };
 
-    let staging_dir = vault_path.join(".axiom_sync");
-    let sync_engine: SyncEngine<dyn axiomvault_storage::StorageProvider> =
-        SyncEngine::from_arc(session.provider(), &staging_dir, sync_config)
+    // Each attach point's provider is synced by an engine of its own, with
+    // its own staging directory.
+    let staging_root = vault_path.join(".axiom_sync");
+    let mut targets = vec![(None, session.provider(), staging_root.clone())];
+    for point in &session.config().attachments {
+        let provider = session
+            .attached_provider(point)
+            .with_context(|| format!("{} is not connected", point.vault_path.display_lossy()))?;
+        let dirname: String = point
+            .vault_path
+            .to_string()
+            .bytes()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        targets.push((
+            Some(point.vault_path.clone()),
+            provider,
+            staging_root.join("attach").join(dirname),
+        ));
+    }
+
+    for (attach_point, provider, staging_dir) in targets {
+        let sync_engine: SyncEngine<dyn axiomvault_storage::StorageProvider> =
+            SyncEngine::from_arc(
+                provider,
+                &staging_dir,
+                StagingKey::for_session(&session)?,
+                sync_config.clone(),
+            )
             .await
             .context("Failed to create sync engine")?;
+        let target = attach_point
+            .as_ref()
+            .map(|path| format!(" ({})", path.display_lossy()))
+            .unwrap_or_default();
+        let mut events = sync_engine.subscribe();
+
+        let sync = async {
+            if paths.is_empty() {
+                println!("Starting sync{}...", target);
+                sync_engine.sync_full().await
+            } else {
+                println!("Syncing {}{}...", escape_lossy(&paths.join(", ")), target);
+                sync_engine.sync_paths(paths.clone()).await
+            }
+        };
+        tokio::pin!(sync);
+
+        // A signal lets the transfer in flight finish its current chunk, so
+        // the next sync resumes it instead of starting over.
+        let result = tokio::select! {
+            result = &mut sync => match result {
+                Ok(result) => result,
+                Err(e) => {
+                    if let Some(hooks) = &hooks {
+                        hooks.dispatch(&HookEvent::sync_failed(&e.to_string()));
+                    }
+                    finish_hooks(hooks.as_ref()).await;
+                    return Err(sync_failure(e, "Sync failed"));
+                }
+            },
+            signal = shutdown_signal() => {
+                signal?;
+                eprintln!("Interrupted; stopping after the current transfer...");
+                let stopped = tokio::time::timeout(SHUTDOWN_DEADLINE, async {
+                    tokio::join!(&mut sync, sync_engine.shutdown(SHUTDOWN_DEADLINE)).1
+                })
+                .await;
+                if !matches!(stopped, Ok(Ok(ShutdownOutcome::Drained))) {
+                    eprintln!("Sync did not stop in time; aborting");
+                }
+                session.close().await.context("Failed to close vault")?;
+                anyhow::bail!("Sync interrupted; pending changes resume on the next sync");
+            }
+        };
+
+        if let Some(hooks) = &hooks {
+            while let Ok(event) = events.try_recv() {
+                hooks.dispatch(&HookEvent::from(&event));
+            }
+            hooks.dispatch(&HookEvent::sync_finished(&result));
+            if let Some(conflicts) = HookEvent::conflicts_found(&result) {
+                hooks.dispatch(&conflicts);
+            }
+        }
 
-    println!("Starting sync...");
-    let result = sync_engine.sync_full().await.context("Sync failed")?;
+        println!("Sync completed{}!", target);
+        println!("  Files synced: {}", result.files_synced);
+        println!("  Files failed: {}", result.files_failed);
+        println!("  Conflicts found: {}", result.conflicts_found);
+        println!("  Duration: {:?}", result.duration);
 
-    println!("Sync completed!");
-    println!("  Files synced: {}", result.files_synced);
-    println!("  Files failed: {}", result.files_failed);
-    println!("  Conflicts found: {}", result.conflicts_found);
-    println!("  Duration: {:?}", result.duration);
+        if let Some(shortfall) = result.quota_shortfall {
+            println!(
+                "Warning: uploads need {} bytes but the storage provider has only {} bytes free",
+                shortfall.needed, shortfall.remaining
+            );
+        }
+        if result.quota_exhausted {
+            println!(
+                "Storage quota exhausted: {} uploads ({} bytes) paused. Free up space and sync again.",
+                result.uploads_paused, result.paused_bytes
+            );
+        }
+    }
 
+    finish_hooks(hooks.as_ref()).await;
     Ok(())
 }
 
+/// Describe a failed sync operation, declining plainly when another
+/// process is already syncing the vault.
+fn sync_failure(err: axiomvault_common::Error, context: &'static str) -> anyhow::Error {
+    match err {
+        axiomvault_common::Error::SyncInProgress(_) => {
+            anyhow::anyhow!("{}; try again once it finishes", err)
+        }
+        err => anyhow::Error::new(err).context(context),
+    }
+}
+
 /// Show sync status for the vault.
 async fn cmd_sync_status(vault_path: &Path) -> Result<()> {
     info!("Getting sync status");
@@ -1589,7 +3989,7 @@ async fn cmd_sync_conflicts(vault_path: &Path) -> Result<()> {
     } else {
         println!("Sync Conflicts:");
         for entry in conflicts {
-            println!("\n  Path: {}", entry.path);
+            println!("\n  Path: {}", escape_lossy(&entry.path));
             println!("    Local etag: {:?}", entry.local_etag);
             println!("    Remote etag: {:?}", entry.remote_etag);
             println!("    Local modified: {}", entry.local_modified);
@@ -1607,12 +4007,13 @@ async fn cmd_sync_conflicts(vault_path: &Path) -> Result<()> {
 async fn cmd_sync_resolve(
     vault_path: &Path,
     file: &str,
-    strategy: ConflictStrategyArg,
+    strategy: Option<ConflictStrategyArg>,
+    show: bool,
+    unlock: &Unlock,
 ) -> Result<()> {
     info!("Resolving sync conflict for {}", file);
 
-    let conflict_strategy = conflict_strategy_from(strategy);
-    let password = prompt_password("Enter password: ")?;
+    let conflict_strategy = strategy.map_or(ConflictStrategy::Manual, conflict_strategy_from);
     let path_str = vault_path.to_string_lossy().to_string();
 
     let manager = VaultManager::new();
@@ -1620,10 +4021,7 @@ async fn cmd_sync_resolve(
         "root": path_str
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     let sync_config = SyncConfig {
         conflict_strategy,
@@ -1631,24 +4029,59 @@ async fn cmd_sync_resolve(
     };
 
     let staging_dir = vault_path.join(".axiom_sync");
-    let sync_engine: SyncEngine<dyn axiomvault_storage::StorageProvider> =
-        SyncEngine::from_arc(session.provider(), &staging_dir, sync_config)
-            .await
-            .context("Failed to create sync engine")?;
+    let sync_engine: SyncEngine<dyn axiomvault_storage::StorageProvider> = SyncEngine::from_arc(
+        session.provider(),
+        &staging_dir,
+        StagingKey::for_session(&session)?,
+        sync_config,
+    )
+    .await
+    .context("Failed to create sync engine")?;
 
     let file_path = VaultPath::parse(file).context("Invalid file path")?;
 
-    // Read local file content for resolution
     let ops = VaultOperations::new(&session)?;
-    let local_data = ops
-        .read_file(&file_path)
+
+    if show {
+        // A file deleted locally has no content to preview.
+        let local_data = match ops.read_file(&file_path).await {
+            Ok(data) => Some(data),
+            Err(axiomvault_common::Error::NotFound(_)) => None,
+            Err(e) => return Err(e).context("Failed to read local file"),
+        };
+        let mut details = sync_engine
+            .conflict_details(&file_path)
+            .await
+            .context("Failed to inspect conflict")?;
+        // The preview is best effort: a remote blob this session cannot
+        // decrypt still leaves the metadata to go on.
+        let remote_data = match details.kind {
+            ConflictKind::Edit => match session.provider().download(&file_path).await {
+                Ok(blob) => ops.open_staged_blob(&file_path, &blob).await.ok(),
+                Err(_) => None,
+            },
+            ConflictKind::DeleteVsEdit { .. } => None,
+        };
+        if let (Some(local_data), Some(remote_data)) = (&local_data, remote_data) {
+            details = details.with_preview(local_data, &remote_data);
+        }
+        print_conflict_details(&details);
+    }
+
+    let Some(strategy) = strategy else {
+        return Ok(());
+    };
+
+    // The remote holds vault blobs, so the local side is sealed before it
+    // can be uploaded; a file deleted locally has none.
+    let local = ops
+        .seal_current_for_staging(&file_path)
         .await
         .context("Failed to read local file")?;
-
     sync_engine
-        .resolve_conflict(&file_path, local_data, conflict_strategy)
+        .resolve_conflict(&file_path, local, conflict_strategy)
         .await
-        .context("Failed to resolve conflict")?;
+        .map_err(|e| sync_failure(e, "Failed to resolve conflict"))?;
 
     println!(
         "Conflict resolved for {} using strategy: {:?}",
@@ -1658,6 +4091,115 @@ async fn cmd_sync_resolve(
     Ok(())
 }
 
+/// Resolve every sync conflict with one strategy.
+async fn cmd_sync_resolve_all(
+    vault_path: &Path,
+    strategy: ConflictStrategyArg,
+    unlock: &Unlock,
+) -> Result<()> {
+    info!("Resolving all sync conflicts");
+
+    let conflict_strategy = conflict_strategy_from(strategy);
+    let path_str = vault_path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path_str
+    });
+
+    let session = unlock.open(&manager, "local", provider_config).await?;
+
+    let sync_config = SyncConfig {
+        conflict_strategy,
+        ..Default::default()
+    };
+
+    let staging_dir = vault_path.join(".axiom_sync");
+    let sync_engine: SyncEngine<dyn axiomvault_storage::StorageProvider> = SyncEngine::from_arc(
+        session.provider(),
+        &staging_dir,
+        StagingKey::for_session(&session)?,
+        sync_config,
+    )
+    .await
+    .context("Failed to create sync engine")?;
+
+    // Each local side is the vault's current file, sealed as
+    // `sync-resolve` seals it.
+    let ops = VaultOperations::new(&session)?;
+    let outcomes = sync_engine
+        .resolve_all(conflict_strategy, |path| {
+            let ops = &ops;
+            async move { ops.seal_current_for_staging(&path).await }
+        })
+        .await
+        .map_err(|e| sync_failure(e, "Failed to resolve conflicts"))?;
+    if outcomes.is_empty() {
+        println!("No conflicts found.");
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for (path, result) in &outcomes {
+        match result {
+            Ok(()) => println!("  Resolved: {}", path.display_lossy()),
+            Err(e) => {
+                failed += 1;
+                println!("  Failed:   {} ({})", path.display_lossy(), e);
+            }
+        }
+    }
+    println!(
+        "{} of {} conflicts resolved using strategy: {:?}",
+        outcomes.len() - failed,
+        outcomes.len(),
+        strategy
+    );
+    if failed > 0 {
+        anyhow::bail!("{} conflicts could not be resolved", failed);
+    }
+
+    Ok(())
+}
+
+/// Print both sides of a conflict, and the diff preview if there is one.
+fn print_conflict_details(details: &ConflictDetails) {
+    fn side(label: &str, version: &VersionInfo) {
+        println!("  {}:", label);
+        println!("    ETag: {}", version.etag.as_deref().unwrap_or("-"));
+        match version.modified {
+            Some(modified) => println!("    Modified: {}", modified),
+            None => println!("    Modified: -"),
+        }
+        match version.size {
+            Some(size) => println!("    Size: {} bytes", size),
+            None => println!("    Size: -"),
+        }
+    }
+
+    println!("Conflict: {}", details.path.display_lossy());
+    match details.kind {
+        ConflictKind::Edit => println!("  Kind: edited on both sides"),
+        ConflictKind::DeleteVsEdit {
+            deleted: Side::Local,
+        } => println!("  Kind: deleted locally, edited remotely"),
+        ConflictKind::DeleteVsEdit {
+            deleted: Side::Remote,
+        } => println!("  Kind: edited locally, deleted remotely"),
+    }
+    side("Local", &details.local);
+    side("Remote", &details.remote);
+    if let Some(preview) = &details.preview {
+        println!("  Changes from line {}:", preview.first_line);
+        for line in &preview.lines {
+            println!("    {}", line);
+        }
+        if preview.truncated {
+            println!("    ...");
+        }
+    }
+}
+
 /// Configure sync mode for the vault.
 async fn cmd_sync_configure(
     vault_path: &Path,
@@ -1701,10 +4243,150 @@ async fn cmd_sync_configure(
     Ok(())
 }
 
+/// What `axiomvault migrate` does.
+enum MigrateAction {
+    /// List pending migrations and the migrations already run.
+    List,
+    /// Show what would run, changing nothing.
+    DryRun,
+    /// Apply one migration.
+    Apply(String),
+    /// Apply every pending migration.
+    All,
+    /// Undo one migration.
+    Rollback(String),
+}
+
 /// Detect and run vault format migrations.
-async fn cmd_migrate(path: &Path, dry_run: bool) -> Result<()> {
+async fn cmd_migrate(path: &Path, action: MigrateAction, unlock: &Unlock) -> Result<()> {
     info!("Checking vault migration status: {}", path.display());
 
+    match action {
+        MigrateAction::DryRun => migrate_version(path, true).await?,
+        MigrateAction::All => migrate_version(path, false).await?,
+        _ => {}
+    }
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": path.to_string_lossy()
+    });
+    let mut session = unlock
+        .open_for_migration(&manager, "local", provider_config)
+        .await?;
+    let registry = SessionMigrationRegistry::with_defaults();
+    let pending = registry
+        .pending(&session)
+        .await
+        .context("Failed to check for migrations")?;
+    let progress = |p: MigrationProgress| println!("  {}: {}/{}", p.id, p.done, p.total);
+
+    match action {
+        MigrateAction::List => {
+            if pending.is_empty() {
+                println!("No migrations pending.");
+            } else {
+                println!("Pending migrations:");
+                for plan in &pending {
+                    print_migration_plan(plan);
+                }
+            }
+            let runs = &session.config().migrations;
+            if !runs.is_empty() {
+                println!("\nMigrations run:");
+                for run in runs {
+                    let outcome = match &run.outcome {
+                        MigrationOutcome::Running => "interrupted".to_string(),
+                        MigrationOutcome::Applied => "applied".to_string(),
+                        MigrationOutcome::Failed { error } => format!("failed: {}", error),
+                        MigrationOutcome::RolledBack => "rolled back".to_string(),
+                    };
+                    println!(
+                        "  {}  {}  {}",
+                        run.started_at.format("%Y-%m-%d %H:%M:%S"),
+                        run.id,
+                        outcome
+                    );
+                }
+            }
+        }
+        MigrateAction::DryRun => {
+            if pending.is_empty() {
+                println!("No migrations pending.");
+            } else {
+                println!("Migration plan ({} migration(s)):", pending.len());
+                for plan in &pending {
+                    print_migration_plan(plan);
+                }
+            }
+            println!("\nDry run complete. No changes were made.");
+        }
+        MigrateAction::Apply(id) => {
+            let output = registry
+                .apply(&mut session, &id, &progress)
+                .await
+                .with_context(|| format!("Migration {} failed", id))?;
+            println!("Applied migration {}.", id);
+            if let Some(words) = output.recovery_words {
+                display_recovery_words(&words);
+            }
+        }
+        MigrateAction::All => {
+            if pending.is_empty() {
+                println!("No migrations pending.");
+                return Ok(());
+            }
+            for plan in &pending {
+                let output = registry
+                    .apply(&mut session, plan.id, &progress)
+                    .await
+                    .with_context(|| format!("Migration {} failed", plan.id))?;
+                println!("Applied migration {}.", plan.id);
+                if let Some(words) = output.recovery_words {
+                    display_recovery_words(&words);
+                }
+            }
+        }
+        MigrateAction::Rollback(id) => {
+            registry
+                .rollback(&mut session, &id)
+                .await
+                .with_context(|| format!("Failed to roll back migration {}", id))?;
+            println!("Rolled back migration {}.", id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print what a pending migration will do.
+fn print_migration_plan(plan: &MigrationPlan) {
+    println!("  {}: {}", plan.id, plan.description);
+    println!("      {} -> {}", plan.from, plan.to);
+    for change in &plan.changes {
+        println!("      - {}", change);
+    }
+    println!(
+        "      {} object(s), {}, {}",
+        plan.objects,
+        if plan.reversible {
+            "reversible"
+        } else {
+            "not reversible"
+        },
+        match plan.kind {
+            MigrationKind::Automatic => "applied on open",
+            MigrationKind::Explicit => "applied on request",
+        }
+    );
+    if plan.interrupted {
+        println!("      An earlier run did not finish; applying resumes it.");
+    }
+}
+
+/// Step the vault's config to the current format version. These steps
+/// need no keys and run before the session migrations.
+async fn migrate_version(path: &Path, dry_run: bool) -> Result<()> {
     let config_path = path.join("vault.config");
     if !config_path.exists() {
         anyhow::bail!("No vault found at {}", path.display());
@@ -1757,7 +4439,6 @@ async fn cmd_migrate(path: &Path, dry_run: bool) -> Result<()> {
     }
 
     if dry_run {
-        println!("\nDry run complete. No changes were made.");
         return Ok(());
     }
 
@@ -1816,6 +4497,67 @@ async fn save_raid_config(vault_path: &Path, config: &RaidConfig) -> Result<()>
     Ok(())
 }
 
+// ---------------------------------------------------------------------------
+// Hook helpers
+// ---------------------------------------------------------------------------
+
+/// Path to the hooks configuration within a vault directory. It stays on
+/// this machine and is never synced.
+fn hooks_config_path(vault_path: &Path) -> PathBuf {
+    vault_path.join(".axiomvault").join("hooks.json")
+}
+
+/// Load the hooks configuration from disk.  Returns `None` if not found.
+async fn load_hooks_config(vault_path: &Path) -> Result<Option<HooksConfig>> {
+    let path = hooks_config_path(vault_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = tokio::fs::read_to_string(&path)
+        .await
+        .context("Failed to read hooks config")?;
+    let config = serde_json::from_str(&data).context("Failed to parse hooks config")?;
+    Ok(Some(config))
+}
+
+/// A dispatcher for the vault's hooks, or `None` when it has none.
+///
+/// Command hooks run only with `--allow-exec-hooks`; the toggle in the file
+/// is ignored so that editing the file alone cannot enable them.
+async fn load_hooks(vault_path: &Path, allow_exec_hooks: bool) -> Result<Option<HookDispatcher>> {
+    let Some(mut config) = load_hooks_config(vault_path).await? else {
+        return Ok(None);
+    };
+    if config.hooks.is_empty() {
+        return Ok(None);
+    }
+    config.allow_exec_hooks = allow_exec_hooks;
+    match HookDispatcher::new(config) {
+        Ok(hooks) => Ok(Some(hooks)),
+        Err(e @ axiomvault_common::Error::NotPermitted(_)) => {
+            anyhow::bail!("{}; pass --allow-exec-hooks to run it", e)
+        }
+        Err(e) => Err(anyhow::Error::new(e).context("Invalid hooks config")),
+    }
+}
+
+/// Let hook runs finish, then report hooks that failed. Hook failures
+/// never fail the command itself.
+async fn finish_hooks(hooks: Option<&HookDispatcher>) {
+    let Some(hooks) = hooks else {
+        return;
+    };
+    hooks.wait_idle().await;
+    for status in hooks.status() {
+        if let Some(error) = status.last_error.filter(|_| status.failures > 0) {
+            eprintln!(
+                "Warning: hook {} failed {} of {} runs; last error: {}",
+                status.name, status.failures, status.runs, error
+            );
+        }
+    }
+}
+
 /// Convert a `RaidConfig` into a `RaidMode`.
 fn raid_mode_from_config(cfg: &RaidModeConfig) -> Result<RaidMode> {
     match cfg.mode_type.as_str() {
@@ -1867,10 +4609,13 @@ fn build_composite(config: &RaidConfig) -> Result<CompositeStorageProvider> {
 async fn cmd_raid_add_backend(vault_path: &Path, provider: &str, config_json: &str) -> Result<()> {
     info!("Adding backend to RAID pool: {}", provider);
 
-    // Validate provider type and config by trying to resolve it.
+    // Validate provider type and config, then make sure it can be created.
     let provider_config: serde_json::Value =
         serde_json::from_str(config_json).context("Invalid JSON config")?;
     let registry = create_default_registry();
+    registry
+        .validate(provider, &provider_config)
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
     registry
         .resolve(provider, provider_config.clone())
         .with_context(|| format!("Failed to create provider '{}'", provider))?;
@@ -2409,10 +5154,22 @@ async fn cmd_raid_configure(
 }
 
 /// Serve vault contents over WebDAV.
-async fn cmd_webdav(path: &Path, port: u16) -> Result<()> {
+#[cfg(feature = "webdav")]
+async fn cmd_serve_webdav(
+    path: &Path,
+    port: u16,
+    metrics_port: Option<u16>,
+    unlock: &Unlock,
+    allow_exec_hooks: bool,
+) -> Result<()> {
     info!("Starting WebDAV server for vault at: {}", path.display());
 
-    let password = prompt_password("Enter password: ")?;
+    let hooks = load_hooks(path, allow_exec_hooks).await?;
+
+    if let Some(metrics_port) = metrics_port {
+        serve_metrics(metrics_port).await?;
+    }
+
     let vault_path = path.to_string_lossy().to_string();
 
     let manager = VaultManager::new();
@@ -2420,16 +5177,18 @@ async fn cmd_webdav(path: &Path, port: u16) -> Result<()> {
         "root": vault_path
     });
 
-    let session = manager
-        .open_vault("local", provider_config, &password)
-        .await
-        .context("Failed to open vault")?;
+    let session = unlock.open(&manager, "local", provider_config).await?;
 
     let session = Arc::new(session);
+    if let Some(hooks) = &hooks {
+        hooks.listen(session.subscribe(), |event| Some(HookEvent::from(event)));
+    }
 
+    let token = axiomvault_webdav::WebDavConfig::generate_token();
     let config = axiomvault_webdav::WebDavConfig {
         bind_address: "127.0.0.1".to_string(),
         port,
+        auth_token: Some(Arc::new(token.clone())),
         ..Default::default()
     };
 
@@ -2437,12 +5196,159 @@ async fn cmd_webdav(path: &Path, port: u16) -> Result<()> {
     let url = server.url();
 
     println!("WebDAV server running at {}/", url);
+    println!("  User:     axiomvault (any name is accepted)");
+    println!("  Password: {}", token.as_str());
+    println!("The password is valid until the server stops.");
     println!("Press Ctrl+C to stop.");
 
-    server
+    let served = server
         .start()
         .await
-        .map_err(|e| anyhow::anyhow!("WebDAV server error: {}", e))
+        .map_err(|e| anyhow::anyhow!("WebDAV server error: {}", e));
+    finish_hooks(hooks.as_ref()).await;
+    served
+}
+
+/// Mount a vault and keep it mounted until Ctrl+C.
+async fn cmd_mount(
+    path: &Path,
+    at: &Path,
+    read_only: bool,
+    allow_other: bool,
+    unlock: &Unlock,
+) -> Result<()> {
+    if !axiomvault_fuse::mount::is_fuse_available() {
+        anyhow::bail!(axiomvault_fuse::mount::fuse_info());
+    }
+
+    info!("Mounting vault at: {}", at.display());
+
+    let vault_path = path.to_string_lossy().to_string();
+
+    let manager = VaultManager::new();
+    let provider_config = serde_json::json!({
+        "root": vault_path
+    });
+
+    let session = unlock.open(&manager, "local", provider_config).await?;
+
+    let session = Arc::new(session);
+    let handle = mount_session(session.clone(), at, read_only, allow_other)?;
+
+    println!("Vault mounted at {}", handle.mount_point().display());
+    println!("Press Ctrl+C to unmount.");
+
+    shutdown_signal().await?;
+
+    // Open files are saved before the mount is released. Unmounting joins
+    // the FUSE thread off the runtime workers, which it may still block on.
+    let unmounted = handle.unmount_and_wait(SHUTDOWN_DEADLINE).await;
+    session.close().await.context("Failed to close vault")?;
+    unmounted.context("Failed to unmount vault")?;
+    println!("Vault unmounted.");
+
+    Ok(())
+}
+
+/// How long a signal-triggered shutdown waits for in-flight work.
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(15);
+
+/// Wait for Ctrl+C or, on Unix, SIGTERM.
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut terminate =
+            signal(SignalKind::terminate()).context("Failed to listen for SIGTERM")?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.context("Failed to wait for Ctrl+C"),
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c()
+            .await
+            .context("Failed to wait for Ctrl+C")
+    }
+}
+
+/// Mount `session` at `at` with the CLI's mount flags.
+fn mount_session(
+    session: Arc<axiomvault_vault::VaultSession>,
+    at: &Path,
+    read_only: bool,
+    allow_other: bool,
+) -> Result<axiomvault_fuse::MountHandle> {
+    let options = axiomvault_fuse::MountOptions {
+        read_only,
+        allow_other,
+        ..Default::default()
+    };
+    axiomvault_fuse::mount::mount(session, at, options, tokio::runtime::Handle::current())
+        .context("Failed to mount vault")
+}
+
+/// Release a stale FUSE mount with the platform's unmount helper.
+///
+/// A busy mount is retried for a few seconds; if it stays busy, the
+/// processes holding files open are listed so they can be closed first.
+async fn cmd_unmount(at: &Path, lazy: bool) -> Result<()> {
+    let retry = axiomvault_fuse::UnmountRetry {
+        lazy_fallback: lazy,
+        ..Default::default()
+    };
+    match axiomvault_fuse::release_mount_point(at, &retry).await {
+        Ok(()) => {
+            println!("Unmounted {}", at.display());
+            Ok(())
+        }
+        Err(axiomvault_common::Error::MountBusy(pids)) => {
+            eprintln!("{} is still in use.", at.display());
+            for pid in &pids {
+                let name = axiomvault_fuse::process_name(*pid).unwrap_or_default();
+                eprintln!("  pid {:<8} {}", pid, name);
+            }
+            anyhow::bail!("Close these files or retry with --lazy")
+        }
+        Err(e) => Err(e).context("Failed to unmount vault"),
+    }
+}
+
+/// Serve the collected metrics as plain text on a loopback port.
+///
+/// Every request, whatever its path, receives the current snapshot.
+#[cfg(all(feature = "webdav", feature = "metrics"))]
+async fn serve_metrics(port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Failed to bind metrics port {}", port))?;
+    println!("Metrics available at http://127.0.0.1:{}/metrics", port);
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                continue;
+            };
+            tokio::spawn(async move {
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).await;
+                let body = telemetry::metrics::render();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(all(feature = "webdav", not(feature = "metrics")))]
+async fn serve_metrics(_port: u16) -> Result<()> {
+    anyhow::bail!("--metrics-port requires a build with the `metrics` feature")
 }
 
 #[cfg(test)]
@@ -2707,4 +5613,190 @@ mod tests {
         let result = rebuild_with_progress(&rebuilder).await;
         assert!(result.is_ok(), "expected Ok, got: {:?}", result.err());
     }
+
+    // -----------------------------------------------------------------------
+    // backup_since – incremental export
+    // -----------------------------------------------------------------------
+
+    /// Only the file edited after the cutoff is exported, at its vault path.
+    #[tokio::test]
+    async fn test_backup_since_exports_only_files_edited_after_cutoff() {
+        use super::backup_since;
+        use axiomvault_common::{Secret, VaultId, VaultPath};
+        use axiomvault_crypto::KdfParams;
+        use axiomvault_vault::{VaultManager, VaultOperations};
+        use std::time::Duration;
+
+        let creation = VaultManager::new()
+            .create_vault(
+                VaultId::new("backup-test").unwrap(),
+                &Secret::from_slice(b"password"),
+                "memory",
+                serde_json::Value::Null,
+                KdfParams::interactive(),
+            )
+            .await
+            .expect("create vault");
+        let session = creation.session;
+        let ops = VaultOperations::new(&session).unwrap();
+
+        let docs = VaultPath::parse("/docs").unwrap();
+        ops.create_directory(&docs).await.unwrap();
+        let files = ["/a.txt", "/docs/b.txt", "/docs/c.txt"];
+        for file in files {
+            ops.create_file(&VaultPath::parse(file).unwrap(), b"original")
+                .await
+                .unwrap();
+        }
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let cutoff = chrono::Utc::now();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let edited = VaultPath::parse("/docs/b.txt").unwrap();
+        ops.update_file(&edited, b"edited").await.unwrap();
+
+        let out = tempfile::tempdir().unwrap();
+        let entries = backup_since(&session, cutoff, out.path())
+            .await
+            .expect("backup");
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, edited);
+        assert_eq!(entries[0].size, 6);
+        assert!(entries[0].modified_at > cutoff);
+
+        let exported = out.path().join("docs").join("b.txt");
+        assert_eq!(std::fs::read(&exported).unwrap(), b"edited");
+        assert!(!out.path().join("a.txt").exists());
+        assert!(!out.path().join("docs").join("c.txt").exists());
+    }
+
+    // -----------------------------------------------------------------------
+    // --key-file-hex / --key-from-env – external key parsing
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn test_external_key_from_env_is_validated() {
+        use super::Unlock;
+
+        let unlock = |var: &str| Unlock {
+            keyfile: None,
+            key_file_hex: None,
+            key_from_env: Some(var.to_string()),
+        };
+
+        std::env::set_var("AXIOMVAULT_TEST_KEY_OK", format!("{}\n", "ab".repeat(32)));
+        assert!(unlock("AXIOMVAULT_TEST_KEY_OK")
+            .external_key()
+            .unwrap()
+            .is_some());
+
+        std::env::set_var("AXIOMVAULT_TEST_KEY_SHORT", "ab".repeat(16));
+        std::env::set_var("AXIOMVAULT_TEST_KEY_ZERO", "00".repeat(32));
+        std::env::set_var("AXIOMVAULT_TEST_KEY_BAD", format!("+f{}", "ab".repeat(31)));
+        for var in [
+            "AXIOMVAULT_TEST_KEY_SHORT",
+            "AXIOMVAULT_TEST_KEY_ZERO",
+            "AXIOMVAULT_TEST_KEY_BAD",
+            "AXIOMVAULT_TEST_KEY_UNSET",
+        ] {
+            assert!(unlock(var).external_key().is_err(), "{var} was accepted");
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // export-plaintext – destination interlocks
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn test_export_destination_inside_vault_or_mount_is_refused() {
+        use super::check_export_destination;
+
+        let root = tempfile::tempdir().unwrap();
+        let vault = root.path().join("vault");
+        let mount = root.path().join("mnt");
+        std::fs::create_dir_all(&vault).unwrap();
+        std::fs::create_dir_all(&mount).unwrap();
+        let mounts = [mount.clone()];
+
+        assert!(check_export_destination(&vault.join("out"), &vault, &mounts).is_err());
+        assert!(check_export_destination(&mount.join("a/b"), &vault, &mounts).is_err());
+        // `..` must not sneak a destination back into the vault.
+        let sneaky = root.path().join("elsewhere/../vault/out");
+        assert!(check_export_destination(&sneaky, &vault, &mounts).is_err());
+
+        assert!(check_export_destination(&root.path().join("out"), &vault, &mounts).is_ok());
+        // A sibling whose name merely starts with the vault's is fine.
+        let sibling = root.path().join("vault-export");
+        assert!(check_export_destination(&sibling, &vault, &mounts).is_ok());
+    }
+
+    #[test]
+    fn test_cloud_sync_marker_detection() {
+        use super::cloud_sync_marker;
+        use std::path::Path;
+
+        assert_eq!(
+            cloud_sync_marker(Path::new("/home/me/Dropbox/export")),
+            Some("dropbox")
+        );
+        assert_eq!(
+            cloud_sync_marker(Path::new("/Users/me/OneDrive - Contoso/out")),
+            Some("onedrive")
+        );
+        assert_eq!(
+            cloud_sync_marker(Path::new(
+                "/Users/me/Library/Mobile Documents/com~apple~CloudDocs"
+            )),
+            Some("mobile documents")
+        );
+        assert_eq!(cloud_sync_marker(Path::new("/home/me/export")), None);
+    }
+
+    #[cfg(feature = "fuse")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_mount_round_trips_through_vault_operations() {
+        use super::mount_session;
+        use axiomvault_common::{Secret, VaultId, VaultPath};
+        use axiomvault_crypto::KdfParams;
+        use axiomvault_vault::{VaultManager, VaultOperations};
+
+        if !axiomvault_fuse::mount::is_fuse_available() {
+            eprintln!("skipping: {}", axiomvault_fuse::mount::fuse_info());
+            return;
+        }
+
+        let creation = VaultManager::new()
+            .create_vault(
+                VaultId::new("mount-test").unwrap(),
+                &Secret::from_slice(b"password"),
+                "memory",
+                serde_json::Value::Null,
+                KdfParams::interactive(),
+            )
+            .await
+            .unwrap();
+        let session = Arc::new(creation.session);
+
+        let mount_point = tempfile::tempdir().unwrap();
+        let handle = mount_session(session.clone(), mount_point.path(), false, false).unwrap();
+
+        // File I/O through the mount blocks until the FUSE thread answers,
+        // and that thread runs vault operations on this runtime.
+        let file = mount_point.path().join("hello.txt");
+        tokio::task::spawn_blocking(move || {
+            std::fs::write(&file, b"through fuse").unwrap();
+            handle.unmount();
+        })
+        .await
+        .unwrap();
+
+        let ops = VaultOperations::new(&session).unwrap();
+        let content = ops
+            .read_file(&VaultPath::parse("/hello.txt").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(content, b"through fuse");
+    }
 }